@@ -88,7 +88,8 @@ void GFp_BN_free(BIGNUM *bn) {
     return;
   }
 
-  if ((bn->flags & BN_FLG_STATIC_DATA) == 0) {
+  if ((bn->flags & BN_FLG_STATIC_DATA) == 0 && bn->d != NULL) {
+    GFp_cleanse(bn->d, bn->dmax * sizeof(bn->d[0]));
     OPENSSL_free(bn->d);
   }
 
@@ -241,6 +242,9 @@ BIGNUM *GFp_bn_wexpand(BIGNUM *bn, size_t words) {
 
   memcpy(a, bn->d, sizeof(BN_ULONG) * bn->top);
 
+  if (bn->d != NULL) {
+    GFp_cleanse(bn->d, sizeof(BN_ULONG) * bn->dmax);
+  }
   OPENSSL_free(bn->d);
   bn->d = a;
   bn->dmax = (int)words;
@@ -118,7 +118,7 @@
 #include "internal.h"
 
 
-#if defined(OPENSSL_X86_64)
+#if !defined(OPENSSL_NO_ASM) && defined(OPENSSL_X86_64)
 #define OPENSSL_BN_ASM_MONT5
 #define RSAZ_ENABLED
 
@@ -764,6 +764,9 @@ int GFp_BN_mod_exp_mont_consttime(BIGNUM *rr, const BIGNUM *a, const BIGNUM *p,
 
 err:
   GFp_BN_MONT_CTX_free(new_mont);
+  if (powerbuf != NULL) {
+    GFp_cleanse(powerbuf, powerbufLen);
+  }
   OPENSSL_free(powerbufFree);
   return (ret);
 }
@@ -360,3 +360,48 @@ err:
   GFp_BN_free(&tmp);
   return ret;
 }
+
+#if defined(OPENSSL_NO_ASM)
+
+/* The largest modulus |GFp_bn_mul_mont| supports, in words. This is enough
+ * for the largest RSA moduli (8192 bits) that *ring* supports. */
+#define BN_MUL_MONT_MAX_WORDS (8192 / BN_BITS2)
+
+/* A portable implementation for targets without an assembly language
+ * implementation. It computes the product of |ap| and |bp| and then reduces
+ * it like |GFp_BN_from_montgomery_word|. */
+void GFp_bn_mul_mont(BN_ULONG *rp, const BN_ULONG *ap, const BN_ULONG *bp,
+                     const BN_ULONG *np, const BN_ULONG *n0, int num) {
+  assert(num >= 1 && num <= BN_MUL_MONT_MAX_WORDS);
+
+  BN_ULONG t[2 * BN_MUL_MONT_MAX_WORDS];
+  memset(t, 0, 2 * (size_t)num * sizeof(BN_ULONG));
+
+  for (int i = 0; i < num; i++) {
+    t[num + i] = GFp_bn_mul_add_words(&t[i], ap, num, bp[i]);
+  }
+
+  BN_ULONG carry = 0;
+  for (int i = 0; i < num; i++) {
+    BN_ULONG v =
+        GFp_bn_mul_add_words(&t[i], np, num, (t[i] * n0[0]) & BN_MASK2);
+    v = (v + carry + t[num + i]) & BN_MASK2;
+    carry |= (v != t[num + i]);
+    carry &= (v <= t[num + i]);
+    t[num + i] = v;
+  }
+
+  /* Subtract the modulus, in constant time, unless the result is already
+   * reduced. The result is less than twice the modulus, so |v| is one
+   * exactly when the subtraction borrowed without a carry to cancel it out,
+   * i.e. when it must be undone. */
+  BN_ULONG v = GFp_bn_sub_words(rp, &t[num], np, num) - carry;
+  BN_ULONG mask = 0u - v;
+  for (int i = 0; i < num; i++) {
+    rp[i] = (t[num + i] & mask) | (rp[i] & ~mask);
+  }
+
+  GFp_cleanse(t, 2 * (size_t)num * sizeof(BN_ULONG));
+}
+
+#endif  /* OPENSSL_NO_ASM */
@@ -0,0 +1,97 @@
+/* Copyright 2016 Brian Smith.
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+ * SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+ * OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+ * CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE. */
+
+/* A portable implementation of ChaCha20, as specified in RFC 7539, for
+ * targets that don't have an assembly language implementation. */
+
+#include <openssl/base.h>
+
+#if defined(OPENSSL_NO_ASM)
+
+#include <string.h>
+
+#include <openssl/mem.h>
+
+#include "../internal.h"
+
+
+/* Prototypes to avoid -Wmissing-prototypes warnings. */
+void GFp_ChaCha20_ctr32(uint8_t *out, const uint8_t *in, size_t in_len,
+                        const uint32_t key[8], const uint32_t counter[4]);
+
+
+#define ROTL32(v, n) (((v) << (n)) | ((v) >> (32 - (n))))
+
+#define QUARTERROUND(a, b, c, d)                \
+  x[a] += x[b]; x[d] = ROTL32(x[d] ^ x[a], 16); \
+  x[c] += x[d]; x[b] = ROTL32(x[b] ^ x[c], 12); \
+  x[a] += x[b]; x[d] = ROTL32(x[d] ^ x[a], 8);  \
+  x[c] += x[d]; x[b] = ROTL32(x[b] ^ x[c], 7);
+
+static void chacha20_block(uint8_t out[64], const uint32_t input[16]) {
+  uint32_t x[16];
+  memcpy(x, input, sizeof(x));
+
+  for (size_t i = 0; i < 10; ++i) {
+    QUARTERROUND(0, 4, 8, 12)
+    QUARTERROUND(1, 5, 9, 13)
+    QUARTERROUND(2, 6, 10, 14)
+    QUARTERROUND(3, 7, 11, 15)
+    QUARTERROUND(0, 5, 10, 15)
+    QUARTERROUND(1, 6, 11, 12)
+    QUARTERROUND(2, 7, 8, 13)
+    QUARTERROUND(3, 4, 9, 14)
+  }
+
+  for (size_t i = 0; i < 16; ++i) {
+    uint32_t word = x[i] + input[i];
+    out[4 * i + 0] = (uint8_t)word;
+    out[4 * i + 1] = (uint8_t)(word >> 8);
+    out[4 * i + 2] = (uint8_t)(word >> 16);
+    out[4 * i + 3] = (uint8_t)(word >> 24);
+  }
+}
+
+/* |key| and |counter| are in native byte order, like the assembly language
+ * implementations expect. The block counter in |counter[0]| wraps around
+ * without carrying into the nonce; the caller must prevent that. */
+void GFp_ChaCha20_ctr32(uint8_t *out, const uint8_t *in, size_t in_len,
+                        const uint32_t key[8], const uint32_t counter[4]) {
+  uint32_t input[16];
+  /* "expand 32-byte k" */
+  input[0] = 0x61707865;
+  input[1] = 0x3320646e;
+  input[2] = 0x79622d32;
+  input[3] = 0x6b206574;
+  memcpy(&input[4], key, 8 * sizeof(uint32_t));
+  memcpy(&input[12], counter, 4 * sizeof(uint32_t));
+
+  uint8_t block[64];
+  while (in_len > 0) {
+    chacha20_block(block, input);
+    size_t todo = in_len < sizeof(block) ? in_len : sizeof(block);
+    for (size_t i = 0; i < todo; ++i) {
+      out[i] = in[i] ^ block[i];
+    }
+    out += todo;
+    in += todo;
+    in_len -= todo;
+    input[12] += 1;
+  }
+
+  GFp_cleanse(block, sizeof(block));
+  GFp_cleanse(input, sizeof(input));
+}
+
+#endif  /* OPENSSL_NO_ASM */
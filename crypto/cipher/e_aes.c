@@ -72,11 +72,13 @@ int GFp_aes_gcm_init(void *ctx_buf, size_t ctx_buf_len, const uint8_t *key,
 int GFp_aes_gcm_open(const void *ctx_buf, uint8_t *out, size_t in_out_len,
                      uint8_t tag_out[EVP_AEAD_AES_GCM_TAG_LEN],
                      const uint8_t nonce[EVP_AEAD_AES_GCM_NONCE_LEN],
-                     const uint8_t *in, const uint8_t *ad, size_t ad_len);
+                     const uint8_t *in, const uint8_t *const ad_parts[],
+                     const size_t ad_part_lens[], size_t num_ad_parts);
 int GFp_aes_gcm_seal(const void *ctx_buf, uint8_t *in_out, size_t in_out_len,
                      uint8_t tag_out[EVP_AEAD_AES_GCM_TAG_LEN],
                      const uint8_t nonce[EVP_AEAD_AES_GCM_NONCE_LEN],
-                     const uint8_t *ad, size_t ad_len);
+                     const uint8_t *const ad_parts[],
+                     const size_t ad_part_lens[], size_t num_ad_parts);
 int GFp_has_aes_hardware(void);
 
 
@@ -105,6 +107,7 @@ static char bsaes_capable(void) {
 }
 #endif
 
+#if __ARM_MAX_ARCH__ >= 7
 #define HWAES
 static int hwaes_capable(void) {
   return GFp_is_ARMv8_AES_capable();
@@ -115,6 +118,7 @@ int GFp_aes_v8_set_encrypt_key(const uint8_t *user_key, const unsigned bits,
 void GFp_aes_v8_encrypt(const uint8_t *in, uint8_t *out, const AES_KEY *key);
 void GFp_aes_v8_ctr32_encrypt_blocks(const uint8_t *in, uint8_t *out, size_t len,
                                  const AES_KEY *key, const uint8_t ivec[16]);
+#endif
 
 #endif  /* OPENSSL_ARM */
 
@@ -249,15 +253,57 @@ int GFp_aes_gcm_init(void *ctx_buf, size_t ctx_buf_len, const uint8_t *key,
   return 1;
 }
 
+/* The additional data is the concatenation of the |num_ad_parts| parts.
+ * |GFp_gcm128_aad| can only be given a partial block in its last call, so
+ * parts that don't end on a block boundary are joined in |block|. */
 static int gfp_aes_gcm_init_and_aad(GCM128_CONTEXT *gcm, AES_KEY *ks,
                                     const void *ctx_buf, const uint8_t nonce[],
-                                    const uint8_t ad[], size_t ad_len) {
-  assert(ad != NULL || ad_len == 0);
+                                    const uint8_t *const ad_parts[],
+                                    const size_t ad_part_lens[],
+                                    size_t num_ad_parts) {
   memcpy(ks, ctx_buf, sizeof(*ks));
   GFp_gcm128_init(gcm, ks, aes_block(), (const uint8_t *)ctx_buf + sizeof(*ks),
                   nonce);
-  if (ad_len > 0) {
-    if (!GFp_gcm128_aad(gcm, ad, ad_len)) {
+
+  uint8_t block[16];
+  size_t block_len = 0;
+  for (size_t i = 0; i < num_ad_parts; ++i) {
+    const uint8_t *ad = ad_parts[i];
+    size_t ad_len = ad_part_lens[i];
+    assert(ad != NULL || ad_len == 0);
+    if (ad_len == 0) {
+      continue;
+    }
+    if (block_len > 0) {
+      size_t n = sizeof(block) - block_len;
+      if (n > ad_len) {
+        n = ad_len;
+      }
+      memcpy(block + block_len, ad, n);
+      block_len += n;
+      ad += n;
+      ad_len -= n;
+      if (block_len < sizeof(block)) {
+        continue;
+      }
+      if (!GFp_gcm128_aad(gcm, block, sizeof(block))) {
+        return 0;
+      }
+      block_len = 0;
+    }
+    size_t whole_len = ad_len - (ad_len % sizeof(block));
+    if (whole_len > 0) {
+      if (!GFp_gcm128_aad(gcm, ad, whole_len)) {
+        return 0;
+      }
+    }
+    block_len = ad_len - whole_len;
+    if (block_len > 0) {
+      memcpy(block, ad + whole_len, block_len);
+    }
+  }
+  if (block_len > 0) {
+    if (!GFp_gcm128_aad(gcm, block, block_len)) {
       return 0;
     }
   }
@@ -267,14 +313,15 @@ static int gfp_aes_gcm_init_and_aad(GCM128_CONTEXT *gcm, AES_KEY *ks,
 int GFp_aes_gcm_seal(const void *ctx_buf, uint8_t *in_out, size_t in_out_len,
                      uint8_t tag_out[EVP_AEAD_AES_GCM_TAG_LEN],
                      const uint8_t nonce[EVP_AEAD_AES_GCM_NONCE_LEN],
-                     const uint8_t *ad, size_t ad_len) {
+                     const uint8_t *const ad_parts[],
+                     const size_t ad_part_lens[], size_t num_ad_parts) {
   assert(in_out != NULL || in_out_len == 0);
   assert(aead_check_in_len(in_out_len));
-  assert(ad != NULL || ad_len == 0);
 
   GCM128_CONTEXT gcm;
   alignas(16) AES_KEY ks;
-  if (!gfp_aes_gcm_init_and_aad(&gcm, &ks, ctx_buf, nonce, ad, ad_len)) {
+  if (!gfp_aes_gcm_init_and_aad(&gcm, &ks, ctx_buf, nonce, ad_parts,
+                                ad_part_lens, num_ad_parts)) {
     return 0;
   }
   if (in_out_len > 0) {
@@ -297,16 +344,17 @@ int GFp_aes_gcm_seal(const void *ctx_buf, uint8_t *in_out, size_t in_out_len,
 int GFp_aes_gcm_open(const void *ctx_buf, uint8_t *out, size_t in_out_len,
                      uint8_t tag_out[EVP_AEAD_AES_GCM_TAG_LEN],
                      const uint8_t nonce[EVP_AEAD_AES_GCM_NONCE_LEN],
-                     const uint8_t *in, const uint8_t *ad, size_t ad_len) {
+                     const uint8_t *in, const uint8_t *const ad_parts[],
+                     const size_t ad_part_lens[], size_t num_ad_parts) {
   assert(out != NULL || in_out_len == 0);
   assert(aead_check_in_len(in_out_len));
   assert(aead_check_alias(in, in_out_len, out));
   assert(in != NULL || in_out_len == 0);
-  assert(ad != NULL || ad_len == 0);
 
   GCM128_CONTEXT gcm;
   alignas(16) AES_KEY ks;
-  if (!gfp_aes_gcm_init_and_aad(&gcm, &ks, ctx_buf, nonce, ad, ad_len)) {
+  if (!gfp_aes_gcm_init_and_aad(&gcm, &ks, ctx_buf, nonce, ad_parts,
+                                ad_part_lens, num_ad_parts)) {
     return 0;
   }
   if (in_out_len > 0) {
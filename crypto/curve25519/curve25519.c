@@ -4548,11 +4548,32 @@ static void sc_muladd(uint8_t *s, const uint8_t *a, const uint8_t *b,
 
 /* Prototype to avoid -Wmissing-prototypes warnings. */
 void GFp_ed25519_public_from_private(uint8_t out[32], const uint8_t in[32]);
-void GFp_ed25519_sign(uint8_t *out_sig, const uint8_t *message,
-                      size_t message_len, const uint8_t private_key[64]);
-int GFp_ed25519_verify(const uint8_t *message, size_t message_len,
+void GFp_ed25519_sign(uint8_t *out_sig, const uint8_t *dom, size_t dom_len,
+                      const uint8_t *message, size_t message_len,
+                      const uint8_t private_key[64]);
+int GFp_ed25519_verify(const uint8_t *dom, size_t dom_len,
+                       const uint8_t *message, size_t message_len,
                        const uint8_t signature[64],
                        const uint8_t public_key[32]);
+void GFp_ed25519_scalar_reduce(uint8_t s[64]);
+void GFp_ed25519_scalar_mul_add(uint8_t s[32], const uint8_t a[32],
+                                const uint8_t b[32], const uint8_t c[32]);
+void GFp_ed25519_point_mul_base(uint8_t out[32], const uint8_t a[32]);
+int GFp_ed25519_point_mul_add_base_vartime(uint8_t out[32],
+                                           const uint8_t a[32],
+                                           const uint8_t A[32],
+                                           const uint8_t b[32]);
+int GFp_ed25519_point_add_vartime(uint8_t out[32], const uint8_t a[32],
+                                  const uint8_t b[32]);
+int GFp_ed25519_multiscalar_is_zero_vartime(const uint8_t b[32],
+                                            const uint8_t *scalars,
+                                            const uint8_t *points,
+                                            size_t num_points,
+                                            size_t num_canonical);
+int GFp_ed25519_point_is_on_curve(const uint8_t p[32]);
+int GFp_ed25519_point_is_small_order(const uint8_t p[32]);
+int GFp_ed25519_point_is_torsion_free_vartime(const uint8_t p[32]);
+int GFp_x25519_point_is_on_curve(const uint8_t u[32]);
 
 
 void GFp_ed25519_public_from_private(uint8_t out[32], const uint8_t in[32]) {
@@ -4568,8 +4589,10 @@ void GFp_ed25519_public_from_private(uint8_t out[32], const uint8_t in[32]) {
   ge_p3_tobytes(out, &A);
 }
 
-void GFp_ed25519_sign(uint8_t *out_sig, const uint8_t *message,
-                      size_t message_len, const uint8_t private_key[64]) {
+/* |dom| is the domain separation prefix, as for |GFp_ed25519_verify|. */
+void GFp_ed25519_sign(uint8_t *out_sig, const uint8_t *dom, size_t dom_len,
+                      const uint8_t *message, size_t message_len,
+                      const uint8_t private_key[64]) {
   uint8_t az[SHA512_DIGEST_LENGTH];
   GFp_SHA512_4(az, sizeof(az), private_key, 32, NULL, 0, NULL, 0, NULL, 0);
 
@@ -4578,8 +4601,8 @@ void GFp_ed25519_sign(uint8_t *out_sig, const uint8_t *message,
   az[31] |= 64;
 
   uint8_t nonce[SHA512_DIGEST_LENGTH];
-  GFp_SHA512_4(nonce, sizeof(nonce), az + 32, 32, message, message_len, NULL, 0,
-               NULL, 0);
+  GFp_SHA512_4(nonce, sizeof(nonce), dom, dom_len, az + 32, 32, message,
+               message_len, NULL, 0);
 
   x25519_sc_reduce(nonce);
   ge_p3 R;
@@ -4587,14 +4610,18 @@ void GFp_ed25519_sign(uint8_t *out_sig, const uint8_t *message,
   ge_p3_tobytes(out_sig, &R);
 
   uint8_t hram[SHA512_DIGEST_LENGTH];
-  GFp_SHA512_4(hram, sizeof(hram), out_sig, 32, private_key + 32, 32, message,
-               message_len, NULL, 0);
+  GFp_SHA512_4(hram, sizeof(hram), dom, dom_len, out_sig, 32,
+               private_key + 32, 32, message, message_len);
 
   x25519_sc_reduce(hram);
   sc_muladd(out_sig + 32, hram, az, nonce);
 }
 
-int GFp_ed25519_verify(const uint8_t *message, size_t message_len,
+/* |dom| is the domain separation prefix, dom2(F, C) in RFC 8032, that is
+ * hashed before R || A || M. It is empty (|dom_len| == 0) for Ed25519 and
+ * non-empty for Ed25519ph and Ed25519ctx. */
+int GFp_ed25519_verify(const uint8_t *dom, size_t dom_len,
+                       const uint8_t *message, size_t message_len,
                        const uint8_t signature[64],
                        const uint8_t public_key[32]) {
   ge_p3 A;
@@ -4614,8 +4641,8 @@ int GFp_ed25519_verify(const uint8_t *message, size_t message_len,
   memcpy(scopy, signature + 32, 32);
 
   uint8_t h[SHA512_DIGEST_LENGTH];
-  GFp_SHA512_4(h, sizeof(h), signature, 32, public_key, 32, message,
-               message_len, NULL, 0);
+  GFp_SHA512_4(h, sizeof(h), dom, dom_len, signature, 32, public_key, 32,
+               message, message_len);
 
   x25519_sc_reduce(h);
 
@@ -4628,6 +4655,781 @@ int GFp_ed25519_verify(const uint8_t *message, size_t message_len,
   return GFp_memcmp(rcheck, rcopy, sizeof(rcheck)) == 0;
 }
 
+/* The following functions expose the group and scalar arithmetic used above
+ * as building blocks for protocols built on Ed25519, such as threshold
+ * signatures. Scalars are little-endian and, except for the input to
+ * |GFp_ed25519_scalar_reduce|, must be less than 2^253. Points are in the
+ * standard Ed25519 encoding. */
+
+/* s = s mod l, where |s| is 64 bytes on input and the result is in the first
+ * 32 bytes. */
+void GFp_ed25519_scalar_reduce(uint8_t s[64]) {
+  x25519_sc_reduce(s);
+}
+
+/* s = (a * b + c) mod l. */
+void GFp_ed25519_scalar_mul_add(uint8_t s[32], const uint8_t a[32],
+                                const uint8_t b[32], const uint8_t c[32]) {
+  sc_muladd(s, a, b, c);
+}
+
+/* out = a * B, where B is the base point. |a| must be reduced mod l. This is
+ * constant-time with respect to |a|. */
+void GFp_ed25519_point_mul_base(uint8_t out[32], const uint8_t a[32]) {
+  ge_p3 R;
+  x25519_ge_scalarmult_base(&R, a);
+  ge_p3_tobytes(out, &R);
+}
+
+/* out = a * A + b * B, where B is the base point. This is *not*
+ * constant-time, so it must only be used with public values. Returns zero if
+ * |A| cannot be decoded. */
+int GFp_ed25519_point_mul_add_base_vartime(uint8_t out[32],
+                                           const uint8_t a[32],
+                                           const uint8_t A[32],
+                                           const uint8_t b[32]) {
+  ge_p3 A_p3;
+  if (x25519_ge_frombytes_vartime(&A_p3, A) != 0) {
+    return 0;
+  }
+  ge_p2 R;
+  ge_double_scalarmult_vartime(&R, a, &A_p3, b);
+  x25519_ge_tobytes(out, &R);
+  return 1;
+}
+
+/* out = a + b. This is *not* constant-time, so it must only be used with
+ * public values. Returns zero if |a| or |b| cannot be decoded. */
+int GFp_ed25519_point_add_vartime(uint8_t out[32], const uint8_t a[32],
+                                  const uint8_t b[32]) {
+  ge_p3 a_p3;
+  ge_p3 b_p3;
+  if (x25519_ge_frombytes_vartime(&a_p3, a) != 0 ||
+      x25519_ge_frombytes_vartime(&b_p3, b) != 0) {
+    return 0;
+  }
+  ge_cached b_cached;
+  x25519_ge_p3_to_cached(&b_cached, &b_p3);
+  ge_p1p1 R_p1p1;
+  x25519_ge_add(&R_p1p1, &a_p3, &b_cached);
+  ge_p2 R;
+  x25519_ge_p1p1_to_p2(&R, &R_p1p1);
+  x25519_ge_tobytes(out, &R);
+  return 1;
+}
+
+/* The maximum number of points that |GFp_ed25519_multiscalar_is_zero_vartime|
+ * accepts. Its tables are on the stack, so this is kept small. */
+#define ED25519_MULTISCALAR_MAX_POINTS 32
+
+/* Ai = A, 3A, 5A, ..., 15A, for use with the output of |slide|. */
+static void ge_odd_multiples(ge_cached Ai[8], const ge_p3 *A) {
+  ge_p1p1 t;
+  ge_p3 u;
+  ge_p3 A2;
+  int i;
+
+  x25519_ge_p3_to_cached(&Ai[0], A);
+  ge_p3_dbl(&t, A);
+  x25519_ge_p1p1_to_p3(&A2, &t);
+  for (i = 1; i < 8; ++i) {
+    x25519_ge_add(&t, &A2, &Ai[i - 1]);
+    x25519_ge_p1p1_to_p3(&u, &t);
+    x25519_ge_p3_to_cached(&Ai[i], &u);
+  }
+}
+
+/* Returns one iff [8](a_0 * P_0 + ... + a_{n-1} * P_{n-1} - b * B) is the
+ * identity, where B is the base point, the a_i are the |num_points|
+ * consecutive 32-byte scalars in |scalars|, and the P_i are the
+ * |num_points| consecutive encoded points in |points|. Returns zero if any
+ * point can't be decoded, or if any of the first |num_canonical| points isn't
+ * canonically encoded. The scalars must be less than 2^253. This is *not*
+ * constant-time, so it must only be used with public values. */
+int GFp_ed25519_multiscalar_is_zero_vartime(const uint8_t b[32],
+                                            const uint8_t *scalars,
+                                            const uint8_t *points,
+                                            size_t num_points,
+                                            size_t num_canonical) {
+  signed char bslide[256];
+  signed char slides[ED25519_MULTISCALAR_MAX_POINTS][256];
+  ge_cached tables[ED25519_MULTISCALAR_MAX_POINTS][8];
+  ge_p2 r;
+  ge_p1p1 t;
+  ge_p3 u;
+  fe check;
+  size_t i;
+  int k;
+
+  if (num_points > ED25519_MULTISCALAR_MAX_POINTS) {
+    return 0;
+  }
+
+  for (i = 0; i < num_points; ++i) {
+    const uint8_t *encoded = points + (32 * i);
+    if (x25519_ge_frombytes_vartime(&u, encoded) != 0) {
+      return 0;
+    }
+    if (i < num_canonical) {
+      uint8_t reencoded[32];
+      ge_p3_tobytes(reencoded, &u);
+      if (GFp_memcmp(reencoded, encoded, sizeof(reencoded)) != 0) {
+        return 0;
+      }
+    }
+    ge_odd_multiples(tables[i], &u);
+    slide(slides[i], scalars + (32 * i));
+  }
+  slide(bslide, b);
+
+  /* All the points share the doublings, which is where the speedup over
+   * checking each a_i * P_i separately comes from. */
+  ge_p2_0(&r);
+  for (k = 255; k >= 0; --k) {
+    ge_p2_dbl(&t, &r);
+
+    for (i = 0; i < num_points; ++i) {
+      if (slides[i][k] > 0) {
+        x25519_ge_p1p1_to_p3(&u, &t);
+        x25519_ge_add(&t, &u, &tables[i][slides[i][k] / 2]);
+      } else if (slides[i][k] < 0) {
+        x25519_ge_p1p1_to_p3(&u, &t);
+        x25519_ge_sub(&t, &u, &tables[i][(-slides[i][k]) / 2]);
+      }
+    }
+
+    if (bslide[k] > 0) {
+      x25519_ge_p1p1_to_p3(&u, &t);
+      ge_msub(&t, &u, &Bi[bslide[k] / 2]);
+    } else if (bslide[k] < 0) {
+      x25519_ge_p1p1_to_p3(&u, &t);
+      ge_madd(&t, &u, &Bi[(-bslide[k]) / 2]);
+    }
+
+    x25519_ge_p1p1_to_p2(&r, &t);
+  }
+
+  /* Multiply by the cofactor. */
+  for (k = 0; k < 3; ++k) {
+    ge_p2_dbl(&t, &r);
+    x25519_ge_p1p1_to_p2(&r, &t);
+  }
+
+  /* The identity is (0, 1), i.e. X == 0 and Y == Z. */
+  fe_sub(check, r.Y, r.Z);
+  return !fe_isnonzero(r.X) & !fe_isnonzero(check);
+}
+
+/* The following functions validate encoded public keys, for protocols that
+ * need to reject points that aren't in the prime-order subgroup. */
+
+/* Returns one iff |r| is the identity. */
+static int ge_p2_is_identity(const ge_p2 *r) {
+  fe check;
+  fe_sub(check, r->Y, r->Z);
+  return !fe_isnonzero(r->X) & !fe_isnonzero(check);
+}
+
+/* Returns one iff |p| is the encoding of a point on the Ed25519 curve. */
+int GFp_ed25519_point_is_on_curve(const uint8_t p[32]) {
+  ge_p3 P;
+  return x25519_ge_frombytes_vartime(&P, p) == 0;
+}
+
+/* Returns one iff |p| is the encoding of a point on the Ed25519 curve whose
+ * order divides the cofactor, 8. */
+int GFp_ed25519_point_is_small_order(const uint8_t p[32]) {
+  ge_p3 P;
+  ge_p2 r;
+  ge_p1p1 t;
+  int i;
+
+  if (x25519_ge_frombytes_vartime(&P, p) != 0) {
+    return 0;
+  }
+  ge_p3_to_p2(&r, &P);
+  for (i = 0; i < 3; ++i) {
+    ge_p2_dbl(&t, &r);
+    x25519_ge_p1p1_to_p2(&r, &t);
+  }
+  return ge_p2_is_identity(&r);
+}
+
+/* Returns one iff |p| is the encoding of a point on the Ed25519 curve whose
+ * order divides l, i.e. that is in the subgroup generated by the base point.
+ * This is *not* constant-time, so it must only be used with public values. */
+int GFp_ed25519_point_is_torsion_free_vartime(const uint8_t p[32]) {
+  static const uint8_t l[32] = {
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7,
+    0xa2, 0xde, 0xf9, 0xde, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+  };
+  static const uint8_t zero[32] = {0};
+  ge_p3 P;
+  ge_p2 r;
+
+  if (x25519_ge_frombytes_vartime(&P, p) != 0) {
+    return 0;
+  }
+  ge_double_scalarmult_vartime(&r, l, &P, zero);
+  return ge_p2_is_identity(&r);
+}
+
+/* Returns one iff the u-coordinate |u|, with its most significant bit
+ * ignored as required by RFC 7748, is that of a point on Curve25519 rather
+ * than on its quadratic twist, i.e. iff u^3 + A*u^2 + u is a square. */
+int GFp_x25519_point_is_on_curve(const uint8_t u[32]) {
+  static const fe A = {486662};
+  fe x;
+  fe rhs;
+  fe t;
+  fe chi;
+  fe check;
+
+  fe_frombytes(x, u);
+
+  /* rhs = u * (u * (u + A) + 1). */
+  fe_add(t, x, A);
+  fe_mul(t, t, x);
+  fe_1(rhs);
+  fe_add(t, t, rhs);
+  fe_mul(rhs, t, x);
+
+  /* Euler's criterion: chi = rhs^((p - 1) / 2) = rhs^(2^254 - 10), which is
+   * (rhs^(2^252 - 3))^4 * rhs^2. */
+  fe_pow22523(chi, rhs);
+  fe_sq(chi, chi);
+  fe_sq(chi, chi);
+  fe_sq(t, rhs);
+  fe_mul(chi, chi, t);
+
+  fe_1(t);
+  fe_sub(check, chi, t);
+  return !fe_isnonzero(rhs) | !fe_isnonzero(check);
+}
+
+/* ristretto255, as specified in RFC 9496. The group elements are
+ * represented internally as Ed25519 points; see RFC 9496 Section 4. Only the
+ * encoding, decoding, and mapping functions are specific to ristretto255. */
+
+static const fe sqrt_ad_minus_one = {24849947, 33400850, 43495378, 6347714,
+                                     46036536, 32887293, 41837720, 18186727,
+                                     66238516, 14525638};
+
+static const fe invsqrt_a_minus_d = {6111466,  4156064,  39310137, 12243467,
+                                     41204824, 120896,   20826367, 26493656,
+                                     6093567,  31568420};
+
+static const fe one_minus_d_sq = {6275446,  16937061, 44170319, 29780721,
+                                  11667076, 7397348,  39186143, 1766194,
+                                  42675006, 672202};
+
+static const fe d_minus_one_sq = {15551776, 22456977, 53683765, 23429360,
+                                  55212328, 10178283, 40474537, 4729243,
+                                  61826754, 23438029};
+
+/* return 1 if f == g
+ * return 0 if f != g */
+static int fe_equal(const fe f, const fe g) {
+  fe t;
+  fe_sub(t, f, g);
+  return 1 - fe_isnonzero(t);
+}
+
+/* h = |f|, i.e. -f if f is negative and f otherwise. */
+static void fe_abs(fe h, const fe f) {
+  fe minus_f;
+  fe_neg(minus_f, f);
+  fe_copy(h, f);
+  fe_cmov(h, minus_f, fe_isnegative(f));
+}
+
+/* SQRT_RATIO_M1 from RFC 9496 Section 4.2. Sets |r| to the non-negative
+ * square root of u/v, or of SQRT_M1 * u/v if u/v isn't square, and returns 1
+ * if u/v is square and 0 otherwise. */
+static int fe_sqrt_ratio_m1(fe r, const fe u, const fe v) {
+  fe v3;
+  fe v7;
+  fe t;
+  fe check;
+  fe minus_u;
+  fe minus_u_i;
+  fe r_prime;
+
+  fe_sq(v3, v);
+  fe_mul(v3, v3, v);
+  fe_sq(v7, v3);
+  fe_mul(v7, v7, v);
+
+  fe_mul(t, u, v7);
+  fe_pow22523(t, t);
+  fe_mul(r, u, v3);
+  fe_mul(r, r, t);
+
+  fe_sq(check, r);
+  fe_mul(check, check, v);
+
+  fe_neg(minus_u, u);
+  fe_mul(minus_u_i, minus_u, sqrtm1);
+
+  int correct_sign_sqrt = fe_equal(check, u);
+  int flipped_sign_sqrt = fe_equal(check, minus_u);
+  int flipped_sign_sqrt_i = fe_equal(check, minus_u_i);
+
+  fe_mul(r_prime, r, sqrtm1);
+  fe_cmov(r, r_prime, flipped_sign_sqrt | flipped_sign_sqrt_i);
+  fe_abs(r, r);
+
+  return correct_sign_sqrt | flipped_sign_sqrt;
+}
+
+/* Decodes |s| as specified in RFC 9496 Section 4.3.1. Returns 1 on success
+ * and 0 if |s| isn't a valid encoding. */
+static int ristretto255_decode(ge_p3 *h, const uint8_t s_bytes[32]) {
+  fe s;
+  fe_frombytes(s, s_bytes);
+
+  /* The encoding must be canonical and non-negative. */
+  uint8_t check[32];
+  fe_tobytes(check, s);
+  if (GFp_memcmp(check, s_bytes, sizeof(check)) != 0 || fe_isnegative(s)) {
+    return 0;
+  }
+
+  fe one;
+  fe ss;
+  fe u1;
+  fe u2;
+  fe u2_sqr;
+  fe v;
+  fe t;
+  fe invsqrt;
+  fe den_x;
+  fe den_y;
+
+  fe_1(one);
+  fe_sq(ss, s);
+  fe_sub(u1, one, ss);
+  fe_add(u2, one, ss);
+  fe_sq(u2_sqr, u2);
+
+  fe_sq(t, u1);
+  fe_mul(t, t, d);
+  fe_neg(t, t);
+  fe_sub(v, t, u2_sqr);
+
+  fe_mul(t, v, u2_sqr);
+  int was_square = fe_sqrt_ratio_m1(invsqrt, one, t);
+
+  fe_mul(den_x, invsqrt, u2);
+  fe_mul(den_y, invsqrt, den_x);
+  fe_mul(den_y, den_y, v);
+
+  fe_add(t, s, s);
+  fe_mul(t, t, den_x);
+  fe_abs(h->X, t);
+  fe_mul(h->Y, u1, den_y);
+  fe_1(h->Z);
+  fe_mul(h->T, h->X, h->Y);
+
+  return was_square & (1 - fe_isnegative(h->T)) & fe_isnonzero(h->Y);
+}
+
+/* Encodes |h| as specified in RFC 9496 Section 4.3.2. */
+static void ristretto255_encode(uint8_t s_bytes[32], const ge_p3 *h) {
+  fe one;
+  fe u1;
+  fe u2;
+  fe t;
+  fe invsqrt;
+  fe den1;
+  fe den2;
+  fe z_inv;
+  fe ix;
+  fe iy;
+  fe enchanted_denominator;
+  fe x;
+  fe y;
+  fe minus_y;
+  fe den_inv;
+
+  fe_1(one);
+  fe_add(t, h->Z, h->Y);
+  fe_sub(u1, h->Z, h->Y);
+  fe_mul(u1, u1, t);
+  fe_mul(u2, h->X, h->Y);
+
+  fe_sq(t, u2);
+  fe_mul(t, t, u1);
+  (void)fe_sqrt_ratio_m1(invsqrt, one, t);
+
+  fe_mul(den1, invsqrt, u1);
+  fe_mul(den2, invsqrt, u2);
+  fe_mul(z_inv, den1, den2);
+  fe_mul(z_inv, z_inv, h->T);
+
+  fe_mul(ix, h->X, sqrtm1);
+  fe_mul(iy, h->Y, sqrtm1);
+  fe_mul(enchanted_denominator, den1, invsqrt_a_minus_d);
+
+  fe_mul(t, h->T, z_inv);
+  int rotate = fe_isnegative(t);
+  fe_copy(x, h->X);
+  fe_cmov(x, iy, rotate);
+  fe_copy(y, h->Y);
+  fe_cmov(y, ix, rotate);
+  fe_copy(den_inv, den2);
+  fe_cmov(den_inv, enchanted_denominator, rotate);
+
+  fe_mul(t, x, z_inv);
+  fe_neg(minus_y, y);
+  fe_cmov(y, minus_y, fe_isnegative(t));
+
+  fe_sub(t, h->Z, y);
+  fe_mul(t, t, den_inv);
+  fe_abs(t, t);
+  fe_tobytes(s_bytes, t);
+}
+
+/* The map from field elements to points from RFC 9496 Section 4.3.4. */
+static void ristretto255_map(ge_p3 *h, const fe t) {
+  fe one;
+  fe r;
+  fe u;
+  fe v;
+  fe tmp;
+  fe s;
+  fe s_prime;
+  fe c;
+  fe n;
+  fe w0;
+  fe w1;
+  fe w2;
+  fe w3;
+
+  fe_1(one);
+  fe_sq(r, t);
+  fe_mul(r, r, sqrtm1);
+  fe_add(u, r, one);
+  fe_mul(u, u, one_minus_d_sq);
+
+  fe_mul(tmp, r, d);
+  fe_add(tmp, tmp, one);
+  fe_neg(tmp, tmp);
+  fe_add(v, r, d);
+  fe_mul(v, v, tmp);
+
+  int was_square = fe_sqrt_ratio_m1(s, u, v);
+
+  fe_mul(s_prime, s, t);
+  fe_abs(s_prime, s_prime);
+  fe_neg(s_prime, s_prime);
+  fe_cmov(s, s_prime, 1 - was_square);
+
+  fe_neg(c, one);
+  fe_cmov(c, r, 1 - was_square);
+
+  fe_sub(tmp, r, one);
+  fe_mul(n, c, tmp);
+  fe_mul(n, n, d_minus_one_sq);
+  fe_sub(n, n, v);
+
+  fe_add(w0, s, s);
+  fe_mul(w0, w0, v);
+  fe_mul(w1, n, sqrt_ad_minus_one);
+  fe_sq(tmp, s);
+  fe_sub(w2, one, tmp);
+  fe_add(w3, one, tmp);
+
+  fe_mul(h->X, w0, w3);
+  fe_mul(h->Y, w2, w1);
+  fe_mul(h->Z, w1, w3);
+  fe_mul(h->T, w0, w2);
+}
+
+static void ge_cached_0(ge_cached *h) {
+  fe_1(h->YplusX);
+  fe_1(h->YminusX);
+  fe_1(h->Z);
+  fe_0(h->T2d);
+}
+
+static void cmov_cached(ge_cached *t, const ge_cached *u, uint8_t b) {
+  fe_cmov(t->YplusX, u->YplusX, b);
+  fe_cmov(t->YminusX, u->YminusX, b);
+  fe_cmov(t->Z, u->Z, b);
+  fe_cmov(t->T2d, u->T2d, b);
+}
+
+/* h = a * A, in constant time with respect to |a| and |A|, using a fixed
+ * 4-bit window.
+ *
+ * Preconditions:
+ *   a[31] <= 127 */
+static void ge_scalarmult(ge_p3 *h, const uint8_t a[32], const ge_p3 *A) {
+  ge_cached table[16];
+  ge_p3 tmp;
+  ge_p1p1 r;
+  ge_cached t;
+  int i;
+  int j;
+
+  ge_cached_0(&table[0]);
+  x25519_ge_p3_to_cached(&table[1], A);
+  tmp = *A;
+  for (i = 2; i < 16; ++i) {
+    x25519_ge_add(&r, &tmp, &table[1]);
+    x25519_ge_p1p1_to_p3(&tmp, &r);
+    x25519_ge_p3_to_cached(&table[i], &tmp);
+  }
+
+  ge_p3_0(h);
+  for (i = 63; i >= 0; --i) {
+    for (j = 0; j < 4; ++j) {
+      ge_p3_dbl(&r, h);
+      x25519_ge_p1p1_to_p3(h, &r);
+    }
+    signed char e = (a[i / 2] >> (4 * (i & 1))) & 15;
+    ge_cached_0(&t);
+    for (j = 1; j < 16; ++j) {
+      cmov_cached(&t, &table[j], equal(e, (signed char)j));
+    }
+    x25519_ge_add(&r, h, &t);
+    x25519_ge_p1p1_to_p3(h, &r);
+  }
+}
+
+
+/* Prototypes to avoid -Wmissing-prototypes warnings. */
+void GFp_ristretto255_from_uniform_bytes(uint8_t out[32],
+                                         const uint8_t in[64]);
+int GFp_ristretto255_point_is_valid(const uint8_t in[32]);
+void GFp_ristretto255_point_mul_base(uint8_t out[32], const uint8_t a[32]);
+int GFp_ristretto255_point_mul(uint8_t out[32], const uint8_t a[32],
+                               const uint8_t A[32]);
+int GFp_ristretto255_point_add(uint8_t out[32], const uint8_t a[32],
+                               const uint8_t b[32]);
+int GFp_ristretto255_point_sub(uint8_t out[32], const uint8_t a[32],
+                               const uint8_t b[32]);
+
+/* out = the one-way map from RFC 9496 Section 4.3.4 applied to |in|. */
+void GFp_ristretto255_from_uniform_bytes(uint8_t out[32],
+                                         const uint8_t in[64]) {
+  /* |fe_frombytes| ignores the most significant bit, as required. */
+  fe t;
+  ge_p3 p1;
+  ge_p3 p2;
+  ge_cached p2_cached;
+  ge_p1p1 r;
+  ge_p3 sum;
+
+  fe_frombytes(t, in);
+  ristretto255_map(&p1, t);
+  fe_frombytes(t, in + 32);
+  ristretto255_map(&p2, t);
+
+  x25519_ge_p3_to_cached(&p2_cached, &p2);
+  x25519_ge_add(&r, &p1, &p2_cached);
+  x25519_ge_p1p1_to_p3(&sum, &r);
+  ristretto255_encode(out, &sum);
+}
+
+/* Returns 1 if |in| is a valid encoding and 0 otherwise. */
+int GFp_ristretto255_point_is_valid(const uint8_t in[32]) {
+  ge_p3 p;
+  return ristretto255_decode(&p, in);
+}
+
+/* out = a * B, where B is the ristretto255 generator. |a| must be reduced
+ * mod l. This is constant-time with respect to |a|. */
+void GFp_ristretto255_point_mul_base(uint8_t out[32], const uint8_t a[32]) {
+  ge_p3 R;
+  x25519_ge_scalarmult_base(&R, a);
+  ristretto255_encode(out, &R);
+}
+
+/* out = a * A. |a| must be reduced mod l. This is constant-time with respect
+ * to |a|. Returns zero if |A| cannot be decoded. */
+int GFp_ristretto255_point_mul(uint8_t out[32], const uint8_t a[32],
+                               const uint8_t A[32]) {
+  ge_p3 A_p3;
+  if (!ristretto255_decode(&A_p3, A)) {
+    return 0;
+  }
+  ge_p3 R;
+  ge_scalarmult(&R, a, &A_p3);
+  ristretto255_encode(out, &R);
+  return 1;
+}
+
+/* out = a + b. Returns zero if |a| or |b| cannot be decoded. */
+int GFp_ristretto255_point_add(uint8_t out[32], const uint8_t a[32],
+                               const uint8_t b[32]) {
+  ge_p3 a_p3;
+  ge_p3 b_p3;
+  if (!ristretto255_decode(&a_p3, a) || !ristretto255_decode(&b_p3, b)) {
+    return 0;
+  }
+  ge_cached b_cached;
+  x25519_ge_p3_to_cached(&b_cached, &b_p3);
+  ge_p1p1 r;
+  x25519_ge_add(&r, &a_p3, &b_cached);
+  ge_p3 R;
+  x25519_ge_p1p1_to_p3(&R, &r);
+  ristretto255_encode(out, &R);
+  return 1;
+}
+
+/* out = a - b. Returns zero if |a| or |b| cannot be decoded. */
+int GFp_ristretto255_point_sub(uint8_t out[32], const uint8_t a[32],
+                               const uint8_t b[32]) {
+  ge_p3 a_p3;
+  ge_p3 b_p3;
+  if (!ristretto255_decode(&a_p3, a) || !ristretto255_decode(&b_p3, b)) {
+    return 0;
+  }
+  ge_cached b_cached;
+  x25519_ge_p3_to_cached(&b_cached, &b_p3);
+  ge_p1p1 r;
+  x25519_ge_sub(&r, &a_p3, &b_cached);
+  ge_p3 R;
+  x25519_ge_p1p1_to_p3(&R, &r);
+  ristretto255_encode(out, &R);
+  return 1;
+}
+
+
+/* Field arithmetic for |ring::hazmat::curve25519|. The inputs and outputs are
+ * the canonical little-endian encodings of field elements; the inputs of all
+ * the functions except |GFp_curve25519_fe_reduce| must be canonical. */
+
+/* Prototypes to avoid -Wmissing-prototypes warnings. */
+void GFp_curve25519_fe_reduce(uint8_t out[32], const uint8_t in[32]);
+void GFp_curve25519_fe_add(uint8_t out[32], const uint8_t a[32],
+                           const uint8_t b[32]);
+void GFp_curve25519_fe_sub(uint8_t out[32], const uint8_t a[32],
+                           const uint8_t b[32]);
+void GFp_curve25519_fe_mul(uint8_t out[32], const uint8_t a[32],
+                           const uint8_t b[32]);
+void GFp_curve25519_fe_neg(uint8_t out[32], const uint8_t a[32]);
+void GFp_curve25519_fe_invert(uint8_t out[32], const uint8_t a[32]);
+int GFp_curve25519_fe_sqrt_ratio_m1(uint8_t out[32], const uint8_t u[32],
+                                    const uint8_t v[32]);
+
+/* out = in mod p, ignoring the most significant bit of |in|. */
+void GFp_curve25519_fe_reduce(uint8_t out[32], const uint8_t in[32]) {
+  fe t;
+  fe_frombytes(t, in);
+  fe_tobytes(out, t);
+}
+
+void GFp_curve25519_fe_add(uint8_t out[32], const uint8_t a[32],
+                           const uint8_t b[32]) {
+  fe f, g, h;
+  fe_frombytes(f, a);
+  fe_frombytes(g, b);
+  fe_add(h, f, g);
+  fe_tobytes(out, h);
+}
+
+void GFp_curve25519_fe_sub(uint8_t out[32], const uint8_t a[32],
+                           const uint8_t b[32]) {
+  fe f, g, h;
+  fe_frombytes(f, a);
+  fe_frombytes(g, b);
+  fe_sub(h, f, g);
+  fe_tobytes(out, h);
+}
+
+void GFp_curve25519_fe_mul(uint8_t out[32], const uint8_t a[32],
+                           const uint8_t b[32]) {
+  fe f, g, h;
+  fe_frombytes(f, a);
+  fe_frombytes(g, b);
+  fe_mul(h, f, g);
+  fe_tobytes(out, h);
+}
+
+void GFp_curve25519_fe_neg(uint8_t out[32], const uint8_t a[32]) {
+  fe f, h;
+  fe_frombytes(f, a);
+  fe_neg(h, f);
+  fe_tobytes(out, h);
+}
+
+/* out = 1/a, or zero if |a| is zero. */
+void GFp_curve25519_fe_invert(uint8_t out[32], const uint8_t a[32]) {
+  fe f, h;
+  fe_frombytes(f, a);
+  fe_invert(h, f);
+  fe_tobytes(out, h);
+}
+
+/* See |fe_sqrt_ratio_m1|. */
+int GFp_curve25519_fe_sqrt_ratio_m1(uint8_t out[32], const uint8_t u[32],
+                                    const uint8_t v[32]) {
+  fe f, g, r;
+  fe_frombytes(f, u);
+  fe_frombytes(g, v);
+  int was_square = fe_sqrt_ratio_m1(r, f, g);
+  fe_tobytes(out, r);
+  return was_square;
+}
+
+
+/* X25519 public keys for |ring::elligator2|. */
+
+/* Prototypes to avoid -Wmissing-prototypes warnings. */
+void GFp_x25519_public_from_private_dirty(uint8_t out_public_value[32],
+                                          const uint8_t private_key[32]);
+
+/* Like |GFp_x25519_public_from_private|, but adds to the public point the
+ * multiple of a point of order 8 given by the low three bits of
+ * |private_key|, which X25519 ignores. Honestly-generated X25519 public keys
+ * are all in the prime-order subgroup, which makes their Elligator2
+ * representatives distinguishable from random strings; these aren't. The
+ * result of X25519 with any private key is the same for both public keys. */
+void GFp_x25519_public_from_private_dirty(uint8_t out_public_value[32],
+                                          const uint8_t private_key[32]) {
+  static const uint8_t kOrder8Point[32] = {
+      0x26, 0xe8, 0x95, 0x8f, 0xc2, 0xb2, 0x27, 0xb0,
+      0x45, 0xc3, 0xf4, 0x89, 0xf2, 0xef, 0x98, 0xf0,
+      0xd5, 0xdf, 0xac, 0x05, 0xd3, 0xc6, 0x33, 0x39,
+      0xb1, 0x38, 0x02, 0x88, 0x6d, 0x53, 0xfc, 0x05
+  };
+
+  uint8_t e[32];
+  memcpy(e, private_key, 32);
+  e[0] &= 248;
+  e[31] &= 127;
+  e[31] |= 64;
+
+  ge_p3 A;
+  x25519_ge_scalarmult_base(&A, e);
+
+  /* This can't fail since |kOrder8Point| is a valid encoding. */
+  ge_p3 T8;
+  (void)x25519_ge_frombytes_vartime(&T8, kOrder8Point);
+  uint8_t k[32] = {0};
+  k[0] = private_key[0] & 7;
+  ge_p3 T;
+  ge_scalarmult(&T, k, &T8);
+
+  ge_cached T_cached;
+  ge_p1p1 r;
+  x25519_ge_p3_to_cached(&T_cached, &T);
+  x25519_ge_add(&r, &A, &T_cached);
+  x25519_ge_p1p1_to_p3(&A, &r);
+
+  /* u=(Z+Y)/(Z-Y); see |GFp_x25519_public_from_private|. */
+  fe zplusy, zminusy, zminusy_inv;
+  fe_add(zplusy, A.Z, A.Y);
+  fe_sub(zminusy, A.Z, A.Y);
+  fe_invert(zminusy_inv, zminusy);
+  fe_mul(zplusy, zplusy, zminusy_inv);
+  fe_tobytes(out_public_value, zplusy);
+}
+
 
 #if defined(BORINGSSL_X25519_X86_64)
 
@@ -35,6 +35,10 @@ void GFp_x25519_x86_64(uint8_t out[32], const uint8_t scalar[32],
 
 
 #if defined(OPENSSL_ARM) && !defined(OPENSSL_NO_ASM)
+#include <openssl/arm_arch.h>
+#endif
+
+#if defined(OPENSSL_ARM) && !defined(OPENSSL_NO_ASM) && __ARM_MAX_ARCH__ >= 7
 #define BORINGSSL_X25519_NEON
 
 /* x25519_NEON is defined in asm/x25519-arm.S. */
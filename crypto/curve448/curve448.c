@@ -0,0 +1,300 @@
+/* Copyright 2016 Brian Smith.
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+ * SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+ * OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+ * CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE. */
+
+/* X448 as described in RFC 7748.
+ *
+ * Field elements of GF(p), p = 2**448 - 2**224 - 1, are represented as
+ * sixteen 28-bit limbs, least significant first, so that the products of
+ * limbs fit comfortably in 64 bits. Since 224 = 8 * 28, the reduction
+ * 2**448 == 2**224 + 1 (mod p) just adds the high half of a product into
+ * limbs 0 and 8. All operations are constant-time. */
+
+#include <string.h>
+
+#include <openssl/mem.h>
+
+#include "../internal.h"
+
+
+#define X448_LEN 56
+#define FE_LIMBS 16
+
+typedef uint32_t fe[FE_LIMBS];
+
+static const uint64_t kBottom28Bits = UINT64_C(0xfffffff);
+
+
+/* Prototypes to avoid -Wmissing-prototypes warnings. */
+int GFp_x448_ecdh(uint8_t out_shared_key[X448_LEN],
+                  const uint8_t private_key[X448_LEN],
+                  const uint8_t peer_public_value[X448_LEN]);
+void GFp_x448_public_from_private(uint8_t out_public_value[X448_LEN],
+                                  const uint8_t private_key[X448_LEN]);
+
+
+/* Carries |c| into |h|. Each element of |c| must be less than 2**63. The
+ * limbs of |h| are less than 2**28, except that limbs 0 and 8 may be a little
+ * larger. */
+static void fe_carry(fe h, uint64_t c[FE_LIMBS]) {
+  size_t pass;
+  size_t i;
+  for (pass = 0; pass < 2; ++pass) {
+    uint64_t top;
+    for (i = 0; i < FE_LIMBS - 1; ++i) {
+      c[i + 1] += c[i] >> 28;
+      c[i] &= kBottom28Bits;
+    }
+    top = c[FE_LIMBS - 1] >> 28;
+    c[FE_LIMBS - 1] &= kBottom28Bits;
+    c[0] += top;
+    c[8] += top;
+  }
+  for (i = 0; i < FE_LIMBS; ++i) {
+    h[i] = (uint32_t)c[i];
+  }
+}
+
+static void fe_frombytes(fe h, const uint8_t s[X448_LEN]) {
+  uint64_t c[FE_LIMBS];
+  size_t i;
+  /* Each pair of limbs is seven bytes. */
+  for (i = 0; i < FE_LIMBS; i += 2) {
+    const uint8_t *in = &s[(i / 2) * 7];
+    uint64_t v = 0;
+    size_t j;
+    for (j = 0; j < 7; ++j) {
+      v |= ((uint64_t)in[j]) << (8 * j);
+    }
+    c[i] = v & kBottom28Bits;
+    c[i + 1] = v >> 28;
+  }
+  fe_carry(h, c);
+}
+
+/* Writes the fully-reduced value of |f| to |s|. */
+static void fe_tobytes(uint8_t s[X448_LEN], const fe f) {
+  /* p, as limbs. */
+  static const uint32_t kP[FE_LIMBS] = {
+    0xfffffff, 0xfffffff, 0xfffffff, 0xfffffff,
+    0xfffffff, 0xfffffff, 0xfffffff, 0xfffffff,
+    0xffffffe, 0xfffffff, 0xfffffff, 0xfffffff,
+    0xfffffff, 0xfffffff, 0xfffffff, 0xfffffff,
+  };
+  uint64_t c[FE_LIMBS];
+  uint32_t d[FE_LIMBS];
+  int64_t borrow = 0;
+  uint32_t mask;
+  size_t pass;
+  size_t i;
+
+  for (i = 0; i < FE_LIMBS; ++i) {
+    c[i] = f[i];
+  }
+  /* After these passes the value is less than 2**448, and so less than 2p,
+   * and every limb is less than 2**28. */
+  for (pass = 0; pass < 4; ++pass) {
+    uint64_t top;
+    for (i = 0; i < FE_LIMBS - 1; ++i) {
+      c[i + 1] += c[i] >> 28;
+      c[i] &= kBottom28Bits;
+    }
+    top = c[FE_LIMBS - 1] >> 28;
+    c[FE_LIMBS - 1] &= kBottom28Bits;
+    c[0] += top;
+    c[8] += top;
+  }
+
+  /* Subtract p if the value is at least p. */
+  for (i = 0; i < FE_LIMBS; ++i) {
+    int64_t t = (int64_t)c[i] - (int64_t)kP[i] + borrow;
+    d[i] = (uint32_t)(t & (int64_t)kBottom28Bits);
+    borrow = t >> 28;
+  }
+  /* |mask| is all ones iff there was no borrow, i.e. the value was >= p. */
+  mask = (uint32_t)0 - (uint32_t)(borrow + 1);
+  for (i = 0; i < FE_LIMBS; ++i) {
+    c[i] = (mask & d[i]) | (~mask & (uint32_t)c[i]);
+  }
+
+  for (i = 0; i < FE_LIMBS; i += 2) {
+    uint8_t *out = &s[(i / 2) * 7];
+    uint64_t v = c[i] | (c[i + 1] << 28);
+    size_t j;
+    for (j = 0; j < 7; ++j) {
+      out[j] = (uint8_t)(v >> (8 * j));
+    }
+  }
+}
+
+static void fe_0(fe h) {
+  memset(h, 0, sizeof(fe));
+}
+
+static void fe_1(fe h) {
+  fe_0(h);
+  h[0] = 1;
+}
+
+static void fe_copy(fe h, const fe f) {
+  memmove(h, f, sizeof(fe));
+}
+
+static void fe_add(fe h, const fe f, const fe g) {
+  uint64_t c[FE_LIMBS];
+  size_t i;
+  for (i = 0; i < FE_LIMBS; ++i) {
+    c[i] = (uint64_t)f[i] + g[i];
+  }
+  fe_carry(h, c);
+}
+
+static void fe_sub(fe h, const fe f, const fe g) {
+  uint64_t c[FE_LIMBS];
+  size_t i;
+  for (i = 0; i < FE_LIMBS; ++i) {
+    /* Add 4p so that no limb of the difference is negative. */
+    uint64_t four_p_limb = (i == 8) ? UINT64_C(0x3ffffff8)
+                                    : UINT64_C(0x3ffffffc);
+    c[i] = (uint64_t)f[i] + four_p_limb - g[i];
+  }
+  fe_carry(h, c);
+}
+
+static void fe_mul(fe h, const fe f, const fe g) {
+  uint64_t c[2 * FE_LIMBS];
+  size_t i;
+  size_t j;
+  memset(c, 0, sizeof(c));
+  for (i = 0; i < FE_LIMBS; ++i) {
+    for (j = 0; j < FE_LIMBS; ++j) {
+      c[i + j] += (uint64_t)f[i] * g[j];
+    }
+  }
+  /* Fold the high half in, from the top down so that the additions to limbs
+   * 16..23 are themselves folded. */
+  for (i = FE_LIMBS; i-- > 0; ) {
+    c[i] += c[FE_LIMBS + i];
+    c[i + 8] += c[FE_LIMBS + i];
+  }
+  fe_carry(h, c);
+}
+
+static void fe_sq(fe h, const fe f) {
+  fe_mul(h, f, f);
+}
+
+static void fe_mul_a24(fe h, const fe f) {
+  static const uint64_t kA24 = 39081;
+  uint64_t c[FE_LIMBS];
+  size_t i;
+  for (i = 0; i < FE_LIMBS; ++i) {
+    c[i] = f[i] * kA24;
+  }
+  fe_carry(h, c);
+}
+
+/* Sets |out| to |z|**(p - 2) = 1/|z|. The exponent is public, so the
+ * square-and-multiply is constant-time. */
+static void fe_invert(fe out, const fe z) {
+  fe t;
+  size_t i;
+  fe_1(t);
+  for (i = 448; i-- > 0; ) {
+    /* p - 2 = 2**448 - 2**224 - 3 has every bit set except bits 224 and 1. */
+    fe_sq(t, t);
+    if (i != 224 && i != 1) {
+      fe_mul(t, t, z);
+    }
+  }
+  fe_copy(out, t);
+}
+
+/* Swaps |f| and |g| iff |b| is 1. |b| must be 0 or 1. */
+static void fe_cswap(fe f, fe g, uint32_t b) {
+  uint32_t mask = (uint32_t)0 - b;
+  size_t i;
+  for (i = 0; i < FE_LIMBS; ++i) {
+    uint32_t x = mask & (f[i] ^ g[i]);
+    f[i] ^= x;
+    g[i] ^= x;
+  }
+}
+
+static void x448_scalar_mult(uint8_t out[X448_LEN],
+                             const uint8_t scalar[X448_LEN],
+                             const uint8_t point[X448_LEN]) {
+  uint8_t k[X448_LEN];
+  fe x1, x2, z2, x3, z3, a, aa, b, bb, e, c, d, da, cb;
+  uint32_t swap = 0;
+  size_t pos;
+
+  memcpy(k, scalar, X448_LEN);
+  k[0] &= 252;
+  k[55] |= 128;
+
+  fe_frombytes(x1, point);
+  fe_1(x2);
+  fe_0(z2);
+  fe_copy(x3, x1);
+  fe_1(z3);
+
+  for (pos = 448; pos-- > 0; ) {
+    uint32_t k_t = (k[pos / 8] >> (pos & 7)) & 1;
+    swap ^= k_t;
+    fe_cswap(x2, x3, swap);
+    fe_cswap(z2, z3, swap);
+    swap = k_t;
+
+    fe_add(a, x2, z2);
+    fe_sq(aa, a);
+    fe_sub(b, x2, z2);
+    fe_sq(bb, b);
+    fe_sub(e, aa, bb);
+    fe_add(c, x3, z3);
+    fe_sub(d, x3, z3);
+    fe_mul(da, d, a);
+    fe_mul(cb, c, b);
+    fe_add(x3, da, cb);
+    fe_sq(x3, x3);
+    fe_sub(z3, da, cb);
+    fe_sq(z3, z3);
+    fe_mul(z3, z3, x1);
+    fe_mul(x2, aa, bb);
+    fe_mul_a24(z2, e);
+    fe_add(z2, z2, aa);
+    fe_mul(z2, z2, e);
+  }
+  fe_cswap(x2, x3, swap);
+  fe_cswap(z2, z3, swap);
+
+  fe_invert(z2, z2);
+  fe_mul(x2, x2, z2);
+  fe_tobytes(out, x2);
+}
+
+/* GFp_x448_ecdh is the |X448| function from the RFC. */
+int GFp_x448_ecdh(uint8_t out_shared_key[X448_LEN],
+                  const uint8_t private_key[X448_LEN],
+                  const uint8_t peer_public_value[X448_LEN]) {
+  static const uint8_t kZeros[X448_LEN] = {0};
+  x448_scalar_mult(out_shared_key, private_key, peer_public_value);
+  /* The all-zero output results when the input is a point of small order. */
+  return GFp_memcmp(kZeros, out_shared_key, X448_LEN) != 0;
+}
+
+void GFp_x448_public_from_private(uint8_t out_public_value[X448_LEN],
+                                  const uint8_t private_key[X448_LEN]) {
+  static const uint8_t kMontgomeryBasePoint[X448_LEN] = {5};
+  x448_scalar_mult(out_public_value, private_key, kMontgomeryBasePoint);
+}
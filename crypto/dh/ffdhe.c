@@ -0,0 +1,144 @@
+/* Copyright 2016 Brian Smith.
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+ * SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+ * OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+ * CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE. */
+
+/* Finite-field Diffie-Hellman in the RFC 7919 groups. The groups themselves
+ * are defined on the Rust side; these functions just do the modular
+ * exponentiation and the validation of the peer's public value. */
+
+#include <openssl/bn.h>
+
+#include "../internal.h"
+
+
+/* Prototypes to avoid -Wmissing-prototypes warnings. */
+int GFp_ffdhe_public_from_private(uint8_t *out, const uint8_t *p,
+                                  size_t p_len, const uint8_t *private_key,
+                                  size_t private_key_len);
+int GFp_ffdhe_compute_key(uint8_t *out, const uint8_t *p, size_t p_len,
+                          const uint8_t *private_key, size_t private_key_len,
+                          const uint8_t *peer_public_value);
+
+
+/* Sets |out|, which is |p_len| bytes long, to the big-endian encoding of
+ * |base|**|private_key| (mod |p|), left-padded with zeros. It returns one on
+ * success and zero otherwise. |base| must be less than |p|. */
+static int mod_exp(uint8_t *out, const BIGNUM *p, size_t p_len,
+                   const BIGNUM *base, const uint8_t *private_key,
+                   size_t private_key_len) {
+  int ret = 0;
+
+  BIGNUM x;
+  GFp_BN_init(&x);
+
+  BIGNUM result;
+  GFp_BN_init(&result);
+
+  BN_MONT_CTX *mont = GFp_BN_MONT_CTX_new();
+  if (mont == NULL ||
+      !GFp_BN_MONT_CTX_set(mont, p) ||
+      GFp_BN_bin2bn(private_key, private_key_len, &x) == NULL ||
+      !GFp_BN_mod_exp_mont_consttime(&result, base, &x, mont) ||
+      !GFp_BN_bn2bin_padded(out, p_len, &result)) {
+    goto err;
+  }
+
+  ret = 1;
+
+err:
+  GFp_BN_MONT_CTX_free(mont);
+  GFp_BN_free(&x);
+  GFp_BN_free(&result);
+  return ret;
+}
+
+/* GFp_ffdhe_public_from_private sets |out|, which is |p_len| bytes long, to
+ * 2**|private_key| (mod |p|), where |p| is the |p_len|-byte big-endian
+ * encoding of the group's prime. It returns one on success and zero
+ * otherwise. */
+int GFp_ffdhe_public_from_private(uint8_t *out, const uint8_t *p,
+                                  size_t p_len, const uint8_t *private_key,
+                                  size_t private_key_len) {
+  int ret = 0;
+
+  BIGNUM p_bn;
+  GFp_BN_init(&p_bn);
+
+  BIGNUM g;
+  GFp_BN_init(&g);
+
+  if (GFp_BN_bin2bn(p, p_len, &p_bn) == NULL ||
+      !GFp_BN_set_word(&g, 2) ||
+      !mod_exp(out, &p_bn, p_len, &g, private_key, private_key_len)) {
+    goto err;
+  }
+
+  ret = 1;
+
+err:
+  GFp_BN_free(&p_bn);
+  GFp_BN_free(&g);
+  return ret;
+}
+
+/* GFp_ffdhe_compute_key sets |out|, which is |p_len| bytes long, to the
+ * shared secret |peer_public_value|**|private_key| (mod |p|), left-padded
+ * with zeros as RFC 7919 Section 5 requires. |peer_public_value| is
+ * |p_len| bytes long. As RFC 7919 Section 5.1 requires, the peer's public
+ * value must be in the range [2, p - 2], and the shared secret must not be
+ * one. It returns one on success and zero otherwise. */
+int GFp_ffdhe_compute_key(uint8_t *out, const uint8_t *p, size_t p_len,
+                          const uint8_t *private_key, size_t private_key_len,
+                          const uint8_t *peer_public_value) {
+  int ret = 0;
+
+  BIGNUM p_bn;
+  GFp_BN_init(&p_bn);
+
+  BIGNUM y;
+  GFp_BN_init(&y);
+
+  BIGNUM p_minus_y;
+  GFp_BN_init(&p_minus_y);
+
+  BIGNUM z;
+  GFp_BN_init(&z);
+
+  if (GFp_BN_bin2bn(p, p_len, &p_bn) == NULL ||
+      GFp_BN_bin2bn(peer_public_value, p_len, &y) == NULL) {
+    goto err;
+  }
+
+  /* 2 <= y, and y < p with p - y >= 2, i.e. y <= p - 2. */
+  if (GFp_BN_cmp_word(&y, 2) < 0 ||
+      GFp_BN_ucmp(&y, &p_bn) >= 0 ||
+      !GFp_BN_usub(&p_minus_y, &p_bn, &y) ||
+      GFp_BN_cmp_word(&p_minus_y, 2) < 0) {
+    goto err;
+  }
+
+  if (!mod_exp(out, &p_bn, p_len, &y, private_key, private_key_len) ||
+      GFp_BN_bin2bn(out, p_len, &z) == NULL ||
+      GFp_BN_is_one(&z)) {
+    goto err;
+  }
+
+  ret = 1;
+
+err:
+  GFp_BN_free(&p_bn);
+  GFp_BN_free(&y);
+  GFp_BN_free(&p_minus_y);
+  GFp_BN_free(&z);
+  return ret;
+}
@@ -0,0 +1,44 @@
+/* Copyright (c) 2014, Intel Corporation.
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY
+ * SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+ * OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+ * CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE. */
+
+#ifndef OPENSSL_HEADER_EC_ECP_NISTZ521_H
+#define OPENSSL_HEADER_EC_ECP_NISTZ521_H
+
+#include <openssl/bn.h>
+
+#include "gfp_internal.h"
+
+#if defined(__cplusplus)
+extern "C" {
+#endif
+
+
+typedef struct {
+  BN_ULONG X[P521_LIMBS];
+  BN_ULONG Y[P521_LIMBS];
+  BN_ULONG Z[P521_LIMBS];
+} P521_POINT;
+
+
+/* Prototypes to avoid -Wmissing-prototypes warnings. */
+void GFp_nistz521_point_double(P521_POINT *r, const P521_POINT *a);
+void GFp_nistz521_point_add(P521_POINT *r, const P521_POINT *a,
+                            const P521_POINT *b);
+
+
+#if defined(__cplusplus)
+}
+#endif
+
+#endif /* OPENSSL_HEADER_EC_ECP_NISTZ521_H */
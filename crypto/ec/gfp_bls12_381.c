@@ -0,0 +1,100 @@
+/* Copyright 2016 Brian Smith.
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+ * SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+ * OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+ * CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE. */
+
+/* Arithmetic in the base field of BLS12-381. Elements are fully reduced and,
+ * for |GFp_bls12_381_elem_mul_mont|, Montgomery-encoded with R = 2**384. */
+
+#include "gfp_internal.h"
+
+#include "../bn/internal.h"
+#include "../internal.h"
+
+#include "gfp_limbs.inl"
+
+
+#define BLS12_381_LIMBS (384u / BN_BITS2)
+
+typedef GFp_Limb Elem[BLS12_381_LIMBS];
+
+
+/* Prototypes to avoid -Wmissing-prototypes warnings. */
+void GFp_bls12_381_elem_add(Elem r, const Elem a, const Elem b);
+void GFp_bls12_381_elem_mul_mont(Elem r, const Elem a, const Elem b);
+void GFp_bls12_381_elem_neg(Elem r, const Elem a);
+void GFp_bls12_381_elem_sub(Elem r, const Elem a, const Elem b);
+
+
+/* q = 0x1a0111ea397fe69a4b1ba7b6434bacd764774b84f38512bf6730d2a0f6b0f6241eabfffe
+ *       b153ffffb9feffffffffaaab. */
+static const BN_ULONG Q[BLS12_381_LIMBS] = {
+  TOBN(0xb9feffff, 0xffffaaab),
+  TOBN(0x1eabfffe, 0xb153ffff),
+  TOBN(0x6730d2a0, 0xf6b0f624),
+  TOBN(0x64774b84, 0xf38512bf),
+  TOBN(0x4b1ba7b6, 0x434bacd7),
+  TOBN(0x1a0111ea, 0x397fe69a),
+};
+
+OPENSSL_COMPILE_ASSERT(sizeof(size_t) == sizeof(GFp_Limb),
+                       size_t_and_gfp_limb_are_different_sizes);
+
+
+static void copy_conditional(Elem r, const Elem a, const GFp_Limb condition) {
+  for (size_t i = 0; i < BLS12_381_LIMBS; ++i) {
+    r[i] = constant_time_select_size_t(condition, a[i], r[i]);
+  }
+}
+
+
+void GFp_bls12_381_elem_add(Elem r, const Elem a, const Elem b) {
+  /* q < 2**381, so the sum can't overflow. */
+  GFp_Carry carry = gfp_limbs_add(r, a, b, BLS12_381_LIMBS);
+#if defined(NDEBUG)
+  (void)carry;
+#endif
+  assert(carry == 0);
+  Elem adjusted;
+  GFp_Limb no_borrow = constant_time_is_zero_size_t(
+      gfp_limbs_sub(adjusted, r, Q, BLS12_381_LIMBS));
+  copy_conditional(r, adjusted, no_borrow);
+}
+
+void GFp_bls12_381_elem_sub(Elem r, const Elem a, const Elem b) {
+  GFp_Limb borrow = constant_time_is_nonzero_size_t(
+      gfp_limbs_sub(r, a, b, BLS12_381_LIMBS));
+  Elem adjusted;
+  (void)gfp_limbs_add(adjusted, r, Q, BLS12_381_LIMBS);
+  copy_conditional(r, adjusted, borrow);
+}
+
+void GFp_bls12_381_elem_neg(Elem r, const Elem a) {
+  GFp_Limb is_zero = GFp_constant_time_limbs_are_zero(a, BLS12_381_LIMBS);
+  GFp_Carry borrow = gfp_limbs_sub(r, Q, a, BLS12_381_LIMBS);
+#if defined(NDEBUG)
+  (void)borrow;
+#endif
+  assert(borrow == 0);
+  for (size_t i = 0; i < BLS12_381_LIMBS; ++i) {
+    r[i] = constant_time_select_size_t(is_zero, 0, r[i]);
+  }
+}
+
+void GFp_bls12_381_elem_mul_mont(Elem r, const Elem a, const Elem b) {
+  static const BN_ULONG Q_N0[] = {
+    BN_MONT_CTX_N0(0x89f3fffc, 0xfffcfffd)
+  };
+  /* XXX: Inefficient. TODO: Add dedicated multiplication and squaring
+   * routines. */
+  GFp_bn_mul_mont(r, a, b, Q, Q_N0, BLS12_381_LIMBS);
+}
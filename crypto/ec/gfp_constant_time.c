@@ -25,6 +25,9 @@
 GFp_Limb GFp_constant_time_limbs_lt_limbs(const GFp_Limb a[],
                                           const GFp_Limb b[],
                                           size_t num_limbs);
+void GFp_constant_time_limbs_add_mod(GFp_Limb r[], const GFp_Limb a[],
+                                     const GFp_Limb b[], const GFp_Limb m[],
+                                     size_t num_limbs);
 
 
 /* We have constant time primitives on |size_t|. Rather than duplicate them,
@@ -96,3 +99,23 @@ void GFp_constant_time_limbs_reduce_once(GFp_Limb r[], const GFp_Limb m[],
   }
   assert(borrow == 0);
 }
+
+/* r = (a + b) mod m, where |a| and |b| are both less than |m|. */
+void GFp_constant_time_limbs_add_mod(GFp_Limb r[], const GFp_Limb a[],
+                                     const GFp_Limb b[], const GFp_Limb m[],
+                                     size_t num_limbs) {
+  assert(num_limbs >= 1);
+  GFp_Carry carry = gfp_limbs_add(r, a, b, num_limbs);
+  /* Subtract |m| if the sum overflowed or if it is at least |m|. When the sum
+   * overflowed, the final borrow cancels out the carry. */
+  GFp_Limb lt = GFp_constant_time_limbs_lt_limbs(r, m, num_limbs);
+  GFp_Limb subtract = constant_time_is_nonzero_size_t(carry) | ~lt;
+  GFp_Carry borrow = gfp_sub(&r[0], r[0], m[0] & subtract);
+  for (size_t i = 1; i < num_limbs; ++i) {
+    borrow = gfp_sbb(&r[i], r[i], m[i] & subtract, borrow);
+  }
+#if defined(NDEBUG)
+  (void)borrow;
+#endif
+  assert(borrow == carry);
+}
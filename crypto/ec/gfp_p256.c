@@ -33,14 +33,14 @@ void GFp_p256_scalar_sqr_mont(ScalarMont r, const ScalarMont a);
 void GFp_p256_scalar_sqr_rep_mont(ScalarMont r, const ScalarMont a, int rep);
 
 
-#if defined(OPENSSL_ARM) || defined(OPENSSL_X86)
+#if defined(OPENSSL_ARM) || defined(OPENSSL_X86) || defined(OPENSSL_NO_ASM)
 void GFp_nistz256_sqr_mont(Elem r, const Elem a) {
   /* XXX: Inefficient. TODO: optimize with dedicated squaring routine. */
   GFp_nistz256_mul_mont(r, a, a);
 }
 #endif
 
-#if !defined(OPENSSL_X86_64)
+#if !defined(OPENSSL_X86_64) || defined(OPENSSL_NO_ASM)
 void GFp_p256_scalar_mul_mont(ScalarMont r, const ScalarMont a,
                               const ScalarMont b) {
   static const BN_ULONG N[] = {
@@ -57,7 +57,7 @@ void GFp_p256_scalar_mul_mont(ScalarMont r, const ScalarMont a,
 }
 #endif
 
-#if defined(OPENSSL_X86_64)
+#if defined(OPENSSL_X86_64) && !defined(OPENSSL_NO_ASM)
 void GFp_p256_scalar_sqr_mont(ScalarMont r, const ScalarMont a) {
   GFp_p256_scalar_sqr_rep_mont(r, a, 1);
 }
@@ -76,7 +76,7 @@ void GFp_p256_scalar_sqr_rep_mont(ScalarMont r, const ScalarMont a, int rep) {
 #endif
 
 
-#if !defined(OPENSSL_X86_64)
+#if !defined(OPENSSL_X86_64) || defined(OPENSSL_NO_ASM)
 
 /* TODO(perf): Optimize these. */
 
@@ -128,3 +128,281 @@ void GFp_nistz256_select_w7(P256_POINT_AFFINE *out,
 }
 
 #endif
+
+
+#if defined(OPENSSL_NO_ASM)
+
+/* Portable implementations of the field arithmetic and point operations that
+ * the other targets implement in assembly language. They work like the P-384
+ * ones in gfp_p384.c and ecp_nistz384.inl. */
+
+#include "gfp_limbs.inl"
+
+
+/* Prototypes to avoid -Wmissing-prototypes warnings. */
+void GFp_nistz256_add(Elem r, const Elem a, const Elem b);
+void GFp_nistz256_neg(Elem r, const Elem a);
+void GFp_nistz256_point_double(P256_POINT *r, const P256_POINT *a);
+void GFp_nistz256_point_add(P256_POINT *r, const P256_POINT *a,
+                            const P256_POINT *b);
+void GFp_nistz256_point_add_affine(P256_POINT *r, const P256_POINT *a,
+                                   const P256_POINT_AFFINE *b);
+
+
+static const BN_ULONG Q[P256_LIMBS] = {
+  TOBN(0xffffffff, 0xffffffff),
+  TOBN(0x00000000, 0xffffffff),
+  TOBN(0x00000000, 0x00000000),
+  TOBN(0xffffffff, 0x00000001),
+};
+
+/* One converted into the Montgomery domain */
+static const BN_ULONG ONE[P256_LIMBS] = {
+  TOBN(0x00000000, 0x00000001), TOBN(0xffffffff, 0x00000000),
+  TOBN(0xffffffff, 0xffffffff), TOBN(0x00000000, 0xfffffffe),
+};
+
+
+static GFp_Limb is_equal(const Elem a, const Elem b) {
+  return GFp_constant_time_limbs_eq_limbs(a, b, P256_LIMBS);
+}
+
+/* We encode infinity as (0,0), which is not on the curve, so it is OK. */
+static GFp_Limb is_infinity(const Elem x, const Elem y) {
+  GFp_Limb acc = 0;
+  for (size_t i = 0; i < P256_LIMBS; ++i) {
+    acc |= x[i] | y[i];
+  }
+  return constant_time_is_zero_size_t(acc);
+}
+
+static void copy_conditional(Elem r, const Elem a, const GFp_Limb condition) {
+  for (size_t i = 0; i < P256_LIMBS; ++i) {
+    r[i] = constant_time_select_size_t(condition, a[i], r[i]);
+  }
+}
+
+static void elem_add(Elem r, const Elem a, const Elem b) {
+  GFp_Limb carry =
+      constant_time_is_nonzero_size_t(gfp_limbs_add(r, a, b, P256_LIMBS));
+  Elem adjusted;
+  GFp_Limb no_borrow =
+      constant_time_is_zero_size_t(gfp_limbs_sub(adjusted, r, Q, P256_LIMBS));
+  copy_conditional(r, adjusted,
+                   constant_time_select_size_t(carry, carry, no_borrow));
+}
+
+static void elem_sub(Elem r, const Elem a, const Elem b) {
+  GFp_Limb borrow =
+    constant_time_is_nonzero_size_t(gfp_limbs_sub(r, a, b, P256_LIMBS));
+  Elem adjusted;
+  (void)gfp_limbs_add(adjusted, r, Q, P256_LIMBS);
+  copy_conditional(r, adjusted, borrow);
+}
+
+/* See |elem_div_by_2| in gfp_p384.c for why this works. */
+static void elem_div_by_2(Elem r, const Elem a) {
+  GFp_Limb is_odd = constant_time_is_nonzero_size_t(a[0] & 1);
+
+  /* r = a >> 1. */
+  GFp_Limb carry = a[P256_LIMBS - 1] & 1;
+  r[P256_LIMBS - 1] = a[P256_LIMBS - 1] >> 1;
+  for (size_t i = 1; i < P256_LIMBS; ++i) {
+    GFp_Limb new_carry = a[P256_LIMBS - i - 1];
+    r[P256_LIMBS - i - 1] =
+        (a[P256_LIMBS - i - 1] >> 1) | (carry << (GFp_LIMB_BITS - 1));
+    carry = new_carry;
+  }
+
+  static const Elem Q_PLUS_1_SHR_1 = {
+    TOBN(0x00000000, 0x00000000), TOBN(0x00000000, 0x80000000),
+    TOBN(0x80000000, 0x00000000), TOBN(0x7fffffff, 0x80000000),
+  };
+
+  Elem adjusted;
+  BN_ULONG carry2 = gfp_limbs_add(adjusted, r, Q_PLUS_1_SHR_1, P256_LIMBS);
+#if defined(NDEBUG)
+  (void)carry2;
+#endif
+  assert(carry2 == 0);
+
+  copy_conditional(r, adjusted, is_odd);
+}
+
+static void elem_mul_mont(Elem r, const Elem a, const Elem b) {
+  GFp_nistz256_mul_mont(r, a, b);
+}
+
+static void elem_mul_by_2(Elem r, const Elem a) {
+  elem_add(r, a, a);
+}
+
+static void elem_mul_by_3(Elem r, const Elem a) {
+  Elem doubled;
+  elem_add(doubled, a, a);
+  elem_add(r, doubled, a);
+}
+
+static void elem_sqr_mont(Elem r, const Elem a) {
+  elem_mul_mont(r, a, a);
+}
+
+/* Like the assembly language implementations, this reduces the sum only if
+ * it doesn't fit in |P256_LIMBS| limbs, so the result is in [0, 2**256) but
+ * may not be fully reduced. */
+void GFp_nistz256_add(Elem r, const Elem a, const Elem b) {
+  GFp_Limb carry =
+      constant_time_is_nonzero_size_t(gfp_limbs_add(r, a, b, P256_LIMBS));
+  Elem adjusted;
+  (void)gfp_limbs_sub(adjusted, r, Q, P256_LIMBS);
+  copy_conditional(r, adjusted, carry);
+}
+
+void GFp_nistz256_neg(Elem r, const Elem a) {
+  GFp_Limb is_zero = GFp_constant_time_limbs_are_zero(a, P256_LIMBS);
+  GFp_Carry borrow = gfp_limbs_sub(r, Q, a, P256_LIMBS);
+#if defined(NDEBUG)
+  (void)borrow;
+#endif
+  assert(borrow == 0);
+  for (size_t i = 0; i < P256_LIMBS; ++i) {
+    r[i] = constant_time_select_size_t(is_zero, 0, r[i]);
+  }
+}
+
+void GFp_nistz256_mul_mont(Elem r, const Elem a, const Elem b) {
+  static const BN_ULONG Q_N0[] = {
+    BN_MONT_CTX_N0(0x0, 0x1)
+  };
+  /* XXX: Not (clearly) constant-time; inefficient. */
+  GFp_bn_mul_mont(r, a, b, Q, Q_N0, P256_LIMBS);
+}
+
+/* Point double: r = 2*a */
+void GFp_nistz256_point_double(P256_POINT *r, const P256_POINT *a) {
+  Elem S, M, Zsqr, tmp0;
+
+  const GFp_Limb *in_x = a->X;
+  const GFp_Limb *in_y = a->Y;
+  const GFp_Limb *in_z = a->Z;
+
+  GFp_Limb *res_x = r->X;
+  GFp_Limb *res_y = r->Y;
+  GFp_Limb *res_z = r->Z;
+
+  elem_mul_by_2(S, in_y);
+
+  elem_sqr_mont(Zsqr, in_z);
+
+  elem_sqr_mont(S, S);
+
+  elem_mul_mont(res_z, in_z, in_y);
+  elem_mul_by_2(res_z, res_z);
+
+  elem_add(M, in_x, Zsqr);
+  elem_sub(Zsqr, in_x, Zsqr);
+
+  elem_sqr_mont(res_y, S);
+  elem_div_by_2(res_y, res_y);
+
+  elem_mul_mont(M, M, Zsqr);
+  elem_mul_by_3(M, M);
+
+  elem_mul_mont(S, S, in_x);
+  elem_mul_by_2(tmp0, S);
+
+  elem_sqr_mont(res_x, M);
+
+  elem_sub(res_x, res_x, tmp0);
+  elem_sub(S, S, res_x);
+
+  elem_mul_mont(S, S, M);
+  elem_sub(res_y, S, res_y);
+}
+
+/* Point addition: r = a+b */
+void GFp_nistz256_point_add(P256_POINT *r, const P256_POINT *a,
+                            const P256_POINT *b) {
+  Elem U2, S2, U1, S1, Z1sqr, Z2sqr, H, R, Hsqr, Rsqr, Hcub;
+  Elem res_x, res_y, res_z;
+
+  const GFp_Limb *in1_x = a->X;
+  const GFp_Limb *in1_y = a->Y;
+  const GFp_Limb *in1_z = a->Z;
+
+  const GFp_Limb *in2_x = b->X;
+  const GFp_Limb *in2_y = b->Y;
+  const GFp_Limb *in2_z = b->Z;
+
+  GFp_Limb in1infty = is_infinity(a->X, a->Y);
+  GFp_Limb in2infty = is_infinity(b->X, b->Y);
+
+  elem_sqr_mont(Z2sqr, in2_z); /* Z2^2 */
+  elem_sqr_mont(Z1sqr, in1_z); /* Z1^2 */
+
+  elem_mul_mont(S1, Z2sqr, in2_z); /* S1 = Z2^3 */
+  elem_mul_mont(S2, Z1sqr, in1_z); /* S2 = Z1^3 */
+
+  elem_mul_mont(S1, S1, in1_y); /* S1 = Y1*Z2^3 */
+  elem_mul_mont(S2, S2, in2_y); /* S2 = Y2*Z1^3 */
+  elem_sub(R, S2, S1);          /* R = S2 - S1 */
+
+  elem_mul_mont(U1, in1_x, Z2sqr); /* U1 = X1*Z2^2 */
+  elem_mul_mont(U2, in2_x, Z1sqr); /* U2 = X2*Z1^2 */
+  elem_sub(H, U2, U1);             /* H = U2 - U1 */
+
+  /* This should not happen during sign/ecdh,
+   * so no constant time violation */
+  if (is_equal(U1, U2) && !in1infty && !in2infty) {
+    if (is_equal(S1, S2)) {
+      GFp_nistz256_point_double(r, a);
+    } else {
+      memset(r, 0, sizeof(*r));
+    }
+    return;
+  }
+
+  elem_sqr_mont(Rsqr, R);             /* R^2 */
+  elem_mul_mont(res_z, H, in1_z);     /* Z3 = H*Z1*Z2 */
+  elem_sqr_mont(Hsqr, H);             /* H^2 */
+  elem_mul_mont(res_z, res_z, in2_z); /* Z3 = H*Z1*Z2 */
+  elem_mul_mont(Hcub, Hsqr, H);       /* H^3 */
+
+  elem_mul_mont(U2, U1, Hsqr); /* U1*H^2 */
+  elem_mul_by_2(Hsqr, U2);     /* 2*U1*H^2 */
+
+  elem_sub(res_x, Rsqr, Hsqr);
+  elem_sub(res_x, res_x, Hcub);
+
+  elem_sub(res_y, U2, res_x);
+
+  elem_mul_mont(S2, S1, Hcub);
+  elem_mul_mont(res_y, R, res_y);
+  elem_sub(res_y, res_y, S2);
+
+  copy_conditional(res_x, in2_x, in1infty);
+  copy_conditional(res_y, in2_y, in1infty);
+  copy_conditional(res_z, in2_z, in1infty);
+
+  copy_conditional(res_x, in1_x, in2infty);
+  copy_conditional(res_y, in1_y, in2infty);
+  copy_conditional(res_z, in1_z, in2infty);
+
+  memcpy(r->X, res_x, sizeof(res_x));
+  memcpy(r->Y, res_y, sizeof(res_y));
+  memcpy(r->Z, res_z, sizeof(res_z));
+}
+
+/* Point addition when |b| is known to be affine: r = a+b. |b| is infinity
+ * when its coordinates are (0,0), which |GFp_nistz256_point_add| also
+ * checks for, so setting its Z coordinate to one is OK. */
+void GFp_nistz256_point_add_affine(P256_POINT *r, const P256_POINT *a,
+                                   const P256_POINT_AFFINE *b) {
+  P256_POINT b_projective;
+  memcpy(b_projective.X, b->X, sizeof(b_projective.X));
+  memcpy(b_projective.Y, b->Y, sizeof(b_projective.Y));
+  memcpy(b_projective.Z, ONE, sizeof(b_projective.Z));
+  GFp_nistz256_point_add(r, a, &b_projective);
+}
+
+#endif  /* OPENSSL_NO_ASM */
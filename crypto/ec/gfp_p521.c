@@ -0,0 +1,286 @@
+/* Copyright 2016 Brian Smith.
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+ * SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+ * OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+ * CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE. */
+
+#include "gfp_internal.h"
+
+#include <string.h>
+
+#include "ecp_nistz521.h"
+#include "../bn/internal.h"
+#include "../internal.h"
+
+#include "gfp_limbs.inl"
+
+ /* XXX: Here we assume that the conversion from |GFp_Carry| to |GFp_Limb|
+  * is constant-time, but we haven't verified that assumption. TODO: Fix it so
+  * we don't need to make that assumption. */
+
+
+typedef GFp_Limb Elem[P521_LIMBS];
+typedef GFp_Limb ScalarMont[P521_LIMBS];
+typedef GFp_Limb Scalar[P521_LIMBS];
+
+
+/* Prototypes to avoid -Wmissing-prototypes warnings. */
+void GFp_p521_elem_add(Elem r, const Elem a, const Elem b);
+void GFp_p521_elem_div_by_2(Elem r, const Elem a);
+void GFp_p521_elem_mul_mont(Elem r, const Elem a, const Elem b);
+void GFp_p521_elem_neg(Elem r, const Elem a);
+void GFp_p521_elem_sub(Elem r, const Elem a, const Elem b);
+void GFp_p521_scalar_mul_mont(ScalarMont r, const ScalarMont a,
+                              const ScalarMont b);
+
+
+/* The most significant limb of these values has only nine significant bits
+ * regardless of the limb size, so it is given as a single limb after the
+ * |TOBN| pairs. */
+
+static const BN_ULONG Q[P521_LIMBS] = {
+  TOBN(0xffffffff, 0xffffffff),
+  TOBN(0xffffffff, 0xffffffff),
+  TOBN(0xffffffff, 0xffffffff),
+  TOBN(0xffffffff, 0xffffffff),
+  TOBN(0xffffffff, 0xffffffff),
+  TOBN(0xffffffff, 0xffffffff),
+  TOBN(0xffffffff, 0xffffffff),
+  TOBN(0xffffffff, 0xffffffff),
+  0x1ff,
+};
+
+static const BN_ULONG N[P521_LIMBS] = {
+  TOBN(0xbb6fb71e, 0x91386409),
+  TOBN(0x3bb5c9b8, 0x899c47ae),
+  TOBN(0x7fcc0148, 0xf709a5d0),
+  TOBN(0x51868783, 0xbf2f966b),
+  TOBN(0xffffffff, 0xfffffffa),
+  TOBN(0xffffffff, 0xffffffff),
+  TOBN(0xffffffff, 0xffffffff),
+  TOBN(0xffffffff, 0xffffffff),
+  0x1ff,
+};
+
+OPENSSL_COMPILE_ASSERT(sizeof(size_t) == sizeof(GFp_Limb),
+                       size_t_and_gfp_limb_are_different_sizes);
+
+OPENSSL_COMPILE_ASSERT(sizeof(size_t) == sizeof(BN_ULONG),
+                       size_t_and_bn_ulong_are_different_sizes);
+
+
+/* R mod q, where R = 2**(P521_LIMBS * BN_BITS2). Since q = 2**521 - 1, this
+ * is 2**((P521_LIMBS * BN_BITS2) - 521), which depends on the limb size. */
+static const BN_ULONG ONE[P521_LIMBS] = {
+#if defined(OPENSSL_64_BIT)
+  TOBN(0x00800000, 0),
+#else
+  TOBN(0, 0x00800000),
+#endif
+};
+
+
+/* XXX: MSVC for x86 warns when it fails to inline these functions it should
+ * probably inline. */
+#if defined(_MSC_VER)  && defined(OPENSSL_X86)
+#define INLINE_IF_POSSIBLE __forceinline
+#else
+#define INLINE_IF_POSSIBLE inline
+#endif
+
+
+static INLINE_IF_POSSIBLE GFp_Limb is_equal(const Elem a, const Elem b) {
+  return GFp_constant_time_limbs_eq_limbs(a, b, P521_LIMBS);
+}
+
+static INLINE_IF_POSSIBLE void copy_conditional(Elem r, const Elem a,
+                                                const GFp_Limb condition) {
+  for (size_t i = 0; i < P521_LIMBS; ++i) {
+    r[i] = constant_time_select_size_t(condition, a[i], r[i]);
+  }
+}
+
+
+static void elem_add(Elem r, const Elem a, const Elem b) {
+  GFp_Limb carry =
+      constant_time_is_nonzero_size_t(gfp_limbs_add(r, a, b, P521_LIMBS));
+  Elem adjusted;
+  GFp_Limb no_borrow =
+      constant_time_is_zero_size_t(gfp_limbs_sub(adjusted, r, Q, P521_LIMBS));
+  copy_conditional(r, adjusted,
+                   constant_time_select_size_t(carry, carry, no_borrow));
+}
+
+static void elem_sub(Elem r, const Elem a, const Elem b) {
+  GFp_Limb borrow =
+    constant_time_is_nonzero_size_t(gfp_limbs_sub(r, a, b, P521_LIMBS));
+  Elem adjusted;
+  (void)gfp_limbs_add(adjusted, r, Q, P521_LIMBS);
+  copy_conditional(r, adjusted, borrow);
+}
+
+static void elem_div_by_2(Elem r, const Elem a) {
+  /* Consider the case where `a` is even. Then we can shift `a` right one bit
+   * and the result will still be valid because we didn't lose any bits and so
+   * `(a >> 1) * 2 == a (mod q)`, which is the invariant we must satisfy.
+   *
+   * The remainder of this comment is considering the case where `a` is odd.
+   *
+   * Since `a` is odd, it isn't the case that `(a >> 1) * 2 == a (mod q)`
+   * because the lowest bit is lost during the shift. For example, consider:
+   *
+   * ```python
+   * q = 2**521 - 1
+   * a = 2**520
+   * two_a = a * 2 % q
+   * assert two_a == 0x1
+   * ```
+   *
+   * Notice there how `(2 * a) % q` wrapped around to a smaller odd value. When
+   * we divide `two_a` by two (mod q), we need to get the value `2**520`, which
+   * we obviously can't get with just a right shift.
+   *
+   * `q` is odd, and `a` is odd, so `a + q` is even. We could calculate
+   * `(a + q) >> 1` and then reduce it mod `q`. However, we then we would have
+   * to keep track of an extra most significant bit. We can avoid that by
+   * instead calculating `(a >> 1) + ((q + 1) >> 1)`. The `1` in `q + 1` is the
+   * least significant bit of `a`. `q + 1` is even, which means it can be
+   * shifted without losing any bits. Since `q` is odd, `q - 1` is even, so the
+   * largest odd field element is `q - 2`. Thus we know that `a <= q - 2`. We
+   * know `(q + 1) >> 1` is `(q + 1) / 2` since (`q + 1`) is even. The value of
+   * `a >> 1` is `(a - 1)/2` since the shift will drop the least significant
+   * bit of `a`, which is 1. Thus:
+   *
+   * sum  =  ((q + 1) >> 1) + (a >> 1)
+   * sum  =  (q + 1)/2 + (a >> 1)       (substituting (q + 1)/2)
+   *     <=  (q + 1)/2 + (q - 2 - 1)/2  (substituting a <= q - 2)
+   *     <=  (q + 1)/2 + (q - 3)/2      (simplifying)
+   *     <=  (q + 1 + q - 3)/2          (factoring out the common divisor)
+   *     <=  (2q - 2)/2                 (simplifying)
+   *     <=  q - 1                      (simplifying)
+   *
+   * Thus, no reduction of the sum mod `q` is necessary. */
+
+  GFp_Limb is_odd = constant_time_is_nonzero_size_t(a[0] & 1);
+
+  /* r = a >> 1. */
+  GFp_Limb carry = a[P521_LIMBS - 1] & 1;
+  r[P521_LIMBS - 1] = a[P521_LIMBS - 1] >> 1;
+  for (size_t i = 1; i < P521_LIMBS; ++i) {
+    GFp_Limb new_carry = a[P521_LIMBS - i - 1];
+    r[P521_LIMBS - i - 1] =
+        (a[P521_LIMBS - i - 1] >> 1) | (carry << (GFp_LIMB_BITS - 1));
+    carry = new_carry;
+  }
+
+  static const Elem Q_PLUS_1_SHR_1 = {
+    TOBN(0, 0), TOBN(0, 0), TOBN(0, 0), TOBN(0, 0), TOBN(0, 0), TOBN(0, 0),
+    TOBN(0, 0), TOBN(0, 0), 0x100,
+  };
+
+  Elem adjusted;
+  BN_ULONG carry2 = gfp_limbs_add(adjusted, r, Q_PLUS_1_SHR_1, P521_LIMBS);
+#if defined(NDEBUG)
+  (void)carry2;
+#endif
+  assert(carry2 == 0);
+
+  copy_conditional(r, adjusted, is_odd);
+}
+
+static inline void elem_mul_mont(Elem r, const Elem a, const Elem b) {
+  static const BN_ULONG Q_N0[] = {
+    BN_MONT_CTX_N0(0, 0x1)
+  };
+  /* XXX: Not (clearly) constant-time; inefficient. TODO: Add a dedicated
+   * squaring routine. */
+  GFp_bn_mul_mont(r, a, b, Q, Q_N0, P521_LIMBS);
+}
+
+static inline void elem_mul_by_2(Elem r, const Elem a) {
+  elem_add(r, a, a);
+}
+
+static INLINE_IF_POSSIBLE void elem_mul_by_3(Elem r, const Elem a) {
+  /* XXX: inefficient. TODO: Replace with an integrated shift + add. */
+  Elem doubled;
+  elem_add(doubled, a, a);
+  elem_add(r, doubled, a);
+}
+
+static inline void elem_sqr_mont(Elem r, const Elem a) {
+  elem_mul_mont(r, a, a);
+}
+
+void GFp_p521_elem_add(Elem r, const Elem a, const Elem b) {
+  elem_add(r, a, b);
+}
+
+void GFp_p521_elem_sub(Elem r, const Elem a, const Elem b) {
+  elem_sub(r, a, b);
+}
+
+void GFp_p521_elem_div_by_2(Elem r, const Elem a) {
+  elem_div_by_2(r, a);
+}
+
+void GFp_p521_elem_mul_mont(Elem r, const Elem a, const Elem b) {
+  elem_mul_mont(r, a, b);
+}
+
+void GFp_p521_elem_neg(Elem r, const Elem a) {
+  GFp_Limb is_zero = GFp_constant_time_limbs_are_zero(a, P521_LIMBS);
+  GFp_Carry borrow = gfp_limbs_sub(r, Q, a, P521_LIMBS);
+#if defined(NDEBUG)
+  (void)borrow;
+#endif
+  assert(borrow == 0);
+  for (size_t i = 0; i < P521_LIMBS; ++i) {
+    r[i] = constant_time_select_size_t(is_zero, 0, r[i]);
+  }
+}
+
+
+void GFp_p521_scalar_mul_mont(ScalarMont r, const ScalarMont a,
+                              const ScalarMont b) {
+  static const BN_ULONG N_N0[] = {
+    BN_MONT_CTX_N0(0x1d2f5ccd, 0x79a995c7)
+  };
+  /* XXX: Inefficient. TODO: Add dedicated multiplication routine. */
+  GFp_bn_mul_mont(r, a, b, N, N_N0, P521_LIMBS);
+}
+
+
+/* TODO(perf): Optimize this. */
+
+static void gfp_p521_point_select_w5(P521_POINT *out,
+                                     const P521_POINT table[16], size_t index) {
+  Elem x; memset(x, 0, sizeof(x));
+  Elem y; memset(y, 0, sizeof(y));
+  Elem z; memset(z, 0, sizeof(z));
+
+  for (size_t i = 0; i < 16; ++i) {
+    GFp_Limb mask = constant_time_eq_size_t(index, i + 1);
+    for (size_t j = 0; j < P521_LIMBS; ++j) {
+      x[j] |= table[i].X[j] & mask;
+      y[j] |= table[i].Y[j] & mask;
+      z[j] |= table[i].Z[j] & mask;
+    }
+  }
+
+  memcpy(out->X, x, sizeof(x));
+  memcpy(out->Y, y, sizeof(y));
+  memcpy(out->Z, z, sizeof(z));
+}
+
+
+#include "ecp_nistz521.inl"
+#include "ecp_nistz521_mul.inl"
@@ -325,6 +325,7 @@ void GFp_gcm_ghash_v8(uint8_t Xi[16], const u128 Htable[16], const uint8_t *inp,
 #endif
 
 #if defined(OPENSSL_ARM) && __ARM_MAX_ARCH__ >= 7
+#define ARM_NEON_ASM
 #define GCM_FUNCREF_4BIT
 /* 32-bit ARM also has support for doing GCM with NEON instructions. */
 void GFp_gcm_init_neon(u128 Htable[16], const uint64_t Xi[2]);
@@ -390,7 +391,7 @@ static void gcm128_init_htable(u128 Htable[GCM128_HTABLE_LEN],
     return;
   }
 #endif
-#if defined(OPENSSL_ARM)
+#if defined(ARM_NEON_ASM)
   if (GFp_is_NEON_capable()) {
     GFp_gcm_init_neon(Htable, H);
     return;
@@ -433,7 +434,7 @@ static void gcm128_init_gmult_ghash(GCM128_CONTEXT *ctx) {
     return;
   }
 #endif
-#if defined(OPENSSL_ARM)
+#if defined(ARM_NEON_ASM)
   if (GFp_is_NEON_capable()) {
     ctx->gmult = GFp_gcm_gmult_neon;
     ctx->ghash = GFp_gcm_ghash_neon;
@@ -469,17 +470,18 @@ void GFp_gcm128_init(GCM128_CONTEXT *ctx, const AES_KEY *key,
 }
 
 int GFp_gcm128_aad(GCM128_CONTEXT *ctx, const uint8_t *aad, size_t len) {
-  assert(ctx->len.u[0] == 0);
+  /* Only the last call may process a partial block. */
+  assert(ctx->len.u[0] % 16 == 0);
   assert(ctx->len.u[1] == 0);
 
 #ifdef GCM_FUNCREF_4BIT
   gcm128_gmult_f gcm_gmult_p = ctx->gmult;
 #endif
 
-  ctx->len.u[0] = len;
-  if (ctx->len.u[0] > (UINT64_C(1) << 61)) {
+  if (len > (UINT64_C(1) << 61) - ctx->len.u[0]) {
     return 0;
   }
+  ctx->len.u[0] += len;
 
   if (len > 0) {
     for (;;) {
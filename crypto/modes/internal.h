@@ -131,8 +131,10 @@ OPENSSL_EXPORT void GFp_gcm128_init(
     const uint8_t serialized_ctx[GCM128_SERIALIZED_LEN], const uint8_t *iv);
 
 /* GFp_gcm128_aad sets the authenticated data for an instance of GCM. This must
- * be called before and data is encrypted. It returns one on success and zero
- * otherwise. */
+ * be called before and data is encrypted. It may be called more than once, in
+ * which case the authenticated data is the concatenation of the inputs, but
+ * only the last call may have a |len| that isn't a multiple of 16. It returns
+ * one on success and zero otherwise. */
 OPENSSL_EXPORT int GFp_gcm128_aad(GCM128_CONTEXT *ctx, const uint8_t *aad,
                                   size_t len);
 
@@ -29,13 +29,6 @@
 #define POLY1305_BLOCK_STATE_SIZE 192
 
 
-/* We can assume little-endian. */
-static uint32_t U8TO32_LE(const uint8_t *m) {
-  uint32_t r;
-  memcpy(&r, m, sizeof(r));
-  return r;
-}
-
 #if defined(OPENSSL_X86)
 /* See comment above |_poly1305_init_sse2| in poly1305-x86.pl. */
 OPENSSL_COMPILE_ASSERT(POLY1305_BLOCK_STATE_SIZE >= 4 * (5 + 1 + 4 + 2 + 4 * 9),
@@ -58,6 +51,163 @@ void GFp_poly1305_blocks(void *ctx, const uint8_t *in, size_t len,
                          uint32_t padbit);
 void GFp_poly1305_emit(void *ctx, uint8_t mac[16], const uint32_t nonce[4]);
 
+
+#if defined(OPENSSL_NO_ASM)
+
+/* The portable implementation for targets without an assembly language
+ * implementation is poly1305-donna-32, which uses 26-bit limbs so that the
+ * products fit in 64 bits. */
+
+struct poly1305_state_generic {
+  uint32_t r0, r1, r2, r3, r4;
+  uint32_t s1, s2, s3, s4;
+  uint32_t h0, h1, h2, h3, h4;
+};
+
+OPENSSL_COMPILE_ASSERT(POLY1305_BLOCK_STATE_SIZE >=
+                           sizeof(struct poly1305_state_generic),
+                       poly1305_block_state_too_small);
+
+int GFp_poly1305_init_asm(void *ctx, const uint8_t key[16], void *out_func) {
+  (void)out_func;
+  struct poly1305_state_generic *state = ctx;
+
+  /* r &= 0xffffffc0ffffffc0ffffffc0fffffff */
+  state->r0 = from_le_u32_ptr(key + 0) & 0x3ffffff;
+  state->r1 = (from_le_u32_ptr(key + 3) >> 2) & 0x3ffff03;
+  state->r2 = (from_le_u32_ptr(key + 6) >> 4) & 0x3ffc0ff;
+  state->r3 = (from_le_u32_ptr(key + 9) >> 6) & 0x3f03fff;
+  state->r4 = (from_le_u32_ptr(key + 12) >> 8) & 0x00fffff;
+
+  state->s1 = state->r1 * 5;
+  state->s2 = state->r2 * 5;
+  state->s3 = state->r3 * 5;
+  state->s4 = state->r4 * 5;
+
+  state->h0 = 0;
+  state->h1 = 0;
+  state->h2 = 0;
+  state->h3 = 0;
+  state->h4 = 0;
+
+  /* Use |GFp_poly1305_blocks| and |GFp_poly1305_emit|. */
+  return 0;
+}
+
+void GFp_poly1305_blocks(void *ctx, const uint8_t *in, size_t len,
+                         uint32_t padbit) {
+  struct poly1305_state_generic *state = ctx;
+  const uint32_t hibit = padbit << 24;
+
+  const uint32_t r0 = state->r0, r1 = state->r1, r2 = state->r2,
+                 r3 = state->r3, r4 = state->r4;
+  const uint32_t s1 = state->s1, s2 = state->s2, s3 = state->s3,
+                 s4 = state->s4;
+  uint32_t h0 = state->h0, h1 = state->h1, h2 = state->h2, h3 = state->h3,
+           h4 = state->h4;
+
+  while (len >= 16) {
+    /* h += m[i] */
+    h0 += from_le_u32_ptr(in + 0) & 0x3ffffff;
+    h1 += (from_le_u32_ptr(in + 3) >> 2) & 0x3ffffff;
+    h2 += (from_le_u32_ptr(in + 6) >> 4) & 0x3ffffff;
+    h3 += (from_le_u32_ptr(in + 9) >> 6) & 0x3ffffff;
+    h4 += (from_le_u32_ptr(in + 12) >> 8) | hibit;
+
+    /* h *= r */
+    uint64_t d0 = ((uint64_t)h0 * r0) + ((uint64_t)h1 * s4) +
+                  ((uint64_t)h2 * s3) + ((uint64_t)h3 * s2) +
+                  ((uint64_t)h4 * s1);
+    uint64_t d1 = ((uint64_t)h0 * r1) + ((uint64_t)h1 * r0) +
+                  ((uint64_t)h2 * s4) + ((uint64_t)h3 * s3) +
+                  ((uint64_t)h4 * s2);
+    uint64_t d2 = ((uint64_t)h0 * r2) + ((uint64_t)h1 * r1) +
+                  ((uint64_t)h2 * r0) + ((uint64_t)h3 * s4) +
+                  ((uint64_t)h4 * s3);
+    uint64_t d3 = ((uint64_t)h0 * r3) + ((uint64_t)h1 * r2) +
+                  ((uint64_t)h2 * r1) + ((uint64_t)h3 * r0) +
+                  ((uint64_t)h4 * s4);
+    uint64_t d4 = ((uint64_t)h0 * r4) + ((uint64_t)h1 * r3) +
+                  ((uint64_t)h2 * r2) + ((uint64_t)h3 * r1) +
+                  ((uint64_t)h4 * r0);
+
+    /* (partial) h %= p */
+    uint32_t c = (uint32_t)(d0 >> 26); h0 = (uint32_t)d0 & 0x3ffffff;
+    d1 += c; c = (uint32_t)(d1 >> 26); h1 = (uint32_t)d1 & 0x3ffffff;
+    d2 += c; c = (uint32_t)(d2 >> 26); h2 = (uint32_t)d2 & 0x3ffffff;
+    d3 += c; c = (uint32_t)(d3 >> 26); h3 = (uint32_t)d3 & 0x3ffffff;
+    d4 += c; c = (uint32_t)(d4 >> 26); h4 = (uint32_t)d4 & 0x3ffffff;
+    h0 += c * 5; c = h0 >> 26; h0 &= 0x3ffffff;
+    h1 += c;
+
+    in += 16;
+    len -= 16;
+  }
+
+  state->h0 = h0;
+  state->h1 = h1;
+  state->h2 = h2;
+  state->h3 = h3;
+  state->h4 = h4;
+}
+
+void GFp_poly1305_emit(void *ctx, uint8_t mac[16], const uint32_t nonce[4]) {
+  struct poly1305_state_generic *state = ctx;
+  uint32_t h0 = state->h0, h1 = state->h1, h2 = state->h2, h3 = state->h3,
+           h4 = state->h4;
+
+  /* fully carry h */
+  uint32_t c = h1 >> 26; h1 &= 0x3ffffff;
+  h2 += c; c = h2 >> 26; h2 &= 0x3ffffff;
+  h3 += c; c = h3 >> 26; h3 &= 0x3ffffff;
+  h4 += c; c = h4 >> 26; h4 &= 0x3ffffff;
+  h0 += c * 5; c = h0 >> 26; h0 &= 0x3ffffff;
+  h1 += c;
+
+  /* compute h + -p */
+  uint32_t g0 = h0 + 5; c = g0 >> 26; g0 &= 0x3ffffff;
+  uint32_t g1 = h1 + c; c = g1 >> 26; g1 &= 0x3ffffff;
+  uint32_t g2 = h2 + c; c = g2 >> 26; g2 &= 0x3ffffff;
+  uint32_t g3 = h3 + c; c = g3 >> 26; g3 &= 0x3ffffff;
+  uint32_t g4 = h4 + c - (1u << 26);
+
+  /* select h if h < p, or h + -p if h >= p */
+  uint32_t mask = (g4 >> 31) - 1;
+  g0 &= mask;
+  g1 &= mask;
+  g2 &= mask;
+  g3 &= mask;
+  g4 &= mask;
+  mask = ~mask;
+  h0 = (h0 & mask) | g0;
+  h1 = (h1 & mask) | g1;
+  h2 = (h2 & mask) | g2;
+  h3 = (h3 & mask) | g3;
+  h4 = (h4 & mask) | g4;
+
+  /* h = h % (2^128) */
+  h0 = (h0 | (h1 << 26));
+  h1 = ((h1 >> 6) | (h2 << 20));
+  h2 = ((h2 >> 12) | (h3 << 14));
+  h3 = ((h3 >> 18) | (h4 << 8));
+
+  /* mac = (h + nonce) % (2^128) */
+  uint64_t f = (uint64_t)h0 + nonce[0]; h0 = (uint32_t)f;
+  f = (uint64_t)h1 + nonce[1] + (f >> 32); h1 = (uint32_t)f;
+  f = (uint64_t)h2 + nonce[2] + (f >> 32); h2 = (uint32_t)f;
+  f = (uint64_t)h3 + nonce[3] + (f >> 32); h3 = (uint32_t)f;
+
+  const uint32_t words[4] = { h0, h1, h2, h3 };
+  for (size_t i = 0; i < 4; ++i) {
+    mac[4 * i + 0] = (uint8_t)words[i];
+    mac[4 * i + 1] = (uint8_t)(words[i] >> 8);
+    mac[4 * i + 2] = (uint8_t)(words[i] >> 16);
+    mac[4 * i + 3] = (uint8_t)(words[i] >> 24);
+  }
+}
+
+#endif  /* OPENSSL_NO_ASM */
+
 struct poly1305_state_st {
   alignas(8) uint8_t opaque[POLY1305_BLOCK_STATE_SIZE];
   uint32_t nonce[4];
@@ -88,10 +238,10 @@ void GFp_poly1305_init(poly1305_state *statep, const uint8_t key[32]) {
   }
 
   state.buf_used = 0;
-  state.nonce[0] = U8TO32_LE(key + 16);
-  state.nonce[1] = U8TO32_LE(key + 20);
-  state.nonce[2] = U8TO32_LE(key + 24);
-  state.nonce[3] = U8TO32_LE(key + 28);
+  state.nonce[0] = from_le_u32_ptr(key + 16);
+  state.nonce[1] = from_le_u32_ptr(key + 20);
+  state.nonce[2] = from_le_u32_ptr(key + 24);
+  state.nonce[3] = from_le_u32_ptr(key + 28);
 
   memset(statep, 0, sizeof(*statep));
   memcpy(statep, &state, sizeof(state));
@@ -74,9 +74,6 @@ int GFp_BN_BLINDING_invert(BIGNUM *n, const BN_BLINDING *b, BN_MONT_CTX *mont);
 
 int GFp_rsa_check_modulus_and_exponent(const BIGNUM *n, const BIGNUM *e,
                                        size_t min_bits, size_t max_bits);
-int GFp_rsa_public_decrypt(uint8_t *out, size_t out_len, const BIGNUM *n,
-                           const BIGNUM *e, const uint8_t *in, size_t in_len,
-                           size_t min_bits, size_t max_bits);
 
 
 #if defined(__cplusplus)
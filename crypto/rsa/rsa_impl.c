@@ -131,65 +131,6 @@ size_t GFp_RSA_size(const RSA *rsa) {
 }
 
 
-/* GFp_rsa_public_decrypt decrypts the RSA signature |in| using the public key
- * with modulus |public_key_n| and exponent |public_key_e|, leaving the
- * decrypted signature in |out|. |out_len| and |in_len| must both be equal to
- * |RSA_size(rsa)|. |min_bits| and |max_bits| are the minimum and maximum
- * allowed public key modulus sizes, in bits. It returns one on success and
- * zero on failure.
- *
- * When |rsa_public_decrypt| succeeds, the caller must then check the
- * signature value (and padding) left in |out|. */
-int GFp_rsa_public_decrypt(uint8_t *out, size_t out_len, const BIGNUM *n,
-                           const BIGNUM *e, const uint8_t *in, size_t in_len,
-                           size_t min_bits, size_t max_bits) {
-  BIGNUM f;
-  GFp_BN_init(&f);
-
-  BIGNUM result;
-  GFp_BN_init(&result);
-
-  int ret = 0;
-  unsigned rsa_size = GFp_BN_num_bytes(n); /* RSA_size((n, e)); */
-
-  if (out_len != rsa_size) {
-    OPENSSL_PUT_ERROR(RSA, RSA_R_OUTPUT_BUFFER_TOO_SMALL);
-    goto err;
-  }
-
-  if (in_len != rsa_size) {
-    OPENSSL_PUT_ERROR(RSA, RSA_R_DATA_LEN_NOT_EQUAL_TO_MOD_LEN);
-    goto err;
-  }
-
-  if (!GFp_rsa_check_modulus_and_exponent(n, e, min_bits, max_bits)) {
-    goto err;
-  }
-
-  if (GFp_BN_bin2bn(in, in_len, &f) == NULL) {
-    OPENSSL_PUT_ERROR(RSA, ERR_R_INTERNAL_ERROR);
-    goto err;
-  }
-
-  if (GFp_BN_ucmp(&f, n) >= 0) {
-    OPENSSL_PUT_ERROR(RSA, RSA_R_DATA_TOO_LARGE_FOR_MODULUS);
-    goto err;
-  }
-
-  if (!GFp_BN_mod_exp_mont_vartime(&result, &f, e, n, NULL) ||
-      !GFp_BN_bn2bin_padded(out, out_len, &result)) {
-    OPENSSL_PUT_ERROR(RSA, ERR_R_INTERNAL_ERROR);
-    goto err;
-  }
-
-  ret = 1;
-
-err:
-  GFp_BN_free(&f);
-  GFp_BN_free(&result);
-  return ret;
-}
-
 /* GFp_rsa_private_transform takes a big-endian integer from |inout|,
  * calculates the d'th power of it, modulo the RSA modulus and writes the
  * result as a big-endian integer back to |inout|. |inout| is |len| bytes long
@@ -88,6 +88,19 @@ OPENSSL_EXPORT int GFp_AES_set_encrypt_key(const uint8_t *key, unsigned bits,
 OPENSSL_EXPORT void GFp_AES_encrypt(const uint8_t *in, uint8_t *out,
                                     const AES_KEY *key);
 
+/* GFp_AES_set_decrypt_key configures |aeskey| to decrypt with the |bits|-bit
+ * key, |key|. |bits| must be 128, 192, or 256.
+ *
+ * WARNING: unlike other OpenSSL functions, this returns zero on success and a
+ * negative number on error. */
+OPENSSL_EXPORT int GFp_AES_set_decrypt_key(const uint8_t *key, unsigned bits,
+                                           AES_KEY *aeskey);
+
+/* GFp_AES_decrypt decrypts a single block from |in| to |out| with |key|. The
+ * |in| and |out| pointers may overlap. */
+OPENSSL_EXPORT void GFp_AES_decrypt(const uint8_t *in, uint8_t *out,
+                                    const AES_KEY *key);
+
 
 #if defined(__cplusplus)
 }  /* extern C */
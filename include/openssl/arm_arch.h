@@ -99,9 +99,16 @@
 #endif
 
 /* Even when building for 32-bit ARM, support for aarch64 crypto instructions
- * will be included. */
+ * will be included. M-profile CPUs, e.g. the Cortex-M4, have neither NEON nor
+ * the crypto instructions, and A-profile code can't be linked with M-profile
+ * code, so only the code for ARMv6 and earlier, which includes the Thumb-2
+ * integer code, is included for them. */
 #if !defined(__ARM_MAX_ARCH__)
-#define __ARM_MAX_ARCH__ 8
+# if defined(__ARM_ARCH_7EM__)
+#  define __ARM_MAX_ARCH__ 6
+# else
+#  define __ARM_MAX_ARCH__ 8
+# endif
 #endif
 
 /* ARMV7_NEON is true when a NEON unit is present in the current CPU. */
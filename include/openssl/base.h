@@ -92,10 +92,20 @@ extern "C" {
 #define OPENSSL_PNACL
 #elif defined(__myriad2__)
 #define OPENSSL_32_BIT
+#elif defined(__wasm32__)
+#define OPENSSL_32_BIT
 #else
 #error "Unknown target CPU"
 #endif
 
+/* Only x86, x86-64, ARM, and AArch64 have assembly language implementations.
+ * The other targets, e.g. wasm32, use the portable C implementations. */
+#if !defined(OPENSSL_NO_ASM) && !defined(OPENSSL_X86) && \
+    !defined(OPENSSL_X86_64) && !defined(OPENSSL_ARM) && \
+    !defined(OPENSSL_AARCH64)
+#define OPENSSL_NO_ASM
+#endif
+
 #if defined(__APPLE__)
 #define OPENSSL_APPLE
 #endif
@@ -106,6 +106,11 @@ extern uint32_t GFp_ia32cap_P[4];
 #define OPENSSL_STATIC_ARMCAP_PMULL
 #endif
 
+#elif defined(__ARM_ARCH_7EM__)
+/* Cortex-M4 and Cortex-M7 builds use the static ARM configuration, without
+ * NEON, as there's no OS to ask for the CPU's capabilities. */
+#define OPENSSL_STATIC_ARMCAP
+
 #endif
 
 #if !defined(OPENSSL_STATIC_ARMCAP)
@@ -85,6 +85,10 @@ extern "C" {
  * non-zero. */
 OPENSSL_EXPORT int GFp_memcmp(const void *a, const void *b, size_t len);
 
+/* GFp_cleanse zeros out |len| bytes of memory at |ptr|. This is similar to
+ * |memset((ptr), 0, (len))| but it won't be optimized out by the compiler. */
+OPENSSL_EXPORT void GFp_cleanse(void *ptr, size_t len);
+
 
 #if defined(__cplusplus)
 }  /* extern C */
@@ -32,14 +32,19 @@ RING_SRCS = $(addprefix $(RING_PREFIX), \
   crypto/bn/random.c \
   crypto/bn/rsaz_exp.c \
   crypto/bn/shift.c \
+  crypto/chacha/chacha.c \
   crypto/cipher/e_aes.c \
   crypto/crypto.c \
   crypto/curve25519/curve25519.c \
+  crypto/curve448/curve448.c \
+  crypto/dh/ffdhe.c \
   crypto/ec/ecp_nistz.c \
   crypto/ec/ecp_nistz256.c \
+  crypto/ec/gfp_bls12_381.c \
   crypto/ec/gfp_constant_time.c \
   crypto/ec/gfp_p256.c \
   crypto/ec/gfp_p384.c \
+  crypto/ec/gfp_p521.c \
   crypto/ec/wnaf.c \
   crypto/mem.c \
   crypto/modes/gcm.c \
@@ -104,18 +109,27 @@ RING_ARM_SHARED_SRCS = $(addprefix $(RING_PREFIX), \
 
 RING_arm_SRCS = $(addprefix $(RING_PREFIX), \
   crypto/aes/asm/aes-armv4.pl \
-  crypto/aes/asm/bsaes-armv7.pl \
   crypto/bn/asm/armv4-mont.pl \
   crypto/chacha/asm/chacha-armv4.pl \
-  crypto/curve25519/asm/x25519-asm-arm.S \
   crypto/ec/asm/ecp_nistz256-armv4.pl \
   crypto/modes/asm/ghash-armv4.pl \
   crypto/poly1305/asm/poly1305-armv4.pl \
   crypto/sha/asm/sha256-armv4.pl \
   crypto/sha/asm/sha512-armv4.pl \
+  $(NULL))
+
+# Cortex-M4 and Cortex-M7 (thumbv7em) have neither NEON nor the ARMv8 crypto
+# extensions, and there's no OS to ask, so only the Thumb-2 integer code above
+# is built for them and their CPU capabilities are static; see
+# |__ARM_MAX_ARCH__| in arm_arch.h and |OPENSSL_STATIC_ARMCAP| in cpu.h.
+ifneq ($(TARGET_ARCH_BASE),thumbv7em)
+RING_arm_SRCS += $(addprefix $(RING_PREFIX), \
+  crypto/aes/asm/bsaes-armv7.pl \
+  crypto/curve25519/asm/x25519-asm-arm.S \
   $(NULL)) \
   $(RING_ARM_SHARED_SRCS) \
   $(NULL)
+endif
 
 RING_aarch64_SRCS = $(addprefix $(RING_PREFIX), \
   crypto/cpu-aarch64-linux.c \
@@ -18,14 +18,16 @@
 # Due to how Rust names platforms the following exceptions are allowed:
 # <abi> can be omitted for Mac OS X (Darwin) and iOS.
 # <vendor> can be omitted for the Android ABIs (android and androideabi).
+# <vendor> can be omitted for bare metal targets (<sys> "none").
 # The list of Rust supported platforms is at:
 # https://forge.rust-lang.org/platform-support.html
 TARGET_WORDS = $(subst -, ,$(TARGET))
 TARGET_ARCH_BASE = $(word 1,$(TARGET_WORDS))
 TARGET_ARCH_NORMAL = \
   $(strip $(if $(findstring arm, $(TARGET_ARCH_BASE)),arm, \
+               $(if $(filter thumbv7em, $(TARGET_ARCH_BASE)),arm, \
                $(if $(filter i386 i486 i586 i686, \
-                    $(TARGET_ARCH_BASE)),x86,$(TARGET_ARCH_BASE))))
+                    $(TARGET_ARCH_BASE)),x86,$(TARGET_ARCH_BASE)))))
 
 TARGET_VENDOR = $(word 2,$(TARGET_WORDS))
 TARGET_SYS = $(word 3,$(TARGET_WORDS))
@@ -41,6 +43,17 @@ else ifeq ($(findstring linux-android,$(TARGET_VENDOR)-$(TARGET_SYS)),linux-andr
 TARGET_ABI = $(TARGET_SYS)
 TARGET_VENDOR = unknow
 TARGET_SYS = linux
+# Set the correct VENDOR, SYS and ABI when building for bare metal, e.g.
+# thumbv7em-none-eabi.
+else ifeq ($(TARGET_VENDOR),none)
+TARGET_ABI := $(TARGET_SYS)
+TARGET_VENDOR = unknown
+TARGET_SYS = none
+# Set the correct SYS and ABI when building for WebAssembly, e.g.
+# wasm32-unknown-unknown, which has no operating system.
+else ifeq ($(TARGET_ARCH_BASE)-$(TARGET_SYS),wasm32-unknown)
+TARGET_ABI = wasm
+TARGET_SYS = none
 else
 define NEWLINE
 
@@ -73,6 +86,10 @@ MINSTR = 32
 else ifeq ($(TARGET_ARCH_NORMAL),x86_64)
 MARCH = x86-64
 MINSTR = 64
+else ifeq ($(TARGET_ARCH_BASE),thumbv7em)
+# The assembly language code needs Thumb-2, which the toolchain's default
+# architecture (e.g. ARMv4T for arm-none-eabi-gcc) may not have.
+MARCH = armv7e-m
 else
 # TODO: Pass -march and related options. For now, use the default of the
 # toolchain's C compiler.
@@ -85,8 +102,8 @@ endif
 
 # Cortex-M0, Cortex-M0+, Cortex-M1: armv6_m
 # Cortex-M3: armv7_m
-# Cortex-M4, Cortex-M7: armv7e_m
-ifeq ($(filter-out armv6_m armv7_m armv7e_m,$(TARGET_ARCH_BASE)),)
+# Cortex-M4, Cortex-M7: armv7e_m, thumbv7em
+ifeq ($(filter-out armv6_m armv7_m armv7e_m thumbv7em,$(TARGET_ARCH_BASE)),)
 MINSTR = thumb
 endif
 
@@ -27,18 +27,53 @@
 
 mod chacha20_poly1305;
 mod aes_gcm;
-
-use {constant_time, error, init, polyfill};
+mod aes_ocb;
+mod aes_gcm_siv;
+mod salsa20;
+
+pub mod aes_kw;
+pub mod chacha20_poly1305_openssh;
+pub mod channel;
+pub mod deoxys_ii;
+pub mod derived_key;
+pub mod nonce_sequence;
+pub mod poly1305;
+pub mod poly1305_aes;
+pub mod quic;
+pub mod replay_window;
+pub mod secretbox;
+pub mod secretstream;
+pub mod stream;
+pub mod tls13;
+pub mod xaes_256_gcm;
+pub mod xchacha20_poly1305;
+
+use {audit, constant_time, error, init, kat, polyfill};
+use secret::Zeroize;
+use core;
 
 pub use self::chacha20_poly1305::CHACHA20_POLY1305;
+pub use self::channel::SecureChannel;
 pub use self::aes_gcm::{AES_128_GCM, AES_256_GCM};
+pub use self::aes_ocb::{AES_128_OCB, AES_256_OCB};
+pub use self::aes_gcm_siv::{AES_128_GCM_SIV, AES_256_GCM_SIV};
+pub use self::nonce_sequence::{CounterNonceSequence, NonceSequence,
+                               SequencedOpeningKey, SequencedSealingKey};
+pub use self::replay_window::ReplayWindow;
+pub use self::stream::{StreamOpener, StreamSealer};
+pub use self::xchacha20_poly1305::XCHACHA20_POLY1305;
 
 /// A key for authenticating and decrypting (&ldquo;opening&rdquo;)
 /// AEAD-protected data.
 ///
+/// Cloning an `OpeningKey` copies its expanded key schedule, which is much
+/// cheaper than constructing a new key from the same key bytes. `OpeningKey`
+/// is also `Send` and `Sync`, so a single key can be shared between threads.
+///
 /// C analog: `EVP_AEAD_CTX` with direction `evp_aead_open`
 ///
 /// Go analog: [`crypto.cipher.AEAD`]
+#[derive(Clone)]
 pub struct OpeningKey {
     key: Key,
 }
@@ -46,7 +81,9 @@ pub struct OpeningKey {
 impl OpeningKey {
     /// Create a new opening key.
     ///
-    /// `key_bytes` must be exactly `algorithm.key_len` bytes long.
+    /// `key_bytes` must be exactly `algorithm.key_len` bytes long; otherwise
+    /// `error::KeyRejected::TooShort` or `error::KeyRejected::TooLong` is
+    /// returned.
     ///
     /// C analogs: `EVP_AEAD_CTX_init_with_direction` with direction
     ///            `evp_aead_open`, `EVP_AEAD_CTX_init`.
@@ -56,7 +93,7 @@ impl OpeningKey {
     /// + [`crypto.cipher.NewGCM`](https://golang.org/pkg/crypto/cipher/#NewGCM)
     #[inline]
     pub fn new(algorithm: &'static Algorithm, key_bytes: &[u8])
-               -> Result<OpeningKey, error::Unspecified> {
+               -> Result<OpeningKey, error::KeyRejected> {
         let mut key = OpeningKey {
             key: Key {
                 algorithm: algorithm,
@@ -92,33 +129,113 @@ impl OpeningKey {
 pub fn open_in_place(key: &OpeningKey, nonce: &[u8], in_prefix_len: usize,
                      in_out: &mut [u8], ad: &[u8])
                      -> Result<usize, error::Unspecified> {
-    let nonce = try!(slice_as_array_ref!(nonce, NONCE_LEN));
+    open_in_place_(key, nonce, in_prefix_len, in_out, ad, TAG_LEN)
+}
+
+/// Like `open_in_place`, except the input ends with a tag truncated to its
+/// first 96 bits, as some SRTP and IPsec profiles require.
+///
+/// Only `AES_128_GCM` and `AES_256_GCM` keys may be used; other keys are
+/// rejected. A truncated tag offers less protection against forgeries than a
+/// full one, so use this only to interoperate with protocols that mandate
+/// truncation. The matching truncated tag is the first
+/// `TRUNCATED_TAG_96_LEN` bytes of the tag that `seal_in_place_separate_tag`
+/// returns.
+pub fn open_in_place_truncated_tag_96(key: &OpeningKey, nonce: &[u8],
+                                      in_prefix_len: usize,
+                                      in_out: &mut [u8], ad: &[u8])
+                                      -> Result<usize, error::Unspecified> {
+    if !aes_gcm::is_aes_gcm(key.key.algorithm) {
+        return Err(error::Unspecified);
+    }
+    open_in_place_(key, nonce, in_prefix_len, in_out, ad,
+                   TRUNCATED_TAG_96_LEN)
+}
+
+// `open_in_place` for a received tag that is the first `tag_len` bytes of the
+// calculated tag.
+fn open_in_place_(key: &OpeningKey, nonce: &[u8], in_prefix_len: usize,
+                  in_out: &mut [u8], ad: &[u8], tag_len: usize)
+                  -> Result<usize, error::Unspecified> {
+    if nonce.len() != key.key.algorithm.nonce_len {
+        return Err(error::Unspecified);
+    }
     let ciphertext_and_tag_len =
         try!(in_out.len().checked_sub(in_prefix_len)
                          .ok_or(error::Unspecified));
     let ciphertext_len =
-        try!(ciphertext_and_tag_len.checked_sub(TAG_LEN)
+        try!(ciphertext_and_tag_len.checked_sub(tag_len)
                                    .ok_or(error::Unspecified));
     try!(check_per_nonce_max_bytes(ciphertext_len));
     let (in_out, received_tag) =
         in_out.split_at_mut(in_prefix_len + ciphertext_len);
     let mut calculated_tag = [0u8; TAG_LEN];
+    calculated_tag[..tag_len].copy_from_slice(received_tag);
     try!((key.key.algorithm.open)(&key.key.ctx_buf, nonce, in_out,
-                                  in_prefix_len, &mut calculated_tag, ad));
-    try!(constant_time::verify_slices_are_equal(&calculated_tag, received_tag));
+                                  in_prefix_len, &mut calculated_tag, &[ad]));
+    if constant_time::verify_slices_are_equal(&calculated_tag[..tag_len],
+                                              received_tag).is_err() {
+        audit::notify(audit::EventKind::DecryptionFailed,
+                      key.key.algorithm.name);
+        return Err(error::Unspecified);
+    }
     Ok(ciphertext_len) // `ciphertext_len` is also the plaintext length.
 }
 
+/// Authenticates and decrypts (&ldquo;opens&rdquo;) data in place, where the
+/// tag was received separately from the ciphertext.
+///
+/// `in_out` is the ciphertext, without the tag, and `received_tag` is the tag.
+/// When `open_in_place_separate_tag` returns `Ok(())`, `in_out` has been
+/// overwritten with the plaintext.
+///
+/// `ad` is the additional authenticated data, if any, as the concatenation of
+/// at most `MAX_AD_PARTS` slices.
+pub fn open_in_place_separate_tag(key: &OpeningKey, nonce: &[u8],
+                                  in_out: &mut [u8],
+                                  received_tag: &[u8; TAG_LEN],
+                                  ad: &[&[u8]])
+                                  -> Result<(), error::Unspecified> {
+    if nonce.len() != key.key.algorithm.nonce_len {
+        return Err(error::Unspecified);
+    }
+    if ad.len() > MAX_AD_PARTS {
+        return Err(error::Unspecified);
+    }
+    try!(check_per_nonce_max_bytes(in_out.len()));
+    let mut calculated_tag = *received_tag;
+    try!((key.key.algorithm.open)(&key.key.ctx_buf, nonce, in_out, 0,
+                                  &mut calculated_tag, ad));
+    if constant_time::verify_slices_are_equal(&calculated_tag, received_tag)
+            .is_err() {
+        audit::notify(audit::EventKind::DecryptionFailed,
+                      key.key.algorithm.name);
+        return Err(error::Unspecified);
+    }
+    Ok(())
+}
+
 /// A key for encrypting and signing (&ldquo;sealing&rdquo;) data.
 ///
+/// Cloning a `SealingKey` copies its expanded key schedule, which is much
+/// cheaper than constructing a new key from the same key bytes. `SealingKey`
+/// is also `Send` and `Sync`, so a single key can be shared between threads.
+///
 /// C analog: `EVP_AEAD_CTX` with direction `evp_aead_seal`.
 ///
 /// Go analog: [`AEAD`](https://golang.org/pkg/crypto/cipher/#AEAD)
+#[derive(Clone)]
 pub struct SealingKey {
     key: Key,
 }
 
 impl SealingKey {
+    /// Create a new sealing key.
+    ///
+    /// `key_bytes` must be exactly `algorithm.key_len` bytes long; otherwise
+    /// `error::KeyRejected::TooShort` or `error::KeyRejected::TooLong` is
+    /// returned.
+    ///
     /// C analogs: `EVP_AEAD_CTX_init_with_direction` with direction
     ///            `evp_aead_seal`, `EVP_AEAD_CTX_init`.
     ///
@@ -127,7 +244,7 @@ impl SealingKey {
     /// + [`crypto.cipher.NewGCM`](https://golang.org/pkg/crypto/cipher/#NewGCM)
     #[inline]
     pub fn new(algorithm: &'static Algorithm, key_bytes: &[u8])
-               -> Result<SealingKey, error::Unspecified> {
+               -> Result<SealingKey, error::KeyRejected> {
         let mut key = SealingKey {
             key: Key {
                 algorithm: algorithm,
@@ -163,26 +280,74 @@ impl SealingKey {
 ///
 /// `ad` is the additional authenticated data, if any.
 ///
+/// Sealing only fails if `nonce`, `out_suffix_capacity`, or the input has an
+/// invalid length, so it fails with `error::InvalidLength`.
+///
 /// C analog: `EVP_AEAD_CTX_seal`.
 ///
 /// Go analog: [`AEAD.Seal`](https://golang.org/pkg/crypto/cipher/#AEAD)
 pub fn seal_in_place(key: &SealingKey, nonce: &[u8], in_out: &mut [u8],
                      out_suffix_capacity: usize, ad: &[u8])
-                     -> Result<usize, error::Unspecified> {
+                     -> Result<usize, error::InvalidLength> {
     if out_suffix_capacity < key.key.algorithm.max_overhead_len() {
-        return Err(error::Unspecified);
+        return Err(error::InvalidLength);
+    }
+    if nonce.len() != key.key.algorithm.nonce_len {
+        return Err(error::InvalidLength);
     }
-    let nonce = try!(slice_as_array_ref!(nonce, NONCE_LEN));
     let in_out_len =
         try!(in_out.len().checked_sub(out_suffix_capacity)
-                         .ok_or(error::Unspecified));
+                         .ok_or(error::InvalidLength));
     try!(check_per_nonce_max_bytes(in_out_len));
     let (in_out, tag_out) = in_out.split_at_mut(in_out_len);
-    let tag_out = try!(slice_as_array_ref_mut!(tag_out, TAG_LEN));
-    try!((key.key.algorithm.seal)(&key.key.ctx_buf, nonce, in_out, tag_out, ad));
+    let tag_out = try!(slice_as_array_ref_mut!(tag_out, TAG_LEN)
+                           .map_err(|_| error::InvalidLength));
+    try!(seal(key, nonce, in_out, tag_out, &[ad]));
     Ok(in_out_len + TAG_LEN)
 }
 
+/// Encrypts and signs (&ldquo;seals&rdquo;) data in place, returning the tag
+/// separately.
+///
+/// `nonce` must be unique for every use of the key to seal data.
+///
+/// All of `in_out` is the input. When `seal_in_place_separate_tag` returns
+/// `Ok(tag)`, `in_out` has been overwritten with the ciphertext, which is the
+/// same length as the plaintext, and `tag` is the authentication tag. This is
+/// useful for protocols that don't put the tag directly after the ciphertext.
+///
+/// `ad` is the additional authenticated data, if any, as the concatenation of
+/// at most `MAX_AD_PARTS` slices. This lets protocols that authenticate
+/// several non-contiguous header fields avoid copying them into one buffer.
+///
+/// Like `seal_in_place`, this only fails with `error::InvalidLength`; here
+/// `ad` having too many parts also counts as an invalid length.
+pub fn seal_in_place_separate_tag(key: &SealingKey, nonce: &[u8],
+                                  in_out: &mut [u8], ad: &[&[u8]])
+                                  -> Result<[u8; TAG_LEN],
+                                            error::InvalidLength> {
+    if nonce.len() != key.key.algorithm.nonce_len {
+        return Err(error::InvalidLength);
+    }
+    if ad.len() > MAX_AD_PARTS {
+        return Err(error::InvalidLength);
+    }
+    try!(check_per_nonce_max_bytes(in_out.len()));
+    let mut tag = [0u8; TAG_LEN];
+    try!(seal(key, nonce, in_out, &mut tag, ad));
+    Ok(tag)
+}
+
+// The algorithms' `seal` functions only fail for lengths that the callers
+// above have already checked, e.g. the nonce length, or for lengths that are
+// too large for the algorithm.
+fn seal(key: &SealingKey, nonce: &[u8], in_out: &mut [u8],
+        tag_out: &mut [u8; TAG_LEN], ad: &[&[u8]])
+        -> Result<(), error::InvalidLength> {
+    (key.key.algorithm.seal)(&key.key.ctx_buf, nonce, in_out, tag_out, ad)
+        .map_err(|error::Unspecified| error::InvalidLength)
+}
+
 /// `OpeningKey` and `SealingKey` are type-safety wrappers around `Key`, which
 /// does all the actual work via the C AEAD interface.
 ///
@@ -192,7 +357,21 @@ struct Key {
     algorithm: &'static Algorithm,
 }
 
-// TODO: Implement Drop for Key that zeroizes the key data?
+// XXX: This should just be `#[derive(Clone)]` but that doesn't work because
+// `[u64; KEY_CTX_BUF_ELEMS]` doesn't implement `Clone`. The context buffer
+// doesn't contain any pointers into itself, so a copy of it is usable as-is.
+impl Clone for Key {
+    fn clone(&self) -> Key {
+        Key {
+            ctx_buf: self.ctx_buf,
+            algorithm: self.algorithm,
+        }
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) { self.ctx_buf[..].zeroize(); }
+}
 
 const KEY_CTX_BUF_ELEMS: usize = (KEY_CTX_BUF_LEN + 7) / 8;
 
@@ -203,15 +382,15 @@ impl Key {
     /// XXX: Assumes self.algorithm is already filled in.
     ///
     /// C analogs: `EVP_AEAD_CTX_init`, `EVP_AEAD_CTX_init_with_direction`
-    fn init(&mut self, key_bytes: &[u8]) -> Result<(), error::Unspecified> {
+    fn init(&mut self, key_bytes: &[u8]) -> Result<(), error::KeyRejected> {
         init::init_once();
 
-        if key_bytes.len() != self.algorithm.key_len() {
-            return Err(error::Unspecified);
-        }
+        try!(error::KeyRejected::check_len(key_bytes.len(),
+                                           self.algorithm.key_len()));
 
         let ctx_buf_bytes = polyfill::slice::u64_as_u8_mut(&mut self.ctx_buf);
         (self.algorithm.init)(ctx_buf_bytes, key_bytes)
+            .map_err(|_| error::KeyRejected::Unexpected)
     }
 
     /// The key's AEAD algorithm.
@@ -228,18 +407,38 @@ impl Key {
 pub struct Algorithm {
     init: fn(ctx_buf: &mut [u8], key: &[u8]) -> Result<(), error::Unspecified>,
 
-    seal: fn(ctx: &[u64; KEY_CTX_BUF_ELEMS], nonce: &[u8; NONCE_LEN],
-              in_out: &mut [u8], tag_out: &mut [u8; TAG_LEN], ad: &[u8])
-              -> Result<(), error::Unspecified>,
-    open: fn(ctx: &[u64; KEY_CTX_BUF_ELEMS], nonce: &[u8; NONCE_LEN],
-             in_out: &mut [u8], in_prefix_len: usize,
-             tag_out: &mut [u8; TAG_LEN], ad: &[u8])
+    // `nonce` is always `nonce_len` bytes long. `open` is passed the received
+    // tag in `tag_out`, which it replaces with the calculated tag. The
+    // additional data is the concatenation of the slices in `ad`, of which
+    // there are at most `MAX_AD_PARTS`.
+    seal: fn(ctx: &[u64; KEY_CTX_BUF_ELEMS], nonce: &[u8], in_out: &mut [u8],
+             tag_out: &mut [u8; TAG_LEN], ad: &[&[u8]])
+             -> Result<(), error::Unspecified>,
+    open: fn(ctx: &[u64; KEY_CTX_BUF_ELEMS], nonce: &[u8], in_out: &mut [u8],
+             in_prefix_len: usize, tag_out: &mut [u8; TAG_LEN], ad: &[&[u8]])
              -> Result<(), error::Unspecified>,
 
     key_len: usize,
+    nonce_len: usize,
+    name: &'static str,
 }
 
 impl Algorithm {
+    /// The name of the algorithm, which is the name of its static in this
+    /// module, e.g. `"AES_128_GCM"` for `AES_128_GCM`.
+    #[inline(always)]
+    pub fn name(&self) -> &'static str { self.name }
+
+    /// Returns the algorithm whose `name()` is `name`, e.g. `AES_256_GCM` for
+    /// `"AES_256_GCM"`. The comparison is case-sensitive.
+    pub fn from_name(name: &str)
+                     -> Result<&'static Algorithm, error::Unspecified> {
+        ALGORITHMS.iter()
+            .map(|&alg| alg)
+            .find(|alg| alg.name == name)
+            .ok_or(error::Unspecified)
+    }
+
     /// The length of the key.
     ///
     /// C analog: `EVP_AEAD_key_length`
@@ -263,36 +462,196 @@ impl Algorithm {
     /// Go analog:
     ///   [`crypto.cipher.AEAD.NonceSize`](https://golang.org/pkg/crypto/cipher/#AEAD)
     #[inline(always)]
-    pub fn nonce_len(&self) -> usize { NONCE_LEN }
+    pub fn nonce_len(&self) -> usize { self.nonce_len }
+
+    /// The length of the authentication tags, which is also the number of
+    /// bytes that sealing operations add to plaintexts.
+    #[inline(always)]
+    pub fn tag_len(&self) -> usize { TAG_LEN }
+
+    /// The security level of the algorithm, in bits, which is the length of
+    /// the key in bits.
+    #[inline(always)]
+    pub fn security_bits(&self) -> usize { self.key_len * 8 }
 }
 
+/// Formats the algorithm as its `name()`, which `Algorithm::from_name`
+/// accepts.
+impl core::fmt::Display for Algorithm {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+        fmt.write_str(self.name)
+    }
+}
+
+static ALGORITHMS: [&'static Algorithm; 8] = [
+    &CHACHA20_POLY1305,
+    &XCHACHA20_POLY1305,
+    &AES_128_GCM,
+    &AES_256_GCM,
+    &AES_128_GCM_SIV,
+    &AES_256_GCM_SIV,
+    &AES_128_OCB,
+    &AES_256_OCB,
+];
+
 
 /// The maximum amount of overhead for the algorithms in this module.
 pub const MAX_OVERHEAD_LEN: usize = TAG_LEN;
 
-// All the AEADs we support use 128-bit tags.
-const TAG_LEN: usize = 128 / 8;
+/// The length of the authentication tags. All the AEADs in this module use
+/// 128-bit tags.
+pub const TAG_LEN: usize = 128 / 8;
+
+/// The length of the truncated AES-GCM tags that
+/// `open_in_place_truncated_tag_96` accepts.
+pub const TRUNCATED_TAG_96_LEN: usize = 96 / 8;
 
-// All the AEADs we support use 96-bit nonces.
-const NONCE_LEN: usize = 96 / 8;
+/// The maximum number of slices that the additional authenticated data can
+/// be split into for `seal_in_place_separate_tag` and
+/// `open_in_place_separate_tag`.
+pub const MAX_AD_PARTS: usize = 8;
 
+/// The length of the nonces of all the AEADs in this module except
+/// `XCHACHA20_POLY1305`.
+pub const NONCE_LEN: usize = 96 / 8;
+
+
+/// The total length of the additional data in `ad`.
+fn ad_len(ad: &[&[u8]]) -> usize {
+    ad.iter().fold(0, |acc, part| acc + part.len())
+}
+
+/// Calls `f` with each 16-byte block of the concatenation of the slices in
+/// `ad`, in order. Only the last block may be shorter than 16 bytes.
+fn for_each_ad_block<F>(ad: &[&[u8]], mut f: F) where F: FnMut(&[u8]) {
+    const BLOCK_LEN: usize = 16;
+    let mut block = [0u8; BLOCK_LEN];
+    let mut block_len = 0;
+    for part in ad {
+        let mut part = *part;
+        if block_len > 0 {
+            let n = core::cmp::min(BLOCK_LEN - block_len, part.len());
+            block[block_len..(block_len + n)].copy_from_slice(&part[..n]);
+            block_len += n;
+            part = &part[n..];
+            if block_len < BLOCK_LEN {
+                continue;
+            }
+            f(&block);
+        }
+        let whole_len = part.len() - (part.len() % BLOCK_LEN);
+        for whole_block in part[..whole_len].chunks(BLOCK_LEN) {
+            f(whole_block);
+        }
+        block_len = part.len() - whole_len;
+        block[..block_len].copy_from_slice(&part[whole_len..]);
+    }
+    if block_len > 0 {
+        f(&block[..block_len]);
+    }
+}
 
 /// |GFp_chacha_20| uses a 32-bit block counter, so we disallow individual
 /// operations that work on more than 256GB at a time, for all AEADs.
 fn check_per_nonce_max_bytes(in_out_len: usize)
-                             -> Result<(), error::Unspecified> {
+                             -> Result<(), error::InvalidLength> {
     if polyfill::u64_from_usize(in_out_len) >= (1u64 << 32) * 64 - 64 {
-        return Err(error::Unspecified);
+        return Err(error::InvalidLength);
     }
     Ok(())
 }
 
+/// Runs the known-answer tests of sealing and opening with `AES_128_GCM`,
+/// `AES_256_GCM`, and `CHACHA20_POLY1305`; see `kat`.
+pub fn known_answer_tests() -> [kat::TestResult; 6] {
+    const PLAINTEXT: &'static [u8] = b"plaintext";
+    const KEY: [u8; 32] = [0x11; 32];
+    const NONCE: [u8; NONCE_LEN] = [0x22; NONCE_LEN];
+    const AD: &'static [u8] = b"ad";
+
+    fn seal(alg: &'static Algorithm, expected: &[u8]) -> bool {
+        let key = match SealingKey::new(alg, &KEY[..alg.key_len]) {
+            Ok(key) => key,
+            Err(_) => { return false; },
+        };
+        let mut in_out = [0u8; 9 + TAG_LEN];
+        in_out[..PLAINTEXT.len()].copy_from_slice(PLAINTEXT);
+        match seal_in_place(&key, &NONCE, &mut in_out, TAG_LEN, AD) {
+            Ok(len) => &in_out[..len] == expected,
+            Err(_) => false,
+        }
+    }
+
+    fn open(alg: &'static Algorithm, ciphertext: &[u8]) -> bool {
+        let key = match OpeningKey::new(alg, &KEY[..alg.key_len]) {
+            Ok(key) => key,
+            Err(_) => { return false; },
+        };
+        let mut in_out = [0u8; 9 + TAG_LEN];
+        in_out.copy_from_slice(ciphertext);
+        match open_in_place(&key, &NONCE, 0, &mut in_out, AD) {
+            Ok(len) => &in_out[..len] == PLAINTEXT,
+            Err(_) => false,
+        }
+    }
+
+    [
+        kat::TestResult { name: "AES_128_GCM seal",
+                          passed: seal(&AES_128_GCM, &KAT_AES_128_GCM) },
+        kat::TestResult { name: "AES_128_GCM open",
+                          passed: open(&AES_128_GCM, &KAT_AES_128_GCM) },
+        kat::TestResult { name: "AES_256_GCM seal",
+                          passed: seal(&AES_256_GCM, &KAT_AES_256_GCM) },
+        kat::TestResult { name: "AES_256_GCM open",
+                          passed: open(&AES_256_GCM, &KAT_AES_256_GCM) },
+        kat::TestResult {
+            name: "CHACHA20_POLY1305 seal",
+            passed: seal(&CHACHA20_POLY1305, &KAT_CHACHA20_POLY1305),
+        },
+        kat::TestResult {
+            name: "CHACHA20_POLY1305 open",
+            passed: open(&CHACHA20_POLY1305, &KAT_CHACHA20_POLY1305),
+        },
+    ]
+}
+
+// The ciphertexts and tags of `known_answer_tests`, generated with OpenSSL.
+const KAT_AES_128_GCM: [u8; 25] = [
+    0xd9, 0x99, 0x3d, 0x7e, 0xe9, 0x2c, 0x49, 0xec, 0x38, 0xb5, 0x6c, 0x59,
+    0xae, 0x7b, 0x00, 0x00, 0xa2, 0x55, 0x55, 0xdf, 0x05, 0x70, 0x5a, 0x65,
+    0x7d,
+];
+
+const KAT_AES_256_GCM: [u8; 25] = [
+    0x67, 0x9b, 0x66, 0x20, 0xae, 0xbb, 0xfa, 0x27, 0x91, 0xbe, 0xbb, 0xa9,
+    0xd3, 0xd0, 0x91, 0xc6, 0x80, 0xe0, 0xbd, 0xb0, 0x63, 0x60, 0xef, 0xca,
+    0x5a,
+];
+
+const KAT_CHACHA20_POLY1305: [u8; 25] = [
+    0xf5, 0x19, 0xb4, 0x77, 0x53, 0xe4, 0xc8, 0x0f, 0xa9, 0x49, 0xd8, 0x87,
+    0x09, 0x5a, 0xbc, 0x5a, 0xf4, 0xff, 0xc8, 0x61, 0x5d, 0x32, 0xa7, 0x60,
+    0x96,
+];
+
 
 #[cfg(test)]
 mod tests {
     use super::super::{aead, error, test};
     use std::vec::Vec;
 
+    #[test]
+    fn test_from_name() {
+        for alg in super::ALGORITHMS.iter() {
+            let found = aead::Algorithm::from_name(alg.name()).unwrap();
+            assert_eq!(found.name(), alg.name());
+            assert_eq!(format!("{}", alg), alg.name());
+        }
+        for name in &["", "aes_256_gcm", "AES-256-GCM", "AES_256"] {
+            assert!(aead::Algorithm::from_name(name).is_err());
+        }
+    }
+
     pub fn test_aead(aead_alg: &'static aead::Algorithm, file_path: &str) {
         test_aead_key_sizes(aead_alg);
         test_aead_nonce_sizes(aead_alg).unwrap();
@@ -313,10 +672,13 @@ mod tests {
                 s_in_out.push(0);
             }
             let s_key = try!(aead::SealingKey::new(aead_alg, &key_bytes[..]));
+            // A clone must work exactly like the key it was cloned from.
+            let s_key = s_key.clone();
             let s_result = aead::seal_in_place(&s_key, &nonce[..],
                                                &mut s_in_out[..],
                                                max_overhead_len, &ad);
             let o_key = try!(aead::OpeningKey::new(aead_alg, &key_bytes[..]));
+            let o_key = o_key.clone();
 
             ct.extend(tag);
 
@@ -418,7 +780,7 @@ mod tests {
                                    &o_in_out[..plaintext.len()]);
                     },
                     Some(ref error) if error == "WRONG_NONCE_LENGTH" => {
-                        assert_eq!(Err(error::Unspecified), s_result);
+                        assert_eq!(Err(error::InvalidLength), s_result);
                         assert_eq!(Err(error::Unspecified), o_result);
                     },
                     Some(error) => {
@@ -427,10 +789,103 @@ mod tests {
                 };
             }
 
+            if error.is_none() {
+                let (ciphertext, tag) = ct.split_at(plaintext.len());
+                test_aead_separate_tag(&s_key, &o_key, &nonce, &plaintext,
+                                       &ad, ciphertext, tag);
+            }
+
             Ok(())
         });
     }
 
+    // Checks that `seal_in_place_separate_tag` and
+    // `open_in_place_separate_tag` agree with `seal_in_place` and
+    // `open_in_place` for every way of splitting `ad` into two parts, and for
+    // splits into more parts, including empty ones.
+    fn test_aead_separate_tag(s_key: &aead::SealingKey,
+                              o_key: &aead::OpeningKey, nonce: &[u8],
+                              plaintext: &[u8], ad: &[u8],
+                              ciphertext: &[u8], tag: &[u8]) {
+        let mut splits: Vec<Vec<&[u8]>> = Vec::new();
+        splits.push(vec![]);
+        for i in 0..(ad.len() + 1) {
+            splits.push(vec![&ad[..i], &ad[i..]]);
+            let j = i + (ad.len() - i) / 2;
+            splits.push(vec![&ad[..i], &[], &ad[i..j], &ad[j..]]);
+        }
+        splits.push(ad.chunks(1).take(aead::MAX_AD_PARTS).collect());
+
+        for ad_parts in &splits {
+            let joined = ad_parts.iter().fold(Vec::new(), |mut acc, part| {
+                acc.extend_from_slice(part);
+                acc
+            });
+            if joined != ad {
+                continue;
+            }
+
+            let mut in_out = plaintext.to_vec();
+            let s_tag = aead::seal_in_place_separate_tag(s_key, nonce,
+                                                         &mut in_out,
+                                                         ad_parts).unwrap();
+            assert_eq!(&in_out[..], ciphertext);
+            assert_eq!(&s_tag[..], tag);
+
+            assert!(aead::open_in_place_separate_tag(o_key, nonce,
+                                                     &mut in_out, &s_tag,
+                                                     ad_parts).is_ok());
+            assert_eq!(&in_out[..], plaintext);
+
+            let mut bad_tag = s_tag;
+            bad_tag[0] ^= 1;
+            let mut in_out = ciphertext.to_vec();
+            assert!(aead::open_in_place_separate_tag(o_key, nonce,
+                                                     &mut in_out, &bad_tag,
+                                                     ad_parts).is_err());
+        }
+
+        let too_many_parts = [ad; aead::MAX_AD_PARTS + 1];
+        let mut in_out = plaintext.to_vec();
+        assert!(aead::seal_in_place_separate_tag(s_key, nonce, &mut in_out,
+                                                 &too_many_parts).is_err());
+    }
+
+    #[test]
+    fn test_sync_and_send() {
+        let key_bytes = [0u8; 16];
+        let s_key = aead::SealingKey::new(&aead::AES_128_GCM, &key_bytes)
+            .unwrap();
+        let _: &Send = &s_key;
+        let _: &Sync = &s_key;
+        let o_key = aead::OpeningKey::new(&aead::AES_128_GCM, &key_bytes)
+            .unwrap();
+        let _: &Send = &o_key;
+        let _: &Sync = &o_key;
+    }
+
+    #[test]
+    fn test_algorithm_metadata() {
+        for &(alg, name, security_bits) in
+                &[(&aead::AES_128_GCM, "AES_128_GCM", 128),
+                  (&aead::AES_256_GCM, "AES_256_GCM", 256),
+                  (&aead::AES_128_GCM_SIV, "AES_128_GCM_SIV", 128),
+                  (&aead::AES_256_GCM_SIV, "AES_256_GCM_SIV", 256),
+                  (&aead::CHACHA20_POLY1305, "CHACHA20_POLY1305", 256)] {
+            assert_eq!(alg.name(), name);
+            assert_eq!(alg.security_bits(), security_bits);
+            assert_eq!(alg.tag_len(), alg.max_overhead_len());
+            assert_eq!(alg.tag_len(), 16);
+            assert_eq!(alg.nonce_len(), 12);
+        }
+
+        let alg = &aead::XCHACHA20_POLY1305;
+        assert_eq!(alg.name(), "XCHACHA20_POLY1305");
+        assert_eq!(alg.security_bits(), 256);
+        assert_eq!(alg.tag_len(), 16);
+        assert_eq!(alg.nonce_len(), 24);
+    }
+
     fn test_aead_key_sizes(aead_alg: &'static aead::Algorithm) {
         let key_len = aead_alg.key_len();
         let key_data = vec![0u8; key_len * 2];
@@ -440,16 +895,20 @@ mod tests {
         assert!(aead::SealingKey::new(aead_alg, &key_data[..key_len]).is_ok());
 
         // Key is one byte too small.
-        assert!(aead::OpeningKey::new(aead_alg, &key_data[..(key_len - 1)])
-                    .is_err());
-        assert!(aead::SealingKey::new(aead_alg, &key_data[..(key_len - 1)])
-                    .is_err());
+        assert_eq!(aead::OpeningKey::new(aead_alg, &key_data[..(key_len - 1)])
+                       .err(),
+                   Some(error::KeyRejected::TooShort));
+        assert_eq!(aead::SealingKey::new(aead_alg, &key_data[..(key_len - 1)])
+                       .err(),
+                   Some(error::KeyRejected::TooShort));
 
         // Key is one byte too large.
-        assert!(aead::OpeningKey::new(aead_alg, &key_data[..(key_len + 1)])
-                    .is_err());
-        assert!(aead::SealingKey::new(aead_alg, &key_data[..(key_len + 1)])
-                    .is_err());
+        assert_eq!(aead::OpeningKey::new(aead_alg, &key_data[..(key_len + 1)])
+                       .err(),
+                   Some(error::KeyRejected::TooLong));
+        assert_eq!(aead::SealingKey::new(aead_alg, &key_data[..(key_len + 1)])
+                       .err(),
+                   Some(error::KeyRejected::TooLong));
 
         // Key is half the required size.
         assert!(aead::OpeningKey::new(aead_alg, &key_data[..(key_len / 2)])
@@ -13,6 +13,7 @@
 // CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 
 use {aead, bssl, c, error, polyfill};
+use core;
 
 const AES_128_KEY_LEN: usize = 128 / 8;
 const AES_256_KEY_LEN: usize = 32; // 256 / 8
@@ -39,6 +40,8 @@ const GCM128_SERIALIZED_LEN: usize = 16 * 16;
 /// Go analog: [`crypto.aes`](https://golang.org/pkg/crypto/aes/)
 pub static AES_128_GCM: aead::Algorithm = aead::Algorithm {
     key_len: AES_128_KEY_LEN,
+    nonce_len: aead::NONCE_LEN,
+    name: "AES_128_GCM",
     init: aes_gcm_init,
     seal: aes_gcm_seal,
     open: aes_gcm_open,
@@ -51,11 +54,18 @@ pub static AES_128_GCM: aead::Algorithm = aead::Algorithm {
 /// Go analog: [`crypto.aes`](https://golang.org/pkg/crypto/aes/)
 pub static AES_256_GCM: aead::Algorithm = aead::Algorithm {
     key_len: AES_256_KEY_LEN,
+    nonce_len: aead::NONCE_LEN,
+    name: "AES_256_GCM",
     init: aes_gcm_init,
     seal: aes_gcm_seal,
     open: aes_gcm_open,
 };
 
+// Whether `alg` is `AES_128_GCM` or `AES_256_GCM`.
+pub fn is_aes_gcm(alg: &aead::Algorithm) -> bool {
+    core::ptr::eq(alg, &AES_128_GCM) || core::ptr::eq(alg, &AES_256_GCM)
+}
+
 fn aes_gcm_init(ctx_buf: &mut [u8], key: &[u8])
                 -> Result<(), error::Unspecified> {
     bssl::map_result(unsafe {
@@ -65,29 +75,59 @@ fn aes_gcm_init(ctx_buf: &mut [u8], key: &[u8])
 }
 
 fn aes_gcm_seal(ctx: &[u64; aead::KEY_CTX_BUF_ELEMS],
-                nonce: &[u8; aead::NONCE_LEN], in_out: &mut [u8],
-                tag: &mut [u8; aead::TAG_LEN], ad: &[u8])
+                nonce: &[u8], in_out: &mut [u8],
+                tag: &mut [u8; aead::TAG_LEN], ad: &[&[u8]])
                 -> Result<(), error::Unspecified> {
+    let nonce = try!(slice_as_array_ref!(nonce, aead::NONCE_LEN));
     let ctx = polyfill::slice::u64_as_u8(ctx);
+    let ad = try!(AdParts::new(ad));
     bssl::map_result(unsafe {
         GFp_aes_gcm_seal(ctx.as_ptr(), in_out.as_mut_ptr(), in_out.len(), tag,
-                         nonce, ad.as_ptr(), ad.len())
+                         nonce, ad.ptrs.as_ptr(), ad.lens.as_ptr(), ad.num)
     })
 }
 
 fn aes_gcm_open(ctx: &[u64; aead::KEY_CTX_BUF_ELEMS],
-                nonce: &[u8; aead::NONCE_LEN], in_out: &mut [u8],
+                nonce: &[u8], in_out: &mut [u8],
                 in_prefix_len: usize, tag_out: &mut [u8; aead::TAG_LEN],
-                ad: &[u8]) -> Result<(), error::Unspecified> {
+                ad: &[&[u8]]) -> Result<(), error::Unspecified> {
+    let nonce = try!(slice_as_array_ref!(nonce, aead::NONCE_LEN));
     let ctx = polyfill::slice::u64_as_u8(ctx);
+    let ad = try!(AdParts::new(ad));
     bssl::map_result(unsafe {
         GFp_aes_gcm_open(ctx.as_ptr(), in_out.as_mut_ptr(),
                          in_out.len() - in_prefix_len, tag_out, nonce,
-                         in_out[in_prefix_len..].as_ptr(), ad.as_ptr(),
-                         ad.len())
+                         in_out[in_prefix_len..].as_ptr(), ad.ptrs.as_ptr(),
+                         ad.lens.as_ptr(), ad.num)
     })
 }
 
+// The additional data as the parallel arrays of pointers and lengths that
+// `GFp_aes_gcm_seal` and `GFp_aes_gcm_open` take.
+struct AdParts {
+    ptrs: [*const u8; aead::MAX_AD_PARTS],
+    lens: [c::size_t; aead::MAX_AD_PARTS],
+    num: c::size_t,
+}
+
+impl AdParts {
+    fn new(ad: &[&[u8]]) -> Result<AdParts, error::Unspecified> {
+        if ad.len() > aead::MAX_AD_PARTS {
+            return Err(error::Unspecified);
+        }
+        let mut parts = AdParts {
+            ptrs: [core::ptr::null(); aead::MAX_AD_PARTS],
+            lens: [0; aead::MAX_AD_PARTS],
+            num: ad.len(),
+        };
+        for (i, part) in ad.iter().enumerate() {
+            parts.ptrs[i] = part.as_ptr();
+            parts.lens[i] = part.len();
+        }
+        Ok(parts)
+    }
+}
+
 extern {
     fn GFp_aes_gcm_init(ctx_buf: *mut u8, ctx_buf_len: c::size_t,
                         key: *const u8, key_len: c::size_t) -> c::int;
@@ -95,14 +135,18 @@ extern {
     fn GFp_aes_gcm_seal(ctx_buf: *const u8, in_out: *mut u8,
                         in_out_len: c::size_t,
                         tag_out: &mut [u8; aead::TAG_LEN],
-                        nonce: &[u8; aead::NONCE_LEN], ad: *const u8,
-                        ad_len: c::size_t) -> c::int;
+                        nonce: &[u8; aead::NONCE_LEN],
+                        ad_parts: *const *const u8,
+                        ad_part_lens: *const c::size_t,
+                        num_ad_parts: c::size_t) -> c::int;
 
     fn GFp_aes_gcm_open(ctx_buf: *const u8, out: *mut u8,
                         in_out_len: c::size_t,
                         tag_out: &mut [u8; aead::TAG_LEN],
                         nonce: &[u8; aead::NONCE_LEN], in_: *const u8,
-                        ad: *const u8, ad_len: c::size_t) -> c::int;
+                        ad_parts: *const *const u8,
+                        ad_part_lens: *const c::size_t,
+                        num_ad_parts: c::size_t) -> c::int;
 }
 
 
@@ -112,6 +156,7 @@ mod tests {
     use super::super::super::aead;
     use super::super::tests::test_aead;
     use super::AES_MAX_ROUNDS;
+    use std::vec::Vec;
 
     #[test]
     pub fn test_aes_gcm_128() {
@@ -123,6 +168,65 @@ mod tests {
         test_aead(&aead::AES_256_GCM, "src/aead/aes_256_gcm_tests.txt");
     }
 
+    #[test]
+    fn test_aes_gcm_truncated_tag_96() {
+        test_truncated_tag_96(&aead::AES_128_GCM,
+                              "src/aead/aes_128_gcm_tests.txt");
+        test_truncated_tag_96(&aead::AES_256_GCM,
+                              "src/aead/aes_256_gcm_tests.txt");
+
+        // Only AES-GCM keys may be used with truncated tags.
+        let key = aead::OpeningKey::new(&aead::CHACHA20_POLY1305, &[0; 32])
+            .unwrap();
+        let mut in_out = [0u8; aead::TRUNCATED_TAG_96_LEN];
+        assert!(aead::open_in_place_truncated_tag_96(
+                    &key, &[0; aead::NONCE_LEN], 0, &mut in_out, &[])
+                .is_err());
+    }
+
+    fn test_truncated_tag_96(alg: &'static aead::Algorithm, file_path: &str) {
+        test::from_file(file_path, |section, test_case| {
+            assert_eq!(section, "");
+            let key_bytes = test_case.consume_bytes("KEY");
+            let nonce = test_case.consume_bytes("NONCE");
+            let plaintext = test_case.consume_bytes("IN");
+            let ad = test_case.consume_bytes("AD");
+            let ct = test_case.consume_bytes("CT");
+            let tag = test_case.consume_bytes("TAG");
+            if test_case.consume_optional_string("FAILS").is_some() {
+                return Ok(());
+            }
+
+            let key = try!(aead::OpeningKey::new(alg, &key_bytes));
+            let truncated_tag = &tag[..aead::TRUNCATED_TAG_96_LEN];
+
+            for &in_prefix_len in &[0, 5, 16] {
+                let mut in_out = vec![0u8; in_prefix_len];
+                in_out.extend_from_slice(&ct);
+                in_out.extend_from_slice(truncated_tag);
+                let len = try!(aead::open_in_place_truncated_tag_96(
+                    &key, &nonce, in_prefix_len, &mut in_out, &ad));
+                assert_eq!(&in_out[..len], &plaintext[..]);
+            }
+
+            // The last byte of the truncated tag is authenticated.
+            let mut in_out: Vec<u8> = ct.clone();
+            in_out.extend_from_slice(truncated_tag);
+            let last = in_out.len() - 1;
+            in_out[last] ^= 1;
+            assert!(aead::open_in_place_truncated_tag_96(
+                        &key, &nonce, 0, &mut in_out, &ad).is_err());
+
+            // The full tag isn't accepted in place of the truncated one.
+            let mut in_out: Vec<u8> = ct.clone();
+            in_out.extend_from_slice(&tag);
+            assert!(aead::open_in_place_truncated_tag_96(
+                        &key, &nonce, 0, &mut in_out, &ad).is_err());
+
+            Ok(())
+        });
+    }
+
     #[test]
     pub fn test_aes() {
         test::from_file("src/aead/aes_tests.txt", |section, test_case| {
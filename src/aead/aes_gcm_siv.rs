@@ -0,0 +1,364 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use {aead, error, polyfill};
+use core;
+use super::aes_ocb::{AES_KEY, AES_MAX_ROUNDS, BLOCK_LEN, Block,
+                     GFp_AES_encrypt, GFp_AES_set_encrypt_key};
+
+const AES_128_KEY_LEN: usize = 128 / 8;
+const AES_256_KEY_LEN: usize = 256 / 8;
+
+
+/// AES-128 in GCM-SIV mode with 128-bit tags and 96 bit nonces, as described
+/// in [RFC 8452].
+///
+/// AES-GCM-SIV is nonce-misuse-resistant: sealing two messages with the same
+/// nonce only reveals whether the two messages (and their additional data)
+/// are equal. Nonces should still be unique whenever possible.
+///
+/// [RFC 8452]: https://tools.ietf.org/html/rfc8452
+pub static AES_128_GCM_SIV: aead::Algorithm = aead::Algorithm {
+    key_len: AES_128_KEY_LEN,
+    nonce_len: aead::NONCE_LEN,
+    name: "AES_128_GCM_SIV",
+    init: aes_gcm_siv_init,
+    seal: aes_gcm_siv_seal,
+    open: aes_gcm_siv_open,
+};
+
+/// AES-256 in GCM-SIV mode with 128-bit tags and 96 bit nonces, as described
+/// in [RFC 8452].
+///
+/// AES-GCM-SIV is nonce-misuse-resistant: sealing two messages with the same
+/// nonce only reveals whether the two messages (and their additional data)
+/// are equal. Nonces should still be unique whenever possible.
+///
+/// [RFC 8452]: https://tools.ietf.org/html/rfc8452
+pub static AES_256_GCM_SIV: aead::Algorithm = aead::Algorithm {
+    key_len: AES_256_KEY_LEN,
+    nonce_len: aead::NONCE_LEN,
+    name: "AES_256_GCM_SIV",
+    init: aes_gcm_siv_init,
+    seal: aes_gcm_siv_seal,
+    open: aes_gcm_siv_open,
+};
+
+// The key-generating key. The per-nonce keys are derived from it.
+#[repr(C)]
+struct Key {
+    aes_key: AES_KEY,
+    key_len: usize,
+}
+
+fn aes_gcm_siv_init(ctx_buf: &mut [u8], key: &[u8])
+                    -> Result<(), error::Unspecified> {
+    debug_assert!(ctx_buf.len() >= core::mem::size_of::<Key>());
+    let siv_key = unsafe { &mut *(ctx_buf.as_mut_ptr() as *mut Key) };
+    try!(set_encrypt_key(key, &mut siv_key.aes_key));
+    siv_key.key_len = key.len();
+    Ok(())
+}
+
+fn aes_gcm_siv_seal(ctx: &[u64; aead::KEY_CTX_BUF_ELEMS],
+                    nonce: &[u8], in_out: &mut [u8],
+                    tag_out: &mut [u8; aead::TAG_LEN], ad: &[&[u8]])
+                    -> Result<(), error::Unspecified> {
+    let nonce = try!(slice_as_array_ref!(nonce, aead::NONCE_LEN));
+    try!(check_lengths(in_out.len(), aead::ad_len(ad)));
+    let (auth_key, enc_key) = try!(derive_keys(ctx_as_key(ctx), nonce));
+    let tag = compute_tag(&auth_key, &enc_key, nonce, ad, in_out);
+    ctr32_xor_in_place(&enc_key, &tag, in_out);
+    tag_out.copy_from_slice(&tag);
+    Ok(())
+}
+
+fn aes_gcm_siv_open(ctx: &[u64; aead::KEY_CTX_BUF_ELEMS],
+                    nonce: &[u8], in_out: &mut [u8],
+                    in_prefix_len: usize, tag_out: &mut [u8; aead::TAG_LEN],
+                    ad: &[&[u8]]) -> Result<(), error::Unspecified> {
+    let nonce = try!(slice_as_array_ref!(nonce, aead::NONCE_LEN));
+    let ciphertext_len = in_out.len() - in_prefix_len;
+    try!(check_lengths(ciphertext_len, aead::ad_len(ad)));
+    let (auth_key, enc_key) = try!(derive_keys(ctx_as_key(ctx), nonce));
+
+    // The received tag, which `tag_out` holds on input, is the initial
+    // counter block.
+    ctr32_xor_in_place(&enc_key, tag_out, &mut in_out[in_prefix_len..]);
+    unsafe {
+        core::ptr::copy(in_out[in_prefix_len..].as_ptr(), in_out.as_mut_ptr(),
+                        ciphertext_len);
+    }
+
+    // The caller compares the tag of the decrypted plaintext to the received
+    // tag.
+    let tag = compute_tag(&auth_key, &enc_key, nonce, ad,
+                          &in_out[..ciphertext_len]);
+    tag_out.copy_from_slice(&tag);
+    Ok(())
+}
+
+fn ctx_as_key(ctx: &[u64; aead::KEY_CTX_BUF_ELEMS]) -> &Key {
+    debug_assert!(core::mem::size_of_val(ctx) >= core::mem::size_of::<Key>());
+    unsafe { &*(ctx.as_ptr() as *const Key) }
+}
+
+// RFC 8452 Section 6: "[T]he length of P ... and the length of AAD ... MUST
+// be less than or equal to 2^36".
+fn check_lengths(in_out_len: usize, ad_len: usize)
+                 -> Result<(), error::Unspecified> {
+    const MAX_LEN: u64 = 1 << 36;
+    if polyfill::u64_from_usize(in_out_len) > MAX_LEN ||
+       polyfill::u64_from_usize(ad_len) > MAX_LEN {
+        return Err(error::Unspecified);
+    }
+    Ok(())
+}
+
+// Derives the message-authentication key and the message-encryption key for
+// `nonce`, as described in RFC 8452 Section 4.
+fn derive_keys(key: &Key, nonce: &[u8; aead::NONCE_LEN])
+               -> Result<(Block, AES_KEY), error::Unspecified> {
+    let mut derived = [0u8; BLOCK_LEN + AES_256_KEY_LEN];
+    let derived_len = BLOCK_LEN + key.key_len;
+    for (i, chunk) in derived[..derived_len].chunks_mut(8).enumerate() {
+        let mut block = [0u8; BLOCK_LEN];
+        block[..4].copy_from_slice(&le_u8_from_u32(i as u32));
+        block[4..].copy_from_slice(nonce);
+        encrypt_in_place(&key.aes_key, &mut block);
+        chunk.copy_from_slice(&block[..8]);
+    }
+    let mut auth_key = [0u8; BLOCK_LEN];
+    auth_key.copy_from_slice(&derived[..BLOCK_LEN]);
+    let mut enc_key = AES_KEY {
+        rd_key: [0u32; 4 * (AES_MAX_ROUNDS + 1)],
+        rounds: 0,
+    };
+    try!(set_encrypt_key(&derived[BLOCK_LEN..derived_len], &mut enc_key));
+    Ok((auth_key, enc_key))
+}
+
+// Computes the tag from RFC 8452 Section 4, from the POLYVAL of the
+// additional data and the plaintext.
+fn compute_tag(auth_key: &Block, enc_key: &AES_KEY,
+               nonce: &[u8; aead::NONCE_LEN], ad: &[&[u8]],
+               plaintext: &[u8]) -> Block {
+    let mut polyval = Polyval::new(auth_key);
+    aead::for_each_ad_block(ad, |block| polyval.update_padded(block));
+    polyval.update_padded(plaintext);
+    polyval.update_block(&u64s_as_block(
+        &[polyfill::u64_from_usize(aead::ad_len(ad)) * 8,
+          polyfill::u64_from_usize(plaintext.len()) * 8]));
+
+    let mut tag = polyval.finish();
+    for (t, n) in tag.iter_mut().zip(nonce.iter()) {
+        *t ^= *n;
+    }
+    tag[BLOCK_LEN - 1] &= 0x7f;
+    encrypt_in_place(enc_key, &mut tag);
+    tag
+}
+
+// AES-CTR as described in RFC 8452 Section 4: the initial counter block is
+// the tag with the most significant bit of the last byte set, and only the
+// first 32 bits, as a little-endian integer, are incremented.
+fn ctr32_xor_in_place(enc_key: &AES_KEY, tag: &Block, in_out: &mut [u8]) {
+    let mut counter_block = *tag;
+    counter_block[BLOCK_LEN - 1] |= 0x80;
+    let mut counter = polyfill::slice::u32_from_le_u8(
+        slice_as_array_ref!(&counter_block[..4], 4).unwrap());
+    for chunk in in_out.chunks_mut(BLOCK_LEN) {
+        let mut key_stream = counter_block;
+        encrypt_in_place(enc_key, &mut key_stream);
+        for (b, k) in chunk.iter_mut().zip(key_stream.iter()) {
+            *b ^= *k;
+        }
+        counter = counter.wrapping_add(1);
+        counter_block[..4].copy_from_slice(&le_u8_from_u32(counter));
+    }
+}
+
+// POLYVAL from RFC 8452 Section 3. The field multiplication is done with
+// integer multiplications with "holes" so that it takes constant time, as in
+// BearSSL's `br_ghash_ctmul64`.
+struct Polyval {
+    h: [u64; 2],
+    s: [u64; 2],
+}
+
+impl Polyval {
+    fn new(h: &Block) -> Polyval {
+        Polyval { h: block_as_u64s(h), s: [0, 0] }
+    }
+
+    // Processes `input`, zero-padded to a multiple of the block length.
+    fn update_padded(&mut self, input: &[u8]) {
+        for chunk in input.chunks(BLOCK_LEN) {
+            let mut block = [0u8; BLOCK_LEN];
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.update_block(&block);
+        }
+    }
+
+    fn update_block(&mut self, block: &Block) {
+        let x = block_as_u64s(block);
+        let s = [self.s[0] ^ x[0], self.s[1] ^ x[1]];
+        self.s = dot(&s, &self.h);
+    }
+
+    fn finish(self) -> Block { u64s_as_block(&self.s) }
+}
+
+// POLYVAL's field elements are little-endian.
+fn block_as_u64s(block: &Block) -> [u64; 2] {
+    let mut result = [0u64; 2];
+    for (i, b) in block.iter().enumerate() {
+        result[i / 8] |= u64::from(*b) << (8 * (i % 8));
+    }
+    result
+}
+
+fn u64s_as_block(value: &[u64; 2]) -> Block {
+    let mut result = [0u8; BLOCK_LEN];
+    for (i, b) in result.iter_mut().enumerate() {
+        *b = (value[i / 8] >> (8 * (i % 8))) as u8;
+    }
+    result
+}
+
+fn le_u8_from_u32(value: u32) -> [u8; 4] {
+    [value as u8, (value >> 8) as u8, (value >> 16) as u8,
+     (value >> 24) as u8]
+}
+
+// dot(a, b) = a * b * x^-128 from RFC 8452 Section 3. The 128x128-bit
+// carryless product is computed with Karatsuba from 64x64-bit products;
+// `bmul64` only returns the low half of those, so the high half is computed
+// as the bit-reversed low half of the product of the bit-reversed inputs.
+// The product is then reduced by x^-128 mod
+// x^128 + x^127 + x^126 + x^121 + 1.
+fn dot(a: &[u64; 2], b: &[u64; 2]) -> [u64; 2] {
+    let (a0, a1) = (a[0], a[1]);
+    let (b0, b1) = (b[0], b[1]);
+    let (a0r, a1r) = (rev64(a0), rev64(a1));
+    let (b0r, b1r) = (rev64(b0), rev64(b1));
+
+    let z0 = bmul64(a0, b0);
+    let z1 = bmul64(a1, b1);
+    let z2 = bmul64(a0 ^ a1, b0 ^ b1) ^ z0 ^ z1;
+    let z0h = bmul64(a0r, b0r);
+    let z1h = bmul64(a1r, b1r);
+    let z2h = bmul64(a0r ^ a1r, b0r ^ b1r) ^ z0h ^ z1h;
+    let z0h = rev64(z0h) >> 1;
+    let z1h = rev64(z1h) >> 1;
+    let z2h = rev64(z2h) >> 1;
+
+    let v0 = z0;
+    let mut v1 = z0h ^ z2;
+    let mut v2 = z1 ^ z2h;
+    let mut v3 = z1h;
+
+    v2 ^= v0 ^ (v0 >> 1) ^ (v0 >> 2) ^ (v0 >> 7);
+    v1 ^= (v0 << 63) ^ (v0 << 62) ^ (v0 << 57);
+    v3 ^= v1 ^ (v1 >> 1) ^ (v1 >> 2) ^ (v1 >> 7);
+    v2 ^= (v1 << 63) ^ (v1 << 62) ^ (v1 << 57);
+
+    [v2, v3]
+}
+
+// The low 64 bits of the carryless product of `x` and `y`. Every fourth bit
+// of each operand is multiplied at a time, so that the carries of the integer
+// multiplications land in bits that are masked off.
+fn bmul64(x: u64, y: u64) -> u64 {
+    const M0: u64 = 0x1111111111111111;
+    const M1: u64 = 0x2222222222222222;
+    const M2: u64 = 0x4444444444444444;
+    const M3: u64 = 0x8888888888888888;
+    let (x0, x1, x2, x3) = (x & M0, x & M1, x & M2, x & M3);
+    let (y0, y1, y2, y3) = (y & M0, y & M1, y & M2, y & M3);
+    let z0 = x0.wrapping_mul(y0) ^ x1.wrapping_mul(y3) ^
+             x2.wrapping_mul(y2) ^ x3.wrapping_mul(y1);
+    let z1 = x0.wrapping_mul(y1) ^ x1.wrapping_mul(y0) ^
+             x2.wrapping_mul(y3) ^ x3.wrapping_mul(y2);
+    let z2 = x0.wrapping_mul(y2) ^ x1.wrapping_mul(y1) ^
+             x2.wrapping_mul(y0) ^ x3.wrapping_mul(y3);
+    let z3 = x0.wrapping_mul(y3) ^ x1.wrapping_mul(y2) ^
+             x2.wrapping_mul(y1) ^ x3.wrapping_mul(y0);
+    (z0 & M0) | (z1 & M1) | (z2 & M2) | (z3 & M3)
+}
+
+// Reverses the order of the bits of `x`.
+fn rev64(x: u64) -> u64 {
+    let x = ((x & 0x5555555555555555) << 1) | ((x >> 1) & 0x5555555555555555);
+    let x = ((x & 0x3333333333333333) << 2) | ((x >> 2) & 0x3333333333333333);
+    let x = ((x & 0x0f0f0f0f0f0f0f0f) << 4) | ((x >> 4) & 0x0f0f0f0f0f0f0f0f);
+    let x = ((x & 0x00ff00ff00ff00ff) << 8) | ((x >> 8) & 0x00ff00ff00ff00ff);
+    let x = ((x & 0x0000ffff0000ffff) << 16) | ((x >> 16) & 0x0000ffff0000ffff);
+    (x << 32) | (x >> 32)
+}
+
+fn set_encrypt_key(key: &[u8], aes_key: &mut AES_KEY)
+                   -> Result<(), error::Unspecified> {
+    let bits = (key.len() * 8) as u32;
+    if unsafe { GFp_AES_set_encrypt_key(key.as_ptr(), bits, aes_key) } != 0 {
+        return Err(error::Unspecified);
+    }
+    Ok(())
+}
+
+fn encrypt_in_place(aes_key: &AES_KEY, block: &mut Block) {
+    unsafe {
+        GFp_AES_encrypt(block.as_ptr(), block.as_mut_ptr(), aes_key);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {aead, test};
+    use core;
+    use super::{Key, Polyval};
+
+    #[test]
+    pub fn test_aes_gcm_siv_128() {
+        aead::tests::test_aead(&aead::AES_128_GCM_SIV,
+                               "src/aead/aes_128_gcm_siv_tests.txt");
+    }
+
+    #[test]
+    pub fn test_aes_gcm_siv_256() {
+        aead::tests::test_aead(&aead::AES_256_GCM_SIV,
+                               "src/aead/aes_256_gcm_siv_tests.txt");
+    }
+
+    #[test]
+    fn test_key_fits_in_ctx_buf() {
+        assert!(core::mem::size_of::<Key>() <=
+                aead::KEY_CTX_BUF_ELEMS * core::mem::size_of::<u64>());
+    }
+
+    // The example from RFC 8452 Appendix A.
+    #[test]
+    fn test_polyval() {
+        let h = test::from_hex("25629347589242761d31f826ba4b757b").unwrap();
+        let x = test::from_hex("4f4f95668c83dfb6401762bb2d01a262\
+                                d1a24ddd2721d006bbe45f20d3c9f362").unwrap();
+        let mut polyval =
+            Polyval::new(slice_as_array_ref!(&h, 16).unwrap());
+        polyval.update_padded(&x);
+        assert_eq!(&polyval.finish()[..],
+                   &test::from_hex("f7a3b47b846119fae5b7866cf5e5b77e")
+                        .unwrap()[..]);
+    }
+}
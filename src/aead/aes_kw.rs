@@ -0,0 +1,418 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! AES Key Wrap (AES-KW), as specified in [RFC 3394], and AES Key Wrap with
+//! Padding (AES-KWP), as specified in [RFC 5649].
+//!
+//! Key wrapping is deterministic authenticated encryption for keys: it is how
+//! keys are exchanged with HSMs, and it is the `A128KW` and `A256KW` key
+//! management algorithms of JOSE ([RFC 7518] Section 4.4). AES-KW requires
+//! the wrapped key to be a multiple of 8 bytes, and at least 16 bytes, long;
+//! AES-KWP wraps keys of any non-zero length.
+//!
+//! The wrapped key is 8 bytes longer than the key, after the key is padded to
+//! a multiple of 8 bytes for AES-KWP. None of the output of `unwrap` and
+//! `unwrap_with_padding` is valid unless they succeed; on failure, the output
+//! is zeroed.
+//!
+//! C analogs: `AES_wrap_key`, `AES_unwrap_key`, `AES_wrap_key_padded`,
+//! `AES_unwrap_key_padded`
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::aead::aes_kw;
+//!
+//! # fn main() { foo().unwrap() }
+//! # fn foo() -> Result<(), ring::error::Unspecified> {
+//! let kek = try!(aes_kw::Key::new(&aes_kw::AES_256, &[0x42; 32]));
+//!
+//! let key_to_wrap = [0x17; 16];
+//! let mut wrapped = [0u8; 16 + aes_kw::OVERHEAD_LEN];
+//! let wrapped_len = try!(aes_kw::wrap(&kek, &key_to_wrap, &mut wrapped));
+//!
+//! let mut unwrapped = [0u8; 16];
+//! let unwrapped_len =
+//!     try!(aes_kw::unwrap(&kek, &wrapped[..wrapped_len], &mut unwrapped));
+//! assert_eq!(&unwrapped[..unwrapped_len], &key_to_wrap[..]);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [RFC 3394]: https://tools.ietf.org/html/rfc3394
+//! [RFC 5649]: https://tools.ietf.org/html/rfc5649
+//! [RFC 7518]: https://tools.ietf.org/html/rfc7518#section-4.4
+
+use {constant_time, error, init, polyfill};
+use super::aes_ocb::{AES_KEY, AES_MAX_ROUNDS, BLOCK_LEN, Block,
+                     GFp_AES_decrypt, GFp_AES_encrypt, GFp_AES_set_decrypt_key,
+                     GFp_AES_set_encrypt_key};
+
+/// An AES key wrap algorithm, i.e. the length of the key-encryption key.
+pub struct Algorithm {
+    key_len: usize,
+}
+
+/// AES-128 key wrap, e.g. JOSE's `A128KW`. The key-encryption key must be 16
+/// bytes.
+pub static AES_128: Algorithm = Algorithm {
+    key_len: 128 / 8,
+};
+
+/// AES-256 key wrap, e.g. JOSE's `A256KW`. The key-encryption key must be 32
+/// bytes.
+pub static AES_256: Algorithm = Algorithm {
+    key_len: 256 / 8,
+};
+
+impl Algorithm {
+    /// The length of the key-encryption key, in bytes.
+    #[inline(always)]
+    pub fn key_len(&self) -> usize { self.key_len }
+}
+
+/// The number of bytes that wrapping adds to the (padded) key.
+pub const OVERHEAD_LEN: usize = SEMIBLOCK_LEN;
+
+/// A key-encryption key (KEK), for both wrapping and unwrapping.
+pub struct Key {
+    encrypt: AES_KEY,
+    decrypt: AES_KEY,
+}
+
+impl Key {
+    /// Create a new key-encryption key. `key_bytes` must be exactly
+    /// `algorithm.key_len()` bytes long.
+    pub fn new(algorithm: &'static Algorithm, key_bytes: &[u8])
+               -> Result<Key, error::KeyRejected> {
+        init::init_once();
+        try!(error::KeyRejected::check_len(key_bytes.len(),
+                                           algorithm.key_len));
+        let mut key = Key {
+            encrypt: AES_KEY {
+                rd_key: [0u32; 4 * (AES_MAX_ROUNDS + 1)],
+                rounds: 0,
+            },
+            decrypt: AES_KEY {
+                rd_key: [0u32; 4 * (AES_MAX_ROUNDS + 1)],
+                rounds: 0,
+            },
+        };
+        let bits = (key_bytes.len() * 8) as u32;
+        if unsafe {
+            GFp_AES_set_encrypt_key(key_bytes.as_ptr(), bits, &mut key.encrypt)
+        } != 0 {
+            return Err(error::KeyRejected::Unexpected);
+        }
+        if unsafe {
+            GFp_AES_set_decrypt_key(key_bytes.as_ptr(), bits, &mut key.decrypt)
+        } != 0 {
+            return Err(error::KeyRejected::Unexpected);
+        }
+        Ok(key)
+    }
+
+    fn encrypt_in_place(&self, block: &mut Block) {
+        unsafe {
+            GFp_AES_encrypt(block.as_ptr(), block.as_mut_ptr(), &self.encrypt);
+        }
+    }
+
+    fn decrypt_in_place(&self, block: &mut Block) {
+        unsafe {
+            GFp_AES_decrypt(block.as_ptr(), block.as_mut_ptr(), &self.decrypt);
+        }
+    }
+}
+
+/// Wraps `plaintext` with AES-KW and writes the result to the beginning of
+/// `out`, returning its length, which is `plaintext.len() + OVERHEAD_LEN`.
+///
+/// `plaintext.len()` must be a multiple of 8 and at least 16, and `out` must
+/// be at least `plaintext.len() + OVERHEAD_LEN` bytes long.
+pub fn wrap(key: &Key, plaintext: &[u8], out: &mut [u8])
+            -> Result<usize, error::Unspecified> {
+    if plaintext.len() % SEMIBLOCK_LEN != 0 ||
+       plaintext.len() < 2 * SEMIBLOCK_LEN {
+        return Err(error::Unspecified);
+    }
+    let out_len = plaintext.len() + OVERHEAD_LEN;
+    if out.len() < out_len {
+        return Err(error::Unspecified);
+    }
+    let (a, r) = out[..out_len].split_at_mut(SEMIBLOCK_LEN);
+    let a = slice_as_array_ref_mut!(a, SEMIBLOCK_LEN).unwrap();
+    *a = DEFAULT_IV;
+    r.copy_from_slice(plaintext);
+    w(key, a, r);
+    Ok(out_len)
+}
+
+/// Unwraps the AES-KW-wrapped `ciphertext` and writes the result to the
+/// beginning of `out`, returning its length, which is
+/// `ciphertext.len() - OVERHEAD_LEN`.
+///
+/// `out` must be at least `ciphertext.len() - OVERHEAD_LEN` bytes long. Fails
+/// if `ciphertext` wasn't wrapped by `wrap` with the same key.
+pub fn unwrap(key: &Key, ciphertext: &[u8], out: &mut [u8])
+              -> Result<usize, error::Unspecified> {
+    if ciphertext.len() % SEMIBLOCK_LEN != 0 ||
+       ciphertext.len() < 3 * SEMIBLOCK_LEN {
+        return Err(error::Unspecified);
+    }
+    let out_len = ciphertext.len() - OVERHEAD_LEN;
+    if out.len() < out_len {
+        return Err(error::Unspecified);
+    }
+    let out = &mut out[..out_len];
+    let mut a = [0u8; SEMIBLOCK_LEN];
+    a.copy_from_slice(&ciphertext[..SEMIBLOCK_LEN]);
+    out.copy_from_slice(&ciphertext[SEMIBLOCK_LEN..]);
+    w_inverse(key, &mut a, out);
+    if constant_time::verify_slices_are_equal(&a, &DEFAULT_IV).is_err() {
+        polyfill::slice::fill(out, 0);
+        return Err(error::Unspecified);
+    }
+    Ok(out_len)
+}
+
+/// Wraps `plaintext` with AES-KWP and writes the result to the beginning of
+/// `out`, returning its length, which is `plaintext.len()` rounded up to a
+/// multiple of 8, plus `OVERHEAD_LEN`.
+///
+/// `plaintext` must not be empty, and must be shorter than 2**32 bytes, and
+/// `out` must be long enough for the result.
+pub fn wrap_with_padding(key: &Key, plaintext: &[u8], out: &mut [u8])
+                         -> Result<usize, error::Unspecified> {
+    if plaintext.is_empty() ||
+       polyfill::u64_from_usize(plaintext.len()) > 0xffffffff {
+        return Err(error::Unspecified);
+    }
+    let padded_len = padded_len(plaintext.len());
+    let out_len = padded_len + OVERHEAD_LEN;
+    if out.len() < out_len {
+        return Err(error::Unspecified);
+    }
+
+    let mut aiv = [0u8; SEMIBLOCK_LEN];
+    aiv[..4].copy_from_slice(&AIV_PREFIX);
+    aiv[4..].copy_from_slice(
+        &polyfill::slice::be_u8_from_u32(plaintext.len() as u32));
+
+    let (a, r) = out[..out_len].split_at_mut(SEMIBLOCK_LEN);
+    let a = slice_as_array_ref_mut!(a, SEMIBLOCK_LEN).unwrap();
+    r[..plaintext.len()].copy_from_slice(plaintext);
+    polyfill::slice::fill(&mut r[plaintext.len()..], 0);
+    if padded_len == SEMIBLOCK_LEN {
+        // RFC 5649 Section 4.1: a single semiblock is encrypted with AES
+        // directly.
+        let mut b = [0u8; BLOCK_LEN];
+        b[..SEMIBLOCK_LEN].copy_from_slice(&aiv);
+        b[SEMIBLOCK_LEN..].copy_from_slice(r);
+        key.encrypt_in_place(&mut b);
+        a.copy_from_slice(&b[..SEMIBLOCK_LEN]);
+        r.copy_from_slice(&b[SEMIBLOCK_LEN..]);
+    } else {
+        *a = aiv;
+        w(key, a, r);
+    }
+    Ok(out_len)
+}
+
+/// Unwraps the AES-KWP-wrapped `ciphertext` and writes the result to the
+/// beginning of `out`, returning its length.
+///
+/// `out` must be at least `ciphertext.len() - OVERHEAD_LEN` bytes long, which
+/// leaves room for the padding. Fails if `ciphertext` wasn't wrapped by
+/// `wrap_with_padding` with the same key.
+pub fn unwrap_with_padding(key: &Key, ciphertext: &[u8], out: &mut [u8])
+                           -> Result<usize, error::Unspecified> {
+    if ciphertext.len() % SEMIBLOCK_LEN != 0 ||
+       ciphertext.len() < 2 * SEMIBLOCK_LEN {
+        return Err(error::Unspecified);
+    }
+    let padded_len = ciphertext.len() - OVERHEAD_LEN;
+    if out.len() < padded_len {
+        return Err(error::Unspecified);
+    }
+    let out = &mut out[..padded_len];
+    let mut a = [0u8; SEMIBLOCK_LEN];
+    if padded_len == SEMIBLOCK_LEN {
+        let mut b = [0u8; BLOCK_LEN];
+        b.copy_from_slice(ciphertext);
+        key.decrypt_in_place(&mut b);
+        a.copy_from_slice(&b[..SEMIBLOCK_LEN]);
+        out.copy_from_slice(&b[SEMIBLOCK_LEN..]);
+    } else {
+        a.copy_from_slice(&ciphertext[..SEMIBLOCK_LEN]);
+        out.copy_from_slice(&ciphertext[SEMIBLOCK_LEN..]);
+        w_inverse(key, &mut a, out);
+    }
+
+    // RFC 5649 Section 3: the AIV prefix must match, the message length
+    // indicator must be consistent with the padded length, and the padding
+    // must be zero.
+    let mli = polyfill::slice::u32_from_be_u8(
+        slice_as_array_ref!(&a[4..], 4).unwrap()) as usize;
+    let valid =
+        constant_time::verify_slices_are_equal(&a[..4], &AIV_PREFIX).is_ok() &&
+        mli <= padded_len && mli + SEMIBLOCK_LEN > padded_len &&
+        out[mli..].iter().all(|b| *b == 0);
+    if !valid {
+        polyfill::slice::fill(out, 0);
+        return Err(error::Unspecified);
+    }
+    Ok(mli)
+}
+
+const SEMIBLOCK_LEN: usize = BLOCK_LEN / 2;
+
+// The default initial value of RFC 3394 Section 2.2.3.1.
+const DEFAULT_IV: [u8; SEMIBLOCK_LEN] = [0xa6; SEMIBLOCK_LEN];
+
+// The constant part of the alternative initial value of RFC 5649 Section 3.
+const AIV_PREFIX: [u8; 4] = [0xa6, 0x59, 0x59, 0xa6];
+
+fn padded_len(len: usize) -> usize {
+    (len + SEMIBLOCK_LEN - 1) / SEMIBLOCK_LEN * SEMIBLOCK_LEN
+}
+
+// The wrapping process W of RFC 3394 Section 2.2.1, in its index-based form.
+// `a` is the initial value on input and the integrity check register on
+// output, and `r` holds the n >= 2 semiblocks.
+fn w(key: &Key, a: &mut [u8; SEMIBLOCK_LEN], r: &mut [u8]) {
+    let n = r.len() / SEMIBLOCK_LEN;
+    let mut b = [0u8; BLOCK_LEN];
+    for j in 0..6 {
+        for (i, r_i) in r.chunks_mut(SEMIBLOCK_LEN).enumerate() {
+            b[..SEMIBLOCK_LEN].copy_from_slice(a);
+            b[SEMIBLOCK_LEN..].copy_from_slice(r_i);
+            key.encrypt_in_place(&mut b);
+            a.copy_from_slice(&b[..SEMIBLOCK_LEN]);
+            xor_t(a, n * j + i + 1);
+            r_i.copy_from_slice(&b[SEMIBLOCK_LEN..]);
+        }
+    }
+}
+
+// The unwrapping process W**-1 of RFC 3394 Section 2.2.2, in its index-based
+// form.
+fn w_inverse(key: &Key, a: &mut [u8; SEMIBLOCK_LEN], r: &mut [u8]) {
+    let n = r.len() / SEMIBLOCK_LEN;
+    let mut b = [0u8; BLOCK_LEN];
+    for j in (0..6).rev() {
+        for (i, r_i) in r.chunks_mut(SEMIBLOCK_LEN).enumerate().rev() {
+            xor_t(a, n * j + i + 1);
+            b[..SEMIBLOCK_LEN].copy_from_slice(a);
+            b[SEMIBLOCK_LEN..].copy_from_slice(r_i);
+            key.decrypt_in_place(&mut b);
+            a.copy_from_slice(&b[..SEMIBLOCK_LEN]);
+            r_i.copy_from_slice(&b[SEMIBLOCK_LEN..]);
+        }
+    }
+}
+
+// XORs the big-endian 64-bit encoding of `t` into `a`.
+fn xor_t(a: &mut [u8; SEMIBLOCK_LEN], t: usize) {
+    let t = polyfill::u64_from_usize(t);
+    for (i, a) in a.iter_mut().enumerate() {
+        *a ^= (t >> (8 * (SEMIBLOCK_LEN - 1 - i))) as u8;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {error, test};
+    use super::*;
+
+    #[test]
+    fn test_aes_kw() {
+        test::from_file("src/aead/aes_kw_tests.txt", |section, test_case| {
+            let alg = match test_case.consume_string("Alg").as_str() {
+                "AES_128" => &AES_128,
+                "AES_256" => &AES_256,
+                s => panic!("Unsupported algorithm: {}", s),
+            };
+            let kek = test_case.consume_bytes("KEK");
+            let plaintext = test_case.consume_bytes("Key");
+            let expected = test_case.consume_bytes("Wrapped");
+            let (wrap_fn, unwrap_fn): (WrapFn, WrapFn) = match section {
+                "KW" => (wrap, unwrap),
+                "KWP" => (wrap_with_padding, unwrap_with_padding),
+                _ => unreachable!(),
+            };
+            let key = Key::new(alg, &kek).unwrap();
+
+            let mut wrapped = vec![0u8; expected.len() + 1];
+            let wrapped_len = try!(wrap_fn(&key, &plaintext, &mut wrapped));
+            assert_eq!(&wrapped[..wrapped_len], &expected[..]);
+            assert!(wrap_fn(&key, &plaintext,
+                            &mut wrapped[..(expected.len() - 1)]).is_err());
+
+            let mut unwrapped = vec![0u8; expected.len() - OVERHEAD_LEN];
+            let unwrapped_len =
+                try!(unwrap_fn(&key, &expected, &mut unwrapped));
+            assert_eq!(&unwrapped[..unwrapped_len], &plaintext[..]);
+            assert!(unwrap_fn(&key, &expected,
+                              &mut unwrapped[..(expected.len() -
+                                                OVERHEAD_LEN - 1)]).is_err());
+
+            // Any modification of the wrapped key is detected, and the
+            // output is zeroed.
+            for i in 0..expected.len() {
+                let mut bad = expected.clone();
+                bad[i] ^= 1;
+                let mut unwrapped = vec![0xffu8; expected.len()];
+                assert_eq!(unwrap_fn(&key, &bad, &mut unwrapped),
+                           Err(error::Unspecified));
+                let unwrapped = &unwrapped[..(expected.len() - OVERHEAD_LEN)];
+                assert!(unwrapped.iter().all(|b| *b == 0));
+            }
+            assert!(unwrap_fn(&key, &expected[..(expected.len() - 8)],
+                              &mut unwrapped).is_err());
+
+            Ok(())
+        });
+    }
+
+    type WrapFn = fn(&Key, &[u8], &mut [u8])
+                     -> Result<usize, error::Unspecified>;
+
+    #[test]
+    fn test_aes_kw_lengths() {
+        let key = Key::new(&AES_128, &[0u8; 16]).unwrap();
+        let mut out = [0u8; 64];
+        for len in 0..48 {
+            let plaintext = [0x42u8; 48];
+            let plaintext = &plaintext[..len];
+            assert_eq!(wrap(&key, plaintext, &mut out).is_ok(),
+                       len >= 16 && len % 8 == 0);
+            assert_eq!(wrap_with_padding(&key, plaintext, &mut out).is_ok(),
+                       len >= 1);
+        }
+
+        // The two modes can't unwrap each other's output.
+        let mut wrapped = [0u8; 24];
+        let mut unwrapped = [0u8; 16];
+        let _ = wrap(&key, &[0x42u8; 16], &mut wrapped).unwrap();
+        assert!(unwrap_with_padding(&key, &wrapped, &mut unwrapped).is_err());
+        let _ = wrap_with_padding(&key, &[0x42u8; 16], &mut wrapped)
+            .unwrap();
+        assert!(unwrap(&key, &wrapped, &mut unwrapped).is_err());
+
+        assert!(Key::new(&AES_128, &[0u8; 32]).is_err());
+        assert!(Key::new(&AES_256, &[0u8; 16]).is_err());
+    }
+}
@@ -0,0 +1,370 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use {aead, c, error};
+use core;
+
+const AES_128_KEY_LEN: usize = 128 / 8;
+const AES_256_KEY_LEN: usize = 256 / 8;
+
+pub const BLOCK_LEN: usize = 16;
+
+pub type Block = [u8; BLOCK_LEN];
+
+
+/// AES-128 in OCB mode (OCB3) with 128-bit tags and 96 bit nonces, as
+/// described in [RFC 7253].
+///
+/// [RFC 7253]: https://tools.ietf.org/html/rfc7253
+pub static AES_128_OCB: aead::Algorithm = aead::Algorithm {
+    key_len: AES_128_KEY_LEN,
+    nonce_len: aead::NONCE_LEN,
+    name: "AES_128_OCB",
+    init: aes_ocb_init,
+    seal: aes_ocb_seal,
+    open: aes_ocb_open,
+};
+
+/// AES-256 in OCB mode (OCB3) with 128-bit tags and 96 bit nonces, as
+/// described in [RFC 7253].
+///
+/// [RFC 7253]: https://tools.ietf.org/html/rfc7253
+pub static AES_256_OCB: aead::Algorithm = aead::Algorithm {
+    key_len: AES_256_KEY_LEN,
+    nonce_len: aead::NONCE_LEN,
+    name: "AES_256_OCB",
+    init: aes_ocb_init,
+    seal: aes_ocb_seal,
+    open: aes_ocb_open,
+};
+
+// Keep this in sync with `AES_KEY` in aes.h.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+pub struct AES_KEY {
+    pub rd_key: [u32; 4 * (AES_MAX_ROUNDS + 1)],
+    pub rounds: u32, // `unsigned` in C.
+}
+
+// Keep this in sync with `AES_MAXNR` in aes.h.
+pub const AES_MAX_ROUNDS: usize = 14;
+
+// OCB needs both the forward cipher and the inverse cipher. The decryption key
+// schedule isn't compatible with `GFp_AES_encrypt`, so both are kept.
+#[repr(C)]
+struct Key {
+    encrypt: AES_KEY,
+    decrypt: AES_KEY,
+}
+
+fn aes_ocb_init(ctx_buf: &mut [u8], key: &[u8])
+                -> Result<(), error::Unspecified> {
+    debug_assert!(ctx_buf.len() >= core::mem::size_of::<Key>());
+    let bits = (key.len() * 8) as u32;
+    let ocb_key = unsafe { &mut *(ctx_buf.as_mut_ptr() as *mut Key) };
+    if unsafe {
+        GFp_AES_set_encrypt_key(key.as_ptr(), bits, &mut ocb_key.encrypt)
+    } != 0 {
+        return Err(error::Unspecified);
+    }
+    if unsafe {
+        GFp_AES_set_decrypt_key(key.as_ptr(), bits, &mut ocb_key.decrypt)
+    } != 0 {
+        return Err(error::Unspecified);
+    }
+    Ok(())
+}
+
+fn aes_ocb_seal(ctx: &[u64; aead::KEY_CTX_BUF_ELEMS],
+                nonce: &[u8], in_out: &mut [u8],
+                tag_out: &mut [u8; aead::TAG_LEN], ad: &[&[u8]])
+                -> Result<(), error::Unspecified> {
+    let nonce = try!(slice_as_array_ref!(nonce, aead::NONCE_LEN));
+    let key = ctx_as_key(ctx);
+    let l = LValues::new(key);
+    let mut offset = initial_offset(key, nonce);
+    let mut checksum = [0u8; BLOCK_LEN];
+
+    let mut i = 0;
+    for block in in_out.chunks_mut(BLOCK_LEN) {
+        if block.len() == BLOCK_LEN {
+            i += 1;
+            xor_assign(&mut offset, &l.l(i));
+            xor_assign(&mut checksum, block);
+            xor_assign(block, &offset);
+            encrypt_in_place(key, block);
+            xor_assign(block, &offset);
+        } else {
+            xor_assign(&mut offset, &l.l_star);
+            let pad = encrypt_block(key, &offset);
+            pad_checksum(&mut checksum, block);
+            xor_assign(block, &pad[..block.len()]);
+        }
+    }
+
+    finish_tag(key, &l, &offset, &checksum, ad, tag_out);
+    Ok(())
+}
+
+fn aes_ocb_open(ctx: &[u64; aead::KEY_CTX_BUF_ELEMS],
+                nonce: &[u8], in_out: &mut [u8],
+                in_prefix_len: usize, tag_out: &mut [u8; aead::TAG_LEN],
+                ad: &[&[u8]]) -> Result<(), error::Unspecified> {
+    let nonce = try!(slice_as_array_ref!(nonce, aead::NONCE_LEN));
+    let key = ctx_as_key(ctx);
+    let l = LValues::new(key);
+    let mut offset = initial_offset(key, nonce);
+    let mut checksum = [0u8; BLOCK_LEN];
+
+    let ciphertext_len = in_out.len() - in_prefix_len;
+    let mut i = 0;
+    let mut pos = 0;
+    while pos < ciphertext_len {
+        let block_len = core::cmp::min(BLOCK_LEN, ciphertext_len - pos);
+        let mut block = [0u8; BLOCK_LEN];
+        block[..block_len].copy_from_slice(
+            &in_out[(in_prefix_len + pos)..(in_prefix_len + pos + block_len)]);
+        if block_len == BLOCK_LEN {
+            i += 1;
+            xor_assign(&mut offset, &l.l(i));
+            xor_assign(&mut block, &offset);
+            unsafe {
+                GFp_AES_decrypt(block.as_ptr(), block.as_mut_ptr(),
+                                &key.decrypt);
+            }
+            xor_assign(&mut block, &offset);
+            xor_assign(&mut checksum, &block);
+        } else {
+            xor_assign(&mut offset, &l.l_star);
+            let pad = encrypt_block(key, &offset);
+            xor_assign(&mut block[..block_len], &pad[..block_len]);
+            pad_checksum(&mut checksum, &block[..block_len]);
+        }
+        in_out[pos..(pos + block_len)].copy_from_slice(&block[..block_len]);
+        pos += block_len;
+    }
+
+    finish_tag(key, &l, &offset, &checksum, ad, tag_out);
+    Ok(())
+}
+
+fn ctx_as_key(ctx: &[u64; aead::KEY_CTX_BUF_ELEMS]) -> &Key {
+    debug_assert!(core::mem::size_of_val(ctx) >= core::mem::size_of::<Key>());
+    unsafe { &*(ctx.as_ptr() as *const Key) }
+}
+
+/// L_*, L_$, and L_0 from RFC 7253 Section 4.1. L_i for i > 0 is computed on
+/// demand.
+struct LValues {
+    l_star: Block,
+    l_dollar: Block,
+    l_0: Block,
+}
+
+impl LValues {
+    fn new(key: &Key) -> LValues {
+        let l_star = encrypt_block(key, &[0u8; BLOCK_LEN]);
+        let l_dollar = double(&l_star);
+        let l_0 = double(&l_dollar);
+        LValues { l_star: l_star, l_dollar: l_dollar, l_0: l_0 }
+    }
+
+    /// Returns L_{ntz(i)}.
+    fn l(&self, i: usize) -> Block {
+        let mut result = self.l_0;
+        for _ in 0..i.trailing_zeros() {
+            result = double(&result);
+        }
+        result
+    }
+}
+
+// Nonce-dependent and per-encryption variables from RFC 7253 Section 4.2,
+// for TAGLEN = 128 and a 96-bit nonce.
+fn initial_offset(key: &Key, nonce: &[u8; aead::NONCE_LEN]) -> Block {
+    let mut full_nonce = [0u8; BLOCK_LEN];
+    full_nonce[BLOCK_LEN - aead::NONCE_LEN - 1] = 1;
+    full_nonce[(BLOCK_LEN - aead::NONCE_LEN)..].copy_from_slice(nonce);
+
+    let bottom = (full_nonce[BLOCK_LEN - 1] & 0x3f) as usize;
+    full_nonce[BLOCK_LEN - 1] &= 0xc0;
+    let ktop = encrypt_block(key, &full_nonce);
+
+    // Stretch = Ktop || (Ktop[1..64] xor Ktop[9..72])
+    let mut stretch = [0u8; BLOCK_LEN + 8];
+    stretch[..BLOCK_LEN].copy_from_slice(&ktop);
+    for i in 0..8 {
+        stretch[BLOCK_LEN + i] = ktop[i] ^ ktop[i + 1];
+    }
+
+    // Offset_0 = Stretch[1+bottom..128+bottom]
+    let byte_shift = bottom / 8;
+    let bit_shift = bottom % 8;
+    let mut offset = [0u8; BLOCK_LEN];
+    for i in 0..BLOCK_LEN {
+        let hi = stretch[i + byte_shift] << bit_shift;
+        let lo = if bit_shift == 0 {
+            0
+        } else {
+            stretch[i + byte_shift + 1] >> (8 - bit_shift)
+        };
+        offset[i] = hi | lo;
+    }
+    offset
+}
+
+// Tag = ENCIPHER(K, Checksum_* xor Offset_* xor L_$) xor HASH(K,A)
+fn finish_tag(key: &Key, l: &LValues, offset: &Block, checksum: &Block,
+              ad: &[&[u8]], tag_out: &mut [u8; aead::TAG_LEN]) {
+    let mut tag = *checksum;
+    xor_assign(&mut tag, offset);
+    xor_assign(&mut tag, &l.l_dollar);
+    encrypt_in_place(key, &mut tag);
+    xor_assign(&mut tag, &hash(key, l, ad));
+    tag_out.copy_from_slice(&tag);
+}
+
+// HASH(K, A) from RFC 7253 Section 4.1.
+fn hash(key: &Key, l: &LValues, ad: &[&[u8]]) -> Block {
+    let mut sum = [0u8; BLOCK_LEN];
+    let mut offset = [0u8; BLOCK_LEN];
+
+    let mut i = 0;
+    aead::for_each_ad_block(ad, |block| {
+        let mut cipher_input = [0u8; BLOCK_LEN];
+        if block.len() == BLOCK_LEN {
+            i += 1;
+            xor_assign(&mut offset, &l.l(i));
+            cipher_input.copy_from_slice(block);
+        } else {
+            xor_assign(&mut offset, &l.l_star);
+            cipher_input[..block.len()].copy_from_slice(block);
+            cipher_input[block.len()] = 0x80;
+        }
+        xor_assign(&mut cipher_input, &offset);
+        encrypt_in_place(key, &mut cipher_input);
+        xor_assign(&mut sum, &cipher_input);
+    });
+    sum
+}
+
+// Checksum_* = Checksum_m xor (P_* || 1 || zeros(127-bitlen(P_*)))
+fn pad_checksum(checksum: &mut Block, partial: &[u8]) {
+    debug_assert!(partial.len() < BLOCK_LEN);
+    xor_assign(&mut checksum[..partial.len()], partial);
+    checksum[partial.len()] ^= 0x80;
+}
+
+// double(S) from RFC 7253 Section 2. This is also the doubling used to derive
+// CMAC subkeys.
+pub fn double(s: &Block) -> Block {
+    let mut result = [0u8; BLOCK_LEN];
+    for i in 0..(BLOCK_LEN - 1) {
+        result[i] = (s[i] << 1) | (s[i + 1] >> 7);
+    }
+    result[BLOCK_LEN - 1] = (s[BLOCK_LEN - 1] << 1) ^
+                            (0x87 & 0u8.wrapping_sub(s[0] >> 7));
+    result
+}
+
+fn encrypt_block(key: &Key, block: &Block) -> Block {
+    let mut result = *block;
+    encrypt_in_place(key, &mut result);
+    result
+}
+
+fn encrypt_in_place(key: &Key, block: &mut [u8]) {
+    debug_assert_eq!(block.len(), BLOCK_LEN);
+    unsafe {
+        GFp_AES_encrypt(block.as_ptr(), block.as_mut_ptr(), &key.encrypt);
+    }
+}
+
+fn xor_assign(a: &mut [u8], b: &[u8]) {
+    debug_assert_eq!(a.len(), b.len());
+    for (a, b) in a.iter_mut().zip(b.iter()) {
+        *a ^= *b;
+    }
+}
+
+extern {
+    pub fn GFp_AES_set_encrypt_key(key: *const u8, bits: u32,
+                                   aes_key: *mut AES_KEY) -> c::int;
+    pub fn GFp_AES_encrypt(in_: *const u8, out: *mut u8,
+                           key: *const AES_KEY);
+    pub fn GFp_AES_set_decrypt_key(key: *const u8, bits: u32,
+                                   aes_key: *mut AES_KEY) -> c::int;
+    pub fn GFp_AES_decrypt(in_: *const u8, out: *mut u8,
+                           key: *const AES_KEY);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {aead, test};
+    use core;
+    use super::{AES_KEY, GFp_AES_decrypt, GFp_AES_set_decrypt_key, Key};
+
+    #[test]
+    pub fn test_aes_ocb_128() {
+        aead::tests::test_aead(&aead::AES_128_OCB,
+                               "src/aead/aes_128_ocb_tests.txt");
+    }
+
+    #[test]
+    pub fn test_aes_ocb_256() {
+        aead::tests::test_aead(&aead::AES_256_OCB,
+                               "src/aead/aes_256_ocb_tests.txt");
+    }
+
+    #[test]
+    fn test_key_fits_in_ctx_buf() {
+        assert!(core::mem::size_of::<Key>() <=
+                aead::KEY_CTX_BUF_ELEMS * core::mem::size_of::<u64>());
+    }
+
+    // Test vectors from BoringSSL, reusing the AES tests for the forward
+    // cipher.
+    #[test]
+    pub fn test_aes_decrypt() {
+        test::from_file("src/aead/aes_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let key = test_case.consume_bytes("Key");
+            let input = test_case.consume_bytes("Input");
+            let output = test_case.consume_bytes("Output");
+
+            let mut aes_key = AES_KEY {
+                rd_key: [0u32; 60],
+                rounds: 0,
+            };
+            let bits = (key.len() * 8) as u32;
+            if unsafe {
+                GFp_AES_set_decrypt_key(key.as_ptr(), bits, &mut aes_key)
+            } != 0 {
+                // The AES tests include key sizes that OCB doesn't support.
+                assert_eq!(key.len(), 192 / 8);
+                return Ok(());
+            }
+
+            let mut actual = vec![0u8; input.len()];
+            unsafe {
+                GFp_AES_decrypt(output.as_ptr(), actual.as_mut_ptr(),
+                                &aes_key);
+            }
+            assert_eq!(actual, input);
+
+            Ok(())
+        });
+    }
+}
@@ -17,9 +17,9 @@
 use {aead, c, error, polyfill};
 use core;
 
-const CHACHA20_KEY_LEN: usize = 256 / 8;
-const POLY1305_STATE_LEN: usize = 256;
-const POLY1305_KEY_LEN: usize = 32;
+pub const CHACHA20_KEY_LEN: usize = 256 / 8;
+pub const POLY1305_STATE_LEN: usize = 256;
+pub const POLY1305_KEY_LEN: usize = 32;
 
 
 /// ChaCha20-Poly1305 as described in [RFC 7539].
@@ -29,6 +29,8 @@ const POLY1305_KEY_LEN: usize = 32;
 /// [RFC 7539]: https://tools.ietf.org/html/rfc7539
 pub static CHACHA20_POLY1305: aead::Algorithm = aead::Algorithm {
     key_len: CHACHA20_KEY_LEN,
+    nonce_len: aead::NONCE_LEN,
+    name: "CHACHA20_POLY1305",
     init: chacha20_poly1305_init,
     seal: chacha20_poly1305_seal,
     open: chacha20_poly1305_open,
@@ -41,11 +43,12 @@ pub fn chacha20_poly1305_init(ctx_buf: &mut [u8], key: &[u8])
     Ok(())
 }
 
-fn chacha20_poly1305_seal(ctx: &[u64; aead::KEY_CTX_BUF_ELEMS],
-                          nonce: &[u8; aead::NONCE_LEN], in_out: &mut [u8],
-                          tag_out: &mut [u8; aead::TAG_LEN], ad: &[u8])
+pub fn chacha20_poly1305_seal(ctx: &[u64; aead::KEY_CTX_BUF_ELEMS],
+                              nonce: &[u8], in_out: &mut [u8],
+                          tag_out: &mut [u8; aead::TAG_LEN], ad: &[&[u8]])
                           -> Result<(), error::Unspecified> {
-    let chacha20_key = try!(ctx_as_key(ctx));
+    let nonce = try!(slice_as_array_ref!(nonce, aead::NONCE_LEN));
+    let chacha20_key = &ctx_as_key(ctx);
     let mut counter = make_counter(1, nonce);
     debug_assert!(core::mem::align_of_val(chacha20_key) >= 4);
     debug_assert!(core::mem::align_of_val(&counter) >= 4);
@@ -58,12 +61,13 @@ fn chacha20_poly1305_seal(ctx: &[u64; aead::KEY_CTX_BUF_ELEMS],
     Ok(())
 }
 
-fn chacha20_poly1305_open(ctx: &[u64; aead::KEY_CTX_BUF_ELEMS],
-                          nonce: &[u8; aead::NONCE_LEN], in_out: &mut [u8],
+pub fn chacha20_poly1305_open(ctx: &[u64; aead::KEY_CTX_BUF_ELEMS],
+                          nonce: &[u8], in_out: &mut [u8],
                           in_prefix_len: usize,
-                          tag_out: &mut [u8; aead::TAG_LEN], ad: &[u8])
+                          tag_out: &mut [u8; aead::TAG_LEN], ad: &[&[u8]])
                           -> Result<(), error::Unspecified> {
-    let chacha20_key = try!(ctx_as_key(ctx));
+    let nonce = try!(slice_as_array_ref!(nonce, aead::NONCE_LEN));
+    let chacha20_key = &ctx_as_key(ctx);
     let mut counter = make_counter(0, nonce);
     {
         let ciphertext = &in_out[in_prefix_len..];
@@ -97,28 +101,52 @@ fn chacha20_poly1305_open(ctx: &[u64; aead::KEY_CTX_BUF_ELEMS],
     Ok(())
 }
 
+// The key is stored in the context buffer as bytes, in the order
+// `chacha20_poly1305_init` copied them.
 fn ctx_as_key(ctx: &[u64; aead::KEY_CTX_BUF_ELEMS])
-              -> Result<&[u32; CHACHA20_KEY_LEN / 4], error::Unspecified> {
-    slice_as_array_ref!(
-        &polyfill::slice::u64_as_u32(ctx)[..(CHACHA20_KEY_LEN / 4)],
-        CHACHA20_KEY_LEN / 4)
+              -> [u32; CHACHA20_KEY_LEN / 4] {
+    chacha20_key_from_bytes(
+        &polyfill::slice::u64_as_u8(ctx)[..CHACHA20_KEY_LEN])
+}
+
+/// Converts the `CHACHA20_KEY_LEN` bytes of `bytes` to the key words, which
+/// are little-endian encoded regardless of the target's byte order.
+pub fn chacha20_key_from_bytes(bytes: &[u8]) -> [u32; CHACHA20_KEY_LEN / 4] {
+    let mut key = [0u32; CHACHA20_KEY_LEN / 4];
+    for (k, chunk) in key.iter_mut().zip(bytes.chunks(4)) {
+        *k = polyfill::slice::u32_from_le_u8(
+            slice_as_array_ref!(chunk, 4).unwrap());
+    }
+    key
 }
 
 #[inline]
-fn make_counter(counter: u32, nonce: &[u8; aead::NONCE_LEN]) -> [u32; 4] {
+pub fn make_counter(counter: u32, nonce: &[u8; aead::NONCE_LEN]) -> [u32; 4] {
     use polyfill::slice::u32_from_le_u8;
-    [counter.to_le(),
+    [counter,
      u32_from_le_u8(slice_as_array_ref!(&nonce[0..4], 4).unwrap()),
      u32_from_le_u8(slice_as_array_ref!(&nonce[4..8], 4).unwrap()),
      u32_from_le_u8(slice_as_array_ref!(&nonce[8..12], 4).unwrap())]
 }
 
+/// Encrypts or decrypts `in_out` in place with ChaCha20, starting at the
+/// block given by `counter[0]`.
+pub fn chacha20_xor_in_place(key: &[u32; CHACHA20_KEY_LEN / 4],
+                             counter: &[u32; 4], in_out: &mut [u8]) {
+    debug_assert!(core::mem::align_of_val(key) >= 4);
+    debug_assert!(core::mem::align_of_val(counter) >= 4);
+    unsafe {
+        GFp_ChaCha20_ctr32(in_out.as_mut_ptr(), in_out.as_ptr(), in_out.len(),
+                           key, counter);
+    }
+}
+
 type UpdateFn = fn(state: &mut [u8; POLY1305_STATE_LEN], ad: &[u8],
                    ciphertext: &[u8]);
 
 fn aead_poly1305(tag_out: &mut [u8; aead::TAG_LEN],
                  chacha20_key: &[u32; CHACHA20_KEY_LEN / 4],
-                 counter: &[u32; 4], ad: &[u8], ciphertext: &[u8]) {
+                 counter: &[u32; 4], ad: &[&[u8]], ciphertext: &[u8]) {
     debug_assert_eq!(counter[0], 0);
     let mut poly1305_key = [0u8; POLY1305_KEY_LEN];
     debug_assert!(core::mem::align_of_val(chacha20_key) >= 4);
@@ -129,9 +157,13 @@ fn aead_poly1305(tag_out: &mut [u8; aead::TAG_LEN],
     }
     let mut ctx = [0u8; POLY1305_STATE_LEN];
     poly1305_init(&mut ctx, &poly1305_key);
-    poly1305_update_padded_16(&mut ctx, ad);
+    let ad_len = aead::ad_len(ad);
+    for part in ad {
+        poly1305_update(&mut ctx, part);
+    }
+    poly1305_pad_16(&mut ctx, ad_len);
     poly1305_update_padded_16(&mut ctx, ciphertext);
-    poly1305_update_length(&mut ctx, ad.len());
+    poly1305_update_length(&mut ctx, ad_len);
     poly1305_update_length(&mut ctx, ciphertext.len());
     poly1305_finish(&mut ctx, tag_out);
 }
@@ -140,16 +172,23 @@ fn aead_poly1305(tag_out: &mut [u8; aead::TAG_LEN],
 fn poly1305_update_padded_16(state: &mut [u8; POLY1305_STATE_LEN],
                                 data: &[u8]) {
     poly1305_update(state, data);
-    if data.len() % 16 != 0 {
+    poly1305_pad_16(state, data.len());
+}
+
+/// Pads the input to the Poly1305 context |state| with zeros to a multiple of
+/// 16 bytes, after |len| bytes have been input.
+#[inline]
+pub fn poly1305_pad_16(state: &mut [u8; POLY1305_STATE_LEN], len: usize) {
+    if len % 16 != 0 {
         static PADDING: [u8; 16] = [0u8; 16];
-        poly1305_update(state, &PADDING[..PADDING.len() - (data.len() % 16)])
+        poly1305_update(state, &PADDING[..PADDING.len() - (len % 16)])
     }
 }
 
 /// Updates the Poly1305 context |ctx| with the 64-bit little-endian encoded
 /// length value |len|.
 #[inline]
-fn poly1305_update_length(ctx: &mut [u8; POLY1305_STATE_LEN], len: usize) {
+pub fn poly1305_update_length(ctx: &mut [u8; POLY1305_STATE_LEN], len: usize) {
     let mut j = len;
     let mut length_bytes = [0u8; 8];
     for b in &mut length_bytes {
@@ -161,8 +200,8 @@ fn poly1305_update_length(ctx: &mut [u8; POLY1305_STATE_LEN], len: usize) {
 
 
 #[inline(always)]
-fn poly1305_init(state: &mut [u8; POLY1305_STATE_LEN],
-                 key: &[u8; POLY1305_KEY_LEN]) {
+pub fn poly1305_init(state: &mut [u8; POLY1305_STATE_LEN],
+                     key: &[u8; POLY1305_KEY_LEN]) {
     unsafe {
         GFp_poly1305_init(state, key)
     }
@@ -173,15 +212,15 @@ fn poly1305_init(state: &mut [u8; POLY1305_STATE_LEN],
 // can't in Rust yet. Where does this alignment requirement come from?
 // TODO: address this.
 #[inline(always)]
-fn poly1305_finish(state: &mut [u8; POLY1305_STATE_LEN],
-                   tag_out: &mut [u8; aead::TAG_LEN]) {
+pub fn poly1305_finish(state: &mut [u8; POLY1305_STATE_LEN],
+                       tag_out: &mut [u8; aead::TAG_LEN]) {
     unsafe {
         GFp_poly1305_finish(state, tag_out)
     }
 }
 
 #[inline(always)]
-fn poly1305_update(state: &mut [u8; POLY1305_STATE_LEN], in_: &[u8]) {
+pub fn poly1305_update(state: &mut [u8; POLY1305_STATE_LEN], in_: &[u8]) {
     unsafe {
         GFp_poly1305_update(state, in_.as_ptr(), in_.len())
     }
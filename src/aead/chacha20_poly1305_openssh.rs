@@ -0,0 +1,268 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The [chacha20-poly1305@openssh.com] AEAD-ish construct.
+//!
+//! This should only be used by SSH implementations. It has a similar, but
+//! different API from `ring::aead` because the construct cannot use the same
+//! API as `ring::aead` due to the way the construct handles the encrypted
+//! packet length.
+//!
+//! The 512-bit key is two ChaCha20 keys: the first 256 bits are K_2, which
+//! encrypts the packet payload and derives the Poly1305 key, and the last 256
+//! bits are K_1, which encrypts the four-byte packet length. The nonce is the
+//! SSH packet sequence number. The tag is computed over the encrypted packet
+//! length and the encrypted payload.
+//!
+//! [chacha20-poly1305@openssh.com]:
+//!    http://cvsweb.openbsd.org/cgi-bin/cvsweb/~checkout~/src/usr.bin/ssh/PROTOCOL.chacha20poly1305?annotate=HEAD
+
+use {audit, constant_time, error, init, polyfill};
+use secret::Zeroize;
+use super::chacha20_poly1305::{CHACHA20_KEY_LEN, POLY1305_KEY_LEN,
+                               POLY1305_STATE_LEN, chacha20_key_from_bytes,
+                               chacha20_xor_in_place, poly1305_finish,
+                               poly1305_init, poly1305_update};
+
+/// The length of key.
+pub const KEY_LEN: usize = 2 * CHACHA20_KEY_LEN;
+
+/// The length in bytes of the `packet_length` field in a SSH packet.
+pub const PACKET_LENGTH_LEN: usize = 4; // 32 bits
+
+/// The length in bytes of an authentication tag.
+pub const TAG_LEN: usize = super::TAG_LEN;
+
+/// A key for sealing packets.
+pub struct SealingKey {
+    key: Key,
+}
+
+impl SealingKey {
+    /// Constructs a new `SealingKey`. `key_material` must be exactly
+    /// `KEY_LEN` bytes long.
+    pub fn new(key_material: &[u8])
+               -> Result<SealingKey, error::KeyRejected> {
+        Ok(SealingKey { key: try!(Key::new(key_material)) })
+    }
+
+    /// Seals (encrypts and signs) a packet.
+    ///
+    /// On input, `in_out` must contain the unencrypted packet, starting with
+    /// the `packet_length` field. On output, `in_out` contains the encrypted
+    /// packet and `tag_out` contains the authentication tag.
+    ///
+    /// `in_out.len()` must be at least `PACKET_LENGTH_LEN`.
+    pub fn seal_in_place(&self, sequence_number: u32, in_out: &mut [u8],
+                         tag_out: &mut [u8; TAG_LEN])
+                         -> Result<(), error::Unspecified> {
+        if in_out.len() < PACKET_LENGTH_LEN {
+            return Err(error::Unspecified);
+        }
+        try!(super::check_per_nonce_max_bytes(in_out.len()));
+        {
+            let (packet_length, payload) =
+                in_out.split_at_mut(PACKET_LENGTH_LEN);
+            chacha20_xor_in_place(&self.key.k_1,
+                                  &make_counter(0, sequence_number),
+                                  packet_length);
+            chacha20_xor_in_place(&self.key.k_2,
+                                  &make_counter(1, sequence_number), payload);
+        }
+        *tag_out = self.key.tag(sequence_number, in_out);
+        Ok(())
+    }
+}
+
+/// A key for opening packets.
+pub struct OpeningKey {
+    key: Key,
+}
+
+impl OpeningKey {
+    /// Constructs a new `OpeningKey`. `key_material` must be exactly
+    /// `KEY_LEN` bytes long.
+    pub fn new(key_material: &[u8])
+               -> Result<OpeningKey, error::KeyRejected> {
+        Ok(OpeningKey { key: try!(Key::new(key_material)) })
+    }
+
+    /// Returns the decrypted, but unauthenticated, packet length.
+    ///
+    /// Importantly, the result won't be authenticated until `open_in_place`
+    /// is called.
+    pub fn decrypt_packet_length(&self, sequence_number: u32,
+                                 encrypted_packet_length:
+                                     &[u8; PACKET_LENGTH_LEN])
+                                 -> [u8; PACKET_LENGTH_LEN] {
+        let mut packet_length = *encrypted_packet_length;
+        chacha20_xor_in_place(&self.key.k_1, &make_counter(0, sequence_number),
+                              &mut packet_length);
+        packet_length
+    }
+
+    /// Opens (authenticates and decrypts) a packet.
+    ///
+    /// `in_out` must contain the encrypted packet, starting with the
+    /// encrypted `packet_length` field, and `received_tag` must be the tag
+    /// that was received with it. On success, the decrypted packet, without
+    /// the `packet_length` field, is returned; `in_out[..PACKET_LENGTH_LEN]`
+    /// is left encrypted.
+    pub fn open_in_place<'a>(&self, sequence_number: u32,
+                             in_out: &'a mut [u8],
+                             received_tag: &[u8; TAG_LEN])
+                             -> Result<&'a mut [u8], error::Unspecified> {
+        if in_out.len() < PACKET_LENGTH_LEN {
+            return Err(error::Unspecified);
+        }
+        try!(super::check_per_nonce_max_bytes(in_out.len()));
+        let calculated_tag = self.key.tag(sequence_number, in_out);
+        if constant_time::verify_slices_are_equal(&calculated_tag,
+                                                  received_tag).is_err() {
+            audit::notify(audit::EventKind::DecryptionFailed,
+                          "CHACHA20_POLY1305_OPENSSH");
+            return Err(error::Unspecified);
+        }
+        let payload = &mut in_out[PACKET_LENGTH_LEN..];
+        chacha20_xor_in_place(&self.key.k_2, &make_counter(1, sequence_number),
+                              payload);
+        Ok(payload)
+    }
+}
+
+struct Key {
+    k_1: [u32; CHACHA20_KEY_LEN / 4],
+    k_2: [u32; CHACHA20_KEY_LEN / 4],
+}
+
+impl Key {
+    fn new(key_material: &[u8]) -> Result<Key, error::KeyRejected> {
+        init::init_once();
+        try!(error::KeyRejected::check_len(key_material.len(), KEY_LEN));
+        let (k_2, k_1) = key_material.split_at(CHACHA20_KEY_LEN);
+        Ok(Key {
+            k_1: chacha20_key_from_bytes(k_1),
+            k_2: chacha20_key_from_bytes(k_2),
+        })
+    }
+
+    // The Poly1305 key is the first 32 bytes of the K_2 key stream, and the
+    // tag is computed over the whole encrypted packet.
+    fn tag(&self, sequence_number: u32, ciphertext: &[u8]) -> [u8; TAG_LEN] {
+        let mut poly1305_key = [0u8; POLY1305_KEY_LEN];
+        chacha20_xor_in_place(&self.k_2, &make_counter(0, sequence_number),
+                              &mut poly1305_key);
+        let mut ctx = [0u8; POLY1305_STATE_LEN];
+        poly1305_init(&mut ctx, &poly1305_key);
+        poly1305_update(&mut ctx, ciphertext);
+        let mut tag = [0u8; TAG_LEN];
+        poly1305_finish(&mut ctx, &mut tag);
+        tag
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        self.k_1[..].zeroize();
+        self.k_2[..].zeroize();
+    }
+}
+
+// The nonce is the sequence number as a 64-bit big-endian value. This uses
+// the original ChaCha20 layout, with a 64-bit block counter followed by a
+// 64-bit nonce, so the high half of the counter and the high half of the
+// nonce are zero.
+fn make_counter(block: u32, sequence_number: u32) -> [u32; 4] {
+    [block, 0, 0,
+     polyfill::slice::u32_from_le_u8(
+         &polyfill::slice::be_u8_from_u32(sequence_number))]
+}
+
+#[cfg(test)]
+mod tests {
+    use {error, test};
+    use super::*;
+
+    #[test]
+    fn test_chacha20_poly1305_openssh() {
+        test::from_file("src/aead/chacha20_poly1305_openssh_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+            let key = test_case.consume_bytes("KEY");
+            let sequence_number =
+                test_case.consume_usize("SEQUENCE_NUMBER") as u32;
+            let plaintext = test_case.consume_bytes("IN");
+            let ciphertext = test_case.consume_bytes("CT");
+            let expected_tag = test_case.consume_bytes("TAG");
+
+            let sealing_key = SealingKey::new(&key).unwrap();
+            let mut in_out = plaintext.clone();
+            let mut tag = [0u8; TAG_LEN];
+            try!(sealing_key.seal_in_place(sequence_number, &mut in_out,
+                                           &mut tag));
+            assert_eq!(in_out, ciphertext);
+            assert_eq!(&tag[..], &expected_tag[..]);
+
+            let opening_key = OpeningKey::new(&key).unwrap();
+            let encrypted_packet_length =
+                slice_as_array_ref!(&ciphertext[..PACKET_LENGTH_LEN],
+                                    PACKET_LENGTH_LEN).unwrap();
+            assert_eq!(&opening_key.decrypt_packet_length(
+                           sequence_number, encrypted_packet_length)[..],
+                       &plaintext[..PACKET_LENGTH_LEN]);
+            {
+                let mut in_out = ciphertext.clone();
+                let payload = try!(opening_key.open_in_place(sequence_number,
+                                                             &mut in_out,
+                                                             &tag));
+                assert_eq!(&payload[..], &plaintext[PACKET_LENGTH_LEN..]);
+            }
+
+            // Any modification of the packet, including of the encrypted
+            // packet length, or of the tag, or of the sequence number, is
+            // detected.
+            for i in 0..ciphertext.len() {
+                let mut in_out = ciphertext.clone();
+                in_out[i] ^= 1;
+                assert!(opening_key.open_in_place(sequence_number, &mut in_out,
+                                                  &tag).is_err());
+            }
+            let mut bad_tag = tag;
+            bad_tag[TAG_LEN - 1] ^= 0x80;
+            let mut in_out = ciphertext.clone();
+            assert!(opening_key.open_in_place(sequence_number, &mut in_out,
+                                              &bad_tag).is_err());
+            let mut in_out = ciphertext.clone();
+            assert!(opening_key.open_in_place(sequence_number.wrapping_add(1),
+                                              &mut in_out, &tag).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_chacha20_poly1305_openssh_bad_lengths() {
+        assert!(SealingKey::new(&[0u8; KEY_LEN - 1]).is_err());
+        assert!(SealingKey::new(&[0u8; KEY_LEN + 1]).is_err());
+        assert!(OpeningKey::new(&[0u8; KEY_LEN - 1]).is_err());
+        assert!(OpeningKey::new(&[0u8; KEY_LEN + 1]).is_err());
+
+        let sealing_key = SealingKey::new(&[0u8; KEY_LEN]).unwrap();
+        let mut tag = [0u8; TAG_LEN];
+        assert_eq!(sealing_key.seal_in_place(0, &mut [0u8; 3], &mut tag),
+                   Err(error::Unspecified));
+        let opening_key = OpeningKey::new(&[0u8; KEY_LEN]).unwrap();
+        assert!(opening_key.open_in_place(0, &mut [0u8; 3], &tag).is_err());
+    }
+}
@@ -0,0 +1,389 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! A record layer for datagram protocols, with sequence-number nonces,
+//! periodic rekeying, and replay protection.
+//!
+//! A `SecureChannel` is one endpoint of a channel. It is constructed from two
+//! secrets that both endpoints share after their handshake, one for each
+//! direction; what one endpoint sends with, the other receives with. Each
+//! record is sealed with the next sequence number, starting at zero, which
+//! the application sends along with the record, e.g. in its header, so that
+//! records can be lost or reordered in transit.
+//!
+//! The records are divided into epochs of `rekey_interval` records. The key
+//! and IV of each epoch are derived from the epoch's secret with HKDF-SHA256,
+//! and the secret of the next epoch is derived from it in the same way, so
+//! the keys of earlier epochs can't be recovered from the current state. The
+//! nonce of a record is the epoch's IV XORed with the big-endian sequence
+//! number, as in TLS 1.3.
+//!
+//! The receiver accepts records of the epoch before the current one, the
+//! current one, and the next one; when a record of the next epoch is
+//! authentic, the receiver moves to it and forgets the keys of the oldest
+//! epoch. If every record of an epoch is lost, the channel can't continue, so
+//! `rekey_interval` should be much larger than the number of records that
+//! may be lost in a row. A record whose sequence number has been opened
+//! before, or is 64 or more below the highest one opened, is rejected.
+//!
+//! Any `ring::aead` algorithm with 96-bit nonces can be used.
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::aead;
+//!
+//! # fn main() { foo().unwrap() }
+//! # fn foo() -> Result<(), ring::error::Unspecified> {
+//! let client_to_server = [1u8; aead::channel::SECRET_LEN];
+//! let server_to_client = [2u8; aead::channel::SECRET_LEN];
+//! let mut client = try!(aead::SecureChannel::new(
+//!     &aead::CHACHA20_POLY1305, &client_to_server, &server_to_client,
+//!     1 << 20));
+//! let mut server = try!(aead::SecureChannel::new(
+//!     &aead::CHACHA20_POLY1305, &server_to_client, &client_to_server,
+//!     1 << 20));
+//!
+//! let mut in_out = b"hello".to_vec();
+//! in_out.extend_from_slice(&[0u8; aead::MAX_OVERHEAD_LEN]);
+//! let (sequence_number, len) = try!(client.seal_in_place(
+//!     &mut in_out, aead::MAX_OVERHEAD_LEN, b""));
+//!
+//! let plaintext_len = try!(server.open_in_place(
+//!     sequence_number, 0, &mut in_out[..len], b""));
+//! assert_eq!(&in_out[..plaintext_len], b"hello");
+//!
+//! // The same record can't be opened twice.
+//! assert!(server.open_in_place(sequence_number, 0, &mut in_out[..len], b"")
+//!               .is_err());
+//! # Ok(())
+//! # }
+//! ```
+
+use {aead, digest, error, hkdf, secret};
+use core;
+
+/// The length of the secrets that a `SecureChannel` is constructed from.
+pub const SECRET_LEN: usize = 32;
+
+/// One endpoint of a secure channel.
+pub struct SecureChannel {
+    algorithm: &'static aead::Algorithm,
+    rekey_interval: u64,
+    send: SendState,
+    receive: ReceiveState,
+}
+
+impl SecureChannel {
+    /// Constructs an endpoint that seals records with keys derived from
+    /// `send_secret` and opens records with keys derived from
+    /// `receive_secret`. Both must be `SECRET_LEN` bytes long, and must be
+    /// secret, e.g. derived from the shared secret of a handshake.
+    ///
+    /// The keys are changed every `rekey_interval` records, which must not
+    /// be zero. `algorithm.nonce_len()` must be 12.
+    pub fn new(algorithm: &'static aead::Algorithm, send_secret: &[u8],
+               receive_secret: &[u8], rekey_interval: u64)
+               -> Result<SecureChannel, error::Unspecified> {
+        if algorithm.nonce_len() != aead::NONCE_LEN || rekey_interval == 0 {
+            return Err(error::Unspecified);
+        }
+        try!(error::KeyRejected::check_len(send_secret.len(), SECRET_LEN));
+        try!(error::KeyRejected::check_len(receive_secret.len(), SECRET_LEN));
+
+        let (send_key, send_next_secret) =
+            try!(derive_key(algorithm, 0, send_secret, aead::SealingKey::new));
+        let (current, next_secret) =
+            try!(derive_key(algorithm, 0, receive_secret,
+                            aead::OpeningKey::new));
+        let (next, after_next_secret) =
+            try!(derive_key(algorithm, 1, next_secret.expose(),
+                            aead::OpeningKey::new));
+        Ok(SecureChannel {
+            algorithm: algorithm,
+            rekey_interval: rekey_interval,
+            send: SendState {
+                key: send_key,
+                next_secret: send_next_secret,
+                next_sequence_number: 0,
+            },
+            receive: ReceiveState {
+                previous: None,
+                current: current,
+                next: next,
+                after_next_secret: after_next_secret,
+                window: try!(aead::ReplayWindow::new(64)),
+            },
+        })
+    }
+
+    /// Seals the next record, like `aead::seal_in_place`, and returns its
+    /// sequence number and the length of the sealed record.
+    ///
+    /// The sequence number must be sent to the peer with the record. Fails
+    /// when the sequence numbers are exhausted, which won't happen in
+    /// practice.
+    pub fn seal_in_place(&mut self, in_out: &mut [u8],
+                         out_suffix_capacity: usize, ad: &[u8])
+                         -> Result<(u64, usize), error::Unspecified> {
+        let sequence_number = self.send.next_sequence_number;
+        if sequence_number == MAX_SEQUENCE_NUMBER {
+            return Err(error::Unspecified);
+        }
+        if sequence_number != 0 && sequence_number % self.rekey_interval == 0 {
+            let epoch = sequence_number / self.rekey_interval;
+            let (key, next_secret) =
+                try!(derive_key(self.algorithm, epoch,
+                                self.send.next_secret.expose(),
+                                aead::SealingKey::new));
+            self.send.key = key;
+            self.send.next_secret = next_secret;
+        }
+        self.send.next_sequence_number += 1;
+
+        let nonce = self.send.key.nonce(sequence_number);
+        let len = try!(aead::seal_in_place(&self.send.key.key, &nonce, in_out,
+                                           out_suffix_capacity, ad));
+        Ok((sequence_number, len))
+    }
+
+    /// Opens the record with the sequence number `sequence_number`, like
+    /// `aead::open_in_place`.
+    ///
+    /// Fails if the record isn't authentic, if it is a replay, or if its
+    /// epoch isn't the previous, the current, or the next one. A failure
+    /// doesn't change the state of the channel, so an application can drop
+    /// a bad record and continue.
+    pub fn open_in_place(&mut self, sequence_number: u64,
+                         in_prefix_len: usize, in_out: &mut [u8], ad: &[u8])
+                         -> Result<usize, error::Unspecified> {
+        if sequence_number >= MAX_SEQUENCE_NUMBER {
+            return Err(error::Unspecified);
+        }
+        try!(self.receive.window.check(sequence_number));
+
+        let epoch = sequence_number / self.rekey_interval;
+        let len = {
+            let key = try!(self.receive.key_for_epoch(epoch));
+            let nonce = key.nonce(sequence_number);
+            try!(aead::open_in_place(&key.key, &nonce, in_prefix_len, in_out,
+                                     ad))
+        };
+        try!(self.receive.window.mark(sequence_number));
+
+        if epoch == self.receive.next.epoch {
+            let (after_next, after_next_secret) =
+                try!(derive_key(self.algorithm, epoch + 1,
+                                self.receive.after_next_secret.expose(),
+                                aead::OpeningKey::new));
+            let next = core::mem::replace(&mut self.receive.next, after_next);
+            let current = core::mem::replace(&mut self.receive.current, next);
+            self.receive.previous = Some(current);
+            self.receive.after_next_secret = after_next_secret;
+        }
+        Ok(len)
+    }
+
+    /// The AEAD algorithm of the channel.
+    #[inline(always)]
+    pub fn algorithm(&self) -> &'static aead::Algorithm { self.algorithm }
+}
+
+// Sequence numbers are never `u64::MAX`, so that `sequence_number + 1` and
+// `epoch + 1` can't overflow.
+const MAX_SEQUENCE_NUMBER: u64 = 0xffffffffffffffff;
+
+struct SendState {
+    key: EpochKey<aead::SealingKey>,
+    next_secret: secret::SecretBox<[u8; SECRET_LEN]>,
+    next_sequence_number: u64,
+}
+
+struct ReceiveState {
+    previous: Option<EpochKey<aead::OpeningKey>>,
+    current: EpochKey<aead::OpeningKey>,
+    next: EpochKey<aead::OpeningKey>,
+    after_next_secret: secret::SecretBox<[u8; SECRET_LEN]>,
+    window: aead::ReplayWindow,
+}
+
+impl ReceiveState {
+    fn key_for_epoch(&self, epoch: u64)
+                     -> Result<&EpochKey<aead::OpeningKey>,
+                               error::Unspecified> {
+        if epoch == self.current.epoch {
+            return Ok(&self.current);
+        }
+        if epoch == self.next.epoch {
+            return Ok(&self.next);
+        }
+        match self.previous {
+            Some(ref previous) if epoch == previous.epoch => Ok(previous),
+            _ => Err(error::Unspecified),
+        }
+    }
+}
+
+struct EpochKey<K> {
+    epoch: u64,
+    key: K,
+    iv: [u8; aead::NONCE_LEN],
+}
+
+impl<K> EpochKey<K> {
+    fn nonce(&self, sequence_number: u64) -> [u8; aead::NONCE_LEN] {
+        let mut nonce = self.iv;
+        for (i, b) in nonce[(aead::NONCE_LEN - 8)..].iter_mut().enumerate() {
+            *b ^= (sequence_number >> (8 * (7 - i))) as u8;
+        }
+        nonce
+    }
+}
+
+const LABEL_KEY: &'static [u8] = b"ring channel key";
+const LABEL_IV: &'static [u8] = b"ring channel iv";
+const LABEL_NEXT_SECRET: &'static [u8] = b"ring channel next secret";
+
+const MAX_KEY_LEN: usize = 32;
+
+// Derives the key and IV of an epoch, and the secret of the next epoch, from
+// the epoch's secret.
+fn derive(algorithm: &'static aead::Algorithm, secret: &[u8],
+          key_out: &mut [u8; MAX_KEY_LEN], iv_out: &mut [u8; aead::NONCE_LEN])
+          -> secret::SecretBox<[u8; SECRET_LEN]> {
+    let prk = hkdf::Prk::new(&digest::SHA256, secret);
+    prk.expand(LABEL_KEY, &mut key_out[..algorithm.key_len()]);
+    prk.expand(LABEL_IV, iv_out);
+    let mut next_secret = secret::SecretBox::new([0u8; SECRET_LEN]);
+    prk.expand(LABEL_NEXT_SECRET, next_secret.expose_mut());
+    next_secret
+}
+
+fn derive_key<K>(algorithm: &'static aead::Algorithm, epoch: u64,
+                 secret: &[u8],
+                 new_key: fn(&'static aead::Algorithm, &[u8])
+                             -> Result<K, error::KeyRejected>)
+                 -> Result<(EpochKey<K>, secret::SecretBox<[u8; SECRET_LEN]>),
+                           error::Unspecified> {
+    let mut key_bytes = secret::SecretBox::new([0u8; MAX_KEY_LEN]);
+    let mut iv = [0u8; aead::NONCE_LEN];
+    let next_secret = derive(algorithm, secret, key_bytes.expose_mut(),
+                             &mut iv);
+    let key = try!(new_key(algorithm,
+                           &key_bytes.expose()[..algorithm.key_len()]));
+    Ok((EpochKey { epoch: epoch, key: key, iv: iv }, next_secret))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {aead, error};
+    use std::vec::Vec;
+    use super::*;
+
+    fn new_pair(rekey_interval: u64) -> (SecureChannel, SecureChannel) {
+        let a_to_b = [1u8; SECRET_LEN];
+        let b_to_a = [2u8; SECRET_LEN];
+        (SecureChannel::new(&aead::AES_128_GCM, &a_to_b, &b_to_a,
+                            rekey_interval).unwrap(),
+         SecureChannel::new(&aead::AES_128_GCM, &b_to_a, &a_to_b,
+                            rekey_interval).unwrap())
+    }
+
+    fn seal(channel: &mut SecureChannel, msg: &[u8]) -> (u64, Vec<u8>) {
+        let mut in_out = msg.to_vec();
+        in_out.extend_from_slice(&[0u8; aead::MAX_OVERHEAD_LEN]);
+        let (sequence_number, len) =
+            channel.seal_in_place(&mut in_out, aead::MAX_OVERHEAD_LEN, b"")
+                   .unwrap();
+        in_out.truncate(len);
+        (sequence_number, in_out)
+    }
+
+    fn open(channel: &mut SecureChannel, sequence_number: u64,
+            record: &[u8]) -> Result<Vec<u8>, error::Unspecified> {
+        let mut in_out = record.to_vec();
+        let len = try!(channel.open_in_place(sequence_number, 0, &mut in_out,
+                                             b""));
+        in_out.truncate(len);
+        Ok(in_out)
+    }
+
+    #[test]
+    fn test_secure_channel_rekeying() {
+        let (mut a, mut b) = new_pair(3);
+        let records: Vec<(u64, Vec<u8>)> =
+            (0..10u8).map(|i| seal(&mut a, &[i])).collect();
+        for (i, &(sequence_number, ref record)) in records.iter().enumerate() {
+            assert_eq!(sequence_number, i as u64);
+            // Records of different epochs are sealed with different keys, and
+            // every record has a different nonce.
+            assert_eq!(open(&mut b, sequence_number, record),
+                       Ok(vec![i as u8]));
+        }
+
+        // Both directions are independent.
+        let (sequence_number, record) = seal(&mut b, b"reply");
+        assert_eq!(sequence_number, 0);
+        assert_eq!(open(&mut a, sequence_number, &record),
+                   Ok(b"reply".to_vec()));
+        assert!(open(&mut b, sequence_number, &record).is_err());
+    }
+
+    #[test]
+    fn test_secure_channel_reordering_and_replay() {
+        let (mut a, mut b) = new_pair(4);
+        let records: Vec<(u64, Vec<u8>)> =
+            (0..12u8).map(|i| seal(&mut a, &[i])).collect();
+        let open_record = |b: &mut SecureChannel, i: usize| {
+            let (sequence_number, ref record) = records[i];
+            open(b, sequence_number, record)
+        };
+
+        // Records 0..4 are epoch 0, 4..8 epoch 1, and 8..12 epoch 2.
+        assert!(open_record(&mut b, 1).is_ok());
+        assert!(open_record(&mut b, 0).is_ok());
+        assert!(open_record(&mut b, 0).is_err());
+        // Epoch 2 is too far ahead until a record of epoch 1 is opened.
+        assert!(open_record(&mut b, 8).is_err());
+        assert!(open_record(&mut b, 5).is_ok());
+        // Late records of the previous epoch are still accepted.
+        assert!(open_record(&mut b, 3).is_ok());
+        assert!(open_record(&mut b, 8).is_ok());
+        // Epoch 0 is now two epochs behind.
+        assert!(open_record(&mut b, 2).is_err());
+        assert!(open_record(&mut b, 6).is_ok());
+        assert!(open_record(&mut b, 5).is_err());
+        assert!(open_record(&mut b, 11).is_ok());
+
+        // A modified record is rejected without affecting the channel.
+        let (sequence_number, ref record) = records[9];
+        let mut tampered = record.clone();
+        tampered[0] ^= 1;
+        assert!(open(&mut b, sequence_number, &tampered).is_err());
+        assert!(open(&mut b, sequence_number + 1, record).is_err());
+        assert!(open_record(&mut b, 9).is_ok());
+    }
+
+    #[test]
+    fn test_secure_channel_new() {
+        let secret = [0u8; SECRET_LEN];
+        assert!(SecureChannel::new(&aead::AES_128_GCM, &secret, &secret, 0)
+                    .is_err());
+        assert!(SecureChannel::new(&aead::AES_128_GCM, &secret[1..], &secret,
+                                   1).is_err());
+        assert!(SecureChannel::new(&aead::XCHACHA20_POLY1305, &secret,
+                                   &secret, 1).is_err());
+    }
+}
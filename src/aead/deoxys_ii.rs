@@ -0,0 +1,384 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Deoxys-II-256-128, the nonce-misuse-resistant AEAD from the CAESAR final
+//! portfolio, as described in the [Deoxys v1.43 specification].
+//!
+//! Deoxys-II is an SIV-like construction: repeating a nonce only reveals
+//! whether the same (additional data, plaintext) pair was sealed twice. The
+//! price is that sealing makes two passes over the plaintext.
+//!
+//! Deoxys-II uses 120-bit nonces, which don't fit the `ring::aead::Algorithm`
+//! interface, so it has its own `seal_in_place` and `open_in_place` functions
+//! that otherwise work like the ones in `ring::aead`.
+//!
+//! The Deoxys-BC-384 tweakable block cipher is implemented in portable code
+//! that uses a lookup table for the S-box, like the non-assembly AES
+//! implementation.
+//!
+//! [Deoxys v1.43 specification]:
+//!     https://competitions.cr.yp.to/round3/deoxysv143.pdf
+
+use {constant_time, error, init, polyfill};
+use core;
+
+/// The length of Deoxys-II-256-128 keys.
+pub const KEY_LEN: usize = 32;
+
+/// The length of Deoxys-II nonces.
+pub const NONCE_LEN: usize = 120 / 8;
+
+/// The length of Deoxys-II tags.
+pub const TAG_LEN: usize = BLOCK_LEN;
+
+const BLOCK_LEN: usize = 16;
+
+type Block = [u8; BLOCK_LEN];
+
+// Deoxys-BC-384 has 16 rounds, and so 17 subtweakeys.
+const ROUNDS: usize = 16;
+
+/// A Deoxys-II-256-128 key, for both sealing and opening.
+pub struct Key {
+    // The key-dependent part of each subtweakey: TK2_i ^ TK3_i ^ RC_i.
+    round_keys: [Block; ROUNDS + 1],
+}
+
+impl Key {
+    /// Create a new key. `key_bytes` must be exactly `KEY_LEN` bytes long.
+    pub fn new(key_bytes: &[u8]) -> Result<Key, error::KeyRejected> {
+        init::init_once();
+        try!(error::KeyRejected::check_len(key_bytes.len(), KEY_LEN));
+        let key_bytes = slice_as_array_ref!(key_bytes, KEY_LEN).unwrap();
+
+        // The tweakey is K || T, so TK3 is the first half of the key and TK2
+        // is the second half.
+        let mut tk2 = [0u8; BLOCK_LEN];
+        tk2.copy_from_slice(&key_bytes[BLOCK_LEN..]);
+        let mut tk3 = [0u8; BLOCK_LEN];
+        tk3.copy_from_slice(&key_bytes[..BLOCK_LEN]);
+
+        let mut round_keys = [[0u8; BLOCK_LEN]; ROUNDS + 1];
+        for (i, round_key) in round_keys.iter_mut().enumerate() {
+            let rc = RCON[i];
+            let round_constant =
+                [1, 2, 4, 8, rc, rc, rc, rc, 0, 0, 0, 0, 0, 0, 0, 0];
+            for j in 0..BLOCK_LEN {
+                round_key[j] = tk2[j] ^ tk3[j] ^ round_constant[j];
+            }
+            for b in tk2.iter_mut() {
+                *b = (*b << 1) | (((*b >> 7) ^ (*b >> 5)) & 1);
+            }
+            for b in tk3.iter_mut() {
+                *b = (*b >> 1) | (((*b << 7) ^ (*b << 1)) & 0x80);
+            }
+            tk2 = h(&tk2);
+            tk3 = h(&tk3);
+        }
+
+        Ok(Key { round_keys: round_keys })
+    }
+}
+
+/// Encrypts and signs (&ldquo;seals&rdquo;) data in place, like
+/// `ring::aead::seal_in_place`.
+///
+/// The input is `in_out[..(in_out.len() - out_suffix_capacity)]`. When
+/// `seal_in_place` returns `Ok(out_len)`, the ciphertext followed by the tag
+/// is `in_out[..out_len]`. `out_suffix_capacity` must be at least `TAG_LEN`.
+///
+/// `nonce` must be exactly `NONCE_LEN` bytes long. Unlike with the algorithms
+/// in `ring::aead`, reusing a nonce doesn't compromise the confidentiality of
+/// unrelated messages, but nonces should still be unique.
+pub fn seal_in_place(key: &Key, nonce: &[u8], in_out: &mut [u8],
+                     out_suffix_capacity: usize, ad: &[u8])
+                     -> Result<usize, error::Unspecified> {
+    let nonce = try!(slice_as_array_ref!(nonce, NONCE_LEN));
+    if out_suffix_capacity < TAG_LEN {
+        return Err(error::Unspecified);
+    }
+    let in_out_len =
+        try!(in_out.len().checked_sub(out_suffix_capacity)
+                         .ok_or(error::Unspecified));
+    let (in_out, tag_out) = in_out.split_at_mut(in_out_len);
+
+    let tag = calculate_tag(key, nonce, ad, in_out);
+    xor_keystream(key, nonce, &tag, in_out, 0);
+    tag_out[..TAG_LEN].copy_from_slice(&tag);
+    Ok(in_out_len + TAG_LEN)
+}
+
+/// Authenticates and decrypts (&ldquo;opens&rdquo;) data in place, like
+/// `ring::aead::open_in_place`.
+///
+/// The input is `in_out[in_prefix_len..]`, which must be the ciphertext
+/// followed by the tag. When `open_in_place` returns `Ok(out_len)`, the
+/// decrypted output is `in_out[..out_len]`. If the input isn't valid, the
+/// part of `in_out` that would have held the output is zeroed.
+pub fn open_in_place(key: &Key, nonce: &[u8], in_prefix_len: usize,
+                     in_out: &mut [u8], ad: &[u8])
+                     -> Result<usize, error::Unspecified> {
+    let nonce = try!(slice_as_array_ref!(nonce, NONCE_LEN));
+    let ciphertext_and_tag_len =
+        try!(in_out.len().checked_sub(in_prefix_len)
+                         .ok_or(error::Unspecified));
+    let ciphertext_len =
+        try!(ciphertext_and_tag_len.checked_sub(TAG_LEN)
+                                   .ok_or(error::Unspecified));
+    let mut received_tag = [0u8; TAG_LEN];
+    received_tag.copy_from_slice(&in_out[(in_prefix_len + ciphertext_len)..]);
+
+    let in_out = &mut in_out[..(in_prefix_len + ciphertext_len)];
+    xor_keystream(key, nonce, &received_tag, in_out, in_prefix_len);
+    let plaintext = &mut in_out[..ciphertext_len];
+    let calculated_tag = calculate_tag(key, nonce, ad, plaintext);
+    match constant_time::verify_slices_are_equal(&calculated_tag,
+                                                 &received_tag) {
+        Ok(()) => Ok(ciphertext_len),
+        Err(e) => {
+            for b in plaintext.iter_mut() {
+                *b = 0;
+            }
+            Err(e)
+        },
+    }
+}
+
+// Tweak prefixes from the specification.
+const PREFIX_AD_BLOCK: u8 = 0b0010;
+const PREFIX_AD_FINAL: u8 = 0b0110;
+const PREFIX_MSG_BLOCK: u8 = 0b0000;
+const PREFIX_MSG_FINAL: u8 = 0b0100;
+const PREFIX_TAG: u8 = 0b0001;
+
+fn calculate_tag(key: &Key, nonce: &[u8; NONCE_LEN], ad: &[u8],
+                 plaintext: &[u8]) -> Block {
+    let mut auth = [0u8; BLOCK_LEN];
+    authenticate(key, PREFIX_AD_BLOCK, PREFIX_AD_FINAL, ad, &mut auth);
+    authenticate(key, PREFIX_MSG_BLOCK, PREFIX_MSG_FINAL, plaintext,
+                 &mut auth);
+
+    let mut tweak = [0u8; BLOCK_LEN];
+    tweak[0] = PREFIX_TAG << 4;
+    tweak[1..].copy_from_slice(nonce);
+    encrypt_block(key, &tweak, &auth)
+}
+
+fn authenticate(key: &Key, block_prefix: u8, final_prefix: u8, input: &[u8],
+                auth: &mut Block) {
+    for (i, chunk) in input.chunks(BLOCK_LEN).enumerate() {
+        let mut block = [0u8; BLOCK_LEN];
+        block[..chunk.len()].copy_from_slice(chunk);
+        let prefix = if chunk.len() == BLOCK_LEN {
+            block_prefix
+        } else {
+            block[chunk.len()] = 0x80;
+            final_prefix
+        };
+        let mut tweak = [0u8; BLOCK_LEN];
+        tweak[0] = prefix << 4;
+        be_u8_from_u64(&mut tweak[8..], polyfill::u64_from_usize(i));
+        xor_assign(auth, &encrypt_block(key, &tweak, &block));
+    }
+}
+
+// XORs `in_out[in_prefix_len..]` with the keystream for `tag`, writing the
+// result to `in_out[..(in_out.len() - in_prefix_len)]`.
+fn xor_keystream(key: &Key, nonce: &[u8; NONCE_LEN], tag: &Block,
+                 in_out: &mut [u8], in_prefix_len: usize) {
+    let mut nonce_block = [0u8; BLOCK_LEN];
+    nonce_block[1..].copy_from_slice(nonce);
+
+    let len = in_out.len() - in_prefix_len;
+    let mut pos = 0;
+    let mut counter = 0u64;
+    while pos < len {
+        let mut tweak = *tag;
+        tweak[0] |= 0x80;
+        let mut counter_bytes = [0u8; 8];
+        be_u8_from_u64(&mut counter_bytes, counter);
+        xor_assign(&mut tweak[8..], &counter_bytes);
+        let keystream = encrypt_block(key, &tweak, &nonce_block);
+
+        let block_len = core::cmp::min(BLOCK_LEN, len - pos);
+        for i in 0..block_len {
+            in_out[pos + i] = in_out[in_prefix_len + pos + i] ^ keystream[i];
+        }
+        pos += block_len;
+        counter += 1;
+    }
+}
+
+// Deoxys-BC-384 encryption of `block` under `key` and `tweak`.
+fn encrypt_block(key: &Key, tweak: &Block, block: &Block) -> Block {
+    let mut tk1 = *tweak;
+    let mut state = *block;
+    xor_assign(&mut state, &key.round_keys[0]);
+    xor_assign(&mut state, &tk1);
+    for round_key in key.round_keys[1..].iter() {
+        tk1 = h(&tk1);
+        aes_round(&mut state);
+        xor_assign(&mut state, round_key);
+        xor_assign(&mut state, &tk1);
+    }
+    state
+}
+
+// The tweakey byte permutation h.
+fn h(tk: &Block) -> Block {
+    const H: [usize; BLOCK_LEN] =
+        [1, 6, 11, 12, 5, 10, 15, 0, 9, 14, 3, 4, 13, 2, 7, 8];
+    let mut result = [0u8; BLOCK_LEN];
+    for (r, &i) in result.iter_mut().zip(H.iter()) {
+        *r = tk[i];
+    }
+    result
+}
+
+// SubBytes, ShiftRows, and MixColumns, without AddRoundKey. The state is in
+// column-major order, as in AES.
+fn aes_round(state: &mut Block) {
+    let mut shifted = [0u8; BLOCK_LEN];
+    for column in 0..4 {
+        for row in 0..4 {
+            shifted[(4 * column) + row] =
+                SBOX[state[(4 * ((column + row) % 4)) + row] as usize];
+        }
+    }
+    for column in 0..4 {
+        let a = &shifted[(4 * column)..(4 * column + 4)];
+        let all = a[0] ^ a[1] ^ a[2] ^ a[3];
+        for row in 0..4 {
+            state[(4 * column) + row] =
+                a[row] ^ all ^ xtime(a[row] ^ a[(row + 1) % 4]);
+        }
+    }
+}
+
+fn xtime(x: u8) -> u8 { (x << 1) ^ (0x1b & 0u8.wrapping_sub(x >> 7)) }
+
+fn be_u8_from_u64(out: &mut [u8], value: u64) {
+    debug_assert_eq!(out.len(), 8);
+    for (i, b) in out.iter_mut().enumerate() {
+        *b = (value >> (8 * (7 - i))) as u8;
+    }
+}
+
+fn xor_assign(a: &mut [u8], b: &[u8]) {
+    debug_assert_eq!(a.len(), b.len());
+    for (a, b) in a.iter_mut().zip(b.iter()) {
+        *a ^= *b;
+    }
+}
+
+const RCON: [u8; ROUNDS + 1] = [
+    0x2f, 0x5e, 0xbc, 0x63, 0xc6, 0x97, 0x35, 0x6a, 0xd4, 0xb3, 0x7d, 0xfa,
+    0xef, 0xc5, 0x91, 0x39, 0x72,
+];
+
+// The AES S-box.
+const SBOX: [u8; 256] = [
+    0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b,
+    0xfe, 0xd7, 0xab, 0x76, 0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0,
+    0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0, 0xb7, 0xfd, 0x93, 0x26,
+    0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+    0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2,
+    0xeb, 0x27, 0xb2, 0x75, 0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0,
+    0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84, 0x53, 0xd1, 0x00, 0xed,
+    0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+    0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f,
+    0x50, 0x3c, 0x9f, 0xa8, 0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5,
+    0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2, 0xcd, 0x0c, 0x13, 0xec,
+    0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+    0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14,
+    0xde, 0x5e, 0x0b, 0xdb, 0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c,
+    0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79, 0xe7, 0xc8, 0x37, 0x6d,
+    0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+    0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f,
+    0x4b, 0xbd, 0x8b, 0x8a, 0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e,
+    0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e, 0xe1, 0xf8, 0x98, 0x11,
+    0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+    0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f,
+    0xb0, 0x54, 0xbb, 0x16,
+];
+
+
+#[cfg(test)]
+mod tests {
+    use super::super::deoxys_ii;
+    use test;
+
+    #[test]
+    fn deoxys_ii_test() {
+        test::from_file("src/aead/deoxys_ii_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let key = test_case.consume_bytes("KEY");
+            let key = try!(deoxys_ii::Key::new(&key));
+            let nonce = test_case.consume_bytes("NONCE");
+            let plaintext = test_case.consume_bytes("IN");
+            let ad = test_case.consume_bytes("AD");
+            let mut ct = test_case.consume_bytes("CT");
+            let tag = test_case.consume_bytes("TAG");
+            ct.extend_from_slice(&tag);
+
+            let mut s_in_out = plaintext.clone();
+            s_in_out.extend_from_slice(&[0u8; deoxys_ii::TAG_LEN + 1]);
+            let s_len = try!(deoxys_ii::seal_in_place(
+                &key, &nonce, &mut s_in_out, deoxys_ii::TAG_LEN + 1, &ad));
+            assert_eq!(&s_in_out[..s_len], &ct[..]);
+
+            for in_prefix_len in &[0, 1, 15, 16, 17, 64] {
+                let mut o_in_out = vec![123u8; *in_prefix_len];
+                o_in_out.extend_from_slice(&ct);
+                let o_len = try!(deoxys_ii::open_in_place(
+                    &key, &nonce, *in_prefix_len, &mut o_in_out, &ad));
+                assert_eq!(&o_in_out[..o_len], &plaintext[..]);
+            }
+
+            // Any modification to the ciphertext or tag is detected, and the
+            // output is zeroed.
+            for i in 0..ct.len() {
+                let mut o_in_out = ct.clone();
+                o_in_out[i] ^= 1;
+                assert!(deoxys_ii::open_in_place(&key, &nonce, 0,
+                                                 &mut o_in_out, &ad).is_err());
+                assert!(o_in_out[..plaintext.len()].iter().all(|b| *b == 0));
+            }
+
+            // So is a change to the additional data.
+            let mut o_in_out = ct.clone();
+            let mut bad_ad = ad.clone();
+            bad_ad.push(0);
+            assert!(deoxys_ii::open_in_place(&key, &nonce, 0, &mut o_in_out,
+                                             &bad_ad).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn deoxys_ii_bad_lengths_test() {
+        let key = deoxys_ii::Key::new(&[0u8; deoxys_ii::KEY_LEN]).unwrap();
+        let nonce = [0u8; deoxys_ii::NONCE_LEN];
+        let mut in_out = [0u8; deoxys_ii::TAG_LEN];
+        assert!(deoxys_ii::seal_in_place(&key, &nonce[1..], &mut in_out,
+                                         deoxys_ii::TAG_LEN, &[]).is_err());
+        assert!(deoxys_ii::seal_in_place(&key, &nonce, &mut in_out,
+                                         deoxys_ii::TAG_LEN - 1, &[])
+                    .is_err());
+        assert!(deoxys_ii::open_in_place(&key, &nonce, 1, &mut in_out, &[])
+                    .is_err());
+        assert!(deoxys_ii::Key::new(&[0u8; deoxys_ii::KEY_LEN - 1]).is_err());
+    }
+}
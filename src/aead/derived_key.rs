@@ -0,0 +1,207 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! AEAD with a fresh subkey derived for every nonce prefix.
+//!
+//! The nonces of most of the algorithms in `ring::aead` are only 96 bits long,
+//! which is too short to be chosen at random for more than about 2**32
+//! messages per key. This module extends such an AEAD algorithm with a
+//! 192-bit nonce, like XChaCha20-Poly1305 does for ChaCha20-Poly1305: the
+//! first `PREFIX_LEN` bytes of the nonce are used to derive a subkey from the
+//! master key, and the remaining bytes are the nonce used with the subkey.
+//! This way, a single long-lived key, e.g. one that protects records in a
+//! storage system, can safely seal far more messages with random nonces.
+//!
+//! The subkey is `HKDF-Expand(PRK = key, info = label || prefix, L)` as
+//! described in [RFC 5869 Section 2.3], where the hash function is SHA-256,
+//! `label` is the ASCII string "ring derived-key AEAD", and `L` is the key
+//! length of the underlying algorithm. The master key is used directly as the
+//! HKDF pseudorandom key, so it must be uniformly random.
+//!
+//! The algorithm isn't an input to the key derivation, so a master key must
+//! only be used with one algorithm.
+//!
+//! [RFC 5869 Section 2.3]: https://tools.ietf.org/html/rfc5869#section-2.3
+
+use {aead, digest, error, hkdf, init};
+
+/// The length of master keys.
+pub const KEY_LEN: usize = 256 / 8;
+
+/// The length of the part of the nonce that is used to derive the subkey.
+pub const PREFIX_LEN: usize = super::NONCE_LEN;
+
+/// The length of the nonces.
+pub const NONCE_LEN: usize = PREFIX_LEN + super::NONCE_LEN;
+
+// The maximum key length of the algorithms in `ring::aead`.
+const MAX_SUBKEY_LEN: usize = 256 / 8;
+
+const LABEL: &'static [u8] = b"ring derived-key AEAD";
+
+// The length of the HKDF info, `LABEL || prefix`.
+const INFO_LEN: usize = 21 + PREFIX_LEN;
+
+/// A master key for an AEAD algorithm, for both sealing and opening.
+pub struct Key {
+    prk: hkdf::Prk,
+    algorithm: &'static aead::Algorithm,
+}
+
+impl Key {
+    /// Create a new master key for `algorithm`, which must use 96-bit nonces.
+    /// `key_bytes` must be exactly `KEY_LEN` bytes long.
+    pub fn new(algorithm: &'static aead::Algorithm, key_bytes: &[u8])
+               -> Result<Key, error::KeyRejected> {
+        init::init_once();
+        if algorithm.key_len() > MAX_SUBKEY_LEN ||
+           algorithm.nonce_len() != super::NONCE_LEN {
+            return Err(error::KeyRejected::UnsupportedAlgorithm);
+        }
+        try!(error::KeyRejected::check_len(key_bytes.len(), KEY_LEN));
+        Ok(Key {
+            prk: hkdf::Prk::new(&digest::SHA256, key_bytes),
+            algorithm: algorithm,
+        })
+    }
+
+    /// The underlying AEAD algorithm.
+    #[inline(always)]
+    pub fn algorithm(&self) -> &'static aead::Algorithm { self.algorithm }
+}
+
+/// Encrypts and authenticates (&ldquo;seals&rdquo;) data in place, like
+/// `ring::aead::seal_in_place`.
+///
+/// The input is `in_out[..(in_out.len() - out_suffix_capacity)]`. When
+/// `seal_in_place` returns `Ok(out_len)`, the ciphertext followed by the tag
+/// is `in_out[..out_len]`. `out_suffix_capacity` must be at least
+/// `key.algorithm().max_overhead_len()`.
+///
+/// `nonce` must be exactly `NONCE_LEN` bytes long and must be unique for every
+/// use of the key to seal data. It may be chosen at random.
+pub fn seal_in_place(key: &Key, nonce: &[u8], in_out: &mut [u8],
+                     out_suffix_capacity: usize, ad: &[u8])
+                     -> Result<usize, error::Unspecified> {
+    let nonce = try!(slice_as_array_ref!(nonce, NONCE_LEN));
+    let mut subkey = [0u8; MAX_SUBKEY_LEN];
+    let subkey = derive_subkey(key, &nonce[..PREFIX_LEN], &mut subkey);
+    let subkey = try!(aead::SealingKey::new(key.algorithm, subkey));
+    Ok(try!(aead::seal_in_place(&subkey, &nonce[PREFIX_LEN..], in_out,
+                                out_suffix_capacity, ad)))
+}
+
+/// Authenticates and decrypts (&ldquo;opens&rdquo;) data in place, like
+/// `ring::aead::open_in_place`.
+///
+/// The input is `in_out[in_prefix_len..]`, which must be the ciphertext
+/// followed by the tag. When `open_in_place` returns `Ok(out_len)`, the
+/// decrypted output is `in_out[..out_len]`.
+pub fn open_in_place(key: &Key, nonce: &[u8], in_prefix_len: usize,
+                     in_out: &mut [u8], ad: &[u8])
+                     -> Result<usize, error::Unspecified> {
+    let nonce = try!(slice_as_array_ref!(nonce, NONCE_LEN));
+    let mut subkey = [0u8; MAX_SUBKEY_LEN];
+    let subkey = derive_subkey(key, &nonce[..PREFIX_LEN], &mut subkey);
+    let subkey = try!(aead::OpeningKey::new(key.algorithm, subkey));
+    aead::open_in_place(&subkey, &nonce[PREFIX_LEN..], in_prefix_len, in_out,
+                        ad)
+}
+
+// Derives the subkey for `prefix` into `out`, returning the part of `out`
+// that holds it.
+fn derive_subkey<'a>(key: &Key, prefix: &[u8], out: &'a mut [u8])
+                     -> &'a [u8] {
+    let mut info = [0u8; INFO_LEN];
+    info[..LABEL.len()].copy_from_slice(LABEL);
+    info[LABEL.len()..].copy_from_slice(prefix);
+    let out = &mut out[..key.algorithm.key_len()];
+    key.prk.expand(&info, out);
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {aead, test};
+    use super::*;
+
+    #[test]
+    fn derived_key_test() {
+        test::from_file("src/aead/derived_key_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+            let algorithm = match test_case.consume_string("ALG").as_str() {
+                "AES_128_GCM" => &aead::AES_128_GCM,
+                "AES_256_GCM" => &aead::AES_256_GCM,
+                "CHACHA20_POLY1305" => &aead::CHACHA20_POLY1305,
+                _ => unreachable!(),
+            };
+            let key =
+                Key::new(algorithm, &test_case.consume_bytes("KEY")).unwrap();
+            let nonce = test_case.consume_bytes("NONCE");
+            let plaintext = test_case.consume_bytes("IN");
+            let ad = test_case.consume_bytes("AD");
+            let ct = test_case.consume_bytes("CT");
+            let tag = test_case.consume_bytes("TAG");
+            let tag_len = algorithm.max_overhead_len();
+
+            let mut in_out = plaintext.clone();
+            in_out.extend_from_slice(&vec![0u8; tag_len]);
+            let len =
+                seal_in_place(&key, &nonce, &mut in_out, tag_len, &ad).unwrap();
+            assert_eq!(&in_out[..plaintext.len()], &ct[..]);
+            assert_eq!(&in_out[plaintext.len()..len], &tag[..]);
+
+            for in_prefix_len in &[0, 1, 64] {
+                let mut in_out = vec![123u8; *in_prefix_len];
+                in_out.extend_from_slice(&ct);
+                in_out.extend_from_slice(&tag);
+                let len = open_in_place(&key, &nonce, *in_prefix_len,
+                                        &mut in_out, &ad).unwrap();
+                assert_eq!(&in_out[..len], &plaintext[..]);
+            }
+
+            // Changing the prefix, which changes the subkey, or the rest of
+            // the nonce is detected.
+            for &i in &[0, PREFIX_LEN - 1, PREFIX_LEN, NONCE_LEN - 1] {
+                let mut bad_nonce = nonce.clone();
+                bad_nonce[i] ^= 1;
+                let mut in_out = ct.clone();
+                in_out.extend_from_slice(&tag);
+                assert!(open_in_place(&key, &bad_nonce, 0, &mut in_out, &ad)
+                            .is_err());
+            }
+
+            // Wrong nonce lengths are rejected.
+            let mut in_out = ct.clone();
+            in_out.extend_from_slice(&tag);
+            assert!(open_in_place(&key, &nonce[..(NONCE_LEN - 1)], 0,
+                                  &mut in_out, &ad).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn derived_key_wrong_key_len() {
+        assert_eq!(Key::new(&aead::AES_128_GCM, &[0u8; 16]).err(),
+                   Some(error::KeyRejected::TooShort));
+        assert_eq!(Key::new(&aead::AES_256_GCM, &[0u8; KEY_LEN + 1]).err(),
+                   Some(error::KeyRejected::TooLong));
+        assert!(Key::new(&aead::AES_256_GCM, &[0u8; KEY_LEN]).is_ok());
+        assert_eq!(Key::new(&aead::XCHACHA20_POLY1305, &[0u8; KEY_LEN]).err(),
+                   Some(error::KeyRejected::UnsupportedAlgorithm));
+    }
+}
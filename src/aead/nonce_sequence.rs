@@ -0,0 +1,304 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! AEAD keys that manage their own nonces.
+//!
+//! Reusing a nonce with the same key is catastrophic for every AEAD in
+//! `ring::aead`, and passing a nonce to every call of `aead::seal_in_place`
+//! makes it easy to do by accident. A `SequencedSealingKey` owns a
+//! `NonceSequence` and takes the next nonce from it for every message it
+//! seals, so the caller never handles nonces. A `SequencedOpeningKey` does
+//! the same for opening, so the two sides stay in step as long as they use
+//! equal sequences and process the same messages in the same order.
+//!
+//! `aead::seal_in_place` and `aead::open_in_place`, which take explicit
+//! nonces, remain available as the less safe interface for protocols that
+//! construct their nonces themselves, e.g. from a record sequence number
+//! that is also sent on the wire.
+//!
+//! Any `ring::aead` algorithm with 96-bit nonces can be used.
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::aead;
+//!
+//! # fn main_with_result() -> Result<(), ring::error::Unspecified> {
+//! let key_bytes = [0u8; 16];
+//! let mut sealing_key = try!(aead::SequencedSealingKey::new(
+//!     try!(aead::SealingKey::new(&aead::AES_128_GCM, &key_bytes)),
+//!     aead::CounterNonceSequence::new()));
+//! let mut opening_key = try!(aead::SequencedOpeningKey::new(
+//!     try!(aead::OpeningKey::new(&aead::AES_128_GCM, &key_bytes)),
+//!     aead::CounterNonceSequence::new()));
+//!
+//! for message in &[&b"hello"[..], &b"world"[..]] {
+//!     let mut in_out = message.to_vec();
+//!     in_out.extend_from_slice(&[0u8; aead::MAX_OVERHEAD_LEN]);
+//!     let out_len = try!(sealing_key.seal_in_place(
+//!         &mut in_out, aead::MAX_OVERHEAD_LEN, b""));
+//!     let plaintext_len = try!(opening_key.open_in_place(
+//!         0, &mut in_out[..out_len], b""));
+//!     assert_eq!(&in_out[..plaintext_len], *message);
+//! }
+//! # Ok(())
+//! # }
+//! # fn main() { main_with_result().unwrap() }
+//! ```
+
+use {aead, error};
+
+/// A sequence of unique nonces.
+pub trait NonceSequence {
+    /// Returns the next nonce in the sequence, or an error if the sequence
+    /// is exhausted. An implementation must never return the same nonce
+    /// twice.
+    fn advance(&mut self) -> Result<[u8; aead::NONCE_LEN], error::Unspecified>;
+}
+
+/// A `NonceSequence` of 96-bit big-endian counter values, starting at zero.
+///
+/// Every key must only be used with one `CounterNonceSequence` for sealing;
+/// a new `CounterNonceSequence` starts at zero again.
+pub struct CounterNonceSequence {
+    next: [u8; aead::NONCE_LEN],
+    exhausted: bool,
+}
+
+impl CounterNonceSequence {
+    /// Constructs a new `CounterNonceSequence` whose first nonce is zero.
+    pub fn new() -> CounterNonceSequence {
+        CounterNonceSequence {
+            next: [0u8; aead::NONCE_LEN],
+            exhausted: false,
+        }
+    }
+}
+
+impl NonceSequence for CounterNonceSequence {
+    fn advance(&mut self)
+               -> Result<[u8; aead::NONCE_LEN], error::Unspecified> {
+        if self.exhausted {
+            return Err(error::Unspecified);
+        }
+        let nonce = self.next;
+        self.exhausted = true;
+        for b in self.next.iter_mut().rev() {
+            *b = b.wrapping_add(1);
+            if *b != 0 {
+                self.exhausted = false;
+                break;
+            }
+        }
+        Ok(nonce)
+    }
+}
+
+/// A sealing key that takes the nonce for each message from a
+/// `NonceSequence`.
+pub struct SequencedSealingKey<N: NonceSequence> {
+    key: aead::SealingKey,
+    nonce_sequence: N,
+}
+
+impl<N: NonceSequence> SequencedSealingKey<N> {
+    /// Constructs a new `SequencedSealingKey`.
+    ///
+    /// `key.algorithm().nonce_len()` must be 12; otherwise
+    /// `error::KeyRejected::UnsupportedAlgorithm` is returned.
+    pub fn new(key: aead::SealingKey, nonce_sequence: N)
+               -> Result<SequencedSealingKey<N>, error::KeyRejected> {
+        try!(check_algorithm(key.algorithm()));
+        Ok(SequencedSealingKey { key: key, nonce_sequence: nonce_sequence })
+    }
+
+    /// Seals the next message, like `aead::seal_in_place`, using the next
+    /// nonce in the sequence.
+    pub fn seal_in_place(&mut self, in_out: &mut [u8],
+                         out_suffix_capacity: usize, ad: &[u8])
+                         -> Result<usize, error::Unspecified> {
+        let nonce = try!(self.nonce_sequence.advance());
+        Ok(try!(aead::seal_in_place(&self.key, &nonce, in_out,
+                                    out_suffix_capacity, ad)))
+    }
+
+    /// Seals the next message, like `aead::seal_in_place_separate_tag`,
+    /// using the next nonce in the sequence.
+    pub fn seal_in_place_separate_tag(&mut self, in_out: &mut [u8],
+                                      ad: &[&[u8]])
+                                      -> Result<[u8; aead::TAG_LEN],
+                                                error::Unspecified> {
+        let nonce = try!(self.nonce_sequence.advance());
+        Ok(try!(aead::seal_in_place_separate_tag(&self.key, &nonce, in_out,
+                                                 ad)))
+    }
+
+    /// The key's AEAD algorithm.
+    #[inline(always)]
+    pub fn algorithm(&self) -> &'static aead::Algorithm {
+        self.key.algorithm()
+    }
+}
+
+/// An opening key that takes the nonce for each message from a
+/// `NonceSequence`.
+///
+/// A nonce is used up by every call to `open_in_place`, even when opening
+/// fails, so after a failure the sequence is no longer in step with the
+/// sender's unless the sender's message was dropped on purpose. Usually the
+/// connection should be closed instead.
+pub struct SequencedOpeningKey<N: NonceSequence> {
+    key: aead::OpeningKey,
+    nonce_sequence: N,
+}
+
+impl<N: NonceSequence> SequencedOpeningKey<N> {
+    /// Constructs a new `SequencedOpeningKey`.
+    ///
+    /// `nonce_sequence` must produce the same nonces as the sealer's
+    /// sequence. `key.algorithm().nonce_len()` must be 12; otherwise
+    /// `error::KeyRejected::UnsupportedAlgorithm` is returned.
+    pub fn new(key: aead::OpeningKey, nonce_sequence: N)
+               -> Result<SequencedOpeningKey<N>, error::KeyRejected> {
+        try!(check_algorithm(key.algorithm()));
+        Ok(SequencedOpeningKey { key: key, nonce_sequence: nonce_sequence })
+    }
+
+    /// Opens the next message, like `aead::open_in_place`, using the next
+    /// nonce in the sequence.
+    pub fn open_in_place(&mut self, in_prefix_len: usize, in_out: &mut [u8],
+                         ad: &[u8]) -> Result<usize, error::Unspecified> {
+        let nonce = try!(self.nonce_sequence.advance());
+        aead::open_in_place(&self.key, &nonce, in_prefix_len, in_out, ad)
+    }
+
+    /// Opens the next message, like `aead::open_in_place_separate_tag`,
+    /// using the next nonce in the sequence.
+    pub fn open_in_place_separate_tag(&mut self, in_out: &mut [u8],
+                                      received_tag: &[u8; aead::TAG_LEN],
+                                      ad: &[&[u8]])
+                                      -> Result<(), error::Unspecified> {
+        let nonce = try!(self.nonce_sequence.advance());
+        aead::open_in_place_separate_tag(&self.key, &nonce, in_out,
+                                         received_tag, ad)
+    }
+
+    /// The key's AEAD algorithm.
+    #[inline(always)]
+    pub fn algorithm(&self) -> &'static aead::Algorithm {
+        self.key.algorithm()
+    }
+}
+
+fn check_algorithm(algorithm: &'static aead::Algorithm)
+                   -> Result<(), error::KeyRejected> {
+    if algorithm.nonce_len() != aead::NONCE_LEN {
+        return Err(error::KeyRejected::UnsupportedAlgorithm);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {aead, error};
+    use super::*;
+
+    #[test]
+    fn test_counter_nonce_sequence() {
+        let mut nonces = CounterNonceSequence::new();
+        assert_eq!(nonces.advance(), Ok([0u8; aead::NONCE_LEN]));
+        assert_eq!(nonces.advance(),
+                   Ok([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]));
+
+        let mut nonces = CounterNonceSequence {
+            next: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff],
+            exhausted: false,
+        };
+        assert!(nonces.advance().is_ok());
+        assert_eq!(nonces.advance(),
+                   Ok([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0]));
+
+        // The last nonce can be used, but then the sequence is exhausted.
+        let mut nonces = CounterNonceSequence {
+            next: [0xff; aead::NONCE_LEN],
+            exhausted: false,
+        };
+        assert_eq!(nonces.advance(), Ok([0xff; aead::NONCE_LEN]));
+        assert_eq!(nonces.advance(), Err(error::Unspecified));
+        assert_eq!(nonces.advance(), Err(error::Unspecified));
+    }
+
+    // Each message is sealed with the next nonce, exactly like sealing it
+    // with `aead::seal_in_place` and an explicit counter nonce.
+    #[test]
+    fn test_sequenced_keys() {
+        let key_bytes = [0x42u8; 32];
+        let alg = &aead::CHACHA20_POLY1305;
+        let mut sealing_key = SequencedSealingKey::new(
+            aead::SealingKey::new(alg, &key_bytes).unwrap(),
+            CounterNonceSequence::new()).unwrap();
+        let mut opening_key = SequencedOpeningKey::new(
+            aead::OpeningKey::new(alg, &key_bytes).unwrap(),
+            CounterNonceSequence::new()).unwrap();
+        let explicit_key = aead::SealingKey::new(alg, &key_bytes).unwrap();
+
+        for i in 0..3u8 {
+            let mut in_out = [i; 20 + aead::MAX_OVERHEAD_LEN];
+            let out_len = sealing_key.seal_in_place(
+                &mut in_out, aead::MAX_OVERHEAD_LEN, b"ad").unwrap();
+
+            let mut expected = [i; 20 + aead::MAX_OVERHEAD_LEN];
+            let mut nonce = [0u8; aead::NONCE_LEN];
+            nonce[aead::NONCE_LEN - 1] = i;
+            assert_eq!(aead::seal_in_place(&explicit_key, &nonce,
+                                           &mut expected,
+                                           aead::MAX_OVERHEAD_LEN, b"ad"),
+                       Ok(out_len));
+            assert_eq!(&in_out[..], &expected[..]);
+
+            assert_eq!(opening_key.open_in_place(0, &mut in_out[..out_len],
+                                                 b"ad"),
+                       Ok(20));
+            assert_eq!(&in_out[..20], &[i; 20]);
+        }
+
+        // Opening messages out of order fails.
+        let mut first = [1u8; 4];
+        let first_tag =
+            sealing_key.seal_in_place_separate_tag(&mut first, &[]).unwrap();
+        let mut second = [2u8; 4];
+        let second_tag =
+            sealing_key.seal_in_place_separate_tag(&mut second, &[]).unwrap();
+        assert!(opening_key.open_in_place_separate_tag(&mut second,
+                                                       &second_tag,
+                                                       &[]).is_err());
+        assert!(opening_key.open_in_place_separate_tag(&mut first,
+                                                       &first_tag,
+                                                       &[]).is_err());
+    }
+
+    #[test]
+    fn test_sequenced_keys_unsupported_algorithm() {
+        let key_bytes = [0u8; 32];
+        let alg = &aead::XCHACHA20_POLY1305;
+        assert_eq!(SequencedSealingKey::new(
+                       aead::SealingKey::new(alg, &key_bytes).unwrap(),
+                       CounterNonceSequence::new()).err(),
+                   Some(error::KeyRejected::UnsupportedAlgorithm));
+        assert_eq!(SequencedOpeningKey::new(
+                       aead::OpeningKey::new(alg, &key_bytes).unwrap(),
+                       CounterNonceSequence::new()).err(),
+                   Some(error::KeyRejected::UnsupportedAlgorithm));
+    }
+}
@@ -0,0 +1,195 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The Poly1305 one-time authenticator, as specified in [RFC 7539].
+//!
+//! This is the same Poly1305 implementation that `CHACHA20_POLY1305` uses.
+//! It is provided for constructions that use Poly1305 directly, such as
+//! libsodium's `crypto_onetimeauth`.
+//!
+//! A Poly1305 key must never be used to authenticate more than one message;
+//! an attacker that sees the tags of two messages under the same key can
+//! forge tags. For this reason, `sign`, `verify`, and `Context::new` consume
+//! the `Key`. Poly1305 keys are normally derived from a longer-term key and a
+//! nonce, as `CHACHA20_POLY1305` does.
+//!
+//! C analogs: `crypto_onetimeauth`, `crypto_onetimeauth_verify`
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::aead::poly1305;
+//!
+//! # fn main() { foo().unwrap() }
+//! # fn foo() -> Result<(), ring::error::Unspecified> {
+//! let key_bytes = [0x42; poly1305::KEY_LEN];
+//!
+//! let key = try!(poly1305::Key::new(&key_bytes));
+//! let tag = poly1305::sign(key, b"hello, world");
+//!
+//! let key = try!(poly1305::Key::new(&key_bytes));
+//! try!(poly1305::verify(key, b"hello, world", tag.as_ref()));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [RFC 7539]: https://tools.ietf.org/html/rfc7539
+
+use {constant_time, error, init};
+use secret::Zeroize;
+use super::chacha20_poly1305::{poly1305_finish, poly1305_init, poly1305_update,
+                               POLY1305_KEY_LEN, POLY1305_STATE_LEN};
+
+/// The length of Poly1305 keys.
+///
+/// C analog: `crypto_onetimeauth_KEYBYTES`
+pub const KEY_LEN: usize = POLY1305_KEY_LEN;
+
+/// The length of Poly1305 tags.
+///
+/// C analog: `crypto_onetimeauth_BYTES`
+pub const TAG_LEN: usize = 16;
+
+/// A Poly1305 key, which must be used for only one message.
+pub struct Key {
+    key: [u8; KEY_LEN],
+}
+
+impl Key {
+    /// Create a new key. `key_bytes` must be exactly `KEY_LEN` bytes long.
+    pub fn new(key_bytes: &[u8]) -> Result<Key, error::KeyRejected> {
+        init::init_once();
+        try!(error::KeyRejected::check_len(key_bytes.len(), KEY_LEN));
+        let key_bytes = slice_as_array_ref!(key_bytes, KEY_LEN).unwrap();
+        Ok(Key { key: *key_bytes })
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) { self.key.zeroize(); }
+}
+
+/// A Poly1305 tag.
+#[derive(Clone, Copy)]
+pub struct Tag([u8; TAG_LEN]);
+
+impl AsRef<[u8]> for Tag {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.0[..] }
+}
+
+/// A context for multi-step (Init-Update-Finish) Poly1305 signing.
+///
+/// Use `sign` for single-step Poly1305 signing.
+///
+/// C analog: `crypto_onetimeauth_state`
+pub struct Context {
+    state: [u8; POLY1305_STATE_LEN],
+}
+
+impl Context {
+    /// Constructs a new Poly1305 signing context using the given key.
+    ///
+    /// C analog: `crypto_onetimeauth_init`
+    pub fn new(key: Key) -> Context {
+        let mut state = [0u8; POLY1305_STATE_LEN];
+        poly1305_init(&mut state, &key.key);
+        Context { state: state }
+    }
+
+    /// Updates the Poly1305 with all the data in `data`. `update` may be
+    /// called zero or more times until `sign` is called.
+    ///
+    /// C analog: `crypto_onetimeauth_update`
+    pub fn update(&mut self, data: &[u8]) {
+        poly1305_update(&mut self.state, data);
+    }
+
+    /// Finalizes the Poly1305 calculation and returns the tag. `sign`
+    /// consumes the context so it cannot be (mis-)used after `sign` has been
+    /// called.
+    ///
+    /// C analog: `crypto_onetimeauth_final`
+    pub fn sign(mut self) -> Tag {
+        let mut tag = [0u8; TAG_LEN];
+        poly1305_finish(&mut self.state, &mut tag);
+        Tag(tag)
+    }
+}
+
+/// Calculates the Poly1305 tag of `data` using `key`, in one step.
+///
+/// C analog: `crypto_onetimeauth`
+pub fn sign(key: Key, data: &[u8]) -> Tag {
+    let mut ctx = Context::new(key);
+    ctx.update(data);
+    ctx.sign()
+}
+
+/// Calculates the Poly1305 tag of `data` using `key`, and verifies whether
+/// it equals `tag`, in one step. The comparison is done in constant time.
+///
+/// C analog: `crypto_onetimeauth_verify`
+pub fn verify(key: Key, data: &[u8], tag: &[u8])
+              -> Result<(), error::Unspecified> {
+    let calculated = sign(key, data);
+    constant_time::verify_slices_are_equal(calculated.as_ref(), tag)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {error, test};
+    use super::*;
+
+    #[test]
+    fn test_poly1305_public_api() {
+        test::from_file("src/aead/poly1305_test.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let key_bytes = test_case.consume_bytes("Key");
+            let input = test_case.consume_bytes("Input");
+            let expected_tag = test_case.consume_bytes("MAC");
+
+            let key = Key::new(&key_bytes).unwrap();
+            assert_eq!(sign(key, &input).as_ref(), &expected_tag[..]);
+
+            let mut ctx = Context::new(Key::new(&key_bytes).unwrap());
+            for chunk in input.chunks(7) {
+                ctx.update(chunk);
+            }
+            assert_eq!(ctx.sign().as_ref(), &expected_tag[..]);
+
+            let key = Key::new(&key_bytes).unwrap();
+            assert_eq!(verify(key, &input, &expected_tag), Ok(()));
+
+            let mut bad_tag = expected_tag.clone();
+            bad_tag[TAG_LEN - 1] ^= 1;
+            let key = Key::new(&key_bytes).unwrap();
+            assert_eq!(verify(key, &input, &bad_tag), Err(error::Unspecified));
+
+            let key = Key::new(&key_bytes).unwrap();
+            assert_eq!(verify(key, &input, &expected_tag[..(TAG_LEN - 1)]),
+                       Err(error::Unspecified));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_poly1305_key_len() {
+        assert!(Key::new(&[0u8; KEY_LEN - 1]).is_err());
+        assert!(Key::new(&[0u8; KEY_LEN + 1]).is_err());
+        assert!(Key::new(&[0u8; KEY_LEN]).is_ok());
+    }
+}
@@ -0,0 +1,179 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The Poly1305-AES message authentication code, as specified in
+//! [The Poly1305-AES message-authentication code].
+//!
+//! Poly1305-AES is the original construction of Poly1305, which predates
+//! ChaCha20-Poly1305: the one-time key for each message is derived by
+//! encrypting a 16-byte nonce with AES-128. It is provided only to verify
+//! (and, where necessary, produce) tags for data from older tools that use
+//! it, e.g. djb's `poly1305aes` library. New protocols should use an AEAD
+//! such as `aead::CHACHA20_POLY1305` instead.
+//!
+//! Unlike a `poly1305::Key`, a Poly1305-AES key may be used for many
+//! messages, but each message must use a different nonce.
+//!
+//! [The Poly1305-AES message-authentication code]:
+//!     https://cr.yp.to/mac/poly1305-20050329.pdf
+
+use {constant_time, error, init};
+use secret::Zeroize;
+use super::aes_ocb::{AES_KEY, AES_MAX_ROUNDS, BLOCK_LEN, GFp_AES_encrypt,
+                     GFp_AES_set_encrypt_key};
+use super::poly1305;
+
+/// The length of Poly1305-AES keys: a 16-byte AES-128 key `k` followed by
+/// the 16-byte Poly1305 multiplier `r`, as in `poly1305aes`'s `kr`.
+pub const KEY_LEN: usize = 32;
+
+/// The length of Poly1305-AES nonces.
+pub const NONCE_LEN: usize = BLOCK_LEN;
+
+/// The length of Poly1305-AES tags.
+pub const TAG_LEN: usize = poly1305::TAG_LEN;
+
+/// A Poly1305-AES key.
+pub struct Key {
+    aes_key: AES_KEY,
+    r: [u8; 16],
+}
+
+impl Key {
+    /// Create a new key. `key_bytes` must be exactly `KEY_LEN` bytes long.
+    ///
+    /// Poly1305-AES requires some bits of `r` to be clear, which
+    /// `poly1305aes_clamp` ensures when the key is generated. Keys in which
+    /// they aren't clear, e.g. because `k` and `r` were swapped, are rejected
+    /// with `error::KeyRejected::InvalidEncoding`.
+    pub fn new(key_bytes: &[u8]) -> Result<Key, error::KeyRejected> {
+        init::init_once();
+        try!(error::KeyRejected::check_len(key_bytes.len(), KEY_LEN));
+        let (k, r) = key_bytes.split_at(16);
+        if !is_clamped(r) {
+            return Err(error::KeyRejected::InvalidEncoding);
+        }
+        let mut key = Key {
+            aes_key: AES_KEY {
+                rd_key: [0u32; 4 * (AES_MAX_ROUNDS + 1)],
+                rounds: 0,
+            },
+            r: [0u8; 16],
+        };
+        key.r.copy_from_slice(r);
+        if unsafe {
+            GFp_AES_set_encrypt_key(k.as_ptr(), 128, &mut key.aes_key)
+        } != 0 {
+            return Err(error::KeyRejected::Unexpected);
+        }
+        Ok(key)
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        self.aes_key.rd_key.zeroize();
+        self.r.zeroize();
+    }
+}
+
+/// Calculates the Poly1305-AES tag of `data` using `key` and `nonce`.
+///
+/// C analog: `poly1305aes_authenticate`
+pub fn sign(key: &Key, nonce: &[u8; NONCE_LEN], data: &[u8])
+            -> poly1305::Tag {
+    // The one-time Poly1305 key is `r || AES_k(nonce)`.
+    let mut one_time_key = [0u8; poly1305::KEY_LEN];
+    {
+        let (r, s) = one_time_key.split_at_mut(16);
+        r.copy_from_slice(&key.r);
+        unsafe {
+            GFp_AES_encrypt(nonce.as_ptr(), s.as_mut_ptr(), &key.aes_key);
+        }
+    }
+    let one_time_key = poly1305::Key::new(&one_time_key).unwrap();
+    poly1305::sign(one_time_key, data)
+}
+
+/// Calculates the Poly1305-AES tag of `data` using `key` and `nonce`, and
+/// verifies whether it equals `tag`. The comparison is done in constant time.
+///
+/// C analog: `poly1305aes_verify`
+pub fn verify(key: &Key, nonce: &[u8; NONCE_LEN], data: &[u8], tag: &[u8])
+              -> Result<(), error::Unspecified> {
+    let calculated = sign(key, nonce, data);
+    constant_time::verify_slices_are_equal(calculated.as_ref(), tag)
+}
+
+// r[3], r[7], r[11], and r[15] must be less than 16, and r[4], r[8], and
+// r[12] must be multiples of 4.
+fn is_clamped(r: &[u8]) -> bool {
+    (r[3] | r[7] | r[11] | r[15]) & 0xf0 == 0 &&
+        (r[4] | r[8] | r[12]) & 0x03 == 0
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {error, test};
+    use super::*;
+
+    #[test]
+    fn test_poly1305_aes() {
+        test::from_file("src/aead/poly1305_aes_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+            let k = test_case.consume_bytes("K");
+            let r = test_case.consume_bytes("R");
+            let nonce = test_case.consume_bytes("Nonce");
+            let nonce = slice_as_array_ref!(&nonce, NONCE_LEN).unwrap();
+            let input = test_case.consume_bytes("Input");
+            let expected_tag = test_case.consume_bytes("MAC");
+
+            let mut key_bytes = k.clone();
+            key_bytes.extend_from_slice(&r);
+            let key = Key::new(&key_bytes).unwrap();
+            assert_eq!(sign(&key, nonce, &input).as_ref(), &expected_tag[..]);
+            assert_eq!(verify(&key, nonce, &input, &expected_tag), Ok(()));
+
+            let mut bad_tag = expected_tag.clone();
+            bad_tag[0] ^= 1;
+            assert_eq!(verify(&key, nonce, &input, &bad_tag),
+                       Err(error::Unspecified));
+
+            let mut bad_nonce = *nonce;
+            bad_nonce[NONCE_LEN - 1] ^= 1;
+            assert_eq!(verify(&key, &bad_nonce, &input, &expected_tag),
+                       Err(error::Unspecified));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_poly1305_aes_key() {
+        assert_eq!(Key::new(&[0u8; KEY_LEN - 1]).err(),
+                   Some(error::KeyRejected::TooShort));
+        assert_eq!(Key::new(&[0u8; KEY_LEN + 1]).err(),
+                   Some(error::KeyRejected::TooLong));
+        assert!(Key::new(&[0u8; KEY_LEN]).is_ok());
+        for &(i, bit) in &[(3, 0x10), (7, 0x80), (11, 0x20), (15, 0x40),
+                           (4, 0x01), (8, 0x02), (12, 0x01)] {
+            let mut key_bytes = [0u8; KEY_LEN];
+            key_bytes[16 + i] = bit;
+            assert_eq!(Key::new(&key_bytes).err(),
+                       Some(error::KeyRejected::InvalidEncoding));
+        }
+    }
+}
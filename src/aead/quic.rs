@@ -0,0 +1,574 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! QUIC header protection, as described in [RFC 9001 Section 5.4].
+//!
+//! This should only be used by QUIC implementations. The packet payload is
+//! protected with an AEAD from `ring::aead`; header protection then masks
+//! the packet number and some bits of the first byte of the header, using a
+//! mask that is computed from a sample of the protected payload with a
+//! separate header protection key.
+//!
+//! The additional data for the payload's AEAD is the unprotected header, up to
+//! and including the packet number. `ShortHeader` and `LongHeader` write that
+//! header into the packet, encoding the packet number and the `Length` field
+//! as described in [RFC 9000 Section 17], and return a `HeaderLayout` that
+//! says how much of the packet is the additional data and where the packet
+//! number is.
+//!
+//! [RFC 9001 Section 5.4]: https://tools.ietf.org/html/rfc9001#section-5.4
+//! [RFC 9000 Section 17]: https://tools.ietf.org/html/rfc9000#section-17
+
+use {error, init, polyfill};
+use super::aes_ocb::{AES_KEY, AES_MAX_ROUNDS, BLOCK_LEN, Block,
+                     GFp_AES_encrypt, GFp_AES_set_encrypt_key};
+use super::chacha20_poly1305::{CHACHA20_KEY_LEN, chacha20_xor_in_place};
+
+/// The length of the sample of the protected payload.
+pub const SAMPLE_LEN: usize = 16;
+
+/// The length of the mask.
+pub const MASK_LEN: usize = 5;
+
+/// A QUIC header protection algorithm.
+pub struct Algorithm {
+    id: AlgorithmID,
+    key_len: usize,
+    name: &'static str,
+}
+
+impl Algorithm {
+    /// The name of the algorithm, which is the name of its static in this
+    /// module, e.g. `"AES_128"` for `AES_128`.
+    #[inline(always)]
+    pub fn name(&self) -> &'static str { self.name }
+
+    /// The length of the key.
+    #[inline(always)]
+    pub fn key_len(&self) -> usize { self.key_len }
+}
+
+enum AlgorithmID {
+    AES,
+    CHACHA20,
+}
+
+/// AES-128 header protection, for use with `aead::AES_128_GCM`.
+pub static AES_128: Algorithm = Algorithm {
+    id: AlgorithmID::AES,
+    key_len: 128 / 8,
+    name: "AES_128",
+};
+
+/// AES-256 header protection, for use with `aead::AES_256_GCM`.
+pub static AES_256: Algorithm = Algorithm {
+    id: AlgorithmID::AES,
+    key_len: 256 / 8,
+    name: "AES_256",
+};
+
+/// ChaCha20 header protection, for use with `aead::CHACHA20_POLY1305`.
+pub static CHACHA20: Algorithm = Algorithm {
+    id: AlgorithmID::CHACHA20,
+    key_len: CHACHA20_KEY_LEN,
+    name: "CHACHA20",
+};
+
+/// A QUIC header protection key.
+pub struct HeaderProtectionKey {
+    // Only the key for `algorithm` is used; the other one is all zeros.
+    aes_key: AES_KEY,
+    chacha20_key: [u32; CHACHA20_KEY_LEN / 4],
+    algorithm: &'static Algorithm,
+}
+
+impl HeaderProtectionKey {
+    /// Constructs a new header protection key.
+    ///
+    /// `key_bytes` must be exactly `algorithm.key_len()` bytes long.
+    pub fn new(algorithm: &'static Algorithm, key_bytes: &[u8])
+               -> Result<HeaderProtectionKey, error::KeyRejected> {
+        init::init_once();
+        try!(error::KeyRejected::check_len(key_bytes.len(),
+                                           algorithm.key_len));
+        let mut key = HeaderProtectionKey {
+            aes_key: AES_KEY {
+                rd_key: [0u32; 4 * (AES_MAX_ROUNDS + 1)],
+                rounds: 0,
+            },
+            chacha20_key: [0u32; CHACHA20_KEY_LEN / 4],
+            algorithm: algorithm,
+        };
+        match algorithm.id {
+            AlgorithmID::AES => {
+                if unsafe {
+                    GFp_AES_set_encrypt_key(key_bytes.as_ptr(),
+                                            8 * key_bytes.len() as u32,
+                                            &mut key.aes_key)
+                } != 0 {
+                    return Err(error::KeyRejected::Unexpected);
+                }
+            },
+            AlgorithmID::CHACHA20 => {
+                for (k, chunk) in key.chacha20_key.iter_mut()
+                                     .zip(key_bytes.chunks(4)) {
+                    *k = polyfill::slice::u32_from_le_u8(
+                        slice_as_array_ref!(chunk, 4).unwrap());
+                }
+            },
+        }
+        Ok(key)
+    }
+
+    /// Computes the mask for the sample `sample`, which must be exactly
+    /// `SAMPLE_LEN` bytes long.
+    pub fn new_mask(&self, sample: &[u8])
+                    -> Result<[u8; MASK_LEN], error::Unspecified> {
+        let sample = try!(slice_as_array_ref!(sample, SAMPLE_LEN));
+        let mut mask = [0u8; MASK_LEN];
+        match self.algorithm.id {
+            // mask = AES-ECB(hp_key, sample)
+            AlgorithmID::AES => {
+                let mut block: Block = *sample;
+                debug_assert_eq!(block.len(), BLOCK_LEN);
+                unsafe {
+                    GFp_AES_encrypt(block.as_ptr(), block.as_mut_ptr(),
+                                    &self.aes_key);
+                }
+                mask.copy_from_slice(&block[..MASK_LEN]);
+            },
+
+            // The first four bytes of the sample are the block counter and
+            // the remaining twelve bytes are the nonce, and the mask is the
+            // first bytes of the key stream.
+            AlgorithmID::CHACHA20 => {
+                let mut counter = [0u32; 4];
+                for (c, chunk) in counter.iter_mut().zip(sample.chunks(4)) {
+                    *c = polyfill::slice::u32_from_le_u8(
+                        slice_as_array_ref!(chunk, 4).unwrap());
+                }
+                chacha20_xor_in_place(&self.chacha20_key, &counter,
+                                      &mut mask);
+            },
+        }
+        Ok(mask)
+    }
+
+    /// The key's algorithm.
+    #[inline(always)]
+    pub fn algorithm(&self) -> &'static Algorithm { self.algorithm }
+}
+
+/// The maximum length of a connection ID in QUIC version 1.
+pub const MAX_CID_LEN: usize = 20;
+
+/// The largest packet number.
+pub const MAX_PACKET_NUMBER: u64 = (1 << 62) - 1;
+
+/// A packet number and the length of its truncated encoding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PacketNumber {
+    value: u64,
+    len: usize,
+}
+
+impl PacketNumber {
+    /// Constructs the packet number `value`, with the shortest encoding that
+    /// the peer can decode given that `largest_acked` is the largest packet
+    /// number that it has acknowledged in the same packet number space, as
+    /// described in [RFC 9000 Section 17.1].
+    ///
+    /// [RFC 9000 Section 17.1]:
+    ///     https://tools.ietf.org/html/rfc9000#section-17.1
+    pub fn new(value: u64, largest_acked: Option<u64>)
+               -> Result<PacketNumber, error::Unspecified> {
+        if value > MAX_PACKET_NUMBER {
+            return Err(error::Unspecified);
+        }
+        let num_unacked = match largest_acked {
+            Some(largest_acked) if largest_acked < value =>
+                value - largest_acked,
+            Some(_) => { return Err(error::Unspecified); },
+            None => value + 1,
+        };
+        // The encoding must be able to represent at least twice the number
+        // of unacknowledged packets, i.e. min_bits = ceil(log2(num_unacked))
+        // + 1.
+        let min_bits = 64 - (num_unacked - 1).leading_zeros() as usize + 1;
+        PacketNumber::with_len(value, (min_bits + 7) / 8)
+    }
+
+    /// Constructs the packet number `value` with a `len`-byte encoding.
+    /// `len` must be between 1 and 4, inclusive.
+    pub fn with_len(value: u64, len: usize)
+                    -> Result<PacketNumber, error::Unspecified> {
+        if value > MAX_PACKET_NUMBER || len < 1 || len > 4 {
+            return Err(error::Unspecified);
+        }
+        Ok(PacketNumber { value: value, len: len })
+    }
+
+    /// The full packet number.
+    #[inline(always)]
+    pub fn value(&self) -> u64 { self.value }
+
+    /// The length of the encoded packet number.
+    #[inline(always)]
+    pub fn len(&self) -> usize { self.len }
+
+    fn write(&self, out: &mut Writer) -> Result<(), error::Unspecified> {
+        for i in (0..self.len).rev() {
+            try!(out.write_byte((self.value >> (8 * i)) as u8));
+        }
+        Ok(())
+    }
+}
+
+/// Where the header is in a packet that was written by `ShortHeader::write`
+/// or `LongHeader::write`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HeaderLayout {
+    len: usize,
+    packet_number_offset: usize,
+}
+
+impl HeaderLayout {
+    /// The length of the header, including the packet number. The first
+    /// `len()` bytes of the packet are the additional data for the payload's
+    /// AEAD, and the protected payload starts right after them.
+    #[inline(always)]
+    pub fn len(&self) -> usize { self.len }
+
+    /// The offset of the packet number in the packet. The header protection
+    /// sample starts four bytes after it.
+    #[inline(always)]
+    pub fn packet_number_offset(&self) -> usize { self.packet_number_offset }
+}
+
+/// The type of a long header packet that has a packet number.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LongPacketType {
+    /// An Initial packet.
+    Initial,
+
+    /// A 0-RTT packet.
+    ZeroRtt,
+
+    /// A Handshake packet.
+    Handshake,
+}
+
+/// A long packet header.
+pub struct LongHeader<'a> {
+    packet_type: LongPacketType,
+    version: u32,
+    dcid: &'a [u8],
+    scid: &'a [u8],
+    token: &'a [u8],
+}
+
+impl<'a> LongHeader<'a> {
+    /// Constructs a long header. `dcid` and `scid` are the destination and
+    /// source connection IDs, which may each be at most `MAX_CID_LEN` bytes
+    /// long. `token` must be empty unless `packet_type` is
+    /// `LongPacketType::Initial`.
+    pub fn new(packet_type: LongPacketType, version: u32, dcid: &'a [u8],
+               scid: &'a [u8], token: &'a [u8])
+               -> Result<LongHeader<'a>, error::Unspecified> {
+        if dcid.len() > MAX_CID_LEN || scid.len() > MAX_CID_LEN {
+            return Err(error::Unspecified);
+        }
+        if packet_type != LongPacketType::Initial && !token.is_empty() {
+            return Err(error::Unspecified);
+        }
+        Ok(LongHeader {
+            packet_type: packet_type,
+            version: version,
+            dcid: dcid,
+            scid: scid,
+            token: token,
+        })
+    }
+
+    /// Writes the unprotected header for a packet with the packet number
+    /// `packet_number` and a `payload_len`-byte protected payload (including
+    /// the AEAD tag) to the start of `out`.
+    pub fn write(&self, packet_number: &PacketNumber, payload_len: usize,
+                 out: &mut [u8])
+                 -> Result<HeaderLayout, error::Unspecified> {
+        let type_bits = match self.packet_type {
+            LongPacketType::Initial => 0,
+            LongPacketType::ZeroRtt => 1,
+            LongPacketType::Handshake => 2,
+        };
+        let length = try!((packet_number.len as u64)
+                              .checked_add(payload_len as u64)
+                              .ok_or(error::Unspecified));
+
+        let mut out = Writer { out: out, len: 0 };
+        try!(out.write_byte(0x80 | FIXED_BIT | (type_bits << 4) |
+                            (packet_number.len as u8 - 1)));
+        try!(out.write(&polyfill::slice::be_u8_from_u32(self.version)));
+        try!(out.write_byte(self.dcid.len() as u8));
+        try!(out.write(self.dcid));
+        try!(out.write_byte(self.scid.len() as u8));
+        try!(out.write(self.scid));
+        if self.packet_type == LongPacketType::Initial {
+            try!(out.write_varint(self.token.len() as u64));
+            try!(out.write(self.token));
+        }
+        try!(out.write_varint(length));
+        let packet_number_offset = out.len;
+        try!(packet_number.write(&mut out));
+        Ok(HeaderLayout {
+            len: out.len,
+            packet_number_offset: packet_number_offset,
+        })
+    }
+}
+
+/// A short packet header, as used by 1-RTT packets.
+pub struct ShortHeader<'a> {
+    dcid: &'a [u8],
+    spin_bit: bool,
+    key_phase: bool,
+}
+
+impl<'a> ShortHeader<'a> {
+    /// Constructs a short header. `dcid` is the destination connection ID,
+    /// which may be at most `MAX_CID_LEN` bytes long.
+    pub fn new(dcid: &'a [u8], spin_bit: bool, key_phase: bool)
+               -> Result<ShortHeader<'a>, error::Unspecified> {
+        if dcid.len() > MAX_CID_LEN {
+            return Err(error::Unspecified);
+        }
+        Ok(ShortHeader { dcid: dcid, spin_bit: spin_bit, key_phase: key_phase })
+    }
+
+    /// Writes the unprotected header for a packet with the packet number
+    /// `packet_number` to the start of `out`.
+    pub fn write(&self, packet_number: &PacketNumber, out: &mut [u8])
+                 -> Result<HeaderLayout, error::Unspecified> {
+        let mut out = Writer { out: out, len: 0 };
+        try!(out.write_byte(FIXED_BIT | ((self.spin_bit as u8) << 5) |
+                            ((self.key_phase as u8) << 2) |
+                            (packet_number.len as u8 - 1)));
+        try!(out.write(self.dcid));
+        let packet_number_offset = out.len;
+        try!(packet_number.write(&mut out));
+        Ok(HeaderLayout {
+            len: out.len,
+            packet_number_offset: packet_number_offset,
+        })
+    }
+}
+
+const FIXED_BIT: u8 = 0x40;
+
+struct Writer<'a> {
+    out: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Writer<'a> {
+    fn write_byte(&mut self, b: u8) -> Result<(), error::Unspecified> {
+        self.write(&[b])
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), error::Unspecified> {
+        let end = try!(self.len.checked_add(bytes.len())
+                                .ok_or(error::Unspecified));
+        if end > self.out.len() {
+            return Err(error::Unspecified);
+        }
+        self.out[self.len..end].copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+
+    // A variable-length integer in its shortest encoding, as described in
+    // https://tools.ietf.org/html/rfc9000#section-16.
+    fn write_varint(&mut self, value: u64) -> Result<(), error::Unspecified> {
+        let (prefix, len) =
+            if value < 1 << 6 {
+                (0x00, 1)
+            } else if value < 1 << 14 {
+                (0x40, 2)
+            } else if value < 1 << 30 {
+                (0x80, 4)
+            } else if value < 1 << 62 {
+                (0xc0, 8)
+            } else {
+                return Err(error::Unspecified);
+            };
+        for i in (0..len).rev() {
+            let mut b = (value >> (8 * i)) as u8;
+            if i == len - 1 {
+                b |= prefix;
+            }
+            try!(self.write_byte(b));
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {aead, error, test};
+    use super::*;
+
+    #[test]
+    fn test_quic_header_protection() {
+        test::from_file("src/aead/quic_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let algorithm = match test_case.consume_string("Algorithm")
+                                           .as_ref() {
+                "AES_128" => &AES_128,
+                "AES_256" => &AES_256,
+                "CHACHA20" => &CHACHA20,
+                _ => unreachable!(),
+            };
+            let key_bytes = test_case.consume_bytes("Key");
+            let sample = test_case.consume_bytes("Sample");
+            let expected_mask = test_case.consume_bytes("Mask");
+
+            let key = try!(HeaderProtectionKey::new(algorithm, &key_bytes));
+            assert_eq!(key.algorithm().name(), algorithm.name());
+            let mask = try!(key.new_mask(&sample));
+            assert_eq!(&mask[..], &expected_mask[..]);
+
+            assert!(key.new_mask(&sample[..(SAMPLE_LEN - 1)]).is_err());
+            let mut long_sample = sample.clone();
+            long_sample.push(0);
+            assert!(key.new_mask(&long_sample).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_quic_key_lengths() {
+        for alg in &[&AES_128, &AES_256, &CHACHA20] {
+            let key_bytes = [0u8; 33];
+            let key_len = alg.key_len();
+            assert!(HeaderProtectionKey::new(alg, &key_bytes[..key_len])
+                        .is_ok());
+            assert!(HeaderProtectionKey::new(alg, &key_bytes[..(key_len - 1)])
+                        .is_err());
+            assert!(HeaderProtectionKey::new(alg, &key_bytes[..(key_len + 1)])
+                        .is_err());
+        }
+    }
+
+    #[test]
+    fn test_quic_packet_number_len() {
+        // The examples in RFC 9000 Section 17.1 and Appendix A.2.
+        assert_eq!(PacketNumber::new(0xac5c02, Some(0xabe8b3)).unwrap().len(),
+                   2);
+        assert_eq!(PacketNumber::new(0xace8fe, Some(0xabe8b3)).unwrap().len(),
+                   3);
+
+        assert_eq!(PacketNumber::new(0, None).unwrap().len(), 1);
+        assert_eq!(PacketNumber::new(127, None).unwrap().len(), 1);
+        assert_eq!(PacketNumber::new(128, None).unwrap().len(), 2);
+        assert_eq!(PacketNumber::new(1000, Some(872)).unwrap().len(), 1);
+        assert_eq!(PacketNumber::new(1000, Some(871)).unwrap().len(), 2);
+        assert_eq!(PacketNumber::new((1 << 31) - 1, None).unwrap().len(), 4);
+        assert_eq!(PacketNumber::new(1 << 31, None), Err(error::Unspecified));
+        assert!(PacketNumber::new(MAX_PACKET_NUMBER,
+                                  Some(MAX_PACKET_NUMBER - 1)).is_ok());
+        assert!(PacketNumber::new(MAX_PACKET_NUMBER + 1, None).is_err());
+
+        // The packet number must be larger than any acknowledged one.
+        assert!(PacketNumber::new(5, Some(5)).is_err());
+        assert!(PacketNumber::new(5, Some(6)).is_err());
+
+        assert!(PacketNumber::with_len(0, 0).is_err());
+        assert!(PacketNumber::with_len(0, 5).is_err());
+    }
+
+    #[test]
+    fn test_quic_long_header() {
+        // The client Initial packet in RFC 9001 Appendix A.2.
+        let dcid = test::from_hex("8394c8f03e515708").unwrap();
+        let header = LongHeader::new(LongPacketType::Initial, 1, &dcid, &[],
+                                     &[]).unwrap();
+        let pn = PacketNumber::with_len(2, 4).unwrap();
+        let mut packet = [0u8; 64];
+        let layout = header.write(&pn, 1162 + aead::TAG_LEN, &mut packet)
+                           .unwrap();
+        assert_eq!(&packet[..layout.len()],
+                   &test::from_hex(
+                       "c300000001088394c8f03e5157080000449e00000002")
+                        .unwrap()[..]);
+        assert_eq!(layout.packet_number_offset(), 18);
+
+        // The server Initial packet in RFC 9001 Appendix A.3.
+        let scid = test::from_hex("f067a5502a4262b5").unwrap();
+        let header = LongHeader::new(LongPacketType::Initial, 1, &[], &scid,
+                                     &[]).unwrap();
+        let pn = PacketNumber::with_len(1, 2).unwrap();
+        let layout = header.write(&pn, 99 + aead::TAG_LEN, &mut packet)
+                           .unwrap();
+        assert_eq!(&packet[..layout.len()],
+                   &test::from_hex("c1000000010008f067a5502a4262b50040750001")
+                        .unwrap()[..]);
+        assert_eq!(layout.packet_number_offset(), 18);
+
+        // A Handshake packet has no token.
+        let header = LongHeader::new(LongPacketType::Handshake, 1, &dcid,
+                                     &scid, &[]).unwrap();
+        let pn = PacketNumber::new(0, None).unwrap();
+        let layout = header.write(&pn, 20, &mut packet).unwrap();
+        assert_eq!(&packet[..layout.len()],
+                   &test::from_hex(
+                       "e000000001088394c8f03e51570808f067a5502a4262b51500")
+                        .unwrap()[..]);
+
+        // The buffer is too small.
+        assert!(header.write(&pn, 20, &mut packet[..(layout.len() - 1)])
+                      .is_err());
+
+        assert!(LongHeader::new(LongPacketType::Handshake, 1, &dcid, &scid,
+                                &[1]).is_err());
+        assert!(LongHeader::new(LongPacketType::Initial, 1,
+                                &[0u8; MAX_CID_LEN + 1], &scid, &[]).is_err());
+        assert!(LongHeader::new(LongPacketType::ZeroRtt, 1, &dcid,
+                                &[0u8; MAX_CID_LEN + 1], &[]).is_err());
+    }
+
+    #[test]
+    fn test_quic_short_header() {
+        // The ChaCha20-Poly1305 short header packet in RFC 9001 Appendix
+        // A.5.
+        let header = ShortHeader::new(&[], false, false).unwrap();
+        let pn = PacketNumber::with_len(654360564, 3).unwrap();
+        let mut packet = [0u8; 32];
+        let layout = header.write(&pn, &mut packet).unwrap();
+        assert_eq!(&packet[..layout.len()], &[0x42, 0x00, 0xbf, 0xf4]);
+        assert_eq!(layout.packet_number_offset(), 1);
+
+        let dcid = [0xaa; 8];
+        let header = ShortHeader::new(&dcid, true, true).unwrap();
+        let pn = PacketNumber::new(0x1234, Some(0x1200)).unwrap();
+        let layout = header.write(&pn, &mut packet).unwrap();
+        assert_eq!(&packet[..layout.len()],
+                   &test::from_hex("64aaaaaaaaaaaaaaaa34").unwrap()[..]);
+        assert_eq!(layout.packet_number_offset(), 9);
+
+        assert!(ShortHeader::new(&[0u8; MAX_CID_LEN + 1], false, false)
+                    .is_err());
+    }
+}
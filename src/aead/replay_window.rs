@@ -0,0 +1,215 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Anti-replay sliding windows for datagram protocols.
+//!
+//! A receiver of a datagram protocol, e.g. IPsec ESP or WireGuard, can't
+//! require the records to arrive in order, but it must not accept the same
+//! record twice. A `ReplayWindow` remembers which of the highest
+//! `window_len()` sequence numbers have been accepted, as described in
+//! [RFC 4303 Section 3.4.3], and rejects the ones that were accepted before
+//! and the ones that are too old to tell.
+//!
+//! The receiver uses a `ReplayWindow` in two steps. Before opening a record,
+//! it calls `check`, so that replayed records are dropped without the cost
+//! of opening them. Only after the record was opened successfully does it
+//! call `mark`; marking a sequence number of a record that isn't authentic
+//! would let an attacker move the window forward.
+//!
+//! The sequence numbers are sent in the clear, so `check` and `mark` don't
+//! need to be, and aren't, constant-time.
+//!
+//! [RFC 4303 Section 3.4.3]: https://tools.ietf.org/html/rfc4303#section-3.4.3
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::aead;
+//!
+//! # fn main() { foo().unwrap() }
+//! # fn foo() -> Result<(), ring::error::Unspecified> {
+//! let mut window = try!(aead::ReplayWindow::new(64));
+//!
+//! for &sequence_number in &[1, 0, 3] {
+//!     try!(window.check(sequence_number));
+//!     // ... open the record with `aead::open_in_place` ...
+//!     try!(window.mark(sequence_number));
+//! }
+//!
+//! assert!(window.check(3).is_err());
+//! assert!(window.check(2).is_ok());
+//! # Ok(())
+//! # }
+//! ```
+
+use error;
+
+/// The largest supported window length.
+pub const MAX_WINDOW_LEN: usize = 128;
+
+const WORD_BITS: u64 = 64;
+
+/// A sliding window over the highest sequence numbers that were accepted.
+///
+/// Bit `i` of the window is set if `highest - i` was accepted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayWindow {
+    highest: Option<u64>,
+    bitmap: [u64; MAX_WINDOW_LEN / 64],
+    window_len: u64,
+}
+
+impl ReplayWindow {
+    /// Constructs a window that remembers the highest `window_len` sequence
+    /// numbers. `window_len` must be 64, the length RFC 4303 recommends, or
+    /// 128, for links that reorder records more.
+    pub fn new(window_len: usize)
+               -> Result<ReplayWindow, error::Unspecified> {
+        if window_len != 64 && window_len != MAX_WINDOW_LEN {
+            return Err(error::Unspecified);
+        }
+        Ok(ReplayWindow {
+            highest: None,
+            bitmap: [0; MAX_WINDOW_LEN / 64],
+            window_len: window_len as u64,
+        })
+    }
+
+    /// Fails if `sequence_number` was accepted before, or if it is
+    /// `window_len()` or more below the highest sequence number that was
+    /// accepted. Doesn't change the window.
+    pub fn check(&self, sequence_number: u64)
+                 -> Result<(), error::Unspecified> {
+        let highest = match self.highest {
+            Some(highest) if sequence_number <= highest => highest,
+            _ => { return Ok(()); },
+        };
+        let age = highest - sequence_number;
+        if age >= self.window_len {
+            return Err(error::Unspecified);
+        }
+        let word = self.bitmap[(age / WORD_BITS) as usize];
+        if (word >> (age % WORD_BITS)) & 1 != 0 {
+            return Err(error::Unspecified);
+        }
+        Ok(())
+    }
+
+    /// Records that `sequence_number` was accepted, sliding the window
+    /// forward if it is the highest one so far.
+    ///
+    /// Fails, without changing the window, if `check(sequence_number)`
+    /// fails. Call `mark` only after the record with `sequence_number` has
+    /// been authenticated.
+    pub fn mark(&mut self, sequence_number: u64)
+                -> Result<(), error::Unspecified> {
+        try!(self.check(sequence_number));
+        match self.highest {
+            Some(highest) if sequence_number <= highest => {
+                let age = highest - sequence_number;
+                self.bitmap[(age / WORD_BITS) as usize] |=
+                    1 << (age % WORD_BITS);
+            },
+            Some(highest) => {
+                self.shift(sequence_number - highest);
+                self.bitmap[0] |= 1;
+                self.highest = Some(sequence_number);
+            },
+            None => {
+                self.bitmap[0] = 1;
+                self.highest = Some(sequence_number);
+            },
+        }
+        Ok(())
+    }
+
+    /// The highest sequence number that was accepted, if any.
+    #[inline(always)]
+    pub fn highest(&self) -> Option<u64> { self.highest }
+
+    /// The number of sequence numbers the window remembers.
+    #[inline(always)]
+    pub fn window_len(&self) -> usize { self.window_len as usize }
+
+    // Moves every bit of the bitmap `shift` positions up, dropping the bits
+    // that leave the window.
+    fn shift(&mut self, shift: u64) {
+        if shift >= self.window_len {
+            self.bitmap = [0; MAX_WINDOW_LEN / 64];
+        } else if shift >= WORD_BITS {
+            self.bitmap[1] = self.bitmap[0] << (shift - WORD_BITS);
+            self.bitmap[0] = 0;
+        } else {
+            self.bitmap[1] = (self.bitmap[1] << shift) |
+                             (self.bitmap[0] >> (WORD_BITS - shift));
+            self.bitmap[0] <<= shift;
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use error;
+    use super::*;
+
+    #[test]
+    fn test_replay_window_64() {
+        let mut window = ReplayWindow::new(64).unwrap();
+        assert_eq!(window.highest(), None);
+        assert!(window.check(100).is_ok());
+        assert_eq!(window.mark(100), Ok(()));
+        assert_eq!(window.check(100), Err(error::Unspecified));
+        assert_eq!(window.mark(100), Err(error::Unspecified));
+        assert!(window.check(99).is_ok());
+        assert!(window.check(100 - 63).is_ok());
+        assert!(window.check(100 - 64).is_err());
+        assert_eq!(window.mark(99), Ok(()));
+        assert_eq!(window.mark(163), Ok(()));
+        // 99 is now outside the window, and 100 is still remembered.
+        assert!(window.check(99).is_err());
+        assert!(window.check(100).is_err());
+        assert!(window.check(101).is_ok());
+        assert_eq!(window.mark(1000), Ok(()));
+        assert_eq!(window.highest(), Some(1000));
+        assert!(window.check(163).is_err());
+        assert!(window.check(999).is_ok());
+    }
+
+    #[test]
+    fn test_replay_window_128() {
+        let mut window = ReplayWindow::new(128).unwrap();
+        assert_eq!(window.window_len(), 128);
+        assert_eq!(window.mark(0), Ok(()));
+        assert_eq!(window.mark(10), Ok(()));
+        // Shifts that cross the word boundary keep the older bits.
+        assert_eq!(window.mark(80), Ok(()));
+        assert!(window.check(0).is_err());
+        assert!(window.check(10).is_err());
+        assert!(window.check(1).is_ok());
+        assert_eq!(window.mark(137), Ok(()));
+        assert!(window.check(10).is_err());
+        assert!(window.check(11).is_ok());
+        assert!(window.check(80).is_err());
+        assert!(window.check(137 - 128).is_err());
+        assert_eq!(window.mark(137 + 100), Ok(()));
+        assert!(window.check(137).is_err());
+        assert!(window.check(138).is_ok());
+        assert!(window.check(80).is_err());
+
+        assert!(ReplayWindow::new(0).is_err());
+        assert!(ReplayWindow::new(96).is_err());
+        assert!(ReplayWindow::new(256).is_err());
+    }
+}
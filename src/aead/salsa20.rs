@@ -0,0 +1,199 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The Salsa20 and XSalsa20 stream ciphers, as described in [Salsa20
+//! specification] and [Extending the Salsa20 nonce].
+//!
+//! [Salsa20 specification]: https://cr.yp.to/snuffle/spec.pdf
+//! [Extending the Salsa20 nonce]: https://cr.yp.to/snuffle/xsalsa-20110204.pdf
+
+use polyfill;
+
+pub const KEY_LEN: usize = 32;
+pub const SALSA20_NONCE_LEN: usize = 8;
+pub const XSALSA20_NONCE_LEN: usize = 24;
+
+const BLOCK_LEN: usize = 64;
+
+// "expand 32-byte k"
+const SIGMA: [u32; 4] = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574];
+
+/// XORs `in_out[in_prefix_len..]` with the XSalsa20 keystream, starting
+/// `keystream_offset` bytes into the keystream, writing the result to
+/// `in_out[..(in_out.len() - in_prefix_len)]`.
+pub fn xsalsa20_xor_in_place(key: &[u8; KEY_LEN],
+                             nonce: &[u8; XSALSA20_NONCE_LEN],
+                             keystream_offset: usize, in_out: &mut [u8],
+                             in_prefix_len: usize) {
+    let subkey = hsalsa20(key, slice_as_array_ref!(&nonce[..16], 16).unwrap());
+    salsa20_xor_in_place(&subkey,
+                         slice_as_array_ref!(&nonce[16..], 8).unwrap(),
+                         keystream_offset, in_out, in_prefix_len);
+}
+
+/// Like `xsalsa20_xor_in_place`, but for Salsa20 with a 64-bit nonce. The
+/// keystream starts at block counter zero.
+pub fn salsa20_xor_in_place(key: &[u8; KEY_LEN],
+                            nonce: &[u8; SALSA20_NONCE_LEN],
+                            keystream_offset: usize, in_out: &mut [u8],
+                            in_prefix_len: usize) {
+    let len = in_out.len() - in_prefix_len;
+    let mut counter = polyfill::u64_from_usize(keystream_offset / BLOCK_LEN);
+    let mut block_offset = keystream_offset % BLOCK_LEN;
+    let mut pos = 0;
+    while pos < len {
+        let mut nonce_and_counter = [0u8; 16];
+        nonce_and_counter[..8].copy_from_slice(nonce);
+        for (i, b) in nonce_and_counter[8..].iter_mut().enumerate() {
+            *b = (counter >> (8 * i)) as u8;
+        }
+        let keystream = salsa20_block(key, &nonce_and_counter);
+        while block_offset < BLOCK_LEN && pos < len {
+            in_out[pos] = in_out[in_prefix_len + pos] ^ keystream[block_offset];
+            pos += 1;
+            block_offset += 1;
+        }
+        block_offset = 0;
+        counter = counter.wrapping_add(1);
+    }
+}
+
+/// The HSalsa20 function, used to derive XSalsa20 subkeys.
+pub fn hsalsa20(key: &[u8; KEY_LEN], nonce: &[u8; 16]) -> [u8; KEY_LEN] {
+    let input = initial_state(key, nonce);
+    let mut x = input;
+    rounds(&mut x);
+    let mut out = [0u8; KEY_LEN];
+    for (i, &word) in [x[0], x[5], x[10], x[15], x[6], x[7], x[8], x[9]]
+                          .iter().enumerate() {
+        le_u8_from_u32(&mut out[(4 * i)..(4 * i + 4)], word);
+    }
+    out
+}
+
+fn salsa20_block(key: &[u8; KEY_LEN], nonce_and_counter: &[u8; 16])
+                 -> [u8; BLOCK_LEN] {
+    let input = initial_state(key, nonce_and_counter);
+    let mut x = input;
+    rounds(&mut x);
+    let mut out = [0u8; BLOCK_LEN];
+    for i in 0..16 {
+        le_u8_from_u32(&mut out[(4 * i)..(4 * i + 4)],
+                       x[i].wrapping_add(input[i]));
+    }
+    out
+}
+
+fn initial_state(key: &[u8; KEY_LEN], input: &[u8; 16]) -> [u32; 16] {
+    let k = |i: usize| {
+        polyfill::slice::u32_from_le_u8(
+            slice_as_array_ref!(&key[(4 * i)..(4 * i + 4)], 4).unwrap())
+    };
+    let n = |i: usize| {
+        polyfill::slice::u32_from_le_u8(
+            slice_as_array_ref!(&input[(4 * i)..(4 * i + 4)], 4).unwrap())
+    };
+    [SIGMA[0], k(0), k(1), k(2),
+     k(3), SIGMA[1], n(0), n(1),
+     n(2), n(3), SIGMA[2], k(4),
+     k(5), k(6), k(7), SIGMA[3]]
+}
+
+// The 20 rounds of the Salsa20 core, without the final addition.
+fn rounds(x: &mut [u32; 16]) {
+    fn quarter_round(x: &mut [u32; 16], a: usize, b: usize, c: usize,
+                     d: usize) {
+        x[b] ^= x[a].wrapping_add(x[d]).rotate_left(7);
+        x[c] ^= x[b].wrapping_add(x[a]).rotate_left(9);
+        x[d] ^= x[c].wrapping_add(x[b]).rotate_left(13);
+        x[a] ^= x[d].wrapping_add(x[c]).rotate_left(18);
+    }
+
+    for _ in 0..10 {
+        // Column round.
+        quarter_round(x, 0, 4, 8, 12);
+        quarter_round(x, 5, 9, 13, 1);
+        quarter_round(x, 10, 14, 2, 6);
+        quarter_round(x, 15, 3, 7, 11);
+
+        // Row round.
+        quarter_round(x, 0, 1, 2, 3);
+        quarter_round(x, 5, 6, 7, 4);
+        quarter_round(x, 10, 11, 8, 9);
+        quarter_round(x, 15, 12, 13, 14);
+    }
+}
+
+fn le_u8_from_u32(out: &mut [u8], value: u32) {
+    for (i, b) in out.iter_mut().enumerate() {
+        *b = (value >> (8 * i)) as u8;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use test;
+    use super::*;
+
+    #[test]
+    fn salsa20_ecrypt_test() {
+        // ECRYPT Set 1, vector #0, with a 256-bit key.
+        let mut key = [0u8; KEY_LEN];
+        key[0] = 0x80;
+        let expected =
+            test::from_hex("e3be8fdd8beca2e3ea8ef9475b29a6e7003951e1097a5c38\
+                            d23b7a5fad9f6844b22c97559e2723c7cbbd3fe4fc8d9a07\
+                            44652a83e72a9c461876af4d7ef1a117").unwrap();
+        let mut in_out = [0u8; 64];
+        salsa20_xor_in_place(&key, &[0u8; SALSA20_NONCE_LEN], 0, &mut in_out,
+                             0);
+        assert_eq!(&in_out[..], &expected[..]);
+    }
+
+    #[test]
+    fn xsalsa20_test() {
+        test::from_file("src/aead/xsalsa20_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let key = test_case.consume_bytes("KEY");
+            let key = slice_as_array_ref!(&key, KEY_LEN).unwrap();
+            let nonce = test_case.consume_bytes("NONCE");
+            let nonce =
+                slice_as_array_ref!(&nonce, XSALSA20_NONCE_LEN).unwrap();
+            let input = test_case.consume_bytes("IN");
+            let output = test_case.consume_bytes("OUT");
+
+            for in_prefix_len in &[0, 1, 63, 64, 65] {
+                let mut in_out = vec![123u8; *in_prefix_len];
+                in_out.extend_from_slice(&input);
+                xsalsa20_xor_in_place(key, nonce, 0, &mut in_out,
+                                      *in_prefix_len);
+                assert_eq!(&in_out[..input.len()], &output[..]);
+            }
+
+            // Starting in the middle of the keystream gives the corresponding
+            // suffix of the output.
+            for offset in &[1, 63, 64, 65] {
+                if *offset > input.len() {
+                    continue;
+                }
+                let mut in_out = input[*offset..].to_vec();
+                xsalsa20_xor_in_place(key, nonce, *offset, &mut in_out, 0);
+                assert_eq!(&in_out[..], &output[*offset..]);
+            }
+
+            Ok(())
+        });
+    }
+}
@@ -0,0 +1,191 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The NaCl `crypto_secretbox` construction (XSalsa20-Poly1305).
+//!
+//! This is provided for interoperability with data produced by NaCl and
+//! libsodium. The "combined" format of libsodium's `crypto_secretbox_easy` is
+//! used: the 16-byte tag is followed by the ciphertext. Unlike the AEADs in
+//! `ring::aead`, secretbox does not support additional authenticated data.
+//!
+//! New protocols should use `ring::aead::CHACHA20_POLY1305` instead.
+//!
+//! C analogs: `crypto_secretbox_easy`, `crypto_secretbox_open_easy`
+
+use {constant_time, error, init};
+use secret::Zeroize;
+use super::chacha20_poly1305::{poly1305_finish, poly1305_init, poly1305_update,
+                               POLY1305_KEY_LEN, POLY1305_STATE_LEN};
+use super::salsa20;
+
+/// The length of secretbox keys.
+///
+/// C analog: `crypto_secretbox_KEYBYTES`
+pub const KEY_LEN: usize = salsa20::KEY_LEN;
+
+/// The length of secretbox nonces.
+///
+/// C analog: `crypto_secretbox_NONCEBYTES`
+pub const NONCE_LEN: usize = salsa20::XSALSA20_NONCE_LEN;
+
+/// The length of secretbox tags.
+///
+/// C analog: `crypto_secretbox_MACBYTES`
+pub const TAG_LEN: usize = 16;
+
+/// A secretbox key, for both sealing and opening.
+pub struct Key {
+    key: [u8; KEY_LEN],
+}
+
+impl Key {
+    /// Create a new key. `key_bytes` must be exactly `KEY_LEN` bytes long.
+    pub fn new(key_bytes: &[u8]) -> Result<Key, error::KeyRejected> {
+        init::init_once();
+        try!(error::KeyRejected::check_len(key_bytes.len(), KEY_LEN));
+        let key_bytes = slice_as_array_ref!(key_bytes, KEY_LEN).unwrap();
+        Ok(Key { key: *key_bytes })
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) { self.key.zeroize(); }
+}
+
+/// Encrypts and authenticates (&ldquo;seals&rdquo;) data in place.
+///
+/// The input is `in_out[TAG_LEN..]`; the first `TAG_LEN` bytes of `in_out`
+/// are reserved for the tag and their initial values are ignored. On success,
+/// `in_out` contains the tag followed by the ciphertext.
+///
+/// `nonce` must be exactly `NONCE_LEN` bytes long and must be unique for every
+/// use of the key to seal data.
+pub fn seal_in_place(key: &Key, nonce: &[u8], in_out: &mut [u8])
+                     -> Result<(), error::Unspecified> {
+    let nonce = try!(slice_as_array_ref!(nonce, NONCE_LEN));
+    if in_out.len() < TAG_LEN {
+        return Err(error::Unspecified);
+    }
+    let poly1305_key = poly1305_key(key, nonce);
+    let (tag_out, in_out) = in_out.split_at_mut(TAG_LEN);
+    salsa20::xsalsa20_xor_in_place(&key.key, nonce, POLY1305_KEY_LEN, in_out,
+                                   0);
+    let tag_out = try!(slice_as_array_ref_mut!(tag_out, TAG_LEN));
+    poly1305(&poly1305_key, in_out, tag_out);
+    Ok(())
+}
+
+/// Authenticates and decrypts (&ldquo;opens&rdquo;) data in place.
+///
+/// The input is `in_out[in_prefix_len..]`, which must be the tag followed by
+/// the ciphertext. When `open_in_place` returns `Ok(out_len)`, the decrypted
+/// output is `in_out[..out_len]`, like `ring::aead::open_in_place`. Nothing is
+/// decrypted unless the tag is valid.
+pub fn open_in_place(key: &Key, nonce: &[u8], in_prefix_len: usize,
+                     in_out: &mut [u8]) -> Result<usize, error::Unspecified> {
+    let nonce = try!(slice_as_array_ref!(nonce, NONCE_LEN));
+    let tag_and_ciphertext_len =
+        try!(in_out.len().checked_sub(in_prefix_len)
+                         .ok_or(error::Unspecified));
+    let ciphertext_len =
+        try!(tag_and_ciphertext_len.checked_sub(TAG_LEN)
+                                   .ok_or(error::Unspecified));
+    let poly1305_key = poly1305_key(key, nonce);
+    {
+        let (received_tag, ciphertext) =
+            in_out[in_prefix_len..].split_at(TAG_LEN);
+        let mut calculated_tag = [0u8; TAG_LEN];
+        poly1305(&poly1305_key, ciphertext, &mut calculated_tag);
+        try!(constant_time::verify_slices_are_equal(&calculated_tag,
+                                                    received_tag));
+    }
+    salsa20::xsalsa20_xor_in_place(&key.key, nonce, POLY1305_KEY_LEN, in_out,
+                                   in_prefix_len + TAG_LEN);
+    Ok(ciphertext_len)
+}
+
+// The Poly1305 key is the first 32 bytes of the XSalsa20 keystream.
+fn poly1305_key(key: &Key, nonce: &[u8; NONCE_LEN])
+                -> [u8; POLY1305_KEY_LEN] {
+    let mut poly1305_key = [0u8; POLY1305_KEY_LEN];
+    salsa20::xsalsa20_xor_in_place(&key.key, nonce, 0, &mut poly1305_key, 0);
+    poly1305_key
+}
+
+fn poly1305(key: &[u8; POLY1305_KEY_LEN], input: &[u8],
+            tag_out: &mut [u8; TAG_LEN]) {
+    let mut ctx = [0u8; POLY1305_STATE_LEN];
+    poly1305_init(&mut ctx, key);
+    poly1305_update(&mut ctx, input);
+    poly1305_finish(&mut ctx, tag_out);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::super::secretbox;
+    use test;
+
+    #[test]
+    fn secretbox_test() {
+        test::from_file("src/aead/secretbox_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let key = test_case.consume_bytes("KEY");
+            let key = try!(secretbox::Key::new(&key));
+            let nonce = test_case.consume_bytes("NONCE");
+            let plaintext = test_case.consume_bytes("IN");
+            let ct = test_case.consume_bytes("CT");
+
+            let mut s_in_out = vec![0u8; secretbox::TAG_LEN];
+            s_in_out.extend_from_slice(&plaintext);
+            try!(secretbox::seal_in_place(&key, &nonce, &mut s_in_out));
+            assert_eq!(&s_in_out[..], &ct[..]);
+
+            for in_prefix_len in &[0, 1, 16, 17, 64, 65] {
+                let mut o_in_out = vec![123u8; *in_prefix_len];
+                o_in_out.extend_from_slice(&ct);
+                let len = try!(secretbox::open_in_place(&key, &nonce,
+                                                        *in_prefix_len,
+                                                        &mut o_in_out));
+                assert_eq!(&o_in_out[..len], &plaintext[..]);
+            }
+
+            // Any modification to the tag or ciphertext is detected.
+            for i in 0..ct.len() {
+                let mut o_in_out = ct.clone();
+                o_in_out[i] ^= 1;
+                assert!(secretbox::open_in_place(&key, &nonce, 0,
+                                                 &mut o_in_out).is_err());
+            }
+
+            // Wrong nonce lengths are rejected.
+            let mut o_in_out = ct.clone();
+            assert!(secretbox::open_in_place(&key, &nonce[1..], 0,
+                                             &mut o_in_out).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn secretbox_short_input_test() {
+        let key = secretbox::Key::new(&[0u8; secretbox::KEY_LEN]).unwrap();
+        let nonce = [0u8; secretbox::NONCE_LEN];
+        let mut in_out = [0u8; secretbox::TAG_LEN - 1];
+        assert!(secretbox::seal_in_place(&key, &nonce, &mut in_out).is_err());
+        assert!(secretbox::open_in_place(&key, &nonce, 0, &mut in_out)
+                    .is_err());
+        assert!(secretbox::Key::new(&[0u8; secretbox::KEY_LEN - 1]).is_err());
+    }
+}
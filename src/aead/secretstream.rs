@@ -0,0 +1,458 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! libsodium's `crypto_secretstream_xchacha20poly1305` construction.
+//!
+//! This is provided for interoperability with data produced by libsodium.
+//! A stream is a sequence of messages that are sealed in order with a state
+//! that is derived from the key and a random header. The header is sent
+//! before the first message. Each message has a `Tag` that is encrypted and
+//! authenticated along with it; `Tag::Final` marks the end of the stream.
+//! Messages can't be reordered, dropped, or moved to other streams without
+//! opening failing, but the caller is responsible for framing them and for
+//! treating a stream as complete only when a message tagged `Tag::Final`
+//! has been opened.
+//!
+//! The sealed form of a message is `OVERHEAD_LEN` bytes longer than the
+//! message: one byte for the encrypted tag, then the ciphertext, then a
+//! 16-byte Poly1305 authenticator.
+//!
+//! New protocols should use `ring::aead::stream` instead.
+//!
+//! C analogs: `crypto_secretstream_xchacha20poly1305_init_push`,
+//! `crypto_secretstream_xchacha20poly1305_push`,
+//! `crypto_secretstream_xchacha20poly1305_init_pull`,
+//! `crypto_secretstream_xchacha20poly1305_pull`,
+//! `crypto_secretstream_xchacha20poly1305_rekey`
+
+use {aead, constant_time, error, init, polyfill, rand};
+use secret::Zeroize;
+use super::chacha20_poly1305::{chacha20_key_from_bytes, chacha20_xor_in_place,
+                               make_counter, poly1305_finish, poly1305_init,
+                               poly1305_pad_16, poly1305_update,
+                               poly1305_update_length, POLY1305_KEY_LEN,
+                               POLY1305_STATE_LEN};
+use super::xchacha20_poly1305::hchacha20;
+
+/// The length of secretstream keys.
+///
+/// C analog: `crypto_secretstream_xchacha20poly1305_KEYBYTES`
+pub const KEY_LEN: usize = 32;
+
+/// The length of the header that starts a stream.
+///
+/// C analog: `crypto_secretstream_xchacha20poly1305_HEADERBYTES`
+pub const HEADER_LEN: usize = 24;
+
+/// How much longer a sealed message is than the message.
+///
+/// C analog: `crypto_secretstream_xchacha20poly1305_ABYTES`
+pub const OVERHEAD_LEN: usize = 1 + MAC_LEN;
+
+const MAC_LEN: usize = 16;
+
+// The length of the part of the nonce that isn't the counter.
+const INONCE_LEN: usize = 8;
+
+// The encrypted tag is the first byte of ChaCha20 block 1, and the message
+// is encrypted starting at block 2. The block counter is 32 bits.
+const MAX_MESSAGE_LEN: u64 = 64 * ((1 << 32) - 2);
+
+/// The tag of a message.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tag {
+    /// An ordinary message.
+    ///
+    /// C analog: `crypto_secretstream_xchacha20poly1305_TAG_MESSAGE`
+    Message,
+
+    /// The end of a set of messages, but not the end of the stream.
+    ///
+    /// C analog: `crypto_secretstream_xchacha20poly1305_TAG_PUSH`
+    Push,
+
+    /// The state is rekeyed after the message.
+    ///
+    /// C analog: `crypto_secretstream_xchacha20poly1305_TAG_REKEY`
+    Rekey,
+
+    /// The end of the stream. The state is rekeyed after the message.
+    ///
+    /// C analog: `crypto_secretstream_xchacha20poly1305_TAG_FINAL`
+    Final,
+}
+
+impl Tag {
+    fn from_u8(value: u8) -> Result<Tag, error::Unspecified> {
+        match value {
+            0 => Ok(Tag::Message),
+            1 => Ok(Tag::Push),
+            2 => Ok(Tag::Rekey),
+            3 => Ok(Tag::Final),
+            _ => Err(error::Unspecified),
+        }
+    }
+
+    fn as_u8(&self) -> u8 {
+        match *self {
+            Tag::Message => 0,
+            Tag::Push => 1,
+            Tag::Rekey => 2,
+            Tag::Final => 3,
+        }
+    }
+}
+
+/// A secretstream key, for both sealing and opening.
+pub struct Key {
+    key: [u8; KEY_LEN],
+}
+
+impl Key {
+    /// Create a new key. `key_bytes` must be exactly `KEY_LEN` bytes long.
+    pub fn new(key_bytes: &[u8]) -> Result<Key, error::KeyRejected> {
+        init::init_once();
+        try!(error::KeyRejected::check_len(key_bytes.len(), KEY_LEN));
+        let key_bytes = slice_as_array_ref!(key_bytes, KEY_LEN).unwrap();
+        Ok(Key { key: *key_bytes })
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) { self.key.zeroize(); }
+}
+
+/// Seals the messages of a stream, in order.
+pub struct Sealer {
+    state: State,
+}
+
+impl Sealer {
+    /// Starts a new stream, returning the `Sealer` and the header that must
+    /// be sent to the opener before the first message.
+    ///
+    /// C analog: `crypto_secretstream_xchacha20poly1305_init_push`
+    pub fn new(key: &Key, rng: &rand::SecureRandom)
+               -> Result<(Sealer, [u8; HEADER_LEN]), error::Unspecified> {
+        let mut header = [0u8; HEADER_LEN];
+        try!(rng.fill(&mut header));
+        Ok((Sealer { state: State::new(key, &header) }, header))
+    }
+
+    /// Encrypts and authenticates (&ldquo;seals&rdquo;) the next message in
+    /// place.
+    ///
+    /// The input is `in_out[..(in_out.len() - OVERHEAD_LEN)]`; the last
+    /// `OVERHEAD_LEN` bytes of `in_out` are reserved and their initial values
+    /// are ignored. On success, `in_out` contains the sealed message.
+    ///
+    /// C analog: `crypto_secretstream_xchacha20poly1305_push`
+    pub fn seal_in_place(&mut self, in_out: &mut [u8], ad: &[u8], tag: Tag)
+                         -> Result<(), error::Unspecified> {
+        let message_len = try!(in_out.len().checked_sub(OVERHEAD_LEN)
+                                           .ok_or(error::Unspecified));
+        try!(check_message_len(message_len));
+
+        // Make room for the encrypted tag.
+        for i in (0..message_len).rev() {
+            in_out[i + 1] = in_out[i];
+        }
+
+        let mut block = [0u8; 64];
+        block[0] = tag.as_u8();
+        self.state.xor_block_1(&mut block);
+        in_out[0] = block[0];
+
+        let (c, mac) = in_out[1..].split_at_mut(message_len);
+        chacha20_xor_in_place(&self.state.chacha20_key(),
+                              &make_counter(2, &self.state.nonce), c);
+        let mac = slice_as_array_ref_mut!(mac, MAC_LEN).unwrap();
+        self.state.mac(ad, &block, c, mac);
+        self.state.advance(mac, tag);
+        Ok(())
+    }
+
+    /// Rekeys the state explicitly. The opener must call `Opener::rekey` at
+    /// the same point in the stream.
+    ///
+    /// C analog: `crypto_secretstream_xchacha20poly1305_rekey`
+    pub fn rekey(&mut self) { self.state.rekey(); }
+}
+
+/// Opens the messages of a stream, in order.
+pub struct Opener {
+    state: State,
+}
+
+impl Opener {
+    /// Starts opening a stream that starts with `header`, which must be
+    /// exactly `HEADER_LEN` bytes long.
+    ///
+    /// C analog: `crypto_secretstream_xchacha20poly1305_init_pull`
+    pub fn new(key: &Key, header: &[u8]) -> Result<Opener, error::Unspecified> {
+        let header = try!(slice_as_array_ref!(header, HEADER_LEN));
+        Ok(Opener { state: State::new(key, header) })
+    }
+
+    /// Authenticates and decrypts (&ldquo;opens&rdquo;) the next message in
+    /// place.
+    ///
+    /// The input is `in_out[in_prefix_len..]`, which must be a sealed
+    /// message. When `open_in_place` returns `Ok((out_len, tag))`, the
+    /// decrypted message is `in_out[..out_len]`, like
+    /// `ring::aead::open_in_place`, and `tag` is its tag. Nothing is decrypted
+    /// unless the message is valid, and the state is only advanced when it
+    /// is.
+    ///
+    /// C analog: `crypto_secretstream_xchacha20poly1305_pull`
+    pub fn open_in_place(&mut self, in_prefix_len: usize, in_out: &mut [u8],
+                         ad: &[u8]) -> Result<(usize, Tag), error::Unspecified> {
+        let sealed_len = try!(in_out.len().checked_sub(in_prefix_len)
+                                          .ok_or(error::Unspecified));
+        let message_len = try!(sealed_len.checked_sub(OVERHEAD_LEN)
+                                         .ok_or(error::Unspecified));
+        try!(check_message_len(message_len));
+
+        let mut block = [0u8; 64];
+        block[0] = in_out[in_prefix_len];
+        self.state.xor_block_1(&mut block);
+        let tag = block[0];
+        block[0] = in_out[in_prefix_len];
+
+        let mut calculated_mac = [0u8; MAC_LEN];
+        {
+            let (c, received_mac) =
+                in_out[(in_prefix_len + 1)..].split_at(message_len);
+            self.state.mac(ad, &block, c, &mut calculated_mac);
+            try!(constant_time::verify_slices_are_equal(&calculated_mac,
+                                                        received_mac));
+        }
+        let tag = try!(Tag::from_u8(tag));
+
+        {
+            let c = &mut in_out[(in_prefix_len + 1)..][..message_len];
+            chacha20_xor_in_place(&self.state.chacha20_key(),
+                                  &make_counter(2, &self.state.nonce), c);
+        }
+        for i in 0..message_len {
+            in_out[i] = in_out[in_prefix_len + 1 + i];
+        }
+        self.state.advance(&calculated_mac, tag);
+        Ok((message_len, tag))
+    }
+
+    /// Rekeys the state explicitly, at the point in the stream where the
+    /// sealer called `Sealer::rekey`.
+    ///
+    /// C analog: `crypto_secretstream_xchacha20poly1305_rekey`
+    pub fn rekey(&mut self) { self.state.rekey(); }
+}
+
+fn check_message_len(message_len: usize) -> Result<(), error::Unspecified> {
+    if polyfill::u64_from_usize(message_len) > MAX_MESSAGE_LEN {
+        return Err(error::Unspecified);
+    }
+    Ok(())
+}
+
+struct State {
+    key: [u8; KEY_LEN],
+
+    // A 32-bit little-endian counter followed by `INONCE_LEN` bytes.
+    nonce: [u8; aead::NONCE_LEN],
+}
+
+impl State {
+    fn new(key: &Key, header: &[u8; HEADER_LEN]) -> State {
+        let mut state = State {
+            key: hchacha20(&key.key,
+                           slice_as_array_ref!(&header[..16], 16).unwrap()),
+            nonce: [0u8; aead::NONCE_LEN],
+        };
+        state.nonce[4..].copy_from_slice(&header[16..]);
+        state.reset_counter();
+        state
+    }
+
+    fn chacha20_key(&self) -> [u32; KEY_LEN / 4] {
+        chacha20_key_from_bytes(&self.key)
+    }
+
+    fn xor_block_1(&self, block: &mut [u8; 64]) {
+        chacha20_xor_in_place(&self.chacha20_key(),
+                              &make_counter(1, &self.nonce), block);
+    }
+
+    // The Poly1305 key is the first 32 bytes of ChaCha20 block 0. Unlike in
+    // RFC 7539, the ciphertext is padded with `c.len() % 16` zeros, not to a
+    // multiple of 16 bytes.
+    fn mac(&self, ad: &[u8], block: &[u8; 64], c: &[u8],
+           mac_out: &mut [u8; MAC_LEN]) {
+        let mut poly1305_key = [0u8; POLY1305_KEY_LEN];
+        chacha20_xor_in_place(&self.chacha20_key(),
+                              &make_counter(0, &self.nonce),
+                              &mut poly1305_key);
+        let mut ctx = [0u8; POLY1305_STATE_LEN];
+        poly1305_init(&mut ctx, &poly1305_key);
+        poly1305_key.zeroize();
+        poly1305_update(&mut ctx, ad);
+        poly1305_pad_16(&mut ctx, ad.len());
+        poly1305_update(&mut ctx, block);
+        poly1305_update(&mut ctx, c);
+        static PADDING: [u8; 16] = [0u8; 16];
+        poly1305_update(&mut ctx, &PADDING[..(c.len() % 16)]);
+        poly1305_update_length(&mut ctx, ad.len());
+        poly1305_update_length(&mut ctx, block.len() + c.len());
+        poly1305_finish(&mut ctx, mac_out);
+    }
+
+    fn advance(&mut self, mac: &[u8; MAC_LEN], tag: Tag) {
+        for (n, m) in self.nonce[4..].iter_mut().zip(mac[..INONCE_LEN].iter()) {
+            *n ^= *m;
+        }
+        let counter = polyfill::slice::u32_from_le_u8(
+            slice_as_array_ref!(&self.nonce[..4], 4).unwrap()).wrapping_add(1);
+        self.set_counter(counter);
+        if tag.as_u8() & Tag::Rekey.as_u8() != 0 || counter == 0 {
+            self.rekey();
+        }
+    }
+
+    // The new key and the new `INONCE_LEN` bytes of the nonce are the old
+    // ones encrypted with ChaCha20 under the current nonce.
+    fn rekey(&mut self) {
+        let mut new_key_and_inonce = [0u8; KEY_LEN + INONCE_LEN];
+        new_key_and_inonce[..KEY_LEN].copy_from_slice(&self.key);
+        new_key_and_inonce[KEY_LEN..].copy_from_slice(&self.nonce[4..]);
+        chacha20_xor_in_place(&self.chacha20_key(),
+                              &make_counter(0, &self.nonce),
+                              &mut new_key_and_inonce);
+        self.key.copy_from_slice(&new_key_and_inonce[..KEY_LEN]);
+        self.nonce[4..].copy_from_slice(&new_key_and_inonce[KEY_LEN..]);
+        new_key_and_inonce[..].zeroize();
+        self.reset_counter();
+    }
+
+    fn reset_counter(&mut self) { self.set_counter(1); }
+
+    fn set_counter(&mut self, counter: u32) {
+        for (i, b) in self.nonce[..4].iter_mut().enumerate() {
+            *b = (counter >> (8 * i)) as u8;
+        }
+    }
+}
+
+impl Drop for State {
+    fn drop(&mut self) {
+        self.key.zeroize();
+        self.nonce[..].zeroize();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::super::secretstream;
+    use {rand, test};
+
+    struct Stream {
+        sealer: secretstream::Sealer,
+        opener: secretstream::Opener,
+    }
+
+    #[test]
+    fn secretstream_test() {
+        let mut stream: Option<Stream> = None;
+        test::from_file("src/aead/secretstream_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+            if let Some(key) = test_case.consume_optional_string("KEY") {
+                let key = try!(secretstream::Key::new(
+                    &test::from_hex(&key).unwrap()));
+                let header = test_case.consume_bytes("HEADER");
+                let rng = rand::test_util::FixedSliceRandom { bytes: &header };
+                let (sealer, sealer_header) =
+                    try!(secretstream::Sealer::new(&key, &rng));
+                assert_eq!(&sealer_header[..], &header[..]);
+                let opener = try!(secretstream::Opener::new(&key, &header));
+                stream = Some(Stream { sealer: sealer, opener: opener });
+            }
+            let stream = stream.as_mut().unwrap();
+            if test_case.consume_optional_string("Rekey").is_some() {
+                stream.sealer.rekey();
+                stream.opener.rekey();
+            }
+            let plaintext = test_case.consume_bytes("IN");
+            let ad = test_case.consume_bytes("AD");
+            let tag = match test_case.consume_string("TAG").as_ref() {
+                "MESSAGE" => secretstream::Tag::Message,
+                "PUSH" => secretstream::Tag::Push,
+                "REKEY" => secretstream::Tag::Rekey,
+                "FINAL" => secretstream::Tag::Final,
+                _ => unreachable!(),
+            };
+            let ct = test_case.consume_bytes("CT");
+
+            let mut s_in_out = plaintext.clone();
+            s_in_out.extend_from_slice(&[0u8; secretstream::OVERHEAD_LEN]);
+            try!(stream.sealer.seal_in_place(&mut s_in_out, &ad, tag));
+            assert_eq!(&s_in_out[..], &ct[..]);
+
+            // Any modification to the message or the additional data is
+            // detected, and doesn't change the state of the opener.
+            for i in 0..ct.len() {
+                let mut o_in_out = ct.clone();
+                o_in_out[i] ^= 1;
+                assert!(stream.opener.open_in_place(0, &mut o_in_out, &ad)
+                              .is_err());
+            }
+            let mut o_in_out = ct.clone();
+            let mut wrong_ad = ad.clone();
+            wrong_ad.push(0);
+            assert!(stream.opener.open_in_place(0, &mut o_in_out, &wrong_ad)
+                          .is_err());
+
+            let mut o_in_out = vec![123u8; 17];
+            o_in_out.extend_from_slice(&ct);
+            let (len, opened_tag) =
+                try!(stream.opener.open_in_place(17, &mut o_in_out, &ad));
+            assert_eq!(&o_in_out[..len], &plaintext[..]);
+            assert_eq!(opened_tag, tag);
+
+            // The same message can't be opened twice.
+            let mut o_in_out = ct.clone();
+            assert!(stream.opener.open_in_place(0, &mut o_in_out, &ad)
+                          .is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn secretstream_short_input_test() {
+        let key = secretstream::Key::new(&[0u8; secretstream::KEY_LEN])
+            .unwrap();
+        let header = [0u8; secretstream::HEADER_LEN];
+        let rng = rand::test_util::FixedSliceRandom { bytes: &header };
+        let (mut sealer, _) = secretstream::Sealer::new(&key, &rng).unwrap();
+        let mut opener = secretstream::Opener::new(&key, &header).unwrap();
+        let mut in_out = [0u8; secretstream::OVERHEAD_LEN - 1];
+        assert!(sealer.seal_in_place(&mut in_out, &[],
+                                     secretstream::Tag::Message).is_err());
+        assert!(opener.open_in_place(0, &mut in_out, &[]).is_err());
+        assert!(secretstream::Opener::new(&key, &header[1..]).is_err());
+        assert!(secretstream::Key::new(&[0u8; secretstream::KEY_LEN - 1])
+                    .is_err());
+    }
+}
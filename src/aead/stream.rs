@@ -0,0 +1,298 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Streaming encryption of long messages, using the STREAM construction from
+//! [Online Authenticated-Encryption and its Nonce-Reuse Misuse-Resistance].
+//!
+//! A long message is split into chunks that are sealed separately, so that
+//! the message never has to be in memory all at once. The nonce for each
+//! chunk is a `NONCE_PREFIX_LEN`-byte prefix that is unique for the message,
+//! followed by the chunk's index as a 32-bit big-endian integer and one byte
+//! that is 1 for the last chunk and 0 for the others. Thus chunks can't be
+//! reordered, dropped, or moved to other messages, and a message can't be
+//! truncated, without opening failing. The chunks can have different lengths;
+//! the caller is responsible for framing them.
+//!
+//! A `StreamOpener` only detects truncation if the caller treats a message as
+//! complete only when `open_last_chunk_in_place` has succeeded.
+//!
+//! Any `ring::aead` algorithm with 96-bit nonces can be used.
+//!
+//! [Online Authenticated-Encryption and its Nonce-Reuse Misuse-Resistance]:
+//!     https://eprint.iacr.org/2015/189.pdf
+
+use {aead, error, polyfill};
+
+/// The length of the nonce prefix, which must be unique for each message
+/// that is sealed with a key.
+pub const NONCE_PREFIX_LEN: usize = aead::NONCE_LEN - 4 - 1;
+
+/// Seals the chunks of a message, in order.
+pub struct StreamSealer {
+    key: aead::SealingKey,
+    nonces: Nonces,
+}
+
+impl StreamSealer {
+    /// Constructs a new `StreamSealer` for a message.
+    ///
+    /// `nonce_prefix` must be exactly `NONCE_PREFIX_LEN` bytes long and must
+    /// be unique for each message that is sealed with `key`.
+    /// `key.algorithm().nonce_len()` must be 12.
+    pub fn new(key: aead::SealingKey, nonce_prefix: &[u8])
+               -> Result<StreamSealer, error::Unspecified> {
+        let nonces = try!(Nonces::new(key.algorithm(), nonce_prefix));
+        Ok(StreamSealer { key: key, nonces: nonces })
+    }
+
+    /// Seals the next chunk, which must not be the last chunk, like
+    /// `aead::seal_in_place`.
+    pub fn seal_chunk_in_place(&mut self, in_out: &mut [u8],
+                               out_suffix_capacity: usize, ad: &[u8])
+                               -> Result<usize, error::Unspecified> {
+        let nonce = try!(self.nonces.next());
+        let out_len = try!(aead::seal_in_place(&self.key, &nonce, in_out,
+                                               out_suffix_capacity, ad));
+        self.nonces.advance();
+        Ok(out_len)
+    }
+
+    /// Seals the last chunk, like `aead::seal_in_place`.
+    pub fn seal_last_chunk_in_place(self, in_out: &mut [u8],
+                                    out_suffix_capacity: usize, ad: &[u8])
+                                    -> Result<usize, error::Unspecified> {
+        Ok(try!(aead::seal_in_place(&self.key, &self.nonces.last(), in_out,
+                                    out_suffix_capacity, ad)))
+    }
+}
+
+/// Opens the chunks of a message, in order.
+pub struct StreamOpener {
+    key: aead::OpeningKey,
+    nonces: Nonces,
+}
+
+impl StreamOpener {
+    /// Constructs a new `StreamOpener` for a message.
+    ///
+    /// `nonce_prefix` must be the nonce prefix the message was sealed with.
+    /// `key.algorithm().nonce_len()` must be 12.
+    pub fn new(key: aead::OpeningKey, nonce_prefix: &[u8])
+               -> Result<StreamOpener, error::Unspecified> {
+        let nonces = try!(Nonces::new(key.algorithm(), nonce_prefix));
+        Ok(StreamOpener { key: key, nonces: nonces })
+    }
+
+    /// Opens the next chunk, which must not be the last chunk, like
+    /// `aead::open_in_place`.
+    pub fn open_chunk_in_place(&mut self, in_prefix_len: usize,
+                               in_out: &mut [u8], ad: &[u8])
+                               -> Result<usize, error::Unspecified> {
+        let nonce = try!(self.nonces.next());
+        let out_len = try!(aead::open_in_place(&self.key, &nonce,
+                                               in_prefix_len, in_out, ad));
+        self.nonces.advance();
+        Ok(out_len)
+    }
+
+    /// Opens the last chunk, like `aead::open_in_place`. The message is
+    /// complete only when this succeeds.
+    pub fn open_last_chunk_in_place(self, in_prefix_len: usize,
+                                    in_out: &mut [u8], ad: &[u8])
+                                    -> Result<usize, error::Unspecified> {
+        aead::open_in_place(&self.key, &self.nonces.last(), in_prefix_len,
+                            in_out, ad)
+    }
+}
+
+struct Nonces {
+    prefix: [u8; NONCE_PREFIX_LEN],
+    counter: u32,
+}
+
+impl Nonces {
+    fn new(algorithm: &'static aead::Algorithm, nonce_prefix: &[u8])
+           -> Result<Nonces, error::Unspecified> {
+        if algorithm.nonce_len() != aead::NONCE_LEN {
+            return Err(error::Unspecified);
+        }
+        let nonce_prefix =
+            try!(slice_as_array_ref!(nonce_prefix, NONCE_PREFIX_LEN));
+        Ok(Nonces { prefix: *nonce_prefix, counter: 0 })
+    }
+
+    // The nonce for the next chunk, if it isn't the last chunk. The last
+    // chunk must have a counter value too, so the largest counter value can't
+    // be used for any other chunk.
+    fn next(&self) -> Result<[u8; aead::NONCE_LEN], error::Unspecified> {
+        if self.counter == 0xffffffff {
+            return Err(error::Unspecified);
+        }
+        Ok(self.nonce(0))
+    }
+
+    fn last(&self) -> [u8; aead::NONCE_LEN] { self.nonce(1) }
+
+    fn advance(&mut self) { self.counter += 1; }
+
+    fn nonce(&self, last_chunk: u8) -> [u8; aead::NONCE_LEN] {
+        let mut nonce = [0u8; aead::NONCE_LEN];
+        nonce[..NONCE_PREFIX_LEN].copy_from_slice(&self.prefix);
+        nonce[NONCE_PREFIX_LEN..(aead::NONCE_LEN - 1)].copy_from_slice(
+            &polyfill::slice::be_u8_from_u32(self.counter));
+        nonce[aead::NONCE_LEN - 1] = last_chunk;
+        nonce
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {aead, test};
+    use std::vec::Vec;
+    use super::*;
+
+    const KEY: [u8; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14,
+                           15];
+    const NONCE_PREFIX: [u8; NONCE_PREFIX_LEN] =
+        [0xa0, 0xa1, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6];
+
+    fn sealer() -> StreamSealer {
+        let key = aead::SealingKey::new(&aead::AES_128_GCM, &KEY).unwrap();
+        StreamSealer::new(key, &NONCE_PREFIX).unwrap()
+    }
+
+    fn opener() -> StreamOpener {
+        let key = aead::OpeningKey::new(&aead::AES_128_GCM, &KEY).unwrap();
+        StreamOpener::new(key, &NONCE_PREFIX).unwrap()
+    }
+
+    fn seal(sealer: &mut StreamSealer, chunk: &[u8]) -> Vec<u8> {
+        let mut in_out = Vec::from(chunk);
+        in_out.extend_from_slice(&[0u8; aead::MAX_OVERHEAD_LEN]);
+        let len = sealer.seal_chunk_in_place(&mut in_out,
+                                             aead::MAX_OVERHEAD_LEN,
+                                             b"header").unwrap();
+        in_out.truncate(len);
+        in_out
+    }
+
+    fn seal_last(sealer: StreamSealer, chunk: &[u8]) -> Vec<u8> {
+        let mut in_out = Vec::from(chunk);
+        in_out.extend_from_slice(&[0u8; aead::MAX_OVERHEAD_LEN]);
+        let len = sealer.seal_last_chunk_in_place(&mut in_out,
+                                                  aead::MAX_OVERHEAD_LEN,
+                                                  b"header").unwrap();
+        in_out.truncate(len);
+        in_out
+    }
+
+    #[test]
+    fn test_stream_known_answer() {
+        let mut sealer = sealer();
+        let c0 = seal(&mut sealer, b"Hello, ");
+        let c1 = seal_last(sealer, b"world!");
+        assert_eq!(c0, test::from_hex("83cbbc43dbed116a030b05c031d53d26\
+                                       08f00df9cef9ce").unwrap());
+        assert_eq!(c1, test::from_hex("c39aaff64a69bb80fb93edcfd4ddfa9b\
+                                       70e9dad31eca").unwrap());
+
+        let mut opener = opener();
+        let mut in_out = c0.clone();
+        let len = opener.open_chunk_in_place(0, &mut in_out, b"header")
+                        .unwrap();
+        assert_eq!(&in_out[..len], b"Hello, ");
+        let mut in_out = c1.clone();
+        let len = opener.open_last_chunk_in_place(0, &mut in_out, b"header")
+                        .unwrap();
+        assert_eq!(&in_out[..len], b"world!");
+    }
+
+    #[test]
+    fn test_stream_modifications_detected() {
+        let mut sealer = sealer();
+        let c0 = seal(&mut sealer, b"chunk 0");
+        let c1 = seal(&mut sealer, b"chunk 1");
+        let c2 = seal_last(sealer, b"chunk 2");
+
+        // Truncation: a non-last chunk can't be opened as the last chunk.
+        {
+            let mut opener = opener();
+            assert!(opener.open_chunk_in_place(0, &mut c0.clone(), b"header")
+                          .is_ok());
+            assert!(opener.open_last_chunk_in_place(0, &mut c1.clone(),
+                                                    b"header").is_err());
+        }
+
+        // Extension: the last chunk can't be opened as a non-last chunk.
+        {
+            let mut opener = opener();
+            assert!(opener.open_chunk_in_place(0, &mut c0.clone(), b"header")
+                          .is_ok());
+            assert!(opener.open_chunk_in_place(0, &mut c1.clone(), b"header")
+                          .is_ok());
+            assert!(opener.open_chunk_in_place(0, &mut c2.clone(), b"header")
+                          .is_err());
+        }
+
+        // Reordering.
+        {
+            let mut opener = opener();
+            assert!(opener.open_chunk_in_place(0, &mut c1.clone(), b"header")
+                          .is_err());
+        }
+
+        // A failed chunk doesn't advance the opener.
+        {
+            let mut opener = opener();
+            assert!(opener.open_chunk_in_place(0, &mut c1.clone(), b"header")
+                          .is_err());
+            assert!(opener.open_chunk_in_place(0, &mut c0.clone(), b"header")
+                          .is_ok());
+        }
+
+        // A different nonce prefix.
+        {
+            let key = aead::OpeningKey::new(&aead::AES_128_GCM, &KEY).unwrap();
+            let mut opener =
+                StreamOpener::new(key, &[0u8; NONCE_PREFIX_LEN]).unwrap();
+            assert!(opener.open_chunk_in_place(0, &mut c0.clone(), b"header")
+                          .is_err());
+        }
+    }
+
+    #[test]
+    fn test_stream_counter_exhausted() {
+        let mut sealer = sealer();
+        sealer.nonces.counter = 0xfffffffe;
+        let _ = seal(&mut sealer, b"chunk");
+        let mut in_out = [0u8; aead::MAX_OVERHEAD_LEN];
+        assert!(sealer.seal_chunk_in_place(&mut in_out, aead::MAX_OVERHEAD_LEN,
+                                           b"").is_err());
+        // The last chunk can still be sealed.
+        let _ = seal_last(sealer, b"chunk");
+    }
+
+    #[test]
+    fn test_stream_bad_parameters() {
+        let key = aead::SealingKey::new(&aead::AES_128_GCM, &KEY).unwrap();
+        assert!(StreamSealer::new(key.clone(), &[0u8; NONCE_PREFIX_LEN - 1])
+                    .is_err());
+        assert!(StreamSealer::new(key, &[0u8; NONCE_PREFIX_LEN + 1])
+                    .is_err());
+
+        let key = aead::SealingKey::new(&aead::XCHACHA20_POLY1305, &[0u8; 32])
+                    .unwrap();
+        assert!(StreamSealer::new(key, &NONCE_PREFIX).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The additional data of TLS 1.3 records.
+//!
+//! In TLS 1.3, the additional data of each protected record is its five-byte
+//! record header, as described in [RFC 8446 Section 5.2]: the
+//! `application_data` content type, the legacy record version 0x0303, and
+//! the length of the encrypted record, *including* the authentication tag.
+//! Getting that length wrong, e.g. by using the plaintext length, results in
+//! records that no other implementation can open.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::aead;
+//!
+//! # let key_bytes = [0u8; 16];
+//! # let nonce = [0u8; aead::NONCE_LEN];
+//! let key = try!(aead::SealingKey::new(&aead::AES_128_GCM, &key_bytes));
+//!
+//! // The inner plaintext is the content followed by the real content type.
+//! let mut in_out = b"hello\x17".to_vec();
+//! let header = try!(aead::tls13::RecordHeader::for_plaintext(
+//!     key.algorithm(), in_out.len()));
+//! in_out.extend_from_slice(&[0u8; aead::MAX_OVERHEAD_LEN]);
+//! let len = try!(aead::seal_in_place(&key, &nonce, &mut in_out,
+//!                                    aead::MAX_OVERHEAD_LEN,
+//!                                    header.as_ref()));
+//! assert_eq!(header.ciphertext_len(), len);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [RFC 8446 Section 5.2]: https://tools.ietf.org/html/rfc8446#section-5.2
+
+use error;
+
+/// The length of a TLS record header.
+pub const RECORD_HEADER_LEN: usize = 5;
+
+/// The maximum length of the encrypted record (`TLSCiphertext.length`),
+/// 2**14 + 256 bytes.
+pub const MAX_CIPHERTEXT_LEN: usize = (1 << 14) + 256;
+
+const CONTENT_TYPE_APPLICATION_DATA: u8 = 23;
+const LEGACY_RECORD_VERSION: [u8; 2] = [0x03, 0x03];
+
+/// The header of a protected TLS 1.3 record, which is the additional data
+/// for the record's AEAD.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RecordHeader {
+    bytes: [u8; RECORD_HEADER_LEN],
+}
+
+impl RecordHeader {
+    /// Constructs the header of a protected record whose encrypted record,
+    /// including the tag, is `ciphertext_len` bytes long.
+    ///
+    /// `ciphertext_len` must be no more than `MAX_CIPHERTEXT_LEN`.
+    pub fn new(ciphertext_len: usize)
+               -> Result<RecordHeader, error::Unspecified> {
+        if ciphertext_len > MAX_CIPHERTEXT_LEN {
+            return Err(error::Unspecified);
+        }
+        Ok(RecordHeader {
+            bytes: [CONTENT_TYPE_APPLICATION_DATA, LEGACY_RECORD_VERSION[0],
+                    LEGACY_RECORD_VERSION[1], (ciphertext_len >> 8) as u8,
+                    ciphertext_len as u8],
+        })
+    }
+
+    /// Constructs the header of the record that results from sealing the
+    /// `plaintext_len`-byte inner plaintext (`TLSInnerPlaintext`, i.e. the
+    /// content, the real content type, and any padding) with `algorithm`.
+    pub fn for_plaintext(algorithm: &'static super::Algorithm,
+                         plaintext_len: usize)
+                         -> Result<RecordHeader, error::Unspecified> {
+        let ciphertext_len = try!(plaintext_len.checked_add(
+            algorithm.tag_len()).ok_or(error::Unspecified));
+        RecordHeader::new(ciphertext_len)
+    }
+
+    /// Parses the header of a received record. The content type must be
+    /// `application_data`, the legacy record version must be 0x0303, and the
+    /// length must be no more than `MAX_CIPHERTEXT_LEN`.
+    pub fn parse(input: &[u8]) -> Result<RecordHeader, error::Unspecified> {
+        if input.len() != RECORD_HEADER_LEN ||
+           input[0] != CONTENT_TYPE_APPLICATION_DATA ||
+           input[1..3] != LEGACY_RECORD_VERSION {
+            return Err(error::Unspecified);
+        }
+        RecordHeader::new(((input[3] as usize) << 8) | (input[4] as usize))
+    }
+
+    /// The length of the encrypted record, including the tag.
+    pub fn ciphertext_len(&self) -> usize {
+        ((self.bytes[3] as usize) << 8) | (self.bytes[4] as usize)
+    }
+}
+
+impl AsRef<[u8]> for RecordHeader {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.bytes }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {aead, error};
+    use super::*;
+
+    #[test]
+    fn test_tls13_record_header() {
+        // The header of the server's first encrypted record in
+        // RFC 8448 Section 3.
+        let header = RecordHeader::new(0x2a2).unwrap();
+        assert_eq!(header.as_ref(), &[0x17, 0x03, 0x03, 0x02, 0xa2]);
+        assert_eq!(header.ciphertext_len(), 0x2a2);
+        assert_eq!(RecordHeader::parse(header.as_ref()), Ok(header));
+
+        assert_eq!(RecordHeader::for_plaintext(&aead::CHACHA20_POLY1305,
+                                               0x2a2 - aead::TAG_LEN),
+                   Ok(header));
+
+        assert!(RecordHeader::new(MAX_CIPHERTEXT_LEN).is_ok());
+        assert_eq!(RecordHeader::new(MAX_CIPHERTEXT_LEN + 1),
+                   Err(error::Unspecified));
+        assert!(RecordHeader::for_plaintext(&aead::AES_128_GCM,
+                                            MAX_CIPHERTEXT_LEN).is_err());
+        assert!(RecordHeader::for_plaintext(&aead::AES_128_GCM,
+                                            usize::max_value()).is_err());
+    }
+
+    #[test]
+    fn test_tls13_record_header_parse() {
+        assert!(RecordHeader::parse(&[0x17, 0x03, 0x03, 0x41, 0x00]).is_ok());
+        assert!(RecordHeader::parse(&[0x17, 0x03, 0x03, 0x41, 0x01]).is_err());
+
+        // Handshake records and records with other versions aren't
+        // protected records in TLS 1.3.
+        assert!(RecordHeader::parse(&[0x16, 0x03, 0x03, 0x00, 0x10]).is_err());
+        assert!(RecordHeader::parse(&[0x17, 0x03, 0x01, 0x00, 0x10]).is_err());
+
+        assert!(RecordHeader::parse(&[0x17, 0x03, 0x03, 0x00]).is_err());
+        assert!(RecordHeader::parse(&[0x17, 0x03, 0x03, 0x00, 0x10, 0x00])
+                    .is_err());
+    }
+}
@@ -0,0 +1,202 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! XAES-256-GCM, as described in the [XAES-256-GCM specification].
+//!
+//! XAES-256-GCM is AES-256-GCM with a subkey derived from the key and the
+//! first 12 bytes of a 192-bit nonce, using AES-256 in counter mode as a
+//! KDF as described in [NIST SP 800-108r1], with CMAC-style subkey
+//! derivation. The remaining 12 bytes of the nonce are the AES-256-GCM nonce.
+//! The nonces are long enough to be chosen at random, and only the AES block
+//! cipher is used, so it is suitable when AES is required.
+//!
+//! The 192-bit nonces don't fit the `ring::aead::Algorithm` interface, so
+//! XAES-256-GCM has its own `seal_in_place` and `open_in_place` functions that
+//! otherwise work like the ones in `ring::aead`.
+//!
+//! [XAES-256-GCM specification]: https://c2sp.org/XAES-256-GCM
+//! [NIST SP 800-108r1]:
+//!     https://nvlpubs.nist.gov/nistpubs/SpecialPublications/NIST.SP.800-108r1.pdf
+
+use {aead, error, init};
+use super::aes_ocb::{AES_KEY, AES_MAX_ROUNDS, BLOCK_LEN, Block, double,
+                     GFp_AES_encrypt, GFp_AES_set_encrypt_key};
+
+/// The length of XAES-256-GCM keys.
+pub const KEY_LEN: usize = 256 / 8;
+
+/// The length of XAES-256-GCM nonces.
+pub const NONCE_LEN: usize = 192 / 8;
+
+/// The length of XAES-256-GCM tags.
+pub const TAG_LEN: usize = super::TAG_LEN;
+
+/// An XAES-256-GCM key, for both sealing and opening.
+pub struct Key {
+    aes_key: AES_KEY,
+
+    // K1 from the specification: L = AES-256(key, 0**128), doubled as for
+    // CMAC in NIST SP 800-38B.
+    k1: Block,
+}
+
+impl Key {
+    /// Create a new key. `key_bytes` must be exactly `KEY_LEN` bytes long.
+    pub fn new(key_bytes: &[u8]) -> Result<Key, error::KeyRejected> {
+        init::init_once();
+        try!(error::KeyRejected::check_len(key_bytes.len(), KEY_LEN));
+        let key_bytes = slice_as_array_ref!(key_bytes, KEY_LEN).unwrap();
+        let mut aes_key = AES_KEY {
+            rd_key: [0u32; 4 * (AES_MAX_ROUNDS + 1)],
+            rounds: 0,
+        };
+        if unsafe {
+            GFp_AES_set_encrypt_key(key_bytes.as_ptr(), 8 * KEY_LEN as u32,
+                                    &mut aes_key)
+        } != 0 {
+            return Err(error::KeyRejected::Unexpected);
+        }
+        let mut l = [0u8; BLOCK_LEN];
+        encrypt_in_place(&aes_key, &mut l);
+        Ok(Key {
+            aes_key: aes_key,
+            k1: double(&l),
+        })
+    }
+}
+
+/// Encrypts and authenticates (&ldquo;seals&rdquo;) data in place, like
+/// `ring::aead::seal_in_place`.
+///
+/// The input is `in_out[..(in_out.len() - out_suffix_capacity)]`. When
+/// `seal_in_place` returns `Ok(out_len)`, the ciphertext followed by the tag
+/// is `in_out[..out_len]`. `out_suffix_capacity` must be at least `TAG_LEN`.
+///
+/// `nonce` must be exactly `NONCE_LEN` bytes long and must be unique for every
+/// use of the key to seal data. It may be chosen at random.
+pub fn seal_in_place(key: &Key, nonce: &[u8], in_out: &mut [u8],
+                     out_suffix_capacity: usize, ad: &[u8])
+                     -> Result<usize, error::Unspecified> {
+    let nonce = try!(slice_as_array_ref!(nonce, NONCE_LEN));
+    let subkey = derive_subkey(key, &nonce[..PREFIX_LEN]);
+    let subkey = try!(aead::SealingKey::new(&aead::AES_256_GCM, &subkey));
+    Ok(try!(aead::seal_in_place(&subkey, &nonce[PREFIX_LEN..], in_out,
+                                out_suffix_capacity, ad)))
+}
+
+/// Authenticates and decrypts (&ldquo;opens&rdquo;) data in place, like
+/// `ring::aead::open_in_place`.
+///
+/// The input is `in_out[in_prefix_len..]`, which must be the ciphertext
+/// followed by the tag. When `open_in_place` returns `Ok(out_len)`, the
+/// decrypted output is `in_out[..out_len]`.
+pub fn open_in_place(key: &Key, nonce: &[u8], in_prefix_len: usize,
+                     in_out: &mut [u8], ad: &[u8])
+                     -> Result<usize, error::Unspecified> {
+    let nonce = try!(slice_as_array_ref!(nonce, NONCE_LEN));
+    let subkey = derive_subkey(key, &nonce[..PREFIX_LEN]);
+    let subkey = try!(aead::OpeningKey::new(&aead::AES_256_GCM, &subkey));
+    aead::open_in_place(&subkey, &nonce[PREFIX_LEN..], in_prefix_len, in_out,
+                        ad)
+}
+
+// The length of the part of the nonce that is used to derive the subkey.
+const PREFIX_LEN: usize = 12;
+
+// Derives the AES-256-GCM key for the nonce prefix `prefix`. The two halves
+// of the key are AES-256(key, M_i XOR K1), where
+// M_i = [0x00, i, 'X', 0x00] || prefix.
+fn derive_subkey(key: &Key, prefix: &[u8]) -> [u8; KEY_LEN] {
+    debug_assert_eq!(prefix.len(), PREFIX_LEN);
+    let mut subkey = [0u8; KEY_LEN];
+    for (i, half) in subkey.chunks_mut(BLOCK_LEN).enumerate() {
+        half[..4].copy_from_slice(&[0x00, (i + 1) as u8, b'X', 0x00]);
+        half[4..].copy_from_slice(prefix);
+        for (b, k1) in half.iter_mut().zip(key.k1.iter()) {
+            *b ^= *k1;
+        }
+        encrypt_in_place(&key.aes_key, half);
+    }
+    subkey
+}
+
+fn encrypt_in_place(aes_key: &AES_KEY, block: &mut [u8]) {
+    debug_assert_eq!(block.len(), BLOCK_LEN);
+    unsafe {
+        GFp_AES_encrypt(block.as_ptr(), block.as_mut_ptr(), aes_key);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use test;
+    use super::*;
+
+    #[test]
+    fn xaes_256_gcm_test() {
+        test::from_file("src/aead/xaes_256_gcm_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+            let key = Key::new(&test_case.consume_bytes("KEY")).unwrap();
+            let nonce = test_case.consume_bytes("NONCE");
+            let plaintext = test_case.consume_bytes("IN");
+            let ad = test_case.consume_bytes("AD");
+            let ct = test_case.consume_bytes("CT");
+            let tag = test_case.consume_bytes("TAG");
+
+            let mut in_out = plaintext.clone();
+            in_out.extend_from_slice(&[0u8; TAG_LEN]);
+            let len =
+                seal_in_place(&key, &nonce, &mut in_out, TAG_LEN, &ad).unwrap();
+            assert_eq!(&in_out[..plaintext.len()], &ct[..]);
+            assert_eq!(&in_out[plaintext.len()..len], &tag[..]);
+
+            for in_prefix_len in &[0, 1, 64] {
+                let mut in_out = vec![123u8; *in_prefix_len];
+                in_out.extend_from_slice(&ct);
+                in_out.extend_from_slice(&tag);
+                let len = open_in_place(&key, &nonce, *in_prefix_len,
+                                        &mut in_out, &ad).unwrap();
+                assert_eq!(&in_out[..len], &plaintext[..]);
+            }
+
+            // Changing the part of the nonce that the subkey is derived from,
+            // or the AES-256-GCM nonce, is detected.
+            for &i in &[0, PREFIX_LEN - 1, PREFIX_LEN, NONCE_LEN - 1] {
+                let mut bad_nonce = nonce.clone();
+                bad_nonce[i] ^= 1;
+                let mut in_out = ct.clone();
+                in_out.extend_from_slice(&tag);
+                assert!(open_in_place(&key, &bad_nonce, 0, &mut in_out, &ad)
+                            .is_err());
+            }
+
+            // Wrong nonce lengths are rejected.
+            let mut in_out = ct.clone();
+            in_out.extend_from_slice(&tag);
+            assert!(open_in_place(&key, &nonce[..(NONCE_LEN - 1)], 0,
+                                  &mut in_out, &ad).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn xaes_256_gcm_wrong_key_len() {
+        assert!(Key::new(&[0u8; KEY_LEN - 1]).is_err());
+        assert!(Key::new(&[0u8; KEY_LEN + 1]).is_err());
+        assert!(Key::new(&[0u8; 16]).is_err());
+    }
+}
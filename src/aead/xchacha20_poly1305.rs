@@ -0,0 +1,281 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! XChaCha20-Poly1305, as described in [draft-irtf-cfrg-xchacha-03].
+//!
+//! XChaCha20-Poly1305 is ChaCha20-Poly1305 with a subkey derived by HChaCha20
+//! from the key and the first 16 bytes of a 192-bit nonce. The nonces are
+//! long enough to be chosen at random. WireGuard uses it, as `XAEAD`, to
+//! encrypt cookies.
+//!
+//! XChaCha20-Poly1305 is usually used through `ring::aead`, as
+//! `aead::XCHACHA20_POLY1305`. This module also has `seal_in_place` and
+//! `open_in_place` functions that take a `Key` directly, and that otherwise
+//! work like the ones in `ring::aead`.
+//!
+//! [draft-irtf-cfrg-xchacha-03]:
+//!     https://tools.ietf.org/html/draft-irtf-cfrg-xchacha-03
+
+use {aead, error, init, polyfill, secret};
+use secret::Zeroize;
+use super::chacha20_poly1305::{chacha20_poly1305_init, chacha20_poly1305_open,
+                               chacha20_poly1305_seal};
+
+/// XChaCha20-Poly1305 as described in [draft-irtf-cfrg-xchacha-03].
+///
+/// The keys are 256 bits long and the nonces are 192 bits long, which is long
+/// enough for nonces to be chosen at random.
+///
+/// [draft-irtf-cfrg-xchacha-03]:
+///     https://tools.ietf.org/html/draft-irtf-cfrg-xchacha-03
+pub static XCHACHA20_POLY1305: aead::Algorithm = aead::Algorithm {
+    key_len: KEY_LEN,
+    nonce_len: NONCE_LEN,
+    name: "XCHACHA20_POLY1305",
+    init: chacha20_poly1305_init,
+    seal: xchacha20_poly1305_seal,
+    open: xchacha20_poly1305_open,
+};
+
+/// The length of XChaCha20-Poly1305 keys.
+pub const KEY_LEN: usize = 32;
+
+/// The length of XChaCha20-Poly1305 nonces.
+pub const NONCE_LEN: usize = 192 / 8;
+
+/// The length of XChaCha20-Poly1305 tags.
+pub const TAG_LEN: usize = super::TAG_LEN;
+
+/// An XChaCha20-Poly1305 key, for both sealing and opening.
+pub struct Key {
+    key: [u8; KEY_LEN],
+}
+
+impl Key {
+    /// Create a new key. `key_bytes` must be exactly `KEY_LEN` bytes long.
+    pub fn new(key_bytes: &[u8]) -> Result<Key, error::KeyRejected> {
+        init::init_once();
+        try!(error::KeyRejected::check_len(key_bytes.len(), KEY_LEN));
+        let key_bytes = slice_as_array_ref!(key_bytes, KEY_LEN).unwrap();
+        Ok(Key { key: *key_bytes })
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) { self.key.zeroize(); }
+}
+
+/// Encrypts and authenticates (&ldquo;seals&rdquo;) data in place, like
+/// `ring::aead::seal_in_place`.
+///
+/// The input is `in_out[..(in_out.len() - out_suffix_capacity)]`. When
+/// `seal_in_place` returns `Ok(out_len)`, the ciphertext followed by the tag
+/// is `in_out[..out_len]`. `out_suffix_capacity` must be at least `TAG_LEN`.
+///
+/// `nonce` must be exactly `NONCE_LEN` bytes long and must be unique for every
+/// use of the key to seal data.
+pub fn seal_in_place(key: &Key, nonce: &[u8], in_out: &mut [u8],
+                     out_suffix_capacity: usize, ad: &[u8])
+                     -> Result<usize, error::Unspecified> {
+    let (subkey, nonce) = try!(derive(key, nonce));
+    let key = try!(aead::SealingKey::new(&aead::CHACHA20_POLY1305,
+                                         subkey.expose()));
+    Ok(try!(aead::seal_in_place(&key, &nonce, in_out, out_suffix_capacity,
+                                ad)))
+}
+
+/// Authenticates and decrypts (&ldquo;opens&rdquo;) data in place, like
+/// `ring::aead::open_in_place`.
+///
+/// The input is `in_out[in_prefix_len..]`, which must be the ciphertext
+/// followed by the tag. When `open_in_place` returns `Ok(out_len)`, the
+/// decrypted output is `in_out[..out_len]`.
+pub fn open_in_place(key: &Key, nonce: &[u8], in_prefix_len: usize,
+                     in_out: &mut [u8], ad: &[u8])
+                     -> Result<usize, error::Unspecified> {
+    let (subkey, nonce) = try!(derive(key, nonce));
+    let key = try!(aead::OpeningKey::new(&aead::CHACHA20_POLY1305,
+                                         subkey.expose()));
+    aead::open_in_place(&key, &nonce, in_prefix_len, in_out, ad)
+}
+
+fn xchacha20_poly1305_seal(ctx: &[u64; aead::KEY_CTX_BUF_ELEMS],
+                           nonce: &[u8], in_out: &mut [u8],
+                           tag_out: &mut [u8; aead::TAG_LEN], ad: &[&[u8]])
+                           -> Result<(), error::Unspecified> {
+    let (subkey_ctx, nonce) = try!(derive_ctx(ctx, nonce));
+    chacha20_poly1305_seal(&subkey_ctx, &nonce, in_out, tag_out, ad)
+}
+
+fn xchacha20_poly1305_open(ctx: &[u64; aead::KEY_CTX_BUF_ELEMS],
+                           nonce: &[u8], in_out: &mut [u8],
+                           in_prefix_len: usize,
+                           tag_out: &mut [u8; aead::TAG_LEN], ad: &[&[u8]])
+                           -> Result<(), error::Unspecified> {
+    let (subkey_ctx, nonce) = try!(derive_ctx(ctx, nonce));
+    chacha20_poly1305_open(&subkey_ctx, &nonce, in_out, in_prefix_len,
+                           tag_out, ad)
+}
+
+// Like `derive`, but for the key in the context buffer of an `aead::Key`,
+// and returning the ChaCha20-Poly1305 context buffer for the subkey.
+fn derive_ctx(ctx: &[u64; aead::KEY_CTX_BUF_ELEMS], nonce: &[u8])
+              -> Result<([u64; aead::KEY_CTX_BUF_ELEMS],
+                         [u8; super::NONCE_LEN]), error::Unspecified> {
+    let key = try!(slice_as_array_ref!(
+        &polyfill::slice::u64_as_u8(ctx)[..KEY_LEN], KEY_LEN));
+    let (subkey, nonce) = try!(derive(&Key { key: *key }, nonce));
+    let mut subkey_ctx = [0u64; aead::KEY_CTX_BUF_ELEMS];
+    try!(chacha20_poly1305_init(
+        polyfill::slice::u64_as_u8_mut(&mut subkey_ctx), subkey.expose()));
+    Ok((subkey_ctx, nonce))
+}
+
+// Returns the ChaCha20-Poly1305 key and nonce for `key` and the XChaCha20
+// nonce `nonce`.
+fn derive(key: &Key, nonce: &[u8])
+          -> Result<(secret::SecretBox<[u8; KEY_LEN]>, [u8; super::NONCE_LEN]),
+                    error::Unspecified> {
+    let nonce = try!(slice_as_array_ref!(nonce, NONCE_LEN));
+    let subkey = secret::SecretBox::new(
+        hchacha20(&key.key, slice_as_array_ref!(&nonce[..16], 16).unwrap()));
+    let mut chacha20_nonce = [0u8; super::NONCE_LEN];
+    chacha20_nonce[4..].copy_from_slice(&nonce[16..]);
+    Ok((subkey, chacha20_nonce))
+}
+
+// The HChaCha20 function, used to derive XChaCha20 subkeys.
+#[doc(hidden)]
+pub fn hchacha20(key: &[u8; KEY_LEN], nonce: &[u8; 16]) -> [u8; KEY_LEN] {
+    fn quarter_round(x: &mut [u32; 16], a: usize, b: usize, c: usize,
+                     d: usize) {
+        x[a] = x[a].wrapping_add(x[b]);
+        x[d] = (x[d] ^ x[a]).rotate_left(16);
+        x[c] = x[c].wrapping_add(x[d]);
+        x[b] = (x[b] ^ x[c]).rotate_left(12);
+        x[a] = x[a].wrapping_add(x[b]);
+        x[d] = (x[d] ^ x[a]).rotate_left(8);
+        x[c] = x[c].wrapping_add(x[d]);
+        x[b] = (x[b] ^ x[c]).rotate_left(7);
+    }
+
+    let word = |bytes: &[u8], i: usize| {
+        polyfill::slice::u32_from_le_u8(
+            slice_as_array_ref!(&bytes[(4 * i)..(4 * i + 4)], 4).unwrap())
+    };
+
+    // "expand 32-byte k"
+    let mut x = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574,
+                 word(key, 0), word(key, 1), word(key, 2), word(key, 3),
+                 word(key, 4), word(key, 5), word(key, 6), word(key, 7),
+                 word(nonce, 0), word(nonce, 1), word(nonce, 2),
+                 word(nonce, 3)];
+    for _ in 0..10 {
+        // Column round.
+        quarter_round(&mut x, 0, 4, 8, 12);
+        quarter_round(&mut x, 1, 5, 9, 13);
+        quarter_round(&mut x, 2, 6, 10, 14);
+        quarter_round(&mut x, 3, 7, 11, 15);
+
+        // Diagonal round.
+        quarter_round(&mut x, 0, 5, 10, 15);
+        quarter_round(&mut x, 1, 6, 11, 12);
+        quarter_round(&mut x, 2, 7, 8, 13);
+        quarter_round(&mut x, 3, 4, 9, 14);
+    }
+
+    let mut out = [0u8; KEY_LEN];
+    for (out, &w) in out.chunks_mut(4)
+                        .zip(x[0..4].iter().chain(x[12..16].iter())) {
+        for (i, b) in out.iter_mut().enumerate() {
+            *b = (w >> (8 * i)) as u8;
+        }
+    }
+    out
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {aead, test};
+    use super::*;
+
+    #[test]
+    pub fn test_xchacha20_poly1305() {
+        aead::tests::test_aead(&XCHACHA20_POLY1305,
+                               "src/aead/xchacha20_poly1305_tests.txt");
+    }
+
+    // draft-irtf-cfrg-xchacha-03 Section 2.2.1.
+    #[test]
+    fn hchacha20_test() {
+        let key = test::from_hex("000102030405060708090a0b0c0d0e0f\
+                                  101112131415161718191a1b1c1d1e1f").unwrap();
+        let nonce =
+            test::from_hex("000000090000004a0000000031415927").unwrap();
+        let expected =
+            test::from_hex("82413b4227b27bfed30e42508a877d73\
+                            a0f9e4d58a74a853c12ec41326d3ecdc").unwrap();
+        let subkey = hchacha20(slice_as_array_ref!(&key, KEY_LEN).unwrap(),
+                               slice_as_array_ref!(&nonce, 16).unwrap());
+        assert_eq!(&subkey[..], &expected[..]);
+    }
+
+    #[test]
+    fn xchacha20_poly1305_test() {
+        test::from_file("src/aead/xchacha20_poly1305_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+            let key = Key::new(&test_case.consume_bytes("KEY")).unwrap();
+            let nonce = test_case.consume_bytes("NONCE");
+            let plaintext = test_case.consume_bytes("IN");
+            let ad = test_case.consume_bytes("AD");
+            let ct = test_case.consume_bytes("CT");
+            let tag = test_case.consume_bytes("TAG");
+
+            let mut in_out = plaintext.clone();
+            in_out.extend_from_slice(&[0u8; TAG_LEN]);
+            let len =
+                seal_in_place(&key, &nonce, &mut in_out, TAG_LEN, &ad).unwrap();
+            assert_eq!(&in_out[..plaintext.len()], &ct[..]);
+            assert_eq!(&in_out[plaintext.len()..len], &tag[..]);
+
+            for in_prefix_len in &[0, 1, 64] {
+                let mut in_out = vec![123u8; *in_prefix_len];
+                in_out.extend_from_slice(&ct);
+                in_out.extend_from_slice(&tag);
+                let len = open_in_place(&key, &nonce, *in_prefix_len,
+                                        &mut in_out, &ad).unwrap();
+                assert_eq!(&in_out[..len], &plaintext[..]);
+            }
+
+            // Flipping a bit of the last nonce byte, which only affects the
+            // ChaCha20 nonce and not the subkey, is detected.
+            let mut bad_nonce = nonce.clone();
+            bad_nonce[NONCE_LEN - 1] ^= 1;
+            let mut in_out = ct.clone();
+            in_out.extend_from_slice(&tag);
+            assert!(open_in_place(&key, &bad_nonce, 0, &mut in_out, &ad)
+                        .is_err());
+
+            // Wrong nonce lengths are rejected.
+            let mut in_out = ct.clone();
+            in_out.extend_from_slice(&tag);
+            assert!(open_in_place(&key, &nonce[..(NONCE_LEN - 1)], 0,
+                                  &mut in_out, &ad).is_err());
+
+            Ok(())
+        });
+    }
+}
@@ -12,13 +12,16 @@
 // OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
 // CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 
-//! Key Agreement: ECDH, including X25519.
+//! Key Agreement: ECDH, including X25519 and X448, and finite-field
+//! Diffie-Hellman (FFDHE).
 //!
 //! # Example
 //!
-//! Note that this example uses X25519, but ECDH using NIST P-256/P-384 is done
-//! exactly the same way, just substituting
-//! `agreement::ECDH_P256`/`agreement::ECDH_P384` for `agreement::X25519`.
+//! Note that this example uses X25519, but X448, ECDH using NIST
+//! P-256/P-384/P-521, and FFDHE are done exactly the same way, just
+//! substituting `agreement::X448`, `agreement::ECDH_P256`,
+//! `agreement::ECDH_P384`, `agreement::ECDH_P521`, `agreement::FFDHE2048`,
+//! `agreement::FFDHE3072`, or `agreement::FFDHE4096` for `agreement::X25519`.
 //!
 //! ```
 //! # extern crate untrusted;
@@ -77,16 +80,29 @@
 
 
 
-use {ec, error, rand};
+use {audit, ec, error, kat, rand, secret};
+use core;
 use untrusted;
 
 
-pub use ec::PUBLIC_KEY_MAX_LEN;
+/// The maximum length, in bytes, of an encoded public key. This is the
+/// length of an FFDHE4096 public key.
+pub const PUBLIC_KEY_MAX_LEN: usize = FFDHE_ELEM_MAX_BYTES;
 
-pub use ec::suite_b::ecdh::{ECDH_P256, ECDH_P384};
+// The maximum length, in bytes, of a shared secret.
+const SHARED_SECRET_MAX_LEN: usize = FFDHE_ELEM_MAX_BYTES;
+
+const FFDHE_ELEM_MAX_BYTES: usize = 4096 / 8;
+
+pub use ec::suite_b::ecdh::{ECDH_P256, ECDH_P384, ECDH_P521};
 
 pub use ec::x25519::X25519;
 
+pub use ec::x448::X448;
+
+#[cfg(feature = "use_heap")]
+pub use ffdhe::{FFDHE2048, FFDHE3072, FFDHE4096};
+
 
 /// A key agreement algorithm.
 #[cfg_attr(not(test), allow(dead_code))]
@@ -98,6 +114,53 @@ pub struct Algorithm {
     pub i: ec::AgreementAlgorithmImpl,
 }
 
+impl Algorithm {
+    /// The name of the algorithm, which is the name of its static in this
+    /// module, e.g. `"ECDH_P256"` for `ECDH_P256`.
+    #[inline(always)]
+    pub fn name(&self) -> &'static str { self.i.name }
+
+    /// Returns the algorithm whose `name()` is `name`, e.g. `X25519` for
+    /// `"X25519"`. The comparison is case-sensitive. The `FFDHE` algorithms
+    /// are only found in `use_heap` mode.
+    pub fn from_name(name: &str)
+                     -> Result<&'static Algorithm, error::Unspecified> {
+        ALGORITHMS.iter()
+            .chain(ffdhe_algorithms().iter())
+            .map(|&alg| alg)
+            .find(|alg| alg.i.name == name)
+            .ok_or(error::Unspecified)
+    }
+
+    /// The size in bytes of the encoded public keys.
+    #[inline(always)]
+    pub fn public_key_len(&self) -> usize { self.i.public_key_len }
+
+    /// The size in bytes of the compressed encoding of public keys, or `None`
+    /// if the algorithm doesn't have a compressed encoding. Only the ECDH
+    /// algorithms for the NIST curves have one.
+    #[inline(always)]
+    pub fn compressed_public_key_len(&self) -> Option<usize> {
+        self.i.compressed_public_key_len
+    }
+
+    /// The size in bytes of the key material that `agree_ephemeral` passes
+    /// to the KDF.
+    #[inline(always)]
+    pub fn shared_secret_len(&self) -> usize { self.i.elem_and_scalar_len }
+
+    /// The security level of the algorithm, in bits, as given for ECC keys
+    /// in [NIST SP 800-57 Part 1] Table 2, for X25519 and X448 in
+    /// [RFC 7748], and for the FFDHE groups in [RFC 7919] Appendix A.
+    ///
+    /// [NIST SP 800-57 Part 1]:
+    ///     http://nvlpubs.nist.gov/nistpubs/SpecialPublications/NIST.SP.800-57pt1r4.pdf
+    /// [RFC 7748]: https://tools.ietf.org/html/rfc7748
+    /// [RFC 7919]: https://tools.ietf.org/html/rfc7919
+    #[inline(always)]
+    pub fn security_bits(&self) -> usize { self.i.security_bits }
+}
+
 /// An ephemeral private key for use (only) with `agree_ephemeral`. The
 /// signature of `agree_ephemeral` ensures that an `EphemeralPrivateKey` can be
 /// used for at most one key agreement.
@@ -116,8 +179,29 @@ impl<'a> EphemeralPrivateKey {
         //
         // This only handles the key generation part of step 1. The rest of
         // step one is done by `compute_public_key()`.
+        let private_key = try!(ec::PrivateKey::generate(&alg.i, rng));
+        audit::notify(audit::EventKind::KeyCreated, alg.name());
+        Ok(EphemeralPrivateKey {
+            private_key: private_key,
+            alg: alg,
+        })
+    }
+
+    /// Constructs the private key whose value is `seed`, deterministically.
+    ///
+    /// This is intended only for reproducible tests and for deterministic key
+    /// derivation schemes that produce the private key value themselves;
+    /// otherwise, use `generate`. `seed` must be secret and must have been
+    /// generated as `generate` would: for X25519 and X448 it is any 32 or 56
+    /// bytes, respectively, and for ECDH_P256, ECDH_P384, and ECDH_P521 it is
+    /// the big-endian encoding of a scalar in the range [1, n), exactly 32,
+    /// 48, or 66 bytes long, respectively.
+    pub fn from_seed(alg: &'static Algorithm, seed: &[u8])
+                     -> Result<EphemeralPrivateKey, error::Unspecified> {
+        let private_key = try!(ec::PrivateKey::from_seed(&alg.i, seed));
+        audit::notify(audit::EventKind::KeyCreated, alg.name());
         Ok(EphemeralPrivateKey {
-            private_key: try!(ec::PrivateKey::generate(&alg.i, rng)),
+            private_key: private_key,
             alg: alg,
         })
     }
@@ -154,6 +238,22 @@ impl<'a> EphemeralPrivateKey {
         self.private_key.compute_public_key(&self.alg.i, out)
     }
 
+    /// Like `compute_public_key`, but encodes the public point in compressed
+    /// form as described in [SEC 1: Elliptic Curve Cryptography, Version 2.0]
+    /// Section 2.3.3, i.e. 2 or 3, for an even or odd y coordinate, followed
+    /// by the x coordinate.
+    ///
+    /// `out.len()` must be equal to the value returned by the algorithm's
+    /// `compressed_public_key_len`; this fails for algorithms that don't have
+    /// a compressed encoding.
+    ///
+    /// [SEC 1: Elliptic Curve Cryptography, Version 2.0]:
+    ///     http://www.secg.org/sec1-v2.pdf
+    pub fn compute_public_key_compressed(&self, out: &mut [u8])
+                                         -> Result<(), error::Unspecified> {
+        self.private_key.compute_public_key_compressed(&self.alg.i, out)
+    }
+
     #[cfg(test)]
     pub fn bytes(&'a self) -> &'a [u8] { self.private_key.bytes() }
 }
@@ -182,6 +282,13 @@ impl<'a> EphemeralPrivateKey {
 /// key material from the key agreement operation and then returns what `kdf`
 /// returns.
 ///
+/// To get typed session keys, `kdf` can pass the key material to
+/// `hkdf::extract` and construct the keys with `hkdf::Prk::expand_value`; see
+/// `secret::ValueFromSecret`.
+///
+/// `agree_ephemeral_unparsed` does the same with the peer's public key as an
+/// `UnparsedPublicKey`, for callers that don't use `untrusted`.
+///
 /// C analogs: `EC_POINT_oct2point` + `ECDH_compute_key`, `X25519`.
 pub fn agree_ephemeral<F, R, E>(my_private_key: EphemeralPrivateKey,
                                 peer_public_key_alg: &Algorithm,
@@ -207,24 +314,159 @@ pub fn agree_ephemeral<F, R, E>(my_private_key: EphemeralPrivateKey,
     // NSA Guide Step 1 is handled by `EphemeralPrivateKey::generate()` and
     // `EphemeralPrivateKey::compute_public_key()`.
 
-    let mut shared_key = [0u8; ec::ELEM_MAX_BYTES];
-    let shared_key =
-        &mut shared_key[..my_private_key.alg.i.elem_and_scalar_len];
+    // The shared secret is zeroized when `shared_key` is dropped, whether or
+    // not the key agreement succeeds.
+    let mut shared_key = secret::SecretBox::new([0u8; SHARED_SECRET_MAX_LEN]);
+    let shared_key_len = my_private_key.alg.i.elem_and_scalar_len;
+    let shared_key = &mut shared_key.expose_mut()[..shared_key_len];
 
     // NSA Guide Steps 2, 3, and 4.
     //
     // We have a pretty liberal interpretation of the NIST's spec's "Destroy"
-    // that doesn't meet the NSA requirement to "zeroize."
+    // that doesn't meet the NSA requirement to "zeroize" the intermediate
+    // values.
     try!((my_private_key.alg.i.ecdh)(shared_key, &my_private_key.private_key,
                                      peer_public_key).map_err(|_| error_value));
 
-    // NSA Guide Steps 5 and 6.
-    //
-    // Again, we have a pretty liberal interpretation of the NIST's spec's
-    // "Destroy" that doesn't meet the NSA requirement to "zeroize."
+    // NSA Guide Steps 5 and 6. The caller must not keep copies of
+    // `shared_key`.
     kdf(shared_key)
 }
 
+/// Formats the algorithm as its `name()`, which `Algorithm::from_name`
+/// accepts.
+impl core::fmt::Display for Algorithm {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+        fmt.write_str(self.i.name)
+    }
+}
+
+static ALGORITHMS: [&'static Algorithm; 5] = [
+    &ECDH_P256,
+    &ECDH_P384,
+    &ECDH_P521,
+    &X25519,
+    &X448,
+];
+
+#[cfg(feature = "use_heap")]
+fn ffdhe_algorithms() -> &'static [&'static Algorithm] {
+    static FFDHE_ALGORITHMS: [&'static Algorithm; 3] =
+        [&FFDHE2048, &FFDHE3072, &FFDHE4096];
+    &FFDHE_ALGORITHMS
+}
+
+#[cfg(not(feature = "use_heap"))]
+fn ffdhe_algorithms() -> &'static [&'static Algorithm] { &[] }
+
+/// Runs the known-answer test of `X25519`, with Alice's private key and Bob's
+/// public key from [RFC 7748 Section 6.1]; see `kat`.
+///
+/// [RFC 7748 Section 6.1]: https://tools.ietf.org/html/rfc7748#section-6.1
+pub fn known_answer_tests() -> [kat::TestResult; 1] {
+    let passed =
+        EphemeralPrivateKey::from_seed(&X25519, &KAT_X25519_PRIVATE_KEY)
+            .and_then(|private_key| {
+                agree_ephemeral(private_key, &X25519,
+                                untrusted::Input::from(
+                                    &KAT_X25519_PEER_PUBLIC_KEY),
+                                error::Unspecified, |shared_secret| {
+                    Ok(shared_secret == KAT_X25519_SHARED_SECRET)
+                })
+            })
+            .unwrap_or(false);
+    [kat::TestResult { name: "X25519", passed: passed }]
+}
+
+const KAT_X25519_PRIVATE_KEY: [u8; 32] = [
+    0x77, 0x07, 0x6d, 0x0a, 0x73, 0x18, 0xa5, 0x7d, 0x3c, 0x16, 0xc1, 0x72,
+    0x51, 0xb2, 0x66, 0x45, 0xdf, 0x4c, 0x2f, 0x87, 0xeb, 0xc0, 0x99, 0x2a,
+    0xb1, 0x77, 0xfb, 0xa5, 0x1d, 0xb9, 0x2c, 0x2a,
+];
+
+const KAT_X25519_PEER_PUBLIC_KEY: [u8; 32] = [
+    0xde, 0x9e, 0xdb, 0x7d, 0x7b, 0x7d, 0xc1, 0xb4, 0xd3, 0x5b, 0x61, 0xc2,
+    0xec, 0xe4, 0x35, 0x37, 0x3f, 0x83, 0x43, 0xc8, 0x5b, 0x78, 0x67, 0x4d,
+    0xad, 0xfc, 0x7e, 0x14, 0x6f, 0x88, 0x2b, 0x4f,
+];
+
+const KAT_X25519_SHARED_SECRET: [u8; 32] = [
+    0x4a, 0x5d, 0x9d, 0x5b, 0xa4, 0xce, 0x2d, 0xe1, 0x72, 0x8e, 0x3b, 0xf4,
+    0x80, 0x35, 0x0f, 0x25, 0xe0, 0x7e, 0x21, 0xc9, 0x47, 0xd1, 0x9e, 0x33,
+    0x76, 0xf0, 0x9b, 0x3c, 0x1e, 0x16, 0x17, 0x42,
+];
+
+/// An unparsed public key for key agreement, which may be invalid.
+///
+/// This is a convenience for callers that don't use `untrusted`; see
+/// `agree_ephemeral_unparsed`.
+#[derive(Clone, Copy)]
+pub struct UnparsedPublicKey<B: AsRef<[u8]>> {
+    alg: &'static Algorithm,
+    bytes: B,
+}
+
+impl<B: AsRef<[u8]>> UnparsedPublicKey<B> {
+    /// Constructs the public key of algorithm `alg` encoded as `bytes`,
+    /// without parsing or validating it.
+    #[inline]
+    pub fn new(alg: &'static Algorithm, bytes: B) -> Self {
+        UnparsedPublicKey { alg: alg, bytes: bytes }
+    }
+
+    /// The key agreement algorithm.
+    #[inline]
+    pub fn algorithm(&self) -> &'static Algorithm { self.alg }
+
+    /// The encoded public key.
+    #[inline]
+    pub fn bytes(&self) -> &B { &self.bytes }
+}
+
+/// Performs a key agreement with an ephemeral private key and the given
+/// public key, exactly like `agree_ephemeral` with the algorithm and the
+/// bytes of `peer_public_key`.
+///
+/// # Examples
+///
+/// ```
+/// use ring::{agreement, error, rand};
+///
+/// # fn agree() -> Result<(), error::Unspecified> {
+/// let rng = rand::SystemRandom::new();
+/// let my_private_key =
+///     try!(agreement::EphemeralPrivateKey::generate(&agreement::X25519,
+///                                                   &rng));
+///
+/// // In a real application, the peer's public key would be received over
+/// // the network.
+/// let peer_private_key =
+///     try!(agreement::EphemeralPrivateKey::generate(&agreement::X25519,
+///                                                   &rng));
+/// let mut peer_public_key = [0u8; 32];
+/// try!(peer_private_key.compute_public_key(&mut peer_public_key));
+/// let peer_public_key =
+///     agreement::UnparsedPublicKey::new(&agreement::X25519, peer_public_key);
+///
+/// agreement::agree_ephemeral_unparsed(my_private_key, &peer_public_key,
+///                                     error::Unspecified, |key_material| {
+///     // In a real application, `key_material` would be passed to a KDF.
+///     assert_eq!(key_material.len(), 32);
+///     Ok(())
+/// })
+/// # }
+/// # fn main() { agree().unwrap() }
+/// ```
+pub fn agree_ephemeral_unparsed<B, F, R, E>(
+        my_private_key: EphemeralPrivateKey,
+        peer_public_key: &UnparsedPublicKey<B>, error_value: E, kdf: F)
+        -> Result<R, E>
+        where B: AsRef<[u8]>, F: FnOnce(&[u8]) -> Result<R, E> {
+    agree_ephemeral(my_private_key, peer_public_key.alg,
+                    untrusted::Input::from(peer_public_key.bytes.as_ref()),
+                    error_value, kdf)
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -260,11 +502,40 @@ mod tests {
                         private_key.compute_public_key(computed_public).is_ok());
                     assert_eq!(computed_public, &my_public[..]);
 
+                    match alg.compressed_public_key_len() {
+                        Some(len) => {
+                            let mut computed = [0u8; PUBLIC_KEY_MAX_LEN];
+                            let computed = &mut computed[..len];
+                            assert!(private_key.compute_public_key_compressed(
+                                        computed).is_ok());
+                            let y_is_odd = my_public[my_public.len() - 1] & 1;
+                            assert_eq!(computed[0], 2 | y_is_odd);
+                            assert_eq!(&computed[1..], &my_public[1..len]);
+                        },
+                        None => {
+                            assert!(private_key.compute_public_key_compressed(
+                                        computed_public).is_err());
+                        },
+                    }
+
+                    let seeded_key =
+                        EphemeralPrivateKey::from_seed(alg, &my_private)
+                            .unwrap();
+                    assert_eq!(seeded_key.bytes(), private_key.bytes());
+
                     assert!(agree_ephemeral(private_key, alg, peer_public, (),
                                             |key_material| {
                         assert_eq!(key_material, &output[..]);
                         Ok(())
                     }).is_ok());
+
+                    let unparsed = UnparsedPublicKey::new(
+                        alg, peer_public.as_slice_less_safe());
+                    assert!(agree_ephemeral_unparsed(seeded_key, &unparsed, (),
+                                                     |key_material| {
+                        assert_eq!(key_material, &output[..]);
+                        Ok(())
+                    }).is_ok());
                 },
 
                 Some(_) => {
@@ -279,6 +550,14 @@ mod tests {
                     assert!(
                         agree_ephemeral(dummy_private_key, alg, peer_public,
                                         (), kdf_not_called).is_err());
+
+                    let dummy_private_key =
+                        try!(EphemeralPrivateKey::generate(alg, &rng));
+                    let unparsed = UnparsedPublicKey::new(
+                        alg, peer_public.as_slice_less_safe());
+                    assert!(agree_ephemeral_unparsed(dummy_private_key,
+                                                     &unparsed, (),
+                                                     kdf_not_called).is_err());
                 }
             }
 
@@ -286,13 +565,44 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_algorithm_metadata() {
+        for &(alg, name, public_key_len, shared_secret_len, security_bits) in
+                &[(&ECDH_P256, "ECDH_P256", 65, 32, 128),
+                  (&ECDH_P384, "ECDH_P384", 97, 48, 192),
+                  (&ECDH_P521, "ECDH_P521", 133, 66, 256),
+                  (&X25519, "X25519", 32, 32, 128),
+                  (&X448, "X448", 56, 56, 224)] {
+            assert_eq!(alg.name(), name);
+            assert_eq!(alg.public_key_len(), public_key_len);
+            assert_eq!(alg.shared_secret_len(), shared_secret_len);
+            assert_eq!(alg.security_bits(), security_bits);
+        }
+    }
+
+    #[test]
+    fn test_algorithm_from_name() {
+        for alg in ALGORITHMS.iter().chain(ffdhe_algorithms().iter()) {
+            let found = Algorithm::from_name(alg.name()).unwrap();
+            assert_eq!(found.name(), alg.name());
+            assert_eq!(format!("{}", alg), alg.name());
+        }
+        for name in &["", "x25519", "P-256", "ECDH_P256 "] {
+            assert!(Algorithm::from_name(name).is_err());
+        }
+    }
+
     fn alg_from_curve_name(curve_name: &str) -> &'static Algorithm {
         if curve_name == "P-256" {
             &ECDH_P256
         } else if curve_name == "P-384" {
             &ECDH_P384
+        } else if curve_name == "P-521" {
+            &ECDH_P521
         } else if curve_name == "X25519" {
             &X25519
+        } else if curve_name == "X448" {
+            &X448
         } else {
             panic!("Unsupported curve: {}", curve_name);
         }
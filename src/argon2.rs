@@ -0,0 +1,590 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Argon2 derivation and verification.
+//!
+//! Argon2 is the memory-hard password hashing function specified in
+//! [RFC 9106]. Use `derive` to derive Argon2 outputs and `verify` to verify a
+//! secret against a previously-derived output, like `pbkdf2::derive` and
+//! `pbkdf2::verify`. Argon2id is the variant recommended by RFC 9106; Argon2i
+//! is supported for interoperability. Argon2d, whose memory access pattern
+//! depends on the secret, isn't supported.
+//!
+//! The memory is filled one lane at a time, so the degree of parallelism
+//! affects the output but doesn't make `derive` use more than one thread.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::argon2;
+//!
+//! # fn main() { foo().unwrap() }
+//! # fn foo() -> Result<(), ring::error::Unspecified> {
+//! let params = try!(argon2::Params::new(64, 3, 1));
+//! let salt = b"per-user salt";
+//!
+//! let mut credential = [0u8; 32];
+//! try!(argon2::derive(&argon2::ARGON2ID, &params, salt, b"hunter2",
+//!                     &mut credential));
+//!
+//! assert!(argon2::verify(&argon2::ARGON2ID, &params, salt, b"hunter2",
+//!                        &credential).is_ok());
+//! assert!(argon2::verify(&argon2::ARGON2ID, &params, salt, b"hunter3",
+//!                        &credential).is_err());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [RFC 9106]: https://tools.ietf.org/html/rfc9106
+
+use {constant_time, error, polyfill};
+use digest::blake2b;
+
+/// The largest amount of memory, in bytes, that `derive` will use, 1 GiB.
+pub const MAX_MEMORY: usize = 1 << 30;
+
+/// The minimum length of the salt, in bytes.
+pub const MIN_SALT_LEN: usize = 8;
+
+/// The minimum length of the output, in bytes.
+pub const MIN_OUTPUT_LEN: usize = 4;
+
+/// An Argon2 variant.
+pub struct Algorithm {
+    // The type y of RFC 9106 Section 3.2.
+    y: u32,
+}
+
+/// Argon2i, which uses data-independent memory access.
+pub static ARGON2I: Algorithm = Algorithm { y: 1 };
+
+/// Argon2id, which uses data-independent memory access for the first half of
+/// the first pass and data-dependent memory access afterwards.
+pub static ARGON2ID: Algorithm = Algorithm { y: 2 };
+
+/// Argon2 parameters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Params {
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+impl Params {
+    /// Validates and returns the given parameters.
+    ///
+    /// | Parameter     | RFC 9106 Term
+    /// |---------------|-------------------------------------
+    /// | `memory_kib`  | m (memory size, in KiB)
+    /// | `iterations`  | t (number of passes)
+    /// | `parallelism` | p (degree of parallelism)
+    ///
+    /// `iterations` must be at least 1, `parallelism` must be in the range
+    /// [1, 2**24 - 1], `memory_kib` must be at least `8 * parallelism`, and
+    /// the amount of memory used must be no more than `MAX_MEMORY`.
+    pub fn new(memory_kib: u32, iterations: u32, parallelism: u32)
+               -> Result<Params, error::Unspecified> {
+        if iterations < 1 || parallelism < 1 || parallelism >= 1 << 24 {
+            return Err(error::Unspecified);
+        }
+        if (memory_kib as u64) < 8 * (parallelism as u64) ||
+           (memory_kib as u64) * (BLOCK_LEN as u64) > MAX_MEMORY as u64 {
+            return Err(error::Unspecified);
+        }
+        Ok(Params {
+            memory_kib: memory_kib,
+            iterations: iterations,
+            parallelism: parallelism,
+        })
+    }
+
+    /// The second recommended option of [RFC 9106 Section 4], for
+    /// environments where memory is constrained: t = 3, p = 4, and
+    /// m = 64 MiB. Use with `ARGON2ID`.
+    ///
+    /// [RFC 9106 Section 4]: https://tools.ietf.org/html/rfc9106#section-4
+    pub fn recommended() -> Params {
+        Params { memory_kib: 64 * 1024, iterations: 3, parallelism: 4 }
+    }
+
+    /// m, the memory size in KiB.
+    #[inline(always)]
+    pub fn memory_kib(&self) -> u32 { self.memory_kib }
+
+    /// t, the number of passes.
+    #[inline(always)]
+    pub fn iterations(&self) -> u32 { self.iterations }
+
+    /// p, the degree of parallelism.
+    #[inline(always)]
+    pub fn parallelism(&self) -> u32 { self.parallelism }
+}
+
+/// Fills `out` with the key derived using Argon2 with the given inputs.
+///
+/// Do not use `derive` as part of verifying a secret; use `verify` instead, to
+/// minimize the effectiveness of timing attacks.
+///
+/// | Parameter   | RFC 9106 Term
+/// |-------------|-------------------------------
+/// | `alg`       | y (Argon2 type)
+/// | `params`    | m, t, p
+/// | `salt`      | S (nonce)
+/// | `secret`    | P (message)
+/// | `out`       | tag
+/// | `out.len()` | T (tag length)
+///
+/// Returns `Err(error::Unspecified)`, without writing to `out`, if `salt` is
+/// shorter than `MIN_SALT_LEN` or `out` is shorter than `MIN_OUTPUT_LEN`.
+pub fn derive(alg: &'static Algorithm, params: &Params, salt: &[u8],
+              secret: &[u8], out: &mut [u8])
+              -> Result<(), error::Unspecified> {
+    derive_keyed(alg, params, &[], &[], salt, secret, out)
+}
+
+/// Like `derive`, but also takes the optional inputs `key`, the secret value
+/// K, and `ad`, the associated data X, of RFC 9106.
+///
+/// `key` is typically a pepper that is stored apart from the derived values.
+pub fn derive_keyed(alg: &'static Algorithm, params: &Params, key: &[u8],
+                    ad: &[u8], salt: &[u8], secret: &[u8], out: &mut [u8])
+                    -> Result<(), error::Unspecified> {
+    if salt.len() < MIN_SALT_LEN || out.len() < MIN_OUTPUT_LEN {
+        return Err(error::Unspecified);
+    }
+    for input in &[key, ad, salt, secret, &*out] {
+        if (input.len() as u64) > 0xffffffff {
+            return Err(error::Unspecified);
+        }
+    }
+
+    let p = params.parallelism as usize;
+    let lane_len = 4 * (params.memory_kib as usize / (4 * p));
+    let segment_len = lane_len / 4;
+
+    let h0 = {
+        let mut ctx = blake2b::Context::new(blake2b::MAX_OUTPUT_LEN).unwrap();
+        for value in &[params.parallelism, out.len() as u32,
+                       params.memory_kib, params.iterations, VERSION, alg.y] {
+            ctx.update(&le_u8_from_u32(*value));
+        }
+        for input in &[secret, salt, key, ad] {
+            ctx.update(&le_u8_from_u32(input.len() as u32));
+            ctx.update(input);
+        }
+        ctx.finish()
+    };
+
+    let mut memory = vec![0u64; p * lane_len * WORDS_PER_BLOCK];
+    let mut block_bytes = [0u8; BLOCK_LEN];
+    for lane in 0..p {
+        for i in 0..2 {
+            h_prime(&mut block_bytes, &[h0.as_ref(), &le_u8_from_u32(i),
+                                        &le_u8_from_u32(lane as u32)]);
+            let start = ((lane * lane_len) + (i as usize)) * WORDS_PER_BLOCK;
+            let block = &mut memory[start..(start + WORDS_PER_BLOCK)];
+            for (w, chunk) in block.iter_mut().zip(block_bytes.chunks(8)) {
+                *w = u64_from_le_u8(chunk);
+            }
+        }
+    }
+
+    let instance = Instance {
+        y: alg.y,
+        iterations: params.iterations,
+        lanes: p,
+        lane_len: lane_len,
+        segment_len: segment_len,
+    };
+    for pass in 0..params.iterations {
+        for slice in 0..SYNC_POINTS {
+            for lane in 0..p {
+                instance.fill_segment(&mut memory, pass, slice, lane);
+            }
+        }
+    }
+
+    // XOR the last block of each lane together.
+    let mut c = [0u64; WORDS_PER_BLOCK];
+    for lane in 0..p {
+        let start = ((lane * lane_len) + lane_len - 1) * WORDS_PER_BLOCK;
+        for (c, w) in c.iter_mut()
+                       .zip(&memory[start..(start + WORDS_PER_BLOCK)]) {
+            *c ^= *w;
+        }
+    }
+    for (chunk, w) in block_bytes.chunks_mut(8).zip(c.iter()) {
+        chunk.copy_from_slice(&le_u8_from_u64(*w));
+    }
+    h_prime(out, &[&block_bytes]);
+
+    polyfill::slice::fill(&mut block_bytes, 0);
+    for w in memory.iter_mut().chain(c.iter_mut()) {
+        *w = 0;
+    }
+
+    Ok(())
+}
+
+/// Verifies that a previously-derived (e.g., using `derive`) Argon2 value
+/// matches the Argon2 value derived from the other inputs.
+///
+/// The comparison is done in constant time to prevent timing attacks. The
+/// comparison will fail if `previously_derived` is shorter than
+/// `MIN_OUTPUT_LEN`.
+///
+/// | Parameter                  | RFC 9106 Term
+/// |----------------------------|-------------------------------
+/// | `alg`                      | y (Argon2 type)
+/// | `params`                   | m, t, p
+/// | `salt`                     | S (nonce)
+/// | `secret`                   | P (message)
+/// | `previously_derived`       | tag
+/// | `previously_derived.len()` | T (tag length)
+pub fn verify(alg: &'static Algorithm, params: &Params, salt: &[u8],
+              secret: &[u8], previously_derived: &[u8])
+              -> Result<(), error::Unspecified> {
+    verify_keyed(alg, params, &[], &[], salt, secret, previously_derived)
+}
+
+/// Like `verify`, but also takes the optional inputs `key` and `ad`, as
+/// `derive_keyed` does.
+pub fn verify_keyed(alg: &'static Algorithm, params: &Params, key: &[u8],
+                    ad: &[u8], salt: &[u8], secret: &[u8],
+                    previously_derived: &[u8])
+                    -> Result<(), error::Unspecified> {
+    let mut derived = vec![0u8; previously_derived.len()];
+    try!(derive_keyed(alg, params, key, ad, salt, secret, &mut derived));
+    let result =
+        constant_time::verify_slices_are_equal(&derived, previously_derived);
+    polyfill::slice::fill(&mut derived, 0);
+    result
+}
+
+// The Argon2 version number, 0x13.
+const VERSION: u32 = 0x13;
+
+// The number of slices in each pass, and so of segments in each lane.
+const SYNC_POINTS: u32 = 4;
+
+// The length of a memory block, in bytes.
+const BLOCK_LEN: usize = 1024;
+
+const WORDS_PER_BLOCK: usize = BLOCK_LEN / 8;
+
+// The number of reference block indexes in each address block.
+const ADDRESSES_PER_BLOCK: usize = WORDS_PER_BLOCK;
+
+struct Instance {
+    y: u32,
+    iterations: u32,
+    lanes: usize,
+    lane_len: usize,
+    segment_len: usize,
+}
+
+impl Instance {
+    // Fills the segment `slice` of lane `lane` in pass `pass`, as described in
+    // RFC 9106 Section 3.4.
+    fn fill_segment(&self, memory: &mut [u64], pass: u32, slice: u32,
+                    lane: usize) {
+        let data_independent =
+            self.y == ARGON2I.y || (pass == 0 && slice < SYNC_POINTS / 2);
+
+        let zero = [0u64; WORDS_PER_BLOCK];
+        let mut input = [0u64; WORDS_PER_BLOCK];
+        let mut addresses = [0u64; WORDS_PER_BLOCK];
+        if data_independent {
+            input[0] = pass as u64;
+            input[1] = lane as u64;
+            input[2] = slice as u64;
+            input[3] = (self.lanes * self.lane_len) as u64;
+            input[4] = self.iterations as u64;
+            input[5] = self.y as u64;
+        }
+
+        // The first two blocks of each lane were computed from H0.
+        let first = if pass == 0 && slice == 0 { 2 } else { 0 };
+        if data_independent && first != 0 {
+            next_addresses(&zero, &mut input, &mut addresses);
+        }
+
+        for index in first..self.segment_len {
+            let offset = (slice as usize) * self.segment_len + index;
+            let prev_offset =
+                if offset == 0 { self.lane_len - 1 } else { offset - 1 };
+            let prev_start =
+                ((lane * self.lane_len) + prev_offset) * WORDS_PER_BLOCK;
+
+            let pseudo_random = if data_independent {
+                if index % ADDRESSES_PER_BLOCK == 0 {
+                    next_addresses(&zero, &mut input, &mut addresses);
+                }
+                addresses[index % ADDRESSES_PER_BLOCK]
+            } else {
+                memory[prev_start]
+            };
+
+            let ref_lane = if pass == 0 && slice == 0 {
+                lane
+            } else {
+                ((pseudo_random >> 32) % (self.lanes as u64)) as usize
+            };
+            let ref_offset = self.index_alpha(pass, slice, index,
+                                              pseudo_random as u32,
+                                              ref_lane == lane);
+
+            let mut prev = [0u64; WORDS_PER_BLOCK];
+            prev.copy_from_slice(
+                &memory[prev_start..(prev_start + WORDS_PER_BLOCK)]);
+            let ref_start =
+                ((ref_lane * self.lane_len) + ref_offset) * WORDS_PER_BLOCK;
+            let mut reference = [0u64; WORDS_PER_BLOCK];
+            reference.copy_from_slice(
+                &memory[ref_start..(ref_start + WORDS_PER_BLOCK)]);
+
+            let start = ((lane * self.lane_len) + offset) * WORDS_PER_BLOCK;
+            fill_block(&prev, &reference,
+                       &mut memory[start..(start + WORDS_PER_BLOCK)],
+                       pass != 0);
+        }
+    }
+
+    // Maps J_1 to the offset of the reference block within its lane, as
+    // described in RFC 9106 Section 3.4.1.2.
+    fn index_alpha(&self, pass: u32, slice: u32, index: usize, j1: u32,
+                   same_lane: bool) -> usize {
+        let slice = slice as usize;
+        let area_size = if pass == 0 {
+            if slice == 0 {
+                index - 1
+            } else if same_lane {
+                slice * self.segment_len + index - 1
+            } else {
+                slice * self.segment_len - if index == 0 { 1 } else { 0 }
+            }
+        } else if same_lane {
+            self.lane_len - self.segment_len + index - 1
+        } else {
+            self.lane_len - self.segment_len - if index == 0 { 1 } else { 0 }
+        };
+
+        let x = ((j1 as u64) * (j1 as u64)) >> 32;
+        let y = ((area_size as u64) * x) >> 32;
+        let relative = area_size - 1 - (y as usize);
+
+        let start = if pass == 0 || slice == (SYNC_POINTS as usize) - 1 {
+            0
+        } else {
+            (slice + 1) * self.segment_len
+        };
+        (start + relative) % self.lane_len
+    }
+}
+
+// Increments the counter in `input` and computes the next block of reference
+// block indexes for data-independent addressing.
+fn next_addresses(zero: &[u64; WORDS_PER_BLOCK],
+                  input: &mut [u64; WORDS_PER_BLOCK],
+                  addresses: &mut [u64; WORDS_PER_BLOCK]) {
+    input[6] += 1;
+    fill_block(zero, input, addresses, false);
+    let first = *addresses;
+    fill_block(zero, &first, addresses, false);
+}
+
+// The compression function G of RFC 9106 Section 3.5, writing G(X, Y) to
+// `out`, or XORing it into `out` if `with_xor` is set.
+fn fill_block(x: &[u64; WORDS_PER_BLOCK], y: &[u64; WORDS_PER_BLOCK],
+              out: &mut [u64], with_xor: bool) {
+    let mut r = [0u64; WORDS_PER_BLOCK];
+    for (r, (x, y)) in r.iter_mut().zip(x.iter().zip(y.iter())) {
+        *r = *x ^ *y;
+    }
+    let mut z = r;
+
+    // Apply P to the rows, then to the columns, of the 8x8 matrix of 16-byte
+    // registers.
+    for i in 0..8 {
+        let mut indexes = [0usize; 16];
+        for (j, index) in indexes.iter_mut().enumerate() {
+            *index = 16 * i + j;
+        }
+        permute(&mut z, &indexes);
+    }
+    for i in 0..8 {
+        let mut indexes = [0usize; 16];
+        for (j, index) in indexes.iter_mut().enumerate() {
+            *index = 2 * i + 16 * (j / 2) + (j % 2);
+        }
+        permute(&mut z, &indexes);
+    }
+
+    for (out, (z, r)) in out.iter_mut().zip(z.iter().zip(r.iter())) {
+        if with_xor {
+            *out ^= *z ^ *r;
+        } else {
+            *out = *z ^ *r;
+        }
+    }
+}
+
+// The permutation P of RFC 9106 Section 3.6, applied to the words of `block`
+// at `indexes`.
+fn permute(block: &mut [u64; WORDS_PER_BLOCK], indexes: &[usize; 16]) {
+    fn gb(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize) {
+        fn f(x: u64, y: u64) -> u64 {
+            let xy = (x & 0xffffffff) * (y & 0xffffffff);
+            x.wrapping_add(y).wrapping_add(xy.wrapping_mul(2))
+        }
+        v[a] = f(v[a], v[b]);
+        v[d] = (v[d] ^ v[a]).rotate_right(32);
+        v[c] = f(v[c], v[d]);
+        v[b] = (v[b] ^ v[c]).rotate_right(24);
+        v[a] = f(v[a], v[b]);
+        v[d] = (v[d] ^ v[a]).rotate_right(16);
+        v[c] = f(v[c], v[d]);
+        v[b] = (v[b] ^ v[c]).rotate_right(63);
+    }
+
+    let mut v = [0u64; 16];
+    for (v, index) in v.iter_mut().zip(indexes.iter()) {
+        *v = block[*index];
+    }
+
+    gb(&mut v, 0, 4, 8, 12);
+    gb(&mut v, 1, 5, 9, 13);
+    gb(&mut v, 2, 6, 10, 14);
+    gb(&mut v, 3, 7, 11, 15);
+    gb(&mut v, 0, 5, 10, 15);
+    gb(&mut v, 1, 6, 11, 12);
+    gb(&mut v, 2, 7, 8, 13);
+    gb(&mut v, 3, 4, 9, 14);
+
+    for (v, index) in v.iter().zip(indexes.iter()) {
+        block[*index] = *v;
+    }
+}
+
+// The variable-length hash function H' of RFC 9106 Section 3.3, applied to
+// the concatenation of `inputs`.
+fn h_prime(out: &mut [u8], inputs: &[&[u8]]) {
+    let first_len = core::cmp::min(out.len(), blake2b::MAX_OUTPUT_LEN);
+    let mut ctx = blake2b::Context::new(first_len).unwrap();
+    ctx.update(&le_u8_from_u32(out.len() as u32));
+    for input in inputs {
+        ctx.update(input);
+    }
+    let mut v = ctx.finish();
+    if out.len() <= blake2b::MAX_OUTPUT_LEN {
+        out.copy_from_slice(v.as_ref());
+        return;
+    }
+
+    // Each V_i but the last contributes its first 32 bytes.
+    let r = ((out.len() + 31) / 32) - 2;
+    out[..32].copy_from_slice(&v.as_ref()[..32]);
+    for i in 1..r {
+        v = blake2b::digest(v.as_ref());
+        out[(32 * i)..(32 * (i + 1))].copy_from_slice(&v.as_ref()[..32]);
+    }
+    let mut ctx = blake2b::Context::new(out.len() - (32 * r)).unwrap();
+    ctx.update(v.as_ref());
+    out[(32 * r)..].copy_from_slice(ctx.finish().as_ref());
+}
+
+fn le_u8_from_u32(value: u32) -> [u8; 4] {
+    [value as u8, (value >> 8) as u8, (value >> 16) as u8,
+     (value >> 24) as u8]
+}
+
+fn le_u8_from_u64(value: u64) -> [u8; 8] {
+    let mut out = [0u8; 8];
+    for (i, b) in out.iter_mut().enumerate() {
+        *b = (value >> (8 * i)) as u8;
+    }
+    out
+}
+
+fn u64_from_le_u8(bytes: &[u8]) -> u64 {
+    bytes.iter().rev().fold(0, |acc, b| (acc << 8) | (*b as u64))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {error, test};
+    use super::*;
+
+    #[test]
+    fn test_argon2() {
+        test::from_file("src/argon2_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let alg = match test_case.consume_string("Alg").as_str() {
+                "ARGON2I" => &ARGON2I,
+                "ARGON2ID" => &ARGON2ID,
+                _ => unreachable!(),
+            };
+            let m = test_case.consume_usize("M") as u32;
+            let t = test_case.consume_usize("T") as u32;
+            let p = test_case.consume_usize("P") as u32;
+            let secret = test_case.consume_bytes("Secret");
+            let salt = test_case.consume_bytes("Salt");
+            let key = test_case.consume_bytes("Key");
+            let ad = test_case.consume_bytes("AD");
+            let expected = test_case.consume_bytes("Out");
+
+            let params = try!(Params::new(m, t, p));
+            let mut out = vec![0u8; expected.len()];
+            try!(derive_keyed(alg, &params, &key, &ad, &salt, &secret,
+                              &mut out));
+            assert_eq!(out, expected);
+
+            assert_eq!(verify_keyed(alg, &params, &key, &ad, &salt, &secret,
+                                    &expected),
+                       Ok(()));
+            let mut wrong = expected.clone();
+            wrong[0] ^= 1;
+            assert_eq!(verify_keyed(alg, &params, &key, &ad, &salt, &secret,
+                                    &wrong),
+                       Err(error::Unspecified));
+
+            if key.is_empty() && ad.is_empty() {
+                assert_eq!(verify(alg, &params, &salt, &secret, &expected),
+                           Ok(()));
+            }
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_argon2_params() {
+        assert!(Params::new(8, 1, 1).is_ok());
+        assert_eq!(Params::new(8, 0, 1), Err(error::Unspecified));
+        assert!(Params::new(8, 1, 0).is_err());
+        assert!(Params::new(15, 1, 2).is_err());
+        assert!(Params::new(1 << 30, 1, 1 << 24).is_err());
+        assert!(Params::new(1 << 20, 1, 1).is_ok());
+        assert!(Params::new((1 << 20) + 1, 1, 1).is_err());
+        assert_eq!(Params::new(64 * 1024, 3, 4), Ok(Params::recommended()));
+
+        let params = Params::new(8, 1, 1).unwrap();
+        let mut out = [0u8; 32];
+        assert!(derive(&ARGON2ID, &params, b"short", b"", &mut out).is_err());
+        assert!(derive(&ARGON2ID, &params, b"saltsalt", b"",
+                       &mut out[..3]).is_err());
+        assert!(verify(&ARGON2ID, &params, b"saltsalt", b"", &[]).is_err());
+    }
+}
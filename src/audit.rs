@@ -0,0 +1,213 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Notification of key usage, for audit logging.
+//!
+//! An application can install a hook, once per process, that *ring* calls
+//! when a private key is created, when a signature is created, and when
+//! decryption fails, e.g. to feed crypto-usage telemetry into a security
+//! monitoring system. The hook only receives non-secret metadata: the kind
+//! of the event and the name of the algorithm.
+//!
+//! The hook is also called the first time each deprecated algorithm is used:
+//! the SHA-1-based signature verification algorithms, and PKCS#1 1.5 padding
+//! for signing. Logging these events finds the code paths that still use
+//! legacy algorithms at run time, including ones that are hard to find by
+//! searching the code, e.g. because the algorithm is chosen from a
+//! configuration file.
+//!
+//! The hook is called synchronously, on the thread doing the operation, so
+//! it should be cheap, e.g. increment a counter or enqueue a log record, and
+//! it must not call back into *ring* operations that would notify it again.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::audit;
+//!
+//! fn log_event(event: &audit::Event) {
+//!     println!("crypto event: {:?} {}", event.kind, event.algorithm);
+//! }
+//!
+//! # fn main() {
+//! // Only the first hook that is installed is used.
+//! let _ = audit::set_hook(log_event);
+//! # }
+//! ```
+
+use {core, error};
+
+/// A key usage event.
+pub struct Event {
+    /// What happened.
+    pub kind: EventKind,
+
+    /// The name of the algorithm, which is the name of its `static` in
+    /// *ring*, e.g. `"ECDSA_P256_SHA256_ASN1_SIGNING_DETERMINISTIC"`, or
+    /// `"ED25519"` or `"RSA"` for key pairs that aren't tied to a single
+    /// algorithm `static`.
+    pub algorithm: &'static str,
+}
+
+/// The kind of a key usage event.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EventKind {
+    /// A private key or key pair was generated or was constructed from
+    /// serialized key material.
+    KeyCreated,
+
+    /// A signature was created.
+    Signed,
+
+    /// Decryption failed, e.g. because an AEAD tag didn't match. Repeated
+    /// failures may indicate an attack.
+    DecryptionFailed,
+
+    /// A deprecated algorithm was used to verify or create a signature. This
+    /// is reported only the first time each deprecated algorithm is used
+    /// while the hook is installed.
+    DeprecatedAlgorithmUsed,
+}
+
+/// Installs `hook` as the process-wide audit hook.
+///
+/// The hook can only be installed once; this fails if a hook was already
+/// installed, and the existing hook remains in use.
+pub fn set_hook(hook: fn(&Event)) -> Result<(), error::Unspecified> {
+    HOOK.compare_exchange(0, hook as usize,
+                          core::sync::atomic::Ordering::SeqCst,
+                          core::sync::atomic::Ordering::SeqCst)
+        .map(|_| ())
+        .map_err(|_| error::Unspecified)
+}
+
+// Calls the hook, if one is installed. This is called by the rest of *ring*;
+// it is public only so that the other modules can reach it.
+#[doc(hidden)]
+pub fn notify(kind: EventKind, algorithm: &'static str) {
+    let hook = HOOK.load(core::sync::atomic::Ordering::SeqCst);
+    if hook != 0 {
+        let hook: fn(&Event) = unsafe { core::mem::transmute(hook) };
+        hook(&Event { kind: kind, algorithm: algorithm });
+    }
+}
+
+// Calls the hook with a `DeprecatedAlgorithmUsed` event if `algorithm` is
+// deprecated and this hasn't been done for it already. This is called by the
+// rest of *ring* wherever a deprecated algorithm might be used; it is public
+// only so that the other modules can reach it.
+#[doc(hidden)]
+pub fn notify_if_deprecated(algorithm: &'static str) {
+    if HOOK.load(core::sync::atomic::Ordering::SeqCst) == 0 {
+        return;
+    }
+    let i = match DEPRECATED.iter().position(|&name| name == algorithm) {
+        Some(i) => i,
+        None => { return; },
+    };
+    let bit = 1 << i;
+    let reported = DEPRECATED_REPORTED.fetch_or(
+        bit, core::sync::atomic::Ordering::SeqCst);
+    if reported & bit == 0 {
+        notify(EventKind::DeprecatedAlgorithmUsed, algorithm);
+    }
+}
+
+// The installed hook, as a function pointer cast to `usize`, or zero.
+static HOOK: core::sync::atomic::AtomicUsize =
+    core::sync::atomic::AtomicUsize::new(0);
+
+// The deprecated algorithms. The verification algorithms are named by their
+// `signature` statics and the signing algorithms by their padding statics.
+static DEPRECATED: [&'static str; 9] = [
+    "ECDSA_P256_SHA1_ASN1",
+    "ECDSA_P384_SHA1_ASN1",
+    "RSA_PKCS1_1024_8192_SHA1_FOR_LEGACY_USE_ONLY",
+    "RSA_PKCS1_2048_8192_SHA1",
+    "RSA_PKCS1_SHA1",
+    "RSA_PKCS1_SHA256",
+    "RSA_PKCS1_SHA384",
+    "RSA_PKCS1_SHA512",
+    "RSA_PKCS1_SHA512_256",
+];
+
+// Bit `i` is set once `DEPRECATED[i]` has been reported.
+static DEPRECATED_REPORTED: core::sync::atomic::AtomicUsize =
+    core::sync::atomic::AtomicUsize::new(0);
+
+#[cfg(test)]
+mod tests {
+    use {aead, agreement, rand, signature, test};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use super::*;
+    use untrusted;
+
+    static KEYS_CREATED: AtomicUsize = AtomicUsize::new(0);
+    static DECRYPTION_FAILURES: AtomicUsize = AtomicUsize::new(0);
+    static ECDSA_P256_SHA1_USED: AtomicUsize = AtomicUsize::new(0);
+
+    fn count_event(event: &Event) {
+        match event.kind {
+            EventKind::KeyCreated => {
+                let _ = KEYS_CREATED.fetch_add(1, Ordering::SeqCst);
+            },
+            EventKind::DecryptionFailed => {
+                let _ = DECRYPTION_FAILURES.fetch_add(1, Ordering::SeqCst);
+            },
+            EventKind::Signed => (),
+            EventKind::DeprecatedAlgorithmUsed => {
+                if event.algorithm == "ECDSA_P256_SHA1_ASN1" {
+                    let _ = ECDSA_P256_SHA1_USED.fetch_add(1, Ordering::SeqCst);
+                }
+            },
+        }
+    }
+
+    #[test]
+    fn test_audit_hook() {
+        // Other tests run concurrently with this one and may also trigger
+        // events, so only lower bounds on the counts are checked.
+        assert!(set_hook(count_event).is_ok());
+        assert!(set_hook(count_event).is_err());
+
+        let rng = rand::SystemRandom::new();
+        let keys_created = KEYS_CREATED.load(Ordering::SeqCst);
+        let _ = agreement::EphemeralPrivateKey::generate(&agreement::X25519,
+                                                         &rng).unwrap();
+        assert!(KEYS_CREATED.load(Ordering::SeqCst) > keys_created);
+
+        let alg = &aead::CHACHA20_POLY1305;
+        let key = aead::OpeningKey::new(alg, &[0u8; 32]).unwrap();
+        let mut in_out = [0u8; 32];
+        assert!(aead::open_in_place(&key, &[0u8; 12], 0, &mut in_out, &[])
+                    .is_err());
+        assert!(DECRYPTION_FAILURES.load(Ordering::SeqCst) >= 1);
+
+        // The deprecated algorithm is reported when it is used, even if the
+        // signature is invalid, but only once. The public key is the P-256
+        // base point.
+        let public_key = test::from_hex(
+            "046b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c2\
+             964fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51\
+             f5").unwrap();
+        for _ in 0..2 {
+            assert!(signature::verify(&signature::ECDSA_P256_SHA1_ASN1,
+                                      untrusted::Input::from(&public_key),
+                                      untrusted::Input::from(b"msg"),
+                                      untrusted::Input::from(&[0x30, 0x00]))
+                        .is_err());
+        }
+        assert_eq!(ECDSA_P256_SHA1_USED.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,206 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! AES in counter (CTR) mode, as specified in [NIST SP 800-38A] Section 6.5.
+//! **Hazardous; see `ring::cipher`.**
+//!
+//! The counter block is incremented as a 128-bit big-endian integer, wrapping
+//! around to zero, which is the counter of SSH's `aes128-ctr` and
+//! `aes256-ctr` ([RFC 4344] Section 4). Protocols that use only part of the
+//! block as the counter, such as SRTP, use the rest of the block as an IV and
+//! are limited to fewer blocks per IV than this module is.
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::cipher::aes_ctr;
+//!
+//! # fn main() { foo().unwrap() }
+//! # fn foo() -> Result<(), ring::error::Unspecified> {
+//! let key = try!(aes_ctr::Key::new(&aes_ctr::AES_128, &[0x42; 16]));
+//! let iv = [0u8; aes_ctr::BLOCK_LEN];
+//!
+//! let mut in_out = *b"0123456789abcdef";
+//! let mut counter = iv;
+//! key.xor_in_place(&mut counter, &mut in_out);
+//!
+//! // Decryption is the same operation, starting at the same counter block.
+//! let mut counter = iv;
+//! key.xor_in_place(&mut counter, &mut in_out);
+//! assert_eq!(&in_out, b"0123456789abcdef");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [NIST SP 800-38A]:
+//!     https://nvlpubs.nist.gov/nistpubs/Legacy/SP/nistspecialpublication800-38a.pdf
+//! [RFC 4344]: https://tools.ietf.org/html/rfc4344#section-4
+
+use {c, error, init};
+
+/// An AES-CTR algorithm.
+pub struct Algorithm {
+    key_len: usize,
+}
+
+/// AES-128 in CTR mode. The key must be 16 bytes.
+pub static AES_128: Algorithm = Algorithm {
+    key_len: 128 / 8,
+};
+
+/// AES-256 in CTR mode. The key must be 32 bytes.
+pub static AES_256: Algorithm = Algorithm {
+    key_len: 256 / 8,
+};
+
+impl Algorithm {
+    /// The length of the AES key, in bytes.
+    #[inline(always)]
+    pub fn key_len(&self) -> usize { self.key_len }
+}
+
+/// The length of an AES block, which is also the length of the counter block.
+pub const BLOCK_LEN: usize = 16;
+
+/// An AES block.
+pub type Block = [u8; BLOCK_LEN];
+
+/// An AES-CTR key.
+pub struct Key {
+    aes_key: AES_KEY,
+}
+
+impl Key {
+    /// Constructs a key from `key_bytes`, which must be exactly
+    /// `algorithm.key_len()` bytes long.
+    pub fn new(algorithm: &'static Algorithm, key_bytes: &[u8])
+               -> Result<Key, error::KeyRejected> {
+        init::init_once();
+        try!(error::KeyRejected::check_len(key_bytes.len(),
+                                           algorithm.key_len));
+        let mut key = Key {
+            aes_key: AES_KEY {
+                rd_key: [0u32; 4 * (AES_MAX_ROUNDS + 1)],
+                rounds: 0,
+            },
+        };
+        let bits = (key_bytes.len() * 8) as u32;
+        if unsafe {
+            GFp_AES_set_encrypt_key(key_bytes.as_ptr(), bits, &mut key.aes_key)
+        } != 0 {
+            return Err(error::KeyRejected::Unexpected);
+        }
+        Ok(key)
+    }
+
+    /// Encrypts or decrypts `in_out` in place, starting at the counter block
+    /// `counter`.
+    ///
+    /// On return, `counter` is the counter block after the last one that was
+    /// used, so a stream that is processed in several calls continues where
+    /// the previous call stopped. If `in_out.len()` isn't a multiple of
+    /// `BLOCK_LEN`, the rest of the keystream of the last block is discarded.
+    pub fn xor_in_place(&self, counter: &mut Block, in_out: &mut [u8]) {
+        for chunk in in_out.chunks_mut(BLOCK_LEN) {
+            let mut key_stream = [0u8; BLOCK_LEN];
+            unsafe {
+                GFp_AES_encrypt(counter.as_ptr(), key_stream.as_mut_ptr(),
+                                &self.aes_key);
+            }
+            for (b, k) in chunk.iter_mut().zip(key_stream.iter()) {
+                *b ^= *k;
+            }
+            increment(counter);
+        }
+    }
+}
+
+fn increment(counter: &mut Block) {
+    for b in counter.iter_mut().rev() {
+        *b = b.wrapping_add(1);
+        if *b != 0 {
+            break;
+        }
+    }
+}
+
+const AES_MAX_ROUNDS: usize = 14;
+
+// Keep this in sync with `AES_KEY` in aes.h.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+struct AES_KEY {
+    rd_key: [u32; 4 * (AES_MAX_ROUNDS + 1)],
+    rounds: u32, // `unsigned` in C.
+}
+
+extern {
+    fn GFp_AES_set_encrypt_key(key: *const u8, bits: u32,
+                               aes_key: *mut AES_KEY) -> c::int;
+    fn GFp_AES_encrypt(in_: *const u8, out: *mut u8, key: *const AES_KEY);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use test;
+    use super::*;
+
+    #[test]
+    fn test_aes_ctr() {
+        test::from_file("src/cipher/aes_ctr_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let alg = match test_case.consume_string("Alg").as_str() {
+                "AES_128" => &AES_128,
+                "AES_256" => &AES_256,
+                s => panic!("Unsupported algorithm: {}", s),
+            };
+            let key_bytes = test_case.consume_bytes("Key");
+            let counter = test_case.consume_bytes("Counter");
+            let counter = slice_as_array_ref!(&counter, BLOCK_LEN).unwrap();
+            let plaintext = test_case.consume_bytes("Plaintext");
+            let ciphertext = test_case.consume_bytes("Ciphertext");
+
+            let key = Key::new(alg, &key_bytes).unwrap();
+            let mut in_out = plaintext.clone();
+            key.xor_in_place(&mut counter.clone(), &mut in_out);
+            assert_eq!(in_out, ciphertext);
+
+            // Processing the input in block-sized pieces gives the same
+            // result as processing it all at once.
+            let mut in_out = ciphertext.clone();
+            let mut next_counter = *counter;
+            for chunk in in_out.chunks_mut(BLOCK_LEN) {
+                key.xor_in_place(&mut next_counter, chunk);
+            }
+            assert_eq!(in_out, plaintext);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_aes_ctr_counter() {
+        let key = Key::new(&AES_128, &[0u8; 16]).unwrap();
+        let mut counter = [0u8; BLOCK_LEN];
+        counter[BLOCK_LEN - 1] = 0xfe;
+        key.xor_in_place(&mut counter, &mut [0u8; BLOCK_LEN + 1]);
+        let mut expected = [0u8; BLOCK_LEN];
+        expected[BLOCK_LEN - 2] = 1;
+        assert_eq!(counter, expected);
+
+        assert!(Key::new(&AES_128, &[0u8; 32]).is_err());
+        assert!(Key::new(&AES_256, &[0u8; 16]).is_err());
+    }
+}
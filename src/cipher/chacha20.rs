@@ -0,0 +1,160 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The ChaCha20 stream cipher, as specified in [RFC 7539] Section 2.4, with a
+//! 96-bit nonce and a 32-bit block counter. **Hazardous; see
+//! `ring::cipher`.**
+//!
+//! This is the same ChaCha20 implementation that `aead::CHACHA20_POLY1305`
+//! uses.
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::cipher::chacha20;
+//!
+//! # fn main() { foo().unwrap() }
+//! # fn foo() -> Result<(), ring::error::Unspecified> {
+//! let key = try!(chacha20::Key::new(&[0x42; chacha20::KEY_LEN]));
+//! let nonce = [0u8; chacha20::NONCE_LEN];
+//!
+//! let mut in_out = *b"hello, world";
+//! try!(key.xor_in_place(&nonce, 0, &mut in_out));
+//!
+//! // Decryption is the same operation, with the same nonce and counter.
+//! try!(key.xor_in_place(&nonce, 0, &mut in_out));
+//! assert_eq!(&in_out, b"hello, world");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [RFC 7539]: https://tools.ietf.org/html/rfc7539#section-2.4
+
+use {c, error, init, polyfill};
+
+/// The length of ChaCha20 keys.
+pub const KEY_LEN: usize = 32;
+
+/// The length of ChaCha20 nonces.
+pub const NONCE_LEN: usize = 12;
+
+/// The length of a ChaCha20 block. The block counter counts blocks of this
+/// length.
+pub const BLOCK_LEN: usize = 64;
+
+/// A ChaCha20 key.
+pub struct Key {
+    key: [u32; KEY_LEN / 4],
+}
+
+impl Key {
+    /// Constructs a key from `key_bytes`, which must be exactly `KEY_LEN`
+    /// bytes long.
+    pub fn new(key_bytes: &[u8]) -> Result<Key, error::KeyRejected> {
+        init::init_once();
+        try!(error::KeyRejected::check_len(key_bytes.len(), KEY_LEN));
+        let mut key = [0u32; KEY_LEN / 4];
+        for (key, bytes) in key.iter_mut().zip(key_bytes.chunks(4)) {
+            *key = polyfill::slice::u32_from_le_u8(
+                slice_as_array_ref!(bytes, 4).unwrap());
+        }
+        Ok(Key { key: key })
+    }
+
+    /// Encrypts or decrypts `in_out` in place with the keystream for `nonce`,
+    /// which must be `NONCE_LEN` bytes long, starting at block `counter`.
+    ///
+    /// Fails, without modifying `in_out`, if the nonce has the wrong length
+    /// or if the counter would wrap around, i.e. if `in_out` extends past
+    /// the last block of the keystream for `nonce`.
+    pub fn xor_in_place(&self, nonce: &[u8], counter: u32, in_out: &mut [u8])
+                        -> Result<(), error::Unspecified> {
+        let nonce = try!(slice_as_array_ref!(nonce, NONCE_LEN));
+        let num_blocks = (polyfill::u64_from_usize(in_out.len()) +
+                          (BLOCK_LEN as u64 - 1)) / (BLOCK_LEN as u64);
+        if (counter as u64) + num_blocks > (1u64 << 32) {
+            return Err(error::Unspecified);
+        }
+        use polyfill::slice::u32_from_le_u8;
+        let counter = [
+            counter,
+            u32_from_le_u8(slice_as_array_ref!(&nonce[0..4], 4).unwrap()),
+            u32_from_le_u8(slice_as_array_ref!(&nonce[4..8], 4).unwrap()),
+            u32_from_le_u8(slice_as_array_ref!(&nonce[8..12], 4).unwrap()),
+        ];
+        unsafe {
+            GFp_ChaCha20_ctr32(in_out.as_mut_ptr(), in_out.as_ptr(),
+                               in_out.len(), &self.key, &counter);
+        }
+        Ok(())
+    }
+}
+
+extern {
+    fn GFp_ChaCha20_ctr32(out: *mut u8, in_: *const u8, in_len: c::size_t,
+                          key: &[u32; KEY_LEN / 4], counter: &[u32; 4]);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {error, test};
+    use super::*;
+
+    #[test]
+    fn test_chacha20() {
+        test::from_file("src/cipher/chacha20_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let key_bytes = test_case.consume_bytes("Key");
+            let nonce = test_case.consume_bytes("Nonce");
+            let counter = test_case.consume_usize("Counter") as u32;
+            let plaintext = test_case.consume_bytes("Plaintext");
+            let ciphertext = test_case.consume_bytes("Ciphertext");
+
+            let key = Key::new(&key_bytes).unwrap();
+            let mut in_out = plaintext.clone();
+            try!(key.xor_in_place(&nonce, counter, &mut in_out));
+            assert_eq!(in_out, ciphertext);
+
+            // Processing the input one block at a time gives the same result
+            // as processing it all at once.
+            let mut in_out = ciphertext.clone();
+            for (i, chunk) in in_out.chunks_mut(BLOCK_LEN).enumerate() {
+                try!(key.xor_in_place(&nonce, counter + (i as u32), chunk));
+            }
+            assert_eq!(in_out, plaintext);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_chacha20_limits() {
+        let key = Key::new(&[0u8; KEY_LEN]).unwrap();
+        let nonce = [0u8; NONCE_LEN];
+        let mut in_out = [0u8; BLOCK_LEN + 1];
+        assert_eq!(key.xor_in_place(&nonce, 0xffffffff,
+                                    &mut in_out[..BLOCK_LEN]),
+                   Ok(()));
+        assert_eq!(key.xor_in_place(&nonce, 0xffffffff, &mut in_out),
+                   Err(error::Unspecified));
+        assert_eq!(key.xor_in_place(&nonce, 0xfffffffe, &mut in_out), Ok(()));
+        assert_eq!(key.xor_in_place(&nonce[..(NONCE_LEN - 1)], 0,
+                                    &mut in_out),
+                   Err(error::Unspecified));
+
+        assert!(Key::new(&[0u8; KEY_LEN - 1]).is_err());
+        assert!(Key::new(&[0u8; KEY_LEN + 1]).is_err());
+    }
+}
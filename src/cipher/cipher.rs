@@ -0,0 +1,36 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Unauthenticated stream ciphers. **Hazardous: use `ring::aead` instead
+//! unless a protocol requires one of these.**
+//!
+//! The ciphers in this module only XOR a keystream into the data. Nothing
+//! detects modifications of the ciphertext, which an attacker can make
+//! without knowing the key, e.g. to flip chosen bits of the plaintext, and
+//! encrypting two messages with the same key and counter (or nonce) reveals
+//! the XOR of the plaintexts. They are provided for protocols that
+//! authenticate the ciphertext themselves, such as the `aes128-ctr` and
+//! `aes256-ctr` ciphers of SSH and the AES-CM cipher of SRTP, and for
+//! research tooling.
+//!
+//! Because these ciphers are gathered here, a project can forbid them
+//! wholesale by rejecting any path that starts with `ring::cipher`, as with
+//! `ring::legacy`.
+//!
+//! Go analog: [`crypto.cipher.Stream`]
+//!
+//! [`crypto.cipher.Stream`]: https://golang.org/pkg/crypto/cipher/#Stream
+
+pub mod aes_ctr;
+pub mod chacha20;
@@ -0,0 +1,323 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! AES-CMAC (OMAC1), as specified in [NIST SP 800-38B] and [RFC 4493].
+//!
+//! The API mirrors `ring::hmac`: a `Key` is constructed once, and then the
+//! module-level `sign` and `verify` functions, or a `Context` for input that
+//! is in multiple parts, are used with it. Since CMAC is symmetric, the same
+//! `Key` is used for both signing and verification.
+//!
+//! The subkeys K1 and K2, and the `double` operation that generates them, are
+//! also exposed, for constructions such as [S2V] of AES-SIV that are built on
+//! them.
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::cmac;
+//!
+//! # fn main() { foo().unwrap() }
+//! # fn foo() -> Result<(), ring::error::Unspecified> {
+//! let key = try!(cmac::Key::new(&cmac::AES_128, &[0x42; 16]));
+//!
+//! let tag = cmac::sign(&key, b"hello, world");
+//!
+//! try!(cmac::verify(&key, b"hello, world", tag.as_ref()));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [NIST SP 800-38B]:
+//!     https://nvlpubs.nist.gov/nistpubs/SpecialPublications/NIST.SP.800-38B.pdf
+//! [RFC 4493]: https://tools.ietf.org/html/rfc4493
+//! [S2V]: https://tools.ietf.org/html/rfc5297#section-2.4
+
+use {c, constant_time, error, init, polyfill};
+use core;
+
+/// A CMAC algorithm.
+pub struct Algorithm {
+    key_len: usize,
+}
+
+/// AES-128-CMAC. The key must be 16 bytes.
+pub static AES_128: Algorithm = Algorithm {
+    key_len: 128 / 8,
+};
+
+/// AES-256-CMAC. The key must be 32 bytes.
+pub static AES_256: Algorithm = Algorithm {
+    key_len: 256 / 8,
+};
+
+impl Algorithm {
+    /// The length of the AES key, in bytes.
+    #[inline(always)]
+    pub fn key_len(&self) -> usize { self.key_len }
+}
+
+/// The length of an AES block, which is also the length of CMAC tags and of
+/// the subkeys.
+pub const BLOCK_LEN: usize = 16;
+
+/// An AES block.
+pub type Block = [u8; BLOCK_LEN];
+
+/// A key for signing and verifying CMAC tags.
+pub struct Key {
+    algorithm: &'static Algorithm,
+    aes_key: AES_KEY,
+    k1: Block,
+    k2: Block,
+}
+
+impl Key {
+    /// Constructs a key from `key_value`, which must be exactly
+    /// `algorithm.key_len()` bytes long. The subkeys are generated here, as
+    /// described in [RFC 4493 Section 2.3].
+    ///
+    /// [RFC 4493 Section 2.3]: https://tools.ietf.org/html/rfc4493#section-2.3
+    pub fn new(algorithm: &'static Algorithm, key_value: &[u8])
+               -> Result<Key, error::Unspecified> {
+        init::init_once();
+
+        if key_value.len() != algorithm.key_len {
+            return Err(error::Unspecified);
+        }
+        let mut key = Key {
+            algorithm: algorithm,
+            aes_key: AES_KEY {
+                rd_key: [0u32; 4 * (AES_MAX_ROUNDS + 1)],
+                rounds: 0,
+            },
+            k1: [0u8; BLOCK_LEN],
+            k2: [0u8; BLOCK_LEN],
+        };
+        let bits = (key_value.len() * 8) as u32;
+        if unsafe {
+            GFp_AES_set_encrypt_key(key_value.as_ptr(), bits, &mut key.aes_key)
+        } != 0 {
+            return Err(error::Unspecified);
+        }
+        let mut l = [0u8; BLOCK_LEN];
+        key.encrypt_in_place(&mut l);
+        key.k1 = double(&l);
+        key.k2 = double(&key.k1);
+        Ok(key)
+    }
+
+    /// The algorithm for the key.
+    #[inline(always)]
+    pub fn algorithm(&self) -> &'static Algorithm { self.algorithm }
+
+    /// The subkeys K1 and K2.
+    pub fn subkeys(&self) -> (Block, Block) { (self.k1, self.k2) }
+
+    fn encrypt_in_place(&self, block: &mut Block) {
+        unsafe {
+            GFp_AES_encrypt(block.as_ptr(), block.as_mut_ptr(), &self.aes_key);
+        }
+    }
+}
+
+/// A CMAC tag.
+#[derive(Clone, Copy)]
+pub struct Tag(Block);
+
+impl AsRef<[u8]> for Tag {
+    #[inline]
+    fn as_ref(&self) -> &[u8] { &self.0[..] }
+}
+
+/// A context for multi-step (Init-Update-Finish) CMAC signing.
+///
+/// Use `sign` for single-step CMAC signing.
+pub struct Context<'a> {
+    key: &'a Key,
+
+    // The last block of the message is processed differently from the
+    // others, so the most recent block is kept in `pending` until more input
+    // arrives or `sign` is called.
+    state: Block,
+    pending: Block,
+    num_pending: usize,
+}
+
+impl<'a> Context<'a> {
+    /// Constructs a new CMAC signing context using the given key.
+    pub fn with_key(key: &'a Key) -> Context<'a> {
+        Context {
+            key: key,
+            state: [0u8; BLOCK_LEN],
+            pending: [0u8; BLOCK_LEN],
+            num_pending: 0,
+        }
+    }
+
+    /// Updates the CMAC with all the data in `data`. `update` may be called
+    /// zero or more times until `sign` is called.
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            if self.num_pending == BLOCK_LEN {
+                xor_assign(&mut self.state, &self.pending);
+                self.key.encrypt_in_place(&mut self.state);
+                self.num_pending = 0;
+            }
+            let n = core::cmp::min(BLOCK_LEN - self.num_pending, data.len());
+            self.pending[self.num_pending..(self.num_pending + n)]
+                .copy_from_slice(&data[..n]);
+            self.num_pending += n;
+            data = &data[n..];
+        }
+    }
+
+    /// Finalizes the CMAC calculation and returns the tag. `sign` consumes
+    /// the context so it cannot be (mis-)used after `sign` has been called.
+    pub fn sign(mut self) -> Tag {
+        if self.num_pending == BLOCK_LEN {
+            xor_assign(&mut self.pending, &self.key.k1);
+        } else {
+            // padding(M_n) = M_n || 10^i.
+            self.pending[self.num_pending] = 0x80;
+            polyfill::slice::fill(&mut self.pending[(self.num_pending + 1)..],
+                                  0);
+            xor_assign(&mut self.pending, &self.key.k2);
+        }
+        xor_assign(&mut self.state, &self.pending);
+        self.key.encrypt_in_place(&mut self.state);
+        Tag(self.state)
+    }
+}
+
+/// Calculates the CMAC of `data` using the key `key`, in one step.
+pub fn sign(key: &Key, data: &[u8]) -> Tag {
+    let mut ctx = Context::with_key(key);
+    ctx.update(data);
+    ctx.sign()
+}
+
+/// Calculates the CMAC of `data` using the key `key`, and verifies whether
+/// the resultant value equals `tag`, in one step.
+///
+/// The verification will be done in constant time to prevent timing attacks.
+pub fn verify(key: &Key, data: &[u8], tag: &[u8])
+              -> Result<(), error::Unspecified> {
+    constant_time::verify_slices_are_equal(sign(key, data).as_ref(), tag)
+}
+
+/// The doubling in GF(2**128) that generates the subkeys, as described in
+/// [RFC 4493 Section 2.3]. [RFC 5297] calls it `dbl`.
+///
+/// [RFC 4493 Section 2.3]: https://tools.ietf.org/html/rfc4493#section-2.3
+/// [RFC 5297]: https://tools.ietf.org/html/rfc5297#section-2.3
+pub fn double(s: &Block) -> Block {
+    let mut result = [0u8; BLOCK_LEN];
+    for i in 0..(BLOCK_LEN - 1) {
+        result[i] = (s[i] << 1) | (s[i + 1] >> 7);
+    }
+    result[BLOCK_LEN - 1] = (s[BLOCK_LEN - 1] << 1) ^
+                            (0x87 & 0u8.wrapping_sub(s[0] >> 7));
+    result
+}
+
+fn xor_assign(a: &mut [u8], b: &[u8]) {
+    debug_assert_eq!(a.len(), b.len());
+    for (a, b) in a.iter_mut().zip(b.iter()) {
+        *a ^= *b;
+    }
+}
+
+const AES_MAX_ROUNDS: usize = 14;
+
+// Keep this in sync with `AES_KEY` in aes.h.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+struct AES_KEY {
+    rd_key: [u32; 4 * (AES_MAX_ROUNDS + 1)],
+    rounds: u32, // `unsigned` in C.
+}
+
+extern {
+    fn GFp_AES_set_encrypt_key(key: *const u8, bits: u32,
+                               aes_key: *mut AES_KEY) -> c::int;
+    fn GFp_AES_encrypt(in_: *const u8, out: *mut u8, key: *const AES_KEY);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {cmac, error, test};
+
+    #[test]
+    fn test_cmac() {
+        test::from_file("src/cmac_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let alg = match test_case.consume_string("Alg").as_str() {
+                "AES_128" => &cmac::AES_128,
+                "AES_256" => &cmac::AES_256,
+                s => panic!("Unsupported algorithm: {}", s),
+            };
+            let key = test_case.consume_bytes("Key");
+            let msg = test_case.consume_bytes("Msg");
+            let expected = test_case.consume_bytes("Tag");
+
+            let key = cmac::Key::new(alg, &key).unwrap();
+            assert_eq!(cmac::sign(&key, &msg).as_ref(), &expected[..]);
+            assert_eq!(cmac::verify(&key, &msg, &expected), Ok(()));
+
+            // Feed the message to `update` in uneven pieces.
+            for piece_len in 1..(msg.len() + 2) {
+                let mut ctx = cmac::Context::with_key(&key);
+                for piece in msg.chunks(piece_len) {
+                    ctx.update(piece);
+                }
+                assert_eq!(ctx.sign().as_ref(), &expected[..]);
+            }
+
+            let mut bad_tag = expected.clone();
+            bad_tag[0] ^= 1;
+            assert_eq!(cmac::verify(&key, &msg, &bad_tag),
+                       Err(error::Unspecified));
+            assert_eq!(cmac::verify(&key, &msg, &expected[1..]),
+                       Err(error::Unspecified));
+
+            Ok(())
+        });
+    }
+
+    // The subkeys from RFC 4493 Section 4.
+    #[test]
+    fn test_cmac_subkeys() {
+        let key = test::from_hex("2b7e151628aed2a6abf7158809cf4f3c").unwrap();
+        let key = cmac::Key::new(&cmac::AES_128, &key).unwrap();
+        let (k1, k2) = key.subkeys();
+        assert_eq!(&k1[..], &test::from_hex(
+            "fbeed618357133667c85e08f7236a8de").unwrap()[..]);
+        assert_eq!(&k2[..], &test::from_hex(
+            "f7ddac306ae266ccf90bc11ee46d513b").unwrap()[..]);
+        assert_eq!(k2, cmac::double(&k1));
+    }
+
+    #[test]
+    fn test_cmac_key_len() {
+        for &alg in [&cmac::AES_128, &cmac::AES_256].iter() {
+            let key = [0u8; 32 + 1];
+            for len in 0..key.len() {
+                assert_eq!(cmac::Key::new(alg, &key[..len]).is_ok(),
+                           len == alg.key_len());
+            }
+        }
+    }
+}
@@ -0,0 +1,406 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Building blocks for the CMS-based messages of certificate enrollment
+//! protocols like CMP, EST, and SCEP.
+//!
+//! `PasswordBasedMAC` is the password-based MAC of [RFC 4211] Section 4.4,
+//! which CMP ([RFC 4210] Section 5.1.3.1) uses to protect enrollment
+//! requests, e.g. PKCS#10 requests, with a shared secret.
+//!
+//! `Attributes` computes the signed attributes of a CMS `SignerInfo`
+//! ([RFC 5652] Section 5.3), as used by SCEP, or the authenticated attributes
+//! of a CMS `AuthenticatedData` (Section 9.1). The signature or MAC is
+//! computed over `Attributes::to_der()`, with *ring*'s signature or `hmac`
+//! APIs. Assembling the rest of the CMS structure is up to the caller.
+//!
+//! Feature: `use_heap`.
+//!
+//! [RFC 4211]: https://tools.ietf.org/html/rfc4211#section-4.4
+//! [RFC 4210]: https://tools.ietf.org/html/rfc4210#section-5.1.3.1
+//! [RFC 5652]: https://tools.ietf.org/html/rfc5652
+
+use {constant_time, der, digest, error, hmac, init, pbe};
+use std::vec::Vec;
+use untrusted;
+
+/// The parameters of a CMP `PasswordBasedMac`.
+pub struct PasswordBasedMAC {
+    salt: Vec<u8>,
+    owf: &'static digest::Algorithm,
+    iteration_count: usize,
+    mac: &'static digest::Algorithm,
+}
+
+impl PasswordBasedMAC {
+    /// Constructs parameters for `PasswordBasedMac` with `digest_alg` as
+    /// both the one-way function and the HMAC digest algorithm. `digest_alg`
+    /// must be SHA-256, SHA-384, or SHA-512. `salt` should be random and at
+    /// least 16 bytes long.
+    pub fn new(digest_alg: &'static digest::Algorithm, salt: &[u8],
+               iteration_count: usize)
+               -> Result<PasswordBasedMAC, error::Unspecified> {
+        let _ = try!(digest_oid(digest_alg));
+        if iteration_count == 0 || iteration_count > pbe::MAX_ITERATIONS {
+            return Err(error::Unspecified);
+        }
+        Ok(PasswordBasedMAC {
+            salt: salt.to_vec(),
+            owf: digest_alg,
+            iteration_count: iteration_count,
+            mac: digest_alg,
+        })
+    }
+
+    /// Parses a DER-encoded `AlgorithmIdentifier` for `PasswordBasedMac`, as
+    /// in the `protectionAlg` of a `PKIHeader`.
+    ///
+    /// The one-way function must be SHA-256, SHA-384, or SHA-512 and the MAC
+    /// must be HMAC with SHA-1, SHA-256, SHA-384, or SHA-512. HMAC-SHA1 is
+    /// supported because it is OpenSSL's default.
+    pub fn from_der(input: untrusted::Input)
+                    -> Result<PasswordBasedMAC, error::Unspecified> {
+        init::init_once();
+        input.read_all(error::Unspecified, |input| {
+            der::nested(input, der::Tag::Sequence, error::Unspecified,
+                        |input| {
+                let oid = try!(der::expect_tag_and_get_value(input,
+                                                             der::Tag::OID));
+                if oid.as_slice_less_safe() != PASSWORD_BASED_MAC {
+                    return Err(error::Unspecified);
+                }
+                der::nested(input, der::Tag::Sequence, error::Unspecified,
+                            |input| {
+                    let salt = try!(der::expect_tag_and_get_value(
+                                        input, der::Tag::OctetString));
+                    let owf = try!(algorithm_identifier(input, DIGESTS));
+                    let iteration_count = try!(pbe::iteration_count(input));
+                    let mac = try!(algorithm_identifier(input, HMACS));
+                    Ok(PasswordBasedMAC {
+                        salt: salt.as_slice_less_safe().to_vec(),
+                        owf: owf,
+                        iteration_count: iteration_count,
+                        mac: mac,
+                    })
+                })
+            })
+        })
+    }
+
+    /// Returns the DER-encoded `AlgorithmIdentifier`. The algorithm
+    /// identifiers of the one-way function and MAC have absent parameters.
+    pub fn to_der(&self) -> Vec<u8> {
+        let mut params = Vec::new();
+        der::write_tlv(der::Tag::OctetString, &self.salt, &mut params);
+        write_algorithm_identifier(self.owf, DIGESTS, &mut params);
+        let count = self.iteration_count as u32;
+        let count = [0, (count >> 24) as u8, (count >> 16) as u8,
+                     (count >> 8) as u8, count as u8];
+        let leading_zeros = count.windows(2)
+            .take_while(|w| w[0] == 0 && w[1] & 0x80 == 0)
+            .count();
+        der::write_tlv(der::Tag::Integer, &count[leading_zeros..],
+                       &mut params);
+        write_algorithm_identifier(self.mac, HMACS, &mut params);
+
+        let mut algorithm = Vec::new();
+        der::write_tlv(der::Tag::OID, PASSWORD_BASED_MAC, &mut algorithm);
+        der::write_tlv(der::Tag::Sequence, &params, &mut algorithm);
+        let mut result = Vec::new();
+        der::write_tlv(der::Tag::Sequence, &algorithm, &mut result);
+        result
+    }
+
+    /// Computes the MAC of `data`, e.g. the DER-encoded `ProtectedPart` of a
+    /// `PKIMessage`, with the key derived from `password`.
+    pub fn sign(&self, password: &[u8], data: &[u8]) -> digest::Digest {
+        // The key is the result of `iteration_count` applications of the
+        // one-way function, starting with `password || salt`.
+        let mut ctx = digest::Context::new(self.owf);
+        ctx.update(password);
+        ctx.update(&self.salt);
+        let mut key = ctx.finish();
+        for _ in 1..self.iteration_count {
+            key = digest::digest(self.owf, key.as_ref());
+        }
+        let key = hmac::SigningKey::new(self.mac, key.as_ref());
+        hmac::sign(&key, data)
+    }
+
+    /// Verifies that `mac` is the MAC of `data` with the key derived from
+    /// `password`.
+    pub fn verify(&self, password: &[u8], data: &[u8], mac: &[u8])
+                  -> Result<(), error::Unspecified> {
+        let expected = self.sign(password, data);
+        constant_time::verify_slices_are_equal(expected.as_ref(), mac)
+    }
+}
+
+/// CMS signed or authenticated attributes.
+pub struct Attributes {
+    // The DER-encoded `Attribute`s.
+    attributes: Vec<Vec<u8>>,
+}
+
+impl Attributes {
+    /// Constructs the attributes for `content`, with the mandatory
+    /// `content-type` and `message-digest` attributes. `content_type` is the
+    /// value of the content type OID, without the tag and length, e.g.
+    /// `id-data` for SCEP. `digest_alg` must match the `SignerInfo`'s
+    /// `digestAlgorithm`.
+    pub fn new(content_type: &[u8], digest_alg: &'static digest::Algorithm,
+               content: &[u8]) -> Attributes {
+        init::init_once();
+        let mut result = Attributes { attributes: Vec::new() };
+
+        let mut value = Vec::new();
+        der::write_tlv(der::Tag::OID, content_type, &mut value);
+        result.add(CONTENT_TYPE, &[&value]);
+
+        let digest = digest::digest(digest_alg, content);
+        let mut value = Vec::new();
+        der::write_tlv(der::Tag::OctetString, digest.as_ref(), &mut value);
+        result.add(MESSAGE_DIGEST, &[&value]);
+
+        result
+    }
+
+    /// Adds an attribute of type `attr_type`, the value of its OID without
+    /// the tag and length, with the DER-encoded values `values`, e.g. the
+    /// SCEP `messageType`, `transactionID`, and `senderNonce`.
+    pub fn add(&mut self, attr_type: &[u8], values: &[&[u8]]) {
+        let mut sorted_values = values.to_vec();
+        sorted_values.sort();
+        let mut set = Vec::new();
+        for value in sorted_values {
+            set.extend_from_slice(value);
+        }
+        let mut attribute = Vec::new();
+        der::write_tlv(der::Tag::OID, attr_type, &mut attribute);
+        der::write_tlv(der::Tag::Set, &set, &mut attribute);
+        let mut encoded = Vec::new();
+        der::write_tlv(der::Tag::Sequence, &attribute, &mut encoded);
+        self.attributes.push(encoded);
+    }
+
+    /// Returns the DER encoding of the attributes as a `SET OF Attribute`.
+    /// This is the input to the signature or MAC.
+    pub fn to_der(&self) -> Vec<u8> {
+        self.encode(der::Tag::Set)
+    }
+
+    /// Returns the DER encoding of the attributes as the `signedAttrs` of a
+    /// `SignerInfo`, i.e. with an implicit `[0]` tag.
+    pub fn to_signed_attrs_der(&self) -> Vec<u8> {
+        self.encode(der::Tag::ContextSpecificConstructed0)
+    }
+
+    /// Returns the DER encoding of the attributes as the `authAttrs` of an
+    /// `AuthenticatedData`, i.e. with an implicit `[2]` tag.
+    pub fn to_auth_attrs_der(&self) -> Vec<u8> {
+        self.encode(der::Tag::ContextSpecificConstructed2)
+    }
+
+    fn encode(&self, tag: der::Tag) -> Vec<u8> {
+        // DER requires the elements of a SET OF to be sorted by their
+        // encodings.
+        let mut sorted = self.attributes.iter().collect::<Vec<_>>();
+        sorted.sort();
+        let mut value = Vec::new();
+        for attribute in sorted {
+            value.extend_from_slice(attribute);
+        }
+        let mut result = Vec::new();
+        der::write_tlv(tag, &value, &mut result);
+        result
+    }
+}
+
+// Reads an `AlgorithmIdentifier` with absent or NULL parameters, returning
+// the digest algorithm `algorithms` maps its OID to.
+fn algorithm_identifier(input: &mut untrusted::Reader,
+                        algorithms: &[(&'static [u8],
+                                       &'static digest::Algorithm)])
+                        -> Result<&'static digest::Algorithm,
+                                  error::Unspecified> {
+    der::nested(input, der::Tag::Sequence, error::Unspecified, |input| {
+        let oid = try!(der::expect_tag_and_get_value(input, der::Tag::OID));
+        try!(pbe::optional_null(input));
+        algorithms.iter()
+            .find(|&&(alg_oid, _)| alg_oid == oid.as_slice_less_safe())
+            .map(|&(_, alg)| alg)
+            .ok_or(error::Unspecified)
+    })
+}
+
+fn write_algorithm_identifier(alg: &'static digest::Algorithm,
+                              algorithms: &[(&'static [u8],
+                                             &'static digest::Algorithm)],
+                              out: &mut Vec<u8>) {
+    let oid = algorithms.iter()
+        .find(|&&(_, a)| same_digest(a, alg))
+        .map(|&(oid, _)| oid)
+        .unwrap();
+    let mut value = Vec::new();
+    der::write_tlv(der::Tag::OID, oid, &mut value);
+    der::write_tlv(der::Tag::Sequence, &value, out);
+}
+
+fn digest_oid(alg: &'static digest::Algorithm)
+              -> Result<&'static [u8], error::Unspecified> {
+    DIGESTS.iter()
+        .find(|&&(_, a)| same_digest(a, alg))
+        .map(|&(oid, _)| oid)
+        .ok_or(error::Unspecified)
+}
+
+// `digest::Algorithm` doesn't implement `PartialEq`.
+fn same_digest(a: &digest::Algorithm, b: &digest::Algorithm) -> bool {
+    a.output_len == b.output_len && a.chaining_len == b.chaining_len &&
+        a.block_len == b.block_len
+}
+
+// PasswordBasedMac (1.2.840.113533.7.66.13).
+const PASSWORD_BASED_MAC: &'static [u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf6, 0x7d, 0x07, 0x42, 0x0d,
+];
+
+// id-sha256, id-sha384, and id-sha512 (2.16.840.1.101.3.4.2.{1,2,3}).
+const DIGESTS: &'static [(&'static [u8], &'static digest::Algorithm)] = &[
+    (&[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01], &digest::SHA256),
+    (&[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02], &digest::SHA384),
+    (&[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03], &digest::SHA512),
+];
+
+// hmacWithSHA256, hmacWithSHA384, and hmacWithSHA512
+// (1.2.840.113549.2.{9,10,11}), and hmac-sha1 (1.3.6.1.5.5.8.1.2).
+const HMACS: &'static [(&'static [u8], &'static digest::Algorithm)] = &[
+    (&[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x09], &digest::SHA256),
+    (&[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x0a], &digest::SHA384),
+    (&[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x0b], &digest::SHA512),
+    (&[0x2b, 0x06, 0x01, 0x05, 0x05, 0x08, 0x01, 0x02], &digest::SHA1),
+];
+
+// id-contentType (1.2.840.113549.1.9.3).
+const CONTENT_TYPE: &'static [u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x03,
+];
+
+// id-messageDigest (1.2.840.113549.1.9.4).
+const MESSAGE_DIGEST: &'static [u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x04,
+];
+
+#[cfg(test)]
+mod tests {
+    use {digest, signature, test};
+    use super::*;
+    use untrusted;
+
+    #[test]
+    fn test_cms() {
+        test::from_file("src/cms_tests.txt", |section, test_case| {
+            match section {
+                "PasswordBasedMAC" => test_password_based_mac(test_case),
+                "Attributes" => test_attributes(test_case),
+                _ => panic!("Unexpected section: {}", section),
+            }
+            Ok(())
+        });
+    }
+
+    fn test_password_based_mac(test_case: &mut test::TestCase) {
+        let algorithm = test_case.consume_bytes("Algorithm");
+        let password = test_case.consume_bytes("Password");
+        let message = test_case.consume_bytes("Message");
+        let mac = test_case.consume_bytes("MAC");
+
+        let pbm =
+            PasswordBasedMAC::from_der(untrusted::Input::from(&algorithm))
+                .unwrap();
+        assert_eq!(pbm.to_der(), algorithm);
+        assert_eq!(pbm.sign(&password, &message).as_ref(), &mac[..]);
+        assert!(pbm.verify(&password, &message, &mac).is_ok());
+
+        let mut wrong_password = password.clone();
+        wrong_password.push(b'!');
+        assert!(pbm.verify(&wrong_password, &message, &mac).is_err());
+        let mut wrong_mac = mac.clone();
+        wrong_mac[0] ^= 1;
+        assert!(pbm.verify(&password, &message, &wrong_mac).is_err());
+    }
+
+    fn test_attributes(test_case: &mut test::TestCase) {
+        let digest_alg = test_case.consume_digest_alg("Digest").unwrap();
+        let content = test_case.consume_bytes("Content");
+        let signing_time = test_case.consume_bytes("SigningTime");
+        let signed_attrs = test_case.consume_bytes("SignedAttrs");
+        let public_key = test_case.consume_bytes("PublicKey");
+        let sig = test_case.consume_bytes("Signature");
+
+        // Add the attributes in a different order than they are encoded.
+        let mut attributes = Attributes::new(ID_DATA, digest_alg, &content);
+        attributes.add(SIGNING_TIME, &[&signing_time]);
+        assert_eq!(attributes.to_signed_attrs_der(), signed_attrs);
+
+        let to_be_signed = attributes.to_der();
+        assert_eq!(to_be_signed[0], 0x31);
+        assert_eq!(&to_be_signed[1..], &signed_attrs[1..]);
+        assert!(signature::verify(&signature::ED25519,
+                                  untrusted::Input::from(&public_key),
+                                  untrusted::Input::from(&to_be_signed),
+                                  untrusted::Input::from(&sig)).is_ok());
+
+        let auth_attrs = attributes.to_auth_attrs_der();
+        assert_eq!(auth_attrs[0], 0xa2);
+        assert_eq!(&auth_attrs[1..], &signed_attrs[1..]);
+    }
+
+    #[test]
+    fn test_password_based_mac_new() {
+        let pbm = PasswordBasedMAC::new(&digest::SHA256, b"salt", 500).unwrap();
+        let encoded = pbm.to_der();
+        let decoded =
+            PasswordBasedMAC::from_der(untrusted::Input::from(&encoded))
+                .unwrap();
+        assert_eq!(decoded.to_der(), encoded);
+        assert_eq!(decoded.sign(b"pw", b"msg").as_ref(),
+                   pbm.sign(b"pw", b"msg").as_ref());
+
+        assert!(PasswordBasedMAC::new(&digest::SHA1, b"salt", 500).is_err());
+        assert!(PasswordBasedMAC::new(&digest::SHA256, b"salt", 0).is_err());
+        assert!(PasswordBasedMAC::new(&digest::SHA256, b"salt",
+                                      10_000_001).is_err());
+    }
+
+    #[test]
+    fn test_attributes_value_order() {
+        let mut a = Attributes { attributes: Vec::new() };
+        a.add(&[0x55, 0x04, 0x03], &[&[0x04, 0x01, 0x02], &[0x04, 0x01, 0x01]]);
+        assert_eq!(a.to_der(), vec![0x31, 0x0f, 0x30, 0x0d,
+                                    0x06, 0x03, 0x55, 0x04, 0x03,
+                                    0x31, 0x06, 0x04, 0x01, 0x01,
+                                    0x04, 0x01, 0x02]);
+    }
+
+    // id-data (1.2.840.113549.1.7.1).
+    const ID_DATA: &'static [u8] = &[
+        0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x01,
+    ];
+
+    // id-signingTime (1.2.840.113549.1.9.5).
+    const SIGNING_TIME: &'static [u8] = &[
+        0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x05,
+    ];
+}
@@ -32,6 +32,378 @@ pub fn verify_slices_are_equal(a: &[u8], b: &[u8])
     }
 }
 
+/// The maximum length of the secrets compared by `verify_secrets_are_equal`.
+pub const MAX_SECRET_LEN: usize = 256;
+
+/// Returns `Ok(())` if `expected == actual` and `Err(error::Unspecified)`
+/// otherwise, in constant time with respect to both the contents and the
+/// lengths of `expected` and `actual`.
+///
+/// This is for comparing short secrets such as password-reset tokens, API
+/// keys, and one-time passwords, where the length of the value supplied by
+/// the peer often differs from the length of the expected value. Both values
+/// are padded internally to `MAX_SECRET_LEN` bytes, so the timing reveals
+/// neither their contents nor their lengths. If either value is longer than
+/// `MAX_SECRET_LEN` bytes then `Err(error::Unspecified)` is returned without
+/// doing the comparison.
+///
+/// The values are compared exactly as bytes; no normalization (e.g. of case
+/// or of surrounding whitespace) is done.
+pub fn verify_secrets_are_equal(expected: &[u8], actual: &[u8])
+                                -> Result<(), error::Unspecified> {
+    if expected.len() > MAX_SECRET_LEN || actual.len() > MAX_SECRET_LEN {
+        return Err(error::Unspecified);
+    }
+    let mut padded_expected = [0u8; MAX_SECRET_LEN];
+    padded_expected[..expected.len()].copy_from_slice(expected);
+    let mut padded_actual = [0u8; MAX_SECRET_LEN];
+    padded_actual[..actual.len()].copy_from_slice(actual);
+
+    let contents_differ = unsafe {
+        GFp_memcmp(padded_expected.as_ptr(), padded_actual.as_ptr(),
+                   MAX_SECRET_LEN)
+    };
+    // The lengths must be compared too, since the zero padding makes e.g.
+    // "abc" and "abc\0" look the same.
+    let lengths_differ = expected.len() ^ actual.len();
+    match (contents_differ as usize) | lengths_differ {
+        0 => Ok(()),
+        _ => Err(error::Unspecified),
+    }
+}
+
+/// The maximum number of digits in a PIN normalized by `normalize_pin`.
+pub const MAX_PIN_LEN: usize = 16;
+
+/// The maximum length of the input to `normalize_pin`, including separators.
+pub const MAX_PIN_INPUT_LEN: usize = 64;
+
+/// The lengths, in digits, of the PINs that `normalize_pin` accepts.
+pub struct PINPolicy {
+    min_len: usize,
+    max_len: usize,
+}
+
+impl PINPolicy {
+    /// Constructs a policy that accepts PINs with at least `min_len` and at
+    /// most `max_len` digits. `min_len` must be at least 1 and `max_len` must
+    /// be at least `min_len` and at most `MAX_PIN_LEN`.
+    pub fn new(min_len: usize, max_len: usize)
+               -> Result<PINPolicy, error::Unspecified> {
+        if min_len < 1 || min_len > max_len || max_len > MAX_PIN_LEN {
+            return Err(error::Unspecified);
+        }
+        Ok(PINPolicy { min_len: min_len, max_len: max_len })
+    }
+}
+
+/// A PIN or one-time code that has been normalized to a string of ASCII
+/// digits by `normalize_pin`.
+pub struct NormalizedPIN {
+    digits: [u8; MAX_PIN_LEN], // The values 0-9, not ASCII.
+    len: usize,
+}
+
+impl NormalizedPIN {
+    /// The length of the PIN, in digits.
+    #[inline]
+    pub fn len(&self) -> usize { self.len }
+
+    /// Writes the ASCII digits of the PIN to `out`, which must be exactly
+    /// `self.len()` bytes long, e.g. to store a hash of the PIN.
+    pub fn write_ascii(&self, out: &mut [u8])
+                       -> Result<(), error::Unspecified> {
+        if out.len() != self.len {
+            return Err(error::Unspecified);
+        }
+        for (out, &digit) in out.iter_mut().zip(self.digits.iter()) {
+            *out = b'0' + digit;
+        }
+        Ok(())
+    }
+}
+
+/// Normalizes a numeric PIN or one-time code, such as a TOTP code typed as
+/// "123 456", in constant time with respect to the contents of `input`.
+///
+/// ASCII spaces, tabs, and hyphens are removed and the remaining bytes must
+/// all be the ASCII digits 0-9. Other characters, including non-ASCII
+/// digits, are rejected instead of being mapped to ASCII digits. The number
+/// of digits must be within the range allowed by `policy`. `input` may be at
+/// most `MAX_PIN_INPUT_LEN` bytes long.
+///
+/// The timing depends on the length of `input` but not on which bytes are
+/// digits or separators, so it doesn't reveal the number of digits.
+pub fn normalize_pin(policy: &PINPolicy, input: &[u8])
+                     -> Result<NormalizedPIN, error::Unspecified> {
+    if input.len() > MAX_PIN_INPUT_LEN {
+        return Err(error::Unspecified);
+    }
+    let mut digits = [0u8; MAX_PIN_LEN];
+    let mut len = 0;
+    let mut invalid = 0;
+    for &b in input {
+        let b = b as usize;
+        let is_digit =
+            lt_mask(b, b'9' as usize + 1) & !lt_mask(b, b'0' as usize);
+        let is_separator = eq_mask(b, b' ' as usize) |
+                           eq_mask(b, b'\t' as usize) |
+                           eq_mask(b, b'-' as usize);
+        invalid |= !(is_digit | is_separator);
+
+        // Avoid indexing `digits` with the secret-dependent `len`.
+        let digit = b.wrapping_sub(b'0' as usize) & is_digit;
+        for (i, d) in digits.iter_mut().enumerate() {
+            *d |= (digit & eq_mask(i, len)) as u8;
+        }
+        len += 1 & is_digit;
+    }
+    invalid |= lt_mask(len, policy.min_len) | lt_mask(policy.max_len, len);
+    if invalid != 0 {
+        return Err(error::Unspecified);
+    }
+    Ok(NormalizedPIN { digits: digits, len: len })
+}
+
+/// Normalizes `expected` and `actual` with `normalize_pin` and then returns
+/// `Ok(())` if they are equal and `Err(error::Unspecified)` otherwise. The
+/// comparison is done in constant time with respect to the contents and the
+/// number of digits of both PINs.
+pub fn verify_pins_are_equal(policy: &PINPolicy, expected: &[u8],
+                             actual: &[u8])
+                             -> Result<(), error::Unspecified> {
+    let expected = try!(normalize_pin(policy, expected));
+    let actual = try!(normalize_pin(policy, actual));
+    let contents_differ = unsafe {
+        GFp_memcmp(expected.digits.as_ptr(), actual.digits.as_ptr(),
+                   MAX_PIN_LEN)
+    };
+    // As in `verify_secrets_are_equal`, the zero padding makes e.g. "123" and
+    // "1230" look the same, so the lengths must be compared too.
+    let lengths_differ = expected.len ^ actual.len;
+    match (contents_differ as usize) | lengths_differ {
+        0 => Ok(()),
+        _ => Err(error::Unspecified),
+    }
+}
+
+/// Returns `a` if `choice` is 1 and `b` if `choice` is 0, in constant time
+/// with respect to all three values. `choice` must be 0 or 1.
+#[inline]
+pub fn select_u8(choice: u8, a: u8, b: u8) -> u8 {
+    debug_assert!(choice <= 1);
+    let mask = 0u8.wrapping_sub(choice);
+    (a & mask) | (b & !mask)
+}
+
+/// Returns `a` if `choice` is 1 and `b` if `choice` is 0, in constant time
+/// with respect to all three values. `choice` must be 0 or 1.
+#[inline]
+pub fn select_u32(choice: u8, a: u32, b: u32) -> u32 {
+    debug_assert!(choice <= 1);
+    let mask = 0u32.wrapping_sub(choice as u32);
+    (a & mask) | (b & !mask)
+}
+
+/// Sets `*dst` to `src` if `choice` is 1 and leaves it unchanged if `choice`
+/// is 0, in constant time with respect to all three values. `choice` must be
+/// 0 or 1.
+#[inline]
+pub fn conditional_assign(choice: u8, dst: &mut u32, src: u32) {
+    *dst = select_u32(choice, src, *dst);
+}
+
+/// Copies `src` to `dst` if `choice` is 1 and leaves `dst` unchanged if
+/// `choice` is 0, in constant time with respect to `choice` and the contents
+/// of `dst` and `src`. `choice` must be 0 or 1.
+///
+/// This is useful for e.g. copying out a decrypted message only if its
+/// padding is valid, without a padding-dependent branch. Returns
+/// `Err(error::Unspecified)`, without modifying `dst`, if `dst` and `src`
+/// aren't the same length.
+pub fn copy_if(choice: u8, dst: &mut [u8], src: &[u8])
+               -> Result<(), error::Unspecified> {
+    if dst.len() != src.len() {
+        return Err(error::Unspecified);
+    }
+    for (d, &s) in dst.iter_mut().zip(src.iter()) {
+        *d = select_u8(choice, s, *d);
+    }
+    Ok(())
+}
+
+/// Returns 1 if `a < b` and 0 otherwise, in constant time with respect to
+/// `a` and `b`. The result is a choice bit suitable for `select_u8`,
+/// `select_u32`, `conditional_assign`, and `copy_if`.
+#[inline]
+pub fn less_than(a: u32, b: u32) -> u8 {
+    // The borrow out of the top bit of `a - b`.
+    ((a ^ ((a ^ b) | (a.wrapping_sub(b) ^ b))) >> 31) as u8
+}
+
+// Returns all ones if `a < b` and zero otherwise. `a` and `b` must both be
+// less than 2**(usize::BITS - 1).
+#[inline]
+fn lt_mask(a: usize, b: usize) -> usize {
+    let shift = usize::max_value().count_ones() - 1;
+    0usize.wrapping_sub(a.wrapping_sub(b) >> shift)
+}
+
+// Returns all ones if `a == b` and zero otherwise. `a` and `b` must both be
+// less than 2**(usize::BITS - 1).
+#[inline]
+fn eq_mask(a: usize, b: usize) -> usize {
+    !(lt_mask(a, b) | lt_mask(b, a))
+}
+
 extern {
     fn GFp_memcmp(a: *const u8, b: *const u8, len: c::size_t) -> c::int;
 }
+
+
+#[cfg(test)]
+mod tests {
+    use constant_time;
+
+    #[test]
+    fn test_verify_secrets_are_equal() {
+        let ok = |a: &[u8], b: &[u8]| {
+            constant_time::verify_secrets_are_equal(a, b).is_ok()
+        };
+        assert!(ok(b"", b""));
+        assert!(ok(b"abc", b"abc"));
+        assert!(!ok(b"abc", b"abd"));
+        assert!(!ok(b"abc", b"ab"));
+        assert!(!ok(b"ab", b"abc"));
+        assert!(!ok(b"abc", b"abc\0"));
+        assert!(!ok(b"abc", b""));
+        assert!(!ok(b"abc", b"ABC"));
+
+        let max = [b'a'; constant_time::MAX_SECRET_LEN];
+        assert!(ok(&max, &max));
+        assert!(!ok(&max, &max[1..]));
+
+        let too_long = [b'a'; constant_time::MAX_SECRET_LEN + 1];
+        assert!(!ok(&too_long, &too_long));
+        assert!(!ok(&max, &too_long));
+        assert!(!ok(&too_long, &max));
+    }
+
+    #[test]
+    fn test_normalize_pin() {
+        let policy = constant_time::PINPolicy::new(4, 8).unwrap();
+        let normalize = |input: &[u8]| {
+            constant_time::normalize_pin(&policy, input).map(|pin| {
+                let mut ascii = vec![0u8; pin.len()];
+                pin.write_ascii(&mut ascii).unwrap();
+                ascii
+            })
+        };
+        assert_eq!(normalize(b"1234").unwrap(), b"1234");
+        assert_eq!(normalize(b"0000").unwrap(), b"0000");
+        assert_eq!(normalize(b" 123 456\t").unwrap(), b"123456");
+        assert_eq!(normalize(b"1234-5678").unwrap(), b"12345678");
+        assert_eq!(normalize(b"--9-0-1-2--").unwrap(), b"9012");
+
+        // Too few or too many digits; separators don't count.
+        assert!(normalize(b"").is_err());
+        assert!(normalize(b"123").is_err());
+        assert!(normalize(b"1 2 3").is_err());
+        assert!(normalize(b"123456789").is_err());
+        assert!(normalize(b"12345678901234567890").is_err());
+
+        // Characters other than digits and separators, including non-ASCII
+        // digits, are rejected.
+        assert!(normalize(b"12a4").is_err());
+        assert!(normalize(b"1234\n").is_err());
+        assert!(normalize(b"12.34").is_err());
+        assert!(normalize(b"1234\0").is_err());
+        let fullwidth_digits = "\u{FF11}\u{FF12}\u{FF13}\u{FF14}";
+        assert!(normalize(fullwidth_digits.as_bytes()).is_err());
+        assert!(normalize("12\u{0663}4".as_bytes()).is_err());
+
+        let long_input = [b' '; constant_time::MAX_PIN_INPUT_LEN + 1];
+        let mut max_input = [b' '; constant_time::MAX_PIN_INPUT_LEN];
+        max_input[..4].copy_from_slice(b"1234");
+        assert_eq!(normalize(&max_input).unwrap(), b"1234");
+        assert!(normalize(&long_input).is_err());
+
+        let mut ascii = [0u8; 3];
+        let pin = constant_time::normalize_pin(&policy, b"1234").unwrap();
+        assert!(pin.write_ascii(&mut ascii).is_err());
+    }
+
+    #[test]
+    fn test_pin_policy() {
+        let max = constant_time::MAX_PIN_LEN;
+        assert!(constant_time::PINPolicy::new(0, 4).is_err());
+        assert!(constant_time::PINPolicy::new(5, 4).is_err());
+        assert!(constant_time::PINPolicy::new(1, max + 1).is_err());
+        assert!(constant_time::PINPolicy::new(1, 1).is_ok());
+        assert!(constant_time::PINPolicy::new(max, max).is_ok());
+    }
+
+    #[test]
+    fn test_verify_pins_are_equal() {
+        let policy = constant_time::PINPolicy::new(6, 6).unwrap();
+        let ok = |a: &[u8], b: &[u8]| {
+            constant_time::verify_pins_are_equal(&policy, a, b).is_ok()
+        };
+        assert!(ok(b"123456", b"123456"));
+        assert!(ok(b"123456", b"123 456"));
+        assert!(ok(b"123-456", b" 123456 "));
+        assert!(!ok(b"123456", b"123457"));
+        assert!(!ok(b"123456", b"12345"));
+        assert!(!ok(b"123450", b"12345"));
+        assert!(!ok(b"123456", b"1234567"));
+        assert!(!ok(b"12345", b"12345"));
+        assert!(!ok(b"123456", b"12345x"));
+
+        let policy = constant_time::PINPolicy::new(4, 8).unwrap();
+        assert!(constant_time::verify_pins_are_equal(&policy, b"12340",
+                                                     b"1234").is_err());
+    }
+
+    #[test]
+    fn test_select() {
+        assert_eq!(constant_time::select_u8(1, 0x12, 0xab), 0x12);
+        assert_eq!(constant_time::select_u8(0, 0x12, 0xab), 0xab);
+        assert_eq!(constant_time::select_u8(1, 0, 0xff), 0);
+        assert_eq!(constant_time::select_u8(0, 0, 0xff), 0xff);
+        assert_eq!(constant_time::select_u32(1, 0xdeadbeef, 7), 0xdeadbeef);
+        assert_eq!(constant_time::select_u32(0, 0xdeadbeef, 7), 7);
+
+        let mut x = 5;
+        constant_time::conditional_assign(0, &mut x, 0xffffffff);
+        assert_eq!(x, 5);
+        constant_time::conditional_assign(1, &mut x, 0xffffffff);
+        assert_eq!(x, 0xffffffff);
+    }
+
+    #[test]
+    fn test_copy_if() {
+        let mut dst = [1u8, 2, 3];
+        assert!(constant_time::copy_if(0, &mut dst, &[4, 5, 6]).is_ok());
+        assert_eq!(dst, [1, 2, 3]);
+        assert!(constant_time::copy_if(1, &mut dst, &[4, 5, 6]).is_ok());
+        assert_eq!(dst, [4, 5, 6]);
+        assert!(constant_time::copy_if(1, &mut dst, &[7, 8]).is_err());
+        assert!(constant_time::copy_if(1, &mut dst, &[7, 8, 9, 10]).is_err());
+        assert_eq!(dst, [4, 5, 6]);
+        assert!(constant_time::copy_if(1, &mut [], &[]).is_ok());
+    }
+
+    #[test]
+    fn test_less_than() {
+        let max = u32::max_value();
+        let cases = [
+            (0, 0, 0), (0, 1, 1), (1, 0, 0), (1, 1, 0),
+            (0, max, 1), (max, 0, 0), (max, max, 0), (max - 1, max, 1),
+            (0x7fffffff, 0x80000000, 1), (0x80000000, 0x7fffffff, 0),
+            (0x80000000, 0x80000001, 1), (0x80000001, 0x80000000, 0),
+        ];
+        for &(a, b, expected) in cases.iter() {
+            assert_eq!(constant_time::less_than(a, b), expected);
+        }
+    }
+}
@@ -0,0 +1,514 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//! CPace, the balanced password-authenticated key exchange (PAKE) specified
+//! in [draft-irtf-cfrg-cpace], which the CFRG selected in its PAKE selection
+//! process.
+//!
+//! CPace is suitable for pairing devices and for authenticating peers on a
+//! local network with a short shared password or PIN. Both parties know the
+//! password-related string (PRS), and each sends one share to the other. An
+//! active attacker can test only one password guess per exchange.
+//!
+//! Each party calls `KeyExchange::new` with the same PRS, channel identifier
+//! (CI), and session identifier (sid), sends `share()` and its associated
+//! data (AD) to the peer, and then calls the `finish_*` method for its role
+//! with the peer's share and AD. In the initiator-responder setting, one
+//! party calls `finish_initiator` and the other calls `finish_responder`. In
+//! the symmetric setting, where neither party is distinguished, both call
+//! `finish_symmetric`. The result is the intermediate session key (ISK).
+//!
+//! The ISK is the same for both parties only if they used the same PRS. No
+//! key confirmation is done; the application must either confirm the key or
+//! use it in a way that fails safely when it doesn't match.
+//!
+//! The sid should be unique for each exchange; the draft recommends that the
+//! parties agree on it beforehand, e.g. by exchanging random nonces.
+//!
+//! [draft-irtf-cfrg-cpace]:
+//!     https://tools.ietf.org/html/draft-irtf-cfrg-cpace-13
+//!
+//! # Example
+//!
+//! ```
+//! # extern crate ring;
+//! # extern crate untrusted;
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::{cpace, rand};
+//!
+//! let rng = rand::SystemRandom::new();
+//! let suite = &cpace::RISTRETTO255_SHA512;
+//! let (prs, ci, sid) = (b"123456", b"device-pairing", b"session nonces");
+//!
+//! let a = try!(cpace::KeyExchange::new(suite, prs, ci, sid, &rng));
+//! let b = try!(cpace::KeyExchange::new(suite, prs, ci, sid, &rng));
+//!
+//! // A sends its share and "A" to B; B sends its share and "B" to A.
+//! let mut share_b = [0u8; cpace::MAX_SHARE_LEN];
+//! let share_b = &mut share_b[..suite.share_len()];
+//! share_b.copy_from_slice(b.share());
+//!
+//! let isk_b = try!(b.finish_responder(b"B",
+//!                                     untrusted::Input::from(a.share()),
+//!                                     b"A"));
+//! let isk_a = try!(a.finish_initiator(b"A",
+//!                                     untrusted::Input::from(&share_b[..]),
+//!                                     b"B"));
+//! assert_eq!(isk_a.as_ref(), isk_b.as_ref());
+//! # Ok(())
+//! # }
+//! ```
+
+use {digest, error, init, rand};
+use ec::{edwards25519, ristretto255};
+use ec::suite_b::cpace as p256;
+use untrusted;
+
+/// The maximum length of a share, for any suite.
+pub const MAX_SHARE_LEN: usize = p256::POINT_LEN;
+
+/// A CPace ciphersuite.
+pub struct Suite {
+    dsi: &'static [u8],
+    digest_alg: &'static digest::Algorithm,
+
+    // s_in_bytes, the input block size of the hash function.
+    s_in_bytes: usize,
+
+    share_len: usize,
+    calculate_generator: fn(generator_string: &[&[u8]], out: &mut [u8])
+                            -> Result<(), error::Unspecified>,
+    sample_scalar: fn(rng: &rand::SecureRandom, out: &mut [u8])
+                      -> Result<(), error::Unspecified>,
+    scalar_mult: fn(scalar: &[u8], point: untrusted::Input, out: &mut [u8])
+                    -> Result<(), error::Unspecified>,
+    scalar_mult_vfy: fn(scalar: &[u8], point: untrusted::Input,
+                        out: &mut [u8]) -> Result<(), error::Unspecified>,
+}
+
+impl Suite {
+    /// The length of the shares.
+    #[inline]
+    pub fn share_len(&self) -> usize { self.share_len }
+
+    /// The hash function, which determines the length of the ISK.
+    #[inline]
+    pub fn digest_algorithm(&self) -> &'static digest::Algorithm {
+        self.digest_alg
+    }
+}
+
+/// CPACE-RISTR255-SHA512. Shares are 32-byte ristretto255 encodings.
+pub static RISTRETTO255_SHA512: Suite = Suite {
+    dsi: b"CPaceRistretto255",
+    digest_alg: &digest::SHA512,
+    s_in_bytes: 128,
+    share_len: ristretto255::ELEM_LEN,
+    calculate_generator: ristretto255_calculate_generator,
+    sample_scalar: ristretto255_sample_scalar,
+    scalar_mult: ristretto255_scalar_mult,
+    scalar_mult_vfy: ristretto255_scalar_mult_vfy,
+};
+
+/// CPACE-P256_XMD:SHA-256_SSWU_NU_-SHA256. Shares are P-256 points in the
+/// uncompressed form (0x04 || x || y).
+pub static P256_XMD_SHA256_SSWU_NU_SHA256: Suite = Suite {
+    dsi: P256_DSI,
+    digest_alg: &digest::SHA256,
+    s_in_bytes: 64,
+    share_len: p256::POINT_LEN,
+    calculate_generator: p256_calculate_generator,
+    sample_scalar: p256::sample_scalar,
+    scalar_mult: p256::scalar_mult,
+    scalar_mult_vfy: p256::scalar_mult_vfy,
+};
+
+/// One party's state in a CPace exchange.
+pub struct KeyExchange {
+    suite: &'static Suite,
+    scalar: [u8; SCALAR_LEN],
+    share: [u8; MAX_SHARE_LEN],
+
+    // Has been fed lv_cat(DSI || "_ISK", sid).
+    isk_ctx: digest::Context,
+}
+
+impl KeyExchange {
+    /// Computes the generator from `prs`, `ci`, and `sid`, and generates this
+    /// party's scalar and share.
+    pub fn new(suite: &'static Suite, prs: &[u8], ci: &[u8], sid: &[u8],
+               rng: &rand::SecureRandom)
+               -> Result<KeyExchange, error::Unspecified> {
+        init::init_once();
+
+        // generator_string(DSI, PRS, CI, sid, s_in_bytes), which pads the
+        // PRS with zeros so that it fills the first block of the hash input.
+        let dsi_len = EncodedLen::new(suite.dsi.len());
+        let prs_len = EncodedLen::new(prs.len());
+        let zpad_len = suite.s_in_bytes
+            .saturating_sub(1 + prs_len.as_ref().len() + prs.len() +
+                            dsi_len.as_ref().len() + suite.dsi.len());
+        let zpad = &ZEROS[..zpad_len];
+        let zpad_len = EncodedLen::new(zpad_len);
+        let ci_len = EncodedLen::new(ci.len());
+        let sid_len = EncodedLen::new(sid.len());
+        let generator_string = [
+            dsi_len.as_ref(), suite.dsi,
+            prs_len.as_ref(), prs,
+            zpad_len.as_ref(), zpad,
+            ci_len.as_ref(), ci,
+            sid_len.as_ref(), sid,
+        ];
+        let mut generator = [0u8; MAX_SHARE_LEN];
+        let generator = &mut generator[..suite.share_len];
+        try!((suite.calculate_generator)(&generator_string, generator));
+
+        let mut scalar = [0u8; SCALAR_LEN];
+        try!((suite.sample_scalar)(rng, &mut scalar));
+        let mut share = [0u8; MAX_SHARE_LEN];
+        try!((suite.scalar_mult)(&scalar, untrusted::Input::from(generator),
+                                 &mut share[..suite.share_len]));
+
+        let mut isk_ctx = digest::Context::new(suite.digest_alg);
+        isk_ctx.update(EncodedLen::new(suite.dsi.len() + ISK_SUFFIX.len())
+                           .as_ref());
+        isk_ctx.update(suite.dsi);
+        isk_ctx.update(ISK_SUFFIX);
+        update_lv(&mut isk_ctx, sid);
+
+        Ok(KeyExchange {
+            suite: suite,
+            scalar: scalar,
+            share: share,
+            isk_ctx: isk_ctx,
+        })
+    }
+
+    /// This party's share, `Ya` or `Yb`, which is `suite.share_len()` bytes
+    /// long.
+    #[inline]
+    pub fn share(&self) -> &[u8] { &self.share[..self.suite.share_len] }
+
+    /// Computes the ISK as the initiator, whose share and associated data are
+    /// `Ya` and `ADa`.
+    pub fn finish_initiator(self, own_ad: &[u8], peer_share: untrusted::Input,
+                            peer_ad: &[u8])
+                            -> Result<digest::Digest, error::Unspecified> {
+        self.finish(Role::Initiator, own_ad, peer_share, peer_ad)
+    }
+
+    /// Computes the ISK as the responder, whose share and associated data are
+    /// `Yb` and `ADb`.
+    pub fn finish_responder(self, own_ad: &[u8], peer_share: untrusted::Input,
+                            peer_ad: &[u8])
+                            -> Result<digest::Digest, error::Unspecified> {
+        self.finish(Role::Responder, own_ad, peer_share, peer_ad)
+    }
+
+    /// Computes the ISK in the symmetric setting, where the messages are
+    /// ordered by their encodings instead of by role.
+    pub fn finish_symmetric(self, own_ad: &[u8], peer_share: untrusted::Input,
+                            peer_ad: &[u8])
+                            -> Result<digest::Digest, error::Unspecified> {
+        self.finish(Role::Symmetric, own_ad, peer_share, peer_ad)
+    }
+
+    fn finish(self, role: Role, own_ad: &[u8], peer_share: untrusted::Input,
+              peer_ad: &[u8]) -> Result<digest::Digest, error::Unspecified> {
+        if peer_share.len() != self.suite.share_len {
+            return Err(error::Unspecified);
+        }
+        let mut k = [0u8; SHARED_SECRET_LEN];
+        try!((self.suite.scalar_mult_vfy)(&self.scalar, peer_share, &mut k));
+
+        let mut ctx = self.isk_ctx;
+        update_lv(&mut ctx, &k);
+
+        let own = Message {
+            share: &self.share[..self.suite.share_len],
+            ad: own_ad,
+        };
+        let peer = Message {
+            share: peer_share.as_slice_less_safe(),
+            ad: peer_ad,
+        };
+        let (first, second) = match role {
+            Role::Initiator => (own, peer),
+            Role::Responder => (peer, own),
+            Role::Symmetric => {
+                // o_cat: "oc" followed by the larger message.
+                ctx.update(b"oc");
+                if own.is_larger_than(&peer) {
+                    (own, peer)
+                } else {
+                    (peer, own)
+                }
+            },
+        };
+        first.update(&mut ctx);
+        second.update(&mut ctx);
+        Ok(ctx.finish())
+    }
+}
+
+enum Role {
+    Initiator,
+    Responder,
+    Symmetric,
+}
+
+// lv_cat(Y, AD).
+struct Message<'a> {
+    share: &'a [u8],
+    ad: &'a [u8],
+}
+
+impl<'a> Message<'a> {
+    fn update(&self, ctx: &mut digest::Context) {
+        update_lv(ctx, self.share);
+        update_lv(ctx, self.ad);
+    }
+
+    // `lexiographically_larger` of the draft: the encodings are compared
+    // byte by byte, and if one is a prefix of the other then the longer one
+    // is larger. The shares and AD are public, so this needn't be
+    // constant-time.
+    fn is_larger_than(&self, b: &Message) -> bool {
+        let a_share_len = EncodedLen::new(self.share.len());
+        let a_ad_len = EncodedLen::new(self.ad.len());
+        let b_share_len = EncodedLen::new(b.share.len());
+        let b_ad_len = EncodedLen::new(b.ad.len());
+        let a = a_share_len.as_ref().iter().chain(self.share.iter())
+                    .chain(a_ad_len.as_ref().iter()).chain(self.ad.iter());
+        let b = b_share_len.as_ref().iter().chain(b.share.iter())
+                    .chain(b_ad_len.as_ref().iter()).chain(b.ad.iter());
+        a.gt(b)
+    }
+}
+
+// prepend_len(data).
+fn update_lv(ctx: &mut digest::Context, data: &[u8]) {
+    ctx.update(EncodedLen::new(data.len()).as_ref());
+    ctx.update(data);
+}
+
+// The LEB128 encoding of a length, as `prepend_len` uses.
+struct EncodedLen {
+    bytes: [u8; MAX_ENCODED_LEN_LEN],
+    len: usize,
+}
+
+// ceil(64 / 7).
+const MAX_ENCODED_LEN_LEN: usize = 10;
+
+impl EncodedLen {
+    fn new(mut value: usize) -> EncodedLen {
+        let mut r = EncodedLen { bytes: [0; MAX_ENCODED_LEN_LEN], len: 0 };
+        loop {
+            let low = (value & 0x7f) as u8;
+            value >>= 7;
+            r.bytes[r.len] = if value == 0 { low } else { low | 0x80 };
+            r.len += 1;
+            if value == 0 {
+                return r;
+            }
+        }
+    }
+}
+
+impl AsRef<[u8]> for EncodedLen {
+    fn as_ref(&self) -> &[u8] { &self.bytes[..self.len] }
+}
+
+// Both suites use 32-byte scalars and shared secrets.
+const SCALAR_LEN: usize = p256::SCALAR_LEN;
+const SHARED_SECRET_LEN: usize = p256::SHARED_SECRET_LEN;
+const ISK_SUFFIX: &'static [u8] = b"_ISK";
+
+// Long enough for the zero padding of any suite's generator string.
+static ZEROS: [u8; 128] = [0; 128];
+
+fn ristretto255_calculate_generator(generator_string: &[&[u8]],
+                                    out: &mut [u8])
+                                    -> Result<(), error::Unspecified> {
+    let mut ctx = digest::Context::new(&digest::SHA512);
+    for part in generator_string {
+        ctx.update(part);
+    }
+    let mut uniform_bytes = [0u8; ristretto255::UNIFORM_BYTES_LEN];
+    uniform_bytes.copy_from_slice(ctx.finish().as_ref());
+    let g = ristretto255::Point::from_uniform_bytes(&uniform_bytes);
+    out.copy_from_slice(g.as_bytes());
+    Ok(())
+}
+
+fn ristretto255_sample_scalar(rng: &rand::SecureRandom, out: &mut [u8])
+                              -> Result<(), error::Unspecified> {
+    let scalar = try!(edwards25519::Scalar::generate(rng));
+    out.copy_from_slice(scalar.as_bytes());
+    Ok(())
+}
+
+fn ristretto255_scalar_mult(scalar: &[u8], point: untrusted::Input,
+                            out: &mut [u8])
+                            -> Result<(), error::Unspecified> {
+    let scalar =
+        try!(edwards25519::Scalar::from_bytes(untrusted::Input::from(scalar)));
+    let point = try!(ristretto255::Point::from_bytes(point));
+    out.copy_from_slice(point.mul(&scalar).as_bytes());
+    Ok(())
+}
+
+// Like `ristretto255_scalar_mult`, but the identity element, which encodes
+// as all zeros, is rejected.
+fn ristretto255_scalar_mult_vfy(scalar: &[u8], point: untrusted::Input,
+                                out: &mut [u8])
+                                -> Result<(), error::Unspecified> {
+    try!(ristretto255_scalar_mult(scalar, point, out));
+    if out.iter().all(|&b| b == 0) {
+        return Err(error::Unspecified);
+    }
+    Ok(())
+}
+
+const P256_DSI: &'static [u8] = b"CPaceP256_XMD:SHA-256_SSWU_NU_";
+
+fn p256_calculate_generator(generator_string: &[&[u8]], out: &mut [u8])
+                            -> Result<(), error::Unspecified> {
+    // DSI || "_DST".
+    p256::calculate_generator(generator_string,
+                              b"CPaceP256_XMD:SHA-256_SSWU_NU__DST", out)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {cpace, test};
+    use rand::test_util::FixedSliceRandom;
+    use untrusted;
+
+    #[test]
+    fn test_cpace() {
+        test::from_file("src/cpace_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+
+            let suite = match test_case.consume_string("Suite").as_str() {
+                "RISTRETTO255_SHA512" => &cpace::RISTRETTO255_SHA512,
+                "P256_XMD_SHA256_SSWU_NU_SHA256" =>
+                    &cpace::P256_XMD_SHA256_SSWU_NU_SHA256,
+                s => panic!("Unsupported suite: {}", s),
+            };
+            let prs = test_case.consume_bytes("PRS");
+            let ci = test_case.consume_bytes("CI");
+            let sid = test_case.consume_bytes("sid");
+            let ad_a = test_case.consume_bytes("ADa");
+            let ad_b = test_case.consume_bytes("ADb");
+            let random_a = test_case.consume_bytes("RandomA");
+            let random_b = test_case.consume_bytes("RandomB");
+            let y_a = test_case.consume_bytes("Ya");
+            let y_b = test_case.consume_bytes("Yb");
+            let isk_ir = test_case.consume_bytes("ISK_IR");
+            let isk_oc = test_case.consume_bytes("ISK_OC");
+
+            let start = |random: &[u8]| {
+                cpace::KeyExchange::new(suite, &prs, &ci, &sid,
+                                        &FixedSliceRandom { bytes: random })
+                    .unwrap()
+            };
+            assert_eq!(start(&random_a).share(), &y_a[..]);
+            assert_eq!(start(&random_b).share(), &y_b[..]);
+
+            let isk = start(&random_a)
+                .finish_initiator(&ad_a, untrusted::Input::from(&y_b), &ad_b)
+                .unwrap();
+            assert_eq!(isk.as_ref(), &isk_ir[..]);
+            let isk = start(&random_b)
+                .finish_responder(&ad_b, untrusted::Input::from(&y_a), &ad_a)
+                .unwrap();
+            assert_eq!(isk.as_ref(), &isk_ir[..]);
+
+            let isk = start(&random_a)
+                .finish_symmetric(&ad_a, untrusted::Input::from(&y_b), &ad_b)
+                .unwrap();
+            assert_eq!(isk.as_ref(), &isk_oc[..]);
+            let isk = start(&random_b)
+                .finish_symmetric(&ad_b, untrusted::Input::from(&y_a), &ad_a)
+                .unwrap();
+            assert_eq!(isk.as_ref(), &isk_oc[..]);
+
+            // Using the wrong password results in a different ISK.
+            let isk = cpace::KeyExchange::new(
+                    suite, b"wrong", &ci, &sid,
+                    &FixedSliceRandom { bytes: &random_a }).unwrap()
+                .finish_initiator(&ad_a, untrusted::Input::from(&y_b), &ad_b)
+                .unwrap();
+            assert!(isk.as_ref() != &isk_ir[..]);
+
+            // Truncated shares are rejected.
+            assert!(start(&random_a)
+                        .finish_initiator(&ad_a,
+                                          untrusted::Input::from(
+                                              &y_b[..(y_b.len() - 1)]),
+                                          &ad_b)
+                        .is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_cpace_invalid_shares() {
+        let rng = FixedSliceRandom { bytes: &[1u8; 64] };
+
+        // The ristretto255 identity element.
+        let ke = cpace::KeyExchange::new(&cpace::RISTRETTO255_SHA512, b"", b"",
+                                         b"", &rng).unwrap();
+        assert!(ke.finish_initiator(b"", untrusted::Input::from(&[0u8; 32]),
+                                    b"")
+                  .is_err());
+
+        // A non-canonical ristretto255 encoding (the field prime).
+        let mut p = [0xffu8; 32];
+        p[0] = 0xed;
+        p[31] = 0x7f;
+        let ke = cpace::KeyExchange::new(&cpace::RISTRETTO255_SHA512, b"", b"",
+                                         b"", &rng).unwrap();
+        assert!(ke.finish_initiator(b"", untrusted::Input::from(&p), b"")
+                  .is_err());
+
+        // A P-256 point that isn't on the curve.
+        let rng = FixedSliceRandom { bytes: &[1u8; 32] };
+        let ke = cpace::KeyExchange::new(
+            &cpace::P256_XMD_SHA256_SSWU_NU_SHA256, b"", b"", b"", &rng)
+            .unwrap();
+        let mut bad = [0u8; 65];
+        bad.copy_from_slice(ke.share());
+        bad[64] ^= 1;
+        assert!(ke.finish_initiator(b"", untrusted::Input::from(&bad), b"")
+                  .is_err());
+    }
+
+    #[test]
+    fn test_encoded_len() {
+        for &(value, expected) in [
+            (0, &[0x00][..]),
+            (127, &[0x7f][..]),
+            (128, &[0x80, 0x01][..]),
+            (300, &[0xac, 0x02][..]),
+            (16384, &[0x80, 0x80, 0x01][..]),
+        ].iter() {
+            assert_eq!(super::EncodedLen::new(value).as_ref(), expected);
+        }
+    }
+}
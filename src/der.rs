@@ -14,11 +14,15 @@
 
 //! Building blocks for parsing DER-encoded ASN.1 structures.
 //!
-//! This module contains the foundational parts of an ASN.1 DER parser.
+//! This module contains the foundational parts of an ASN.1 DER parser, and a
+//! minimal DER writer for the few structures *ring* needs to encode.
 
 use untrusted;
 use error;
 
+#[cfg(feature = "use_heap")]
+use std::vec::Vec;
+
 pub const CONSTRUCTED: u8 = 1 << 5;
 pub const CONTEXT_SPECIFIC: u8 = 2 << 6;
 
@@ -31,12 +35,19 @@ pub enum Tag {
     OctetString = 0x04,
     Null = 0x05,
     OID = 0x06,
+    UTF8String = 0x0C,
     Sequence = CONSTRUCTED | 0x10, // 0x30
+    Set = CONSTRUCTED | 0x11, // 0x31
+    PrintableString = 0x13,
     UTCTime = 0x17,
     GeneralizedTime = 0x18,
 
+    ContextSpecificPrimitive0 = CONTEXT_SPECIFIC | 0,
+    ContextSpecificPrimitive1 = CONTEXT_SPECIFIC | 1,
+    ContextSpecificPrimitive2 = CONTEXT_SPECIFIC | 2,
     ContextSpecificConstructed0 = CONTEXT_SPECIFIC | CONSTRUCTED | 0,
     ContextSpecificConstructed1 = CONTEXT_SPECIFIC | CONSTRUCTED | 1,
+    ContextSpecificConstructed2 = CONTEXT_SPECIFIC | CONSTRUCTED | 2,
     ContextSpecificConstructed3 = CONTEXT_SPECIFIC | CONSTRUCTED | 3,
 }
 
@@ -89,6 +100,18 @@ pub fn read_tag_and_get_value<'a>(input: &mut untrusted::Reader<'a>)
     Ok((tag, inner))
 }
 
+/// Reads a TLV with the tag `tag`, returning both the whole TLV and its value.
+pub fn read_tlv<'a>(input: &mut untrusted::Reader<'a>, tag: Tag)
+                    -> Result<(untrusted::Input<'a>, untrusted::Input<'a>),
+                              error::Unspecified> {
+    let mark1 = input.mark();
+    let value = try!(expect_tag_and_get_value(input, tag));
+    let mark2 = input.mark();
+    let tlv = try!(input.get_input_between_marks(mark1, mark2)
+                        .map_err(|_| error::Unspecified));
+    Ok((tlv, value))
+}
+
 // TODO: investigate taking decoder as a reference to reduce generated code
 // size.
 pub fn nested<'a, F, R, E: Copy>(input: &mut untrusted::Reader<'a>, tag: Tag,
@@ -176,6 +199,41 @@ pub fn positive_integer<'a>(input: &mut untrusted::Reader<'a>)
     nonnegative_integer(input, 1)
 }
 
+/// Appends the encoding of `value` with the tag `tag`, using the shortest
+/// length encoding, to `out`.
+#[cfg(feature = "use_heap")]
+pub fn write_tlv(tag: Tag, value: &[u8], out: &mut Vec<u8>) {
+    out.push(tag as u8);
+    let len = value.len() as u64;
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let mut len_len = 1;
+        while len_len < 8 && (len >> (8 * len_len)) != 0 {
+            len_len += 1;
+        }
+        out.push(0x80 | (len_len as u8));
+        for i in (0..len_len).rev() {
+            out.push((len >> (8 * i)) as u8);
+        }
+    }
+    out.extend_from_slice(value);
+}
+
+/// Appends the encoding of the positive integer `value`, which is big-endian
+/// encoded without leading zeros, to `out`. A zero byte is prepended when the
+/// high bit of `value` is set, so that it isn't interpreted as negative.
+#[cfg(feature = "use_heap")]
+pub fn write_positive_integer(value: &[u8], out: &mut Vec<u8>) {
+    if value.first().map_or(false, |b| b & 0x80 != 0) {
+        let mut with_sign_byte = Vec::with_capacity(value.len() + 1);
+        with_sign_byte.push(0);
+        with_sign_byte.extend_from_slice(value);
+        write_tlv(Tag::Integer, &with_sign_byte, out);
+    } else {
+        write_tlv(Tag::Integer, value, out);
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,340 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! BLAKE2b, as specified in [RFC 7693].
+//!
+//! Unkeyed BLAKE2b with 64-byte and 32-byte outputs is available as the
+//! digest algorithms `digest::BLAKE2B_512` and `digest::BLAKE2B_256`, which
+//! also work with `hmac`. This module supports BLAKE2b's native keying and
+//! the other output lengths with its own `Context`, like `blake2s`. In keyed
+//! mode it is a MAC.
+//!
+//! [RFC 7693]: https://tools.ietf.org/html/rfc7693
+
+use {c, constant_time, error, init, polyfill};
+use core;
+use super::{MAX_OUTPUT_LEN as DIGEST_MAX_OUTPUT_LEN, State};
+
+/// The BLAKE2b block length.
+pub const BLOCK_LEN: usize = 128;
+
+/// The maximum (and default) BLAKE2b output length.
+pub const MAX_OUTPUT_LEN: usize = 64;
+
+/// The maximum length of a BLAKE2b key.
+pub const MAX_KEY_LEN: usize = 64;
+
+/// A context for multi-step (Init-Update-Finish) BLAKE2b calculations.
+///
+/// # Examples
+///
+/// ```
+/// use ring::digest::blake2b;
+///
+/// let key = [0u8; 32];
+/// let mut ctx = blake2b::Context::with_key(&key, 32).unwrap();
+/// ctx.update(b"hello");
+/// ctx.update(b", world");
+/// let tag = ctx.finish();
+/// assert_eq!(tag.as_ref().len(), 32);
+/// ```
+#[derive(Clone)]
+pub struct Context {
+    h: [u64; 8],
+    t: u64,
+
+    // BLAKE2b flags the last block as such, so a full block is only processed
+    // once more input is known to follow it.
+    pending: [u8; BLOCK_LEN],
+    num_pending: usize,
+
+    output_len: usize,
+}
+
+impl Context {
+    /// Constructs a new unkeyed context. `output_len` must be in the range
+    /// [1, `MAX_OUTPUT_LEN`].
+    pub fn new(output_len: usize) -> Result<Context, error::InvalidLength> {
+        Context::with_key(&[], output_len)
+    }
+
+    /// Constructs a new keyed context. `key` must be at most `MAX_KEY_LEN`
+    /// bytes long, and `output_len` must be in the range
+    /// [1, `MAX_OUTPUT_LEN`]. An empty key is the same as no key. Fails with
+    /// `error::InvalidLength` otherwise.
+    pub fn with_key(key: &[u8], output_len: usize)
+                    -> Result<Context, error::InvalidLength> {
+        init::init_once();
+        if key.len() > MAX_KEY_LEN || output_len == 0 ||
+           output_len > MAX_OUTPUT_LEN {
+            return Err(error::InvalidLength);
+        }
+        let mut ctx = Context {
+            h: initial_h(key.len(), output_len),
+            t: 0,
+            pending: [0u8; BLOCK_LEN],
+            num_pending: 0,
+            output_len: output_len,
+        };
+        if !key.is_empty() {
+            ctx.pending[..key.len()].copy_from_slice(key);
+            ctx.num_pending = BLOCK_LEN;
+        }
+        Ok(ctx)
+    }
+
+    /// Updates the calculation with the given data.
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            if self.num_pending == BLOCK_LEN {
+                self.t = self.t.wrapping_add(BLOCK_LEN as u64);
+                let block = self.pending;
+                compress(&mut self.h, &block, self.t, false);
+                self.num_pending = 0;
+            }
+            let n = core::cmp::min(BLOCK_LEN - self.num_pending, data.len());
+            self.pending[self.num_pending..(self.num_pending + n)]
+                .copy_from_slice(&data[..n]);
+            self.num_pending += n;
+            data = &data[n..];
+        }
+    }
+
+    /// Finalizes the calculation and returns the output.
+    pub fn finish(mut self) -> Output {
+        let num_pending = polyfill::u64_from_usize(self.num_pending);
+        self.t = self.t.wrapping_add(num_pending);
+        for b in &mut self.pending[self.num_pending..] {
+            *b = 0;
+        }
+        let block = self.pending;
+        compress(&mut self.h, &block, self.t, true);
+
+        let mut output = Output {
+            value: [0u8; MAX_OUTPUT_LEN],
+            len: self.output_len,
+        };
+        for (out, h) in output.value.chunks_mut(8).zip(self.h.iter()) {
+            for (i, b) in out.iter_mut().enumerate() {
+                *b = (h >> (8 * i)) as u8;
+            }
+        }
+        output
+    }
+
+    /// The length of the output.
+    #[inline(always)]
+    pub fn output_len(&self) -> usize { self.output_len }
+}
+
+/// Returns the unkeyed, 64-byte BLAKE2b digest of `data`.
+pub fn digest(data: &[u8]) -> Output {
+    let mut ctx = Context::new(MAX_OUTPUT_LEN).unwrap();
+    ctx.update(data);
+    ctx.finish()
+}
+
+/// Verifies that `tag` is the keyed BLAKE2b MAC of `data` with the key `key`,
+/// with an output length of `tag.len()`. `key` must not be empty, since
+/// unkeyed BLAKE2b isn't a MAC.
+pub fn verify(key: &[u8], data: &[u8], tag: &[u8])
+              -> Result<(), error::Unspecified> {
+    if key.is_empty() {
+        return Err(error::Unspecified);
+    }
+    let mut ctx = try!(Context::with_key(key, tag.len())
+                           .map_err(|_| error::Unspecified));
+    ctx.update(data);
+    constant_time::verify_slices_are_equal(ctx.finish().as_ref(), tag)
+}
+
+/// A BLAKE2b output.
+#[derive(Clone, Copy)]
+pub struct Output {
+    value: [u8; MAX_OUTPUT_LEN],
+    len: usize,
+}
+
+impl AsRef<[u8]> for Output {
+    #[inline(always)]
+    fn as_ref(&self) -> &[u8] { &self.value[..self.len] }
+}
+
+// The glue for `digest::BLAKE2B_512` and `digest::BLAKE2B_256`. The chaining
+// value is in the first eight words of the state, and the number of bytes
+// compressed so far is in the ninth.
+
+const T_INDEX: usize = 8;
+
+/// Compresses `num` full blocks that are known not to be the last block.
+pub unsafe extern fn block_data_order(state: &mut State, data: *const u8,
+                                      num: c::size_t) {
+    let data = data as *const [u8; BLOCK_LEN];
+    let blocks = core::slice::from_raw_parts(data, num);
+    for block in blocks {
+        state[T_INDEX] = state[T_INDEX].wrapping_add(BLOCK_LEN as u64);
+        let t = state[T_INDEX];
+        compress(chaining_value(state), block, t, false);
+    }
+}
+
+/// Sets the byte counter for a state that has compressed `completed_blocks`
+/// blocks.
+pub fn set_counter(state: &mut State, completed_blocks: u64) {
+    state[T_INDEX] = completed_blocks.wrapping_mul(BLOCK_LEN as u64);
+}
+
+/// Compresses the last, `num_pending`-byte, block, which has been padded with
+/// zeros.
+pub fn finish(state: &mut State, block: &[u8], num_pending: usize) {
+    let t = state[T_INDEX].wrapping_add(polyfill::u64_from_usize(num_pending));
+    let block = slice_as_array_ref!(block, BLOCK_LEN).unwrap();
+    compress(chaining_value(state), block, t, true);
+}
+
+/// Returns the chaining value as the little-endian words of the output.
+pub fn format_output(input: &State) -> [u64; DIGEST_MAX_OUTPUT_LEN / 8] {
+    [input[0].to_le(),
+     input[1].to_le(),
+     input[2].to_le(),
+     input[3].to_le(),
+     input[4].to_le(),
+     input[5].to_le(),
+     input[6].to_le(),
+     input[7].to_le()]
+}
+
+fn chaining_value(state: &mut State) -> &mut [u64; 8] {
+    slice_as_array_ref_mut!(&mut state[..8], 8).unwrap()
+}
+
+// The initial chaining value for an unkeyed or keyed BLAKE2b with the given
+// output length, with the parameter block folded in.
+fn initial_h(key_len: usize, output_len: usize) -> [u64; 8] {
+    let mut h = IV;
+    h[0] ^= 0x01010000 ^ ((key_len as u64) << 8) ^ (output_len as u64);
+    h
+}
+
+const IV: [u64; 8] = [
+    0x6a09e667f3bcc908, 0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+    0x510e527fade682d1, 0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
+];
+
+const SIGMA: [[usize; 16]; 12] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+];
+
+// The compression function F. Inputs are limited to 2**64 - 1 bytes, so the
+// high half of the 128-bit counter is always zero.
+fn compress(h: &mut [u64; 8], block: &[u8; BLOCK_LEN], t: u64, last: bool) {
+    fn g(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64,
+         y: u64) {
+        v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+        v[d] = (v[d] ^ v[a]).rotate_right(32);
+        v[c] = v[c].wrapping_add(v[d]);
+        v[b] = (v[b] ^ v[c]).rotate_right(24);
+        v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+        v[d] = (v[d] ^ v[a]).rotate_right(16);
+        v[c] = v[c].wrapping_add(v[d]);
+        v[b] = (v[b] ^ v[c]).rotate_right(63);
+    }
+
+    let mut m = [0u64; 16];
+    for (m, bytes) in m.iter_mut().zip(block.chunks(8)) {
+        *m = bytes.iter().rev().fold(0, |acc, b| (acc << 8) | (*b as u64));
+    }
+
+    let mut v = [0u64; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&IV);
+    v[12] ^= t;
+    if last {
+        v[14] = !v[14];
+    }
+
+    for s in SIGMA.iter() {
+        g(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+        g(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+        g(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+        g(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+        g(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+        g(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+        g(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+        g(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i + 8];
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use test;
+    use super::*;
+
+    #[test]
+    fn blake2b_test() {
+        test::from_file("src/digest/blake2b_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let key = test_case.consume_bytes("Key");
+            let input = test_case.consume_bytes("Input");
+            let output = test_case.consume_bytes("Output");
+
+            // One-shot, and split at various points.
+            for split in &[0, 1, 127, 128, 129] {
+                let split = core::cmp::min(*split, input.len());
+                let mut ctx = Context::with_key(&key, output.len()).unwrap();
+                ctx.update(&input[..split]);
+                ctx.update(&input[split..]);
+                assert_eq!(ctx.finish().as_ref(), &output[..]);
+            }
+
+            if key.is_empty() && output.len() == MAX_OUTPUT_LEN {
+                assert_eq!(digest(&input).as_ref(), &output[..]);
+            }
+
+            // Only keyed outputs are MACs.
+            assert_eq!(verify(&key, &input, &output).is_ok(), !key.is_empty());
+            let mut bad_output = output.clone();
+            bad_output[0] ^= 1;
+            assert!(verify(&key, &input, &bad_output).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn blake2b_parameters_test() {
+        assert!(Context::new(0).is_err());
+        assert!(Context::new(MAX_OUTPUT_LEN + 1).is_err());
+        assert!(Context::with_key(&[0u8; MAX_KEY_LEN + 1], 64).is_err());
+        assert!(Context::with_key(&[0u8; MAX_KEY_LEN], 1).is_ok());
+    }
+}
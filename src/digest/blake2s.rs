@@ -0,0 +1,336 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! BLAKE2s, as specified in [RFC 7693].
+//!
+//! Unkeyed BLAKE2s with a 32-byte output is available as the digest algorithm
+//! `digest::BLAKE2S_256`, which also works with `hmac` and `hkdf`, as the
+//! Noise protocols with `BLAKE2s` in their names require. This module
+//! supports BLAKE2s's native keying and the other output lengths with its own
+//! `Context`. In keyed mode it is a MAC; that is how WireGuard uses it for its
+//! `MAC` function.
+//!
+//! [RFC 7693]: https://tools.ietf.org/html/rfc7693
+
+use {c, constant_time, error, init, polyfill};
+use core;
+use super::{MAX_OUTPUT_LEN as DIGEST_MAX_OUTPUT_LEN, State};
+
+/// The BLAKE2s block length.
+pub const BLOCK_LEN: usize = 64;
+
+/// The maximum (and default) BLAKE2s output length.
+pub const MAX_OUTPUT_LEN: usize = 32;
+
+/// The maximum length of a BLAKE2s key.
+pub const MAX_KEY_LEN: usize = 32;
+
+/// A context for multi-step (Init-Update-Finish) BLAKE2s calculations.
+///
+/// # Examples
+///
+/// ```
+/// use ring::digest::blake2s;
+///
+/// let one_shot = blake2s::digest(b"hello, world");
+///
+/// let mut ctx = blake2s::Context::new(blake2s::MAX_OUTPUT_LEN).unwrap();
+/// ctx.update(b"hello");
+/// ctx.update(b", world");
+/// let multi_part = ctx.finish();
+///
+/// assert_eq!(one_shot.as_ref(), multi_part.as_ref());
+/// ```
+#[derive(Clone)]
+pub struct Context {
+    h: [u32; 8],
+    t: u64,
+
+    // BLAKE2s flags the last block as such, so a full block is only processed
+    // once more input is known to follow it.
+    pending: [u8; BLOCK_LEN],
+    num_pending: usize,
+
+    output_len: usize,
+}
+
+impl Context {
+    /// Constructs a new unkeyed context. `output_len` must be in the range
+    /// [1, `MAX_OUTPUT_LEN`].
+    pub fn new(output_len: usize) -> Result<Context, error::InvalidLength> {
+        Context::with_key(&[], output_len)
+    }
+
+    /// Constructs a new keyed context. `key` must be at most `MAX_KEY_LEN`
+    /// bytes long, and `output_len` must be in the range
+    /// [1, `MAX_OUTPUT_LEN`]. An empty key is the same as no key. Fails with
+    /// `error::InvalidLength` otherwise.
+    pub fn with_key(key: &[u8], output_len: usize)
+                    -> Result<Context, error::InvalidLength> {
+        init::init_once();
+        if key.len() > MAX_KEY_LEN || output_len == 0 ||
+           output_len > MAX_OUTPUT_LEN {
+            return Err(error::InvalidLength);
+        }
+        let mut h = IV;
+        h[0] ^= 0x01010000 ^ ((key.len() as u32) << 8) ^ (output_len as u32);
+        let mut ctx = Context {
+            h: h,
+            t: 0,
+            pending: [0u8; BLOCK_LEN],
+            num_pending: 0,
+            output_len: output_len,
+        };
+        if !key.is_empty() {
+            ctx.pending[..key.len()].copy_from_slice(key);
+            ctx.num_pending = BLOCK_LEN;
+        }
+        Ok(ctx)
+    }
+
+    /// Updates the calculation with the given data.
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            if self.num_pending == BLOCK_LEN {
+                self.t = self.t.wrapping_add(BLOCK_LEN as u64);
+                let block = self.pending;
+                compress(&mut self.h, &block, self.t, false);
+                self.num_pending = 0;
+            }
+            let n = core::cmp::min(BLOCK_LEN - self.num_pending, data.len());
+            self.pending[self.num_pending..(self.num_pending + n)]
+                .copy_from_slice(&data[..n]);
+            self.num_pending += n;
+            data = &data[n..];
+        }
+    }
+
+    /// Finalizes the calculation and returns the output.
+    pub fn finish(mut self) -> Output {
+        let num_pending = polyfill::u64_from_usize(self.num_pending);
+        self.t = self.t.wrapping_add(num_pending);
+        for b in &mut self.pending[self.num_pending..] {
+            *b = 0;
+        }
+        let block = self.pending;
+        compress(&mut self.h, &block, self.t, true);
+
+        let mut output = Output {
+            value: [0u8; MAX_OUTPUT_LEN],
+            len: self.output_len,
+        };
+        for (out, h) in output.value.chunks_mut(4).zip(self.h.iter()) {
+            for (i, b) in out.iter_mut().enumerate() {
+                *b = (h >> (8 * i)) as u8;
+            }
+        }
+        output
+    }
+
+    /// The length of the output.
+    #[inline(always)]
+    pub fn output_len(&self) -> usize { self.output_len }
+}
+
+/// Returns the unkeyed, 32-byte BLAKE2s digest of `data`.
+pub fn digest(data: &[u8]) -> Output {
+    let mut ctx = Context::new(MAX_OUTPUT_LEN).unwrap();
+    ctx.update(data);
+    ctx.finish()
+}
+
+/// Verifies that `tag` is the keyed BLAKE2s MAC of `data` with the key `key`,
+/// with an output length of `tag.len()`. `key` must not be empty, since
+/// unkeyed BLAKE2s isn't a MAC.
+pub fn verify(key: &[u8], data: &[u8], tag: &[u8])
+              -> Result<(), error::Unspecified> {
+    if key.is_empty() {
+        return Err(error::Unspecified);
+    }
+    let mut ctx = try!(Context::with_key(key, tag.len())
+                           .map_err(|_| error::Unspecified));
+    ctx.update(data);
+    constant_time::verify_slices_are_equal(ctx.finish().as_ref(), tag)
+}
+
+/// A BLAKE2s output.
+#[derive(Clone, Copy)]
+pub struct Output {
+    value: [u8; MAX_OUTPUT_LEN],
+    len: usize,
+}
+
+impl AsRef<[u8]> for Output {
+    #[inline(always)]
+    fn as_ref(&self) -> &[u8] { &self.value[..self.len] }
+}
+
+// The glue for `digest::BLAKE2S_256`. The chaining value is in the first
+// eight 32-bit words of the state, and the number of bytes compressed so far
+// is in the fifth 64-bit word.
+
+const T_INDEX: usize = 4;
+
+/// Compresses `num` full blocks that are known not to be the last block.
+pub unsafe extern fn block_data_order(state: &mut State, data: *const u8,
+                                      num: c::size_t) {
+    let data = data as *const [u8; BLOCK_LEN];
+    let blocks = core::slice::from_raw_parts(data, num);
+    for block in blocks {
+        state[T_INDEX] = state[T_INDEX].wrapping_add(BLOCK_LEN as u64);
+        let t = state[T_INDEX];
+        compress(chaining_value(state), block, t, false);
+    }
+}
+
+/// Sets the byte counter for a state that has compressed `completed_blocks`
+/// blocks.
+pub fn set_counter(state: &mut State, completed_blocks: u64) {
+    state[T_INDEX] = completed_blocks.wrapping_mul(BLOCK_LEN as u64);
+}
+
+/// Compresses the last, `num_pending`-byte, block, which has been padded with
+/// zeros.
+pub fn finish(state: &mut State, block: &[u8], num_pending: usize) {
+    let t = state[T_INDEX].wrapping_add(polyfill::u64_from_usize(num_pending));
+    let block = slice_as_array_ref!(block, BLOCK_LEN).unwrap();
+    compress(chaining_value(state), block, t, true);
+}
+
+/// Returns the chaining value as the little-endian words of the output.
+pub fn format_output(input: &State) -> [u64; DIGEST_MAX_OUTPUT_LEN / 8] {
+    let h = &polyfill::slice::u64_as_u32(input)[..8];
+    [u32x2!(h[0].to_le(), h[1].to_le()),
+     u32x2!(h[2].to_le(), h[3].to_le()),
+     u32x2!(h[4].to_le(), h[5].to_le()),
+     u32x2!(h[6].to_le(), h[7].to_le()),
+     0,
+     0,
+     0,
+     0]
+}
+
+fn chaining_value(state: &mut State) -> &mut [u32; 8] {
+    let words = polyfill::slice::u64_as_u32_mut(&mut state[..4]);
+    slice_as_array_ref_mut!(words, 8).unwrap()
+}
+
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+    0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+// The compression function F.
+fn compress(h: &mut [u32; 8], block: &[u8; BLOCK_LEN], t: u64, last: bool) {
+    fn g(v: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, x: u32,
+         y: u32) {
+        v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+        v[d] = (v[d] ^ v[a]).rotate_right(16);
+        v[c] = v[c].wrapping_add(v[d]);
+        v[b] = (v[b] ^ v[c]).rotate_right(12);
+        v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+        v[d] = (v[d] ^ v[a]).rotate_right(8);
+        v[c] = v[c].wrapping_add(v[d]);
+        v[b] = (v[b] ^ v[c]).rotate_right(7);
+    }
+
+    let mut m = [0u32; 16];
+    for (m, bytes) in m.iter_mut().zip(block.chunks(4)) {
+        *m = polyfill::slice::u32_from_le_u8(
+            slice_as_array_ref!(bytes, 4).unwrap());
+    }
+
+    let mut v = [0u32; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&IV);
+    v[12] ^= t as u32;
+    v[13] ^= (t >> 32) as u32;
+    if last {
+        v[14] = !v[14];
+    }
+
+    for s in SIGMA.iter() {
+        g(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+        g(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+        g(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+        g(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+        g(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+        g(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+        g(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+        g(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i + 8];
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use test;
+    use super::*;
+
+    #[test]
+    fn blake2s_test() {
+        test::from_file("src/digest/blake2s_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let key = test_case.consume_bytes("Key");
+            let input = test_case.consume_bytes("Input");
+            let output = test_case.consume_bytes("Output");
+
+            // One-shot, and split at various points.
+            for split in &[0, 1, 63, 64, 65] {
+                let split = core::cmp::min(*split, input.len());
+                let mut ctx = Context::with_key(&key, output.len()).unwrap();
+                ctx.update(&input[..split]);
+                ctx.update(&input[split..]);
+                assert_eq!(ctx.finish().as_ref(), &output[..]);
+            }
+
+            if key.is_empty() && output.len() == MAX_OUTPUT_LEN {
+                assert_eq!(digest(&input).as_ref(), &output[..]);
+            }
+
+            // Only keyed outputs are MACs.
+            assert_eq!(verify(&key, &input, &output).is_ok(), !key.is_empty());
+            let mut bad_output = output.clone();
+            bad_output[0] ^= 1;
+            assert!(verify(&key, &input, &bad_output).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn blake2s_parameters_test() {
+        assert!(Context::new(0).is_err());
+        assert!(Context::new(MAX_OUTPUT_LEN + 1).is_err());
+        assert!(Context::with_key(&[0u8; MAX_KEY_LEN + 1], 32).is_err());
+        assert!(Context::with_key(&[0u8; MAX_KEY_LEN], 1).is_ok());
+    }
+}
@@ -12,11 +12,15 @@
 // OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
 // CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 
-//! SHA-2 and the legacy SHA-1 digest algorithm.
+//! SHA-2, SHA-3, BLAKE2b, BLAKE2s, and the legacy SHA-1 digest algorithm.
+//! Keyed BLAKE2b is in the `blake2b` submodule, keyed BLAKE2s is in the
+//! `blake2s` submodule, and the SHAKE extendable-output functions are in the
+//! `shake` submodule.
 //!
 //! If all the data is available in a single contiguous slice then the `digest`
 //! function should be used. Otherwise, the digest can be calculated in
-//! multiple steps using `Context`.
+//! multiple steps using `Context`. The `multi` submodule digests many
+//! independent inputs at once.
 
 // Note on why are we doing things the hard way: It would be easy to implement
 // this using the C `EVP_MD`/`EVP_MD_CTX` interface. However, if we were to do
@@ -24,19 +28,49 @@
 // The goal for this implementation is to drive the overhead as close to zero
 // as possible.
 
-use {c, init, polyfill};
+use {c, error, init, kat, polyfill};
+use secret::Zeroize;
+use untrusted;
 use core;
 
 // XXX: Replace with `const fn` when `const fn` is stable:
 // https://github.com/rust-lang/rust/issues/24111
+//
+// The 32-bit algorithms use the state as an array of `u32`, so `$first` must
+// end up in the lower-addressed half of the `u64`.
 #[cfg(target_endian = "little")]
 macro_rules! u32x2 {
     ( $first:expr, $second:expr ) =>
     ( ((($second as u64) << 32) | ($first as u64)) )
 }
 
+#[cfg(target_endian = "big")]
+macro_rules! u32x2 {
+    ( $first:expr, $second:expr ) =>
+    ( ((($first as u64) << 32) | ($second as u64)) )
+}
+
+pub mod blake2b;
+pub mod blake2s;
+
+#[cfg(feature = "use_heap")]
+pub mod multi;
+
 mod sha1;
 
+// Keep the `cfg` conditions in sync with the ones below.
+#[cfg(any(test, not(any(target_arch = "aarch64", target_arch = "arm",
+                        target_arch = "x86", target_arch = "x86_64"))))]
+mod sha2;
+
+mod sha3;
+pub mod shake;
+
+// The state of the compression function or permutation. It is large enough
+// for the Keccak-f[1600] state of SHA-3; the other algorithms only use the
+// first `MAX_CHAINING_LEN / 8` words.
+type State = [u64; sha3::STATE_LANES];
+
 /// A context for multi-step (Init-Update-Finish) digest calculations.
 ///
 /// C analog: `EVP_MD_CTX`.
@@ -58,7 +92,7 @@ mod sha1;
 /// ```
 pub struct Context {
     // We use u64 to try to ensure 64-bit alignment/padding.
-    state: [u64; MAX_CHAINING_LEN / 8],
+    state: State,
 
     // Note that SHA-512 has a 128-bit input bit counter, but this
     // implementation only supports up to 2^64-1 input bits for all algorithms,
@@ -82,7 +116,7 @@ impl Context {
 
         Context {
             algorithm: algorithm,
-            state: algorithm.initial_state,
+            state: algorithm.state(),
             completed_data_blocks: 0,
             pending: [0u8; MAX_BLOCK_LEN],
             num_pending: 0,
@@ -95,7 +129,15 @@ impl Context {
     ///
     /// C analog: `EVP_DigestUpdate`
     pub fn update(&mut self, data: &[u8]) {
-        if data.len() < self.algorithm.block_len - self.num_pending {
+        // BLAKE2b flags the last block as such, so for BLAKE2b a full block
+        // is only processed once more input is known to follow it.
+        let hold_back = match self.algorithm.padding {
+            Padding::Blake2b | Padding::Blake2s => 1,
+            _ => 0,
+        };
+
+        if data.len() <
+               self.algorithm.block_len - self.num_pending + hold_back {
             self.pending[self.num_pending..(self.num_pending + data.len())]
                 .copy_from_slice(data);
             self.num_pending += data.len();
@@ -119,8 +161,13 @@ impl Context {
             self.num_pending = 0;
         }
 
-        let num_blocks = remaining.len() / self.algorithm.block_len;
-        let num_to_save_for_later = remaining.len() % self.algorithm.block_len;
+        let mut num_blocks = remaining.len() / self.algorithm.block_len;
+        let mut num_to_save_for_later =
+            remaining.len() % self.algorithm.block_len;
+        if hold_back != 0 && num_blocks > 0 && num_to_save_for_later == 0 {
+            num_blocks -= 1;
+            num_to_save_for_later = self.algorithm.block_len;
+        }
         if num_blocks > 0 {
             unsafe {
                 (self.algorithm.block_data_order)(&mut self.state,
@@ -146,7 +193,43 @@ impl Context {
     /// C analogs: `EVP_DigestFinal`, `EVP_DigestFinal_ex`
     pub fn finish(mut self) -> Digest {
         // We know |num_pending < self.algorithm.block_len|, because we would
-        // have processed the block otherwise.
+        // have processed the block otherwise, except for BLAKE2b and BLAKE2s,
+        // which hold back the last full block.
+
+        let blake2_finish: Option<fn(&mut State, &[u8], usize)> =
+            match self.algorithm.padding {
+                Padding::Blake2b => Some(blake2b::finish),
+                Padding::Blake2s => Some(blake2s::finish),
+                _ => None,
+            };
+        if let Some(blake2_finish) = blake2_finish {
+            polyfill::slice::fill(
+                &mut self.pending[self.num_pending..self.algorithm.block_len],
+                0);
+            blake2_finish(&mut self.state,
+                          &self.pending[..self.algorithm.block_len],
+                          self.num_pending);
+            return Digest {
+                algorithm: self.algorithm,
+                value: (self.algorithm.format_output)(&self.state),
+            };
+        }
+
+        if let Padding::Sha3 = self.algorithm.padding {
+            let block_len = self.algorithm.block_len;
+            polyfill::slice::fill(
+                &mut self.pending[self.num_pending..block_len], 0);
+            self.pending[self.num_pending] = sha3::SHA3_DOMAIN;
+            self.pending[block_len - 1] |= 0x80;
+            unsafe {
+                (self.algorithm.block_data_order)(&mut self.state,
+                                                  self.pending.as_ptr(), 1);
+            }
+            return Digest {
+                algorithm: self.algorithm,
+                value: (self.algorithm.format_output)(&self.state),
+            };
+        }
 
         let mut padding_pos = self.num_pending;
         self.pending[padding_pos] = 0x80;
@@ -190,6 +273,132 @@ impl Context {
         }
     }
 
+    /// Like `finish`, but writes the digest value into the first
+    /// `self.algorithm().output_len` bytes of `out` and returns its length,
+    /// instead of returning a `Digest`. Fails with `error::InvalidLength` if
+    /// `out` is too short, in which case `out` is left unmodified.
+    pub fn finish_into(self, out: &mut [u8])
+                       -> Result<usize, error::InvalidLength> {
+        let output_len = self.algorithm.output_len;
+        if out.len() < output_len {
+            return Err(error::InvalidLength);
+        }
+        out[..output_len].copy_from_slice(self.finish().as_ref());
+        Ok(output_len)
+    }
+
+    /// Resets the context to the state of a newly-constructed context for
+    /// the same algorithm, discarding any data passed to `update`, so that the
+    /// context can be reused to calculate the digest of another message.
+    ///
+    /// C analog: `EVP_DigestInit_ex` with the context's current algorithm.
+    pub fn reset(&mut self) {
+        self.state = self.algorithm.state();
+        self.completed_data_blocks = 0;
+        self.num_pending = 0;
+    }
+
+    /// Returns the state of the context: the state of the digest function,
+    /// the length of the data passed to `update` so far, and the data that
+    /// hasn't been processed yet. `Context::from_state` reconstructs the
+    /// context from it, e.g. in another process, so that a long calculation
+    /// can be checkpointed and resumed.
+    ///
+    /// The state contains up to one block of the data in the clear, and
+    /// anybody who has the state can calculate the digest of any data that
+    /// starts with the data passed to `update` so far. Protect it like the
+    /// data itself.
+    pub fn clone_state(&self) -> SavedState {
+        let mut saved = SavedState {
+            value: [0u8; MAX_SAVED_STATE_LEN],
+            len: 0,
+        };
+        {
+            let mut out = Writer {
+                out: &mut saved.value,
+                len: 0,
+            };
+            let name = self.algorithm.name.as_bytes();
+            debug_assert!(name.len() <= MAX_NAME_LEN);
+            out.write_byte(name.len() as u8);
+            out.write(name);
+            out.write_u64(self.completed_data_blocks);
+            out.write_byte(self.num_pending as u8);
+            out.write(&self.pending[..self.num_pending]);
+            if self.algorithm.has_32_bit_words() {
+                let words = polyfill::slice::u64_as_u32(&self.state);
+                for word in &words[..self.algorithm.num_state_words()] {
+                    out.write(&polyfill::slice::be_u8_from_u32(*word));
+                }
+            } else {
+                for word in &self.state[..self.algorithm.num_state_words()] {
+                    out.write_u64(*word);
+                }
+            }
+            saved.len = out.len;
+        }
+        saved
+    }
+
+    /// Reconstructs a context for `algorithm` from `state`, which was
+    /// returned by `clone_state`. Fails if `state` isn't the state of a
+    /// context for `algorithm`.
+    pub fn from_state(algorithm: &'static Algorithm, state: untrusted::Input)
+                      -> Result<Context, error::Unspecified> {
+        init::init_once();
+
+        state.read_all(error::Unspecified, |input| {
+            let name_len = try!(input.read_byte()) as usize;
+            let name = try!(input.skip_and_get_input(name_len));
+            if name.as_slice_less_safe() != algorithm.name.as_bytes() {
+                return Err(error::Unspecified);
+            }
+            let mut ctx = Context::new(algorithm);
+            ctx.completed_data_blocks = try!(read_u64(input));
+
+            // BLAKE2b and BLAKE2s hold back the last full block; see
+            // `update`.
+            let max_pending = match algorithm.padding {
+                Padding::Blake2b | Padding::Blake2s => algorithm.block_len,
+                _ => algorithm.block_len - 1,
+            };
+            let num_pending = try!(input.read_byte()) as usize;
+            if num_pending > max_pending {
+                return Err(error::Unspecified);
+            }
+            let pending = try!(input.skip_and_get_input(num_pending));
+            ctx.pending[..num_pending]
+                .copy_from_slice(pending.as_slice_less_safe());
+            ctx.num_pending = num_pending;
+
+            let num_words = algorithm.num_state_words();
+            ctx.state = [0u64; sha3::STATE_LANES];
+            if algorithm.has_32_bit_words() {
+                let words = polyfill::slice::u64_as_u32_mut(&mut ctx.state);
+                for word in &mut words[..num_words] {
+                    let bytes = try!(input.skip_and_get_input(4));
+                    let bytes = slice_as_array_ref!(
+                        bytes.as_slice_less_safe(), 4).unwrap();
+                    *word = polyfill::slice::u32_from_be_u8(bytes);
+                }
+            } else {
+                for word in &mut ctx.state[..num_words] {
+                    *word = try!(read_u64(input));
+                }
+            }
+            match algorithm.padding {
+                Padding::Blake2b =>
+                    blake2b::set_counter(&mut ctx.state,
+                                         ctx.completed_data_blocks),
+                Padding::Blake2s =>
+                    blake2s::set_counter(&mut ctx.state,
+                                         ctx.completed_data_blocks),
+                _ => (),
+            }
+            Ok(ctx)
+        })
+    }
+
     /// The algorithm that this context is using.
     #[inline(always)]
     pub fn algorithm(&self) -> &'static Algorithm { self.algorithm }
@@ -209,6 +418,67 @@ impl Clone for Context {
     }
 }
 
+/// Zeroizes the state and the buffered input, e.g. when the context was
+/// keyed with a secret as in `hmac::SigningKey`. The context must not be used
+/// afterwards.
+impl Zeroize for Context {
+    fn zeroize(&mut self) {
+        self.state[..].zeroize();
+        self.pending[..].zeroize();
+    }
+}
+
+/// The state of a `Context`, returned by `Context::clone_state`.
+///
+/// Use `as_ref` to get the state as a `&[u8]`.
+pub struct SavedState {
+    value: [u8; MAX_SAVED_STATE_LEN],
+    len: usize,
+}
+
+impl AsRef<[u8]> for SavedState {
+    #[inline(always)]
+    fn as_ref(&self) -> &[u8] { &self.value[..self.len] }
+}
+
+// The maximum length of `Algorithm::name`.
+const MAX_NAME_LEN: usize = 16;
+
+// The maximum length of a `SavedState`: the length-prefixed name, the number
+// of completed blocks, the length-prefixed pending data, and the state of the
+// digest function, which is largest for SHA-3.
+const MAX_SAVED_STATE_LEN: usize =
+    1 + MAX_NAME_LEN + 8 + 1 + MAX_BLOCK_LEN + (sha3::STATE_LANES * 8);
+
+struct Writer<'a> {
+    out: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Writer<'a> {
+    fn write(&mut self, bytes: &[u8]) {
+        self.out[self.len..][..bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    fn write_byte(&mut self, byte: u8) { self.write(&[byte]) }
+
+    fn write_u64(&mut self, value: u64) {
+        let hi = polyfill::slice::be_u8_from_u32((value >> 32) as u32);
+        let lo = polyfill::slice::be_u8_from_u32(value as u32);
+        self.write(&hi);
+        self.write(&lo);
+    }
+}
+
+fn read_u64(input: &mut untrusted::Reader) -> Result<u64, error::Unspecified> {
+    let mut value = 0u64;
+    for _ in 0..8 {
+        value = (value << 8) | (try!(input.read_byte()) as u64);
+    }
+    Ok(value)
+}
+
 /// Returns the digest of `data` using the given digest algorithm.
 ///
 /// C analog: `EVP_Digest`
@@ -277,11 +547,11 @@ pub struct Algorithm {
     pub output_len: usize,
 
     /// The size of the chaining value of the digest function, in bytes. For
-    /// non-truncated algorithms (SHA-1, SHA-256, SHA-512), this is equal to
-    /// `output_len`. For truncated algorithms (e.g. SHA-384, SHA-512/256),
-    /// this is equal to the length before truncation. This is mostly helpful
-    /// for determining the size of an HMAC key that is appropriate for the
-    /// digest algorithm.
+    /// non-truncated algorithms (SHA-1, SHA-256, SHA-512) and for SHA-3, this
+    /// is equal to `output_len`. For truncated algorithms (e.g. SHA-384,
+    /// SHA-512/256), this is equal to the length before truncation. This is
+    /// mostly helpful for determining the size of an HMAC key that is
+    /// appropriate for the digest algorithm.
     pub chaining_len: usize,
 
     /// C analog: `EVP_MD_block_size`
@@ -290,16 +560,111 @@ pub struct Algorithm {
     /// The length of the length in the padding.
     len_len: usize,
 
-    block_data_order: unsafe extern fn(state: &mut [u64; MAX_CHAINING_LEN / 8],
-                                       data: *const u8, num: c::size_t),
-    format_output: fn(input: &[u64; MAX_CHAINING_LEN / 8])
-                      -> [u64; MAX_OUTPUT_LEN / 8],
+    padding: Padding,
+
+    name: &'static str,
+    security_bits: usize,
+
+    block_data_order: unsafe extern fn(state: &mut State, data: *const u8,
+                                       num: c::size_t),
+    format_output: fn(input: &State) -> [u64; MAX_OUTPUT_LEN / 8],
 
     initial_state: [u64; MAX_CHAINING_LEN / 8],
 }
 
+impl Algorithm {
+    /// The name of the algorithm, which is the name of its static in this
+    /// module, e.g. `"SHA256"` for `SHA256`.
+    #[inline(always)]
+    pub fn name(&self) -> &'static str { self.name }
+
+    /// Returns the algorithm whose `name()` is `name`, e.g. `SHA256` for
+    /// `"SHA256"`. The comparison is case-sensitive.
+    pub fn from_name(name: &str)
+                     -> Result<&'static Algorithm, error::Unspecified> {
+        ALGORITHMS.iter()
+            .map(|&alg| alg)
+            .find(|alg| alg.name == name)
+            .ok_or(error::Unspecified)
+    }
+
+    /// The collision resistance of the algorithm, in bits, as given in
+    /// [NIST SP 800-107 Rev. 1] Table 1. SHA-1 is broken and its value is the
+    /// cost of the [SHAttered] collision attack; see also NIST's guidance
+    /// that it not be used for collision-resistant applications.
+    ///
+    /// [NIST SP 800-107 Rev. 1]:
+    ///     http://csrc.nist.gov/publications/nistpubs/800-107-rev1/sp800-107-rev1.pdf
+    /// [SHAttered]: https://shattered.io/
+    #[inline(always)]
+    pub fn security_bits(&self) -> usize { self.security_bits }
+
+    // Whether the state of the digest function is an array of 32-bit words,
+    // as for SHA-1, SHA-256, and BLAKE2s, instead of 64-bit words.
+    fn has_32_bit_words(&self) -> bool {
+        match self.padding {
+            Padding::MerkleDamgard => self.len_len == 64 / 8,
+            Padding::Blake2s => true,
+            _ => false,
+        }
+    }
+
+    // The number of words of the state of the digest function that
+    // `Context::clone_state` saves. The BLAKE2b and BLAKE2s counters aren't
+    // saved because they are determined by the number of completed blocks.
+    fn num_state_words(&self) -> usize {
+        match self.padding {
+            Padding::MerkleDamgard if self.has_32_bit_words() =>
+                self.chaining_len / 4,
+            Padding::MerkleDamgard => self.chaining_len / 8,
+            Padding::Sha3 => sha3::STATE_LANES,
+            Padding::Blake2b => self.chaining_len / 8,
+            Padding::Blake2s => self.chaining_len / 4,
+        }
+    }
+
+    fn state(&self) -> State {
+        let mut state = [0u64; sha3::STATE_LANES];
+        state[..(MAX_CHAINING_LEN / 8)].copy_from_slice(&self.initial_state);
+        state
+    }
+}
+
+// How the final block is padded.
+enum Padding {
+    // The Merkle–Damgård strengthening of SHA-1 and SHA-2: a one bit, zeros,
+    // and the length of the input in bits.
+    MerkleDamgard,
+
+    // The domain separation bits of SHA-3 followed by the pad10*1 padding of
+    // the sponge construction.
+    Sha3,
+
+    // Zeros, with the last block flagged as such in the compression function,
+    // as in BLAKE2b.
+    Blake2b,
+
+    // The same as `Blake2b`, for BLAKE2s, whose state is made of 32-bit words.
+    Blake2s,
+}
+
 impl core::fmt::Debug for Algorithm {
     fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self.padding {
+            Padding::Sha3 => {
+                return write!(fmt, "SHA3-{:?}", self.output_len * 8);
+            },
+            Padding::Blake2b => {
+                return write!(fmt, "BLAKE2b-{:?}", self.output_len * 8);
+            },
+            Padding::Blake2s => {
+                return write!(fmt, "BLAKE2s-{:?}", self.output_len * 8);
+            },
+            Padding::MerkleDamgard => (),
+        }
+        if self.chaining_len == 512 / 8 && self.output_len < 384 / 8 {
+            return write!(fmt, "SHA-512/{:?}", self.output_len * 8);
+        }
         // This would have to change if/when we add other algorithms with the
         // same output lengths.
         let n = if self.output_len == 20 {
@@ -311,6 +676,14 @@ impl core::fmt::Debug for Algorithm {
     }
 }
 
+/// Formats the algorithm as its `name()`, which `Algorithm::from_name`
+/// accepts.
+impl core::fmt::Display for Algorithm {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+        fmt.write_str(self.name)
+    }
+}
+
 /// SHA-1 as specified in [FIPS 180-4]. Deprecated.
 ///
 /// [FIPS 180-4]: http://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.180-4.pdf
@@ -319,6 +692,9 @@ pub static SHA1: Algorithm = Algorithm {
     chaining_len: sha1::CHAINING_LEN,
     block_len: sha1::BLOCK_LEN,
     len_len: 64 / 8,
+    padding: Padding::MerkleDamgard,
+    name: "SHA1",
+    security_bits: 63,
     block_data_order: sha1::block_data_order,
     format_output: sha256_format_output,
     initial_state: [
@@ -337,7 +713,10 @@ pub static SHA256: Algorithm = Algorithm {
     chaining_len: 256 / 8,
     block_len: 512 / 8,
     len_len: 64 / 8,
-    block_data_order: GFp_sha256_block_data_order,
+    padding: Padding::MerkleDamgard,
+    name: "SHA256",
+    security_bits: 128,
+    block_data_order: sha256_block_data_order,
     format_output: sha256_format_output,
     initial_state: [
         u32x2!(0x6a09e667u32, 0xbb67ae85u32),
@@ -356,7 +735,10 @@ pub static SHA384: Algorithm = Algorithm {
     chaining_len: 512 / 8,
     block_len: 1024 / 8,
     len_len: 128 / 8,
-    block_data_order: GFp_sha512_block_data_order,
+    padding: Padding::MerkleDamgard,
+    name: "SHA384",
+    security_bits: 192,
+    block_data_order: sha512_block_data_order,
     format_output: sha512_format_output,
     initial_state: [
         0xcbbb9d5dc1059ed8,
@@ -378,7 +760,10 @@ pub static SHA512: Algorithm = Algorithm {
     chaining_len: 512 / 8,
     block_len: 1024 / 8,
     len_len: 128 / 8,
-    block_data_order: GFp_sha512_block_data_order,
+    padding: Padding::MerkleDamgard,
+    name: "SHA512",
+    security_bits: 256,
+    block_data_order: sha512_block_data_order,
     format_output: sha512_format_output,
     initial_state: [
         0x6a09e667f3bcc908,
@@ -392,12 +777,205 @@ pub static SHA512: Algorithm = Algorithm {
     ],
 };
 
+/// SHA-512/256 as specified in [FIPS 180-4].
+///
+/// This is SHA-512 with a distinct initial hash value, truncated to 256 bits.
+/// It is generally faster than SHA-256 on 64-bit targets.
+///
+/// [FIPS 180-4]: http://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.180-4.pdf
+pub static SHA512_256: Algorithm = Algorithm {
+    output_len: 256 / 8,
+    chaining_len: 512 / 8,
+    block_len: 1024 / 8,
+    len_len: 128 / 8,
+    padding: Padding::MerkleDamgard,
+    name: "SHA512_256",
+    security_bits: 128,
+    block_data_order: sha512_block_data_order,
+    format_output: sha512_format_output,
+    initial_state: [
+        0x22312194fc2bf72c,
+        0x9f555fa3c84c64c2,
+        0x2393b86b6f53b151,
+        0x963877195940eabd,
+        0x96283ee2a88effe3,
+        0xbe5e1e2553863992,
+        0x2b0199fc2c85b8aa,
+        0x0eb72ddc81c52ca2,
+    ],
+};
+
+/// SHA-512/224 as specified in [FIPS 180-4].
+///
+/// This is SHA-512 with a distinct initial hash value, truncated to 224 bits.
+///
+/// [FIPS 180-4]: http://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.180-4.pdf
+pub static SHA512_224: Algorithm = Algorithm {
+    output_len: 224 / 8,
+    chaining_len: 512 / 8,
+    block_len: 1024 / 8,
+    len_len: 128 / 8,
+    padding: Padding::MerkleDamgard,
+    name: "SHA512_224",
+    security_bits: 112,
+    block_data_order: sha512_block_data_order,
+    format_output: sha512_format_output,
+    initial_state: [
+        0x8c3d37c819544da2,
+        0x73e1996689dcd4d6,
+        0x1dfab7ae32ff9c82,
+        0x679dd514582f9fcf,
+        0x0f6d2b697bd44da8,
+        0x77e36f7304c48942,
+        0x3f9d85a86a1d36c8,
+        0x1112e6ad91d692a1,
+    ],
+};
+
+/// SHA3-256 as specified in [FIPS 202].
+///
+/// [FIPS 202]: http://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.202.pdf
+pub static SHA3_256: Algorithm = Algorithm {
+    output_len: 256 / 8,
+    chaining_len: 256 / 8,
+    block_len: sha3::SHA3_256_RATE,
+    len_len: 0,
+    padding: Padding::Sha3,
+    name: "SHA3_256",
+    security_bits: 128,
+    block_data_order: sha3::sha3_256_block_data_order,
+    format_output: sha3::format_output,
+    initial_state: [0; MAX_CHAINING_LEN / 8],
+};
+
+/// SHA3-384 as specified in [FIPS 202].
+///
+/// [FIPS 202]: http://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.202.pdf
+pub static SHA3_384: Algorithm = Algorithm {
+    output_len: 384 / 8,
+    chaining_len: 384 / 8,
+    block_len: sha3::SHA3_384_RATE,
+    len_len: 0,
+    padding: Padding::Sha3,
+    name: "SHA3_384",
+    security_bits: 192,
+    block_data_order: sha3::sha3_384_block_data_order,
+    format_output: sha3::format_output,
+    initial_state: [0; MAX_CHAINING_LEN / 8],
+};
+
+/// SHA3-512 as specified in [FIPS 202].
+///
+/// [FIPS 202]: http://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.202.pdf
+pub static SHA3_512: Algorithm = Algorithm {
+    output_len: 512 / 8,
+    chaining_len: 512 / 8,
+    block_len: sha3::SHA3_512_RATE,
+    len_len: 0,
+    padding: Padding::Sha3,
+    name: "SHA3_512",
+    security_bits: 256,
+    block_data_order: sha3::sha3_512_block_data_order,
+    format_output: sha3::format_output,
+    initial_state: [0; MAX_CHAINING_LEN / 8],
+};
+
+/// BLAKE2b-512, i.e. unkeyed BLAKE2b with a 64-byte output, as specified in
+/// [RFC 7693].
+///
+/// [RFC 7693]: https://tools.ietf.org/html/rfc7693
+pub static BLAKE2B_512: Algorithm = Algorithm {
+    output_len: 512 / 8,
+    chaining_len: 512 / 8,
+    block_len: blake2b::BLOCK_LEN,
+    len_len: 0,
+    padding: Padding::Blake2b,
+    name: "BLAKE2B_512",
+    security_bits: 256,
+    block_data_order: blake2b::block_data_order,
+    format_output: blake2b::format_output,
+    initial_state: [
+        0x6a09e667f3bcc908 ^ 0x01010040,
+        0xbb67ae8584caa73b,
+        0x3c6ef372fe94f82b,
+        0xa54ff53a5f1d36f1,
+        0x510e527fade682d1,
+        0x9b05688c2b3e6c1f,
+        0x1f83d9abfb41bd6b,
+        0x5be0cd19137e2179,
+    ],
+};
+
+/// BLAKE2b-256, i.e. unkeyed BLAKE2b with a 32-byte output, as specified in
+/// [RFC 7693].
+///
+/// [RFC 7693]: https://tools.ietf.org/html/rfc7693
+pub static BLAKE2B_256: Algorithm = Algorithm {
+    output_len: 256 / 8,
+    chaining_len: 512 / 8,
+    block_len: blake2b::BLOCK_LEN,
+    len_len: 0,
+    padding: Padding::Blake2b,
+    name: "BLAKE2B_256",
+    security_bits: 128,
+    block_data_order: blake2b::block_data_order,
+    format_output: blake2b::format_output,
+    initial_state: [
+        0x6a09e667f3bcc908 ^ 0x01010020,
+        0xbb67ae8584caa73b,
+        0x3c6ef372fe94f82b,
+        0xa54ff53a5f1d36f1,
+        0x510e527fade682d1,
+        0x9b05688c2b3e6c1f,
+        0x1f83d9abfb41bd6b,
+        0x5be0cd19137e2179,
+    ],
+};
+
+/// BLAKE2s-256, i.e. unkeyed BLAKE2s with a 32-byte output, as specified in
+/// [RFC 7693].
+///
+/// [RFC 7693]: https://tools.ietf.org/html/rfc7693
+pub static BLAKE2S_256: Algorithm = Algorithm {
+    output_len: 256 / 8,
+    chaining_len: 256 / 8,
+    block_len: blake2s::BLOCK_LEN,
+    len_len: 0,
+    padding: Padding::Blake2s,
+    name: "BLAKE2S_256",
+    security_bits: 128,
+    block_data_order: blake2s::block_data_order,
+    format_output: blake2s::format_output,
+    initial_state: [
+        u32x2!(0x6a09e667u32 ^ 0x01010020u32, 0xbb67ae85u32),
+        u32x2!(0x3c6ef372u32, 0xa54ff53au32),
+        u32x2!(0x510e527fu32, 0x9b05688cu32),
+        u32x2!(0x1f83d9abu32, 0x5be0cd19u32),
+        0, 0, 0, 0,
+    ],
+};
+
+static ALGORITHMS: [&'static Algorithm; 12] = [
+    &SHA1,
+    &SHA256,
+    &SHA384,
+    &SHA512,
+    &SHA512_256,
+    &SHA512_224,
+    &SHA3_256,
+    &SHA3_384,
+    &SHA3_512,
+    &BLAKE2B_512,
+    &BLAKE2B_256,
+    &BLAKE2S_256,
+];
+
 #[inline(always)]
 fn widen_u64(x: usize) -> u64 { x as u64 }
 
 /// The maximum block length (`Algorithm::block_len`) of all the algorithms in
-/// this module.
-pub const MAX_BLOCK_LEN: usize = 1024 / 8;
+/// this module, which is the rate of SHA3-256.
+pub const MAX_BLOCK_LEN: usize = 1088 / 8;
 
 /// The maximum output length (`Algorithm::output_len`) of all the algorithms
 /// in this module.
@@ -407,8 +985,7 @@ pub const MAX_OUTPUT_LEN: usize = 512 / 8;
 /// algorithms in this module.
 pub const MAX_CHAINING_LEN: usize = MAX_OUTPUT_LEN;
 
-fn sha256_format_output(input: &[u64; MAX_CHAINING_LEN / 8])
-                        -> [u64; MAX_OUTPUT_LEN / 8] {
+fn sha256_format_output(input: &State) -> [u64; MAX_OUTPUT_LEN / 8] {
     let in32 = &polyfill::slice::u64_as_u32(input)[..8];
     [u32x2!(in32[0].to_be(), in32[1].to_be()),
      u32x2!(in32[2].to_be(), in32[3].to_be()),
@@ -420,8 +997,7 @@ fn sha256_format_output(input: &[u64; MAX_CHAINING_LEN / 8])
      0]
 }
 
-fn sha512_format_output(input: &[u64; MAX_CHAINING_LEN / 8])
-                        -> [u64; MAX_OUTPUT_LEN / 8] {
+fn sha512_format_output(input: &State) -> [u64; MAX_OUTPUT_LEN / 8] {
     [input[0].to_be(),
      input[1].to_be(),
      input[2].to_be(),
@@ -462,29 +1038,125 @@ pub extern fn GFp_SHA512_4(out: *mut u8, out_len: c::size_t,
     out.copy_from_slice(digest);
 }
 
+#[cfg(any(target_arch = "aarch64", target_arch = "arm", target_arch = "x86",
+          target_arch = "x86_64"))]
+use self::{GFp_sha256_block_data_order as sha256_block_data_order,
+           GFp_sha512_block_data_order as sha512_block_data_order};
+
+#[cfg(not(any(target_arch = "aarch64", target_arch = "arm",
+              target_arch = "x86", target_arch = "x86_64")))]
+use self::sha2::{sha256_block_data_order, sha512_block_data_order};
+
+#[cfg(any(target_arch = "aarch64", target_arch = "arm", target_arch = "x86",
+          target_arch = "x86_64"))]
 extern {
-    fn GFp_sha256_block_data_order(state: &mut [u64; MAX_CHAINING_LEN / 8],
-                                   data: *const u8, num: c::size_t);
-    fn GFp_sha512_block_data_order(state: &mut [u64; MAX_CHAINING_LEN / 8],
-                                   data: *const u8, num: c::size_t);
+    fn GFp_sha256_block_data_order(state: &mut State, data: *const u8,
+                                   num: c::size_t);
+    fn GFp_sha512_block_data_order(state: &mut State, data: *const u8,
+                                   num: c::size_t);
 }
 
 #[cfg(test)]
 pub mod test_util {
     use super::super::digest;
 
-    pub static ALL_ALGORITHMS: [&'static digest::Algorithm; 4] = [
+    pub static ALL_ALGORITHMS: [&'static digest::Algorithm; 12] = [
         &digest::SHA1,
         &digest::SHA256,
         &digest::SHA384,
         &digest::SHA512,
+        &digest::SHA512_256,
+        &digest::SHA512_224,
+        &digest::SHA3_256,
+        &digest::SHA3_384,
+        &digest::SHA3_512,
+        &digest::BLAKE2B_512,
+        &digest::BLAKE2B_256,
+        &digest::BLAKE2S_256,
     ];
 }
 
+/// Runs the known-answer tests of `SHA1`, `SHA256`, `SHA384`, `SHA512`,
+/// `SHA3_256`, and `BLAKE2B_512`, which digest `"abc"`; see `kat`.
+pub fn known_answer_tests() -> [kat::TestResult; 6] {
+    fn test(alg: &'static Algorithm, expected: &[u8]) -> kat::TestResult {
+        kat::TestResult {
+            name: alg.name,
+            passed: digest(alg, b"abc").as_ref() == expected,
+        }
+    }
+    [
+        test(&SHA1, &KAT_SHA1),
+        test(&SHA256, &KAT_SHA256),
+        test(&SHA384, &KAT_SHA384),
+        test(&SHA512, &KAT_SHA512),
+        test(&SHA3_256, &KAT_SHA3_256),
+        test(&BLAKE2B_512, &KAT_BLAKE2B_512),
+    ]
+}
+
+// FIPS 180-4 Appendix A, FIPS 202's example values, and RFC 7693 Appendix A.
+const KAT_SHA1: [u8; 20] = [
+    0xa9, 0x99, 0x3e, 0x36, 0x47, 0x06, 0x81, 0x6a, 0xba, 0x3e, 0x25, 0x71,
+    0x78, 0x50, 0xc2, 0x6c, 0x9c, 0xd0, 0xd8, 0x9d,
+];
+
+const KAT_SHA256: [u8; 32] = [
+    0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde,
+    0x5d, 0xae, 0x22, 0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c,
+    0xb4, 0x10, 0xff, 0x61, 0xf2, 0x00, 0x15, 0xad,
+];
+
+const KAT_SHA384: [u8; 48] = [
+    0xcb, 0x00, 0x75, 0x3f, 0x45, 0xa3, 0x5e, 0x8b, 0xb5, 0xa0, 0x3d, 0x69,
+    0x9a, 0xc6, 0x50, 0x07, 0x27, 0x2c, 0x32, 0xab, 0x0e, 0xde, 0xd1, 0x63,
+    0x1a, 0x8b, 0x60, 0x5a, 0x43, 0xff, 0x5b, 0xed, 0x80, 0x86, 0x07, 0x2b,
+    0xa1, 0xe7, 0xcc, 0x23, 0x58, 0xba, 0xec, 0xa1, 0x34, 0xc8, 0x25, 0xa7,
+];
+
+const KAT_SHA512: [u8; 64] = [
+    0xdd, 0xaf, 0x35, 0xa1, 0x93, 0x61, 0x7a, 0xba, 0xcc, 0x41, 0x73, 0x49,
+    0xae, 0x20, 0x41, 0x31, 0x12, 0xe6, 0xfa, 0x4e, 0x89, 0xa9, 0x7e, 0xa2,
+    0x0a, 0x9e, 0xee, 0xe6, 0x4b, 0x55, 0xd3, 0x9a, 0x21, 0x92, 0x99, 0x2a,
+    0x27, 0x4f, 0xc1, 0xa8, 0x36, 0xba, 0x3c, 0x23, 0xa3, 0xfe, 0xeb, 0xbd,
+    0x45, 0x4d, 0x44, 0x23, 0x64, 0x3c, 0xe8, 0x0e, 0x2a, 0x9a, 0xc9, 0x4f,
+    0xa5, 0x4c, 0xa4, 0x9f,
+];
+
+const KAT_SHA3_256: [u8; 32] = [
+    0x3a, 0x98, 0x5d, 0xa7, 0x4f, 0xe2, 0x25, 0xb2, 0x04, 0x5c, 0x17, 0x2d,
+    0x6b, 0xd3, 0x90, 0xbd, 0x85, 0x5f, 0x08, 0x6e, 0x3e, 0x9d, 0x52, 0x5b,
+    0x46, 0xbf, 0xe2, 0x45, 0x11, 0x43, 0x15, 0x32,
+];
+
+const KAT_BLAKE2B_512: [u8; 64] = [
+    0xba, 0x80, 0xa5, 0x3f, 0x98, 0x1c, 0x4d, 0x0d, 0x6a, 0x27, 0x97, 0xb6,
+    0x9f, 0x12, 0xf6, 0xe9, 0x4c, 0x21, 0x2f, 0x14, 0x68, 0x5a, 0xc4, 0xb7,
+    0x4b, 0x12, 0xbb, 0x6f, 0xdb, 0xff, 0xa2, 0xd1, 0x7d, 0x87, 0xc5, 0x39,
+    0x2a, 0xab, 0x79, 0x2d, 0xc2, 0x52, 0xd5, 0xde, 0x45, 0x33, 0xcc, 0x95,
+    0x18, 0xd3, 0x8a, 0xa8, 0xdb, 0xf1, 0x92, 0x5a, 0xb9, 0x23, 0x86, 0xed,
+    0xd4, 0x00, 0x99, 0x23,
+];
+
+
 #[cfg(test)]
 mod tests {
     use std::vec::Vec;
     use super::super::{digest, test};
+    use untrusted;
+
+    #[test]
+    fn test_from_name() {
+        for alg in super::ALGORITHMS.iter() {
+            let found = digest::Algorithm::from_name(alg.name()).unwrap();
+            assert_eq!(found.name(), alg.name());
+            assert_eq!(format!("{}", alg), alg.name());
+        }
+        assert_eq!(format!("{}", digest::SHA512_256), "SHA512_256");
+        for name in &["", "sha256", "SHA-256", "SHA256 ", "SHAKE128"] {
+            assert!(digest::Algorithm::from_name(name).is_err());
+        }
+    }
 
     /// Test vectors from BoringSSL.
     #[test]
@@ -669,9 +1341,16 @@ mod tests {
     test_i_u_f!(test_i_u_f_sha256, digest::SHA256);
     test_i_u_f!(test_i_u_f_sha384, digest::SHA384);
     test_i_u_f!(test_i_u_f_sha512, digest::SHA512);
+    test_i_u_f!(test_i_u_f_sha512_224, digest::SHA512_224);
+    test_i_u_f!(test_i_u_f_sha3_256, digest::SHA3_256);
+    test_i_u_f!(test_i_u_f_sha3_512, digest::SHA3_512);
+    test_i_u_f!(test_i_u_f_blake2b_512, digest::BLAKE2B_512);
+    test_i_u_f!(test_i_u_f_blake2s_256, digest::BLAKE2S_256);
 
     /// See https://bugzilla.mozilla.org/show_bug.cgi?id=610162. This tests the
-    /// calculation of 8GB of the byte 123.
+    /// calculation of 8GB of the byte 123. The length of the input doesn't fit
+    /// in 32 bits, in bytes or in bits, so these also test that the length
+    /// is counted and encoded correctly.
     ///
     /// You can verify the expected values in many ways. One way is
     /// `python ~/p/write_big.py`, where write_big.py is:
@@ -689,6 +1368,9 @@ mod tests {
     /// sha256sum -b tempfile
     /// sha384sum -b tempfile
     /// sha512sum -b tempfile
+    /// openssl dgst -sha512-256 -binary tempfile | xxd -p
+    /// openssl dgst -sha3-256 -binary tempfile | xxd -p
+    /// b2sum -b tempfile
     /// ```
     ///
     /// This is not run in dev (debug) builds because it is too slow.
@@ -743,6 +1425,154 @@ mod tests {
         0x49, 0x1A, 0x6B, 0xEC, 0x9C, 0x98, 0xC8, 0x19,
         0xA6, 0xA9, 0x88, 0x3E, 0x2F, 0x09, 0xB9, 0x9A
     ]);
+    test_large_digest!(test_large_digest_sha512_256, digest::SHA512_256,
+                       256 / 8, [
+        0x32, 0xCB, 0xB8, 0x5A, 0x5E, 0x66, 0x05, 0xA3,
+        0xB9, 0x4C, 0x99, 0x07, 0xB6, 0xE4, 0xFC, 0xC6,
+        0x00, 0x1B, 0x05, 0x91, 0x1C, 0xAE, 0xB0, 0x7F,
+        0xB0, 0xD3, 0x0E, 0xA9, 0x81, 0x7C, 0xDC, 0x62
+    ]);
+    test_large_digest!(test_large_digest_sha3_256, digest::SHA3_256,
+                       256 / 8, [
+        0x20, 0x88, 0x1C, 0x27, 0x6C, 0x42, 0x79, 0x35,
+        0xDB, 0xF5, 0x4D, 0x94, 0x63, 0xEB, 0x18, 0xAE,
+        0xC4, 0x78, 0xD2, 0xF0, 0xDF, 0x52, 0x9C, 0x9E,
+        0xDE, 0xAC, 0x3A, 0x8D, 0x23, 0xBC, 0x80, 0x42
+    ]);
+    test_large_digest!(test_large_digest_blake2b_512, digest::BLAKE2B_512,
+                       512 / 8, [
+        0xA4, 0xD8, 0x5C, 0x35, 0x93, 0xB8, 0x22, 0x41,
+        0xA6, 0xB1, 0x68, 0xA0, 0x67, 0x6A, 0x56, 0xA1,
+        0xEF, 0x50, 0x73, 0xA7, 0x88, 0x36, 0x9B, 0x6F,
+        0x34, 0xB6, 0x4F, 0x70, 0x27, 0x76, 0xC1, 0xF2,
+        0x94, 0x73, 0x37, 0x4C, 0xCD, 0xEB, 0xEA, 0x80,
+        0x66, 0x2F, 0xE2, 0x60, 0x46, 0xA8, 0x4F, 0xAA,
+        0x02, 0xF7, 0xFA, 0x55, 0x61, 0x95, 0x81, 0xC1,
+        0xD5, 0x65, 0xC6, 0x08, 0xCD, 0x9E, 0x3C, 0x98
+    ]);
+
+    #[test]
+    fn test_context_reset() {
+        for alg in &digest::test_util::ALL_ALGORITHMS {
+            let expected = digest::digest(alg, b"hello, world");
+
+            let mut ctx = digest::Context::new(alg);
+            ctx.update(&[0xab; 1000]);
+            ctx.update(b"hello");
+            ctx.reset();
+            ctx.update(b"hello, world");
+            let reused = ctx.clone();
+            assert_eq!(ctx.finish().as_ref(), expected.as_ref());
+
+            // A reset context behaves like a new one even after it was
+            // cloned.
+            let mut ctx = reused;
+            ctx.reset();
+            ctx.update(b"hello, ");
+            ctx.update(b"world");
+            assert_eq!(ctx.finish().as_ref(), expected.as_ref());
+        }
+    }
+
+    #[test]
+    fn test_context_state() {
+        let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        for alg in &digest::test_util::ALL_ALGORITHMS {
+            let expected = digest::digest(alg, &data);
+            let block_len = alg.block_len;
+            for &split in &[0, 1, block_len - 1, block_len, block_len + 1,
+                            (3 * block_len) + 5, data.len()] {
+                let mut ctx = digest::Context::new(alg);
+                ctx.update(&data[..split]);
+                let state = ctx.clone_state();
+                let mut resumed = digest::Context::from_state(
+                    alg, untrusted::Input::from(state.as_ref())).unwrap();
+                assert_eq!(resumed.clone_state().as_ref(), state.as_ref());
+                resumed.update(&data[split..]);
+                assert_eq!(resumed.finish().as_ref(), expected.as_ref());
+                assert_eq!(ctx.finish().as_ref(),
+                           digest::digest(alg, &data[..split]).as_ref());
+
+                // The state of one algorithm can't be used with another.
+                for other in &digest::test_util::ALL_ALGORITHMS {
+                    if other.name() != alg.name() {
+                        assert!(digest::Context::from_state(
+                            other, untrusted::Input::from(state.as_ref()))
+                                .is_err());
+                    }
+                }
+
+                // Truncated and extended states are rejected.
+                let state = state.as_ref();
+                assert!(digest::Context::from_state(
+                    alg, untrusted::Input::from(&state[..(state.len() - 1)]))
+                        .is_err());
+                let mut extended = state.to_vec();
+                extended.push(0);
+                assert!(digest::Context::from_state(
+                    alg, untrusted::Input::from(&extended)).is_err());
+            }
+        }
+
+        // The format of the state is stable, so that it can be stored. This
+        // is the state of a new SHA-256 context.
+        let expected = test::from_hex(
+            "06534841323536\
+             0000000000000000\
+             00\
+             6a09e667bb67ae853c6ef372a54ff53a510e527f9b05688c1f83d9ab5be0cd19")
+            .unwrap();
+        assert_eq!(digest::Context::new(&digest::SHA256).clone_state().as_ref(),
+                   &expected[..]);
+
+        // A full block of pending data is rejected.
+        for &num_pending in &[63, 64] {
+            let mut state = expected[..15].to_vec();
+            state.push(num_pending);
+            state.extend_from_slice(&vec![0u8; num_pending as usize]);
+            state.extend_from_slice(&expected[16..]);
+            assert_eq!(digest::Context::from_state(
+                           &digest::SHA256, untrusted::Input::from(&state))
+                               .is_ok(),
+                       num_pending < 64);
+        }
+    }
+
+    #[test]
+    fn test_context_finish_into() {
+        for alg in &digest::test_util::ALL_ALGORITHMS {
+            let expected = digest::digest(alg, b"hello, world");
+
+            let mut out = [0u8; digest::MAX_OUTPUT_LEN + 1];
+            let mut ctx = digest::Context::new(alg);
+            ctx.update(b"hello, world");
+            assert_eq!(ctx.finish_into(&mut out), Ok(alg.output_len));
+            assert_eq!(&out[..alg.output_len], expected.as_ref());
+            assert!(out[alg.output_len..].iter().all(|b| *b == 0));
+
+            let mut short = [0u8; digest::MAX_OUTPUT_LEN];
+            let ctx = digest::Context::new(alg);
+            assert!(ctx.finish_into(&mut short[..(alg.output_len - 1)])
+                       .is_err());
+        }
+    }
+
+    #[test]
+    fn test_algorithm_metadata() {
+        assert_eq!(digest::SHA1.name(), "SHA1");
+        assert_eq!(digest::SHA256.name(), "SHA256");
+        assert_eq!(digest::SHA384.name(), "SHA384");
+        assert_eq!(digest::SHA512.name(), "SHA512");
+        assert_eq!(digest::SHA512_256.name(), "SHA512_256");
+        assert_eq!(digest::SHA3_256.name(), "SHA3_256");
+        assert_eq!(digest::BLAKE2B_512.name(), "BLAKE2B_512");
+        assert_eq!(digest::BLAKE2S_256.name(), "BLAKE2S_256");
+
+        assert!(digest::SHA1.security_bits() < 80);
+        for alg in &digest::test_util::ALL_ALGORITHMS[1..] {
+            assert_eq!(alg.security_bits(), alg.output_len * 8 / 2);
+        }
+    }
 
     #[test]
     fn test_fmt_algorithm() {
@@ -750,6 +1580,14 @@ mod tests {
         assert_eq!("SHA-256", &format!("{:?}", digest::SHA256));
         assert_eq!("SHA-384", &format!("{:?}", digest::SHA384));
         assert_eq!("SHA-512", &format!("{:?}", digest::SHA512));
+        assert_eq!("SHA-512/256", &format!("{:?}", digest::SHA512_256));
+        assert_eq!("SHA-512/224", &format!("{:?}", digest::SHA512_224));
+        assert_eq!("SHA3-256", &format!("{:?}", digest::SHA3_256));
+        assert_eq!("SHA3-384", &format!("{:?}", digest::SHA3_384));
+        assert_eq!("SHA3-512", &format!("{:?}", digest::SHA3_512));
+        assert_eq!("BLAKE2b-512", &format!("{:?}", digest::BLAKE2B_512));
+        assert_eq!("BLAKE2b-256", &format!("{:?}", digest::BLAKE2B_256));
+        assert_eq!("BLAKE2s-256", &format!("{:?}", digest::BLAKE2S_256));
     }
 
     #[test]
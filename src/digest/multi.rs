@@ -0,0 +1,126 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Digesting many independent inputs at once.
+//!
+//! Applications that digest many independent inputs, e.g. the leaves of a
+//! large Merkle tree, can pass them all to `digest_many` instead of calling
+//! `digest::digest` for each one. This allows *ring* to digest several inputs
+//! in parallel with multi-buffer implementations that use SIMD instructions,
+//! where they are available.
+//!
+//! Currently, there are no multi-buffer implementations; the inputs are
+//! digested one after another with the same implementations as
+//! `digest::digest` uses, which already use SIMD instructions and SHA
+//! extensions where they are available. Multi-buffer implementations will be
+//! added without changes to this API.
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::digest;
+//!
+//! let leaves: [&[u8]; 3] = [b"a", b"b", b"c"];
+//! let digests = digest::multi::digest_many(&digest::SHA256, &leaves);
+//! for (leaf, leaf_digest) in leaves.iter().zip(digests.iter()) {
+//!     assert_eq!(leaf_digest.as_ref(),
+//!                digest::digest(&digest::SHA256, leaf).as_ref());
+//! }
+//! ```
+
+use executor;
+use std;
+use super::{Algorithm, Context, Digest};
+
+/// Returns the digests of each of `inputs`, in the same order, using the
+/// digest algorithm `algorithm`.
+pub fn digest_many(algorithm: &'static Algorithm, inputs: &[&[u8]])
+                   -> std::vec::Vec<Digest> {
+    inputs.iter().map(|input| digest(algorithm, input)).collect()
+}
+
+/// Like `digest_many`, but the inputs are digested in jobs, one per input,
+/// that `executor` runs, e.g. on the application's thread pool.
+pub fn digest_many_with(algorithm: &'static Algorithm, inputs: &[&[u8]],
+                        executor: &executor::Executor)
+                        -> std::vec::Vec<Digest> {
+    let mut digests: std::vec::Vec<Option<Digest>> =
+        inputs.iter().map(|_| None).collect();
+    {
+        let mut jobs: std::vec::Vec<_> =
+            digests.iter_mut().zip(inputs.iter()).map(|(out, input)| move || {
+                *out = Some(digest(algorithm, input));
+            }).collect();
+        let mut jobs: std::vec::Vec<&mut (FnMut() + Send)> =
+            jobs.iter_mut().map(|job| {
+                let job: &mut (FnMut() + Send) = job;
+                job
+            }).collect();
+        executor.execute(&mut jobs);
+    }
+    digests.into_iter().map(|digest| digest.unwrap()).collect()
+}
+
+fn digest(algorithm: &'static Algorithm, input: &[u8]) -> Digest {
+    let mut ctx = Context::new(algorithm);
+    ctx.update(input);
+    ctx.finish()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+    use {digest, executor, test};
+
+    #[test]
+    fn test_digest_many() {
+        let inputs: Vec<Vec<u8>> =
+            (0..300).map(|len| vec![len as u8; len]).collect();
+        let inputs: Vec<&[u8]> = inputs.iter().map(|input| &input[..])
+                                       .collect();
+        for alg in &digest::test_util::ALL_ALGORITHMS {
+            let digests = digest::multi::digest_many(alg, &inputs);
+            assert_eq!(digests.len(), inputs.len());
+            for (input, actual) in inputs.iter().zip(digests.iter()) {
+                assert_eq!(actual.algorithm().name(), alg.name());
+                assert_eq!(actual.as_ref(),
+                           digest::digest(alg, input).as_ref());
+            }
+            assert!(digest::multi::digest_many(alg, &[]).is_empty());
+
+            let executors: [&executor::Executor; 2] =
+                [&executor::Sequential, &executor::test_util::Reversed];
+            for executor in &executors {
+                let digests_with = digest::multi::digest_many_with(
+                    alg, &inputs, *executor);
+                assert_eq!(digests_with.len(), inputs.len());
+                for (actual, expected) in digests_with.iter()
+                                                      .zip(digests.iter()) {
+                    assert_eq!(actual.as_ref(), expected.as_ref());
+                }
+            }
+        }
+
+        // A known answer, to make sure the inputs aren't mixed up.
+        let inputs: [&[u8]; 2] = [b"", b"abc"];
+        let digests = digest::multi::digest_many(&digest::SHA256, &inputs);
+        assert_eq!(digests[0].as_ref(), &test::from_hex(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+            .unwrap()[..]);
+        assert_eq!(digests[1].as_ref(), &test::from_hex(
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+            .unwrap()[..]);
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+// The Keccak-f[1600] permutation and the sponge operations used by SHA-3 and
+// SHAKE, as specified in FIPS 202.
+//
+// Like SHA-1, this favors size and simplicity over speed; there is no
+// assembly language implementation.
+
+use {c, polyfill};
+use core;
+use super::{MAX_OUTPUT_LEN, State};
+
+/// The number of 64-bit lanes in the Keccak-f[1600] state.
+pub const STATE_LANES: usize = 25;
+
+pub const SHA3_256_RATE: usize = 1088 / 8;
+pub const SHA3_384_RATE: usize = 832 / 8;
+pub const SHA3_512_RATE: usize = 576 / 8;
+
+/// The domain separation bits of SHA-3, followed by the first bit of the
+/// padding.
+pub const SHA3_DOMAIN: u8 = 0x06;
+
+pub unsafe extern fn sha3_256_block_data_order(state: &mut State,
+                                               data: *const u8,
+                                               num: c::size_t) {
+    absorb_blocks(state, data, num, SHA3_256_RATE)
+}
+
+pub unsafe extern fn sha3_384_block_data_order(state: &mut State,
+                                               data: *const u8,
+                                               num: c::size_t) {
+    absorb_blocks(state, data, num, SHA3_384_RATE)
+}
+
+pub unsafe extern fn sha3_512_block_data_order(state: &mut State,
+                                               data: *const u8,
+                                               num: c::size_t) {
+    absorb_blocks(state, data, num, SHA3_512_RATE)
+}
+
+unsafe fn absorb_blocks(state: &mut State, data: *const u8, num: c::size_t,
+                        rate: usize) {
+    let data = core::slice::from_raw_parts(data, num * rate);
+    for block in data.chunks(rate) {
+        absorb(state, block);
+    }
+}
+
+/// XORs `block`, which must be at most `STATE_LANES * 8` bytes long and a
+/// multiple of 8 bytes long, into the state and then permutes it.
+pub fn absorb(state: &mut [u64; STATE_LANES], block: &[u8]) {
+    for (lane, bytes) in state.iter_mut().zip(block.chunks(8)) {
+        *lane ^= u64_from_le_u8(bytes);
+    }
+    keccak_f(state);
+}
+
+/// Writes the first `out.len()` bytes of the state to `out`.
+pub fn squeeze(state: &[u64; STATE_LANES], out: &mut [u8]) {
+    for (lane, bytes) in state.iter().zip(out.chunks_mut(8)) {
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = (lane >> (8 * i)) as u8;
+        }
+    }
+}
+
+pub fn format_output(input: &State) -> [u64; MAX_OUTPUT_LEN / 8] {
+    let mut output = [0u64; MAX_OUTPUT_LEN / 8];
+    squeeze(input, polyfill::slice::u64_as_u8_mut(&mut output));
+    output
+}
+
+fn u64_from_le_u8(bytes: &[u8]) -> u64 {
+    bytes.iter().rev().fold(0, |acc, b| (acc << 8) | (*b as u64))
+}
+
+// The round constants of the iota step.
+const RC: [u64; 24] = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808a,
+    0x8000000080008000, 0x000000000000808b, 0x0000000080000001,
+    0x8000000080008081, 0x8000000000008009, 0x000000000000008a,
+    0x0000000000000088, 0x0000000080008009, 0x000000008000000a,
+    0x000000008000808b, 0x800000000000008b, 0x8000000000008089,
+    0x8000000000008003, 0x8000000000008002, 0x8000000000000080,
+    0x000000000000800a, 0x800000008000000a, 0x8000000080008081,
+    0x8000000000008080, 0x0000000080000001, 0x8000000080008008,
+];
+
+// The rotation offsets of the rho step, indexed by x + 5*y.
+const RHO: [u32; STATE_LANES] = [
+     0,  1, 62, 28, 27,
+    36, 44,  6, 55, 20,
+     3, 10, 43, 25, 39,
+    41, 45, 15, 21,  8,
+    18,  2, 61, 56, 14,
+];
+
+// Keccak-f[1600]. Lane (x, y) is `a[x + 5*y]`.
+fn keccak_f(a: &mut [u64; STATE_LANES]) {
+    for rc in RC.iter() {
+        // Theta.
+        let mut c = [0u64; 5];
+        for x in 0..5 {
+            c[x] = a[x] ^ a[x + 5] ^ a[x + 10] ^ a[x + 15] ^ a[x + 20];
+        }
+        for x in 0..5 {
+            let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                a[x + 5 * y] ^= d;
+            }
+        }
+
+        // Rho and pi.
+        let mut b = [0u64; STATE_LANES];
+        for x in 0..5 {
+            for y in 0..5 {
+                b[y + 5 * ((2 * x + 3 * y) % 5)] =
+                    a[x + 5 * y].rotate_left(RHO[x + 5 * y]);
+            }
+        }
+
+        // Chi.
+        for y in 0..5 {
+            for x in 0..5 {
+                a[x + 5 * y] = b[x + 5 * y] ^
+                               (!b[(x + 1) % 5 + 5 * y] &
+                                b[(x + 2) % 5 + 5 * y]);
+            }
+        }
+
+        // Iota.
+        a[0] ^= *rc;
+    }
+}
@@ -0,0 +1,242 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The SHAKE128 and SHAKE256 extendable-output functions (XOFs).
+//!
+//! SHAKE128 and SHAKE256 are specified in [FIPS 202]. Unlike the digest
+//! algorithms in `ring::digest`, they can produce any amount of output, so
+//! they have their own API: input is passed to a `Context`, and then the
+//! output is read from the `Reader` returned by `Context::finish`, in one or
+//! more steps.
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::digest::shake;
+//!
+//! let mut one_shot = [0u8; 100];
+//! shake::shake(&shake::SHAKE128, b"hello, world", &mut one_shot);
+//!
+//! let mut ctx = shake::Context::new(&shake::SHAKE128);
+//! ctx.update(b"hello, ");
+//! ctx.update(b"world");
+//! let mut reader = ctx.finish();
+//! let mut multi_part = [0u8; 100];
+//! reader.read(&mut multi_part[..30]);
+//! reader.read(&mut multi_part[30..]);
+//!
+//! assert_eq!(&one_shot[..], &multi_part[..]);
+//! ```
+//!
+//! [FIPS 202]: http://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.202.pdf
+
+use core;
+use super::sha3;
+
+/// A SHAKE algorithm.
+pub struct Algorithm {
+    rate: usize,
+    name: &'static str,
+    security_bits: usize,
+}
+
+impl Algorithm {
+    /// The name of the algorithm, which is the name of its static in this
+    /// module, e.g. `"SHAKE128"` for `SHAKE128`.
+    #[inline(always)]
+    pub fn name(&self) -> &'static str { self.name }
+
+    /// The security strength of the algorithm, in bits, against all attacks,
+    /// given enough output.
+    #[inline(always)]
+    pub fn security_bits(&self) -> usize { self.security_bits }
+}
+
+impl core::fmt::Debug for Algorithm {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(fmt, "SHAKE{:?}", self.security_bits)
+    }
+}
+
+/// SHAKE128 as specified in [FIPS 202].
+///
+/// [FIPS 202]: http://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.202.pdf
+pub static SHAKE128: Algorithm = Algorithm {
+    rate: 1344 / 8,
+    name: "SHAKE128",
+    security_bits: 128,
+};
+
+/// SHAKE256 as specified in [FIPS 202].
+///
+/// [FIPS 202]: http://nvlpubs.nist.gov/nistpubs/FIPS/NIST.FIPS.202.pdf
+pub static SHAKE256: Algorithm = Algorithm {
+    rate: 1088 / 8,
+    name: "SHAKE256",
+    security_bits: 256,
+};
+
+// The largest rate, SHAKE128's.
+const MAX_RATE: usize = 1344 / 8;
+
+// The domain separation bits of SHAKE, followed by the first bit of the
+// padding.
+const SHAKE_DOMAIN: u8 = 0x1f;
+
+/// A context for absorbing the input of a SHAKE computation.
+#[derive(Clone)]
+pub struct Context {
+    state: [u64; sha3::STATE_LANES],
+    pending: [u8; MAX_RATE],
+    num_pending: usize,
+    algorithm: &'static Algorithm,
+}
+
+impl Context {
+    /// Constructs a new context.
+    pub fn new(algorithm: &'static Algorithm) -> Context {
+        Context {
+            state: [0u64; sha3::STATE_LANES],
+            pending: [0u8; MAX_RATE],
+            num_pending: 0,
+            algorithm: algorithm,
+        }
+    }
+
+    /// Updates the context with all the data in `data`.
+    pub fn update(&mut self, data: &[u8]) {
+        let rate = self.algorithm.rate;
+        let mut remaining = data;
+        while !remaining.is_empty() {
+            let to_copy = core::cmp::min(rate - self.num_pending,
+                                         remaining.len());
+            self.pending[self.num_pending..][..to_copy]
+                .copy_from_slice(&remaining[..to_copy]);
+            self.num_pending += to_copy;
+            remaining = &remaining[to_copy..];
+            if self.num_pending == rate {
+                sha3::absorb(&mut self.state, &self.pending[..rate]);
+                self.num_pending = 0;
+            }
+        }
+    }
+
+    /// Finishes absorbing the input and returns a `Reader` for the output.
+    pub fn finish(mut self) -> Reader {
+        let rate = self.algorithm.rate;
+        for b in &mut self.pending[self.num_pending..rate] {
+            *b = 0;
+        }
+        self.pending[self.num_pending] ^= SHAKE_DOMAIN;
+        self.pending[rate - 1] ^= 0x80;
+        sha3::absorb(&mut self.state, &self.pending[..rate]);
+
+        let mut reader = Reader {
+            state: self.state,
+            buffer: [0u8; MAX_RATE],
+            buffer_pos: 0,
+            algorithm: self.algorithm,
+        };
+        sha3::squeeze(&reader.state, &mut reader.buffer[..rate]);
+        reader
+    }
+
+    /// The algorithm that this context is using.
+    #[inline(always)]
+    pub fn algorithm(&self) -> &'static Algorithm { self.algorithm }
+}
+
+/// The output of a SHAKE computation.
+pub struct Reader {
+    state: [u64; sha3::STATE_LANES],
+    buffer: [u8; MAX_RATE],
+    buffer_pos: usize,
+    algorithm: &'static Algorithm,
+}
+
+impl Reader {
+    /// Fills `out` with the next `out.len()` bytes of output.
+    pub fn read(&mut self, out: &mut [u8]) {
+        let rate = self.algorithm.rate;
+        let mut out = out;
+        while !out.is_empty() {
+            if self.buffer_pos == rate {
+                sha3::absorb(&mut self.state, &[]);
+                sha3::squeeze(&self.state, &mut self.buffer[..rate]);
+                self.buffer_pos = 0;
+            }
+            let to_copy = core::cmp::min(rate - self.buffer_pos, out.len());
+            let (head, tail) = { out }.split_at_mut(to_copy);
+            head.copy_from_slice(&self.buffer[self.buffer_pos..][..to_copy]);
+            self.buffer_pos += to_copy;
+            out = tail;
+        }
+    }
+}
+
+/// Fills `out` with the first `out.len()` bytes of the output of `algorithm`
+/// for the input `data`.
+pub fn shake(algorithm: &'static Algorithm, data: &[u8], out: &mut [u8]) {
+    let mut ctx = Context::new(algorithm);
+    ctx.update(data);
+    ctx.finish().read(out)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use test;
+    use super::*;
+
+    #[test]
+    fn test_shake() {
+        test::from_file("src/digest/shake_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let algorithm = match test_case.consume_string("Algorithm")
+                                           .as_str() {
+                "SHAKE128" => &SHAKE128,
+                "SHAKE256" => &SHAKE256,
+                s => panic!("Unsupported algorithm: {}", s),
+            };
+            let input = test_case.consume_bytes("Input");
+            let expected = test_case.consume_bytes("Output");
+
+            let mut actual = vec![0u8; expected.len()];
+            shake(algorithm, &input, &mut actual);
+            assert_eq!(actual, expected);
+
+            // Byte-at-a-time input and output.
+            let mut ctx = Context::new(algorithm);
+            for b in &input {
+                ctx.update(&[*b]);
+            }
+            let mut reader = ctx.finish();
+            for (i, b) in expected.iter().enumerate() {
+                let mut actual = [0u8; 1];
+                reader.read(&mut actual);
+                assert_eq!(actual[0], *b, "byte {}", i);
+            }
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_shake_metadata() {
+        assert_eq!(SHAKE128.name(), "SHAKE128");
+        assert_eq!(SHAKE256.name(), "SHAKE256");
+        assert_eq!(&format!("{:?}", SHAKE256), "SHAKE256");
+        assert_eq!(Context::new(&SHAKE128).algorithm().name(), "SHAKE128");
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! BLS signature verification on BLS12-381, in the "minimal-pubkey-size"
+//! variant, as specified in [draft-irtf-cfrg-bls-signature-05].
+//!
+//! Public keys are compressed G1 points (48 bytes) and signatures are
+//! compressed G2 points (96 bytes), in the Zcash serialization format.
+//!
+//! Only verification is implemented. Only public values are processed, so
+//! the implementation isn't constant-time.
+//!
+//! [draft-irtf-cfrg-bls-signature-05]:
+//!     https://tools.ietf.org/html/draft-irtf-cfrg-bls-signature-05
+
+use {error, init, private, signature};
+use untrusted;
+
+#[cfg(target_pointer_width = "32")]
+macro_rules! fp {
+    ( $limb_b:expr, $limb_a:expr, $limb_9:expr, $limb_8:expr,
+      $limb_7:expr, $limb_6:expr, $limb_5:expr, $limb_4:expr,
+      $limb_3:expr, $limb_2:expr, $limb_1:expr, $limb_0:expr ) => {
+        [$limb_0, $limb_1, $limb_2, $limb_3,
+         $limb_4, $limb_5, $limb_6, $limb_7,
+         $limb_8, $limb_9, $limb_a, $limb_b]
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+macro_rules! fp {
+    ( $limb_b:expr, $limb_a:expr, $limb_9:expr, $limb_8:expr,
+      $limb_7:expr, $limb_6:expr, $limb_5:expr, $limb_4:expr,
+      $limb_3:expr, $limb_2:expr, $limb_1:expr, $limb_0:expr ) => {
+        [(($limb_1 | 0u64) << 32) | $limb_0,
+         (($limb_3 | 0u64) << 32) | $limb_2,
+         (($limb_5 | 0u64) << 32) | $limb_4,
+         (($limb_7 | 0u64) << 32) | $limb_6,
+         (($limb_9 | 0u64) << 32) | $limb_8,
+         (($limb_b | 0u64) << 32) | $limb_a]
+    }
+}
+
+mod curve;
+mod field;
+mod hash_to_curve;
+mod pairing;
+
+use self::curve::*;
+
+/// Parameters for BLS12-381 signature verification.
+pub struct BLSParameters {
+    name: &'static str,
+    dst: &'static [u8],
+}
+
+/// Verification of BLS12-381 min-pk signatures with the proof-of-possession
+/// ciphersuite `BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_`, as used by
+/// Ethereum's consensus layer.
+///
+/// This doesn't check a proof of possession of the public key; that must be
+/// done separately, when the key is registered.
+pub static BLS12_381_MIN_PK_POP: BLSParameters = BLSParameters {
+    name: "BLS12_381_MIN_PK_POP",
+    dst: b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_",
+};
+
+/// Verification of BLS12-381 min-pk signatures with the basic ciphersuite
+/// `BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_`, as used by drand's default
+/// (chained and unchained) beacons.
+///
+/// The basic scheme requires that messages are distinct when signatures from
+/// different signers are aggregated; this only verifies single signatures.
+pub static BLS12_381_MIN_PK_BASIC: BLSParameters = BLSParameters {
+    name: "BLS12_381_MIN_PK_BASIC",
+    dst: b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_",
+};
+
+impl signature::VerificationAlgorithm for BLSParameters {
+    fn verify(&self, public_key: untrusted::Input, msg: untrusted::Input,
+              signature: untrusted::Input) -> Result<(), error::Unspecified> {
+        init::init_once();
+
+        // KeyValidate and signature_to_point, including the subgroup checks.
+        let pk = try!(g1_decompress(public_key.as_slice_less_safe()));
+        let sig = try!(g2_decompress(signature.as_slice_less_safe()));
+
+        let h = try!(hash_to_curve::hash_to_g2(msg.as_slice_less_safe(),
+                                               self.dst));
+        let h = try!(h.to_affine().ok_or(error::Unspecified));
+
+        // e(-G1, sig) * e(pk, H(msg)) == 1.
+        let f = pairing::multi_miller_loop(&[(&g1_generator().neg(), &sig),
+                                             (&pk, &h)]);
+        if !pairing::final_exponentiation(&f).is_one() {
+            return Err(error::Unspecified);
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str { self.name }
+
+    // Since the tower number field sieve improvements, estimates of the
+    // strength of BLS12-381 range from about 117 to 120 bits; use the lower
+    // one.
+    fn security_bits(&self) -> usize { 117 }
+}
+
+impl private::Private for BLSParameters {}
+
+
+#[cfg(test)]
+mod tests {
+    use {signature, test};
+    use super::{curve, field, hash_to_curve, pairing};
+    use super::field::Field;
+    use untrusted;
+
+    #[test]
+    fn test_signature_bls12_381_verify() {
+        test::from_file("src/ec/bls12_381/bls12_381_verify_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+
+            let alg = match test_case.consume_string("Scheme").as_str() {
+                "POP" => &signature::BLS12_381_MIN_PK_POP,
+                "BASIC" => &signature::BLS12_381_MIN_PK_BASIC,
+                s => panic!("Unsupported scheme: {}", s),
+            };
+            let public_key = test_case.consume_bytes("PublicKey");
+            let msg = test_case.consume_bytes("Message");
+            let sig = test_case.consume_bytes("Signature");
+            let expected_result = test_case.consume_string("Result");
+
+            let actual_result =
+                signature::verify(alg, untrusted::Input::from(&public_key),
+                                  untrusted::Input::from(&msg),
+                                  untrusted::Input::from(&sig));
+            assert_eq!(actual_result.is_ok(), expected_result == "P");
+
+            Ok(())
+        });
+    }
+
+    // The BLS12381G2_XMD:SHA-256_SSWU_RO_ vectors from RFC 9380 Appendix
+    // J.10.1.
+    #[test]
+    fn test_hash_to_g2() {
+        const DST: &'static [u8] =
+            b"QUUX-V01-CS02-with-BLS12381G2_XMD:SHA-256_SSWU_RO_";
+        let vectors: [(&[u8], &str); 2] = [
+            (b"",
+             "0141ebfbdca40eb85b87142e130ab689c673cf60f1a3e98d69335266f30d9b8d\
+              4ac44c1038e9dcdd5393faf5c41fb78a\
+              05cb8437535e20ecffaef7752baddf98034139c38452458baeefab379ba13dff\
+              5bf5dd71b72418717047f5b0f37da03d\
+              0503921d7f6a12805e72940b963c0cf3471c7b2a524950ca195d11062ee75ec0\
+              76daf2d4bc358c4b190c0c98064fdd92\
+              12424ac32561493f3fe3c260708a12b7c620e7be00099a974e259ddc7d1f6395\
+              c3c811cdd19f1e8dbf3e9ecfdcbab8d6"),
+            (b"abc",
+             "02c2d18e033b960562aae3cab37a27ce00d80ccd5ba4b7fe0e7a210245129dbe\
+              c7780ccc7954725f4168aff2787776e6\
+              139cddbccdc5e91b9623efd38c49f81a6f83f175e80b06fc374de9eb4b41dfe4\
+              ca3a230ed250fbe3a2acf73a41177fd8\
+              1787327b68159716a37440985269cf584bcb1e621d3a7202be6ea05c4cfe244a\
+              eb197642555a0645fb87bf7466b2ba48\
+              00aa65dae3c8d732d10ecd2c50f8a1baf3001578f71c694e03866e9f3d49ac1e\
+              1ce70dd94a733534f106d4cec0eddd16"),
+        ];
+        for &(msg, expected) in vectors.iter() {
+            let expected = test::from_hex(expected).unwrap();
+            let p = hash_to_curve::hash_to_g2(msg, DST).unwrap()
+                        .to_affine().unwrap();
+            // x.c0 || x.c1 || y.c0 || y.c1.
+            assert_eq!(encode(&p.x.c0), &expected[0..48]);
+            assert_eq!(encode(&p.x.c1), &expected[48..96]);
+            assert_eq!(encode(&p.y.c0), &expected[96..144]);
+            assert_eq!(encode(&p.y.c1), &expected[144..192]);
+        }
+    }
+
+    #[test]
+    fn test_pairing_bilinearity() {
+        let g1 = curve::g1_generator();
+        let g2 = curve::g2_decompress(&G2_GENERATOR_COMPRESSED).unwrap();
+
+        // e(2 * G1, 3 * G2) == e(6 * G1, G2), checked as
+        // e(-(2 * G1), 3 * G2) * e(6 * G1, G2) == 1.
+        let g1_2 = mul(&g1, &[2]).neg();
+        let g1_6 = mul(&g1, &[6]);
+        let g2_3 = mul(&g2, &[3]);
+        let f = pairing::multi_miller_loop(&[(&g1_2, &g2_3), (&g1_6, &g2)]);
+        assert!(pairing::final_exponentiation(&f).is_one());
+
+        // e(G1, G2) != 1.
+        let f = pairing::multi_miller_loop(&[(&g1, &g2)]);
+        assert!(!pairing::final_exponentiation(&f).is_one());
+    }
+
+    fn mul<F: Field>(p: &curve::Affine<F>, k: &[u8]) -> curve::Affine<F> {
+        curve::Point::from_affine(p).mul(k).to_affine().unwrap()
+    }
+
+    fn encode(a: &field::Fp) -> [u8; field::FP_LEN] {
+        let mut out = [0u8; field::FP_LEN];
+        a.to_be_bytes(&mut out);
+        out
+    }
+
+    const G2_GENERATOR_COMPRESSED: [u8; curve::G2_COMPRESSED_LEN] = [
+        0x93, 0xe0, 0x2b, 0x60, 0x52, 0x71, 0x9f, 0x60, 0x7d, 0xac, 0xd3, 0xa0,
+        0x88, 0x27, 0x4f, 0x65, 0x59, 0x6b, 0xd0, 0xd0, 0x99, 0x20, 0xb6, 0x1a,
+        0xb5, 0xda, 0x61, 0xbb, 0xdc, 0x7f, 0x50, 0x49, 0x33, 0x4c, 0xf1, 0x12,
+        0x13, 0x94, 0x5d, 0x57, 0xe5, 0xac, 0x7d, 0x05, 0x5d, 0x04, 0x2b, 0x7e,
+        0x02, 0x4a, 0xa2, 0xb2, 0xf0, 0x8f, 0x0a, 0x91, 0x26, 0x08, 0x05, 0x27,
+        0x2d, 0xc5, 0x10, 0x51, 0xc6, 0xe4, 0x7a, 0xd4, 0xfa, 0x40, 0x3b, 0x02,
+        0xb4, 0x51, 0x0b, 0x64, 0x7a, 0xe3, 0xd1, 0x77, 0x0b, 0xac, 0x03, 0x26,
+        0xa8, 0x05, 0xbb, 0xef, 0xd4, 0x80, 0x56, 0xc8, 0xc1, 0x21, 0xbd, 0xb8,
+    ];
+}
@@ -0,0 +1,226 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The groups G1, on E: y**2 = x**3 + 4 over Fp, and G2, on the twist
+//! E': y**2 = x**3 + 4(u + 1) over Fp2, and the compressed point encodings
+//! of the Zcash serialization format.
+
+use error;
+use limb::*;
+use super::field::*;
+
+/// A point in Jacobian coordinates (X, Y, Z), representing the affine point
+/// (X/Z**2, Y/Z**3), on a curve of the form y**2 = x**3 + b. Z == 0 for the
+/// point at infinity.
+#[derive(Clone, Copy)]
+pub struct Point<F: Field> {
+    x: F,
+    y: F,
+    z: F,
+}
+
+/// An affine point that isn't the point at infinity.
+#[derive(Clone, Copy)]
+pub struct Affine<F: Field> {
+    pub x: F,
+    pub y: F,
+}
+
+impl<F: Field> Affine<F> {
+    pub fn neg(&self) -> Affine<F> { Affine { x: self.x, y: self.y.neg() } }
+}
+
+impl<F: Field> Point<F> {
+    pub fn infinity() -> Point<F> {
+        Point { x: F::one(), y: F::one(), z: F::zero() }
+    }
+
+    pub fn from_affine(p: &Affine<F>) -> Point<F> {
+        Point { x: p.x, y: p.y, z: F::one() }
+    }
+
+    #[inline]
+    pub fn is_infinity(&self) -> bool { self.z.is_zero() }
+
+    /// Returns `None` for the point at infinity.
+    pub fn to_affine(&self) -> Option<Affine<F>> {
+        if self.is_infinity() {
+            return None;
+        }
+        let z_inv = self.z.invert();
+        let z_inv_2 = z_inv.square();
+        Some(Affine {
+            x: self.x.mul(&z_inv_2),
+            y: self.y.mul(&z_inv_2.mul(&z_inv)),
+        })
+    }
+
+    /// dbl-2009-l from the Explicit-Formulas Database, for a == 0.
+    pub fn double(&self) -> Point<F> {
+        if self.is_infinity() {
+            return *self;
+        }
+        let a = self.x.square();
+        let b = self.y.square();
+        let c = b.square();
+        let d = self.x.add(&b).square().sub(&a).sub(&c).double();
+        let e = a.double().add(&a);
+        let f = e.square();
+        let x3 = f.sub(&d.double());
+        let c8 = c.double().double().double();
+        let y3 = e.mul(&d.sub(&x3)).sub(&c8);
+        let z3 = self.y.mul(&self.z).double();
+        Point { x: x3, y: y3, z: z3 }
+    }
+
+    /// add-2007-bl from the Explicit-Formulas Database, falling back to
+    /// doubling when the points are equal.
+    pub fn add(&self, b: &Point<F>) -> Point<F> {
+        if self.is_infinity() {
+            return *b;
+        }
+        if b.is_infinity() {
+            return *self;
+        }
+        let z1z1 = self.z.square();
+        let z2z2 = b.z.square();
+        let u1 = self.x.mul(&z2z2);
+        let u2 = b.x.mul(&z1z1);
+        let s1 = self.y.mul(&b.z).mul(&z2z2);
+        let s2 = b.y.mul(&self.z).mul(&z1z1);
+        if u1.is_equal(&u2) {
+            if s1.is_equal(&s2) {
+                return self.double();
+            }
+            return Point::infinity();
+        }
+        let h = u2.sub(&u1);
+        let r = s2.sub(&s1);
+        let h2 = h.square();
+        let h3 = h2.mul(&h);
+        let u1h2 = u1.mul(&h2);
+        let x3 = r.square().sub(&h3).sub(&u1h2.double());
+        let y3 = r.mul(&u1h2.sub(&x3)).sub(&s1.mul(&h3));
+        let z3 = self.z.mul(&b.z).mul(&h);
+        Point { x: x3, y: y3, z: z3 }
+    }
+
+    /// Returns `self * k`, where `k` is big-endian encoded. This isn't
+    /// constant-time.
+    pub fn mul(&self, k: &[u8]) -> Point<F> {
+        let mut acc = Point::infinity();
+        for byte in k {
+            for i in (0..8).rev() {
+                acc = acc.double();
+                if (byte >> i) & 1 == 1 {
+                    acc = acc.add(self);
+                }
+            }
+        }
+        acc
+    }
+
+    /// Whether the point is in the subgroup of order r.
+    pub fn is_torsion_free(&self) -> bool { self.mul(&R).is_infinity() }
+}
+
+/// The order of G1 and G2.
+const R: [u8; 32] = [
+    0x73, 0xed, 0xa7, 0x53, 0x29, 0x9d, 0x7d, 0x48, 0x33, 0x39, 0xd8, 0x08,
+    0x09, 0xa1, 0xd8, 0x05, 0x53, 0xbd, 0xa4, 0x02, 0xff, 0xfe, 0x5b, 0xfe,
+    0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x01,
+];
+
+
+/// The length of a compressed G1 point.
+pub const G1_COMPRESSED_LEN: usize = FP_LEN;
+
+/// The length of a compressed G2 point.
+pub const G2_COMPRESSED_LEN: usize = 2 * FP_LEN;
+
+const FLAG_COMPRESSED: u8 = 0x80;
+const FLAG_INFINITY: u8 = 0x40;
+const FLAG_SIGN: u8 = 0x20;
+const FLAG_BITS: u8 = 3;
+
+/// The generator of G1.
+pub fn g1_generator() -> Affine<Fp> {
+    static X: [Limb; FP_LIMBS] =
+        fp![0x17f1d3a7, 0x3197d794, 0x2695638c, 0x4fa9ac0f,
+            0xc3688c4f, 0x9774b905, 0xa14e3a3f, 0x171bac58,
+            0x6c55e83f, 0xf97a1aef, 0xfb3af00a, 0xdb22c6bb];
+    static Y: [Limb; FP_LIMBS] =
+        fp![0x08b3f481, 0xe3aaa0f1, 0xa09e30ed, 0x741d8ae4,
+            0xfcf5e095, 0xd5d00af6, 0x00db18cb, 0x2c04b3ed,
+            0xd03cc744, 0xa2888ae4, 0x0caa2329, 0x46c5e7e1];
+    Affine { x: Fp::from_plain(&X), y: Fp::from_plain(&Y) }
+}
+
+/// The `b` coefficient of the twist E'.
+pub fn g2_b() -> Fp2 {
+    let four = Fp::from_limb(4);
+    Fp2::new(four, four)
+}
+
+/// Decodes a compressed G1 point, verifying that it is in G1. The point at
+/// infinity is rejected.
+pub fn g1_decompress(input: &[u8]) -> Result<Affine<Fp>, error::Unspecified> {
+    if input.len() != G1_COMPRESSED_LEN {
+        return Err(error::Unspecified);
+    }
+    let sign = try!(check_flags(input[0]));
+    let x = try!(Fp::parse(input, FLAG_BITS));
+    let rhs = x.square().mul(&x).add(&Fp::from_limb(4));
+    let mut y = try!(rhs.sqrt().ok_or(error::Unspecified));
+    if y.is_lexicographically_largest() != sign {
+        y = y.neg();
+    }
+    let p = Affine { x: x, y: y };
+    if !Point::from_affine(&p).is_torsion_free() {
+        return Err(error::Unspecified);
+    }
+    Ok(p)
+}
+
+/// Decodes a compressed G2 point, verifying that it is in G2. The point at
+/// infinity is rejected.
+pub fn g2_decompress(input: &[u8]) -> Result<Affine<Fp2>, error::Unspecified> {
+    if input.len() != G2_COMPRESSED_LEN {
+        return Err(error::Unspecified);
+    }
+    let sign = try!(check_flags(input[0]));
+    // The encoding is c1 || c0.
+    let c1 = try!(Fp::parse(&input[..FP_LEN], FLAG_BITS));
+    let c0 = try!(Fp::parse(&input[FP_LEN..], 0));
+    let x = Fp2::new(c0, c1);
+    let rhs = x.square().mul(&x).add(&g2_b());
+    let mut y = try!(rhs.sqrt().ok_or(error::Unspecified));
+    if y.is_lexicographically_largest() != sign {
+        y = y.neg();
+    }
+    let p = Affine { x: x, y: y };
+    if !Point::from_affine(&p).is_torsion_free() {
+        return Err(error::Unspecified);
+    }
+    Ok(p)
+}
+
+// Returns the sign flag. Only compressed encodings of points other than the
+// point at infinity are accepted.
+fn check_flags(b: u8) -> Result<bool, error::Unspecified> {
+    if b & FLAG_COMPRESSED == 0 || b & FLAG_INFINITY != 0 {
+        return Err(error::Unspecified);
+    }
+    Ok(b & FLAG_SIGN != 0)
+}
@@ -0,0 +1,525 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The base field GF(q) of BLS12-381 and its extensions:
+//!
+//! * Fp2 = Fp[u] / (u**2 + 1)
+//! * Fp6 = Fp2[v] / (v**3 - (u + 1))
+//! * Fp12 = Fp6[w] / (w**2 - v)
+//!
+//! Only public values are processed, so none of this is constant-time.
+
+use error;
+use limb::*;
+
+pub const FP_LIMBS: usize = 384 / LIMB_BITS;
+
+/// The length of an encoded element of Fp.
+pub const FP_LEN: usize = 48;
+
+/// An element of Fp, fully reduced and Montgomery-encoded.
+#[derive(Clone, Copy)]
+pub struct Fp {
+    limbs: [Limb; FP_LIMBS],
+}
+
+// q.
+static Q: [Limb; FP_LIMBS] =
+    fp![0x1a0111ea, 0x397fe69a, 0x4b1ba7b6, 0x434bacd7,
+        0x64774b84, 0xf38512bf, 0x6730d2a0, 0xf6b0f624,
+        0x1eabfffe, 0xb153ffff, 0xb9feffff, 0xffffaaab];
+
+// (2**384)**2 % q.
+static RR: [Limb; FP_LIMBS] =
+    fp![0x11988fe5, 0x92cae3aa, 0x9a793e85, 0xb519952d,
+        0x67eb88a9, 0x939d83c0, 0x8de5476c, 0x4c95b6d5,
+        0x0a76e6a6, 0x09d104f1, 0xf4df1f34, 0x1c341746];
+
+// (q + 1) / 2.
+static Q_PLUS_1_OVER_2: [Limb; FP_LIMBS] =
+    fp![0x0d0088f5, 0x1cbff34d, 0x258dd3db, 0x21a5d66b,
+        0xb23ba5c2, 0x79c2895f, 0xb3986950, 0x7b587b12,
+        0x0f55ffff, 0x58a9ffff, 0xdcff7fff, 0xffffd556];
+
+const Q_MINUS_2: [u8; FP_LEN] = [
+    0x1a, 0x01, 0x11, 0xea, 0x39, 0x7f, 0xe6, 0x9a, 0x4b, 0x1b, 0xa7, 0xb6,
+    0x43, 0x4b, 0xac, 0xd7, 0x64, 0x77, 0x4b, 0x84, 0xf3, 0x85, 0x12, 0xbf,
+    0x67, 0x30, 0xd2, 0xa0, 0xf6, 0xb0, 0xf6, 0x24, 0x1e, 0xab, 0xff, 0xfe,
+    0xb1, 0x53, 0xff, 0xff, 0xb9, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xaa, 0xa9,
+];
+
+const Q_PLUS_1_OVER_4: [u8; FP_LEN] = [
+    0x06, 0x80, 0x44, 0x7a, 0x8e, 0x5f, 0xf9, 0xa6, 0x92, 0xc6, 0xe9, 0xed,
+    0x90, 0xd2, 0xeb, 0x35, 0xd9, 0x1d, 0xd2, 0xe1, 0x3c, 0xe1, 0x44, 0xaf,
+    0xd9, 0xcc, 0x34, 0xa8, 0x3d, 0xac, 0x3d, 0x89, 0x07, 0xaa, 0xff, 0xff,
+    0xac, 0x54, 0xff, 0xff, 0xee, 0x7f, 0xbf, 0xff, 0xff, 0xff, 0xea, 0xab,
+];
+
+const Q_MINUS_3_OVER_4: [u8; FP_LEN] = [
+    0x06, 0x80, 0x44, 0x7a, 0x8e, 0x5f, 0xf9, 0xa6, 0x92, 0xc6, 0xe9, 0xed,
+    0x90, 0xd2, 0xeb, 0x35, 0xd9, 0x1d, 0xd2, 0xe1, 0x3c, 0xe1, 0x44, 0xaf,
+    0xd9, 0xcc, 0x34, 0xa8, 0x3d, 0xac, 0x3d, 0x89, 0x07, 0xaa, 0xff, 0xff,
+    0xac, 0x54, 0xff, 0xff, 0xee, 0x7f, 0xbf, 0xff, 0xff, 0xff, 0xea, 0xaa,
+];
+
+const Q_MINUS_1_OVER_2: [u8; FP_LEN] = [
+    0x0d, 0x00, 0x88, 0xf5, 0x1c, 0xbf, 0xf3, 0x4d, 0x25, 0x8d, 0xd3, 0xdb,
+    0x21, 0xa5, 0xd6, 0x6b, 0xb2, 0x3b, 0xa5, 0xc2, 0x79, 0xc2, 0x89, 0x5f,
+    0xb3, 0x98, 0x69, 0x50, 0x7b, 0x58, 0x7b, 0x12, 0x0f, 0x55, 0xff, 0xff,
+    0x58, 0xa9, 0xff, 0xff, 0xdc, 0xff, 0x7f, 0xff, 0xff, 0xff, 0xd5, 0x55,
+];
+
+/// Operations that are common to Fp and Fp2, for the curve arithmetic.
+pub trait Field: Copy {
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn add(&self, b: &Self) -> Self;
+    fn sub(&self, b: &Self) -> Self;
+    fn mul(&self, b: &Self) -> Self;
+    fn neg(&self) -> Self;
+    fn is_zero(&self) -> bool;
+    fn is_equal(&self, b: &Self) -> bool;
+
+    /// Returns `self**-1`, or zero if `self` is zero.
+    fn invert(&self) -> Self;
+
+    #[inline]
+    fn square(&self) -> Self { self.mul(self) }
+
+    #[inline]
+    fn double(&self) -> Self { self.add(self) }
+
+    /// Returns `self**e`, where `e` is big-endian encoded.
+    fn pow(&self, e: &[u8]) -> Self {
+        let mut acc = Self::one();
+        for byte in e {
+            for i in (0..8).rev() {
+                acc = acc.square();
+                if (byte >> i) & 1 == 1 {
+                    acc = acc.mul(self);
+                }
+            }
+        }
+        acc
+    }
+}
+
+impl Fp {
+    /// Converts a fully-reduced value to an `Fp`.
+    pub fn from_plain(a: &[Limb; FP_LIMBS]) -> Fp {
+        Fp { limbs: mul_mont(a, &RR) }
+    }
+
+    /// Converts a small value to an `Fp`.
+    pub fn from_limb(a: Limb) -> Fp {
+        let mut plain = [0; FP_LIMBS];
+        plain[0] = a;
+        Fp::from_plain(&plain)
+    }
+
+    /// Parses a big-endian-encoded value, which must be less than q. The
+    /// first `unused_bits` bits of the input (the flag bits of the point
+    /// encodings) are ignored.
+    pub fn parse(input: &[u8], unused_bits: u8)
+                 -> Result<Fp, error::Unspecified> {
+        let input = try!(slice_as_array_ref!(input, FP_LEN));
+        let mut bytes = *input;
+        bytes[0] &= 0xff >> unused_bits;
+        let plain = limbs_from_big_endian(&bytes);
+        if limbs_less_than_limbs_constant_time(&plain, &Q) != LimbMask::True {
+            return Err(error::Unspecified);
+        }
+        Ok(Fp::from_plain(&plain))
+    }
+
+    /// Converts a value less than 2**256 to an `Fp`.
+    pub fn from_be_bytes_256(input: &[u8; 32]) -> Fp {
+        let mut bytes = [0u8; FP_LEN];
+        bytes[(FP_LEN - 32)..].copy_from_slice(input);
+        Fp::from_plain(&limbs_from_big_endian(&bytes))
+    }
+
+    #[cfg(test)]
+    pub fn to_be_bytes(&self, out: &mut [u8; FP_LEN]) {
+        let plain = self.to_plain();
+        for i in 0..FP_LEN {
+            let limb = plain[(FP_LEN - i - 1) / LIMB_BYTES];
+            out[i] = (limb >> (8 * ((FP_LEN - i - 1) % LIMB_BYTES))) as u8;
+        }
+    }
+
+    fn to_plain(&self) -> [Limb; FP_LIMBS] {
+        let mut one = [0; FP_LIMBS];
+        one[0] = 1;
+        mul_mont(&self.limbs, &one)
+    }
+
+    /// Whether the value is odd; `sgn0` in RFC 9380.
+    pub fn is_odd(&self) -> bool { self.to_plain()[0] & 1 == 1 }
+
+    /// Whether the value is greater than `(q - 1) / 2`, which is how the
+    /// point encodings choose between `y` and `-y`.
+    pub fn is_lexicographically_largest(&self) -> bool {
+        limbs_less_than_limbs_constant_time(&self.to_plain(), &Q_PLUS_1_OVER_2)
+            == LimbMask::False
+    }
+
+    /// Returns the square root of `self`, if it is a square.
+    pub fn sqrt(&self) -> Option<Fp> {
+        // q == 3 (mod 4).
+        let r = self.pow(&Q_PLUS_1_OVER_4);
+        if r.square().is_equal(self) { Some(r) } else { None }
+    }
+}
+
+impl Field for Fp {
+    #[inline]
+    fn zero() -> Fp { Fp { limbs: [0; FP_LIMBS] } }
+
+    #[inline]
+    fn one() -> Fp { Fp::from_limb(1) }
+
+    fn add(&self, b: &Fp) -> Fp {
+        let mut r = Fp::zero();
+        unsafe {
+            GFp_bls12_381_elem_add(r.limbs.as_mut_ptr(), self.limbs.as_ptr(),
+                                   b.limbs.as_ptr());
+        }
+        r
+    }
+
+    fn sub(&self, b: &Fp) -> Fp {
+        let mut r = Fp::zero();
+        unsafe {
+            GFp_bls12_381_elem_sub(r.limbs.as_mut_ptr(), self.limbs.as_ptr(),
+                                   b.limbs.as_ptr());
+        }
+        r
+    }
+
+    #[inline]
+    fn mul(&self, b: &Fp) -> Fp {
+        Fp { limbs: mul_mont(&self.limbs, &b.limbs) }
+    }
+
+    fn neg(&self) -> Fp {
+        let mut r = Fp::zero();
+        unsafe {
+            GFp_bls12_381_elem_neg(r.limbs.as_mut_ptr(), self.limbs.as_ptr());
+        }
+        r
+    }
+
+    #[inline]
+    fn is_zero(&self) -> bool { self.limbs == [0; FP_LIMBS] }
+
+    #[inline]
+    fn is_equal(&self, b: &Fp) -> bool { self.limbs == b.limbs }
+
+    #[inline]
+    fn invert(&self) -> Fp { self.pow(&Q_MINUS_2) }
+}
+
+fn mul_mont(a: &[Limb; FP_LIMBS], b: &[Limb; FP_LIMBS]) -> [Limb; FP_LIMBS] {
+    let mut r = [0; FP_LIMBS];
+    unsafe {
+        GFp_bls12_381_elem_mul_mont(r.as_mut_ptr(), a.as_ptr(), b.as_ptr());
+    }
+    r
+}
+
+fn limbs_from_big_endian(bytes: &[u8; FP_LEN]) -> [Limb; FP_LIMBS] {
+    let mut limbs = [0; FP_LIMBS];
+    for i in 0..FP_LIMBS {
+        let mut limb = 0;
+        for j in 0..LIMB_BYTES {
+            limb = (limb << 8) |
+                   (bytes[((FP_LIMBS - i - 1) * LIMB_BYTES) + j] as Limb);
+        }
+        limbs[i] = limb;
+    }
+    limbs
+}
+
+
+/// An element `c0 + c1*u` of Fp2.
+#[derive(Clone, Copy)]
+pub struct Fp2 {
+    pub c0: Fp,
+    pub c1: Fp,
+}
+
+impl Fp2 {
+    #[inline]
+    pub fn new(c0: Fp, c1: Fp) -> Fp2 { Fp2 { c0: c0, c1: c1 } }
+
+    pub fn from_plain(c0: &[Limb; FP_LIMBS], c1: &[Limb; FP_LIMBS]) -> Fp2 {
+        Fp2::new(Fp::from_plain(c0), Fp::from_plain(c1))
+    }
+
+    #[inline]
+    pub fn mul_by_fp(&self, b: &Fp) -> Fp2 {
+        Fp2::new(self.c0.mul(b), self.c1.mul(b))
+    }
+
+    /// Multiplies by the non-residue `u + 1` used to construct Fp6.
+    #[inline]
+    pub fn mul_by_nonresidue(&self) -> Fp2 {
+        Fp2::new(self.c0.sub(&self.c1), self.c0.add(&self.c1))
+    }
+
+    /// `sgn0` from RFC 9380 Section 4.1.
+    pub fn sgn0(&self) -> bool {
+        self.c0.is_odd() || (self.c0.is_zero() && self.c1.is_odd())
+    }
+
+    /// Whether the value is greater than its negation, comparing `c1` first
+    /// and then `c0`.
+    pub fn is_lexicographically_largest(&self) -> bool {
+        if self.c1.is_zero() {
+            self.c0.is_lexicographically_largest()
+        } else {
+            self.c1.is_lexicographically_largest()
+        }
+    }
+
+    /// Returns the square root of `self`, if it is a square, using Algorithm
+    /// 9 of https://eprint.iacr.org/2012/685.
+    pub fn sqrt(&self) -> Option<Fp2> {
+        if self.is_zero() {
+            return Some(Fp2::zero());
+        }
+        let a1 = self.pow(&Q_MINUS_3_OVER_4);
+        let alpha = a1.square().mul(self);
+        let x0 = a1.mul(self);
+        let x = if alpha.is_equal(&Fp2::one().neg()) {
+            // x = u * x0.
+            Fp2::new(x0.c1.neg(), x0.c0)
+        } else {
+            alpha.add(&Fp2::one()).pow(&Q_MINUS_1_OVER_2).mul(&x0)
+        };
+        if x.square().is_equal(self) { Some(x) } else { None }
+    }
+}
+
+impl Field for Fp2 {
+    #[inline]
+    fn zero() -> Fp2 { Fp2::new(Fp::zero(), Fp::zero()) }
+
+    #[inline]
+    fn one() -> Fp2 { Fp2::new(Fp::one(), Fp::zero()) }
+
+    #[inline]
+    fn add(&self, b: &Fp2) -> Fp2 {
+        Fp2::new(self.c0.add(&b.c0), self.c1.add(&b.c1))
+    }
+
+    #[inline]
+    fn sub(&self, b: &Fp2) -> Fp2 {
+        Fp2::new(self.c0.sub(&b.c0), self.c1.sub(&b.c1))
+    }
+
+    fn mul(&self, b: &Fp2) -> Fp2 {
+        // Karatsuba.
+        let t0 = self.c0.mul(&b.c0);
+        let t1 = self.c1.mul(&b.c1);
+        let t2 = self.c0.add(&self.c1).mul(&b.c0.add(&b.c1));
+        Fp2::new(t0.sub(&t1), t2.sub(&t0).sub(&t1))
+    }
+
+    #[inline]
+    fn neg(&self) -> Fp2 { Fp2::new(self.c0.neg(), self.c1.neg()) }
+
+    #[inline]
+    fn is_zero(&self) -> bool { self.c0.is_zero() && self.c1.is_zero() }
+
+    #[inline]
+    fn is_equal(&self, b: &Fp2) -> bool {
+        self.c0.is_equal(&b.c0) && self.c1.is_equal(&b.c1)
+    }
+
+    fn invert(&self) -> Fp2 {
+        // (c0 - c1*u) / (c0**2 + c1**2).
+        let t = self.c0.square().add(&self.c1.square()).invert();
+        Fp2::new(self.c0.mul(&t), self.c1.mul(&t).neg())
+    }
+}
+
+
+/// An element `c0 + c1*v + c2*v**2` of Fp6.
+#[derive(Clone, Copy)]
+pub struct Fp6 {
+    pub c0: Fp2,
+    pub c1: Fp2,
+    pub c2: Fp2,
+}
+
+impl Fp6 {
+    #[inline]
+    pub fn new(c0: Fp2, c1: Fp2, c2: Fp2) -> Fp6 {
+        Fp6 { c0: c0, c1: c1, c2: c2 }
+    }
+
+    #[inline]
+    pub fn zero() -> Fp6 { Fp6::new(Fp2::zero(), Fp2::zero(), Fp2::zero()) }
+
+    #[inline]
+    fn one() -> Fp6 { Fp6::new(Fp2::one(), Fp2::zero(), Fp2::zero()) }
+
+    fn add(&self, b: &Fp6) -> Fp6 {
+        Fp6::new(self.c0.add(&b.c0), self.c1.add(&b.c1), self.c2.add(&b.c2))
+    }
+
+    fn sub(&self, b: &Fp6) -> Fp6 {
+        Fp6::new(self.c0.sub(&b.c0), self.c1.sub(&b.c1), self.c2.sub(&b.c2))
+    }
+
+    fn neg(&self) -> Fp6 {
+        Fp6::new(self.c0.neg(), self.c1.neg(), self.c2.neg())
+    }
+
+    fn mul(&self, b: &Fp6) -> Fp6 {
+        let (a0, a1, a2) = (&self.c0, &self.c1, &self.c2);
+        let (b0, b1, b2) = (&b.c0, &b.c1, &b.c2);
+        Fp6::new(
+            a0.mul(b0).add(&a1.mul(b2).add(&a2.mul(b1)).mul_by_nonresidue()),
+            a0.mul(b1).add(&a1.mul(b0)).add(&a2.mul(b2).mul_by_nonresidue()),
+            a0.mul(b2).add(&a1.mul(b1)).add(&a2.mul(b0)))
+    }
+
+    /// Multiplies by `v`.
+    fn mul_by_v(&self) -> Fp6 {
+        Fp6::new(self.c2.mul_by_nonresidue(), self.c0, self.c1)
+    }
+
+    fn is_equal(&self, b: &Fp6) -> bool {
+        self.c0.is_equal(&b.c0) && self.c1.is_equal(&b.c1) &&
+            self.c2.is_equal(&b.c2)
+    }
+
+    fn invert(&self) -> Fp6 {
+        let (a0, a1, a2) = (&self.c0, &self.c1, &self.c2);
+        let t0 = a0.square().sub(&a1.mul(a2).mul_by_nonresidue());
+        let t1 = a2.square().mul_by_nonresidue().sub(&a0.mul(a1));
+        let t2 = a1.square().sub(&a0.mul(a2));
+        let d = a0.mul(&t0)
+                  .add(&a2.mul(&t1).add(&a1.mul(&t2)).mul_by_nonresidue())
+                  .invert();
+        Fp6::new(t0.mul(&d), t1.mul(&d), t2.mul(&d))
+    }
+}
+
+
+/// An element `c0 + c1*w` of Fp12.
+#[derive(Clone, Copy)]
+pub struct Fp12 {
+    pub c0: Fp6,
+    pub c1: Fp6,
+}
+
+// The constants for the Frobenius map x -> x**(q**2): gamma**i, where
+// gamma = (u + 1)**((q**2 - 1) / 6), which is in Fp.
+static FROBENIUS2_GAMMA: [[Limb; FP_LIMBS]; 5] = [
+    fp![0x00000000, 0x00000000, 0x5f19672f, 0xdf76ce51,
+        0xba69c607, 0x6a0f77ea, 0xddb3a93b, 0xe6f89688,
+        0xde17d813, 0x620a0002, 0x2e01ffff, 0xfffeffff],
+    fp![0x00000000, 0x00000000, 0x5f19672f, 0xdf76ce51,
+        0xba69c607, 0x6a0f77ea, 0xddb3a93b, 0xe6f89688,
+        0xde17d813, 0x620a0002, 0x2e01ffff, 0xfffefffe],
+    fp![0x1a0111ea, 0x397fe69a, 0x4b1ba7b6, 0x434bacd7,
+        0x64774b84, 0xf38512bf, 0x6730d2a0, 0xf6b0f624,
+        0x1eabfffe, 0xb153ffff, 0xb9feffff, 0xffffaaaa],
+    fp![0x1a0111ea, 0x397fe699, 0xec024086, 0x63d4de85,
+        0xaa0d857d, 0x89759ad4, 0x897d2965, 0x0fb85f9b,
+        0x409427eb, 0x4f49fffd, 0x8bfd0000, 0x0000aaac],
+    fp![0x1a0111ea, 0x397fe699, 0xec024086, 0x63d4de85,
+        0xaa0d857d, 0x89759ad4, 0x897d2965, 0x0fb85f9b,
+        0x409427eb, 0x4f49fffd, 0x8bfd0000, 0x0000aaad],
+];
+
+impl Fp12 {
+    #[inline]
+    pub fn new(c0: Fp6, c1: Fp6) -> Fp12 { Fp12 { c0: c0, c1: c1 } }
+
+    #[inline]
+    pub fn one() -> Fp12 { Fp12::new(Fp6::one(), Fp6::zero()) }
+
+    pub fn mul(&self, b: &Fp12) -> Fp12 {
+        // Karatsuba.
+        let t0 = self.c0.mul(&b.c0);
+        let t1 = self.c1.mul(&b.c1);
+        let c1 = self.c0.add(&self.c1).mul(&b.c0.add(&b.c1)).sub(&t0).sub(&t1);
+        Fp12::new(t0.add(&t1.mul_by_v()), c1)
+    }
+
+    #[inline]
+    pub fn square(&self) -> Fp12 { self.mul(self) }
+
+    /// Returns `self**(q**6)`.
+    #[inline]
+    pub fn conjugate(&self) -> Fp12 { Fp12::new(self.c0, self.c1.neg()) }
+
+    pub fn invert(&self) -> Fp12 {
+        let t = self.c0.mul(&self.c0).sub(&self.c1.mul(&self.c1).mul_by_v())
+                       .invert();
+        Fp12::new(self.c0.mul(&t), self.c1.mul(&t).neg())
+    }
+
+    /// Returns `self**(q**2)`.
+    pub fn frobenius2(&self) -> Fp12 {
+        // With self = sum(a_i * w**i), self**(q**2) = sum(a_i * gamma**i *
+        // w**i), since a_i is in Fp2 and w**(q**2) = gamma * w.
+        let g: [Fp; 5] = [
+            Fp::from_plain(&FROBENIUS2_GAMMA[0]),
+            Fp::from_plain(&FROBENIUS2_GAMMA[1]),
+            Fp::from_plain(&FROBENIUS2_GAMMA[2]),
+            Fp::from_plain(&FROBENIUS2_GAMMA[3]),
+            Fp::from_plain(&FROBENIUS2_GAMMA[4]),
+        ];
+        // c0 holds the coefficients of w**0, w**2, w**4 and c1 those of w**1,
+        // w**3, w**5.
+        Fp12::new(
+            Fp6::new(self.c0.c0, self.c0.c1.mul_by_fp(&g[1]),
+                     self.c0.c2.mul_by_fp(&g[3])),
+            Fp6::new(self.c1.c0.mul_by_fp(&g[0]), self.c1.c1.mul_by_fp(&g[2]),
+                     self.c1.c2.mul_by_fp(&g[4])))
+    }
+
+    /// Returns `self**e`, where `e` is big-endian encoded.
+    pub fn pow(&self, e: &[u8]) -> Fp12 {
+        let mut acc = Fp12::one();
+        for byte in e {
+            for i in (0..8).rev() {
+                acc = acc.square();
+                if (byte >> i) & 1 == 1 {
+                    acc = acc.mul(self);
+                }
+            }
+        }
+        acc
+    }
+
+    pub fn is_one(&self) -> bool {
+        self.c0.is_equal(&Fp6::one()) && self.c1.is_equal(&Fp6::zero())
+    }
+}
+
+
+extern {
+    fn GFp_bls12_381_elem_add(r: *mut Limb, a: *const Limb, b: *const Limb);
+    fn GFp_bls12_381_elem_mul_mont(r: *mut Limb, a: *const Limb,
+                                   b: *const Limb);
+    fn GFp_bls12_381_elem_neg(r: *mut Limb, a: *const Limb);
+    fn GFp_bls12_381_elem_sub(r: *mut Limb, a: *const Limb, b: *const Limb);
+}
@@ -0,0 +1,226 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The BLS12381G2_XMD:SHA-256_SSWU_RO_ hash-to-curve suite of
+//! [RFC 9380 Section 8.8.2].
+//!
+//! [RFC 9380 Section 8.8.2]:
+//!     https://www.rfc-editor.org/rfc/rfc9380.html#section-8.8.2
+
+use {digest, error};
+use hash_to_curve::expand_message_xmd;
+use limb::*;
+use super::curve::*;
+use super::field::*;
+
+/// Hashes `msg` to a point in G2 using the domain separation tag `dst`.
+pub fn hash_to_g2(msg: &[u8], dst: &[u8])
+                  -> Result<Point<Fp2>, error::Unspecified> {
+    let mut uniform_bytes = [0u8; 4 * HASH_TO_FIELD_CHUNK_LEN];
+    try!(expand_message_xmd(&digest::SHA256, msg, dst, &mut uniform_bytes));
+    let (u0, u1) = uniform_bytes.split_at(2 * HASH_TO_FIELD_CHUNK_LEN);
+    let q0 = iso_map(&map_to_curve_sswu(&fp2_from_uniform_bytes(u0)));
+    let q1 = iso_map(&map_to_curve_sswu(&fp2_from_uniform_bytes(u1)));
+    Ok(q0.add(&q1).mul(&H_EFF))
+}
+
+// L in RFC 9380 Section 5.
+const HASH_TO_FIELD_CHUNK_LEN: usize = 64;
+
+// Reduces two 64-byte big-endian values mod q to produce an element of Fp2.
+fn fp2_from_uniform_bytes(bytes: &[u8]) -> Fp2 {
+    let (c0, c1) = bytes.split_at(HASH_TO_FIELD_CHUNK_LEN);
+    Fp2::new(fp_from_uniform_bytes(c0), fp_from_uniform_bytes(c1))
+}
+
+fn fp_from_uniform_bytes(bytes: &[u8]) -> Fp {
+    // hi * 2**256 + lo, where 2**256 < q.
+    static TWO_256: [Limb; FP_LIMBS] =
+        fp![0x00000000, 0x00000000, 0x00000000, 0x00000001,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000,
+            0x00000000, 0x00000000, 0x00000000, 0x00000000];
+    let (hi, lo) = bytes.split_at(32);
+    let hi = Fp::from_be_bytes_256(slice_as_array_ref!(hi, 32).unwrap());
+    let lo = Fp::from_be_bytes_256(slice_as_array_ref!(lo, 32).unwrap());
+    hi.mul(&Fp::from_plain(&TWO_256)).add(&lo)
+}
+
+/// The simplified SWU map of RFC 9380 Section 6.6.2 onto the curve E'',
+/// which is 3-isogenous to E'. This isn't constant-time.
+fn map_to_curve_sswu(u: &Fp2) -> Affine<Fp2> {
+    // A'' = 240 * u, B'' = 1012 * (1 + u), Z = -(2 + u).
+    let a = Fp2::new(Fp::zero(), Fp::from_limb(240));
+    let b = Fp2::new(Fp::from_limb(1012), Fp::from_limb(1012));
+    let z = Fp2::new(Fp::from_limb(2), Fp::one()).neg();
+
+    let g = |x: &Fp2| x.square().mul(x).add(&a.mul(x)).add(&b);
+
+    let z_u2 = z.mul(&u.square());
+    let tv1 = z_u2.square().add(&z_u2);
+    let x1 = if tv1.is_zero() {
+        b.mul(&z.mul(&a).invert())
+    } else {
+        b.neg().mul(&a.invert()).mul(&Fp2::one().add(&tv1.invert()))
+    };
+    let (x, mut y) = match g(&x1).sqrt() {
+        Some(y1) => (x1, y1),
+        None => {
+            let x2 = z_u2.mul(&x1);
+            // g(x2) is square whenever g(x1) isn't.
+            (x2, g(&x2).sqrt().unwrap())
+        },
+    };
+    if u.sgn0() != y.sgn0() {
+        y = y.neg();
+    }
+    Affine { x: x, y: y }
+}
+
+/// The 3-isogeny map from E'' to E' of RFC 9380 Appendix E.3.
+fn iso_map(p: &Affine<Fp2>) -> Point<Fp2> {
+    let x_num = eval(&XNUM, &p.x);
+    let x_den = eval(&XDEN, &p.x);
+    let y_num = eval(&YNUM, &p.x);
+    let y_den = eval(&YDEN, &p.x);
+    if x_den.is_zero() || y_den.is_zero() {
+        return Point::infinity();
+    }
+    Point::from_affine(&Affine {
+        x: x_num.mul(&x_den.invert()),
+        y: p.y.mul(&y_num).mul(&y_den.invert()),
+    })
+}
+
+// Evaluates the polynomial with coefficients `k` (lowest degree first).
+fn eval(k: &[[[Limb; FP_LIMBS]; 2]], x: &Fp2) -> Fp2 {
+    k.iter().rev().fold(Fp2::zero(), |acc, k_i| {
+        acc.mul(x).add(&Fp2::from_plain(&k_i[0], &k_i[1]))
+    })
+}
+
+// h_eff for G2, from RFC 9380 Section 8.8.2.
+const H_EFF: [u8; 80] = [
+    0x0b, 0xc6, 0x9f, 0x08, 0xf2, 0xee, 0x75, 0xb3, 0x58, 0x4c, 0x6a, 0x0e,
+    0xa9, 0x1b, 0x35, 0x28, 0x88, 0xe2, 0xa8, 0xe9, 0x14, 0x5a, 0xd7, 0x68,
+    0x99, 0x86, 0xff, 0x03, 0x15, 0x08, 0xff, 0xe1, 0x32, 0x9c, 0x2f, 0x17,
+    0x87, 0x31, 0xdb, 0x95, 0x6d, 0x82, 0xbf, 0x01, 0x5d, 0x12, 0x12, 0xb0,
+    0x2e, 0xc0, 0xec, 0x69, 0xd7, 0x47, 0x7c, 0x1a, 0xe9, 0x54, 0xcb, 0xc0,
+    0x66, 0x89, 0xf6, 0xa3, 0x59, 0x89, 0x4c, 0x0a, 0xde, 0xbb, 0xf6, 0xb4,
+    0xe8, 0x02, 0x00, 0x05, 0xaa, 0xa9, 0x55, 0x51,
+];
+
+// The coefficients of the 3-isogeny map, as (c0, c1) pairs, lowest degree
+// first.
+
+static XNUM: [[[Limb; FP_LIMBS]; 2]; 4] = [
+    [fp![0x05c75950, 0x7e8e333e, 0xbb5b7a9a, 0x47d7ed85,
+         0x32c52d39, 0xfd3a042a, 0x88b58423, 0xc50ae15d,
+         0x5c2638e3, 0x43d9c71c, 0x6238aaaa, 0xaaaa97d6],
+     fp![0x05c75950, 0x7e8e333e, 0xbb5b7a9a, 0x47d7ed85,
+         0x32c52d39, 0xfd3a042a, 0x88b58423, 0xc50ae15d,
+         0x5c2638e3, 0x43d9c71c, 0x6238aaaa, 0xaaaa97d6]],
+    [fp![0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000],
+     fp![0x11560bf1, 0x7baa99bc, 0x32126fce, 0xd787c88f,
+         0x984f87ad, 0xf7ae0c7f, 0x9a208c6b, 0x4f20a418,
+         0x1472aaa9, 0xcb8d5555, 0x26a9ffff, 0xffffc71a]],
+    [fp![0x11560bf1, 0x7baa99bc, 0x32126fce, 0xd787c88f,
+         0x984f87ad, 0xf7ae0c7f, 0x9a208c6b, 0x4f20a418,
+         0x1472aaa9, 0xcb8d5555, 0x26a9ffff, 0xffffc71e],
+     fp![0x08ab05f8, 0xbdd54cde, 0x190937e7, 0x6bc3e447,
+         0xcc27c3d6, 0xfbd7063f, 0xcd104635, 0xa790520c,
+         0x0a395554, 0xe5c6aaaa, 0x9354ffff, 0xffffe38d]],
+    [fp![0x171d6541, 0xfa38ccfa, 0xed6dea69, 0x1f5fb614,
+         0xcb14b4e7, 0xf4e810aa, 0x22d6108f, 0x142b8575,
+         0x7098e38d, 0x0f671c71, 0x88e2aaaa, 0xaaaa5ed1],
+     fp![0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000]],
+];
+
+static XDEN: [[[Limb; FP_LIMBS]; 2]; 3] = [
+    [fp![0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000],
+     fp![0x1a0111ea, 0x397fe69a, 0x4b1ba7b6, 0x434bacd7,
+         0x64774b84, 0xf38512bf, 0x6730d2a0, 0xf6b0f624,
+         0x1eabfffe, 0xb153ffff, 0xb9feffff, 0xffffaa63]],
+    [fp![0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x0000000c],
+     fp![0x1a0111ea, 0x397fe69a, 0x4b1ba7b6, 0x434bacd7,
+         0x64774b84, 0xf38512bf, 0x6730d2a0, 0xf6b0f624,
+         0x1eabfffe, 0xb153ffff, 0xb9feffff, 0xffffaa9f]],
+    [fp![0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000001],
+     fp![0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000]],
+];
+
+static YNUM: [[[Limb; FP_LIMBS]; 2]; 4] = [
+    [fp![0x1530477c, 0x7ab4113b, 0x59a4c18b, 0x076d1193,
+         0x0f7da5d4, 0xa07f649b, 0xf54439d8, 0x7d27e500,
+         0xfc8c25eb, 0xf8c92f68, 0x12cfc71c, 0x71c6d706],
+     fp![0x1530477c, 0x7ab4113b, 0x59a4c18b, 0x076d1193,
+         0x0f7da5d4, 0xa07f649b, 0xf54439d8, 0x7d27e500,
+         0xfc8c25eb, 0xf8c92f68, 0x12cfc71c, 0x71c6d706]],
+    [fp![0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000],
+     fp![0x05c75950, 0x7e8e333e, 0xbb5b7a9a, 0x47d7ed85,
+         0x32c52d39, 0xfd3a042a, 0x88b58423, 0xc50ae15d,
+         0x5c2638e3, 0x43d9c71c, 0x6238aaaa, 0xaaaa97be]],
+    [fp![0x11560bf1, 0x7baa99bc, 0x32126fce, 0xd787c88f,
+         0x984f87ad, 0xf7ae0c7f, 0x9a208c6b, 0x4f20a418,
+         0x1472aaa9, 0xcb8d5555, 0x26a9ffff, 0xffffc71c],
+     fp![0x08ab05f8, 0xbdd54cde, 0x190937e7, 0x6bc3e447,
+         0xcc27c3d6, 0xfbd7063f, 0xcd104635, 0xa790520c,
+         0x0a395554, 0xe5c6aaaa, 0x9354ffff, 0xffffe38f]],
+    [fp![0x124c9ad4, 0x3b6cf79b, 0xfbf7043d, 0xe3811ad0,
+         0x761b0f37, 0xa1e26286, 0xb0e977c6, 0x9aa27452,
+         0x4e79097a, 0x56dc4bd9, 0xe1b371c7, 0x1c718b10],
+     fp![0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000]],
+];
+
+static YDEN: [[[Limb; FP_LIMBS]; 2]; 4] = [
+    [fp![0x1a0111ea, 0x397fe69a, 0x4b1ba7b6, 0x434bacd7,
+         0x64774b84, 0xf38512bf, 0x6730d2a0, 0xf6b0f624,
+         0x1eabfffe, 0xb153ffff, 0xb9feffff, 0xffffa8fb],
+     fp![0x1a0111ea, 0x397fe69a, 0x4b1ba7b6, 0x434bacd7,
+         0x64774b84, 0xf38512bf, 0x6730d2a0, 0xf6b0f624,
+         0x1eabfffe, 0xb153ffff, 0xb9feffff, 0xffffa8fb]],
+    [fp![0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000],
+     fp![0x1a0111ea, 0x397fe69a, 0x4b1ba7b6, 0x434bacd7,
+         0x64774b84, 0xf38512bf, 0x6730d2a0, 0xf6b0f624,
+         0x1eabfffe, 0xb153ffff, 0xb9feffff, 0xffffa9d3]],
+    [fp![0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000012],
+     fp![0x1a0111ea, 0x397fe69a, 0x4b1ba7b6, 0x434bacd7,
+         0x64774b84, 0xf38512bf, 0x6730d2a0, 0xf6b0f624,
+         0x1eabfffe, 0xb153ffff, 0xb9feffff, 0xffffaa99]],
+    [fp![0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000001],
+     fp![0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000,
+         0x00000000, 0x00000000, 0x00000000, 0x00000000]],
+];
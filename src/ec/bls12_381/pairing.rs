@@ -0,0 +1,104 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The optimal ate pairing e: G1 x G2 -> GT.
+//!
+//! This is a straightforward implementation in affine coordinates. It is
+//! slow but it is easy to check against the definition, and it is only used
+//! on public values.
+
+use super::curve::*;
+use super::field::*;
+
+/// Returns the product of the Miller loop outputs for each pair, before the
+/// final exponentiation. `final_exponentiation(multi_miller_loop(...))` is
+/// the product of the pairings.
+pub fn multi_miller_loop(pairs: &[(&Affine<Fp>, &Affine<Fp2>)]) -> Fp12 {
+    pairs.iter().fold(Fp12::one(), |acc, &(p, q)| acc.mul(&miller_loop(p, q)))
+}
+
+fn miller_loop(p: &Affine<Fp>, q: &Affine<Fp2>) -> Fp12 {
+    let mut f = Fp12::one();
+    let mut t = *q;
+    // T starts as Q, so the leading bit of |x| (the top bit of `X_ABS[0]`) is
+    // skipped.
+    let bits = X_ABS.iter()
+                    .flat_map(|b| (0..8).rev().map(move |i| (b >> i) & 1))
+                    .skip(1);
+    for bit in bits {
+        // Doubling step: the tangent at T.
+        let x2 = t.x.square();
+        let lambda = x2.double().add(&x2).mul(&t.y.double().invert());
+        f = f.square().mul(&line(&lambda, &t, p));
+        t = affine_add(&lambda, &t, &t);
+
+        if bit == 1 {
+            // Addition step: the line through T and Q.
+            let lambda = q.y.sub(&t.y).mul(&q.x.sub(&t.x).invert());
+            f = f.mul(&line(&lambda, &t, p));
+            t = affine_add(&lambda, &t, q);
+        }
+    }
+    // x is negative.
+    f.conjugate()
+}
+
+// The line with slope `lambda` through T, evaluated at P, and mapped to Fp12
+// via the untwisting isomorphism.
+fn line(lambda: &Fp2, t: &Affine<Fp2>, p: &Affine<Fp>) -> Fp12 {
+    Fp12::new(
+        Fp6::new(lambda.mul(&t.x).sub(&t.y), lambda.mul_by_fp(&p.x).neg(),
+                 Fp2::zero()),
+        Fp6::new(Fp2::zero(), Fp2::new(p.y, Fp::zero()), Fp2::zero()))
+}
+
+// Returns `a + b`, given the slope `lambda` of the line through them (or of
+// the tangent, when they are equal). Neither the inputs nor the result may
+// be the point at infinity; that can't happen in the Miller loop for points
+// of order r.
+fn affine_add(lambda: &Fp2, a: &Affine<Fp2>, b: &Affine<Fp2>) -> Affine<Fp2> {
+    let x3 = lambda.square().sub(&a.x).sub(&b.x);
+    let y3 = lambda.mul(&a.x.sub(&x3)).sub(&a.y);
+    Affine { x: x3, y: y3 }
+}
+
+/// Raises `f` to the power (q**12 - 1) / r.
+pub fn final_exponentiation(f: &Fp12) -> Fp12 {
+    // The easy part: (q**6 - 1) * (q**2 + 1).
+    let f = f.conjugate().mul(&f.invert());
+    let f = f.frobenius2().mul(&f);
+    // The hard part: (q**4 - q**2 + 1) / r.
+    f.pow(&FINAL_EXP_HARD)
+}
+
+// |x|, where x = -0xd201000000010000 is the BLS parameter.
+const X_ABS: [u8; 8] = [0xd2, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00];
+
+// (q**4 - q**2 + 1) / r.
+const FINAL_EXP_HARD: [u8; 159] = [
+    0x0f, 0x68, 0x6b, 0x3d, 0x80, 0x7d, 0x01, 0xc0, 0xbd, 0x38, 0xc3, 0x19,
+    0x5c, 0x89, 0x9e, 0xd3, 0xcd, 0xe8, 0x8e, 0xeb, 0x99, 0x6c, 0xa3, 0x94,
+    0x50, 0x66, 0x32, 0x52, 0x8d, 0x6a, 0x9a, 0x2f, 0x23, 0x00, 0x63, 0xcf,
+    0x08, 0x15, 0x17, 0xf6, 0x8f, 0x77, 0x64, 0xc2, 0x8b, 0x6f, 0x8a, 0xe5,
+    0xa7, 0x2b, 0xce, 0x8d, 0x63, 0xcb, 0x9f, 0x82, 0x7e, 0xca, 0x0b, 0xa6,
+    0x21, 0x31, 0x5b, 0x20, 0x76, 0x99, 0x50, 0x03, 0xfc, 0x77, 0xa1, 0x79,
+    0x88, 0xf8, 0x76, 0x1b, 0xdc, 0x51, 0xdc, 0x23, 0x78, 0xb9, 0x03, 0x90,
+    0x96, 0xd1, 0xb7, 0x67, 0xf1, 0x7f, 0xcb, 0xde, 0x78, 0x37, 0x65, 0x91,
+    0x5c, 0x97, 0xf3, 0x6c, 0x6f, 0x18, 0x21, 0x2e, 0xd0, 0xb2, 0x83, 0xed,
+    0x23, 0x7d, 0xb4, 0x21, 0xd1, 0x60, 0xae, 0xb6, 0xa1, 0xe7, 0x99, 0x83,
+    0x77, 0x49, 0x40, 0x99, 0x67, 0x54, 0xc8, 0xc7, 0x1a, 0x26, 0x29, 0xb0,
+    0xde, 0xa2, 0x36, 0x90, 0x5c, 0xe9, 0x37, 0x33, 0x5d, 0x5b, 0x68, 0xfa,
+    0x99, 0x12, 0xaa, 0xe2, 0x08, 0xcc, 0xf1, 0xe5, 0x16, 0xc3, 0xf4, 0x38,
+    0xe3, 0xba, 0x79,
+];
@@ -0,0 +1,271 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Arithmetic in GF(2**255 - 19), the field of Curve25519 and Ed25519.
+//!
+//! This is for implementing encodings and maps that *ring* doesn't provide,
+//! such as Elligator2, on top of the same constant-time field arithmetic that
+//! X25519 and Ed25519 use. All the operations, including `inverse` and
+//! `sqrt_ratio_m1`, take the same amount of time for all inputs. Conditions
+//! are returned as choice bits, i.e. 0 or 1, which can be used with
+//! `FieldElement::select` and the `constant_time` module without branching.
+//!
+//! Feature: `hazmat`.
+
+use {c, constant_time, error};
+use untrusted;
+
+/// The length of an encoded field element.
+pub const ELEM_LEN: usize = 32;
+
+/// An element of GF(2**255 - 19).
+#[derive(Clone, Copy)]
+pub struct FieldElement {
+    // The canonical little-endian encoding.
+    bytes: [u8; ELEM_LEN],
+}
+
+impl FieldElement {
+    /// Parses the canonical little-endian encoding of a field element. The
+    /// encoding must be less than 2**255 - 19; in particular, its most
+    /// significant bit must be zero.
+    pub fn from_bytes(input: untrusted::Input)
+                      -> Result<FieldElement, error::Unspecified> {
+        let bytes = try!(slice_as_array_ref!(input.as_slice_less_safe(),
+                                             ELEM_LEN));
+        let elem = FieldElement::from_bytes_mod_p(bytes);
+        try!(constant_time::verify_slices_are_equal(&elem.bytes, bytes));
+        Ok(elem)
+    }
+
+    /// Decodes `bytes` the way X25519 decodes u-coordinates: the most
+    /// significant bit is ignored and the remaining 255-bit value is reduced
+    /// mod 2**255 - 19. This is also how Elligator2 representatives are
+    /// decoded.
+    pub fn from_bytes_mod_p(bytes: &[u8; ELEM_LEN]) -> FieldElement {
+        let mut r = FieldElement::zero();
+        unsafe {
+            GFp_curve25519_fe_reduce(r.bytes.as_mut_ptr(), bytes.as_ptr())
+        };
+        r
+    }
+
+    /// The field element zero.
+    #[inline]
+    pub fn zero() -> FieldElement { FieldElement { bytes: [0u8; ELEM_LEN] } }
+
+    /// The field element one.
+    #[inline]
+    pub fn one() -> FieldElement {
+        let mut r = FieldElement::zero();
+        r.bytes[0] = 1;
+        r
+    }
+
+    /// The canonical little-endian encoding of the element.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8; ELEM_LEN] { &self.bytes }
+
+    /// Returns `self + b`.
+    pub fn sum(&self, b: &FieldElement) -> FieldElement {
+        self.binary_op(b, GFp_curve25519_fe_add)
+    }
+
+    /// Returns `self - b`.
+    pub fn difference(&self, b: &FieldElement) -> FieldElement {
+        self.binary_op(b, GFp_curve25519_fe_sub)
+    }
+
+    /// Returns `self * b`.
+    pub fn product(&self, b: &FieldElement) -> FieldElement {
+        self.binary_op(b, GFp_curve25519_fe_mul)
+    }
+
+    /// Returns `self * self`.
+    #[inline]
+    pub fn square(&self) -> FieldElement { self.product(self) }
+
+    /// Returns `-self`.
+    pub fn negation(&self) -> FieldElement {
+        self.unary_op(GFp_curve25519_fe_neg)
+    }
+
+    /// Returns `1 / self`, or zero if `self` is zero.
+    pub fn inverse(&self) -> FieldElement {
+        self.unary_op(GFp_curve25519_fe_invert)
+    }
+
+    /// `SQRT_RATIO_M1(u, v)` from [RFC 9496 Section 4.2]. Returns 1 and the
+    /// non-negative square root of `u/v` if `u/v` is square, and 0 and the
+    /// non-negative square root of `sqrt(-1) * u/v` otherwise. If `v` is zero
+    /// then the result is (1, 0) when `u` is zero and (0, 0) otherwise.
+    ///
+    /// [RFC 9496 Section 4.2]:
+    ///     https://www.rfc-editor.org/rfc/rfc9496.html#section-4.2
+    pub fn sqrt_ratio_m1(u: &FieldElement, v: &FieldElement)
+                         -> (u8, FieldElement) {
+        let mut r = FieldElement::zero();
+        let was_square = unsafe {
+            GFp_curve25519_fe_sqrt_ratio_m1(r.bytes.as_mut_ptr(),
+                                            u.bytes.as_ptr(), v.bytes.as_ptr())
+        };
+        (was_square as u8, r)
+    }
+
+    /// Returns 1 if `self` is zero and 0 otherwise.
+    pub fn is_zero(&self) -> u8 {
+        let acc = self.bytes.iter().fold(0u32, |acc, b| acc | u32::from(*b));
+        constant_time::less_than(acc, 1)
+    }
+
+    /// Returns 1 if `self` is negative, i.e. its encoding is odd, and 0
+    /// otherwise, as in RFC 9496.
+    #[inline]
+    pub fn is_negative(&self) -> u8 { self.bytes[0] & 1 }
+
+    /// Returns `a` if `choice` is 1 and `b` if `choice` is 0, in constant
+    /// time. `choice` must be 0 or 1.
+    pub fn select(choice: u8, a: &FieldElement, b: &FieldElement)
+                  -> FieldElement {
+        let mut r = *b;
+        constant_time::copy_if(choice, &mut r.bytes, &a.bytes).unwrap();
+        r
+    }
+
+    fn unary_op(&self,
+                f: unsafe extern fn(out: *mut u8, a: *const u8))
+                -> FieldElement {
+        let mut r = FieldElement::zero();
+        unsafe { f(r.bytes.as_mut_ptr(), self.bytes.as_ptr()) };
+        r
+    }
+
+    fn binary_op(&self, b: &FieldElement,
+                 f: unsafe extern fn(out: *mut u8, a: *const u8,
+                                     b: *const u8))
+                 -> FieldElement {
+        let mut r = FieldElement::zero();
+        unsafe {
+            f(r.bytes.as_mut_ptr(), self.bytes.as_ptr(), b.bytes.as_ptr())
+        };
+        r
+    }
+}
+
+extern {
+    fn GFp_curve25519_fe_reduce(out: *mut u8/*[32]*/, a: *const u8/*[32]*/);
+    fn GFp_curve25519_fe_add(out: *mut u8/*[32]*/, a: *const u8/*[32]*/,
+                             b: *const u8/*[32]*/);
+    fn GFp_curve25519_fe_sub(out: *mut u8/*[32]*/, a: *const u8/*[32]*/,
+                             b: *const u8/*[32]*/);
+    fn GFp_curve25519_fe_mul(out: *mut u8/*[32]*/, a: *const u8/*[32]*/,
+                             b: *const u8/*[32]*/);
+    fn GFp_curve25519_fe_neg(out: *mut u8/*[32]*/, a: *const u8/*[32]*/);
+    fn GFp_curve25519_fe_invert(out: *mut u8/*[32]*/, a: *const u8/*[32]*/);
+    fn GFp_curve25519_fe_sqrt_ratio_m1(out: *mut u8/*[32]*/,
+                                       u: *const u8/*[32]*/,
+                                       v: *const u8/*[32]*/) -> c::int;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test;
+    use untrusted;
+
+    fn consume_elem(test_case: &mut test::TestCase, name: &str)
+                    -> FieldElement {
+        let bytes = test_case.consume_bytes(name);
+        FieldElement::from_bytes(untrusted::Input::from(&bytes)).unwrap()
+    }
+
+    #[test]
+    fn test_curve25519_field() {
+        test::from_file("src/ec/curve25519_field_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+            let a = consume_elem(test_case, "A");
+            let b = consume_elem(test_case, "B");
+            let sum = consume_elem(test_case, "Sum");
+            let difference = consume_elem(test_case, "Difference");
+            let product = consume_elem(test_case, "Product");
+            let negated_a = consume_elem(test_case, "NegatedA");
+            let inverse_a = consume_elem(test_case, "InverseA");
+            let sqrt_ratio = consume_elem(test_case, "SqrtRatio");
+            let was_square = test_case.consume_usize("WasSquare") as u8;
+
+            assert_eq!(a.sum(&b).as_bytes(), sum.as_bytes());
+            assert_eq!(a.difference(&b).as_bytes(), difference.as_bytes());
+            assert_eq!(a.product(&b).as_bytes(), product.as_bytes());
+            assert_eq!(a.negation().as_bytes(), negated_a.as_bytes());
+            assert_eq!(a.inverse().as_bytes(), inverse_a.as_bytes());
+            assert_eq!(a.square().as_bytes(), a.product(&a).as_bytes());
+
+            let (actual_was_square, actual_sqrt_ratio) =
+                FieldElement::sqrt_ratio_m1(&a, &b);
+            assert_eq!(actual_was_square, was_square);
+            assert_eq!(actual_sqrt_ratio.as_bytes(), sqrt_ratio.as_bytes());
+            assert_eq!(actual_sqrt_ratio.is_negative(), 0);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_curve25519_field_encoding() {
+        // p - 1, the largest canonical encoding.
+        let mut p_minus_1 = [0xffu8; ELEM_LEN];
+        p_minus_1[0] = 0xec;
+        p_minus_1[ELEM_LEN - 1] = 0x7f;
+        let elem =
+            FieldElement::from_bytes(untrusted::Input::from(&p_minus_1))
+                .unwrap();
+        assert_eq!(elem.sum(&FieldElement::one()).is_zero(), 1);
+        assert_eq!(elem.is_negative(), 0);
+        assert_eq!(FieldElement::one().is_negative(), 1);
+
+        // p, and a value with the most significant bit set, aren't canonical.
+        // `from_bytes_mod_p` reduces the first and ignores the bit in the
+        // second.
+        let mut p = p_minus_1;
+        p[0] = 0xed;
+        assert!(FieldElement::from_bytes(untrusted::Input::from(&p)).is_err());
+        assert_eq!(FieldElement::from_bytes_mod_p(&p).is_zero(), 1);
+        let mut one_with_high_bit = [0u8; ELEM_LEN];
+        one_with_high_bit[0] = 1;
+        one_with_high_bit[ELEM_LEN - 1] = 0x80;
+        assert!(FieldElement::from_bytes(
+                    untrusted::Input::from(&one_with_high_bit)).is_err());
+        assert_eq!(FieldElement::from_bytes_mod_p(&one_with_high_bit)
+                       .as_bytes(), FieldElement::one().as_bytes());
+
+        assert!(FieldElement::from_bytes(
+                    untrusted::Input::from(&[0u8; ELEM_LEN - 1])).is_err());
+        assert!(FieldElement::from_bytes(
+                    untrusted::Input::from(&[0u8; ELEM_LEN + 1])).is_err());
+    }
+
+    #[test]
+    fn test_curve25519_field_select() {
+        let zero = FieldElement::zero();
+        let one = FieldElement::one();
+        assert_eq!(FieldElement::select(1, &one, &zero).as_bytes(),
+                   one.as_bytes());
+        assert_eq!(FieldElement::select(0, &one, &zero).as_bytes(),
+                   zero.as_bytes());
+        assert_eq!(zero.is_zero(), 1);
+        assert_eq!(one.is_zero(), 0);
+    }
+}
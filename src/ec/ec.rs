@@ -13,20 +13,37 @@
 // CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 
 use {c, error, init, rand};
+use secret::Zeroize;
 use untrusted;
 
 /// A key agreement algorithm.
 #[cfg_attr(not(test), allow(dead_code))]
 pub struct AgreementAlgorithmImpl {
     pub public_key_len: usize,
+
+    // The length of the compressed encoding of public keys, for algorithms
+    // that have one. `public_from_private` produces the compressed encoding
+    // when its output has this length.
+    pub compressed_public_key_len: Option<usize>,
+
     pub elem_and_scalar_len: usize,
 
+    // The length of private keys. This is `elem_and_scalar_len` for the EC
+    // algorithms, but FFDHE exponents are much shorter than group elements.
+    pub private_key_len: usize,
+
     pub nid: c::int,
 
-    generate_private_key: fn(rng: &rand::SecureRandom)
-                             -> Result<PrivateKey, error::Unspecified>,
+    pub name: &'static str,
+    pub security_bits: usize,
+
+    pub generate_private_key: fn(rng: &rand::SecureRandom)
+                                 -> Result<PrivateKey, error::Unspecified>,
+
+    pub private_key_from_seed: fn(seed: &[u8])
+                                  -> Result<PrivateKey, error::Unspecified>,
 
-    public_from_private: fn(public_out: &mut [u8], private_key: &PrivateKey)
+    pub public_from_private: fn(public_out: &mut [u8], private_key: &PrivateKey)
                             -> Result<(), error::Unspecified>,
 
     pub ecdh: fn(out: &mut [u8], private_key: &PrivateKey,
@@ -35,7 +52,7 @@ pub struct AgreementAlgorithmImpl {
 }
 
 pub struct PrivateKey {
-    bytes: [u8; SCALAR_MAX_BYTES],
+    pub bytes: [u8; SCALAR_MAX_BYTES],
 }
 
 impl<'a> PrivateKey {
@@ -45,13 +62,19 @@ impl<'a> PrivateKey {
         (alg.generate_private_key)(rng)
     }
 
+    pub fn from_seed(alg: &AgreementAlgorithmImpl, seed: &[u8])
+                     -> Result<PrivateKey, error::Unspecified> {
+        init::init_once();
+        (alg.private_key_from_seed)(seed)
+    }
+
     #[cfg(test)]
     pub fn from_test_vector(alg: &AgreementAlgorithmImpl, test_vector: &[u8])
                             -> PrivateKey {
         init::init_once();
         let mut result = PrivateKey { bytes: [0; SCALAR_MAX_BYTES] };
         {
-            let private_key_bytes = &mut result.bytes[..alg.elem_and_scalar_len];
+            let private_key_bytes = &mut result.bytes[..alg.private_key_len];
             assert_eq!(test_vector.len(), private_key_bytes.len());
             for i in 0..private_key_bytes.len() {
                 private_key_bytes[i] = test_vector[i];
@@ -72,21 +95,44 @@ impl<'a> PrivateKey {
         }
         (alg.public_from_private)(out, self)
     }
+
+    pub fn compute_public_key_compressed(&self, alg: &AgreementAlgorithmImpl,
+                                         out: &mut [u8])
+                                         -> Result<(), error::Unspecified> {
+        if alg.compressed_public_key_len != Some(out.len()) {
+            return Err(error::Unspecified);
+        }
+        (alg.public_from_private)(out, self)
+    }
+}
+
+impl Drop for PrivateKey {
+    fn drop(&mut self) { self.bytes[..].zeroize(); }
 }
 
 
-const ELEM_MAX_BITS: usize = 384;
+const ELEM_MAX_BITS: usize = 521;
 pub const ELEM_MAX_BYTES: usize = (ELEM_MAX_BITS + 7) / 8;
 
-const SCALAR_MAX_BYTES: usize = ELEM_MAX_BYTES;
+pub const SCALAR_MAX_BYTES: usize = ELEM_MAX_BYTES;
 
 /// The maximum length, in bytes, of an encoded public key.
 pub const PUBLIC_KEY_MAX_LEN: usize = 1 + (2 * ELEM_MAX_BYTES);
 
 
+#[path = "bls12_381/bls12_381.rs"]
+pub mod bls12_381;
+
+// Used by `elligator2`, and exposed as `hazmat::curve25519`.
+#[cfg_attr(not(feature = "hazmat"), allow(dead_code))]
+pub mod curve25519_field;
+
 pub mod eddsa;
+pub mod edwards25519;
+pub mod ristretto255;
 
 #[path = "suite_b/suite_b.rs"]
 pub mod suite_b;
 
 pub mod x25519;
+pub mod x448;
@@ -14,12 +14,25 @@
 
 //! EdDSA Signatures.
 
-use {bssl, c, error, private, rand, signature};
+use {audit, bssl, c, digest, error, init, private, rand, signature};
+use secret::Zeroize;
+use super::edwards25519::{ELEM_LEN, SCALAR_LEN, Scalar};
 use untrusted;
 
+#[cfg(feature = "use_heap")]
+use {executor, std};
+
 /// Parameters for EdDSA signing and verification.
 pub struct EdDSAParameters;
 
+/// Parameters for prehashed EdDSA (e.g. Ed25519ph) verification.
+pub struct EdDSAPrehashedParameters;
+
+/// Parameters for EdDSA verification with a context (e.g. Ed25519ctx).
+pub struct EdDSAContextParameters<'a> {
+    context: &'a [u8],
+}
+
 /// An Ed25519 key pair, for signing.
 pub struct Ed25519KeyPair {
     private_public: [u8; 64],
@@ -47,6 +60,15 @@ impl<'a> Ed25519KeyPair {
     /// `Ed25519KeyPair` and a `Ed25519KeyPairBytes`. There is no way to
     /// extract the private key bytes from an `Ed25519KeyPair`, so extracting
     /// the values from the `Ed25519KeyPairBytes` is the only way to get them.
+    ///
+    /// `private_key` is the 32-byte seed of [RFC 8032 Section 5.1.5], which is
+    /// what most other implementations and formats, e.g. OpenSSH and
+    /// libsodium's `crypto_sign_seed_keypair`, store. Use
+    /// `from_seed_and_public_key` or `from_seed_unchecked` to reconstruct the
+    /// key pair from it.
+    ///
+    /// [RFC 8032 Section 5.1.5]:
+    ///     https://tools.ietf.org/html/rfc8032#section-5.1.5
     pub fn generate_serializable(rng: &rand::SecureRandom)
             -> Result<(Ed25519KeyPair, Ed25519KeyPairBytes),
                       error::Unspecified> {
@@ -62,23 +84,53 @@ impl<'a> Ed25519KeyPair {
         let key_pair =
             try!(Ed25519KeyPair::from_bytes_unchecked(&bytes.private_key,
                                                       &bytes.public_key));
+        audit::notify(audit::EventKind::KeyCreated, "ED25519");
         Ok((key_pair, bytes))
     }
 
-    /// Copies key data from the given slices to create a new key pair. The
-    /// first slice must hold the private key and the second slice must hold
-    /// the public key. Both slices must contain 32 little-endian-encoded
-    /// bytes.
+    /// Constructs the key pair from the 32-byte seed `seed`, computing the
+    /// public key from it.
+    ///
+    /// `seed` is the private key of [RFC 8032 Section 5.1.5], i.e.
+    /// `bytes.private_key` of `generate_serializable`. This is intended for
+    /// formats that store only the seed and for deterministic key derivation
+    /// schemes, such as SLIP-0010, that produce the seed themselves. When the
+    /// public key is stored along with the seed, prefer
+    /// `from_seed_and_public_key`, which detects when the two don't belong
+    /// together. `seed` must be secret and uniformly random.
+    ///
+    /// [RFC 8032 Section 5.1.5]:
+    ///     https://tools.ietf.org/html/rfc8032#section-5.1.5
+    pub fn from_seed_unchecked(seed: &[u8])
+                     -> Result<Ed25519KeyPair, error::Unspecified> {
+        if seed.len() != 32 {
+            return Err(error::Unspecified);
+        }
+        let mut public_key = [0; 32];
+        unsafe {
+            GFp_ed25519_public_from_private(public_key.as_mut_ptr(),
+                                            seed.as_ptr());
+        }
+        let pair = try!(Ed25519KeyPair::from_bytes_unchecked(seed,
+                                                             &public_key));
+        audit::notify(audit::EventKind::KeyCreated, "ED25519");
+        Ok(pair)
+    }
+
+    /// Constructs the key pair from the 32-byte seed `seed` and the 32-byte
+    /// public key `public_key`, e.g. `bytes.private_key` and
+    /// `bytes.public_key` of `generate_serializable`.
     ///
     /// This is intended for use by code that deserializes key pairs.
     ///
-    /// The private and public keys will be verified to be consistent. This
+    /// The seed and public key will be verified to be consistent. This
     /// helps protect, for example, against the accidental swapping of the
     /// public and private components of the key pair. This also detects
     /// corruption that might have occurred during storage of the key pair.
-    pub fn from_bytes(private_key: &[u8], public_key: &[u8])
-                      -> Result<Ed25519KeyPair, error::Unspecified> {
-        let pair = try!(Ed25519KeyPair::from_bytes_unchecked(private_key,
+    pub fn from_seed_and_public_key(seed: &[u8], public_key: &[u8])
+                                    -> Result<Ed25519KeyPair,
+                                              error::Unspecified> {
+        let pair = try!(Ed25519KeyPair::from_bytes_unchecked(seed,
                                                              public_key));
         let mut public_key_check = [0; 32];
         unsafe {
@@ -88,6 +140,7 @@ impl<'a> Ed25519KeyPair {
         if public_key != public_key_check {
             return Err(error::Unspecified);
         }
+        audit::notify(audit::EventKind::KeyCreated, "ED25519");
         Ok(pair)
     }
 
@@ -112,15 +165,81 @@ impl<'a> Ed25519KeyPair {
 
     /// Returns the signature of the message `msg`.
     pub fn sign(&self, msg: &[u8]) -> signature::Signature {
+        self.sign_(&[], msg, "ED25519")
+    }
+
+    /// Returns the [Ed25519ph] signature, with an empty context, of the
+    /// message with SHA-512 digest `digest`.
+    ///
+    /// This allows a large message to be signed without buffering it: the
+    /// digest can be computed incrementally using a `digest::Context`. The
+    /// signature can be verified with `signature::ED25519PH`. Fails if
+    /// `digest` isn't a SHA-512 digest.
+    ///
+    /// [Ed25519ph]: https://tools.ietf.org/html/rfc8032#section-5.1
+    pub fn sign_prehashed(&self, digest: &digest::Digest)
+                          -> Result<signature::Signature, error::Unspecified> {
+        if digest.algorithm().output_len != digest::SHA512.output_len {
+            return Err(error::Unspecified);
+        }
+        Ok(self.sign_(ED25519PH_DOM, digest.as_ref(), "ED25519PH"))
+    }
+
+    /// Returns the [Ed25519ctx] signature of the message `msg` with the
+    /// context `context`.
+    ///
+    /// `context` identifies the protocol the signature is used in, so that a
+    /// signature for one protocol can't be used in another. It must be
+    /// between 1 and 255 bytes long. The signature can be verified using
+    /// `EdDSAContextParameters::ed25519ctx(context)`.
+    ///
+    /// [Ed25519ctx]: https://tools.ietf.org/html/rfc8032#section-5.1
+    pub fn sign_with_context(&self, context: &[u8], msg: &[u8])
+                             -> Result<signature::Signature,
+                                       error::Unspecified> {
+        let mut dom = [0u8; DOM2_MAX_LEN];
+        let dom_len = try!(dom2(&mut dom, 0, context));
+        Ok(self.sign_(&dom[..dom_len], msg, "ED25519CTX"))
+    }
+
+    fn sign_(&self, dom: &[u8], msg: &[u8], alg_name: &'static str)
+             -> signature::Signature {
         let mut signature_bytes = [0u8; 64];
         unsafe {
-            GFp_ed25519_sign(signature_bytes.as_mut_ptr(), msg.as_ptr(),
-                             msg.len(), self.private_public.as_ptr());
+            GFp_ed25519_sign(signature_bytes.as_mut_ptr(), dom.as_ptr(),
+                             dom.len(), msg.as_ptr(), msg.len(),
+                             self.private_public.as_ptr());
         }
+        audit::notify(audit::EventKind::Signed, alg_name);
         signature::Signature::new(signature_bytes)
     }
 }
 
+impl Drop for Ed25519KeyPair {
+    fn drop(&mut self) { self.private_public.zeroize(); }
+}
+
+impl signature::PrehashedSigningKey for Ed25519KeyPair {
+    fn digest_algorithm(&self) -> &'static digest::Algorithm {
+        &digest::SHA512
+    }
+
+    fn sign_prehashed(&self, digest: &digest::Digest)
+                      -> Result<signature::Signature, error::Unspecified> {
+        Ed25519KeyPair::sign_prehashed(self, digest)
+    }
+}
+
+impl signature::KeyPair for Ed25519KeyPair {
+    type PublicKey = [u8; 32];
+
+    fn public_key(&self) -> &[u8; 32] {
+        slice_as_array_ref!(&self.private_public[32..], 32).unwrap()
+    }
+}
+
+impl private::Private for Ed25519KeyPair {}
+
 
 /// Verification of [Ed25519] signatures.
 ///
@@ -132,38 +251,276 @@ pub static ED25519: EdDSAParameters = EdDSAParameters {};
 impl signature::VerificationAlgorithm for EdDSAParameters {
     fn verify(&self, public_key: untrusted::Input, msg: untrusted::Input,
               signature: untrusted::Input) -> Result<(), error::Unspecified> {
-        let public_key = public_key.as_slice_less_safe();
-        if public_key.len() != 32 || signature.len() != 64 {
+        ed25519_verify(&[], public_key, msg.as_slice_less_safe(), signature)
+    }
+
+    fn name(&self) -> &'static str { "ED25519" }
+
+    fn security_bits(&self) -> usize { 128 }
+}
+
+impl private::Private for EdDSAParameters {}
+
+
+/// Verification of [Ed25519ph] signatures, with an empty context.
+///
+/// Ed25519ph signs the SHA-512 digest of the message instead of the message
+/// itself. `verify` computes the digest of the message; `verify_prehashed`
+/// takes a SHA-512 digest that was computed elsewhere.
+///
+/// [Ed25519ph]: https://tools.ietf.org/html/rfc8032#section-5.1
+pub static ED25519PH: EdDSAPrehashedParameters = EdDSAPrehashedParameters {};
+
+// dom2(1, "") from RFC 8032 Section 5.1.
+const ED25519PH_DOM: &'static [u8] =
+    b"SigEd25519 no Ed25519 collisions\x01\x00";
+
+impl signature::VerificationAlgorithm for EdDSAPrehashedParameters {
+    fn verify(&self, public_key: untrusted::Input, msg: untrusted::Input,
+              signature: untrusted::Input) -> Result<(), error::Unspecified> {
+        let digest = digest::digest(&digest::SHA512, msg.as_slice_less_safe());
+        ed25519_verify(ED25519PH_DOM, public_key, digest.as_ref(), signature)
+    }
+
+    fn name(&self) -> &'static str { "ED25519PH" }
+
+    fn security_bits(&self) -> usize { 128 }
+}
+
+impl signature::PrehashedVerificationAlgorithm for EdDSAPrehashedParameters {
+    fn digest_algorithm(&self) -> &'static digest::Algorithm {
+        &digest::SHA512
+    }
+
+    fn verify_prehashed(&self, public_key: untrusted::Input,
+                        digest: untrusted::Input, signature: untrusted::Input)
+                        -> Result<(), error::Unspecified> {
+        if digest.len() != digest::SHA512.output_len {
             return Err(error::Unspecified);
         }
-        let msg = msg.as_slice_less_safe();
-        let signature = signature.as_slice_less_safe();
-        bssl::map_result(unsafe {
-            GFp_ed25519_verify(msg.as_ptr(), msg.len(), signature.as_ptr(),
-                               public_key.as_ptr())
-        })
+        ed25519_verify(ED25519PH_DOM, public_key, digest.as_slice_less_safe(),
+                       signature)
     }
 }
 
-impl private::Private for EdDSAParameters {}
+impl private::Private for EdDSAPrehashedParameters {}
+
+
+impl<'a> EdDSAContextParameters<'a> {
+    /// Verification of [Ed25519ctx] signatures with the context `context`.
+    ///
+    /// `context` must be between 1 and 255 bytes long; RFC 8032 doesn't
+    /// allow Ed25519ctx with an empty context, for which `ED25519` should be
+    /// used instead.
+    ///
+    /// [Ed25519ctx]: https://tools.ietf.org/html/rfc8032#section-5.1
+    pub fn ed25519ctx(context: &'a [u8])
+                      -> Result<EdDSAContextParameters<'a>,
+                                error::Unspecified> {
+        if context.is_empty() || context.len() > DOM2_MAX_CONTEXT_LEN {
+            return Err(error::Unspecified);
+        }
+        Ok(EdDSAContextParameters { context: context })
+    }
+}
+
+impl<'a> signature::VerificationAlgorithm for EdDSAContextParameters<'a> {
+    fn verify(&self, public_key: untrusted::Input, msg: untrusted::Input,
+              signature: untrusted::Input) -> Result<(), error::Unspecified> {
+        let mut dom = [0u8; DOM2_MAX_LEN];
+        let dom_len = try!(dom2(&mut dom, 0, self.context));
+        ed25519_verify(&dom[..dom_len], public_key, msg.as_slice_less_safe(),
+                       signature)
+    }
+
+    fn name(&self) -> &'static str { "ED25519CTX" }
+
+    fn security_bits(&self) -> usize { 128 }
+}
+
+impl<'a> private::Private for EdDSAContextParameters<'a> {}
+
+const DOM2_PREFIX: &'static [u8] = b"SigEd25519 no Ed25519 collisions";
+const DOM2_MAX_CONTEXT_LEN: usize = 255;
+const DOM2_MAX_LEN: usize = 32 + 2 + DOM2_MAX_CONTEXT_LEN;
+
+// Writes dom2(`phflag`, `context`) from RFC 8032 Section 5.1 to `out`, and
+// returns its length. Only Ed25519ph may use an empty context.
+fn dom2(out: &mut [u8; DOM2_MAX_LEN], phflag: u8, context: &[u8])
+        -> Result<usize, error::Unspecified> {
+    if (phflag == 0 && context.is_empty()) ||
+       context.len() > DOM2_MAX_CONTEXT_LEN {
+        return Err(error::Unspecified);
+    }
+    let (prefix, rest) = out.split_at_mut(DOM2_PREFIX.len());
+    prefix.copy_from_slice(DOM2_PREFIX);
+    rest[0] = phflag;
+    rest[1] = context.len() as u8;
+    rest[2..][..context.len()].copy_from_slice(context);
+    Ok(DOM2_PREFIX.len() + 2 + context.len())
+}
+
+fn ed25519_verify(dom: &[u8], public_key: untrusted::Input, msg: &[u8],
+                  signature: untrusted::Input)
+                  -> Result<(), error::Unspecified> {
+    let public_key = public_key.as_slice_less_safe();
+    if public_key.len() != 32 || signature.len() != 64 {
+        return Err(error::Unspecified);
+    }
+    let signature = signature.as_slice_less_safe();
+    bssl::map_result(unsafe {
+        GFp_ed25519_verify(dom.as_ptr(), dom.len(), msg.as_ptr(), msg.len(),
+                           signature.as_ptr(), public_key.as_ptr())
+    })
+}
+
+
+/// Verifies a batch of Ed25519 signatures, where each element of `batch` is
+/// a `(public_key, msg, signature)` triple as passed to `signature::verify`
+/// with `ED25519`.
+///
+/// This is much faster than verifying each signature separately. It
+/// succeeds only if every signature in the batch is valid; when it fails,
+/// each signature has to be verified separately to find out which ones are
+/// invalid. An empty batch is valid.
+///
+/// The signatures are checked together using a random linear combination of
+/// the verification equations, with the random coefficients taken from
+/// `rng`. The batch check uses the cofactored verification equation of
+/// [RFC 8032 Section 5.1.7], `[8][S]B = [8]R + [8][k]A`, whereas `verify`
+/// uses the stricter cofactorless equation. They only disagree for
+/// signatures that were specially crafted using points of small order, but
+/// systems in which everybody must agree on the validity of every signature,
+/// e.g. consensus protocols, should use the same kind of verification
+/// everywhere.
+///
+/// [RFC 8032 Section 5.1.7]:
+///     https://tools.ietf.org/html/rfc8032#section-5.1.7
+pub fn ed25519_verify_batch(
+        batch: &[(untrusted::Input, untrusted::Input, untrusted::Input)],
+        rng: &rand::SecureRandom) -> Result<(), error::Unspecified> {
+    init::init_once();
+    for chunk in batch.chunks(BATCH_CHUNK_LEN) {
+        try!(ed25519_verify_batch_chunk(chunk, rng));
+    }
+    Ok(())
+}
+
+/// Like `ed25519_verify_batch`, but the batch is checked in jobs, one per
+/// chunk of up to 16 signatures, that `executor` runs, e.g. on the
+/// application's thread pool. The jobs share `rng`.
+#[cfg(feature = "use_heap")]
+pub fn ed25519_verify_batch_with(
+        batch: &[(untrusted::Input, untrusted::Input, untrusted::Input)],
+        rng: &(rand::SecureRandom + Sync), executor: &executor::Executor)
+        -> Result<(), error::Unspecified> {
+    init::init_once();
+    // A chunk whose job the executor didn't run fails.
+    let mut results: std::vec::Vec<Result<(), error::Unspecified>> =
+        batch.chunks(BATCH_CHUNK_LEN).map(|_| Err(error::Unspecified))
+             .collect();
+    {
+        let mut jobs: std::vec::Vec<_> =
+            results.iter_mut().zip(batch.chunks(BATCH_CHUNK_LEN))
+                .map(|(result, chunk)| move || {
+                    *result = ed25519_verify_batch_chunk(chunk, rng);
+                }).collect();
+        let mut jobs: std::vec::Vec<&mut (FnMut() + Send)> =
+            jobs.iter_mut().map(|job| {
+                let job: &mut (FnMut() + Send) = job;
+                job
+            }).collect();
+        executor.execute(&mut jobs);
+    }
+    for result in results {
+        try!(result);
+    }
+    Ok(())
+}
+
+// Each signature contributes two points, `R` and `A`, to the multi-scalar
+// multiplication, which accepts at most 32 points at a time.
+const BATCH_CHUNK_LEN: usize = 16;
+
+// Checks that the sum over the signatures of
+// z * R + (z * k) * A - (z * S) * B is the identity, after multiplying by the
+// cofactor, for a random 128-bit z for each signature.
+fn ed25519_verify_batch_chunk(
+        chunk: &[(untrusted::Input, untrusted::Input, untrusted::Input)],
+        rng: &rand::SecureRandom) -> Result<(), error::Unspecified> {
+    debug_assert!(chunk.len() <= BATCH_CHUNK_LEN);
+    let mut scalars = [0u8; 2 * BATCH_CHUNK_LEN * SCALAR_LEN];
+    let mut points = [0u8; 2 * BATCH_CHUNK_LEN * ELEM_LEN];
+    let mut b = Scalar::zero();
+
+    for (i, &(public_key, msg, signature)) in chunk.iter().enumerate() {
+        let public_key = public_key.as_slice_less_safe();
+        let signature = signature.as_slice_less_safe();
+        // The same checks as `GFp_ed25519_verify`.
+        if public_key.len() != ELEM_LEN || signature.len() != 64 ||
+           (signature[63] & 0xe0) != 0 {
+            return Err(error::Unspecified);
+        }
+        let (r, s) = signature.split_at(ELEM_LEN);
+        let s = try!(slice_as_array_ref!(s, SCALAR_LEN));
+
+        let mut z = [0u8; SCALAR_LEN];
+        try!(rng.fill(&mut z[..16]));
+        let z = Scalar::from_bytes_unreduced(&z);
+
+        let mut ctx = digest::Context::new(&digest::SHA512);
+        ctx.update(r);
+        ctx.update(public_key);
+        ctx.update(msg.as_slice_less_safe());
+        let k = Scalar::from_sha512_digest(&ctx.finish());
+
+        // The `R`s come first so that they are checked to be canonically
+        // encoded, as `GFp_ed25519_verify` implicitly requires.
+        points[(i * ELEM_LEN)..][..ELEM_LEN].copy_from_slice(r);
+        scalars[(i * SCALAR_LEN)..][..SCALAR_LEN]
+            .copy_from_slice(z.as_bytes());
+        let j = chunk.len() + i;
+        points[(j * ELEM_LEN)..][..ELEM_LEN].copy_from_slice(public_key);
+        scalars[(j * SCALAR_LEN)..][..SCALAR_LEN]
+            .copy_from_slice(z.product(&k).as_bytes());
+        b = z.mul_add(&Scalar::from_bytes_unreduced(s), &b);
+    }
+
+    bssl::map_result(unsafe {
+        GFp_ed25519_multiscalar_is_zero_vartime(b.as_bytes().as_ptr(),
+                                                scalars.as_ptr(),
+                                                points.as_ptr(),
+                                                2 * chunk.len(), chunk.len())
+    })
+}
 
 
 extern  {
     fn GFp_ed25519_public_from_private(out: *mut u8/*[32]*/,
                                        in_: *const u8/*[32]*/);
 
-    fn GFp_ed25519_sign(out_sig: *mut u8/*[64]*/, message: *const u8,
+    fn GFp_ed25519_sign(out_sig: *mut u8/*[64]*/, dom: *const u8,
+                        dom_len: c::size_t, message: *const u8,
                         message_len: c::size_t, private_key: *const u8/*[64]*/);
 
-    fn GFp_ed25519_verify(message: *const u8, message_len: c::size_t,
+    fn GFp_ed25519_verify(dom: *const u8, dom_len: c::size_t,
+                          message: *const u8, message_len: c::size_t,
                           signature: *const u8/*[64]*/,
                           public_key: *const u8/*[32]*/) -> c::int;
+
+    fn GFp_ed25519_multiscalar_is_zero_vartime(b: *const u8/*[32]*/,
+                                               scalars: *const u8,
+                                               points: *const u8,
+                                               num_points: c::size_t,
+                                               num_canonical: c::size_t)
+                                               -> c::int;
 }
 
 
 #[cfg(test)]
 mod tests {
-    use {test, rand, signature};
+    use {digest, executor, test, rand, signature};
+    use std;
     use super::Ed25519KeyPair;
     use untrusted;
 
@@ -179,11 +536,30 @@ mod tests {
             let msg = test_case.consume_bytes("MESSAGE");
             let expected_sig = test_case.consume_bytes("SIG");
 
-            let key_pair = Ed25519KeyPair::from_bytes(&private_key[..32],
-                                                      &public_key).unwrap();
+            let key_pair = Ed25519KeyPair::from_seed_and_public_key(
+                &private_key[..32], &public_key).unwrap();
             let actual_sig = key_pair.sign(&msg);
             assert_eq!(&expected_sig[..], actual_sig.as_slice());
 
+            let key_pair =
+                Ed25519KeyPair::from_seed_unchecked(&private_key[..32])
+                    .unwrap();
+            assert_eq!(key_pair.public_key_bytes(), &public_key[..]);
+            assert_eq!(&signature::KeyPair::public_key(&key_pair)[..],
+                       &public_key[..]);
+            let actual_sig = key_pair.sign(&msg);
+            assert_eq!(&expected_sig[..], actual_sig.as_slice());
+
+            let unparsed = signature::UnparsedPublicKey::new(
+                &signature::ED25519, &public_key[..]);
+            assert!(unparsed.verify(&msg, &expected_sig).is_ok());
+            let mut wrong_msg = msg.clone();
+            wrong_msg.push(0);
+            assert!(unparsed.verify(&wrong_msg, &expected_sig).is_err());
+            let unparsed = signature::UnparsedPublicKey::new(
+                &signature::ECDSA_P256_SHA256_FIXED, &public_key[..]);
+            assert!(unparsed.verify(&msg, &expected_sig).is_err());
+
             let public_key = untrusted::Input::from(&public_key);
             let msg = untrusted::Input::from(&msg);
             let expected_sig = untrusted::Input::from(&expected_sig);
@@ -196,23 +572,230 @@ mod tests {
     }
 
     #[test]
-    fn test_ed25519_from_bytes_misuse() {
+    fn test_signature_ed25519ph() {
+        test::from_file("src/ec/ed25519ph_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let private_key = test_case.consume_optional_string("PRIV");
+            let public_key = test_case.consume_bytes("PUB");
+            let msg = test_case.consume_bytes("MESSAGE");
+            let sig = test_case.consume_bytes("SIG");
+
+            let digest = digest::digest(&digest::SHA512, &msg);
+
+            if let Some(private_key) = private_key {
+                let private_key = test::from_hex(&private_key).unwrap();
+                let key_pair = Ed25519KeyPair::from_seed_and_public_key(
+                    &private_key, &public_key).unwrap();
+                let actual_sig = key_pair.sign_prehashed(&digest).unwrap();
+                assert_eq!(&sig[..], actual_sig.as_slice());
+
+                // The digest must be a SHA-512 digest.
+                let wrong_digest = digest::digest(&digest::SHA384, &msg);
+                assert!(key_pair.sign_prehashed(&wrong_digest).is_err());
+
+                let mut ctx = signature::SigningContext::new(&key_pair);
+                for chunk in msg.chunks(7) {
+                    ctx.update(chunk);
+                }
+                assert_eq!(&sig[..], ctx.finish().unwrap().as_slice());
+            }
+
+            let public_key = untrusted::Input::from(&public_key);
+            let digest = untrusted::Input::from(digest.as_ref());
+
+            assert!(signature::verify(&signature::ED25519PH, public_key,
+                                      untrusted::Input::from(&msg),
+                                      untrusted::Input::from(&sig)).is_ok());
+            assert!(signature::verify_prehashed(&signature::ED25519PH,
+                                                public_key, digest,
+                                                untrusted::Input::from(&sig))
+                        .is_ok());
+
+            let mut ctx = signature::VerificationContext::new(
+                &signature::ED25519PH, public_key);
+            for chunk in msg.chunks(7) {
+                ctx.update(chunk);
+            }
+            assert!(ctx.finish(untrusted::Input::from(&sig)).is_ok());
+
+            // An Ed25519ph signature isn't a valid Ed25519 signature of the
+            // message or of its digest.
+            assert!(signature::verify(&signature::ED25519, public_key,
+                                      untrusted::Input::from(&msg),
+                                      untrusted::Input::from(&sig)).is_err());
+            assert!(signature::verify(&signature::ED25519, public_key, digest,
+                                      untrusted::Input::from(&sig)).is_err());
+
+            let mut bad_sig = sig.clone();
+            bad_sig[0] ^= 1;
+            assert!(signature::verify_prehashed(
+                        &signature::ED25519PH, public_key, digest,
+                        untrusted::Input::from(&bad_sig)).is_err());
+
+            // Digests of the wrong length are rejected.
+            let digest = digest.as_slice_less_safe();
+            assert!(signature::verify_prehashed(
+                        &signature::ED25519PH, public_key,
+                        untrusted::Input::from(&digest[..63]),
+                        untrusted::Input::from(&sig)).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_signature_ed25519ctx() {
+        test::from_file("src/ec/ed25519ctx_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let private_key = test_case.consume_bytes("PRIV");
+            let public_key = test_case.consume_bytes("PUB");
+            let context = test_case.consume_bytes("CONTEXT");
+            let msg = test_case.consume_bytes("MESSAGE");
+            let sig = test_case.consume_bytes("SIG");
+
+            let key_pair = Ed25519KeyPair::from_seed_and_public_key(
+                &private_key, &public_key).unwrap();
+            let actual_sig = key_pair.sign_with_context(&context, &msg)
+                                     .unwrap();
+            assert_eq!(&sig[..], actual_sig.as_slice());
+
+            let alg =
+                signature::EdDSAContextParameters::ed25519ctx(&context)
+                    .unwrap();
+            let public_key = untrusted::Input::from(&public_key);
+            let msg = untrusted::Input::from(&msg);
+            assert!(signature::verify(&alg, public_key, msg,
+                                      untrusted::Input::from(&sig)).is_ok());
+
+            // An Ed25519ctx signature isn't valid for a different context or
+            // as an Ed25519 signature.
+            let mut other_context = context.clone();
+            other_context[0] ^= 1;
+            let other_alg =
+                signature::EdDSAContextParameters::ed25519ctx(&other_context)
+                    .unwrap();
+            assert!(signature::verify(&other_alg, public_key, msg,
+                                      untrusted::Input::from(&sig)).is_err());
+            assert!(signature::verify(&signature::ED25519, public_key, msg,
+                                      untrusted::Input::from(&sig)).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_ed25519ctx_context_len() {
+        let rng = rand::SystemRandom::new();
+        let key_pair = Ed25519KeyPair::generate(&rng).unwrap();
+        let context = [0u8; 256];
+
+        assert!(key_pair.sign_with_context(&context[..0], b"msg").is_err());
+        assert!(key_pair.sign_with_context(&context[..1], b"msg").is_ok());
+        assert!(key_pair.sign_with_context(&context[..255], b"msg").is_ok());
+        assert!(key_pair.sign_with_context(&context, b"msg").is_err());
+
+        let ed25519ctx = signature::EdDSAContextParameters::ed25519ctx;
+        assert!(ed25519ctx(&context[..0]).is_err());
+        assert!(ed25519ctx(&context[..1]).is_ok());
+        assert!(ed25519ctx(&context[..255]).is_ok());
+        assert!(ed25519ctx(&context).is_err());
+    }
+
+    #[test]
+    fn test_signature_ed25519_verify_batch() {
+        let mut vectors = std::vec::Vec::new();
+        test::from_file("src/ec/ed25519_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let _ = test_case.consume_bytes("PRIV");
+            let public_key = test_case.consume_bytes("PUB");
+            let msg = test_case.consume_bytes("MESSAGE");
+            let sig = test_case.consume_bytes("SIG");
+            vectors.push((public_key, msg, sig));
+            Ok(())
+        });
+        let rng = rand::SystemRandom::new();
+        let batch = vectors.iter()
+            .map(|&(ref public_key, ref msg, ref sig)| {
+                (untrusted::Input::from(public_key),
+                 untrusted::Input::from(msg), untrusted::Input::from(sig))
+            })
+            .collect::<std::vec::Vec<_>>();
+
+        // Batches that fit in one chunk, that fill exactly one chunk, and
+        // that span several chunks.
+        for &len in &[0, 1, 2, 16, 17, 33, batch.len()] {
+            assert!(signature::ed25519_verify_batch(&batch[..len], &rng)
+                        .is_ok());
+            assert!(signature::ed25519_verify_batch_with(
+                        &batch[..len], &rng, &executor::Sequential).is_ok());
+            assert!(signature::ed25519_verify_batch_with(
+                        &batch[..len], &rng, &executor::test_util::Reversed)
+                        .is_ok());
+        }
+
+        // The jobs must actually be run.
+        assert!(signature::ed25519_verify_batch_with(
+                    &batch, &rng, &executor::test_util::Broken).is_err());
+
+        // A single bad signature, message, or public key anywhere in the
+        // batch makes the batch invalid.
+        for &i in &[0, 15, 16, batch.len() - 1] {
+            let (ref public_key, ref msg, ref sig) = vectors[i];
+
+            let mut bad_sig = sig.clone();
+            bad_sig[0] ^= 1;
+            let mut bad_batch = batch.clone();
+            bad_batch[i].2 = untrusted::Input::from(&bad_sig);
+            assert!(signature::ed25519_verify_batch(&bad_batch, &rng)
+                        .is_err());
+            assert!(signature::ed25519_verify_batch_with(
+                        &bad_batch, &rng, &executor::test_util::Reversed)
+                        .is_err());
+
+            let mut bad_msg = msg.clone();
+            bad_msg.push(0);
+            let mut bad_batch = batch.clone();
+            bad_batch[i].1 = untrusted::Input::from(&bad_msg);
+            assert!(signature::ed25519_verify_batch(&bad_batch, &rng)
+                        .is_err());
+
+            let mut bad_batch = batch.clone();
+            bad_batch[i].0 = bad_batch[(i + 1) % batch.len()].0;
+            assert!(signature::ed25519_verify_batch(&bad_batch, &rng)
+                        .is_err());
+
+            let mut bad_batch = batch.clone();
+            bad_batch[i].0 = untrusted::Input::from(&public_key[..31]);
+            assert!(signature::ed25519_verify_batch(&bad_batch, &rng)
+                        .is_err());
+        }
+    }
+
+    #[test]
+    fn test_ed25519_from_seed_and_public_key_misuse() {
         let rng = rand::SystemRandom::new();
         let (_, bytes) = Ed25519KeyPair::generate_serializable(&rng).unwrap();
+        let from_seed_and_public_key = |seed: &[u8], public_key: &[u8]| {
+            Ed25519KeyPair::from_seed_and_public_key(seed, public_key)
+        };
 
-        assert!(Ed25519KeyPair::from_bytes(&bytes.private_key,
-                                           &bytes.public_key).is_ok());
+        assert!(from_seed_and_public_key(&bytes.private_key,
+                                         &bytes.public_key).is_ok());
 
         // Truncated private key.
-        assert!(Ed25519KeyPair::from_bytes(&bytes.private_key[..31],
-                                           &bytes.public_key).is_err());
+        assert!(from_seed_and_public_key(&bytes.private_key[..31],
+                                         &bytes.public_key).is_err());
 
         // Truncated public key.
-        assert!(Ed25519KeyPair::from_bytes(&bytes.private_key,
-                                           &bytes.public_key[..31]).is_err());
+        assert!(from_seed_and_public_key(&bytes.private_key,
+                                         &bytes.public_key[..31]).is_err());
 
         // Swapped public and private key.
-        assert!(Ed25519KeyPair::from_bytes(&bytes.public_key,
-                                           &bytes.private_key).is_err());
+        assert!(from_seed_and_public_key(&bytes.public_key,
+                                         &bytes.private_key).is_err());
+
+        // Truncated seed.
+        assert!(Ed25519KeyPair::from_seed_unchecked(&bytes.private_key[..31])
+                    .is_err());
     }
 }
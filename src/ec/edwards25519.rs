@@ -0,0 +1,322 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Scalar and group arithmetic on the Ed25519 curve, for protocols that build
+//! on Ed25519 signatures.
+
+use {bssl, c, digest, error, rand};
+use untrusted;
+
+pub const SCALAR_LEN: usize = 32;
+pub const ELEM_LEN: usize = 32;
+
+/// A scalar, fully reduced mod l, the order of the base point, and encoded in
+/// little-endian form.
+#[derive(Clone, Copy)]
+pub struct Scalar {
+    bytes: [u8; SCALAR_LEN],
+}
+
+// l = 2**252 + 27742317777372353535851937790883648493, little-endian.
+const L: [u8; SCALAR_LEN] = [
+    0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58,
+    0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+];
+
+impl Scalar {
+    /// Parses a scalar, which must be fully reduced.
+    pub fn from_bytes(input: untrusted::Input)
+                      -> Result<Scalar, error::Unspecified> {
+        let bytes = try!(slice_as_array_ref!(input.as_slice_less_safe(),
+                                             SCALAR_LEN));
+        let s = Scalar { bytes: *bytes };
+        // Reducing a reduced scalar doesn't change it.
+        if s.product(&Scalar::one()).bytes != s.bytes {
+            return Err(error::Unspecified);
+        }
+        Ok(s)
+    }
+
+    /// Constructs a scalar from `bytes` without reducing it, for inputs that
+    /// are only required to be less than 2**253, such as the `S` half of an
+    /// Ed25519 signature. Arithmetic on the result is fully reduced.
+    pub fn from_bytes_unreduced(bytes: &[u8; SCALAR_LEN]) -> Scalar {
+        Scalar { bytes: *bytes }
+    }
+
+    pub fn from_u16(value: u16) -> Scalar {
+        let mut bytes = [0u8; SCALAR_LEN];
+        bytes[0] = (value & 0xff) as u8;
+        bytes[1] = (value >> 8) as u8;
+        Scalar { bytes: bytes }
+    }
+
+    pub fn from_u64(value: u64) -> Scalar {
+        let mut bytes = [0u8; SCALAR_LEN];
+        for (i, b) in bytes[..8].iter_mut().enumerate() {
+            *b = (value >> (8 * i)) as u8;
+        }
+        Scalar { bytes: bytes }
+    }
+
+    /// Reduces a SHA-512 digest mod l.
+    pub fn from_sha512_digest(digest: &digest::Digest) -> Scalar {
+        let digest = digest.as_ref();
+        assert_eq!(digest.len(), 2 * SCALAR_LEN);
+        let mut wide = [0u8; 2 * SCALAR_LEN];
+        wide.copy_from_slice(digest);
+        unsafe { GFp_ed25519_scalar_reduce(wide.as_mut_ptr()) };
+        let mut bytes = [0u8; SCALAR_LEN];
+        bytes.copy_from_slice(&wide[..SCALAR_LEN]);
+        Scalar { bytes: bytes }
+    }
+
+    /// Generates a uniformly-distributed random scalar.
+    pub fn generate(rng: &rand::SecureRandom)
+                    -> Result<Scalar, error::Unspecified> {
+        let mut wide = [0u8; 2 * SCALAR_LEN];
+        try!(rng.fill(&mut wide));
+        unsafe { GFp_ed25519_scalar_reduce(wide.as_mut_ptr()) };
+        let mut bytes = [0u8; SCALAR_LEN];
+        bytes.copy_from_slice(&wide[..SCALAR_LEN]);
+        Ok(Scalar { bytes: bytes })
+    }
+
+    #[inline]
+    pub fn zero() -> Scalar { Scalar { bytes: [0u8; SCALAR_LEN] } }
+
+    #[inline]
+    pub fn one() -> Scalar { Scalar::from_u16(1) }
+
+    #[inline]
+    pub fn is_zero(&self) -> bool { self.bytes == [0u8; SCALAR_LEN] }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8; SCALAR_LEN] { &self.bytes }
+
+    /// Returns `self * b + c (mod l)`.
+    pub fn mul_add(&self, b: &Scalar, c: &Scalar) -> Scalar {
+        let mut r = Scalar::zero();
+        unsafe {
+            GFp_ed25519_scalar_mul_add(r.bytes.as_mut_ptr(),
+                                       self.bytes.as_ptr(), b.bytes.as_ptr(),
+                                       c.bytes.as_ptr());
+        }
+        r
+    }
+
+    #[inline]
+    pub fn sum(&self, b: &Scalar) -> Scalar {
+        self.mul_add(&Scalar::one(), b)
+    }
+
+    #[inline]
+    pub fn product(&self, b: &Scalar) -> Scalar {
+        self.mul_add(b, &Scalar::zero())
+    }
+
+    #[inline]
+    pub fn difference(&self, b: &Scalar) -> Scalar {
+        // self - b == (l - 1) * b + self (mod l).
+        b.mul_add(&Scalar::minus_one(), self)
+    }
+
+    /// Returns `self**-1 (mod l)`, computed as `self**(l - 2) (mod l)`. The
+    /// result is zero if `self` is zero. The sequence of operations doesn't
+    /// depend on `self`.
+    pub fn inverse(&self) -> Scalar {
+        let mut l_minus_2 = L;
+        l_minus_2[0] -= 2;
+        let mut acc = Scalar::one();
+        for i in (0..(8 * SCALAR_LEN)).rev() {
+            acc = acc.product(&acc);
+            if (l_minus_2[i / 8] >> (i % 8)) & 1 == 1 {
+                acc = acc.product(self);
+            }
+        }
+        acc
+    }
+
+    fn minus_one() -> Scalar {
+        let mut bytes = L;
+        bytes[0] -= 1;
+        Scalar { bytes: bytes }
+    }
+}
+
+/// An encoded point. Points from untrusted sources are validated by
+/// `Point::from_bytes`; points computed from other points may be the
+/// identity.
+#[derive(Clone, Copy)]
+pub struct Point {
+    bytes: [u8; ELEM_LEN],
+}
+
+impl Point {
+    /// Parses a point, which must be canonically encoded, must be in the
+    /// prime-order subgroup, and must not be the identity.
+    ///
+    /// This is *not* constant-time; it must only be used for public values.
+    pub fn from_bytes(input: untrusted::Input)
+                      -> Result<Point, error::Unspecified> {
+        let bytes = try!(slice_as_array_ref!(input.as_slice_less_safe(),
+                                             ELEM_LEN));
+        let p = Point { bytes: *bytes };
+        if p.bytes == Point::identity().bytes {
+            return Err(error::Unspecified);
+        }
+        // Decoding and re-encoding a canonical encoding doesn't change it.
+        let one = Scalar::one();
+        let zero = Scalar::zero();
+        if try!(p.mul_add_base_vartime(&one, &zero)).bytes != p.bytes {
+            return Err(error::Unspecified);
+        }
+        // l * P is the identity iff P is in the prime-order subgroup.
+        let l = Scalar { bytes: L };
+        if try!(p.mul_add_base_vartime(&l, &zero)).bytes !=
+                Point::identity().bytes {
+            return Err(error::Unspecified);
+        }
+        Ok(p)
+    }
+
+    pub fn identity() -> Point {
+        let mut bytes = [0u8; ELEM_LEN];
+        bytes[0] = 1;
+        Point { bytes: bytes }
+    }
+
+    /// Returns `a * B`, where `B` is the base point, in constant time.
+    pub fn from_base_mul(a: &Scalar) -> Point {
+        let mut r = Point { bytes: [0u8; ELEM_LEN] };
+        unsafe {
+            GFp_ed25519_point_mul_base(r.bytes.as_mut_ptr(), a.bytes.as_ptr());
+        }
+        r
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8; ELEM_LEN] { &self.bytes }
+
+    /// Returns `a * self + b * B`, where `B` is the base point. This is *not*
+    /// constant-time.
+    pub fn mul_add_base_vartime(&self, a: &Scalar, b: &Scalar)
+                                -> Result<Point, error::Unspecified> {
+        let mut r = Point { bytes: [0u8; ELEM_LEN] };
+        try!(bssl::map_result(unsafe {
+            GFp_ed25519_point_mul_add_base_vartime(r.bytes.as_mut_ptr(),
+                                                   a.bytes.as_ptr(),
+                                                   self.bytes.as_ptr(),
+                                                   b.bytes.as_ptr())
+        }));
+        Ok(r)
+    }
+
+    /// Returns `self + b`. This is *not* constant-time.
+    pub fn sum_vartime(&self, b: &Point) -> Result<Point, error::Unspecified> {
+        let mut r = Point { bytes: [0u8; ELEM_LEN] };
+        try!(bssl::map_result(unsafe {
+            GFp_ed25519_point_add_vartime(r.bytes.as_mut_ptr(),
+                                          self.bytes.as_ptr(),
+                                          b.bytes.as_ptr())
+        }));
+        Ok(r)
+    }
+}
+
+
+extern {
+    fn GFp_ed25519_scalar_reduce(s: *mut u8/*[64]*/);
+    fn GFp_ed25519_scalar_mul_add(s: *mut u8/*[32]*/, a: *const u8/*[32]*/,
+                                  b: *const u8/*[32]*/, c: *const u8/*[32]*/);
+    fn GFp_ed25519_point_mul_base(out: *mut u8/*[32]*/, a: *const u8/*[32]*/);
+    fn GFp_ed25519_point_mul_add_base_vartime(out: *mut u8/*[32]*/,
+                                              a: *const u8/*[32]*/,
+                                              p: *const u8/*[32]*/,
+                                              b: *const u8/*[32]*/) -> c::int;
+    fn GFp_ed25519_point_add_vartime(out: *mut u8/*[32]*/,
+                                     a: *const u8/*[32]*/,
+                                     b: *const u8/*[32]*/) -> c::int;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test;
+    use untrusted;
+
+    #[test]
+    fn scalar_arithmetic_test() {
+        let two = Scalar::from_u16(2);
+        let three = Scalar::from_u16(3);
+        assert_eq!(two.sum(&three).as_bytes(), Scalar::from_u16(5).as_bytes());
+        assert_eq!(two.product(&three).as_bytes(),
+                   Scalar::from_u16(6).as_bytes());
+        assert_eq!(three.difference(&two).as_bytes(), Scalar::one().as_bytes());
+        assert_eq!(two.difference(&three).as_bytes(),
+                   Scalar::minus_one().as_bytes());
+        assert_eq!(three.inverse().product(&three).as_bytes(),
+                   Scalar::one().as_bytes());
+        assert!(Scalar::zero().inverse().is_zero());
+
+        // Only fully-reduced scalars are accepted.
+        let minus_one = Scalar::minus_one();
+        assert!(Scalar::from_bytes(untrusted::Input::from(minus_one.as_bytes()))
+                    .is_ok());
+        assert!(Scalar::from_bytes(untrusted::Input::from(&L)).is_err());
+        assert!(Scalar::from_bytes(untrusted::Input::from(&L[1..])).is_err());
+    }
+
+    #[test]
+    fn point_arithmetic_test() {
+        let two = Scalar::from_u16(2);
+        let three = Scalar::from_u16(3);
+        let b2 = Point::from_base_mul(&two);
+        let b3 = Point::from_base_mul(&three);
+        let b5 = Point::from_base_mul(&Scalar::from_u16(5));
+        assert_eq!(b2.sum_vartime(&b3).unwrap().as_bytes(), b5.as_bytes());
+        // 3 * (2 * B) + 2 * B == 8 * B.
+        assert_eq!(b2.mul_add_base_vartime(&three, &two).unwrap().as_bytes(),
+                   Point::from_base_mul(&Scalar::from_u16(8)).as_bytes());
+        assert_eq!(Point::from_base_mul(&Scalar::zero()).as_bytes(),
+                   Point::identity().as_bytes());
+
+        assert!(Point::from_bytes(untrusted::Input::from(b5.as_bytes()))
+                    .is_ok());
+        assert!(Point::from_bytes(
+                    untrusted::Input::from(Point::identity().as_bytes()))
+                .is_err());
+        assert!(Point::from_bytes(untrusted::Input::from(&b5.as_bytes()[1..]))
+                    .is_err());
+
+        // A point of order 8 (not in the prime-order subgroup).
+        let mut small_order = [0u8; ELEM_LEN];
+        small_order.copy_from_slice(&test::from_hex(
+            "c7176a703d4dd84fba3c0b760d10670f2a2053fa2c39ccc64ec7fd7792ac037a")
+            .unwrap());
+        assert!(Point::from_bytes(untrusted::Input::from(&small_order))
+                    .is_err());
+
+        // A non-canonical encoding of the point with y == 1 (the identity)
+        // plus p, i.e. y == 2**255 - 18.
+        let mut non_canonical = [0xffu8; ELEM_LEN];
+        non_canonical[0] = 0xee;
+        non_canonical[31] = 0x7f;
+        assert!(Point::from_bytes(untrusted::Input::from(&non_canonical))
+                    .is_err());
+    }
+}
@@ -0,0 +1,192 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The ristretto255 prime-order group, as specified in [RFC 9496].
+//!
+//! [RFC 9496]: https://www.rfc-editor.org/rfc/rfc9496.html
+
+use {bssl, c, error};
+use ec::edwards25519::Scalar;
+use untrusted;
+
+pub const ELEM_LEN: usize = 32;
+
+/// The input length of `Point::from_uniform_bytes`.
+pub const UNIFORM_BYTES_LEN: usize = 64;
+
+/// An encoded group element. Elements from untrusted sources are validated
+/// by `Point::from_bytes`, so every `Point` is a valid encoding.
+#[derive(Clone, Copy)]
+pub struct Point {
+    bytes: [u8; ELEM_LEN],
+}
+
+impl Point {
+    /// Parses a group element, which must be canonically encoded. The
+    /// identity element is accepted.
+    pub fn from_bytes(input: untrusted::Input)
+                      -> Result<Point, error::Unspecified> {
+        let bytes = try!(slice_as_array_ref!(input.as_slice_less_safe(),
+                                             ELEM_LEN));
+        try!(bssl::map_result(unsafe {
+            GFp_ristretto255_point_is_valid(bytes.as_ptr())
+        }));
+        Ok(Point { bytes: *bytes })
+    }
+
+    /// The one-way map from uniformly-random bytes to group elements of RFC
+    /// 9496 Section 4.3.4.
+    pub fn from_uniform_bytes(input: &[u8; UNIFORM_BYTES_LEN]) -> Point {
+        let mut r = Point { bytes: [0u8; ELEM_LEN] };
+        unsafe {
+            GFp_ristretto255_from_uniform_bytes(r.bytes.as_mut_ptr(),
+                                                input.as_ptr());
+        }
+        r
+    }
+
+    /// Returns `a * B`, where `B` is the generator, in constant time.
+    pub fn from_base_mul(a: &Scalar) -> Point {
+        let mut r = Point { bytes: [0u8; ELEM_LEN] };
+        unsafe {
+            GFp_ristretto255_point_mul_base(r.bytes.as_mut_ptr(),
+                                            a.as_bytes().as_ptr());
+        }
+        r
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8; ELEM_LEN] { &self.bytes }
+
+    /// Returns `a * self`, in constant time.
+    pub fn mul(&self, a: &Scalar) -> Point {
+        let mut r = Point { bytes: [0u8; ELEM_LEN] };
+        let result = unsafe {
+            GFp_ristretto255_point_mul(r.bytes.as_mut_ptr(),
+                                       a.as_bytes().as_ptr(),
+                                       self.bytes.as_ptr())
+        };
+        // `self` is always a valid encoding.
+        assert_eq!(result, 1);
+        r
+    }
+
+    /// Returns `self + b`.
+    pub fn sum(&self, b: &Point) -> Point {
+        let mut r = Point { bytes: [0u8; ELEM_LEN] };
+        let result = unsafe {
+            GFp_ristretto255_point_add(r.bytes.as_mut_ptr(),
+                                       self.bytes.as_ptr(), b.bytes.as_ptr())
+        };
+        assert_eq!(result, 1);
+        r
+    }
+
+    /// Returns `self - b`.
+    pub fn difference(&self, b: &Point) -> Point {
+        let mut r = Point { bytes: [0u8; ELEM_LEN] };
+        let result = unsafe {
+            GFp_ristretto255_point_sub(r.bytes.as_mut_ptr(),
+                                       self.bytes.as_ptr(), b.bytes.as_ptr())
+        };
+        assert_eq!(result, 1);
+        r
+    }
+}
+
+
+extern {
+    fn GFp_ristretto255_from_uniform_bytes(out: *mut u8/*[32]*/,
+                                           input: *const u8/*[64]*/);
+    fn GFp_ristretto255_point_is_valid(input: *const u8/*[32]*/) -> c::int;
+    fn GFp_ristretto255_point_mul_base(out: *mut u8/*[32]*/,
+                                       a: *const u8/*[32]*/);
+    fn GFp_ristretto255_point_mul(out: *mut u8/*[32]*/, a: *const u8/*[32]*/,
+                                  p: *const u8/*[32]*/) -> c::int;
+    fn GFp_ristretto255_point_add(out: *mut u8/*[32]*/, a: *const u8/*[32]*/,
+                                  b: *const u8/*[32]*/) -> c::int;
+    fn GFp_ristretto255_point_sub(out: *mut u8/*[32]*/, a: *const u8/*[32]*/,
+                                  b: *const u8/*[32]*/) -> c::int;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ec::edwards25519::Scalar;
+    use test;
+    use untrusted;
+
+    #[test]
+    fn test_ristretto255_multiples_of_generator() {
+        test::from_file("src/ec/ristretto255_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+
+            let n = test_case.consume_usize("n");
+            let expected = test_case.consume_bytes("P");
+
+            let n = Scalar::from_u16(n as u16);
+            assert_eq!(&Point::from_base_mul(&n).as_bytes()[..], &expected[..]);
+            let b = Point::from_base_mul(&Scalar::one());
+            assert_eq!(&b.mul(&n).as_bytes()[..], &expected[..]);
+            assert!(Point::from_bytes(untrusted::Input::from(&expected))
+                        .is_ok());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_ristretto255_bad_encodings() {
+        test::from_file("src/ec/ristretto255_bad_encodings_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+
+            let encoding = test_case.consume_bytes("P");
+            assert!(Point::from_bytes(untrusted::Input::from(&encoding))
+                        .is_err());
+
+            Ok(())
+        });
+    }
+
+    // From RFC 9496 Appendix A.3.
+    #[test]
+    fn test_ristretto255_from_uniform_bytes() {
+        let mut input = [0u8; UNIFORM_BYTES_LEN];
+        input.copy_from_slice(&test::from_hex(
+            "5d1be09e3d0c82fc538112490e35701979d99e06ca3e2b5b54bffe8b4dc772c1\
+             4d98b696a1bbfb5ca32c436cc61c16563790306c79eaca7705668b47dffe5bb6")
+            .unwrap());
+        assert_eq!(&Point::from_uniform_bytes(&input).as_bytes()[..],
+                   &test::from_hex("3066f82a1a747d45120d1740f14358531a8f04bb\
+                                    ffe6a819f86dfe50f44a0a46").unwrap()[..]);
+    }
+
+    #[test]
+    fn test_ristretto255_arithmetic() {
+        let b = Point::from_base_mul(&Scalar::one());
+        let b2 = Point::from_base_mul(&Scalar::from_u16(2));
+        let b3 = Point::from_base_mul(&Scalar::from_u16(3));
+        let identity = Point::from_base_mul(&Scalar::zero());
+        assert_eq!(identity.as_bytes(), &[0u8; ELEM_LEN]);
+        assert_eq!(b.sum(&b2).as_bytes(), b3.as_bytes());
+        assert_eq!(b3.difference(&b2).as_bytes(), b.as_bytes());
+        assert_eq!(b.difference(&b).as_bytes(), identity.as_bytes());
+        assert_eq!(b.mul(&Scalar::zero()).as_bytes(), identity.as_bytes());
+        assert!(Point::from_bytes(untrusted::Input::from(identity.as_bytes()))
+                    .is_ok());
+    }
+}
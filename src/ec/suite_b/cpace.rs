@@ -0,0 +1,78 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//! The P-256 group operations of CPace.
+
+use {ec, error, rand};
+use super::hash_to_curve::{encode_to_curve, P256_XMD_SHA256_SSWU_RO};
+use super::ops::*;
+use super::private_key::*;
+use super::public_key::parse_uncompressed_point;
+use untrusted;
+
+/// The length of an encoded scalar.
+pub const SCALAR_LEN: usize = ELEM_LEN;
+
+/// The length of an encoded point, in uncompressed form.
+pub const POINT_LEN: usize = 1 + (2 * ELEM_LEN);
+
+/// The length of the shared secret K, the x coordinate of the shared point.
+pub const SHARED_SECRET_LEN: usize = ELEM_LEN;
+
+const ELEM_LEN: usize = 256 / 8;
+
+/// Computes the generator `g` from the generator string, using the
+/// `encode_to_curve` variant of P256_XMD:SHA-256_SSWU_NU_.
+pub fn calculate_generator(generator_string: &[&[u8]], dst: &[u8],
+                           out: &mut [u8]) -> Result<(), error::Unspecified> {
+    encode_to_curve(&P256_XMD_SHA256_SSWU_RO, generator_string, dst, out)
+}
+
+/// Generates a random scalar in the range [1, n), big-endian encoded.
+pub fn sample_scalar(rng: &rand::SecureRandom, out: &mut [u8])
+                     -> Result<(), error::Unspecified> {
+    let private_key = try!(generate_private_key(OPS, rng));
+    out.copy_from_slice(&private_key.bytes[..SCALAR_LEN]);
+    Ok(())
+}
+
+/// Writes `scalar * point` to `out` in uncompressed form.
+pub fn scalar_mult(scalar: &[u8], point: untrusted::Input, out: &mut [u8])
+                   -> Result<(), error::Unspecified> {
+    let product = try!(product(scalar, point));
+    out[0] = 4;
+    let (x_out, y_out) = (&mut out[1..]).split_at_mut(ELEM_LEN);
+    big_endian_affine_from_jacobian(OPS, Some(x_out), Some(y_out), &product)
+}
+
+/// Writes the x coordinate of `scalar * point` to `out`. Fails if `point`
+/// isn't a valid point on the curve.
+pub fn scalar_mult_vfy(scalar: &[u8], point: untrusted::Input, out: &mut [u8])
+                       -> Result<(), error::Unspecified> {
+    let product = try!(product(scalar, point));
+    big_endian_affine_from_jacobian(OPS, Some(out), None, &product)
+}
+
+static OPS: &'static PrivateKeyOps = &p256::PRIVATE_KEY_OPS;
+static PUBLIC_KEY_OPS: &'static PublicKeyOps = &p256::PUBLIC_KEY_OPS;
+
+// `scalar` must have been generated by `sample_scalar`. The point at infinity
+// is never returned, because the group has prime order.
+fn product(scalar: &[u8], point: untrusted::Input)
+           -> Result<Point, error::Unspecified> {
+    let point = try!(parse_uncompressed_point(PUBLIC_KEY_OPS, point));
+    let mut private_key = ec::PrivateKey { bytes: [0; ec::SCALAR_MAX_BYTES] };
+    private_key.bytes[..SCALAR_LEN].copy_from_slice(scalar);
+    let scalar = private_key_as_scalar(OPS, &private_key);
+    Ok(OPS.point_mul(&scalar, &point))
+}
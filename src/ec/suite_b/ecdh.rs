@@ -12,7 +12,7 @@
 // OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
 // CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 
-//! ECDH key agreement using the P-256 and P-384 curves.
+//! ECDH key agreement using the P-256, P-384, and P-521 curves.
 
 use {agreement, ec, error, rand};
 use super::ops::*;
@@ -22,9 +22,10 @@ use untrusted;
 
 /// A key agreement algorithm.
 macro_rules! ecdh {
-    ( $NAME:ident, $bits:expr, $name_str:expr, $private_key_ops:expr,
-      $public_key_ops:expr, $nid:expr, $ecdh:ident,
-      $generate_private_key:ident, $public_from_private:ident) =>
+    ( $NAME:ident, $bits:expr, $security_bits:expr, $name_str:expr,
+      $private_key_ops:expr, $public_key_ops:expr, $nid:expr, $ecdh:ident,
+      $generate_private_key:ident, $private_key_from_seed:ident,
+      $public_from_private:ident) =>
     {
         #[doc="ECDH using the NSA Suite B"]
         #[doc=$name_str]
@@ -32,8 +33,10 @@ macro_rules! ecdh {
         ///
         /// Public keys are encoding in uncompressed form using the
         /// Octet-String-to-Elliptic-Curve-Point algorithm in
-        /// [SEC 1: Elliptic Curve Cryptography, Version 2.0]. Public keys are
-        /// validated during key agreement according to
+        /// [SEC 1: Elliptic Curve Cryptography, Version 2.0]. Peer public keys
+        /// may also be in compressed form, which
+        /// `EphemeralPrivateKey::compute_public_key_compressed` produces.
+        /// Public keys are validated during key agreement according to
         /// [NIST Special Publication 800-56A, revision 2] and Appendix B.3 of
         /// the NSA's [Suite B Implementer's Guide to NIST SP 800-56A].
         ///
@@ -46,9 +49,14 @@ macro_rules! ecdh {
         pub static $NAME: agreement::Algorithm = agreement::Algorithm {
             i: ec::AgreementAlgorithmImpl {
                 public_key_len: 1 + (2 * (($bits + 7) / 8)),
+                compressed_public_key_len: Some(1 + (($bits + 7) / 8)),
                 elem_and_scalar_len: ($bits + 7) / 8,
+                private_key_len: ($bits + 7) / 8,
                 nid: $nid,
+                name: stringify!($NAME),
+                security_bits: $security_bits,
                 generate_private_key: $generate_private_key,
+                private_key_from_seed: $private_key_from_seed,
                 public_from_private: $public_from_private,
                 ecdh: $ecdh,
             },
@@ -66,6 +74,11 @@ macro_rules! ecdh {
             generate_private_key($private_key_ops, rng)
         }
 
+        fn $private_key_from_seed(seed: &[u8])
+                -> Result<ec::PrivateKey, error::Unspecified> {
+            private_key_from_bytes($private_key_ops, seed)
+        }
+
         fn $public_from_private(public_out: &mut [u8],
                                 private_key: &ec::PrivateKey)
                                 -> Result<(), error::Unspecified> {
@@ -74,13 +87,20 @@ macro_rules! ecdh {
     }
 }
 
-ecdh!(ECDH_P256, 256, "P-256 (secp256r1)", &p256::PRIVATE_KEY_OPS,
+ecdh!(ECDH_P256, 256, 128, "P-256 (secp256r1)", &p256::PRIVATE_KEY_OPS,
       &p256::PUBLIC_KEY_OPS, 415 /*NID_X9_62_prime256v1*/, p256_ecdh,
-      p256_generate_private_key, p256_public_from_private);
+      p256_generate_private_key, p256_private_key_from_seed,
+      p256_public_from_private);
 
-ecdh!(ECDH_P384, 384, "P-384 (secp384r1)", &p384::PRIVATE_KEY_OPS,
+ecdh!(ECDH_P384, 384, 192, "P-384 (secp384r1)", &p384::PRIVATE_KEY_OPS,
       &p384::PUBLIC_KEY_OPS, 715 /*NID_secp384r1*/, p384_ecdh,
-      p384_generate_private_key, p384_public_from_private);
+      p384_generate_private_key, p384_private_key_from_seed,
+      p384_public_from_private);
+
+ecdh!(ECDH_P521, 521, 256, "P-521 (secp521r1)", &p521::PRIVATE_KEY_OPS,
+      &p521::PUBLIC_KEY_OPS, 716 /*NID_secp521r1*/, p521_ecdh,
+      p521_generate_private_key, p521_private_key_from_seed,
+      p521_public_from_private);
 
 
 fn ecdh(private_key_ops: &PrivateKeyOps, public_key_ops: &PublicKeyOps,
@@ -97,11 +117,9 @@ fn ecdh(private_key_ops: &PrivateKeyOps, public_key_ops: &PublicKeyOps,
     // NIST SP 800-56Ar2 5.6.2.2.2.
     // NSA Guide Step 2.
     //
-    // `parse_uncompressed_point` verifies that the point is not at infinity
-    // and that it is on the curve, using the Partial Public-Key Validation
-    // Routine.
-    let peer_public_key = try!(parse_uncompressed_point(public_key_ops,
-                                                        peer_public_key));
+    // `parse_point` verifies that the point is not at infinity and that it is
+    // on the curve, using the Partial Public-Key Validation Routine.
+    let peer_public_key = try!(parse_point(public_key_ops, peer_public_key));
 
     // NIST SP 800-56Ar2 Step 1.
     // NSA Guide Step 3 (except point at infinity check).
@@ -111,9 +129,9 @@ fn ecdh(private_key_ops: &PrivateKeyOps, public_key_ops: &PublicKeyOps,
     //
     // It is impossible for the result to be the point at infinity because our
     // private key is in the range [1, n) and the curve has prime order and
-    // `parse_uncompressed_point` verified that the peer public key is on the
-    // curve and not at infinity. However, since the standards require the
-    // check, we do it using `assert!`.
+    // `parse_point` verified that the peer public key is on the curve and not
+    // at infinity. However, since the standards require the check, we do it
+    // using `assert!`.
     //
     // NIST SP 800-56Ar2 defines "Destroy" thusly: "In this Recommendation, to
     // destroy is an action applied to a key or a piece of secret data. After
@@ -150,9 +168,10 @@ mod tests {
 
     static SUPPORTED_SUITE_B_ALGS:
         [(&'static str, &'static agreement::Algorithm,
-          &'static ops::CommonOps); 2] = [
+          &'static ops::CommonOps); 3] = [
         ("P-256", &agreement::ECDH_P256, &ops::p256::COMMON_OPS),
         ("P-384", &agreement::ECDH_P384, &ops::p384::COMMON_OPS),
+        ("P-521", &agreement::ECDH_P521, &ops::p521::COMMON_OPS),
     ];
 
     #[test]
@@ -181,7 +200,7 @@ mod tests {
             // is rejected and that `generate` gives up after a while of only
             // getting that value from the PRNG.
             let mut n_bytes = [0u8; ec::SCALAR_MAX_BYTES];
-            let num_bytes = ops.num_limbs * ops::LIMB_BYTES;
+            let num_bytes = ops.elem_and_scalar_len;
             private_key::test_util::big_endian_from_limbs(
                 &mut n_bytes[..num_bytes], &ops.n.limbs[..ops.num_limbs]);
             {
@@ -232,6 +251,25 @@ mod tests {
                 assert_eq!(&n_minus_1_bytes[..num_bytes],
                            &key.bytes()[..num_bytes]);
             }
+
+            // `from_seed` accepts exactly the values in [1, n).
+            let from_seed = |seed: &[u8]| {
+                agreement::EphemeralPrivateKey::from_seed(alg, seed)
+            };
+            assert!(from_seed(&[0u8; ec::SCALAR_MAX_BYTES][..num_bytes])
+                        .is_err());
+            assert!(from_seed(&n_bytes[..num_bytes]).is_err());
+            assert!(from_seed(&n_plus_1_bytes[..num_bytes]).is_err());
+            assert!(from_seed(&[0xffu8; ec::SCALAR_MAX_BYTES][..num_bytes])
+                        .is_err());
+            let key = from_seed(&n_minus_1_bytes[..num_bytes]).unwrap();
+            assert_eq!(&n_minus_1_bytes[..num_bytes],
+                       &key.bytes()[..num_bytes]);
+
+            // The seed must be exactly as long as a scalar.
+            assert!(from_seed(&n_minus_1_bytes[..(num_bytes - 1)]).is_err());
+            let long_seed = [1u8; ec::SCALAR_MAX_BYTES + 1];
+            assert!(from_seed(&long_seed[..(num_bytes + 1)]).is_err());
         }
     }
 }
@@ -12,25 +12,78 @@
 // OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
 // CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 
-//! ECDSA Signatures using the P-256 and P-384 curves.
+//! ECDSA Signatures using the P-256, P-384, and P-521 curves.
 
-use {der, digest, error, private, signature};
+use {audit, core, der, digest, ec, error, hmac, init, private, signature};
 use super::verify_jacobian_point_is_on_the_curve;
 use super::ops::*;
+use super::private_key;
 use super::public_key::*;
 use untrusted;
 
+#[cfg(feature = "use_heap")]
+use {pkcs8, rand, std};
+
+#[cfg(feature = "public_key_cache")]
+use signed_data;
+
 /// Parameters for ECDSA signing and verification.
 pub struct ECDSAParameters {
+    name: &'static str,
     ops: &'static PublicScalarOps,
     digest_alg: &'static digest::Algorithm,
+    split_rs: for<'a> fn(ops: &'static PublicScalarOps,
+                         input: &mut untrusted::Reader<'a>)
+                         -> Result<(untrusted::Input<'a>,
+                                    untrusted::Input<'a>),
+                                   error::Unspecified>,
 }
 
 impl signature::VerificationAlgorithm for ECDSAParameters {
-    // Verify an ECDSA signature as documented in the NSA Suite B Implementer's
-    // Guide to ECDSA Section 3.4.2: ECDSA Signature Verification.
     fn verify(&self, public_key: untrusted::Input, msg: untrusted::Input,
               signature: untrusted::Input) -> Result<(), error::Unspecified> {
+        // NSA Guide Step 2: "Use the selected hash function to compute H =
+        // Hash(M)."
+        // NSA Guide Step 3: "Convert the bit string H to an integer e as
+        // described in Appendix B.2."
+        let e = digest_scalar(self.ops, self.digest_alg, msg);
+        self.verify_digest_scalar(public_key, e, signature)
+    }
+
+    fn name(&self) -> &'static str { self.name }
+
+    fn security_bits(&self) -> usize {
+        security_bits(self.ops, self.digest_alg)
+    }
+}
+
+impl signature::PrehashedVerificationAlgorithm for ECDSAParameters {
+    fn digest_algorithm(&self) -> &'static digest::Algorithm {
+        self.digest_alg
+    }
+
+    fn verify_prehashed(&self, public_key: untrusted::Input,
+                        digest: untrusted::Input, signature: untrusted::Input)
+                        -> Result<(), error::Unspecified> {
+        let digest = digest.as_slice_less_safe();
+        if digest.len() != self.digest_alg.output_len {
+            return Err(error::Unspecified);
+        }
+        // NSA Guide Step 2 was done by the caller.
+        // NSA Guide Step 3: "Convert the bit string H to an integer e as
+        // described in Appendix B.2."
+        let e = digest_scalar_(self.ops, digest);
+        self.verify_digest_scalar(public_key, e, signature)
+    }
+}
+
+impl ECDSAParameters {
+    // Verify an ECDSA signature as documented in the NSA Suite B Implementer's
+    // Guide to ECDSA Section 3.4.2: ECDSA Signature Verification, given the
+    // result `e` of steps 2 and 3.
+    fn verify_digest_scalar(&self, public_key: untrusted::Input, e: Scalar,
+                            signature: untrusted::Input)
+                            -> Result<(), error::Unspecified> {
         // NSA Guide Prerequisites:
         //
         //    Prior to accepting a verified digital signature as valid the
@@ -47,25 +100,28 @@ impl signature::VerificationAlgorithm for ECDSAParameters {
         // Prerequisites #1 and #4 are outside the scope of what this function
         // can do. Prerequisite #2 is handled implicitly as the domain
         // parameters are hard-coded into the source. Prerequisite #3 is
-        // handled by `parse_uncompressed_point`.
+        // handled by `parse_point`.
         let peer_pub_key =
-            try!(parse_uncompressed_point(self.ops.public_key_ops, public_key));
+            try!(parse_point(self.ops.public_key_ops, public_key));
+        self.verify_digest_scalar_with_point(&peer_pub_key, e, signature)
+    }
+
+    // Like `verify_digest_scalar`, but with a public key that has already been
+    // parsed and validated with `parse_point`.
+    fn verify_digest_scalar_with_point(&self, peer_pub_key: &(Elem, Elem),
+                                       e: Scalar, signature: untrusted::Input)
+                                       -> Result<(), error::Unspecified> {
+        audit::notify_if_deprecated(self.name);
 
         // NSA Guide Step 1: "If r and s are not both integers in the interval
         // [1, n − 1], output INVALID."
         let (r, s) = try!(signature.read_all(error::Unspecified, |input| {
-            der::nested(input, der::Tag::Sequence, error::Unspecified, |input| {
-                let r = try!(self.ops.scalar_parse(input));
-                let s = try!(self.ops.scalar_parse(input));
-                Ok((r, s))
-            })
+            (self.split_rs)(self.ops, input)
         }));
+        let r = try!(self.ops.scalar_parse(r));
+        let s = try!(self.ops.scalar_parse(s));
 
-        // NSA Guide Step 2: "Use the selected hash function to compute H =
-        // Hash(M)."
-        // NSA Guide Step 3: "Convert the bit string H to an integer e as
-        // described in Appendix B.2."
-        let e = digest_scalar(self.ops, self.digest_alg, msg);
+        // NSA Guide Steps 2 and 3 were done by the caller to compute `e`.
 
         // NSA Guide Step 4: "Compute w = s**−1 mod n, using the routine in
         // Appendix B.1."
@@ -80,7 +136,7 @@ impl signature::VerificationAlgorithm for ECDSAParameters {
         // R = (xR, yR) = u1*G + u2*Q, using EC scalar multiplication and EC
         // addition. If R is equal to the point at infinity, output INVALID."
         let product =
-            twin_mul(self.ops.private_key_ops, &u1, &u2, &peer_pub_key);
+            twin_mul(self.ops.private_key_ops, &u1, &u2, peer_pub_key);
 
         // Verify that the point we computed is on the curve; see
         // `verify_affine_point_is_on_the_curve_scaled` for details on why. It
@@ -125,6 +181,632 @@ impl signature::VerificationAlgorithm for ECDSAParameters {
 
 impl private::Private for ECDSAParameters {}
 
+/// An ECDSA public key that has been parsed and validated, so that it can be
+/// used for many verifications, e.g. by `ring::public_key_cache`.
+#[cfg(feature = "public_key_cache")]
+pub struct PublicKey {
+    ops: &'static PublicKeyOps,
+    point: (Elem, Elem),
+}
+
+#[cfg(feature = "public_key_cache")]
+impl PublicKey {
+    /// Parses and validates the public key `public_key` of a
+    /// `SubjectPublicKeyInfo` with the algorithm identifier contents
+    /// `alg_id`, which must identify P-256 or P-384.
+    pub fn from_spki_parts(alg_id: untrusted::Input,
+                           public_key: untrusted::Input)
+                           -> Result<PublicKey, error::Unspecified> {
+        let alg_id = alg_id.as_slice_less_safe();
+        let ops: &'static PublicKeyOps = if alg_id == signed_data::ECDSA_P256 {
+            &p256::PUBLIC_KEY_OPS
+        } else if alg_id == signed_data::ECDSA_P384 {
+            &p384::PUBLIC_KEY_OPS
+        } else {
+            return Err(error::Unspecified);
+        };
+        Ok(PublicKey {
+            ops: ops,
+            point: try!(parse_point(ops, public_key)),
+        })
+    }
+}
+
+/// Verifies the signature `signature` of `msg` with the parsed public key
+/// `public_key` using the algorithm `params`, like `signature::verify`. Fails
+/// if `public_key` isn't a key for `params`'s curve.
+#[cfg(feature = "public_key_cache")]
+pub fn verify_with_public_key(params: &ECDSAParameters, public_key: &PublicKey,
+                              msg: untrusted::Input,
+                              signature: untrusted::Input)
+                              -> Result<(), error::Unspecified> {
+    let key_ops: *const PublicKeyOps = public_key.ops;
+    let params_ops: *const PublicKeyOps = params.ops.public_key_ops;
+    if key_ops != params_ops {
+        return Err(error::Unspecified);
+    }
+    let e = digest_scalar(params.ops, params.digest_alg, msg);
+    params.verify_digest_scalar_with_point(&public_key.point, e, signature)
+}
+
+fn split_rs_fixed<'a>(ops: &'static PublicScalarOps,
+                      input: &mut untrusted::Reader<'a>)
+                      -> Result<(untrusted::Input<'a>, untrusted::Input<'a>),
+                                error::Unspecified> {
+    let scalar_len = ops.public_key_ops.common.elem_and_scalar_len;
+    let r = try!(input.skip_and_get_input(scalar_len));
+    let s = try!(input.skip_and_get_input(scalar_len));
+    Ok((r, s))
+}
+
+fn split_rs_asn1<'a>(_ops: &'static PublicScalarOps,
+                     input: &mut untrusted::Reader<'a>)
+                     -> Result<(untrusted::Input<'a>, untrusted::Input<'a>),
+                               error::Unspecified> {
+    der::nested(input, der::Tag::Sequence, error::Unspecified, |input| {
+        let r = try!(der::positive_integer(input));
+        let s = try!(der::positive_integer(input));
+        Ok((r, s))
+    })
+}
+
+
+/// An ECDSA signing algorithm.
+pub struct ECDSASigningAlgorithm {
+    name: &'static str,
+    ops: &'static PublicScalarOps,
+    digest_alg: &'static digest::Algorithm,
+    format_rs: fn(ops: &'static PublicScalarOps, r: &Scalar, s: &Scalar)
+                  -> signature::Signature,
+
+    // The contents of the `AlgorithmIdentifier` of the curve's keys, for
+    // PKCS#8 encoding.
+    #[cfg(feature = "use_heap")]
+    pkcs8_alg_id: &'static [u8],
+}
+
+impl ECDSASigningAlgorithm {
+    /// The name of the algorithm, which is the name of its `static` in
+    /// *ring*, e.g. `"ECDSA_P256_SHA256_ASN1_SIGNING_DETERMINISTIC"`.
+    pub fn name(&self) -> &'static str { self.name }
+
+    /// The approximate security strength of the algorithm, in bits. See
+    /// `signature::VerificationAlgorithm::security_bits()`.
+    pub fn security_bits(&self) -> usize {
+        security_bits(self.ops, self.digest_alg)
+    }
+}
+
+// The lesser of the strength of the curve, which is half the length of `n` in
+// bits, and the strength of the digest algorithm.
+fn security_bits(ops: &PublicScalarOps, digest_alg: &digest::Algorithm)
+                 -> usize {
+    core::cmp::min(n_bits(ops) / 2, digest_alg.security_bits())
+}
+
+// The length of `n` in bits.
+fn n_bits(ops: &PublicScalarOps) -> usize {
+    let cops = ops.public_key_ops.common;
+    let num_limbs = cops.num_limbs;
+    (num_limbs * LIMB_BITS) -
+        (cops.n.limbs[num_limbs - 1].leading_zeros() as usize)
+}
+
+/// An ECDSA key pair, for signing.
+pub struct ECDSAKeyPair {
+    alg: &'static ECDSASigningAlgorithm,
+    private_key: ec::PrivateKey,
+    public_key: [u8; ec::PUBLIC_KEY_MAX_LEN],
+}
+
+impl<'a> ECDSAKeyPair {
+    /// Generates a new key pair and returns the key pair serialized as a
+    /// PKCS#8 document.
+    ///
+    /// The PKCS#8 document will be a v1 `PrivateKeyInfo` structure (RFC 5208)
+    /// with the `id-ecPublicKey` algorithm and the curve of `alg`, wrapping
+    /// an `ECPrivateKey` structure (RFC 5915) that includes the public key.
+    /// Use `from_pkcs8` to construct the key pair from it. There is no way to
+    /// extract the private key bytes from an `ECDSAKeyPair`, so the PKCS#8
+    /// document is the only way to get them.
+    ///
+    /// Only available in `use_heap` mode.
+    #[cfg(feature = "use_heap")]
+    pub fn generate_pkcs8(alg: &'static ECDSASigningAlgorithm,
+                          rng: &rand::SecureRandom)
+                          -> Result<std::vec::Vec<u8>, error::Unspecified> {
+        init::init_once();
+        let private_key =
+            try!(private_key::generate_private_key(alg.ops.private_key_ops,
+                                                   rng));
+        let key_pair = try!(ECDSAKeyPair::new(alg, private_key));
+        let private_key_len = alg.ops.public_key_ops.common.elem_and_scalar_len;
+        Ok(pkcs8::wrap_ec_key_pair(
+                alg.pkcs8_alg_id,
+                &key_pair.private_key.bytes[..private_key_len],
+                key_pair.public_key_bytes()))
+    }
+
+    /// Constructs the key pair from the private key and public key in a
+    /// DER-encoded PKCS#8 document, such as one produced by `generate_pkcs8`.
+    ///
+    /// The input must be a v1 or v2 `PrivateKeyInfo` (RFC 5208, RFC 5958)
+    /// with the `id-ecPublicKey` algorithm and the curve of `alg`, wrapping
+    /// an `ECPrivateKey` (RFC 5915). If the `ECPrivateKey` includes the
+    /// curve parameters, they must name the same curve, and if it includes
+    /// the public key, the public key must match the private key.
+    ///
+    /// Only available in `use_heap` mode.
+    #[cfg(feature = "use_heap")]
+    pub fn from_pkcs8(alg: &'static ECDSASigningAlgorithm,
+                      input: untrusted::Input)
+                      -> Result<ECDSAKeyPair, error::Unspecified> {
+        let (private_key, public_key) =
+            try!(pkcs8::ec_key_pair(alg.pkcs8_alg_id, input));
+        match public_key {
+            Some(public_key) =>
+                ECDSAKeyPair::from_private_key_and_public_key(alg, private_key,
+                                                              public_key),
+            None => ECDSAKeyPair::from_private_key(alg, private_key),
+        }
+    }
+
+    /// Constructs the key pair whose private key is `private_key`, which must
+    /// be the big-endian encoding of a scalar in the range [1, n), padded
+    /// with zeros to the length of the curve's field elements.
+    ///
+    /// This is intended for use by code that deserializes key pairs. When the
+    /// public key is stored along with the private key, prefer
+    /// `from_private_key_and_public_key`, which detects when the two don't
+    /// belong together.
+    pub fn from_private_key(alg: &'static ECDSASigningAlgorithm,
+                            private_key: untrusted::Input)
+                            -> Result<ECDSAKeyPair, error::Unspecified> {
+        init::init_once();
+        let ops = alg.ops.private_key_ops;
+        let private_key =
+            try!(private_key::private_key_from_bytes(
+                    ops, private_key.as_slice_less_safe()));
+        ECDSAKeyPair::new(alg, private_key)
+    }
+
+    /// Constructs the key pair from the private key `private_key`, encoded as
+    /// for `from_private_key`, and the public key `public_key`, encoded in
+    /// uncompressed form as described for the verification algorithms.
+    ///
+    /// This is intended for use by code that deserializes key pairs.
+    ///
+    /// The public key is computed from the private key and compared with
+    /// `public_key`. This detects key files that have been corrupted in
+    /// storage or that pair a private key with the wrong public key when the
+    /// key is loaded, instead of producing signatures that don't verify.
+    pub fn from_private_key_and_public_key(
+            alg: &'static ECDSASigningAlgorithm, private_key: untrusted::Input,
+            public_key: untrusted::Input)
+            -> Result<ECDSAKeyPair, error::Unspecified> {
+        let key_pair = try!(ECDSAKeyPair::from_private_key(alg, private_key));
+        if public_key.as_slice_less_safe() != key_pair.public_key_bytes() {
+            return Err(error::Unspecified);
+        }
+        Ok(key_pair)
+    }
+
+    fn new(alg: &'static ECDSASigningAlgorithm, private_key: ec::PrivateKey)
+           -> Result<ECDSAKeyPair, error::Unspecified> {
+        let ops = alg.ops.private_key_ops;
+        let mut r = ECDSAKeyPair {
+            alg: alg,
+            private_key: private_key,
+            public_key: [0; ec::PUBLIC_KEY_MAX_LEN],
+        };
+        let public_key_len = 1 + (2 * ops.common.elem_and_scalar_len);
+        try!(private_key::public_from_private(
+                ops, &mut r.public_key[..public_key_len], &r.private_key));
+        audit::notify(audit::EventKind::KeyCreated, alg.name);
+        Ok(r)
+    }
+
+    /// Returns a reference to the public key, encoded in uncompressed form as
+    /// described for the verification algorithms.
+    pub fn public_key_bytes(&'a self) -> &'a [u8] {
+        let cops = self.alg.ops.public_key_ops.common;
+        &self.public_key[..(1 + (2 * cops.elem_and_scalar_len))]
+    }
+
+    /// Returns the signature of the message `msg`.
+    pub fn sign(&self, msg: &[u8])
+                -> Result<signature::Signature, error::Unspecified> {
+        let h = digest::digest(self.alg.digest_alg, msg);
+        self.sign_digest(h.as_ref())
+    }
+
+    /// Writes the candidate for the per-message secret `k` that `sign` would
+    /// derive from the message digest `h`, as described in RFC 6979 Section
+    /// 3.2, after `counter` earlier candidates were rejected. `k` is written
+    /// to `out` in big-endian form, zero-padded to the length of the curve's
+    /// order.
+    ///
+    /// This is only useful for checking the nonce derivation against
+    /// known-answer tests, e.g. during a certification audit. Anybody who
+    /// knows `k` for a signature can calculate the private key from it, so
+    /// the output must never be revealed.
+    ///
+    /// Fails if `h` doesn't have the length of the algorithm's digest, if
+    /// `out` doesn't have the length of the curve's order, or if the
+    /// candidate isn't in the range [1, n), in which case `sign` would have
+    /// rejected it and moved on to the next one.
+    pub fn rfc6979_nonce(&self, h: &digest::Digest, counter: usize,
+                         out: &mut [u8]) -> Result<(), error::Unspecified> {
+        let cops = self.alg.ops.public_key_ops.common;
+        if h.algorithm().output_len != self.alg.digest_alg.output_len ||
+           out.len() != cops.elem_and_scalar_len {
+            return Err(error::Unspecified);
+        }
+        let mut nonces = try!(RFC6979Nonces::new(self.alg, &self.private_key,
+                                                 h.as_ref()));
+        for _ in 0..counter {
+            let _ = nonces.next();
+        }
+        let k = try!(nonces.next().ok_or(error::Unspecified));
+        private_key::big_endian_from_limbs(out, &k.limbs[..cops.num_limbs]);
+        Ok(())
+    }
+
+    // Sign the digest `h` as documented in the NSA Suite B Implementer's
+    // Guide to ECDSA Section 3.4.1: ECDSA Signature Generation, with the
+    // per-message secret `k` generated as described in RFC 6979 Section 3.2
+    // instead of randomly.
+    fn sign_digest(&self, h: &[u8])
+                   -> Result<signature::Signature, error::Unspecified> {
+        let ops = self.alg.ops;
+        let private_key_ops = ops.private_key_ops;
+        let cops = ops.public_key_ops.common;
+        let num_limbs = cops.num_limbs;
+
+        let d = private_key::private_key_as_scalar(private_key_ops,
+                                                   &self.private_key);
+
+        // NSA Guide Steps 4 and 5: "Convert the bit string H to an integer e
+        // as described in Appendix B.2."
+        let e = digest_scalar_(ops, h);
+
+        let mut nonces = try!(RFC6979Nonces::new(self.alg, &self.private_key,
+                                                 h));
+
+        // XXX: The value 100 was chosen to match `generate_private_key`.
+        for _ in 0..100 {
+            // NSA Guide Step 1 is replaced by RFC 6979 Section 3.2 Step h.
+            let k = match nonces.next() {
+                Some(k) => k,
+                None => { continue; }
+            };
+
+            // NSA Guide Step 2: "Compute the elliptic curve point
+            // R = (xR, yR) = k*G."
+            let r_point = private_key_ops.point_mul_base(&k);
+
+            // NSA Guide Step 3: "Compute r = xR mod n. If r = 0, then return
+            // to Step 1."
+            let (x, _) = try!(private_key::affine_from_jacobian(private_key_ops,
+                                                                &r_point));
+            let x = cops.elem_decoded(&ElemUnreduced::from(&x));
+            let r = ops.scalar_from_limbs_reduced(&x.limbs);
+            if limbs_are_zero_constant_time(&r.limbs[..num_limbs]) ==
+                    LimbMask::True {
+                continue;
+            }
+
+            // NSA Guide Step 6: "Compute s = (k**−1 * (e + d*r)) mod n. If
+            // s = 0, then return to Step 1."
+            let k_inv = ops.scalar_inv_to_mont(&k);
+            let e_plus_dr = ops.scalar_sum(&e, &ops.scalar_product(&d, &r));
+            let s = ops.scalar_mul_mixed(&e_plus_dr, &k_inv);
+            if limbs_are_zero_constant_time(&s.limbs[..num_limbs]) ==
+                    LimbMask::True {
+                continue;
+            }
+
+            // NSA Guide Step 7: "Return (r, s)."
+            audit::notify(audit::EventKind::Signed, self.alg.name);
+            return Ok((self.alg.format_rs)(ops, &r, &s));
+        }
+
+        Err(error::Unspecified)
+    }
+}
+
+impl signature::PrehashedSigningKey for ECDSAKeyPair {
+    fn digest_algorithm(&self) -> &'static digest::Algorithm {
+        self.alg.digest_alg
+    }
+
+    fn sign_prehashed(&self, digest: &digest::Digest)
+                      -> Result<signature::Signature, error::Unspecified> {
+        if digest.algorithm().output_len != self.alg.digest_alg.output_len {
+            return Err(error::Unspecified);
+        }
+        self.sign_digest(digest.as_ref())
+    }
+}
+
+impl signature::KeyPair for ECDSAKeyPair {
+    type PublicKey = [u8];
+
+    fn public_key(&self) -> &[u8] { self.public_key_bytes() }
+}
+
+impl private::Private for ECDSAKeyPair {}
+
+// The generation of the per-message secret `k` from the private key and the
+// message digest as described in RFC 6979 Section 3.2, Steps b through h.
+struct RFC6979Nonces {
+    ops: &'static PublicScalarOps,
+    digest_alg: &'static digest::Algorithm,
+    k: hmac::SigningKey,
+    v: [u8; digest::MAX_OUTPUT_LEN],
+    first: bool,
+}
+
+impl RFC6979Nonces {
+    fn new(alg: &'static ECDSASigningAlgorithm, private_key: &ec::PrivateKey,
+           h: &[u8]) -> Result<RFC6979Nonces, error::Unspecified> {
+        let ops = alg.ops;
+        let cops = ops.public_key_ops.common;
+        let rlen = cops.elem_and_scalar_len;
+        let hlen = alg.digest_alg.output_len;
+
+        // Step a was done by the caller.
+
+        // Step b: "V = 0x01 0x01 0x01 ... 0x01".
+        let mut v = [0; digest::MAX_OUTPUT_LEN];
+        for b in &mut v[..hlen] {
+            *b = 0x01;
+        }
+
+        // Step c: "K = 0x00 0x00 0x00 ... 0x00".
+        let k = hmac::SigningKey::new(alg.digest_alg,
+                                      &[0; digest::MAX_OUTPUT_LEN][..hlen]);
+
+        // bits2octets(h1) = int2octets(bits2int(h1) mod q).
+        let mut h_octets = [0; ec::ELEM_MAX_BYTES];
+        let h_int = try!(bits2int(ops, h));
+        let h_int = ops.scalar_from_limbs_reduced(&h_int);
+        private_key::big_endian_from_limbs(&mut h_octets[..rlen],
+                                           &h_int.limbs[..cops.num_limbs]);
+
+        let x = &private_key.bytes[..rlen];
+        let h_octets = &h_octets[..rlen];
+
+        let mut r = RFC6979Nonces {
+            ops: ops,
+            digest_alg: alg.digest_alg,
+            k: k,
+            v: v,
+            first: true,
+        };
+
+        // Steps d and e: "K = HMAC_K(V || 0x00 || int2octets(x) ||
+        // bits2octets(h1))" and "V = HMAC_K(V)".
+        r.update_k_and_v(&[0x00], x, h_octets);
+
+        // Steps f and g: "K = HMAC_K(V || 0x01 || int2octets(x) ||
+        // bits2octets(h1))" and "V = HMAC_K(V)".
+        r.update_k_and_v(&[0x01], x, h_octets);
+
+        Ok(r)
+    }
+
+    // Returns the next candidate `k` (Step h), or `None` if the candidate
+    // isn't in the range [1, n), in which case the caller should try again.
+    fn next(&mut self) -> Option<Scalar> {
+        let cops = self.ops.public_key_ops.common;
+        let num_limbs = cops.num_limbs;
+        let rlen = cops.elem_and_scalar_len;
+        let hlen = self.digest_alg.output_len;
+
+        // Step h.3: If the previous candidate was rejected, then
+        // "K = HMAC_K(V || 0x00)" and "V = HMAC_K(V)".
+        if !self.first {
+            self.update_k_and_v(&[0x00], &[], &[]);
+        }
+        self.first = false;
+
+        // Steps h.1 and h.2: "Set T to the empty sequence" and "While tlen <
+        // qlen, do the following: V = HMAC_K(V); T = T || V".
+        let mut t = [0; ec::ELEM_MAX_BYTES + digest::MAX_OUTPUT_LEN];
+        let mut tlen = 0;
+        while tlen < rlen {
+            self.v_update();
+            t[tlen..(tlen + hlen)].copy_from_slice(&self.v[..hlen]);
+            tlen += hlen;
+        }
+
+        // Step h.3: "Compute k = bits2int(T). If that value of k is within the
+        // [1,q-1] range [...] then the generation of k is finished."
+        let k = match bits2int(self.ops, &t[..tlen]) {
+            Ok(k) => k,
+            Err(_) => { return None; }
+        };
+        let k = &k[..num_limbs];
+        let n = &cops.n.limbs[..num_limbs];
+        if limbs_are_zero_constant_time(k) != LimbMask::False ||
+           limbs_less_than_limbs_constant_time(k, n) != LimbMask::True {
+            return None;
+        }
+        let mut limbs = [0; MAX_LIMBS];
+        limbs[..num_limbs].copy_from_slice(k);
+        Some(Scalar::from_limbs_unchecked(&limbs))
+    }
+
+    // K = HMAC_K(V || `separator` || `x` || `h`); V = HMAC_K(V).
+    fn update_k_and_v(&mut self, separator: &[u8], x: &[u8], h: &[u8]) {
+        let hlen = self.digest_alg.output_len;
+        let k = {
+            let mut ctx = hmac::SigningContext::with_key(&self.k);
+            ctx.update(&self.v[..hlen]);
+            ctx.update(separator);
+            ctx.update(x);
+            ctx.update(h);
+            ctx.sign()
+        };
+        self.k = hmac::SigningKey::new(self.digest_alg, k.as_ref());
+        self.v_update();
+    }
+
+    // V = HMAC_K(V).
+    fn v_update(&mut self) {
+        let hlen = self.digest_alg.output_len;
+        let v = hmac::sign(&self.k, &self.v[..hlen]);
+        self.v[..hlen].copy_from_slice(v.as_ref());
+    }
+}
+
+// The bits2int function of RFC 6979 Section 2.3.2: the leftmost qlen bits of
+// `bits`, as an integer, where qlen is the bit length of `n`. The result may
+// be larger than `n`.
+fn bits2int(ops: &PublicScalarOps, bits: &[u8])
+            -> Result<[Limb; MAX_LIMBS], error::Unspecified> {
+    let cops = ops.public_key_ops.common;
+    let num_limbs = cops.num_limbs;
+    let qlen = n_bits(ops);
+
+    let bits = if bits.len() > cops.elem_and_scalar_len {
+        &bits[..cops.elem_and_scalar_len]
+    } else {
+        bits
+    };
+    let mut limbs =
+        try!(parse_big_endian_value(untrusted::Input::from(bits), num_limbs));
+
+    // Only P-521's `n` has a bit length that isn't a multiple of eight, so
+    // the shift is always less than a byte.
+    let blen = bits.len() * 8;
+    if blen > qlen {
+        let shift = blen - qlen;
+        debug_assert!(shift < 8);
+        for i in 0..num_limbs {
+            let next = if i + 1 < num_limbs { limbs[i + 1] } else { 0 };
+            limbs[i] = (limbs[i] >> shift) | (next << (LIMB_BITS - shift));
+        }
+    }
+    Ok(limbs)
+}
+
+// Encodes the signature (r, s) as the concatenation of the fixed-length
+// big-endian encodings of `r` and `s`.
+fn format_rs_fixed(ops: &'static PublicScalarOps, r: &Scalar, s: &Scalar)
+                   -> signature::Signature {
+    let cops = ops.public_key_ops.common;
+    let num_limbs = cops.num_limbs;
+    let scalar_len = cops.elem_and_scalar_len;
+    signature::Signature::new_with_len(|out| {
+        let (r_out, rest) = out.split_at_mut(scalar_len);
+        private_key::big_endian_from_limbs(r_out, &r.limbs[..num_limbs]);
+        private_key::big_endian_from_limbs(&mut rest[..scalar_len],
+                                           &s.limbs[..num_limbs]);
+        2 * scalar_len
+    })
+}
+
+// Encodes the signature (r, s) as an ASN.1 DER-encoded `Ecdsa-Sig-Value`.
+fn format_rs_asn1(ops: &'static PublicScalarOps, r: &Scalar, s: &Scalar)
+                  -> signature::Signature {
+    let cops = ops.public_key_ops.common;
+    let num_limbs = cops.num_limbs;
+    let scalar_len = cops.elem_and_scalar_len;
+
+    fn format_integer(out: &mut [u8], value: &[u8]) -> usize {
+        // Skip the leading zeros, but keep at least one byte.
+        let first_nonzero =
+            value.iter().position(|b| *b != 0).unwrap_or(value.len() - 1);
+        let value = &value[first_nonzero..];
+        // Prepend a zero if the high bit is set, so the value isn't negative.
+        let pad = if (value[0] & 0x80) != 0 { 1 } else { 0 };
+        out[0] = der::Tag::Integer as u8;
+        out[1] = (pad + value.len()) as u8;
+        out[2] = 0;
+        out[(2 + pad)..(2 + pad + value.len())].copy_from_slice(value);
+        2 + pad + value.len()
+    }
+
+    let mut r_bytes = [0; ec::ELEM_MAX_BYTES];
+    let mut s_bytes = [0; ec::ELEM_MAX_BYTES];
+    private_key::big_endian_from_limbs(&mut r_bytes[..scalar_len],
+                                       &r.limbs[..num_limbs]);
+    private_key::big_endian_from_limbs(&mut s_bytes[..scalar_len],
+                                       &s.limbs[..num_limbs]);
+
+    signature::Signature::new_with_len(|out| {
+        // The integers are written after the longest possible SEQUENCE header
+        // and then moved into place.
+        const HEADER_MAX_LEN: usize = 3;
+        let r_len = format_integer(&mut out[HEADER_MAX_LEN..],
+                                   &r_bytes[..scalar_len]);
+        let s_len = format_integer(&mut out[(HEADER_MAX_LEN + r_len)..],
+                                   &s_bytes[..scalar_len]);
+        let value_len = r_len + s_len;
+        let header_len = if value_len < 0x80 {
+            out[1] = value_len as u8;
+            2
+        } else {
+            out[1] = 0x81;
+            out[2] = value_len as u8;
+            3
+        };
+        out[0] = der::Tag::Sequence as u8;
+        for i in 0..value_len {
+            out[header_len + i] = out[HEADER_MAX_LEN + i];
+        }
+        header_len + value_len
+    })
+}
+
+
+/// Converts the ASN.1 DER-encoded `Ecdsa-Sig-Value` `signature` to the
+/// fixed-length encoding: the big-endian encoding of `r` followed by that of
+/// `s`, each padded with leading zeros to the length of the curve's scalars,
+/// as used by `ECDSA_P256_SHA256_FIXED`, JOSE, and WebCrypto.
+///
+/// Only the curve of `alg` is used; its digest algorithm and signature
+/// encoding don't matter. This fails unless `signature` is strictly
+/// DER-encoded and `r` and `s` are in the range [1, n), so that there is
+/// exactly one encoding in each form for every signature.
+pub fn ecdsa_asn1_to_fixed(alg: &'static ECDSAParameters,
+                           signature: untrusted::Input)
+                           -> Result<signature::Signature, error::Unspecified> {
+    convert_rs(alg.ops, signature, split_rs_asn1, format_rs_fixed)
+}
+
+/// Converts the fixed-length encoding of an ECDSA signature, as produced by
+/// `ecdsa_asn1_to_fixed`, to an ASN.1 DER-encoded `Ecdsa-Sig-Value`, as used
+/// by `ECDSA_P256_SHA256_ASN1`, X.509, and TLS.
+///
+/// Only the curve of `alg` is used; its digest algorithm and signature
+/// encoding don't matter. This fails unless `signature` is exactly twice as
+/// long as the curve's scalars and `r` and `s` are in the range [1, n).
+pub fn ecdsa_fixed_to_asn1(alg: &'static ECDSAParameters,
+                           signature: untrusted::Input)
+                           -> Result<signature::Signature, error::Unspecified> {
+    convert_rs(alg.ops, signature, split_rs_fixed, format_rs_asn1)
+}
+
+fn convert_rs(ops: &'static PublicScalarOps, signature: untrusted::Input,
+              split_rs: for<'a> fn(ops: &'static PublicScalarOps,
+                                   input: &mut untrusted::Reader<'a>)
+                                   -> Result<(untrusted::Input<'a>,
+                                              untrusted::Input<'a>),
+                                             error::Unspecified>,
+              format_rs: fn(ops: &'static PublicScalarOps, r: &Scalar,
+                            s: &Scalar) -> signature::Signature)
+              -> Result<signature::Signature, error::Unspecified> {
+    let (r, s) = try!(signature.read_all(error::Unspecified, |input| {
+        split_rs(ops, input)
+    }));
+    let r = try!(ops.scalar_parse(r));
+    let s = try!(ops.scalar_parse(s));
+    Ok(format_rs(ops, &r, &s))
+}
+
 
 /// Calculate the digest of `msg` using the digest algorithm `digest_alg`. Then
 /// convert the digest to a scalar in the range [0, n) as described in
@@ -151,20 +833,24 @@ impl private::Private for ECDSAParameters {}
 /// less than 2**256. If the value is larger than `n` then shifting it one bit
 /// right will give a value less than 2**255, which is less than `n`. The
 /// analogous argument applies for P-384. However, it does *not* apply in
-/// general; for example, it doesn't apply to P-521.
+/// general; for example, it doesn't apply to P-521. For P-521, no shifting is
+/// necessary since the only supported digest algorithm is SHA-512, and every
+/// 512-bit value is less than `n`.
 fn digest_scalar(ops: &PublicScalarOps, digest_alg: &'static digest::Algorithm,
                  msg: untrusted::Input) -> Scalar {
     let digest = digest::digest(digest_alg, msg.as_slice_less_safe());
     digest_scalar_(ops, digest.as_ref())
 }
 
-// This is a separate function solely so that we can test specific digest
-// values like all-zero values and values larger than `n`.
+// This is separate from `digest_scalar` so that it can be used for prehashed
+// verification, and so that we can test specific digest values like all-zero
+// values and values larger than `n`.
 fn digest_scalar_(ops: &PublicScalarOps, digest: &[u8]) -> Scalar {
-    let num_limbs = ops.public_key_ops.common.num_limbs;
+    let cops = ops.public_key_ops.common;
+    let num_limbs = cops.num_limbs;
 
-    let digest = if digest.len() > num_limbs * LIMB_BYTES {
-        &digest[..(num_limbs * LIMB_BYTES)]
+    let digest = if digest.len() > cops.elem_and_scalar_len {
+        &digest[..cops.elem_and_scalar_len]
     } else {
         digest
     };
@@ -173,7 +859,7 @@ fn digest_scalar_(ops: &PublicScalarOps, digest: &[u8]) -> Scalar {
     let mut limbs =
         parse_big_endian_value(untrusted::Input::from(digest), num_limbs)
             .unwrap();
-    let n = &ops.public_key_ops.common.n.limbs[..num_limbs];
+    let n = &cops.n.limbs[..num_limbs];
     if !limbs_less_than_limbs(&limbs[..num_limbs], n) {
         let mut carried_bit = 0;
         for i in 0..num_limbs {
@@ -198,22 +884,38 @@ fn twin_mul(ops: &PrivateKeyOps, g_scalar: &Scalar, p_scalar: &Scalar,
 
 macro_rules! ecdsa {
     ( $VERIFY_ALGORITHM:ident, $ecdsa_verify_ops:expr, $digest_alg:expr,
-      $doc_str:expr ) => {
+      ASN1, $doc_str:expr ) => {
+        ecdsa!($VERIFY_ALGORITHM, $ecdsa_verify_ops, $digest_alg, $doc_str,
+               split_rs_asn1,
+               "The signature will be parsed as a DER-encoded \
+                `Ecdsa-Sig-Value` as described in [RFC 3279 Section 2.2.3].");
+    };
+    ( $VERIFY_ALGORITHM:ident, $ecdsa_verify_ops:expr, $digest_alg:expr,
+      FIXED, $doc_str:expr ) => {
+        ecdsa!($VERIFY_ALGORITHM, $ecdsa_verify_ops, $digest_alg, $doc_str,
+               split_rs_fixed,
+               "The signature will be parsed as the fixed-length big-endian \
+                encodings of r and s, each as long as an encoded field \
+                element, concatenated; this is the encoding of IEEE P1363 \
+                and of JWS as described in [RFC 7518 Section 3.4].");
+    };
+    ( $VERIFY_ALGORITHM:ident, $ecdsa_verify_ops:expr, $digest_alg:expr,
+      $doc_str:expr, $split_rs:expr, $encoding_doc_str:expr ) => {
         #[doc=$doc_str]
         ///
         /// Public keys are encoding in uncompressed form using the
         /// Octet-String-to-Elliptic-Curve-Point algorithm in
-        /// [SEC 1: Elliptic Curve Cryptography, Version 2.0]. Public keys are
-        /// validated during key agreement as described in using the ECC
-        /// Partial Public-Key Validation Routine from Section 5.6.2.3.3 of
+        /// [SEC 1: Elliptic Curve Cryptography, Version 2.0], or in
+        /// compressed form. Public keys are validated during key agreement as
+        /// described in using the ECC Partial Public-Key Validation Routine
+        /// from Section 5.6.2.3.3 of
         /// [NIST Special Publication 800-56A, revision 2] and Appendix A.3 of
         /// the NSA's [Suite B implementer's guide to FIPS 186-3]. Note that,
         /// as explained in the NSA guide, ECC Partial Public-Key Validation is
         /// equivalent to ECC Full Public-Key Validation for prime-order curves
         /// like this one.
         ///
-        /// The signature will be parsed as a DER-encoded `Ecdsa-Sig-Value` as
-        /// described in [RFC 3279 Section 2.2.3].
+        #[doc=$encoding_doc_str]
         ///
         /// [SEC 1: Elliptic Curve Cryptography, Version 2.0]:
         ///     http://www.secg.org/sec1-v2.pdf
@@ -223,35 +925,130 @@ macro_rules! ecdsa {
         ///     https://github.com/briansmith/ring/blob/master/doc/ecdsa.pdf
         /// [RFC 3279 Section 2.2.3]:
         ///     https://tools.ietf.org/html/rfc3279#section-2.2.3
+        /// [RFC 7518 Section 3.4]:
+        ///     https://tools.ietf.org/html/rfc7518#section-3.4
         pub static $VERIFY_ALGORITHM: ECDSAParameters = ECDSAParameters {
+            name: stringify!($VERIFY_ALGORITHM),
             ops: $ecdsa_verify_ops,
             digest_alg: $digest_alg,
+            split_rs: $split_rs,
         };
     }
 }
 
-ecdsa!(ECDSA_P256_SHA1_ASN1, &p256::PUBLIC_SCALAR_OPS, &digest::SHA1,
+ecdsa!(ECDSA_P256_SHA1_ASN1, &p256::PUBLIC_SCALAR_OPS, &digest::SHA1, ASN1,
        "Verification of ECDSA signatures using the P-256 curve and SHA-1.");
-ecdsa!(ECDSA_P256_SHA256_ASN1, &p256::PUBLIC_SCALAR_OPS, &digest::SHA256,
+ecdsa!(ECDSA_P256_SHA256_ASN1, &p256::PUBLIC_SCALAR_OPS, &digest::SHA256, ASN1,
        "Verification of ECDSA signatures using the P-256 curve and SHA-256.");
-ecdsa!(ECDSA_P256_SHA384_ASN1, &p256::PUBLIC_SCALAR_OPS, &digest::SHA384,
+ecdsa!(ECDSA_P256_SHA384_ASN1, &p256::PUBLIC_SCALAR_OPS, &digest::SHA384, ASN1,
        "Verification of ECDSA signatures using the P-256 curve and SHA-384.");
-ecdsa!(ECDSA_P256_SHA512_ASN1, &p256::PUBLIC_SCALAR_OPS, &digest::SHA512,
+ecdsa!(ECDSA_P256_SHA512_ASN1, &p256::PUBLIC_SCALAR_OPS, &digest::SHA512, ASN1,
        "Verification of ECDSA signatures using the P-256 curve and SHA-512.");
 
-ecdsa!(ECDSA_P384_SHA1_ASN1, &p384::PUBLIC_SCALAR_OPS, &digest::SHA1,
+ecdsa!(ECDSA_P384_SHA1_ASN1, &p384::PUBLIC_SCALAR_OPS, &digest::SHA1, ASN1,
        "Verification of ECDSA signatures using the P-384 curve and SHA-1.");
-ecdsa!(ECDSA_P384_SHA256_ASN1, &p384::PUBLIC_SCALAR_OPS, &digest::SHA256,
+ecdsa!(ECDSA_P384_SHA256_ASN1, &p384::PUBLIC_SCALAR_OPS, &digest::SHA256, ASN1,
        "Verification of ECDSA signatures using the P-384 curve and SHA-256.");
-ecdsa!(ECDSA_P384_SHA384_ASN1, &p384::PUBLIC_SCALAR_OPS, &digest::SHA384,
+ecdsa!(ECDSA_P384_SHA384_ASN1, &p384::PUBLIC_SCALAR_OPS, &digest::SHA384, ASN1,
        "Verification of ECDSA signatures using the P-384 curve and SHA-384.");
-ecdsa!(ECDSA_P384_SHA512_ASN1, &p384::PUBLIC_SCALAR_OPS, &digest::SHA512,
+ecdsa!(ECDSA_P384_SHA512_ASN1, &p384::PUBLIC_SCALAR_OPS, &digest::SHA512, ASN1,
        "Verification of ECDSA signatures using the P-384 curve and SHA-512.");
 
+ecdsa!(ECDSA_P521_SHA512_ASN1, &p521::PUBLIC_SCALAR_OPS, &digest::SHA512, ASN1,
+       "Verification of ECDSA signatures using the P-521 curve and SHA-512.");
+
+ecdsa!(ECDSA_P256_SHA256_FIXED, &p256::PUBLIC_SCALAR_OPS, &digest::SHA256,
+       FIXED,
+       "Verification of fixed-length (IEEE P1363) ECDSA signatures using the \
+        P-256 curve and SHA-256.");
+ecdsa!(ECDSA_P384_SHA384_FIXED, &p384::PUBLIC_SCALAR_OPS, &digest::SHA384,
+       FIXED,
+       "Verification of fixed-length (IEEE P1363) ECDSA signatures using the \
+        P-384 curve and SHA-384.");
+
+macro_rules! ecdsa_signing {
+    ( $SIGNING_ALGORITHM:ident, $ecdsa_ops:expr, $pkcs8_alg_id:expr,
+      $digest_alg:expr, ASN1, $doc_str:expr ) => {
+        ecdsa_signing!($SIGNING_ALGORITHM, $ecdsa_ops, $pkcs8_alg_id,
+                       $digest_alg, $doc_str, format_rs_asn1,
+                       "The signature is encoded as a DER-encoded \
+                        `Ecdsa-Sig-Value` as described in \
+                        [RFC 3279 Section 2.2.3].");
+    };
+    ( $SIGNING_ALGORITHM:ident, $ecdsa_ops:expr, $pkcs8_alg_id:expr,
+      $digest_alg:expr, FIXED, $doc_str:expr ) => {
+        ecdsa_signing!($SIGNING_ALGORITHM, $ecdsa_ops, $pkcs8_alg_id,
+                       $digest_alg, $doc_str, format_rs_fixed,
+                       "The signature is encoded as the fixed-length \
+                        big-endian encodings of r and s, each as long as an \
+                        encoded field element, concatenated; this is the \
+                        encoding of IEEE P1363 and of JWS as described in \
+                        [RFC 7518 Section 3.4].");
+    };
+    ( $SIGNING_ALGORITHM:ident, $ecdsa_ops:expr, $pkcs8_alg_id:expr,
+      $digest_alg:expr, $doc_str:expr, $format_rs:expr,
+      $encoding_doc_str:expr ) => {
+        #[doc=$doc_str]
+        ///
+        /// The per-message secret `k` is derived deterministically from the
+        /// private key and the digest of the message as described in
+        /// [RFC 6979 Section 3.2], so signing the same message with the same
+        /// key always gives the same signature, and signing doesn't depend on
+        /// the quality of a random number generator.
+        ///
+        #[doc=$encoding_doc_str]
+        /// It can be verified with the verification algorithm for the same
+        /// curve, digest algorithm, and encoding.
+        ///
+        /// [RFC 6979 Section 3.2]:
+        ///     https://tools.ietf.org/html/rfc6979#section-3.2
+        /// [RFC 3279 Section 2.2.3]:
+        ///     https://tools.ietf.org/html/rfc3279#section-2.2.3
+        /// [RFC 7518 Section 3.4]:
+        ///     https://tools.ietf.org/html/rfc7518#section-3.4
+        pub static $SIGNING_ALGORITHM: ECDSASigningAlgorithm =
+                ECDSASigningAlgorithm {
+            name: stringify!($SIGNING_ALGORITHM),
+            ops: $ecdsa_ops,
+            digest_alg: $digest_alg,
+            format_rs: $format_rs,
+            #[cfg(feature = "use_heap")]
+            pkcs8_alg_id: $pkcs8_alg_id,
+        };
+    }
+}
+
+ecdsa_signing!(ECDSA_P256_SHA256_ASN1_SIGNING_DETERMINISTIC,
+               &p256::PUBLIC_SCALAR_OPS, pkcs8::EC_PUBLIC_KEY_P256,
+               &digest::SHA256, ASN1,
+               "Deterministic signing of ECDSA signatures using the P-256 \
+                curve and SHA-256.");
+ecdsa_signing!(ECDSA_P384_SHA384_ASN1_SIGNING_DETERMINISTIC,
+               &p384::PUBLIC_SCALAR_OPS, pkcs8::EC_PUBLIC_KEY_P384,
+               &digest::SHA384, ASN1,
+               "Deterministic signing of ECDSA signatures using the P-384 \
+                curve and SHA-384.");
+ecdsa_signing!(ECDSA_P521_SHA512_ASN1_SIGNING_DETERMINISTIC,
+               &p521::PUBLIC_SCALAR_OPS, pkcs8::EC_PUBLIC_KEY_P521,
+               &digest::SHA512, ASN1,
+               "Deterministic signing of ECDSA signatures using the P-521 \
+                curve and SHA-512.");
+ecdsa_signing!(ECDSA_P256_SHA256_FIXED_SIGNING_DETERMINISTIC,
+               &p256::PUBLIC_SCALAR_OPS, pkcs8::EC_PUBLIC_KEY_P256,
+               &digest::SHA256, FIXED,
+               "Deterministic signing of fixed-length (IEEE P1363) ECDSA \
+                signatures using the P-256 curve and SHA-256.");
+ecdsa_signing!(ECDSA_P384_SHA384_FIXED_SIGNING_DETERMINISTIC,
+               &p384::PUBLIC_SCALAR_OPS, pkcs8::EC_PUBLIC_KEY_P384,
+               &digest::SHA384, FIXED,
+               "Deterministic signing of fixed-length (IEEE P1363) ECDSA \
+                signatures using the P-384 curve and SHA-384.");
+
 
 #[cfg(test)]
 mod tests {
-    use {digest, test, signature};
+    use {digest, ec, error, test, signature};
+    use std;
     use super::digest_scalar_;
     use super::super::ops::*;
     use untrusted;
@@ -282,10 +1079,478 @@ mod tests {
             let actual_result = signature::verify(alg, public_key, msg, sig);
             assert_eq!(actual_result.is_ok(), expected_result == "P (0 )");
 
+            // Verifying the message in pieces gives the same result.
+            let prehashed_alg =
+                prehashed_alg_from_curve_and_digest(&curve_name, &digest_name);
+            let mut ctx =
+                signature::VerificationContext::new(prehashed_alg, public_key);
+            for chunk in msg.as_slice_less_safe().chunks(7) {
+                ctx.update(chunk);
+            }
+            assert_eq!(ctx.finish(sig).is_ok(), actual_result.is_ok());
+
+            // Valid signatures also verify with the compressed public key.
+            if actual_result.is_ok() {
+                let public_key = public_key.as_slice_less_safe();
+                let elem_len = (public_key.len() - 1) / 2;
+                let mut compressed =
+                    vec![2 | (public_key[public_key.len() - 1] & 1)];
+                compressed.extend_from_slice(&public_key[1..(1 + elem_len)]);
+                assert!(signature::verify(alg,
+                                          untrusted::Input::from(&compressed),
+                                          msg, sig).is_ok());
+            }
+
             Ok(())
         });
     }
 
+    #[test]
+    fn signature_ecdsa_verify_fixed_test() {
+        test::from_file("src/ec/suite_b/ecdsa_verify_fixed_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+
+            let curve_name = test_case.consume_string("Curve");
+            let digest_name = test_case.consume_string("Digest");
+
+            let msg = test_case.consume_bytes("Msg");
+            let msg = untrusted::Input::from(&msg);
+
+            let public_key = test_case.consume_bytes("Q");
+            let public_key = untrusted::Input::from(&public_key);
+
+            let sig = test_case.consume_bytes("Sig");
+            let sig = untrusted::Input::from(&sig);
+
+            let expected_result = test_case.consume_string("Result");
+
+            let alg = fixed_alg_from_curve_and_digest(&curve_name,
+                                                      &digest_name);
+
+            let actual_result = signature::verify(alg, public_key, msg, sig);
+            assert_eq!(actual_result.is_ok(), expected_result == "P");
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn signature_ecdsa_verify_prehashed_test() {
+        test::from_file("src/ec/suite_b/ecdsa_verify_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+
+            let curve_name = test_case.consume_string("Curve");
+            let digest_name = test_case.consume_string("Digest");
+
+            let msg = test_case.consume_bytes("Msg");
+
+            let public_key = test_case.consume_bytes("Q");
+            let public_key = untrusted::Input::from(&public_key);
+
+            let sig = test_case.consume_bytes("Sig");
+            let sig = untrusted::Input::from(&sig);
+
+            let expected_result = test_case.consume_string("Result");
+
+            let (_, _, digest_alg) = alg_from_curve_and_digest(&curve_name,
+                                                               &digest_name);
+            let alg = prehashed_alg_from_curve_and_digest(&curve_name,
+                                                          &digest_name);
+            assert_eq!(alg.digest_algorithm().output_len,
+                       digest_alg.output_len);
+
+            let digest = digest::digest(digest_alg, &msg);
+            let digest = digest.as_ref();
+
+            let actual_result =
+                signature::verify_prehashed(alg, public_key,
+                                            untrusted::Input::from(digest),
+                                            sig);
+            assert_eq!(actual_result.is_ok(), expected_result == "P (0 )");
+
+            // Digests of the wrong length are rejected.
+            let truncated = &digest[..(digest.len() - 1)];
+            assert!(signature::verify_prehashed(
+                        alg, public_key, untrusted::Input::from(truncated),
+                        sig).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn signature_ecdsa_sign_deterministic_test() {
+        test::from_file("src/ec/suite_b/ecdsa_sign_deterministic_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+
+            let curve_name = test_case.consume_string("Curve");
+            let digest_name = test_case.consume_string("Digest");
+            let msg = test_case.consume_bytes("Msg");
+            let d = test_case.consume_bytes("d");
+            let q = test_case.consume_bytes("Q");
+            let expected_sig = test_case.consume_bytes("Sig");
+
+            let alg = match (curve_name.as_str(), digest_name.as_str()) {
+                ("P-256", "SHA256") =>
+                    &signature::ECDSA_P256_SHA256_ASN1_SIGNING_DETERMINISTIC,
+                ("P-384", "SHA384") =>
+                    &signature::ECDSA_P384_SHA384_ASN1_SIGNING_DETERMINISTIC,
+                ("P-521", "SHA512") =>
+                    &signature::ECDSA_P521_SHA512_ASN1_SIGNING_DETERMINISTIC,
+                _ => panic!("Unsupported curve/digest: {}/{}", curve_name,
+                            digest_name),
+            };
+            let (verification_alg, _, _) =
+                alg_from_curve_and_digest(&curve_name, &digest_name);
+
+            let key_pair =
+                signature::ECDSAKeyPair::from_private_key(
+                    alg, untrusted::Input::from(&d)).unwrap();
+            assert_eq!(key_pair.public_key_bytes(), &q[..]);
+            assert_eq!(signature::KeyPair::public_key(&key_pair), &q[..]);
+
+            let actual_sig = key_pair.sign(&msg).unwrap();
+            assert_eq!(actual_sig.as_slice(), &expected_sig[..]);
+
+            let mut ctx = signature::SigningContext::new(&key_pair);
+            for chunk in msg.chunks(7) {
+                ctx.update(chunk);
+            }
+            assert_eq!(ctx.finish().unwrap().as_slice(), &expected_sig[..]);
+
+            // A digest from the wrong digest algorithm is rejected.
+            let wrong_digest = digest::digest(&digest::SHA1, &msg);
+            assert!(signature::PrehashedSigningKey::sign_prehashed(
+                        &key_pair, &wrong_digest).is_err());
+
+            assert!(signature::verify(verification_alg,
+                                      untrusted::Input::from(&q),
+                                      untrusted::Input::from(&msg),
+                                      untrusted::Input::from(
+                                          actual_sig.as_slice())).is_ok());
+
+            // The same signature, in the fixed-length encoding.
+            let alg = match (curve_name.as_str(), digest_name.as_str()) {
+                ("P-256", "SHA256") =>
+                    &signature::ECDSA_P256_SHA256_FIXED_SIGNING_DETERMINISTIC,
+                ("P-384", "SHA384") =>
+                    &signature::ECDSA_P384_SHA384_FIXED_SIGNING_DETERMINISTIC,
+                _ => { return Ok(()); },
+            };
+            let verification_alg =
+                fixed_alg_from_curve_and_digest(&curve_name, &digest_name);
+            let scalar_len = (q.len() - 1) / 2;
+            let expected_sig = try!(fixed_from_asn1(&expected_sig, scalar_len));
+
+            let key_pair =
+                signature::ECDSAKeyPair::from_private_key(
+                    alg, untrusted::Input::from(&d)).unwrap();
+            let actual_sig = key_pair.sign(&msg).unwrap();
+            assert_eq!(actual_sig.as_slice(), &expected_sig[..]);
+
+            assert!(signature::verify(verification_alg,
+                                      untrusted::Input::from(&q),
+                                      untrusted::Input::from(&msg),
+                                      untrusted::Input::from(
+                                          actual_sig.as_slice())).is_ok());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn signature_ecdsa_rfc6979_nonce_test() {
+        test::from_file("src/ec/suite_b/ecdsa_rfc6979_nonce_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+
+            let curve_name = test_case.consume_string("Curve");
+            let digest_name = test_case.consume_string("Digest");
+            let msg = test_case.consume_bytes("Msg");
+            let d = test_case.consume_bytes("d");
+            let counter = test_case.consume_usize("Counter");
+            let expected_k = test_case.consume_bytes("K");
+
+            let alg = match (curve_name.as_str(), digest_name.as_str()) {
+                ("P-256", "SHA256") =>
+                    &signature::ECDSA_P256_SHA256_ASN1_SIGNING_DETERMINISTIC,
+                ("P-384", "SHA384") =>
+                    &signature::ECDSA_P384_SHA384_ASN1_SIGNING_DETERMINISTIC,
+                ("P-521", "SHA512") =>
+                    &signature::ECDSA_P521_SHA512_ASN1_SIGNING_DETERMINISTIC,
+                _ => panic!("Unsupported curve/digest: {}/{}", curve_name,
+                            digest_name),
+            };
+            let digest_alg = digest::Algorithm::from_name(&digest_name)
+                .unwrap();
+
+            let key_pair =
+                signature::ECDSAKeyPair::from_private_key(
+                    alg, untrusted::Input::from(&d)).unwrap();
+            let h = digest::digest(digest_alg, &msg);
+
+            let mut actual_k = [0u8; ec::ELEM_MAX_BYTES];
+            let actual_k = &mut actual_k[..expected_k.len()];
+            assert!(key_pair.rfc6979_nonce(&h, counter, actual_k).is_ok());
+            assert_eq!(&actual_k[..], &expected_k[..]);
+
+            // `out` must have exactly the length of the curve's order.
+            let mut too_long = [0u8; ec::ELEM_MAX_BYTES + 1];
+            let too_long = &mut too_long[..(expected_k.len() + 1)];
+            assert!(key_pair.rfc6979_nonce(&h, counter, too_long).is_err());
+            let too_short = &mut actual_k[..(expected_k.len() - 1)];
+            assert!(key_pair.rfc6979_nonce(&h, counter, too_short).is_err());
+
+            // A digest from the wrong digest algorithm is rejected.
+            let wrong_digest = digest::digest(&digest::SHA1, &msg);
+            assert!(key_pair.rfc6979_nonce(&wrong_digest, counter,
+                                           actual_k).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn signature_ecdsa_format_conversion_test() {
+        test::from_file("src/ec/suite_b/ecdsa_sign_deterministic_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+
+            let curve_name = test_case.consume_string("Curve");
+            let _ = test_case.consume_string("Digest");
+            let _ = test_case.consume_bytes("Msg");
+            let _ = test_case.consume_bytes("d");
+            let q = test_case.consume_bytes("Q");
+            let asn1_sig = test_case.consume_bytes("Sig");
+
+            let alg = match curve_name.as_str() {
+                "P-256" => &signature::ECDSA_P256_SHA256_ASN1,
+                "P-384" => &signature::ECDSA_P384_SHA384_ASN1,
+                "P-521" => &signature::ECDSA_P521_SHA512_ASN1,
+                _ => panic!("Unsupported curve: {}", curve_name),
+            };
+            let scalar_len = (q.len() - 1) / 2;
+            let fixed_sig = try!(fixed_from_asn1(&asn1_sig, scalar_len));
+
+            let actual_fixed = signature::format::ecdsa_asn1_to_fixed(
+                alg, untrusted::Input::from(&asn1_sig)).unwrap();
+            assert_eq!(actual_fixed.as_slice(), &fixed_sig[..]);
+
+            let actual_asn1 = signature::format::ecdsa_fixed_to_asn1(
+                alg, untrusted::Input::from(&fixed_sig)).unwrap();
+            assert_eq!(actual_asn1.as_slice(), &asn1_sig[..]);
+
+            // The fixed-length encoding must have exactly the right length.
+            assert!(signature::format::ecdsa_fixed_to_asn1(
+                        alg, untrusted::Input::from(&fixed_sig[1..]))
+                        .is_err());
+            let mut long_sig = fixed_sig.clone();
+            long_sig.push(0);
+            assert!(signature::format::ecdsa_fixed_to_asn1(
+                        alg, untrusted::Input::from(&long_sig)).is_err());
+
+            // Trailing garbage after the DER `SEQUENCE` is rejected.
+            let mut long_sig = asn1_sig.clone();
+            long_sig.push(0);
+            assert!(signature::format::ecdsa_asn1_to_fixed(
+                        alg, untrusted::Input::from(&long_sig)).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn signature_ecdsa_format_conversion_out_of_range_test() {
+        let alg = &signature::ECDSA_P256_SHA256_ASN1;
+        let n = test::from_hex("ffffffff00000000ffffffffffffffffbce6faada7179e8\
+                                4f3b9cac2fc632551").unwrap();
+        let zero = [0u8; 32];
+        let mut one = [0u8; 32];
+        one[31] = 1;
+
+        let fixed = |r: &[u8], s: &[u8]| {
+            let mut sig = std::vec::Vec::new();
+            sig.extend_from_slice(r);
+            sig.extend_from_slice(s);
+            signature::format::ecdsa_fixed_to_asn1(
+                alg, untrusted::Input::from(&sig))
+        };
+        assert!(fixed(&one, &one).is_ok());
+        assert!(fixed(&zero, &one).is_err());
+        assert!(fixed(&one, &zero).is_err());
+        assert!(fixed(&n, &one).is_err());
+        assert!(fixed(&one, &n).is_err());
+
+        let asn1 = |sig: &[u8]| {
+            signature::format::ecdsa_asn1_to_fixed(
+                alg, untrusted::Input::from(sig))
+        };
+        assert!(asn1(&[0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x01])
+                    .is_ok());
+        // r = 0.
+        assert!(asn1(&[0x30, 0x06, 0x02, 0x01, 0x00, 0x02, 0x01, 0x01])
+                    .is_err());
+        // r isn't minimally encoded.
+        assert!(asn1(&[0x30, 0x07, 0x02, 0x02, 0x00, 0x01, 0x02, 0x01, 0x01])
+                    .is_err());
+        // r is negative.
+        assert!(asn1(&[0x30, 0x06, 0x02, 0x01, 0x81, 0x02, 0x01, 0x01])
+                    .is_err());
+    }
+
+    #[test]
+    fn signature_ecdsa_key_pair_from_private_key_test() {
+        let alg = &signature::ECDSA_P256_SHA256_ASN1_SIGNING_DETERMINISTIC;
+        let n = test::from_hex("ffffffff00000000ffffffffffffffffbce6faada7179e8\
+                                4f3b9cac2fc632551").unwrap();
+        let n_minus_1 =
+            test::from_hex("ffffffff00000000ffffffffffffffffbce6faada7179e84f3\
+                            b9cac2fc632550").unwrap();
+        let zero = [0u8; 32];
+        let one = {
+            let mut one = [0u8; 32];
+            one[31] = 1;
+            one
+        };
+
+        assert!(signature::ECDSAKeyPair::from_private_key(
+                    alg, untrusted::Input::from(&one)).is_ok());
+        assert!(signature::ECDSAKeyPair::from_private_key(
+                    alg, untrusted::Input::from(&n_minus_1)).is_ok());
+
+        // Out of range.
+        assert!(signature::ECDSAKeyPair::from_private_key(
+                    alg, untrusted::Input::from(&zero)).is_err());
+        assert!(signature::ECDSAKeyPair::from_private_key(
+                    alg, untrusted::Input::from(&n)).is_err());
+
+        // Wrong length.
+        assert!(signature::ECDSAKeyPair::from_private_key(
+                    alg, untrusted::Input::from(&one[1..])).is_err());
+        assert!(signature::ECDSAKeyPair::from_private_key(
+                    alg, untrusted::Input::from(&n_minus_1[..31])).is_err());
+    }
+
+    #[test]
+    fn signature_ecdsa_key_pair_from_private_key_and_public_key_test() {
+        let alg = &signature::ECDSA_P256_SHA256_FIXED_SIGNING_DETERMINISTIC;
+        let one = {
+            let mut one = [0u8; 32];
+            one[31] = 1;
+            one
+        };
+        let two = {
+            let mut two = [0u8; 32];
+            two[31] = 2;
+            two
+        };
+        let from_private_key = |private_key: &[u8]| {
+            signature::ECDSAKeyPair::from_private_key(
+                alg, untrusted::Input::from(private_key)).unwrap()
+        };
+        let g = from_private_key(&one);
+        let g = g.public_key_bytes();
+        let two_g = from_private_key(&two);
+        let two_g = two_g.public_key_bytes();
+
+        let from_private_key_and_public_key =
+                |private_key: &[u8], public_key: &[u8]| {
+            signature::ECDSAKeyPair::from_private_key_and_public_key(
+                alg, untrusted::Input::from(private_key),
+                untrusted::Input::from(public_key))
+        };
+
+        // The key pair is the same as the one `from_private_key` constructs.
+        let key_pair = from_private_key_and_public_key(&one, g).unwrap();
+        assert_eq!(key_pair.public_key_bytes(), g);
+
+        // Mismatched.
+        assert!(from_private_key_and_public_key(&one, two_g).is_err());
+        assert!(from_private_key_and_public_key(&two, g).is_err());
+
+        // The public key is corrupted or truncated.
+        let mut corrupted = [0u8; 65];
+        corrupted.copy_from_slice(g);
+        corrupted[64] ^= 1;
+        assert!(from_private_key_and_public_key(&one, &corrupted).is_err());
+        assert!(from_private_key_and_public_key(&one, &g[..64]).is_err());
+
+        // The private key is invalid.
+        assert!(from_private_key_and_public_key(&[0u8; 32], g).is_err());
+    }
+
+    #[cfg(feature = "use_heap")]
+    #[test]
+    fn signature_ecdsa_key_pair_from_pkcs8_test() {
+        test::from_file("src/ec/suite_b/ecdsa_from_pkcs8_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+
+            let curve_name = test_case.consume_string("Curve");
+            let input = test_case.consume_bytes("Input");
+            let q = test_case.consume_bytes("Q");
+            let expected_result = test_case.consume_string("Result");
+
+            let alg = signing_alg_from_curve(&curve_name);
+            let result = signature::ECDSAKeyPair::from_pkcs8(
+                alg, untrusted::Input::from(&input));
+            match expected_result.as_str() {
+                "P" => assert_eq!(result.unwrap().public_key_bytes(), &q[..]),
+                "F" => assert!(result.is_err()),
+                _ => panic!("Unexpected Result: {}", expected_result),
+            }
+
+            Ok(())
+        });
+    }
+
+    #[cfg(feature = "use_heap")]
+    #[test]
+    fn signature_ecdsa_key_pair_generate_pkcs8_test() {
+        use rand;
+
+        let rng = rand::SystemRandom::new();
+        for &(curve_name, digest_name) in &[("P-256", "SHA256"),
+                                           ("P-384", "SHA384"),
+                                           ("P-521", "SHA512")] {
+            let alg = signing_alg_from_curve(curve_name);
+            let pkcs8 = signature::ECDSAKeyPair::generate_pkcs8(alg, &rng)
+                .unwrap();
+            let key_pair = signature::ECDSAKeyPair::from_pkcs8(
+                alg, untrusted::Input::from(&pkcs8)).unwrap();
+
+            let (verification_alg, _, _) =
+                alg_from_curve_and_digest(curve_name, digest_name);
+            const MSG: &'static [u8] = b"hello, world";
+            let sig = key_pair.sign(MSG).unwrap();
+            assert!(signature::verify(
+                verification_alg,
+                untrusted::Input::from(key_pair.public_key_bytes()),
+                untrusted::Input::from(MSG),
+                untrusted::Input::from(sig.as_slice())).is_ok());
+
+            // The document is specific to the curve.
+            let other_alg = signing_alg_from_curve(
+                if curve_name == "P-256" { "P-384" } else { "P-256" });
+            assert!(signature::ECDSAKeyPair::from_pkcs8(
+                        other_alg, untrusted::Input::from(&pkcs8)).is_err());
+        }
+    }
+
+    #[cfg(feature = "use_heap")]
+    fn signing_alg_from_curve(curve_name: &str)
+                              -> &'static signature::ECDSASigningAlgorithm {
+        match curve_name {
+            "P-256" => &signature::ECDSA_P256_SHA256_ASN1_SIGNING_DETERMINISTIC,
+            "P-384" => &signature::ECDSA_P384_SHA384_ASN1_SIGNING_DETERMINISTIC,
+            "P-521" => &signature::ECDSA_P521_SHA512_ASN1_SIGNING_DETERMINISTIC,
+            _ => panic!("Unsupported curve: {}", curve_name),
+        }
+    }
+
     #[test]
     fn ecdsa_digest_scalar_test() {
         test::from_file("src/ec/suite_b/ecdsa_digest_scalar_tests.txt",
@@ -305,7 +1570,7 @@ mod tests {
             let num_limbs = ops.public_key_ops.common.num_limbs;
             assert_eq!(input.len(), digest_alg.output_len);
             assert_eq!(output.len(),
-                       ops.public_key_ops.common.num_limbs * LIMB_BYTES);
+                       ops.public_key_ops.common.elem_and_scalar_len);
 
             let expected =
                 try!(parse_big_endian_value(untrusted::Input::from(&output),
@@ -319,6 +1584,77 @@ mod tests {
         });
     }
 
+    #[test]
+    fn signature_ecdsa_algorithm_metadata_test() {
+        use signature::VerificationAlgorithm;
+
+        let verification_algs: &[(&VerificationAlgorithm, &str, usize)] = &[
+            (&signature::ECDSA_P256_SHA1_ASN1, "ECDSA_P256_SHA1_ASN1", 63),
+            (&signature::ECDSA_P256_SHA256_ASN1, "ECDSA_P256_SHA256_ASN1",
+             128),
+            (&signature::ECDSA_P256_SHA512_ASN1, "ECDSA_P256_SHA512_ASN1",
+             128),
+            (&signature::ECDSA_P384_SHA256_ASN1, "ECDSA_P384_SHA256_ASN1",
+             128),
+            (&signature::ECDSA_P384_SHA384_FIXED, "ECDSA_P384_SHA384_FIXED",
+             192),
+            (&signature::ECDSA_P521_SHA512_ASN1, "ECDSA_P521_SHA512_ASN1",
+             256),
+        ];
+        for &(alg, name, security_bits) in verification_algs {
+            assert_eq!(alg.name(), name);
+            assert_eq!(alg.security_bits(), security_bits);
+        }
+
+        let alg = &signature::ECDSA_P384_SHA384_FIXED_SIGNING_DETERMINISTIC;
+        assert_eq!(alg.name(), "ECDSA_P384_SHA384_FIXED_SIGNING_DETERMINISTIC");
+        assert_eq!(alg.security_bits(), 192);
+    }
+
+    // Converts a DER-encoded `Ecdsa-Sig-Value` to the fixed-length encoding.
+    fn fixed_from_asn1(sig: &[u8], scalar_len: usize)
+                       -> Result<std::vec::Vec<u8>, error::Unspecified> {
+        let (r, s) = try!(untrusted::Input::from(sig).read_all(
+                error::Unspecified, |input| {
+            super::split_rs_asn1(&p256::PUBLIC_SCALAR_OPS, input)
+        }));
+        let mut fixed = vec![0; 2 * scalar_len];
+        for (value, out) in [r, s].iter().zip(fixed.chunks_mut(scalar_len)) {
+            let value = value.as_slice_less_safe();
+            let out_len = out.len();
+            out[(out_len - value.len())..].copy_from_slice(value);
+        }
+        Ok(fixed)
+    }
+
+    fn fixed_alg_from_curve_and_digest(curve_name: &str, digest_name: &str)
+            -> &'static signature::VerificationAlgorithm {
+        match (curve_name, digest_name) {
+            ("P-256", "SHA256") => &signature::ECDSA_P256_SHA256_FIXED,
+            ("P-384", "SHA384") => &signature::ECDSA_P384_SHA384_FIXED,
+            _ => panic!("Unsupported curve/digest: {}/{}", curve_name,
+                        digest_name),
+        }
+    }
+
+    fn prehashed_alg_from_curve_and_digest(curve_name: &str,
+                                           digest_name: &str)
+            -> &'static signature::PrehashedVerificationAlgorithm {
+        match (curve_name, digest_name) {
+            ("P-256", "SHA1") => &signature::ECDSA_P256_SHA1_ASN1,
+            ("P-256", "SHA256") => &signature::ECDSA_P256_SHA256_ASN1,
+            ("P-256", "SHA384") => &signature::ECDSA_P256_SHA384_ASN1,
+            ("P-256", "SHA512") => &signature::ECDSA_P256_SHA512_ASN1,
+            ("P-384", "SHA1") => &signature::ECDSA_P384_SHA1_ASN1,
+            ("P-384", "SHA256") => &signature::ECDSA_P384_SHA256_ASN1,
+            ("P-384", "SHA384") => &signature::ECDSA_P384_SHA384_ASN1,
+            ("P-384", "SHA512") => &signature::ECDSA_P384_SHA512_ASN1,
+            ("P-521", "SHA512") => &signature::ECDSA_P521_SHA512_ASN1,
+            _ => panic!("Unsupported curve/digest: {}/{}", curve_name,
+                        digest_name),
+        }
+    }
+
     fn alg_from_curve_and_digest(curve_name: &str, digest_name: &str)
                                  -> (&'static signature::VerificationAlgorithm,
                                      &'static PublicScalarOps,
@@ -355,6 +1691,13 @@ mod tests {
             } else {
                 panic!("Unsupported digest algorithm: {}", digest_name);
             }
+        } else if curve_name == "P-521" {
+            if digest_name == "SHA512" {
+                (&signature::ECDSA_P521_SHA512_ASN1, &p521::PUBLIC_SCALAR_OPS,
+                 &digest::SHA512)
+            } else {
+                panic!("Unsupported digest algorithm: {}", digest_name);
+            }
         } else {
             panic!("Unsupported curve: {}", curve_name);
         }
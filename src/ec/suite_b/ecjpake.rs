@@ -0,0 +1,498 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//! EC J-PAKE over P-256 with SHA-256.
+
+use {constant_time, digest, error, rand};
+use limb::*;
+use super::ops::*;
+use super::private_key::*;
+use super::public_key::parse_uncompressed_point;
+use untrusted;
+
+/// The maximum length of a round one message.
+pub const ROUND_ONE_MAX_LEN: usize = 2 * KKP_MAX_LEN;
+
+/// The maximum length of a round two message.
+pub const ROUND_TWO_MAX_LEN: usize = EC_PARAMETERS_LEN + KKP_MAX_LEN;
+
+/// The maximum length of the shared secret (the password).
+pub const MAX_SECRET_LEN: usize = SCALAR_LEN;
+
+/// The length of the premaster secret.
+pub const PREMASTER_SECRET_LEN: usize = 256 / 8;
+
+const ELEM_LEN: usize = 256 / 8;
+const SCALAR_LEN: usize = ELEM_LEN;
+const POINT_LEN: usize = 1 + (2 * ELEM_LEN);
+
+// ECJPAKEKeyKP: the length-prefixed point X, and the ECSchnorrZKP, which is
+// the length-prefixed point V and the length-prefixed scalar r.
+const KKP_MAX_LEN: usize = (2 * (1 + POINT_LEN)) + (1 + SCALAR_LEN);
+
+// ECParameters for secp256r1: the curve type named_curve (3) and the
+// NamedCurve secp256r1 (23).
+const EC_PARAMETERS: [u8; EC_PARAMETERS_LEN] = [3, 0, 23];
+const EC_PARAMETERS_LEN: usize = 3;
+
+/// A party's role in the exchange.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Role {
+    /// The client.
+    Client,
+
+    /// The server.
+    Server,
+}
+
+impl Role {
+    // The identities used in the zero-knowledge proofs.
+    fn id(self) -> &'static [u8] {
+        match self {
+            Role::Client => b"client",
+            Role::Server => b"server",
+        }
+    }
+
+    fn peer(self) -> Role {
+        match self {
+            Role::Client => Role::Server,
+            Role::Server => Role::Client,
+        }
+    }
+}
+
+/// A party's state during the first round.
+pub struct RoundOne {
+    role: Role,
+    s: Scalar,
+    x1_pub: Point,
+    x2: Scalar,
+    x2_pub: Point,
+    message: [u8; ROUND_ONE_MAX_LEN],
+    message_len: usize,
+}
+
+impl RoundOne {
+    /// Generates the keys `x1` and `x2` and the round one message, which
+    /// contains `X1` and `X2` with their zero-knowledge proofs.
+    ///
+    /// `secret` is interpreted as a big-endian integer and reduced modulo the
+    /// group order, as mbed TLS does. It must be at most `MAX_SECRET_LEN`
+    /// bytes long and its value mustn't be a multiple of the group order.
+    pub fn new(role: Role, secret: &[u8], rng: &rand::SecureRandom)
+               -> Result<RoundOne, error::Unspecified> {
+        let s = try!(secret_scalar(secret));
+
+        let mut message = [0u8; ROUND_ONE_MAX_LEN];
+        let x1 = try!(random_scalar(rng));
+        let (x1_pub, len1) = try!(write_kkp(&Base::Generator, &G, &x1,
+                                            role.id(), rng, &mut message));
+        let x2 = try!(random_scalar(rng));
+        let (x2_pub, len2) = try!(write_kkp(&Base::Generator, &G, &x2,
+                                            role.id(), rng,
+                                            &mut message[len1..]));
+        Ok(RoundOne {
+            role: role,
+            s: s,
+            x1_pub: x1_pub,
+            x2: x2,
+            x2_pub: x2_pub,
+            message: message,
+            message_len: len1 + len2,
+        })
+    }
+
+    /// The round one message, which is sent to the peer.
+    #[inline]
+    pub fn message(&self) -> &[u8] { &self.message[..self.message_len] }
+
+    /// Verifies the peer's round one message, and generates the round two
+    /// message.
+    pub fn finish(self, peer_message: untrusted::Input,
+                  rng: &rand::SecureRandom)
+                  -> Result<RoundTwo, error::Unspecified> {
+        let peer_id = self.role.peer().id();
+        let (x3, x4) = try!(peer_message.read_all(error::Unspecified, |r| {
+            let x3 = try!(read_kkp(&Base::Generator, &G, peer_id, r));
+            let x4 = try!(read_kkp(&Base::Generator, &G, peer_id, r));
+            Ok((x3, x4))
+        }));
+        let x3_pub = point_from_affine(&x3);
+        let x4_pub = point_from_affine(&x4);
+
+        // Our base is X1 + X3 + X4 and the peer's is X1 + X2 + X3.
+        let ops = OPS.common;
+        let g = ops.point_sum(&ops.point_sum(&self.x1_pub, &x3_pub), &x4_pub);
+        let mut g_encoded = [0u8; POINT_LEN];
+        try!(point_encode(&g, &mut g_encoded));
+        let g = Base::Point(try!(affine_from_jacobian(OPS, &g)));
+        let peer_g = ops.point_sum(&ops.point_sum(&self.x1_pub, &self.x2_pub),
+                                   &x3_pub);
+        let mut peer_g_encoded = [0u8; POINT_LEN];
+        try!(point_encode(&peer_g, &mut peer_g_encoded));
+        let peer_g = try!(affine_from_jacobian(OPS, &peer_g));
+
+        // The server's message starts with the curve.
+        let mut message = [0u8; ROUND_TWO_MAX_LEN];
+        let params_len = match self.role {
+            Role::Client => 0,
+            Role::Server => {
+                message[..EC_PARAMETERS_LEN].copy_from_slice(&EC_PARAMETERS);
+                EC_PARAMETERS_LEN
+            },
+        };
+        let x2_s = SCALAR_OPS.scalar_product(&self.x2, &self.s);
+        let (_, kkp_len) =
+            try!(write_kkp(&g, &g_encoded, &x2_s, self.role.id(), rng,
+                           &mut message[params_len..]));
+
+        Ok(RoundTwo {
+            role: self.role,
+            x2: self.x2,
+            x2_s: x2_s,
+            x4: x4,
+            peer_g: peer_g,
+            peer_g_encoded: peer_g_encoded,
+            message: message,
+            message_len: params_len + kkp_len,
+        })
+    }
+}
+
+/// A party's state during the second round.
+pub struct RoundTwo {
+    role: Role,
+    x2: Scalar,
+    x2_s: Scalar,
+    x4: (Elem, Elem),
+    peer_g: (Elem, Elem),
+    peer_g_encoded: [u8; POINT_LEN],
+    message: [u8; ROUND_TWO_MAX_LEN],
+    message_len: usize,
+}
+
+impl RoundTwo {
+    /// The round two message, which is sent to the peer.
+    #[inline]
+    pub fn message(&self) -> &[u8] { &self.message[..self.message_len] }
+
+    /// Verifies the peer's round two message and returns the premaster
+    /// secret, which is SHA-256 of the x coordinate of the shared point
+    /// `K`. It is the same for both parties only if they used the same
+    /// secret.
+    pub fn finish(self, peer_message: untrusted::Input)
+                  -> Result<digest::Digest, error::Unspecified> {
+        let role = self.role;
+        let peer_g = Base::Point(self.peer_g);
+        let peer_g_encoded = &self.peer_g_encoded;
+        let x4_x2_s = try!(peer_message.read_all(error::Unspecified, |r| {
+            if role == Role::Client {
+                let params = try!(r.skip_and_get_input(EC_PARAMETERS_LEN));
+                if params.as_slice_less_safe() != &EC_PARAMETERS[..] {
+                    return Err(error::Unspecified);
+                }
+            }
+            read_kkp(&peer_g, peer_g_encoded, role.peer().id(), r)
+        }));
+
+        // K = (X4x2s - X4 * x2 * s) * x2
+        //   = x2 * X4x2s - (x2 * x2 * s) * X4.
+        let a = OPS.point_mul(&self.x2, &x4_x2_s);
+        let b = SCALAR_OPS.scalar_negated(
+            &SCALAR_OPS.scalar_product(&self.x2, &self.x2_s));
+        let k = OPS.common.point_sum(&a, &OPS.point_mul(&b, &self.x4));
+        try!(OPS.common.elem_verify_is_not_zero(&OPS.common.point_z(&k)));
+        let mut k_x = [0u8; ELEM_LEN];
+        try!(big_endian_affine_from_jacobian(OPS, Some(&mut k_x), None, &k));
+        Ok(digest::digest(&digest::SHA256, &k_x))
+    }
+}
+
+// The base point of the keys and proofs in a message.
+enum Base {
+    Generator,
+    Point((Elem, Elem)),
+}
+
+impl Base {
+    fn mul(&self, a: &Scalar) -> Point {
+        match *self {
+            Base::Generator => OPS.point_mul_base(a),
+            Base::Point(ref p) => OPS.point_mul(a, p),
+        }
+    }
+}
+
+// Writes the ECJPAKEKeyKP for `X = x * g` to `out`, returning `X` and the
+// length written. The proof that the writer knows `x` is a Schnorr proof
+// (V = v * g, r = v - x * h), where `h` is `zkp_hash(g, V, X, id)`.
+fn write_kkp(g: &Base, g_encoded: &[u8], x: &Scalar, id: &[u8],
+             rng: &rand::SecureRandom, out: &mut [u8])
+             -> Result<(Point, usize), error::Unspecified> {
+    let x_pub = g.mul(x);
+    let mut x_encoded = [0u8; POINT_LEN];
+    try!(point_encode(&x_pub, &mut x_encoded));
+
+    let v = try!(random_scalar(rng));
+    let mut v_encoded = [0u8; POINT_LEN];
+    try!(point_encode(&g.mul(&v), &mut v_encoded));
+
+    let h = zkp_hash(g_encoded, &v_encoded, &x_encoded, id);
+    let r = SCALAR_OPS.scalar_sum(
+        &v, &SCALAR_OPS.scalar_negated(&SCALAR_OPS.scalar_product(x, &h)));
+
+    // r is written with the minimal number of bytes, but at least one.
+    let mut r_encoded = [0u8; SCALAR_LEN];
+    big_endian_from_limbs(&mut r_encoded, &r.limbs[..OPS.common.num_limbs]);
+    let leading_zeros = r_encoded[..(SCALAR_LEN - 1)].iter()
+                                                     .take_while(|&&b| b == 0)
+                                                     .count();
+    let r_encoded = &r_encoded[leading_zeros..];
+
+    let mut len = 0;
+    for field in [&x_encoded[..], &v_encoded[..], r_encoded].iter() {
+        out[len] = field.len() as u8;
+        out[(len + 1)..(len + 1 + field.len())].copy_from_slice(field);
+        len += 1 + field.len();
+    }
+    Ok((x_pub, len))
+}
+
+// Reads an ECJPAKEKeyKP and verifies its proof, returning `X`.
+fn read_kkp(g: &Base, g_encoded: &[u8], id: &[u8],
+            input: &mut untrusted::Reader)
+            -> Result<(Elem, Elem), error::Unspecified> {
+    let x_encoded = try!(read_len_prefixed(input));
+    let x = try!(parse_uncompressed_point(PUBLIC_KEY_OPS, x_encoded));
+    let v_encoded = try!(read_len_prefixed(input));
+    // `V` is compared with the computed point below, so it doesn't need to
+    // be validated separately.
+    if v_encoded.len() != POINT_LEN {
+        return Err(error::Unspecified);
+    }
+    let r = try!(scalar_parse(try!(read_len_prefixed(input))));
+
+    // r * g + h * X == V.
+    let h = zkp_hash(g_encoded, v_encoded.as_slice_less_safe(),
+                     x_encoded.as_slice_less_safe(), id);
+    let v = OPS.common.point_sum(&g.mul(&r), &OPS.point_mul(&h, &x));
+    let mut v_computed = [0u8; POINT_LEN];
+    try!(point_encode(&v, &mut v_computed));
+    try!(constant_time::verify_slices_are_equal(
+            &v_computed, v_encoded.as_slice_less_safe()));
+    Ok(x)
+}
+
+fn read_len_prefixed<'a>(input: &mut untrusted::Reader<'a>)
+                         -> Result<untrusted::Input<'a>, error::Unspecified> {
+    let len = try!(input.read_byte().map_err(|_| error::Unspecified));
+    input.skip_and_get_input(len as usize).map_err(|_| error::Unspecified)
+}
+
+// SHA-256(G || V || X || id) mod n, where each point is prefixed with its
+// length as a 32-bit big-endian value, and so is `id`.
+fn zkp_hash(g: &[u8], v: &[u8], x: &[u8], id: &[u8]) -> Scalar {
+    let mut ctx = digest::Context::new(&digest::SHA256);
+    for part in [g, v, x, id].iter() {
+        ctx.update(&be_u8_from_u32(part.len() as u32));
+        ctx.update(part);
+    }
+    let h = ctx.finish();
+    // A SHA-256 digest always fits in `num_limbs` limbs.
+    let limbs = parse_big_endian_value(untrusted::Input::from(h.as_ref()),
+                                       OPS.common.num_limbs).unwrap();
+    SCALAR_OPS.scalar_from_limbs_reduced(&limbs)
+}
+
+fn be_u8_from_u32(value: u32) -> [u8; 4] {
+    [(value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8,
+     value as u8]
+}
+
+static OPS: &'static PrivateKeyOps = &p256::PRIVATE_KEY_OPS;
+static PUBLIC_KEY_OPS: &'static PublicKeyOps = &p256::PUBLIC_KEY_OPS;
+static SCALAR_OPS: &'static PublicScalarOps = &p256::PUBLIC_SCALAR_OPS;
+
+fn random_scalar(rng: &rand::SecureRandom)
+                 -> Result<Scalar, error::Unspecified> {
+    let private_key = try!(generate_private_key(OPS, rng));
+    Ok(private_key_as_scalar(OPS, &private_key))
+}
+
+fn secret_scalar(secret: &[u8]) -> Result<Scalar, error::Unspecified> {
+    if secret.is_empty() || secret.len() > MAX_SECRET_LEN {
+        return Err(error::Unspecified);
+    }
+    let limbs = try!(parse_big_endian_value(untrusted::Input::from(secret),
+                                            OPS.common.num_limbs));
+    let s = SCALAR_OPS.scalar_from_limbs_reduced(&limbs);
+    if limbs_are_zero_constant_time(&s.limbs[..OPS.common.num_limbs]) ==
+            LimbMask::True {
+        return Err(error::Unspecified);
+    }
+    Ok(s)
+}
+
+// Parses a big-endian scalar of at most `SCALAR_LEN` bytes in the range
+// [0, n).
+fn scalar_parse(input: untrusted::Input)
+                -> Result<Scalar, error::Unspecified> {
+    if input.is_empty() || input.len() > SCALAR_LEN {
+        return Err(error::Unspecified);
+    }
+    let n = &OPS.common.n.limbs[..OPS.common.num_limbs];
+    let limbs = try!(parse_big_endian_value(input, n.len()));
+    if limbs_less_than_limbs_constant_time(&limbs[..n.len()], n) !=
+            LimbMask::True {
+        return Err(error::Unspecified);
+    }
+    Ok(Scalar::from_limbs_unchecked(&limbs))
+}
+
+// Encodes `p` in uncompressed form. Fails if `p` is at infinity.
+fn point_encode(p: &Point, out: &mut [u8])
+                -> Result<(), error::Unspecified> {
+    debug_assert_eq!(out.len(), POINT_LEN);
+    try!(OPS.common.elem_verify_is_not_zero(&OPS.common.point_z(p)));
+    out[0] = 4;
+    let (x_out, y_out) = (&mut out[1..]).split_at_mut(ELEM_LEN);
+    big_endian_affine_from_jacobian(OPS, Some(x_out), Some(y_out), p)
+}
+
+fn point_from_affine(&(ref x, ref y): &(Elem, Elem)) -> Point {
+    OPS.common.point_from_affine(&ElemUnreduced::from(x),
+                                 &ElemUnreduced::from(y))
+}
+
+// The generator of P-256, in uncompressed form.
+static G: [u8; POINT_LEN] = [
+    0x04, 0x6b, 0x17, 0xd1, 0xf2, 0xe1, 0x2c, 0x42, 0x47, 0xf8, 0xbc, 0xe6,
+    0xe5, 0x63, 0xa4, 0x40, 0xf2, 0x77, 0x03, 0x7d, 0x81, 0x2d, 0xeb, 0x33,
+    0xa0, 0xf4, 0xa1, 0x39, 0x45, 0xd8, 0x98, 0xc2, 0x96, 0x4f, 0xe3, 0x42,
+    0xe2, 0xfe, 0x1a, 0x7f, 0x9b, 0x8e, 0xe7, 0xeb, 0x4a, 0x7c, 0x0f, 0x9e,
+    0x16, 0x2b, 0xce, 0x33, 0x57, 0x6b, 0x31, 0x5e, 0xce, 0xcb, 0xb6, 0x40,
+    0x68, 0x37, 0xbf, 0x51, 0xf5,
+];
+
+#[cfg(test)]
+mod tests {
+    use {core, ecjpake, error, test};
+    use rand::test_util::{FixedSliceRandom, FixedSliceSequenceRandom};
+    use untrusted;
+
+    // Starts round one with the first four 32-byte chunks of `random` and
+    // finishes it with the fifth.
+    fn round_one(role: ecjpake::Role, secret: &[u8], random: &[u8])
+                 -> ecjpake::RoundOne {
+        let chunks = [&random[0..32], &random[32..64], &random[64..96],
+                      &random[96..128]];
+        let rng = FixedSliceSequenceRandom {
+            bytes: &chunks,
+            current: core::cell::UnsafeCell::new(0),
+        };
+        ecjpake::RoundOne::new(role, secret, &rng).unwrap()
+    }
+
+    fn round_two(role: ecjpake::Role, secret: &[u8], random: &[u8],
+                 peer_message: &[u8])
+                 -> Result<ecjpake::RoundTwo, error::Unspecified> {
+        round_one(role, secret, random)
+            .finish(untrusted::Input::from(peer_message),
+                    &FixedSliceRandom { bytes: &random[128..] })
+    }
+
+    #[test]
+    fn test_ecjpake() {
+        test::from_file("src/ec/suite_b/ecjpake_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+            let secret = test_case.consume_bytes("Secret");
+            let random_c = test_case.consume_bytes("RandomC");
+            let random_s = test_case.consume_bytes("RandomS");
+            let client_one = test_case.consume_bytes("ClientOne");
+            let server_one = test_case.consume_bytes("ServerOne");
+            let server_two = test_case.consume_bytes("ServerTwo");
+            let client_two = test_case.consume_bytes("ClientTwo");
+            let pms = test_case.consume_bytes("PMS");
+
+            let client = ecjpake::Role::Client;
+            let server = ecjpake::Role::Server;
+            assert_eq!(round_one(client, &secret, &random_c).message(),
+                       &client_one[..]);
+            assert_eq!(round_one(server, &secret, &random_s).message(),
+                       &server_one[..]);
+
+            let c = round_two(client, &secret, &random_c, &server_one).unwrap();
+            assert_eq!(c.message(), &client_two[..]);
+            let s = round_two(server, &secret, &random_s, &client_one).unwrap();
+            assert_eq!(s.message(), &server_two[..]);
+
+            let actual = c.finish(untrusted::Input::from(&server_two)).unwrap();
+            assert_eq!(actual.as_ref(), &pms[..]);
+            let actual = s.finish(untrusted::Input::from(&client_two)).unwrap();
+            assert_eq!(actual.as_ref(), &pms[..]);
+
+            // The proofs are bound to the identities, so a party's own
+            // messages are rejected.
+            assert!(round_two(client, &secret, &random_c, &client_one)
+                        .is_err());
+
+            // Any change to the peer's messages is detected.
+            for i in 0..server_one.len() {
+                let mut bad = server_one.clone();
+                bad[i] ^= 1;
+                assert!(round_two(client, &secret, &random_c, &bad).is_err());
+            }
+            let mut bad = server_two.clone();
+            let last = bad.len() - 1;
+            bad[last] ^= 1;
+            let c = round_two(client, &secret, &random_c, &server_one).unwrap();
+            assert!(c.finish(untrusted::Input::from(&bad)).is_err());
+
+            // The client requires the curve in the server's round two
+            // message, and the server doesn't accept it.
+            let c = round_two(client, &secret, &random_c, &server_one).unwrap();
+            assert!(c.finish(untrusted::Input::from(&server_two[3..]))
+                        .is_err());
+            let mut bad = server_two[..3].to_vec();
+            bad.extend_from_slice(&client_two);
+            let s = round_two(server, &secret, &random_s, &client_one).unwrap();
+            assert!(s.finish(untrusted::Input::from(&bad)).is_err());
+
+            // A client with the wrong secret gets a different premaster
+            // secret.
+            let c = round_two(client, b"wrong", &random_c, &server_one)
+                        .unwrap();
+            let actual = c.finish(untrusted::Input::from(&server_two))
+                          .unwrap();
+            assert!(actual.as_ref() != &pms[..]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_ecjpake_secret_limits() {
+        let rng = FixedSliceRandom { bytes: &[1u8; 32] };
+        let client = ecjpake::Role::Client;
+        assert!(ecjpake::RoundOne::new(client, b"", &rng).is_err());
+        assert!(ecjpake::RoundOne::new(client, &[0u8; 4], &rng).is_err());
+        assert!(ecjpake::RoundOne::new(client, &[1u8; 33], &rng).is_err());
+
+        // n.
+        let n = test::from_hex("ffffffff00000000ffffffffffffffff\
+                                bce6faada7179e84f3b9cac2fc632551").unwrap();
+        assert!(ecjpake::RoundOne::new(client, &n, &rng).is_err());
+    }
+}
@@ -0,0 +1,260 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The P256_XMD:SHA-256_SSWU_RO_ and P384_XMD:SHA-384_SSWU_RO_ hash-to-curve
+//! suites of [RFC 9380 Section 8.2].
+//!
+//! The simplified SWU map is implemented with the straight-line,
+//! constant-time algorithm of RFC 9380 Appendix F.2, so the message may be
+//! secret.
+//!
+//! [RFC 9380 Section 8.2]:
+//!     https://www.rfc-editor.org/rfc/rfc9380.html#section-8.2
+
+use {digest, error};
+use hash_to_curve::{expand_message_xmd, expand_message_xmd_parts};
+use super::ops::*;
+use super::private_key::big_endian_affine_from_jacobian;
+use untrusted;
+
+/// A NIST curve hash-to-curve suite.
+pub struct Suite {
+    ops: &'static PrivateKeyOps,
+    digest_alg: &'static digest::Algorithm,
+
+    // L in RFC 9380 Section 5.
+    hash_to_field_chunk_len: usize,
+
+    // -Z, where Z is the non-square of RFC 9380 Section 8.2.
+    minus_z: Limb,
+
+    // The constants c1 and c2 of `sqrt_ratio` in RFC 9380 Appendix F.2.1.2,
+    // big-endian encoded.
+    q_minus_3_over_4: &'static [u8],
+    sqrt_minus_z: &'static [u8],
+}
+
+/// P256_XMD:SHA-256_SSWU_RO_.
+pub static P256_XMD_SHA256_SSWU_RO: Suite = Suite {
+    ops: &p256::PRIVATE_KEY_OPS,
+    digest_alg: &digest::SHA256,
+    hash_to_field_chunk_len: 48,
+    minus_z: 10,
+    q_minus_3_over_4: &P256_Q_MINUS_3_OVER_4,
+    sqrt_minus_z: &P256_SQRT_MINUS_Z,
+};
+
+/// P384_XMD:SHA-384_SSWU_RO_.
+pub static P384_XMD_SHA384_SSWU_RO: Suite = Suite {
+    ops: &p384::PRIVATE_KEY_OPS,
+    digest_alg: &digest::SHA384,
+    hash_to_field_chunk_len: 72,
+    minus_z: 12,
+    q_minus_3_over_4: &P384_Q_MINUS_3_OVER_4,
+    sqrt_minus_z: &P384_SQRT_MINUS_Z,
+};
+
+impl Suite {
+    /// The length of the uncompressed encoding of a point.
+    #[inline]
+    pub fn output_len(&self) -> usize { 1 + (2 * self.elem_len()) }
+
+    #[inline]
+    fn elem_len(&self) -> usize { self.ops.common.num_limbs * LIMB_BYTES }
+}
+
+/// Hashes `msg` to a point using the domain separation tag `dst`, writing
+/// the point to `out` in the uncompressed form (0x04 || x || y). `out` must
+/// be exactly `suite.output_len()` bytes long.
+pub fn hash_to_curve(suite: &Suite, msg: &[u8], dst: &[u8], out: &mut [u8])
+                     -> Result<(), error::Unspecified> {
+    if out.len() != suite.output_len() {
+        return Err(error::Unspecified);
+    }
+    let ops = suite.ops.common;
+    let chunk_len = suite.hash_to_field_chunk_len;
+
+    let mut uniform_bytes = [0u8; 2 * MAX_CHUNK_LEN];
+    let uniform_bytes = &mut uniform_bytes[..(2 * chunk_len)];
+    try!(expand_message_xmd(suite.digest_alg, msg, dst, uniform_bytes));
+    let (u0, u1) = uniform_bytes.split_at(chunk_len);
+    let q0 = try!(map_to_curve(suite, &try!(elem_from_uniform_bytes(ops, u0))));
+    let q1 = try!(map_to_curve(suite, &try!(elem_from_uniform_bytes(ops, u1))));
+
+    // The cofactor is 1, so clear_cofactor is a no-op.
+    point_encode(suite, &ops.point_sum(&q0, &q1), out)
+}
+
+/// Like `hash_to_curve`, but with the nonuniform encoding `encode_to_curve`
+/// (the `_NU_` variant of the suite) and with the message given as the
+/// concatenation of `msg_parts`.
+pub fn encode_to_curve(suite: &Suite, msg_parts: &[&[u8]], dst: &[u8],
+                       out: &mut [u8]) -> Result<(), error::Unspecified> {
+    if out.len() != suite.output_len() {
+        return Err(error::Unspecified);
+    }
+    let ops = suite.ops.common;
+
+    let mut uniform_bytes = [0u8; MAX_CHUNK_LEN];
+    let uniform_bytes = &mut uniform_bytes[..suite.hash_to_field_chunk_len];
+    try!(expand_message_xmd_parts(suite.digest_alg, msg_parts, dst,
+                                  uniform_bytes));
+    let u = try!(elem_from_uniform_bytes(ops, uniform_bytes));
+    let q = try!(map_to_curve(suite, &u));
+    point_encode(suite, &q, out)
+}
+
+fn point_encode(suite: &Suite, p: &Point, out: &mut [u8])
+                -> Result<(), error::Unspecified> {
+    let ops = suite.ops.common;
+    if ops.elem_verify_is_not_zero(&ops.point_z(p)).is_err() {
+        return Err(error::Unspecified);
+    }
+
+    let elem_len = suite.elem_len();
+    let (tag, xy) = out.split_at_mut(1);
+    let (x, y) = xy.split_at_mut(elem_len);
+    tag[0] = 4;
+    big_endian_affine_from_jacobian(suite.ops, Some(x), Some(y), p)
+}
+
+const MAX_CHUNK_LEN: usize = 72;
+
+// Reduces a `hash_to_field_chunk_len`-byte big-endian value mod q.
+fn elem_from_uniform_bytes(ops: &CommonOps, bytes: &[u8])
+                           -> Result<ElemUnreduced, error::Unspecified> {
+    // hi * 2**(num_limbs * LIMB_BITS) + lo.
+    let (hi, lo) = bytes.split_at(bytes.len() - (ops.num_limbs * LIMB_BYTES));
+    let hi = try!(parse_big_endian_value(untrusted::Input::from(hi),
+                                         ops.num_limbs));
+    let lo = try!(parse_big_endian_value(untrusted::Input::from(lo),
+                                         ops.num_limbs));
+
+    // 2**(num_limbs * LIMB_BITS) doesn't fit in `num_limbs` limbs, so it is
+    // computed as the square of 2**(num_limbs * LIMB_BITS / 2).
+    let mut half_shift = [0; MAX_LIMBS];
+    half_shift[ops.num_limbs / 2] = 1;
+    let shift = ops.elem_squared(&ops.elem_from_limbs(&half_shift));
+
+    let hi = ops.elem_product(&ops.elem_from_limbs(&hi), &shift);
+    Ok(elem_sum(ops, &hi, &ops.elem_from_limbs(&lo)))
+}
+
+// The simplified SWU map, following the straight-line implementation of
+// `map_to_curve_simple_swu` in RFC 9380 Appendix F.2.
+fn map_to_curve(suite: &Suite, u: &ElemUnreduced)
+                -> Result<Point, error::Unspecified> {
+    let ops = suite.ops.common;
+    let one = elem_from_limb(ops, 1);
+    let z = ops.elem_negated(&elem_from_limb(ops, suite.minus_z));
+
+    let tv1 = ops.elem_product(&z, &ops.elem_squared(u));
+    let tv2 = elem_sum(ops, &ops.elem_squared(&tv1), &tv1);
+    let tv3 = ops.elem_product(&ops.b, &elem_sum(ops, &tv2, &one));
+    let tv4 = ops.elem_select(ops.elem_is_zero(&tv2), &z,
+                              &ops.elem_negated(&tv2));
+    let tv4 = ops.elem_product(&ops.a, &tv4);
+    let tv6 = ops.elem_squared(&tv4);
+    let tv5 = ops.elem_product(&ops.a, &tv6);
+    let tv2 = elem_sum(ops, &ops.elem_squared(&tv3), &tv5);
+    let tv2 = ops.elem_product(&tv2, &tv3);
+    let tv6 = ops.elem_product(&tv6, &tv4);
+    let tv5 = ops.elem_product(&ops.b, &tv6);
+    let tv2 = elem_sum(ops, &tv2, &tv5);
+    let x = ops.elem_product(&tv1, &tv3);
+    let (is_gx1_square, y1) = try!(sqrt_ratio(suite, &tv2, &tv6));
+    let y = ops.elem_product(&ops.elem_product(&tv1, u), &y1);
+    let x = ops.elem_select(is_gx1_square, &tv3, &x);
+    let y = ops.elem_select(is_gx1_square, &y1, &y);
+    let e1 = limbs_are_zero_constant_time(&[ops.elem_parity(u) ^
+                                            ops.elem_parity(&y)]);
+    let y = ops.elem_select(e1, &y, &ops.elem_negated(&y));
+    let x = ops.elem_product(&x, &suite.ops.elem_inverse(&tv4));
+
+    Ok(ops.point_from_affine(&x, &y))
+}
+
+// `sqrt_ratio` for q = 3 (mod 4), from RFC 9380 Appendix F.2.1.2. Returns
+// `LimbMask::True` and sqrt(u / v) if u / v is square, and `LimbMask::False`
+// and sqrt(Z * u / v) otherwise.
+fn sqrt_ratio(suite: &Suite, u: &ElemUnreduced, v: &ElemUnreduced)
+              -> Result<(LimbMask, ElemUnreduced), error::Unspecified> {
+    let ops = suite.ops.common;
+    let c2 = try!(parse_big_endian_value(
+        untrusted::Input::from(suite.sqrt_minus_z), ops.num_limbs));
+    let c2 = ops.elem_from_limbs(&c2);
+
+    let tv2 = ops.elem_product(u, v);
+    let tv1 = ops.elem_product(&ops.elem_squared(v), &tv2);
+    let y1 = elem_exp_vartime(ops, &tv1, suite.q_minus_3_over_4);
+    let y1 = ops.elem_product(&y1, &tv2);
+    let y2 = ops.elem_product(&y1, &c2);
+    let tv3 = ops.elem_product(&ops.elem_squared(&y1), v);
+    let is_qr = ops.elem_is_zero(&ops.elem_difference(&tv3, u));
+    let y = ops.elem_select(is_qr, &y1, &y2);
+    Ok((is_qr, y))
+}
+
+// Returns `a**e`, where the exponent `e` is big-endian encoded. This is
+// constant-time with respect to `a` but not `e`.
+pub fn elem_exp_vartime(ops: &CommonOps, a: &ElemUnreduced, e: &[u8])
+                        -> ElemUnreduced {
+    let mut acc = elem_from_limb(ops, 1);
+    for byte in e {
+        for i in (0..8).rev() {
+            ops.elem_square(&mut acc);
+            if (byte >> i) & 1 == 1 {
+                ops.elem_mul(&mut acc, a);
+            }
+        }
+    }
+    acc
+}
+
+fn elem_from_limb(ops: &CommonOps, a: Limb) -> ElemUnreduced {
+    let mut limbs = [0; MAX_LIMBS];
+    limbs[0] = a;
+    ops.elem_from_limbs(&limbs)
+}
+
+// `elem_add` requires its inputs to be fully reduced.
+fn elem_sum(ops: &CommonOps, a: &ElemUnreduced, b: &ElemUnreduced)
+            -> ElemUnreduced {
+    let mut r = ElemUnreduced::from(&ops.elem_reduced(a));
+    ops.elem_add(&mut r, &ElemUnreduced::from(&ops.elem_reduced(b)));
+    r
+}
+
+const P256_SQRT_MINUS_Z: [u8; 32] = [
+    0xda, 0x53, 0x8e, 0x3b, 0xe1, 0xd8, 0x9b, 0x99, 0xc9, 0x78, 0xfc, 0x67,
+    0x51, 0x80, 0xaa, 0xb2, 0x7b, 0x8d, 0x1f, 0xf8, 0x4c, 0x55, 0xd5, 0xb6,
+    0x2c, 0xcd, 0x34, 0x27, 0xe4, 0x33, 0xc4, 0x7f,
+];
+const P256_Q_MINUS_3_OVER_4: [u8; 32] = [
+    0x3f, 0xff, 0xff, 0xff, 0xc0, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x3f, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+];
+const P384_SQRT_MINUS_Z: [u8; 48] = [
+    0x2a, 0xcc, 0xb4, 0xa6, 0x56, 0xb0, 0x24, 0x9c, 0x71, 0xf0, 0x50, 0x0e,
+    0x83, 0xda, 0x2f, 0xdd, 0x7f, 0x98, 0xe3, 0x83, 0xd6, 0x8b, 0x53, 0x87,
+    0x1f, 0x87, 0x2f, 0xcb, 0x9c, 0xcb, 0x80, 0xc5, 0x3c, 0x0d, 0xe1, 0xf8,
+    0xa8, 0x0f, 0x7e, 0x19, 0x14, 0xe2, 0xec, 0x69, 0xf5, 0xa6, 0x26, 0xb3,
+];
+const P384_Q_MINUS_3_OVER_4: [u8; 48] = [
+    0x3f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xbf, 0xff, 0xff, 0xff,
+    0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x3f, 0xff, 0xff, 0xff,
+];
@@ -12,7 +12,7 @@
 // OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
 // CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 
-use {c, der, error};
+use {c, error};
 use core;
 use untrusted;
 
@@ -89,40 +89,56 @@ impl Clone for Point {
 
 impl Copy for Point {}
 
-#[cfg(all(target_pointer_width = "32", target_endian = "little"))]
+#[cfg(target_pointer_width = "32")]
 macro_rules! limbs {
-    ( $limb_b:expr, $limb_a:expr, $limb_9:expr, $limb_8:expr,
-      $limb_7:expr, $limb_6:expr, $limb_5:expr, $limb_4:expr,
-      $limb_3:expr, $limb_2:expr, $limb_1:expr, $limb_0:expr ) => {
+    ( $limb_16:expr, $limb_15:expr, $limb_14:expr, $limb_13:expr,
+      $limb_12:expr, $limb_11:expr, $limb_10:expr, $limb_9:expr,
+      $limb_8:expr, $limb_7:expr, $limb_6:expr, $limb_5:expr,
+      $limb_4:expr, $limb_3:expr, $limb_2:expr, $limb_1:expr,
+      $limb_0:expr ) => {
         [$limb_0, $limb_1, $limb_2, $limb_3,
          $limb_4, $limb_5, $limb_6, $limb_7,
-         $limb_8, $limb_9, $limb_a, $limb_b]
+         $limb_8, $limb_9, $limb_10, $limb_11,
+         $limb_12, $limb_13, $limb_14, $limb_15,
+         $limb_16]
     }
 }
 
-#[cfg(all(target_pointer_width = "64", target_endian = "little"))]
+#[cfg(target_pointer_width = "64")]
 macro_rules! limbs {
-    ( $limb_b:expr, $limb_a:expr, $limb_9:expr, $limb_8:expr,
-      $limb_7:expr, $limb_6:expr, $limb_5:expr, $limb_4:expr,
-      $limb_3:expr, $limb_2:expr, $limb_1:expr, $limb_0:expr ) => {
+    ( $limb_16:expr, $limb_15:expr, $limb_14:expr, $limb_13:expr,
+      $limb_12:expr, $limb_11:expr, $limb_10:expr, $limb_9:expr,
+      $limb_8:expr, $limb_7:expr, $limb_6:expr, $limb_5:expr,
+      $limb_4:expr, $limb_3:expr, $limb_2:expr, $limb_1:expr,
+      $limb_0:expr ) => {
         [(($limb_1 | 0u64) << 32) | $limb_0,
          (($limb_3 | 0u64) << 32) | $limb_2,
          (($limb_5 | 0u64) << 32) | $limb_4,
          (($limb_7 | 0u64) << 32) | $limb_6,
          (($limb_9 | 0u64) << 32) | $limb_8,
-         (($limb_b | 0u64) << 32) | $limb_a]
+         (($limb_11 | 0u64) << 32) | $limb_10,
+         (($limb_13 | 0u64) << 32) | $limb_12,
+         (($limb_15 | 0u64) << 32) | $limb_14,
+         $limb_16 | 0u64]
     }
 }
 
-pub const MAX_LIMBS: usize = (384 + (LIMB_BITS - 1)) / LIMB_BITS;
+pub const MAX_LIMBS: usize = (521 + (LIMB_BITS - 1)) / LIMB_BITS;
 
-static ONE: ElemDecoded =
-    ElemDecoded { limbs: limbs![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1] };
+static ONE: ElemDecoded = ElemDecoded {
+    limbs: limbs![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
+};
 
 
 /// Operations and values needed by all curve operations.
 pub struct CommonOps {
     pub num_limbs: usize,
+
+    // The length of the big-endian encoding of a field element or scalar. This
+    // is less than `num_limbs * LIMB_BYTES` when the field size isn't a
+    // multiple of the limb size, as is the case for P-521.
+    pub elem_and_scalar_len: usize,
+
     q: Mont,
     pub n: ElemDecoded,
 
@@ -137,8 +153,8 @@ pub struct CommonOps {
     elem_mul_mont: unsafe extern fn(r: *mut Limb, a: *const Limb,
                                     b: *const Limb),
     elem_sqr_mont: unsafe extern fn(r: *mut Limb, a: *const Limb),
+    elem_neg_impl: unsafe extern fn(r: *mut Limb, a: *const Limb),
 
-    #[cfg_attr(not(test), allow(dead_code))]
     point_add_jacobian_impl: unsafe extern fn(r: *mut Limb, a: *const Limb,
                                               b: *const Limb),
 }
@@ -181,6 +197,39 @@ impl CommonOps {
         ElemUnreduced { limbs: rab(self.elem_mul_mont, &a.limbs, &b.limbs) }
     }
 
+    /// Returns `-a`.
+    pub fn elem_negated(&self, a: &ElemUnreduced) -> ElemUnreduced {
+        let a = self.elem_reduced(a);
+        ElemUnreduced { limbs: ra(self.elem_neg_impl, &a.limbs) }
+    }
+
+    /// Returns `a - b`.
+    pub fn elem_difference(&self, a: &ElemUnreduced, b: &ElemUnreduced)
+                           -> ElemUnreduced {
+        let mut r = self.elem_negated(b);
+        self.elem_add(&mut r, &ElemUnreduced::from(&self.elem_reduced(a)));
+        r
+    }
+
+    /// Montgomery-encodes `a`, which must be less than
+    /// 2**(`self.num_limbs` * `LIMB_BITS`) but need not be less than q.
+    #[inline]
+    pub fn elem_from_limbs(&self, a: &[Limb; MAX_LIMBS]) -> ElemUnreduced {
+        ElemUnreduced { limbs: rab(self.elem_mul_mont, a, &self.q.rr) }
+    }
+
+    /// Returns `LimbMask::True` if `a` is zero mod q, in constant time.
+    pub fn elem_is_zero(&self, a: &ElemUnreduced) -> LimbMask {
+        let a = self.elem_reduced(a);
+        limbs_are_zero_constant_time(&a.limbs[..self.num_limbs])
+    }
+
+    /// Returns the least significant bit of the (fully reduced, not
+    /// Montgomery-encoded) value of `a`; i.e. sgn0(a) as defined in RFC 9380.
+    pub fn elem_parity(&self, a: &ElemUnreduced) -> Limb {
+        self.elem_decoded(a).limbs[0] & 1
+    }
+
     #[inline]
     pub fn elem_reduced(&self, a: &ElemUnreduced) -> Elem {
         Elem { limbs: self.reduced_limbs(&a.limbs, &self.q.p) }
@@ -196,6 +245,52 @@ impl CommonOps {
         ElemUnreduced { limbs: ra(self.elem_sqr_mont, &a.limbs) }
     }
 
+    /// Returns `a` if `condition` is `LimbMask::True`, or `b` otherwise, in
+    /// constant time.
+    pub fn elem_select(&self, condition: LimbMask, a: &ElemUnreduced,
+                       b: &ElemUnreduced) -> ElemUnreduced {
+        let mask = condition as Limb;
+        let mut r = ElemUnreduced::zero();
+        for i in 0..self.num_limbs {
+            r.limbs[i] = (a.limbs[i] & mask) | (b.limbs[i] & !mask);
+        }
+        r
+    }
+
+    /// Returns a**((q + 1) / 4), which is a square root of `a` if `a` is a
+    /// square, since q = 3 (mod 4) for all the supported curves. The caller
+    /// must verify that the result is actually a square root of `a`. This
+    /// isn't constant-time, so it may only be used for public values.
+    pub fn elem_sqrt_vartime(&self, a: &ElemUnreduced) -> ElemUnreduced {
+        // (q + 1) / 4. q + 1 fits in `self.num_limbs` limbs for all the
+        // supported curves.
+        let mut e = self.q.p;
+        let mut carry = 1;
+        for limb in e[..self.num_limbs].iter_mut() {
+            let (sum, overflow) = limb.overflowing_add(carry);
+            *limb = sum;
+            carry = overflow as Limb;
+        }
+        debug_assert_eq!(carry, 0);
+        for i in 0..self.num_limbs {
+            e[i] >>= 2;
+            if i + 1 < self.num_limbs {
+                e[i] |= e[i + 1] << (LIMB_BITS - 2);
+            }
+        }
+
+        let mut r = self.elem_from_limbs(&ONE.limbs);
+        for limb in e[..self.num_limbs].iter().rev() {
+            for bit in (0..LIMB_BITS).rev() {
+                self.elem_square(&mut r);
+                if (limb >> bit) & 1 == 1 {
+                    self.elem_mul(&mut r, a);
+                }
+            }
+        }
+        r
+    }
+
     pub fn elem_verify_is_not_zero(&self, a: &ElemUnreduced)
                                    -> Result<(), error::Unspecified> {
         match limbs_are_zero_constant_time(&a.limbs[..self.num_limbs]) {
@@ -204,6 +299,20 @@ impl CommonOps {
         }
     }
 
+    /// Returns the point (x, y) in Jacobian coordinates. (x, y) must be on
+    /// the curve.
+    pub fn point_from_affine(&self, x: &ElemUnreduced, y: &ElemUnreduced)
+                             -> Point {
+        let n = self.num_limbs;
+        let z = self.elem_from_limbs(&ONE.limbs);
+        let mut r = Point::new_at_infinity();
+        r.xyz[..n].copy_from_slice(&self.elem_reduced(x).limbs[..n]);
+        r.xyz[n..(2 * n)].copy_from_slice(&self.elem_reduced(y).limbs[..n]);
+        r.xyz[(2 * n)..(3 * n)]
+            .copy_from_slice(&self.elem_reduced(&z).limbs[..n]);
+        r
+    }
+
     pub fn point_sum(&self, a: &Point, b: &Point) -> Point {
         let mut r = Point::new_at_infinity();
         unsafe {
@@ -213,6 +322,15 @@ impl CommonOps {
         r
     }
 
+    /// Returns `-p`.
+    pub fn point_negated(&self, p: &Point) -> Point {
+        let n = self.num_limbs;
+        let y = self.elem_negated(&self.point_y(p));
+        let mut r = *p;
+        r.xyz[n..(2 * n)].copy_from_slice(&y.limbs[..n]);
+        r
+    }
+
     pub fn point_x(&self, p: &Point) -> ElemUnreduced {
         let mut r = ElemUnreduced::zero();
         r.limbs[..self.num_limbs].copy_from_slice(&p.xyz[0..self.num_limbs]);
@@ -239,7 +357,9 @@ impl CommonOps {
     // "Efficient Software Implementations of Modular Exponentiation" by Shay
     // Gueron for the details. This is the case for both the field order and
     // group order for both P-256 and P-384, but it is not the case for all
-    // curves. For example, it is not true for P-521.
+    // curves. For example, it is not true for P-521. For P-521, `a` must
+    // instead be less than `2 * p`; that is always the case for the outputs of
+    // the P-521 arithmetic functions, which are always fully reduced.
     fn reduced_limbs(&self, a: &[Limb; MAX_LIMBS], p: &[Limb; MAX_LIMBS])
                      -> [Limb; MAX_LIMBS] {
         let mut r = *a;
@@ -307,7 +427,7 @@ impl PublicKeyOps {
     pub fn elem_parse(&self, input: &mut untrusted::Reader)
                       -> Result<Elem, error::Unspecified> {
         let encoded_value =
-            try!(input.skip_and_get_input(self.common.num_limbs * LIMB_BYTES));
+            try!(input.skip_and_get_input(self.common.elem_and_scalar_len));
         let mut elem_limbs =
             try!(parse_big_endian_value_in_range(
                     encoded_value, 0,
@@ -333,15 +453,19 @@ pub struct PublicScalarOps {
 
     pub q_minus_n: ElemDecoded,
 
+    // R**2 (mod n), for converting scalars to the Montgomery domain.
+    n_rr: ScalarMont,
+
     scalar_inv_to_mont_impl: fn(a: &Scalar) -> ScalarMont,
     scalar_mul_mont: unsafe extern fn(r: *mut Limb, a: *const Limb,
                                       b: *const Limb),
 }
 
 impl PublicScalarOps {
-    pub fn scalar_parse(&self, input: &mut untrusted::Reader)
+    // Parses the big-endian-encoded value `encoded_value`, which must be in
+    // the range [1, n).
+    pub fn scalar_parse(&self, encoded_value: untrusted::Input)
                         -> Result<Scalar, error::Unspecified> {
-        let encoded_value = try!(der::positive_integer(input));
         let limbs = try!(parse_big_endian_value_in_range(
                             encoded_value, 1,
                             &self.public_key_ops.common.n.limbs[
@@ -366,6 +490,56 @@ impl PublicScalarOps {
         Scalar { limbs: cops.reduced_limbs(&unreduced, &cops.n.limbs) }
     }
 
+    #[inline]
+    pub fn scalar_to_mont(&self, a: &Scalar) -> ScalarMont {
+        ScalarMont {
+            limbs: rab(self.scalar_mul_mont, &a.limbs, &self.n_rr.limbs),
+        }
+    }
+
+    /// Returns `a + b (mod n)`, in constant time.
+    pub fn scalar_sum(&self, a: &Scalar, b: &Scalar) -> Scalar {
+        let cops = self.public_key_ops.common;
+        let mut r = Scalar { limbs: [0; MAX_LIMBS] };
+        unsafe {
+            GFp_constant_time_limbs_add_mod(r.limbs.as_mut_ptr(),
+                                            a.limbs.as_ptr(), b.limbs.as_ptr(),
+                                            cops.n.limbs.as_ptr(),
+                                            cops.num_limbs);
+        }
+        r
+    }
+
+    /// Returns `a * b (mod n)`, in constant time.
+    pub fn scalar_product(&self, a: &Scalar, b: &Scalar) -> Scalar {
+        self.scalar_mul_mixed(a, &self.scalar_to_mont(b))
+    }
+
+    /// Returns `-a (mod n)`, in constant time.
+    pub fn scalar_negated(&self, a: &Scalar) -> Scalar {
+        // -a == (n - 1) * a (mod n). n is odd, so the subtraction doesn't
+        // borrow.
+        let mut n_minus_1 = self.public_key_ops.common.n.limbs;
+        n_minus_1[0] -= 1;
+        self.scalar_product(a, &Scalar::from_limbs_unchecked(&n_minus_1))
+    }
+
+    /// Returns `a (mod n)`, in constant time. `a` may be any value that fits
+    /// in `num_limbs` limbs, except for P-521; see
+    /// `CommonOps::reduced_limbs`.
+    pub fn scalar_from_limbs_reduced(&self, a: &[Limb; MAX_LIMBS]) -> Scalar {
+        let cops = self.public_key_ops.common;
+        Scalar { limbs: cops.reduced_limbs(a, &cops.n.limbs) }
+    }
+
+    /// Returns the modular inverse of `a` (mod `n`), in constant time. `a`
+    /// must not be zero.
+    pub fn scalar_inv(&self, a: &Scalar) -> Scalar {
+        // (1 * (a**-1 * R)) / R == a**-1.
+        self.scalar_mul_mixed(&Scalar::from_limbs_unchecked(&ONE.limbs),
+                              &self.scalar_inv_to_mont(a))
+    }
+
     #[inline]
     pub fn scalar_as_elem_decoded(&self, a: &Scalar) -> ElemDecoded {
         ElemDecoded { limbs: a.limbs }
@@ -535,6 +709,9 @@ pub fn limbs_less_than_limbs(a: &[Limb], b: &[Limb]) -> bool {
 
 
 extern {
+    fn GFp_constant_time_limbs_add_mod(r: *mut Limb, a: *const Limb,
+                                       b: *const Limb, m: *const Limb,
+                                       num_limbs: c::size_t);
     fn GFp_constant_time_limbs_reduce_once(r: *mut Limb, m: *const Limb,
                                            num_limbs: c::size_t);
 }
@@ -554,6 +731,9 @@ mod tests {
     #[test]
     fn p384_elem_reduced_test() { test_elem_reduced(&p384::COMMON_OPS); }
 
+    #[test]
+    fn p521_elem_reduced_test() { test_elem_reduced(&p521::COMMON_OPS); }
+
     fn test_elem_reduced(ops: &CommonOps) {
         let zero = ElemUnreduced::zero();
 
@@ -593,6 +773,45 @@ mod tests {
 
     const ZERO_SCALAR: Scalar = Scalar { limbs: [0; MAX_LIMBS] };
 
+    #[test]
+    fn p256_scalar_negated_and_reduced_test() {
+        test_scalar_negated_and_reduced(&p256::PUBLIC_SCALAR_OPS);
+    }
+
+    #[test]
+    fn p384_scalar_negated_and_reduced_test() {
+        test_scalar_negated_and_reduced(&p384::PUBLIC_SCALAR_OPS);
+    }
+
+    #[test]
+    fn p521_scalar_negated_and_reduced_test() {
+        test_scalar_negated_and_reduced(&p521::PUBLIC_SCALAR_OPS);
+    }
+
+    fn test_scalar_negated_and_reduced(ops: &PublicScalarOps) {
+        let cops = ops.public_key_ops.common;
+        let mut one = ZERO_SCALAR;
+        one.limbs[0] = 1;
+        let mut n_minus_1 = Scalar { limbs: cops.n.limbs };
+        n_minus_1.limbs[0] -= 1;
+
+        assert_eq!(ops.scalar_negated(&ZERO_SCALAR).limbs, ZERO_SCALAR.limbs);
+        assert_eq!(ops.scalar_negated(&one).limbs, n_minus_1.limbs);
+        assert_eq!(ops.scalar_negated(&n_minus_1).limbs, one.limbs);
+        let two = ops.scalar_sum(&one, &one);
+        assert_eq!(ops.scalar_sum(&two, &ops.scalar_negated(&two)).limbs,
+                   ZERO_SCALAR.limbs);
+
+        assert_eq!(ops.scalar_from_limbs_reduced(&one.limbs).limbs, one.limbs);
+        assert_eq!(ops.scalar_from_limbs_reduced(&n_minus_1.limbs).limbs,
+                   n_minus_1.limbs);
+        assert_eq!(ops.scalar_from_limbs_reduced(&cops.n.limbs).limbs,
+                   ZERO_SCALAR.limbs);
+        let mut n_plus_1 = cops.n.limbs;
+        n_plus_1[0] += 1;
+        assert_eq!(ops.scalar_from_limbs_reduced(&n_plus_1).limbs, one.limbs);
+    }
+
     #[test]
     fn p256_sum_test() {
         sum_test(&p256::PUBLIC_SCALAR_OPS,
@@ -605,6 +824,12 @@ mod tests {
                  "src/ec/suite_b/ops/p384_sum_tests.txt");
     }
 
+    #[test]
+    fn p521_sum_test() {
+        sum_test(&p521::PUBLIC_SCALAR_OPS,
+                 "src/ec/suite_b/ops/p521_sum_tests.txt");
+    }
+
     fn sum_test(ops: &PublicScalarOps, file_path: &str) {
         test::from_file(file_path, |section, test_case| {
             assert_eq!(section, "");
@@ -638,6 +863,15 @@ mod tests {
                         "src/ec/suite_b/ops/p384_sum_tests.txt");
     }
 
+    #[test]
+    fn p521_difference_test() {
+        extern {
+            fn GFp_p521_elem_sub(r: *mut Limb, a: *const Limb, b: *const Limb);
+        }
+        difference_test(&p521::COMMON_OPS, GFp_p521_elem_sub,
+                        "src/ec/suite_b/ops/p521_sum_tests.txt");
+    }
+
     fn difference_test(ops: &CommonOps,
                        elem_sub: unsafe extern fn(r: *mut Limb, a: *const Limb,
                                                   b: *const Limb),
@@ -679,6 +913,15 @@ mod tests {
                       "src/ec/suite_b/ops/p384_div_by_2_tests.txt");
     }
 
+    #[test]
+    fn p521_div_by_2_test() {
+        extern {
+            fn GFp_p521_elem_div_by_2(r: *mut Limb, a: *const Limb);
+        }
+        div_by_2_test(&p521::COMMON_OPS, GFp_p521_elem_div_by_2,
+                      "src/ec/suite_b/ops/p521_div_by_2_tests.txt");
+    }
+
     fn div_by_2_test(ops: &CommonOps,
                      elem_div_by_2: unsafe extern fn(r: *mut Limb,
                                                      a: *const Limb),
@@ -719,6 +962,15 @@ mod tests {
                       "src/ec/suite_b/ops/p384_neg_tests.txt");
     }
 
+    #[test]
+    fn p521_elem_neg_test() {
+        extern {
+            fn GFp_p521_elem_neg(r: *mut Limb, a: *const Limb);
+        }
+        elem_neg_test(&p521::COMMON_OPS, GFp_p521_elem_neg,
+                      "src/ec/suite_b/ops/p521_neg_tests.txt");
+    }
+
     fn elem_neg_test(ops: &CommonOps,
                      elem_neg: unsafe extern fn(r: *mut Limb, a: *const Limb),
                      file_path: &str) {
@@ -755,6 +1007,12 @@ mod tests {
         let _ = p384::PUBLIC_SCALAR_OPS.scalar_inv_to_mont(&ZERO_SCALAR);
     }
 
+    #[test]
+    #[should_panic(expected = "a.limbs[..num_limbs].iter().any(|x| *x != 0)")]
+    fn p521_scalar_inv_to_mont_zero_panic_test() {
+        let _ = p521::PUBLIC_SCALAR_OPS.scalar_inv_to_mont(&ZERO_SCALAR);
+    }
+
     #[test]
     fn parse_big_endian_value_test() {
         // Empty input.
@@ -766,46 +1024,50 @@ mod tests {
         let inp = [0xfe];
         let inp = untrusted::Input::from(&inp);
         assert_eq!(parse_big_endian_value(inp, MAX_LIMBS),
-                   Ok(limbs![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xfe]));
+                   Ok(limbs![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                             0xfe]));
 
         // A whole limb for 32-bit, half a limb for 64-bit.
         let inp = [0xbe, 0xef, 0xf0, 0x0d];
         let inp = untrusted::Input::from(&inp);
         assert_eq!(parse_big_endian_value(inp, MAX_LIMBS),
-                   Ok(limbs![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xbeeff00d]));
+                   Ok(limbs![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                             0xbeeff00d]));
 
         // A whole number of limbs (2 for 32-bit, 1 for 64-bit).
         let inp = [0xfe, 0xed, 0xde, 0xad, 0xbe, 0xef, 0xf0, 0x0d];
         let inp = untrusted::Input::from(&inp);
         assert_eq!(parse_big_endian_value(inp, MAX_LIMBS),
-                   Ok(limbs![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xfeeddead,
-                             0xbeeff00d]));
+                   Ok(limbs![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                             0xfeeddead, 0xbeeff00d]));
 
         // One limb - 1 for 32-bit.
         let inp = [0xef, 0xf0, 0x0d];
         let inp = untrusted::Input::from(&inp);
         assert_eq!(parse_big_endian_value(inp, MAX_LIMBS),
-                   Ok(limbs![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xeff00d]));
+                   Ok(limbs![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                             0xeff00d]));
 
         // Two limbs - 1 for 64-bit, four limbs - 1 for 32-bit.
         let inp = [     0xe, 0xd, 0xc, 0xb, 0xa, 0x9, 0x8,
                    0x7, 0x6, 0x5, 0x4, 0x3, 0x2, 0x1, 0x0];
         let inp = untrusted::Input::from(&inp);
         assert_eq!(parse_big_endian_value(inp, MAX_LIMBS),
-                   Ok(limbs![0, 0, 0, 0, 0, 0, 0, 0, 0x000e0d0c, 0x0b0a0908,
-                             0x07060504, 0x03020100]));
+                   Ok(limbs![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x000e0d0c,
+                             0x0b0a0908, 0x07060504, 0x03020100]));
 
         // One limb + 1 for for 32-bit, half a limb + 1 for 64-bit.
         let inp = [0x4, 0x3, 0x2, 0x1, 0x0];
         let inp = untrusted::Input::from(&inp);
         assert_eq!(parse_big_endian_value(inp, MAX_LIMBS),
-                   Ok(limbs![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x04, 0x03020100]));
+                   Ok(limbs![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x04,
+                             0x03020100]));
 
         // A whole number of limbs + 1.
         let inp = [0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x00];
         let inp = untrusted::Input::from(&inp);
-        let out = limbs![0, 0, 0, 0, 0, 0, 0, 0, 0, 0x88, 0x77665544,
-                         0x33221100];
+        let out = limbs![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x88,
+                         0x77665544, 0x33221100];
         assert_eq!(parse_big_endian_value(inp, 3), Ok(out));
 
         // The input is longer than will fit in the given number of limbs.
@@ -1025,7 +1287,7 @@ mod internal_benches {
     use super::{Limb, MAX_LIMBS};
 
     pub const LIMBS_1: [Limb; MAX_LIMBS] =
-        limbs![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+        limbs![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
 
     pub const LIMBS_ALTERNATING_10: [Limb; MAX_LIMBS] =
         limbs![0b10101010_10101010_10101010_10101010,
@@ -1039,6 +1301,11 @@ mod internal_benches {
                0b10101010_10101010_10101010_10101010,
                0b10101010_10101010_10101010_10101010,
                0b10101010_10101010_10101010_10101010,
+               0b10101010_10101010_10101010_10101010,
+               0b10101010_10101010_10101010_10101010,
+               0b10101010_10101010_10101010_10101010,
+               0b10101010_10101010_10101010_10101010,
+               0b10101010_10101010_10101010_10101010,
                0b10101010_10101010_10101010_10101010];
 }
 
@@ -1101,3 +1368,4 @@ macro_rules! bench_curve {
 
 pub mod p256;
 pub mod p384;
+pub mod p521;
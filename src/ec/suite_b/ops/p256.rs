@@ -19,7 +19,7 @@ use super::{Mont, elem_sqr_mul, elem_sqr_mul_acc, ab_assign, ra, rab};
 macro_rules! p256_limbs {
     [$limb_7:expr, $limb_6:expr, $limb_5:expr, $limb_4:expr,
      $limb_3:expr, $limb_2:expr, $limb_1:expr, $limb_0:expr] => {
-        limbs![0, 0, 0, 0,
+        limbs![0, 0, 0, 0, 0, 0, 0, 0, 0,
                $limb_7, $limb_6, $limb_5, $limb_4,
                $limb_3, $limb_2, $limb_1, $limb_0]
     };
@@ -28,6 +28,7 @@ macro_rules! p256_limbs {
 
 pub static COMMON_OPS: CommonOps = CommonOps {
     num_limbs: 256 / LIMB_BITS,
+    elem_and_scalar_len: 256 / 8,
 
     q: Mont {
         p: p256_limbs![0xffffffff, 0x00000001, 0x00000000, 0x00000000,
@@ -53,6 +54,7 @@ pub static COMMON_OPS: CommonOps = CommonOps {
     elem_add_impl: GFp_nistz256_add,
     elem_mul_mont: GFp_nistz256_mul_mont,
     elem_sqr_mont: GFp_nistz256_sqr_mont,
+    elem_neg_impl: GFp_nistz256_neg,
 
     point_add_jacobian_impl: GFp_nistz256_point_add,
 };
@@ -138,6 +140,11 @@ pub static PUBLIC_SCALAR_OPS: PublicScalarOps = PublicScalarOps {
                            0x039cdaae],
     },
 
+    n_rr: ScalarMont {
+        limbs: p256_limbs![0x66e12d94, 0xf3d95620, 0x2845b239, 0x2b6bec59,
+                           0x4699799c, 0x49bd6fa6, 0x83244c95, 0xbe79eea2],
+    },
+
     scalar_inv_to_mont_impl: p256_scalar_inv_to_mont,
     scalar_mul_mont: GFp_p256_scalar_mul_mont,
 };
@@ -184,13 +191,6 @@ fn p256_scalar_inv_to_mont(a: &Scalar) -> ScalarMont {
         ab_assign(GFp_p256_scalar_mul_mont, &mut acc.limbs, &b.limbs);
     }
 
-    fn to_mont(a: &Scalar) -> ScalarMont {
-        static N_RR: [Limb; MAX_LIMBS] =
-            p256_limbs![0x66e12d94, 0xf3d95620, 0x2845b239, 0x2b6bec59,
-                        0x4699799c, 0x49bd6fa6, 0x83244c95, 0xbe79eea2];
-        ScalarMont { limbs: rab(GFp_p256_scalar_mul_mont, &a.limbs, &N_RR) }
-    }
-
     // Indexes into `d`.
     const B_1: usize = 0;
     const B_10: usize = 1;
@@ -205,7 +205,7 @@ fn p256_scalar_inv_to_mont(a: &Scalar) -> ScalarMont {
 
     let mut d = [ScalarMont { limbs: [0; MAX_LIMBS] }; DIGIT_COUNT];
 
-    d[B_1]    = to_mont(a);
+    d[B_1]    = PUBLIC_SCALAR_OPS.scalar_to_mont(a);
     d[B_10]   = sqr(&d[B_1]);
     d[B_11]   = mul(&d[B_10],   &d[B_1]);
     d[B_101]  = sqr_mul(&d[B_10], 0 + 1, &d[B_1]);
@@ -278,6 +278,8 @@ extern {
                              b: *const Limb/*[COMMON_OPS.num_limbs]*/);
     fn GFp_nistz256_sqr_mont(r: *mut Limb/*[COMMON_OPS.num_limbs]*/,
                              a: *const Limb/*[COMMON_OPS.num_limbs]*/);
+    fn GFp_nistz256_neg(r: *mut Limb/*[COMMON_OPS.num_limbs]*/,
+                        a: *const Limb/*[COMMON_OPS.num_limbs]*/);
 
     fn GFp_nistz256_point_add(r: *mut Limb/*[3][COMMON_OPS.num_limbs]*/,
                               a: *const Limb/*[3][COMMON_OPS.num_limbs]*/,
@@ -20,7 +20,8 @@ macro_rules! p384_limbs {
     [$limb_b:expr, $limb_a:expr, $limb_9:expr, $limb_8:expr,
      $limb_7:expr, $limb_6:expr, $limb_5:expr, $limb_4:expr,
      $limb_3:expr, $limb_2:expr, $limb_1:expr, $limb_0:expr] => {
-        limbs![$limb_b, $limb_a, $limb_9, $limb_8,
+        limbs![0, 0, 0, 0, 0,
+               $limb_b, $limb_a, $limb_9, $limb_8,
                $limb_7, $limb_6, $limb_5, $limb_4,
                $limb_3, $limb_2, $limb_1, $limb_0]
     };
@@ -29,12 +30,13 @@ macro_rules! p384_limbs {
 
 pub static COMMON_OPS: CommonOps = CommonOps {
     num_limbs: 384 / LIMB_BITS,
+    elem_and_scalar_len: 384 / 8,
 
     q: Mont {
         p: p384_limbs![0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff,
                        0xffffffff, 0xffffffff, 0xffffffff, 0xfffffffe,
                        0xffffffff, 0x00000000, 0x00000000, 0xffffffff],
-        rr: limbs![0, 0, 0, 1, 2, 0, 0xfffffffe, 0, 2, 0, 0xfffffffe, 1],
+        rr: p384_limbs![0, 0, 0, 1, 2, 0, 0xfffffffe, 0, 2, 0, 0xfffffffe, 1],
     },
 
     n: ElemDecoded {
@@ -57,6 +59,7 @@ pub static COMMON_OPS: CommonOps = CommonOps {
     elem_add_impl: GFp_p384_elem_add,
     elem_mul_mont: GFp_p384_elem_mul_mont,
     elem_sqr_mont: GFp_p384_elem_sqr_mont,
+    elem_neg_impl: GFp_p384_elem_neg,
 
     point_add_jacobian_impl: GFp_nistz384_point_add,
 };
@@ -167,6 +170,12 @@ pub static PUBLIC_SCALAR_OPS: PublicScalarOps = PublicScalarOps {
                            0x1313e696, 0x333ad68c, 0xa7e5f24c, 0xb74f5885],
     },
 
+    n_rr: ScalarMont {
+        limbs: p384_limbs![0x0c84ee01, 0x2b39bf21, 0x3fb05b7a, 0x28266895,
+                           0xd40d4917, 0x4aab1cc5, 0xbc3e483a, 0xfcb82947,
+                           0xff3d81e5, 0xdf1aa419, 0x2d319b24, 0x19b409a9],
+    },
+
     scalar_inv_to_mont_impl: p384_scalar_inv_to_mont,
     scalar_mul_mont: GFp_p384_scalar_mul_mont,
 };
@@ -218,14 +227,6 @@ fn p384_scalar_inv_to_mont(a: &Scalar) -> ScalarMont {
         ab_assign(GFp_p384_scalar_mul_mont, &mut acc.limbs, &b.limbs)
     }
 
-    fn to_mont(a: &Scalar) -> ScalarMont {
-        static N_RR: [Limb; MAX_LIMBS] =
-            p384_limbs![0x0c84ee01, 0x2b39bf21, 0x3fb05b7a, 0x28266895,
-                        0xd40d4917, 0x4aab1cc5, 0xbc3e483a, 0xfcb82947,
-                        0xff3d81e5, 0xdf1aa419, 0x2d319b24, 0x19b409a9];
-        ScalarMont { limbs: rab(GFp_p384_scalar_mul_mont, &a.limbs, &N_RR) }
-    }
-
     // Indexes into `d`.
     const B_1: usize = 0;
     const B_10: usize = 1;
@@ -237,7 +238,7 @@ fn p384_scalar_inv_to_mont(a: &Scalar) -> ScalarMont {
 
     let mut d = [ScalarMont { limbs: [0; MAX_LIMBS] }; DIGIT_COUNT];
 
-    d[B_1]    = to_mont(a);
+    d[B_1]    = PUBLIC_SCALAR_OPS.scalar_to_mont(a);
     d[B_10]   = sqr    (&d[B_1]);
     d[B_11]   = mul    (&d[B_10],         &d[B_1]);
     d[B_101]  = sqr_mul(&d[B_10],  0 + 1, &d[B_1]);
@@ -338,6 +339,8 @@ extern {
     fn GFp_p384_elem_mul_mont(r: *mut Limb/*[COMMON_OPS.num_limbs]*/,
                               a: *const Limb/*[COMMON_OPS.num_limbs]*/,
                               b: *const Limb/*[COMMON_OPS.num_limbs]*/);
+    fn GFp_p384_elem_neg(r: *mut Limb/*[COMMON_OPS.num_limbs]*/,
+                         a: *const Limb/*[COMMON_OPS.num_limbs]*/);
 
     fn GFp_nistz384_point_add(r: *mut Limb/*[3][COMMON_OPS.num_limbs]*/,
                               a: *const Limb/*[3][COMMON_OPS.num_limbs]*/,
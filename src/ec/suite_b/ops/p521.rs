@@ -0,0 +1,363 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use super::*;
+use super::{elem_sqr_mul, elem_sqr_mul_acc, Mont, ab_assign, rab};
+
+
+pub static COMMON_OPS: CommonOps = CommonOps {
+    num_limbs: (521 + (LIMB_BITS - 1)) / LIMB_BITS,
+    elem_and_scalar_len: (521 + 7) / 8,
+
+    q: Mont {
+        p: limbs![0x000001ff, 0xffffffff, 0xffffffff, 0xffffffff,
+                  0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff,
+                  0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff,
+                  0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff,
+                  0xffffffff],
+        rr: mont::Q_RR,
+    },
+
+    n: ElemDecoded {
+        limbs: limbs![0x000001ff, 0xffffffff, 0xffffffff, 0xffffffff,
+                      0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff,
+                      0xfffffffa, 0x51868783, 0xbf2f966b, 0x7fcc0148,
+                      0xf709a5d0, 0x3bb5c9b8, 0x899c47ae, 0xbb6fb71e,
+                      0x91386409],
+    },
+
+    a: ElemUnreduced { limbs: mont::A },
+    b: ElemUnreduced { limbs: mont::B },
+
+    elem_add_impl: GFp_p521_elem_add,
+    elem_mul_mont: GFp_p521_elem_mul_mont,
+    elem_sqr_mont: GFp_p521_elem_sqr_mont,
+    elem_neg_impl: GFp_p521_elem_neg,
+
+    point_add_jacobian_impl: GFp_nistz521_point_add,
+};
+
+
+// Since 521 isn't a multiple of the limb size, R = 2**(num_limbs * LIMB_BITS)
+// is different for 32-bit and 64-bit targets, and so are the Montgomery-encoded
+// constants.
+
+#[cfg(target_pointer_width = "64")]
+mod mont {
+    use super::super::{Limb, MAX_LIMBS};
+
+    pub const Q_RR: [Limb; MAX_LIMBS] =
+        limbs![0x00000000, 0x00000000, 0x00000000, 0x00000000,
+               0x00000000, 0x00000000, 0x00000000, 0x00000000,
+               0x00000000, 0x00000000, 0x00000000, 0x00000000,
+               0x00000000, 0x00004000, 0x00000000, 0x00000000,
+               0x00000000];
+
+    pub const A: [Limb; MAX_LIMBS] =
+        limbs![0x000001ff, 0xffffffff, 0xffffffff, 0xffffffff,
+               0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff,
+               0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff,
+               0xffffffff, 0xffffffff, 0xffffffff, 0xfe7fffff,
+               0xffffffff];
+
+    pub const B: [Limb; MAX_LIMBS] =
+        limbs![0x0000004d, 0x0fc94d10, 0xd05b42a0, 0x77516d39,
+               0x2dccd98a, 0xf9dc5a44, 0xc8c77884, 0xf0ab0c9c,
+               0xa8f63f49, 0xbd8b2960, 0x5e9dd8df, 0x839ab9ef,
+               0xc41e961a, 0x78f7a28f, 0xea35a81f, 0x8014654f,
+               0xae586387];
+
+    pub const G_X: [Limb; MAX_LIMBS] =
+        limbs![0x00000074, 0xe6cf1f65, 0xb311cada, 0x214e3240,
+               0x9c829fda, 0x90fc1457, 0xb035a69e, 0xdd50a5af,
+               0x3bf7f3ac, 0x947f0ee0, 0x93d17fd4, 0x6f19a459,
+               0xe0c2b521, 0x4dfcbf3f, 0x18e172de, 0xb331a163,
+               0x81adc101];
+
+    pub const G_Y: [Limb; MAX_LIMBS] =
+        limbs![0x000001e0, 0x022e452f, 0xda163e8d, 0xeccc7aa2,
+               0x24abcda2, 0x340bd7de, 0x8b939f33, 0x164bf739,
+               0x4caf7a13, 0x2062a85c, 0x809fd683, 0xb09a9e38,
+               0x43513961, 0x20445f4a, 0x3b4fe8b3, 0x28460e4a,
+               0x5a9e268e];
+
+    pub const N_RR: [Limb; MAX_LIMBS] =
+        limbs![0x0000003d, 0x2d8e03d1, 0x492d0d45, 0x5bcc6d61,
+               0xa8e567bc, 0xcff3d142, 0xb7756e3e, 0xdd6e23d8,
+               0x2e49c7db, 0xd3721ef5, 0x57f75e06, 0x12a78d38,
+               0x794573ff, 0xf707badc, 0xe5547ea3, 0x137cd04d,
+               0xcf15dd04];
+}
+
+#[cfg(target_pointer_width = "32")]
+mod mont {
+    use super::super::{Limb, MAX_LIMBS};
+
+    pub const Q_RR: [Limb; MAX_LIMBS] =
+        limbs![0x00000000, 0x00000000, 0x00000000, 0x00000000,
+               0x00000000, 0x00000000, 0x00000000, 0x00000000,
+               0x00000000, 0x00000000, 0x00000000, 0x00000000,
+               0x00000000, 0x00000000, 0x00000000, 0x00004000,
+               0x00000000];
+
+    pub const A: [Limb; MAX_LIMBS] =
+        limbs![0x000001ff, 0xffffffff, 0xffffffff, 0xffffffff,
+               0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff,
+               0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff,
+               0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff,
+               0xfe7fffff];
+
+    pub const B: [Limb; MAX_LIMBS] =
+        limbs![0x0000015c, 0xb0c70e4d, 0x0fc94d10, 0xd05b42a0,
+               0x77516d39, 0x2dccd98a, 0xf9dc5a44, 0xc8c77884,
+               0xf0ab0c9c, 0xa8f63f49, 0xbd8b2960, 0x5e9dd8df,
+               0x839ab9ef, 0xc41e961a, 0x78f7a28f, 0xea35a81f,
+               0x8014654f];
+
+    pub const G_X: [Limb; MAX_LIMBS] =
+        limbs![0x00000103, 0x5b820274, 0xe6cf1f65, 0xb311cada,
+               0x214e3240, 0x9c829fda, 0x90fc1457, 0xb035a69e,
+               0xdd50a5af, 0x3bf7f3ac, 0x947f0ee0, 0x93d17fd4,
+               0x6f19a459, 0xe0c2b521, 0x4dfcbf3f, 0x18e172de,
+               0xb331a163];
+
+    pub const G_Y: [Limb; MAX_LIMBS] =
+        limbs![0x000000b5, 0x3c4d1de0, 0x022e452f, 0xda163e8d,
+               0xeccc7aa2, 0x24abcda2, 0x340bd7de, 0x8b939f33,
+               0x164bf739, 0x4caf7a13, 0x2062a85c, 0x809fd683,
+               0xb09a9e38, 0x43513961, 0x20445f4a, 0x3b4fe8b3,
+               0x28460e4a];
+
+    pub const N_RR: [Limb; MAX_LIMBS] =
+        limbs![0x0000019a, 0x5b5a3afe, 0x8c44383d, 0x2d8e03d1,
+               0x492d0d45, 0x5bcc6d61, 0xa8e567bc, 0xcff3d142,
+               0xb7756e3a, 0x4fb35b72, 0xd3402705, 0x5d4dd6d3,
+               0x0791d9dc, 0x18354a56, 0x4374a642, 0x1163115a,
+               0x61c64ca7];
+}
+
+
+pub static PRIVATE_KEY_OPS: PrivateKeyOps = PrivateKeyOps {
+    common: &COMMON_OPS,
+    elem_inv: p521_elem_inv,
+    point_mul_base_impl: p521_point_mul_base_impl,
+    point_mul_impl: GFp_nistz521_point_mul,
+};
+
+fn p521_elem_inv(a: &ElemUnreduced) -> ElemUnreduced {
+    // Calculate the modular inverse of field element |a| using Fermat's Little
+    // Theorem:
+    //
+    //    a**-1 (mod q) == a**(q - 2) (mod q)
+    //
+    // The exponent (q - 2) is 2**521 - 3, which is 519 one bits followed by
+    // the bits 01.
+
+    #[inline]
+    fn sqr_mul(a: &ElemUnreduced, squarings: usize, b: &ElemUnreduced)
+               -> ElemUnreduced {
+        elem_sqr_mul(&COMMON_OPS, a, squarings, b)
+    }
+
+    #[inline]
+    fn sqr_mul_acc(a: &mut ElemUnreduced, squarings: usize, b: &ElemUnreduced) {
+        elem_sqr_mul_acc(&COMMON_OPS, a, squarings, b)
+    }
+
+    // Each value is named for the number of one bits in its exponent.
+    let b_1 = &a;
+    let b_2   = sqr_mul(b_1,    0 + 1, b_1);
+    let b_3   = sqr_mul(&b_2,   0 + 1, b_1);
+    let b_4   = sqr_mul(&b_2,   0 + 2, &b_2);
+    let b_7   = sqr_mul(&b_4,   0 + 3, &b_3);
+    let b_8   = sqr_mul(&b_4,   0 + 4, &b_4);
+    let b_16  = sqr_mul(&b_8,   0 + 8, &b_8);
+    let b_32  = sqr_mul(&b_16,  0 + 16, &b_16);
+    let b_64  = sqr_mul(&b_32,  0 + 32, &b_32);
+    let b_128 = sqr_mul(&b_64,  0 + 64, &b_64);
+    let b_256 = sqr_mul(&b_128, 0 + 128, &b_128);
+
+    let mut acc = sqr_mul(&b_256, 0 + 256, &b_256);
+
+    // 519 one bits.
+    sqr_mul_acc(&mut acc, 0 + 7, &b_7);
+
+    // 519 one bits followed by 01.
+    sqr_mul(&acc, 1 + 1, b_1)
+}
+
+
+fn p521_point_mul_base_impl(a: &Scalar) -> Point {
+    // XXX: Not efficient. TODO: Precompute multiples of the generator.
+    static P521_GENERATOR: (Elem, Elem) = (
+        Elem { limbs: mont::G_X },
+        Elem { limbs: mont::G_Y }
+    );
+
+    PRIVATE_KEY_OPS.point_mul(a, &P521_GENERATOR)
+}
+
+
+pub static PUBLIC_KEY_OPS: PublicKeyOps = PublicKeyOps { common: &COMMON_OPS };
+
+
+pub static PUBLIC_SCALAR_OPS: PublicScalarOps = PublicScalarOps {
+    public_key_ops: &PUBLIC_KEY_OPS,
+    private_key_ops: &PRIVATE_KEY_OPS,
+
+    q_minus_n: ElemDecoded {
+        limbs: limbs![0x00000000, 0x00000000, 0x00000000, 0x00000000,
+                      0x00000000, 0x00000000, 0x00000000, 0x00000000,
+                      0x00000005, 0xae79787c, 0x40d06994, 0x8033feb7,
+                      0x08f65a2f, 0xc44a3647, 0x7663b851, 0x449048e1,
+                      0x6ec79bf6],
+    },
+
+    n_rr: ScalarMont { limbs: mont::N_RR },
+
+    scalar_inv_to_mont_impl: p521_scalar_inv_to_mont,
+    scalar_mul_mont: GFp_p521_scalar_mul_mont,
+};
+
+fn p521_scalar_inv_to_mont(a: &Scalar) -> ScalarMont {
+    // Calculate the modular inverse of scalar |a| using Fermat's Little
+    // Theorem:
+    //
+    //   a**-1 (mod n) == a**(n - 2) (mod n)
+    //
+    // The exponent (n - 2) is:
+    //
+    //     0x1ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff\
+    //       a51868783bf2f966b7fcc0148f709a5d03bb5c9b8899c47aebb6fb71e91386407
+    //
+    // That is, 261 one bits followed by the 65 hex digits on the second line.
+
+    // XXX(perf): This hasn't been optimized at all. TODO: optimize.
+
+    fn mul(a: &ScalarMont, b: &ScalarMont) -> ScalarMont {
+        ScalarMont { limbs: rab(GFp_p521_scalar_mul_mont, &a.limbs, &b.limbs) }
+    }
+
+    fn sqr_mut(a: &mut ScalarMont) {
+        unsafe {
+            GFp_p521_scalar_mul_mont(a.limbs.as_mut_ptr(), a.limbs.as_ptr(),
+                                     a.limbs.as_ptr())
+        }
+    }
+
+    // Returns (`a` squared `squarings` times) * `b`.
+    fn sqr_mul(a: &ScalarMont, squarings: usize, b: &ScalarMont) -> ScalarMont {
+        let mut tmp = *a;
+        sqr_mul_acc(&mut tmp, squarings, b);
+        tmp
+    }
+
+    // Sets `acc` = (`acc` squared `squarings` times) * `b`.
+    fn sqr_mul_acc(acc: &mut ScalarMont, squarings: usize, b: &ScalarMont) {
+        debug_assert!(squarings >= 1);
+        for _ in 0..squarings {
+            sqr_mut(acc);
+        }
+        ab_assign(GFp_p521_scalar_mul_mont, &mut acc.limbs, &b.limbs)
+    }
+
+    // `d[i]` is `a**i`, for each possible value `i` of a hex digit. `d[0]` is
+    // never used.
+    let mut d = [ScalarMont { limbs: [0; MAX_LIMBS] }; 16];
+    d[1] = PUBLIC_SCALAR_OPS.scalar_to_mont(a);
+    for i in 2..16 {
+        d[i] = mul(&d[i - 1], &d[1]);
+    }
+
+    // Each value is named for the number of one bits in its exponent.
+    let b_4   = d[0xf];
+    let b_5   = sqr_mul(&b_4,   0 + 1, &d[1]);
+    let b_8   = sqr_mul(&b_4,   0 + 4, &b_4);
+    let b_16  = sqr_mul(&b_8,   0 + 8, &b_8);
+    let b_32  = sqr_mul(&b_16,  0 + 16, &b_16);
+    let b_64  = sqr_mul(&b_32,  0 + 32, &b_32);
+    let b_128 = sqr_mul(&b_64,  0 + 64, &b_64);
+    let b_256 = sqr_mul(&b_128, 0 + 128, &b_128);
+
+    // 261 one bits.
+    let mut acc = sqr_mul(&b_256, 0 + 5, &b_5);
+
+    static REMAINING_DIGITS: &'static [u8] =
+        b"a51868783bf2f966b7fcc0148f709a5d03bb5c9b8899c47aebb6fb71e91386407";
+
+    for digit in REMAINING_DIGITS {
+        let digit = (*digit as char).to_digit(16).unwrap() as usize;
+        if digit == 0 {
+            for _ in 0..4 {
+                sqr_mut(&mut acc);
+            }
+        } else {
+            sqr_mul_acc(&mut acc, 4, &d[digit]);
+        }
+    }
+
+    acc
+}
+
+
+#[allow(non_snake_case)]
+unsafe extern fn GFp_p521_elem_sqr_mont(
+        r: *mut Limb/*[COMMON_OPS.num_limbs]*/,
+        a: *const Limb/*[COMMON_OPS.num_limbs]*/) {
+    // XXX: Inefficient. TODO: Make a dedicated squaring routine.
+    GFp_p521_elem_mul_mont(r, a, a);
+}
+
+
+extern {
+    fn GFp_p521_elem_add(r: *mut Limb/*[COMMON_OPS.num_limbs]*/,
+                         a: *const Limb/*[COMMON_OPS.num_limbs]*/,
+                         b: *const Limb/*[COMMON_OPS.num_limbs]*/);
+    fn GFp_p521_elem_mul_mont(r: *mut Limb/*[COMMON_OPS.num_limbs]*/,
+                              a: *const Limb/*[COMMON_OPS.num_limbs]*/,
+                              b: *const Limb/*[COMMON_OPS.num_limbs]*/);
+    fn GFp_p521_elem_neg(r: *mut Limb/*[COMMON_OPS.num_limbs]*/,
+                         a: *const Limb/*[COMMON_OPS.num_limbs]*/);
+
+    fn GFp_nistz521_point_add(r: *mut Limb/*[3][COMMON_OPS.num_limbs]*/,
+                              a: *const Limb/*[3][COMMON_OPS.num_limbs]*/,
+                              b: *const Limb/*[3][COMMON_OPS.num_limbs]*/);
+    fn GFp_nistz521_point_mul(r: *mut Limb/*[3][COMMON_OPS.num_limbs]*/,
+                              p_scalar: *const Limb/*[COMMON_OPS.num_limbs]*/,
+                              p_x: *const Limb/*[COMMON_OPS.num_limbs]*/,
+                              p_y: *const Limb/*[COMMON_OPS.num_limbs]*/);
+
+    fn GFp_p521_scalar_mul_mont(r: *mut Limb/*[COMMON_OPS.num_limbs]*/,
+                                a: *const Limb/*[COMMON_OPS.num_limbs]*/,
+                                b: *const Limb/*[COMMON_OPS.num_limbs]*/);
+}
+
+
+#[cfg(feature = "internal_benches")]
+mod internal_benches {
+    use super::*;
+    use super::super::internal_benches::*;
+
+    bench_curve!(&[
+        Scalar { limbs: LIMBS_1 },
+        Scalar { limbs: LIMBS_ALTERNATING_10, },
+        Scalar { // n - 1
+            limbs: limbs![0x000001ff, 0xffffffff, 0xffffffff, 0xffffffff,
+                          0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff,
+                          0xfffffffa, 0x51868783, 0xbf2f966b, 0x7fcc0148,
+                          0xf709a5d0, 0x3bb5c9b8, 0x899c47ae, 0xbb6fb71e,
+                          0x91386409 - 1],
+        },
+    ]);
+}
@@ -39,6 +39,14 @@ pub fn generate_private_key(ops: &PrivateKeyOps, rng: &rand::SecureRandom)
     let num_limbs = ops.common.num_limbs;
     let max_exclusive =  &ops.common.n.limbs[..num_limbs];
 
+    // When the length of `n` in bits isn't a multiple of 8, as for P-521, the
+    // unused high-order bits of the first byte are cleared so that candidates
+    // are generated with the same bit length as `n`; otherwise almost every
+    // candidate would be rejected.
+    let n_bits = (num_limbs * LIMB_BITS) -
+                 (max_exclusive[num_limbs - 1].leading_zeros() as usize);
+    let first_byte_mask = 0xffu8 >> ((8 - (n_bits % 8)) % 8);
+
     // XXX: The value 100 was chosen to match OpenSSL due to uncertainty of
     // what specific value would be better, but it seems bad to try 100 times.
     for _ in 0..100 {
@@ -49,13 +57,14 @@ pub fn generate_private_key(ops: &PrivateKeyOps, rng: &rand::SecureRandom)
         //
         // Since we calculate the length ourselves, it is pointless to check
         // it, since we can only check it by doing the same calculation.
-        let num_bytes = num_limbs * LIMB_BYTES;
+        let num_bytes = ops.common.elem_and_scalar_len;
 
         // NSA Guide Step 4.
         //
         // The requirement that the random number generator has the requested
         // security strength is delegated to `rng`.
         try!(rng.fill(&mut candidate_private_key.bytes[..num_bytes]));
+        candidate_private_key.bytes[0] &= first_byte_mask;
 
         // NSA Guide Steps 5, 6, and 7.
         //
@@ -84,6 +93,24 @@ pub fn generate_private_key(ops: &PrivateKeyOps, rng: &rand::SecureRandom)
 }
 
 
+// Parses a big-endian-encoded private key, which must be exactly as long as
+// the scalar and in the range [1, n).
+pub fn private_key_from_bytes(ops: &PrivateKeyOps, bytes: &[u8])
+                              -> Result<ec::PrivateKey, error::Unspecified> {
+    let num_limbs = ops.common.num_limbs;
+    let max_exclusive = &ops.common.n.limbs[..num_limbs];
+    if bytes.len() != ops.common.elem_and_scalar_len {
+        return Err(error::Unspecified);
+    }
+    let mut private_key = ec::PrivateKey { bytes: [0; ec::SCALAR_MAX_BYTES] };
+    private_key.bytes[..bytes.len()].copy_from_slice(bytes);
+    let scalar = private_key_as_scalar_(ops, &private_key);
+    if !is_scalar_within_range(&scalar, max_exclusive) {
+        return Err(error::Unspecified);
+    }
+    Ok(private_key)
+}
+
 // The underlying X25519 and Ed25519 code uses an [u8; 32] to store the private
 // key. To make the ECDH and ECDSA code similar to that, we also store the
 // private key that way, which means we have to convert it to a Scalar whenever
@@ -103,16 +130,11 @@ pub fn private_key_as_scalar(ops: &PrivateKeyOps,
 // the value.
 fn private_key_as_scalar_(ops: &PrivateKeyOps, private_key: &ec::PrivateKey)
                           -> Scalar {
-    let num_limbs = ops.common.num_limbs;
-    let bytes = &private_key.bytes;
+    let bytes = &private_key.bytes[..ops.common.elem_and_scalar_len];
     let mut limbs = [0; MAX_LIMBS];
-    for i in 0..num_limbs {
-        let mut limb = 0;
-        for j in 0..LIMB_BYTES {
-            limb = (limb << 8) |
-                   (bytes[((num_limbs - i - 1) * LIMB_BYTES) + j] as Limb);
-        }
-        limbs[i] = limb;
+    for i in 0..bytes.len() {
+        limbs[i / LIMB_BYTES] |=
+            (bytes[bytes.len() - i - 1] as Limb) << (8 * (i % LIMB_BYTES));
     }
     Scalar::from_limbs_unchecked(&limbs)
 }
@@ -131,10 +153,22 @@ fn is_scalar_within_range(scalar: &Scalar, max_exclusive: &[Limb]) -> bool {
 pub fn public_from_private(ops: &PrivateKeyOps, public_out: &mut [u8],
                            my_private_key: &ec::PrivateKey)
                            -> Result<(), error::Unspecified> {
-    let elem_and_scalar_bytes = ops.common.num_limbs * LIMB_BYTES;
-    debug_assert_eq!(public_out.len(), 1 + (2 * elem_and_scalar_bytes));
+    let elem_and_scalar_bytes = ops.common.elem_and_scalar_len;
     let my_private_key = private_key_as_scalar(ops, my_private_key);
     let my_public_key = ops.point_mul_base(&my_private_key);
+
+    if public_out.len() == 1 + elem_and_scalar_bytes {
+        // Compressed encoding: 2 or 3, for an even or odd y coordinate,
+        // followed by the x coordinate.
+        let mut y_out = [0u8; ec::ELEM_MAX_BYTES];
+        let y_out = &mut y_out[..elem_and_scalar_bytes];
+        try!(big_endian_affine_from_jacobian(ops, Some(&mut public_out[1..]),
+                                             Some(y_out), &my_public_key));
+        public_out[0] = 2 | (y_out[elem_and_scalar_bytes - 1] & 1);
+        return Ok(());
+    }
+
+    debug_assert_eq!(public_out.len(), 1 + (2 * elem_and_scalar_bytes));
     public_out[0] = 4; // Uncompressed encoding.
     let (x_out, y_out) =
         (&mut public_out[1..]).split_at_mut(elem_and_scalar_bytes);
@@ -157,6 +191,34 @@ pub fn big_endian_affine_from_jacobian(ops: &PrivateKeyOps,
     // instead of `debug_assert!` anyway
     assert!(ops.common.elem_verify_is_not_zero(&z).is_ok());
 
+    let (x_aff, y_aff) = try!(affine_from_jacobian_(ops, p));
+
+    let num_limbs = ops.common.num_limbs;
+    if let Some(x_out) = x_out {
+        let x_decoded = ops.common.elem_decoded(&x_aff);
+        big_endian_from_limbs(x_out, &x_decoded.limbs[..num_limbs]);
+    }
+    if let Some(y_out) = y_out {
+        let y_decoded = ops.common.elem_decoded(&y_aff);
+        big_endian_from_limbs(y_out, &y_decoded.limbs[..num_limbs]);
+    }
+
+    Ok(())
+}
+
+// Returns the affine coordinates of `p`, which may be the result of a
+// computation on untrusted inputs. Fails if `p` is at infinity.
+pub fn affine_from_jacobian(ops: &PrivateKeyOps, p: &Point)
+                            -> Result<(Elem, Elem), error::Unspecified> {
+    try!(ops.common.elem_verify_is_not_zero(&ops.common.point_z(p)));
+    let (x_aff, y_aff) = try!(affine_from_jacobian_(ops, p));
+    Ok((ops.common.elem_reduced(&x_aff), ops.common.elem_reduced(&y_aff)))
+}
+
+fn affine_from_jacobian_(ops: &PrivateKeyOps, p: &Point)
+                         -> Result<(ElemUnreduced, ElemUnreduced),
+                                   error::Unspecified> {
+    let z = ops.common.point_z(p);
     let x = ops.common.point_x(p);
     let y = ops.common.point_y(p);
 
@@ -172,29 +234,20 @@ pub fn big_endian_affine_from_jacobian(ops: &PrivateKeyOps,
     // `verify_affine_point_is_on_the_curve_scaled` for the motivation.
     try!(verify_affine_point_is_on_the_curve(ops.common, (&x_aff, &y_aff)));
 
-    let num_limbs = ops.common.num_limbs;
-    if let Some(x_out) = x_out {
-        let x_decoded = ops.common.elem_decoded(&x_aff);
-        big_endian_from_limbs(x_out, &x_decoded.limbs[..num_limbs]);
-    }
-    if let Some(y_out) = y_out {
-        let y_decoded = ops.common.elem_decoded(&y_aff);
-        big_endian_from_limbs(y_out, &y_decoded.limbs[..num_limbs]);
-    }
-
-    Ok(())
+    Ok((x_aff, y_aff))
 }
 
-fn big_endian_from_limbs(out: &mut [u8], limbs: &[Limb]) {
+// `out` may be shorter than `limbs` when the value doesn't use all the bits of
+// its most significant limb, as for P-521, in which case those unused bits
+// must be zero.
+pub fn big_endian_from_limbs(out: &mut [u8], limbs: &[Limb]) {
     let num_limbs = limbs.len();
-    debug_assert_eq!(out.len(), num_limbs * LIMB_BYTES);
-    for i in 0..num_limbs {
-        let mut limb = limbs[i];
-        for j in 0..LIMB_BYTES {
-            out[((num_limbs - i - 1) * LIMB_BYTES) + (LIMB_BYTES - j - 1)] =
-                (limb & 0xff) as u8;
-            limb >>= 8;
-        }
+    debug_assert!(out.len() <= num_limbs * LIMB_BYTES);
+    debug_assert!(out.len() > (num_limbs - 1) * LIMB_BYTES);
+    let out_len = out.len();
+    for i in 0..out_len {
+        out[out_len - i - 1] =
+            (limbs[i / LIMB_BYTES] >> (8 * (i % LIMB_BYTES))) as u8;
     }
 }
 
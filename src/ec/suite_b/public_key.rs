@@ -20,6 +20,20 @@ use super::ops::*;
 use super::verify_affine_point_is_on_the_curve;
 use untrusted;
 
+/// Parses a public key encoded in either uncompressed or compressed form, as
+/// described in [SEC 1: Elliptic Curve Cryptography, Version 2.0] Section
+/// 2.3.4. The key is validated as described for `parse_uncompressed_point`.
+///
+/// [SEC 1: Elliptic Curve Cryptography, Version 2.0]:
+///     http://www.secg.org/sec1-v2.pdf
+pub fn parse_point(ops: &PublicKeyOps, input: untrusted::Input)
+                   -> Result<(Elem, Elem), error::Unspecified> {
+    match input.as_slice_less_safe().first() {
+        Some(&2) | Some(&3) => parse_compressed_point(ops, input),
+        _ => parse_uncompressed_point(ops, input),
+    }
+}
+
 /// Parses a public key encoded in uncompressed form. The key is validated
 /// using the ECC Partial Public-Key Validation Routine from
 /// [NIST SP 800-56A, revision 2] Section 5.6.2.3.3, the NSA's
@@ -66,6 +80,50 @@ pub fn parse_uncompressed_point(ops: &PublicKeyOps, input: untrusted::Input)
     Ok((x, y))
 }
 
+// Parses a public key encoded in compressed form: 2 or 3, for an even or odd
+// y coordinate, followed by the x coordinate. The y coordinate is recovered
+// from the curve equation, which also verifies that the point is on the
+// curve, so the result is validated as it would be in uncompressed form.
+fn parse_compressed_point(ops: &PublicKeyOps, input: untrusted::Input)
+                          -> Result<(Elem, Elem), error::Unspecified> {
+    let (y_parity, x) = try!(input.read_all(error::Unspecified, |input| {
+        let y_parity = match try!(input.read_byte()) {
+            2 => 0,
+            3 => 1,
+            _ => { return Err(error::Unspecified); },
+        };
+
+        // NIST SP 800-56A Step 2, for x only.
+        let x = try!(ops.elem_parse(input));
+        Ok((y_parity, x))
+    }));
+
+    let ops = ops.common;
+    let x_ = ElemUnreduced::from(&x);
+
+    // y**2 = x**3 + a*x + b = (x**2 + a)*x + b.
+    let mut rhs = ops.elem_squared(&x_);
+    ops.elem_add(&mut rhs, &ops.a);
+    ops.elem_mul(&mut rhs, &x_);
+    ops.elem_add(&mut rhs, &ops.b);
+
+    // The result of `elem_sqrt_vartime` is only a square root of `rhs` if
+    // `rhs` is a square, i.e. if there is a point with x coordinate `x`.
+    // Verifying that the point is on the curve (NIST SP 800-56A Step 3) also
+    // verifies that.
+    let y = ops.elem_sqrt_vartime(&rhs);
+    let y = if ops.elem_parity(&y) == y_parity {
+        y
+    } else {
+        ops.elem_negated(&y)
+    };
+    try!(verify_affine_point_is_on_the_curve(ops, (&x_, &y)));
+
+    // There is no point with y == 0 on these prime-order curves, so the
+    // parity of the negated square root is always the requested one.
+    Ok((x, ops.elem_reduced(&y)))
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -94,6 +152,37 @@ mod tests {
             // TODO: Verify that we when we re-serialize the parsed (x, y), the
             // output is equal to the input.
 
+            // `parse_point` parses everything except compressed points the
+            // same way.
+            match public_key.as_slice_less_safe().first() {
+                Some(&2) | Some(&3) => (),
+                _ => {
+                    assert_eq!(valid,
+                               parse_point(curve_ops, public_key).is_ok());
+                },
+            }
+
+            // The compressed form of every valid point decodes to the same
+            // point.
+            if let Ok((x, y)) = result {
+                let uncompressed = public_key.as_slice_less_safe();
+                let elem_len = curve_ops.common.elem_and_scalar_len;
+                let mut compressed = vec![2 | (uncompressed[2 * elem_len] & 1)];
+                compressed.extend_from_slice(&uncompressed[1..(1 + elem_len)]);
+                let (cx, cy) =
+                    parse_point(curve_ops, untrusted::Input::from(&compressed))
+                        .unwrap();
+                assert!(curve_ops.common.elems_are_equal(&x, &cx));
+                assert!(curve_ops.common.elems_are_equal(&y, &cy));
+
+                // The other parity is a different point.
+                compressed[0] ^= 1;
+                let (_, cy) =
+                    parse_point(curve_ops, untrusted::Input::from(&compressed))
+                        .unwrap();
+                assert!(!curve_ops.common.elems_are_equal(&y, &cy));
+            }
+
             Ok(())
         });
     }
@@ -104,6 +193,8 @@ mod tests {
             &ops::p256::PUBLIC_KEY_OPS
         } else if curve_name == "P-384" {
             &ops::p384::PUBLIC_KEY_OPS
+        } else if curve_name == "P-521" {
+            &ops::p521::PUBLIC_KEY_OPS
         } else {
             panic!("Unsupported curve: {}", curve_name);
         }
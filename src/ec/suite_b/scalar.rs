@@ -0,0 +1,253 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Arithmetic on scalars modulo the group order of P-256 and P-384.
+
+use error;
+use super::ops::{self, *};
+use super::private_key::big_endian_from_limbs;
+use untrusted;
+
+/// A curve whose scalars are supported by `Scalar`.
+pub struct Curve {
+    ops: &'static PublicScalarOps,
+}
+
+impl Curve {
+    /// The length of the big-endian encoding of scalars, in bytes.
+    #[inline]
+    pub fn scalar_len(&self) -> usize {
+        self.ops.public_key_ops.common.num_limbs * LIMB_BYTES
+    }
+
+    #[inline]
+    fn n(&self) -> &[Limb] {
+        let cops = self.ops.public_key_ops.common;
+        &cops.n.limbs[..cops.num_limbs]
+    }
+}
+
+/// P-256 (secp256r1).
+pub static P256: Curve = Curve { ops: &p256::PUBLIC_SCALAR_OPS };
+
+/// P-384 (secp384r1).
+pub static P384: Curve = Curve { ops: &p384::PUBLIC_SCALAR_OPS };
+
+/// A scalar in the range [0, n), where n is the order of the curve's group.
+///
+/// All arithmetic is done in constant time with respect to the values of the
+/// scalars.
+pub struct Scalar {
+    curve: &'static Curve,
+    value: ops::Scalar,
+}
+
+impl Scalar {
+    /// Parses a scalar from its big-endian encoding.
+    ///
+    /// `input` must be exactly `curve.scalar_len()` bytes long, and its value
+    /// must be less than n. Zero is allowed. The range check is done in
+    /// constant time.
+    pub fn from_big_endian(curve: &'static Curve, input: untrusted::Input)
+                           -> Result<Scalar, error::Unspecified> {
+        if input.len() != curve.scalar_len() {
+            return Err(error::Unspecified);
+        }
+        let n = curve.n();
+        let limbs = try!(parse_big_endian_value(input, n.len()));
+        if limbs_less_than_limbs_constant_time(&limbs[..n.len()], n) !=
+                LimbMask::True {
+            return Err(error::Unspecified);
+        }
+        Ok(Scalar {
+            curve: curve,
+            value: ops::Scalar::from_limbs_unchecked(&limbs),
+        })
+    }
+
+    /// Writes the big-endian encoding of the scalar to `out`, which must be
+    /// exactly `self.curve().scalar_len()` bytes long.
+    pub fn to_big_endian(&self, out: &mut [u8])
+                         -> Result<(), error::Unspecified> {
+        if out.len() != self.curve.scalar_len() {
+            return Err(error::Unspecified);
+        }
+        big_endian_from_limbs(out, &self.value.limbs[..self.curve.n().len()]);
+        Ok(())
+    }
+
+    /// The curve the scalar belongs to.
+    #[inline]
+    pub fn curve(&self) -> &'static Curve { self.curve }
+
+    /// Returns `self + other (mod n)`.
+    ///
+    /// Fails if `self` and `other` belong to different curves.
+    pub fn add(&self, other: &Scalar) -> Result<Scalar, error::Unspecified> {
+        try!(self.check_same_curve(other));
+        Ok(self.with_value(self.curve.ops.scalar_sum(&self.value,
+                                                     &other.value)))
+    }
+
+    /// Returns `self * other (mod n)`.
+    ///
+    /// Fails if `self` and `other` belong to different curves.
+    pub fn mul(&self, other: &Scalar) -> Result<Scalar, error::Unspecified> {
+        try!(self.check_same_curve(other));
+        Ok(self.with_value(self.curve.ops.scalar_product(&self.value,
+                                                         &other.value)))
+    }
+
+    /// Returns the multiplicative inverse of `self` (mod n).
+    ///
+    /// Fails if `self` is zero. Other than revealing whether `self` is zero,
+    /// this is done in constant time.
+    pub fn invert(&self) -> Result<Scalar, error::Unspecified> {
+        let num_limbs = self.curve.n().len();
+        if limbs_are_zero_constant_time(&self.value.limbs[..num_limbs]) !=
+                LimbMask::False {
+            return Err(error::Unspecified);
+        }
+        Ok(self.with_value(self.curve.ops.scalar_inv(&self.value)))
+    }
+
+    fn check_same_curve(&self, other: &Scalar)
+                        -> Result<(), error::Unspecified> {
+        let a: *const Curve = self.curve;
+        let b: *const Curve = other.curve;
+        if a != b {
+            return Err(error::Unspecified);
+        }
+        Ok(())
+    }
+
+    fn with_value(&self, value: ops::Scalar) -> Scalar {
+        Scalar { curve: self.curve, value: value }
+    }
+}
+
+impl Clone for Scalar {
+    fn clone(&self) -> Self {
+        self.with_value(ops::Scalar::from_limbs_unchecked(&self.value.limbs))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {scalar, test};
+    use std;
+    use untrusted;
+
+    #[test]
+    fn scalar_arithmetic_test() {
+        test::from_file("src/ec/suite_b/scalar_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+
+            let curve_name = test_case.consume_string("Curve");
+            let curve = if curve_name == "P-256" {
+                &scalar::P256
+            } else if curve_name == "P-384" {
+                &scalar::P384
+            } else {
+                panic!("Unsupported curve: {}", curve_name);
+            };
+            let a = consume_scalar(curve, test_case, "A");
+            let b = consume_scalar(curve, test_case, "B");
+            let sum = test_case.consume_bytes("Sum");
+            let product = test_case.consume_bytes("Product");
+            let inv_a = test_case.consume_optional_string("InvA");
+
+            assert_eq!(to_big_endian(&try!(a.add(&b))), sum);
+            assert_eq!(to_big_endian(&try!(b.add(&a))), sum);
+            assert_eq!(to_big_endian(&try!(a.mul(&b))), product);
+            assert_eq!(to_big_endian(&try!(b.mul(&a))), product);
+            match inv_a {
+                Some(inv_a) => {
+                    let inv_a = test::from_hex(&inv_a).unwrap();
+                    let actual = try!(a.invert());
+                    assert_eq!(to_big_endian(&actual), inv_a);
+                    assert_eq!(to_big_endian(&try!(actual.invert())),
+                               to_big_endian(&a));
+                },
+                None => {
+                    assert!(a.invert().is_err());
+                },
+            }
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn scalar_from_big_endian_test() {
+        for curve in &[&scalar::P256, &scalar::P384] {
+            let len = curve.scalar_len();
+            let zero = vec![0u8; len];
+            assert!(scalar::Scalar::from_big_endian(
+                        curve, untrusted::Input::from(&zero)).is_ok());
+            assert!(scalar::Scalar::from_big_endian(
+                        curve, untrusted::Input::from(&zero[1..])).is_err());
+            let mut too_long = zero.clone();
+            too_long.push(1);
+            assert!(scalar::Scalar::from_big_endian(
+                        curve, untrusted::Input::from(&too_long)).is_err());
+
+            // All-ones is larger than n.
+            let max = vec![0xffu8; len];
+            assert!(scalar::Scalar::from_big_endian(
+                        curve, untrusted::Input::from(&max)).is_err());
+
+            let s = scalar::Scalar::from_big_endian(
+                curve, untrusted::Input::from(&zero)).unwrap();
+            let mut out = vec![0u8; len + 1];
+            assert!(s.to_big_endian(&mut out).is_err());
+            assert!(s.to_big_endian(&mut out[..len]).is_ok());
+        }
+    }
+
+    #[test]
+    fn scalar_curve_mismatch_test() {
+        let one_256 = p256_one();
+        let mut one_384 = vec![0u8; scalar::P384.scalar_len()];
+        one_384[scalar::P384.scalar_len() - 1] = 1;
+        let one_384 = scalar::Scalar::from_big_endian(
+            &scalar::P384, untrusted::Input::from(&one_384)).unwrap();
+        assert!(one_256.add(&one_384).is_err());
+        assert!(one_256.mul(&one_384).is_err());
+        assert!(one_256.add(&one_256.clone()).is_ok());
+    }
+
+    fn p256_one() -> scalar::Scalar {
+        let mut one = vec![0u8; scalar::P256.scalar_len()];
+        one[scalar::P256.scalar_len() - 1] = 1;
+        scalar::Scalar::from_big_endian(&scalar::P256,
+                                        untrusted::Input::from(&one)).unwrap()
+    }
+
+    fn consume_scalar(curve: &'static scalar::Curve,
+                      test_case: &mut test::TestCase, name: &str)
+                      -> scalar::Scalar {
+        let bytes = test_case.consume_bytes(name);
+        scalar::Scalar::from_big_endian(curve, untrusted::Input::from(&bytes))
+            .unwrap()
+    }
+
+    fn to_big_endian(s: &scalar::Scalar) -> std::vec::Vec<u8> {
+        let mut out = vec![0u8; s.curve().scalar_len()];
+        s.to_big_endian(&mut out).unwrap();
+        out
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//! The P-256 operations of SLIP-0010 hierarchical key derivation.
+
+use error;
+use super::hash_to_curve::elem_exp_vartime;
+use super::ops::*;
+use super::private_key::*;
+use super::verify_affine_point_is_on_the_curve;
+use untrusted;
+
+/// The length of an encoded private key, and of the tweaks that are added
+/// to private and public keys.
+pub const SCALAR_LEN: usize = ELEM_LEN;
+
+/// The length of an encoded point, in compressed form.
+pub const COMPRESSED_POINT_LEN: usize = 1 + ELEM_LEN;
+
+const ELEM_LEN: usize = 256 / 8;
+
+/// Whether `bytes` is a valid private key; i.e. whether it is `SCALAR_LEN`
+/// bytes long and in the range [1, n).
+pub fn private_key_is_valid(bytes: &[u8]) -> bool {
+    private_key_from_bytes(OPS, bytes).is_ok()
+}
+
+/// Writes `(parent + tweak) mod n` to `out`, in constant time. Fails if
+/// `tweak` isn't less than n or if the result is zero.
+pub fn private_key_tweak_add(parent: &[u8], tweak: &[u8], out: &mut [u8])
+                             -> Result<(), error::Unspecified> {
+    let parent = try!(private_key_from_bytes(OPS, parent));
+    let parent = private_key_as_scalar(OPS, &parent);
+    let tweak = try!(scalar_from_bytes(tweak));
+    let sum = SCALAR_OPS.scalar_sum(&parent, &tweak);
+    let num_limbs = OPS.common.num_limbs;
+    if limbs_are_zero_constant_time(&sum.limbs[..num_limbs]) ==
+            LimbMask::True {
+        return Err(error::Unspecified);
+    }
+    big_endian_from_limbs(out, &sum.limbs[..num_limbs]);
+    Ok(())
+}
+
+/// Writes the public key of `private_key` to `out`, in compressed form.
+pub fn public_from_private(private_key: &[u8], out: &mut [u8])
+                           -> Result<(), error::Unspecified> {
+    let private_key = try!(private_key_from_bytes(OPS, private_key));
+    let private_key = private_key_as_scalar(OPS, &private_key);
+    point_encode_compressed(&OPS.point_mul_base(&private_key), out)
+}
+
+/// Verifies that `point` is the compressed encoding of a point on the curve.
+pub fn verify_compressed_point(point: untrusted::Input)
+                               -> Result<(), error::Unspecified> {
+    parse_compressed_point(point).map(|_| ())
+}
+
+/// Writes `tweak * G + parent` to `out`, in compressed form. Fails if
+/// `tweak` isn't less than n or if the result is the point at infinity.
+pub fn public_key_tweak_add(parent: untrusted::Input, tweak: &[u8],
+                            out: &mut [u8]) -> Result<(), error::Unspecified> {
+    let (x, y) = try!(parse_compressed_point(parent));
+    let parent = OPS.common.point_from_affine(&ElemUnreduced::from(&x),
+                                              &ElemUnreduced::from(&y));
+    let tweak = try!(scalar_from_bytes(tweak));
+    let sum = OPS.common.point_sum(&OPS.point_mul_base(&tweak), &parent);
+    point_encode_compressed(&sum, out)
+}
+
+static OPS: &'static PrivateKeyOps = &p256::PRIVATE_KEY_OPS;
+static PUBLIC_KEY_OPS: &'static PublicKeyOps = &p256::PUBLIC_KEY_OPS;
+static SCALAR_OPS: &'static PublicScalarOps = &p256::PUBLIC_SCALAR_OPS;
+
+// Parses a big-endian value in the range [0, n). Unlike private keys, the
+// tweaks may be zero.
+fn scalar_from_bytes(bytes: &[u8]) -> Result<Scalar, error::Unspecified> {
+    let num_limbs = OPS.common.num_limbs;
+    if bytes.len() != SCALAR_LEN {
+        return Err(error::Unspecified);
+    }
+    let limbs = try!(parse_big_endian_value(untrusted::Input::from(bytes),
+                                            num_limbs));
+    if limbs_less_than_limbs_constant_time(
+            &limbs[..num_limbs], &OPS.common.n.limbs[..num_limbs]) !=
+                LimbMask::True {
+        return Err(error::Unspecified);
+    }
+    Ok(Scalar::from_limbs_unchecked(&limbs))
+}
+
+// Parses the SEC 1 compressed encoding (0x02 or 0x03 || x) of a point,
+// recovering y from the curve equation. Only public values are processed.
+fn parse_compressed_point(input: untrusted::Input)
+                          -> Result<(Elem, Elem), error::Unspecified> {
+    let (y_parity, x) = try!(input.read_all(error::Unspecified, |input| {
+        let y_parity = match try!(input.read_byte()) {
+            2 => 0,
+            3 => 1,
+            _ => {
+                return Err(error::Unspecified);
+            },
+        };
+        let x = try!(PUBLIC_KEY_OPS.elem_parse(input));
+        Ok((y_parity, x))
+    }));
+
+    let ops = OPS.common;
+    let x = ElemUnreduced::from(&x);
+
+    // y**2 == (x**2 + a)*x + b (mod q).
+    let mut rhs = ops.elem_squared(&x);
+    ops.elem_add(&mut rhs, &ops.a);
+    ops.elem_mul(&mut rhs, &x);
+    ops.elem_add(&mut rhs, &ops.b);
+
+    // q == 3 (mod 4), so rhs**((q + 1) / 4) is the square root of `rhs` if
+    // it has one. Otherwise, the check below fails.
+    let y = elem_exp_vartime(ops, &rhs, &P256_Q_PLUS_1_OVER_4);
+    let y = if ops.elem_parity(&y) == y_parity {
+        y
+    } else {
+        ops.elem_negated(&y)
+    };
+    try!(verify_affine_point_is_on_the_curve(ops, (&x, &y)));
+
+    Ok((ops.elem_reduced(&x), ops.elem_reduced(&y)))
+}
+
+// Encodes `p` in compressed form. Fails if `p` is at infinity.
+fn point_encode_compressed(p: &Point, out: &mut [u8])
+                           -> Result<(), error::Unspecified> {
+    debug_assert_eq!(out.len(), COMPRESSED_POINT_LEN);
+    try!(OPS.common.elem_verify_is_not_zero(&OPS.common.point_z(p)));
+    let mut y = [0u8; ELEM_LEN];
+    try!(big_endian_affine_from_jacobian(OPS, Some(&mut out[1..]),
+                                         Some(&mut y), p));
+    out[0] = 2 | (y[ELEM_LEN - 1] & 1);
+    Ok(())
+}
+
+const P256_Q_PLUS_1_OVER_4: [u8; ELEM_LEN] = [
+    0x3f, 0xff, 0xff, 0xff, 0xc0, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
@@ -0,0 +1,542 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! SPAKE2+ over P-256.
+
+use {constant_time, digest, error, hkdf, hmac, rand};
+use super::ops::*;
+use super::private_key::*;
+use super::public_key::parse_uncompressed_point;
+use untrusted;
+
+/// The length of the PBKDF output `w0s || w1s` that the prover's secrets are
+/// derived from.
+pub const PBKDF_OUTPUT_LEN: usize = 2 * (SCALAR_LEN + 8);
+
+/// The length of an encoded scalar `w0` or `w1`.
+pub const SCALAR_LEN: usize = ELEM_LEN;
+
+/// The length of an encoded share `shareP` or `shareV`.
+pub const SHARE_LEN: usize = 1 + (2 * ELEM_LEN);
+
+/// The length of an encoded registration record, `w0 || L`.
+pub const REGISTRATION_RECORD_LEN: usize = SCALAR_LEN + SHARE_LEN;
+
+/// The length of a key confirmation message `confirmP` or `confirmV`.
+pub const CONFIRMATION_LEN: usize = 256 / 8;
+
+/// The length of the shared key `K_shared`.
+pub const SHARED_KEY_LEN: usize = 256 / 8;
+
+const ELEM_LEN: usize = 256 / 8;
+
+/// The prover's secrets, `w0` and `w1`.
+pub struct ProverSecrets {
+    w0: Scalar,
+    w1: Scalar,
+}
+
+impl ProverSecrets {
+    /// Derives `w0` and `w1` from `w0s || w1s`, the output of the PBKDF
+    /// applied to the password and the identities, as in RFC 9383 Section
+    /// 3.2. `w0s_w1s` must be `PBKDF_OUTPUT_LEN` bytes long.
+    pub fn from_pbkdf_output(w0s_w1s: &[u8])
+                             -> Result<ProverSecrets, error::Unspecified> {
+        if w0s_w1s.len() != PBKDF_OUTPUT_LEN {
+            return Err(error::Unspecified);
+        }
+        let (w0s, w1s) = w0s_w1s.split_at(PBKDF_OUTPUT_LEN / 2);
+        Ok(ProverSecrets {
+            w0: try!(scalar_from_wide(w0s)),
+            w1: try!(scalar_from_wide(w1s)),
+        })
+    }
+
+    /// Constructs the secrets from the big-endian encodings of `w0` and `w1`,
+    /// which must each be `SCALAR_LEN` bytes long and less than the group
+    /// order.
+    pub fn from_scalars(w0: untrusted::Input, w1: untrusted::Input)
+                        -> Result<ProverSecrets, error::Unspecified> {
+        Ok(ProverSecrets {
+            w0: try!(scalar_parse(w0)),
+            w1: try!(scalar_parse(w1)),
+        })
+    }
+
+    /// Writes the registration record `w0 || L`, which the verifier stores
+    /// instead of the password, to `out`. `out` must be
+    /// `REGISTRATION_RECORD_LEN` bytes long.
+    pub fn registration_record(&self, out: &mut [u8])
+                               -> Result<(), error::Unspecified> {
+        if out.len() != REGISTRATION_RECORD_LEN {
+            return Err(error::Unspecified);
+        }
+        let (w0_out, l_out) = out.split_at_mut(SCALAR_LEN);
+        scalar_encode(&self.w0, w0_out);
+        let l = OPS.point_mul_base(&self.w1);
+        point_encode(&l, l_out)
+    }
+}
+
+/// The verifier's registration record, `w0` and `L = w1*P`.
+pub struct RegistrationRecord {
+    w0: Scalar,
+    l: (Elem, Elem),
+}
+
+impl RegistrationRecord {
+    /// Parses a registration record produced by
+    /// `ProverSecrets::registration_record`.
+    pub fn from_bytes(input: untrusted::Input)
+                      -> Result<RegistrationRecord, error::Unspecified> {
+        if input.len() != REGISTRATION_RECORD_LEN {
+            return Err(error::Unspecified);
+        }
+        let (w0, l) = input.as_slice_less_safe().split_at(SCALAR_LEN);
+        Ok(RegistrationRecord {
+            w0: try!(scalar_parse(untrusted::Input::from(w0))),
+            l: try!(parse_uncompressed_point(PUBLIC_KEY_OPS,
+                                             untrusted::Input::from(l))),
+        })
+    }
+}
+
+/// The prover's (client's) side of a SPAKE2+ exchange.
+pub struct Prover<'a> {
+    secrets: &'a ProverSecrets,
+    x: Scalar,
+    share: [u8; SHARE_LEN],
+}
+
+impl<'a> Prover<'a> {
+    /// Generates the prover's random scalar `x` and its share
+    /// `shareP = x*P + w0*M`, which is sent to the verifier.
+    pub fn start(secrets: &'a ProverSecrets, rng: &rand::SecureRandom)
+                 -> Result<Prover<'a>, error::Unspecified> {
+        let x = try!(random_scalar(rng));
+        let mut share = [0u8; SHARE_LEN];
+        let x_p = OPS.point_mul_base(&x);
+        let w0_m = OPS.point_mul(&secrets.w0, &try!(m()));
+        try!(point_encode(&OPS.common.point_sum(&x_p, &w0_m), &mut share));
+        Ok(Prover { secrets: secrets, x: x, share: share })
+    }
+
+    /// The prover's share, `shareP`.
+    #[inline]
+    pub fn share(&self) -> &[u8] { &self.share }
+
+    /// Processes the verifier's share `shareV` and key confirmation message
+    /// `confirmV`. On success, returns the prover's key confirmation message
+    /// `confirmP`, which is sent to the verifier, and the shared key.
+    ///
+    /// `context`, `id_prover`, and `id_verifier` must be the same values
+    /// that the verifier used.
+    pub fn finish(self, context: &[u8], id_prover: &[u8], id_verifier: &[u8],
+                  share_v: untrusted::Input, confirm_v: untrusted::Input)
+                  -> Result<(Confirmation, SharedKey), error::Unspecified> {
+        let y = try!(parse_uncompressed_point(PUBLIC_KEY_OPS, share_v));
+        // Y - w0*N.
+        let w0_n = OPS.point_mul(&self.secrets.w0, &try!(n()));
+        let t = OPS.common.point_sum(&point_from_affine(&y),
+                                     &OPS.common.point_negated(&w0_n));
+        let t = try!(affine_from_jacobian(OPS, &t));
+
+        let mut z = [0u8; SHARE_LEN];
+        try!(point_encode(&OPS.point_mul(&self.x, &t), &mut z));
+        let mut v = [0u8; SHARE_LEN];
+        try!(point_encode(&OPS.point_mul(&self.secrets.w1, &t), &mut v));
+
+        let keys = key_schedule(context, id_prover, id_verifier, &self.share,
+                                share_v.as_slice_less_safe(), &z, &v,
+                                &self.secrets.w0);
+        try!(constant_time::verify_slices_are_equal(
+                &keys.confirm_v.value, confirm_v.as_slice_less_safe()));
+        Ok((keys.confirm_p, keys.shared_key))
+    }
+}
+
+/// The verifier's (server's) side of a SPAKE2+ exchange.
+pub struct Verifier {
+    share: [u8; SHARE_LEN],
+    keys: Keys,
+}
+
+impl Verifier {
+    /// Processes the prover's share `shareP`, generating the verifier's
+    /// random scalar `y` and share `shareV = y*P + w0*N`. `share()` and
+    /// `confirmation()` are then sent to the prover.
+    pub fn start(record: &RegistrationRecord, context: &[u8],
+                 id_prover: &[u8], id_verifier: &[u8],
+                 share_p: untrusted::Input, rng: &rand::SecureRandom)
+                 -> Result<Verifier, error::Unspecified> {
+        let x = try!(parse_uncompressed_point(PUBLIC_KEY_OPS, share_p));
+
+        let y = try!(random_scalar(rng));
+        let mut share = [0u8; SHARE_LEN];
+        let y_p = OPS.point_mul_base(&y);
+        let w0_n = OPS.point_mul(&record.w0, &try!(n()));
+        try!(point_encode(&OPS.common.point_sum(&y_p, &w0_n), &mut share));
+
+        // X - w0*M.
+        let w0_m = OPS.point_mul(&record.w0, &try!(m()));
+        let t = OPS.common.point_sum(&point_from_affine(&x),
+                                     &OPS.common.point_negated(&w0_m));
+        let t = try!(affine_from_jacobian(OPS, &t));
+
+        let mut z = [0u8; SHARE_LEN];
+        try!(point_encode(&OPS.point_mul(&y, &t), &mut z));
+        let mut v = [0u8; SHARE_LEN];
+        try!(point_encode(&OPS.point_mul(&y, &record.l), &mut v));
+
+        let keys = key_schedule(context, id_prover, id_verifier,
+                                share_p.as_slice_less_safe(), &share, &z, &v,
+                                &record.w0);
+        Ok(Verifier { share: share, keys: keys })
+    }
+
+    /// The verifier's share, `shareV`.
+    #[inline]
+    pub fn share(&self) -> &[u8] { &self.share }
+
+    /// The verifier's key confirmation message, `confirmV`.
+    #[inline]
+    pub fn confirmation(&self) -> &[u8] { &self.keys.confirm_v.value }
+
+    /// Verifies the prover's key confirmation message `confirmP`. On success,
+    /// returns the shared key.
+    pub fn finish(self, confirm_p: untrusted::Input)
+                  -> Result<SharedKey, error::Unspecified> {
+        try!(constant_time::verify_slices_are_equal(
+                &self.keys.confirm_p.value, confirm_p.as_slice_less_safe()));
+        Ok(self.keys.shared_key)
+    }
+}
+
+/// A key confirmation message.
+pub struct Confirmation {
+    value: [u8; CONFIRMATION_LEN],
+}
+
+impl AsRef<[u8]> for Confirmation {
+    fn as_ref(&self) -> &[u8] { &self.value }
+}
+
+/// The shared key `K_shared` that results from a successful exchange.
+pub struct SharedKey {
+    value: [u8; SHARED_KEY_LEN],
+}
+
+impl AsRef<[u8]> for SharedKey {
+    fn as_ref(&self) -> &[u8] { &self.value }
+}
+
+struct Keys {
+    confirm_p: Confirmation,
+    confirm_v: Confirmation,
+    shared_key: SharedKey,
+}
+
+// RFC 9383 Sections 3.4 and 3.5, for
+// SPAKE2+-P256-SHA256-HKDF-SHA256-HMAC-SHA256.
+fn key_schedule(context: &[u8], id_prover: &[u8], id_verifier: &[u8],
+                share_p: &[u8], share_v: &[u8], z: &[u8], v: &[u8],
+                w0: &Scalar) -> Keys {
+    let mut w0_bytes = [0u8; SCALAR_LEN];
+    scalar_encode(w0, &mut w0_bytes);
+
+    let mut tt = digest::Context::new(&digest::SHA256);
+    let parts: [&[u8]; 10] = [context, id_prover, id_verifier, &M, &N,
+                              share_p, share_v, z, v, &w0_bytes];
+    for part in parts.iter() {
+        tt.update(&le_u8_from_u64(part.len() as u64));
+        tt.update(part);
+    }
+    let k_main = tt.finish();
+
+    let salt = hmac::SigningKey::new(&digest::SHA256, &[]);
+    let mut k_confirm = [0u8; 2 * CONFIRMATION_LEN];
+    hkdf::extract_and_expand(&salt, k_main.as_ref(), b"ConfirmationKeys",
+                             &mut k_confirm);
+    let mut shared_key = SharedKey { value: [0u8; SHARED_KEY_LEN] };
+    hkdf::extract_and_expand(&salt, k_main.as_ref(), b"SharedKey",
+                             &mut shared_key.value);
+
+    let (k_confirm_p, k_confirm_v) = k_confirm.split_at(CONFIRMATION_LEN);
+    Keys {
+        confirm_p: confirmation(k_confirm_p, share_v),
+        confirm_v: confirmation(k_confirm_v, share_p),
+        shared_key: shared_key,
+    }
+}
+
+fn confirmation(key: &[u8], share: &[u8]) -> Confirmation {
+    let key = hmac::SigningKey::new(&digest::SHA256, key);
+    let mut r = Confirmation { value: [0u8; CONFIRMATION_LEN] };
+    r.value.copy_from_slice(hmac::sign(&key, share).as_ref());
+    r
+}
+
+fn le_u8_from_u64(value: u64) -> [u8; 8] {
+    let mut r = [0u8; 8];
+    for (i, b) in r.iter_mut().enumerate() {
+        *b = (value >> (8 * i)) as u8;
+    }
+    r
+}
+
+static OPS: &'static PrivateKeyOps = &p256::PRIVATE_KEY_OPS;
+static PUBLIC_KEY_OPS: &'static PublicKeyOps = &p256::PUBLIC_KEY_OPS;
+static SCALAR_OPS: &'static PublicScalarOps = &p256::PUBLIC_SCALAR_OPS;
+
+fn random_scalar(rng: &rand::SecureRandom)
+                 -> Result<Scalar, error::Unspecified> {
+    let private_key = try!(generate_private_key(OPS, rng));
+    Ok(private_key_as_scalar(OPS, &private_key))
+}
+
+// Parses a scalar in the range [0, n).
+fn scalar_parse(input: untrusted::Input)
+                -> Result<Scalar, error::Unspecified> {
+    if input.len() != SCALAR_LEN {
+        return Err(error::Unspecified);
+    }
+    let n = &OPS.common.n.limbs[..OPS.common.num_limbs];
+    let limbs = try!(parse_big_endian_value(input, n.len()));
+    if limbs_less_than_limbs_constant_time(&limbs[..n.len()], n) !=
+            LimbMask::True {
+        return Err(error::Unspecified);
+    }
+    Ok(Scalar::from_limbs_unchecked(&limbs))
+}
+
+// Reduces a `SCALAR_LEN + 8`-byte big-endian value mod n, in constant time.
+// The value is split into two 160-bit halves, each of which is less than n,
+// and then `hi * 2**160 + lo` is computed mod n.
+fn scalar_from_wide(input: &[u8]) -> Result<Scalar, error::Unspecified> {
+    debug_assert_eq!(input.len(), SCALAR_LEN + 8);
+    let (hi, lo) = input.split_at(input.len() / 2);
+    let num_limbs = OPS.common.num_limbs;
+    let hi = try!(parse_big_endian_value(untrusted::Input::from(hi),
+                                         num_limbs));
+    let lo = try!(parse_big_endian_value(untrusted::Input::from(lo),
+                                         num_limbs));
+    let mut two_160 = [0u8; 21];
+    two_160[0] = 1;
+    let two_160 = try!(parse_big_endian_value(untrusted::Input::from(&two_160),
+                                              num_limbs));
+    let hi = SCALAR_OPS.scalar_product(&Scalar::from_limbs_unchecked(&hi),
+                                       &Scalar::from_limbs_unchecked(&two_160));
+    Ok(SCALAR_OPS.scalar_sum(&hi, &Scalar::from_limbs_unchecked(&lo)))
+}
+
+fn scalar_encode(a: &Scalar, out: &mut [u8]) {
+    big_endian_from_limbs(out, &a.limbs[..OPS.common.num_limbs]);
+}
+
+// Encodes `p` in uncompressed form. Fails if `p` is at infinity.
+fn point_encode(p: &Point, out: &mut [u8])
+                -> Result<(), error::Unspecified> {
+    debug_assert_eq!(out.len(), SHARE_LEN);
+    try!(OPS.common.elem_verify_is_not_zero(&OPS.common.point_z(p)));
+    out[0] = 4;
+    let (x_out, y_out) = (&mut out[1..]).split_at_mut(ELEM_LEN);
+    big_endian_affine_from_jacobian(OPS, Some(x_out), Some(y_out), p)
+}
+
+fn point_from_affine(&(ref x, ref y): &(Elem, Elem)) -> Point {
+    OPS.common.point_from_affine(&ElemUnreduced::from(x),
+                                 &ElemUnreduced::from(y))
+}
+
+fn m() -> Result<(Elem, Elem), error::Unspecified> {
+    parse_uncompressed_point(PUBLIC_KEY_OPS, untrusted::Input::from(&M))
+}
+
+fn n() -> Result<(Elem, Elem), error::Unspecified> {
+    parse_uncompressed_point(PUBLIC_KEY_OPS, untrusted::Input::from(&N))
+}
+
+// The points M and N for P-256 from RFC 9383 Section 4, which are the same
+// as in RFC 9382.
+static M: [u8; SHARE_LEN] = [
+    0x04, 0x88, 0x6e, 0x2f, 0x97, 0xac, 0xe4, 0x6e, 0x55, 0xba, 0x9d, 0xd7,
+    0x24, 0x25, 0x79, 0xf2, 0x99, 0x3b, 0x64, 0xe1, 0x6e, 0xf3, 0xdc, 0xab,
+    0x95, 0xaf, 0xd4, 0x97, 0x33, 0x3d, 0x8f, 0xa1, 0x2f, 0x5f, 0xf3, 0x55,
+    0x16, 0x3e, 0x43, 0xce, 0x22, 0x4e, 0x0b, 0x0e, 0x65, 0xff, 0x02, 0xac,
+    0x8e, 0x5c, 0x7b, 0xe0, 0x94, 0x19, 0xc7, 0x85, 0xe0, 0xca, 0x54, 0x7d,
+    0x55, 0xa1, 0x2e, 0x2d, 0x20,
+];
+
+static N: [u8; SHARE_LEN] = [
+    0x04, 0xd8, 0xbb, 0xd6, 0xc6, 0x39, 0xc6, 0x29, 0x37, 0xb0, 0x4d, 0x99,
+    0x7f, 0x38, 0xc3, 0x77, 0x07, 0x19, 0xc6, 0x29, 0xd7, 0x01, 0x4d, 0x49,
+    0xa2, 0x4b, 0x4f, 0x98, 0xba, 0xa1, 0x29, 0x2b, 0x49, 0x07, 0xd6, 0x0a,
+    0xa6, 0xbf, 0xad, 0xe4, 0x50, 0x08, 0xa6, 0x36, 0x33, 0x7f, 0x51, 0x68,
+    0xc6, 0x4d, 0x9b, 0xd3, 0x60, 0x34, 0x80, 0x8c, 0xd5, 0x64, 0x49, 0x0b,
+    0x1e, 0x65, 0x6e, 0xdb, 0xe7,
+];
+
+#[cfg(test)]
+mod tests {
+    use {spake2plus, test};
+    use rand::test_util::FixedSliceRandom;
+    use untrusted;
+
+    #[test]
+    fn test_spake2plus() {
+        test::from_file("src/ec/suite_b/spake2plus_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+            let context = test_case.consume_bytes("Context");
+            let id_prover = test_case.consume_bytes("IdProver");
+            let id_verifier = test_case.consume_bytes("IdVerifier");
+            let w0 = test_case.consume_bytes("W0");
+            let w1 = test_case.consume_bytes("W1");
+            let l = test_case.consume_bytes("L");
+            let x = test_case.consume_bytes("x");
+            let share_p = test_case.consume_bytes("ShareP");
+            let y = test_case.consume_bytes("y");
+            let share_v = test_case.consume_bytes("ShareV");
+            let confirm_p = test_case.consume_bytes("ConfirmP");
+            let confirm_v = test_case.consume_bytes("ConfirmV");
+            let shared_key = test_case.consume_bytes("SharedKey");
+
+            let secrets = spake2plus::ProverSecrets::from_scalars(
+                untrusted::Input::from(&w0), untrusted::Input::from(&w1))
+                .unwrap();
+            let mut record = [0u8; spake2plus::REGISTRATION_RECORD_LEN];
+            secrets.registration_record(&mut record).unwrap();
+            assert_eq!(&record[..w0.len()], &w0[..]);
+            assert_eq!(&record[w0.len()..], &l[..]);
+            let record = spake2plus::RegistrationRecord::from_bytes(
+                untrusted::Input::from(&record)).unwrap();
+
+            let prover = spake2plus::Prover::start(
+                &secrets, &FixedSliceRandom { bytes: &x }).unwrap();
+            assert_eq!(prover.share(), &share_p[..]);
+
+            let verifier = spake2plus::Verifier::start(
+                &record, &context, &id_prover, &id_verifier,
+                untrusted::Input::from(prover.share()),
+                &FixedSliceRandom { bytes: &y }).unwrap();
+            assert_eq!(verifier.share(), &share_v[..]);
+            assert_eq!(verifier.confirmation(), &confirm_v[..]);
+
+            // A prover with the wrong identities fails.
+            {
+                let prover = spake2plus::Prover::start(
+                    &secrets, &FixedSliceRandom { bytes: &x }).unwrap();
+                assert!(prover.finish(&context, &id_prover, b"mallory",
+                                      untrusted::Input::from(&share_v),
+                                      untrusted::Input::from(&confirm_v))
+                              .is_err());
+            }
+
+            let (actual_confirm_p, prover_key) =
+                prover.finish(&context, &id_prover, &id_verifier,
+                              untrusted::Input::from(&share_v),
+                              untrusted::Input::from(&confirm_v)).unwrap();
+            assert_eq!(actual_confirm_p.as_ref(), &confirm_p[..]);
+            assert_eq!(prover_key.as_ref(), &shared_key[..]);
+
+            let mut bad_confirm_p = confirm_p.clone();
+            bad_confirm_p[0] ^= 1;
+            let verifier2 = spake2plus::Verifier::start(
+                &record, &context, &id_prover, &id_verifier,
+                untrusted::Input::from(&share_p),
+                &FixedSliceRandom { bytes: &y }).unwrap();
+            assert!(verifier2.finish(untrusted::Input::from(&bad_confirm_p))
+                        .is_err());
+
+            let verifier_key =
+                verifier.finish(untrusted::Input::from(&confirm_p)).unwrap();
+            assert_eq!(verifier_key.as_ref(), &shared_key[..]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_spake2plus_from_pbkdf_output() {
+        let vectors = [
+            ("1f40fc92da241694750979ee6cf582f2d5d7d28e18335de05abc54d0560e0f53\
+              02860c652bf08d560252aa5e74210546f369fbbbce8c12cfc7957b2652fe9a75\
+              ca978112ca1bbdcafac231b39a23dc4d",
+             "4f2d908273906fcbde08e2a78c6da853\
+              5aa4a5b614507e82c43aa959db1bdaff",
+             "678b01025818632ac83157dc0bd563a9\
+              a2e6437c1d290252d358a6040e577018"),
+            ("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff\
+              ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff\
+              ffffffffffffffffffffffffffffffff",
+             "fffffffe00000001431905529c0166cd\
+              22159165b6faae70f756a571fc632550",
+             "fffffffe00000001431905529c0166cd\
+              22159165b6faae70f756a571fc632550"),
+        ];
+        for &(w0s_w1s, w0, w1) in vectors.iter() {
+            let w0s_w1s = test::from_hex(w0s_w1s).unwrap();
+            let secrets =
+                spake2plus::ProverSecrets::from_pbkdf_output(&w0s_w1s).unwrap();
+            let expected = spake2plus::ProverSecrets::from_scalars(
+                untrusted::Input::from(&test::from_hex(w0).unwrap()),
+                untrusted::Input::from(&test::from_hex(w1).unwrap())).unwrap();
+
+            let mut actual_record = [0u8; spake2plus::REGISTRATION_RECORD_LEN];
+            secrets.registration_record(&mut actual_record).unwrap();
+            let mut expected_record =
+                [0u8; spake2plus::REGISTRATION_RECORD_LEN];
+            expected.registration_record(&mut expected_record).unwrap();
+            assert_eq!(&actual_record[..], &expected_record[..]);
+
+            assert!(spake2plus::ProverSecrets::from_pbkdf_output(
+                        &w0s_w1s[1..]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_spake2plus_bad_inputs() {
+        // w0 == n.
+        let n = test::from_hex("ffffffff00000000ffffffffffffffff\
+                                bce6faada7179e84f3b9cac2fc632551").unwrap();
+        let mut one = [0u8; spake2plus::SCALAR_LEN];
+        one[spake2plus::SCALAR_LEN - 1] = 1;
+        assert!(spake2plus::ProverSecrets::from_scalars(
+                    untrusted::Input::from(&n), untrusted::Input::from(&one))
+                    .is_err());
+        assert!(spake2plus::ProverSecrets::from_scalars(
+                    untrusted::Input::from(&one), untrusted::Input::from(&n))
+                    .is_err());
+
+        // A share that isn't on the curve.
+        let secrets = spake2plus::ProverSecrets::from_scalars(
+            untrusted::Input::from(&one), untrusted::Input::from(&one))
+            .unwrap();
+        let mut record = [0u8; spake2plus::REGISTRATION_RECORD_LEN];
+        secrets.registration_record(&mut record).unwrap();
+        let record = spake2plus::RegistrationRecord::from_bytes(
+            untrusted::Input::from(&record)).unwrap();
+        let mut share = [0u8; spake2plus::SHARE_LEN];
+        share[0] = 4;
+        share[1] = 1;
+        assert!(spake2plus::Verifier::start(
+                    &record, b"", b"", b"", untrusted::Input::from(&share),
+                    &FixedSliceRandom { bytes: &[1u8; 32] }).is_err());
+
+        // shareP = w0*M, so that X - w0*M is the point at infinity.
+        assert!(spake2plus::Verifier::start(
+                    &record, b"", b"", b"", untrusted::Input::from(&super::M),
+                    &FixedSliceRandom { bytes: &[1u8; 32] }).is_err());
+    }
+}
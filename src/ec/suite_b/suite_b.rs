@@ -12,7 +12,7 @@
 // OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
 // CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 
-//! Elliptic curve operations on P-256 & P-384.
+//! Elliptic curve operations on P-256, P-384, & P-521.
 
 use error;
 use self::ops::*;
@@ -156,8 +156,14 @@ fn verify_affine_point_is_on_the_curve_scaled(
 }
 
 
+pub mod cpace;
 pub mod ecdsa;
 pub mod ecdh;
+pub mod ecjpake;
+pub mod hash_to_curve;
+pub mod scalar;
+pub mod slip10;
+pub mod spake2plus;
 
 #[macro_use]
 #[path = "ops/ops.rs"]
@@ -165,3 +171,12 @@ mod ops;
 
 mod private_key;
 mod public_key;
+
+/// Parses a P-256 public key encoded in uncompressed form, discarding the
+/// result. This is the operation measured by `side_channel::PointDecode`.
+#[cfg(all(feature = "side_channel_testing", feature = "use_heap"))]
+pub fn parse_p256_uncompressed_point(input: ::untrusted::Input)
+                                     -> Result<(), error::Unspecified> {
+    public_key::parse_uncompressed_point(&p256::PUBLIC_KEY_OPS, input)
+        .map(|_| ())
+}
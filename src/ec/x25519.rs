@@ -29,9 +29,14 @@ use untrusted;
 pub static X25519: agreement::Algorithm = agreement::Algorithm {
     i: ec::AgreementAlgorithmImpl {
         public_key_len: X25519_ELEM_SCALAR_PUBLIC_KEY_LEN,
+        compressed_public_key_len: None,
         elem_and_scalar_len: X25519_ELEM_SCALAR_PUBLIC_KEY_LEN,
+        private_key_len: X25519_ELEM_SCALAR_PUBLIC_KEY_LEN,
         nid: 948 /* NID_X25519 */,
+        name: "X25519",
+        security_bits: 128,
         generate_private_key: x25519_generate_private_key,
+        private_key_from_seed: x25519_private_key_from_seed,
         public_from_private: x25519_public_from_private,
         ecdh: x25519_ecdh,
     },
@@ -44,6 +49,17 @@ fn x25519_generate_private_key(rng: &rand::SecureRandom)
     Ok(result)
 }
 
+// Every 32-byte string is a valid X25519 private key; see RFC 7748 Section 5.
+fn x25519_private_key_from_seed(seed: &[u8])
+                                -> Result<ec::PrivateKey, error::Unspecified> {
+    if seed.len() != X25519_ELEM_SCALAR_PUBLIC_KEY_LEN {
+        return Err(error::Unspecified);
+    }
+    let mut result = ec::PrivateKey { bytes: [0; ec::SCALAR_MAX_BYTES] };
+    result.bytes[..X25519_ELEM_SCALAR_PUBLIC_KEY_LEN].copy_from_slice(seed);
+    Ok(result)
+}
+
 fn x25519_public_from_private(public_out: &mut [u8],
                               private_key: &ec::PrivateKey)
                               -> Result<(), error::Unspecified> {
@@ -56,9 +72,7 @@ fn x25519_public_from_private(public_out: &mut [u8],
         try!(slice_as_array_ref!(
                 &private_key.bytes[..X25519_ELEM_SCALAR_PUBLIC_KEY_LEN],
                 X25519_ELEM_SCALAR_PUBLIC_KEY_LEN));
-    unsafe {
-        GFp_x25519_public_from_private(public_out, private_key);
-    }
+    public_from_private_bytes(public_out, private_key);
     Ok(())
 }
 
@@ -73,6 +87,34 @@ fn x25519_ecdh(out: &mut [u8], my_private_key: &ec::PrivateKey,
         try!(slice_as_array_ref!(
                 &my_private_key.bytes[..X25519_ELEM_SCALAR_PUBLIC_KEY_LEN],
                 X25519_ELEM_SCALAR_PUBLIC_KEY_LEN));
+    ecdh_bytes(out, my_private_key, peer_public_key)
+}
+
+// The X25519 operations on raw private keys, for protocols such as WireGuard
+// that use static Diffie-Hellman keys, which `agreement` doesn't support.
+
+pub fn public_from_private_bytes(
+        public_out: &mut [u8; X25519_ELEM_SCALAR_PUBLIC_KEY_LEN],
+        private_key: &[u8; X25519_ELEM_SCALAR_PUBLIC_KEY_LEN]) {
+    unsafe {
+        GFp_x25519_public_from_private(public_out, private_key);
+    }
+}
+
+// Like `public_from_private_bytes`, but the public point has a random
+// low-order component, for `elligator2`.
+pub fn public_from_private_bytes_dirty(
+        public_out: &mut [u8; X25519_ELEM_SCALAR_PUBLIC_KEY_LEN],
+        private_key: &[u8; X25519_ELEM_SCALAR_PUBLIC_KEY_LEN]) {
+    unsafe {
+        GFp_x25519_public_from_private_dirty(public_out, private_key);
+    }
+}
+
+pub fn ecdh_bytes(out: &mut [u8; X25519_ELEM_SCALAR_PUBLIC_KEY_LEN],
+                  my_private_key: &[u8; X25519_ELEM_SCALAR_PUBLIC_KEY_LEN],
+                  peer_public_key: untrusted::Input)
+                  -> Result<(), error::Unspecified> {
     let peer_public_key =
         try!(slice_as_array_ref!(peer_public_key.as_slice_less_safe(),
                                  X25519_ELEM_SCALAR_PUBLIC_KEY_LEN));
@@ -81,8 +123,7 @@ fn x25519_ecdh(out: &mut [u8], my_private_key: &ec::PrivateKey,
     })
 }
 
-
-const X25519_ELEM_SCALAR_PUBLIC_KEY_LEN: usize = 32;
+pub const X25519_ELEM_SCALAR_PUBLIC_KEY_LEN: usize = 32;
 
 extern {
     fn GFp_x25519_ecdh(
@@ -92,6 +133,9 @@ extern {
     fn GFp_x25519_public_from_private(
         public_key_out: &mut [u8; X25519_ELEM_SCALAR_PUBLIC_KEY_LEN],
         private_key: &[u8; X25519_ELEM_SCALAR_PUBLIC_KEY_LEN]);
+    fn GFp_x25519_public_from_private_dirty(
+        public_key_out: &mut [u8; X25519_ELEM_SCALAR_PUBLIC_KEY_LEN],
+        private_key: &[u8; X25519_ELEM_SCALAR_PUBLIC_KEY_LEN]);
 }
 
 #[cfg(test)]
@@ -0,0 +1,80 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//! EC J-PAKE, the elliptic curve variant of the password-authenticated key
+//! exchange J-PAKE ([RFC 8236]), in the form used by Thread commissioning
+//! and the TLS `ECJPAKE` key exchange.
+//!
+//! Only P-256 with SHA-256 is implemented. The messages are formatted as
+//! mbed TLS formats them (`ECJPAKEKeyKPPairList` for round one and
+//! `ECJPAKEKeyKP`, preceded by the curve's `ECParameters` for the server, for
+//! round two), and the zero-knowledge proofs are the Schnorr proofs of
+//! [RFC 8235] with the identities "client" and "server", so this
+//! interoperates with existing Thread devices.
+//!
+//! Each party constructs a `RoundOne` with its role and the shared secret,
+//! sends its round one message, and passes the peer's round one message to
+//! `RoundOne::finish`. It then sends its round two message and passes the
+//! peer's round two message to `RoundTwo::finish`, which returns the
+//! premaster secret. Both messages of each round may be sent at the same
+//! time. The premaster secret is the same for both parties only if they used
+//! the same secret; J-PAKE doesn't confirm the key itself, so the protocol
+//! that uses the premaster secret must.
+//!
+//! [RFC 8236]: https://tools.ietf.org/html/rfc8236
+//! [RFC 8235]: https://tools.ietf.org/html/rfc8235
+//!
+//! # Example
+//!
+//! ```
+//! # extern crate ring;
+//! # extern crate untrusted;
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::{ecjpake, rand};
+//!
+//! let rng = rand::SystemRandom::new();
+//! let secret = b"J01NME";
+//!
+//! let client = try!(ecjpake::RoundOne::new(ecjpake::Role::Client, secret,
+//!                                          &rng));
+//! let server = try!(ecjpake::RoundOne::new(ecjpake::Role::Server, secret,
+//!                                          &rng));
+//!
+//! // Each party sends its message before it processes the peer's message.
+//! let (client_one, server_one) =
+//!     (client.message().to_vec(), server.message().to_vec());
+//! let client = try!(client.finish(untrusted::Input::from(&server_one),
+//!                                 &rng));
+//! let server = try!(server.finish(untrusted::Input::from(&client_one),
+//!                                 &rng));
+//!
+//! let (client_two, server_two) =
+//!     (client.message().to_vec(), server.message().to_vec());
+//! let client_pms = try!(client.finish(untrusted::Input::from(&server_two)));
+//! let server_pms = try!(server.finish(untrusted::Input::from(&client_two)));
+//! assert_eq!(client_pms.as_ref(), server_pms.as_ref());
+//! # Ok(())
+//! # }
+//! ```
+
+pub use ec::suite_b::ecjpake::{
+    Role,
+    RoundOne,
+    RoundTwo,
+
+    MAX_SECRET_LEN,
+    PREMASTER_SECRET_LEN,
+    ROUND_ONE_MAX_LEN,
+    ROUND_TWO_MAX_LEN,
+};
@@ -0,0 +1,303 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Elligator2 encodings of X25519 public keys, for protocols that need their
+//! public keys to be indistinguishable from uniformly random strings, e.g.
+//! censorship-resistant transports like obfs4.
+//!
+//! About half of all X25519 public keys have an Elligator2 representative, a
+//! 32-byte string that is indistinguishable from random and that decodes to
+//! the public key. `EphemeralKeyPair::generate` generates private keys until
+//! it finds one whose public key has a representative. The peer decodes the
+//! representative with `public_key_from_representative` and then uses the
+//! public key with `agreement::agree_ephemeral` as usual.
+//!
+//! Public keys generated the usual way are all in the prime-order subgroup of
+//! Curve25519, and their representatives can be distinguished from random
+//! strings by decoding them and checking that. To prevent that, the public
+//! key of an `EphemeralKeyPair` also has a random component of small order,
+//! which doesn't affect the result of the key agreement. Because of that,
+//! the private key's `compute_public_key` doesn't compute the public key that
+//! the peer sees; use `EphemeralKeyPair::public_key` instead.
+//!
+//! The map uses the non-square 2, like [RFC 9380]'s `map_to_curve_elligator2`
+//! for Curve25519. Representatives are at most (p - 1) / 2, where p is
+//! 2**255 - 19, so the two most significant bits of their encoding are random
+//! padding, which decoding ignores.
+//!
+//! # Example
+//!
+//! ```
+//! # extern crate untrusted;
+//! # extern crate ring;
+//! #
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::{agreement, elligator2, error, rand};
+//! use untrusted;
+//!
+//! let rng = rand::SystemRandom::new();
+//!
+//! let my_key_pair = try!(elligator2::EphemeralKeyPair::generate(&rng));
+//! // Send `my_key_pair.representative()` to the peer.
+//! let my_representative = *my_key_pair.representative();
+//!
+//! // The peer decodes it to get my public key.
+//! let my_public_key =
+//!     try!(elligator2::public_key_from_representative(
+//!             untrusted::Input::from(&my_representative)));
+//! assert_eq!(&my_public_key, my_key_pair.public_key());
+//!
+//! let peer_private_key =
+//!     try!(agreement::EphemeralPrivateKey::generate(&agreement::X25519,
+//!                                                   &rng));
+//! agreement::agree_ephemeral(peer_private_key, &agreement::X25519,
+//!                            untrusted::Input::from(&my_public_key),
+//!                            error::Unspecified, |_key_material| {
+//!     // Derive session keys from the key material.
+//!     Ok(())
+//! })
+//! # }
+//! # fn main() { example().unwrap() }
+//! ```
+//!
+//! [RFC 9380]: https://www.rfc-editor.org/rfc/rfc9380.html#section-6.7.1
+
+use {agreement, error, init, rand};
+use ec::curve25519_field::FieldElement;
+use ec::x25519;
+use untrusted;
+
+/// The length of an X25519 public key.
+pub const PUBLIC_KEY_LEN: usize = x25519::X25519_ELEM_SCALAR_PUBLIC_KEY_LEN;
+
+/// The length of an Elligator2 representative.
+pub const REPRESENTATIVE_LEN: usize = 32;
+
+const PRIVATE_KEY_LEN: usize = x25519::X25519_ELEM_SCALAR_PUBLIC_KEY_LEN;
+
+/// An X25519 ephemeral key pair whose public key has an Elligator2
+/// representative.
+pub struct EphemeralKeyPair {
+    private_key: agreement::EphemeralPrivateKey,
+    public_key: [u8; PUBLIC_KEY_LEN],
+    representative: [u8; REPRESENTATIVE_LEN],
+}
+
+impl EphemeralKeyPair {
+    /// Generates a new key pair.
+    ///
+    /// This takes two attempts on average, and each attempt costs about as
+    /// much as generating an ordinary X25519 key pair.
+    pub fn generate(rng: &rand::SecureRandom)
+                    -> Result<EphemeralKeyPair, error::Unspecified> {
+        loop {
+            let mut seed = [0u8; PRIVATE_KEY_LEN];
+            let mut tweak = [0u8; 1];
+            try!(rng.fill(&mut seed));
+            try!(rng.fill(&mut tweak));
+            if let Ok(key_pair) = EphemeralKeyPair::from_seed(&seed,
+                                                              tweak[0]) {
+                return Ok(key_pair);
+            }
+        }
+    }
+
+    // `tweak`'s least significant bit chooses between the two
+    // representatives of the public key, and its two most significant bits
+    // are the padding.
+    fn from_seed(seed: &[u8; PRIVATE_KEY_LEN], tweak: u8)
+                 -> Result<EphemeralKeyPair, error::Unspecified> {
+        init::init_once();
+        let mut public_key = [0u8; PUBLIC_KEY_LEN];
+        x25519::public_from_private_bytes_dirty(&mut public_key, seed);
+        let representative =
+            try!(representative_from_public_key(&public_key, tweak));
+        let private_key =
+            try!(agreement::EphemeralPrivateKey::from_seed(&agreement::X25519,
+                                                           seed));
+        Ok(EphemeralKeyPair {
+            private_key: private_key,
+            public_key: public_key,
+            representative: representative,
+        })
+    }
+
+    /// The public key, as the peer will decode it from the representative.
+    #[inline]
+    pub fn public_key(&self) -> &[u8; PUBLIC_KEY_LEN] { &self.public_key }
+
+    /// The Elligator2 representative of the public key, to send to the peer
+    /// instead of the public key.
+    #[inline]
+    pub fn representative(&self) -> &[u8; REPRESENTATIVE_LEN] {
+        &self.representative
+    }
+
+    /// The private key, for use with `agreement::agree_ephemeral`.
+    pub fn into_private_key(self) -> agreement::EphemeralPrivateKey {
+        self.private_key
+    }
+}
+
+/// Decodes an Elligator2 representative into the X25519 public key that it
+/// represents. Every 32-byte string is a valid representative.
+pub fn public_key_from_representative(representative: untrusted::Input)
+        -> Result<[u8; PUBLIC_KEY_LEN], error::Unspecified> {
+    init::init_once();
+    let mut r = *try!(slice_as_array_ref!(representative.as_slice_less_safe(),
+                                          REPRESENTATIVE_LEN));
+    r[REPRESENTATIVE_LEN - 1] &= 0x3f;
+    let r = FieldElement::from_bytes_mod_p(&r);
+
+    // w = -A / (1 + 2r**2). The denominator is never zero since -1/2 isn't a
+    // square.
+    let a = curve_a();
+    let r_squared = r.square();
+    let w = a.negation()
+             .product(&FieldElement::one().sum(&r_squared.sum(&r_squared))
+                                          .inverse());
+
+    // u = w if w**3 + A*w**2 + w is a square, and -w - A otherwise.
+    let v_squared = w.sum(&a).product(&w).sum(&FieldElement::one()).product(&w);
+    let (is_square, _) =
+        FieldElement::sqrt_ratio_m1(&v_squared, &FieldElement::one());
+    let u = FieldElement::select(is_square, &w,
+                                 &w.negation().difference(&a));
+    Ok(*u.as_bytes())
+}
+
+// The inverse of `public_key_from_representative`: the representative r of u
+// is sqrt(-u / (2(u + A))) or sqrt(-(u + A) / (2u)), whichever the least
+// significant bit of `tweak` selects. The two most significant bits of
+// `tweak` are used as the padding.
+fn representative_from_public_key(public_key: &[u8; PUBLIC_KEY_LEN],
+                                  tweak: u8)
+        -> Result<[u8; REPRESENTATIVE_LEN], error::Unspecified> {
+    let u = FieldElement::from_bytes_mod_p(public_key);
+    let u_plus_a = u.sum(&curve_a());
+    let choice = tweak & 1;
+    let n = FieldElement::select(choice, &u_plus_a, &u);
+    let d = FieldElement::select(choice, &u, &u_plus_a);
+    let (was_square, r) =
+        FieldElement::sqrt_ratio_m1(&n.negation(), &d.sum(&d));
+    // u = -A has no representative, but `sqrt_ratio_m1` succeeds for it when
+    // `n` is zero.
+    if was_square & (1 ^ u_plus_a.is_zero()) != 1 {
+        return Err(error::Unspecified);
+    }
+    // Of the two square roots, use the one that is at most (p - 1) / 2, i.e.
+    // the one whose double isn't reduced mod p and so is even. Its two most
+    // significant bits are zero.
+    let r = FieldElement::select(r.sum(&r).is_negative(), &r.negation(), &r);
+    let mut representative = *r.as_bytes();
+    representative[REPRESENTATIVE_LEN - 1] |= tweak & 0xc0;
+    Ok(representative)
+}
+
+// A = 486662, the coefficient of Curve25519.
+fn curve_a() -> FieldElement {
+    let mut bytes = [0u8; 32];
+    bytes[..3].copy_from_slice(&[0x06, 0x6d, 0x07]);
+    FieldElement::from_bytes_mod_p(&bytes)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {agreement, error, rand, test};
+    use untrusted;
+
+    #[test]
+    fn test_elligator2() {
+        test::from_file("src/elligator2_tests.txt", |section, test_case| {
+            match section {
+                "Map" => {
+                    let representative =
+                        test_case.consume_bytes("Representative");
+                    let expected = test_case.consume_bytes("PublicKey");
+                    let actual = public_key_from_representative(
+                        untrusted::Input::from(&representative)).unwrap();
+                    assert_eq!(&actual[..], &expected[..]);
+                },
+
+                "KeyPair" => {
+                    let seed = test_case.consume_bytes("Seed");
+                    let seed = slice_as_array_ref!(&seed, PRIVATE_KEY_LEN)
+                        .unwrap();
+                    let tweak = test_case.consume_usize("Tweak") as u8;
+                    let result = EphemeralKeyPair::from_seed(seed, tweak);
+                    if test_case.consume_optional_string("Error").is_some() {
+                        assert!(result.is_err());
+                        return Ok(());
+                    }
+                    let key_pair = result.unwrap();
+                    let public_key = test_case.consume_bytes("PublicKey");
+                    let representative =
+                        test_case.consume_bytes("Representative");
+                    assert_eq!(&key_pair.public_key()[..], &public_key[..]);
+                    assert_eq!(&key_pair.representative()[..],
+                               &representative[..]);
+                    let decoded = public_key_from_representative(
+                        untrusted::Input::from(&representative)).unwrap();
+                    assert_eq!(&decoded[..], &public_key[..]);
+                },
+
+                _ => unreachable!()
+            }
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_elligator2_agreement() {
+        let rng = rand::SystemRandom::new();
+        let key_pair = EphemeralKeyPair::generate(&rng).unwrap();
+        let public_key = public_key_from_representative(
+            untrusted::Input::from(key_pair.representative())).unwrap();
+        assert_eq!(&public_key, key_pair.public_key());
+
+        let peer = agreement::EphemeralPrivateKey::generate(&agreement::X25519,
+                                                            &rng).unwrap();
+        let mut peer_public_key = [0u8; PUBLIC_KEY_LEN];
+        peer.compute_public_key(&mut peer_public_key).unwrap();
+
+        let mut my_secret = [0u8; 32];
+        agreement::agree_ephemeral(key_pair.into_private_key(),
+                                   &agreement::X25519,
+                                   untrusted::Input::from(&peer_public_key),
+                                   error::Unspecified, |secret| {
+            my_secret.copy_from_slice(secret);
+            Ok(())
+        }).unwrap();
+        let mut peer_secret = [0u8; 32];
+        agreement::agree_ephemeral(peer, &agreement::X25519,
+                                   untrusted::Input::from(&public_key),
+                                   error::Unspecified, |secret| {
+            peer_secret.copy_from_slice(secret);
+            Ok(())
+        }).unwrap();
+        assert_eq!(my_secret, peer_secret);
+    }
+
+    #[test]
+    fn test_elligator2_representative_len() {
+        let too_short = [0u8; REPRESENTATIVE_LEN - 1];
+        assert!(public_key_from_representative(
+                    untrusted::Input::from(&too_short)).is_err());
+        let too_long = [0u8; REPRESENTATIVE_LEN + 1];
+        assert!(public_key_from_representative(
+                    untrusted::Input::from(&too_long)).is_err());
+    }
+}
@@ -0,0 +1,440 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! A compact, self-describing, versioned envelope for AEAD ciphertexts and
+//! signatures.
+//!
+//! *ring* deliberately leaves the wire format to the application, but many
+//! applications only need a safe default: something that records which
+//! algorithm and key were used, carries the nonce, and can't be confused
+//! with another algorithm or key when it is read back. An envelope is:
+//!
+//! ```text
+//! version       u8       1
+//! kind          u8       1 (AEAD ciphertext) or 2 (signature)
+//! algorithm     i32      COSE `alg` value, big-endian
+//! key_id_len    u8
+//! key_id        [u8; key_id_len]
+//! nonce_len     u8
+//! nonce         [u8; nonce_len]
+//! ```
+//!
+//! followed, for an AEAD ciphertext, by the ciphertext and tag, or, for a
+//! signature, by the length of the message as a big-endian `u32`, the
+//! message, and the signature.
+//!
+//! Everything before the ciphertext is the additional authenticated data of
+//! the AEAD, and everything before the signature is the signed data, so the
+//! algorithm, the key ID, and the version can't be changed without detection.
+//! The key ID is chosen by the application, and is up to 255 bytes long.
+//!
+//! Algorithms are identified by their COSE `alg` values ([RFC 8152] Section
+//! 10 and `iana::to_cose_alg`); AEAD algorithms without one, and signature
+//! algorithms that `iana` doesn't map, can't be used in envelopes. AEAD
+//! envelopes use a random nonce, so no more than 2**32 messages should be
+//! sealed with the same key.
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::{aead, envelope, rand};
+//!
+//! # fn main() { foo().unwrap() }
+//! # fn foo() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let key_bytes = [0x42; 32];
+//!
+//! let sealing_key =
+//!     try!(aead::SealingKey::new(&aead::CHACHA20_POLY1305, &key_bytes));
+//! let sealed = try!(envelope::seal(&sealing_key, b"key-2016", b"hello",
+//!                                  &rng));
+//!
+//! let envelope = try!(envelope::Envelope::parse(&sealed));
+//! assert_eq!(envelope.key_id(), b"key-2016");
+//! let opening_key =
+//!     try!(aead::OpeningKey::new(&aead::CHACHA20_POLY1305, &key_bytes));
+//! assert_eq!(try!(envelope::open(&opening_key, &envelope)), b"hello");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [RFC 8152]: https://tools.ietf.org/html/rfc8152#section-10
+
+use {aead, error, iana, polyfill, rand, signature, std};
+use core;
+use untrusted;
+
+/// The version of the envelope format that is produced and accepted.
+pub const VERSION: u8 = 1;
+
+/// The kind of contents of an envelope.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    /// An AEAD ciphertext.
+    Aead,
+
+    /// A signed message.
+    Signature,
+}
+
+impl Kind {
+    fn encoding(&self) -> u8 {
+        match *self {
+            Kind::Aead => 1,
+            Kind::Signature => 2,
+        }
+    }
+}
+
+/// A parsed envelope. Nothing in it has been authenticated yet; use `open`
+/// or `verify` to do so.
+pub struct Envelope<'a> {
+    kind: Kind,
+    algorithm_id: i32,
+    key_id: &'a [u8],
+    nonce: &'a [u8],
+
+    // The AD of an AEAD envelope or the signed data of a signature envelope.
+    authenticated: &'a [u8],
+
+    payload: &'a [u8],
+    signature: &'a [u8],
+}
+
+impl<'a> Envelope<'a> {
+    /// Parses `input`. Fails if `input` isn't a well-formed envelope of
+    /// version `VERSION`.
+    pub fn parse(bytes: &'a [u8]) -> Result<Envelope<'a>, error::Unspecified> {
+        untrusted::Input::from(bytes).read_all(error::Unspecified, |input| {
+            if try!(input.read_byte()) != VERSION {
+                return Err(error::Unspecified);
+            }
+            let kind = match try!(input.read_byte()) {
+                1 => Kind::Aead,
+                2 => Kind::Signature,
+                _ => { return Err(error::Unspecified); },
+            };
+            let algorithm_id = try!(read_u32(input)) as i32;
+            let key_id = try!(read_bytes_u8(input));
+            let nonce = try!(read_bytes_u8(input));
+            let header_len = HEADER_FIXED_LEN + key_id.len() + nonce.len();
+
+            let (authenticated, payload, signature) = match kind {
+                Kind::Aead => {
+                    let payload = input.skip_to_end();
+                    (&bytes[..header_len], payload.as_slice_less_safe(),
+                     &[][..])
+                },
+                Kind::Signature => {
+                    let payload_len = try!(read_u32(input)) as usize;
+                    let payload = try!(input.skip_and_get_input(payload_len)
+                                            .map_err(|_| error::Unspecified));
+                    let signature = input.skip_to_end();
+                    let signed_len = header_len + 4 + payload_len;
+                    (&bytes[..signed_len], payload.as_slice_less_safe(),
+                     signature.as_slice_less_safe())
+                },
+            };
+            Ok(Envelope {
+                kind: kind,
+                algorithm_id: algorithm_id,
+                key_id: key_id,
+                nonce: nonce,
+                authenticated: authenticated,
+                payload: payload,
+                signature: signature,
+            })
+        })
+    }
+
+    /// The kind of contents of the envelope.
+    pub fn kind(&self) -> Kind { self.kind }
+
+    /// The COSE `alg` value of the algorithm that was used.
+    pub fn algorithm_id(&self) -> i32 { self.algorithm_id }
+
+    /// The key ID, which the application uses to find the key to open or
+    /// verify the envelope with.
+    pub fn key_id(&self) -> &'a [u8] { self.key_id }
+
+    /// The nonce of an AEAD envelope. It is empty for signature envelopes.
+    pub fn nonce(&self) -> &'a [u8] { self.nonce }
+}
+
+/// Encrypts and authenticates `plaintext` with `key`, using a random nonce
+/// from `rng`, and returns the envelope.
+///
+/// Fails if `key_id` is longer than 255 bytes or if the algorithm of `key`
+/// has no COSE `alg` value.
+pub fn seal(key: &aead::SealingKey, key_id: &[u8], plaintext: &[u8],
+            rng: &rand::SecureRandom)
+            -> Result<std::vec::Vec<u8>, error::Unspecified> {
+    let algorithm = key.algorithm();
+    let algorithm_id = try!(aead_algorithm_id(algorithm));
+    let mut nonce = [0u8; MAX_NONCE_LEN];
+    let nonce = &mut nonce[..algorithm.nonce_len()];
+    try!(rng.fill(nonce));
+
+    let mut out = try!(encode_header(Kind::Aead, algorithm_id, key_id, nonce));
+    let header_len = out.len();
+    let tag_len = algorithm.tag_len();
+    out.extend_from_slice(plaintext);
+    out.extend(core::iter::repeat(0).take(tag_len));
+    let (header, in_out) = out.split_at_mut(header_len);
+    let _ = try!(aead::seal_in_place(key, nonce, in_out, tag_len, header));
+    Ok(out)
+}
+
+/// Authenticates and decrypts the AEAD envelope `envelope` with `key`, and
+/// returns the plaintext.
+///
+/// Fails if `envelope` isn't an AEAD envelope for the algorithm of `key`, or
+/// if it isn't authentic.
+pub fn open(key: &aead::OpeningKey, envelope: &Envelope)
+            -> Result<std::vec::Vec<u8>, error::Unspecified> {
+    if envelope.kind != Kind::Aead ||
+       envelope.algorithm_id != try!(aead_algorithm_id(key.algorithm())) {
+        return Err(error::Unspecified);
+    }
+    let mut in_out = envelope.payload.to_vec();
+    let plaintext_len = try!(aead::open_in_place(key, envelope.nonce, 0,
+                                                 &mut in_out,
+                                                 envelope.authenticated));
+    in_out.truncate(plaintext_len);
+    Ok(in_out)
+}
+
+/// Signs `msg` and returns the envelope.
+///
+/// `alg` is the algorithm that verifiers will use. `sign` is given the data
+/// to be signed, which includes `msg`, and returns its signature, e.g.
+/// `|data| Ok(key_pair.sign(data).as_slice().to_vec())` for an
+/// `Ed25519KeyPair`.
+///
+/// Fails if `key_id` is longer than 255 bytes, if `msg` is 2**32 bytes or
+/// longer, if `alg` has no COSE `alg` value, or if `sign` fails.
+pub fn sign<F>(alg: &signature::VerificationAlgorithm, key_id: &[u8],
+               msg: &[u8], sign: F)
+               -> Result<std::vec::Vec<u8>, error::Unspecified>
+               where F: FnOnce(&[u8])
+                               -> Result<std::vec::Vec<u8>,
+                                         error::Unspecified> {
+    let algorithm_id = try!(iana::to_cose_alg(alg));
+    if polyfill::u64_from_usize(msg.len()) > 0xffffffff {
+        return Err(error::Unspecified);
+    }
+    let mut out =
+        try!(encode_header(Kind::Signature, algorithm_id, key_id, &[]));
+    out.extend_from_slice(&polyfill::slice::be_u8_from_u32(msg.len() as u32));
+    out.extend_from_slice(msg);
+    let signature = try!(sign(&out));
+    out.extend_from_slice(&signature);
+    Ok(out)
+}
+
+/// Verifies the signature envelope `envelope` with `public_key`, and returns
+/// the signed message.
+///
+/// The verification algorithm is the one identified by the envelope; use
+/// `policy::CryptoPolicy::check_verification` on
+/// `iana::from_cose_alg(envelope.algorithm_id())` first to restrict it.
+pub fn verify<'a>(envelope: &Envelope<'a>, public_key: untrusted::Input)
+                  -> Result<&'a [u8], error::Unspecified> {
+    if envelope.kind != Kind::Signature {
+        return Err(error::Unspecified);
+    }
+    let alg = try!(iana::from_cose_alg(envelope.algorithm_id));
+    try!(signature::verify(alg, public_key,
+                           untrusted::Input::from(envelope.authenticated),
+                           untrusted::Input::from(envelope.signature)));
+    Ok(envelope.payload)
+}
+
+// The version, the kind, the algorithm, and the two length bytes.
+const HEADER_FIXED_LEN: usize = 1 + 1 + 4 + 1 + 1;
+
+const MAX_NONCE_LEN: usize = 255;
+
+// The AEAD algorithms that have COSE `alg` values ([RFC 8152] Section 10).
+fn aead_algorithm_id(alg: &aead::Algorithm)
+                     -> Result<i32, error::Unspecified> {
+    match alg.name() {
+        "AES_128_GCM" => Ok(1),
+        "AES_256_GCM" => Ok(3),
+        "CHACHA20_POLY1305" => Ok(24),
+        _ => Err(error::Unspecified),
+    }
+}
+
+fn encode_header(kind: Kind, algorithm_id: i32, key_id: &[u8], nonce: &[u8])
+                 -> Result<std::vec::Vec<u8>, error::Unspecified> {
+    if key_id.len() > 0xff || nonce.len() > 0xff {
+        return Err(error::Unspecified);
+    }
+    let mut out = std::vec::Vec::with_capacity(
+        HEADER_FIXED_LEN + key_id.len() + nonce.len());
+    out.push(VERSION);
+    out.push(kind.encoding());
+    out.extend_from_slice(
+        &polyfill::slice::be_u8_from_u32(algorithm_id as u32));
+    out.push(key_id.len() as u8);
+    out.extend_from_slice(key_id);
+    out.push(nonce.len() as u8);
+    out.extend_from_slice(nonce);
+    Ok(out)
+}
+
+fn read_u32(input: &mut untrusted::Reader) -> Result<u32, error::Unspecified> {
+    let mut value = 0u32;
+    for _ in 0..4 {
+        value = (value << 8) | (try!(input.read_byte()) as u32);
+    }
+    Ok(value)
+}
+
+fn read_bytes_u8<'a>(input: &mut untrusted::Reader<'a>)
+                     -> Result<&'a [u8], error::Unspecified> {
+    let len = try!(input.read_byte());
+    input.skip_and_get_input(len as usize)
+         .map(|bytes| bytes.as_slice_less_safe())
+         .map_err(|_| error::Unspecified)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {aead, error, rand, signature, test};
+    use super::*;
+    use untrusted;
+
+    #[test]
+    fn test_envelope_aead() {
+        let rng = rand::SystemRandom::new();
+        let key_bytes = [0x42u8; 32];
+        for &(alg, id) in [(&aead::AES_128_GCM, 1), (&aead::AES_256_GCM, 3),
+                           (&aead::CHACHA20_POLY1305, 24)].iter() {
+            let key_bytes = &key_bytes[..alg.key_len()];
+            let sealing_key = aead::SealingKey::new(alg, key_bytes).unwrap();
+            let opening_key = aead::OpeningKey::new(alg, key_bytes).unwrap();
+
+            let sealed = seal(&sealing_key, b"id", b"hello", &rng).unwrap();
+            {
+                let envelope = Envelope::parse(&sealed).unwrap();
+                assert_eq!(envelope.kind(), Kind::Aead);
+                assert_eq!(envelope.algorithm_id(), id);
+                assert_eq!(envelope.key_id(), b"id");
+                assert_eq!(envelope.nonce().len(), alg.nonce_len());
+                assert_eq!(open(&opening_key, &envelope).unwrap(), b"hello");
+                assert_eq!(verify(&envelope, untrusted::Input::from(&[])),
+                           Err(error::Unspecified));
+            }
+
+            // Every byte, including those of the header, is authenticated.
+            for i in 0..sealed.len() {
+                let mut tampered = sealed.clone();
+                tampered[i] ^= 1;
+                if let Ok(envelope) = Envelope::parse(&tampered) {
+                    assert!(open(&opening_key, &envelope).is_err());
+                }
+            }
+        }
+
+        // The algorithm of the key must match the envelope.
+        let sealing_key =
+            aead::SealingKey::new(&aead::AES_256_GCM, &key_bytes).unwrap();
+        let sealed = seal(&sealing_key, b"", b"hello", &rng).unwrap();
+        let opening_key =
+            aead::OpeningKey::new(&aead::CHACHA20_POLY1305, &key_bytes)
+                .unwrap();
+        let envelope = Envelope::parse(&sealed).unwrap();
+        assert!(open(&opening_key, &envelope).is_err());
+
+        // AEADs without a COSE `alg` value and overlong key IDs are rejected.
+        let sealing_key =
+            aead::SealingKey::new(&aead::AES_256_OCB, &key_bytes).unwrap();
+        assert!(seal(&sealing_key, b"", b"hello", &rng).is_err());
+        let sealing_key =
+            aead::SealingKey::new(&aead::AES_256_GCM, &key_bytes).unwrap();
+        assert!(seal(&sealing_key, &[0u8; 256], b"hello", &rng).is_err());
+        assert!(seal(&sealing_key, &[0u8; 255], b"hello", &rng).is_ok());
+    }
+
+    #[test]
+    fn test_envelope_signature() {
+        let key_pair =
+            signature::Ed25519KeyPair::from_seed_unchecked(&[0x42u8; 32])
+                .unwrap();
+        let public_key = untrusted::Input::from(key_pair.public_key_bytes());
+
+        let signed = sign(&signature::ED25519, b"id", b"hello", |data| {
+            Ok(key_pair.sign(data).as_slice().to_vec())
+        }).unwrap();
+        {
+            let envelope = Envelope::parse(&signed).unwrap();
+            assert_eq!(envelope.kind(), Kind::Signature);
+            assert_eq!(envelope.algorithm_id(), -8);
+            assert_eq!(envelope.key_id(), b"id");
+            assert_eq!(envelope.nonce(), b"");
+            assert_eq!(verify(&envelope, public_key), Ok(&b"hello"[..]));
+
+            let opening_key =
+                aead::OpeningKey::new(&aead::AES_128_GCM, &[0u8; 16])
+                    .unwrap();
+            assert!(open(&opening_key, &envelope).is_err());
+        }
+
+        for i in 0..signed.len() {
+            let mut tampered = signed.clone();
+            tampered[i] ^= 1;
+            if let Ok(envelope) = Envelope::parse(&tampered) {
+                assert!(verify(&envelope, public_key).is_err());
+            }
+        }
+
+        let result = sign(&signature::ED25519, b"id", b"hello",
+                          |_| Err(error::Unspecified));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_envelope_parse() {
+        let encoded = test::from_hex(concat!(
+            "0102fffffff8", // Version 1, signature, -8 (Ed25519).
+            "026964", // Key ID "id".
+            "00", // No nonce.
+            "000000026869", // Message "hi".
+            "aabb")).unwrap(); // Signature.
+        {
+            let envelope = Envelope::parse(&encoded).unwrap();
+            assert_eq!(envelope.kind(), Kind::Signature);
+            assert_eq!(envelope.algorithm_id(), -8);
+            assert_eq!(envelope.key_id(), b"id");
+            assert_eq!(envelope.nonce(), b"");
+            assert_eq!(envelope.authenticated, &encoded[..16]);
+            assert_eq!(envelope.payload, b"hi");
+            assert_eq!(envelope.signature, &[0xaa, 0xbb]);
+        }
+
+        // Other versions and kinds, and truncated envelopes, are rejected.
+        let mut other_version = encoded.clone();
+        other_version[0] = 2;
+        assert!(Envelope::parse(&other_version).is_err());
+        let mut other_kind = encoded.clone();
+        other_kind[1] = 3;
+        assert!(Envelope::parse(&other_kind).is_err());
+        for len in 0..16 {
+            assert!(Envelope::parse(&encoded[..len]).is_err());
+        }
+    }
+}
@@ -17,7 +17,7 @@
 use core;
 use untrusted;
 
-#[cfg(feature = "use_heap")]
+#[cfg(feature = "use_std")]
 use std;
 
 /// An error with absolutely no details.
@@ -29,8 +29,9 @@ use std;
 /// error handling logic.
 ///
 /// `Result<T, ring::error::Unspecified>` is mostly equivalent to
-/// `Result<T, ()>`. However, `ring::error::Unspecified` implements
-/// [`std::error::Error`] and users of *ring* can implement
+/// `Result<T, ()>`. However, when the `use_std` feature is enabled,
+/// `ring::error::Unspecified` implements [`std::error::Error`] and converts
+/// to `std::io::Error`. Users of *ring* can also implement
 /// `From<ring::error::Unspecified>` to map this to their own error types, as
 /// described in [“Error Handling” in the Rust Book]:
 ///
@@ -89,7 +90,7 @@ impl core::fmt::Display for Unspecified {
     }
 }
 
-#[cfg(feature = "use_heap")]
+#[cfg(feature = "use_std")]
 impl std::error::Error for Unspecified {
     #[inline]
     fn cause(&self) -> Option<&std::error::Error> { None }
@@ -98,6 +99,182 @@ impl std::error::Error for Unspecified {
     fn description(&self) -> &str { "ring::error::Unspecified" }
 }
 
+#[cfg(feature = "use_std")]
+impl From<Unspecified> for std::io::Error {
+    fn from(e: Unspecified) -> Self {
+        std::io::Error::new(std::io::ErrorKind::Other, e)
+    }
+}
+
 impl From<untrusted::EndOfInput> for Unspecified {
     fn from(_: untrusted::EndOfInput) -> Self { Unspecified }
 }
+
+/// The reason a key constructor rejected a key.
+///
+/// Key constructors return `KeyRejected` instead of `Unspecified` because key
+/// material often comes from configuration, where knowing why a key was
+/// rejected makes the problem easy to fix. For symmetric keys, the reason only
+/// depends on the length of the key and on the algorithm, never on the value
+/// of the key. For private keys that are parsed from an encoding, e.g. by
+/// `signature::RSAKeyPair::from_der`, the reason also tells which step of
+/// the parsing and checking failed, which is no more than the key's owner
+/// learns from the key itself.
+///
+/// `KeyRejected` converts to `Unspecified`, so `try!` works with it in
+/// functions that return `Unspecified`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyRejected {
+    /// The key is shorter than the algorithm requires.
+    TooShort,
+
+    /// The key is longer than the algorithm allows.
+    TooLong,
+
+    /// The algorithm isn't supported for this kind of key.
+    UnsupportedAlgorithm,
+
+    /// The key isn't validly encoded, e.g. it isn't valid DER or a component
+    /// has leading zeros.
+    InvalidEncoding,
+
+    /// The version of the key's encoding isn't supported, e.g. a multi-prime
+    /// RSA key.
+    VersionNotSupported,
+
+    /// The size of the key, e.g. the length of an RSA public modulus, isn't
+    /// supported.
+    UnsupportedSize,
+
+    /// The components of the key are invalid or aren't consistent with each
+    /// other, e.g. an RSA public modulus isn't the product of the primes.
+    InconsistentComponents,
+
+    /// The key was rejected for a reason that shouldn't happen, e.g. an
+    /// internal failure while expanding the key.
+    Unexpected,
+}
+
+impl KeyRejected {
+    /// Checks that a key is exactly `expected_len` bytes long.
+    #[doc(hidden)]
+    pub fn check_len(key_len: usize, expected_len: usize)
+                     -> Result<(), KeyRejected> {
+        if key_len < expected_len {
+            return Err(KeyRejected::TooShort);
+        }
+        if key_len > expected_len {
+            return Err(KeyRejected::TooLong);
+        }
+        Ok(())
+    }
+
+    /// Maps a failure to parse an encoded key to `InvalidEncoding`.
+    #[doc(hidden)]
+    #[inline]
+    pub fn invalid_encoding(_: Unspecified) -> KeyRejected {
+        KeyRejected::InvalidEncoding
+    }
+
+    fn description_(&self) -> &'static str {
+        match *self {
+            KeyRejected::TooShort => "key too short",
+            KeyRejected::TooLong => "key too long",
+            KeyRejected::UnsupportedAlgorithm => "unsupported algorithm",
+            KeyRejected::InvalidEncoding => "invalid key encoding",
+            KeyRejected::VersionNotSupported =>
+                "key encoding version not supported",
+            KeyRejected::UnsupportedSize => "unsupported key size",
+            KeyRejected::InconsistentComponents =>
+                "inconsistent key components",
+            KeyRejected::Unexpected => "unexpected error",
+        }
+    }
+}
+
+impl core::fmt::Display for KeyRejected {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str(self.description_())
+    }
+}
+
+#[cfg(feature = "use_std")]
+impl std::error::Error for KeyRejected {
+    #[inline]
+    fn cause(&self) -> Option<&std::error::Error> { None }
+
+    #[inline]
+    fn description(&self) -> &str { self.description_() }
+}
+
+impl From<KeyRejected> for Unspecified {
+    fn from(_: KeyRejected) -> Self { Unspecified }
+}
+
+#[cfg(feature = "use_std")]
+impl From<KeyRejected> for std::io::Error {
+    fn from(e: KeyRejected) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// An input or output had a length that the operation doesn't accept.
+///
+/// Operations whose failures can only be caused by the lengths of their
+/// arguments, e.g. `aead::seal_in_place` and `digest::Context::finish_into`,
+/// return `InvalidLength` instead of `Unspecified`, so that a caller can tell
+/// such a bug in their code apart from other errors. Failures that depend on
+/// secret or attacker-controlled values, like a failure to open an AEAD
+/// record or to verify a signature, are still `Unspecified`.
+///
+/// `InvalidLength` converts to `Unspecified`, so `try!` works with it in
+/// functions that return `Unspecified`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InvalidLength;
+
+impl core::fmt::Display for InvalidLength {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("ring::error::InvalidLength")
+    }
+}
+
+#[cfg(feature = "use_std")]
+impl std::error::Error for InvalidLength {
+    #[inline]
+    fn cause(&self) -> Option<&std::error::Error> { None }
+
+    #[inline]
+    fn description(&self) -> &str { "ring::error::InvalidLength" }
+}
+
+impl From<InvalidLength> for Unspecified {
+    fn from(_: InvalidLength) -> Self { Unspecified }
+}
+
+#[cfg(feature = "use_std")]
+impl From<InvalidLength> for std::io::Error {
+    fn from(e: InvalidLength) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, e)
+    }
+}
+
+
+#[cfg(all(test, feature = "use_std"))]
+mod tests {
+    use std;
+    use super::*;
+
+    #[test]
+    fn test_error_into_io_error() {
+        fn check<E: std::error::Error + Into<std::io::Error>>(
+                e: E, kind: std::io::ErrorKind) {
+            let description = format!("{}", e);
+            let io_error: std::io::Error = e.into();
+            assert_eq!(io_error.kind(), kind);
+            assert_eq!(format!("{}", io_error), description);
+        }
+        check(Unspecified, std::io::ErrorKind::Other);
+        check(KeyRejected::TooShort, std::io::ErrorKind::InvalidData);
+        check(InvalidLength, std::io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Control over where *ring* runs work that can be done in parallel.
+//!
+//! *ring* never spawns threads. Instead, operations that consist of
+//! independent jobs, e.g. `signature::ed25519_verify_batch_with` and
+//! `digest::multi::digest_many_with`, take an `Executor`, which decides where
+//! the jobs run: one after another on the calling thread with `Sequential`,
+//! or on the thread pool, async runtime, or scheduler that the application
+//! already has. The variants of those operations that don't take an
+//! `Executor` use `Sequential`.
+//!
+//! An application that has a thread pool implements `Executor` by handing
+//! each job to the pool and waiting until all of them have finished. The jobs
+//! borrow the operation's inputs and outputs, so `execute` must not return
+//! before they have all returned.
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::{digest, executor};
+//!
+//! let leaves: [&[u8]; 3] = [b"a", b"b", b"c"];
+//! let digests = digest::multi::digest_many_with(&digest::SHA256, &leaves,
+//!                                               &executor::Sequential);
+//! assert_eq!(digests.len(), leaves.len());
+//! ```
+
+/// Runs the independent jobs that *ring* splits an operation into.
+pub trait Executor {
+    /// Runs each of `jobs` exactly once and returns after all of them have
+    /// returned. The jobs may be run in any order and concurrently.
+    fn execute(&self, jobs: &mut [&mut (FnMut() + Send)]);
+}
+
+/// An `Executor` that runs the jobs one after another on the calling thread.
+pub struct Sequential;
+
+impl Executor for Sequential {
+    fn execute(&self, jobs: &mut [&mut (FnMut() + Send)]) {
+        for job in jobs.iter_mut() {
+            job();
+        }
+    }
+}
+
+/// Executors for testing the operations that take an `Executor`.
+#[cfg(test)]
+pub mod test_util {
+    use super::Executor;
+
+    /// Runs the jobs in the reverse order, to check that the results don't
+    /// depend on the order.
+    pub struct Reversed;
+
+    impl Executor for Reversed {
+        fn execute(&self, jobs: &mut [&mut (FnMut() + Send)]) {
+            for job in jobs.iter_mut().rev() {
+                job();
+            }
+        }
+    }
+
+    /// Doesn't run any of the jobs.
+    pub struct Broken;
+
+    impl Executor for Broken {
+        fn execute(&self, _: &mut [&mut (FnMut() + Send)]) {}
+    }
+}
@@ -0,0 +1,233 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Labeled keying material exporters, for channel binding.
+//!
+//! An `Exporter` derives values that are bound to one session of a secure
+//! transport from a secret of that session, a label, and a context, like the
+//! exporters of [RFC 5705] and [RFC 8446 Section 7.5]. Application-layer
+//! protocols use these values to bind their own authentication to the
+//! transport, e.g. the `tls-exporter` channel binding of [RFC 9266].
+//!
+//! `Exporter::tls13` constructs an exporter from a TLS 1.3 connection's
+//! exporter secret, and computes exactly the TLS 1.3 `TLS-Exporter`
+//! function. `Exporter::hkdf` constructs an exporter from a secret of any
+//! other transport, such as an SSH session identifier, and computes
+//! HKDF-Expand(PRK, info, L), where PRK is HKDF-Extract("ring exporter",
+//! secret) and `info` is:
+//!
+//! ```text
+//! struct {
+//!     uint16 length = L;
+//!     opaque label<1..255> = Label;
+//!     opaque context_hash[Hash.length] = Hash(Context);
+//! } ExporterLabel;
+//! ```
+//!
+//! Both sides of the transport must of course use the same kind of exporter
+//! with the same digest algorithm.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::{digest, exporter};
+//!
+//! # let session_id = [0u8; 32];
+//! // An SSH session identifier is the exchange hash H of the first key
+//! // exchange.
+//! let exporter = exporter::Exporter::hkdf(&digest::SHA256, &session_id);
+//! let binding = try!(exporter.channel_binding());
+//!
+//! let mut key = [0u8; 32];
+//! try!(exporter.export(b"EXPORTER-my-protocol", b"", &mut key));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [RFC 5705]: https://tools.ietf.org/html/rfc5705
+//! [RFC 8446 Section 7.5]: https://tools.ietf.org/html/rfc8446#section-7.5
+//! [RFC 9266]: https://tools.ietf.org/html/rfc9266
+
+use {digest, error, hkdf, hmac, tls_kdf};
+
+/// The length of the value returned by `Exporter::channel_binding`.
+pub const CHANNEL_BINDING_LEN: usize = 32;
+
+/// The maximum length of a label.
+pub const MAX_LABEL_LEN: usize = 255;
+
+const CHANNEL_BINDING_LABEL: &'static [u8] = b"EXPORTER-Channel-Binding";
+
+const HKDF_SALT: &'static [u8] = b"ring exporter";
+
+/// A keying material exporter for one session of a transport.
+pub struct Exporter {
+    secret: hkdf::Prk,
+    kind: Kind,
+}
+
+enum Kind {
+    Tls13,
+    Hkdf,
+}
+
+impl Exporter {
+    /// Constructs an exporter that computes the TLS 1.3 `TLS-Exporter`
+    /// function for the connection with the exporter secret
+    /// `exporter_secret` (`exporter_master_secret`). `digest_alg` is the hash
+    /// of the connection's cipher suite.
+    pub fn tls13(digest_alg: &'static digest::Algorithm,
+                 exporter_secret: &[u8]) -> Exporter {
+        Exporter {
+            secret: hkdf::Prk::new(digest_alg, exporter_secret),
+            kind: Kind::Tls13,
+        }
+    }
+
+    /// Constructs an HKDF-based exporter for the session with the secret
+    /// `secret`, e.g. an SSH session identifier.
+    pub fn hkdf(digest_alg: &'static digest::Algorithm, secret: &[u8])
+                -> Exporter {
+        let salt = hmac::SigningKey::new(digest_alg, HKDF_SALT);
+        Exporter {
+            secret: hkdf::extract(&salt, secret),
+            kind: Kind::Hkdf,
+        }
+    }
+
+    /// Fills `out` with the value exported for `label` and `context`.
+    ///
+    /// `label` must not be empty and must be at most `MAX_LABEL_LEN` bytes
+    /// long; TLS 1.3 exporters further limit it to
+    /// `tls_kdf::MAX_LABEL_LEN` bytes. `out` may be at most 255 times the
+    /// digest algorithm's output length.
+    pub fn export(&self, label: &[u8], context: &[u8], out: &mut [u8])
+                  -> Result<(), error::Unspecified> {
+        if label.is_empty() || label.len() > MAX_LABEL_LEN {
+            return Err(error::Unspecified);
+        }
+        let digest_alg = self.secret.digest_algorithm();
+        let context_hash = digest::digest(digest_alg, context);
+        match self.kind {
+            Kind::Tls13 => {
+                let derived = try!(tls_kdf::derive_secret(
+                    &self.secret, label, &digest::digest(digest_alg, b"")));
+                tls_kdf::hkdf_expand_label(&derived, b"exporter",
+                                           context_hash.as_ref(), out)
+            },
+            Kind::Hkdf => {
+                let output_len = digest_alg.output_len;
+                let mut info = [0u8; 2 + 1 + MAX_LABEL_LEN +
+                                     digest::MAX_OUTPUT_LEN];
+                let info_len = 2 + 1 + label.len() + output_len;
+                if out.len() > 255 * output_len {
+                    return Err(error::Unspecified);
+                }
+                info[0] = (out.len() >> 8) as u8;
+                info[1] = out.len() as u8;
+                info[2] = label.len() as u8;
+                info[3..][..label.len()].copy_from_slice(label);
+                info[(3 + label.len())..info_len]
+                    .copy_from_slice(context_hash.as_ref());
+                self.secret.expand(&info[..info_len], out);
+                Ok(())
+            },
+        }
+    }
+
+    /// Returns the `tls-exporter` channel binding value of [RFC 9266], i.e.
+    /// the `CHANNEL_BINDING_LEN`-byte value exported for the label
+    /// `"EXPORTER-Channel-Binding"` and an empty context.
+    ///
+    /// [RFC 9266]: https://tools.ietf.org/html/rfc9266#section-2
+    pub fn channel_binding(&self)
+            -> Result<[u8; CHANNEL_BINDING_LEN], error::Unspecified> {
+        let mut binding = [0u8; CHANNEL_BINDING_LEN];
+        try!(self.export(CHANNEL_BINDING_LABEL, b"", &mut binding));
+        Ok(binding)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {digest, error, test};
+    use super::*;
+
+    #[test]
+    fn test_exporter() {
+        test::from_file("src/exporter_tests.txt", |section, test_case| {
+            let digest_alg = match test_case.consume_string("Digest")
+                                            .as_str() {
+                "SHA256" => &digest::SHA256,
+                "SHA384" => &digest::SHA384,
+                s => panic!("Unsupported digest: {}", s),
+            };
+            let secret = test_case.consume_bytes("Secret");
+            let label = test_case.consume_bytes("Label");
+            let context = test_case.consume_bytes("Context");
+            let expected = test_case.consume_bytes("Output");
+            let exporter = match section {
+                "Tls13" => Exporter::tls13(digest_alg, &secret),
+                "Hkdf" => Exporter::hkdf(digest_alg, &secret),
+                _ => panic!("Unexpected section: {}", section),
+            };
+            let mut actual = vec![0u8; expected.len()];
+            try!(exporter.export(&label, &context, &mut actual));
+            assert_eq!(actual, expected);
+            if label == CHANNEL_BINDING_LABEL && context.is_empty() &&
+               expected.len() == CHANNEL_BINDING_LEN {
+                assert_eq!(&try!(exporter.channel_binding())[..],
+                           &expected[..]);
+            }
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_exporter_separation() {
+        let secret = [1u8; 32];
+        let tls13 = Exporter::tls13(&digest::SHA256, &secret);
+        let hkdf = Exporter::hkdf(&digest::SHA256, &secret);
+        assert!(tls13.channel_binding().unwrap() !=
+                    hkdf.channel_binding().unwrap());
+
+        // The output length is bound to the output.
+        let mut short = [0u8; 16];
+        let long = hkdf.channel_binding().unwrap();
+        hkdf.export(CHANNEL_BINDING_LABEL, b"", &mut short).unwrap();
+        assert!(short[..] != long[..16]);
+    }
+
+    #[test]
+    fn test_exporter_lengths() {
+        for exporter in &[Exporter::tls13(&digest::SHA256, &[0u8; 32]),
+                          Exporter::hkdf(&digest::SHA256, &[0u8; 32])] {
+            let mut out = [0u8; 32];
+            assert_eq!(exporter.export(b"", b"", &mut out),
+                       Err(error::Unspecified));
+            assert!(exporter.export(&[b'a'; MAX_LABEL_LEN + 1], b"", &mut out)
+                        .is_err());
+            let mut out = vec![0u8; 255 * 32 + 1];
+            assert!(exporter.export(b"label", b"", &mut out).is_err());
+            assert!(exporter.export(b"label", b"", &mut out[..(255 * 32)])
+                        .is_ok());
+        }
+        let hkdf = Exporter::hkdf(&digest::SHA256, &[0u8; 32]);
+        let mut out = [0u8; 32];
+        assert!(hkdf.export(&[b'a'; MAX_LABEL_LEN], b"", &mut out).is_ok());
+    }
+}
@@ -0,0 +1,390 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Finite-field Diffie-Hellman key agreement using the [RFC 7919] groups.
+//!
+//! [RFC 7919]: https://tools.ietf.org/html/rfc7919
+
+use {agreement, bssl, c, ec, error, rand};
+use untrusted;
+
+macro_rules! ffdhe {
+    ( $NAME:ident, $bits:expr, $group_str:expr, $security_bits:expr, $nid:expr,
+      $private_key_len:expr, $p:ident, $ffdhe:ident,
+      $generate_private_key:ident, $private_key_from_seed:ident,
+      $public_from_private:ident ) =>
+    {
+        #[doc="Finite-field Diffie-Hellman using the"]
+        #[doc=$group_str]
+        #[doc="group from [RFC 7919]."]
+        ///
+        /// Public keys and shared secrets are big-endian encoded and
+        /// left-padded with zeros to the length of the prime. Peer public
+        /// keys are validated as described in RFC 7919 Section 5.1, and key
+        /// agreement fails if the shared secret is one. Private keys are
+        /// random exponents of at least the length suggested in RFC 7919
+        /// Section 5.2.
+        ///
+        /// Only available in `use_heap` mode.
+        ///
+        /// [RFC 7919]: https://tools.ietf.org/html/rfc7919
+        pub static $NAME: agreement::Algorithm = agreement::Algorithm {
+            i: ec::AgreementAlgorithmImpl {
+                public_key_len: $bits / 8,
+                compressed_public_key_len: None,
+                elem_and_scalar_len: $bits / 8,
+                private_key_len: $private_key_len,
+                nid: $nid,
+                name: stringify!($NAME),
+                security_bits: $security_bits,
+                generate_private_key: $generate_private_key,
+                private_key_from_seed: $private_key_from_seed,
+                public_from_private: $public_from_private,
+                ecdh: $ffdhe,
+            },
+        };
+
+        fn $ffdhe(out: &mut [u8], my_private_key: &ec::PrivateKey,
+                  peer_public_key: untrusted::Input)
+                  -> Result<(), error::Unspecified> {
+            ffdhe(&$p, $private_key_len, out, my_private_key, peer_public_key)
+        }
+
+        fn $generate_private_key(rng: &rand::SecureRandom)
+                                 -> Result<ec::PrivateKey, error::Unspecified> {
+            generate_private_key($private_key_len, rng)
+        }
+
+        fn $private_key_from_seed(seed: &[u8])
+                -> Result<ec::PrivateKey, error::Unspecified> {
+            private_key_from_seed($private_key_len, seed)
+        }
+
+        fn $public_from_private(public_out: &mut [u8],
+                                private_key: &ec::PrivateKey)
+                                -> Result<(), error::Unspecified> {
+            public_from_private(&$p, $private_key_len, public_out, private_key)
+        }
+    }
+}
+
+// The private key lengths are the exponent lengths from RFC 7919 Appendix A
+// (225, 275, and 325 bits), rounded up to whole bytes.
+
+ffdhe!(FFDHE2048, 2048, "ffdhe2048", 103, 1126 /*NID_ffdhe2048*/, 29,
+       FFDHE2048_P, ffdhe2048_ffdhe, ffdhe2048_generate_private_key,
+       ffdhe2048_private_key_from_seed, ffdhe2048_public_from_private);
+
+ffdhe!(FFDHE3072, 3072, "ffdhe3072", 125, 1127 /*NID_ffdhe3072*/, 35,
+       FFDHE3072_P, ffdhe3072_ffdhe, ffdhe3072_generate_private_key,
+       ffdhe3072_private_key_from_seed, ffdhe3072_public_from_private);
+
+ffdhe!(FFDHE4096, 4096, "ffdhe4096", 150, 1128 /*NID_ffdhe4096*/, 41,
+       FFDHE4096_P, ffdhe4096_ffdhe, ffdhe4096_generate_private_key,
+       ffdhe4096_private_key_from_seed, ffdhe4096_public_from_private);
+
+
+fn generate_private_key(private_key_len: usize, rng: &rand::SecureRandom)
+                        -> Result<ec::PrivateKey, error::Unspecified> {
+    let mut result = ec::PrivateKey { bytes: [0; ec::SCALAR_MAX_BYTES] };
+    try!(rng.fill(&mut result.bytes[..private_key_len]));
+    Ok(result)
+}
+
+// Every exponent of the right length is a valid private key.
+fn private_key_from_seed(private_key_len: usize, seed: &[u8])
+                         -> Result<ec::PrivateKey, error::Unspecified> {
+    if seed.len() != private_key_len {
+        return Err(error::Unspecified);
+    }
+    let mut result = ec::PrivateKey { bytes: [0; ec::SCALAR_MAX_BYTES] };
+    result.bytes[..private_key_len].copy_from_slice(seed);
+    Ok(result)
+}
+
+fn public_from_private(p: &[u8], private_key_len: usize,
+                       public_out: &mut [u8], private_key: &ec::PrivateKey)
+                       -> Result<(), error::Unspecified> {
+    if public_out.len() != p.len() {
+        return Err(error::Unspecified);
+    }
+    let private_key = &private_key.bytes[..private_key_len];
+    bssl::map_result(unsafe {
+        GFp_ffdhe_public_from_private(public_out.as_mut_ptr(), p.as_ptr(),
+                                      p.len(), private_key.as_ptr(),
+                                      private_key.len())
+    })
+}
+
+fn ffdhe(p: &[u8], private_key_len: usize, out: &mut [u8],
+         my_private_key: &ec::PrivateKey, peer_public_key: untrusted::Input)
+         -> Result<(), error::Unspecified> {
+    let peer_public_key = peer_public_key.as_slice_less_safe();
+    if out.len() != p.len() || peer_public_key.len() != p.len() {
+        return Err(error::Unspecified);
+    }
+    let my_private_key = &my_private_key.bytes[..private_key_len];
+    bssl::map_result(unsafe {
+        GFp_ffdhe_compute_key(out.as_mut_ptr(), p.as_ptr(), p.len(),
+                              my_private_key.as_ptr(), my_private_key.len(),
+                              peer_public_key.as_ptr())
+    })
+}
+
+extern {
+    fn GFp_ffdhe_public_from_private(out: *mut u8, p: *const u8,
+                                     p_len: c::size_t,
+                                     private_key: *const u8,
+                                     private_key_len: c::size_t) -> c::int;
+    fn GFp_ffdhe_compute_key(out: *mut u8, p: *const u8, p_len: c::size_t,
+                             private_key: *const u8,
+                             private_key_len: c::size_t,
+                             peer_public_value: *const u8) -> c::int;
+}
+
+static FFDHE2048_P: [u8; 2048 / 8] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xad, 0xf8, 0x54, 0x58,
+    0xa2, 0xbb, 0x4a, 0x9a, 0xaf, 0xdc, 0x56, 0x20, 0x27, 0x3d, 0x3c, 0xf1,
+    0xd8, 0xb9, 0xc5, 0x83, 0xce, 0x2d, 0x36, 0x95, 0xa9, 0xe1, 0x36, 0x41,
+    0x14, 0x64, 0x33, 0xfb, 0xcc, 0x93, 0x9d, 0xce, 0x24, 0x9b, 0x3e, 0xf9,
+    0x7d, 0x2f, 0xe3, 0x63, 0x63, 0x0c, 0x75, 0xd8, 0xf6, 0x81, 0xb2, 0x02,
+    0xae, 0xc4, 0x61, 0x7a, 0xd3, 0xdf, 0x1e, 0xd5, 0xd5, 0xfd, 0x65, 0x61,
+    0x24, 0x33, 0xf5, 0x1f, 0x5f, 0x06, 0x6e, 0xd0, 0x85, 0x63, 0x65, 0x55,
+    0x3d, 0xed, 0x1a, 0xf3, 0xb5, 0x57, 0x13, 0x5e, 0x7f, 0x57, 0xc9, 0x35,
+    0x98, 0x4f, 0x0c, 0x70, 0xe0, 0xe6, 0x8b, 0x77, 0xe2, 0xa6, 0x89, 0xda,
+    0xf3, 0xef, 0xe8, 0x72, 0x1d, 0xf1, 0x58, 0xa1, 0x36, 0xad, 0xe7, 0x35,
+    0x30, 0xac, 0xca, 0x4f, 0x48, 0x3a, 0x79, 0x7a, 0xbc, 0x0a, 0xb1, 0x82,
+    0xb3, 0x24, 0xfb, 0x61, 0xd1, 0x08, 0xa9, 0x4b, 0xb2, 0xc8, 0xe3, 0xfb,
+    0xb9, 0x6a, 0xda, 0xb7, 0x60, 0xd7, 0xf4, 0x68, 0x1d, 0x4f, 0x42, 0xa3,
+    0xde, 0x39, 0x4d, 0xf4, 0xae, 0x56, 0xed, 0xe7, 0x63, 0x72, 0xbb, 0x19,
+    0x0b, 0x07, 0xa7, 0xc8, 0xee, 0x0a, 0x6d, 0x70, 0x9e, 0x02, 0xfc, 0xe1,
+    0xcd, 0xf7, 0xe2, 0xec, 0xc0, 0x34, 0x04, 0xcd, 0x28, 0x34, 0x2f, 0x61,
+    0x91, 0x72, 0xfe, 0x9c, 0xe9, 0x85, 0x83, 0xff, 0x8e, 0x4f, 0x12, 0x32,
+    0xee, 0xf2, 0x81, 0x83, 0xc3, 0xfe, 0x3b, 0x1b, 0x4c, 0x6f, 0xad, 0x73,
+    0x3b, 0xb5, 0xfc, 0xbc, 0x2e, 0xc2, 0x20, 0x05, 0xc5, 0x8e, 0xf1, 0x83,
+    0x7d, 0x16, 0x83, 0xb2, 0xc6, 0xf3, 0x4a, 0x26, 0xc1, 0xb2, 0xef, 0xfa,
+    0x88, 0x6b, 0x42, 0x38, 0x61, 0x28, 0x5c, 0x97, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff,
+];
+
+static FFDHE3072_P: [u8; 3072 / 8] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xad, 0xf8, 0x54, 0x58,
+    0xa2, 0xbb, 0x4a, 0x9a, 0xaf, 0xdc, 0x56, 0x20, 0x27, 0x3d, 0x3c, 0xf1,
+    0xd8, 0xb9, 0xc5, 0x83, 0xce, 0x2d, 0x36, 0x95, 0xa9, 0xe1, 0x36, 0x41,
+    0x14, 0x64, 0x33, 0xfb, 0xcc, 0x93, 0x9d, 0xce, 0x24, 0x9b, 0x3e, 0xf9,
+    0x7d, 0x2f, 0xe3, 0x63, 0x63, 0x0c, 0x75, 0xd8, 0xf6, 0x81, 0xb2, 0x02,
+    0xae, 0xc4, 0x61, 0x7a, 0xd3, 0xdf, 0x1e, 0xd5, 0xd5, 0xfd, 0x65, 0x61,
+    0x24, 0x33, 0xf5, 0x1f, 0x5f, 0x06, 0x6e, 0xd0, 0x85, 0x63, 0x65, 0x55,
+    0x3d, 0xed, 0x1a, 0xf3, 0xb5, 0x57, 0x13, 0x5e, 0x7f, 0x57, 0xc9, 0x35,
+    0x98, 0x4f, 0x0c, 0x70, 0xe0, 0xe6, 0x8b, 0x77, 0xe2, 0xa6, 0x89, 0xda,
+    0xf3, 0xef, 0xe8, 0x72, 0x1d, 0xf1, 0x58, 0xa1, 0x36, 0xad, 0xe7, 0x35,
+    0x30, 0xac, 0xca, 0x4f, 0x48, 0x3a, 0x79, 0x7a, 0xbc, 0x0a, 0xb1, 0x82,
+    0xb3, 0x24, 0xfb, 0x61, 0xd1, 0x08, 0xa9, 0x4b, 0xb2, 0xc8, 0xe3, 0xfb,
+    0xb9, 0x6a, 0xda, 0xb7, 0x60, 0xd7, 0xf4, 0x68, 0x1d, 0x4f, 0x42, 0xa3,
+    0xde, 0x39, 0x4d, 0xf4, 0xae, 0x56, 0xed, 0xe7, 0x63, 0x72, 0xbb, 0x19,
+    0x0b, 0x07, 0xa7, 0xc8, 0xee, 0x0a, 0x6d, 0x70, 0x9e, 0x02, 0xfc, 0xe1,
+    0xcd, 0xf7, 0xe2, 0xec, 0xc0, 0x34, 0x04, 0xcd, 0x28, 0x34, 0x2f, 0x61,
+    0x91, 0x72, 0xfe, 0x9c, 0xe9, 0x85, 0x83, 0xff, 0x8e, 0x4f, 0x12, 0x32,
+    0xee, 0xf2, 0x81, 0x83, 0xc3, 0xfe, 0x3b, 0x1b, 0x4c, 0x6f, 0xad, 0x73,
+    0x3b, 0xb5, 0xfc, 0xbc, 0x2e, 0xc2, 0x20, 0x05, 0xc5, 0x8e, 0xf1, 0x83,
+    0x7d, 0x16, 0x83, 0xb2, 0xc6, 0xf3, 0x4a, 0x26, 0xc1, 0xb2, 0xef, 0xfa,
+    0x88, 0x6b, 0x42, 0x38, 0x61, 0x1f, 0xcf, 0xdc, 0xde, 0x35, 0x5b, 0x3b,
+    0x65, 0x19, 0x03, 0x5b, 0xbc, 0x34, 0xf4, 0xde, 0xf9, 0x9c, 0x02, 0x38,
+    0x61, 0xb4, 0x6f, 0xc9, 0xd6, 0xe6, 0xc9, 0x07, 0x7a, 0xd9, 0x1d, 0x26,
+    0x91, 0xf7, 0xf7, 0xee, 0x59, 0x8c, 0xb0, 0xfa, 0xc1, 0x86, 0xd9, 0x1c,
+    0xae, 0xfe, 0x13, 0x09, 0x85, 0x13, 0x92, 0x70, 0xb4, 0x13, 0x0c, 0x93,
+    0xbc, 0x43, 0x79, 0x44, 0xf4, 0xfd, 0x44, 0x52, 0xe2, 0xd7, 0x4d, 0xd3,
+    0x64, 0xf2, 0xe2, 0x1e, 0x71, 0xf5, 0x4b, 0xff, 0x5c, 0xae, 0x82, 0xab,
+    0x9c, 0x9d, 0xf6, 0x9e, 0xe8, 0x6d, 0x2b, 0xc5, 0x22, 0x36, 0x3a, 0x0d,
+    0xab, 0xc5, 0x21, 0x97, 0x9b, 0x0d, 0xea, 0xda, 0x1d, 0xbf, 0x9a, 0x42,
+    0xd5, 0xc4, 0x48, 0x4e, 0x0a, 0xbc, 0xd0, 0x6b, 0xfa, 0x53, 0xdd, 0xef,
+    0x3c, 0x1b, 0x20, 0xee, 0x3f, 0xd5, 0x9d, 0x7c, 0x25, 0xe4, 0x1d, 0x2b,
+    0x66, 0xc6, 0x2e, 0x37, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+];
+
+static FFDHE4096_P: [u8; 4096 / 8] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xad, 0xf8, 0x54, 0x58,
+    0xa2, 0xbb, 0x4a, 0x9a, 0xaf, 0xdc, 0x56, 0x20, 0x27, 0x3d, 0x3c, 0xf1,
+    0xd8, 0xb9, 0xc5, 0x83, 0xce, 0x2d, 0x36, 0x95, 0xa9, 0xe1, 0x36, 0x41,
+    0x14, 0x64, 0x33, 0xfb, 0xcc, 0x93, 0x9d, 0xce, 0x24, 0x9b, 0x3e, 0xf9,
+    0x7d, 0x2f, 0xe3, 0x63, 0x63, 0x0c, 0x75, 0xd8, 0xf6, 0x81, 0xb2, 0x02,
+    0xae, 0xc4, 0x61, 0x7a, 0xd3, 0xdf, 0x1e, 0xd5, 0xd5, 0xfd, 0x65, 0x61,
+    0x24, 0x33, 0xf5, 0x1f, 0x5f, 0x06, 0x6e, 0xd0, 0x85, 0x63, 0x65, 0x55,
+    0x3d, 0xed, 0x1a, 0xf3, 0xb5, 0x57, 0x13, 0x5e, 0x7f, 0x57, 0xc9, 0x35,
+    0x98, 0x4f, 0x0c, 0x70, 0xe0, 0xe6, 0x8b, 0x77, 0xe2, 0xa6, 0x89, 0xda,
+    0xf3, 0xef, 0xe8, 0x72, 0x1d, 0xf1, 0x58, 0xa1, 0x36, 0xad, 0xe7, 0x35,
+    0x30, 0xac, 0xca, 0x4f, 0x48, 0x3a, 0x79, 0x7a, 0xbc, 0x0a, 0xb1, 0x82,
+    0xb3, 0x24, 0xfb, 0x61, 0xd1, 0x08, 0xa9, 0x4b, 0xb2, 0xc8, 0xe3, 0xfb,
+    0xb9, 0x6a, 0xda, 0xb7, 0x60, 0xd7, 0xf4, 0x68, 0x1d, 0x4f, 0x42, 0xa3,
+    0xde, 0x39, 0x4d, 0xf4, 0xae, 0x56, 0xed, 0xe7, 0x63, 0x72, 0xbb, 0x19,
+    0x0b, 0x07, 0xa7, 0xc8, 0xee, 0x0a, 0x6d, 0x70, 0x9e, 0x02, 0xfc, 0xe1,
+    0xcd, 0xf7, 0xe2, 0xec, 0xc0, 0x34, 0x04, 0xcd, 0x28, 0x34, 0x2f, 0x61,
+    0x91, 0x72, 0xfe, 0x9c, 0xe9, 0x85, 0x83, 0xff, 0x8e, 0x4f, 0x12, 0x32,
+    0xee, 0xf2, 0x81, 0x83, 0xc3, 0xfe, 0x3b, 0x1b, 0x4c, 0x6f, 0xad, 0x73,
+    0x3b, 0xb5, 0xfc, 0xbc, 0x2e, 0xc2, 0x20, 0x05, 0xc5, 0x8e, 0xf1, 0x83,
+    0x7d, 0x16, 0x83, 0xb2, 0xc6, 0xf3, 0x4a, 0x26, 0xc1, 0xb2, 0xef, 0xfa,
+    0x88, 0x6b, 0x42, 0x38, 0x61, 0x1f, 0xcf, 0xdc, 0xde, 0x35, 0x5b, 0x3b,
+    0x65, 0x19, 0x03, 0x5b, 0xbc, 0x34, 0xf4, 0xde, 0xf9, 0x9c, 0x02, 0x38,
+    0x61, 0xb4, 0x6f, 0xc9, 0xd6, 0xe6, 0xc9, 0x07, 0x7a, 0xd9, 0x1d, 0x26,
+    0x91, 0xf7, 0xf7, 0xee, 0x59, 0x8c, 0xb0, 0xfa, 0xc1, 0x86, 0xd9, 0x1c,
+    0xae, 0xfe, 0x13, 0x09, 0x85, 0x13, 0x92, 0x70, 0xb4, 0x13, 0x0c, 0x93,
+    0xbc, 0x43, 0x79, 0x44, 0xf4, 0xfd, 0x44, 0x52, 0xe2, 0xd7, 0x4d, 0xd3,
+    0x64, 0xf2, 0xe2, 0x1e, 0x71, 0xf5, 0x4b, 0xff, 0x5c, 0xae, 0x82, 0xab,
+    0x9c, 0x9d, 0xf6, 0x9e, 0xe8, 0x6d, 0x2b, 0xc5, 0x22, 0x36, 0x3a, 0x0d,
+    0xab, 0xc5, 0x21, 0x97, 0x9b, 0x0d, 0xea, 0xda, 0x1d, 0xbf, 0x9a, 0x42,
+    0xd5, 0xc4, 0x48, 0x4e, 0x0a, 0xbc, 0xd0, 0x6b, 0xfa, 0x53, 0xdd, 0xef,
+    0x3c, 0x1b, 0x20, 0xee, 0x3f, 0xd5, 0x9d, 0x7c, 0x25, 0xe4, 0x1d, 0x2b,
+    0x66, 0x9e, 0x1e, 0xf1, 0x6e, 0x6f, 0x52, 0xc3, 0x16, 0x4d, 0xf4, 0xfb,
+    0x79, 0x30, 0xe9, 0xe4, 0xe5, 0x88, 0x57, 0xb6, 0xac, 0x7d, 0x5f, 0x42,
+    0xd6, 0x9f, 0x6d, 0x18, 0x77, 0x63, 0xcf, 0x1d, 0x55, 0x03, 0x40, 0x04,
+    0x87, 0xf5, 0x5b, 0xa5, 0x7e, 0x31, 0xcc, 0x7a, 0x71, 0x35, 0xc8, 0x86,
+    0xef, 0xb4, 0x31, 0x8a, 0xed, 0x6a, 0x1e, 0x01, 0x2d, 0x9e, 0x68, 0x32,
+    0xa9, 0x07, 0x60, 0x0a, 0x91, 0x81, 0x30, 0xc4, 0x6d, 0xc7, 0x78, 0xf9,
+    0x71, 0xad, 0x00, 0x38, 0x09, 0x29, 0x99, 0xa3, 0x33, 0xcb, 0x8b, 0x7a,
+    0x1a, 0x1d, 0xb9, 0x3d, 0x71, 0x40, 0x00, 0x3c, 0x2a, 0x4e, 0xce, 0xa9,
+    0xf9, 0x8d, 0x0a, 0xcc, 0x0a, 0x82, 0x91, 0xcd, 0xce, 0xc9, 0x7d, 0xcf,
+    0x8e, 0xc9, 0xb5, 0x5a, 0x7f, 0x88, 0xa4, 0x6b, 0x4d, 0xb5, 0xa8, 0x51,
+    0xf4, 0x41, 0x82, 0xe1, 0xc6, 0x8a, 0x00, 0x7e, 0x5e, 0x65, 0x5f, 0x6a,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+];
+
+#[cfg(test)]
+mod tests {
+    use {agreement, rand, test};
+    use untrusted;
+
+    #[test]
+    fn test_ffdhe_agree_ephemeral() {
+        let rng = rand::SystemRandom::new();
+
+        test::from_file("src/ffdhe_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+
+            let group_name = test_case.consume_string("Group");
+            let alg = alg_from_group_name(&group_name);
+            let peer_public = test_case.consume_bytes("PeerQ");
+            let peer_public = untrusted::Input::from(&peer_public);
+
+            match test_case.consume_optional_string("Error") {
+                None => {
+                    let my_private = test_case.consume_bytes("D");
+                    let my_public = test_case.consume_bytes("MyQ");
+                    let output = test_case.consume_bytes("Output");
+
+                    let private_key =
+                        agreement::EphemeralPrivateKey::from_seed(alg,
+                                                                  &my_private)
+                            .unwrap();
+
+                    let mut computed_public =
+                        [0u8; agreement::PUBLIC_KEY_MAX_LEN];
+                    let computed_public =
+                        &mut computed_public[..private_key.public_key_len()];
+                    assert!(private_key.compute_public_key(computed_public)
+                                .is_ok());
+                    assert_eq!(computed_public, &my_public[..]);
+
+                    assert!(agreement::agree_ephemeral(private_key, alg,
+                                                       peer_public, (),
+                                                       |key_material| {
+                        assert_eq!(key_material, &output[..]);
+                        Ok(())
+                    }).is_ok());
+                },
+
+                Some(_) => {
+                    let dummy_private_key = try!(
+                        agreement::EphemeralPrivateKey::generate(alg, &rng));
+                    fn kdf_not_called(_: &[u8]) -> Result<(), ()> {
+                        panic!("The KDF was called during FFDHE when the \
+                                peer's public key is invalid.");
+                    }
+                    assert!(agreement::agree_ephemeral(dummy_private_key, alg,
+                                                       peer_public, (),
+                                                       kdf_not_called)
+                                .is_err());
+                }
+            }
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_ffdhe_generated_keys_agree() {
+        let rng = rand::SystemRandom::new();
+        for alg in &[&agreement::FFDHE2048, &agreement::FFDHE3072,
+                     &agreement::FFDHE4096] {
+            let a = agreement::EphemeralPrivateKey::generate(alg, &rng)
+                        .unwrap();
+            let b = agreement::EphemeralPrivateKey::generate(alg, &rng)
+                        .unwrap();
+            let mut a_public = [0u8; agreement::PUBLIC_KEY_MAX_LEN];
+            let a_public = &mut a_public[..a.public_key_len()];
+            a.compute_public_key(a_public).unwrap();
+            let mut b_public = [0u8; agreement::PUBLIC_KEY_MAX_LEN];
+            let b_public = &mut b_public[..b.public_key_len()];
+            b.compute_public_key(b_public).unwrap();
+
+            let ab = agreement::agree_ephemeral(
+                a, alg, untrusted::Input::from(b_public), (),
+                |key_material| Ok(key_material.to_vec())).unwrap();
+            let ba = agreement::agree_ephemeral(
+                b, alg, untrusted::Input::from(a_public), (),
+                |key_material| Ok(key_material.to_vec())).unwrap();
+            assert_eq!(ab, ba);
+        }
+    }
+
+    #[test]
+    fn test_ffdhe_private_key_from_seed_length() {
+        for &(alg, len) in &[(&agreement::FFDHE2048, 29),
+                             (&agreement::FFDHE3072, 35),
+                             (&agreement::FFDHE4096, 41)] {
+            let seed = [1u8; 64];
+            assert!(agreement::EphemeralPrivateKey::from_seed(alg, &seed[..len])
+                        .is_ok());
+            assert!(agreement::EphemeralPrivateKey::from_seed(
+                        alg, &seed[..(len - 1)]).is_err());
+            assert!(agreement::EphemeralPrivateKey::from_seed(
+                        alg, &seed[..(len + 1)]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_ffdhe_algorithm_metadata() {
+        for &(alg, name, public_key_len, shared_secret_len, security_bits) in
+                &[(&agreement::FFDHE2048, "FFDHE2048", 256, 256, 103),
+                  (&agreement::FFDHE3072, "FFDHE3072", 384, 384, 125),
+                  (&agreement::FFDHE4096, "FFDHE4096", 512, 512, 150)] {
+            assert_eq!(alg.name(), name);
+            assert_eq!(alg.public_key_len(), public_key_len);
+            assert_eq!(alg.shared_secret_len(), shared_secret_len);
+            assert_eq!(alg.security_bits(), security_bits);
+            assert_eq!(alg.compressed_public_key_len(), None);
+        }
+    }
+
+    fn alg_from_group_name(group_name: &str) -> &'static agreement::Algorithm {
+        if group_name == "ffdhe2048" {
+            &agreement::FFDHE2048
+        } else if group_name == "ffdhe3072" {
+            &agreement::FFDHE3072
+        } else if group_name == "ffdhe4096" {
+            &agreement::FFDHE4096
+        } else {
+            panic!("Unsupported group: {}", group_name);
+        }
+    }
+}
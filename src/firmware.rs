@@ -0,0 +1,321 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Verification of signed firmware images, for bootloaders and update agents.
+//!
+//! A firmware image is authenticated by a signed manifest that commits to the
+//! image's length and digest:
+//!
+//! ```text
+//! manifest = image_len || image_digest
+//! ```
+//!
+//! where `image_len` is the length of the image in bytes as a 64-bit
+//! big-endian integer and `image_digest` is the digest of the image with the
+//! chosen digest algorithm. The signature is over the whole manifest, using
+//! any `signature::VerificationAlgorithm` (Ed25519, ECDSA, or RSA).
+//!
+//! `verify_manifest` checks the signature and returns an `ImageVerifier`,
+//! which then hashes the image incrementally so that the image never needs to
+//! be in memory all at once. Only a `digest::Context` and the expected digest
+//! are kept, so this is suitable for bootloaders with very little RAM.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate ring;
+//! # extern crate untrusted;
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::{digest, firmware, signature};
+//! use ring::signature::Ed25519KeyPair;
+//!
+//! # let rng = ring::rand::SystemRandom::new();
+//! # let key_pair = try!(Ed25519KeyPair::generate(&rng));
+//! // The image is normally read from a flash slot, a chunk at a time.
+//! let image = [0xa5u8; 1000];
+//!
+//! // Normally the manifest and its signature would be produced by the
+//! // firmware vendor's build system.
+//! # let mut manifest = [0u8; 8 + 32];
+//! # manifest[6] = (image.len() >> 8) as u8;
+//! # manifest[7] = image.len() as u8;
+//! # manifest[8..].copy_from_slice(
+//! #     digest::digest(&digest::SHA256, &image).as_ref());
+//! # let sig = key_pair.sign(&manifest);
+//! # let public_key = key_pair.public_key_bytes();
+//! let verifier = try!(firmware::verify_manifest(
+//!     &signature::ED25519, untrusted::Input::from(public_key),
+//!     &digest::SHA256, untrusted::Input::from(&manifest),
+//!     untrusted::Input::from(sig.as_slice())));
+//!
+//! let mut buf = [0u8; 64];
+//! let mut offset = 0;
+//! try!(verifier.verify_image(&mut buf, |chunk| {
+//!     let n = chunk.len();
+//!     chunk.copy_from_slice(&image[offset..(offset + n)]);
+//!     offset += n;
+//!     Ok(n)
+//! }, |received, total| {
+//!     // Report `received` out of `total` bytes.
+//!     assert!(received <= total);
+//! }));
+//! # Ok(())
+//! # }
+//! ```
+
+use {constant_time, digest, error, polyfill, signature};
+use core;
+use untrusted;
+
+/// Verifies the signature `signature` of `manifest` with `public_key`, and
+/// parses `manifest`. The manifest must contain a digest with the algorithm
+/// `digest_alg`.
+///
+/// On success, returns an `ImageVerifier` for the image the manifest
+/// describes.
+pub fn verify_manifest(signature_alg: &signature::VerificationAlgorithm,
+                       public_key: untrusted::Input,
+                       digest_alg: &'static digest::Algorithm,
+                       manifest: untrusted::Input,
+                       signature: untrusted::Input)
+                       -> Result<ImageVerifier, error::Unspecified> {
+    try!(signature::verify(signature_alg, public_key, manifest, signature));
+
+    manifest.read_all(error::Unspecified, |input| {
+        let mut image_len = 0u64;
+        for _ in 0..8 {
+            image_len = (image_len << 8) | u64::from(try!(input.read_byte()));
+        }
+        let image_digest =
+            try!(input.skip_and_get_input(digest_alg.output_len));
+
+        let mut expected_digest = [0u8; digest::MAX_OUTPUT_LEN];
+        expected_digest[..digest_alg.output_len]
+            .copy_from_slice(image_digest.as_slice_less_safe());
+
+        Ok(ImageVerifier {
+            ctx: digest::Context::new(digest_alg),
+            expected_digest: expected_digest,
+            image_len: image_len,
+            received: 0,
+        })
+    })
+}
+
+/// Incrementally verifies a firmware image against the length and digest in
+/// a manifest whose signature has been verified.
+pub struct ImageVerifier {
+    ctx: digest::Context,
+    expected_digest: [u8; digest::MAX_OUTPUT_LEN],
+    image_len: u64,
+    received: u64,
+}
+
+impl ImageVerifier {
+    /// The length of the image, according to the manifest.
+    #[inline(always)]
+    pub fn image_len(&self) -> u64 { self.image_len }
+
+    /// The number of bytes of the image passed to `update` so far.
+    #[inline(always)]
+    pub fn bytes_received(&self) -> u64 { self.received }
+
+    /// Hashes the next chunk of the image.
+    ///
+    /// Fails, without updating the digest, if the image would become longer
+    /// than the manifest says it is.
+    pub fn update(&mut self, chunk: &[u8]) -> Result<(), error::Unspecified> {
+        if chunk.len() as u64 > self.image_len - self.received {
+            return Err(error::Unspecified);
+        }
+        self.ctx.update(chunk);
+        self.received += chunk.len() as u64;
+        Ok(())
+    }
+
+    /// Verifies that the whole image has been received and that its digest
+    /// matches the manifest. The digests are compared in constant time.
+    pub fn finish(self) -> Result<(), error::Unspecified> {
+        if self.received != self.image_len {
+            return Err(error::Unspecified);
+        }
+        let output_len = self.ctx.algorithm().output_len;
+        let actual = self.ctx.finish();
+        constant_time::verify_slices_are_equal(
+            actual.as_ref(), &self.expected_digest[..output_len])
+    }
+
+    /// Reads the rest of the image with `read`, a chunk of at most
+    /// `buf.len()` bytes at a time, and then verifies it as `finish` does.
+    ///
+    /// `read` must fill the whole slice it is given, which is never longer
+    /// than the rest of the image, and return the number of bytes read;
+    /// returning fewer bytes means the image is truncated, which is an
+    /// error. After each chunk, `progress` is called with the number of bytes
+    /// received so far and the length of the image.
+    ///
+    /// # Panics
+    ///
+    /// `verify_image` panics if `buf` is empty.
+    pub fn verify_image<R, P>(mut self, buf: &mut [u8], mut read: R,
+                              mut progress: P)
+                              -> Result<(), error::Unspecified>
+                              where R: FnMut(&mut [u8])
+                                             -> Result<usize,
+                                                       error::Unspecified>,
+                                    P: FnMut(u64, u64) {
+        assert!(!buf.is_empty());
+        while self.received < self.image_len {
+            let remaining = self.image_len - self.received;
+            let chunk_len =
+                core::cmp::min(buf.len() as u64, remaining) as usize;
+            let chunk = &mut buf[..chunk_len];
+            polyfill::slice::fill(chunk, 0);
+            if try!(read(chunk)) != chunk_len {
+                return Err(error::Unspecified);
+            }
+            try!(self.update(chunk));
+            progress(self.received, self.image_len);
+        }
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {digest, error, firmware, rand, signature};
+    use signature::Ed25519KeyPair;
+    use untrusted;
+
+    const IMAGE_LEN: usize = 1000;
+
+    fn manifest(image: &[u8], image_len: u64) -> [u8; 8 + 32] {
+        let mut manifest = [0u8; 8 + 32];
+        for i in 0..8 {
+            manifest[i] = (image_len >> (56 - 8 * i)) as u8;
+        }
+        manifest[8..].copy_from_slice(
+            digest::digest(&digest::SHA256, image).as_ref());
+        manifest
+    }
+
+    fn verify_manifest(key_pair: &Ed25519KeyPair, manifest: &[u8],
+                       signed: &[u8])
+                       -> Result<firmware::ImageVerifier, error::Unspecified> {
+        let sig = key_pair.sign(signed);
+        firmware::verify_manifest(
+            &signature::ED25519,
+            untrusted::Input::from(key_pair.public_key_bytes()),
+            &digest::SHA256, untrusted::Input::from(manifest),
+            untrusted::Input::from(sig.as_slice()))
+    }
+
+    #[test]
+    fn test_firmware_verify_image() {
+        let key_pair = Ed25519KeyPair::generate(&rand::SystemRandom::new())
+            .unwrap();
+        let mut image = [0u8; IMAGE_LEN];
+        for (i, b) in image.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let manifest = manifest(&image, IMAGE_LEN as u64);
+
+        for &buf_len in [1, 7, 64, IMAGE_LEN, IMAGE_LEN + 1].iter() {
+            let verifier =
+                verify_manifest(&key_pair, &manifest, &manifest).unwrap();
+            let mut buf = vec![0u8; buf_len];
+            let mut offset = 0;
+            let mut calls = 0;
+            let mut last_received = 0;
+            verifier.verify_image(&mut buf, |chunk| {
+                let n = chunk.len();
+                chunk.copy_from_slice(&image[offset..(offset + n)]);
+                offset += n;
+                Ok(n)
+            }, |received, total| {
+                calls += 1;
+                assert!(received > last_received);
+                last_received = received;
+                assert_eq!(total, IMAGE_LEN as u64);
+            }).unwrap();
+            assert_eq!(last_received, IMAGE_LEN as u64);
+            assert_eq!(calls, (IMAGE_LEN + buf_len - 1) / buf_len);
+        }
+
+        // A truncated image.
+        let verifier =
+            verify_manifest(&key_pair, &manifest, &manifest).unwrap();
+        let mut buf = [0u8; 64];
+        assert!(verifier.verify_image(&mut buf, |chunk| Ok(chunk.len() / 2),
+                                      |_, _| {}).is_err());
+    }
+
+    #[test]
+    fn test_firmware_update() {
+        let key_pair = Ed25519KeyPair::generate(&rand::SystemRandom::new())
+            .unwrap();
+        let image = [0x5au8; IMAGE_LEN];
+        let manifest = manifest(&image, IMAGE_LEN as u64);
+
+        // Correct image.
+        let mut verifier =
+            verify_manifest(&key_pair, &manifest, &manifest).unwrap();
+        assert_eq!(verifier.image_len(), IMAGE_LEN as u64);
+        verifier.update(&image[..10]).unwrap();
+        verifier.update(&image[10..]).unwrap();
+        assert_eq!(verifier.bytes_received(), IMAGE_LEN as u64);
+        assert!(verifier.finish().is_ok());
+
+        // Modified image.
+        let mut modified = image;
+        modified[IMAGE_LEN - 1] ^= 1;
+        let mut verifier =
+            verify_manifest(&key_pair, &manifest, &manifest).unwrap();
+        verifier.update(&modified).unwrap();
+        assert!(verifier.finish().is_err());
+
+        // Truncated image.
+        let mut verifier =
+            verify_manifest(&key_pair, &manifest, &manifest).unwrap();
+        verifier.update(&image[1..]).unwrap();
+        assert!(verifier.finish().is_err());
+
+        // Extended image.
+        let mut verifier =
+            verify_manifest(&key_pair, &manifest, &manifest).unwrap();
+        verifier.update(&image).unwrap();
+        assert!(verifier.update(&[0]).is_err());
+        assert!(verifier.finish().is_ok());
+    }
+
+    #[test]
+    fn test_firmware_bad_manifest() {
+        let key_pair = Ed25519KeyPair::generate(&rand::SystemRandom::new())
+            .unwrap();
+        let image = [0u8; IMAGE_LEN];
+        let other = manifest(&image, IMAGE_LEN as u64 + 1);
+        let manifest = manifest(&image, IMAGE_LEN as u64);
+
+        // The signature is over a different manifest.
+        assert!(verify_manifest(&key_pair, &manifest, &other).is_err());
+
+        // Manifests of the wrong length, even when correctly signed.
+        assert!(verify_manifest(&key_pair, &manifest[..39], &manifest[..39])
+                    .is_err());
+        let mut long = [0u8; 41];
+        long[..40].copy_from_slice(&manifest);
+        assert!(verify_manifest(&key_pair, &long, &long).is_err());
+    }
+}
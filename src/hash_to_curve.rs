@@ -0,0 +1,269 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Hashing to elliptic curves, as specified in [RFC 9380].
+//!
+//! `hash_to_curve` implements the random-oracle ("_RO_") encodings of the
+//! suites `P256_XMD:SHA-256_SSWU_RO_`, `P384_XMD:SHA-384_SSWU_RO_`, and
+//! `ristretto255_XMD:SHA-512_R255MAP_RO_`. The computations are constant-time
+//! with respect to the message.
+//!
+//! Every protocol that hashes to a curve must use its own domain separation
+//! tag (DST); see [RFC 9380 Section 3.1]. Tags must be between 1 and 255
+//! bytes long. (The procedure for hashing longer tags in RFC 9380 Section
+//! 5.3.3 isn't implemented.)
+//!
+//! # Example
+//!
+//! ```
+//! use ring::hash_to_curve;
+//!
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! let suite = &hash_to_curve::P256_XMD_SHA256_SSWU_RO;
+//! let mut point = [0u8; 65];
+//! try!(hash_to_curve::hash_to_curve(suite, b"message",
+//!                                   b"MyProtocol-V1-CS01-with-P256",
+//!                                   &mut point));
+//! assert_eq!(point[0], 4);
+//! # Ok(())
+//! # }
+//! # fn main() { example().unwrap() }
+//! ```
+//!
+//! [RFC 9380]: https://www.rfc-editor.org/rfc/rfc9380.html
+//! [RFC 9380 Section 3.1]:
+//!     https://www.rfc-editor.org/rfc/rfc9380.html#section-3.1
+
+use {digest, error, init};
+use ec::ristretto255;
+use ec::suite_b::hash_to_curve as suite_b;
+
+/// A hash-to-curve suite.
+pub struct Suite {
+    output_len: usize,
+    hash_to_curve: fn(msg: &[u8], dst: &[u8], out: &mut [u8])
+                      -> Result<(), error::Unspecified>,
+}
+
+impl Suite {
+    /// The length of the encoded points that `hash_to_curve` outputs.
+    #[inline]
+    pub fn output_len(&self) -> usize { self.output_len }
+}
+
+/// P256_XMD:SHA-256_SSWU_RO_. Points are encoded in the uncompressed form
+/// (0x04 || x || y) of [SEC 1] Section 2.3.3.
+///
+/// [SEC 1]: http://www.secg.org/sec1-v2.pdf
+pub static P256_XMD_SHA256_SSWU_RO: Suite = Suite {
+    output_len: 1 + (2 * 32),
+    hash_to_curve: p256_hash_to_curve,
+};
+
+/// P384_XMD:SHA-384_SSWU_RO_. Points are encoded in the uncompressed form
+/// (0x04 || x || y) of [SEC 1] Section 2.3.3.
+///
+/// [SEC 1]: http://www.secg.org/sec1-v2.pdf
+pub static P384_XMD_SHA384_SSWU_RO: Suite = Suite {
+    output_len: 1 + (2 * 48),
+    hash_to_curve: p384_hash_to_curve,
+};
+
+/// ristretto255_XMD:SHA-512_R255MAP_RO_. Points are encoded as specified in
+/// [RFC 9496].
+///
+/// [RFC 9496]: https://www.rfc-editor.org/rfc/rfc9496.html
+pub static RISTRETTO255_XMD_SHA512_R255MAP_RO: Suite = Suite {
+    output_len: ristretto255::ELEM_LEN,
+    hash_to_curve: ristretto255_hash_to_curve,
+};
+
+/// Hashes `msg` to a point using the domain separation tag `dst`, and writes
+/// the encoded point to `out`, which must be exactly `suite.output_len()`
+/// bytes long.
+pub fn hash_to_curve(suite: &Suite, msg: &[u8], dst: &[u8], out: &mut [u8])
+                     -> Result<(), error::Unspecified> {
+    init::init_once();
+    if out.len() != suite.output_len {
+        return Err(error::Unspecified);
+    }
+    (suite.hash_to_curve)(msg, dst, out)
+}
+
+fn p256_hash_to_curve(msg: &[u8], dst: &[u8], out: &mut [u8])
+                      -> Result<(), error::Unspecified> {
+    suite_b::hash_to_curve(&suite_b::P256_XMD_SHA256_SSWU_RO, msg, dst, out)
+}
+
+fn p384_hash_to_curve(msg: &[u8], dst: &[u8], out: &mut [u8])
+                      -> Result<(), error::Unspecified> {
+    suite_b::hash_to_curve(&suite_b::P384_XMD_SHA384_SSWU_RO, msg, dst, out)
+}
+
+fn ristretto255_hash_to_curve(msg: &[u8], dst: &[u8], out: &mut [u8])
+                              -> Result<(), error::Unspecified> {
+    let mut uniform_bytes = [0u8; ristretto255::UNIFORM_BYTES_LEN];
+    try!(expand_message_xmd(&digest::SHA512, msg, dst, &mut uniform_bytes));
+    let p = ristretto255::Point::from_uniform_bytes(&uniform_bytes);
+    out.copy_from_slice(p.as_bytes());
+    Ok(())
+}
+
+/// Fills `out` with `expand_message_xmd` from [RFC 9380 Section 5.3.1] using
+/// the hash function `digest_alg`.
+///
+/// `out` may be at most 255 digests, and at most 65535 bytes, long. `dst`
+/// must be between 1 and 255 bytes long.
+///
+/// [RFC 9380 Section 5.3.1]:
+///     https://www.rfc-editor.org/rfc/rfc9380.html#section-5.3.1
+pub fn expand_message_xmd(digest_alg: &'static digest::Algorithm, msg: &[u8],
+                          dst: &[u8], out: &mut [u8])
+                          -> Result<(), error::Unspecified> {
+    expand_message_xmd_parts(digest_alg, &[msg], dst, out)
+}
+
+/// Like `expand_message_xmd`, but the message is the concatenation of
+/// `msg_parts`, so that it doesn't have to be assembled in a buffer first.
+pub fn expand_message_xmd_parts(digest_alg: &'static digest::Algorithm,
+                                msg_parts: &[&[u8]], dst: &[u8],
+                                out: &mut [u8])
+                                -> Result<(), error::Unspecified> {
+    let b_len = digest_alg.output_len;
+    let ell = (out.len() + b_len - 1) / b_len;
+    if ell > 255 || out.len() > 0xffff || dst.is_empty() || dst.len() > 255 {
+        return Err(error::Unspecified);
+    }
+    let dst_len = [dst.len() as u8];
+    let l_i_b_str = [(out.len() >> 8) as u8, out.len() as u8];
+
+    let b_0 = {
+        let mut ctx = digest::Context::new(digest_alg);
+        ctx.update(&[0u8; digest::MAX_BLOCK_LEN][..digest_alg.block_len]);
+        for part in msg_parts {
+            ctx.update(part);
+        }
+        ctx.update(&l_i_b_str);
+        ctx.update(&[0]);
+        ctx.update(dst);
+        ctx.update(&dst_len);
+        ctx.finish()
+    };
+
+    let mut b_i = [0u8; digest::MAX_OUTPUT_LEN];
+    for (i, chunk) in out.chunks_mut(b_len).enumerate() {
+        // b_1 = H(b_0 || 1 || DST'); b_i = H((b_0 ^ b_(i-1)) || i || DST').
+        let mut input = [0u8; digest::MAX_OUTPUT_LEN];
+        for (j, x) in input[..b_len].iter_mut().enumerate() {
+            *x = b_0.as_ref()[j] ^ if i == 0 { 0 } else { b_i[j] };
+        }
+        let mut ctx = digest::Context::new(digest_alg);
+        ctx.update(&input[..b_len]);
+        ctx.update(&[(i + 1) as u8]);
+        ctx.update(dst);
+        ctx.update(&dst_len);
+        let digest = ctx.finish();
+        b_i[..b_len].copy_from_slice(digest.as_ref());
+        chunk.copy_from_slice(&b_i[..chunk.len()]);
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {digest, test};
+    use super::*;
+
+    #[test]
+    fn test_hash_to_curve() {
+        test::from_file("src/hash_to_curve_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+
+            let suite = match test_case.consume_string("Suite").as_str() {
+                "P256" => &P256_XMD_SHA256_SSWU_RO,
+                "P384" => &P384_XMD_SHA384_SSWU_RO,
+                "ristretto255" => &RISTRETTO255_XMD_SHA512_R255MAP_RO,
+                s => panic!("Unsupported suite: {}", s),
+            };
+            let dst = test_case.consume_bytes("DST");
+            let msg = test_case.consume_bytes("Msg");
+            let expected = test_case.consume_bytes("P");
+
+            let mut actual = [0u8; 1 + (2 * 48)];
+            let actual = &mut actual[..suite.output_len()];
+            assert!(hash_to_curve(suite, &msg, &dst, actual).is_ok());
+            assert_eq!(&actual[..], &expected[..]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_expand_message_xmd() {
+        test::from_file("src/hash_to_curve_xmd_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+
+            let digest_alg = match test_case.consume_string("Hash").as_str() {
+                "SHA256" => &digest::SHA256,
+                "SHA512" => &digest::SHA512,
+                s => panic!("Unsupported hash: {}", s),
+            };
+            let dst = test_case.consume_bytes("DST");
+            let msg = test_case.consume_bytes("Msg");
+            let expected = test_case.consume_bytes("Output");
+
+            let mut actual = vec![0u8; expected.len()];
+            assert!(expand_message_xmd(digest_alg, &msg, &dst, &mut actual)
+                        .is_ok());
+            assert_eq!(actual, expected);
+
+            let (a, b) = msg.split_at(msg.len() / 2);
+            let mut actual = vec![0u8; expected.len()];
+            assert!(expand_message_xmd_parts(digest_alg, &[a, &[], b], &dst,
+                                             &mut actual).is_ok());
+            assert_eq!(actual, expected);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_expand_message_xmd_limits() {
+        let mut out = [0u8; 32];
+        assert!(expand_message_xmd(&digest::SHA256, b"", b"", &mut out)
+                    .is_err());
+        assert!(expand_message_xmd(&digest::SHA256, b"", &[b'a'; 256],
+                                   &mut out).is_err());
+        assert!(expand_message_xmd(&digest::SHA256, b"", &[b'a'; 255],
+                                   &mut out).is_ok());
+
+        let mut out = [0u8; 255 * 32 + 1];
+        assert!(expand_message_xmd(&digest::SHA256, b"", b"DST", &mut out)
+                    .is_err());
+        assert!(expand_message_xmd(&digest::SHA256, b"", b"DST",
+                                   &mut out[..(255 * 32)]).is_ok());
+    }
+
+    #[test]
+    fn test_hash_to_curve_wrong_output_len() {
+        let mut out = [0u8; 64];
+        assert!(hash_to_curve(&P256_XMD_SHA256_SSWU_RO, b"", b"DST", &mut out)
+                    .is_err());
+        let mut out = [0u8; 66];
+        assert!(hash_to_curve(&P256_XMD_SHA256_SSWU_RO, b"", b"DST", &mut out)
+                    .is_err());
+    }
+}
@@ -0,0 +1,25 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Low-level primitives for implementing protocol-specific constructions.
+//!
+//! The APIs in this module are easy to misuse. They are for implementing
+//! things like Elligator2 encodings and protocol-specific hash-to-field maps
+//! that *ring* doesn't implement itself, without duplicating *ring*'s
+//! constant-time arithmetic. Prefer the higher-level APIs elsewhere in *ring*
+//! whenever they suffice.
+//!
+//! Feature: `hazmat`.
+
+pub use ec::curve25519_field as curve25519;
@@ -18,8 +18,10 @@
 //!
 //! In most situations, it is best to use `extract_and_expand` to do both the
 //! HKDF-Extract and HKDF-Expand as one atomic operation. It is only necessary
-//! to use the separate `expand` and `extract` functions if a single derived
-//! `PRK` (defined in RFC 5869) is used more than once.
+//! to use `extract` and `Prk::expand` separately if a single derived `PRK`
+//! (defined in RFC 5869) is used more than once, e.g. to derive several
+//! labeled keys, or if the PRK comes from elsewhere, e.g. a TLS 1.3 secret,
+//! in which case it is constructed with `Prk::new`.
 //!
 //! Salts have type `hmac::SigningKey` instead of `&[u8]` because they are
 //! frequently used for multiple HKDF operations, and it is more efficient to
@@ -30,7 +32,7 @@
 //! [RFC 5869]: https://tools.ietf.org/html/rfc5869
 
 
-use hmac;
+use {core, digest, error, hmac, kat, secret};
 
 /// Fills `out` with the output of the HKDF Extract-and-Expand operation for
 /// the given inputs.
@@ -42,7 +44,7 @@ use hmac;
 /// # fn foo(salt: &hmac::SigningKey, secret: &[u8], info: &[u8],
 /// #        out: &mut [u8]) {
 /// let prk = hkdf::extract(salt, secret);
-/// hkdf::expand(&prk, info, out)
+/// prk.expand(info, out)
 /// # }
 /// ```
 ///
@@ -50,11 +52,11 @@ use hmac;
 ///
 /// # Panics
 ///
-/// `extract_and_expand` panics if `expand` panics.
+/// `extract_and_expand` panics if `Prk::expand` panics.
 pub fn extract_and_expand(salt: &hmac::SigningKey, secret: &[u8],
                           info: &[u8], out: &mut [u8]) {
     let prk = extract(salt, secret);
-    expand(&prk, info, out)
+    prk.expand(info, out)
 }
 
 /// The HKDF-Extract operation.
@@ -64,7 +66,7 @@ pub fn extract_and_expand(salt: &hmac::SigningKey, secret: &[u8],
 /// | `salt.digest_algorithm()` | Hash
 /// | `secret`                  | IKM (Input Keying Material)
 /// | [return value]            | PRK
-pub fn extract(salt: &hmac::SigningKey, secret: &[u8]) -> hmac::SigningKey {
+pub fn extract(salt: &hmac::SigningKey, secret: &[u8]) -> Prk {
     // The spec says that if no salt is provided then a key of
     // `digest_alg.output_len` bytes of zeros is used. But, HMAC keys are
     // already zero-padded to the block length, which is larger than the output
@@ -72,68 +74,173 @@ pub fn extract(salt: &hmac::SigningKey, secret: &[u8]) -> hmac::SigningKey {
     // `SigningKey` constructor will automatically do the right thing for a
     // zero-length string.
     let prk = hmac::sign(salt, secret);
-    hmac::SigningKey::new(salt.digest_algorithm(), prk.as_ref())
+    Prk::new(salt.digest_algorithm(), prk.as_ref())
 }
 
-/// Fills `out` with the output of the HKDF-Expand operation for the given
-/// inputs.
-///
-/// `prk` should be the return value of an earlier call to `extract`.
-///
-/// | Parameter  | RFC 5869 Term
-/// |------------|--------------
-/// | prk        | PRK
-/// | info       | info
-/// | out        | OKM (Output Keying Material)
-/// | out.len()  | L (Length of output keying material in bytes)
-///
-/// # Panics
-///
-/// `expand` panics if the requested output length is larger than 255 times the
-/// size of the digest algorithm, i.e. if
-/// `out.len() > 255 * salt.digest_algorithm().output_len`. This is the limit
-/// imposed by the HKDF specification, and is necessary to prevent overflow of
-/// the 8-bit iteration counter in the expansion step.
-pub fn expand(prk: &hmac::SigningKey, info: &[u8], out: &mut [u8]) {
-    let digest_alg = prk.digest_algorithm();
-    assert!(out.len() <= 255 * digest_alg.output_len);
-    assert!(digest_alg.block_len >= digest_alg.output_len);
+/// A pseudorandom key (PRK), the output of HKDF-Extract and the input of
+/// HKDF-Expand.
+pub struct Prk(hmac::SigningKey);
 
-    let mut ctx = hmac::SigningContext::with_key(prk);
+impl Prk {
+    /// Constructs a PRK from `value`, a key that is already uniformly random,
+    /// such as a previously-derived HKDF output or a TLS 1.3 traffic secret,
+    /// so that HKDF-Extract can be skipped.
+    ///
+    /// Keys that aren't uniformly random, e.g. Diffie-Hellman shared secrets,
+    /// must be passed through `extract` instead.
+    pub fn new(digest_alg: &'static digest::Algorithm, value: &[u8]) -> Prk {
+        Prk(hmac::SigningKey::new(digest_alg, value))
+    }
 
-    let mut n = 1u8;
-    let mut pos = 0;
-    loop {
-        ctx.update(info);
-        ctx.update(&[n]);
-
-        let t = ctx.sign();
-
-        // Append `t` to the output.
-        let to_copy = if out.len() - pos < digest_alg.output_len {
-            out.len() - pos
-        } else {
-            digest_alg.output_len
-        };
-        let t_bytes = t.as_ref();
-        for i in 0..to_copy {
-            out[pos + i] = t_bytes[i];
+    /// The digest algorithm of the PRK.
+    #[inline(always)]
+    pub fn digest_algorithm(&self) -> &'static digest::Algorithm {
+        self.0.digest_algorithm()
+    }
+
+    /// The PRK as a salt for `extract`, as in the TLS 1.3 key schedule, where
+    /// the output of `Derive-Secret` is the salt of the next HKDF-Extract.
+    #[inline(always)]
+    pub fn as_salt(&self) -> &hmac::SigningKey { &self.0 }
+
+    /// Fills `out` with the output of the HKDF-Expand operation for the given
+    /// inputs.
+    ///
+    /// | Parameter  | RFC 5869 Term
+    /// |------------|--------------
+    /// | self       | PRK
+    /// | info       | info
+    /// | out        | OKM (Output Keying Material)
+    /// | out.len()  | L (Length of output keying material in bytes)
+    ///
+    /// # Panics
+    ///
+    /// `expand` panics if the requested output length is larger than 255
+    /// times the size of the digest algorithm, i.e. if
+    /// `out.len() > 255 * self.digest_algorithm().output_len`. This is the
+    /// limit imposed by the HKDF specification, and is necessary to prevent
+    /// overflow of the 8-bit iteration counter in the expansion step.
+    pub fn expand(&self, info: &[u8], out: &mut [u8]) {
+        assert!(out.len() <= max_output_len(self));
+        fill_okm(self, info, &mut [out]);
+    }
+
+    /// Fills the buffers in `outs`, in order, with the output of a single
+    /// HKDF-Expand operation for the given inputs.
+    ///
+    /// This is equivalent to calling `expand` with a buffer of the combined
+    /// length of the buffers in `outs` and then splitting it up, which is
+    /// what key schedules that derive several keys from one HKDF-Expand
+    /// operation, such as an encryption key followed by a MAC key, need to
+    /// do. To derive keys with different labels, call `expand` once per
+    /// label instead.
+    ///
+    /// Returns `Err(error::Unspecified)`, without writing to any of the
+    /// buffers, if the combined length is larger than 255 times the output
+    /// length of `self.digest_algorithm()`, which is the limit imposed by the
+    /// HKDF specification.
+    pub fn expand_multi(&self, info: &[u8], outs: &mut [&mut [u8]])
+                        -> Result<(), error::Unspecified> {
+        let mut total_len = 0usize;
+        for out in outs.iter() {
+            total_len = try!(total_len.checked_add(out.len())
+                                      .ok_or(error::Unspecified));
         }
-        if to_copy < digest_alg.output_len {
-            break;
+        if total_len > max_output_len(self) {
+            return Err(error::Unspecified);
         }
-        pos += digest_alg.output_len;
+        fill_okm(self, info, outs);
+        Ok(())
+    }
+
+    /// Constructs a `V` from `V::secret_len()` bytes of the output of the
+    /// HKDF-Expand operation for `info`.
+    ///
+    /// Unlike `expand`, this returns `Err(error::Unspecified)` instead of
+    /// panicking if `V::secret_len()` is too large.
+    pub fn expand_value<V>(&self, info: &[u8])
+                           -> Result<V, error::Unspecified>
+                           where V: secret::ValueFromSecret {
+        secret::value_from_secret(|out| {
+            if out.len() > max_output_len(self) {
+                return Err(error::Unspecified);
+            }
+            fill_okm(self, info, &mut [out]);
+            Ok(())
+        })
+    }
+}
+
+// The maximum length of the output of HKDF-Expand using `prk`.
+fn max_output_len(prk: &Prk) -> usize {
+    255 * prk.digest_algorithm().output_len
+}
+
+// Writes the output of HKDF-Expand into the buffers in `outs`, in order. The
+// caller must have checked that the combined length of the buffers is at most
+// `max_output_len(prk)`.
+fn fill_okm(prk: &Prk, info: &[u8], outs: &mut [&mut [u8]]) {
+    let digest_alg = prk.digest_algorithm();
+    assert!(digest_alg.block_len >= digest_alg.output_len);
+
+    let mut ctx = hmac::SigningContext::with_key(&prk.0);
+    ctx.update(info);
+    ctx.update(&[1]);
+    let mut t = ctx.sign();
+    let mut n = 1u8;
+    let mut t_used = 0;
+
+    for out in outs.iter_mut() {
+        let mut out = &mut out[..];
+        while !out.is_empty() {
+            if t_used == digest_alg.output_len {
+                n += 1;
+                let mut ctx = hmac::SigningContext::with_key(&prk.0);
+                ctx.update(t.as_ref());
+                ctx.update(info);
+                ctx.update(&[n]);
+                t = ctx.sign();
+                t_used = 0;
+            }
 
-        ctx = hmac::SigningContext::with_key(prk);
-        ctx.update(t_bytes);
-        n += 1;
+            // Append as much of the remainder of `t` as fits to the output.
+            let to_copy =
+                core::cmp::min(out.len(), digest_alg.output_len - t_used);
+            let (head, tail) = { out }.split_at_mut(to_copy);
+            head.copy_from_slice(&t.as_ref()[t_used..(t_used + to_copy)]);
+            t_used += to_copy;
+            out = tail;
+        }
     }
 }
 
+/// Runs the known-answer test of HKDF with SHA-256, [RFC 5869] Test Case 1;
+/// see `kat`.
+///
+/// [RFC 5869]: https://tools.ietf.org/html/rfc5869#appendix-A.1
+pub fn known_answer_tests() -> [kat::TestResult; 1] {
+    let salt = hmac::SigningKey::new(&digest::SHA256, &[
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b,
+        0x0c,
+    ]);
+    let info = [0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9];
+    let mut okm = [0u8; 42];
+    extract_and_expand(&salt, &[0x0b; 22], &info, &mut okm);
+    [kat::TestResult { name: "HKDF_SHA256", passed: okm == KAT_HKDF_SHA256 }]
+}
+
+const KAT_HKDF_SHA256: [u8; 42] = [
+    0x3c, 0xb2, 0x5f, 0x25, 0xfa, 0xac, 0xd5, 0x7a, 0x90, 0x43, 0x4f, 0x64,
+    0xd0, 0x36, 0x2f, 0x2a, 0x2d, 0x2d, 0x0a, 0x90, 0xcf, 0x1a, 0x5a, 0x4c,
+    0x5d, 0xb0, 0x2d, 0x56, 0xec, 0xc4, 0xc5, 0xbf, 0x34, 0x00, 0x72, 0x08,
+    0xd5, 0xb8, 0x87, 0x18, 0x58, 0x65,
+];
+
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use {error, hmac, test};
+    use {digest, error, hmac, secret, test};
 
     #[test]
     pub fn hkdf_tests() {
@@ -158,7 +265,75 @@ mod tests {
             extract_and_expand(&salt, &secret, &info, &mut out);
             assert_eq!(out, expected_out);
 
+            // Splitting the output into multiple buffers, at and between
+            // block boundaries, gives the same result.
+            let prk = extract(&salt, &secret);
+            let block_len = digest_alg.output_len;
+            for &split in &[0, 1, block_len - 1, block_len, block_len + 1] {
+                if split > expected_out.len() {
+                    continue;
+                }
+                let mut out = vec![0u8; expected_out.len()];
+                {
+                    let (a, rest) = out.split_at_mut(split);
+                    let (b, c) = rest.split_at_mut(rest.len() / 2);
+                    try!(prk.expand_multi(&info, &mut [a, &mut [], b, c]));
+                }
+                assert_eq!(out, expected_out);
+            }
+
+            if expected_out.len() == OKM_LEN {
+                let okm: Okm = try!(prk.expand_value(&info));
+                assert_eq!(&okm.0[..], &expected_out[..]);
+            }
+
             Ok(())
         });
     }
+
+    #[test]
+    pub fn hkdf_max_output_len() {
+        for digest_alg in &[&digest::SHA256, &digest::SHA384] {
+            let prk = Prk::new(digest_alg, b"prk");
+            let max_len = 255 * digest_alg.output_len;
+
+            let mut expected = vec![0u8; max_len];
+            prk.expand(b"info", &mut expected);
+
+            let mut a = vec![0u8; 100];
+            let mut b = vec![0u8; max_len - 100];
+            assert!(prk.expand_multi(b"info", &mut [&mut a, &mut b]).is_ok());
+            assert_eq!(a, &expected[..100]);
+            assert_eq!(b, &expected[100..]);
+
+            // One byte too many is rejected without any output being written.
+            let mut a = vec![0u8; 100];
+            let mut b = vec![0u8; max_len - 100 + 1];
+            assert!(prk.expand_multi(b"info", &mut [&mut a, &mut b])
+                       .is_err());
+            assert!(a.iter().all(|&b| b == 0));
+            assert!(b.iter().all(|&b| b == 0));
+        }
+    }
+
+    const OKM_LEN: usize = 42;
+
+    struct Okm([u8; OKM_LEN]);
+
+    impl secret::ValueFromSecret for Okm {
+        fn secret_len() -> usize { OKM_LEN }
+        fn from_secret(bytes: &[u8]) -> Self {
+            let mut okm = [0u8; OKM_LEN];
+            okm.copy_from_slice(bytes);
+            Okm(okm)
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn hkdf_expand_too_long() {
+        let prk = Prk::new(&digest::SHA256, b"prk");
+        let mut out = vec![0u8; 255 * digest::SHA256.output_len + 1];
+        prk.expand(b"info", &mut out);
+    }
 }
@@ -153,7 +153,8 @@
 //!     https://github.com/briansmith/ring/blob/master/src/hkdf.rs
 
 
-use {constant_time, digest, error, rand};
+use {constant_time, digest, error, kat, rand};
+use secret::Zeroize;
 
 /// A key to use for HMAC signing.
 pub struct SigningKey {
@@ -231,17 +232,46 @@ impl SigningKey {
         key
     }
 
+    /// Construct an HMAC signing key using the given digest algorithm and key
+    /// value, rejecting key values that aren't a recommended length.
+    ///
+    /// `key_value` must be at least `digest_alg.output_len` bytes long, as
+    /// recommended in RFC 2104, and at most `digest_alg.block_len` bytes
+    /// long, so that it is never compressed as described for `new`.
+    pub fn try_new(digest_alg: &'static digest::Algorithm, key_value: &[u8])
+                   -> Result<SigningKey, error::KeyRejected> {
+        if key_value.len() < digest_alg.output_len {
+            return Err(error::KeyRejected::TooShort);
+        }
+        if key_value.len() > digest_alg.block_len {
+            return Err(error::KeyRejected::TooLong);
+        }
+        Ok(SigningKey::new(digest_alg, key_value))
+    }
+
     /// The digest algorithm for the key.
     pub fn digest_algorithm(&self) -> &'static digest::Algorithm {
         self.ctx_prototype.inner.algorithm()
     }
 }
 
+impl Drop for SigningKey {
+    fn drop(&mut self) {
+        self.ctx_prototype.inner.zeroize();
+        self.ctx_prototype.outer.zeroize();
+    }
+}
+
 /// A context for multi-step (Init-Update-Finish) HMAC signing.
 ///
 /// Use `sign` for single-step HMAC signing.
 ///
+/// Cloning a context is cheap. A context that has already been fed data that
+/// is common to many messages, e.g. a header, can be cloned for each message
+/// instead of feeding the common data again.
+///
 /// C analog: `HMAC_CTX`.
+#[derive(Clone)]
 pub struct SigningContext {
     inner: digest::Context,
     outer: digest::Context,
@@ -259,6 +289,18 @@ impl SigningContext {
         }
     }
 
+    /// Resets the context to the state of `SigningContext::with_key(
+    /// signing_key)`, discarding any data passed to `update`, so that the
+    /// context can be reused to sign another message without constructing a
+    /// new one. The key's padded blocks were already digested when the key
+    /// was constructed, so this only copies the key's precomputed state.
+    ///
+    /// C analog: `HMAC_Init_ex`
+    pub fn reset(&mut self, signing_key: &SigningKey) {
+        self.inner = signing_key.ctx_prototype.inner.clone();
+        self.outer = signing_key.ctx_prototype.outer.clone();
+    }
+
     /// Updates the HMAC with all the data in `data`. `update` may be called
     /// zero or more times until `finish` is called.
     ///
@@ -278,6 +320,16 @@ impl SigningContext {
         self.outer.update(self.inner.finish().as_ref());
         self.outer.finish()
     }
+
+    /// Like `sign`, but writes the HMAC value into the beginning of `out` and
+    /// returns its length, which is the digest algorithm's `output_len`,
+    /// instead of returning a `Digest`. Fails with `error::InvalidLength` if
+    /// `out` is too short, in which case `out` is left unmodified.
+    pub fn sign_into(mut self, out: &mut [u8])
+                     -> Result<usize, error::InvalidLength> {
+        self.outer.update(self.inner.finish().as_ref());
+        self.outer.finish_into(out)
+    }
 }
 
 /// Calculates the HMAC of `data` using the key `key` in one step.
@@ -296,6 +348,15 @@ pub fn sign(key: &SigningKey, data: &[u8]) -> digest::Digest {
     ctx.sign()
 }
 
+/// Like `sign`, but writes the HMAC value into `out` as described for
+/// `SigningContext::sign_into`, e.g. directly into the tag field of a packet.
+pub fn sign_into(key: &SigningKey, data: &[u8], out: &mut [u8])
+                 -> Result<usize, error::InvalidLength> {
+    let mut ctx = SigningContext::with_key(key);
+    ctx.update(data);
+    ctx.sign_into(out)
+}
+
 /// A key to use for HMAC authentication.
 pub struct VerificationKey {
     wrapped: SigningKey,
@@ -315,6 +376,15 @@ impl VerificationKey {
                -> VerificationKey {
         VerificationKey { wrapped: SigningKey::new(digest_alg, key_value) }
     }
+
+    /// Construct an HMAC verification key using the given digest algorithm
+    /// and key value, rejecting key values that aren't a recommended length.
+    /// See `SigningKey::try_new`.
+    pub fn try_new(digest_alg: &'static digest::Algorithm, key_value: &[u8])
+                   -> Result<VerificationKey, error::KeyRejected> {
+        let wrapped = try!(SigningKey::try_new(digest_alg, key_value));
+        Ok(VerificationKey { wrapped: wrapped })
+    }
 }
 
 /// Calculates the HMAC of `data` using the key `key`, and verifies whether the
@@ -343,6 +413,49 @@ pub fn verify_with_own_key(key: &SigningKey, data: &[u8], signature: &[u8])
     constant_time::verify_slices_are_equal(sign(key, data).as_ref(), signature)
 }
 
+/// Runs the known-answer tests of HMAC with SHA-256, SHA-384, and SHA-512;
+/// see `kat`.
+pub fn known_answer_tests() -> [kat::TestResult; 3] {
+    fn test(name: &'static str, digest_alg: &'static digest::Algorithm,
+            expected: &[u8]) -> kat::TestResult {
+        let key = SigningKey::new(digest_alg, b"key");
+        kat::TestResult {
+            name: name,
+            passed: verify_with_own_key(
+                &key, b"The quick brown fox jumps over the lazy dog",
+                expected).is_ok(),
+        }
+    }
+    [
+        test("HMAC_SHA256", &digest::SHA256, &KAT_HMAC_SHA256),
+        test("HMAC_SHA384", &digest::SHA384, &KAT_HMAC_SHA384),
+        test("HMAC_SHA512", &digest::SHA512, &KAT_HMAC_SHA512),
+    ]
+}
+
+const KAT_HMAC_SHA256: [u8; 32] = [
+    0xf7, 0xbc, 0x83, 0xf4, 0x30, 0x53, 0x84, 0x24, 0xb1, 0x32, 0x98, 0xe6,
+    0xaa, 0x6f, 0xb1, 0x43, 0xef, 0x4d, 0x59, 0xa1, 0x49, 0x46, 0x17, 0x59,
+    0x97, 0x47, 0x9d, 0xbc, 0x2d, 0x1a, 0x3c, 0xd8,
+];
+
+const KAT_HMAC_SHA384: [u8; 48] = [
+    0xd7, 0xf4, 0x72, 0x7e, 0x2c, 0x0b, 0x39, 0xae, 0x0f, 0x1e, 0x40, 0xcc,
+    0x96, 0xf6, 0x02, 0x42, 0xd5, 0xb7, 0x80, 0x18, 0x41, 0xce, 0xa6, 0xfc,
+    0x59, 0x2c, 0x5d, 0x3e, 0x1a, 0xe5, 0x07, 0x00, 0x58, 0x2a, 0x96, 0xcf,
+    0x35, 0xe1, 0xe5, 0x54, 0x99, 0x5f, 0xe4, 0xe0, 0x33, 0x81, 0xc2, 0x37,
+];
+
+const KAT_HMAC_SHA512: [u8; 64] = [
+    0xb4, 0x2a, 0xf0, 0x90, 0x57, 0xba, 0xc1, 0xe2, 0xd4, 0x17, 0x08, 0xe4,
+    0x8a, 0x90, 0x2e, 0x09, 0xb5, 0xff, 0x7f, 0x12, 0xab, 0x42, 0x8a, 0x4f,
+    0xe8, 0x66, 0x53, 0xc7, 0x3d, 0xd2, 0x48, 0xfb, 0x82, 0xf9, 0x48, 0xa5,
+    0x49, 0xf7, 0xb7, 0x91, 0xa5, 0xb4, 0x19, 0x15, 0xee, 0x4d, 0x1e, 0xc3,
+    0x93, 0x53, 0x57, 0xe4, 0xe2, 0x31, 0x72, 0x50, 0xd0, 0x37, 0x2a, 0xfa,
+    0x2e, 0xbe, 0xeb, 0x3a,
+];
+
+
 #[cfg(test)]
 mod tests {
     use {digest, error, hmac, rand, test};
@@ -366,6 +479,28 @@ mod tests {
         }
     }
 
+    #[test]
+    pub fn hmac_try_new_key_lengths() {
+        let key_value = [0u8; 256];
+        for d in &digest::test_util::ALL_ALGORITHMS {
+            let too_short = &key_value[..(d.output_len - 1)];
+            assert_eq!(hmac::SigningKey::try_new(d, too_short).err(),
+                       Some(error::KeyRejected::TooShort));
+            assert!(hmac::VerificationKey::try_new(d, too_short).is_err());
+
+            let too_long = &key_value[..(d.block_len + 1)];
+            assert_eq!(hmac::SigningKey::try_new(d, too_long).err(),
+                       Some(error::KeyRejected::TooLong));
+            assert!(hmac::VerificationKey::try_new(d, too_long).is_err());
+
+            for len in &[d.output_len, d.block_len] {
+                let key_value = &key_value[..*len];
+                assert!(hmac::SigningKey::try_new(d, key_value).is_ok());
+                assert!(hmac::VerificationKey::try_new(d, key_value).is_ok());
+            }
+        }
+    }
+
     #[test]
     pub fn hmac_tests() {
         test::from_file("src/hmac_tests.txt", |section, test_case| {
@@ -417,6 +552,27 @@ mod tests {
             assert_eq!(is_ok, signature.as_ref() == output);
         }
 
+        // Multi-part API, reusing a context that was fed other data.
+        {
+            let mut s_ctx = hmac::SigningContext::with_key(&s_key);
+            s_ctx.update(b"something else");
+            s_ctx.reset(&s_key);
+            s_ctx.update(input);
+            let signature = s_ctx.clone().sign();
+            assert_eq!(is_ok, signature.as_ref() == output);
+            assert_eq!(is_ok, s_ctx.sign().as_ref() == output);
+        }
+
+        // Writing into a caller-provided buffer.
+        {
+            let mut buf = [0u8; digest::MAX_OUTPUT_LEN + 1];
+            let len = try!(hmac::sign_into(&s_key, input, &mut buf));
+            assert_eq!(len, digest_alg.output_len);
+            assert_eq!(is_ok, &buf[..len] == output);
+            assert!(hmac::sign_into(&s_key, input, &mut buf[..(len - 1)])
+                        .is_err());
+        }
+
         // Multi-part API, byte by byte.
         {
             let mut s_ctx = hmac::SigningContext::with_key(&s_key);
@@ -0,0 +1,287 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Mappings between IANA-registered signature algorithm identifiers and
+//! *ring*'s signature verification algorithms.
+//!
+//! The identifiers are TLS `SignatureScheme` values ([RFC 8446] Section
+//! 4.2.3), COSE `alg` values ([RFC 8152] Section 8), and JOSE `alg` values
+//! ([RFC 7518] Section 3.1 and [RFC 8037]).
+//!
+//! The same kind of signature doesn't always map to the same algorithm: TLS
+//! encodes ECDSA signatures in ASN.1 DER, while COSE and JOSE use the fixed
+//! `r || s` encoding, so `ecdsa_secp256r1_sha256` maps to
+//! `ECDSA_P256_SHA256_ASN1` but `ES256` maps to `ECDSA_P256_SHA256_FIXED`.
+//! Identifiers for algorithms that *ring* doesn't support, and algorithms
+//! that have no identifier in a protocol, are rejected.
+//!
+//! The RSA algorithms are only available with the `use_heap` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::{iana, signature};
+//!
+//! let alg = iana::from_tls_signature_scheme(0x0807).unwrap();
+//! assert_eq!(alg.name(), "ED25519");
+//! assert_eq!(iana::to_jose_alg(alg).unwrap(), "EdDSA");
+//! assert_eq!(iana::to_cose_alg(&signature::ECDSA_P256_SHA256_FIXED),
+//!            Ok(-7));
+//! ```
+//!
+//! [RFC 8446]: https://tools.ietf.org/html/rfc8446#section-4.2.3
+//! [RFC 8152]: https://tools.ietf.org/html/rfc8152#section-8
+//! [RFC 7518]: https://tools.ietf.org/html/rfc7518#section-3.1
+//! [RFC 8037]: https://tools.ietf.org/html/rfc8037#section-3.1
+
+use {error, signature};
+
+/// Returns the verification algorithm for the TLS `SignatureScheme`
+/// `scheme`.
+pub fn from_tls_signature_scheme(scheme: u16)
+        -> Result<&'static signature::VerificationAlgorithm,
+                  error::Unspecified> {
+    find(|alg| alg.tls == Some(scheme)).map(|alg| alg.verification_alg)
+}
+
+/// Returns the TLS `SignatureScheme` for the verification algorithm `alg`.
+pub fn to_tls_signature_scheme(alg: &signature::VerificationAlgorithm)
+                               -> Result<u16, error::Unspecified> {
+    find_by_verification_alg(alg, |alg| alg.tls)
+}
+
+/// Returns the verification algorithm for the COSE `alg` value `alg`.
+pub fn from_cose_alg(alg: i32)
+        -> Result<&'static signature::VerificationAlgorithm,
+                  error::Unspecified> {
+    find(|a| a.cose == Some(alg)).map(|a| a.verification_alg)
+}
+
+/// Returns the COSE `alg` value for the verification algorithm `alg`.
+pub fn to_cose_alg(alg: &signature::VerificationAlgorithm)
+                   -> Result<i32, error::Unspecified> {
+    find_by_verification_alg(alg, |alg| alg.cose)
+}
+
+/// Returns the verification algorithm for the JOSE `alg` value `alg`, e.g.
+/// `"ES256"`. The comparison is case-sensitive.
+pub fn from_jose_alg(alg: &str)
+        -> Result<&'static signature::VerificationAlgorithm,
+                  error::Unspecified> {
+    find(|a| a.jose == Some(alg)).map(|a| a.verification_alg)
+}
+
+/// Returns the JOSE `alg` value for the verification algorithm `alg`.
+pub fn to_jose_alg(alg: &signature::VerificationAlgorithm)
+                   -> Result<&'static str, error::Unspecified> {
+    find_by_verification_alg(alg, |alg| alg.jose)
+}
+
+fn find<F>(f: F) -> Result<&'static IanaAlgorithm, error::Unspecified>
+           where F: Fn(&IanaAlgorithm) -> bool {
+    ECDSA_AND_EDDSA_ALGORITHMS.iter()
+        .chain(rsa_algorithms().iter())
+        .find(|alg| f(alg))
+        .ok_or(error::Unspecified)
+}
+
+// Verification algorithms are identified by their names, which are unique.
+fn find_by_verification_alg<T, F>(alg: &signature::VerificationAlgorithm,
+                                  f: F) -> Result<T, error::Unspecified>
+                                  where F: Fn(&IanaAlgorithm) -> Option<T> {
+    let found = try!(find(|a| a.verification_alg.name() == alg.name()));
+    f(found).ok_or(error::Unspecified)
+}
+
+/// A verification algorithm and its identifiers, if any, in each protocol.
+struct IanaAlgorithm {
+    tls: Option<u16>,
+    cose: Option<i32>,
+    jose: Option<&'static str>,
+    verification_alg: &'static signature::VerificationAlgorithm,
+}
+
+static ECDSA_AND_EDDSA_ALGORITHMS: [IanaAlgorithm; 6] = [
+    // ecdsa_secp256r1_sha256
+    IanaAlgorithm {
+        tls: Some(0x0403),
+        cose: None,
+        jose: None,
+        verification_alg: &signature::ECDSA_P256_SHA256_ASN1,
+    },
+    // ecdsa_secp384r1_sha384
+    IanaAlgorithm {
+        tls: Some(0x0503),
+        cose: None,
+        jose: None,
+        verification_alg: &signature::ECDSA_P384_SHA384_ASN1,
+    },
+    // ecdsa_secp521r1_sha512
+    IanaAlgorithm {
+        tls: Some(0x0603),
+        cose: None,
+        jose: None,
+        verification_alg: &signature::ECDSA_P521_SHA512_ASN1,
+    },
+    IanaAlgorithm {
+        tls: None,
+        cose: Some(-7),
+        jose: Some("ES256"),
+        verification_alg: &signature::ECDSA_P256_SHA256_FIXED,
+    },
+    IanaAlgorithm {
+        tls: None,
+        cose: Some(-35),
+        jose: Some("ES384"),
+        verification_alg: &signature::ECDSA_P384_SHA384_FIXED,
+    },
+    // ed25519
+    IanaAlgorithm {
+        tls: Some(0x0807),
+        cose: Some(-8),
+        jose: Some("EdDSA"),
+        verification_alg: &signature::ED25519,
+    },
+];
+
+fn rsa_algorithms() -> &'static [IanaAlgorithm] {
+    static RSA_ALGORITHMS: [IanaAlgorithm; 6] = [
+        // rsa_pkcs1_sha256
+        IanaAlgorithm {
+            tls: Some(0x0401),
+            cose: Some(-257),
+            jose: Some("RS256"),
+            verification_alg: &signature::RSA_PKCS1_2048_8192_SHA256,
+        },
+        // rsa_pkcs1_sha384
+        IanaAlgorithm {
+            tls: Some(0x0501),
+            cose: Some(-258),
+            jose: Some("RS384"),
+            verification_alg: &signature::RSA_PKCS1_2048_8192_SHA384,
+        },
+        // rsa_pkcs1_sha512
+        IanaAlgorithm {
+            tls: Some(0x0601),
+            cose: Some(-259),
+            jose: Some("RS512"),
+            verification_alg: &signature::RSA_PKCS1_2048_8192_SHA512,
+        },
+        // rsa_pss_rsae_sha256
+        IanaAlgorithm {
+            tls: Some(0x0804),
+            cose: Some(-37),
+            jose: Some("PS256"),
+            verification_alg: &signature::RSA_PSS_2048_8192_SHA256,
+        },
+        // rsa_pss_rsae_sha384
+        IanaAlgorithm {
+            tls: Some(0x0805),
+            cose: Some(-38),
+            jose: Some("PS384"),
+            verification_alg: &signature::RSA_PSS_2048_8192_SHA384,
+        },
+        // rsa_pss_rsae_sha512
+        IanaAlgorithm {
+            tls: Some(0x0806),
+            cose: Some(-39),
+            jose: Some("PS512"),
+            verification_alg: &signature::RSA_PSS_2048_8192_SHA512,
+        },
+    ];
+
+    &RSA_ALGORITHMS
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {error, signature};
+    use super::*;
+
+    #[test]
+    fn test_iana_round_trip() {
+        for alg in ECDSA_AND_EDDSA_ALGORITHMS.iter()
+                .chain(rsa_algorithms().iter()) {
+            let v = alg.verification_alg;
+            match alg.tls {
+                Some(id) => {
+                    assert_eq!(to_tls_signature_scheme(v), Ok(id));
+                    assert_eq!(from_tls_signature_scheme(id).unwrap().name(),
+                               v.name());
+                },
+                None => {
+                    assert!(to_tls_signature_scheme(v).is_err());
+                },
+            }
+            match alg.cose {
+                Some(id) => {
+                    assert_eq!(to_cose_alg(v), Ok(id));
+                    assert_eq!(from_cose_alg(id).unwrap().name(), v.name());
+                },
+                None => { assert!(to_cose_alg(v).is_err()); },
+            }
+            match alg.jose {
+                Some(id) => {
+                    assert_eq!(to_jose_alg(v), Ok(id));
+                    assert_eq!(from_jose_alg(id).unwrap().name(), v.name());
+                },
+                None => { assert!(to_jose_alg(v).is_err()); },
+            }
+        }
+    }
+
+    #[test]
+    fn test_iana_ecdsa_encodings() {
+        assert_eq!(from_tls_signature_scheme(0x0403).unwrap().name(),
+                   "ECDSA_P256_SHA256_ASN1");
+        assert_eq!(from_cose_alg(-7).unwrap().name(),
+                   "ECDSA_P256_SHA256_FIXED");
+        assert_eq!(from_jose_alg("ES384").unwrap().name(),
+                   "ECDSA_P384_SHA384_FIXED");
+    }
+
+    #[test]
+    fn test_iana_unsupported() {
+        // rsa_pkcs1_sha1, ecdsa_sha1, ed448.
+        assert!(from_tls_signature_scheme(0x0201).is_err());
+        assert!(from_tls_signature_scheme(0x0203).is_err());
+        assert!(from_tls_signature_scheme(0x0808).is_err());
+
+        // ES512 isn't supported because there is no fixed-length P-521
+        // verification algorithm.
+        assert!(from_cose_alg(-36).is_err());
+        assert!(from_jose_alg("ES512").is_err());
+        assert!(from_jose_alg("es256").is_err());
+        assert!(from_jose_alg("none").is_err());
+        assert!(from_jose_alg("HS256").is_err());
+
+        assert_eq!(to_tls_signature_scheme(&signature::ECDSA_P256_SHA1_ASN1),
+                   Err(error::Unspecified));
+        assert!(to_cose_alg(&signature::ECDSA_P256_SHA256_ASN1).is_err());
+        assert!(to_jose_alg(&signature::ECDSA_P384_SHA256_ASN1).is_err());
+    }
+
+    #[test]
+    fn test_iana_rsa() {
+        assert_eq!(from_tls_signature_scheme(0x0804).unwrap().name(),
+                   "RSA_PSS_2048_8192_SHA256");
+        assert_eq!(from_cose_alg(-257).unwrap().name(),
+                   "RSA_PKCS1_2048_8192_SHA256");
+        assert_eq!(to_jose_alg(&signature::RSA_PSS_2048_8192_SHA512),
+                   Ok("PS512"));
+        assert!(to_tls_signature_scheme(&signature::RSA_PKCS1_2048_8192_SHA1)
+                    .is_err());
+    }
+}
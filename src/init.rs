@@ -13,17 +13,29 @@
 // CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 
 #[inline(always)]
-#[cfg(not(all(target_arch = "aarch64", target_os = "ios")))]
+#[cfg(all(any(target_arch = "aarch64", target_arch = "arm",
+              target_arch = "x86", target_arch = "x86_64"),
+          not(any(all(target_arch = "aarch64", target_os = "ios"),
+                  all(target_arch = "arm", target_os = "none")))))]
 pub fn init_once() {
     extern crate std;
     static INIT: std::sync::Once = std::sync::ONCE_INIT;
     INIT.call_once(|| unsafe { GFp_cpuid_setup() });
 }
 
-#[cfg(not(all(target_arch = "aarch64", target_os = "ios")))]
+#[cfg(all(any(target_arch = "aarch64", target_arch = "arm",
+              target_arch = "x86", target_arch = "x86_64"),
+          not(any(all(target_arch = "aarch64", target_os = "ios"),
+                  all(target_arch = "arm", target_os = "none")))))]
 extern {
     fn GFp_cpuid_setup();
 }
 
-#[cfg(all(target_arch = "aarch64", target_os = "ios"))]
+// The CPU's capabilities are static on iOS and on Cortex-M (`*-none-eabi*`).
+// The targets without assembly language implementations, e.g. wasm32, don't
+// need to know them.
+#[cfg(any(all(target_arch = "aarch64", target_os = "ios"),
+          all(target_arch = "arm", target_os = "none"),
+          not(any(target_arch = "aarch64", target_arch = "arm",
+                  target_arch = "x86", target_arch = "x86_64"))))]
 pub fn init_once() {}
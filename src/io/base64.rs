@@ -0,0 +1,256 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Incremental decoding of standard, padded base64, as specified in
+//! [RFC 4648 Section 4].
+//!
+//! The line breaks (`"\n"` or `"\r\n"`) of PEM-style input are ignored. Other
+//! whitespace, the URL-safe alphabet, missing padding, and non-canonical
+//! encodings, where the unused bits of the last group aren't zero, are
+//! rejected.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate untrusted;
+//! # extern crate ring;
+//! #
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::io::base64;
+//! use untrusted;
+//!
+//! let mut decoder = base64::Decoder::new();
+//! let mut out = [0u8; 6];
+//! let mut len = 0;
+//! for line in &[&b"Zm9v"[..], b"YmE", b"=\r\n"] {
+//!     let input = untrusted::Input::from(line);
+//!     len += try!(decoder.update(input, &mut out[len..]));
+//! }
+//! try!(decoder.finish());
+//! assert_eq!(&out[..len], b"fooba");
+//! # Ok(())
+//! # }
+//! # fn main() { example().unwrap() }
+//! ```
+//!
+//! [RFC 4648 Section 4]: https://tools.ietf.org/html/rfc4648#section-4
+
+use error;
+use untrusted;
+
+/// An incremental base64 decoder.
+pub struct Decoder {
+    acc: u32,
+    num_digits: usize, // The number of digits and padding characters in `acc`.
+    padding: usize,
+    state: State,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Decoding,
+    Finished, // The final, padded group has been decoded.
+    Failed,
+}
+
+impl Decoder {
+    /// Constructs a new decoder.
+    pub fn new() -> Decoder {
+        Decoder {
+            acc: 0,
+            num_digits: 0,
+            padding: 0,
+            state: State::Decoding,
+        }
+    }
+
+    /// The maximum number of bytes that `update` writes when it is given
+    /// `input_len` bytes of input.
+    #[inline]
+    pub fn max_output_len(&self, input_len: usize) -> usize {
+        (self.num_digits + input_len) / 4 * 3
+    }
+
+    /// Decodes `input`, which may end in the middle of a group of four
+    /// characters, and writes the decoded bytes to the start of `out`.
+    /// Returns the number of bytes written. `out` must be at least
+    /// `self.max_output_len(input.len())` bytes long.
+    ///
+    /// If this fails, e.g. because `input` isn't valid base64, then the
+    /// decoder rejects all further input.
+    pub fn update(&mut self, input: untrusted::Input, out: &mut [u8])
+                  -> Result<usize, error::Unspecified> {
+        let result = self.update_(input, out);
+        if result.is_err() {
+            self.state = State::Failed;
+        }
+        result
+    }
+
+    fn update_(&mut self, input: untrusted::Input, out: &mut [u8])
+               -> Result<usize, error::Unspecified> {
+        if self.state == State::Failed ||
+           out.len() < self.max_output_len(input.len()) {
+            return Err(error::Unspecified);
+        }
+        let mut written = 0;
+        for &c in input.as_slice_less_safe() {
+            if c == b'\r' || c == b'\n' {
+                continue;
+            }
+            if self.state == State::Finished {
+                return Err(error::Unspecified);
+            }
+            if c == b'=' {
+                // Padding may only fill the last one or two positions of a
+                // group.
+                if self.num_digits < 2 {
+                    return Err(error::Unspecified);
+                }
+                self.padding += 1;
+            } else {
+                if self.padding != 0 {
+                    return Err(error::Unspecified);
+                }
+                self.acc = (self.acc << 6) | u32::from(try!(digit(c)));
+            }
+            self.num_digits += 1;
+            if self.num_digits == 4 {
+                written += try!(self.finish_group(&mut out[written..]));
+            }
+        }
+        Ok(written)
+    }
+
+    fn finish_group(&mut self, out: &mut [u8])
+                    -> Result<usize, error::Unspecified> {
+        let acc = self.acc << (6 * self.padding);
+        let bytes = [(acc >> 16) as u8, (acc >> 8) as u8, acc as u8];
+        let len = 3 - self.padding;
+        // Reject non-canonical encodings with nonzero unused bits.
+        if bytes[len..].iter().any(|b| *b != 0) {
+            return Err(error::Unspecified);
+        }
+        out[..len].copy_from_slice(&bytes[..len]);
+        if self.padding != 0 {
+            self.state = State::Finished;
+        }
+        self.acc = 0;
+        self.num_digits = 0;
+        Ok(len)
+    }
+
+    /// Returns `Ok(())` if the input given to `update` was valid base64, i.e.
+    /// it didn't end in the middle of a group, and `Err(error::Unspecified)`
+    /// otherwise.
+    pub fn finish(self) -> Result<(), error::Unspecified> {
+        if self.state == State::Failed || self.num_digits != 0 {
+            return Err(error::Unspecified);
+        }
+        Ok(())
+    }
+}
+
+/// Decodes all of `input` into the start of `out` and returns the number of
+/// bytes written. `out` must be at least `input.len() / 4 * 3` bytes long.
+pub fn decode(input: untrusted::Input, out: &mut [u8])
+              -> Result<usize, error::Unspecified> {
+    let mut decoder = Decoder::new();
+    let len = try!(decoder.update(input, out));
+    try!(decoder.finish());
+    Ok(len)
+}
+
+fn digit(c: u8) -> Result<u8, error::Unspecified> {
+    match c {
+        b'A'...b'Z' => Ok(c - b'A'),
+        b'a'...b'z' => Ok(c - b'a' + 26),
+        b'0'...b'9' => Ok(c - b'0' + 52),
+        b'+' => Ok(62),
+        b'/' => Ok(63),
+        _ => Err(error::Unspecified),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+    use untrusted;
+
+    fn decode_vec(input: &[u8]) -> Result<Vec<u8>, ::error::Unspecified> {
+        let mut out = vec![0u8; input.len() / 4 * 3];
+        let len = try!(decode(untrusted::Input::from(input), &mut out));
+        out.truncate(len);
+        Ok(out)
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode_vec(b"").unwrap(), b"");
+        assert_eq!(decode_vec(b"Zm9vYmFy").unwrap(), b"foobar");
+        assert_eq!(decode_vec(b"Zm9vYg==").unwrap(), b"foob");
+        assert_eq!(decode_vec(b"Zm9vYmE=").unwrap(), b"fooba");
+        assert_eq!(decode_vec(b"+/+/").unwrap(), &[0xfb, 0xff, 0xbf]);
+        assert_eq!(decode_vec(b"Zm9v\r\nYmFy\n").unwrap(), b"foobar");
+        assert_eq!(decode_vec(b"Zm9vYg=\n=\n").unwrap(), b"foob");
+
+        for bad in &[&b"Zm9vYmF"[..], b"Zm9vY===", b"Zm9v=mFy", b"Zm==YmFy",
+                     b"Zm9vYh==", b"Zm9vYmF=", b"Zm9vYm-y", b"Zm9v YmF",
+                     b"Zm9vYm_y", b"Zm9vYg", b"Zm9vYg=", b"Zm9vYg==Zm9v",
+                     b"Zm9vY=g="] {
+            assert!(decode_vec(bad).is_err());
+        }
+    }
+
+    // Every way of splitting the input into two pieces gives the same
+    // result as decoding it all at once.
+    #[test]
+    fn test_decoder_split() {
+        let input = b"SGVsbG8s\nIHdvcmxkIQ==\n";
+        for i in 0..(input.len() + 1) {
+            let mut decoder = Decoder::new();
+            let mut out = [0u8; 18];
+            let (a, b) = input.split_at(i);
+            let len = decoder.update(untrusted::Input::from(a), &mut out)
+                             .unwrap();
+            let len = len + decoder.update(untrusted::Input::from(b),
+                                           &mut out[len..]).unwrap();
+            decoder.finish().unwrap();
+            assert_eq!(&out[..len], b"Hello, world!");
+        }
+    }
+
+    #[test]
+    fn test_decoder_errors() {
+        // `out` is too small.
+        let mut decoder = Decoder::new();
+        let mut out = [0u8; 2];
+        assert!(decoder.update(untrusted::Input::from(b"Zm9v"), &mut out)
+                       .is_err());
+
+        // Once it has failed, the decoder rejects valid input too.
+        let mut out = [0u8; 3];
+        assert!(decoder.update(untrusted::Input::from(b"Zm9v"), &mut out)
+                       .is_err());
+        assert!(decoder.finish().is_err());
+
+        // Input that ends in the middle of a group.
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.update(untrusted::Input::from(b"Zm9vY"), &mut out)
+                          .unwrap(), 3);
+        assert!(decoder.finish().is_err());
+    }
+}
@@ -0,0 +1,361 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Parsing and encoding of the DER-encoded structures that hold public keys,
+//! EC private keys, and ECDSA signatures.
+//!
+//! The parsers are the ones *ring* itself uses: they accept only DER, reject
+//! trailing data, and return the fields as `untrusted::Input`s borrowed from
+//! the input, so they don't allocate. They don't check that the fields make
+//! sense for any particular algorithm; e.g. a `SubjectPublicKeyInfo`'s
+//! algorithm is returned as-is, and `signature::verify` or the key types'
+//! constructors check the key itself.
+//!
+//! The `to_der` methods, which need the `use_heap` feature, encode the
+//! structures back. Parsing the output of `to_der` gives back the same
+//! fields, and encoding the result of parsing DER input gives back that
+//! input.
+
+use {der, error, signed_data};
+use untrusted;
+
+#[cfg(feature = "use_heap")]
+use std::vec::Vec;
+
+/// A `SubjectPublicKeyInfo`, as specified in [RFC 5280 Section 4.1].
+///
+/// [RFC 5280 Section 4.1]: https://tools.ietf.org/html/rfc5280#section-4.1
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SubjectPublicKeyInfo<'a> {
+    algorithm: untrusted::Input<'a>,
+    public_key: untrusted::Input<'a>,
+}
+
+impl<'a> SubjectPublicKeyInfo<'a> {
+    /// Constructs a `SubjectPublicKeyInfo` from the contents of its
+    /// `AlgorithmIdentifier`, e.g. the `id-ecPublicKey` OID followed by the
+    /// `namedCurve` OID, and the public key.
+    pub fn new(algorithm: untrusted::Input<'a>,
+               public_key: untrusted::Input<'a>) -> Self {
+        SubjectPublicKeyInfo { algorithm: algorithm, public_key: public_key }
+    }
+
+    /// Parses a DER-encoded `SubjectPublicKeyInfo`. The `subjectPublicKey`
+    /// BIT STRING must not have any unused bits.
+    pub fn from_der(input: untrusted::Input<'a>)
+                    -> Result<Self, error::Unspecified> {
+        let (algorithm, public_key) = try!(signed_data::parse_spki(input));
+        Ok(Self::new(algorithm, public_key))
+    }
+
+    /// The contents of the `AlgorithmIdentifier`, i.e. the encoded algorithm
+    /// OID followed by the encoded parameters, if any.
+    #[inline]
+    pub fn algorithm(&self) -> untrusted::Input<'a> { self.algorithm }
+
+    /// The public key, i.e. the contents of the `subjectPublicKey` BIT
+    /// STRING without its unused-bits byte.
+    #[inline]
+    pub fn public_key(&self) -> untrusted::Input<'a> { self.public_key }
+
+    /// Encodes the `SubjectPublicKeyInfo` as DER. Feature: `use_heap`.
+    #[cfg(feature = "use_heap")]
+    pub fn to_der(&self) -> Vec<u8> {
+        let mut value = Vec::new();
+        der::write_tlv(der::Tag::Sequence, self.algorithm.as_slice_less_safe(),
+                       &mut value);
+        write_bit_string(self.public_key.as_slice_less_safe(), &mut value);
+        let mut out = Vec::new();
+        der::write_tlv(der::Tag::Sequence, &value, &mut out);
+        out
+    }
+}
+
+/// An `ECPrivateKey`, as specified in [RFC 5915 Section 3].
+///
+/// This is the `privateKey` of an EC PKCS#8 `PrivateKeyInfo`, and the
+/// contents of the `EC PRIVATE KEY` PEM files that `openssl ecparam -genkey`
+/// produces.
+///
+/// [RFC 5915 Section 3]: https://tools.ietf.org/html/rfc5915#section-3
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ECPrivateKey<'a> {
+    private_key: untrusted::Input<'a>,
+    parameters: Option<untrusted::Input<'a>>,
+    public_key: Option<untrusted::Input<'a>>,
+}
+
+impl<'a> ECPrivateKey<'a> {
+    /// Constructs an `ECPrivateKey` from its fields. See the accessors for
+    /// their formats.
+    pub fn new(private_key: untrusted::Input<'a>,
+               parameters: Option<untrusted::Input<'a>>,
+               public_key: Option<untrusted::Input<'a>>) -> Self {
+        ECPrivateKey {
+            private_key: private_key,
+            parameters: parameters,
+            public_key: public_key,
+        }
+    }
+
+    /// Parses a DER-encoded `ECPrivateKey`. The version must be 1 and the
+    /// `publicKey` BIT STRING, if present, must not have any unused bits.
+    pub fn from_der(input: untrusted::Input<'a>)
+                    -> Result<Self, error::Unspecified> {
+        input.read_all(error::Unspecified, |input| {
+            der::nested(input, der::Tag::Sequence, error::Unspecified,
+                        |input| {
+                let version = try!(der::small_nonnegative_integer(input));
+                if version != 1 {
+                    return Err(error::Unspecified);
+                }
+                let private_key =
+                    try!(der::expect_tag_and_get_value(input,
+                                                       der::Tag::OctetString));
+                let parameters =
+                    if input.peek(der::Tag::ContextSpecificConstructed0 as u8) {
+                        Some(try!(der::expect_tag_and_get_value(
+                            input, der::Tag::ContextSpecificConstructed0)))
+                    } else {
+                        None
+                    };
+                let public_key =
+                    if input.peek(der::Tag::ContextSpecificConstructed1 as u8) {
+                        Some(try!(der::nested(
+                            input, der::Tag::ContextSpecificConstructed1,
+                            error::Unspecified,
+                            signed_data::bit_string_with_no_unused_bits)))
+                    } else {
+                        None
+                    };
+                Ok(Self::new(private_key, parameters, public_key))
+            })
+        })
+    }
+
+    /// The private key, i.e. the contents of the `privateKey` OCTET STRING:
+    /// the big-endian encoding of the private scalar, padded to the length of
+    /// the curve's scalars.
+    #[inline]
+    pub fn private_key(&self) -> untrusted::Input<'a> { self.private_key }
+
+    /// The contents of the `[0]`-tagged `parameters`, if present, e.g. the
+    /// encoded `namedCurve` OID.
+    #[inline]
+    pub fn parameters(&self) -> Option<untrusted::Input<'a>> {
+        self.parameters
+    }
+
+    /// The public key, if present, i.e. the contents of the `[1]`-tagged
+    /// `publicKey` BIT STRING without its unused-bits byte.
+    #[inline]
+    pub fn public_key(&self) -> Option<untrusted::Input<'a>> {
+        self.public_key
+    }
+
+    /// Encodes the `ECPrivateKey` as DER. Feature: `use_heap`.
+    #[cfg(feature = "use_heap")]
+    pub fn to_der(&self) -> Vec<u8> {
+        let mut value = Vec::new();
+        der::write_tlv(der::Tag::Integer, &[1], &mut value);
+        der::write_tlv(der::Tag::OctetString,
+                       self.private_key.as_slice_less_safe(), &mut value);
+        if let Some(parameters) = self.parameters {
+            der::write_tlv(der::Tag::ContextSpecificConstructed0,
+                           parameters.as_slice_less_safe(), &mut value);
+        }
+        if let Some(public_key) = self.public_key {
+            let mut bit_string = Vec::new();
+            write_bit_string(public_key.as_slice_less_safe(), &mut bit_string);
+            der::write_tlv(der::Tag::ContextSpecificConstructed1, &bit_string,
+                           &mut value);
+        }
+        let mut out = Vec::new();
+        der::write_tlv(der::Tag::Sequence, &value, &mut out);
+        out
+    }
+}
+
+/// An ECDSA signature encoded as an ASN.1 `Ecdsa-Sig-Value`, as specified in
+/// [RFC 3279 Section 2.2.3].
+///
+/// This is the signature encoding of `signature::ECDSA_P256_SHA256_ASN1` and
+/// friends. Use `signature::ecdsa_asn1_to_fixed` and
+/// `signature::ecdsa_fixed_to_asn1` to convert to and from the fixed-length
+/// encoding when the curve is known.
+///
+/// [RFC 3279 Section 2.2.3]: https://tools.ietf.org/html/rfc3279#section-2.2.3
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ECDSASigValue<'a> {
+    r: untrusted::Input<'a>,
+    s: untrusted::Input<'a>,
+}
+
+impl<'a> ECDSASigValue<'a> {
+    /// Constructs an `ECDSASigValue` from the big-endian encodings of `r` and
+    /// `s`. Leading zeros are removed. This fails if `r` or `s` is zero.
+    pub fn new(r: untrusted::Input<'a>, s: untrusted::Input<'a>)
+               -> Result<Self, error::Unspecified> {
+        let r = try!(strip_leading_zeros(r));
+        let s = try!(strip_leading_zeros(s));
+        Ok(ECDSASigValue { r: r, s: s })
+    }
+
+    /// Parses a DER-encoded `Ecdsa-Sig-Value`. `r` and `s` must be positive.
+    pub fn from_der(input: untrusted::Input<'a>)
+                    -> Result<Self, error::Unspecified> {
+        input.read_all(error::Unspecified, |input| {
+            der::nested(input, der::Tag::Sequence, error::Unspecified,
+                        |input| {
+                let r = try!(der::positive_integer(input));
+                let s = try!(der::positive_integer(input));
+                Ok(ECDSASigValue { r: r, s: s })
+            })
+        })
+    }
+
+    /// The big-endian encoding of `r`, without leading zeros.
+    #[inline]
+    pub fn r(&self) -> untrusted::Input<'a> { self.r }
+
+    /// The big-endian encoding of `s`, without leading zeros.
+    #[inline]
+    pub fn s(&self) -> untrusted::Input<'a> { self.s }
+
+    /// Encodes the `Ecdsa-Sig-Value` as DER. Feature: `use_heap`.
+    #[cfg(feature = "use_heap")]
+    pub fn to_der(&self) -> Vec<u8> {
+        let mut value = Vec::new();
+        der::write_positive_integer(self.r.as_slice_less_safe(), &mut value);
+        der::write_positive_integer(self.s.as_slice_less_safe(), &mut value);
+        let mut out = Vec::new();
+        der::write_tlv(der::Tag::Sequence, &value, &mut out);
+        out
+    }
+}
+
+fn strip_leading_zeros(value: untrusted::Input)
+                       -> Result<untrusted::Input, error::Unspecified> {
+    let value = value.as_slice_less_safe();
+    let first_nonzero =
+        try!(value.iter().position(|b| *b != 0).ok_or(error::Unspecified));
+    Ok(untrusted::Input::from(&value[first_nonzero..]))
+}
+
+// Appends a BIT STRING with no unused bits containing `value` to `out`.
+#[cfg(feature = "use_heap")]
+fn write_bit_string(value: &[u8], out: &mut Vec<u8>) {
+    let mut bit_string = Vec::with_capacity(1 + value.len());
+    bit_string.push(0); // No unused bits.
+    bit_string.extend_from_slice(value);
+    der::write_tlv(der::Tag::BitString, &bit_string, out);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test;
+    use untrusted;
+
+    #[test]
+    fn test_spki() {
+        test::from_file("src/io/der_tests.txt", |section, test_case| {
+            let input = test_case.consume_bytes("Input");
+            let input = untrusted::Input::from(&input);
+            match section {
+                "SubjectPublicKeyInfo" => {
+                    let result = SubjectPublicKeyInfo::from_der(input);
+                    if test_case.consume_optional_string("Error").is_some() {
+                        assert!(result.is_err());
+                        return Ok(());
+                    }
+                    let spki = result.unwrap();
+                    let algorithm = test_case.consume_bytes("Algorithm");
+                    let public_key = test_case.consume_bytes("PublicKey");
+                    assert_eq!(spki.algorithm().as_slice_less_safe(),
+                               &algorithm[..]);
+                    assert_eq!(spki.public_key().as_slice_less_safe(),
+                               &public_key[..]);
+                    check_round_trip(input, &spki.to_der());
+                },
+
+                "ECPrivateKey" => {
+                    let result = ECPrivateKey::from_der(input);
+                    if test_case.consume_optional_string("Error").is_some() {
+                        assert!(result.is_err());
+                        return Ok(());
+                    }
+                    let key = result.unwrap();
+                    let private_key = test_case.consume_bytes("PrivateKey");
+                    assert_eq!(key.private_key().as_slice_less_safe(),
+                               &private_key[..]);
+                    let parameters =
+                        test_case.consume_optional_string("Parameters")
+                            .map(|s| test::from_hex(&s).unwrap());
+                    assert_eq!(key.parameters()
+                                  .map(|p| p.as_slice_less_safe().to_vec()),
+                               parameters);
+                    let public_key =
+                        test_case.consume_optional_string("PublicKey")
+                            .map(|s| test::from_hex(&s).unwrap());
+                    assert_eq!(key.public_key()
+                                  .map(|p| p.as_slice_less_safe().to_vec()),
+                               public_key);
+                    check_round_trip(input, &key.to_der());
+                },
+
+                "ECDSASigValue" => {
+                    let result = ECDSASigValue::from_der(input);
+                    if test_case.consume_optional_string("Error").is_some() {
+                        assert!(result.is_err());
+                        return Ok(());
+                    }
+                    let sig = result.unwrap();
+                    let r = test_case.consume_bytes("R");
+                    let s = test_case.consume_bytes("S");
+                    assert_eq!(sig.r().as_slice_less_safe(), &r[..]);
+                    assert_eq!(sig.s().as_slice_less_safe(), &s[..]);
+                    check_round_trip(input, &sig.to_der());
+                },
+
+                _ => unreachable!("Unexpected section: {}", section),
+            }
+            Ok(())
+        })
+    }
+
+    fn check_round_trip(input: untrusted::Input, encoded: &[u8]) {
+        assert_eq!(encoded, input.as_slice_less_safe());
+    }
+
+    #[test]
+    fn test_ecdsa_sig_value_new() {
+        let r = [0x00, 0x00, 0x80, 0x01];
+        let s = [0x7f];
+        let sig = ECDSASigValue::new(untrusted::Input::from(&r),
+                                     untrusted::Input::from(&s)).unwrap();
+        assert_eq!(sig.r().as_slice_less_safe(), &[0x80, 0x01]);
+        assert_eq!(sig.s().as_slice_less_safe(), &[0x7f]);
+        assert_eq!(&sig.to_der()[..],
+                   &[0x30, 0x08, 0x02, 0x03, 0x00, 0x80, 0x01,
+                     0x02, 0x01, 0x7f][..]);
+
+        let zero = [0x00, 0x00];
+        assert!(ECDSASigValue::new(untrusted::Input::from(&zero),
+                                   untrusted::Input::from(&s)).is_err());
+        assert!(ECDSASigValue::new(untrusted::Input::from(&s),
+                                   untrusted::Input::from(&[])).is_err());
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Incremental decoding of hexadecimal, as specified in
+//! [RFC 4648 Section 8].
+//!
+//! Both uppercase and lowercase digits are accepted. Line breaks (`"\n"` or
+//! `"\r\n"`) are ignored, so hex-encoded keys may be split across lines, but
+//! other whitespace and separators like `":"` are rejected.
+//!
+//! [RFC 4648 Section 8]: https://tools.ietf.org/html/rfc4648#section-8
+
+use error;
+use untrusted;
+
+/// An incremental hex decoder.
+pub struct Decoder {
+    high: Option<u8>, // The high half of a partially-decoded byte.
+    failed: bool,
+}
+
+impl Decoder {
+    /// Constructs a new decoder.
+    pub fn new() -> Decoder { Decoder { high: None, failed: false } }
+
+    /// The maximum number of bytes that `update` writes when it is given
+    /// `input_len` bytes of input.
+    #[inline]
+    pub fn max_output_len(&self, input_len: usize) -> usize {
+        (self.high.map_or(0, |_| 1) + input_len) / 2
+    }
+
+    /// Decodes `input`, which may end between the two digits of a byte, and
+    /// writes the decoded bytes to the start of `out`. Returns the number of
+    /// bytes written. `out` must be at least `self.max_output_len(input.len())`
+    /// bytes long.
+    ///
+    /// If this fails, e.g. because `input` isn't valid hex, then the decoder
+    /// rejects all further input.
+    pub fn update(&mut self, input: untrusted::Input, out: &mut [u8])
+                  -> Result<usize, error::Unspecified> {
+        let result = self.update_(input, out);
+        if result.is_err() {
+            self.failed = true;
+        }
+        result
+    }
+
+    fn update_(&mut self, input: untrusted::Input, out: &mut [u8])
+               -> Result<usize, error::Unspecified> {
+        if self.failed || out.len() < self.max_output_len(input.len()) {
+            return Err(error::Unspecified);
+        }
+        let mut written = 0;
+        for &c in input.as_slice_less_safe() {
+            if c == b'\r' || c == b'\n' {
+                continue;
+            }
+            let d = try!(digit(c));
+            match self.high.take() {
+                None => {
+                    self.high = Some(d);
+                },
+                Some(high) => {
+                    out[written] = (high << 4) | d;
+                    written += 1;
+                },
+            }
+        }
+        Ok(written)
+    }
+
+    /// Returns `Ok(())` if the input given to `update` was valid hex, i.e. it
+    /// didn't end between the two digits of a byte, and
+    /// `Err(error::Unspecified)` otherwise.
+    pub fn finish(self) -> Result<(), error::Unspecified> {
+        if self.failed || self.high.is_some() {
+            return Err(error::Unspecified);
+        }
+        Ok(())
+    }
+}
+
+/// Decodes all of `input` into the start of `out` and returns the number of
+/// bytes written. `out` must be at least `input.len() / 2` bytes long.
+pub fn decode(input: untrusted::Input, out: &mut [u8])
+              -> Result<usize, error::Unspecified> {
+    let mut decoder = Decoder::new();
+    let len = try!(decoder.update(input, out));
+    try!(decoder.finish());
+    Ok(len)
+}
+
+fn digit(c: u8) -> Result<u8, error::Unspecified> {
+    match c {
+        b'0'...b'9' => Ok(c - b'0'),
+        b'a'...b'f' => Ok(c - b'a' + 10),
+        b'A'...b'F' => Ok(c - b'A' + 10),
+        _ => Err(error::Unspecified),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+    use untrusted;
+
+    fn decode_vec(input: &[u8]) -> Result<Vec<u8>, ::error::Unspecified> {
+        let mut out = vec![0u8; input.len() / 2];
+        let len = try!(decode(untrusted::Input::from(input), &mut out));
+        out.truncate(len);
+        Ok(out)
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode_vec(b"").unwrap(), b"");
+        assert_eq!(decode_vec(b"00ff7F80").unwrap(), &[0x00, 0xff, 0x7f, 0x80]);
+        assert_eq!(decode_vec(b"0123456789abcdefABCDEF").unwrap(),
+                   &[0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0xab,
+                     0xcd, 0xef]);
+        assert_eq!(decode_vec(b"de\r\nad\nb\neef\n").unwrap(),
+                   &[0xde, 0xad, 0xbe, 0xef]);
+
+        for bad in &[&b"0"[..], b"abc", b"0g", b"de ad", b"de:ad", b"0x00",
+                     b"\xef\xbc\x90\xef\xbc\x90"] {
+            assert!(decode_vec(bad).is_err());
+        }
+    }
+
+    #[test]
+    fn test_decoder() {
+        let mut decoder = Decoder::new();
+        let mut out = [0u8; 3];
+        assert_eq!(decoder.max_output_len(3), 1);
+        assert_eq!(decoder.update(untrusted::Input::from(b"abc"), &mut out)
+                          .unwrap(), 1);
+        assert_eq!(decoder.max_output_len(3), 2);
+        assert_eq!(decoder.update(untrusted::Input::from(b"def"),
+                                  &mut out[1..]).unwrap(), 2);
+        decoder.finish().unwrap();
+        assert_eq!(out, [0xab, 0xcd, 0xef]);
+
+        // `out` is too small, and afterwards valid input is rejected too.
+        let mut decoder = Decoder::new();
+        assert!(decoder.update(untrusted::Input::from(b"abcdef01"), &mut out)
+                       .is_err());
+        assert!(decoder.update(untrusted::Input::from(b"ab"), &mut out)
+                       .is_err());
+        assert!(decoder.finish().is_err());
+    }
+}
@@ -0,0 +1,28 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Encodings used in key files.
+//!
+//! The decoders in the `base64` and `hex` submodules are incremental: a PEM
+//! file or a hex-encoded key can be decoded piece by piece, e.g. one line at
+//! a time as it is read, into a caller-provided buffer, without any
+//! intermediate allocations. This makes them usable on targets without a
+//! heap.
+//!
+//! The `der` submodule parses and encodes the DER-encoded ASN.1 structures
+//! that hold public keys, EC private keys, and ECDSA signatures.
+
+pub mod base64;
+pub mod der;
+pub mod hex;
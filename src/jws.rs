@@ -0,0 +1,571 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Verification of JSON Web Signatures (JWS) in the compact serialization.
+//!
+//! `verify_compact` verifies a JWS in the compact serialization of [RFC 7515]
+//! Section 7.1, e.g. a signed JSON Web Token (JWT), with one of a set of
+//! keys, and returns its payload. Each `Key` has a fixed algorithm, and the
+//! token's `alg` header parameter has to name both one of the allowed
+//! algorithms and the algorithm of the key that verifies it; the token never
+//! chooses how a key is used, which prevents algorithm confusion attacks. If
+//! the token has a `kid` header parameter, only the keys with that key ID
+//! are tried; otherwise every key with the token's algorithm is tried.
+//!
+//! The algorithms are the signature algorithms with JOSE identifiers in
+//! `iana`. MAC algorithms like `HS256` and the `none` algorithm are rejected.
+//! Tokens with a `crit` header parameter are rejected, since no extensions
+//! are supported; this includes the unencoded payloads of [RFC 7797].
+//!
+//! The payload isn't interpreted. In particular, the claims of a JWT, like
+//! its expiration time, have to be checked by the caller.
+//!
+//! Only available in `use_heap` mode.
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::{jws, signature};
+//!
+//! // The example of RFC 8037 Appendix A.4.
+//! const TOKEN: &'static str =
+//!     "eyJhbGciOiJFZERTQSJ9.RXhhbXBsZSBvZiBFZDI1NTE5IHNpZ25pbmc.hgyY0il_MGCj\
+//!      P0JzlnLWG1PPOt7-09PGcvMg3AIbQR6dWbhijcNR4ki4iylGjg5BhVsPt9g7sVvpAr_MuM\
+//!      0KAg";
+//! const PUBLIC_KEY: [u8; 32] = [
+//!     0xd7, 0x5a, 0x98, 0x01, 0x82, 0xb1, 0x0a, 0xb7, 0xd5, 0x4b, 0xfe, 0xd3,
+//!     0xc9, 0x64, 0x07, 0x3a, 0x0e, 0xe1, 0x72, 0xf3, 0xda, 0xa6, 0x23, 0x25,
+//!     0xaf, 0x02, 0x1a, 0x68, 0xf7, 0x07, 0x51, 0x1a,
+//! ];
+//!
+//! let keys = [jws::Key {
+//!     kid: None,
+//!     public_key: signature::UnparsedPublicKey::new(&signature::ED25519,
+//!                                                   &PUBLIC_KEY[..]),
+//! }];
+//! let payload = jws::verify_compact(TOKEN, &keys, &[&signature::ED25519])
+//!     .unwrap();
+//! assert_eq!(&payload[..], b"Example of Ed25519 signing");
+//!
+//! // The token is rejected if its algorithm isn't allowed, whatever the keys.
+//! let es256 = &signature::ECDSA_P256_SHA256_FIXED;
+//! assert!(jws::verify_compact(TOKEN, &keys, &[es256]).is_err());
+//! ```
+//!
+//! [RFC 7515]: https://tools.ietf.org/html/rfc7515
+//! [RFC 7797]: https://tools.ietf.org/html/rfc7797
+
+use {core, error, iana, signature};
+use std::string::String;
+use std::vec::Vec;
+
+/// A key that can verify tokens.
+#[derive(Clone, Copy)]
+pub struct Key<'a> {
+    /// The key ID, which is compared with the `kid` header parameter of
+    /// tokens that have one.
+    pub kid: Option<&'a str>,
+
+    /// The public key, and the only algorithm that it verifies tokens with.
+    pub public_key: signature::UnparsedPublicKey<&'a [u8]>,
+}
+
+/// Verifies the JWS `token`, in the compact serialization, with one of
+/// `keys`, and returns its decoded payload.
+///
+/// Fails if the token is malformed, if its `alg` header parameter isn't the
+/// JOSE identifier of one of `allowed_algs`, if it has a `crit` header
+/// parameter, or if none of the keys whose algorithm is `alg` and, if the
+/// token has a `kid` header parameter, whose key ID is `kid`, verifies its
+/// signature.
+pub fn verify_compact(
+        token: &str, keys: &[Key],
+        allowed_algs: &[&'static signature::VerificationAlgorithm])
+        -> Result<Vec<u8>, error::Unspecified> {
+    let mut parts = token.splitn(3, '.');
+    let encoded_header = try!(parts.next().ok_or(error::Unspecified));
+    let encoded_payload = try!(parts.next().ok_or(error::Unspecified));
+    let encoded_signature = try!(parts.next().ok_or(error::Unspecified));
+    if encoded_signature.contains('.') {
+        return Err(error::Unspecified);
+    }
+
+    let header = try!(parse_header(&try!(base64url_decode(encoded_header))));
+    let alg = try!(iana::from_jose_alg(&header.alg));
+    if !allowed_algs.iter().any(|allowed| allowed.name() == alg.name()) {
+        return Err(error::Unspecified);
+    }
+
+    // The signing input is the encoded header and payload, with the period
+    // that separates them.
+    let signing_input =
+        &token.as_bytes()[..(encoded_header.len() + 1 + encoded_payload.len())];
+    let signature = try!(base64url_decode(encoded_signature));
+    let verified = keys.iter()
+        .filter(|key| key.public_key.algorithm().name() == alg.name())
+        .filter(|key| match header.kid {
+            Some(ref kid) => key.kid == Some(kid.as_str()),
+            None => true,
+        })
+        .any(|key| key.public_key.verify(signing_input, &signature).is_ok());
+    if !verified {
+        return Err(error::Unspecified);
+    }
+    base64url_decode(encoded_payload)
+}
+
+// The header parameters that `verify_compact` uses.
+struct Header {
+    alg: String,
+    kid: Option<String>,
+}
+
+// Parses the JOSE header, which is a JSON object ([RFC 8259]). Duplicate
+// `alg` and `kid` parameters are rejected; other parameters are ignored.
+//
+// [RFC 8259]: https://tools.ietf.org/html/rfc8259
+fn parse_header(header: &[u8]) -> Result<Header, error::Unspecified> {
+    let header = try!(core::str::from_utf8(header)
+                          .map_err(|_| error::Unspecified));
+    let mut parser = JSONParser { input: header.as_bytes(), pos: 0 };
+    let mut alg = None;
+    let mut kid = None;
+    try!(parser.expect(b'{'));
+    if !parser.eat(b'}') {
+        loop {
+            let name = try!(parser.string());
+            try!(parser.expect(b':'));
+            match name.as_str() {
+                "alg" => try!(parser.unique_string(&mut alg)),
+                "kid" => try!(parser.unique_string(&mut kid)),
+                "crit" => { return Err(error::Unspecified); },
+                _ => try!(parser.skip_value(0)),
+            }
+            if !parser.eat(b',') {
+                try!(parser.expect(b'}'));
+                break;
+            }
+        }
+    }
+    parser.skip_whitespace();
+    if parser.pos != parser.input.len() {
+        return Err(error::Unspecified);
+    }
+    Ok(Header {
+        alg: try!(alg.ok_or(error::Unspecified)),
+        kid: kid,
+    })
+}
+
+struct JSONParser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> JSONParser<'a> {
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.input.len() {
+            match self.input[self.pos] {
+                b' ' | b'\t' | b'\n' | b'\r' => { self.pos += 1; },
+                _ => break,
+            }
+        }
+    }
+
+    // Skips whitespace and then the byte `b`, if it is next.
+    fn eat(&mut self, b: u8) -> bool {
+        self.skip_whitespace();
+        if self.input.get(self.pos) == Some(&b) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, b: u8) -> Result<(), error::Unspecified> {
+        if !self.eat(b) {
+            return Err(error::Unspecified);
+        }
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<u8, error::Unspecified> {
+        let b = try!(self.input.get(self.pos).ok_or(error::Unspecified));
+        self.pos += 1;
+        Ok(*b)
+    }
+
+    fn unique_string(&mut self, value: &mut Option<String>)
+                     -> Result<(), error::Unspecified> {
+        if value.is_some() {
+            return Err(error::Unspecified);
+        }
+        *value = Some(try!(self.string()));
+        Ok(())
+    }
+
+    // Parses a string, decoding its escape sequences.
+    fn string(&mut self) -> Result<String, error::Unspecified> {
+        try!(self.expect(b'"'));
+        let mut value = Vec::new();
+        loop {
+            match try!(self.next()) {
+                b'"' => break,
+                b'\\' => {
+                    let c = match try!(self.next()) {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => try!(self.unicode_escape()),
+                        _ => { return Err(error::Unspecified); },
+                    };
+                    let mut buf = [0u8; 4];
+                    value.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                },
+                b if b < 0x20 => { return Err(error::Unspecified); },
+                b => value.push(b),
+            }
+        }
+        // The input is valid UTF-8 and the escape sequences were decoded to
+        // characters, so `value` is valid UTF-8 too.
+        String::from_utf8(value).map_err(|_| error::Unspecified)
+    }
+
+    // Parses the rest of a `\u` escape sequence, and the low surrogate that
+    // follows it if it is a high surrogate.
+    fn unicode_escape(&mut self) -> Result<char, error::Unspecified> {
+        let high = try!(self.hex4());
+        let code_point = match high {
+            0xd800...0xdbff => {
+                if try!(self.next()) != b'\\' || try!(self.next()) != b'u' {
+                    return Err(error::Unspecified);
+                }
+                let low = try!(self.hex4());
+                if low < 0xdc00 || low > 0xdfff {
+                    return Err(error::Unspecified);
+                }
+                0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+            },
+            _ => high,
+        };
+        core::char::from_u32(code_point).ok_or(error::Unspecified)
+    }
+
+    fn hex4(&mut self) -> Result<u32, error::Unspecified> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = try!((try!(self.next()) as char).to_digit(16)
+                                 .ok_or(error::Unspecified));
+            value = (value << 4) | digit;
+        }
+        Ok(value)
+    }
+
+    // Skips a value of any type. `depth` is the number of arrays and objects
+    // that the value is nested in, which is limited to bound the recursion.
+    fn skip_value(&mut self, depth: usize) -> Result<(), error::Unspecified> {
+        const MAX_DEPTH: usize = 16;
+        self.skip_whitespace();
+        match try!(self.input.get(self.pos).ok_or(error::Unspecified)) {
+            &b'"' => { let _ = try!(self.string()); },
+            &b'{' | &b'[' if depth == MAX_DEPTH => {
+                return Err(error::Unspecified);
+            },
+            &b'{' => {
+                self.pos += 1;
+                if !self.eat(b'}') {
+                    loop {
+                        let _ = try!(self.string());
+                        try!(self.expect(b':'));
+                        try!(self.skip_value(depth + 1));
+                        if !self.eat(b',') {
+                            try!(self.expect(b'}'));
+                            break;
+                        }
+                    }
+                }
+            },
+            &b'[' => {
+                self.pos += 1;
+                if !self.eat(b']') {
+                    loop {
+                        try!(self.skip_value(depth + 1));
+                        if !self.eat(b',') {
+                            try!(self.expect(b']'));
+                            break;
+                        }
+                    }
+                }
+            },
+            &b't' => try!(self.literal(b"true")),
+            &b'f' => try!(self.literal(b"false")),
+            &b'n' => try!(self.literal(b"null")),
+            _ => try!(self.number()),
+        }
+        Ok(())
+    }
+
+    fn literal(&mut self, literal: &[u8]) -> Result<(), error::Unspecified> {
+        if !self.input[self.pos..].starts_with(literal) {
+            return Err(error::Unspecified);
+        }
+        self.pos += literal.len();
+        Ok(())
+    }
+
+    // Skips a number: an optional minus sign, an integer part without leading
+    // zeros, an optional fraction, and an optional exponent.
+    fn number(&mut self) -> Result<(), error::Unspecified> {
+        let _ = self.eat_byte(|b| b == b'-');
+        if !self.eat_byte(|b| b == b'0') {
+            try!(self.digits());
+        }
+        if self.eat_byte(|b| b == b'.') {
+            try!(self.digits());
+        }
+        if self.eat_byte(|b| b == b'e' || b == b'E') {
+            let _ = self.eat_byte(|b| b == b'+' || b == b'-');
+            try!(self.digits());
+        }
+        Ok(())
+    }
+
+    // Skips one or more decimal digits.
+    fn digits(&mut self) -> Result<(), error::Unspecified> {
+        if !self.eat_byte(is_digit) {
+            return Err(error::Unspecified);
+        }
+        while self.eat_byte(is_digit) {}
+        Ok(())
+    }
+
+    // Skips the next byte if it satisfies `f`, without skipping whitespace.
+    fn eat_byte<F>(&mut self, f: F) -> bool where F: Fn(u8) -> bool {
+        match self.input.get(self.pos) {
+            Some(&b) if f(b) => {
+                self.pos += 1;
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+fn is_digit(b: u8) -> bool { b >= b'0' && b <= b'9' }
+
+// Decodes base64url without padding, as JWS requires ([RFC 7515] Section 2).
+// Non-canonical encodings, where the unused bits of the last group aren't
+// zero, are rejected.
+fn base64url_decode(input: &str) -> Result<Vec<u8>, error::Unspecified> {
+    let input = input.as_bytes();
+    if input.len() % 4 == 1 {
+        return Err(error::Unspecified);
+    }
+    let mut out = Vec::with_capacity(input.len() / 4 * 3 + 2);
+    let mut acc = 0u32;
+    let mut num_bits = 0;
+    for &c in input {
+        let digit = match c {
+            b'A'...b'Z' => c - b'A',
+            b'a'...b'z' => c - b'a' + 26,
+            b'0'...b'9' => c - b'0' + 52,
+            b'-' => 62,
+            b'_' => 63,
+            _ => { return Err(error::Unspecified); },
+        };
+        acc = (acc << 6) | u32::from(digit);
+        num_bits += 6;
+        if num_bits >= 8 {
+            num_bits -= 8;
+            out.push((acc >> num_bits) as u8);
+            acc &= (1 << num_bits) - 1;
+        }
+    }
+    if acc != 0 {
+        return Err(error::Unspecified);
+    }
+    Ok(out)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {iana, signature, test};
+    use std::string::String;
+    use std::vec::Vec;
+    use super::*;
+
+    fn base64url_encode(input: &[u8]) -> String {
+        const ALPHABET: &'static [u8; 64] =
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+        let mut out = String::new();
+        for chunk in input.chunks(3) {
+            let mut group = [0u8; 3];
+            group[..chunk.len()].copy_from_slice(chunk);
+            let n = (u32::from(group[0]) << 16) | (u32::from(group[1]) << 8) |
+                    u32::from(group[2]);
+            for i in 0..(chunk.len() + 1) {
+                out.push(ALPHABET[((n >> (18 - 6 * i)) & 63) as usize] as char);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_jws_verify_compact() {
+        test::from_file("src/jws_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let alg = iana::from_jose_alg(&test_case.consume_string("Alg"))
+                .unwrap();
+            let kid = test_case.consume_optional_string("Kid");
+            let public_key = test_case.consume_bytes("PublicKey");
+            let token =
+                String::from_utf8(test_case.consume_bytes("Token")).unwrap();
+            let payload = test_case.consume_bytes("Payload");
+
+            let key = Key {
+                kid: kid.as_ref().map(|kid| kid.as_str()),
+                public_key: signature::UnparsedPublicKey::new(alg,
+                                                              &public_key),
+            };
+            assert_eq!(verify_compact(&token, &[key], &[alg]).unwrap(),
+                       payload);
+
+            // A key of another algorithm, and a key with another key ID, are
+            // skipped.
+            let other_alg = Key {
+                kid: key.kid,
+                public_key: signature::UnparsedPublicKey::new(
+                    &signature::ED25519PH, &public_key),
+            };
+            let other_kid = Key { kid: Some("other"), .. key };
+            let no_kid = Key { kid: None, .. key };
+            assert!(verify_compact(&token, &[other_alg, other_kid, key],
+                                   &[alg]).is_ok());
+            assert_eq!(verify_compact(&token, &[other_kid, no_kid], &[alg])
+                           .is_ok(),
+                       kid.is_none());
+            assert!(verify_compact(&token, &[other_alg], &[alg]).is_err());
+            assert!(verify_compact(&token, &[], &[alg]).is_err());
+
+            // The algorithm has to be allowed.
+            assert!(verify_compact(&token, &[key], &[]).is_err());
+            assert!(verify_compact(&token, &[key], &[&signature::ED25519PH])
+                        .is_err());
+
+            // Changing any part of the token makes it invalid.
+            for i in 0..token.len() {
+                let mut bytes = Vec::from(token.as_bytes());
+                bytes[i] = if bytes[i] == b'A' { b'B' } else { b'A' };
+                let tampered = String::from_utf8(bytes).unwrap();
+                assert!(verify_compact(&tampered, &[key], &[alg]).is_err());
+            }
+            for suffix in &[".", "A", "AA", "=", ".e30"] {
+                let extended = String::from(token.as_str()) + suffix;
+                assert!(verify_compact(&extended, &[key], &[alg]).is_err());
+            }
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_jws_header() {
+        let key_pair = signature::Ed25519KeyPair::from_seed_unchecked(
+            &[7; 32]).unwrap();
+        let keys = [
+            Key {
+                kid: Some("a\"b\u{1f511}"),
+                public_key: signature::UnparsedPublicKey::new(
+                    &signature::ED25519, key_pair.public_key_bytes()),
+            },
+        ];
+        let sign = |header: &str| {
+            let mut token = base64url_encode(header.as_bytes());
+            token.push('.');
+            token.push_str(&base64url_encode(b"payload"));
+            let signature = key_pair.sign(token.as_bytes());
+            token.push('.');
+            token.push_str(&base64url_encode(signature.as_slice()));
+            verify_compact(&token, &keys, &[&signature::ED25519])
+        };
+
+        let valid = [
+            r#"{"alg":"EdDSA"}"#,
+            r#" { "alg" : "EdDSA" } "#,
+            r#"{"typ":"JWT","alg":"EdDSA"}"#,
+            r#"{"alg":"EdDSA","kid":"a\"b🔑"}"#,
+            "{\"alg\":\"EdDSA\",\"kid\":\"a\\\"b\u{1f511}\"}",
+            r#"{"alg":"EdDSA","x":[1,-0.5,2e10,-3E-2,{"y":null}],"z":true}"#,
+            r#"{"alg":"EdDSA","x":[],"y":{},"z":false,"w":"\/\b\f\n\r\t"}"#,
+        ];
+        for header in valid.iter() {
+            assert_eq!(sign(header).unwrap(), b"payload", "{}", header);
+        }
+
+        let invalid = [
+            "",
+            "[]",
+            r#"{}"#,
+            r#"{"alg":"none"}"#,
+            r#"{"alg":"HS256"}"#,
+            r#"{"alg":"eddsa"}"#,
+            r#"{"alg":"EdDSA","crit":["exp"]}"#,
+            r#"{"alg":"EdDSA","alg":"EdDSA"}"#,
+            r#"{"alg":"EdDSA","kid":"other"}"#,
+            r#"{"alg":"EdDSA","kid":"a","kid":"a\"b🔑"}"#,
+            r#"{"alg":"EdDSA"}x"#,
+            r#"{"alg":"EdDSA",}"#,
+            r#"{"alg":"EdDSA""#,
+            r#"{"alg":"EdDSA","x":01}"#,
+            r#"{"alg":"EdDSA","x":1.}"#,
+            r#"{"alg":"EdDSA","x":.5}"#,
+            r#"{"alg":"EdDSA","x":tru}"#,
+            r#"{"alg":"EdDSA","x":[1,]}"#,
+            r#"{"alg":"EdDSA","x":"\x"}"#,
+            r#"{"alg":"EdDSA","x":"\ud83d"}"#,
+            r#"{"alg":"EdDSA","x":"\udd11"}"#,
+            r#"{"alg":"EdDSA","x":"\u12"}"#,
+            "{\"alg\":\"EdDSA\",\"x\":\"\n\"}",
+            r#"{"alg":"EdDSA","x":[[[[[[[[[[[[[[[[[]]]]]]]]]]]]]]]]]}"#,
+        ];
+        for header in invalid.iter() {
+            assert!(sign(header).is_err(), "{}", header);
+        }
+        assert!(sign(r#"{"alg":"EdDSA","x":[[[[[[[[[[[[[[[[]]]]]]]]]]]]]]]]}"#)
+                    .is_ok());
+    }
+
+    #[test]
+    fn test_base64url_decode() {
+        assert_eq!(base64url_decode("").unwrap(), b"");
+        assert_eq!(base64url_decode("Zg").unwrap(), b"f");
+        assert_eq!(base64url_decode("Zm8").unwrap(), b"fo");
+        assert_eq!(base64url_decode("Zm9v").unwrap(), b"foo");
+        assert_eq!(base64url_decode("-_8").unwrap(), &[0xfb, 0xff]);
+        for input in &["Z", "Zh", "Zm9", "Zg==", "+/8", "Zm 9v", "Zm9v\n"] {
+            assert!(base64url_decode(input).is_err(), "{}", input);
+        }
+        for len in 0..10 {
+            let input: Vec<u8> = (0..len).map(|i| (i * 37) as u8).collect();
+            let encoded = base64url_encode(&input);
+            assert_eq!(base64url_decode(&encoded).unwrap(), input);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Known-answer tests (KATs) for release qualification.
+//!
+//! Certification regimes like FIPS 140 require products to run known-answer
+//! tests of their cryptographic algorithms, typically at startup, and to keep
+//! a record of the results. `digest`, `hmac`, `hkdf`, `aead`, `agreement`,
+//! and `signature` each have a `known_answer_tests()` function that runs
+//! KATs of the module's algorithms and returns a `TestResult` for each. The
+//! inputs and expected outputs are compiled into *ring*; they are taken from
+//! the specifications of the algorithms where those have test vectors.
+//!
+//! *ring* doesn't act on failures itself. The application decides whether to
+//! log them, refuse to start, etc.
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::{aead, digest, kat};
+//!
+//! let digest_results = digest::known_answer_tests();
+//! let aead_results = aead::known_answer_tests();
+//! for result in digest_results.iter().chain(aead_results.iter()) {
+//!     println!("{}: {}", result.name,
+//!              if result.passed { "passed" } else { "FAILED" });
+//! }
+//! assert!(kat::all_passed(&digest_results));
+//! assert!(kat::all_passed(&aead_results));
+//! ```
+
+/// The result of a known-answer test.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TestResult {
+    /// The name of the test: the name of the algorithm, followed by the
+    /// operation for algorithms that have more than one test, e.g.
+    /// `"AES_128_GCM open"`.
+    pub name: &'static str,
+
+    /// Whether the algorithm gave the expected result.
+    pub passed: bool,
+}
+
+/// Returns whether all of `results` passed.
+pub fn all_passed(results: &[TestResult]) -> bool {
+    results.iter().all(|result| result.passed)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {aead, agreement, digest, hkdf, hmac, signature};
+    use std::vec::Vec;
+    use super::*;
+
+    #[test]
+    fn test_known_answer_tests() {
+        let mut results = Vec::new();
+        results.extend_from_slice(&digest::known_answer_tests());
+        results.extend_from_slice(&hmac::known_answer_tests());
+        results.extend_from_slice(&hkdf::known_answer_tests());
+        results.extend_from_slice(&aead::known_answer_tests());
+        results.extend_from_slice(&agreement::known_answer_tests());
+        results.extend_from_slice(&signature::known_answer_tests());
+        for (i, result) in results.iter().enumerate() {
+            assert!(result.passed, "{}", result.name);
+            assert!(results[..i].iter().all(|r| r.name != result.name));
+        }
+        assert!(all_passed(&results));
+
+        let failed = TestResult { name: "SHA256", passed: false };
+        assert!(!all_passed(&[results[0], failed]));
+        assert!(all_passed(&[]));
+    }
+}
@@ -0,0 +1,305 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Key derivation in counter mode, as specified in [NIST SP 800-108r1]
+//! Section 4.1, with HMAC or AES-CMAC as the PRF.
+//!
+//! This is the construction that secure-boot ROMs and hardware key ladders
+//! commonly use to derive keys from a hardware unique key (HUK). Wrapping the
+//! HUK in a `Key` and deriving children from it with `Key::derive_key` lets
+//! firmware build its whole key hierarchy with one mechanism, and with no
+//! heap allocation.
+//!
+//! The PRF input for each block of output is
+//! `[i]_32 || Label || 0x00 || Context || [L]_32`, where `i` is the 1-based
+//! block counter and `L` is the output length in bits, both big-endian.
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::kbkdf;
+//!
+//! # fn main() { foo().unwrap() }
+//! # fn foo() -> Result<(), ring::error::Unspecified> {
+//! // Normally the device secret would be read from fuses or a key slot.
+//! let huk = [0x42; 32];
+//! let huk = try!(kbkdf::Key::new(&kbkdf::HMAC_SHA256, &huk));
+//!
+//! let identity = try!(huk.derive_key(b"identity", b""));
+//!
+//! let mut seed = [0u8; 32];
+//! try!(identity.derive(b"attestation-key", b"serial=0123456789",
+//!                      &mut seed));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [NIST SP 800-108r1]:
+//!     https://nvlpubs.nist.gov/nistpubs/SpecialPublications/NIST.SP.800-108r1.pdf
+
+use {cmac, digest, error, hmac, init, polyfill, secret};
+
+/// A PRF algorithm for key derivation.
+pub struct PRF {
+    id: PRFID,
+    key_len: usize,
+}
+
+enum PRFID {
+    Hmac(&'static digest::Algorithm),
+    Cmac(&'static cmac::Algorithm),
+}
+
+/// HMAC-SHA256. The key may have any length.
+pub static HMAC_SHA256: PRF = PRF {
+    id: PRFID::Hmac(&digest::SHA256),
+    key_len: 256 / 8,
+};
+
+/// HMAC-SHA384. The key may have any length.
+pub static HMAC_SHA384: PRF = PRF {
+    id: PRFID::Hmac(&digest::SHA384),
+    key_len: 384 / 8,
+};
+
+/// HMAC-SHA512. The key may have any length.
+pub static HMAC_SHA512: PRF = PRF {
+    id: PRFID::Hmac(&digest::SHA512),
+    key_len: 512 / 8,
+};
+
+/// AES-128-CMAC, as specified in [NIST SP 800-38B] and [RFC 4493]. The key
+/// must be 16 bytes.
+///
+/// [NIST SP 800-38B]:
+///     https://nvlpubs.nist.gov/nistpubs/SpecialPublications/NIST.SP.800-38B.pdf
+/// [RFC 4493]: https://tools.ietf.org/html/rfc4493
+pub static CMAC_AES_128: PRF = PRF {
+    id: PRFID::Cmac(&cmac::AES_128),
+    key_len: 128 / 8,
+};
+
+/// AES-256-CMAC, as specified in [NIST SP 800-38B]. The key must be 32 bytes.
+///
+/// [NIST SP 800-38B]:
+///     https://nvlpubs.nist.gov/nistpubs/SpecialPublications/NIST.SP.800-38B.pdf
+pub static CMAC_AES_256: PRF = PRF {
+    id: PRFID::Cmac(&cmac::AES_256),
+    key_len: 256 / 8,
+};
+
+impl PRF {
+    /// The length of the keys produced by `Key::derive_key`: the digest
+    /// output length for HMAC, and the AES key length for CMAC.
+    #[inline(always)]
+    pub fn key_len(&self) -> usize { self.key_len }
+}
+
+/// A key-derivation key.
+pub struct Key {
+    prf: &'static PRF,
+    inner: KeyInner,
+}
+
+enum KeyInner {
+    Hmac(hmac::SigningKey),
+    Cmac(cmac::Key),
+}
+
+impl Key {
+    /// Constructs a key-derivation key from `key_value`.
+    ///
+    /// For the CMAC PRFs, `key_value.len()` must be `prf.key_len()`.
+    pub fn new(prf: &'static PRF, key_value: &[u8])
+               -> Result<Key, error::Unspecified> {
+        init::init_once();
+
+        let inner = match prf.id {
+            PRFID::Hmac(digest_alg) =>
+                KeyInner::Hmac(hmac::SigningKey::new(digest_alg, key_value)),
+            PRFID::Cmac(cmac_alg) =>
+                KeyInner::Cmac(try!(cmac::Key::new(cmac_alg, key_value))),
+        };
+        Ok(Key { prf: prf, inner: inner })
+    }
+
+    /// The PRF algorithm for the key.
+    #[inline(always)]
+    pub fn prf(&self) -> &'static PRF { self.prf }
+
+    /// Fills `out` with key material derived from `label` and `context`.
+    ///
+    /// `label` identifies the purpose of the derived key and `context`
+    /// identifies the parties or device it is bound to. `label` should not
+    /// contain a zero byte, since that separates it from `context`.
+    ///
+    /// Fails if `out` is empty or if its length in bits doesn't fit in 32
+    /// bits.
+    pub fn derive(&self, label: &[u8], context: &[u8], out: &mut [u8])
+                  -> Result<(), error::Unspecified> {
+        if out.is_empty() || out.len() > (0xffffffff / 8) {
+            return Err(error::Unspecified);
+        }
+        let l = polyfill::slice::be_u8_from_u32((out.len() * 8) as u32);
+
+        let mut i: u32 = 0;
+        for chunk in out.chunks_mut(self.prf_output_len()) {
+            // This can't overflow, since the output length is limited above.
+            i += 1;
+            let parts: [&[u8]; 5] = [
+                &polyfill::slice::be_u8_from_u32(i),
+                label,
+                &[0],
+                context,
+                &l,
+            ];
+            match self.inner {
+                KeyInner::Hmac(ref key) => {
+                    let mut ctx = hmac::SigningContext::with_key(key);
+                    for part in parts.iter() {
+                        ctx.update(part);
+                    }
+                    let block = ctx.sign();
+                    chunk.copy_from_slice(&block.as_ref()[..chunk.len()]);
+                },
+                KeyInner::Cmac(ref key) => {
+                    let mut ctx = cmac::Context::with_key(key);
+                    for part in parts.iter() {
+                        ctx.update(part);
+                    }
+                    let block = ctx.sign();
+                    chunk.copy_from_slice(&block.as_ref()[..chunk.len()]);
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// Derives a child key-derivation key that uses the same PRF, from
+    /// `self.prf().key_len()` bytes of output of `derive(label, context)`.
+    pub fn derive_key(&self, label: &[u8], context: &[u8])
+                      -> Result<Key, error::Unspecified> {
+        let mut key_value = [0u8; MAX_KEY_LEN];
+        let key_value = &mut key_value[..self.prf.key_len];
+        try!(self.derive(label, context, key_value));
+        Key::new(self.prf, key_value)
+    }
+
+    /// Constructs a `V` from `V::secret_len()` bytes of output of
+    /// `derive(label, context)`.
+    pub fn derive_value<V>(&self, label: &[u8], context: &[u8])
+                           -> Result<V, error::Unspecified>
+                           where V: secret::ValueFromSecret {
+        secret::value_from_secret(|out| self.derive(label, context, out))
+    }
+
+    fn prf_output_len(&self) -> usize {
+        match self.prf.id {
+            PRFID::Hmac(digest_alg) => digest_alg.output_len,
+            PRFID::Cmac(_) => cmac::BLOCK_LEN,
+        }
+    }
+}
+
+const MAX_KEY_LEN: usize = digest::MAX_OUTPUT_LEN;
+
+#[cfg(test)]
+mod tests {
+    use {kbkdf, secret, test};
+
+    #[test]
+    fn test_kbkdf() {
+        test::from_file("src/kbkdf_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let prf = match test_case.consume_string("PRF").as_str() {
+                "HMAC_SHA256" => &kbkdf::HMAC_SHA256,
+                "HMAC_SHA384" => &kbkdf::HMAC_SHA384,
+                "HMAC_SHA512" => &kbkdf::HMAC_SHA512,
+                "CMAC_AES_128" => &kbkdf::CMAC_AES_128,
+                "CMAC_AES_256" => &kbkdf::CMAC_AES_256,
+                s => panic!("Unsupported PRF: {}", s),
+            };
+            let ki = test_case.consume_bytes("KI");
+            let label = test_case.consume_bytes("Label");
+            let context = test_case.consume_bytes("Context");
+            let expected = test_case.consume_bytes("KO");
+
+            let key = kbkdf::Key::new(prf, &ki).unwrap();
+            let mut actual = vec![0u8; expected.len()];
+            key.derive(&label, &context, &mut actual).unwrap();
+            assert_eq!(actual, expected);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_kbkdf_key_len() {
+        for &prf in [&kbkdf::CMAC_AES_128, &kbkdf::CMAC_AES_256].iter() {
+            let key = [0u8; 32 + 1];
+            for len in 0..key.len() {
+                assert_eq!(kbkdf::Key::new(prf, &key[..len]).is_ok(),
+                           len == prf.key_len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_kbkdf_output_len() {
+        let key = kbkdf::Key::new(&kbkdf::CMAC_AES_128, &[0u8; 16]).unwrap();
+        assert!(key.derive(b"label", b"", &mut []).is_err());
+    }
+
+    #[test]
+    fn test_kbkdf_derive_key() {
+        for &prf in [&kbkdf::HMAC_SHA256, &kbkdf::HMAC_SHA384,
+                     &kbkdf::HMAC_SHA512, &kbkdf::CMAC_AES_128,
+                     &kbkdf::CMAC_AES_256].iter() {
+            let huk = kbkdf::Key::new(prf, &[0x42u8; 64][..prf.key_len()])
+                .unwrap();
+            let child = huk.derive_key(b"child", b"ctx").unwrap();
+            let mut expected = [0u8; 64];
+            let expected = &mut expected[..prf.key_len()];
+            huk.derive(b"child", b"ctx", expected).unwrap();
+            let expected = kbkdf::Key::new(prf, expected).unwrap();
+
+            let mut a = [0u8; 20];
+            let mut b = [0u8; 20];
+            child.derive(b"leaf", b"", &mut a).unwrap();
+            expected.derive(b"leaf", b"", &mut b).unwrap();
+            assert_eq!(a, b);
+        }
+    }
+
+    #[test]
+    fn test_kbkdf_derive_value() {
+        struct Key20([u8; 20]);
+
+        impl secret::ValueFromSecret for Key20 {
+            fn secret_len() -> usize { 20 }
+            fn from_secret(bytes: &[u8]) -> Self {
+                let mut key = [0u8; 20];
+                key.copy_from_slice(bytes);
+                Key20(key)
+            }
+        }
+
+        let key = kbkdf::Key::new(&kbkdf::HMAC_SHA256, &[0x42u8; 32])
+            .unwrap();
+        let value: Key20 = key.derive_value(b"label", b"ctx").unwrap();
+        let mut expected = [0u8; 20];
+        key.derive(b"label", b"ctx", &mut expected).unwrap();
+        assert_eq!(value.0, expected);
+    }
+}
@@ -0,0 +1,293 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Key confirmation for key-agreement schemes, as specified in
+//! [NIST SP 800-56A Rev. 3] Section 5.9, with HMAC as the MAC algorithm.
+//!
+//! After a key agreement (e.g. with `agreement::agree_ephemeral`), the
+//! derived keying material is split into `MacKey || KeyData`. The party that
+//! provides key confirmation (the *provider*) computes
+//! `MacTag = T_MacTagBits[HMAC(MacKey, MacData)]`, where
+//!
+//! ```text
+//! MacData = message_string || ID_P || ID_R || EphemData_P || EphemData_R
+//!           || Text
+//! ```
+//!
+//! and sends it to the other party (the *recipient*), which computes the same
+//! value and compares it. `ID_P`, `EphemData_P`, `ID_R`, and `EphemData_R`
+//! are the identifiers and ephemeral data (usually the ephemeral public keys)
+//! of the provider and the recipient, taken from the `Transcript`.
+//! `message_string` encodes whether the confirmation is unilateral or
+//! bilateral and which party is the provider.
+//!
+//! `MacKey` should be used only for key confirmation and should be destroyed
+//! once key confirmation is done.
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::{digest, key_confirmation};
+//! use ring::key_confirmation::{Party, Scheme};
+//!
+//! # fn main() { foo().unwrap() }
+//! # fn foo() -> Result<(), ring::error::Unspecified> {
+//! # let (mac_key, ephem_u, ephem_v) = ([1u8; 32], [2u8; 65], [3u8; 65]);
+//! let transcript = key_confirmation::Transcript::new(b"alice", b"bob",
+//!                                                    &ephem_u, &ephem_v);
+//! let mac_key = try!(key_confirmation::MacKey::new(&digest::SHA256,
+//!                                                  &mac_key));
+//!
+//! // Party V (bob) confirms the key to party U (alice).
+//! let mut mac_tag = [0u8; 16];
+//! try!(key_confirmation::sign(&mac_key, Scheme::Unilateral, Party::V,
+//!                             &transcript, b"", &mut mac_tag));
+//!
+//! // Party U checks the `MacTag` it received from party V.
+//! try!(key_confirmation::verify(&mac_key, Scheme::Unilateral, Party::V,
+//!                               &transcript, b"", &mac_tag));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [NIST SP 800-56A Rev. 3]:
+//!     https://nvlpubs.nist.gov/nistpubs/SpecialPublications/NIST.SP.800-56Ar3.pdf
+
+use {constant_time, digest, error, hmac, init};
+
+/// Whether key confirmation is provided by one party or by both.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scheme {
+    /// Unilateral key confirmation (SP 800-56A Section 5.9.1).
+    Unilateral,
+
+    /// Bilateral key confirmation (SP 800-56A Section 5.9.2), where each
+    /// party computes and sends a `MacTag`.
+    Bilateral,
+}
+
+/// The role of a party in a key-agreement scheme.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Party {
+    /// Party U, the party that initiates the key agreement.
+    U,
+
+    /// Party V, the party that responds to the key agreement.
+    V,
+}
+
+/// The identifiers and ephemeral data of both parties to a key agreement.
+///
+/// The ephemeral data of a party is usually its ephemeral public key, encoded
+/// as it was sent to the other party, or its nonce in schemes where it has no
+/// ephemeral key; it is empty if the party contributed no ephemeral data.
+pub struct Transcript<'a> {
+    id_u: &'a [u8],
+    id_v: &'a [u8],
+    ephem_data_u: &'a [u8],
+    ephem_data_v: &'a [u8],
+}
+
+impl<'a> Transcript<'a> {
+    /// Constructs a transcript of a key agreement between party U, identified
+    /// by `id_u`, and party V, identified by `id_v`.
+    pub fn new(id_u: &'a [u8], id_v: &'a [u8], ephem_data_u: &'a [u8],
+               ephem_data_v: &'a [u8]) -> Transcript<'a> {
+        Transcript {
+            id_u: id_u,
+            id_v: id_v,
+            ephem_data_u: ephem_data_u,
+            ephem_data_v: ephem_data_v,
+        }
+    }
+}
+
+/// A key-confirmation key (`MacKey`), taken from the beginning of the derived
+/// keying material.
+pub struct MacKey {
+    key: hmac::SigningKey,
+}
+
+impl MacKey {
+    /// Constructs a key-confirmation key for HMAC with the digest algorithm
+    /// `digest_alg`.
+    ///
+    /// `key_value` must be at least `MIN_MAC_KEY_LEN` bytes long, and should
+    /// be at least as long as the security strength of the key agreement.
+    pub fn new(digest_alg: &'static digest::Algorithm, key_value: &[u8])
+               -> Result<MacKey, error::Unspecified> {
+        init::init_once();
+        if key_value.len() < MIN_MAC_KEY_LEN {
+            return Err(error::Unspecified);
+        }
+        Ok(MacKey { key: hmac::SigningKey::new(digest_alg, key_value) })
+    }
+}
+
+/// The minimum length of a `MacKey`, 112 bits, from SP 800-56A Section 5.9.3.
+pub const MIN_MAC_KEY_LEN: usize = 112 / 8;
+
+/// The minimum length of a `MacTag`, 64 bits, from SP 800-56A Section 5.9.3.
+pub const MIN_MAC_TAG_LEN: usize = 64 / 8;
+
+/// Fills `out` with the `MacTag` that party `provider` sends to the other
+/// party to confirm the key.
+///
+/// `text` is the optional additional input (`Text_P`) that both parties have
+/// agreed on; it is empty if there isn't any. The `MacTag` is the first
+/// `out.len()` bytes of the HMAC output, so `out.len()` must be at least
+/// `MIN_MAC_TAG_LEN` and at most the digest algorithm's output length.
+pub fn sign(key: &MacKey, scheme: Scheme, provider: Party,
+            transcript: &Transcript, text: &[u8], out: &mut [u8])
+            -> Result<(), error::Unspecified> {
+    let output_len = key.key.digest_algorithm().output_len;
+    if out.len() < MIN_MAC_TAG_LEN || out.len() > output_len {
+        return Err(error::Unspecified);
+    }
+    let tag = mac(key, scheme, provider, transcript, text);
+    out.copy_from_slice(&tag.as_ref()[..out.len()]);
+    Ok(())
+}
+
+/// Verifies the `MacTag` `mac_tag` that was received from party `provider`.
+///
+/// The inputs are the same as for `sign`, and the length of `mac_tag` is
+/// checked in the same way. The comparison is done in constant time.
+pub fn verify(key: &MacKey, scheme: Scheme, provider: Party,
+              transcript: &Transcript, text: &[u8], mac_tag: &[u8])
+              -> Result<(), error::Unspecified> {
+    let mut expected = [0u8; digest::MAX_OUTPUT_LEN];
+    let expected = &mut expected[..mac_tag.len()];
+    try!(sign(key, scheme, provider, transcript, text, expected));
+    constant_time::verify_slices_are_equal(expected, mac_tag)
+}
+
+fn mac(key: &MacKey, scheme: Scheme, provider: Party,
+       transcript: &Transcript, text: &[u8]) -> digest::Digest {
+    let message_string: &[u8] = match (scheme, provider) {
+        (Scheme::Unilateral, Party::U) => b"KC_1_U",
+        (Scheme::Unilateral, Party::V) => b"KC_1_V",
+        (Scheme::Bilateral, Party::U) => b"KC_2_U",
+        (Scheme::Bilateral, Party::V) => b"KC_2_V",
+    };
+    let (id_p, id_r, ephem_data_p, ephem_data_r) = match provider {
+        Party::U => (transcript.id_u, transcript.id_v,
+                     transcript.ephem_data_u, transcript.ephem_data_v),
+        Party::V => (transcript.id_v, transcript.id_u,
+                     transcript.ephem_data_v, transcript.ephem_data_u),
+    };
+    let mut ctx = hmac::SigningContext::with_key(&key.key);
+    for part in &[message_string, id_p, id_r, ephem_data_p, ephem_data_r,
+                  text] {
+        ctx.update(part);
+    }
+    ctx.sign()
+}
+
+#[cfg(test)]
+mod tests {
+    use {digest, test};
+    use super::*;
+
+    #[test]
+    fn test_key_confirmation() {
+        test::from_file("src/key_confirmation_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+            let digest_alg = match test_case.consume_string("Digest").as_str() {
+                "SHA256" => &digest::SHA256,
+                "SHA384" => &digest::SHA384,
+                "SHA512" => &digest::SHA512,
+                s => panic!("Unsupported digest: {}", s),
+            };
+            let scheme = match test_case.consume_string("Scheme").as_str() {
+                "Unilateral" => Scheme::Unilateral,
+                "Bilateral" => Scheme::Bilateral,
+                s => panic!("Unsupported scheme: {}", s),
+            };
+            let provider = match test_case.consume_string("Provider").as_str() {
+                "U" => Party::U,
+                "V" => Party::V,
+                s => panic!("Unsupported provider: {}", s),
+            };
+            let mac_key = test_case.consume_bytes("MacKey");
+            let id_u = test_case.consume_bytes("IDU");
+            let id_v = test_case.consume_bytes("IDV");
+            let ephem_data_u = test_case.consume_bytes("EphemDataU");
+            let ephem_data_v = test_case.consume_bytes("EphemDataV");
+            let text = test_case.consume_bytes("Text");
+            let expected = test_case.consume_bytes("MacTag");
+
+            let key = MacKey::new(digest_alg, &mac_key).unwrap();
+            let transcript =
+                Transcript::new(&id_u, &id_v, &ephem_data_u, &ephem_data_v);
+
+            let mut actual = vec![0u8; expected.len()];
+            sign(&key, scheme, provider, &transcript, &text, &mut actual)
+                .unwrap();
+            assert_eq!(actual, expected);
+            assert!(verify(&key, scheme, provider, &transcript, &text,
+                           &expected).is_ok());
+
+            // The tag of the other party, or of the other scheme, differs.
+            let other_party = match provider {
+                Party::U => Party::V,
+                Party::V => Party::U,
+            };
+            assert!(verify(&key, scheme, other_party, &transcript, &text,
+                           &expected).is_err());
+            let other_scheme = match scheme {
+                Scheme::Unilateral => Scheme::Bilateral,
+                Scheme::Bilateral => Scheme::Unilateral,
+            };
+            assert!(verify(&key, other_scheme, provider, &transcript, &text,
+                           &expected).is_err());
+
+            let mut bad_tag = expected.clone();
+            bad_tag[0] ^= 1;
+            assert!(verify(&key, scheme, provider, &transcript, &text,
+                           &bad_tag).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_key_confirmation_lengths() {
+        let transcript = Transcript::new(b"U", b"V", b"", b"");
+
+        assert!(MacKey::new(&digest::SHA256, &[0u8; MIN_MAC_KEY_LEN - 1])
+                    .is_err());
+        let key = MacKey::new(&digest::SHA256, &[0u8; MIN_MAC_KEY_LEN])
+                      .unwrap();
+
+        let mut out = [0u8; digest::MAX_OUTPUT_LEN];
+        for len in 0..(out.len() + 1) {
+            let ok = len >= MIN_MAC_TAG_LEN &&
+                     len <= digest::SHA256.output_len;
+            assert_eq!(sign(&key, Scheme::Bilateral, Party::U, &transcript,
+                            b"", &mut out[..len]).is_ok(), ok);
+        }
+
+        // A truncated tag is the prefix of the full tag, but it must be
+        // verified with its own length.
+        let mut full = [0u8; 32];
+        sign(&key, Scheme::Unilateral, Party::U, &transcript, b"", &mut full)
+            .unwrap();
+        assert!(verify(&key, Scheme::Unilateral, Party::U, &transcript, b"",
+                       &full[..MIN_MAC_TAG_LEN]).is_ok());
+        assert!(verify(&key, Scheme::Unilateral, Party::U, &transcript, b"",
+                       &full[..(MIN_MAC_TAG_LEN - 1)]).is_err());
+    }
+}
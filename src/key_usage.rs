@@ -0,0 +1,214 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Enforcement of key usage separation.
+//!
+//! The types of *ring* already keep the uses of a key apart: an
+//! `ECDSAKeyPair` can only sign and an `agreement::EphemeralPrivateKey` can
+//! only be used for key agreement. However, nothing stops an application
+//! from importing the same private key into both, e.g. by passing the same
+//! P-256 scalar to `ECDSAKeyPair::from_private_key` and
+//! `agreement::EphemeralPrivateKey::from_seed`, or from using the same
+//! `RSAKeyPair` both to sign and, with `rsa::oaep::decrypt`, to decrypt.
+//! Using one key for more than one purpose is forbidden by
+//! [NIST SP 800-57 Part 1] Section 5.2, and auditors commonly ask how it is
+//! prevented.
+//!
+//! A `KeyUsageRegistry` records the one usage of each key that is registered
+//! with it, and rejects the registration of the same key for another usage.
+//! Keys are identified by the SHA-256 digest of their public key, so the
+//! private key material isn't stored. Like `policy::CryptoPolicy`, a registry
+//! isn't process-wide state; the application registers each key where it
+//! imports it, and the checks cover the keys that are registered with the same
+//! registry.
+//!
+//! # Example
+//!
+//! ```
+//! extern crate ring;
+//! extern crate untrusted;
+//!
+//! use ring::{agreement, key_usage, signature};
+//!
+//! # fn main() { foo().unwrap() }
+//! # fn foo() -> Result<(), ring::error::Unspecified> {
+//! let private_key = [0x42; 32];
+//!
+//! let mut registry = key_usage::KeyUsageRegistry::new();
+//!
+//! let signing_key = try!(signature::ECDSAKeyPair::from_private_key(
+//!     &signature::ECDSA_P256_SHA256_ASN1_SIGNING_DETERMINISTIC,
+//!     untrusted::Input::from(&private_key)));
+//! try!(registry.register_ecdsa_signing(&signing_key));
+//!
+//! // The same key material can't also be used for key agreement.
+//! let agreement_key = try!(agreement::EphemeralPrivateKey::from_seed(
+//!     &agreement::ECDH_P256, &private_key));
+//! assert!(registry.register_agreement(&agreement_key).is_err());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [NIST SP 800-57 Part 1]:
+//!     http://nvlpubs.nist.gov/nistpubs/SpecialPublications/NIST.SP.800-57pt1r4.pdf
+
+use {agreement, digest, ec, error, signature, std};
+
+/// The usage of a key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyUsage {
+    /// Creating signatures.
+    Signing,
+
+    /// Key agreement.
+    Agreement,
+
+    /// Decryption, e.g. with RSA-OAEP.
+    Decryption,
+}
+
+/// A record of the usage of each registered key.
+pub struct KeyUsageRegistry {
+    entries: std::vec::Vec<Entry>,
+}
+
+struct Entry {
+    public_key_digest: digest::Digest,
+    usage: KeyUsage,
+}
+
+impl KeyUsageRegistry {
+    /// Constructs a registry with no keys.
+    pub fn new() -> KeyUsageRegistry {
+        KeyUsageRegistry { entries: std::vec::Vec::new() }
+    }
+
+    /// Registers the key whose encoded public key is `public_key` for
+    /// `usage`.
+    ///
+    /// Registering a key again for the same usage succeeds and has no
+    /// effect. Fails if the key was already registered for a different
+    /// usage. The other `register_*` methods are built on this one; use it
+    /// directly for other kinds of keys.
+    pub fn register(&mut self, public_key: &[u8], usage: KeyUsage)
+                    -> Result<(), error::Unspecified> {
+        let public_key_digest = digest::digest(&digest::SHA256, public_key);
+        let existing = self.entries.iter().find(|entry| {
+            entry.public_key_digest.as_ref() == public_key_digest.as_ref()
+        }).map(|entry| entry.usage);
+        match existing {
+            Some(existing) if existing == usage => Ok(()),
+            Some(_) => Err(error::Unspecified),
+            None => {
+                self.entries.push(Entry {
+                    public_key_digest: public_key_digest,
+                    usage: usage,
+                });
+                Ok(())
+            },
+        }
+    }
+
+    /// Registers `key_pair` for `KeyUsage::Signing`.
+    pub fn register_ecdsa_signing(&mut self,
+                                  key_pair: &signature::ECDSAKeyPair)
+                                  -> Result<(), error::Unspecified> {
+        self.register(key_pair.public_key_bytes(), KeyUsage::Signing)
+    }
+
+    /// Registers `private_key` for `KeyUsage::Agreement`.
+    ///
+    /// For ECDH, the key is identified by its uncompressed public point, so
+    /// it matches an `ECDSAKeyPair` with the same private key on the same
+    /// curve.
+    pub fn register_agreement(&mut self,
+                              private_key: &agreement::EphemeralPrivateKey)
+                              -> Result<(), error::Unspecified> {
+        let mut public_key = [0u8; ec::PUBLIC_KEY_MAX_LEN];
+        let public_key = &mut public_key[..private_key.public_key_len()];
+        try!(private_key.compute_public_key(public_key));
+        self.register(public_key, KeyUsage::Agreement)
+    }
+
+    /// Registers `key_pair` for `usage`, which is `KeyUsage::Signing` for
+    /// keys used with `RSAKeyPair::sign` and `KeyUsage::Decryption` for keys
+    /// used with `rsa::oaep::decrypt`.
+    ///
+    /// Feature: `rsa_signing`.
+    #[cfg(feature = "rsa_signing")]
+    pub fn register_rsa(&mut self, key_pair: &signature::RSAKeyPair,
+                        usage: KeyUsage) -> Result<(), error::Unspecified> {
+        self.register(&key_pair.public_key_der(), usage)
+    }
+
+    /// The number of registered keys.
+    pub fn len(&self) -> usize { self.entries.len() }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {agreement, error, signature};
+    use super::*;
+    use untrusted;
+
+    #[test]
+    fn test_key_usage_ecdsa_and_agreement() {
+        let private_key = [0x42u8; 32];
+        let other_private_key = [0x43u8; 32];
+
+        let signing_key = signature::ECDSAKeyPair::from_private_key(
+            &signature::ECDSA_P256_SHA256_ASN1_SIGNING_DETERMINISTIC,
+            untrusted::Input::from(&private_key)).unwrap();
+        let agreement_key = agreement::EphemeralPrivateKey::from_seed(
+            &agreement::ECDH_P256, &private_key).unwrap();
+        let other_agreement_key = agreement::EphemeralPrivateKey::from_seed(
+            &agreement::ECDH_P256, &other_private_key).unwrap();
+
+        let mut registry = KeyUsageRegistry::new();
+        assert_eq!(registry.register_ecdsa_signing(&signing_key), Ok(()));
+        assert_eq!(registry.register_ecdsa_signing(&signing_key), Ok(()));
+        assert_eq!(registry.len(), 1);
+
+        assert_eq!(registry.register_agreement(&agreement_key),
+                   Err(error::Unspecified));
+        assert_eq!(registry.register_agreement(&other_agreement_key), Ok(()));
+        assert_eq!(registry.len(), 2);
+
+        // The other order is rejected too.
+        let mut registry = KeyUsageRegistry::new();
+        assert_eq!(registry.register_agreement(&agreement_key), Ok(()));
+        assert_eq!(registry.register_ecdsa_signing(&signing_key),
+                   Err(error::Unspecified));
+    }
+
+    #[cfg(feature = "rsa_signing")]
+    #[test]
+    fn test_key_usage_rsa() {
+        const PRIVATE_KEY: &'static [u8] =
+            include_bytes!("rsa/signature_rsa_example_private_key.der");
+        let key_pair =
+            signature::RSAKeyPair::from_der(untrusted::Input::from(PRIVATE_KEY))
+                .unwrap();
+
+        let mut registry = KeyUsageRegistry::new();
+        assert_eq!(registry.register_rsa(&key_pair, KeyUsage::Decryption),
+                   Ok(()));
+        assert_eq!(registry.register_rsa(&key_pair, KeyUsage::Signing),
+                   Err(error::Unspecified));
+        assert_eq!(registry.register_rsa(&key_pair, KeyUsage::Decryption),
+                   Ok(()));
+        assert_eq!(registry.len(), 1);
+    }
+}
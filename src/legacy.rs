@@ -0,0 +1,88 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Deprecated algorithms that are still needed to interoperate with old
+//! protocols and old data.
+//!
+//! Everything in this module is SHA-1-based. New code should not use any of
+//! it. Because these algorithms are gathered here, a project can forbid them
+//! wholesale by rejecting any path that starts with `ring::legacy` (e.g. with
+//! a `grep` in CI or a lint configuration), while code that is still being
+//! migrated can continue to use them.
+//!
+//! The SHA-1-based signature verification algorithms are also available in
+//! `ring::signature` for backward compatibility; they are re-exported here so
+//! that all the legacy algorithms can be found in one place.
+
+use {digest, error, hmac};
+
+#[cfg(all(feature = "rsa_signing", feature = "use_heap"))]
+pub use rsa::padding::RSA_PKCS1_SHA1;
+
+pub use signature::{ECDSA_P256_SHA1_ASN1, ECDSA_P384_SHA1_ASN1};
+
+pub use signature::RSA_PKCS1_2048_8192_SHA1;
+
+/// Verifies that `signature` is the HMAC-SHA1 of `data` using the key
+/// `key_value`.
+///
+/// This is for verifying tags produced by old systems. There is intentionally
+/// no corresponding function for producing new HMAC-SHA1 tags.
+///
+/// The verification is done in constant time.
+pub fn verify_hmac_sha1(key_value: &[u8], data: &[u8], signature: &[u8])
+                        -> Result<(), error::Unspecified> {
+    hmac::verify(&hmac_sha1_verification_key(key_value), data, signature)
+}
+
+/// Constructs an HMAC-SHA1 verification key, for verifying many tags with the
+/// same key using `ring::hmac::verify`.
+pub fn hmac_sha1_verification_key(key_value: &[u8]) -> hmac::VerificationKey {
+    hmac::VerificationKey::new(&digest::SHA1, key_value)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {digest, hmac, legacy, test};
+
+    #[test]
+    fn verify_hmac_sha1_test() {
+        // Test cases 1 and 2 from RFC 2202.
+        let key = [0x0b; 20];
+        let data = b"Hi There";
+        let tag =
+            test::from_hex("b617318655057264e28bc0b6fb378c8ef146be00").unwrap();
+        assert!(legacy::verify_hmac_sha1(&key, data, &tag).is_ok());
+
+        let key = b"Jefe";
+        let data = b"what do ya want for nothing?";
+        let tag =
+            test::from_hex("effcdf6ae5eb2fa2d27416d5f184df9c259a7c79").unwrap();
+        assert!(legacy::verify_hmac_sha1(key, data, &tag).is_ok());
+        let verification_key = legacy::hmac_sha1_verification_key(key);
+        assert!(hmac::verify(&verification_key, data, &tag).is_ok());
+
+        let mut bad_tag = tag.clone();
+        bad_tag[0] ^= 1;
+        assert!(legacy::verify_hmac_sha1(key, data, &bad_tag).is_err());
+        assert!(legacy::verify_hmac_sha1(key, data, &tag[1..]).is_err());
+
+        // HMAC-SHA256 tags aren't accepted.
+        let sha256_key = hmac::SigningKey::new(&digest::SHA256, key);
+        let sha256_tag = hmac::sign(&sha256_key, data);
+        assert!(legacy::verify_hmac_sha1(key, data, sha256_tag.as_ref())
+                    .is_err());
+    }
+}
@@ -30,8 +30,23 @@
 //!         <code>dev_urandom_fallback</code> feature is disabled, such
 //!         fallbacks will not occur. See the documentation for
 //!         <code>rand::SystemRandom</code> for more details.
+//! <tr><td><code>hazmat</code>
+//!     <td>Enable <code>ring::hazmat</code>, low-level primitives for
+//!         implementing protocol-specific constructions.
+//! <tr><td><code>public_key_cache</code>
+//!     <td>Enable <code>ring::public_key_cache</code>, a bounded cache of
+//!         parsed and validated public keys for signature verification.
 //! <tr><td><code>rsa_signing</code>
 //!     <td>Enable RSA signing (<code>RSAKeyPair</code> and related things).
+//!         Implies <code>use_heap</code>.
+//! <tr><td><code>side_channel_testing</code>
+//!     <td>Enable <code>ring::side_channel</code>, harnesses for statistical
+//!         timing tests of constant-time-critical routines. Implies
+//!         <code>rsa_signing</code>.
+//! <tr><td><code>use_std (default)</code>
+//!     <td>Implement <code>std::error::Error</code> for the error types in
+//!         <code>ring::error</code>, and convert them to
+//!         <code>std::io::Error</code>. Implies <code>use_heap</code>.
 //! </table>
 
 #![doc(html_root_url="https://briansmith.org/rustdoc/")]
@@ -118,13 +133,42 @@ mod polyfill;
 pub mod aead;
 
 pub mod agreement;
+
+#[cfg(feature = "use_heap")]
+pub mod argon2;
+
+pub mod audit;
+pub mod bcrypt;
 mod c;
+
+#[path = "cipher/cipher.rs"]
+pub mod cipher;
+
+pub mod cmac;
+
+#[cfg(feature = "use_heap")]
+pub mod cms;
+
 pub mod constant_time;
+pub mod cpace;
 
 #[doc(hidden)]
 pub mod der;
 
+pub mod ecjpake;
+pub mod elligator2;
+
+#[cfg(feature = "use_heap")]
+pub mod envelope;
+
 pub mod error;
+pub mod executor;
+pub mod exporter;
+
+#[cfg(feature = "use_heap")]
+mod ffdhe;
+
+pub mod firmware;
 
 #[path = "digest/digest.rs"]
 pub mod digest;
@@ -132,11 +176,69 @@ pub mod digest;
 #[path = "ec/ec.rs"]
 mod ec;
 
+pub mod hash_to_curve;
+
+#[cfg(feature = "hazmat")]
+pub mod hazmat;
+
 pub mod hkdf;
 pub mod hmac;
+pub mod iana;
+pub mod kbkdf;
+pub mod key_confirmation;
+
+#[cfg(feature = "use_heap")]
+pub mod key_usage;
+
 mod init;
+
+#[path = "io/io.rs"]
+pub mod io;
+
+#[cfg(feature = "use_heap")]
+pub mod jws;
+
+pub mod kat;
+
+pub mod legacy;
+pub mod lms;
+pub mod mac_chain;
+pub mod mgf1;
+
+#[cfg(feature = "use_heap")]
+pub mod multi_recipient;
+
+#[cfg(feature = "use_heap")]
+mod pbe;
+
 pub mod pbkdf2;
+pub mod pedersen;
+pub mod point_validation;
+pub mod policy;
+
+#[cfg(feature = "use_heap")]
+pub mod pem;
+
+#[cfg(feature = "use_heap")]
+pub mod pkcs10;
+
+#[cfg(feature = "use_heap")]
+pub mod pkcs12;
+
+#[cfg(feature = "use_heap")]
+mod pkcs8;
+
+#[cfg(all(feature = "public_key_cache", feature = "use_heap"))]
+pub mod public_key_cache;
+
 pub mod rand;
+pub mod raw_public_key;
+pub mod scalar;
+
+#[cfg(feature = "use_heap")]
+pub mod scrypt;
+
+pub mod secret;
 
 mod limb;
 
@@ -145,11 +247,39 @@ mod limb;
 #[doc(hidden)]
 pub use limb::GFp_rand_mod;
 
-#[cfg(feature = "use_heap")]
 #[path = "rsa/rsa.rs"]
 pub mod rsa;
 
+#[cfg(all(feature = "side_channel_testing", feature = "use_heap"))]
+pub mod side_channel;
+
 pub mod signature;
+mod signed_data;
+pub mod skey;
+pub mod slip10;
+pub mod spake2plus;
+
+#[cfg(feature = "use_heap")]
+pub mod ssh_agent;
+
+pub mod threshold;
+
+#[cfg(feature = "use_heap")]
+pub mod timestamp;
+
+#[cfg(feature = "use_heap")]
+pub mod tls_exporter;
+
+pub mod tls_kdf;
+
+#[cfg(feature = "use_heap")]
+pub mod vault;
+
+#[cfg(feature = "use_heap")]
+pub mod volume_key;
+
+pub mod wireguard;
+pub mod x509;
 
 #[cfg(any(feature = "use_heap", test))]
 pub mod test;
@@ -30,7 +30,7 @@ use {rand, polyfill, c, core, error};
 
 #[cfg(target_pointer_width = "64")]
 #[allow(trivial_numeric_casts)] // XXX: workaround compiler bug.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 #[repr(u64)]
 pub enum LimbMask {
     True = 0xffff_ffff_ffff_ffff,
@@ -39,7 +39,7 @@ pub enum LimbMask {
 
 #[cfg(target_pointer_width = "32")]
 #[allow(trivial_numeric_casts)] // XXX: workaround compiler bug.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 #[repr(u32)]
 pub enum LimbMask {
     True = 0xffff_ffff,
@@ -0,0 +1,1207 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! LMS and HSS hash-based signatures.
+//!
+//! LMS and its multi-level variant HSS are specified in [RFC 8554]. They are
+//! *stateful*: every signature uses up one of a fixed number of one-time
+//! signature (OTS) keys, and a key that has signed as many messages as it has
+//! OTS keys can't sign any more. Firmware signing infrastructure has to keep
+//! track of this, so this module describes the parameter sets and the sizes
+//! of keys and signatures, and it extracts the parameters from encoded public
+//! keys and signatures. In particular, `SignatureInfo::remaining_signatures`
+//! tells how many more signatures the key that made a signature can make, if
+//! it signs in order, as RFC 8554 requires.
+//!
+//! `HSS_SHA256` verifies HSS signatures with `signature::verify`.
+//! `HSSKeyPair` signs; the index of its next OTS key is state that has to
+//! survive restarts, so `HSSKeyPair::sign` hands the new state to a callback
+//! that must persist it before the signature is computed. Reusing an OTS key
+//! would let an attacker forge signatures. The private key is a seed from
+//! which the OTS keys are derived as suggested in RFC 8554 Appendix A; the
+//! identifier and seed of the key of each lower level are derived from the
+//! key of the level above in the same way, so a key pair is fully determined
+//! by its private key and its state.
+//!
+//! Only the SHA-256 parameter sets with 32-byte hashes in RFC 8554 Section 4.1
+//! and Section 5.1 are supported.
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::lms;
+//!
+//! let levels = [
+//!     lms::Level { lms: &lms::LMS_SHA256_M32_H10,
+//!                  lmots: &lms::LMOTS_SHA256_N32_W4 },
+//!     lms::Level { lms: &lms::LMS_SHA256_M32_H5,
+//!                  lmots: &lms::LMOTS_SHA256_N32_W8 },
+//! ];
+//! assert_eq!(lms::num_signatures(&levels).unwrap(), 1 << 15);
+//! assert_eq!(lms::signature_len(&levels).unwrap(), 4 + 2508 + 56 + 1292);
+//! ```
+//!
+//! Signing and verifying, with the state kept in a variable instead of a
+//! file:
+//!
+//! ```
+//! # extern crate ring;
+//! # extern crate untrusted;
+//! use ring::{lms, rand, signature};
+//! use ring::signature::KeyPair;
+//!
+//! # fn sign_and_verify() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let levels = [lms::Level { lms: &lms::LMS_SHA256_M32_H5,
+//!                            lmots: &lms::LMOTS_SHA256_N32_W4 }];
+//! let private_key = try!(lms::HSSKeyPair::generate_private_key(&levels,
+//!                                                               &rng));
+//! let mut state = 0;
+//! let mut key_pair = try!(lms::HSSKeyPair::from_private_key(
+//!     untrusted::Input::from(&private_key), state));
+//!
+//! const MESSAGE: &'static [u8] = b"firmware image";
+//! let sig = try!(key_pair.sign(MESSAGE, &rng, |next_index| {
+//!     state = next_index;
+//!     Ok(())
+//! }));
+//! assert_eq!(state, 1);
+//!
+//! try!(signature::verify(&lms::HSS_SHA256,
+//!                        untrusted::Input::from(key_pair.public_key()),
+//!                        untrusted::Input::from(MESSAGE),
+//!                        untrusted::Input::from(&sig)));
+//! # Ok(())
+//! # }
+//! # fn main() { sign_and_verify().unwrap() }
+//! ```
+//!
+//! [RFC 8554]: https://tools.ietf.org/html/rfc8554
+
+use {constant_time, digest, error, polyfill, private, signature};
+#[cfg(feature = "use_heap")]
+use {core, rand, secret};
+#[cfg(feature = "use_heap")]
+use std::vec::Vec;
+use untrusted;
+
+/// An LMS algorithm, i.e. the height of the Merkle tree.
+pub struct LMSAlgorithm {
+    id: u32,
+    height: usize,
+}
+
+impl LMSAlgorithm {
+    /// The height of the Merkle tree.
+    #[inline]
+    pub fn height(&self) -> usize { self.height }
+
+    /// The number of messages that a key can sign: 2 to the power of
+    /// `height()`.
+    #[inline]
+    pub fn num_signatures(&self) -> u64 { 1 << self.height }
+
+    /// The length of an LMS signature made with `lmots`.
+    pub fn signature_len(&self, lmots: &LMOTSAlgorithm) -> usize {
+        4 + lmots.signature_len() + 4 + self.height * HASH_LEN
+    }
+}
+
+/// LMS with SHA-256 and a tree of height 5.
+pub static LMS_SHA256_M32_H5: LMSAlgorithm = LMSAlgorithm { id: 5, height: 5 };
+
+/// LMS with SHA-256 and a tree of height 10.
+pub static LMS_SHA256_M32_H10: LMSAlgorithm =
+    LMSAlgorithm { id: 6, height: 10 };
+
+/// LMS with SHA-256 and a tree of height 15.
+pub static LMS_SHA256_M32_H15: LMSAlgorithm =
+    LMSAlgorithm { id: 7, height: 15 };
+
+/// LMS with SHA-256 and a tree of height 20.
+pub static LMS_SHA256_M32_H20: LMSAlgorithm =
+    LMSAlgorithm { id: 8, height: 20 };
+
+/// LMS with SHA-256 and a tree of height 25.
+pub static LMS_SHA256_M32_H25: LMSAlgorithm =
+    LMSAlgorithm { id: 9, height: 25 };
+
+/// An LM-OTS algorithm, i.e. the Winternitz parameter of the one-time
+/// signatures.
+pub struct LMOTSAlgorithm {
+    id: u32,
+    w: usize,
+    p: usize,
+    ls: usize,
+}
+
+impl LMOTSAlgorithm {
+    /// The Winternitz parameter: the number of bits of the digest that each
+    /// hash chain encodes. Larger values give shorter but slower signatures.
+    #[inline]
+    pub fn winternitz_parameter(&self) -> usize { self.w }
+
+    /// The length of an LM-OTS signature.
+    #[inline]
+    pub fn signature_len(&self) -> usize { 4 + HASH_LEN * (self.p + 1) }
+}
+
+/// LM-OTS with SHA-256 and Winternitz parameter 1.
+pub static LMOTS_SHA256_N32_W1: LMOTSAlgorithm =
+    LMOTSAlgorithm { id: 1, w: 1, p: 265, ls: 7 };
+
+/// LM-OTS with SHA-256 and Winternitz parameter 2.
+pub static LMOTS_SHA256_N32_W2: LMOTSAlgorithm =
+    LMOTSAlgorithm { id: 2, w: 2, p: 133, ls: 6 };
+
+/// LM-OTS with SHA-256 and Winternitz parameter 4.
+pub static LMOTS_SHA256_N32_W4: LMOTSAlgorithm =
+    LMOTSAlgorithm { id: 3, w: 4, p: 67, ls: 4 };
+
+/// LM-OTS with SHA-256 and Winternitz parameter 8.
+pub static LMOTS_SHA256_N32_W8: LMOTSAlgorithm =
+    LMOTSAlgorithm { id: 4, w: 8, p: 34, ls: 0 };
+
+/// The parameters of one level of an HSS key. The first level is the top
+/// level, whose root is the public key; each following level is signed by the
+/// one above it, and the last level signs the messages.
+#[derive(Clone, Copy)]
+pub struct Level {
+    /// The LMS algorithm of the level.
+    pub lms: &'static LMSAlgorithm,
+
+    /// The LM-OTS algorithm of the level.
+    pub lmots: &'static LMOTSAlgorithm,
+}
+
+/// The maximum number of levels of an HSS key.
+pub const MAX_LEVELS: usize = 8;
+
+/// The length of an HSS public key.
+pub const PUBLIC_KEY_LEN: usize = 4 + LMS_PUBLIC_KEY_LEN;
+
+/// The length of an HSS signature made by a key with the given levels.
+///
+/// Fails if there are no levels or more than `MAX_LEVELS` levels.
+pub fn signature_len(levels: &[Level]) -> Result<usize, error::Unspecified> {
+    try!(check_num_levels(levels.len()));
+    // Each level but the last signs the public key of the level below it,
+    // which is included in the signature.
+    let signatures_len = levels.iter().fold(0, |acc, level| {
+        acc + level.lms.signature_len(level.lmots)
+    });
+    Ok(4 + signatures_len + (levels.len() - 1) * LMS_PUBLIC_KEY_LEN)
+}
+
+/// The number of messages that a key with the given levels can sign, or
+/// `u64::MAX` if it can sign more than that.
+///
+/// Fails if there are no levels or more than `MAX_LEVELS` levels.
+pub fn num_signatures(levels: &[Level]) -> Result<u64, error::Unspecified> {
+    try!(check_num_levels(levels.len()));
+    Ok(levels.iter().fold(1, |acc: u64, level| {
+        acc.saturating_mul(level.lms.num_signatures())
+    }))
+}
+
+/// The parameters of an HSS public key.
+pub struct PublicKeyInfo {
+    num_levels: usize,
+    top_level: Level,
+}
+
+impl PublicKeyInfo {
+    /// Parses the parameters of the encoded HSS public key `public_key`.
+    pub fn from_bytes(public_key: untrusted::Input)
+                      -> Result<PublicKeyInfo, error::Unspecified> {
+        public_key.read_all(error::Unspecified, |input| {
+            let num_levels = try!(read_u32(input)) as usize;
+            try!(check_num_levels(num_levels));
+            let top_level = try!(read_lms_public_key(input)).level;
+            Ok(PublicKeyInfo {
+                num_levels: num_levels,
+                top_level: top_level,
+            })
+        })
+    }
+
+    /// The number of levels of the key.
+    #[inline]
+    pub fn num_levels(&self) -> usize { self.num_levels }
+
+    /// The parameters of the top level. The public key doesn't encode the
+    /// parameters of the other levels.
+    #[inline]
+    pub fn top_level(&self) -> Level { self.top_level }
+}
+
+/// The parameters of an HSS signature.
+pub struct SignatureInfo {
+    levels: [Level; MAX_LEVELS],
+    leaf_indices: [u32; MAX_LEVELS],
+    num_levels: usize,
+}
+
+impl SignatureInfo {
+    /// Parses the parameters of the encoded HSS signature `signature`.
+    ///
+    /// This only checks that `signature` is well-formed; it doesn't verify
+    /// it.
+    pub fn from_bytes(signature: untrusted::Input)
+                      -> Result<SignatureInfo, error::Unspecified> {
+        signature.read_all(error::Unspecified, |input| {
+            // The number of signed public keys, one less than the number of
+            // levels.
+            let num_signed_public_keys = try!(read_u32(input)) as usize;
+            if num_signed_public_keys >= MAX_LEVELS {
+                return Err(error::Unspecified);
+            }
+            let num_levels = num_signed_public_keys + 1;
+            let mut result = SignatureInfo {
+                levels: [Level {
+                    lms: &LMS_SHA256_M32_H5,
+                    lmots: &LMOTS_SHA256_N32_W1,
+                }; MAX_LEVELS],
+                leaf_indices: [0; MAX_LEVELS],
+                num_levels: num_levels,
+            };
+            let mut next_level = None;
+            for i in 0..num_levels {
+                let signature = try!(read_lms_signature(input));
+                // The parameters of each level but the first are also in the
+                // public key that the level above signed.
+                if let Some(next_level) = next_level {
+                    if !same_level(&signature.level, &next_level) {
+                        return Err(error::Unspecified);
+                    }
+                }
+                result.levels[i] = signature.level;
+                result.leaf_indices[i] = signature.leaf_index;
+                if i + 1 < num_levels {
+                    next_level = Some(try!(read_lms_public_key(input)).level);
+                }
+            }
+            Ok(result)
+        })
+    }
+
+    /// The parameters of each level, starting with the top level.
+    #[inline]
+    pub fn levels(&self) -> &[Level] { &self.levels[..self.num_levels] }
+
+    /// The index of the OTS key used at each level, starting with the top
+    /// level.
+    #[inline]
+    pub fn leaf_indices(&self) -> &[u32] {
+        &self.leaf_indices[..self.num_levels]
+    }
+
+    /// The number of messages that the key can sign after this signature,
+    /// assuming that it uses its OTS keys in order, or `u64::MAX` if it can
+    /// sign more than that. When this is zero, the key is exhausted.
+    pub fn remaining_signatures(&self) -> u64 {
+        // The OTS keys left at each level, each of which can sign as many
+        // messages as all the levels below it together.
+        let mut remaining = 0u64;
+        let mut below = 1u64;
+        for (level, &leaf_index) in
+                self.levels().iter().zip(self.leaf_indices()).rev() {
+            let left = level.lms.num_signatures() - 1 - (leaf_index as u64);
+            remaining = remaining.saturating_add(left.saturating_mul(below));
+            below = below.saturating_mul(level.lms.num_signatures());
+        }
+        remaining
+    }
+}
+
+/// Parameters for HSS signature verification.
+pub struct HSSParameters;
+
+/// Verification of HSS signatures with the SHA-256 parameter sets of
+/// RFC 8554, with the public key and the signature in the encodings of
+/// RFC 8554 Section 6.
+pub static HSS_SHA256: HSSParameters = HSSParameters {};
+
+impl signature::VerificationAlgorithm for HSSParameters {
+    fn verify(&self, public_key: untrusted::Input, msg: untrusted::Input,
+              signature: untrusted::Input) -> Result<(), error::Unspecified> {
+        let (num_levels, top_public_key) =
+            try!(public_key.read_all(error::Unspecified, |input| {
+                let num_levels = try!(read_u32(input)) as usize;
+                try!(check_num_levels(num_levels));
+                Ok((num_levels, try!(read_lms_public_key(input))))
+            }));
+        signature.read_all(error::Unspecified, |input| {
+            if try!(read_u32(input)) as usize != num_levels - 1 {
+                return Err(error::Unspecified);
+            }
+            // Each level but the last signs the public key of the level
+            // below it.
+            let mut public_key = top_public_key;
+            for _ in 1..num_levels {
+                let signature = try!(read_lms_signature(input));
+                let signed = try!(input.skip_and_get_input(LMS_PUBLIC_KEY_LEN));
+                try!(lms_verify(&public_key, signed.as_slice_less_safe(),
+                                &signature));
+                public_key = try!(signed.read_all(error::Unspecified,
+                                                  read_lms_public_key));
+            }
+            let signature = try!(read_lms_signature(input));
+            lms_verify(&public_key, msg.as_slice_less_safe(), &signature)
+        })
+    }
+
+    fn name(&self) -> &'static str { "HSS_SHA256" }
+
+    fn security_bits(&self) -> usize { 128 }
+}
+
+impl private::Private for HSSParameters {}
+
+/// An HSS key pair, for signing. Feature: `use_heap`.
+///
+/// The private key is a seed from which all the OTS keys are derived; the
+/// state is the index of the next signature, which selects the OTS key at
+/// each level. The whole Merkle tree of each level is computed when the level
+/// is first used, which takes 2 to the power of the height LM-OTS key
+/// generations, so the tree heights have to be chosen with the signer's
+/// resources in mind.
+#[cfg(feature = "use_heap")]
+pub struct HSSKeyPair {
+    params: Vec<Level>,
+    levels: Vec<LevelKey>,
+    next_index: u64,
+    num_signatures: u64,
+    public_key: [u8; PUBLIC_KEY_LEN],
+}
+
+#[cfg(feature = "use_heap")]
+impl HSSKeyPair {
+    /// Generates a new private key with the given levels, returning its
+    /// encoding: the number of levels and the parameters of each level as
+    /// 32-bit big-endian integers, followed by the 16-byte identifier and the
+    /// 32-byte seed of the top level.
+    ///
+    /// Fails if there are no levels or more than `MAX_LEVELS` levels, or if
+    /// the key could sign 2^64 or more messages.
+    pub fn generate_private_key(levels: &[Level], rng: &rand::SecureRandom)
+                                -> Result<Vec<u8>, error::Unspecified> {
+        let _ = try!(check_levels(levels));
+        let mut private_key = Vec::with_capacity(
+            4 + levels.len() * 8 + IDENTIFIER_LEN + SEED_LEN);
+        private_key.extend_from_slice(
+            &polyfill::slice::be_u8_from_u32(levels.len() as u32));
+        for level in levels {
+            private_key.extend_from_slice(
+                &polyfill::slice::be_u8_from_u32(level.lms.id));
+            private_key.extend_from_slice(
+                &polyfill::slice::be_u8_from_u32(level.lmots.id));
+        }
+        let mut secret = [0u8; IDENTIFIER_LEN + SEED_LEN];
+        try!(rng.fill(&mut secret));
+        private_key.extend_from_slice(&secret);
+        Ok(private_key)
+    }
+
+    /// Constructs a key pair from `private_key`, as returned by
+    /// `generate_private_key`, and `next_index`, the index of the next
+    /// signature: zero for a new key, and otherwise the value most recently
+    /// persisted by `sign`.
+    ///
+    /// Fails if `private_key` is invalid or if `next_index` is larger than
+    /// the number of messages that the key can sign.
+    pub fn from_private_key(private_key: untrusted::Input, next_index: u64)
+                            -> Result<HSSKeyPair, error::Unspecified> {
+        let (params, identifier, seed) =
+            try!(private_key.read_all(error::Unspecified, |input| {
+                let num_levels = try!(read_u32(input)) as usize;
+                try!(check_num_levels(num_levels));
+                let mut params = Vec::with_capacity(num_levels);
+                for _ in 0..num_levels {
+                    let lms = try!(lms_algorithm(try!(read_u32(input))));
+                    let lmots = try!(lmots_algorithm(try!(read_u32(input))));
+                    params.push(Level { lms: lms, lmots: lmots });
+                }
+                let mut identifier = [0u8; IDENTIFIER_LEN];
+                identifier.copy_from_slice(
+                    try!(input.skip_and_get_input(IDENTIFIER_LEN))
+                        .as_slice_less_safe());
+                let mut seed = secret::SecretBox::new([0u8; SEED_LEN]);
+                seed.expose_mut().copy_from_slice(
+                    try!(input.skip_and_get_input(SEED_LEN))
+                        .as_slice_less_safe());
+                Ok((params, identifier, seed))
+            }));
+        let num_signatures = try!(check_levels(&params));
+        if next_index > num_signatures {
+            return Err(error::Unspecified);
+        }
+
+        let top = LevelKey::new(params[0], identifier, seed, 0);
+        let mut public_key = [0u8; PUBLIC_KEY_LEN];
+        public_key[..4].copy_from_slice(
+            &polyfill::slice::be_u8_from_u32(params.len() as u32));
+        public_key[4..].copy_from_slice(&top.public_key());
+
+        let mut key_pair = HSSKeyPair {
+            levels: Vec::with_capacity(params.len()),
+            params: params,
+            next_index: next_index,
+            num_signatures: num_signatures,
+            public_key: public_key,
+        };
+        key_pair.levels.push(top);
+        // An exhausted key has no next signature; build the levels for the
+        // last one so that they are consistent anyway.
+        let index = core::cmp::min(next_index, num_signatures - 1);
+        key_pair.update_levels(index);
+        Ok(key_pair)
+    }
+
+    /// Signs `msg`, returning the signature.
+    ///
+    /// Before the signature is computed, `persist` is called with the index
+    /// of the signature after this one. It must store that value durably,
+    /// e.g. write it to a file and flush the file to disk, and return
+    /// `Ok(())` only if it did; the stored value is what must be passed to
+    /// `from_private_key` from then on. If `persist` fails then `sign` fails
+    /// and the key pair's state doesn't change. Releasing a signature before
+    /// the state is persisted would risk reusing its OTS key, e.g. after a
+    /// crash, and that would let an attacker forge signatures.
+    ///
+    /// `rng` is used for the randomizer of the message's LM-OTS signature.
+    ///
+    /// Fails if the key is exhausted, i.e. `remaining_signatures()` is zero.
+    pub fn sign<F>(&mut self, msg: &[u8], rng: &rand::SecureRandom,
+                   persist: F) -> Result<Vec<u8>, error::Unspecified>
+                   where F: FnOnce(u64) -> Result<(), error::Unspecified> {
+        let index = self.next_index;
+        if index >= self.num_signatures {
+            return Err(error::Unspecified);
+        }
+        self.update_levels(index);
+        let mut randomizer = [0u8; HASH_LEN];
+        try!(rng.fill(&mut randomizer));
+
+        try!(persist(index + 1));
+        self.next_index = index + 1;
+
+        let mut signature =
+            Vec::with_capacity(signature_len(&self.params).unwrap());
+        signature.extend_from_slice(&polyfill::slice::be_u8_from_u32(
+            (self.levels.len() - 1) as u32));
+        for level in &self.levels[1..] {
+            signature.extend_from_slice(&level.signature);
+            signature.extend_from_slice(&level.public_key());
+        }
+        let bottom = self.levels.len() - 1;
+        let (_, leaf_index) = self.position(bottom, index);
+        self.levels[bottom].sign(leaf_index, &randomizer, msg, &mut signature);
+        Ok(signature)
+    }
+
+    /// The index of the next signature, i.e. the number of messages that the
+    /// key has signed.
+    #[inline]
+    pub fn next_index(&self) -> u64 { self.next_index }
+
+    /// The number of messages that the key can still sign. When this is zero,
+    /// the key is exhausted.
+    #[inline]
+    pub fn remaining_signatures(&self) -> u64 {
+        self.num_signatures - self.next_index
+    }
+
+    // Which of the keys of level `i` signature `index` uses, counting from
+    // the start of the whole key, and the index of its leaf within that key.
+    fn position(&self, i: usize, index: u64) -> (u64, u32) {
+        let below =
+            self.params[(i + 1)..].iter().fold(0, |acc, l| acc + l.lms.height);
+        let height = self.params[i].lms.height;
+        (index >> (below + height),
+         ((index >> below) & ((1 << height) - 1)) as u32)
+    }
+
+    // Replaces the keys of the levels below the top level that signature
+    // `index` doesn't use with the ones it does, each signed by the new key
+    // of the level above it.
+    fn update_levels(&mut self, index: u64) {
+        for i in 1..self.params.len() {
+            let (instance, _) = self.position(i, index);
+            if i < self.levels.len() && self.levels[i].instance == instance {
+                continue;
+            }
+            let (_, parent_leaf_index) = self.position(i - 1, index);
+            let child = self.levels[i - 1].child(self.params[i],
+                                                 parent_leaf_index, instance);
+            if i < self.levels.len() {
+                self.levels[i] = child;
+            } else {
+                self.levels.push(child);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "use_heap")]
+impl signature::KeyPair for HSSKeyPair {
+    type PublicKey = [u8; PUBLIC_KEY_LEN];
+
+    fn public_key(&self) -> &Self::PublicKey { &self.public_key }
+}
+
+#[cfg(feature = "use_heap")]
+impl private::Private for HSSKeyPair {}
+
+// The LMS key of one level of an `HSSKeyPair`.
+#[cfg(feature = "use_heap")]
+struct LevelKey {
+    level: Level,
+    identifier: [u8; IDENTIFIER_LEN],
+    seed: secret::SecretBox<[u8; SEED_LEN]>,
+
+    // `tree[r]` is node `r` of the Merkle tree, numbered as in RFC 8554
+    // Section 5.3; `tree[0]` is unused.
+    tree: Vec<[u8; HASH_LEN]>,
+
+    // Which of the level's keys this is; see `HSSKeyPair::position`.
+    instance: u64,
+
+    // The signature of the key's public key by the level above, or nothing
+    // for the top level.
+    signature: Vec<u8>,
+}
+
+#[cfg(feature = "use_heap")]
+impl LevelKey {
+    fn new(level: Level, identifier: [u8; IDENTIFIER_LEN],
+           seed: secret::SecretBox<[u8; SEED_LEN]>, instance: u64)
+           -> LevelKey {
+        let num_leaves = 1 << level.lms.height;
+        let mut key = LevelKey {
+            level: level,
+            identifier: identifier,
+            seed: seed,
+            tree: vec![[0u8; HASH_LEN]; 2 * num_leaves],
+            instance: instance,
+            signature: Vec::new(),
+        };
+        for q in 0..num_leaves {
+            let r = num_leaves + q;
+            let ots_public_key = key.ots_public_key(q as u32);
+            key.tree[r] = hash(&key.identifier, r as u32, D_LEAF,
+                               &[&ots_public_key]);
+        }
+        for r in (1..num_leaves).rev() {
+            key.tree[r] = hash(&key.identifier, r as u32, D_INTR,
+                               &[&key.tree[2 * r], &key.tree[2 * r + 1]]);
+        }
+        key
+    }
+
+    fn public_key(&self) -> [u8; LMS_PUBLIC_KEY_LEN] {
+        let mut public_key = [0u8; LMS_PUBLIC_KEY_LEN];
+        public_key[..4].copy_from_slice(
+            &polyfill::slice::be_u8_from_u32(self.level.lms.id));
+        public_key[4..8].copy_from_slice(
+            &polyfill::slice::be_u8_from_u32(self.level.lmots.id));
+        public_key[8..(8 + IDENTIFIER_LEN)].copy_from_slice(&self.identifier);
+        public_key[(8 + IDENTIFIER_LEN)..].copy_from_slice(&self.tree[1]);
+        public_key
+    }
+
+    // The key of the level below, the one that leaf `q` signs.
+    fn child(&self, level: Level, q: u32, instance: u64) -> LevelKey {
+        let mut identifier = [0u8; IDENTIFIER_LEN];
+        identifier.copy_from_slice(
+            &self.derive(q, CHILD_IDENTIFIER)[..IDENTIFIER_LEN]);
+        let seed = secret::SecretBox::new(self.derive(q, CHILD_SEED));
+        let mut child = LevelKey::new(level, identifier, seed, instance);
+        // The randomizer is derived too, so that the child's public key is
+        // always signed the same way, however often the child is rebuilt.
+        let randomizer = self.derive(q, CHILD_RANDOMIZER);
+        let mut signature = Vec::new();
+        self.sign(q, &randomizer, &child.public_key(), &mut signature);
+        child.signature = signature;
+        child
+    }
+
+    // Appends the LMS signature of `msg` with leaf `q` to `out`.
+    fn sign(&self, q: u32, randomizer: &[u8], msg: &[u8],
+            out: &mut Vec<u8>) {
+        let lmots = self.level.lmots;
+        out.extend_from_slice(&polyfill::slice::be_u8_from_u32(q));
+        out.extend_from_slice(&polyfill::slice::be_u8_from_u32(lmots.id));
+        out.extend_from_slice(randomizer);
+        let digits =
+            message_digits(lmots, &self.identifier, q, randomizer, msg);
+        for i in 0..lmots.p {
+            let mut tmp = self.derive(q, i as u16);
+            chain(&self.identifier, q, i, &mut tmp, 0,
+                  coefficient(&digits, i, lmots.w));
+            out.extend_from_slice(&tmp);
+        }
+        out.extend_from_slice(
+            &polyfill::slice::be_u8_from_u32(self.level.lms.id));
+        let mut r = (1 << self.level.lms.height) + q as usize;
+        while r > 1 {
+            out.extend_from_slice(&self.tree[r ^ 1]);
+            r /= 2;
+        }
+    }
+
+    fn ots_public_key(&self, q: u32) -> [u8; HASH_LEN] {
+        let lmots = self.level.lmots;
+        let mut ctx = digest::Context::new(&digest::SHA256);
+        ctx.update(&self.identifier);
+        ctx.update(&polyfill::slice::be_u8_from_u32(q));
+        ctx.update(&be_u8_from_u16(D_PBLC));
+        for i in 0..lmots.p {
+            let mut tmp = self.derive(q, i as u16);
+            chain(&self.identifier, q, i, &mut tmp, 0, (1 << lmots.w) - 1);
+            ctx.update(&tmp);
+        }
+        finish(ctx)
+    }
+
+    fn derive(&self, q: u32, i: u16) -> [u8; HASH_LEN] {
+        hash(&self.identifier, q, i, &[&[DERIVE], self.seed.expose()])
+    }
+}
+
+// An LMS public key.
+struct LMSPublicKey<'a> {
+    level: Level,
+    identifier: &'a [u8],
+    root: &'a [u8],
+}
+
+// An LMS signature. `y` is the LM-OTS signature's hash chain values and
+// `path` is the authentication path.
+struct LMSSignature<'a> {
+    leaf_index: u32,
+    level: Level,
+    randomizer: &'a [u8],
+    y: untrusted::Input<'a>,
+    path: untrusted::Input<'a>,
+}
+
+fn read_lms_signature<'a>(input: &mut untrusted::Reader<'a>)
+                          -> Result<LMSSignature<'a>, error::Unspecified> {
+    let leaf_index = try!(read_u32(input));
+    let lmots = try!(lmots_algorithm(try!(read_u32(input))));
+    let randomizer = try!(input.skip_and_get_input(HASH_LEN));
+    let y = try!(input.skip_and_get_input(HASH_LEN * lmots.p));
+    let lms = try!(lms_algorithm(try!(read_u32(input))));
+    if (leaf_index as u64) >= lms.num_signatures() {
+        return Err(error::Unspecified);
+    }
+    let path = try!(input.skip_and_get_input(HASH_LEN * lms.height));
+    Ok(LMSSignature {
+        leaf_index: leaf_index,
+        level: Level { lms: lms, lmots: lmots },
+        randomizer: randomizer.as_slice_less_safe(),
+        y: y,
+        path: path,
+    })
+}
+
+fn read_lms_public_key<'a>(input: &mut untrusted::Reader<'a>)
+                           -> Result<LMSPublicKey<'a>, error::Unspecified> {
+    let lms = try!(lms_algorithm(try!(read_u32(input))));
+    let lmots = try!(lmots_algorithm(try!(read_u32(input))));
+    let identifier = try!(input.skip_and_get_input(IDENTIFIER_LEN));
+    let root = try!(input.skip_and_get_input(HASH_LEN));
+    Ok(LMSPublicKey {
+        level: Level { lms: lms, lmots: lmots },
+        identifier: identifier.as_slice_less_safe(),
+        root: root.as_slice_less_safe(),
+    })
+}
+
+// RFC 8554 Section 5.4.2.
+fn lms_verify(public_key: &LMSPublicKey, msg: &[u8],
+              signature: &LMSSignature) -> Result<(), error::Unspecified> {
+    if !same_level(&public_key.level, &signature.level) {
+        return Err(error::Unspecified);
+    }
+    let identifier = public_key.identifier;
+    let q = signature.leaf_index;
+
+    // RFC 8554 Section 4.6, Algorithm 4b.
+    let lmots = signature.level.lmots;
+    let digits =
+        message_digits(lmots, identifier, q, signature.randomizer, msg);
+    let mut ctx = digest::Context::new(&digest::SHA256);
+    ctx.update(identifier);
+    ctx.update(&polyfill::slice::be_u8_from_u32(q));
+    ctx.update(&be_u8_from_u16(D_PBLC));
+    try!(signature.y.read_all(error::Unspecified, |y| {
+        for i in 0..lmots.p {
+            let mut tmp = [0u8; HASH_LEN];
+            tmp.copy_from_slice(
+                try!(y.skip_and_get_input(HASH_LEN)).as_slice_less_safe());
+            chain(identifier, q, i, &mut tmp, coefficient(&digits, i, lmots.w),
+                  (1 << lmots.w) - 1);
+            ctx.update(&tmp);
+        }
+        Ok(())
+    }));
+    let ots_public_key = finish(ctx);
+
+    let mut r = (1 << signature.level.lms.height) + q;
+    let mut node = hash(identifier, r, D_LEAF, &[&ots_public_key]);
+    try!(signature.path.read_all(error::Unspecified, |path| {
+        while r > 1 {
+            let sibling =
+                try!(path.skip_and_get_input(HASH_LEN)).as_slice_less_safe();
+            node = if r & 1 == 1 {
+                hash(identifier, r / 2, D_INTR, &[sibling, &node])
+            } else {
+                hash(identifier, r / 2, D_INTR, &[&node, sibling])
+            };
+            r /= 2;
+        }
+        Ok(())
+    }));
+    constant_time::verify_slices_are_equal(&node, public_key.root)
+}
+
+// The message hash followed by its checksum, RFC 8554 Section 4.4, whose
+// `w`-bit digits select how far along its hash chain each element of the
+// LM-OTS signature is.
+fn message_digits(lmots: &LMOTSAlgorithm, identifier: &[u8], q: u32,
+                  randomizer: &[u8], msg: &[u8]) -> [u8; HASH_LEN + 2] {
+    let msg_hash = hash(identifier, q, D_MESG, &[randomizer, msg]);
+    let max = (1 << lmots.w) - 1;
+    let checksum = (0..(HASH_LEN * 8 / lmots.w)).fold(0, |acc, i| {
+        acc + max - coefficient(&msg_hash, i, lmots.w)
+    }) << lmots.ls;
+    let mut digits = [0u8; HASH_LEN + 2];
+    digits[..HASH_LEN].copy_from_slice(&msg_hash);
+    digits[HASH_LEN..].copy_from_slice(&be_u8_from_u16(checksum as u16));
+    digits
+}
+
+// RFC 8554 Section 3.1.3.
+fn coefficient(s: &[u8], i: usize, w: usize) -> usize {
+    let byte = s[i * w / 8] as usize;
+    let shift = 8 - (w * (i % (8 / w)) + w);
+    (byte >> shift) & ((1 << w) - 1)
+}
+
+// Advances `tmp` along hash chain `i` of LM-OTS key `q`, from step `from` to
+// step `to`.
+fn chain(identifier: &[u8], q: u32, i: usize, tmp: &mut [u8; HASH_LEN],
+         from: usize, to: usize) {
+    for j in from..to {
+        *tmp = hash(identifier, q, i as u16, &[&[j as u8], &tmp[..]]);
+    }
+}
+
+// H(I || u32str(q) || u16str(d) || parts...), the form of all the hashes of
+// RFC 8554.
+fn hash(identifier: &[u8], q: u32, d: u16, parts: &[&[u8]])
+        -> [u8; HASH_LEN] {
+    let mut ctx = digest::Context::new(&digest::SHA256);
+    ctx.update(identifier);
+    ctx.update(&polyfill::slice::be_u8_from_u32(q));
+    ctx.update(&be_u8_from_u16(d));
+    for part in parts {
+        ctx.update(part);
+    }
+    finish(ctx)
+}
+
+fn finish(ctx: digest::Context) -> [u8; HASH_LEN] {
+    let mut result = [0u8; HASH_LEN];
+    result.copy_from_slice(ctx.finish().as_ref());
+    result
+}
+
+fn be_u8_from_u16(value: u16) -> [u8; 2] {
+    [(value >> 8) as u8, value as u8]
+}
+
+// Checks the levels of a key that can be signed with and returns the number
+// of messages that it can sign.
+#[cfg(feature = "use_heap")]
+fn check_levels(levels: &[Level]) -> Result<u64, error::Unspecified> {
+    try!(check_num_levels(levels.len()));
+    let total_height = levels.iter().fold(0, |acc, l| acc + l.lms.height);
+    if total_height >= 64 {
+        return Err(error::Unspecified);
+    }
+    Ok(1 << total_height)
+}
+
+fn lms_algorithm(id: u32)
+                 -> Result<&'static LMSAlgorithm, error::Unspecified> {
+    LMS_ALGORITHMS.iter()
+        .map(|&alg| alg)
+        .find(|alg| alg.id == id)
+        .ok_or(error::Unspecified)
+}
+
+fn lmots_algorithm(id: u32)
+                   -> Result<&'static LMOTSAlgorithm, error::Unspecified> {
+    LMOTS_ALGORITHMS.iter()
+        .map(|&alg| alg)
+        .find(|alg| alg.id == id)
+        .ok_or(error::Unspecified)
+}
+
+fn same_level(a: &Level, b: &Level) -> bool {
+    a.lms.id == b.lms.id && a.lmots.id == b.lmots.id
+}
+
+fn check_num_levels(num_levels: usize) -> Result<(), error::Unspecified> {
+    if num_levels < 1 || num_levels > MAX_LEVELS {
+        return Err(error::Unspecified);
+    }
+    Ok(())
+}
+
+fn read_u32(input: &mut untrusted::Reader) -> Result<u32, error::Unspecified> {
+    let bytes = try!(input.skip_and_get_input(4));
+    let bytes = slice_as_array_ref!(bytes.as_slice_less_safe(), 4).unwrap();
+    Ok(polyfill::slice::u32_from_be_u8(bytes))
+}
+
+static LMS_ALGORITHMS: [&'static LMSAlgorithm; 5] = [
+    &LMS_SHA256_M32_H5,
+    &LMS_SHA256_M32_H10,
+    &LMS_SHA256_M32_H15,
+    &LMS_SHA256_M32_H20,
+    &LMS_SHA256_M32_H25,
+];
+
+static LMOTS_ALGORITHMS: [&'static LMOTSAlgorithm; 4] = [
+    &LMOTS_SHA256_N32_W1,
+    &LMOTS_SHA256_N32_W2,
+    &LMOTS_SHA256_N32_W4,
+    &LMOTS_SHA256_N32_W8,
+];
+
+// The length of the hashes, `n` and `m` in RFC 8554.
+const HASH_LEN: usize = 32;
+
+// The length of the key pair identifier, `I` in RFC 8554.
+const IDENTIFIER_LEN: usize = 16;
+
+// The type of the LMS algorithm, the type of the LM-OTS algorithm, the
+// identifier, and the root of the tree.
+const LMS_PUBLIC_KEY_LEN: usize = 4 + 4 + IDENTIFIER_LEN + HASH_LEN;
+
+// The length of the seed from which the private keys are derived.
+#[cfg(feature = "use_heap")]
+const SEED_LEN: usize = 32;
+
+// The domain separation parameters of RFC 8554 Section 3.1.
+const D_PBLC: u16 = 0x8080;
+const D_MESG: u16 = 0x8181;
+const D_LEAF: u16 = 0x8282;
+const D_INTR: u16 = 0x8383;
+
+// The LM-OTS private key elements are derived from the seed as
+// `H(I || u32str(q) || u16str(i) || u8str(0xff) || SEED)`, as suggested in
+// RFC 8554 Appendix A. The identifier and seed of the child of leaf `q`, and
+// the randomizer of the signature of the child's public key, are derived the
+// same way, with values of `i` that are never used for LM-OTS keys.
+#[cfg(feature = "use_heap")]
+const DERIVE: u8 = 0xff;
+#[cfg(feature = "use_heap")]
+const CHILD_RANDOMIZER: u16 = 0xfffd;
+#[cfg(feature = "use_heap")]
+const CHILD_IDENTIFIER: u16 = 0xfffe;
+#[cfg(feature = "use_heap")]
+const CHILD_SEED: u16 = 0xffff;
+
+
+#[cfg(test)]
+mod tests {
+    use core;
+    use {error, polyfill, signature, test};
+    use rand;
+    use rand::test_util::FixedSliceRandom;
+    use signature::KeyPair;
+    use std::vec::Vec;
+    use super::*;
+    use untrusted;
+
+    fn lms_public_key(level: &Level, out: &mut Vec<u8>) {
+        out.extend_from_slice(&polyfill::slice::be_u8_from_u32(level.lms.id));
+        out.extend_from_slice(
+            &polyfill::slice::be_u8_from_u32(level.lmots.id));
+        out.extend_from_slice(&[0xa5; IDENTIFIER_LEN + HASH_LEN]);
+    }
+
+    fn lms_signature(level: &Level, leaf_index: u32, out: &mut Vec<u8>) {
+        out.extend_from_slice(&polyfill::slice::be_u8_from_u32(leaf_index));
+        out.extend_from_slice(
+            &polyfill::slice::be_u8_from_u32(level.lmots.id));
+        out.extend_from_slice(&vec![0xa5; HASH_LEN * (level.lmots.p + 1)]);
+        out.extend_from_slice(&polyfill::slice::be_u8_from_u32(level.lms.id));
+        out.extend_from_slice(&vec![0xa5; HASH_LEN * level.lms.height]);
+    }
+
+    // A signature by a key with the given levels, with the given leaf
+    // indices.
+    fn hss_signature(levels: &[Level], leaf_indices: &[u32]) -> Vec<u8> {
+        let mut signature = Vec::new();
+        signature.extend_from_slice(
+            &polyfill::slice::be_u8_from_u32(levels.len() as u32 - 1));
+        for (i, (level, &leaf_index)) in
+                levels.iter().zip(leaf_indices).enumerate() {
+            lms_signature(level, leaf_index, &mut signature);
+            if i + 1 < levels.len() {
+                lms_public_key(&levels[i + 1], &mut signature);
+            }
+        }
+        signature
+    }
+
+    #[test]
+    fn test_lms_sizes() {
+        // RFC 8554 Section 4.1 and Section 5.1.
+        assert_eq!(LMOTS_SHA256_N32_W1.signature_len(), 8516);
+        assert_eq!(LMOTS_SHA256_N32_W2.signature_len(), 4292);
+        assert_eq!(LMOTS_SHA256_N32_W4.signature_len(), 2180);
+        assert_eq!(LMOTS_SHA256_N32_W8.signature_len(), 1124);
+        assert_eq!(LMS_SHA256_M32_H5.signature_len(&LMOTS_SHA256_N32_W8),
+                   1292);
+        assert_eq!(LMS_SHA256_M32_H25.num_signatures(), 1 << 25);
+
+        let level = Level { lms: &LMS_SHA256_M32_H25,
+                            lmots: &LMOTS_SHA256_N32_W1 };
+        assert_eq!(num_signatures(&[level; 2]).unwrap(), 1 << 50);
+        assert_eq!(num_signatures(&[level; 3]).unwrap(), core::u64::MAX);
+        assert!(num_signatures(&[]).is_err());
+        assert!(num_signatures(&[level; MAX_LEVELS + 1]).is_err());
+        assert!(signature_len(&[]).is_err());
+        assert!(signature_len(&[level; MAX_LEVELS + 1]).is_err());
+    }
+
+    #[test]
+    fn test_lms_public_key_info() {
+        let level = Level { lms: &LMS_SHA256_M32_H5,
+                            lmots: &LMOTS_SHA256_N32_W8 };
+        let mut public_key = vec![0, 0, 0, 2];
+        lms_public_key(&level, &mut public_key);
+        assert_eq!(public_key.len(), PUBLIC_KEY_LEN);
+        let info =
+            PublicKeyInfo::from_bytes(untrusted::Input::from(&public_key))
+                .unwrap();
+        assert_eq!(info.num_levels(), 2);
+        assert!(same_level(&info.top_level(), &level));
+
+        let check_invalid = |i: usize, value: u8| {
+            let mut invalid = public_key.clone();
+            invalid[i] = value;
+            assert!(PublicKeyInfo::from_bytes(untrusted::Input::from(&invalid))
+                        .is_err());
+        };
+        check_invalid(3, 0);
+        check_invalid(3, MAX_LEVELS as u8 + 1);
+        check_invalid(7, 4);
+        check_invalid(7, 10);
+        check_invalid(11, 0);
+        check_invalid(11, 5);
+        assert!(PublicKeyInfo::from_bytes(
+            untrusted::Input::from(&public_key[..(PUBLIC_KEY_LEN - 1)]))
+                .is_err());
+    }
+
+    #[test]
+    fn test_lms_signature_info() {
+        let levels = [
+            Level { lms: &LMS_SHA256_M32_H10, lmots: &LMOTS_SHA256_N32_W4 },
+            Level { lms: &LMS_SHA256_M32_H5, lmots: &LMOTS_SHA256_N32_W8 },
+        ];
+        let cases: [(&[u32], u64); 4] = [
+            (&[0, 0], (1 << 15) - 1),
+            (&[3, 31], 32640),
+            (&[1023, 30], 1),
+            (&[1023, 31], 0),
+        ];
+        for &(leaf_indices, remaining) in cases.iter() {
+            let signature = hss_signature(&levels, leaf_indices);
+            assert_eq!(signature.len(), signature_len(&levels).unwrap());
+            let info =
+                SignatureInfo::from_bytes(untrusted::Input::from(&signature))
+                    .unwrap();
+            assert_eq!(info.levels().len(), 2);
+            assert!(same_level(&info.levels()[0], &levels[0]));
+            assert!(same_level(&info.levels()[1], &levels[1]));
+            assert_eq!(info.leaf_indices(), leaf_indices);
+            assert_eq!(info.remaining_signatures(), remaining);
+        }
+
+        let level = Level { lms: &LMS_SHA256_M32_H25,
+                            lmots: &LMOTS_SHA256_N32_W8 };
+        let signature = hss_signature(&[level; 3], &[0, 0, 0]);
+        let info =
+            SignatureInfo::from_bytes(untrusted::Input::from(&signature))
+                .unwrap();
+        assert_eq!(info.remaining_signatures(), core::u64::MAX);
+
+        // Leaf indices out of range, truncated and extended signatures, and
+        // signatures whose parameters don't match the signed public key are
+        // rejected.
+        let mut extended = hss_signature(&levels, &[0, 0]);
+        extended.push(0);
+        let mut truncated = hss_signature(&levels, &[0, 0]);
+        let _ = truncated.pop();
+        let mut mismatched = hss_signature(&levels[..1], &[0]);
+        mismatched[3] = 1;
+        lms_public_key(&levels[0], &mut mismatched);
+        lms_signature(&levels[1], 0, &mut mismatched);
+        let invalid = [
+            hss_signature(&levels, &[1024, 0]),
+            hss_signature(&levels, &[0, 32]),
+            extended,
+            truncated,
+            mismatched,
+        ];
+        for signature in invalid.iter() {
+            assert!(SignatureInfo::from_bytes(untrusted::Input::from(signature))
+                        .is_err());
+        }
+    }
+
+    fn verify(public_key: &[u8], msg: &[u8], signature: &[u8])
+              -> Result<(), error::Unspecified> {
+        signature::verify(&HSS_SHA256, untrusted::Input::from(public_key),
+                          untrusted::Input::from(msg),
+                          untrusted::Input::from(signature))
+    }
+
+    #[test]
+    fn test_hss_sign_verify() {
+        test::from_file("src/lms_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let private_key = test_case.consume_bytes("PrivateKey");
+            let next_index = test_case.consume_usize("NextIndex") as u64;
+            let public_key = test_case.consume_bytes("PublicKey");
+            let randomizer = test_case.consume_bytes("Randomizer");
+            let msg = test_case.consume_bytes("Message");
+            let expected = test_case.consume_bytes("Signature");
+
+            let mut key_pair = HSSKeyPair::from_private_key(
+                untrusted::Input::from(&private_key), next_index).unwrap();
+            assert_eq!(&key_pair.public_key()[..], &public_key[..]);
+
+            let mut persisted = None;
+            let actual = key_pair.sign(&msg,
+                                       &FixedSliceRandom { bytes: &randomizer },
+                                       |next_index| {
+                persisted = Some(next_index);
+                Ok(())
+            }).unwrap();
+            assert_eq!(persisted, Some(next_index + 1));
+            assert_eq!(key_pair.next_index(), next_index + 1);
+            assert_eq!(actual, expected);
+
+            assert!(verify(&public_key, &msg, &expected).is_ok());
+            let info =
+                SignatureInfo::from_bytes(untrusted::Input::from(&expected))
+                    .unwrap();
+            assert_eq!(info.remaining_signatures(),
+                       key_pair.remaining_signatures());
+
+            // Verification fails if the message, the public key, or the
+            // signature is changed. The first byte of an OTS signature
+            // element of each level is changed.
+            let mut wrong_msg = msg.clone();
+            wrong_msg.push(0);
+            assert!(verify(&public_key, &wrong_msg, &expected).is_err());
+            let mut wrong_public_key = public_key.clone();
+            wrong_public_key[PUBLIC_KEY_LEN - 1] ^= 1;
+            assert!(verify(&wrong_public_key, &msg, &expected).is_err());
+            let mut offset = 4;
+            for level in info.levels() {
+                for &i in &[offset + 8, offset + 8 + HASH_LEN,
+                            offset + level.lms.signature_len(level.lmots) - 1] {
+                    let mut wrong = expected.clone();
+                    wrong[i] ^= 1;
+                    assert!(verify(&public_key, &msg, &wrong).is_err());
+                }
+                offset += level.lms.signature_len(level.lmots) +
+                          LMS_PUBLIC_KEY_LEN;
+            }
+            let mut truncated = expected.clone();
+            let _ = truncated.pop();
+            assert!(verify(&public_key, &msg, &truncated).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_hss_exhaustion() {
+        let rng = rand::SystemRandom::new();
+        let levels = [
+            Level { lms: &LMS_SHA256_M32_H5, lmots: &LMOTS_SHA256_N32_W2 },
+        ];
+        let private_key =
+            HSSKeyPair::generate_private_key(&levels, &rng).unwrap();
+        let private_key = untrusted::Input::from(&private_key);
+        let mut key_pair =
+            HSSKeyPair::from_private_key(private_key, 30).unwrap();
+        let public_key = *key_pair.public_key();
+        assert_eq!(key_pair.remaining_signatures(), 2);
+        for i in 30..32 {
+            let msg = [i as u8];
+            let signature = key_pair.sign(&msg, &rng, |next_index| {
+                assert_eq!(next_index, i + 1);
+                Ok(())
+            }).unwrap();
+            assert!(verify(&public_key, &msg, &signature).is_ok());
+        }
+        assert_eq!(key_pair.remaining_signatures(), 0);
+        assert!(key_pair.sign(b"", &rng, |_| Ok(())).is_err());
+
+        let key_pair = HSSKeyPair::from_private_key(private_key, 32).unwrap();
+        assert_eq!(&key_pair.public_key()[..], &public_key[..]);
+        assert_eq!(key_pair.remaining_signatures(), 0);
+        assert!(HSSKeyPair::from_private_key(private_key, 33).is_err());
+
+        let too_many = [
+            Level { lms: &LMS_SHA256_M32_H25, lmots: &LMOTS_SHA256_N32_W8 };
+            3
+        ];
+        assert!(HSSKeyPair::generate_private_key(&too_many, &rng).is_err());
+        assert!(HSSKeyPair::generate_private_key(&[], &rng).is_err());
+    }
+
+    #[test]
+    fn test_hss_persist_failure() {
+        let rng = rand::SystemRandom::new();
+        let levels = [
+            Level { lms: &LMS_SHA256_M32_H5, lmots: &LMOTS_SHA256_N32_W2 },
+        ];
+        let private_key =
+            HSSKeyPair::generate_private_key(&levels, &rng).unwrap();
+        let mut key_pair = HSSKeyPair::from_private_key(
+            untrusted::Input::from(&private_key), 0).unwrap();
+        assert!(key_pair.sign(b"", &rng, |_| Err(error::Unspecified))
+                        .is_err());
+        assert_eq!(key_pair.next_index(), 0);
+        let signature = key_pair.sign(b"", &rng, |_| Ok(())).unwrap();
+        let info = SignatureInfo::from_bytes(untrusted::Input::from(&signature))
+            .unwrap();
+        assert_eq!(info.leaf_indices(), &[0]);
+        assert_eq!(key_pair.next_index(), 1);
+    }
+}
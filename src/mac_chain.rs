@@ -0,0 +1,213 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! HMAC-chained logs and monotonic counters.
+//!
+//! A `Chain` authenticates a sequence of entries, such as the records of an
+//! audit log or the versions of firmware that have been installed, so that
+//! entries can't be modified, removed, reordered, or rolled back without
+//! knowledge of the key. Each entry is MACed together with the state before
+//! it:
+//!
+//! ```text
+//! T_0 = 0^n
+//! T_i = HMAC(K, T_(i-1) || [i]_64 || entry_i)
+//! ```
+//!
+//! where `n` is the digest output length and `[i]_64` is the 64-bit
+//! big-endian encoding of the counter `i`. The state `(i, T_i)` is the
+//! checkpoint after `i` entries.
+//!
+//! To verify a log, or any prefix of it, append its entries to a new `Chain`
+//! and then `verify` the expected tag. For rollback protection, store the
+//! latest checkpoint somewhere the attacker can't roll back, e.g. in a
+//! hardware monotonic counter plus secure storage; a claimed later state is
+//! then verified by using `Chain::resume` with the stored checkpoint and
+//! appending the entries since then.
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::{digest, hmac, mac_chain};
+//!
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! let key = hmac::SigningKey::new(&digest::SHA256, b"log key");
+//!
+//! let mut log = mac_chain::Chain::new(&key);
+//! try!(log.append(b"boot"));
+//! let checkpoint_counter = log.counter();
+//! let checkpoint_tag = Vec::from(log.tag());
+//! try!(log.append(b"firmware update 1.2.3"));
+//! let latest_tag = Vec::from(log.tag());
+//!
+//! // Verify the entries after the checkpoint.
+//! let mut verifier = try!(mac_chain::Chain::resume(&key, checkpoint_counter,
+//!                                                  &checkpoint_tag));
+//! try!(verifier.append(b"firmware update 1.2.3"));
+//! try!(verifier.verify(&latest_tag));
+//! # Ok(())
+//! # }
+//! ```
+
+use {constant_time, digest, error, hmac, polyfill};
+
+/// The state of an HMAC chain.
+pub struct Chain<'a> {
+    key: &'a hmac::SigningKey,
+    counter: u64,
+    tag: [u8; digest::MAX_OUTPUT_LEN],
+}
+
+impl<'a> Chain<'a> {
+    /// Constructs an empty chain, with the checkpoint `(0, T_0)`.
+    pub fn new(key: &'a hmac::SigningKey) -> Chain<'a> {
+        Chain {
+            key: key,
+            counter: 0,
+            tag: [0u8; digest::MAX_OUTPUT_LEN],
+        }
+    }
+
+    /// Constructs a chain that continues from the checkpoint `(counter, tag)`.
+    ///
+    /// The checkpoint isn't (and can't be) verified; it must come from
+    /// trusted storage, or a tag that is later computed from it must be
+    /// checked with `verify`. Fails if `tag` isn't the length of the key's
+    /// digest output.
+    pub fn resume(key: &'a hmac::SigningKey, counter: u64, tag: &[u8])
+                  -> Result<Chain<'a>, error::Unspecified> {
+        let output_len = key.digest_algorithm().output_len;
+        if tag.len() != output_len {
+            return Err(error::Unspecified);
+        }
+        let mut chain = Chain::new(key);
+        chain.counter = counter;
+        chain.tag[..output_len].copy_from_slice(tag);
+        Ok(chain)
+    }
+
+    /// Appends `entry` to the chain, advancing the counter by one.
+    ///
+    /// Fails, without changing the chain, if the counter would overflow.
+    pub fn append(&mut self, entry: &[u8]) -> Result<(), error::Unspecified> {
+        let counter = try!(self.counter.checked_add(1)
+                               .ok_or(error::Unspecified));
+        let mut ctx = hmac::SigningContext::with_key(self.key);
+        ctx.update(self.tag());
+        ctx.update(&polyfill::slice::be_u8_from_u32((counter >> 32) as u32));
+        ctx.update(&polyfill::slice::be_u8_from_u32(counter as u32));
+        ctx.update(entry);
+        let tag = ctx.sign();
+        self.tag[..tag.as_ref().len()].copy_from_slice(tag.as_ref());
+        self.counter = counter;
+        Ok(())
+    }
+
+    /// The number of entries in the chain, including those before the
+    /// checkpoint it was resumed from.
+    #[inline(always)]
+    pub fn counter(&self) -> u64 { self.counter }
+
+    /// The current tag `T_i`, where `i` is `counter()`.
+    pub fn tag(&self) -> &[u8] {
+        &self.tag[..self.key.digest_algorithm().output_len]
+    }
+
+    /// Verifies, in constant time, that `tag` is the current tag.
+    pub fn verify(&self, tag: &[u8]) -> Result<(), error::Unspecified> {
+        constant_time::verify_slices_are_equal(self.tag(), tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {digest, hmac, mac_chain, test};
+    use std::vec::Vec;
+
+    #[test]
+    fn test_mac_chain() {
+        test::from_file("src/mac_chain_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let digest_alg = test_case.consume_digest_alg("Digest").unwrap();
+            let key = test_case.consume_bytes("Key");
+            let counter =
+                test_case.consume_string("Counter").parse::<u64>().unwrap();
+            let prev_tag = test_case.consume_bytes("PrevTag");
+            let entry = test_case.consume_bytes("Entry");
+            let expected_tag = test_case.consume_bytes("Tag");
+
+            let key = hmac::SigningKey::new(digest_alg, &key);
+            let mut chain =
+                mac_chain::Chain::resume(&key, counter, &prev_tag).unwrap();
+            chain.append(&entry).unwrap();
+            assert_eq!(chain.counter(), counter + 1);
+            assert_eq!(chain.tag(), &expected_tag[..]);
+            assert!(chain.verify(&expected_tag).is_ok());
+            assert!(chain.verify(&prev_tag).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_mac_chain_prefixes() {
+        let key = hmac::SigningKey::new(&digest::SHA256, b"key");
+        let entries: [&[u8]; 4] = [b"a", b"b", b"c", b"d"];
+
+        let mut chain = mac_chain::Chain::new(&key);
+        let mut tags = vec![Vec::from(chain.tag())];
+        for entry in entries.iter() {
+            chain.append(entry).unwrap();
+            tags.push(Vec::from(chain.tag()));
+        }
+
+        for len in 0..(entries.len() + 1) {
+            let mut chain = mac_chain::Chain::new(&key);
+            for entry in entries[..len].iter() {
+                chain.append(entry).unwrap();
+            }
+            for (i, tag) in tags.iter().enumerate() {
+                assert_eq!(chain.verify(tag).is_ok(), i == len);
+            }
+        }
+
+        // Reordered entries.
+        let mut chain = mac_chain::Chain::new(&key);
+        for entry in [b"b", b"a"].iter() {
+            chain.append(*entry).unwrap();
+        }
+        assert!(chain.verify(&tags[2]).is_err());
+
+        // Rollback to an earlier checkpoint can't produce a later tag with a
+        // different history.
+        let mut chain = mac_chain::Chain::resume(&key, 1, &tags[1]).unwrap();
+        chain.append(b"x").unwrap();
+        assert!(chain.verify(&tags[2]).is_err());
+    }
+
+    #[test]
+    fn test_mac_chain_bad_state() {
+        let key = hmac::SigningKey::new(&digest::SHA384, b"key");
+        assert!(mac_chain::Chain::resume(&key, 0, &[0u8; 32]).is_err());
+        assert!(mac_chain::Chain::resume(&key, 0, &[0u8; 64]).is_err());
+
+        let mut chain =
+            mac_chain::Chain::resume(&key, 0xffffffffffffffff, &[0u8; 48])
+                .unwrap();
+        assert!(chain.append(b"").is_err());
+        assert_eq!(chain.counter(), 0xffffffffffffffff);
+        assert_eq!(chain.tag(), &[0u8; 48][..]);
+    }
+}
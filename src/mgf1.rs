@@ -0,0 +1,173 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! MGF1, the mask generation function of PKCS#1, as specified in
+//! [RFC 8017 Appendix B.2.1].
+//!
+//! The mask for a seed is the concatenation of the digests of the seed
+//! followed by a 32-bit big-endian counter, starting at zero, truncated to
+//! the length of the mask. This is the mask generation function of RSA-OAEP
+//! and RSA-PSS, which *ring* uses internally; it is also used on its own,
+//! e.g. by some KDF profiles.
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::{digest, mgf1};
+//!
+//! # fn main() { foo().unwrap() }
+//! # fn foo() -> Result<(), ring::error::Unspecified> {
+//! let mut mask = [0u8; 100];
+//! try!(mgf1::generate(&digest::SHA256, b"seed", &mut mask));
+//!
+//! // Masking twice with the same seed restores the original value.
+//! let mut db = mask;
+//! try!(mgf1::xor_mask(&digest::SHA256, b"seed", &mut db));
+//! assert!(db.iter().all(|b| *b == 0));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [RFC 8017 Appendix B.2.1]: https://tools.ietf.org/html/rfc8017#appendix-B.2.1
+
+use {digest, error, init, polyfill};
+
+/// Fills `out` with the MGF1 mask for `seed`, using `digest_alg`.
+///
+/// Fails if `out` is longer than 2**32 digests of `digest_alg`, which is the
+/// maximum length of an MGF1 mask.
+pub fn generate(digest_alg: &'static digest::Algorithm, seed: &[u8],
+                out: &mut [u8]) -> Result<(), error::Unspecified> {
+    polyfill::slice::fill(out, 0);
+    xor_mask(digest_alg, seed, out)
+}
+
+/// XORs the MGF1 mask for `seed`, using `digest_alg`, into `out`, which is
+/// how both RSA-OAEP and RSA-PSS use the mask.
+///
+/// Fails if `out` is longer than 2**32 digests of `digest_alg`, which is the
+/// maximum length of an MGF1 mask, in which case `out` is left unmodified.
+pub fn xor_mask(digest_alg: &'static digest::Algorithm, seed: &[u8],
+                out: &mut [u8]) -> Result<(), error::Unspecified> {
+    init::init_once();
+
+    let num_blocks =
+        polyfill::u64_from_usize(out.len() / digest_alg.output_len);
+    if num_blocks > (1u64 << 32) ||
+       (num_blocks == (1u64 << 32) && out.len() % digest_alg.output_len != 0) {
+        return Err(error::Unspecified);
+    }
+
+    for (i, out) in out.chunks_mut(digest_alg.output_len).enumerate() {
+        try!(xor_mask_block(digest_alg, seed, i, out));
+    }
+    Ok(())
+}
+
+/// XORs the `block`-th digest-sized block of the MGF1 mask for `seed`, using
+/// `digest_alg`, into `out`, which must be at most `digest_alg.output_len`
+/// bytes long.
+///
+/// This allows a long mask to be applied piece by piece, without a buffer
+/// for the whole masked value. Fails if `out` is too long or if `block` is
+/// 2**32 or more.
+pub fn xor_mask_block(digest_alg: &'static digest::Algorithm, seed: &[u8],
+                      block: usize, out: &mut [u8])
+                      -> Result<(), error::Unspecified> {
+    init::init_once();
+
+    if out.len() > digest_alg.output_len ||
+       polyfill::u64_from_usize(block) >= (1u64 << 32) {
+        return Err(error::Unspecified);
+    }
+    let mut ctx = digest::Context::new(digest_alg);
+    ctx.update(seed);
+    // The counter fits in 32 bits because of the check above.
+    let counter = block as u32;
+    ctx.update(&polyfill::slice::be_u8_from_u32(counter));
+    let mask = ctx.finish();
+    for (out, mask) in out.iter_mut().zip(mask.as_ref()) {
+        *out ^= *mask;
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {core, digest, test};
+    use super::*;
+
+    #[test]
+    fn test_mgf1() {
+        test::from_file("src/mgf1_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let digest_alg = test_case.consume_digest_alg("Digest").unwrap();
+            let seed = test_case.consume_bytes("Seed");
+            let expected = test_case.consume_bytes("Mask");
+
+            let mut actual = vec![0xffu8; expected.len()];
+            try!(generate(digest_alg, &seed, &mut actual));
+            assert_eq!(actual, expected);
+
+            // Each prefix of the mask is the mask of that length.
+            for len in 0..expected.len() {
+                let mut actual = vec![0u8; len];
+                try!(generate(digest_alg, &seed, &mut actual));
+                assert_eq!(&actual[..], &expected[..len]);
+            }
+
+            let mut masked = vec![0x5au8; expected.len()];
+            try!(xor_mask(digest_alg, &seed, &mut masked));
+            for (masked, mask) in masked.iter().zip(expected.iter()) {
+                assert_eq!(*masked, 0x5a ^ *mask);
+            }
+
+            // Applying the mask block by block is the same as applying it
+            // all at once.
+            let mut masked = vec![0x5au8; expected.len()];
+            for (i, out) in
+                    masked.chunks_mut(digest_alg.output_len).enumerate() {
+                try!(xor_mask_block(digest_alg, &seed, i, out));
+            }
+            for (masked, mask) in masked.iter().zip(expected.iter()) {
+                assert_eq!(*masked, 0x5a ^ *mask);
+            }
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_mgf1_empty() {
+        let mut out = [];
+        assert!(generate(&digest::SHA256, b"seed", &mut out).is_ok());
+        assert!(xor_mask(&digest::SHA256, b"", &mut out).is_ok());
+    }
+
+    #[test]
+    fn test_mgf1_xor_mask_block_bounds() {
+        let mut out = [0u8; 33];
+        assert!(xor_mask_block(&digest::SHA256, b"", 0, &mut out[..32])
+                    .is_ok());
+        assert!(xor_mask_block(&digest::SHA256, b"", 0, &mut out).is_err());
+        if core::usize::MAX > 0xffffffff {
+            let block = 0xffffffff;
+            assert!(xor_mask_block(&digest::SHA256, b"", block, &mut out[..1])
+                        .is_ok());
+            assert!(xor_mask_block(&digest::SHA256, b"", block + 1,
+                                   &mut out[..1]).is_err());
+        }
+    }
+}
@@ -0,0 +1,401 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Encryption of one message to multiple recipients.
+//!
+//! `seal` encrypts the message once, with an AEAD and a random content key,
+//! and then wraps the content key to each recipient's public key, so the
+//! message isn't encrypted again for each recipient. This is the structure of
+//! CMS `EnvelopedData` and of OpenPGP messages with several recipients, e.g.
+//! for group messaging and for backups that several people can restore.
+//!
+//! The content key is wrapped in one of two ways:
+//!
+//! * For a key agreement public key, e.g. X25519, a new ephemeral key pair is
+//!   generated for each recipient. A key-encryption key is derived from the
+//!   shared secret with HKDF-SHA256, using the ephemeral and the recipient's
+//!   public keys as the salt, and the content key is wrapped with it using
+//!   AES-256 key wrap (`aead::aes_kw`).
+//!
+//! * For an RSA public key, the content key is encrypted with RSA-OAEP with
+//!   SHA-256 and an empty label (`rsa::oaep::RSA_OAEP_SHA256`).
+//!
+//! Since each content key encrypts only one message, the content is
+//! encrypted with an all-zero nonce. How the wrapped keys and the ciphertext
+//! are stored or sent is up to the application; each recipient needs the
+//! wrapped key that is for them, and the ciphertext.
+//!
+//! Feature: `use_heap`.
+//!
+//! # Examples
+//!
+//! ```
+//! extern crate ring;
+//! extern crate untrusted;
+//!
+//! use ring::{aead, agreement, multi_recipient, rand};
+//!
+//! # fn main() { foo().unwrap() }
+//! # fn foo() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//!
+//! // The recipients' long-term X25519 keys.
+//! let seeds = [[1u8; 32], [2u8; 32]];
+//! let mut public_keys = [[0u8; 32]; 2];
+//! for (seed, public_key) in seeds.iter().zip(public_keys.iter_mut()) {
+//!     let private_key = try!(agreement::EphemeralPrivateKey::from_seed(
+//!         &agreement::X25519, seed));
+//!     try!(private_key.compute_public_key(public_key));
+//! }
+//!
+//! let recipients = [
+//!     multi_recipient::Recipient::Agreement(
+//!         &agreement::X25519, untrusted::Input::from(&public_keys[0])),
+//!     multi_recipient::Recipient::Agreement(
+//!         &agreement::X25519, untrusted::Input::from(&public_keys[1])),
+//! ];
+//! let sealed = try!(multi_recipient::seal(&aead::CHACHA20_POLY1305,
+//!                                         &recipients, b"", b"hello",
+//!                                         &rng));
+//!
+//! // The second recipient opens it.
+//! let private_key = try!(agreement::EphemeralPrivateKey::from_seed(
+//!     &agreement::X25519, &seeds[1]));
+//! let plaintext = try!(multi_recipient::open_with_agreement_key(
+//!     &aead::CHACHA20_POLY1305, private_key, &sealed.wrapped_keys[1],
+//!     &sealed.ciphertext, b""));
+//! assert_eq!(plaintext, b"hello");
+//! # Ok(())
+//! # }
+//! ```
+
+use {aead, agreement, digest, ec, error, hkdf, hmac, rand, secret};
+use aead::aes_kw;
+use core;
+#[cfg(feature = "rsa_signing")]
+use signature;
+use rsa::oaep;
+use std::vec::Vec;
+use untrusted;
+
+/// The public key of a recipient.
+pub enum Recipient<'a> {
+    /// A key agreement public key, e.g. an X25519 public key, encoded in the
+    /// standard form for the algorithm.
+    Agreement(&'static agreement::Algorithm, untrusted::Input<'a>),
+
+    /// An RSA public key, as a DER-encoded ASN.1 `RSAPublicKey`, as for
+    /// `rsa::oaep::encrypt`.
+    RsaOaep(untrusted::Input<'a>),
+}
+
+/// A content key, wrapped to one recipient.
+pub enum WrappedKey {
+    /// A content key wrapped to a `Recipient::Agreement` key.
+    Agreement {
+        /// The public key of the ephemeral key pair.
+        ephemeral_public_key: Vec<u8>,
+
+        /// The AES-KW-wrapped content key.
+        wrapped_key: Vec<u8>,
+    },
+
+    /// A content key wrapped to a `Recipient::RsaOaep` key.
+    RsaOaep {
+        /// The RSA-OAEP ciphertext of the content key.
+        wrapped_key: Vec<u8>,
+    },
+}
+
+/// The result of `seal`.
+pub struct Sealed {
+    /// The wrapped content keys, in the same order as the recipients.
+    pub wrapped_keys: Vec<WrappedKey>,
+
+    /// The ciphertext and tag of the message.
+    pub ciphertext: Vec<u8>,
+}
+
+/// Encrypts `plaintext`, with the additional authenticated data `ad`, using
+/// `content_alg` and a random content key, and wraps the content key to each
+/// of `recipients`.
+///
+/// Fails if `recipients` is empty or if any of the recipients' public keys
+/// is invalid.
+pub fn seal(content_alg: &'static aead::Algorithm, recipients: &[Recipient],
+            ad: &[u8], plaintext: &[u8], rng: &rand::SecureRandom)
+            -> Result<Sealed, error::Unspecified> {
+    if recipients.is_empty() {
+        return Err(error::Unspecified);
+    }
+
+    let mut content_key = secret::SecretBox::new([0u8; MAX_CONTENT_KEY_LEN]);
+    let content_key = &mut content_key.expose_mut()[..content_alg.key_len()];
+    try!(rng.fill(content_key));
+
+    let mut wrapped_keys = Vec::with_capacity(recipients.len());
+    for recipient in recipients {
+        let wrapped_key = match *recipient {
+            Recipient::Agreement(alg, public_key) =>
+                try!(wrap_to_agreement_key(alg, public_key, content_key, rng)),
+            Recipient::RsaOaep(public_key) => WrappedKey::RsaOaep {
+                wrapped_key: try!(oaep::encrypt(&oaep::RSA_OAEP_SHA256,
+                                                public_key, &[], content_key,
+                                                rng)),
+            },
+        };
+        wrapped_keys.push(wrapped_key);
+    }
+
+    let key = try!(aead::SealingKey::new(content_alg, content_key));
+    let tag_len = content_alg.tag_len();
+    let mut ciphertext = plaintext.to_vec();
+    ciphertext.extend(core::iter::repeat(0).take(tag_len));
+    let _ = try!(aead::seal_in_place(&key, &NONCE[..content_alg.nonce_len()],
+                                     &mut ciphertext, tag_len, ad));
+
+    Ok(Sealed {
+        wrapped_keys: wrapped_keys,
+        ciphertext: ciphertext,
+    })
+}
+
+/// Unwraps the content key in `wrapped_key` with `private_key`, and then
+/// authenticates and decrypts `ciphertext`, with the additional
+/// authenticated data `ad`, using `content_alg`.
+///
+/// `private_key` is the recipient's long-term private key, e.g. constructed
+/// with `agreement::EphemeralPrivateKey::from_seed`.
+pub fn open_with_agreement_key(content_alg: &'static aead::Algorithm,
+                               private_key: agreement::EphemeralPrivateKey,
+                               wrapped_key: &WrappedKey, ciphertext: &[u8],
+                               ad: &[u8])
+                               -> Result<Vec<u8>, error::Unspecified> {
+    let (ephemeral_public_key, wrapped_key) = match *wrapped_key {
+        WrappedKey::Agreement { ref ephemeral_public_key, ref wrapped_key } =>
+            (ephemeral_public_key, wrapped_key),
+        _ => { return Err(error::Unspecified); },
+    };
+
+    let alg = private_key.algorithm();
+    let mut public_key = [0u8; ec::PUBLIC_KEY_MAX_LEN];
+    let public_key = &mut public_key[..private_key.public_key_len()];
+    try!(private_key.compute_public_key(public_key));
+
+    let kek = try!(agreement::agree_ephemeral(
+        private_key, alg, untrusted::Input::from(ephemeral_public_key),
+        error::Unspecified, |shared_secret| {
+            derive_kek(shared_secret, ephemeral_public_key, public_key)
+        }));
+
+    let mut content_key = secret::SecretBox::new([0u8; MAX_CONTENT_KEY_LEN]);
+    let content_key_len = try!(aes_kw::unwrap(&kek, wrapped_key,
+                                              content_key.expose_mut()));
+    open(content_alg, &content_key.expose()[..content_key_len], ciphertext,
+         ad)
+}
+
+/// Unwraps the content key in `wrapped_key` with `key_pair`, and then
+/// authenticates and decrypts `ciphertext`, with the additional
+/// authenticated data `ad`, using `content_alg`. `rng` is used for blinding
+/// the RSA private key operation.
+///
+/// Feature: `rsa_signing`.
+#[cfg(feature = "rsa_signing")]
+pub fn open_with_rsa_key(content_alg: &'static aead::Algorithm,
+                         key_pair: &signature::RSAKeyPair,
+                         wrapped_key: &WrappedKey, ciphertext: &[u8],
+                         ad: &[u8], rng: &rand::SecureRandom)
+                         -> Result<Vec<u8>, error::Unspecified> {
+    let wrapped_key = match *wrapped_key {
+        WrappedKey::RsaOaep { ref wrapped_key } => wrapped_key,
+        _ => { return Err(error::Unspecified); },
+    };
+    let content_key = secret::SecretBox::new(
+        try!(oaep::decrypt(&oaep::RSA_OAEP_SHA256, key_pair, &[], wrapped_key,
+                           rng)));
+    open(content_alg, content_key.expose(), ciphertext, ad)
+}
+
+fn wrap_to_agreement_key(alg: &'static agreement::Algorithm,
+                         public_key: untrusted::Input, content_key: &[u8],
+                         rng: &rand::SecureRandom)
+                         -> Result<WrappedKey, error::Unspecified> {
+    let ephemeral_private_key =
+        try!(agreement::EphemeralPrivateKey::generate(alg, rng));
+    let mut ephemeral_public_key =
+        vec![0u8; ephemeral_private_key.public_key_len()];
+    try!(ephemeral_private_key.compute_public_key(&mut ephemeral_public_key));
+
+    let kek = try!(agreement::agree_ephemeral(
+        ephemeral_private_key, alg, public_key, error::Unspecified,
+        |shared_secret| {
+            derive_kek(shared_secret, &ephemeral_public_key,
+                       public_key.as_slice_less_safe())
+        }));
+
+    let mut wrapped_key = vec![0u8; content_key.len() + aes_kw::OVERHEAD_LEN];
+    let _ = try!(aes_kw::wrap(&kek, content_key, &mut wrapped_key));
+    Ok(WrappedKey::Agreement {
+        ephemeral_public_key: ephemeral_public_key,
+        wrapped_key: wrapped_key,
+    })
+}
+
+fn derive_kek(shared_secret: &[u8], ephemeral_public_key: &[u8],
+              recipient_public_key: &[u8])
+              -> Result<aes_kw::Key, error::Unspecified> {
+    let mut salt = ephemeral_public_key.to_vec();
+    salt.extend_from_slice(recipient_public_key);
+    let salt = hmac::SigningKey::new(&digest::SHA256, &salt);
+
+    let mut kek = secret::SecretBox::new([0u8; 32]);
+    hkdf::extract_and_expand(&salt, shared_secret, KEK_INFO,
+                             kek.expose_mut());
+    let kek = try!(aes_kw::Key::new(&aes_kw::AES_256, kek.expose()));
+    Ok(kek)
+}
+
+fn open(content_alg: &'static aead::Algorithm, content_key: &[u8],
+        ciphertext: &[u8], ad: &[u8]) -> Result<Vec<u8>, error::Unspecified> {
+    let key = try!(aead::OpeningKey::new(content_alg, content_key));
+    let mut in_out = ciphertext.to_vec();
+    let plaintext_len =
+        try!(aead::open_in_place(&key, &NONCE[..content_alg.nonce_len()], 0,
+                                 &mut in_out, ad));
+    in_out.truncate(plaintext_len);
+    Ok(in_out)
+}
+
+const MAX_CONTENT_KEY_LEN: usize = 32;
+
+const KEK_INFO: &'static [u8] = b"ring multi-recipient key wrap";
+
+// Each content key encrypts only one message.
+const NONCE: [u8; 24] = [0u8; 24];
+
+
+#[cfg(test)]
+mod tests {
+    use {aead, agreement, rand};
+    use super::*;
+    use untrusted;
+
+    fn public_key(alg: &'static agreement::Algorithm, seed: &[u8])
+                  -> Vec<u8> {
+        let private_key =
+            agreement::EphemeralPrivateKey::from_seed(alg, seed).unwrap();
+        let mut public_key = vec![0u8; private_key.public_key_len()];
+        private_key.compute_public_key(&mut public_key).unwrap();
+        public_key
+    }
+
+    #[test]
+    fn test_multi_recipient_agreement() {
+        let rng = rand::SystemRandom::new();
+        let x25519_seed = [1u8; 32];
+        let p256_seed = [2u8; 32];
+        let x25519_public_key = public_key(&agreement::X25519, &x25519_seed);
+        let p256_public_key = public_key(&agreement::ECDH_P256, &p256_seed);
+        let recipients = [
+            Recipient::Agreement(&agreement::X25519,
+                                 untrusted::Input::from(&x25519_public_key)),
+            Recipient::Agreement(&agreement::ECDH_P256,
+                                 untrusted::Input::from(&p256_public_key)),
+        ];
+
+        for &content_alg in [&aead::AES_128_GCM, &aead::AES_256_GCM,
+                             &aead::CHACHA20_POLY1305,
+                             &aead::XCHACHA20_POLY1305].iter() {
+            let sealed =
+                seal(content_alg, &recipients, b"ad", b"hello", &rng).unwrap();
+            assert_eq!(sealed.wrapped_keys.len(), 2);
+            assert_eq!(sealed.ciphertext.len(), 5 + content_alg.tag_len());
+
+            let open = |alg, seed: &[u8], i: usize, ciphertext: &[u8],
+                        ad: &[u8]| {
+                let private_key =
+                    agreement::EphemeralPrivateKey::from_seed(alg, seed)
+                        .unwrap();
+                open_with_agreement_key(content_alg, private_key,
+                                        &sealed.wrapped_keys[i], ciphertext,
+                                        ad)
+            };
+            assert_eq!(open(&agreement::X25519, &x25519_seed, 0,
+                            &sealed.ciphertext, b"ad").unwrap(), b"hello");
+            assert_eq!(open(&agreement::ECDH_P256, &p256_seed, 1,
+                            &sealed.ciphertext, b"ad").unwrap(), b"hello");
+
+            // The wrong key, the wrong wrapped key, the wrong AD, and a
+            // modified ciphertext are all rejected.
+            assert!(open(&agreement::X25519, &p256_seed, 0,
+                         &sealed.ciphertext, b"ad").is_err());
+            assert!(open(&agreement::X25519, &x25519_seed, 1,
+                         &sealed.ciphertext, b"ad").is_err());
+            assert!(open(&agreement::X25519, &x25519_seed, 0,
+                         &sealed.ciphertext, b"").is_err());
+            let mut ciphertext = sealed.ciphertext.clone();
+            ciphertext[0] ^= 1;
+            assert!(open(&agreement::X25519, &x25519_seed, 0, &ciphertext,
+                         b"ad").is_err());
+        }
+
+        assert!(seal(&aead::AES_128_GCM, &[], b"", b"hello", &rng).is_err());
+        let short_public_key = [0u8; 31];
+        let invalid =
+            [Recipient::Agreement(&agreement::X25519,
+                                  untrusted::Input::from(&short_public_key))];
+        assert!(seal(&aead::AES_128_GCM, &invalid, b"", b"hello", &rng)
+                    .is_err());
+    }
+
+    #[cfg(feature = "rsa_signing")]
+    #[test]
+    fn test_multi_recipient_rsa() {
+        use signature;
+
+        const PRIVATE_KEY: &'static [u8] =
+            include_bytes!("rsa/signature_rsa_example_private_key.der");
+        const PUBLIC_KEY: &'static [u8] =
+            include_bytes!("rsa/signature_rsa_example_public_key.der");
+        let rng = rand::SystemRandom::new();
+        let key_pair =
+            signature::RSAKeyPair::from_der(untrusted::Input::from(PRIVATE_KEY))
+                .unwrap();
+        let x25519_seed = [1u8; 32];
+        let x25519_public_key = public_key(&agreement::X25519, &x25519_seed);
+
+        let recipients = [
+            Recipient::Agreement(&agreement::X25519,
+                                 untrusted::Input::from(&x25519_public_key)),
+            Recipient::RsaOaep(untrusted::Input::from(PUBLIC_KEY)),
+        ];
+        let sealed = seal(&aead::AES_256_GCM, &recipients, b"", b"hello",
+                          &rng).unwrap();
+        assert_eq!(open_with_rsa_key(&aead::AES_256_GCM, &key_pair,
+                                     &sealed.wrapped_keys[1],
+                                     &sealed.ciphertext, b"", &rng).unwrap(),
+                   b"hello");
+        assert!(open_with_rsa_key(&aead::AES_256_GCM, &key_pair,
+                                  &sealed.wrapped_keys[0], &sealed.ciphertext,
+                                  b"", &rng).is_err());
+
+        let private_key = agreement::EphemeralPrivateKey::from_seed(
+            &agreement::X25519, &x25519_seed).unwrap();
+        assert_eq!(open_with_agreement_key(&aead::AES_256_GCM, private_key,
+                                           &sealed.wrapped_keys[0],
+                                           &sealed.ciphertext, b"").unwrap(),
+                   b"hello");
+    }
+}
@@ -0,0 +1,231 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Password-based decryption of stored private keys: PBES2, as specified in
+//! [RFC 8018], with PBKDF2-HMAC-SHA256 or PBKDF2-HMAC-SHA512 and AES-CBC.
+//!
+//! The default PBKDF2 PRF, HMAC-SHA1, isn't supported.
+//!
+//! [RFC 8018]: https://tools.ietf.org/html/rfc8018
+
+use {c, der, error, pbkdf2};
+use std::vec::Vec;
+use untrusted;
+
+/// Decrypts `ciphertext` with the PBES2 scheme whose `AlgorithmIdentifier`
+/// contents are `algorithm`.
+pub fn decrypt(algorithm: untrusted::Input, ciphertext: untrusted::Input,
+               password: &[u8]) -> Result<Vec<u8>, error::Unspecified> {
+    algorithm.read_all(error::Unspecified, |input| {
+        let oid = try!(der::expect_tag_and_get_value(input, der::Tag::OID));
+        if oid.as_slice_less_safe() != PBES2 {
+            return Err(error::Unspecified);
+        }
+        der::nested(input, der::Tag::Sequence, error::Unspecified, |input| {
+            let (prf, salt, iterations, key_len) =
+                try!(der::nested(input, der::Tag::Sequence, error::Unspecified,
+                                 pbkdf2_params));
+            let (cipher_key_len, iv) =
+                try!(der::nested(input, der::Tag::Sequence, error::Unspecified,
+                                 |input| {
+                    let oid =
+                        try!(der::expect_tag_and_get_value(input,
+                                                           der::Tag::OID));
+                    let key_len = match oid.as_slice_less_safe() {
+                        AES_128_CBC => 16,
+                        AES_192_CBC => 24,
+                        AES_256_CBC => 32,
+                        _ => { return Err(error::Unspecified); },
+                    };
+                    let iv =
+                        try!(der::expect_tag_and_get_value(
+                                input, der::Tag::OctetString));
+                    let iv = try!(slice_as_array_ref!(
+                        iv.as_slice_less_safe(), AES_BLOCK_LEN));
+                    Ok((key_len, *iv))
+                }));
+            if key_len.is_some() && key_len != Some(cipher_key_len) {
+                return Err(error::Unspecified);
+            }
+            let mut key = [0u8; 32];
+            let key = &mut key[..cipher_key_len];
+            pbkdf2::derive(prf, iterations, salt.as_slice_less_safe(),
+                           password, key);
+            aes_cbc_decrypt(key, &iv, ciphertext.as_slice_less_safe())
+        })
+    })
+}
+
+// Reads the contents of a `PBKDF2-params`.
+fn pbkdf2_params<'a>(input: &mut untrusted::Reader<'a>)
+                     -> Result<(&'static pbkdf2::PRF, untrusted::Input<'a>,
+                                usize, Option<usize>), error::Unspecified> {
+    let oid = try!(der::expect_tag_and_get_value(input, der::Tag::OID));
+    if oid.as_slice_less_safe() != PBKDF2 {
+        return Err(error::Unspecified);
+    }
+    der::nested(input, der::Tag::Sequence, error::Unspecified, |input| {
+        let salt = try!(der::expect_tag_and_get_value(input,
+                                                      der::Tag::OctetString));
+        let iterations = try!(iteration_count(input));
+        let key_len = if input.peek(der::Tag::Integer as u8) {
+            Some(try!(der::small_nonnegative_integer(input)) as usize)
+        } else {
+            None
+        };
+        // The default PRF, HMAC-SHA1, isn't supported.
+        let prf = try!(der::nested(input, der::Tag::Sequence,
+                                   error::Unspecified, |input| {
+            let oid = try!(der::expect_tag_and_get_value(input,
+                                                         der::Tag::OID));
+            try!(optional_null(input));
+            match oid.as_slice_less_safe() {
+                HMAC_WITH_SHA256 => Ok(&pbkdf2::HMAC_SHA256),
+                HMAC_WITH_SHA512 => Ok(&pbkdf2::HMAC_SHA512),
+                _ => Err(error::Unspecified),
+            }
+        }));
+        Ok((prf, salt, iterations, key_len))
+    })
+}
+
+// Files specify their own iteration counts, so the counts are limited to
+// keep a malicious file from using an unbounded amount of CPU time.
+pub const MAX_ITERATIONS: usize = 10_000_000;
+
+pub fn iteration_count(input: &mut untrusted::Reader)
+                   -> Result<usize, error::Unspecified> {
+    let value = try!(der::positive_integer(input));
+    let value = value.as_slice_less_safe();
+    if value.len() > 4 {
+        return Err(error::Unspecified);
+    }
+    let iterations = value.iter().fold(0, |acc, b| (acc << 8) | (*b as usize));
+    if iterations > MAX_ITERATIONS {
+        return Err(error::Unspecified);
+    }
+    Ok(iterations)
+}
+
+/// Reads algorithm identifier parameters that are either absent or NULL.
+pub fn optional_null(input: &mut untrusted::Reader)
+                 -> Result<(), error::Unspecified> {
+    if !input.at_end() {
+        let null = try!(der::expect_tag_and_get_value(input, der::Tag::Null));
+        if !null.is_empty() {
+            return Err(error::Unspecified);
+        }
+    }
+    Ok(())
+}
+
+pub const AES_BLOCK_LEN: usize = 16;
+
+/// Decrypts `ciphertext` with AES-CBC and removes the PKCS#7 padding. `key`
+/// must be 16, 24, or 32 bytes long.
+///
+/// This is only used to decrypt stored keys with a password supplied by the
+/// user, so it doesn't need to avoid being a padding oracle.
+pub fn aes_cbc_decrypt(key: &[u8], iv: &[u8; AES_BLOCK_LEN], ciphertext: &[u8])
+                       -> Result<Vec<u8>, error::Unspecified> {
+    if ciphertext.is_empty() || ciphertext.len() % AES_BLOCK_LEN != 0 {
+        return Err(error::Unspecified);
+    }
+    let mut aes_key = AES_KEY {
+        rd_key: [0u32; 4 * (AES_MAX_ROUNDS + 1)],
+        rounds: 0,
+    };
+    let bits = (key.len() * 8) as u32;
+    if unsafe {
+        GFp_AES_set_decrypt_key(key.as_ptr(), bits, &mut aes_key)
+    } != 0 {
+        return Err(error::Unspecified);
+    }
+
+    let mut plaintext = ciphertext.to_vec();
+    let mut previous = *iv;
+    for block in plaintext.chunks_mut(AES_BLOCK_LEN) {
+        let mut ciphertext_block = [0u8; AES_BLOCK_LEN];
+        ciphertext_block.copy_from_slice(block);
+        unsafe {
+            GFp_AES_decrypt(block.as_ptr(), block.as_mut_ptr(), &aes_key);
+        }
+        for (b, p) in block.iter_mut().zip(previous.iter()) {
+            *b ^= *p;
+        }
+        previous = ciphertext_block;
+    }
+
+    let padding_len = plaintext[plaintext.len() - 1] as usize;
+    if padding_len == 0 || padding_len > AES_BLOCK_LEN ||
+       plaintext[(plaintext.len() - padding_len)..].iter()
+           .any(|b| *b as usize != padding_len) {
+        return Err(error::Unspecified);
+    }
+    let len = plaintext.len() - padding_len;
+    plaintext.truncate(len);
+    Ok(plaintext)
+}
+
+// Keep this in sync with `AES_KEY` in aes.h.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+struct AES_KEY {
+    rd_key: [u32; 4 * (AES_MAX_ROUNDS + 1)],
+    rounds: u32, // `unsigned` in C.
+}
+
+// Keep this in sync with `AES_MAXNR` in aes.h.
+const AES_MAX_ROUNDS: usize = 14;
+
+// PBES2 (1.2.840.113549.1.5.13).
+const PBES2: &'static [u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0d,
+];
+
+// PBKDF2 (1.2.840.113549.1.5.12).
+const PBKDF2: &'static [u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0c,
+];
+
+// hmacWithSHA256 (1.2.840.113549.2.9).
+const HMAC_WITH_SHA256: &'static [u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x09,
+];
+
+// hmacWithSHA512 (1.2.840.113549.2.11).
+const HMAC_WITH_SHA512: &'static [u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x02, 0x0b,
+];
+
+// aes128-CBC (2.16.840.1.101.3.4.1.2).
+const AES_128_CBC: &'static [u8] = &[
+    0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x02,
+];
+
+// aes192-CBC (2.16.840.1.101.3.4.1.22).
+const AES_192_CBC: &'static [u8] = &[
+    0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x16,
+];
+
+// aes256-CBC (2.16.840.1.101.3.4.1.42).
+const AES_256_CBC: &'static [u8] = &[
+    0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x2a,
+];
+
+extern {
+    fn GFp_AES_set_decrypt_key(key: *const u8, bits: u32,
+                               aes_key: *mut AES_KEY) -> c::int;
+    fn GFp_AES_decrypt(in_: *const u8, out: *mut u8, key: *const AES_KEY);
+}
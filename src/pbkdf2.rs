@@ -15,7 +15,8 @@
 //! PBKDF2 derivation and verification.
 //!
 //! Use `derive` to derive PBKDF2 outputs. Use `verify` to verify secret
-//! against previously-derived outputs.
+//! against previously-derived outputs. Use `derive_labeled_keys` to derive
+//! several keys from one secret.
 //!
 //! PBKDF2 is specified in [RFC 2898 Section 5.2] with test vectors given in
 //! [RFC 6070]. See also [NIST Special Publication 800-132].
@@ -112,7 +113,7 @@
 //!     assert!(db.verify_password("alice", "@74d7]404j|W}6u").is_ok());
 //! }
 
-use {constant_time, digest, error, hmac, polyfill};
+use {constant_time, core, digest, error, hkdf, hmac, polyfill, rand, secret};
 
 /// Fills `out` with the key derived using PBKDF2 with the given inputs.
 ///
@@ -252,20 +253,228 @@ pub fn verify(prf: &'static PRF, iterations: usize, salt: &[u8],
     Ok(())
 }
 
+/// Fills each output buffer in `outs` with a key derived from a single PBKDF2
+/// invocation, using the buffer's label as the HKDF-Expand `info`.
+///
+/// Calling `derive` once per key multiplies the defender's cost by the number
+/// of keys but leaves the attacker's cost unchanged, since the attacker only
+/// needs to compute one of the keys to test a password guess. Instead,
+/// `derive_labeled_keys` computes one PBKDF2 block of `iterations` iterations
+/// and then expands it into each of the keys with HKDF-Expand, with the
+/// label of each key as the `info` parameter.
+///
+/// Each element of `outs` is a `(label, out)` pair. The labels must be
+/// distinct so that distinct keys are derived.
+///
+/// Returns `Err(error::Unspecified)`, without writing to any of the buffers,
+/// if two labels are equal or if any buffer is larger than 255 times the
+/// output length of the PRF's digest algorithm, which is the limit imposed by
+/// the HKDF specification.
+///
+/// # Panics
+///
+/// `derive_labeled_keys` panics if `iterations < 1`.
+pub fn derive_labeled_keys(prf: &'static PRF, iterations: usize, salt: &[u8],
+                           secret: &[u8], outs: &mut [(&[u8], &mut [u8])])
+                           -> Result<(), error::Unspecified> {
+    let output_len = prf.digest_alg.output_len;
+    for (i, &(label, ref out)) in outs.iter().enumerate() {
+        if out.len() > 255 * output_len {
+            return Err(error::Unspecified);
+        }
+        if outs[..i].iter().any(|&(other_label, _)| other_label == label) {
+            return Err(error::Unspecified);
+        }
+    }
+
+    let mut prk = secret::SecretBox::new([0u8; digest::MAX_OUTPUT_LEN]);
+    let prk = &mut prk.expose_mut()[..output_len];
+    derive(prf, iterations, salt, secret, prk);
+    let prk = hkdf::Prk::new(prf.digest_alg, prk);
+    for &mut (label, ref mut out) in outs.iter_mut() {
+        prk.expand(label, out);
+    }
+    Ok(())
+}
+
+/// The length of the salt of `Params`.
+pub const SALT_LEN: usize = 16;
+
+/// The length of serialized `Params`.
+pub const PARAMS_LEN: usize = 1 + 1 + 4 + SALT_LEN;
+
+const PARAMS_VERSION: u8 = 1;
+
+/// The PBKDF2 parameters that a credential was derived with.
+///
+/// Verifying a credential with parameters that differ from the ones it was
+/// derived with, e.g. after the configured iteration count was raised, fails
+/// as if the password were wrong. To avoid that, store the serialized
+/// `Params` alongside each credential, and verify the credential with the
+/// parameters that are parsed from storage, not the configured ones. Use
+/// `is_weaker_than` to find the credentials that should be derived again
+/// with the configured parameters the next time the password is known.
+///
+/// The serialization is `PARAMS_LEN` bytes long: a version byte, which is
+/// currently 1, a byte that identifies the PRF (1 for `HMAC_SHA256` and 2
+/// for `HMAC_SHA512`), the iteration count as a 32-bit big-endian value, and
+/// the `SALT_LEN`-byte salt.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() { example().unwrap() }
+/// # fn example() -> Result<(), ring::error::Unspecified> {
+/// use ring::{pbkdf2, rand};
+///
+/// let rng = rand::SystemRandom::new();
+/// let params = try!(pbkdf2::Params::generate(&pbkdf2::HMAC_SHA256, 100_000,
+///                                            &rng));
+/// let mut credential = [0u8; 32];
+/// params.derive(b"password", &mut credential);
+/// let stored_params = params.to_bytes();
+///
+/// // Later, after the configured iteration count has changed.
+/// let params = try!(pbkdf2::Params::parse(&stored_params));
+/// try!(params.verify(b"password", &credential));
+/// assert!(params.is_weaker_than(&pbkdf2::HMAC_SHA256, 200_000));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy)]
+pub struct Params {
+    prf: &'static PRF,
+    iterations: u32,
+    salt: [u8; SALT_LEN],
+}
+
+impl Params {
+    /// Validates and returns the given parameters. `iterations` must be at
+    /// least 1.
+    pub fn new(prf: &'static PRF, iterations: u32, salt: [u8; SALT_LEN])
+               -> Result<Params, error::Unspecified> {
+        if iterations < 1 {
+            return Err(error::Unspecified);
+        }
+        Ok(Params { prf: prf, iterations: iterations, salt: salt })
+    }
+
+    /// Returns parameters with the given PRF and iteration count and a
+    /// random salt.
+    pub fn generate(prf: &'static PRF, iterations: u32,
+                    rng: &rand::SecureRandom)
+                    -> Result<Params, error::Unspecified> {
+        let mut salt = [0u8; SALT_LEN];
+        try!(rng.fill(&mut salt));
+        Params::new(prf, iterations, salt)
+    }
+
+    /// Parses parameters that were serialized with `to_bytes`.
+    ///
+    /// `input` must be exactly `PARAMS_LEN` bytes long. Parameters with an
+    /// unknown version or PRF, or an iteration count of zero, are rejected.
+    pub fn parse(input: &[u8]) -> Result<Params, error::Unspecified> {
+        if input.len() != PARAMS_LEN || input[0] != PARAMS_VERSION {
+            return Err(error::Unspecified);
+        }
+        let prf = match input[1] {
+            1 => &HMAC_SHA256,
+            2 => &HMAC_SHA512,
+            _ => { return Err(error::Unspecified); },
+        };
+        let iterations = polyfill::slice::u32_from_be_u8(
+            slice_as_array_ref!(&input[2..6], 4).unwrap());
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(&input[6..]);
+        Params::new(prf, iterations, salt)
+    }
+
+    /// Serializes the parameters.
+    pub fn to_bytes(&self) -> [u8; PARAMS_LEN] {
+        let mut out = [0u8; PARAMS_LEN];
+        out[0] = PARAMS_VERSION;
+        out[1] = self.prf.id;
+        out[2..6].copy_from_slice(
+            &polyfill::slice::be_u8_from_u32(self.iterations));
+        out[6..].copy_from_slice(&self.salt);
+        out
+    }
+
+    /// Like `pbkdf2::derive` with these parameters.
+    pub fn derive(&self, secret: &[u8], out: &mut [u8]) {
+        derive(self.prf, self.iterations as usize, &self.salt, secret, out)
+    }
+
+    /// Like `derive`, but constructs a `V` from `V::secret_len()` bytes of
+    /// output.
+    pub fn derive_value<V>(&self, secret: &[u8])
+                           -> Result<V, error::Unspecified>
+                           where V: secret::ValueFromSecret {
+        secret::value_from_secret(|out| {
+            self.derive(secret, out);
+            Ok(())
+        })
+    }
+
+    /// Like `pbkdf2::verify` with these parameters.
+    pub fn verify(&self, secret: &[u8], previously_derived: &[u8])
+                  -> Result<(), error::Unspecified> {
+        verify(self.prf, self.iterations as usize, &self.salt, secret,
+               previously_derived)
+    }
+
+    /// Returns true if these parameters use a different PRF than `prf` or
+    /// fewer iterations than `min_iterations`, i.e. if a credential derived
+    /// with them should be derived again.
+    pub fn is_weaker_than(&self, prf: &'static PRF, min_iterations: u32)
+                          -> bool {
+        self.prf.id != prf.id || self.iterations < min_iterations
+    }
+
+    /// The PRF.
+    #[inline(always)]
+    pub fn prf(&self) -> &'static PRF { self.prf }
+
+    /// The iteration count.
+    #[inline(always)]
+    pub fn iterations(&self) -> u32 { self.iterations }
+
+    /// The salt.
+    #[inline(always)]
+    pub fn salt(&self) -> &[u8; SALT_LEN] { &self.salt }
+}
+
+impl core::fmt::Debug for Params {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(fmt, "Params {{ prf: {:?}, iterations: {} }}",
+               self.prf.digest_alg, self.iterations)
+    }
+}
+
+impl PartialEq for Params {
+    fn eq(&self, other: &Params) -> bool {
+        self.prf.id == other.prf.id && self.iterations == other.iterations &&
+            self.salt == other.salt
+    }
+}
+
 /// A PRF algorithm for use with `derive` and `verify`.
 pub struct PRF {
     digest_alg: &'static digest::Algorithm,
+
+    // The identifier of the PRF in serialized `Params`.
+    id: u8,
 }
 
 /// HMAC-SHA256.
-pub static HMAC_SHA256: PRF = PRF { digest_alg: &digest::SHA256 };
+pub static HMAC_SHA256: PRF = PRF { digest_alg: &digest::SHA256, id: 1 };
 
 /// HMAC-SHA512.
-pub static HMAC_SHA512: PRF = PRF { digest_alg: &digest::SHA512 };
+pub static HMAC_SHA512: PRF = PRF { digest_alg: &digest::SHA512, id: 2 };
 
 #[cfg(test)]
 mod tests {
-    use {error, pbkdf2, test};
+    use {digest, error, hkdf, pbkdf2, rand, secret, test};
 
     #[test]
     pub fn pkbdf2_tests() {
@@ -302,4 +511,132 @@ mod tests {
             Ok(())
         });
     }
+
+    #[test]
+    pub fn pbkdf2_derive_labeled_keys() {
+        let prf = &pbkdf2::HMAC_SHA256;
+        let mut enc_key = [0u8; 16];
+        let mut mac_key = [0u8; 100];
+        pbkdf2::derive_labeled_keys(prf, 2, b"salt", b"password",
+                                    &mut [(b"enc", &mut enc_key),
+                                          (b"mac", &mut mac_key)]).unwrap();
+
+        // Each key is the HKDF-Expand of the single PBKDF2 block.
+        let mut prk = [0u8; 32];
+        pbkdf2::derive(prf, 2, b"salt", b"password", &mut prk);
+        let prk = hkdf::Prk::new(&digest::SHA256, &prk);
+        let mut expected_enc_key = [0u8; 16];
+        prk.expand(b"enc", &mut expected_enc_key);
+        let mut expected_mac_key = [0u8; 100];
+        prk.expand(b"mac", &mut expected_mac_key);
+        assert_eq!(enc_key, expected_enc_key);
+        assert_eq!(&mac_key[..], &expected_mac_key[..]);
+        assert!(enc_key[..] != mac_key[..16]);
+    }
+
+    #[test]
+    pub fn pbkdf2_derive_labeled_keys_errors() {
+        let prf = &pbkdf2::HMAC_SHA256;
+
+        // Duplicate labels.
+        let mut a = [0u8; 16];
+        let mut b = [0u8; 16];
+        assert_eq!(pbkdf2::derive_labeled_keys(prf, 1, b"salt", b"password",
+                                               &mut [(b"key", &mut a),
+                                                     (b"key", &mut b)]),
+                   Err(error::Unspecified));
+        assert!(a.iter().all(|&b| b == 0));
+        assert!(b.iter().all(|&b| b == 0));
+
+        // An output that is too long for HKDF-Expand.
+        let mut a = [0u8; 16];
+        let mut b = vec![0u8; 255 * digest::SHA256.output_len + 1];
+        assert_eq!(pbkdf2::derive_labeled_keys(prf, 1, b"salt", b"password",
+                                               &mut [(b"a", &mut a),
+                                                     (b"b", &mut b)]),
+                   Err(error::Unspecified));
+        assert!(a.iter().all(|&b| b == 0));
+        assert!(b.iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    pub fn pbkdf2_params() {
+        let salt = [0x5a; pbkdf2::SALT_LEN];
+        let params =
+            pbkdf2::Params::new(&pbkdf2::HMAC_SHA512, 1000, salt).unwrap();
+        let bytes = params.to_bytes();
+        assert_eq!(&bytes[..], &test::from_hex(
+            "0102000003e85a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a").unwrap()[..]);
+        assert_eq!(pbkdf2::Params::parse(&bytes), Ok(params));
+
+        let mut out = [0u8; 64];
+        params.derive(b"password", &mut out);
+        let mut expected = [0u8; 64];
+        pbkdf2::derive(&pbkdf2::HMAC_SHA512, 1000, &salt, b"password",
+                       &mut expected);
+        assert_eq!(&out[..], &expected[..]);
+        assert_eq!(params.verify(b"password", &out), Ok(()));
+        assert!(params.verify(b"passwore", &out).is_err());
+
+        struct Key64(secret::SecretBox<[u8; 64]>);
+
+        impl secret::ValueFromSecret for Key64 {
+            fn secret_len() -> usize { 64 }
+            fn from_secret(bytes: &[u8]) -> Self {
+                let mut key = secret::SecretBox::new([0u8; 64]);
+                key.expose_mut().copy_from_slice(bytes);
+                Key64(key)
+            }
+        }
+
+        let key: Key64 = params.derive_value(b"password").unwrap();
+        assert_eq!(&key.0.expose()[..], &expected[..]);
+
+        // Verifying with other parameters fails.
+        let other =
+            pbkdf2::Params::new(&pbkdf2::HMAC_SHA512, 1001, salt).unwrap();
+        assert!(other.verify(b"password", &out).is_err());
+
+        assert!(!params.is_weaker_than(&pbkdf2::HMAC_SHA512, 1000));
+        assert!(params.is_weaker_than(&pbkdf2::HMAC_SHA512, 1001));
+        assert!(params.is_weaker_than(&pbkdf2::HMAC_SHA256, 1000));
+
+        assert!(pbkdf2::Params::new(&pbkdf2::HMAC_SHA256, 0, salt).is_err());
+
+        let rng = rand::SystemRandom::new();
+        let a = pbkdf2::Params::generate(&pbkdf2::HMAC_SHA256, 1, &rng)
+            .unwrap();
+        let b = pbkdf2::Params::generate(&pbkdf2::HMAC_SHA256, 1, &rng)
+            .unwrap();
+        assert!(a.salt() != b.salt());
+    }
+
+    #[test]
+    pub fn pbkdf2_params_parse_errors() {
+        let params = pbkdf2::Params::new(&pbkdf2::HMAC_SHA256, 1000,
+                                         [0; pbkdf2::SALT_LEN]).unwrap();
+        let bytes = params.to_bytes();
+        assert_eq!(pbkdf2::Params::parse(&bytes[..(pbkdf2::PARAMS_LEN - 1)]),
+                   Err(error::Unspecified));
+        let mut long = [0u8; pbkdf2::PARAMS_LEN + 1];
+        long[..pbkdf2::PARAMS_LEN].copy_from_slice(&bytes);
+        assert!(pbkdf2::Params::parse(&long).is_err());
+
+        // Unknown version.
+        let mut bad = bytes;
+        bad[0] = 2;
+        assert!(pbkdf2::Params::parse(&bad).is_err());
+
+        // Unknown PRF.
+        for &id in &[0, 3, 255] {
+            let mut bad = bytes;
+            bad[1] = id;
+            assert!(pbkdf2::Params::parse(&bad).is_err());
+        }
+
+        // No iterations.
+        let mut bad = bytes;
+        bad[2..6].copy_from_slice(&[0, 0, 0, 0]);
+        assert!(pbkdf2::Params::parse(&bad).is_err());
+    }
 }
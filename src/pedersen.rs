@@ -0,0 +1,261 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Pedersen commitments over the ristretto255 group.
+//!
+//! A Pedersen commitment to a value `v` with the blinding factor `r` is the
+//! group element `v * B + r * H`, where `B` is the ristretto255 generator and
+//! `H` is a second generator whose discrete logarithm with respect to `B` is
+//! unknown. `H` is derived by hashing to the curve, with
+//! `hash_to_curve::RISTRETTO255_XMD_SHA512_R255MAP_RO`, so anybody can check
+//! that nobody knows its discrete logarithm.
+//!
+//! A commitment hides the value as long as the blinding factor is uniformly
+//! random and secret, and binds the committer to the value unless they can
+//! compute discrete logarithms in ristretto255. Commitments are additively
+//! homomorphic: the sum of commitments to `v1` and `v2` with blinding
+//! factors `r1` and `r2` is a commitment to `v1 + v2` with the blinding
+//! factor `r1 + r2`.
+//!
+//! Commitments are computed in constant time with respect to the value and
+//! the blinding factor.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{pedersen, rand};
+//!
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! let rng = rand::SystemRandom::new();
+//! let generators =
+//!     try!(pedersen::Generators::new(b"MyProtocol-V1-pedersen"));
+//!
+//! let r1 = try!(pedersen::Scalar::generate(&rng));
+//! let r2 = try!(pedersen::Scalar::generate(&rng));
+//! let c1 = generators.commit(&pedersen::Scalar::from_u64(20), &r1);
+//! let c2 = generators.commit(&pedersen::Scalar::from_u64(22), &r2);
+//!
+//! try!(generators.verify_opening(&c1.sum(&c2),
+//!                                &pedersen::Scalar::from_u64(42),
+//!                                &r1.sum(&r2)));
+//! # Ok(())
+//! # }
+//! # fn main() { example().unwrap() }
+//! ```
+
+use {constant_time, error, hash_to_curve, init, rand};
+use ec::{edwards25519, ristretto255};
+use untrusted;
+
+/// The length of an encoded commitment.
+pub const COMMITMENT_LEN: usize = ristretto255::ELEM_LEN;
+
+/// The length of an encoded scalar.
+pub const SCALAR_LEN: usize = edwards25519::SCALAR_LEN;
+
+/// A value or blinding factor: an integer modulo the order of the
+/// ristretto255 group, encoded in little-endian form.
+#[derive(Clone, Copy)]
+pub struct Scalar {
+    value: edwards25519::Scalar,
+}
+
+impl Scalar {
+    /// Constructs a scalar from a small integer.
+    pub fn from_u64(value: u64) -> Scalar {
+        Scalar { value: edwards25519::Scalar::from_u64(value) }
+    }
+
+    /// Parses a scalar from its `SCALAR_LEN`-byte little-endian encoding,
+    /// which must be fully reduced.
+    pub fn from_bytes(input: untrusted::Input)
+                      -> Result<Scalar, error::Unspecified> {
+        init::init_once();
+        Ok(Scalar { value: try!(edwards25519::Scalar::from_bytes(input)) })
+    }
+
+    /// Generates a uniformly-distributed random scalar, for use as a
+    /// blinding factor.
+    pub fn generate(rng: &rand::SecureRandom)
+                    -> Result<Scalar, error::Unspecified> {
+        init::init_once();
+        Ok(Scalar { value: try!(edwards25519::Scalar::generate(rng)) })
+    }
+
+    /// Returns `self + b`.
+    pub fn sum(&self, b: &Scalar) -> Scalar {
+        Scalar { value: self.value.sum(&b.value) }
+    }
+
+    /// Returns `self - b`.
+    pub fn difference(&self, b: &Scalar) -> Scalar {
+        Scalar { value: self.value.difference(&b.value) }
+    }
+
+    /// The little-endian encoding of the scalar.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8; SCALAR_LEN] { self.value.as_bytes() }
+}
+
+/// A commitment.
+#[derive(Clone, Copy)]
+pub struct Commitment {
+    point: ristretto255::Point,
+}
+
+impl Commitment {
+    /// Parses a commitment, which must be a canonically-encoded ristretto255
+    /// group element.
+    pub fn from_bytes(input: untrusted::Input)
+                      -> Result<Commitment, error::Unspecified> {
+        init::init_once();
+        Ok(Commitment { point: try!(ristretto255::Point::from_bytes(input)) })
+    }
+
+    /// Returns the commitment to the sum of the committed values, with the
+    /// sum of the blinding factors.
+    pub fn sum(&self, b: &Commitment) -> Commitment {
+        Commitment { point: self.point.sum(&b.point) }
+    }
+
+    /// Returns the commitment to the difference of the committed values,
+    /// with the difference of the blinding factors.
+    pub fn difference(&self, b: &Commitment) -> Commitment {
+        Commitment { point: self.point.difference(&b.point) }
+    }
+}
+
+impl AsRef<[u8]> for Commitment {
+    fn as_ref(&self) -> &[u8] { self.point.as_bytes() }
+}
+
+/// The pair of generators (`B`, `H`) that commitments are computed with.
+pub struct Generators {
+    h: ristretto255::Point,
+}
+
+impl Generators {
+    /// Derives `H` as the hash of the encoding of `B` to the curve, using the
+    /// domain separation tag `dst`.
+    ///
+    /// Each protocol should use its own `dst`; see [RFC 9380 Section 3.1].
+    /// `dst` must be between 1 and 255 bytes long.
+    ///
+    /// [RFC 9380 Section 3.1]:
+    ///     https://www.rfc-editor.org/rfc/rfc9380.html#section-3.1
+    pub fn new(dst: &[u8]) -> Result<Generators, error::Unspecified> {
+        init::init_once();
+        let b = ristretto255::Point::from_base_mul(
+            &edwards25519::Scalar::one());
+        let mut h = [0u8; ristretto255::ELEM_LEN];
+        try!(hash_to_curve::hash_to_curve(
+            &hash_to_curve::RISTRETTO255_XMD_SHA512_R255MAP_RO, b.as_bytes(),
+            dst, &mut h));
+        let h = try!(ristretto255::Point::from_bytes(
+            untrusted::Input::from(&h)));
+        Ok(Generators { h: h })
+    }
+
+    /// The encoding of `H`.
+    #[inline]
+    pub fn h(&self) -> &[u8] { self.h.as_bytes() }
+
+    /// Returns the commitment `value * B + blinding * H`.
+    pub fn commit(&self, value: &Scalar, blinding: &Scalar) -> Commitment {
+        let v_b = ristretto255::Point::from_base_mul(&value.value);
+        let r_h = self.h.mul(&blinding.value);
+        Commitment { point: v_b.sum(&r_h) }
+    }
+
+    /// Verifies that `commitment` is the commitment to `value` with the
+    /// blinding factor `blinding`.
+    pub fn verify_opening(&self, commitment: &Commitment, value: &Scalar,
+                          blinding: &Scalar)
+                          -> Result<(), error::Unspecified> {
+        let expected = self.commit(value, blinding);
+        constant_time::verify_slices_are_equal(expected.as_ref(),
+                                               commitment.as_ref())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {rand, test};
+    use untrusted;
+
+    const DST: &'static [u8] = b"ring-pedersen-test";
+
+    #[test]
+    fn test_pedersen_commit() {
+        test::from_file("src/pedersen_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+
+            let dst = test_case.consume_bytes("DST");
+            let h = test_case.consume_bytes("H");
+            let value = test_case.consume_bytes("Value");
+            let blinding = test_case.consume_bytes("Blinding");
+            let expected = test_case.consume_bytes("Commitment");
+
+            let generators = Generators::new(&dst).unwrap();
+            assert_eq!(generators.h(), &h[..]);
+            let value = Scalar::from_bytes(untrusted::Input::from(&value))
+                            .unwrap();
+            let blinding =
+                Scalar::from_bytes(untrusted::Input::from(&blinding)).unwrap();
+            let c = generators.commit(&value, &blinding);
+            assert_eq!(c.as_ref(), &expected[..]);
+
+            let c = Commitment::from_bytes(untrusted::Input::from(&expected))
+                        .unwrap();
+            assert!(generators.verify_opening(&c, &value, &blinding).is_ok());
+            assert!(generators.verify_opening(&c, &blinding, &value).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_pedersen_homomorphism() {
+        let rng = rand::SystemRandom::new();
+        let generators = Generators::new(DST).unwrap();
+
+        let v1 = Scalar::from_u64(1000);
+        let v2 = Scalar::from_u64(0xffff_ffff_ffff_ffff);
+        let r1 = Scalar::generate(&rng).unwrap();
+        let r2 = Scalar::generate(&rng).unwrap();
+        let c1 = generators.commit(&v1, &r1);
+        let c2 = generators.commit(&v2, &r2);
+
+        assert!(generators.verify_opening(&c1.sum(&c2), &v1.sum(&v2),
+                                          &r1.sum(&r2)).is_ok());
+        assert!(generators.verify_opening(&c2.difference(&c1),
+                                          &v2.difference(&v1),
+                                          &r2.difference(&r1)).is_ok());
+        assert!(generators.verify_opening(&c1, &v1, &r2).is_err());
+        assert!(generators.verify_opening(&c1, &v2, &r1).is_err());
+
+        // Different DSTs give different generators.
+        let other = Generators::new(b"ring-pedersen-test-2").unwrap();
+        assert!(other.h() != generators.h());
+        assert!(other.verify_opening(&c1, &v1, &r1).is_err());
+    }
+
+    #[test]
+    fn test_pedersen_invalid_dst() {
+        assert!(Generators::new(b"").is_err());
+        assert!(Generators::new(&[b'a'; 256]).is_err());
+    }
+}
@@ -0,0 +1,494 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Password-protected PEM private keys.
+//!
+//! Two kinds of encrypted PEM files are supported:
+//!
+//! * PKCS#8 `EncryptedPrivateKeyInfo`s, as specified in [RFC 5958], labeled
+//!   `ENCRYPTED PRIVATE KEY`, encrypted with PBES2 using PBKDF2-HMAC-SHA256 or
+//!   PBKDF2-HMAC-SHA512 and AES-CBC. These are what `openssl pkcs8 -topk8`
+//!   and `openssl genpkey` produce by default. The key may be an RSA key or
+//!   an Ed25519 key.
+//! * OpenSSL's traditional encrypted RSA keys, labeled `RSA PRIVATE KEY`,
+//!   with the `Proc-Type: 4,ENCRYPTED` and `DEK-Info` headers of [RFC 1421],
+//!   encrypted with AES-128-CBC, AES-192-CBC, or AES-256-CBC. These are what
+//!   `openssl genrsa -aes256` and `openssl rsa -aes256` produce. The
+//!   encryption key is derived from the password with OpenSSL's
+//!   `EVP_BytesToKey`, i.e. a single iteration of MD5, which makes these
+//!   files easy to brute-force; they should be converted to PKCS#8 when
+//!   possible.
+//!
+//! Keys encrypted with DES or 3DES (including `-des3`, the default of old
+//! versions of OpenSSL), with the PKCS#5 v1.5 (PBES1) schemes, or with
+//! PBKDF2-HMAC-SHA1 aren't supported. Unencrypted PEM files aren't accepted.
+//!
+//! The password is used as-is; PEM files don't specify its encoding.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # extern crate ring;
+//! # extern crate untrusted;
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::pem;
+//!
+//! # let pem_bytes: &[u8] = &[];
+//! let private_key =
+//!     try!(pem::PrivateKey::from_encrypted_pem(
+//!             untrusted::Input::from(pem_bytes), b"password"));
+//! let key_pair = try!(private_key.ed25519_key_pair());
+//! # let _ = key_pair;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [RFC 1421]: https://tools.ietf.org/html/rfc1421
+//! [RFC 5958]: https://tools.ietf.org/html/rfc5958
+
+use {der, error, init, pbe, pkcs8, signature};
+use io::{base64, hex};
+use std::vec::Vec;
+use untrusted;
+
+/// A private key decrypted from a PEM file.
+pub struct PrivateKey {
+    der: Vec<u8>,
+    format: Format,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    // A PKCS#1 `RSAPrivateKey`.
+    RSAPrivateKey,
+
+    // A PKCS#8 `PrivateKeyInfo`.
+    PrivateKeyInfo,
+}
+
+impl PrivateKey {
+    /// Decrypts the first private key in the PEM file `input` using
+    /// `password`. Any text before the key's `-----BEGIN` line is ignored.
+    pub fn from_encrypted_pem(input: untrusted::Input, password: &[u8])
+                              -> Result<PrivateKey, error::Unspecified> {
+        init::init_once();
+        let mut lines =
+            input.as_slice_less_safe().split(|b| *b == b'\n').map(|line| {
+                match line.split_last() {
+                    Some((&b'\r', line)) => line,
+                    _ => line,
+                }
+            });
+        let label = try!(lines.by_ref()
+                              .filter_map(|line| boundary(line, BEGIN))
+                              .next()
+                              .ok_or(error::Unspecified));
+
+        if label == PKCS8_LABEL {
+            let encrypted = try!(read_body(&mut lines, label));
+            let der = try!(untrusted::Input::from(&encrypted)
+                               .read_all(error::Unspecified, |input| {
+                der::nested(input, der::Tag::Sequence, error::Unspecified,
+                            |input| {
+                    let algorithm =
+                        try!(der::expect_tag_and_get_value(
+                                input, der::Tag::Sequence));
+                    let encrypted_data =
+                        try!(der::expect_tag_and_get_value(
+                                input, der::Tag::OctetString));
+                    pbe::decrypt(algorithm, encrypted_data, password)
+                })
+            }));
+            Ok(PrivateKey { der: der, format: Format::PrivateKeyInfo })
+        } else if label == RSA_LABEL {
+            let proc_type = try!(lines.next().ok_or(error::Unspecified));
+            if proc_type != PROC_TYPE_ENCRYPTED {
+                return Err(error::Unspecified);
+            }
+            let dek_info = try!(lines.next().ok_or(error::Unspecified));
+            let (key_len, iv) = try!(parse_dek_info(dek_info));
+            if try!(lines.next().ok_or(error::Unspecified)) != b"" {
+                return Err(error::Unspecified);
+            }
+            let encrypted = try!(read_body(&mut lines, label));
+
+            // `EVP_BytesToKey` uses the first eight bytes of the IV as the
+            // salt.
+            let mut key = [0u8; 32];
+            let key = &mut key[..key_len];
+            bytes_to_key(password, &iv[..8], key);
+            let der = try!(pbe::aes_cbc_decrypt(key, &iv, &encrypted));
+            Ok(PrivateKey { der: der, format: Format::RSAPrivateKey })
+        } else {
+            Err(error::Unspecified)
+        }
+    }
+
+    /// Converts the private key to an `Ed25519KeyPair`. This fails if it
+    /// isn't an Ed25519 key.
+    pub fn ed25519_key_pair(&self)
+                            -> Result<signature::Ed25519KeyPair,
+                                      error::Unspecified> {
+        if self.format != Format::PrivateKeyInfo {
+            return Err(error::Unspecified);
+        }
+        pkcs8::ed25519_key_pair(untrusted::Input::from(&self.der))
+    }
+
+    /// Converts the private key to an `RSAKeyPair`. This fails if it isn't an
+    /// RSA key, with the errors of `signature::RSAKeyPair::from_der` and
+    /// `signature::RSAKeyPair::from_pkcs8`. Feature: `rsa_signing`.
+    #[cfg(feature = "rsa_signing")]
+    pub fn rsa_key_pair(&self)
+                        -> Result<signature::RSAKeyPair, error::KeyRejected> {
+        let der = untrusted::Input::from(&self.der);
+        match self.format {
+            Format::RSAPrivateKey => signature::RSAKeyPair::from_der(der),
+            Format::PrivateKeyInfo => pkcs8::rsa_key_pair(der),
+        }
+    }
+}
+
+const BEGIN: &'static [u8] = b"-----BEGIN ";
+const END: &'static [u8] = b"-----END ";
+const DASHES: &'static [u8] = b"-----";
+
+const PKCS8_LABEL: &'static [u8] = b"ENCRYPTED PRIVATE KEY";
+const RSA_LABEL: &'static [u8] = b"RSA PRIVATE KEY";
+
+const PROC_TYPE_ENCRYPTED: &'static [u8] = b"Proc-Type: 4,ENCRYPTED";
+const DEK_INFO: &'static [u8] = b"DEK-Info: ";
+
+// Returns the label of `line` if it is an encapsulation boundary that starts
+// with `prefix`.
+fn boundary<'a>(line: &'a [u8], prefix: &[u8]) -> Option<&'a [u8]> {
+    if line.len() < prefix.len() + DASHES.len() ||
+       !line.starts_with(prefix) || !line.ends_with(DASHES) {
+        return None;
+    }
+    Some(&line[prefix.len()..(line.len() - DASHES.len())])
+}
+
+// Reads the base64-encoded lines up to the `-----END` line for `label` and
+// returns the decoded contents.
+fn read_body<'a, I>(lines: &mut I, label: &[u8])
+                    -> Result<Vec<u8>, error::Unspecified>
+                    where I: Iterator<Item=&'a [u8]> {
+    let mut encoded = Vec::new();
+    loop {
+        let line = try!(lines.next().ok_or(error::Unspecified));
+        if let Some(end_label) = boundary(line, END) {
+            if end_label != label {
+                return Err(error::Unspecified);
+            }
+            break;
+        }
+        encoded.extend_from_slice(line);
+    }
+    base64_decode(&encoded)
+}
+
+// Parses a `DEK-Info` header, returning the AES key length and the IV.
+fn parse_dek_info(line: &[u8])
+                  -> Result<(usize, [u8; pbe::AES_BLOCK_LEN]),
+                            error::Unspecified> {
+    if !line.starts_with(DEK_INFO) {
+        return Err(error::Unspecified);
+    }
+    let mut fields = line[DEK_INFO.len()..].splitn(2, |b| *b == b',');
+    let key_len = match fields.next() {
+        Some(b"AES-128-CBC") => 16,
+        Some(b"AES-192-CBC") => 24,
+        Some(b"AES-256-CBC") => 32,
+        _ => { return Err(error::Unspecified); },
+    };
+    let iv_hex = try!(fields.next().ok_or(error::Unspecified));
+    if iv_hex.len() != 2 * pbe::AES_BLOCK_LEN {
+        return Err(error::Unspecified);
+    }
+    let mut iv = [0u8; pbe::AES_BLOCK_LEN];
+    try!(hex::decode(untrusted::Input::from(iv_hex), &mut iv));
+    Ok((key_len, iv))
+}
+
+// Decodes standard, padded base64. Unlike `base64::decode`, this rejects
+// empty input.
+fn base64_decode(input: &[u8]) -> Result<Vec<u8>, error::Unspecified> {
+    if input.is_empty() {
+        return Err(error::Unspecified);
+    }
+    let mut out = vec![0u8; input.len() / 4 * 3];
+    let len = try!(base64::decode(untrusted::Input::from(input), &mut out));
+    out.truncate(len);
+    Ok(out)
+}
+
+// OpenSSL's `EVP_BytesToKey` with MD5 and one iteration:
+// D_i = MD5(D_{i-1} || password || salt), and the key is D_1 || D_2 || ....
+fn bytes_to_key(password: &[u8], salt: &[u8], key: &mut [u8]) {
+    let mut previous: Option<[u8; md5::OUTPUT_LEN]> = None;
+    for chunk in key.chunks_mut(md5::OUTPUT_LEN) {
+        let d = match previous {
+            Some(ref d) => md5::digest(&[d, password, salt]),
+            None => md5::digest(&[password, salt]),
+        };
+        chunk.copy_from_slice(&d[..chunk.len()]);
+        previous = Some(d);
+    }
+}
+
+// MD5, as specified in RFC 1321, only for `EVP_BytesToKey`. This favors
+// simplicity over speed.
+mod md5 {
+    use core::num::Wrapping;
+    use polyfill;
+    use std::vec::Vec;
+
+    pub const OUTPUT_LEN: usize = 128 / 8;
+    const BLOCK_LEN: usize = 512 / 8;
+
+    pub fn digest(parts: &[&[u8]]) -> [u8; OUTPUT_LEN] {
+        let mut message = Vec::new();
+        for part in parts {
+            message.extend_from_slice(part);
+        }
+        let bit_len = (message.len() as u64).wrapping_mul(8);
+        message.push(0x80);
+        while message.len() % BLOCK_LEN != BLOCK_LEN - 8 {
+            message.push(0);
+        }
+        for i in 0..8 {
+            message.push((bit_len >> (8 * i)) as u8);
+        }
+
+        let mut state = [Wrapping(0x67452301u32), Wrapping(0xefcdab89),
+                         Wrapping(0x98badcfe), Wrapping(0x10325476)];
+        for block in message.chunks(BLOCK_LEN) {
+            let mut m = [Wrapping(0u32); 16];
+            for (m, word) in m.iter_mut().zip(block.chunks(4)) {
+                *m = Wrapping((word[0] as u32) | ((word[1] as u32) << 8) |
+                              ((word[2] as u32) << 16) |
+                              ((word[3] as u32) << 24));
+            }
+            let (mut a, mut b, mut c, mut d) =
+                (state[0], state[1], state[2], state[3]);
+            for i in 0..64 {
+                let (f, g) = match i / 16 {
+                    0 => ((b & c) | (!b & d), i),
+                    1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                    2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                    _ => (c ^ (b | !d), (7 * i) % 16),
+                };
+                let t = d;
+                d = c;
+                c = b;
+                b = b + polyfill::wrapping_rotate_left_u32(
+                            a + f + Wrapping(K[i]) + m[g], S[i]);
+                a = t;
+            }
+            state[0] = state[0] + a;
+            state[1] = state[1] + b;
+            state[2] = state[2] + c;
+            state[3] = state[3] + d;
+        }
+
+        let mut out = [0u8; OUTPUT_LEN];
+        for (out, word) in out.chunks_mut(4).zip(state.iter()) {
+            for i in 0..4 {
+                out[i] = (word.0 >> (8 * i)) as u8;
+            }
+        }
+        out
+    }
+
+    const S: [u32; 64] = [
+        7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22,
+        5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20,
+        4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23,
+        6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+    ];
+
+    const K: [u32; 64] = [
+        0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee,
+        0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+        0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be,
+        0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+        0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa,
+        0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+        0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed,
+        0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+        0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c,
+        0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+        0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05,
+        0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+        0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039,
+        0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+        0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1,
+        0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+    ];
+}
+
+#[cfg(test)]
+mod tests {
+    use {error, test};
+    use super::*;
+    use untrusted;
+
+    #[test]
+    fn test_pem() {
+        test::from_file("src/pem_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+
+            let pem = test_case.consume_bytes("PEM");
+            let password = test_case.consume_bytes("Password");
+            let key = test_case.consume_string("Key");
+            let public_key = test_case.consume_bytes("PublicKey");
+            let result = test_case.consume_string("Result");
+
+            let actual_result =
+                PrivateKey::from_encrypted_pem(untrusted::Input::from(&pem),
+                                               &password)
+                    .and_then(|private_key| {
+                        check_key(&private_key, &key, &public_key)
+                    });
+            assert_eq!(actual_result.is_ok(), result == "OK");
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_pem_wrong_password() {
+        test::from_file("src/pem_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+
+            let pem = test_case.consume_bytes("PEM");
+            let mut password = test_case.consume_bytes("Password");
+            password.push(b'x');
+            let key = test_case.consume_string("Key");
+            let public_key = test_case.consume_bytes("PublicKey");
+            let _ = test_case.consume_string("Result");
+
+            // With traditional PEM files, the padding may happen to be valid,
+            // in which case the conversion fails instead.
+            let result =
+                PrivateKey::from_encrypted_pem(untrusted::Input::from(&pem),
+                                               &password)
+                    .and_then(|private_key| {
+                        check_key(&private_key, &key, &public_key)
+                    });
+            assert!(result.is_err());
+
+            Ok(())
+        });
+    }
+
+    fn check_key(private_key: &PrivateKey, key: &str, public_key: &[u8])
+                 -> Result<(), error::Unspecified> {
+        match key {
+            "Ed25519" => {
+                let key_pair = try!(private_key.ed25519_key_pair());
+                assert_eq!(key_pair.public_key_bytes(), public_key);
+                Ok(())
+            },
+            "RSA" => {
+                assert!(private_key.ed25519_key_pair().is_err());
+                check_rsa_key_pair(private_key, public_key)
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    #[cfg(feature = "rsa_signing")]
+    fn check_rsa_key_pair(private_key: &PrivateKey, public_key: &[u8])
+                          -> Result<(), error::Unspecified> {
+        use {rand, signature};
+        use std::sync::Arc;
+
+        let key_pair = Arc::new(try!(private_key.rsa_key_pair()));
+        let mut signing_state =
+            signature::RSASigningState::new(key_pair).unwrap();
+        let rng = rand::SystemRandom::new();
+        let msg = b"hello, world";
+        let mut sig = vec![0; signing_state.key_pair().public_modulus_len()];
+        signing_state.sign(&signature::RSA_PKCS1_SHA256, &rng, msg, &mut sig)
+                     .unwrap();
+        assert!(signature::verify(&signature::RSA_PKCS1_2048_8192_SHA256,
+                                  untrusted::Input::from(public_key),
+                                  untrusted::Input::from(msg),
+                                  untrusted::Input::from(&sig)).is_ok());
+        Ok(())
+    }
+
+    // Without `rsa_signing`, only the decryption of RSA keys is checked, by
+    // parsing the `RSAPrivateKey` or `PrivateKeyInfo` as a SEQUENCE.
+    #[cfg(not(feature = "rsa_signing"))]
+    fn check_rsa_key_pair(private_key: &PrivateKey, _: &[u8])
+                          -> Result<(), error::Unspecified> {
+        use der;
+        untrusted::Input::from(&private_key.der)
+            .read_all(error::Unspecified, |input| {
+                der::nested(input, der::Tag::Sequence, error::Unspecified,
+                            |input| {
+                    let version = try!(der::small_nonnegative_integer(input));
+                    if version != 0 {
+                        return Err(error::Unspecified);
+                    }
+                    let _ = input.skip_to_end();
+                    Ok(())
+                })
+            })
+    }
+
+    #[test]
+    fn test_base64_decode() {
+        assert_eq!(base64_decode(b"Zm9vYmFy").unwrap(), b"foobar");
+        assert_eq!(base64_decode(b"Zm9vYg==").unwrap(), b"foob");
+        assert_eq!(base64_decode(b"Zm9vYmE=").unwrap(), b"fooba");
+        assert_eq!(base64_decode(b"+/+/").unwrap(), &[0xfb, 0xff, 0xbf]);
+
+        for bad in &[&b""[..], b"Zm9vYmF", b"Zm9vY===", b"Zm9v=mFy",
+                     b"Zm==YmFy", b"Zm9vYh==", b"Zm9vYmF=", b"Zm9vYm-y",
+                     b"Zm9v YmF"] {
+            assert!(base64_decode(bad).is_err());
+        }
+    }
+
+    // The test suite from RFC 1321 Appendix A.5.
+    #[test]
+    fn test_md5() {
+        const VECTORS: [(&'static [u8], &'static str); 7] = [
+            (b"", "d41d8cd98f00b204e9800998ecf8427e"),
+            (b"a", "0cc175b9c0f1b6a831c399e269772661"),
+            (b"abc", "900150983cd24fb0d6963f7d28e17f72"),
+            (b"message digest", "f96b697d7cb7938d525a2f31aaf161d0"),
+            (b"abcdefghijklmnopqrstuvwxyz",
+             "c3fcd3d76192e4007dfb496cca67e13b"),
+            (b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz\
+               0123456789",
+             "d174ab98d277d9f5a5611c2c9f419d9f"),
+            (b"1234567890123456789012345678901234567890\
+               1234567890123456789012345678901234567890",
+             "57edf4a22be3c955ac49da2e2107b67a"),
+        ];
+        for &(input, expected) in VECTORS.iter() {
+            let expected = test::from_hex(expected).unwrap();
+            assert_eq!(&md5::digest(&[input])[..], &expected[..]);
+            // The input may be split into parts arbitrarily.
+            let (a, b) = input.split_at(input.len() / 3);
+            assert_eq!(&md5::digest(&[a, b])[..], &expected[..]);
+        }
+    }
+}
@@ -0,0 +1,452 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! PKCS#10 certification requests (CSRs), as specified in [RFC 2986].
+//!
+//! This builds and signs the `CertificationRequest` structure so that a
+//! device can generate a key pair and a request for a certificate for it
+//! without any other dependencies. The subject is a sequence of single-valued
+//! relative distinguished names, in the order they are added. DNS names are
+//! requested with a `subjectAltName` extension in an `extensionRequest`
+//! attribute ([RFC 2985] Section 5.4.2); no other attributes are supported.
+//!
+//! Feature: `use_heap`.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate ring;
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::{pkcs10, rand, signature};
+//!
+//! let rng = rand::SystemRandom::new();
+//! let key_pair = try!(signature::Ed25519KeyPair::generate(&rng));
+//!
+//! let mut subject = pkcs10::Name::new();
+//! try!(subject.add_organization("Example Devices"));
+//! try!(subject.add_common_name("device-0001"));
+//!
+//! let mut request = pkcs10::CertificationRequest::new(subject);
+//! try!(request.add_dns_name("device-0001.example.com"));
+//! let csr_der = request.sign_ed25519(&key_pair);
+//! # let _ = csr_der;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [RFC 2986]: https://tools.ietf.org/html/rfc2986
+//! [RFC 2985]: https://tools.ietf.org/html/rfc2985
+
+use {der, error, init, signature};
+use std::vec::Vec;
+
+#[cfg(feature = "rsa_signing")]
+use {rand, rsa};
+
+#[cfg(feature = "rsa_signing")]
+use untrusted;
+
+/// A distinguished name, built one attribute at a time.
+pub struct Name {
+    // The contents of the `RDNSequence`.
+    rdns: Vec<u8>,
+}
+
+impl Name {
+    /// Constructs an empty name.
+    pub fn new() -> Name { Name { rdns: Vec::new() } }
+
+    /// Appends a `countryName`, which must be a two-letter ISO 3166 code.
+    pub fn add_country(&mut self, value: &str)
+                       -> Result<(), error::Unspecified> {
+        if value.len() != 2 ||
+           !value.bytes().all(|b| b >= b'A' && b <= b'Z') {
+            return Err(error::Unspecified);
+        }
+        self.add(COUNTRY_NAME, der::Tag::PrintableString, value)
+    }
+
+    /// Appends an `organizationName`.
+    pub fn add_organization(&mut self, value: &str)
+                            -> Result<(), error::Unspecified> {
+        self.add(ORGANIZATION_NAME, der::Tag::UTF8String, value)
+    }
+
+    /// Appends an `organizationalUnitName`.
+    pub fn add_organizational_unit(&mut self, value: &str)
+                                   -> Result<(), error::Unspecified> {
+        self.add(ORGANIZATIONAL_UNIT_NAME, der::Tag::UTF8String, value)
+    }
+
+    /// Appends a `commonName`.
+    pub fn add_common_name(&mut self, value: &str)
+                           -> Result<(), error::Unspecified> {
+        self.add(COMMON_NAME, der::Tag::UTF8String, value)
+    }
+
+    /// Appends a `serialNumber`, e.g. a device serial number. It must only
+    /// contain the characters allowed in a `PrintableString`.
+    pub fn add_serial_number(&mut self, value: &str)
+                             -> Result<(), error::Unspecified> {
+        if !value.bytes().all(is_printable) {
+            return Err(error::Unspecified);
+        }
+        self.add(SERIAL_NUMBER, der::Tag::PrintableString, value)
+    }
+
+    fn add(&mut self, oid: &[u8], string_tag: der::Tag, value: &str)
+           -> Result<(), error::Unspecified> {
+        if value.is_empty() {
+            return Err(error::Unspecified);
+        }
+        let mut attribute = Vec::new();
+        der::write_tlv(der::Tag::OID, oid, &mut attribute);
+        der::write_tlv(string_tag, value.as_bytes(), &mut attribute);
+        let mut rdn = Vec::new();
+        der::write_tlv(der::Tag::Sequence, &attribute, &mut rdn);
+        der::write_tlv(der::Tag::Set, &rdn, &mut self.rdns);
+        Ok(())
+    }
+}
+
+// The `PrintableString` character set (X.680 Section 41.4).
+fn is_printable(b: u8) -> bool {
+    match b {
+        b'A'...b'Z' | b'a'...b'z' | b'0'...b'9' | b' ' | b'\'' | b'(' |
+        b')' | b'+' | b',' | b'-' | b'.' | b'/' | b':' | b'=' | b'?' => true,
+        _ => false,
+    }
+}
+
+/// A certification request that hasn't been signed yet.
+pub struct CertificationRequest {
+    subject: Name,
+    // The contents of the `GeneralNames` for the `subjectAltName`.
+    dns_names: Vec<u8>,
+}
+
+impl CertificationRequest {
+    /// Constructs a request for a certificate for `subject`.
+    pub fn new(subject: Name) -> CertificationRequest {
+        CertificationRequest { subject: subject, dns_names: Vec::new() }
+    }
+
+    /// Requests that the certificate also be valid for the DNS name `name`.
+    /// `name` must be non-empty and ASCII, without spaces; it isn't otherwise
+    /// validated.
+    pub fn add_dns_name(&mut self, name: &str)
+                        -> Result<(), error::Unspecified> {
+        if name.is_empty() || !name.bytes().all(|b| b > b' ' && b < 0x7f) {
+            return Err(error::Unspecified);
+        }
+        der::write_tlv(der::Tag::ContextSpecificPrimitive2, name.as_bytes(),
+                       &mut self.dns_names);
+        Ok(())
+    }
+
+    /// Signs the request with `key_pair`, returning the DER-encoded
+    /// `CertificationRequest`.
+    pub fn sign_ed25519(&self, key_pair: &signature::Ed25519KeyPair)
+                        -> Vec<u8> {
+        init::init_once();
+        let mut public_key = Vec::new();
+        public_key.push(0); // No unused bits.
+        public_key.extend_from_slice(key_pair.public_key_bytes());
+        let spki = subject_public_key_info(ED25519_ALGORITHM_ID, &public_key);
+
+        let info = self.certification_request_info(&spki);
+        let signature = key_pair.sign(&info);
+        certification_request(&info, ED25519_ALGORITHM_ID,
+                              signature.as_slice())
+    }
+
+    /// Signs the request with the key pair of `signing_state`, returning the
+    /// DER-encoded `CertificationRequest`. `public_key` is the key pair's
+    /// public key, encoded as an ASN.1 `RSAPublicKey`, as returned by
+    /// `RSAKeyPair::public_key_der`. The signature is verified with
+    /// `public_key`, so a mismatched public key results in an error.
+    ///
+    /// Feature: `rsa_signing`.
+    #[cfg(feature = "rsa_signing")]
+    pub fn sign_rsa(&self, alg: &'static RSASigningAlgorithm,
+                    public_key: untrusted::Input,
+                    signing_state: &mut signature::RSASigningState,
+                    rng: &rand::SecureRandom)
+                    -> Result<Vec<u8>, error::Unspecified> {
+        init::init_once();
+        let mut bit_string = Vec::new();
+        bit_string.push(0); // No unused bits.
+        bit_string.extend_from_slice(public_key.as_slice_less_safe());
+        let spki =
+            subject_public_key_info(RSA_ENCRYPTION_ALGORITHM_ID, &bit_string);
+
+        let info = self.certification_request_info(&spki);
+        let mut signature =
+            vec![0; signing_state.key_pair().public_modulus_len()];
+        try!(signing_state.sign(alg.padding, rng, &info, &mut signature));
+        try!(signature::verify(alg.verification, public_key,
+                               untrusted::Input::from(&info),
+                               untrusted::Input::from(&signature)));
+        Ok(certification_request(&info, alg.algorithm_id, &signature))
+    }
+
+    // Returns the DER-encoded `CertificationRequestInfo`, given the
+    // DER-encoded `SubjectPublicKeyInfo`.
+    fn certification_request_info(&self, spki: &[u8]) -> Vec<u8> {
+        let mut attributes = Vec::new();
+        if !self.dns_names.is_empty() {
+            let mut general_names = Vec::new();
+            der::write_tlv(der::Tag::Sequence, &self.dns_names,
+                           &mut general_names);
+            let mut extension = Vec::new();
+            der::write_tlv(der::Tag::OID, SUBJECT_ALT_NAME, &mut extension);
+            der::write_tlv(der::Tag::OctetString, &general_names,
+                           &mut extension);
+            let mut extensions = Vec::new();
+            der::write_tlv(der::Tag::Sequence, &extension, &mut extensions);
+            let mut values = Vec::new();
+            der::write_tlv(der::Tag::Sequence, &extensions, &mut values);
+            let mut attribute = Vec::new();
+            der::write_tlv(der::Tag::OID, EXTENSION_REQUEST, &mut attribute);
+            der::write_tlv(der::Tag::Set, &values, &mut attribute);
+            der::write_tlv(der::Tag::Sequence, &attribute, &mut attributes);
+        }
+
+        let mut info = Vec::new();
+        der::write_tlv(der::Tag::Integer, &[0], &mut info); // Version 1.
+        der::write_tlv(der::Tag::Sequence, &self.subject.rdns, &mut info);
+        info.extend_from_slice(spki);
+        der::write_tlv(der::Tag::ContextSpecificConstructed0, &attributes,
+                       &mut info);
+
+        let mut result = Vec::new();
+        der::write_tlv(der::Tag::Sequence, &info, &mut result);
+        result
+    }
+}
+
+fn subject_public_key_info(algorithm_id: &[u8], public_key: &[u8])
+                           -> Vec<u8> {
+    let mut spki = Vec::new();
+    spki.extend_from_slice(algorithm_id);
+    der::write_tlv(der::Tag::BitString, public_key, &mut spki);
+    let mut result = Vec::new();
+    der::write_tlv(der::Tag::Sequence, &spki, &mut result);
+    result
+}
+
+fn certification_request(info: &[u8], algorithm_id: &[u8], signature: &[u8])
+                         -> Vec<u8> {
+    let mut bit_string = Vec::new();
+    bit_string.push(0); // No unused bits.
+    bit_string.extend_from_slice(signature);
+    let mut request = Vec::new();
+    request.extend_from_slice(info);
+    request.extend_from_slice(algorithm_id);
+    der::write_tlv(der::Tag::BitString, &bit_string, &mut request);
+    let mut result = Vec::new();
+    der::write_tlv(der::Tag::Sequence, &request, &mut result);
+    result
+}
+
+/// An RSA signature algorithm for certification requests.
+///
+/// Feature: `rsa_signing`.
+#[cfg(feature = "rsa_signing")]
+pub struct RSASigningAlgorithm {
+    padding: &'static rsa::padding::Encoding,
+    verification: &'static signature::VerificationAlgorithm,
+    algorithm_id: &'static [u8],
+}
+
+/// RSA PKCS#1 1.5 signatures using SHA-256 (`sha256WithRSAEncryption`).
+///
+/// Feature: `rsa_signing`.
+#[cfg(feature = "rsa_signing")]
+pub static RSA_PKCS1_SHA256: RSASigningAlgorithm = RSASigningAlgorithm {
+    padding: &rsa::RSA_PKCS1_SHA256,
+    verification: &signature::RSA_PKCS1_2048_8192_SHA256,
+    algorithm_id: &[
+        0x30, 0x0d,
+        0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b,
+        0x05, 0x00,
+    ],
+};
+
+/// RSA PKCS#1 1.5 signatures using SHA-384 (`sha384WithRSAEncryption`).
+///
+/// Feature: `rsa_signing`.
+#[cfg(feature = "rsa_signing")]
+pub static RSA_PKCS1_SHA384: RSASigningAlgorithm = RSASigningAlgorithm {
+    padding: &rsa::RSA_PKCS1_SHA384,
+    verification: &signature::RSA_PKCS1_2048_8192_SHA384,
+    algorithm_id: &[
+        0x30, 0x0d,
+        0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0c,
+        0x05, 0x00,
+    ],
+};
+
+/// RSA PKCS#1 1.5 signatures using SHA-512 (`sha512WithRSAEncryption`).
+///
+/// Feature: `rsa_signing`.
+#[cfg(feature = "rsa_signing")]
+pub static RSA_PKCS1_SHA512: RSASigningAlgorithm = RSASigningAlgorithm {
+    padding: &rsa::RSA_PKCS1_SHA512,
+    verification: &signature::RSA_PKCS1_2048_8192_SHA512,
+    algorithm_id: &[
+        0x30, 0x0d,
+        0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0d,
+        0x05, 0x00,
+    ],
+};
+
+// id-Ed25519, with absent parameters.
+const ED25519_ALGORITHM_ID: &'static [u8] = &[
+    0x30, 0x05,
+    0x06, 0x03, 0x2b, 0x65, 0x70,
+];
+
+// rsaEncryption, with NULL parameters.
+#[cfg(feature = "rsa_signing")]
+const RSA_ENCRYPTION_ALGORITHM_ID: &'static [u8] = &[
+    0x30, 0x0d,
+    0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01,
+    0x05, 0x00,
+];
+
+// id-at-* (X.520).
+const COMMON_NAME: &'static [u8] = &[0x55, 0x04, 0x03];
+const SERIAL_NUMBER: &'static [u8] = &[0x55, 0x04, 0x05];
+const COUNTRY_NAME: &'static [u8] = &[0x55, 0x04, 0x06];
+const ORGANIZATION_NAME: &'static [u8] = &[0x55, 0x04, 0x0a];
+const ORGANIZATIONAL_UNIT_NAME: &'static [u8] = &[0x55, 0x04, 0x0b];
+
+// pkcs-9-at-extensionRequest (1.2.840.113549.1.9.14).
+const EXTENSION_REQUEST: &'static [u8] =
+    &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x0e];
+
+// id-ce-subjectAltName (2.5.29.17).
+const SUBJECT_ALT_NAME: &'static [u8] = &[0x55, 0x1d, 0x11];
+
+#[cfg(test)]
+mod tests {
+    use {signature, test};
+    use super::*;
+
+    #[test]
+    fn test_pkcs10() {
+        test::from_file("src/pkcs10_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+
+            let algorithm = test_case.consume_string("Algorithm");
+            let subject = test_case.consume_string("Subject");
+            let subject = name(subject.trim_matches('"'));
+            let mut request = CertificationRequest::new(subject);
+            if let Some(dns_names) = test_case.consume_optional_string(
+                    "DNSNames") {
+                for dns_name in dns_names.trim_matches('"').split(',') {
+                    request.add_dns_name(dns_name).unwrap();
+                }
+            }
+            let expected = test_case.consume_bytes("CSR");
+
+            if algorithm == "ED25519" {
+                let private_key = test_case.consume_bytes("PrivateKey");
+                let public_key = test_case.consume_bytes("PublicKey");
+                let key_pair =
+                    signature::Ed25519KeyPair::from_seed_and_public_key(
+                        &private_key, &public_key).unwrap();
+                assert_eq!(request.sign_ed25519(&key_pair), expected);
+            } else {
+                check_rsa(&request, &algorithm, &expected);
+            }
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_pkcs10_name_validation() {
+        let mut name = Name::new();
+        assert!(name.add_country("us").is_err());
+        assert!(name.add_country("USA").is_err());
+        assert!(name.add_common_name("").is_err());
+        assert!(name.add_serial_number("A1_B2").is_err());
+        assert!(name.add_serial_number("A1 B2").is_ok());
+
+        let mut request = CertificationRequest::new(name);
+        assert!(request.add_dns_name("").is_err());
+        assert!(request.add_dns_name("a b.example").is_err());
+        assert!(request.add_dns_name("caf\u{e9}.example").is_err());
+        assert!(request.add_dns_name("xn--caf-dma.example").is_ok());
+    }
+
+    fn name(subject: &str) -> Name {
+        let mut name = Name::new();
+        for component in subject.split(',') {
+            let mut parts = component.splitn(2, '=');
+            let key = parts.next().unwrap();
+            let value = parts.next().unwrap();
+            match key {
+                "C" => name.add_country(value),
+                "O" => name.add_organization(value),
+                "OU" => name.add_organizational_unit(value),
+                "CN" => name.add_common_name(value),
+                "SN" => name.add_serial_number(value),
+                _ => panic!("Unsupported attribute: {}", key),
+            }.unwrap();
+        }
+        name
+    }
+
+    #[cfg(feature = "rsa_signing")]
+    fn check_rsa(request: &CertificationRequest, algorithm: &str,
+                 expected: &[u8]) {
+        use {rand, std};
+        use untrusted;
+
+        let alg = match algorithm {
+            "RSA_PKCS1_SHA256" => &RSA_PKCS1_SHA256,
+            "RSA_PKCS1_SHA384" => &RSA_PKCS1_SHA384,
+            "RSA_PKCS1_SHA512" => &RSA_PKCS1_SHA512,
+            _ => panic!("Unsupported algorithm: {}", algorithm),
+        };
+        let private_key = untrusted::Input::from(
+            include_bytes!("rsa/signature_rsa_example_private_key.der"));
+        let key_pair = signature::RSAKeyPair::from_der(private_key).unwrap();
+        let key_pair = std::sync::Arc::new(key_pair);
+        let mut signing_state =
+            signature::RSASigningState::new(key_pair).unwrap();
+        let rng = rand::SystemRandom::new();
+
+        let public_key = include_bytes!(
+            "rsa/signature_rsa_example_public_key.der");
+        let actual = request.sign_rsa(alg, untrusted::Input::from(public_key),
+                                      &mut signing_state, &rng).unwrap();
+        assert_eq!(&actual[..], expected);
+
+        // A public key that doesn't match the private key is rejected.
+        let mut wrong_public_key = public_key.to_vec();
+        let last = wrong_public_key.len() - 1;
+        wrong_public_key[last] ^= 2; // Keep the exponent odd.
+        assert!(request.sign_rsa(alg,
+                                 untrusted::Input::from(&wrong_public_key),
+                                 &mut signing_state, &rng).is_err());
+    }
+
+    #[cfg(not(feature = "rsa_signing"))]
+    fn check_rsa(_: &CertificationRequest, _: &str, _: &[u8]) {}
+}
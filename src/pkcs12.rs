@@ -0,0 +1,609 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! PKCS#12 (`.p12`/`.pfx`) files, as specified in [RFC 7292].
+//!
+//! PKCS#12 is the format in which enterprise PKIs, Windows, macOS, and Java
+//! usually deliver a private key together with its certificate chain.
+//! `PFX::from_der` verifies the file's password-based integrity MAC, decrypts
+//! its contents, and extracts the private key and the certificates. The
+//! private key can then be converted to a `ring::signature` key pair.
+//!
+//! Only the algorithms that current tools use by default are supported:
+//!
+//! * The integrity MAC must be present. It must be HMAC with SHA-1, SHA-256,
+//!   SHA-384, or SHA-512, keyed using the PKCS#12 key derivation function of
+//!   RFC 7292 Appendix B.
+//! * Encrypted contents and shrouded key bags must use PBES2, as specified in
+//!   [RFC 8018], with PBKDF2-HMAC-SHA256 or PBKDF2-HMAC-SHA512 and AES-CBC.
+//!   That is what OpenSSL 3.0 and later produce by default. The legacy PKCS#12
+//!   password-based encryption schemes (RC2 and 3DES, which `openssl pkcs12`
+//!   produces with `-legacy`) aren't supported.
+//! * Unencrypted contents and key bags are also accepted.
+//! * At most one private key may be present.
+//! * The encoding must be DER, not BER.
+//!
+//! The MAC key is derived from the password encoded as a UTF-16 BMPString and
+//! the PBES2 keys from its UTF-8 encoding, as OpenSSL does.
+//!
+//! RSA private keys can be converted to `RSAKeyPair`s when the `rsa_signing`
+//! feature is enabled, and Ed25519 private keys to `Ed25519KeyPair`s. The
+//! certificates are extracted regardless of the private key's algorithm.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # extern crate ring;
+//! # extern crate untrusted;
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::{error, pkcs12};
+//!
+//! # let p12_bytes: &[u8] = &[];
+//! let pfx = try!(pkcs12::PFX::from_der(untrusted::Input::from(p12_bytes),
+//!                                      "password"));
+//! let key_pair = try!(pfx.ed25519_key_pair());
+//! let certificate = try!(pfx.key_certificate().ok_or(error::Unspecified));
+//! # let _ = (key_pair, certificate.der());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [RFC 7292]: https://tools.ietf.org/html/rfc7292
+//! [RFC 8018]: https://tools.ietf.org/html/rfc8018
+
+use {der, digest, error, hmac, init, pbe, pkcs8, signature};
+use std::vec::Vec;
+use untrusted;
+
+/// The contents of a PKCS#12 file.
+pub struct PFX {
+    private_key: Option<PrivateKeyInfo>,
+    certificates: Vec<Certificate>,
+}
+
+/// A certificate from a PKCS#12 file.
+pub struct Certificate {
+    der: Vec<u8>,
+    local_key_id: Option<Vec<u8>>,
+}
+
+impl Certificate {
+    /// The DER-encoded X.509 certificate.
+    pub fn der(&self) -> &[u8] { &self.der }
+}
+
+// A DER-encoded PKCS#8 `PrivateKeyInfo`.
+struct PrivateKeyInfo {
+    der: Vec<u8>,
+    local_key_id: Option<Vec<u8>>,
+}
+
+impl PFX {
+    /// Parses a DER-encoded PKCS#12 `PFX`, verifying its MAC and decrypting
+    /// it with `password`.
+    pub fn from_der(input: untrusted::Input, password: &str)
+                    -> Result<PFX, error::Unspecified> {
+        init::init_once();
+        let (auth_safe, mac_data) =
+            try!(input.read_all(error::Unspecified, |input| {
+                der::nested(input, der::Tag::Sequence, error::Unspecified,
+                            |input| {
+                    let version = try!(der::small_nonnegative_integer(input));
+                    if version != 3 {
+                        return Err(error::Unspecified);
+                    }
+                    let auth_safe = try!(der::nested(input, der::Tag::Sequence,
+                                                     error::Unspecified,
+                                                     content_info_data));
+                    let mac_data =
+                        try!(der::expect_tag_and_get_value(input,
+                                                           der::Tag::Sequence));
+                    Ok((auth_safe, mac_data))
+                })
+            }));
+        try!(mac_data.read_all(error::Unspecified, |input| {
+            verify_mac(input, auth_safe, password)
+        }));
+
+        let mut pfx = PFX { private_key: None, certificates: Vec::new() };
+        try!(auth_safe.read_all(error::Unspecified, |input| {
+            der::nested(input, der::Tag::Sequence, error::Unspecified,
+                        |input| {
+                while !input.at_end() {
+                    try!(der::nested(input, der::Tag::Sequence,
+                                     error::Unspecified, |input| {
+                        pfx.read_content_info(input, password)
+                    }));
+                }
+                Ok(())
+            })
+        }));
+        Ok(pfx)
+    }
+
+    /// The certificates, in the order in which they appear in the file.
+    pub fn certificates(&self) -> &[Certificate] { &self.certificates }
+
+    /// The certificate for the private key, i.e. the certificate with the same
+    /// `localKeyId` attribute as the private key, if there is one.
+    pub fn key_certificate(&self) -> Option<&Certificate> {
+        let local_key_id = match self.private_key {
+            Some(PrivateKeyInfo { local_key_id: Some(ref id), .. }) => id,
+            _ => { return None; },
+        };
+        self.certificates.iter().find(|certificate| {
+            certificate.local_key_id.as_ref() == Some(local_key_id)
+        })
+    }
+
+    /// Converts the private key to an `Ed25519KeyPair`. This fails if there
+    /// is no private key or if it isn't an Ed25519 key.
+    pub fn ed25519_key_pair(&self)
+                            -> Result<signature::Ed25519KeyPair,
+                                      error::Unspecified> {
+        pkcs8::ed25519_key_pair(try!(self.private_key_info()))
+    }
+
+    /// Converts the private key to an `RSAKeyPair`. This fails if there is no
+    /// private key or if it isn't an RSA key. Feature: `rsa_signing`.
+    #[cfg(feature = "rsa_signing")]
+    pub fn rsa_key_pair(&self)
+                        -> Result<signature::RSAKeyPair, error::Unspecified> {
+        Ok(try!(pkcs8::rsa_key_pair(try!(self.private_key_info()))))
+    }
+
+    fn private_key_info<'a>(&'a self)
+                            -> Result<untrusted::Input<'a>,
+                                      error::Unspecified> {
+        let private_key = try!(self.private_key.as_ref()
+                                   .ok_or(error::Unspecified));
+        Ok(untrusted::Input::from(&private_key.der))
+    }
+
+    // Reads the contents of a `ContentInfo` of the `AuthenticatedSafe`.
+    fn read_content_info(&mut self, input: &mut untrusted::Reader,
+                         password: &str) -> Result<(), error::Unspecified> {
+        let content_type =
+            try!(der::expect_tag_and_get_value(input, der::Tag::OID));
+        let content =
+            try!(der::expect_tag_and_get_value(
+                    input, der::Tag::ContextSpecificConstructed0));
+        let content_type = content_type.as_slice_less_safe();
+        if content_type == DATA {
+            let safe_contents = try!(content.read_all(error::Unspecified,
+                                                      |input| {
+                der::expect_tag_and_get_value(input, der::Tag::OctetString)
+            }));
+            self.read_safe_contents(safe_contents, password)
+        } else if content_type == ENCRYPTED_DATA {
+            let safe_contents = try!(content.read_all(error::Unspecified,
+                                                      |input| {
+                der::nested(input, der::Tag::Sequence, error::Unspecified,
+                            |input| encrypted_data(input, password))
+            }));
+            self.read_safe_contents(untrusted::Input::from(&safe_contents),
+                                    password)
+        } else {
+            // Public-key privacy mode (`envelopedData`) isn't supported.
+            Err(error::Unspecified)
+        }
+    }
+
+    fn read_safe_contents(&mut self, safe_contents: untrusted::Input,
+                          password: &str) -> Result<(), error::Unspecified> {
+        safe_contents.read_all(error::Unspecified, |input| {
+            der::nested(input, der::Tag::Sequence, error::Unspecified,
+                        |input| {
+                while !input.at_end() {
+                    try!(der::nested(input, der::Tag::Sequence,
+                                     error::Unspecified, |input| {
+                        self.read_safe_bag(input, password)
+                    }));
+                }
+                Ok(())
+            })
+        })
+    }
+
+    fn read_safe_bag(&mut self, input: &mut untrusted::Reader,
+                     password: &str) -> Result<(), error::Unspecified> {
+        let bag_id = try!(der::expect_tag_and_get_value(input, der::Tag::OID));
+        let bag_value =
+            try!(der::expect_tag_and_get_value(
+                    input, der::Tag::ContextSpecificConstructed0));
+        let local_key_id = if input.at_end() {
+            None
+        } else {
+            try!(der::nested(input, der::Tag::Set, error::Unspecified,
+                             local_key_id))
+        };
+
+        let bag_id = bag_id.as_slice_less_safe();
+        if bag_id == KEY_BAG || bag_id == PKCS8_SHROUDED_KEY_BAG {
+            if self.private_key.is_some() {
+                return Err(error::Unspecified);
+            }
+            let der = if bag_id == KEY_BAG {
+                bag_value.as_slice_less_safe().to_vec()
+            } else {
+                try!(bag_value.read_all(error::Unspecified, |input| {
+                    der::nested(input, der::Tag::Sequence, error::Unspecified,
+                                |input| {
+                        let algorithm =
+                            try!(der::expect_tag_and_get_value(
+                                    input, der::Tag::Sequence));
+                        let encrypted_data =
+                            try!(der::expect_tag_and_get_value(
+                                    input, der::Tag::OctetString));
+                        pbe::decrypt(algorithm, encrypted_data,
+                                     password.as_bytes())
+                    })
+                }))
+            };
+            self.private_key = Some(PrivateKeyInfo {
+                der: der,
+                local_key_id: local_key_id,
+            });
+        } else if bag_id == CERT_BAG {
+            let certificate = try!(bag_value.read_all(error::Unspecified,
+                                                      |input| {
+                der::nested(input, der::Tag::Sequence, error::Unspecified,
+                            cert_bag)
+            }));
+            if let Some(certificate) = certificate {
+                self.certificates.push(Certificate {
+                    der: certificate.as_slice_less_safe().to_vec(),
+                    local_key_id: local_key_id,
+                });
+            }
+        }
+        // Other kinds of bags (CRLs, secrets, nested `SafeContents`) are
+        // ignored.
+        Ok(())
+    }
+}
+
+// Reads a `ContentInfo` with content type `data`, returning the contents of
+// the OCTET STRING.
+fn content_info_data<'a>(input: &mut untrusted::Reader<'a>)
+                         -> Result<untrusted::Input<'a>, error::Unspecified> {
+    let content_type =
+        try!(der::expect_tag_and_get_value(input, der::Tag::OID));
+    if content_type.as_slice_less_safe() != DATA {
+        return Err(error::Unspecified);
+    }
+    der::nested(input, der::Tag::ContextSpecificConstructed0,
+                error::Unspecified, |input| {
+        der::expect_tag_and_get_value(input, der::Tag::OctetString)
+    })
+}
+
+// Reads the contents of an `EncryptedData` and returns the decrypted
+// `SafeContents`.
+fn encrypted_data(input: &mut untrusted::Reader, password: &str)
+                  -> Result<Vec<u8>, error::Unspecified> {
+    let version = try!(der::small_nonnegative_integer(input));
+    if version != 0 {
+        return Err(error::Unspecified);
+    }
+    der::nested(input, der::Tag::Sequence, error::Unspecified, |input| {
+        let content_type =
+            try!(der::expect_tag_and_get_value(input, der::Tag::OID));
+        if content_type.as_slice_less_safe() != DATA {
+            return Err(error::Unspecified);
+        }
+        let algorithm =
+            try!(der::expect_tag_and_get_value(input, der::Tag::Sequence));
+        let encrypted_content =
+            try!(der::expect_tag_and_get_value(
+                    input, der::Tag::ContextSpecificPrimitive0));
+        pbe::decrypt(algorithm, encrypted_content, password.as_bytes())
+    })
+}
+
+// Reads the contents of a `CertBag`. Certificates that aren't X.509
+// certificates are ignored.
+fn cert_bag<'a>(input: &mut untrusted::Reader<'a>)
+                -> Result<Option<untrusted::Input<'a>>, error::Unspecified> {
+    let cert_id = try!(der::expect_tag_and_get_value(input, der::Tag::OID));
+    let cert_value =
+        try!(der::nested(input, der::Tag::ContextSpecificConstructed0,
+                         error::Unspecified, |input| {
+            der::expect_tag_and_get_value(input, der::Tag::OctetString)
+        }));
+    if cert_id.as_slice_less_safe() != X509_CERTIFICATE {
+        return Ok(None);
+    }
+    Ok(Some(cert_value))
+}
+
+// Reads the bag attributes and returns the value of the `localKeyId`
+// attribute, if any.
+fn local_key_id(input: &mut untrusted::Reader)
+                -> Result<Option<Vec<u8>>, error::Unspecified> {
+    let mut result = None;
+    while !input.at_end() {
+        try!(der::nested(input, der::Tag::Sequence, error::Unspecified,
+                         |input| {
+            let attr_id =
+                try!(der::expect_tag_and_get_value(input, der::Tag::OID));
+            let values =
+                try!(der::expect_tag_and_get_value(input, der::Tag::Set));
+            if attr_id.as_slice_less_safe() == LOCAL_KEY_ID {
+                let value = try!(values.read_all(error::Unspecified,
+                                                 |input| {
+                    der::expect_tag_and_get_value(input,
+                                                  der::Tag::OctetString)
+                }));
+                result = Some(value.as_slice_less_safe().to_vec());
+            }
+            Ok(())
+        }));
+    }
+    Ok(result)
+}
+
+// Verifies the `MacData` over `auth_safe`.
+fn verify_mac(input: &mut untrusted::Reader, auth_safe: untrusted::Input,
+              password: &str) -> Result<(), error::Unspecified> {
+    let (digest_alg, expected_mac) =
+        try!(der::nested(input, der::Tag::Sequence, error::Unspecified,
+                         |input| {
+            let digest_alg =
+                try!(der::nested(input, der::Tag::Sequence, error::Unspecified,
+                                 |input| {
+                    let oid =
+                        try!(der::expect_tag_and_get_value(input,
+                                                           der::Tag::OID));
+                    try!(pbe::optional_null(input));
+                    match oid.as_slice_less_safe() {
+                        SHA1 => Ok(&digest::SHA1),
+                        SHA256 => Ok(&digest::SHA256),
+                        SHA384 => Ok(&digest::SHA384),
+                        SHA512 => Ok(&digest::SHA512),
+                        _ => Err(error::Unspecified),
+                    }
+                }));
+            let mac =
+                try!(der::expect_tag_and_get_value(input,
+                                                   der::Tag::OctetString));
+            Ok((digest_alg, mac))
+        }));
+    let salt = try!(der::expect_tag_and_get_value(input,
+                                                  der::Tag::OctetString));
+    let iterations = if input.at_end() {
+        1
+    } else {
+        try!(pbe::iteration_count(input))
+    };
+
+    let mut key = [0u8; digest::MAX_OUTPUT_LEN];
+    let key = &mut key[..digest_alg.output_len];
+    pkcs12_mac_key(digest_alg, password, salt.as_slice_less_safe(), iterations,
+                   key);
+    let key = hmac::VerificationKey::new(digest_alg, key);
+    hmac::verify(&key, auth_safe.as_slice_less_safe(),
+                 expected_mac.as_slice_less_safe())
+}
+
+// Derives a MAC key with the key derivation function of RFC 7292 Appendix
+// B.2, with ID 3. The MAC key is the same length as the digest, so only one
+// block of output is needed.
+fn pkcs12_mac_key(digest_alg: &'static digest::Algorithm, password: &str,
+                  salt: &[u8], iterations: usize, out: &mut [u8]) {
+    const ID_MAC: u8 = 3;
+
+    debug_assert_eq!(out.len(), digest_alg.output_len);
+    let v = digest_alg.block_len;
+
+    // The password is a BMPString, including a terminating NUL.
+    let mut bmp_password = Vec::with_capacity(2 * password.len() + 2);
+    for c in password.encode_utf16() {
+        bmp_password.push((c >> 8) as u8);
+        bmp_password.push(c as u8);
+    }
+    bmp_password.extend_from_slice(&[0, 0]);
+
+    // I = S || P, each repeated to a multiple of `v` bytes.
+    let mut i = Vec::new();
+    for s in &[salt, &bmp_password[..]] {
+        let len = v * ((s.len() + v - 1) / v);
+        i.extend(s.iter().cycle().take(len));
+    }
+
+    let mut ctx = digest::Context::new(digest_alg);
+    ctx.update(&[ID_MAC; digest::MAX_BLOCK_LEN][..v]);
+    ctx.update(&i);
+    let mut a = ctx.finish();
+    for _ in 1..iterations {
+        a = digest::digest(digest_alg, a.as_ref());
+    }
+    out.copy_from_slice(a.as_ref());
+}
+
+// The OIDs are the contents of the DER-encoded OBJECT IDENTIFIERs.
+
+// data (1.2.840.113549.1.7.1).
+const DATA: &'static [u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x01,
+];
+
+// encryptedData (1.2.840.113549.1.7.6).
+const ENCRYPTED_DATA: &'static [u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x06,
+];
+
+// keyBag (1.2.840.113549.1.12.10.1.1).
+const KEY_BAG: &'static [u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x0a, 0x01, 0x01,
+];
+
+// pkcs8ShroudedKeyBag (1.2.840.113549.1.12.10.1.2).
+const PKCS8_SHROUDED_KEY_BAG: &'static [u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x0a, 0x01, 0x02,
+];
+
+// certBag (1.2.840.113549.1.12.10.1.3).
+const CERT_BAG: &'static [u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x0c, 0x0a, 0x01, 0x03,
+];
+
+// x509Certificate (1.2.840.113549.1.9.22.1).
+const X509_CERTIFICATE: &'static [u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x16, 0x01,
+];
+
+// localKeyId (1.2.840.113549.1.9.21).
+const LOCAL_KEY_ID: &'static [u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x15,
+];
+
+// id-sha1 (1.3.14.3.2.26).
+const SHA1: &'static [u8] = &[0x2b, 0x0e, 0x03, 0x02, 0x1a];
+
+// id-sha256 (2.16.840.1.101.3.4.2.1).
+const SHA256: &'static [u8] = &[
+    0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01,
+];
+
+// id-sha384 (2.16.840.1.101.3.4.2.2).
+const SHA384: &'static [u8] = &[
+    0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02,
+];
+
+// id-sha512 (2.16.840.1.101.3.4.2.3).
+const SHA512: &'static [u8] = &[
+    0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03,
+];
+
+#[cfg(test)]
+mod tests {
+    use {digest, signature, test};
+    use std::string::String;
+    use std::vec::Vec;
+    use super::*;
+    use untrusted;
+
+    #[test]
+    fn test_pkcs12() {
+        test::from_file("src/pkcs12_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+
+            let pfx = test_case.consume_bytes("PFX");
+            let password = test_case.consume_bytes("Password");
+            let password = String::from_utf8(password).unwrap();
+            let key = test_case.consume_string("Key");
+            let public_key = test_case.consume_bytes("PublicKey");
+            let certificate_digests =
+                test_case.consume_bytes("CertificateDigests");
+            let key_certificate_digest =
+                test_case.consume_bytes("KeyCertificateDigest");
+            let result = test_case.consume_string("Result");
+
+            let pfx = PFX::from_der(untrusted::Input::from(&pfx), &password);
+            if result == "Error" {
+                assert!(pfx.is_err());
+                return Ok(());
+            }
+            assert_eq!(result, "OK");
+            let pfx = pfx.unwrap();
+
+            let actual_digests: Vec<u8> =
+                pfx.certificates().iter()
+                   .flat_map(|c| {
+                       Vec::from(digest::digest(&digest::SHA256, c.der())
+                                     .as_ref())
+                   })
+                   .collect();
+            assert_eq!(actual_digests, certificate_digests);
+
+            match pfx.key_certificate() {
+                Some(c) => {
+                    let actual = digest::digest(&digest::SHA256, c.der());
+                    assert_eq!(actual.as_ref(), &key_certificate_digest[..]);
+                },
+                None => { assert!(key_certificate_digest.is_empty()); },
+            }
+
+            match key.as_str() {
+                "Ed25519" => {
+                    let key_pair = pfx.ed25519_key_pair().unwrap();
+                    assert_eq!(key_pair.public_key_bytes(), &public_key[..]);
+                    let msg = b"hello, world";
+                    let sig = key_pair.sign(msg);
+                    assert!(signature::verify(&signature::ED25519,
+                                              untrusted::Input::from(
+                                                  &public_key),
+                                              untrusted::Input::from(msg),
+                                              untrusted::Input::from(
+                                                  sig.as_slice())).is_ok());
+                },
+                "RSA" => {
+                    assert!(pfx.ed25519_key_pair().is_err());
+                    check_rsa_key_pair(&pfx, &public_key);
+                },
+                "None" => {
+                    assert!(pfx.ed25519_key_pair().is_err());
+                },
+                _ => unreachable!(),
+            }
+
+            Ok(())
+        });
+    }
+
+    #[cfg(feature = "rsa_signing")]
+    fn check_rsa_key_pair(pfx: &PFX, public_key: &[u8]) {
+        use rand;
+        use std::sync::Arc;
+
+        let key_pair = Arc::new(pfx.rsa_key_pair().unwrap());
+        let mut signing_state =
+            signature::RSASigningState::new(key_pair).unwrap();
+        let rng = rand::SystemRandom::new();
+        let msg = b"hello, world";
+        let mut sig = vec![0; signing_state.key_pair().public_modulus_len()];
+        signing_state.sign(&signature::RSA_PKCS1_SHA256, &rng, msg, &mut sig)
+                     .unwrap();
+        assert!(signature::verify(&signature::RSA_PKCS1_2048_8192_SHA256,
+                                  untrusted::Input::from(public_key),
+                                  untrusted::Input::from(msg),
+                                  untrusted::Input::from(&sig)).is_ok());
+    }
+
+    #[cfg(not(feature = "rsa_signing"))]
+    fn check_rsa_key_pair(_: &PFX, _: &[u8]) {}
+
+    #[test]
+    fn test_pkcs12_wrong_password() {
+        test::from_file("src/pkcs12_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+
+            let pfx = test_case.consume_bytes("PFX");
+            let mut password = test_case.consume_bytes("Password");
+            password.push(b'x');
+            let password = String::from_utf8(password).unwrap();
+            assert!(PFX::from_der(untrusted::Input::from(&pfx), &password)
+                        .is_err());
+
+            let _ = test_case.consume_string("Key");
+            let _ = test_case.consume_string("PublicKey");
+            let _ = test_case.consume_string("CertificateDigests");
+            let _ = test_case.consume_string("KeyCertificateDigest");
+            let _ = test_case.consume_string("Result");
+            Ok(())
+        });
+    }
+}
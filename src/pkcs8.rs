@@ -0,0 +1,163 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Conversion of PKCS#8 `PrivateKeyInfo`s, as specified in [RFC 5208] and
+//! [RFC 5958], to and from key pairs.
+//!
+//! [RFC 5208]: https://tools.ietf.org/html/rfc5208
+//! [RFC 5958]: https://tools.ietf.org/html/rfc5958
+
+use {der, error, signature};
+use io::der::ECPrivateKey;
+use std::vec::Vec;
+use untrusted;
+
+/// Converts a DER-encoded Ed25519 `PrivateKeyInfo` to an `Ed25519KeyPair`.
+pub fn ed25519_key_pair(input: untrusted::Input)
+                        -> Result<signature::Ed25519KeyPair,
+                                  error::Unspecified> {
+    let private_key = try!(private_key(input, ED25519));
+    // The `privateKey` is a `CurvePrivateKey`, which is an OCTET STRING.
+    let seed = try!(private_key.read_all(error::Unspecified, |input| {
+        der::expect_tag_and_get_value(input, der::Tag::OctetString)
+    }));
+    signature::Ed25519KeyPair::from_seed_unchecked(seed.as_slice_less_safe())
+}
+
+/// Converts a DER-encoded RSA `PrivateKeyInfo` to an `RSAKeyPair`.
+#[cfg(feature = "rsa_signing")]
+pub fn rsa_key_pair(input: untrusted::Input)
+                    -> Result<signature::RSAKeyPair, error::KeyRejected> {
+    let private_key = try!(private_key(input, RSA_ENCRYPTION));
+    signature::RSAKeyPair::from_der(private_key)
+}
+
+/// Returns the private key and, if it is present, the public key of the
+/// DER-encoded EC `PrivateKeyInfo` `input`, which must have the algorithm
+/// identifier `alg_id`. The `privateKey` is an `ECPrivateKey` as specified in
+/// [RFC 5915].
+///
+/// [RFC 5915]: https://tools.ietf.org/html/rfc5915
+pub fn ec_key_pair<'a>(alg_id: &[u8], input: untrusted::Input<'a>)
+                       -> Result<(untrusted::Input<'a>,
+                                  Option<untrusted::Input<'a>>),
+                                 error::Unspecified> {
+    let private_key = try!(private_key(input, alg_id));
+    let ec_private_key = try!(ECPrivateKey::from_der(private_key));
+    // The `parameters` are redundant with the algorithm identifier, which is
+    // the `id-ecPublicKey` OID followed by the `namedCurve` OID. If present,
+    // they must name the same curve.
+    if let Some(parameters) = ec_private_key.parameters() {
+        if parameters.as_slice_less_safe() !=
+               &alg_id[ID_EC_PUBLIC_KEY.len()..] {
+            return Err(error::Unspecified);
+        }
+    }
+    Ok((ec_private_key.private_key(), ec_private_key.public_key()))
+}
+
+/// Encodes a v1 EC `PrivateKeyInfo` with the algorithm identifier `alg_id`,
+/// wrapping an `ECPrivateKey` that includes the public key.
+pub fn wrap_ec_key_pair(alg_id: &[u8], private_key: &[u8], public_key: &[u8])
+                        -> Vec<u8> {
+    let ec_private_key =
+        ECPrivateKey::new(untrusted::Input::from(private_key), None,
+                          Some(untrusted::Input::from(public_key)))
+            .to_der();
+
+    let mut private_key_info = Vec::new();
+    der::write_tlv(der::Tag::Integer, &[0], &mut private_key_info);
+    der::write_tlv(der::Tag::Sequence, alg_id, &mut private_key_info);
+    der::write_tlv(der::Tag::OctetString, &ec_private_key,
+                   &mut private_key_info);
+
+    let mut out = Vec::new();
+    der::write_tlv(der::Tag::Sequence, &private_key_info, &mut out);
+    out
+}
+
+// Returns the `privateKey` field of the `PrivateKeyInfo` if its algorithm
+// identifier is `algorithm_id`.
+fn private_key<'a>(input: untrusted::Input<'a>, algorithm_id: &[u8])
+                   -> Result<untrusted::Input<'a>, error::KeyRejected> {
+    input.read_all(error::KeyRejected::InvalidEncoding, |input| {
+        der::nested(input, der::Tag::Sequence,
+                    error::KeyRejected::InvalidEncoding, |input| {
+            // Version 1 is the `OneAsymmetricKey` of RFC 5958, which may have
+            // a public key after the attributes.
+            let version =
+                try!(der::small_nonnegative_integer(input)
+                        .map_err(error::KeyRejected::invalid_encoding));
+            if version > 1 {
+                return Err(error::KeyRejected::VersionNotSupported);
+            }
+            let actual_algorithm_id = try!(value(input, der::Tag::Sequence));
+            if actual_algorithm_id.as_slice_less_safe() != algorithm_id {
+                return Err(error::KeyRejected::UnsupportedAlgorithm);
+            }
+            let private_key = try!(value(input, der::Tag::OctetString));
+            // The attributes and, in version 1, the public key are ignored.
+            // Anything else is rejected.
+            if input.peek(der::Tag::ContextSpecificConstructed0 as u8) {
+                let _attributes =
+                    try!(value(input, der::Tag::ContextSpecificConstructed0));
+            }
+            if version == 1 &&
+               input.peek(der::Tag::ContextSpecificPrimitive1 as u8) {
+                let _public_key =
+                    try!(value(input, der::Tag::ContextSpecificPrimitive1));
+            }
+            Ok(private_key)
+        })
+    })
+}
+
+fn value<'a>(input: &mut untrusted::Reader<'a>, tag: der::Tag)
+             -> Result<untrusted::Input<'a>, error::KeyRejected> {
+    der::expect_tag_and_get_value(input, tag)
+        .map_err(error::KeyRejected::invalid_encoding)
+}
+
+// The `AlgorithmIdentifier` contents for RSA private keys: rsaEncryption
+// (1.2.840.113549.1.1.1) with NULL parameters.
+#[cfg(feature = "rsa_signing")]
+const RSA_ENCRYPTION: &'static [u8] = &[
+    0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01,
+    0x05, 0x00,
+];
+
+// id-ecPublicKey (1.2.840.10045.2.1).
+const ID_EC_PUBLIC_KEY: &'static [u8] = &[
+    0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01,
+];
+
+// The `AlgorithmIdentifier` contents for EC private keys: id-ecPublicKey
+// with namedCurve secp256r1 (1.2.840.10045.3.1.7), secp384r1 (1.3.132.0.34),
+// or secp521r1 (1.3.132.0.35).
+pub const EC_PUBLIC_KEY_P256: &'static [u8] = &[
+    0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01,
+    0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07,
+];
+pub const EC_PUBLIC_KEY_P384: &'static [u8] = &[
+    0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01,
+    0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x22,
+];
+pub const EC_PUBLIC_KEY_P521: &'static [u8] = &[
+    0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01,
+    0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x23,
+];
+
+// The `AlgorithmIdentifier` contents for Ed25519 private keys: id-Ed25519
+// (1.3.101.112), with absent parameters.
+const ED25519: &'static [u8] = &[0x06, 0x03, 0x2b, 0x65, 0x70];
@@ -0,0 +1,292 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Validation of Ed25519 and X25519 public keys.
+//!
+//! `signature::verify` and `agreement::agree_ephemeral` accept some public
+//! keys that some protocols need to reject, e.g. points of small order,
+//! which let a peer force the result of X25519 to a value that doesn't
+//! depend on the other party's private key. These functions let such
+//! protocols check public keys before using them, independently of any
+//! verification or key agreement. They only use public values, so they
+//! aren't constant-time.
+//!
+//! Every function returns `false` for inputs that aren't 32 bytes long.
+//!
+//! # Examples
+//!
+//! A protocol that requires contributory behavior from X25519:
+//!
+//! ```
+//! # extern crate ring;
+//! # extern crate untrusted;
+//! use ring::{error, point_validation};
+//!
+//! fn check_peer_public_key(peer_public_key: untrusted::Input)
+//!                          -> Result<(), error::Unspecified> {
+//!     if !point_validation::x25519_is_canonical(peer_public_key) ||
+//!        point_validation::x25519_is_small_order(peer_public_key) {
+//!         return Err(error::Unspecified);
+//!     }
+//!     Ok(())
+//! }
+//! # fn main() {
+//! #     let mut u = [0u8; 32];
+//! #     u[0] = 9;
+//! #     assert!(check_peer_public_key(untrusted::Input::from(&u)).is_ok());
+//! # }
+//! ```
+
+use {c, init};
+use untrusted;
+
+const ELEM_LEN: usize = 32;
+
+// p = 2**255 - 19, little-endian.
+const P: [u8; ELEM_LEN] = [
+    0xed, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f,
+];
+
+/// Returns `true` iff `public_key` is a canonical encoding of an Ed25519
+/// point, as required by [RFC 8032 Section 5.1.3]: the y-coordinate is less
+/// than p, and the sign bit isn't set when the x-coordinate is zero.
+///
+/// An encoding may be canonical without being the encoding of a point; use
+/// `ed25519_is_on_curve` to check that too.
+///
+/// [RFC 8032 Section 5.1.3]:
+///     https://tools.ietf.org/html/rfc8032#section-5.1.3
+pub fn ed25519_is_canonical(public_key: untrusted::Input) -> bool {
+    let encoded = match as_elem(public_key) {
+        Some(encoded) => encoded,
+        None => { return false; },
+    };
+    let mut y = *encoded;
+    y[ELEM_LEN - 1] &= 0x7f;
+    if !less_than_p(&y) {
+        return false;
+    }
+    // The x-coordinate is zero only for the points with y = 1 and y = -1.
+    let sign = (encoded[ELEM_LEN - 1] >> 7) == 1;
+    let mut p_minus_1 = P;
+    p_minus_1[0] -= 1;
+    !(sign && (y == one() || y == p_minus_1))
+}
+
+/// Returns `true` iff `public_key` encodes a point on the Ed25519 curve.
+///
+/// Like the decoding done by `signature::verify` with
+/// `signature::ED25519`, this accepts non-canonical encodings; use
+/// `ed25519_is_canonical` to reject them.
+pub fn ed25519_is_on_curve(public_key: untrusted::Input) -> bool {
+    ed25519_check(public_key, GFp_ed25519_point_is_on_curve)
+}
+
+/// Returns `true` iff `public_key` encodes a point on the Ed25519 curve whose
+/// order divides the cofactor, 8, including the identity.
+pub fn ed25519_is_small_order(public_key: untrusted::Input) -> bool {
+    ed25519_check(public_key, GFp_ed25519_point_is_small_order)
+}
+
+/// Returns `true` iff `public_key` encodes a point on the Ed25519 curve that
+/// is in the prime-order subgroup generated by the base point, i.e. that has
+/// no small-order component. The identity is in the subgroup, so protocols
+/// that must reject it should also use `ed25519_is_small_order`.
+pub fn ed25519_is_torsion_free(public_key: untrusted::Input) -> bool {
+    ed25519_check(public_key, GFp_ed25519_point_is_torsion_free_vartime)
+}
+
+fn ed25519_check(public_key: untrusted::Input,
+                 check: unsafe extern fn(p: *const u8/*[32]*/) -> c::int)
+                 -> bool {
+    init::init_once();
+    match as_elem(public_key) {
+        Some(encoded) => unsafe { check(encoded.as_ptr()) == 1 },
+        None => false,
+    }
+}
+
+/// Returns `true` iff `public_key` is a canonical encoding of an X25519
+/// u-coordinate: its most significant bit, which [RFC 7748 Section 5] says
+/// must be ignored, is clear, and its value is less than p.
+///
+/// [RFC 7748 Section 5]: https://tools.ietf.org/html/rfc7748#section-5
+pub fn x25519_is_canonical(public_key: untrusted::Input) -> bool {
+    match as_elem(public_key) {
+        Some(encoded) => less_than_p(encoded),
+        None => false,
+    }
+}
+
+/// Returns `true` iff `public_key`, with its most significant bit ignored,
+/// is the u-coordinate of a point on Curve25519 rather than on its quadratic
+/// twist.
+///
+/// X25519 accepts u-coordinates on the twist, which is safe for X25519
+/// itself, but protocols that map the result back to a curve point may need
+/// to reject them.
+pub fn x25519_is_on_curve(public_key: untrusted::Input) -> bool {
+    init::init_once();
+    match as_elem(public_key) {
+        Some(encoded) => unsafe {
+            GFp_x25519_point_is_on_curve(encoded.as_ptr()) == 1
+        },
+        None => false,
+    }
+}
+
+/// Returns `true` iff `public_key`, with its most significant bit ignored,
+/// is the u-coordinate of a point of small order on Curve25519 or on its
+/// twist, including non-canonical encodings of such u-coordinates.
+///
+/// X25519 with such a public key produces a shared secret that doesn't
+/// depend on the private key. `agreement::agree_ephemeral` rejects the
+/// all-zero result this produces, but protocols that need *contributory*
+/// behavior, where neither party alone controls the shared secret, should
+/// reject these public keys before use.
+pub fn x25519_is_small_order(public_key: untrusted::Input) -> bool {
+    let encoded = match as_elem(public_key) {
+        Some(encoded) => encoded,
+        None => { return false; },
+    };
+    let mut u = *encoded;
+    u[ELEM_LEN - 1] &= 0x7f;
+    X25519_SMALL_ORDER.iter().any(|small| u == *small)
+}
+
+// The little-endian encodings of the u-coordinates of the points of small
+// order, as used by libsodium: 0 (order 2), 1 (order 4), two points of order
+// 8, and p - 1 (order 4 on the twist). Since the most significant bit is
+// ignored, the only non-canonical encodings are those of p through
+// 2**255 - 1, i.e. of 0 through 18 (mod p), so p and p + 1 are listed too.
+const X25519_SMALL_ORDER: [[u8; ELEM_LEN]; 7] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+    [0xe0, 0xeb, 0x7a, 0x7c, 0x3b, 0x41, 0xb8, 0xae,
+     0x16, 0x56, 0xe3, 0xfa, 0xf1, 0x9f, 0xc4, 0x6a,
+     0xda, 0x09, 0x8d, 0xeb, 0x9c, 0x32, 0xb1, 0xfd,
+     0x86, 0x62, 0x05, 0x16, 0x5f, 0x49, 0xb8, 0x00],
+    [0x5f, 0x9c, 0x95, 0xbc, 0xa3, 0x50, 0x8c, 0x24,
+     0xb1, 0xd0, 0xb1, 0x55, 0x9c, 0x83, 0xef, 0x5b,
+     0x04, 0x44, 0x5c, 0xc4, 0x58, 0x1c, 0x8e, 0x86,
+     0xd8, 0x22, 0x4e, 0xdd, 0xd0, 0x9f, 0x11, 0x57],
+    [0xec, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+     0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+     0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+     0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f],
+    [0xed, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+     0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+     0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+     0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f],
+    [0xee, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+     0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+     0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+     0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f],
+];
+
+fn as_elem<'a>(input: untrusted::Input<'a>) -> Option<&'a [u8; ELEM_LEN]> {
+    slice_as_array_ref!(input.as_slice_less_safe(), ELEM_LEN).ok()
+}
+
+// Compares the little-endian value `value` with p.
+fn less_than_p(value: &[u8; ELEM_LEN]) -> bool {
+    for i in (0..ELEM_LEN).rev() {
+        if value[i] != P[i] {
+            return value[i] < P[i];
+        }
+    }
+    false
+}
+
+fn one() -> [u8; ELEM_LEN] {
+    let mut r = [0u8; ELEM_LEN];
+    r[0] = 1;
+    r
+}
+
+extern {
+    fn GFp_ed25519_point_is_on_curve(p: *const u8/*[32]*/) -> c::int;
+    fn GFp_ed25519_point_is_small_order(p: *const u8/*[32]*/) -> c::int;
+    fn GFp_ed25519_point_is_torsion_free_vartime(p: *const u8/*[32]*/)
+                                                 -> c::int;
+    fn GFp_x25519_point_is_on_curve(u: *const u8/*[32]*/) -> c::int;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test;
+    use untrusted;
+
+    #[test]
+    fn test_point_validation() {
+        test::from_file("src/point_validation_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let curve = test_case.consume_string("Curve");
+            let point = test_case.consume_bytes("Point");
+            let point = untrusted::Input::from(&point);
+            let expected = |test_case: &mut test::TestCase, key: &str| {
+                test_case.consume_string(key) == "Y"
+            };
+
+            match curve.as_str() {
+                "Ed25519" => {
+                    assert_eq!(ed25519_is_canonical(point),
+                               expected(test_case, "Canonical"));
+                    assert_eq!(ed25519_is_on_curve(point),
+                               expected(test_case, "OnCurve"));
+                    assert_eq!(ed25519_is_small_order(point),
+                               expected(test_case, "SmallOrder"));
+                    assert_eq!(ed25519_is_torsion_free(point),
+                               expected(test_case, "TorsionFree"));
+                },
+                "X25519" => {
+                    assert_eq!(x25519_is_canonical(point),
+                               expected(test_case, "Canonical"));
+                    assert_eq!(x25519_is_on_curve(point),
+                               expected(test_case, "OnCurve"));
+                    assert_eq!(x25519_is_small_order(point),
+                               expected(test_case, "SmallOrder"));
+                },
+                _ => panic!("Unsupported curve: {}", curve),
+            }
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_point_validation_wrong_length() {
+        let base_point = [9u8; 33];
+        for &len in &[0, 31, 33] {
+            let input = untrusted::Input::from(&base_point[..len]);
+            assert!(!ed25519_is_canonical(input));
+            assert!(!ed25519_is_on_curve(input));
+            assert!(!ed25519_is_small_order(input));
+            assert!(!ed25519_is_torsion_free(input));
+            assert!(!x25519_is_canonical(input));
+            assert!(!x25519_is_on_curve(input));
+            assert!(!x25519_is_small_order(input));
+        }
+    }
+}
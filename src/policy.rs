@@ -0,0 +1,295 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Run-time policies on which algorithms may be used.
+//!
+//! A `CryptoPolicy` describes which algorithms an organization allows, e.g.
+//! "no SHA-1, no PKCS#1 1.5 signatures, and at least 128 bits of security".
+//! A policy isn't a global switch that changes the behavior of the rest of
+//! *ring*; *ring* avoids process-wide state of that kind. Instead, it
+//! is a value that is checked where the application chooses its algorithms,
+//! usually where it reads its configuration or negotiates with a peer. A
+//! policy that is defined once, as a `static`, and checked at every such
+//! place applies to the whole codebase.
+//!
+//! The checks are based on the algorithms' metadata: their names, which are
+//! the names of their `static`s in *ring*, and their approximate security
+//! strengths, as estimated in [NIST SP 800-57 Part 1] Section 5.6.1.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::{digest, policy, signature};
+//!
+//! static POLICY: policy::CryptoPolicy = policy::CryptoPolicy {
+//!     min_security_bits: 128,
+//!     allow_sha1: false,
+//!     allow_rsa_pkcs1: false,
+//! };
+//!
+//! assert!(POLICY.check_digest(&digest::SHA256).is_ok());
+//! assert!(POLICY.check_digest(&digest::SHA1).is_err());
+//! assert!(POLICY.check_verification(&signature::ED25519).is_ok());
+//! assert!(POLICY.check_verification(&signature::ECDSA_P256_SHA1_ASN1)
+//!               .is_err());
+//! ```
+//!
+//! [NIST SP 800-57 Part 1]:
+//!     http://nvlpubs.nist.gov/nistpubs/SpecialPublications/NIST.SP.800-57pt1r4.pdf
+
+use {aead, agreement, digest, error, signature};
+use untrusted;
+
+#[cfg(all(feature = "rsa_signing", feature = "use_heap"))]
+use rsa;
+
+/// A policy on which algorithms may be used.
+pub struct CryptoPolicy {
+    /// The minimum security strength, in bits. For example, 112 allows
+    /// 2048-bit RSA keys and 128 requires 3072-bit RSA keys, P-256, or
+    /// Curve25519.
+    pub min_security_bits: usize,
+
+    /// Whether SHA-1 may be used, as a digest algorithm or as part of a
+    /// signature algorithm. SHA-1 is also rejected when `min_security_bits`
+    /// is greater than its collision resistance, 63 bits.
+    pub allow_sha1: bool,
+
+    /// Whether RSA signatures with PKCS#1 1.5 padding may be created or
+    /// verified. RSA PSS signatures are unaffected.
+    pub allow_rsa_pkcs1: bool,
+}
+
+impl CryptoPolicy {
+    /// Returns `Ok(())` if the policy allows the digest algorithm `alg`, which
+    /// is also the digest algorithm of HMAC, HKDF, and PBKDF2.
+    pub fn check_digest(&self, alg: &digest::Algorithm)
+                        -> Result<(), error::Unspecified> {
+        if !self.allow_sha1 && alg.name() == "SHA1" {
+            return Err(error::Unspecified);
+        }
+        self.check_security_bits(alg.security_bits())
+    }
+
+    /// Returns `Ok(())` if the policy allows the AEAD algorithm `alg`.
+    pub fn check_aead(&self, alg: &aead::Algorithm)
+                      -> Result<(), error::Unspecified> {
+        self.check_security_bits(alg.security_bits())
+    }
+
+    /// Returns `Ok(())` if the policy allows the key agreement algorithm
+    /// `alg`.
+    pub fn check_agreement(&self, alg: &agreement::Algorithm)
+                           -> Result<(), error::Unspecified> {
+        self.check_security_bits(alg.security_bits())
+    }
+
+    /// Returns `Ok(())` if the policy allows the signature verification
+    /// algorithm `alg`.
+    ///
+    /// The security strength of an RSA verification algorithm is that of the
+    /// smallest key it accepts.
+    pub fn check_verification(&self, alg: &signature::VerificationAlgorithm)
+                              -> Result<(), error::Unspecified> {
+        try!(self.check_name(alg.name()));
+        self.check_security_bits(alg.security_bits())
+    }
+
+    /// Returns `Ok(())` if the policy allows the signing algorithm `alg`.
+    pub fn check_ecdsa_signing(&self, alg: &signature::ECDSASigningAlgorithm)
+                               -> Result<(), error::Unspecified> {
+        try!(self.check_name(alg.name()));
+        self.check_security_bits(alg.security_bits())
+    }
+
+    /// Returns `Ok(())` if the policy allows signing with `key_pair` using
+    /// the padding algorithm `padding_alg`.
+    ///
+    /// Feature: `rsa_signing`.
+    #[cfg(all(feature = "rsa_signing", feature = "use_heap"))]
+    pub fn check_rsa_signing(&self, key_pair: &signature::RSAKeyPair,
+                             padding_alg: &rsa::padding::Encoding)
+                             -> Result<(), error::Unspecified> {
+        try!(self.check_name(padding_alg.name()));
+        try!(self.check_security_bits(key_pair.security_bits()));
+        self.check_security_bits(padding_alg.digest_alg().security_bits())
+    }
+
+    /// Verifies the signature `signature` of message `msg` with the public key
+    /// `public_key` using the algorithm `alg`, like `signature::verify`, but
+    /// fails without verifying anything if the policy doesn't allow `alg`.
+    pub fn verify(&self, alg: &signature::VerificationAlgorithm,
+                  public_key: untrusted::Input, msg: untrusted::Input,
+                  signature: untrusted::Input)
+                  -> Result<(), error::Unspecified> {
+        try!(self.check_verification(alg));
+        signature::verify(alg, public_key, msg, signature)
+    }
+
+    // Checks the algorithm families that are identified by the names of
+    // signature and padding algorithms.
+    fn check_name(&self, name: &str) -> Result<(), error::Unspecified> {
+        if !self.allow_sha1 && name.contains("_SHA1") {
+            return Err(error::Unspecified);
+        }
+        if !self.allow_rsa_pkcs1 && name.starts_with("RSA_PKCS1_") {
+            return Err(error::Unspecified);
+        }
+        Ok(())
+    }
+
+    fn check_security_bits(&self, security_bits: usize)
+                           -> Result<(), error::Unspecified> {
+        if security_bits < self.min_security_bits {
+            return Err(error::Unspecified);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {aead, agreement, digest, signature};
+    use super::*;
+
+    static PERMISSIVE: CryptoPolicy = CryptoPolicy {
+        min_security_bits: 0,
+        allow_sha1: true,
+        allow_rsa_pkcs1: true,
+    };
+
+    static STRICT: CryptoPolicy = CryptoPolicy {
+        min_security_bits: 128,
+        allow_sha1: false,
+        allow_rsa_pkcs1: false,
+    };
+
+    #[test]
+    fn test_policy_digest() {
+        assert!(PERMISSIVE.check_digest(&digest::SHA1).is_ok());
+        assert!(STRICT.check_digest(&digest::SHA1).is_err());
+        assert!(STRICT.check_digest(&digest::SHA256).is_ok());
+
+        // SHA-1 is rejected by its name even when its strength is allowed.
+        let no_sha1 = CryptoPolicy {
+            min_security_bits: 0,
+            allow_sha1: false,
+            allow_rsa_pkcs1: true,
+        };
+        assert!(no_sha1.check_digest(&digest::SHA1).is_err());
+        assert!(no_sha1.check_digest(&digest::SHA256).is_ok());
+
+        let at_least_192 = CryptoPolicy {
+            min_security_bits: 192,
+            allow_sha1: true,
+            allow_rsa_pkcs1: true,
+        };
+        assert!(at_least_192.check_digest(&digest::SHA256).is_err());
+        assert!(at_least_192.check_digest(&digest::SHA384).is_ok());
+    }
+
+    #[test]
+    fn test_policy_aead_and_agreement() {
+        assert!(STRICT.check_aead(&aead::AES_128_GCM).is_ok());
+        assert!(STRICT.check_aead(&aead::CHACHA20_POLY1305).is_ok());
+        assert!(STRICT.check_agreement(&agreement::X25519).is_ok());
+        assert!(STRICT.check_agreement(&agreement::ECDH_P384).is_ok());
+
+        let at_least_192 = CryptoPolicy {
+            min_security_bits: 192,
+            allow_sha1: false,
+            allow_rsa_pkcs1: false,
+        };
+        assert!(at_least_192.check_aead(&aead::AES_128_GCM).is_err());
+        assert!(at_least_192.check_aead(&aead::AES_256_GCM).is_ok());
+        assert!(at_least_192.check_agreement(&agreement::X25519).is_err());
+        assert!(at_least_192.check_agreement(&agreement::ECDH_P384).is_ok());
+    }
+
+    #[test]
+    fn test_policy_signature() {
+        assert!(PERMISSIVE.check_verification(
+                    &signature::ECDSA_P256_SHA1_ASN1).is_ok());
+        assert!(STRICT.check_verification(
+                    &signature::ECDSA_P256_SHA1_ASN1).is_err());
+        assert!(STRICT.check_verification(
+                    &signature::ECDSA_P256_SHA256_ASN1).is_ok());
+        assert!(STRICT.check_verification(&signature::ED25519).is_ok());
+        assert!(STRICT.check_ecdsa_signing(
+            &signature::ECDSA_P256_SHA256_ASN1_SIGNING_DETERMINISTIC).is_ok());
+
+        // A policy that rejects an algorithm rejects even valid signatures.
+        let msg = b"hello, world";
+        let key_pair = signature::Ed25519KeyPair::from_seed_unchecked(
+            &[0x42; 32]).unwrap();
+        let sig = key_pair.sign(msg);
+        let at_least_192 = CryptoPolicy {
+            min_security_bits: 192,
+            allow_sha1: false,
+            allow_rsa_pkcs1: false,
+        };
+        let verify = |policy: &CryptoPolicy| {
+            policy.verify(&signature::ED25519,
+                          untrusted::Input::from(key_pair.public_key_bytes()),
+                          untrusted::Input::from(msg),
+                          untrusted::Input::from(sig.as_slice()))
+        };
+        assert!(verify(&STRICT).is_ok());
+        assert!(verify(&at_least_192).is_err());
+    }
+
+    #[test]
+    fn test_policy_rsa_verification() {
+        assert!(PERMISSIVE.check_verification(
+                    &signature::RSA_PKCS1_2048_8192_SHA256).is_ok());
+
+        // PKCS#1 1.5 padding is rejected by its name.
+        assert!(STRICT.check_verification(
+                    &signature::RSA_PKCS1_3072_8192_SHA384).is_err());
+
+        // 2048-bit keys have only 112 bits of security.
+        assert!(STRICT.check_verification(
+                    &signature::RSA_PSS_2048_8192_SHA256).is_err());
+        let at_least_112 = CryptoPolicy {
+            min_security_bits: 112,
+            allow_sha1: false,
+            allow_rsa_pkcs1: false,
+        };
+        assert!(at_least_112.check_verification(
+                    &signature::RSA_PSS_2048_8192_SHA256).is_ok());
+    }
+
+    #[cfg(all(feature = "rsa_signing", feature = "use_heap"))]
+    #[test]
+    fn test_policy_rsa_signing() {
+        const PRIVATE_KEY_DER: &'static [u8] =
+            include_bytes!("rsa/signature_rsa_example_private_key.der");
+        let key_pair = signature::RSAKeyPair::from_der(
+            untrusted::Input::from(PRIVATE_KEY_DER)).unwrap();
+        assert_eq!(key_pair.security_bits(), 112);
+
+        assert!(PERMISSIVE.check_rsa_signing(
+                    &key_pair, &signature::RSA_PKCS1_SHA256).is_ok());
+        assert!(STRICT.check_rsa_signing(
+                    &key_pair, &signature::RSA_PKCS1_SHA256).is_err());
+
+        let pkcs1_at_least_128 = CryptoPolicy {
+            min_security_bits: 128,
+            allow_sha1: false,
+            allow_rsa_pkcs1: true,
+        };
+        assert!(pkcs1_at_least_128.check_rsa_signing(
+                    &key_pair, &signature::RSA_PKCS1_SHA256).is_err());
+    }
+}
@@ -0,0 +1,285 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! A bounded cache of parsed and validated public keys. Feature:
+//! `public_key_cache`.
+//!
+//! `signature::verify` parses and validates the public key every time it is
+//! called. That is negligible for most applications, but not for services
+//! that verify many signatures, e.g. tokens, from a small set of signers. A
+//! `PublicKeyCache` remembers the parsed and validated form of the most
+//! recently used keys, identified by the SHA-256 digest of their DER-encoded
+//! `SubjectPublicKeyInfo`. For ECDSA keys, this skips the public key
+//! validation, including the decompression of compressed points; for RSA
+//! keys, it skips the parsing of the modulus and exponent.
+//!
+//! Only keys are cached, never the results of verifications, and keys that
+//! fail to parse aren't cached. A `PublicKeyCache` can be shared between
+//! threads.
+//!
+//! # Examples
+//!
+//! ```
+//! extern crate ring;
+//! extern crate untrusted;
+//!
+//! use ring::{error, public_key_cache, signature};
+//!
+//! fn verify_token(cache: &public_key_cache::PublicKeyCache,
+//!                 issuer_spki: &[u8], token: &[u8], sig: &[u8])
+//!                 -> Result<(), error::Unspecified> {
+//!     cache.verify_ecdsa(&signature::ECDSA_P256_SHA256_FIXED,
+//!                        untrusted::Input::from(issuer_spki),
+//!                        untrusted::Input::from(token),
+//!                        untrusted::Input::from(sig))
+//! }
+//! # fn main() { }
+//! ```
+
+use {digest, error, init, signature, signed_data, std};
+use ec::suite_b::ecdsa;
+use rsa::verification;
+use untrusted;
+
+/// A bounded cache of parsed and validated public keys.
+pub struct PublicKeyCache {
+    // Ordered from the most recently used to the least recently used.
+    entries: std::sync::Mutex<std::vec::Vec<Entry>>,
+    capacity: usize,
+}
+
+struct Entry {
+    spki_digest: digest::Digest,
+    key: std::sync::Arc<CachedKey>,
+}
+
+enum CachedKey {
+    RSA(verification::PublicKey),
+    ECDSA(ecdsa::PublicKey),
+}
+
+impl PublicKeyCache {
+    /// Constructs an empty cache that holds at most `capacity` keys. When the
+    /// cache is full, the least recently used key is evicted.
+    pub fn new(capacity: usize) -> PublicKeyCache {
+        init::init_once();
+        PublicKeyCache {
+            entries: std::sync::Mutex::new(
+                std::vec::Vec::with_capacity(capacity)),
+            capacity: capacity,
+        }
+    }
+
+    /// Like `signature::verify(alg, public_key, msg, signature)`, where
+    /// `public_key` is the public key of the DER-encoded
+    /// `SubjectPublicKeyInfo` `spki`, which must be an `rsaEncryption` key.
+    pub fn verify_rsa(&self, alg: &'static signature::RSAParameters,
+                      spki: untrusted::Input, msg: untrusted::Input,
+                      signature: untrusted::Input)
+                      -> Result<(), error::Unspecified> {
+        let key = try!(self.get_or_parse(spki));
+        match *key {
+            CachedKey::RSA(ref public_key) =>
+                verification::verify_rsa_with_public_key(alg, public_key, msg,
+                                                         signature),
+            _ => Err(error::Unspecified),
+        }
+    }
+
+    /// Like `signature::verify(alg, public_key, msg, signature)`, where
+    /// `public_key` is the public key of the DER-encoded
+    /// `SubjectPublicKeyInfo` `spki`, which must be an `id-ecPublicKey` key
+    /// on the curve of `alg`. Only P-256 and P-384 keys are supported.
+    pub fn verify_ecdsa(&self, alg: &'static signature::ECDSAParameters,
+                        spki: untrusted::Input, msg: untrusted::Input,
+                        signature: untrusted::Input)
+                        -> Result<(), error::Unspecified> {
+        let key = try!(self.get_or_parse(spki));
+        match *key {
+            CachedKey::ECDSA(ref public_key) =>
+                ecdsa::verify_with_public_key(alg, public_key, msg, signature),
+            _ => Err(error::Unspecified),
+        }
+    }
+
+    /// The number of keys in the cache.
+    pub fn len(&self) -> usize { self.lock().len() }
+
+    /// Removes all the keys from the cache.
+    pub fn clear(&self) { self.lock().clear() }
+
+    fn get_or_parse(&self, spki: untrusted::Input)
+                    -> Result<std::sync::Arc<CachedKey>, error::Unspecified> {
+        let spki_digest =
+            digest::digest(&digest::SHA256, spki.as_slice_less_safe());
+        {
+            let mut entries = self.lock();
+            let found = entries.iter().position(|entry| {
+                entry.spki_digest.as_ref() == spki_digest.as_ref()
+            });
+            if let Some(i) = found {
+                let entry = entries.remove(i);
+                let key = entry.key.clone();
+                entries.insert(0, entry);
+                return Ok(key);
+            }
+        }
+
+        // The lock isn't held while the key is parsed. If another thread adds
+        // the same key in the meantime, the older entry is eventually evicted.
+        let key = std::sync::Arc::new(try!(parse_spki(spki)));
+        if self.capacity > 0 {
+            let mut entries = self.lock();
+            entries.truncate(self.capacity - 1);
+            entries.insert(0, Entry {
+                spki_digest: spki_digest,
+                key: key.clone(),
+            });
+        }
+        Ok(key)
+    }
+
+    fn lock<'a>(&'a self) -> std::sync::MutexGuard<'a, std::vec::Vec<Entry>> {
+        // The entries are never left in an inconsistent state, so they can
+        // still be used after a panic in another thread.
+        match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+fn parse_spki(spki: untrusted::Input)
+              -> Result<CachedKey, error::Unspecified> {
+    let (alg_id, public_key) = try!(signed_data::parse_spki(spki));
+    if alg_id.as_slice_less_safe() == signed_data::RSA_ENCRYPTION {
+        let public_key = try!(verification::PublicKey::from_der(public_key));
+        return Ok(CachedKey::RSA(public_key));
+    }
+    let public_key =
+        try!(ecdsa::PublicKey::from_spki_parts(alg_id, public_key));
+    Ok(CachedKey::ECDSA(public_key))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {digest, error, signature, test};
+    use super::*;
+    use untrusted;
+
+    #[test]
+    fn test_public_key_cache() {
+        let cache = PublicKeyCache::new(2);
+        test::from_file("src/public_key_cache_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+            let alg_name = test_case.consume_string("Alg");
+            let spki = test_case.consume_bytes("SPKI");
+            let msg = test_case.consume_bytes("Msg");
+            let sig = test_case.consume_bytes("Sig");
+            let expected_result = test_case.consume_string("Result");
+
+            let spki = untrusted::Input::from(&spki);
+            let msg = untrusted::Input::from(&msg);
+            let sig = untrusted::Input::from(&sig);
+
+            // Verify twice, so that the second verification uses the cached
+            // key.
+            for _ in 0..2 {
+                let actual_result = match alg_name.as_str() {
+                    "RSA_PKCS1_2048_8192_SHA256" =>
+                        cache.verify_rsa(
+                            &signature::RSA_PKCS1_2048_8192_SHA256, spki, msg,
+                            sig),
+                    "ECDSA_P256_SHA256_ASN1" =>
+                        cache.verify_ecdsa(
+                            &signature::ECDSA_P256_SHA256_ASN1, spki, msg,
+                            sig),
+                    "ECDSA_P384_SHA384_ASN1" =>
+                        cache.verify_ecdsa(
+                            &signature::ECDSA_P384_SHA384_ASN1, spki, msg,
+                            sig),
+                    _ => panic!("Unsupported algorithm: {}", alg_name),
+                };
+                assert_eq!(actual_result.is_ok(), expected_result == "P");
+            }
+            assert!(cache.len() <= 2);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_public_key_cache_eviction() {
+        // A P-256 key and a P-384 key, from src/signed_data_tests.txt.
+        const P256_SPKI: &'static str =
+            "3059301306072a8648ce3d020106082a8648ce3d03010703420004096d26b04a\
+             840b283aa850f9f7240be068f151d85c74ce600c42df6c4de028acb2706780d6\
+             0644d712472398d74201254561d86103340db642c1aa57ee911b69";
+        const P384_SPKI: &'static str =
+            "3076301006072a8648ce3d020106052b810400220362000457bfdf61b574cbfb\
+             2fb9b04c06a41c5965d0369906b52848f621cc6addc4a6d7846b3f79c82794f0\
+             3623abbd43e3755092581fb53a147ded24e053008fc86e97f013662465901b3f\
+             fd612858023e7a36a4b437ab775fa151038f576f14f2fd9b";
+        let p256_spki = test::from_hex(P256_SPKI).unwrap();
+        let p256_spki = untrusted::Input::from(&p256_spki);
+        let p384_spki = test::from_hex(P384_SPKI).unwrap();
+        let p384_spki = untrusted::Input::from(&p384_spki);
+        let garbage = untrusted::Input::from(b"not a key");
+        let empty = untrusted::Input::from(b"");
+
+        let cache = PublicKeyCache::new(1);
+        assert_eq!(cache.len(), 0);
+
+        // The signature is invalid, but the key is cached anyway.
+        assert_eq!(cache.verify_ecdsa(&signature::ECDSA_P256_SHA256_ASN1,
+                                      p256_spki, empty, empty),
+                   Err(error::Unspecified));
+        assert_eq!(cache.len(), 1);
+
+        // A cached key can't be used with an algorithm for another curve or
+        // another kind of key.
+        assert!(cache.verify_ecdsa(&signature::ECDSA_P384_SHA384_ASN1,
+                                   p256_spki, empty, empty).is_err());
+        assert!(cache.verify_rsa(&signature::RSA_PKCS1_2048_8192_SHA256,
+                                 p256_spki, empty, empty).is_err());
+        assert_eq!(cache.len(), 1);
+
+        // Invalid keys aren't cached and don't evict valid ones.
+        assert!(cache.verify_ecdsa(&signature::ECDSA_P256_SHA256_ASN1,
+                                   garbage, empty, empty).is_err());
+        assert_eq!(cache.len(), 1);
+
+        // A new key evicts the least recently used one.
+        let p256_digest = digest::digest(&digest::SHA256,
+                                         p256_spki.as_slice_less_safe());
+        let p384_digest = digest::digest(&digest::SHA256,
+                                         p384_spki.as_slice_less_safe());
+        assert_eq!(cache.lock()[0].spki_digest.as_ref(), p256_digest.as_ref());
+        assert!(cache.verify_ecdsa(&signature::ECDSA_P384_SHA384_ASN1,
+                                   p384_spki, empty, empty).is_err());
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.lock()[0].spki_digest.as_ref(), p384_digest.as_ref());
+
+        cache.clear();
+        assert_eq!(cache.len(), 0);
+
+        // A cache with no capacity works, but caches nothing.
+        let cache = PublicKeyCache::new(0);
+        assert!(cache.verify_ecdsa(&signature::ECDSA_P256_SHA256_ASN1,
+                                   p256_spki, empty, empty).is_err());
+        assert_eq!(cache.len(), 0);
+    }
+}
@@ -23,16 +23,21 @@
 //! (non-secure) deterministic implementation of `SecureRandom` so that results
 //! can be replayed. Following this pattern also may help with sandboxing
 //! (seccomp filters on Linux in particular). See `SystemRandom`'s
-//! documentation for more details.
+//! documentation for more details. `HmacDrbg` is a deterministic
+//! `SecureRandom` for known-answer tests.
+//!
+//! *ring* itself follows this pattern everywhere: every function that needs
+//! randomness, including every key generation function, takes a
+//! `&SecureRandom`, and *ring* never constructs a `SystemRandom` on its own.
+//! Thus a caller that substitutes another `SecureRandom`, e.g. an `HmacDrbg`,
+//! controls all of the randomness that *ring* uses on its behalf.
 
 
-#[cfg(any(target_os = "linux", windows, test))]
+#[cfg(any(target_os = "linux", windows,
+          all(target_arch = "wasm32", target_os = "unknown"), test))]
 use c;
 
-#[cfg(test)]
-use core;
-
-use error;
+use {core, digest, error, hmac};
 
 
 /// A secure random number generator.
@@ -68,6 +73,14 @@ pub trait SecureRandom {
 /// On Windows, `fill` is implemented using the platform's API for secure
 /// random number generation.
 ///
+/// On wasm32-unknown-unknown, there is no operating system to ask, so `fill`
+/// calls `GFp_wasm32_fill_random(buf: *mut u8, len: usize) -> i32`, which
+/// the environment that instantiates the WebAssembly module must provide in
+/// its `env` imports. It must fill the `len` bytes at `buf` in the module's
+/// memory with secure random bytes and return 1, or return 0 on failure. In
+/// a browser, implement it with `crypto.getRandomValues()`; `fill` never
+/// asks for more than the 65536 bytes per call that it allows.
+///
 /// When `/dev/urandom` is used, a file handle for `/dev/urandom` won't be
 /// opened until `fill` is called. In particular, `SystemRandom::new()` will
 /// not open `/dev/urandom` or do other potentially-high-latency things. The
@@ -107,7 +120,155 @@ impl SecureRandom for SystemRandom {
     }
 }
 
-#[cfg(not(any(target_os = "linux", windows)))]
+/// HMAC_DRBG, the deterministic random bit generator of
+/// [NIST SP 800-90A] Section 10.1.2, without prediction resistance.
+///
+/// An `HmacDrbg` generates the same output whenever it is instantiated with
+/// the same inputs, so it is useful wherever the randomness of an operation
+/// needs to be reproduced: in known-answer tests, e.g. NIST's CAVP tests,
+/// that specify the bytes a `SecureRandom` returns, and in property-based
+/// tests of code that takes a `&SecureRandom`. Its output is only as
+/// unpredictable as its entropy input; applications should use
+/// `SystemRandom` instead.
+///
+/// # Examples
+///
+/// ```
+/// use ring::{digest, rand};
+/// use ring::rand::SecureRandom;
+///
+/// let rng = rand::HmacDrbg::new(&digest::SHA256, &[0u8; 32], &[1u8; 16],
+///                               b"");
+/// let mut a = [0u8; 32];
+/// rng.fill(&mut a).unwrap();
+///
+/// let rng_again = rand::HmacDrbg::new(&digest::SHA256, &[0u8; 32],
+///                                     &[1u8; 16], b"");
+/// let mut b = [0u8; 32];
+/// rng_again.fill(&mut b).unwrap();
+/// assert_eq!(a, b);
+/// ```
+///
+/// [NIST SP 800-90A]:
+///     http://nvlpubs.nist.gov/nistpubs/SpecialPublications/NIST.SP.800-90Ar1.pdf
+pub struct HmacDrbg {
+    state: core::cell::RefCell<HmacDrbgState>,
+}
+
+struct HmacDrbgState {
+    digest_alg: &'static digest::Algorithm,
+    k: [u8; digest::MAX_OUTPUT_LEN],
+    v: [u8; digest::MAX_OUTPUT_LEN],
+    reseed_counter: u64,
+}
+
+/// The maximum number of bytes `HmacDrbg::generate` returns per call.
+pub const HMAC_DRBG_MAX_REQUEST_LEN: usize = (1 << 19) / 8;
+
+// The maximum number of generate calls between reseeds.
+const HMAC_DRBG_RESEED_INTERVAL: u64 = 1 << 48;
+
+impl HmacDrbg {
+    /// Instantiates the DRBG.
+    ///
+    /// | Parameter                | SP 800-90A Term
+    /// |--------------------------|------------------------
+    /// | `digest_alg`             | Hash function of HMAC
+    /// | `entropy_input`          | entropy_input
+    /// | `nonce`                  | nonce
+    /// | `personalization_string` | personalization_string
+    pub fn new(digest_alg: &'static digest::Algorithm, entropy_input: &[u8],
+               nonce: &[u8], personalization_string: &[u8]) -> HmacDrbg {
+        let mut state = HmacDrbgState {
+            digest_alg: digest_alg,
+            k: [0u8; digest::MAX_OUTPUT_LEN],
+            v: [1u8; digest::MAX_OUTPUT_LEN],
+            reseed_counter: 1,
+        };
+        state.update(&[entropy_input, nonce, personalization_string]);
+        HmacDrbg { state: core::cell::RefCell::new(state) }
+    }
+
+    /// Reseeds the DRBG with `entropy_input` and `additional_input`.
+    pub fn reseed(&mut self, entropy_input: &[u8], additional_input: &[u8]) {
+        let state = self.state.get_mut();
+        state.update(&[entropy_input, additional_input]);
+        state.reseed_counter = 1;
+    }
+
+    /// Fills `out` with the next output of the DRBG, with the additional
+    /// input `additional_input`.
+    ///
+    /// Fails if `out` is longer than `HMAC_DRBG_MAX_REQUEST_LEN` bytes or if
+    /// the DRBG must be reseeded, which happens after 2**48 calls.
+    pub fn generate(&self, out: &mut [u8], additional_input: &[u8])
+                    -> Result<(), error::Unspecified> {
+        if out.len() > HMAC_DRBG_MAX_REQUEST_LEN {
+            return Err(error::Unspecified);
+        }
+        let mut state = self.state.borrow_mut();
+        if state.reseed_counter > HMAC_DRBG_RESEED_INTERVAL {
+            return Err(error::Unspecified);
+        }
+        if !additional_input.is_empty() {
+            state.update(&[additional_input]);
+        }
+        let len = state.digest_alg.output_len;
+        for chunk in out.chunks_mut(len) {
+            let v = {
+                let k = hmac::SigningKey::new(state.digest_alg,
+                                              &state.k[..len]);
+                hmac::sign(&k, &state.v[..len])
+            };
+            state.v[..len].copy_from_slice(v.as_ref());
+            chunk.copy_from_slice(&state.v[..chunk.len()]);
+        }
+        state.update(&[additional_input]);
+        state.reseed_counter += 1;
+        Ok(())
+    }
+}
+
+impl SecureRandom for HmacDrbg {
+    fn fill(&self, dest: &mut [u8]) -> Result<(), error::Unspecified> {
+        for chunk in dest.chunks_mut(HMAC_DRBG_MAX_REQUEST_LEN) {
+            try!(self.generate(chunk, &[]));
+        }
+        Ok(())
+    }
+}
+
+impl HmacDrbgState {
+    // HMAC_DRBG_Update, with the concatenation of `provided_data` as
+    // provided_data.
+    fn update(&mut self, provided_data: &[&[u8]]) {
+        let len = self.digest_alg.output_len;
+        for &round in &[0u8, 1u8] {
+            if round == 1 && provided_data.iter().all(|data| data.is_empty()) {
+                break;
+            }
+            let k = {
+                let k = hmac::SigningKey::new(self.digest_alg,
+                                              &self.k[..len]);
+                let mut ctx = hmac::SigningContext::with_key(&k);
+                ctx.update(&self.v[..len]);
+                ctx.update(&[round]);
+                for data in provided_data {
+                    ctx.update(data);
+                }
+                ctx.sign()
+            };
+            self.k[..len].copy_from_slice(k.as_ref());
+            let v = hmac::sign(&hmac::SigningKey::new(self.digest_alg,
+                                                      &self.k[..len]),
+                               &self.v[..len]);
+            self.v[..len].copy_from_slice(v.as_ref());
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows,
+              all(target_arch = "wasm32", target_os = "unknown"))))]
 use self::urandom::fill as fill_impl;
 
 #[cfg(any(all(target_os = "linux", not(feature = "dev_urandom_fallback")),
@@ -117,6 +278,9 @@ use self::sysrand::fill as fill_impl;
 #[cfg(all(target_os = "linux", feature = "dev_urandom_fallback"))]
 use self::sysrand_or_urandom::fill as fill_impl;
 
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+use self::wasm32::fill as fill_impl;
+
 #[cfg(any(target_os = "linux", windows))]
 mod sysrand {
     use {bssl, error};
@@ -188,6 +352,27 @@ mod sysrand_or_urandom {
     }
 }
 
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+mod wasm32 {
+    use {bssl, c, error};
+
+    // `crypto.getRandomValues()` fills at most 65536 bytes per call.
+    const MAX_CHUNK_LEN: usize = 65536;
+
+    pub fn fill(dest: &mut [u8]) -> Result<(), error::Unspecified> {
+        for chunk in dest.chunks_mut(MAX_CHUNK_LEN) {
+            try!(bssl::map_result(unsafe {
+                GFp_wasm32_fill_random(chunk.as_mut_ptr(), chunk.len())
+            }));
+        }
+        Ok(())
+    }
+
+    extern {
+        fn GFp_wasm32_fill_random(buf: *mut u8, len: c::size_t) -> c::int;
+    }
+}
+
 /// An adapter that lets the C code use `SecureRandom`.
 #[allow(non_snake_case)]
 #[doc(hidden)]
@@ -299,7 +484,7 @@ pub mod test_util {
 
 #[cfg(test)]
 mod tests {
-    use rand;
+    use {digest, rand, test};
     extern crate std;
 
     #[test]
@@ -333,6 +518,66 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_hmac_drbg() {
+        test::from_file("src/rand_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let digest_alg = match test_case.consume_string("Hash").as_str() {
+                "SHA256" => &digest::SHA256,
+                "SHA384" => &digest::SHA384,
+                "SHA512" => &digest::SHA512,
+                _ => unreachable!(),
+            };
+            let entropy_input = test_case.consume_bytes("EntropyInput");
+            let nonce = test_case.consume_bytes("Nonce");
+            let personalization_string =
+                test_case.consume_bytes("PersonalizationString");
+            let entropy_input_reseed =
+                test_case.consume_bytes("EntropyInputReseed");
+            let additional_input_reseed =
+                test_case.consume_bytes("AdditionalInputReseed");
+            let additional_input_1 =
+                test_case.consume_bytes("AdditionalInput1");
+            let additional_input_2 =
+                test_case.consume_bytes("AdditionalInput2");
+            let expected = test_case.consume_bytes("ReturnedBits");
+
+            let mut drbg = rand::HmacDrbg::new(digest_alg, &entropy_input,
+                                               &nonce,
+                                               &personalization_string);
+            drbg.reseed(&entropy_input_reseed, &additional_input_reseed);
+            let mut out = vec![0u8; expected.len()];
+            try!(drbg.generate(&mut out, &additional_input_1));
+            try!(drbg.generate(&mut out, &additional_input_2));
+            assert_eq!(out, expected);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_hmac_drbg_fill() {
+        let new_drbg = || {
+            rand::HmacDrbg::new(&digest::SHA256, &[0u8; 32], &[1u8; 16], b"")
+        };
+
+        // `fill` is `generate` without additional input, split into requests
+        // of at most `HMAC_DRBG_MAX_REQUEST_LEN` bytes.
+        let drbg = new_drbg();
+        let mut a = vec![0u8; rand::HMAC_DRBG_MAX_REQUEST_LEN + 1];
+        assert!(rand::SecureRandom::fill(&drbg, &mut a).is_ok());
+        let drbg = new_drbg();
+        let mut b = vec![0u8; rand::HMAC_DRBG_MAX_REQUEST_LEN + 1];
+        assert!(drbg.generate(&mut b[..rand::HMAC_DRBG_MAX_REQUEST_LEN], b"")
+                    .is_ok());
+        assert!(drbg.generate(&mut b[rand::HMAC_DRBG_MAX_REQUEST_LEN..], b"")
+                    .is_ok());
+        assert_eq!(a, b);
+
+        let drbg = new_drbg();
+        assert!(drbg.generate(&mut a, b"").is_err());
+    }
+
     #[cfg(any(target_os = "linux", windows))]
     fn max_chunk_len() -> usize { unsafe { super::GFp_sysrand_chunk_max_len } }
 
@@ -0,0 +1,338 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Raw public keys for TLS and DTLS, as specified in [RFC 7250].
+//!
+//! With raw public keys, a peer sends only the DER-encoded
+//! `SubjectPublicKeyInfo` of its key instead of a certificate chain, and the
+//! other peer authenticates it out of band, e.g. by comparing it with a
+//! pinned key. This module parses and encodes these structures and verifies
+//! the `CertificateVerify` signature with them, using the TLS 1.3
+//! `SignatureScheme` ([RFC 8446] Section 4.2.3) to select the algorithm. The
+//! TLS framing, including the 24-bit length prefix of the `cert_data`, is up
+//! to the caller.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # extern crate ring;
+//! # extern crate untrusted;
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::{constant_time, error, raw_public_key};
+//!
+//! # let cert_data: &[u8] = &[];
+//! # let pinned_spki: &[u8] = &[];
+//! # let code_point = 0x0807;
+//! # let signed_content: &[u8] = &[];
+//! # let signature: &[u8] = &[];
+//! let peer_key = try!(raw_public_key::RawPublicKey::from_der(
+//!     untrusted::Input::from(cert_data)));
+//! try!(constant_time::verify_slices_are_equal(
+//!     peer_key.spki().as_slice_less_safe(), pinned_spki));
+//!
+//! let scheme = try!(raw_public_key::SignatureScheme::from_code_point(
+//!     code_point).ok_or(error::Unspecified));
+//! try!(peer_key.verify(scheme, untrusted::Input::from(signed_content),
+//!                      untrusted::Input::from(signature)));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [RFC 7250]: https://tools.ietf.org/html/rfc7250
+//! [RFC 8446]: https://tools.ietf.org/html/rfc8446#section-4.2.3
+
+use {error, signature, signed_data};
+use untrusted;
+
+#[cfg(feature = "use_heap")]
+use der;
+
+#[cfg(feature = "use_heap")]
+use std::vec::Vec;
+
+/// A TLS 1.3 signature scheme.
+pub struct SignatureScheme {
+    code_point: u16,
+
+    // The contents of the `AlgorithmIdentifier` of the public keys that can be
+    // used with this scheme.
+    public_key_alg_id: &'static [u8],
+
+    verification_alg: &'static signature::VerificationAlgorithm,
+}
+
+impl SignatureScheme {
+    /// The scheme's `SignatureScheme` code point.
+    pub fn code_point(&self) -> u16 { self.code_point }
+
+    /// Returns the scheme with the code point `code_point`, or `None` if it
+    /// isn't supported.
+    pub fn from_code_point(code_point: u16)
+                           -> Option<&'static SignatureScheme> {
+        ECDSA_AND_EDDSA_SCHEMES.iter().cloned()
+            .chain(rsa_schemes().iter().cloned())
+            .find(|scheme| scheme.code_point == code_point)
+    }
+}
+
+/// `ecdsa_secp256r1_sha256`, with a P-256 public key.
+pub static ECDSA_SECP256R1_SHA256: SignatureScheme = SignatureScheme {
+    code_point: 0x0403,
+    public_key_alg_id: signed_data::ECDSA_P256,
+    verification_alg: &signature::ECDSA_P256_SHA256_ASN1,
+};
+
+/// `ecdsa_secp384r1_sha384`, with a P-384 public key.
+pub static ECDSA_SECP384R1_SHA384: SignatureScheme = SignatureScheme {
+    code_point: 0x0503,
+    public_key_alg_id: signed_data::ECDSA_P384,
+    verification_alg: &signature::ECDSA_P384_SHA384_ASN1,
+};
+
+/// `ed25519`.
+pub static ED25519: SignatureScheme = SignatureScheme {
+    code_point: 0x0807,
+    public_key_alg_id: signed_data::ED25519,
+    verification_alg: &signature::ED25519,
+};
+
+/// `rsa_pkcs1_sha256`, with a 2048-8192 bit RSA public key.
+///
+/// TLS 1.3 only allows PKCS#1 1.5 signatures in certificates, not in
+/// `CertificateVerify`; this is for TLS 1.2.
+pub static RSA_PKCS1_SHA256: SignatureScheme = SignatureScheme {
+    code_point: 0x0401,
+    public_key_alg_id: signed_data::RSA_ENCRYPTION,
+    verification_alg: &signature::RSA_PKCS1_2048_8192_SHA256,
+};
+
+/// `rsa_pkcs1_sha384`, with a 2048-8192 bit RSA public key.
+///
+/// TLS 1.3 only allows PKCS#1 1.5 signatures in certificates, not in
+/// `CertificateVerify`; this is for TLS 1.2.
+pub static RSA_PKCS1_SHA384: SignatureScheme = SignatureScheme {
+    code_point: 0x0501,
+    public_key_alg_id: signed_data::RSA_ENCRYPTION,
+    verification_alg: &signature::RSA_PKCS1_2048_8192_SHA384,
+};
+
+/// `rsa_pkcs1_sha512`, with a 2048-8192 bit RSA public key.
+///
+/// TLS 1.3 only allows PKCS#1 1.5 signatures in certificates, not in
+/// `CertificateVerify`; this is for TLS 1.2.
+pub static RSA_PKCS1_SHA512: SignatureScheme = SignatureScheme {
+    code_point: 0x0601,
+    public_key_alg_id: signed_data::RSA_ENCRYPTION,
+    verification_alg: &signature::RSA_PKCS1_2048_8192_SHA512,
+};
+
+static ECDSA_AND_EDDSA_SCHEMES: [&'static SignatureScheme; 3] = [
+    &ECDSA_SECP256R1_SHA256,
+    &ECDSA_SECP384R1_SHA384,
+    &ED25519,
+];
+
+fn rsa_schemes() -> &'static [&'static SignatureScheme] {
+    static RSA_SCHEMES: [&'static SignatureScheme; 3] = [
+        &RSA_PKCS1_SHA256,
+        &RSA_PKCS1_SHA384,
+        &RSA_PKCS1_SHA512,
+    ];
+    &RSA_SCHEMES
+}
+
+/// A parsed raw public key.
+pub struct RawPublicKey<'a> {
+    spki: untrusted::Input<'a>,
+    alg_id: untrusted::Input<'a>,
+    public_key: untrusted::Input<'a>,
+}
+
+impl<'a> RawPublicKey<'a> {
+    /// Parses a DER-encoded `SubjectPublicKeyInfo`. The key's algorithm isn't
+    /// checked until it is used.
+    pub fn from_der(spki: untrusted::Input<'a>)
+                    -> Result<RawPublicKey<'a>, error::Unspecified> {
+        let (alg_id, public_key) = try!(signed_data::parse_spki(spki));
+        Ok(RawPublicKey {
+            spki: spki,
+            alg_id: alg_id,
+            public_key: public_key,
+        })
+    }
+
+    /// The DER-encoded `SubjectPublicKeyInfo`.
+    pub fn spki(&self) -> untrusted::Input<'a> { self.spki }
+
+    /// The contents of the `subjectPublicKey` BIT STRING, in the format that
+    /// `ring::signature::verify` takes.
+    pub fn public_key(&self) -> untrusted::Input<'a> { self.public_key }
+
+    /// Verifies the signature `signature` of `msg` with the key, using
+    /// `scheme`. An error is returned if the key can't be used with `scheme`.
+    pub fn verify(&self, scheme: &SignatureScheme, msg: untrusted::Input,
+                  signature: untrusted::Input)
+                  -> Result<(), error::Unspecified> {
+        if self.alg_id.as_slice_less_safe() != scheme.public_key_alg_id {
+            return Err(error::Unspecified);
+        }
+        signature::verify(scheme.verification_alg, self.public_key, msg,
+                          signature)
+    }
+}
+
+/// Encodes `public_key`, in the format that `ring::signature::verify` takes,
+/// as a DER-encoded `SubjectPublicKeyInfo` for a key to be used with `scheme`.
+/// All the RSA schemes have the same encoding.
+///
+/// The public key is only checked to be superficially well-formed; it is
+/// fully validated when it is used to verify a signature.
+///
+/// Feature: `use_heap`.
+#[cfg(feature = "use_heap")]
+pub fn encode(scheme: &SignatureScheme, public_key: &[u8])
+              -> Result<Vec<u8>, error::Unspecified> {
+    let alg_id = scheme.public_key_alg_id;
+    let expected_len = if alg_id == signed_data::ED25519 {
+        Some(32)
+    } else if alg_id == signed_data::ECDSA_P256 {
+        Some(1 + (2 * 32))
+    } else if alg_id == signed_data::ECDSA_P384 {
+        Some(1 + (2 * 48))
+    } else {
+        None
+    };
+    match expected_len {
+        Some(len) => {
+            if public_key.len() != len {
+                return Err(error::Unspecified);
+            }
+            // Only uncompressed points are allowed.
+            if alg_id != signed_data::ED25519 && public_key[0] != 0x04 {
+                return Err(error::Unspecified);
+            }
+        },
+        None => {
+            // An `RSAPublicKey`.
+            try!(untrusted::Input::from(public_key).read_all(
+                    error::Unspecified, |input| {
+                der::nested(input, der::Tag::Sequence, error::Unspecified,
+                            |input| {
+                    let _n = try!(der::positive_integer(input));
+                    let _e = try!(der::positive_integer(input));
+                    Ok(())
+                })
+            }));
+        },
+    }
+
+    let mut bit_string = Vec::with_capacity(1 + public_key.len());
+    bit_string.push(0); // No unused bits.
+    bit_string.extend_from_slice(public_key);
+    let mut spki = Vec::new();
+    der::write_tlv(der::Tag::Sequence, alg_id, &mut spki);
+    der::write_tlv(der::Tag::BitString, &bit_string, &mut spki);
+    let mut result = Vec::new();
+    der::write_tlv(der::Tag::Sequence, &spki, &mut result);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use test;
+    use super::*;
+    use untrusted;
+
+    #[test]
+    fn test_raw_public_key_verify() {
+        test::from_file("src/raw_public_key_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+
+            let scheme = match test_case.consume_string("Scheme").as_str() {
+                "ECDSA_SECP256R1_SHA256" => &ECDSA_SECP256R1_SHA256,
+                "ECDSA_SECP384R1_SHA384" => &ECDSA_SECP384R1_SHA384,
+                "ED25519" => &ED25519,
+                "RSA_PKCS1_SHA256" => &RSA_PKCS1_SHA256,
+                "RSA_PKCS1_SHA384" => &RSA_PKCS1_SHA384,
+                "RSA_PKCS1_SHA512" => &RSA_PKCS1_SHA512,
+                s => panic!("Unsupported scheme: {}", s),
+            };
+            let spki = test_case.consume_bytes("SPKI");
+            let msg = test_case.consume_bytes("Msg");
+            let sig = test_case.consume_bytes("Sig");
+            let expected_result = test_case.consume_string("Result");
+
+            assert_eq!(SignatureScheme::from_code_point(scheme.code_point())
+                           .map(|s| s.code_point()),
+                       Some(scheme.code_point()));
+
+            let key =
+                RawPublicKey::from_der(untrusted::Input::from(&spki)).unwrap();
+            let actual_result =
+                key.verify(scheme, untrusted::Input::from(&msg),
+                           untrusted::Input::from(&sig));
+            assert_eq!(actual_result.is_ok(), expected_result == "P");
+
+            if let Some(public_key) =
+                    test_case.consume_optional_string("PublicKey") {
+                let public_key = test::from_hex(&public_key).unwrap();
+                assert_eq!(key.public_key().as_slice_less_safe(),
+                           &public_key[..]);
+                assert_eq!(encode(scheme, &public_key).unwrap(), spki);
+            }
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_raw_public_key_from_der_invalid() {
+        const INVALID: &'static [&'static [u8]] = &[
+            // Empty.
+            &[],
+            // Trailing data after the SubjectPublicKeyInfo.
+            &[0x30, 0x0a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x01,
+              0x00, 0x00],
+            // Trailing data inside the SubjectPublicKeyInfo.
+            &[0x30, 0x0b, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x01,
+              0x00, 0x00],
+            // The public key has unused bits.
+            &[0x30, 0x0b, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x02,
+              0x01, 0x00],
+            // The public key is an OCTET STRING.
+            &[0x30, 0x09, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x00],
+        ];
+        for spki in INVALID {
+            assert!(RawPublicKey::from_der(untrusted::Input::from(spki))
+                        .is_err());
+        }
+    }
+
+    #[test]
+    fn test_raw_public_key_encode_invalid() {
+        assert!(encode(&ED25519, &[0; 31]).is_err());
+        assert!(encode(&ECDSA_SECP256R1_SHA256, &[0x04; 64]).is_err());
+        assert!(encode(&ECDSA_SECP256R1_SHA256, &[0x02; 65]).is_err());
+        assert!(encode(&ECDSA_SECP384R1_SHA384, &[0x04; 65]).is_err());
+        assert!(encode(&RSA_PKCS1_SHA256, &[0x30, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_signature_scheme_from_code_point() {
+        assert!(SignatureScheme::from_code_point(0x0807).is_some());
+        assert!(SignatureScheme::from_code_point(0x0808).is_none()); // ed448
+        assert!(SignatureScheme::from_code_point(0x0603).is_none());
+    }
+}
@@ -0,0 +1,381 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Multi-precision arithmetic for the RSA public key operation.
+//!
+//! Unlike the `BIGNUM`-based code used for the private key operation, this
+//! keeps every value in a fixed-size array, so that signature verification
+//! and encryption work without a heap. Everything here operates on public
+//! values only, so none of it is constant-time.
+
+use {c, core, error, limb};
+use limb::{Limb, LIMB_BITS, LIMB_BYTES};
+use untrusted;
+
+// The maximum number of limbs of a public modulus.
+const MODULUS_MAX_LIMBS: usize = (super::MAX_BITS + LIMB_BITS - 1) / LIMB_BITS;
+
+// `n0` is always 64 bits; see `GFp_bn_mont_n0`.
+const N0_LIMBS: usize = 64 / LIMB_BITS;
+
+// The maximum length of the public exponent, in bits. See the comments in
+// `GFp_rsa_check_modulus_and_exponent` for why it is limited.
+const PUBLIC_EXPONENT_MAX_BITS: usize = 33;
+
+// `GFp_bn_mul_mont` requires at least four limbs, at least on x86.
+const MODULUS_MIN_LIMBS: usize = 4;
+
+/// An RSA public modulus, with the values that Montgomery multiplication
+/// modulo it needs.
+pub struct Modulus {
+    limbs: [Limb; MODULUS_MAX_LIMBS],
+    num_limbs: usize,
+
+    // -1/n (mod 2**64).
+    n0: [Limb; N0_LIMBS],
+
+    // R**2 (mod n), where R = 2**(LIMB_BITS * num_limbs).
+    rr: [Limb; MODULUS_MAX_LIMBS],
+
+    bits: usize,
+}
+
+impl Modulus {
+    /// Parses the modulus from its big-endian encoding, which must not have
+    /// any leading zeros. The modulus must be odd and its length must be in
+    /// the range [`min_bits`, `max_bits`]; `max_bits` must not be larger than
+    /// `super::MAX_BITS`.
+    pub fn from_be_bytes(input: untrusted::Input, min_bits: usize,
+                         max_bits: usize)
+                         -> Result<Modulus, error::Unspecified> {
+        debug_assert!(max_bits <= super::MAX_BITS);
+        let bits = try!(bits_of_positive_integer(input));
+        if bits < min_bits || bits > max_bits {
+            return Err(error::Unspecified);
+        }
+        // Checking the length is a shortcut for checking that the modulus is
+        // larger than any public exponent.
+        if bits <= PUBLIC_EXPONENT_MAX_BITS {
+            return Err(error::Unspecified);
+        }
+
+        let num_limbs =
+            core::cmp::max((bits + LIMB_BITS - 1) / LIMB_BITS,
+                           MODULUS_MIN_LIMBS);
+        let mut limbs = [0; MODULUS_MAX_LIMBS];
+        limbs_from_be_bytes(input.as_slice_less_safe(),
+                            &mut limbs[..num_limbs]);
+        if limbs[0] & 1 == 0 {
+            return Err(error::Unspecified);
+        }
+
+        let mut r = Modulus {
+            limbs: limbs,
+            num_limbs: num_limbs,
+            n0: n0_from_u64(n0(limbs_low_u64(&limbs))),
+            rr: [0; MODULUS_MAX_LIMBS],
+            bits: bits,
+        };
+        r.compute_rr();
+        Ok(r)
+    }
+
+    /// The length of the modulus in bits.
+    #[inline]
+    pub fn bits(&self) -> usize { self.bits }
+
+    /// The length of the modulus in bytes, which is the length of the
+    /// signatures and ciphertexts for it.
+    #[inline]
+    pub fn len_bytes(&self) -> usize { (self.bits + 7) / 8 }
+
+    fn n(&self) -> &[Limb] { &self.limbs[..self.num_limbs] }
+
+    // Computes R**2 (mod n). First, R (mod n) is computed by doubling the
+    // largest power of two less than `n` until it reaches R. Then, that
+    // value, which is 1 in the Montgomery domain, is doubled `num_limbs`
+    // more times, to 2**num_limbs in the Montgomery domain, which is then
+    // squared `log2(LIMB_BITS)` times, to
+    // 2**(LIMB_BITS * num_limbs) == R in the Montgomery domain, i.e.
+    // R**2 (mod n).
+    fn compute_rr(&mut self) {
+        let num_limbs = self.num_limbs;
+        let mut acc = [0; MODULUS_MAX_LIMBS];
+        {
+            let acc = &mut acc[..num_limbs];
+            let top_bit = self.bits - 1;
+            acc[top_bit / LIMB_BITS] = 1 << (top_bit % LIMB_BITS);
+            let r_bits = LIMB_BITS * num_limbs;
+            for _ in top_bit..(r_bits + num_limbs) {
+                elem_double(acc, self.n());
+            }
+            let mut i = 1;
+            while i < LIMB_BITS {
+                elem_squared(acc, self);
+                i *= 2;
+            }
+        }
+        self.rr = acc;
+    }
+}
+
+/// An RSA public exponent.
+#[derive(Clone, Copy)]
+pub struct PublicExponent(u64);
+
+impl PublicExponent {
+    /// Parses the public exponent from its big-endian encoding, which must
+    /// not have any leading zeros. The exponent must be odd, at least 3, and
+    /// at most 33 bits long.
+    pub fn from_be_bytes(input: untrusted::Input)
+                         -> Result<PublicExponent, error::Unspecified> {
+        let bits = try!(bits_of_positive_integer(input));
+        if bits < 2 || bits > PUBLIC_EXPONENT_MAX_BITS {
+            return Err(error::Unspecified);
+        }
+        let value = input.as_slice_less_safe().iter()
+                         .fold(0u64, |acc, b| (acc << 8) | u64::from(*b));
+        if value & 1 == 0 {
+            return Err(error::Unspecified);
+        }
+        Ok(PublicExponent(value))
+    }
+}
+
+/// Replaces `inout`, the big-endian encoding of a value less than `n` that is
+/// exactly as long as `n`, with the encoding of `inout**e (mod n)`.
+pub fn public_op(n: &Modulus, e: PublicExponent, inout: &mut [u8])
+                 -> Result<(), error::Unspecified> {
+    if inout.len() != n.len_bytes() {
+        return Err(error::Unspecified);
+    }
+    let num_limbs = n.num_limbs;
+
+    let mut base = [0; MODULUS_MAX_LIMBS];
+    let base = &mut base[..num_limbs];
+    limbs_from_be_bytes(inout, base);
+    if limb::limbs_less_than_limbs_constant_time(base, n.n()) !=
+            limb::LimbMask::True {
+        return Err(error::Unspecified);
+    }
+
+    // Convert `base` to the Montgomery domain.
+    elem_mul(base, &n.rr[..num_limbs], n);
+
+    // Left-to-right binary exponentiation. The most significant bit of `e`
+    // is accounted for by starting with `acc` = `base`.
+    let mut acc = [0; MODULUS_MAX_LIMBS];
+    let acc = &mut acc[..num_limbs];
+    acc.copy_from_slice(base);
+    let e_bits = 64 - (e.0.leading_zeros() as usize);
+    for bit in (0..(e_bits - 1)).rev() {
+        elem_squared(acc, n);
+        if (e.0 >> bit) & 1 == 1 {
+            elem_mul(acc, base, n);
+        }
+    }
+
+    // Convert `acc` out of the Montgomery domain.
+    let mut one = [0; MODULUS_MAX_LIMBS];
+    one[0] = 1;
+    elem_mul(acc, &one[..num_limbs], n);
+
+    limbs_to_be_bytes(acc, inout);
+    Ok(())
+}
+
+// Returns the length in bits of the positive integer encoded in `input`,
+// rejecting the encoding if it is empty or has leading zeros, including the
+// encoding of zero.
+fn bits_of_positive_integer(input: untrusted::Input)
+                            -> Result<usize, error::Unspecified> {
+    let bytes = input.as_slice_less_safe();
+    match bytes.first() {
+        Some(&first) if first != 0 =>
+            Ok((bytes.len() * 8) - (first.leading_zeros() as usize)),
+        _ => Err(error::Unspecified),
+    }
+}
+
+// `out` must be long enough to hold the value of `input`.
+fn limbs_from_be_bytes(input: &[u8], out: &mut [Limb]) {
+    for limb in out.iter_mut() {
+        *limb = 0;
+    }
+    for (i, b) in input.iter().rev().enumerate() {
+        out[i / LIMB_BYTES] |= Limb::from(*b) << (8 * (i % LIMB_BYTES));
+    }
+}
+
+// `out` must be long enough to hold the value of `limbs`.
+fn limbs_to_be_bytes(limbs: &[Limb], out: &mut [u8]) {
+    for (i, b) in out.iter_mut().rev().enumerate() {
+        *b = (limbs[i / LIMB_BYTES] >> (8 * (i % LIMB_BYTES))) as u8;
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+fn limbs_low_u64(limbs: &[Limb]) -> u64 { limbs[0] }
+
+#[cfg(target_pointer_width = "32")]
+fn limbs_low_u64(limbs: &[Limb]) -> u64 {
+    u64::from(limbs[0]) | (u64::from(limbs[1]) << 32)
+}
+
+#[cfg(target_pointer_width = "64")]
+fn n0_from_u64(n0: u64) -> [Limb; N0_LIMBS] { [n0] }
+
+#[cfg(target_pointer_width = "32")]
+fn n0_from_u64(n0: u64) -> [Limb; N0_LIMBS] { [n0 as Limb, (n0 >> 32) as Limb] }
+
+// Returns -1/`n` (mod 2**64) for odd `n`. Each Newton iteration doubles the
+// number of correct low bits of the inverse, starting with one, since every
+// odd number is its own inverse (mod 2).
+fn n0(n: u64) -> u64 {
+    debug_assert_eq!(n & 1, 1);
+    let mut inv = 1u64;
+    for _ in 0..6 {
+        inv = inv.wrapping_mul(2u64.wrapping_sub(n.wrapping_mul(inv)));
+    }
+    inv.wrapping_neg()
+}
+
+// r = 2 * r (mod n), for r < n.
+fn elem_double(r: &mut [Limb], n: &[Limb]) {
+    let mut carry = 0;
+    for limb in r.iter_mut() {
+        let next_carry = *limb >> (LIMB_BITS - 1);
+        *limb = (*limb << 1) | carry;
+        carry = next_carry;
+    }
+    if carry != 0 ||
+       limb::limbs_less_than_limbs_constant_time(r, n) != limb::LimbMask::True {
+        let mut borrow = false;
+        for (r, n) in r.iter_mut().zip(n) {
+            let (difference, b1) = r.overflowing_sub(*n);
+            let (difference, b2) = difference.overflowing_sub(borrow as Limb);
+            *r = difference;
+            borrow = b1 || b2;
+        }
+    }
+}
+
+// a = a * b / R (mod n), for a, b < n.
+fn elem_mul(a: &mut [Limb], b: &[Limb], n: &Modulus) {
+    debug_assert_eq!(a.len(), n.num_limbs);
+    debug_assert_eq!(b.len(), n.num_limbs);
+    unsafe {
+        GFp_bn_mul_mont(a.as_mut_ptr(), a.as_ptr(), b.as_ptr(),
+                        n.limbs.as_ptr(), n.n0.as_ptr(),
+                        n.num_limbs as c::int);
+    }
+}
+
+// a = a * a / R (mod n), for a < n.
+fn elem_squared(a: &mut [Limb], n: &Modulus) {
+    debug_assert_eq!(a.len(), n.num_limbs);
+    unsafe {
+        GFp_bn_mul_mont(a.as_mut_ptr(), a.as_ptr(), a.as_ptr(),
+                        n.limbs.as_ptr(), n.n0.as_ptr(),
+                        n.num_limbs as c::int);
+    }
+}
+
+extern {
+    // `r` may alias `a` and/or `b`.
+    fn GFp_bn_mul_mont(r: *mut Limb, a: *const Limb, b: *const Limb,
+                       n: *const Limb, n0: *const Limb, num_limbs: c::int);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use untrusted;
+
+    #[test]
+    fn test_public_exponent_from_be_bytes() {
+        let e = |bytes: &[u8]| {
+            PublicExponent::from_be_bytes(untrusted::Input::from(bytes))
+                .map(|e| e.0)
+        };
+        assert_eq!(e(&[3]), Ok(3));
+        assert_eq!(e(&[1, 0, 1]), Ok(65537));
+        assert_eq!(e(&[1, 0xff, 0xff, 0xff, 0xff]), Ok(0x1ffffffff));
+
+        // Empty, zero, and leading zeros.
+        assert!(e(&[]).is_err());
+        assert!(e(&[0]).is_err());
+        assert!(e(&[0, 3]).is_err());
+
+        // Too small, even, and too large.
+        assert!(e(&[1]).is_err());
+        assert!(e(&[4]).is_err());
+        assert!(e(&[2, 0, 0, 0, 1]).is_err());
+    }
+
+    #[test]
+    fn test_modulus_from_be_bytes() {
+        let n = |bytes: &[u8], min_bits| {
+            Modulus::from_be_bytes(untrusted::Input::from(bytes), min_bits,
+                                   super::super::MAX_BITS)
+                .map(|n| n.bits())
+        };
+        let mut bytes = [0xffu8; 256];
+        assert_eq!(n(&bytes, 2048), Ok(2048));
+        assert_eq!(n(&bytes[..255], 2040), Ok(2040));
+        assert!(n(&bytes[..255], 2048).is_err());
+
+        // Leading zeros.
+        bytes[0] = 0;
+        assert!(n(&bytes, 2040).is_err());
+
+        // Even.
+        bytes[0] = 1;
+        bytes[255] = 0xfe;
+        assert!(n(&bytes, 2040).is_err());
+        bytes[255] = 0xff;
+        assert_eq!(n(&bytes, 2040), Ok(2041));
+
+        // Empty and too short for any public exponent.
+        assert!(n(&[], 0).is_err());
+        assert!(n(&[0xff; 4], 0).is_err());
+        assert_eq!(n(&[0xff; 5], 0), Ok(40));
+    }
+
+    #[test]
+    fn test_public_op() {
+        // n = 0xfffffffb * 0xffffffef.
+        let n_bytes = [0xff, 0xff, 0xff, 0xea, 0x00, 0x00, 0x00, 0x55];
+        let n = Modulus::from_be_bytes(untrusted::Input::from(&n_bytes), 0,
+                                       super::super::MAX_BITS).unwrap();
+        let e = PublicExponent::from_be_bytes(
+            untrusted::Input::from(&[1, 0, 1])).unwrap();
+
+        let mut inout = [0, 0, 0, 0, 0, 0, 0, 2];
+        assert!(public_op(&n, e, &mut inout).is_ok());
+        // 2**65537 (mod n).
+        assert_eq!(inout, [0xc8, 0x45, 0x3c, 0x14, 0x29, 0xa4, 0x71, 0x31]);
+
+        let mut inout = [0; 8];
+        assert!(public_op(&n, e, &mut inout).is_ok());
+        assert_eq!(inout, [0; 8]);
+
+        // The input must be less than `n` and exactly as long as it.
+        let mut inout = n_bytes;
+        assert!(public_op(&n, e, &mut inout).is_err());
+        assert!(public_op(&n, e, &mut [0; 7]).is_err());
+        assert!(public_op(&n, e, &mut [0; 9]).is_err());
+    }
+}
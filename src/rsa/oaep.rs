@@ -0,0 +1,433 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! RSAES-OAEP encryption, as specified in [RFC 8017 Section 7.1].
+//!
+//! MGF1 is always used with the same digest algorithm as the one used to hash
+//! the label. Public keys must be at least 2048 bits. Decryption requires the
+//! `rsa_signing` feature, since it uses `RSAKeyPair`.
+//!
+//! RSA-OAEP can only encrypt short messages. `seal` and `open` encrypt
+//! messages of any length to an RSA public key by combining RSA-OAEP with an
+//! AEAD.
+//!
+//! ```no_run
+//! # extern crate ring;
+//! # extern crate untrusted;
+//! # #[cfg(feature = "rsa_signing")]
+//! # fn example(public_key_der: &[u8], private_key_der: &[u8])
+//! #            -> Result<(), ring::error::Unspecified> {
+//! use ring::{rand, signature};
+//! use ring::rsa::oaep;
+//!
+//! let rng = rand::SystemRandom::new();
+//!
+//! // Encrypt a message to a DER-encoded `RSAPublicKey`.
+//! let ciphertext =
+//!     try!(oaep::encrypt(&oaep::RSA_OAEP_SHA256,
+//!                        untrusted::Input::from(public_key_der), b"label",
+//!                        b"hello, world", &rng));
+//!
+//! // Decrypt it with the corresponding private key.
+//! let key_pair = try!(signature::RSAKeyPair::from_der(
+//!     untrusted::Input::from(private_key_der)));
+//! let plaintext = try!(oaep::decrypt(&oaep::RSA_OAEP_SHA256, &key_pair,
+//!                                    b"label", &ciphertext, &rng));
+//! assert_eq!(&plaintext[..], b"hello, world");
+//! # Ok(())
+//! # }
+//! #
+//! # #[cfg(not(feature = "rsa_signing"))]
+//! # fn example(_: &[u8], _: &[u8]) -> Result<(), ring::error::Unspecified> {
+//! #     Ok(())
+//! # }
+//! #
+//! # fn main() { example(&[], &[]).unwrap() }
+//! ```
+//!
+//! [RFC 8017 Section 7.1]: https://tools.ietf.org/html/rfc8017#section-7.1
+
+use {aead, digest, error, init, mgf1, rand, secret};
+use core;
+use std::vec::Vec;
+use super::{bigint, MAX_BITS, parse_public_key};
+#[cfg(feature = "rsa_signing")]
+use super::PrivateTransform;
+#[cfg(feature = "rsa_signing")]
+use super::signing::RSAKeyPair;
+#[cfg(feature = "rsa_signing")]
+use audit;
+use untrusted;
+
+/// An RSAES-OAEP encryption algorithm.
+pub struct OAEPAlgorithm {
+    #[cfg(feature = "rsa_signing")]
+    name: &'static str,
+    digest_alg: &'static digest::Algorithm,
+}
+
+/// RSAES-OAEP with SHA-256 and MGF1 with SHA-256.
+pub static RSA_OAEP_SHA256: OAEPAlgorithm = OAEPAlgorithm {
+    #[cfg(feature = "rsa_signing")]
+    name: "RSA_OAEP_SHA256",
+    digest_alg: &digest::SHA256,
+};
+
+/// RSAES-OAEP with SHA-384 and MGF1 with SHA-384.
+pub static RSA_OAEP_SHA384: OAEPAlgorithm = OAEPAlgorithm {
+    #[cfg(feature = "rsa_signing")]
+    name: "RSA_OAEP_SHA384",
+    digest_alg: &digest::SHA384,
+};
+
+/// RSAES-OAEP with SHA-512 and MGF1 with SHA-512.
+pub static RSA_OAEP_SHA512: OAEPAlgorithm = OAEPAlgorithm {
+    #[cfg(feature = "rsa_signing")]
+    name: "RSA_OAEP_SHA512",
+    digest_alg: &digest::SHA512,
+};
+
+// The minimum size of public moduli that are supported.
+const MIN_BITS: usize = 2048;
+
+/// Encrypts `plaintext` to `public_key` using `alg`, with the given `label`,
+/// and returns the ciphertext.
+///
+/// `public_key` is a DER-encoded ASN.1 `RSAPublicKey` (see
+/// [RFC 8017 Appendix A.1.1]). The ciphertext is exactly as long as the
+/// public modulus. `plaintext` may be at most `k - 2 * hLen - 2` bytes long,
+/// where `k` is the length of the public modulus and `hLen` is the output
+/// length of the digest algorithm; e.g. 190 bytes for a 2048-bit key with
+/// `RSA_OAEP_SHA256`.
+///
+/// [RFC 8017 Appendix A.1.1]:
+///     https://tools.ietf.org/html/rfc8017#appendix-A.1.1
+pub fn encrypt(alg: &'static OAEPAlgorithm, public_key: untrusted::Input,
+               label: &[u8], plaintext: &[u8], rng: &rand::SecureRandom)
+               -> Result<Vec<u8>, error::Unspecified> {
+    init::init_once();
+
+    let (n, e) = try!(parse_public_key(public_key));
+    let k = n.len();
+    let h_len = alg.digest_alg.output_len;
+    if k < 2 * h_len + 2 || plaintext.len() > k - 2 * h_len - 2 {
+        return Err(error::Unspecified);
+    }
+    let n = try!(bigint::Modulus::from_be_bytes(n, MIN_BITS, MAX_BITS));
+    let e = try!(bigint::PublicExponent::from_be_bytes(e));
+
+    // EM = 0x00 || maskedSeed || maskedDB, where
+    // DB = lHash || PS || 0x01 || M.
+    let mut em = vec![0u8; k];
+    {
+        let (seed, db) = em[1..].split_at_mut(h_len);
+        try!(rng.fill(seed));
+        db[..h_len].copy_from_slice(
+            digest::digest(alg.digest_alg, label).as_ref());
+        let msg_start = db.len() - plaintext.len();
+        db[msg_start - 1] = 0x01;
+        db[msg_start..].copy_from_slice(plaintext);
+        try!(mgf1::xor_mask(alg.digest_alg, seed, db));
+        try!(mgf1::xor_mask(alg.digest_alg, db, seed));
+    }
+
+    // The leading zero byte ensures that EM is less than the modulus.
+    try!(bigint::public_op(&n, e, &mut em));
+    Ok(em)
+}
+
+/// Decrypts `ciphertext` with `key_pair` using `alg`, with the given `label`,
+/// and returns the plaintext. Feature: `rsa_signing`.
+///
+/// `ciphertext` must be exactly as long as the public modulus. `rng` is used
+/// for blinding the private key operation.
+///
+/// All decryption failures, including a wrong label, are indistinguishable
+/// from each other; in particular, the padding is checked in constant time.
+#[cfg(feature = "rsa_signing")]
+pub fn decrypt(alg: &'static OAEPAlgorithm, key_pair: &RSAKeyPair,
+               label: &[u8], ciphertext: &[u8], rng: &rand::SecureRandom)
+               -> Result<Vec<u8>, error::Unspecified> {
+    init::init_once();
+
+    let mut em = ciphertext.to_vec();
+    try!(key_pair.private_transform(&mut em, rng));
+    decode(alg, label, &mut em)
+        .map(|plaintext| plaintext.to_vec())
+        .map_err(|e| {
+            audit::notify(audit::EventKind::DecryptionFailed, alg.name);
+            e
+        })
+}
+
+/// Encrypts `plaintext`, with the additional authenticated data `ad`, to
+/// `public_key`, and returns a self-contained sealed message.
+///
+/// A random content key for `content_alg` is encrypted to `public_key` with
+/// `RSA_OAEP_SHA256`, using the name of `content_alg` as the label, and
+/// `plaintext` is encrypted with the content key and an all-zero nonce. The
+/// result is the RSA-OAEP ciphertext, which is exactly as long as the public
+/// modulus, followed by the AEAD ciphertext and tag. This is the same
+/// construction as `multi_recipient::seal` uses for `Recipient::RsaOaep`, for
+/// the common case of a single recipient that only has an RSA key.
+///
+/// `public_key` is a DER-encoded ASN.1 `RSAPublicKey`, as for `encrypt`.
+pub fn seal(content_alg: &'static aead::Algorithm, public_key: untrusted::Input,
+            ad: &[u8], plaintext: &[u8], rng: &rand::SecureRandom)
+            -> Result<Vec<u8>, error::Unspecified> {
+    let mut content_key = secret::SecretBox::new([0u8; MAX_CONTENT_KEY_LEN]);
+    let content_key = &mut content_key.expose_mut()[..content_alg.key_len()];
+    try!(rng.fill(content_key));
+
+    let mut sealed = try!(encrypt(&RSA_OAEP_SHA256, public_key,
+                                  content_alg.name().as_bytes(), content_key,
+                                  rng));
+    let wrapped_key_len = sealed.len();
+    let tag_len = content_alg.tag_len();
+    sealed.extend_from_slice(plaintext);
+    sealed.extend(core::iter::repeat(0).take(tag_len));
+
+    let key = try!(aead::SealingKey::new(content_alg, content_key));
+    let _ = try!(aead::seal_in_place(&key, &NONCE[..content_alg.nonce_len()],
+                                     &mut sealed[wrapped_key_len..], tag_len,
+                                     ad));
+    Ok(sealed)
+}
+
+/// Decrypts `sealed`, the result of `seal`, with `key_pair`, authenticating
+/// the additional authenticated data `ad`, and returns the plaintext.
+/// Feature: `rsa_signing`.
+///
+/// `content_alg` must be the algorithm that `sealed` was sealed with. `rng`
+/// is used for blinding the private key operation.
+#[cfg(feature = "rsa_signing")]
+pub fn open(content_alg: &'static aead::Algorithm, key_pair: &RSAKeyPair,
+            ad: &[u8], sealed: &[u8], rng: &rand::SecureRandom)
+            -> Result<Vec<u8>, error::Unspecified> {
+    let wrapped_key_len = key_pair.public_modulus_len();
+    if sealed.len() < wrapped_key_len + content_alg.tag_len() {
+        return Err(error::Unspecified);
+    }
+    let (wrapped_key, ciphertext) = sealed.split_at(wrapped_key_len);
+    let content_key = secret::SecretBox::new(
+        try!(decrypt(&RSA_OAEP_SHA256, key_pair,
+                     content_alg.name().as_bytes(), wrapped_key, rng)));
+
+    let key = try!(aead::OpeningKey::new(content_alg, content_key.expose()));
+    let mut in_out = ciphertext.to_vec();
+    let plaintext_len =
+        try!(aead::open_in_place(&key, &NONCE[..content_alg.nonce_len()], 0,
+                                 &mut in_out, ad));
+    in_out.truncate(plaintext_len);
+    Ok(in_out)
+}
+
+// The longest content key of any AEAD algorithm.
+const MAX_CONTENT_KEY_LEN: usize = 32;
+
+// Each content key that `seal` generates encrypts only one message.
+const NONCE: [u8; 24] = [0u8; 24];
+
+// EME-OAEP decoding, https://tools.ietf.org/html/rfc8017#section-7.1.2 step
+// 3. This must not reveal, through timing or otherwise, which check failed,
+// or Manger's attack becomes possible.
+#[cfg(feature = "rsa_signing")]
+fn decode<'a>(alg: &OAEPAlgorithm, label: &[u8], em: &'a mut [u8])
+              -> Result<&'a [u8], error::Unspecified> {
+    let h_len = alg.digest_alg.output_len;
+    if em.len() < 2 * h_len + 2 {
+        return Err(error::Unspecified);
+    }
+    let (y, rest) = em.split_at_mut(1);
+    let (seed, db) = rest.split_at_mut(h_len);
+    try!(mgf1::xor_mask(alg.digest_alg, db, seed));
+    try!(mgf1::xor_mask(alg.digest_alg, seed, db));
+
+    let mut bad = y[0];
+    let l_hash = digest::digest(alg.digest_alg, label);
+    for (a, b) in db[..h_len].iter().zip(l_hash.as_ref()) {
+        bad |= a ^ b;
+    }
+
+    // PS is zero or more zero bytes, followed by the 0x01 separator. `looking`
+    // is 0xff until the separator has been found.
+    let mut looking = 0xffu8;
+    let mut msg_start = 0;
+    for (i, &b) in db.iter().enumerate().skip(h_len) {
+        let is_one = is_zero(b ^ 0x01);
+        let found = looking & is_one;
+        msg_start |= (i + 1) & 0usize.wrapping_sub((found & 1) as usize);
+        bad |= looking & !is_zero(b) & !is_one;
+        looking &= !is_one;
+    }
+    bad |= looking;
+
+    if bad != 0 {
+        return Err(error::Unspecified);
+    }
+    Ok(&db[msg_start..])
+}
+
+// Returns 0xff if `b` is zero and 0 otherwise, without branching.
+#[cfg(feature = "rsa_signing")]
+fn is_zero(b: u8) -> u8 { ((b as u32).wrapping_sub(1) >> 8) as u8 }
+
+
+#[cfg(test)]
+mod tests {
+    use rand;
+    #[cfg(feature = "rsa_signing")]
+    use test;
+    use super::*;
+    use untrusted;
+
+    #[cfg(feature = "rsa_signing")]
+    const PRIVATE_KEY: &'static [u8] =
+        include_bytes!("signature_rsa_example_private_key.der");
+    const PUBLIC_KEY: &'static [u8] =
+        include_bytes!("signature_rsa_example_public_key.der");
+
+    #[cfg(feature = "rsa_signing")]
+    fn alg_from_name(name: &str) -> &'static OAEPAlgorithm {
+        match name {
+            "SHA256" => &RSA_OAEP_SHA256,
+            "SHA384" => &RSA_OAEP_SHA384,
+            "SHA512" => &RSA_OAEP_SHA512,
+            _ => panic!("Unsupported digest: {}", name),
+        }
+    }
+
+    #[cfg(feature = "rsa_signing")]
+    #[test]
+    fn test_rsa_oaep_decrypt() {
+        let rng = rand::SystemRandom::new();
+        let key_pair =
+            RSAKeyPair::from_der(untrusted::Input::from(PRIVATE_KEY)).unwrap();
+        test::from_file("src/rsa/rsa_oaep_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let alg = alg_from_name(&test_case.consume_string("Digest"));
+            let label = test_case.consume_bytes("Label");
+            let plaintext = test_case.consume_bytes("Plaintext");
+            let ciphertext = test_case.consume_bytes("Ciphertext");
+            let result = test_case.consume_string("Result");
+
+            let actual = decrypt(alg, &key_pair, &label, &ciphertext, &rng);
+            match result.as_str() {
+                "P" => assert_eq!(actual.unwrap(), plaintext),
+                "F" => assert!(actual.is_err()),
+                _ => panic!("Unexpected result: {}", result),
+            }
+            Ok(())
+        });
+    }
+
+    #[cfg(feature = "rsa_signing")]
+    #[test]
+    fn test_rsa_oaep_round_trip() {
+        let rng = rand::SystemRandom::new();
+        let key_pair =
+            RSAKeyPair::from_der(untrusted::Input::from(PRIVATE_KEY)).unwrap();
+        let plaintext = [0x5au8; 190];
+        for &(alg, max_len) in &[(&RSA_OAEP_SHA256, 190),
+                                 (&RSA_OAEP_SHA384, 158),
+                                 (&RSA_OAEP_SHA512, 126)] {
+            for &len in &[0, 1, max_len] {
+                let ciphertext =
+                    encrypt(alg, untrusted::Input::from(PUBLIC_KEY), b"label",
+                            &plaintext[..len], &rng).unwrap();
+                assert_eq!(ciphertext.len(), key_pair.public_modulus_len());
+                let actual = decrypt(alg, &key_pair, b"label", &ciphertext,
+                                     &rng).unwrap();
+                assert_eq!(&actual[..], &plaintext[..len]);
+                assert!(decrypt(alg, &key_pair, b"", &ciphertext,
+                                &rng).is_err());
+            }
+        }
+    }
+
+    #[cfg(feature = "rsa_signing")]
+    #[test]
+    fn test_rsa_oaep_seal_open() {
+        use aead;
+
+        let rng = rand::SystemRandom::new();
+        let key_pair =
+            RSAKeyPair::from_der(untrusted::Input::from(PRIVATE_KEY)).unwrap();
+        let public_key = untrusted::Input::from(PUBLIC_KEY);
+        let plaintext = [0x5au8; 1000];
+        for &content_alg in [&aead::AES_128_GCM, &aead::AES_256_GCM,
+                             &aead::CHACHA20_POLY1305,
+                             &aead::XCHACHA20_POLY1305].iter() {
+            for &len in &[0, 1, plaintext.len()] {
+                let sealed = seal(content_alg, public_key, b"ad",
+                                  &plaintext[..len], &rng).unwrap();
+                assert_eq!(sealed.len(), key_pair.public_modulus_len() + len +
+                                         content_alg.tag_len());
+                let actual =
+                    open(content_alg, &key_pair, b"ad", &sealed, &rng).unwrap();
+                assert_eq!(&actual[..], &plaintext[..len]);
+            }
+
+            // The wrong AD, the wrong content algorithm, a modified wrapped
+            // key or ciphertext, and a truncated message are all rejected.
+            let sealed =
+                seal(content_alg, public_key, b"ad", b"hello", &rng).unwrap();
+            assert!(open(content_alg, &key_pair, b"", &sealed, &rng).is_err());
+            let other_alg = if content_alg.name() == aead::AES_256_GCM.name() {
+                &aead::CHACHA20_POLY1305
+            } else {
+                &aead::AES_256_GCM
+            };
+            assert!(open(other_alg, &key_pair, b"ad", &sealed, &rng).is_err());
+            for &i in &[0, key_pair.public_modulus_len(), sealed.len() - 1] {
+                let mut modified = sealed.clone();
+                modified[i] ^= 1;
+                assert!(open(content_alg, &key_pair, b"ad", &modified,
+                             &rng).is_err());
+            }
+            assert!(open(content_alg, &key_pair, b"ad",
+                         &sealed[..(sealed.len() - 1)], &rng).is_err());
+            assert!(open(content_alg, &key_pair, b"ad",
+                         &sealed[..key_pair.public_modulus_len()],
+                         &rng).is_err());
+        }
+
+        let invalid_public_key = untrusted::Input::from(&PUBLIC_KEY[..100]);
+        assert!(seal(&aead::AES_128_GCM, invalid_public_key, b"", b"hello",
+                     &rng).is_err());
+    }
+
+    #[test]
+    fn test_rsa_oaep_encrypt_plaintext_too_long() {
+        let rng = rand::SystemRandom::new();
+        let public_key = untrusted::Input::from(PUBLIC_KEY);
+        let plaintext = [0u8; 191];
+        for &(alg, max_len) in &[(&RSA_OAEP_SHA256, 190),
+                                 (&RSA_OAEP_SHA384, 158),
+                                 (&RSA_OAEP_SHA512, 126)] {
+            assert!(encrypt(alg, public_key, b"", &plaintext[..max_len],
+                            &rng).is_ok());
+            assert!(encrypt(alg, public_key, b"", &plaintext[..(max_len + 1)],
+                            &rng).is_err());
+        }
+    }
+
+    #[test]
+    fn test_rsa_oaep_encrypt_invalid_public_key() {
+        let rng = rand::SystemRandom::new();
+        let public_key = untrusted::Input::from(&PUBLIC_KEY[..100]);
+        assert!(encrypt(&RSA_OAEP_SHA256, public_key, b"", b"",
+                        &rng).is_err());
+    }
+}
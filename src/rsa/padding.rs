@@ -12,98 +12,236 @@
 // OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
 // CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 
-use {der, digest, error};
+use {constant_time, der, digest, error, mgf1};
 use untrusted;
 
+/// Common features of both RSA padding encoding and RSA padding verification.
+pub trait Padding: Sync {
+    /// The name of the padding algorithm, which is the name of its `static`
+    /// in *ring*, e.g. `"RSA_PKCS1_SHA256"`.
+    fn name(&self) -> &'static str;
+
+    /// The digest algorithm used to hash the message.
+    fn digest_alg(&self) -> &'static digest::Algorithm;
+}
+
 /// The term "Encoding" comes from RFC 3447.
 #[cfg(feature = "rsa_signing")]
-pub trait Encoding: Sync {
-    fn encode(&self, msg: &[u8], out: &mut [u8])
+pub trait Encoding: Padding {
+    /// `m_hash` is the digest of the message, computed with `digest_alg()`.
+    fn encode(&self, m_hash: &digest::Digest, out: &mut [u8])
               -> Result<(), error::Unspecified>;
 }
 
 /// The term "Verification" comes from RFC 3447.
-pub trait Verification: Sync {
-    fn verify(&self, msg: untrusted::Input, encoded: untrusted::Input)
-              -> Result<(), error::Unspecified>;
+pub trait Verification: Padding {
+    /// `m_hash` is the digest of the message, computed with `digest_alg()`.
+    /// `mod_bits` is the length of the public modulus in bits; `encoded` is
+    /// always exactly as long as the public modulus in bytes.
+    fn verify(&self, m_hash: &[u8], encoded: untrusted::Input,
+              mod_bits: usize) -> Result<(), error::Unspecified>;
 }
 
 pub struct PKCS1 {
+    name: &'static str,
     digest_alg: &'static digest::Algorithm,
     digestinfo_prefix: &'static [u8],
 }
 
+impl Padding for PKCS1 {
+    fn name(&self) -> &'static str { self.name }
+
+    fn digest_alg(&self) -> &'static digest::Algorithm { self.digest_alg }
+}
+
 #[cfg(feature ="rsa_signing")]
 impl Encoding for PKCS1 {
-    // Implement padding procedure per EMSA-PKCS1-v1_5,
-    // https://tools.ietf.org/html/rfc3447#section-9.2.
-    fn encode(&self, msg: &[u8], out: &mut [u8])
+    fn encode(&self, m_hash: &digest::Digest, out: &mut [u8])
               -> Result<(), error::Unspecified> {
-        let digest_len = self.digestinfo_prefix.len() +
-                         self.digest_alg.output_len;
-
-        // Require at least 8 bytes of padding. Since we disallow keys smaller
-        // than 2048 bits, this should never happen anyway.
-        debug_assert!(out.len() >= digest_len + 11);
-        let pad_len = out.len() - digest_len - 3;
-        out[0] = 0;
-        out[1] = 1;
-        for i in 0..pad_len {
-            out[2 + i] = 0xff;
-        }
-        out[2 + pad_len] = 0;
-
-        let (digest_prefix, digest_dst) = out[3 + pad_len..]
-            .split_at_mut(self.digestinfo_prefix.len());
-        digest_prefix.copy_from_slice(self.digestinfo_prefix);
-        digest_dst.copy_from_slice(
-            digest::digest(self.digest_alg, msg).as_ref());
+        pkcs1_encode(self, m_hash.as_ref(), out);
         Ok(())
     }
 }
 
 impl Verification for PKCS1 {
-    fn verify(&self, msg: untrusted::Input, encoded: untrusted::Input)
-              -> Result<(), error::Unspecified> {
-        encoded.read_all(error::Unspecified, |decoded| {
-            if try!(decoded.read_byte()) != 0 ||
-               try!(decoded.read_byte()) != 1 {
+    // Rather than parsing the decoded padding, encode the message the way the
+    // signer would have and compare the two encodings. There is exactly one
+    // valid encoding of a given message for a given modulus length, so this
+    // is equivalent to a correct parser, but there is much less to get wrong.
+    fn verify(&self, m_hash: &[u8], encoded: untrusted::Input,
+              _mod_bits: usize) -> Result<(), error::Unspecified> {
+        let encoded = encoded.as_slice_less_safe();
+        let mut calculated = [0u8; (super::MAX_BITS + 7) / 8];
+        if encoded.len() > calculated.len() ||
+           encoded.len() < pkcs1_min_encoded_len(self) {
+            return Err(error::Unspecified);
+        }
+        let calculated = &mut calculated[..encoded.len()];
+        pkcs1_encode(self, m_hash, calculated);
+        constant_time::verify_slices_are_equal(calculated, encoded)
+    }
+}
+
+// Require at least 8 bytes of padding.
+fn pkcs1_min_encoded_len(pkcs1: &PKCS1) -> usize {
+    pkcs1.digestinfo_prefix.len() + pkcs1.digest_alg.output_len + 11
+}
+
+// Implement padding procedure per EMSA-PKCS1-v1_5,
+// https://tools.ietf.org/html/rfc3447#section-9.2, starting from the digest
+// `m_hash` of the message.
+fn pkcs1_encode(pkcs1: &PKCS1, m_hash: &[u8], out: &mut [u8]) {
+    let digest_len = pkcs1.digestinfo_prefix.len() +
+                     pkcs1.digest_alg.output_len;
+
+    // Since we disallow keys smaller than 2048 bits, this should never fail
+    // when signing. When verifying, the length was already checked.
+    debug_assert!(out.len() >= pkcs1_min_encoded_len(pkcs1));
+    let pad_len = out.len() - digest_len - 3;
+    out[0] = 0;
+    out[1] = 1;
+    for i in 0..pad_len {
+        out[2 + i] = 0xff;
+    }
+    out[2 + pad_len] = 0;
+
+    let (digest_prefix, digest_dst) = out[3 + pad_len..]
+        .split_at_mut(pkcs1.digestinfo_prefix.len());
+    digest_prefix.copy_from_slice(pkcs1.digestinfo_prefix);
+    digest_dst.copy_from_slice(m_hash);
+}
+
+/// RSASSA-PSS padding. MGF1 is always used as the mask generation function,
+/// with the same digest algorithm as is used for the message.
+pub struct PSS {
+    name: &'static str,
+    digest_alg: &'static digest::Algorithm,
+    salt_len: SaltLen,
+}
+
+enum SaltLen {
+    Exactly(usize),
+
+    // Recover the salt length from the encoded message, as described in the
+    // note to step 10 of EMSA-PSS-VERIFY.
+    Any,
+}
+
+impl Padding for PSS {
+    fn name(&self) -> &'static str { self.name }
+
+    fn digest_alg(&self) -> &'static digest::Algorithm { self.digest_alg }
+}
+
+impl Verification for PSS {
+    // EMSA-PSS-VERIFY, https://tools.ietf.org/html/rfc8017#section-9.1.2.
+    // Everything here is derived from public values, so it doesn't need to
+    // be constant-time.
+    fn verify(&self, m_hash: &[u8], encoded: untrusted::Input,
+              mod_bits: usize) -> Result<(), error::Unspecified> {
+        let h_len = self.digest_alg.output_len;
+        let em_bits = mod_bits - 1;
+        let em_len = (em_bits + 7) / 8;
+        let top_byte_mask = 0xffu8 >> ((8 * em_len) - em_bits);
+
+        // RSASSA-PSS-VERIFY step 2.c: when `mod_bits` is one more than a
+        // multiple of 8, the encoded message is one byte shorter than the
+        // modulus, so the decoded signature must start with a zero byte.
+        let encoded = encoded.as_slice_less_safe();
+        let em = if encoded.len() > em_len {
+            if encoded[0] != 0 {
                 return Err(error::Unspecified);
             }
+            &encoded[1..]
+        } else {
+            encoded
+        };
+        if em.len() != em_len {
+            return Err(error::Unspecified);
+        }
 
-            let mut ps_len = 0;
-            loop {
-                match try!(decoded.read_byte()) {
-                    0xff => {
-                        ps_len += 1;
-                    },
-                    0x00 => {
+        // Step 3, for the part that doesn't depend on the salt length.
+        if em_len < h_len + 2 {
+            return Err(error::Unspecified);
+        }
+
+        // Step 4.
+        if em[em_len - 1] != 0xbc {
+            return Err(error::Unspecified);
+        }
+
+        // Step 5.
+        let db_len = em_len - h_len - 1;
+        let (masked_db, h) = em[..(em_len - 1)].split_at(db_len);
+
+        // Step 6.
+        if masked_db[0] & !top_byte_mask != 0 {
+            return Err(error::Unspecified);
+        }
+
+        // Steps 7 through 13, one digest-sized chunk of DB at a time, so that
+        // neither DB nor the salt is ever buffered. Step 2 was done by the
+        // caller. `ps_len` is the length of the zero padding that precedes
+        // the 0x01 separator; everything after the separator is the salt,
+        // which is fed into the computation of H' as it is unmasked.
+        let mut ps_len = match self.salt_len {
+            SaltLen::Exactly(salt_len) => {
+                if db_len < salt_len + 1 {
+                    return Err(error::Unspecified);
+                }
+                Some(db_len - salt_len - 1)
+            },
+            SaltLen::Any => None,
+        };
+        let mut ctx = digest::Context::new(self.digest_alg);
+        ctx.update(&[0u8; 8]);
+        ctx.update(m_hash);
+        for (i, masked_chunk) in masked_db.chunks(h_len).enumerate() {
+            let mut chunk = [0u8; digest::MAX_OUTPUT_LEN];
+            let chunk = &mut chunk[..masked_chunk.len()];
+            chunk.copy_from_slice(masked_chunk);
+            try!(mgf1::xor_mask_block(self.digest_alg, h, i, chunk));
+            if i == 0 {
+                chunk[0] &= top_byte_mask;
+            }
+
+            let chunk_start = i * h_len;
+            let mut salt_start = chunk.len();
+            for (j, &b) in chunk.iter().enumerate() {
+                let pos = chunk_start + j;
+                match ps_len {
+                    Some(ps_len) if pos > ps_len => {
+                        salt_start = j;
                         break;
                     },
-                    _ => {
-                        return Err(error::Unspecified);
+                    Some(ps_len) if pos == ps_len => {
+                        if b != 0x01 {
+                            return Err(error::Unspecified);
+                        }
+                    },
+                    Some(_) => {
+                        if b != 0 {
+                            return Err(error::Unspecified);
+                        }
+                    },
+                    None => {
+                        if b == 0x01 {
+                            ps_len = Some(pos);
+                        } else if b != 0 {
+                            return Err(error::Unspecified);
+                        }
                     },
                 }
             }
-            if ps_len < 8 {
-                return Err(error::Unspecified);
-            }
-
-            let decoded_digestinfo_prefix = try!(decoded.skip_and_get_input(
-                        self.digestinfo_prefix.len()));
-            if decoded_digestinfo_prefix != self.digestinfo_prefix {
-                return Err(error::Unspecified);
-            }
+            ctx.update(&chunk[salt_start..]);
+        }
+        if ps_len.is_none() {
+            return Err(error::Unspecified);
+        }
+        let h_prime = ctx.finish();
 
-            let digest_alg = self.digest_alg;
-            let decoded_digest =
-                try!(decoded.skip_and_get_input(digest_alg.output_len));
-            let digest = digest::digest(digest_alg, msg.as_slice_less_safe());
-            if decoded_digest != digest.as_ref() {
-                return Err(error::Unspecified);
-            }
-            Ok(())
-        })
+        // Step 14.
+        constant_time::verify_slices_are_equal(h, h_prime.as_ref())
     }
 }
 
@@ -113,6 +251,7 @@ macro_rules! rsa_pkcs1_padding {
         #[doc=$doc_str]
         /// Feature: `rsa_signing`.
         pub static $PADDING_ALGORITHM: PKCS1 = PKCS1 {
+            name: stringify!($PADDING_ALGORITHM),
             digest_alg: $digest_alg,
             digestinfo_prefix: $digestinfo_prefix,
         };
@@ -131,6 +270,64 @@ rsa_pkcs1_padding!(RSA_PKCS1_SHA384, &digest::SHA384,
 rsa_pkcs1_padding!(RSA_PKCS1_SHA512, &digest::SHA512,
                    &SHA512_PKCS1_DIGESTINFO_PREFIX,
                    "PKCS#1 1.5 padding using SHA-512 for RSA signatures.");
+rsa_pkcs1_padding!(RSA_PKCS1_SHA512_256, &digest::SHA512_256,
+                   &SHA512_256_PKCS1_DIGESTINFO_PREFIX,
+                   "PKCS#1 1.5 padding using SHA-512/256 for RSA signatures.");
+
+macro_rules! rsa_pss_padding {
+    ( $PADDING_ALGORITHM:ident, $digest_alg:expr, $salt_len:expr,
+      $doc_str:expr ) => {
+        #[doc=$doc_str]
+        pub static $PADDING_ALGORITHM: PSS = PSS {
+            name: stringify!($PADDING_ALGORITHM),
+            digest_alg: $digest_alg,
+            salt_len: $salt_len,
+        };
+    }
+}
+
+rsa_pss_padding!(RSA_PSS_SHA256, &digest::SHA256, SaltLen::Exactly(32),
+                 "RSA PSS padding using SHA-256 for RSA signatures, with a
+                  32-byte salt.");
+rsa_pss_padding!(RSA_PSS_SHA384, &digest::SHA384, SaltLen::Exactly(48),
+                 "RSA PSS padding using SHA-384 for RSA signatures, with a
+                  48-byte salt.");
+rsa_pss_padding!(RSA_PSS_SHA512, &digest::SHA512, SaltLen::Exactly(64),
+                 "RSA PSS padding using SHA-512 for RSA signatures, with a
+                  64-byte salt.");
+rsa_pss_padding!(RSA_PSS_SHA256_SALT_LEN_0, &digest::SHA256,
+                 SaltLen::Exactly(0),
+                 "RSA PSS padding using SHA-256 for RSA signatures, with an
+                  empty salt.");
+rsa_pss_padding!(RSA_PSS_SHA384_SALT_LEN_0, &digest::SHA384,
+                 SaltLen::Exactly(0),
+                 "RSA PSS padding using SHA-384 for RSA signatures, with an
+                  empty salt.");
+rsa_pss_padding!(RSA_PSS_SHA512_SALT_LEN_0, &digest::SHA512,
+                 SaltLen::Exactly(0),
+                 "RSA PSS padding using SHA-512 for RSA signatures, with an
+                  empty salt.");
+rsa_pss_padding!(RSA_PSS_SHA256_SALT_LEN_20, &digest::SHA256,
+                 SaltLen::Exactly(20),
+                 "RSA PSS padding using SHA-256 for RSA signatures, with a
+                  20-byte salt.");
+rsa_pss_padding!(RSA_PSS_SHA384_SALT_LEN_20, &digest::SHA384,
+                 SaltLen::Exactly(20),
+                 "RSA PSS padding using SHA-384 for RSA signatures, with a
+                  20-byte salt.");
+rsa_pss_padding!(RSA_PSS_SHA512_SALT_LEN_20, &digest::SHA512,
+                 SaltLen::Exactly(20),
+                 "RSA PSS padding using SHA-512 for RSA signatures, with a
+                  20-byte salt.");
+rsa_pss_padding!(RSA_PSS_SHA256_ANY_SALT_LEN, &digest::SHA256, SaltLen::Any,
+                 "RSA PSS padding using SHA-256 for RSA signatures, with a
+                  salt of any length.");
+rsa_pss_padding!(RSA_PSS_SHA384_ANY_SALT_LEN, &digest::SHA384, SaltLen::Any,
+                 "RSA PSS padding using SHA-384 for RSA signatures, with a
+                  salt of any length.");
+rsa_pss_padding!(RSA_PSS_SHA512_ANY_SALT_LEN, &digest::SHA512, SaltLen::Any,
+                 "RSA PSS padding using SHA-512 for RSA signatures, with a
+                  salt of any length.");
 
 macro_rules! pkcs1_digestinfo_prefix {
     ( $name:ident, $digest_len:expr, $digest_oid_len:expr,
@@ -159,3 +356,82 @@ pkcs1_digestinfo_prefix!(
 pkcs1_digestinfo_prefix!(
     SHA512_PKCS1_DIGESTINFO_PREFIX, 64, 9,
     [ 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03 ]);
+
+pkcs1_digestinfo_prefix!(
+    SHA512_256_PKCS1_DIGESTINFO_PREFIX, 32, 9,
+    [ 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x06 ]);
+
+
+#[cfg(test)]
+mod tests {
+    use {digest, error, mgf1};
+    use std::vec::Vec;
+    use super::*;
+    use untrusted;
+
+    // EMSA-PSS-ENCODE, https://tools.ietf.org/html/rfc8017#section-9.1.1,
+    // for a modulus of `mod_bits` bits, where `mod_bits` is a multiple of 8.
+    fn pss_encode(digest_alg: &'static digest::Algorithm, m_hash: &[u8],
+                  salt: &[u8], mod_bits: usize) -> Vec<u8> {
+        let h_len = digest_alg.output_len;
+        let mut em = vec![0u8; mod_bits / 8];
+        let db_len = em.len() - h_len - 1;
+        let mut ctx = digest::Context::new(digest_alg);
+        ctx.update(&[0u8; 8]);
+        ctx.update(m_hash);
+        ctx.update(salt);
+        let h = ctx.finish();
+        {
+            let (db, rest) = em.split_at_mut(db_len);
+            let ps_len = db_len - salt.len() - 1;
+            db[ps_len] = 0x01;
+            db[(ps_len + 1)..].copy_from_slice(salt);
+            mgf1::xor_mask(digest_alg, h.as_ref(), db).unwrap();
+            db[0] &= 0x7f;
+            rest[..h_len].copy_from_slice(h.as_ref());
+            rest[h_len] = 0xbc;
+        }
+        em
+    }
+
+    #[test]
+    fn test_pss_verify_any_length() {
+        let m_hash = digest::digest(&digest::SHA256, b"message");
+        let m_hash = m_hash.as_ref();
+        let salt = [0x5au8; 32];
+
+        // The moduli are deliberately not limited to the lengths that RSA
+        // signature verification supports; in particular, PSS decoding works
+        // for moduli larger than `MAX_BITS`, and for salts that span several
+        // digest-sized blocks of DB.
+        for &mod_bits in &[1024, 2048, 2056, 4096, 16384] {
+            let em = pss_encode(&digest::SHA256, m_hash, &salt, mod_bits);
+            let em_input = untrusted::Input::from(&em);
+            assert_eq!(RSA_PSS_SHA256.verify(m_hash, em_input, mod_bits),
+                       Ok(()));
+            assert_eq!(RSA_PSS_SHA256_ANY_SALT_LEN.verify(m_hash, em_input,
+                                                          mod_bits),
+                       Ok(()));
+            assert_eq!(RSA_PSS_SHA256_SALT_LEN_20.verify(m_hash, em_input,
+                                                         mod_bits),
+                       Err(error::Unspecified));
+
+            let long_salt = vec![0xa5u8; mod_bits / 8 - 32 - 2];
+            let em = pss_encode(&digest::SHA256, m_hash, &long_salt,
+                                mod_bits);
+            assert_eq!(RSA_PSS_SHA256_ANY_SALT_LEN.verify(
+                           m_hash, untrusted::Input::from(&em), mod_bits),
+                       Ok(()));
+
+            // Flipping any bit of the encoded message, except for the bits
+            // above `mod_bits - 1`, invalidates it.
+            for i in 0..em.len() {
+                let mut bad = em.clone();
+                bad[i] ^= if i == 0 { 0x40 } else { 0x01 };
+                assert_eq!(RSA_PSS_SHA256_ANY_SALT_LEN.verify(
+                               m_hash, untrusted::Input::from(&bad), mod_bits),
+                           Err(error::Unspecified));
+            }
+        }
+    }
+}
@@ -12,19 +12,47 @@
 // OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
 // CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 
-/// RSA PKCS#1 1.5 signatures.
+/// RSA PKCS#1 1.5 and RSASSA-PSS signatures and RSAES-OAEP encryption.
 
-use {c, core, der, error};
+use {der, error};
+#[cfg(feature = "rsa_signing")]
+use {c, core, rand};
 use untrusted;
 
+mod bigint;
+
+#[cfg(feature = "use_heap")]
+pub mod oaep;
+
 pub mod padding;
 
-// `RSA_PKCS1_SHA1` is intentionally not exposed.
-pub use self::padding::{RSA_PKCS1_SHA256, RSA_PKCS1_SHA384, RSA_PKCS1_SHA512};
+// `RSA_PKCS1_SHA1` is intentionally not exposed; see `ring::legacy`.
+pub use self::padding::{RSA_PKCS1_SHA256, RSA_PKCS1_SHA384, RSA_PKCS1_SHA512,
+                        RSA_PKCS1_SHA512_256};
 
+// The maximum size of public moduli that are supported.
+const MAX_BITS: usize = 8192;
 
 /// Parameters for RSA verification.
+///
+/// Verification is always strict, so that every implementation that follows
+/// the same rules agrees bit-for-bit on which signatures are valid. In
+/// particular, a signature is rejected unless:
+///
+/// * it is exactly as long as the public modulus (leading zero bytes must be
+///   present and no extra leading zero bytes are allowed),
+/// * its value is less than the public modulus, and
+/// * the decoded value is exactly the EMSA-PKCS1-v1_5 encoding that the
+///   signer would have produced, including the DER encoding of the
+///   `DigestInfo` with its `NULL` parameters.
+///
+/// Thus, for a given public key and message there is exactly one valid
+/// PKCS#1 1.5 signature. PSS signatures are randomized, so there are many
+/// valid signatures, but the encoded message must still be exactly as
+/// described in RFC 8017, with the salt length fixed by the algorithm unless
+/// an `_ANY_SALT_LEN` variant is used.
 pub struct RSAParameters {
+    name: &'static str,
     padding_alg: &'static padding::Verification,
     min_bits: usize,
 }
@@ -41,18 +69,13 @@ fn parse_public_key(input: untrusted::Input)
     })
 }
 
+#[cfg(feature = "rsa_signing")]
 struct PositiveInteger {
     value: Option<*mut BIGNUM>,
 }
 
+#[cfg(feature = "rsa_signing")]
 impl PositiveInteger {
-    #[cfg(feature = "rsa_signing")]
-    // Parses a single ASN.1 DER-encoded `Integer`, which most be positive.
-    fn from_der(input: &mut untrusted::Reader)
-                -> Result<PositiveInteger, error::Unspecified> {
-        Self::from_be_bytes(try!(der::positive_integer(input)))
-    }
-
     // Turns a sequence of big-endian bytes into a Positive Integer.
     fn from_be_bytes(input: untrusted::Input)
                      -> Result<PositiveInteger, error::Unspecified> {
@@ -78,7 +101,6 @@ impl PositiveInteger {
 
     unsafe fn as_ref<'a>(&'a self) -> &'a BIGNUM { &*self.value.unwrap() }
 
-    #[cfg(feature = "rsa_signing")]
     fn into_raw(&mut self) -> *mut BIGNUM {
         let res = self.value.unwrap();
         self.value = None;
@@ -86,6 +108,7 @@ impl PositiveInteger {
     }
 }
 
+#[cfg(feature = "rsa_signing")]
 impl Drop for PositiveInteger {
     fn drop(&mut self) {
         match self.value {
@@ -104,29 +127,87 @@ enum BN_MONT_CTX {}
 
 pub mod verification;
 
+// The security strength of RSA keys with moduli of `modulus_bits` bits, from
+// NIST SP 800-57 Part 1 Section 5.6.1, Table 2.
+fn security_bits(modulus_bits: usize) -> usize {
+    if modulus_bits >= 15360 { 256 }
+    else if modulus_bits >= 7680 { 192 }
+    else if modulus_bits >= 3072 { 128 }
+    else if modulus_bits >= 2048 { 112 }
+    else { 80 }
+}
+
+/// Converts the RSA signature `signature`, a big-endian-encoded integer with
+/// any number of leading zeros, to its fixed-length encoding in `out`, which
+/// must be exactly as long as the public modulus, e.g.
+/// `RSAKeyPair::public_modulus_len()` bytes. This is the encoding that RSA
+/// signatures have in most protocols and that *ring* produces and expects.
+///
+/// Some APIs represent signatures as integers, e.g. Java's
+/// `BigInteger.toByteArray()`, which omits leading zeros but may add a zero
+/// byte for the sign. This fails if the value of `signature` doesn't fit in
+/// `out`.
+pub fn rsa_integer_to_fixed(signature: untrusted::Input, out: &mut [u8])
+                            -> Result<(), error::Unspecified> {
+    let value = rsa_fixed_to_integer(signature);
+    if value.len() > out.len() {
+        return Err(error::Unspecified);
+    }
+    let (zeros, rest) = out.split_at_mut(out.len() - value.len());
+    for b in zeros {
+        *b = 0;
+    }
+    rest.copy_from_slice(value);
+    Ok(())
+}
+
+/// Returns the RSA signature `signature`, or any other big-endian-encoded
+/// integer, without its leading zeros, for APIs that represent signatures as
+/// integers. The value zero is encoded as a single zero byte. Use
+/// `rsa_integer_to_fixed` to convert the result back.
+pub fn rsa_fixed_to_integer<'a>(signature: untrusted::Input<'a>) -> &'a [u8] {
+    let signature = signature.as_slice_less_safe();
+    match signature.iter().position(|b| *b != 0) {
+        Some(first_nonzero) => &signature[first_nonzero..],
+        None => &signature[signature.len().saturating_sub(1)..],
+    }
+}
+
+#[cfg(feature = "rsa_signing")]
 enum BIGNUM {}
 
 #[cfg(feature = "rsa_signing")]
 pub mod signing;
 
+// The RSA private key operation, implemented by `signing::RSAKeyPair`. This
+// trait is private so that the other RSA modules (i.e. `oaep`) can use the
+// private key without exposing the raw operation outside of this module.
+#[cfg(feature = "rsa_signing")]
+trait PrivateTransform {
+    // Replaces `inout`, which must be exactly as long as the public modulus,
+    // with `inout**d mod n`, using blinding.
+    fn private_transform(&self, inout: &mut [u8], rng: &rand::SecureRandom)
+                         -> Result<(), error::Unspecified>;
+}
+
 
+#[cfg(feature = "rsa_signing")]
 extern {
     fn GFp_BN_bin2bn(in_: *const u8, len: c::size_t, ret: *mut BIGNUM)
                      -> *mut BIGNUM;
     fn GFp_BN_free(bn: *mut BIGNUM);
-}
-
-#[cfg(feature = "rsa_signing")]
-extern {
     fn GFp_BN_MONT_CTX_free(mont: *mut BN_MONT_CTX);
 }
 
 
 #[cfg(test)]
 mod tests {
+    use super::{rsa_fixed_to_integer, rsa_integer_to_fixed};
+    #[cfg(feature = "rsa_signing")]
     use super::PositiveInteger;
     use untrusted;
 
+    #[cfg(feature = "rsa_signing")]
     #[test]
     fn test_positive_integer_from_be_bytes_empty() {
         // Empty values are rejected.
@@ -134,6 +215,7 @@ mod tests {
                     untrusted::Input::from(&[])).is_err());
     }
 
+    #[cfg(feature = "rsa_signing")]
     #[test]
     fn test_positive_integer_from_be_bytes_zero() {
         // The zero value is rejected.
@@ -152,4 +234,30 @@ mod tests {
         assert!(PositiveInteger::from_be_bytes(
                     untrusted::Input::from(&[1, 0])).is_ok());
     }
+
+    #[test]
+    fn test_rsa_signature_format_conversion() {
+        let to_integer =
+            |fixed: &[u8]| rsa_fixed_to_integer(untrusted::Input::from(fixed))
+                               .to_vec();
+        assert_eq!(to_integer(&[0, 0, 1, 0]), [1, 0]);
+        assert_eq!(to_integer(&[1, 0, 0, 0]), [1, 0, 0, 0]);
+        assert_eq!(to_integer(&[0, 0, 0, 0]), [0]);
+        assert_eq!(to_integer(&[]), []);
+
+        let to_fixed = |integer: &[u8], len: usize| {
+            let mut out = vec![0xff; len];
+            rsa_integer_to_fixed(untrusted::Input::from(integer), &mut out)
+                .map(|()| out)
+        };
+        assert_eq!(to_fixed(&[1, 0], 4).unwrap(), [0, 0, 1, 0]);
+        assert_eq!(to_fixed(&[1, 0, 0, 0], 4).unwrap(), [1, 0, 0, 0]);
+        // A leading zero for the sign, as in `BigInteger.toByteArray()`.
+        assert_eq!(to_fixed(&[0, 0x80, 0, 0, 0], 4).unwrap(),
+                   [0x80, 0, 0, 0]);
+        assert_eq!(to_fixed(&[0], 4).unwrap(), [0, 0, 0, 0]);
+        assert_eq!(to_fixed(&[], 4).unwrap(), [0, 0, 0, 0]);
+        // Values that don't fit are rejected.
+        assert!(to_fixed(&[1, 0, 0, 0, 0], 4).is_err());
+    }
 }
@@ -14,7 +14,7 @@
 
 /// RSA PKCS#1 1.5 signatures.
 
-use {bssl, c, der, error};
+use {audit, bssl, c, der, digest, error, pkcs8, private, signature};
 use rand;
 use std;
 use super::{BIGNUM, GFp_BN_free, BN_MONT_CTX, GFp_BN_MONT_CTX_free, padding,
@@ -23,12 +23,25 @@ use untrusted;
 
 /// An RSA key pair, used for signing. Feature: `rsa_signing`.
 ///
-/// After constructing an `RSAKeyPair`, construct one or more
+/// Use `RSAKeyPair::sign()` to generate signatures, or construct one or more
 /// `RSASigningState`s that reference the `RSAKeyPair` and use
-/// `RSASigningState::sign()` to generate signatures. See `ring::signature`'s
-/// module-level documentation for an example.
+/// `RSASigningState::sign()`. See `ring::signature`'s module-level
+/// documentation for an example.
 pub struct RSAKeyPair {
     rsa: RSA,
+
+    // The public modulus and exponent, big-endian encoded without leading
+    // zeros.
+    n: std::vec::Vec<u8>,
+    e: std::vec::Vec<u8>,
+
+    // The DER-encoded `RSAPublicKey`.
+    public_key: std::vec::Vec<u8>,
+
+    // The blindings that `sign` and `private_transform` reuse. A blinding is
+    // removed from the pool while it is in use, so concurrent operations
+    // never share one, and the lock isn't held during the operation.
+    blindings: std::sync::Mutex<std::vec::Vec<Blinding>>,
 }
 
 impl RSAKeyPair {
@@ -39,6 +52,52 @@ impl RSAKeyPair {
     /// (n) must be at least 2048 bits. Currently, the public modulus must be
     /// no larger than 4096 bits.
     ///
+    /// Most tools, including `openssl genpkey`, generate keys in the PKCS#8
+    /// format instead; use `from_pkcs8` for those. PEM-encoded keys that are
+    /// in `RSAPrivateKey` format can be decoded using an OpenSSL command like
+    /// this:
+    ///
+    /// ```sh
+    /// openssl rsa -in private_key.pem -outform DER -out private_key.der
+    /// ```
+    ///
+    /// The error says why the key was rejected: `InvalidEncoding` if the
+    /// input isn't a valid `RSAPrivateKey`, `VersionNotSupported` for
+    /// multi-prime keys, `UnsupportedSize` if the public modulus is too small
+    /// or too large, and `InconsistentComponents` if the key's components
+    /// aren't a valid RSA key.
+    ///
+    /// [RFC 3447 Appendix A.1.2]:
+    ///     https://tools.ietf.org/html/rfc3447#appendix-A.1.2
+    pub fn from_der(input: untrusted::Input)
+                    -> Result<RSAKeyPair, error::KeyRejected> {
+        input.read_all(error::KeyRejected::InvalidEncoding, |input| {
+            der::nested(input, der::Tag::Sequence,
+                        error::KeyRejected::InvalidEncoding, |input| {
+                let version =
+                    try!(der::small_nonnegative_integer(input)
+                            .map_err(error::KeyRejected::invalid_encoding));
+                if version != 0 {
+                    return Err(error::KeyRejected::VersionNotSupported);
+                }
+                let n = try!(positive_integer(input));
+                let e = try!(positive_integer(input));
+                let d = try!(positive_integer(input));
+                let p = try!(positive_integer(input));
+                let q = try!(positive_integer(input));
+                let d_p = try!(positive_integer(input));
+                let d_q = try!(positive_integer(input));
+                let q_inv = try!(positive_integer(input));
+                RSAKeyPair::from_components(n, e, d, p, q, d_p, d_q, q_inv)
+            })
+        })
+    }
+
+    /// Parse a private key in DER-encoded PKCS#8 `PrivateKeyInfo` form (see
+    /// [RFC 5208] and [RFC 5958]), with the algorithm identifier
+    /// `rsaEncryption`. The `privateKey` must be an `RSAPrivateKey` that
+    /// `from_der` accepts.
+    ///
     /// Here's one way to generate a key in the required format using OpenSSL:
     ///
     /// ```sh
@@ -48,54 +107,88 @@ impl RSAKeyPair {
     ///                 -out private_key.der
     /// ```
     ///
-    /// Often, keys generated for use in OpenSSL-based software are
-    /// encoded in PEM format, which is not supported by *ring*. PEM-encoded
-    /// keys that are in `RSAPrivateKey` format can be decoded into the using
-    /// an OpenSSL command like this:
+    /// A `PrivateKeyInfo` for another algorithm is rejected with
+    /// `UnsupportedAlgorithm`; otherwise the errors are those of `from_der`.
     ///
-    /// ```sh
-    /// openssl rsa -in private_key.pem -outform DER -out private_key.der
-    /// ```
+    /// [RFC 5208]: https://tools.ietf.org/html/rfc5208
+    /// [RFC 5958]: https://tools.ietf.org/html/rfc5958
+    pub fn from_pkcs8(input: untrusted::Input)
+                      -> Result<RSAKeyPair, error::KeyRejected> {
+        pkcs8::rsa_key_pair(input)
+    }
+
+    /// Constructs a key pair from the components of an `RSAPrivateKey` (see
+    /// [RFC 3447 Appendix A.1.2]), for keys that come from formats such as
+    /// JWK or from HSM exports.
     ///
-    /// If these commands don't work, it is likely that the private key is in a
-    /// different format like PKCS#8, which isn't supported yet. An upcoming
-    /// version of *ring* will likely replace the support for the
-    /// `RSAPrivateKey` format with support for the PKCS#8 format.
+    /// `d_p`, `d_q`, and `q_inv` are the CRT exponents and coefficient
+    /// `dP`, `dQ`, and `qInv`. Each component is an unsigned big-endian
+    /// encoded value, which must be positive and must not have any leading
+    /// zeros. The key is checked exactly as it is by `from_der`, and rejected
+    /// with the same errors.
     ///
     /// [RFC 3447 Appendix A.1.2]:
     ///     https://tools.ietf.org/html/rfc3447#appendix-A.1.2
-    pub fn from_der(input: untrusted::Input)
-                    -> Result<RSAKeyPair, error::Unspecified> {
-        input.read_all(error::Unspecified, |input| {
-            der::nested(input, der::Tag::Sequence, error::Unspecified, |input| {
-                let version = try!(der::small_nonnegative_integer(input));
-                if version != 0 {
-                    return Err(error::Unspecified);
-                }
-                let n = try!(PositiveInteger::from_der(input));
-                let mut e = try!(PositiveInteger::from_der(input));
-                let d = try!(PositiveInteger::from_der(input));
-                let p = try!(PositiveInteger::from_der(input));
-                let q = try!(PositiveInteger::from_der(input));
-                let mut dmp1 = try!(PositiveInteger::from_der(input));
-                let mut dmq1 = try!(PositiveInteger::from_der(input));
-                let mut iqmp = try!(PositiveInteger::from_der(input));
-                let mut rsa = RSA {
-                    e: e.into_raw(), dmp1: dmp1.into_raw(),
-                    dmq1: dmq1.into_raw(), iqmp: iqmp.into_raw(),
-                    mont_n: std::ptr::null_mut(), mont_p: std::ptr::null_mut(),
-                    mont_q: std::ptr::null_mut(),
-                    mont_qq: std::ptr::null_mut(),
-                    qmn_mont: std::ptr::null_mut(),
-                    iqmp_mont: std::ptr::null_mut(),
-                };
-                try!(bssl::map_result(unsafe {
-                    GFp_rsa_new_end(&mut rsa, n.as_ref(), d.as_ref(),
-                                    p.as_ref(), q.as_ref())
-                }));
-                Ok(RSAKeyPair { rsa: rsa })
-            })
-        })
+    pub fn from_components(n: untrusted::Input, e: untrusted::Input,
+                           d: untrusted::Input, p: untrusted::Input,
+                           q: untrusted::Input, d_p: untrusted::Input,
+                           d_q: untrusted::Input, q_inv: untrusted::Input)
+                           -> Result<RSAKeyPair, error::KeyRejected> {
+        let n_ = try!(component(n));
+        let mut e_ = try!(component(e));
+        let d = try!(component(d));
+        let p = try!(component(p));
+        let q = try!(component(q));
+        let mut dmp1 = try!(component(d_p));
+        let mut dmq1 = try!(component(d_q));
+        let mut iqmp = try!(component(q_inv));
+
+        // `GFp_rsa_new_end` checks the size too, but it can't say why it
+        // rejected a key.
+        let n_bits = (n.len() * 8) -
+                     (n.as_slice_less_safe()[0].leading_zeros() as usize);
+        if n_bits < MIN_MODULUS_BITS || n_bits > MAX_MODULUS_BITS {
+            return Err(error::KeyRejected::UnsupportedSize);
+        }
+
+        // Construct the key pair before finishing the initialization so that
+        // `Drop` frees everything if `GFp_rsa_new_end` fails.
+        let mut key_pair = RSAKeyPair {
+            rsa: RSA {
+                e: e_.into_raw(), dmp1: dmp1.into_raw(),
+                dmq1: dmq1.into_raw(), iqmp: iqmp.into_raw(),
+                mont_n: std::ptr::null_mut(), mont_p: std::ptr::null_mut(),
+                mont_q: std::ptr::null_mut(), mont_qq: std::ptr::null_mut(),
+                qmn_mont: std::ptr::null_mut(),
+                iqmp_mont: std::ptr::null_mut(),
+            },
+            n: n.as_slice_less_safe().to_vec(),
+            e: e.as_slice_less_safe().to_vec(),
+            public_key: public_key_der(n, e),
+            blindings: std::sync::Mutex::new(std::vec::Vec::new()),
+        };
+        try!(bssl::map_result(unsafe {
+            GFp_rsa_new_end(&mut key_pair.rsa, n_.as_ref(), d.as_ref(),
+                            p.as_ref(), q.as_ref())
+        }).map_err(|_| error::KeyRejected::InconsistentComponents));
+        audit::notify(audit::EventKind::KeyCreated, "RSA");
+        Ok(key_pair)
+    }
+
+    /// The public modulus (n), big-endian encoded without leading zeros.
+    pub fn public_modulus_be_bytes(&self) -> &[u8] { &self.n }
+
+    /// The public exponent (e), big-endian encoded without leading zeros.
+    pub fn public_exponent_be_bytes(&self) -> &[u8] { &self.e }
+
+    /// The public key, DER-encoded as an ASN.1 `RSAPublicKey` (see
+    /// [RFC 3447 Appendix A.1.1]). This is the format that
+    /// `ring::signature::verify` accepts for RSA public keys.
+    ///
+    /// [RFC 3447 Appendix A.1.1]:
+    ///     https://tools.ietf.org/html/rfc3447#appendix-A.1.1
+    pub fn public_key_der(&self) -> std::vec::Vec<u8> {
+        self.public_key.clone()
     }
 
     /// Returns the length in bytes of the key pair's public modulus.
@@ -104,8 +197,99 @@ impl RSAKeyPair {
     pub fn public_modulus_len(&self) -> usize {
         unsafe { GFp_RSA_size(&self.rsa) }
     }
+
+    /// The approximate security strength of the key pair, in bits, as
+    /// estimated from the length of its public modulus in
+    /// [NIST SP 800-57 Part 1] Section 5.6.1.
+    ///
+    /// [NIST SP 800-57 Part 1]:
+    ///     http://nvlpubs.nist.gov/nistpubs/SpecialPublications/NIST.SP.800-57pt1r4.pdf
+    pub fn security_bits(&self) -> usize {
+        let modulus_bits =
+            (self.n.len() * 8) - (self.n[0].leading_zeros() as usize);
+        super::security_bits(modulus_bits)
+    }
+
+    /// Signs `msg` like `RSASigningState::sign`, but without needing an
+    /// `RSASigningState`.
+    ///
+    /// The key pair keeps a pool of the blinding values that make the first
+    /// signature with an `RSASigningState` expensive, and each call uses one
+    /// that isn't in use by any other call. Because of that, a single
+    /// `RSAKeyPair`, e.g. in an `Arc`, can be used to sign concurrently from
+    /// any number of threads without a `Mutex` around it, and only the first
+    /// signatures pay for creating the blindings. The pool grows to the
+    /// number of concurrent signing operations, up to a limit.
+    pub fn sign(&self, padding_alg: &'static padding::Encoding,
+                rng: &rand::SecureRandom, msg: &[u8], signature: &mut [u8])
+                -> Result<(), error::Unspecified> {
+        let m_hash = digest::digest(padding_alg.digest_alg(), msg);
+        self.sign_prehashed(padding_alg, rng, &m_hash, signature)
+    }
+
+    /// Like `sign`, but signs the message with digest `m_hash`, like
+    /// `RSASigningState::sign_prehashed`.
+    pub fn sign_prehashed(&self, padding_alg: &'static padding::Encoding,
+                          rng: &rand::SecureRandom, m_hash: &digest::Digest,
+                          signature: &mut [u8])
+                          -> Result<(), error::Unspecified> {
+        let mut blinding = try!(self.take_blinding());
+        try!(sign_prehashed(self, &mut blinding, padding_alg, rng, m_hash,
+                            signature));
+        self.return_blinding(blinding);
+        Ok(())
+    }
+
+    fn take_blinding(&self) -> Result<Blinding, error::Unspecified> {
+        if let Some(blinding) = self.lock_blindings().pop() {
+            return Ok(blinding);
+        }
+        Blinding::new()
+    }
+
+    // A blinding is only returned to the pool after a successful operation,
+    // since it may be left in an inconsistent state by a failure.
+    fn return_blinding(&self, blinding: Blinding) {
+        let mut blindings = self.lock_blindings();
+        if blindings.len() < MAX_POOLED_BLINDINGS {
+            blindings.push(blinding);
+        }
+    }
+
+    fn lock_blindings<'a>(&'a self)
+            -> std::sync::MutexGuard<'a, std::vec::Vec<Blinding>> {
+        // The pool is never left in an inconsistent state, so it can still be
+        // used after a panic in another thread.
+        match self.blindings.lock() {
+            Ok(blindings) => blindings,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+// The maximum number of unused blindings that an `RSAKeyPair` keeps.
+const MAX_POOLED_BLINDINGS: usize = 64;
+
+impl signature::KeyPair for RSAKeyPair {
+    type PublicKey = [u8];
+
+    fn public_key(&self) -> &[u8] { &self.public_key }
+}
+
+impl private::Private for RSAKeyPair {}
+
+fn public_key_der(n: untrusted::Input, e: untrusted::Input)
+                  -> std::vec::Vec<u8> {
+    let mut integers = std::vec::Vec::new();
+    der::write_positive_integer(n.as_slice_less_safe(), &mut integers);
+    der::write_positive_integer(e.as_slice_less_safe(), &mut integers);
+    let mut public_key = std::vec::Vec::new();
+    der::write_tlv(der::Tag::Sequence, &integers, &mut public_key);
+    public_key
 }
 
+// `GFp_BN_free` zeroizes the values before freeing them, so this zeroizes
+// the private key.
 impl Drop for RSAKeyPair {
     fn drop(&mut self) {
         unsafe {
@@ -126,6 +310,22 @@ impl Drop for RSAKeyPair {
 unsafe impl Send for RSAKeyPair {}
 unsafe impl Sync for RSAKeyPair {}
 
+// The limits on the size of the public modulus that `rsa_check_key` in
+// crypto/rsa/rsa.c enforces.
+const MIN_MODULUS_BITS: usize = 2048;
+const MAX_MODULUS_BITS: usize = 4096;
+
+fn positive_integer<'a>(input: &mut untrusted::Reader<'a>)
+                        -> Result<untrusted::Input<'a>, error::KeyRejected> {
+    der::positive_integer(input).map_err(error::KeyRejected::invalid_encoding)
+}
+
+fn component(value: untrusted::Input)
+             -> Result<PositiveInteger, error::KeyRejected> {
+    PositiveInteger::from_be_bytes(value)
+        .map_err(error::KeyRejected::invalid_encoding)
+}
+
 /// Needs to be kept in sync with `struct rsa_st` (in `include/openssl/rsa.h`).
 #[repr(C)]
 struct RSA {
@@ -168,6 +368,8 @@ struct RSA {
 /// computational efficiency by increasing the frequency of the expensive
 /// modular inversions; managing a pool of `RSASigningState`s in a
 /// most-recently-used fashion would improve the computational efficiency.
+/// `RSAKeyPair::sign` manages such a pool internally, so it is usually the
+/// better choice when signing from multiple threads.
 pub struct RSASigningState {
     key_pair: std::sync::Arc<RSAKeyPair>,
     blinding: Blinding,
@@ -177,13 +379,9 @@ impl RSASigningState {
     /// Construct an `RSASigningState` for the given `RSAKeyPair`.
     pub fn new(key_pair: std::sync::Arc<RSAKeyPair>)
                -> Result<Self, error::Unspecified> {
-        let blinding = unsafe { GFp_BN_BLINDING_new() };
-        if blinding.is_null() {
-            return Err(error::Unspecified);
-        }
         Ok(RSASigningState {
             key_pair: key_pair,
-            blinding: Blinding { blinding: blinding },
+            blinding: try!(Blinding::new()),
         })
     }
 
@@ -201,7 +399,9 @@ impl RSASigningState {
     /// Many other crypto libraries have signing functions that takes a
     /// precomputed digest as input, instead of the message to digest. This
     /// function does *not* take a precomputed digest; instead, `sign`
-    /// calculates the digest itself.
+    /// calculates the digest itself. Use `sign_prehashed` to sign a message
+    /// that isn't available as one slice, e.g. because it is too large to
+    /// keep in memory.
     ///
     /// Lots of effort has been made to make the signing operations close to
     /// constant time to protect the private key from side channel attacks. On
@@ -212,24 +412,83 @@ impl RSASigningState {
     pub fn sign(&mut self, padding_alg: &'static padding::Encoding,
                 rng: &rand::SecureRandom, msg: &[u8], signature: &mut [u8])
                 -> Result<(), error::Unspecified> {
-        if signature.len() != self.key_pair.public_modulus_len() {
+        let m_hash = digest::digest(padding_alg.digest_alg(), msg);
+        self.sign_prehashed(padding_alg, rng, &m_hash, signature)
+    }
+
+    /// Like `sign`, but signs the message with digest `m_hash` instead of
+    /// digesting a message. `m_hash` must have been computed with the digest
+    /// algorithm from `padding_alg`, e.g. by passing the message to a
+    /// `digest::Context` in pieces, so the message may be any length.
+    pub fn sign_prehashed(&mut self, padding_alg: &'static padding::Encoding,
+                          rng: &rand::SecureRandom, m_hash: &digest::Digest,
+                          signature: &mut [u8])
+                          -> Result<(), error::Unspecified> {
+        sign_prehashed(&self.key_pair, &mut self.blinding, padding_alg, rng,
+                       m_hash, signature)
+    }
+}
+
+fn sign_prehashed(key_pair: &RSAKeyPair, blinding: &mut Blinding,
+                  padding_alg: &'static padding::Encoding,
+                  rng: &rand::SecureRandom, m_hash: &digest::Digest,
+                  signature: &mut [u8]) -> Result<(), error::Unspecified> {
+    if signature.len() != key_pair.public_modulus_len() {
+        return Err(error::Unspecified);
+    }
+    // Compare the algorithms by address, since different digest
+    // algorithms may have the same output length.
+    let m_hash_alg: *const digest::Algorithm = m_hash.algorithm();
+    let expected_alg: *const digest::Algorithm = padding_alg.digest_alg();
+    if m_hash_alg != expected_alg {
+        return Err(error::Unspecified);
+    }
+
+    try!(padding_alg.encode(m_hash, signature));
+    try!(private_transform(key_pair, blinding, signature, rng));
+    audit::notify(audit::EventKind::Signed, padding_alg.name());
+    audit::notify_if_deprecated(padding_alg.name());
+    Ok(())
+}
+
+impl super::PrivateTransform for RSAKeyPair {
+    // This uses the same pool of blindings as `RSAKeyPair::sign`.
+    fn private_transform(&self, inout: &mut [u8], rng: &rand::SecureRandom)
+                         -> Result<(), error::Unspecified> {
+        if inout.len() != self.public_modulus_len() {
             return Err(error::Unspecified);
         }
-
-        try!(padding_alg.encode(msg, signature));
-        let mut rand = rand::RAND::new(rng);
-        bssl::map_result(unsafe {
-            GFp_rsa_private_transform(&self.key_pair.rsa,
-                                      signature.as_mut_ptr(), signature.len(),
-                                      self.blinding.blinding, &mut rand)
-        })
+        let mut blinding = try!(self.take_blinding());
+        try!(private_transform(self, &mut blinding, inout, rng));
+        self.return_blinding(blinding);
+        Ok(())
     }
 }
 
+fn private_transform(key_pair: &RSAKeyPair, blinding: &mut Blinding,
+                     inout: &mut [u8], rng: &rand::SecureRandom)
+                     -> Result<(), error::Unspecified> {
+    let mut rand = rand::RAND::new(rng);
+    bssl::map_result(unsafe {
+        GFp_rsa_private_transform(&key_pair.rsa, inout.as_mut_ptr(),
+                                  inout.len(), blinding.blinding, &mut rand)
+    })
+}
+
 struct Blinding {
     blinding: *mut BN_BLINDING,
 }
 
+impl Blinding {
+    fn new() -> Result<Blinding, error::Unspecified> {
+        let blinding = unsafe { GFp_BN_BLINDING_new() };
+        if blinding.is_null() {
+            return Err(error::Unspecified);
+        }
+        Ok(Blinding { blinding: blinding })
+    }
+}
+
 impl Drop for Blinding {
     fn drop(&mut self) { unsafe { GFp_BN_BLINDING_free(self.blinding) } }
 }
@@ -264,12 +523,13 @@ extern {
 
 #[cfg(test)]
 mod tests {
-    use rand;
-    use std;
-    use test;
+    use {der, digest, error, rand, std, test};
 
     use super::*;
-    use super::super::{RSA_PKCS1_SHA256, RSA_PKCS1_SHA384, RSA_PKCS1_SHA512};
+    use super::super::padding::Padding;
+    use super::super::{RSA_PKCS1_SHA256, RSA_PKCS1_SHA384, RSA_PKCS1_SHA512,
+                       RSA_PKCS1_SHA512_256};
+    use super::super::padding::RSA_PKCS1_SHA1;
     use untrusted;
 
     extern {
@@ -282,14 +542,16 @@ mod tests {
         test::from_file("src/rsa/rsa_pkcs1_sign_tests.txt",
                         |section, test_case| {
             let digest_name = test_case.consume_string("Digest");
-            // Note that SHA-1 isn't recognized here because we don't expose
-            // PKCS#1 SHA-1 signing, because we don't have test vectors for it.
-            let alg = if digest_name == "SHA256" {
+            let alg = if digest_name == "SHA1" {
+                &RSA_PKCS1_SHA1
+            } else if digest_name == "SHA256" {
                 &RSA_PKCS1_SHA256
             } else if digest_name == "SHA384" {
                 &RSA_PKCS1_SHA384
             } else if digest_name == "SHA512" {
                 &RSA_PKCS1_SHA512
+            } else if digest_name == "SHA512_256" {
+                &RSA_PKCS1_SHA512_256
             } else {
                 panic!("Unsupported digest: {}", digest_name);
             };
@@ -318,6 +580,17 @@ mod tests {
                 vec![0; signing_state.key_pair().public_modulus_len()];
             signing_state.sign(alg, &rng, &msg, actual.as_mut_slice()).unwrap();
             assert_eq!(actual.as_slice() == &expected[..], result == "Pass");
+
+            // Signing the digest of the message, computed incrementally, gives
+            // the same signature.
+            let mut ctx = digest::Context::new(alg.digest_alg());
+            for chunk in msg.chunks(7) {
+                ctx.update(chunk);
+            }
+            let mut prehashed = vec![0; actual.len()];
+            signing_state.sign_prehashed(alg, &rng, &ctx.finish(),
+                                         &mut prehashed).unwrap();
+            assert_eq!(prehashed, actual);
             Ok(())
         });
     }
@@ -357,6 +630,35 @@ mod tests {
                                    &mut signature).is_err());
     }
 
+    // `sign_prehashed` must reject digests that weren't computed with the
+    // padding algorithm's digest algorithm, even if they have the right
+    // length.
+    #[test]
+    fn test_signature_rsa_pkcs1_sign_prehashed_wrong_digest() {
+        const MESSAGE: &'static [u8] = b"hello, world";
+        let rng = rand::SystemRandom::new();
+
+        const PRIVATE_KEY_DER: &'static [u8] =
+            include_bytes!("signature_rsa_example_private_key.der");
+        let key_bytes_der = untrusted::Input::from(PRIVATE_KEY_DER);
+        let key_pair = RSAKeyPair::from_der(key_bytes_der).unwrap();
+        let key_pair = std::sync::Arc::new(key_pair);
+        let mut signature = vec![0; key_pair.public_modulus_len()];
+        let mut signing_state = RSASigningState::new(key_pair).unwrap();
+
+        for digest_alg in &[&digest::SHA384, &digest::SHA512_256,
+                            &digest::SHA3_256] {
+            let m_hash = digest::digest(digest_alg, MESSAGE);
+            assert_eq!(signing_state.sign_prehashed(&RSA_PKCS1_SHA256, &rng,
+                                                    &m_hash, &mut signature),
+                       Err(error::Unspecified));
+        }
+
+        let m_hash = digest::digest(&digest::SHA256, MESSAGE);
+        assert!(signing_state.sign_prehashed(&RSA_PKCS1_SHA256, &rng, &m_hash,
+                                             &mut signature).is_ok());
+    }
+
     // Once the `BN_BLINDING` in an `RSAKeyPair` has been used
     // `GFp_BN_BLINDING_COUNTER` times, a new blinding should be created. we
     // don't check that a new blinding was created; we just make sure to
@@ -415,6 +717,244 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_rsa_key_pair_from_components() {
+        const MESSAGE: &'static [u8] = b"hello, world";
+        let rng = rand::SystemRandom::new();
+
+        const PRIVATE_KEY_DER: &'static [u8] =
+            include_bytes!("signature_rsa_example_private_key.der");
+        let key_bytes_der = untrusted::Input::from(PRIVATE_KEY_DER);
+        let components = key_bytes_der.read_all(error::Unspecified, |input| {
+            der::nested(input, der::Tag::Sequence, error::Unspecified,
+                        |input| {
+                let _ = try!(der::small_nonnegative_integer(input));
+                let mut components = std::vec::Vec::new();
+                for _ in 0..8 {
+                    components.push(try!(der::positive_integer(input)));
+                }
+                Ok(components)
+            })
+        }).unwrap();
+        let from_components = |c: &[untrusted::Input]| {
+            RSAKeyPair::from_components(c[0], c[1], c[2], c[3], c[4], c[5],
+                                        c[6], c[7])
+        };
+
+        // The key pair is the same as the one parsed by `from_der`, so the
+        // deterministic PKCS#1 1.5 signatures are the same.
+        let sign = |key_pair: RSAKeyPair| {
+            let mut signing_state =
+                RSASigningState::new(std::sync::Arc::new(key_pair)).unwrap();
+            let mut signature =
+                vec![0; signing_state.key_pair().public_modulus_len()];
+            signing_state.sign(&RSA_PKCS1_SHA256, &rng, MESSAGE,
+                               &mut signature).unwrap();
+            signature
+        };
+        assert_eq!(sign(from_components(&components).unwrap()),
+                   sign(RSAKeyPair::from_der(key_bytes_der).unwrap()));
+
+        // Inconsistent components are rejected: swapping p and q (p < q
+        // isn't supported), and swapping n and d.
+        let mut swapped = components.clone();
+        swapped.swap(3, 4);
+        assert_eq!(from_components(&swapped).err(),
+                   Some(error::KeyRejected::InconsistentComponents));
+        let mut swapped = components.clone();
+        swapped.swap(0, 2);
+        assert!(from_components(&swapped).is_err());
+
+        // Leading zeros are rejected.
+        let mut n_with_leading_zero = vec![0u8];
+        n_with_leading_zero.extend_from_slice(
+            components[0].as_slice_less_safe());
+        let mut leading_zero = components.clone();
+        leading_zero[0] = untrusted::Input::from(&n_with_leading_zero);
+        assert_eq!(from_components(&leading_zero).err(),
+                   Some(error::KeyRejected::InvalidEncoding));
+
+        // Moduli smaller than 2048 bits or larger than 4096 bits are
+        // rejected before the other components are checked.
+        let small_n = [0xffu8; 2048 / 8 - 1];
+        let mut small = components.clone();
+        small[0] = untrusted::Input::from(&small_n);
+        assert_eq!(from_components(&small).err(),
+                   Some(error::KeyRejected::UnsupportedSize));
+        let large_n = [0x01u8; 4096 / 8 + 1];
+        let mut large = components.clone();
+        large[0] = untrusted::Input::from(&large_n);
+        assert_eq!(from_components(&large).err(),
+                   Some(error::KeyRejected::UnsupportedSize));
+    }
+
+    #[test]
+    fn test_rsa_key_pair_from_der_rejected() {
+        const PRIVATE_KEY_DER: &'static [u8] =
+            include_bytes!("signature_rsa_example_private_key.der");
+        let from_der = |der: &[u8]| {
+            RSAKeyPair::from_der(untrusted::Input::from(der)).err()
+        };
+        assert_eq!(from_der(PRIVATE_KEY_DER), None);
+
+        // The version is at offset 6. Version 1 is for multi-prime keys.
+        let mut multi_prime = PRIVATE_KEY_DER.to_vec();
+        multi_prime[6] = 1;
+        assert_eq!(from_der(&multi_prime),
+                   Some(error::KeyRejected::VersionNotSupported));
+
+        let truncated_len = PRIVATE_KEY_DER.len() - 1;
+        assert_eq!(from_der(&PRIVATE_KEY_DER[..truncated_len]),
+                   Some(error::KeyRejected::InvalidEncoding));
+        assert_eq!(from_der(&[]), Some(error::KeyRejected::InvalidEncoding));
+
+        // The last byte of qInv.
+        let mut inconsistent = PRIVATE_KEY_DER.to_vec();
+        let last = inconsistent.len() - 1;
+        inconsistent[last] ^= 2;
+        assert_eq!(from_der(&inconsistent),
+                   Some(error::KeyRejected::InconsistentComponents));
+    }
+
+    #[test]
+    fn test_rsa_key_pair_from_pkcs8() {
+        const PRIVATE_KEY_DER: &'static [u8] =
+            include_bytes!("signature_rsa_example_private_key.der");
+        const PRIVATE_KEY_PKCS8_DER: &'static [u8] =
+            include_bytes!("signature_rsa_example_private_key_pkcs8.der");
+        let from_pkcs8 = |der: &[u8]| {
+            RSAKeyPair::from_pkcs8(untrusted::Input::from(der))
+        };
+
+        let key_pair = from_pkcs8(PRIVATE_KEY_PKCS8_DER).unwrap();
+        assert_eq!(key_pair.public_key_der(),
+                   RSAKeyPair::from_der(untrusted::Input::from(
+                       PRIVATE_KEY_DER)).unwrap().public_key_der());
+
+        // The formats aren't interchangeable.
+        assert_eq!(from_pkcs8(PRIVATE_KEY_DER).err(),
+                   Some(error::KeyRejected::InvalidEncoding));
+        assert_eq!(RSAKeyPair::from_der(
+                       untrusted::Input::from(PRIVATE_KEY_PKCS8_DER)).err(),
+                   Some(error::KeyRejected::InvalidEncoding));
+
+        // The algorithm identifier must be rsaEncryption with NULL
+        // parameters. The OID is at offset 9 and the NULL at offset 20.
+        let mut wrong_oid = PRIVATE_KEY_PKCS8_DER.to_vec();
+        wrong_oid[9 + 10] ^= 1;
+        assert_eq!(from_pkcs8(&wrong_oid).err(),
+                   Some(error::KeyRejected::UnsupportedAlgorithm));
+        let mut wrong_params = PRIVATE_KEY_PKCS8_DER.to_vec();
+        wrong_params[20] = der::Tag::OctetString as u8;
+        assert_eq!(from_pkcs8(&wrong_params).err(),
+                   Some(error::KeyRejected::UnsupportedAlgorithm));
+
+        // Only versions 0 and 1 are supported.
+        let mut wrong_version = PRIVATE_KEY_PKCS8_DER.to_vec();
+        wrong_version[6] = 2;
+        assert_eq!(from_pkcs8(&wrong_version).err(),
+                   Some(error::KeyRejected::VersionNotSupported));
+
+        // Attributes are allowed and ignored, but a public key is only
+        // allowed in version 1, and other trailing data isn't allowed.
+        let with_trailer = |version: u8, trailer: &[u8]| {
+            let mut contents = PRIVATE_KEY_PKCS8_DER[4..].to_vec();
+            contents[2] = version;
+            contents.extend_from_slice(trailer);
+            let mut der = std::vec::Vec::new();
+            der::write_tlv(der::Tag::Sequence, &contents, &mut der);
+            der
+        };
+        assert!(from_pkcs8(&with_trailer(0, &[])).is_ok());
+        assert!(from_pkcs8(&with_trailer(1, &[])).is_ok());
+        assert!(from_pkcs8(&with_trailer(0, &[0xa0, 0x00])).is_ok());
+        assert!(from_pkcs8(&with_trailer(1, &[0x81, 0x01, 0x00])).is_ok());
+        assert!(from_pkcs8(&with_trailer(1, &[0xa0, 0x00, 0x81, 0x01, 0x00]))
+                    .is_ok());
+        assert!(from_pkcs8(&with_trailer(0, &[0x81, 0x01, 0x00])).is_err());
+        assert!(from_pkcs8(&with_trailer(1, &[0x81, 0x01, 0x00, 0xa0, 0x00]))
+                    .is_err());
+        assert!(from_pkcs8(&with_trailer(0, &[0x05, 0x00])).is_err());
+
+        // Truncated input is rejected.
+        let truncated_len = PRIVATE_KEY_PKCS8_DER.len() - 1;
+        assert_eq!(from_pkcs8(&PRIVATE_KEY_PKCS8_DER[..truncated_len]).err(),
+                   Some(error::KeyRejected::InvalidEncoding));
+    }
+
+    #[test]
+    fn test_rsa_key_pair_public_key() {
+        const PRIVATE_KEY_DER: &'static [u8] =
+            include_bytes!("signature_rsa_example_private_key.der");
+        const PUBLIC_KEY_DER: &'static [u8] =
+            include_bytes!("signature_rsa_example_public_key.der");
+        let key_pair =
+            RSAKeyPair::from_der(untrusted::Input::from(PRIVATE_KEY_DER))
+                .unwrap();
+        assert_eq!(&key_pair.public_key_der()[..], PUBLIC_KEY_DER);
+        assert_eq!(signature::KeyPair::public_key(&key_pair), PUBLIC_KEY_DER);
+
+        let (n, e) = super::super::parse_public_key(
+            untrusted::Input::from(PUBLIC_KEY_DER)).unwrap();
+        assert_eq!(key_pair.public_modulus_be_bytes(),
+                   n.as_slice_less_safe());
+        assert_eq!(key_pair.public_exponent_be_bytes(),
+                   e.as_slice_less_safe());
+        assert_eq!(key_pair.public_modulus_be_bytes().len(),
+                   key_pair.public_modulus_len());
+    }
+
+    #[test]
+    fn test_rsa_key_pair_sign() {
+        const MESSAGE: &'static [u8] = b"hello, world";
+        const PRIVATE_KEY_DER: &'static [u8] =
+            include_bytes!("signature_rsa_example_private_key.der");
+        let key_pair = std::sync::Arc::new(
+            RSAKeyPair::from_der(untrusted::Input::from(PRIVATE_KEY_DER))
+                .unwrap());
+
+        // PKCS#1 v1.5 signatures are deterministic, so all the ways of
+        // signing must produce the same signature.
+        let rng = rand::SystemRandom::new();
+        let mut expected = vec![0; key_pair.public_modulus_len()];
+        RSASigningState::new(key_pair.clone()).unwrap()
+            .sign(&RSA_PKCS1_SHA256, &rng, MESSAGE, &mut expected).unwrap();
+
+        // A failed signature doesn't return its blinding to the pool.
+        let mut signature = vec![0; key_pair.public_modulus_len()];
+        let zero_rng = rand::test_util::FixedByteRandom { byte: 0x00 };
+        assert!(key_pair.sign(&RSA_PKCS1_SHA256, &zero_rng, MESSAGE,
+                              &mut signature).is_err());
+        assert_eq!(key_pair.lock_blindings().len(), 0);
+
+        // Sequential signatures reuse the same blinding.
+        for _ in 0..3 {
+            key_pair.sign(&RSA_PKCS1_SHA256, &rng, MESSAGE, &mut signature)
+                .unwrap();
+            assert_eq!(signature, expected);
+            assert_eq!(key_pair.lock_blindings().len(), 1);
+        }
+
+        let threads = (0..4).map(|_| {
+            let key_pair = key_pair.clone();
+            let expected = expected.clone();
+            std::thread::spawn(move || {
+                let rng = rand::SystemRandom::new();
+                let mut signature = vec![0; key_pair.public_modulus_len()];
+                for _ in 0..5 {
+                    key_pair.sign(&RSA_PKCS1_SHA256, &rng, MESSAGE,
+                                  &mut signature).unwrap();
+                    assert_eq!(signature, expected);
+                }
+            })
+        }).collect::<std::vec::Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let pooled = key_pair.lock_blindings().len();
+        assert!(pooled >= 1 && pooled <= 4);
+    }
+
     #[test]
     fn test_sync_and_send() {
         const PRIVATE_KEY_DER: &'static [u8] =
@@ -12,10 +12,10 @@
 // OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
 // CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 
-/// RSA PKCS#1 1.5 signatures.
+/// RSA PKCS#1 1.5 and RSASSA-PSS signatures.
 
-use {bssl, c, error, private, signature};
-use super::{BIGNUM, PositiveInteger, RSAParameters, parse_public_key};
+use {audit, core, digest, error, private, signature};
+use super::{bigint, MAX_BITS, RSAParameters, parse_public_key};
 use untrusted;
 
 
@@ -26,6 +26,30 @@ impl signature::VerificationAlgorithm for RSAParameters {
         let public_key = try!(parse_public_key(public_key));
         verify_rsa(self, public_key, msg, signature)
     }
+
+    fn name(&self) -> &'static str { self.name }
+
+    fn security_bits(&self) -> usize {
+        core::cmp::min(super::security_bits(self.min_bits),
+                       self.padding_alg.digest_alg().security_bits())
+    }
+}
+
+impl signature::PrehashedVerificationAlgorithm for RSAParameters {
+    fn digest_algorithm(&self) -> &'static digest::Algorithm {
+        self.padding_alg.digest_alg()
+    }
+
+    fn verify_prehashed(&self, public_key: untrusted::Input,
+                        digest: untrusted::Input, signature: untrusted::Input)
+                        -> Result<(), error::Unspecified> {
+        if digest.len() != self.padding_alg.digest_alg().output_len {
+            return Err(error::Unspecified);
+        }
+        let public_key = try!(parse_public_key(public_key));
+        verify_rsa_digest(self, public_key, digest.as_slice_less_safe(),
+                          signature)
+    }
 }
 
 impl private::Private for RSAParameters {}
@@ -34,10 +58,9 @@ macro_rules! rsa_pkcs1 {
     ( $VERIFY_ALGORITHM:ident, $min_bits:expr, $PADDING_ALGORITHM:expr,
       $doc_str:expr ) => {
         #[doc=$doc_str]
-        ///
-        /// Only available in `use_heap` mode.
         pub static $VERIFY_ALGORITHM: RSAParameters =
             RSAParameters {
+                name: stringify!($VERIFY_ALGORITHM),
                 padding_alg: $PADDING_ALGORITHM,
                 min_bits: $min_bits,
             };
@@ -56,10 +79,86 @@ rsa_pkcs1!(RSA_PKCS1_2048_8192_SHA384, 2048, &super::RSA_PKCS1_SHA384,
 rsa_pkcs1!(RSA_PKCS1_2048_8192_SHA512, 2048, &super::RSA_PKCS1_SHA512,
            "Verification of signatures using RSA keys of 2048-8192 bits,
             PKCS#1.5 padding, and SHA-512.");
+rsa_pkcs1!(RSA_PKCS1_2048_8192_SHA512_256, 2048, &super::RSA_PKCS1_SHA512_256,
+           "Verification of signatures using RSA keys of 2048-8192 bits,
+            PKCS#1.5 padding, and SHA-512/256.");
 rsa_pkcs1!(RSA_PKCS1_3072_8192_SHA384, 3072, &super::RSA_PKCS1_SHA384,
            "Verification of signatures using RSA keys of 3072-8192 bits,
             PKCS#1.5 padding, and SHA-384.");
 
+rsa_pkcs1!(RSA_PKCS1_1024_8192_SHA1_FOR_LEGACY_USE_ONLY, 1024,
+           &super::padding::RSA_PKCS1_SHA1,
+           "Verification of signatures using RSA keys of 1024-8192 bits,
+            PKCS#1.5 padding, and SHA-1, for verifying signatures from
+            existing keys that are too small to use with the other algorithms,
+            e.g. old DKIM keys. 1024-bit RSA keys are far weaker than 2048-bit
+            ones; use `RSA_PKCS1_2048_8192_SHA1` unless 1024-bit keys must be
+            accepted.");
+rsa_pkcs1!(RSA_PKCS1_1024_8192_SHA256_FOR_LEGACY_USE_ONLY, 1024,
+           &super::RSA_PKCS1_SHA256,
+           "Verification of signatures using RSA keys of 1024-8192 bits,
+            PKCS#1.5 padding, and SHA-256. See
+            `RSA_PKCS1_1024_8192_SHA1_FOR_LEGACY_USE_ONLY`.");
+rsa_pkcs1!(RSA_PKCS1_1024_8192_SHA512_FOR_LEGACY_USE_ONLY, 1024,
+           &super::RSA_PKCS1_SHA512,
+           "Verification of signatures using RSA keys of 1024-8192 bits,
+            PKCS#1.5 padding, and SHA-512. See
+            `RSA_PKCS1_1024_8192_SHA1_FOR_LEGACY_USE_ONLY`.");
+
+macro_rules! rsa_pss {
+    ( $VERIFY_ALGORITHM:ident, $PADDING_ALGORITHM:ident, $doc_str:expr ) => {
+        #[doc=$doc_str]
+        pub static $VERIFY_ALGORITHM: RSAParameters =
+            RSAParameters {
+                name: stringify!($VERIFY_ALGORITHM),
+                padding_alg: &super::padding::$PADDING_ALGORITHM,
+                min_bits: 2048,
+            };
+    }
+}
+
+rsa_pss!(RSA_PSS_2048_8192_SHA256, RSA_PSS_SHA256,
+         "Verification of signatures using RSA keys of 2048-8192 bits,
+          PSS padding with a 32-byte salt, and SHA-256.");
+rsa_pss!(RSA_PSS_2048_8192_SHA384, RSA_PSS_SHA384,
+         "Verification of signatures using RSA keys of 2048-8192 bits,
+          PSS padding with a 48-byte salt, and SHA-384.");
+rsa_pss!(RSA_PSS_2048_8192_SHA512, RSA_PSS_SHA512,
+         "Verification of signatures using RSA keys of 2048-8192 bits,
+          PSS padding with a 64-byte salt, and SHA-512.");
+rsa_pss!(RSA_PSS_2048_8192_SHA256_SALT_LEN_0, RSA_PSS_SHA256_SALT_LEN_0,
+         "Verification of signatures using RSA keys of 2048-8192 bits,
+          PSS padding with an empty salt, and SHA-256.");
+rsa_pss!(RSA_PSS_2048_8192_SHA384_SALT_LEN_0, RSA_PSS_SHA384_SALT_LEN_0,
+         "Verification of signatures using RSA keys of 2048-8192 bits,
+          PSS padding with an empty salt, and SHA-384.");
+rsa_pss!(RSA_PSS_2048_8192_SHA512_SALT_LEN_0, RSA_PSS_SHA512_SALT_LEN_0,
+         "Verification of signatures using RSA keys of 2048-8192 bits,
+          PSS padding with an empty salt, and SHA-512.");
+rsa_pss!(RSA_PSS_2048_8192_SHA256_SALT_LEN_20, RSA_PSS_SHA256_SALT_LEN_20,
+         "Verification of signatures using RSA keys of 2048-8192 bits,
+          PSS padding with a 20-byte salt, and SHA-256.");
+rsa_pss!(RSA_PSS_2048_8192_SHA384_SALT_LEN_20, RSA_PSS_SHA384_SALT_LEN_20,
+         "Verification of signatures using RSA keys of 2048-8192 bits,
+          PSS padding with a 20-byte salt, and SHA-384.");
+rsa_pss!(RSA_PSS_2048_8192_SHA512_SALT_LEN_20, RSA_PSS_SHA512_SALT_LEN_20,
+         "Verification of signatures using RSA keys of 2048-8192 bits,
+          PSS padding with a 20-byte salt, and SHA-512.");
+rsa_pss!(RSA_PSS_2048_8192_SHA256_ANY_SALT_LEN, RSA_PSS_SHA256_ANY_SALT_LEN,
+         "Verification of signatures using RSA keys of 2048-8192 bits,
+          PSS padding with a salt of any length, and SHA-256. The salt length
+          is recovered from the encoded message as described in RFC 8017
+          section 9.1.2; prefer the variants with a fixed salt length when the
+          signer's salt length is known.");
+rsa_pss!(RSA_PSS_2048_8192_SHA384_ANY_SALT_LEN, RSA_PSS_SHA384_ANY_SALT_LEN,
+         "Verification of signatures using RSA keys of 2048-8192 bits,
+          PSS padding with a salt of any length, and SHA-384. See
+          `RSA_PSS_2048_8192_SHA256_ANY_SALT_LEN`.");
+rsa_pss!(RSA_PSS_2048_8192_SHA512_ANY_SALT_LEN, RSA_PSS_SHA512_ANY_SALT_LEN,
+         "Verification of signatures using RSA keys of 2048-8192 bits,
+          PSS padding with a salt of any length, and SHA-512. See
+          `RSA_PSS_2048_8192_SHA256_ANY_SALT_LEN`.");
+
 /// Lower-level API for the verification of RSA signatures.
 ///
 /// When the public key is in DER-encoded PKCS#1 ASN.1 format, it is
@@ -76,6 +175,9 @@ rsa_pkcs1!(RSA_PKCS1_3072_8192_SHA384, 3072, &super::RSA_PKCS1_SHA384,
 /// `n` is the public key modulus and `e` is the public key exponent. Both are
 /// interpreted as unsigned big-endian encoded values. Both must be positive
 /// and neither may have any leading zeros.
+///
+/// `signature` is checked as strictly as described in the documentation for
+/// `RSAParameters`.
 //
 // There are a small number of tests that test `verify_rsa` directly, but the
 // test coverage for this function mostly depends on the test coverage for the
@@ -89,7 +191,72 @@ pub fn verify_rsa(params: &RSAParameters,
                   (n, e): (untrusted::Input, untrusted::Input),
                   msg: untrusted::Input, signature: untrusted::Input)
                   -> Result<(), error::Unspecified> {
-    const MAX_BITS: usize = 8192;
+    let m_hash = digest::digest(params.padding_alg.digest_alg(),
+                                msg.as_slice_less_safe());
+    verify_rsa_digest(params, (n, e), m_hash.as_ref(), signature)
+}
+
+// Like `verify_rsa`, but `m_hash` is the digest of the message, computed with
+// `params.padding_alg.digest_alg()`.
+fn verify_rsa_digest(params: &RSAParameters,
+                     (n, e): (untrusted::Input, untrusted::Input),
+                     m_hash: &[u8], signature: untrusted::Input)
+                     -> Result<(), error::Unspecified> {
+    let public_key = try!(PublicKey::from_n_e(n, e, params.min_bits));
+    verify_rsa_digest_with_public_key(params, &public_key, m_hash, signature)
+}
+
+/// An RSA public key whose modulus and exponent have been parsed, so that it
+/// can be used for many verifications, e.g. by `ring::public_key_cache`.
+///
+/// A `PublicKey` is a few kilobytes large, since it holds its values in
+/// fixed-size arrays instead of on the heap.
+pub struct PublicKey {
+    n: bigint::Modulus,
+    e: bigint::PublicExponent,
+}
+
+impl PublicKey {
+    /// Parses a DER-encoded `RSAPublicKey`, e.g. the public key bits of an
+    /// RSA `SubjectPublicKeyInfo`.
+    pub fn from_der(input: untrusted::Input)
+                    -> Result<PublicKey, error::Unspecified> {
+        let (n, e) = try!(parse_public_key(input));
+        PublicKey::from_n_e(n, e, 0)
+    }
+
+    // Each verification checks that the modulus is at least as long as its
+    // algorithm requires, so `min_bits` only lets that fail early.
+    fn from_n_e(n: untrusted::Input, e: untrusted::Input, min_bits: usize)
+                -> Result<PublicKey, error::Unspecified> {
+        Ok(PublicKey {
+            n: try!(bigint::Modulus::from_be_bytes(n, min_bits, MAX_BITS)),
+            e: try!(bigint::PublicExponent::from_be_bytes(e)),
+        })
+    }
+}
+
+/// Verifies the signature `signature` of `msg` with the parsed public key
+/// `public_key` using the algorithm `params`, like `signature::verify`.
+pub fn verify_rsa_with_public_key(params: &RSAParameters,
+                                  public_key: &PublicKey,
+                                  msg: untrusted::Input,
+                                  signature: untrusted::Input)
+                                  -> Result<(), error::Unspecified> {
+    let m_hash = digest::digest(params.padding_alg.digest_alg(),
+                                msg.as_slice_less_safe());
+    verify_rsa_digest_with_public_key(params, public_key, m_hash.as_ref(),
+                                      signature)
+}
+
+fn verify_rsa_digest_with_public_key(params: &RSAParameters,
+                                     public_key: &PublicKey, m_hash: &[u8],
+                                     signature: untrusted::Input)
+                                     -> Result<(), error::Unspecified> {
+    audit::notify_if_deprecated(params.name);
+    if public_key.n.bits() < params.min_bits {
+        return Err(error::Unspecified);
+    }
 
     let signature = signature.as_slice_less_safe();
     let mut decoded = [0u8; (MAX_BITS + 7) / 8];
@@ -97,25 +264,12 @@ pub fn verify_rsa(params: &RSAParameters,
         return Err(error::Unspecified);
     }
 
-    let n = try!(PositiveInteger::from_be_bytes(n));
-    let e = try!(PositiveInteger::from_be_bytes(e));
     let decoded = &mut decoded[..signature.len()];
-    try!(bssl::map_result(unsafe {
-        GFp_rsa_public_decrypt(decoded.as_mut_ptr(), decoded.len(), n.as_ref(),
-                               e.as_ref(), signature.as_ptr(), signature.len(),
-                               params.min_bits, MAX_BITS)
-    }));
+    decoded.copy_from_slice(signature);
+    try!(bigint::public_op(&public_key.n, public_key.e, decoded));
 
-    params.padding_alg.verify(msg, untrusted::Input::from(decoded))
-}
-
-extern {
-    fn GFp_rsa_public_decrypt(out: *mut u8, out_len: c::size_t,
-                              public_key_n: *const BIGNUM,
-                              public_key_e: *const BIGNUM,
-                              ciphertext: *const u8,
-                              ciphertext_len: c::size_t, min_bits: c::size_t,
-                              max_bits: c::size_t) -> c::int;
+    params.padding_alg.verify(m_hash, untrusted::Input::from(decoded),
+                              public_key.n.bits())
 }
 
 #[cfg(test)]
@@ -140,6 +294,8 @@ mod tests {
                 &RSA_PKCS1_2048_8192_SHA384
             } else if digest_name == "SHA512" {
                 &RSA_PKCS1_2048_8192_SHA512
+            } else if digest_name == "SHA512_256" {
+                &RSA_PKCS1_2048_8192_SHA512_256
             } else {
                 panic!("Unsupported digest: {}", digest_name);
             };
@@ -170,6 +326,116 @@ mod tests {
             let actual_result = signature::verify(alg, public_key, msg, sig);
             assert_eq!(actual_result.is_ok(), expected_result == "P");
 
+            // Verifying the message in pieces gives the same result.
+            let mut ctx = signature::VerificationContext::new(alg, public_key);
+            for chunk in msg.as_slice_less_safe().chunks(7) {
+                ctx.update(chunk);
+            }
+            assert_eq!(ctx.finish(sig).is_ok(), expected_result == "P");
+
+            // A maximum length that the message doesn't exceed doesn't change
+            // the result, and a message that is too long is rejected.
+            let msg_len = msg.len() as u64;
+            let mut ctx = signature::VerificationContext::with_max_len(
+                alg, public_key, msg_len);
+            ctx.update(msg.as_slice_less_safe());
+            assert_eq!(ctx.finish(sig).is_ok(), expected_result == "P");
+            if msg_len > 0 {
+                let mut ctx = signature::VerificationContext::with_max_len(
+                    alg, public_key, msg_len - 1);
+                ctx.update(msg.as_slice_less_safe());
+                assert!(ctx.finish(sig).is_err());
+            }
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_signature_rsa_pkcs1_legacy_verify() {
+        test::from_file("src/rsa/rsa_pkcs1_legacy_verify_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+
+            let alg_name = test_case.consume_string("Alg");
+            let alg = match alg_name.as_str() {
+                "RSA_PKCS1_1024_8192_SHA1_FOR_LEGACY_USE_ONLY" =>
+                    &RSA_PKCS1_1024_8192_SHA1_FOR_LEGACY_USE_ONLY,
+                "RSA_PKCS1_1024_8192_SHA256_FOR_LEGACY_USE_ONLY" =>
+                    &RSA_PKCS1_1024_8192_SHA256_FOR_LEGACY_USE_ONLY,
+                "RSA_PKCS1_1024_8192_SHA512_FOR_LEGACY_USE_ONLY" =>
+                    &RSA_PKCS1_1024_8192_SHA512_FOR_LEGACY_USE_ONLY,
+                "RSA_PKCS1_2048_8192_SHA256" => &RSA_PKCS1_2048_8192_SHA256,
+                _ => panic!("Unsupported algorithm: {}", alg_name),
+            };
+
+            let public_key = test_case.consume_bytes("Key");
+            let msg = test_case.consume_bytes("Msg");
+            let sig = test_case.consume_bytes("Sig");
+            let expected_result = test_case.consume_string("Result");
+
+            let actual_result =
+                signature::verify(alg, untrusted::Input::from(&public_key),
+                                  untrusted::Input::from(&msg),
+                                  untrusted::Input::from(&sig));
+            assert_eq!(actual_result.is_ok(), expected_result == "P");
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_signature_rsa_pss_verify() {
+        test::from_file("src/rsa/rsa_pss_verify_tests.txt",
+                        |section, test_case| {
+            assert_eq!(section, "");
+
+            let alg_name = test_case.consume_string("Alg");
+            let alg = match alg_name.as_str() {
+                "RSA_PSS_2048_8192_SHA256" => &RSA_PSS_2048_8192_SHA256,
+                "RSA_PSS_2048_8192_SHA384" => &RSA_PSS_2048_8192_SHA384,
+                "RSA_PSS_2048_8192_SHA512" => &RSA_PSS_2048_8192_SHA512,
+                "RSA_PSS_2048_8192_SHA256_SALT_LEN_0" =>
+                    &RSA_PSS_2048_8192_SHA256_SALT_LEN_0,
+                "RSA_PSS_2048_8192_SHA384_SALT_LEN_0" =>
+                    &RSA_PSS_2048_8192_SHA384_SALT_LEN_0,
+                "RSA_PSS_2048_8192_SHA512_SALT_LEN_0" =>
+                    &RSA_PSS_2048_8192_SHA512_SALT_LEN_0,
+                "RSA_PSS_2048_8192_SHA256_SALT_LEN_20" =>
+                    &RSA_PSS_2048_8192_SHA256_SALT_LEN_20,
+                "RSA_PSS_2048_8192_SHA384_SALT_LEN_20" =>
+                    &RSA_PSS_2048_8192_SHA384_SALT_LEN_20,
+                "RSA_PSS_2048_8192_SHA512_SALT_LEN_20" =>
+                    &RSA_PSS_2048_8192_SHA512_SALT_LEN_20,
+                "RSA_PSS_2048_8192_SHA256_ANY_SALT_LEN" =>
+                    &RSA_PSS_2048_8192_SHA256_ANY_SALT_LEN,
+                "RSA_PSS_2048_8192_SHA384_ANY_SALT_LEN" =>
+                    &RSA_PSS_2048_8192_SHA384_ANY_SALT_LEN,
+                "RSA_PSS_2048_8192_SHA512_ANY_SALT_LEN" =>
+                    &RSA_PSS_2048_8192_SHA512_ANY_SALT_LEN,
+                _ => panic!("Unsupported algorithm: {}", alg_name),
+            };
+
+            let public_key = test_case.consume_bytes("Key");
+            let msg = test_case.consume_bytes("Msg");
+            let sig = test_case.consume_bytes("Sig");
+            let expected_result = test_case.consume_string("Result");
+
+            let actual_result =
+                signature::verify(alg, untrusted::Input::from(&public_key),
+                                  untrusted::Input::from(&msg),
+                                  untrusted::Input::from(&sig));
+            assert_eq!(actual_result.is_ok(), expected_result == "P");
+
+            // Verifying the message in pieces gives the same result.
+            let mut ctx = signature::VerificationContext::new(
+                alg, untrusted::Input::from(&public_key));
+            for chunk in msg.chunks(7) {
+                ctx.update(chunk);
+            }
+            assert_eq!(ctx.finish(untrusted::Input::from(&sig)).is_ok(),
+                       expected_result == "P");
+
             Ok(())
         });
     }
@@ -195,4 +461,22 @@ mod tests {
             Ok(())
         })
     }
+
+    #[test]
+    fn test_signature_rsa_algorithm_metadata() {
+        use signature::VerificationAlgorithm;
+
+        let algs: &[(&VerificationAlgorithm, &str, usize)] = &[
+            (&RSA_PKCS1_2048_8192_SHA1, "RSA_PKCS1_2048_8192_SHA1", 63),
+            (&RSA_PKCS1_2048_8192_SHA256, "RSA_PKCS1_2048_8192_SHA256", 112),
+            (&RSA_PKCS1_3072_8192_SHA384, "RSA_PKCS1_3072_8192_SHA384", 128),
+            (&RSA_PKCS1_1024_8192_SHA256_FOR_LEGACY_USE_ONLY,
+             "RSA_PKCS1_1024_8192_SHA256_FOR_LEGACY_USE_ONLY", 80),
+            (&RSA_PSS_2048_8192_SHA512, "RSA_PSS_2048_8192_SHA512", 112),
+        ];
+        for &(alg, name, security_bits) in algs {
+            assert_eq!(alg.name(), name);
+            assert_eq!(alg.security_bits(), security_bits);
+        }
+    }
 }
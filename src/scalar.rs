@@ -0,0 +1,48 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Constant-time arithmetic on scalars modulo the group order of the NIST
+//! P-256 and P-384 curves.
+//!
+//! This is the same scalar field code that *ring*'s ECDSA and ECDH
+//! implementations use. It is exposed so that protocols that need to combine
+//! scalars, such as threshold signing and multi-party computation protocols,
+//! don't need their own implementation of it.
+//!
+//! Scalars are always fully reduced; i.e. they are in the range [0, n), where
+//! n is the order of the curve's group. Results are always fully reduced too.
+//!
+//! # Example
+//!
+//! ```
+//! # extern crate ring;
+//! # extern crate untrusted;
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::scalar;
+//!
+//! let mut two = [0u8; 32];
+//! two[31] = 2;
+//! let two = try!(scalar::Scalar::from_big_endian(
+//!     &scalar::P256, untrusted::Input::from(&two)));
+//! let one = try!(try!(two.invert()).mul(&two));
+//!
+//! let mut out = [0u8; 32];
+//! try!(one.to_big_endian(&mut out));
+//! assert_eq!(out[31], 1);
+//! # Ok(())
+//! # }
+//! ```
+
+pub use ec::suite_b::scalar::{Curve, Scalar, P256, P384};
@@ -0,0 +1,264 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The scrypt memory-hard key derivation function.
+//!
+//! scrypt is specified in [RFC 7914]. Unlike PBKDF2, the cost of scrypt is
+//! dominated by the amount of memory it uses, `128 * r * N` bytes, which makes
+//! it much more expensive to attack with custom hardware.
+//!
+//! Most applications that derive keys from a passphrase should use
+//! `ring::volume_key`, which manages the salt and the parameters, instead of
+//! using this module directly.
+//!
+//! [RFC 7914]: https://tools.ietf.org/html/rfc7914
+
+use {error, pbkdf2, polyfill};
+
+/// The largest amount of memory, in bytes, that `derive` will use, 1 GiB.
+pub const MAX_MEMORY: usize = 1 << 30;
+
+/// scrypt parameters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Params {
+    log2_n: u8,
+    r: u32,
+    p: u32,
+}
+
+impl Params {
+    /// Validates and returns the given parameters.
+    ///
+    /// | Parameter | RFC 7914 Term
+    /// |-----------|-----------------------------------
+    /// | `log2_n`  | log2(N) (the CPU/memory cost)
+    /// | `r`       | r (the block size)
+    /// | `p`       | p (the parallelization parameter)
+    ///
+    /// `log2_n`, `r`, and `p` must all be at least 1, `p * r` must be less
+    /// than 2**30, and the amount of memory used, `128 * r * N` bytes, must be
+    /// no more than `MAX_MEMORY`.
+    pub fn new(log2_n: u8, r: u32, p: u32)
+               -> Result<Params, error::Unspecified> {
+        if log2_n < 1 || log2_n >= 32 || r < 1 || p < 1 {
+            return Err(error::Unspecified);
+        }
+        if (r as u64) * (p as u64) >= 1 << 30 {
+            return Err(error::Unspecified);
+        }
+        let params = Params { log2_n: log2_n, r: r, p: p };
+        let memory = (params.block_len() as u64) << log2_n;
+        if memory > MAX_MEMORY as u64 {
+            return Err(error::Unspecified);
+        }
+        Ok(params)
+    }
+
+    /// The parameters recommended for interactive logins in
+    /// [the scrypt paper]: N = 2**15, r = 8, and p = 1. This uses 32 MiB of
+    /// memory.
+    ///
+    /// [the scrypt paper]: https://www.tarsnap.com/scrypt/scrypt.pdf
+    pub fn interactive() -> Params { Params { log2_n: 15, r: 8, p: 1 } }
+
+    /// The parameters recommended for file encryption in
+    /// [the scrypt paper]: N = 2**20, r = 8, and p = 1. This uses 1 GiB of
+    /// memory.
+    ///
+    /// [the scrypt paper]: https://www.tarsnap.com/scrypt/scrypt.pdf
+    pub fn sensitive() -> Params { Params { log2_n: 20, r: 8, p: 1 } }
+
+    /// The base-2 logarithm of N.
+    #[inline(always)]
+    pub fn log2_n(&self) -> u8 { self.log2_n }
+
+    /// r.
+    #[inline(always)]
+    pub fn r(&self) -> u32 { self.r }
+
+    /// p.
+    #[inline(always)]
+    pub fn p(&self) -> u32 { self.p }
+
+    // The length of one block B_i, in bytes.
+    fn block_len(&self) -> usize { 128 * (self.r as usize) }
+}
+
+/// Fills `out` with the key derived using scrypt with the given inputs.
+///
+/// | Parameter   | RFC 7914 Term
+/// |-------------|-------------------------------
+/// | `params`    | N, r, p
+/// | `password`  | P (passphrase)
+/// | `salt`      | S (salt)
+/// | `out`       | DK (derived key)
+/// | `out.len()` | dkLen (derived key length)
+///
+/// Returns `Err(error::Unspecified)`, without writing to `out`, if `out` is
+/// empty or longer than (2**32 - 1) * 32 bytes.
+pub fn derive(params: &Params, password: &[u8], salt: &[u8], out: &mut [u8])
+              -> Result<(), error::Unspecified> {
+    if out.len() == 0 ||
+       (out.len() as u64) > ((1u64 << 32) - 1) * 32 {
+        return Err(error::Unspecified);
+    }
+
+    let block_len = params.block_len();
+    let words_per_block = block_len / 4;
+    let n = 1usize << params.log2_n;
+
+    let mut b = vec![0u8; block_len * (params.p as usize)];
+    pbkdf2::derive(&pbkdf2::HMAC_SHA256, 1, salt, password, &mut b);
+
+    let mut x = vec![0u32; words_per_block];
+    let mut scratch = vec![0u32; words_per_block];
+    let mut v = vec![0u32; n * words_per_block];
+
+    for block in b.chunks_mut(block_len) {
+        for (x, chunk) in x.iter_mut().zip(block.chunks(4)) {
+            *x = polyfill::slice::u32_from_le_u8(
+                slice_as_array_ref!(chunk, 4).unwrap());
+        }
+        ro_mix(&mut x, &mut v, &mut scratch, n);
+        for (x, chunk) in x.iter().zip(block.chunks_mut(4)) {
+            le_u8_from_u32(chunk, *x);
+        }
+    }
+
+    pbkdf2::derive(&pbkdf2::HMAC_SHA256, 1, &b, password, out);
+
+    polyfill::slice::fill(&mut b, 0);
+    for w in x.iter_mut().chain(v.iter_mut()).chain(scratch.iter_mut()) {
+        *w = 0;
+    }
+
+    Ok(())
+}
+
+// scryptROMix, https://tools.ietf.org/html/rfc7914#section-5. `x` is the
+// block as little-endian words and `v` has room for `n` blocks.
+fn ro_mix(x: &mut [u32], v: &mut [u32], scratch: &mut [u32], n: usize) {
+    let words_per_block = x.len();
+    for v_i in v.chunks_mut(words_per_block) {
+        v_i.copy_from_slice(x);
+        block_mix(x, scratch);
+    }
+    for _ in 0..n {
+        // Integerify(X) mod N. N is less than 2**32, so only the first word
+        // of the last 64-byte block of X matters.
+        let j = (x[words_per_block - 16] as usize) & (n - 1);
+        let v_j = &v[(j * words_per_block)..((j + 1) * words_per_block)];
+        for (x, v) in x.iter_mut().zip(v_j) {
+            *x ^= *v;
+        }
+        block_mix(x, scratch);
+    }
+}
+
+// scryptBlockMix, https://tools.ietf.org/html/rfc7914#section-4. The output
+// blocks Y_0, Y_2, ..., Y_1, Y_3, ... are written to `b` in that order.
+fn block_mix(b: &mut [u32], y: &mut [u32]) {
+    let two_r = b.len() / 16;
+    let mut x = [0u32; 16];
+    x.copy_from_slice(&b[(b.len() - 16)..]);
+    for (i, b_i) in b.chunks(16).enumerate() {
+        for (x, b) in x.iter_mut().zip(b_i) {
+            *x ^= *b;
+        }
+        salsa20_8(&mut x);
+        let dest = (i / 2) + ((i % 2) * (two_r / 2));
+        y[(dest * 16)..((dest + 1) * 16)].copy_from_slice(&x);
+    }
+    b.copy_from_slice(y);
+}
+
+// The Salsa20/8 core, https://tools.ietf.org/html/rfc7914#section-3.
+fn salsa20_8(b: &mut [u32; 16]) {
+    fn quarter_round(x: &mut [u32; 16], a: usize, b: usize, c: usize,
+                     d: usize) {
+        x[b] ^= x[a].wrapping_add(x[d]).rotate_left(7);
+        x[c] ^= x[b].wrapping_add(x[a]).rotate_left(9);
+        x[d] ^= x[c].wrapping_add(x[b]).rotate_left(13);
+        x[a] ^= x[d].wrapping_add(x[c]).rotate_left(18);
+    }
+
+    let mut x = *b;
+    for _ in 0..4 {
+        // Column round.
+        quarter_round(&mut x, 0, 4, 8, 12);
+        quarter_round(&mut x, 5, 9, 13, 1);
+        quarter_round(&mut x, 10, 14, 2, 6);
+        quarter_round(&mut x, 15, 3, 7, 11);
+
+        // Row round.
+        quarter_round(&mut x, 0, 1, 2, 3);
+        quarter_round(&mut x, 5, 6, 7, 4);
+        quarter_round(&mut x, 10, 11, 8, 9);
+        quarter_round(&mut x, 15, 12, 13, 14);
+    }
+    for (b, x) in b.iter_mut().zip(x.iter()) {
+        *b = b.wrapping_add(*x);
+    }
+}
+
+fn le_u8_from_u32(out: &mut [u8], value: u32) {
+    for (i, b) in out.iter_mut().enumerate() {
+        *b = (value >> (8 * i)) as u8;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {error, test};
+    use super::*;
+
+    #[test]
+    fn test_scrypt() {
+        test::from_file("src/scrypt_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let password = test_case.consume_bytes("P");
+            let salt = test_case.consume_bytes("S");
+            let log2_n = test_case.consume_usize("log2N") as u8;
+            let r = test_case.consume_usize("r") as u32;
+            let p = test_case.consume_usize("p") as u32;
+            let expected = test_case.consume_bytes("DK");
+
+            let params = try!(Params::new(log2_n, r, p));
+            let mut out = vec![0u8; expected.len()];
+            try!(derive(&params, &password, &salt, &mut out));
+            assert_eq!(out, expected);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_scrypt_params() {
+        assert!(Params::new(1, 1, 1).is_ok());
+        assert_eq!(Params::new(0, 1, 1), Err(error::Unspecified));
+        assert!(Params::new(10, 0, 1).is_err());
+        assert!(Params::new(10, 1, 0).is_err());
+        assert!(Params::new(10, 1 << 15, 1 << 15).is_err());
+        assert!(Params::new(32, 1, 1).is_err());
+
+        assert_eq!(Params::new(20, 8, 1), Ok(Params::sensitive()));
+        assert!(Params::new(21, 8, 1).is_err());
+        assert!(Params::new(20, 16, 1).is_err());
+        assert_eq!(Params::new(15, 8, 1), Ok(Params::interactive()));
+
+        let params = Params::new(4, 1, 1).unwrap();
+        assert!(derive(&params, b"password", b"salt", &mut []).is_err());
+    }
+}
@@ -0,0 +1,275 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Wrappers for secret values.
+//!
+//! `SecretBox<T>` holds a secret value, such as a shared secret from key
+//! agreement, a PRK, or a derived key. The value can only be accessed with
+//! `expose()` or `expose_mut()`, which makes every use of the secret easy to
+//! find when auditing. `SecretBox` doesn't implement `Display`, and its
+//! `Debug` implementation doesn't print the value, so the secret can't be
+//! logged by accident. `SecretBox` doesn't implement `Clone` or `Copy`, and
+//! the value is zeroized when the `SecretBox` is dropped.
+//!
+//! Zeroization is best-effort: copies of the value that were made before it
+//! was put into the `SecretBox`, e.g. when it was moved, aren't zeroized.
+//!
+//! *ring*'s key types, e.g. `aead::SealingKey`, `hmac::SigningKey`,
+//! `signature::Ed25519KeyPair`, `signature::RSAKeyPair`, and
+//! `agreement::EphemeralPrivateKey`, zeroize their key material in the same
+//! way when they are dropped.
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::secret::SecretBox;
+//!
+//! let key = SecretBox::new([0x42u8; 32]);
+//! assert_eq!(format!("{:?}", key), "SecretBox([REDACTED])");
+//! assert_eq!(key.expose()[0], 0x42);
+//! ```
+//!
+//! # Typed Secrets
+//!
+//! A protocol's key schedule derives many secrets of fixed lengths, and
+//! mixing them up, e.g. using the client's traffic key in place of the
+//! server's, is an easy mistake to make when they are all byte arrays. A
+//! protocol implementation can instead define a type for each kind of secret
+//! and implement `ValueFromSecret` for it. *ring*'s KDFs then construct values
+//! of those types directly, e.g. with `hkdf::Prk::expand_value`, so the
+//! length of each secret is stated once, in its type, and the compiler checks
+//! that each secret is used where it belongs.
+//!
+//! ```
+//! use ring::{digest, hkdf, hmac, secret};
+//!
+//! struct ClientTrafficKey([u8; 16]);
+//!
+//! impl secret::ValueFromSecret for ClientTrafficKey {
+//!     fn secret_len() -> usize { 16 }
+//!
+//!     fn from_secret(bytes: &[u8]) -> Self {
+//!         let mut key = [0u8; 16];
+//!         key.copy_from_slice(bytes);
+//!         ClientTrafficKey(key)
+//!     }
+//! }
+//!
+//! # fn main() { foo().unwrap() }
+//! # fn foo() -> Result<(), ring::error::Unspecified> {
+//! let salt = hmac::SigningKey::new(&digest::SHA256, b"salt");
+//! let prk = hkdf::extract(&salt, b"shared secret from key agreement");
+//! let key: ClientTrafficKey = try!(prk.expand_value(b"client key"));
+//! # let _ = key.0;
+//! # Ok(())
+//! # }
+//! ```
+
+use core;
+use error;
+
+#[cfg(feature = "use_heap")]
+use std;
+
+/// A secret value that is zeroized when it is dropped.
+pub struct SecretBox<T: Zeroize> {
+    value: T,
+}
+
+impl<T: Zeroize> SecretBox<T> {
+    /// Wraps `value`.
+    pub fn new(value: T) -> SecretBox<T> { SecretBox { value: value } }
+
+    /// Returns a reference to the secret value.
+    #[inline]
+    pub fn expose(&self) -> &T { &self.value }
+
+    /// Returns a mutable reference to the secret value.
+    #[inline]
+    pub fn expose_mut(&mut self) -> &mut T { &mut self.value }
+}
+
+impl<T: Zeroize> Drop for SecretBox<T> {
+    fn drop(&mut self) { self.value.zeroize(); }
+}
+
+impl<T: Zeroize> core::fmt::Debug for SecretBox<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("SecretBox([REDACTED])")
+    }
+}
+
+/// A value that can be overwritten with zeros.
+pub trait Zeroize {
+    /// Overwrites the value with zeros in a way that the compiler won't
+    /// optimize away.
+    fn zeroize(&mut self);
+}
+
+macro_rules! impl_zeroize_for_slice {
+    ( $( $T:ty ),* ) => {
+        $(
+            impl Zeroize for [$T] {
+                fn zeroize(&mut self) {
+                    for x in self.iter_mut() {
+                        unsafe { core::ptr::write_volatile(x, 0) };
+                    }
+                    // Keep the compiler from moving later uses of the memory,
+                    // e.g. freeing it, before the writes.
+                    core::sync::atomic::compiler_fence(
+                        core::sync::atomic::Ordering::SeqCst);
+                }
+            }
+        )*
+    }
+}
+
+impl_zeroize_for_slice!(u8, u32, u64);
+
+#[cfg(feature = "use_heap")]
+impl Zeroize for std::vec::Vec<u8> {
+    fn zeroize(&mut self) { self[..].zeroize(); }
+}
+
+macro_rules! impl_zeroize_for_array {
+    ( $( $len:expr ),* ) => {
+        $(
+            impl Zeroize for [u8; $len] {
+                fn zeroize(&mut self) { self[..].zeroize(); }
+            }
+        )*
+    }
+}
+
+impl_zeroize_for_array!(16, 24, 32, 48, 56, 64, 128, 256, 384, 512);
+
+/// A secret of a fixed length that *ring*'s KDFs can construct directly.
+///
+/// See the module documentation.
+pub trait ValueFromSecret: Sized {
+    /// The length of the secret, in bytes. It must be at least one and at
+    /// most `MAX_VALUE_LEN`.
+    fn secret_len() -> usize;
+
+    /// Constructs the value from `bytes`, which are `Self::secret_len()`
+    /// bytes long. *ring* zeroizes `bytes` after this returns.
+    fn from_secret(bytes: &[u8]) -> Self;
+}
+
+/// The maximum value of `ValueFromSecret::secret_len()`.
+pub const MAX_VALUE_LEN: usize = 256;
+
+/// Constructs a `V` from the `V::secret_len()` bytes that `fill` writes.
+///
+/// This adapts any KDF that fills a buffer, e.g. `pbkdf2::derive` with
+/// parameters that `pbkdf2::Params` doesn't cover, to `ValueFromSecret`. The
+/// buffer is zeroized afterwards, whether or not `fill` succeeds.
+///
+/// Fails if `V::secret_len()` is zero or larger than `MAX_VALUE_LEN`, or if
+/// `fill` fails.
+pub fn value_from_secret<V, F>(fill: F) -> Result<V, error::Unspecified>
+                               where V: ValueFromSecret,
+                                     F: FnOnce(&mut [u8])
+                                               -> Result<(),
+                                                         error::Unspecified> {
+    let len = V::secret_len();
+    if len == 0 || len > MAX_VALUE_LEN {
+        return Err(error::Unspecified);
+    }
+    let mut buf = SecretBox::new([0u8; MAX_VALUE_LEN]);
+    let bytes = &mut buf.expose_mut()[..len];
+    try!(fill(bytes));
+    Ok(V::from_secret(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use error;
+    use super::*;
+
+    #[test]
+    fn test_secret_box_expose() {
+        let mut key = SecretBox::new([1u8; 32]);
+        assert_eq!(key.expose(), &[1u8; 32]);
+        key.expose_mut()[0] = 2;
+        assert_eq!(key.expose()[0], 2);
+        assert_eq!(&key.expose()[1..], &[1u8; 31]);
+    }
+
+    #[test]
+    fn test_secret_box_debug_is_redacted() {
+        let key = SecretBox::new([0xabu8; 16]);
+        let debug = format!("{:?}", key);
+        assert_eq!(debug, "SecretBox([REDACTED])");
+    }
+
+    #[test]
+    fn test_zeroize() {
+        let mut a = [0xffu8; 64];
+        a.zeroize();
+        assert_eq!(&a[..], &[0u8; 64][..]);
+    }
+
+    #[test]
+    fn test_zeroize_words() {
+        let mut a = [!0u32; 8];
+        a[..].zeroize();
+        assert_eq!(a, [0u32; 8]);
+        let mut b = [!0u64; 8];
+        b[..].zeroize();
+        assert_eq!(b, [0u64; 8]);
+    }
+
+    #[test]
+    fn test_value_from_secret() {
+        struct Key([u8; 3]);
+
+        impl ValueFromSecret for Key {
+            fn secret_len() -> usize { 3 }
+            fn from_secret(bytes: &[u8]) -> Self {
+                Key([bytes[0], bytes[1], bytes[2]])
+            }
+        }
+
+        let key: Key = value_from_secret(|out| {
+            assert_eq!(out.len(), 3);
+            out.copy_from_slice(&[1, 2, 3]);
+            Ok(())
+        }).unwrap();
+        assert_eq!(key.0, [1, 2, 3]);
+
+        let result: Result<Key, _> =
+            value_from_secret(|_| Err(error::Unspecified));
+        assert!(result.is_err());
+
+        struct Empty;
+
+        impl ValueFromSecret for Empty {
+            fn secret_len() -> usize { 0 }
+            fn from_secret(_: &[u8]) -> Self { Empty }
+        }
+
+        let result: Result<Empty, _> = value_from_secret(|_| Ok(()));
+        assert!(result.is_err());
+    }
+
+    #[cfg(feature = "use_heap")]
+    #[test]
+    fn test_zeroize_vec() {
+        let mut v = vec![0xffu8; 100];
+        v.zeroize();
+        assert!(v.iter().all(|&b| b == 0));
+        assert_eq!(v.len(), 100);
+    }
+}
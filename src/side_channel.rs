@@ -0,0 +1,444 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Fixed-vs-random input harnesses for timing side-channel tests.
+//!
+//! Feature: `side_channel_testing`.
+//!
+//! These harnesses let a statistical timing test, in the style of [dudect],
+//! be run against the exact build of *ring* that is shipped. Each `Target`
+//! prepares inputs of two classes: `Class::Fixed`, which is always the same
+//! (valid) input, and `Class::Random`, which is a fresh random input. The
+//! execution times of the two classes are compared with Welch's t-test,
+//! using `TTest`; a value of `|t|` greater than `T_THRESHOLD` is evidence
+//! that the target's timing depends on its input.
+//!
+//! `measure` runs the whole procedure with `std::time::Instant`. Callers that
+//! have a better clock, e.g. a cycle counter, can drive `Target::prepare`,
+//! `Target::run`, and `TTest::push` themselves. Either way, the results are
+//! only meaningful for optimized builds on a quiet machine, and many millions
+//! of measurements may be needed to detect small leaks.
+//!
+//! # Examples
+//!
+//! ```
+//! use ring::{rand, side_channel};
+//! use ring::side_channel::Target;
+//!
+//! let rng = rand::SystemRandom::new();
+//! let target = side_channel::Pkcs1Verify::new();
+//! let mut t_test = side_channel::TTest::new();
+//! side_channel::measure(&target, &rng, 1000, &mut t_test).unwrap();
+//! if t_test.t().abs() > side_channel::T_THRESHOLD {
+//!     println!("{} might not be constant-time", target.name());
+//! }
+//! ```
+//!
+//! [dudect]: https://eprint.iacr.org/2016/1123
+
+use {digest, error, mgf1, rand, std};
+use ec::suite_b;
+use rsa::{oaep, padding};
+use rsa::padding::{Encoding, Verification};
+use signature::RSAKeyPair;
+use std::vec::Vec;
+use untrusted;
+
+/// The class of an input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Class {
+    /// The same input every time.
+    Fixed,
+
+    /// A fresh, uniformly random input.
+    Random,
+}
+
+/// A routine whose execution time is measured.
+pub trait Target {
+    /// The name of the target, which is the name of its type.
+    fn name(&self) -> &'static str;
+
+    /// The length of the inputs of the target.
+    fn input_len(&self) -> usize;
+
+    /// Fills `input`, which is `input_len()` bytes long, with an input of the
+    /// class `class`.
+    fn prepare(&self, class: Class, rng: &rand::SecureRandom,
+               input: &mut [u8]) -> Result<(), error::Unspecified>;
+
+    /// Runs the measured routine on `input`. The result is only meaningful
+    /// for checking that the inputs were prepared correctly; `Class::Fixed`
+    /// inputs succeed and `Class::Random` inputs almost always fail.
+    fn run(&self, input: &[u8], rng: &rand::SecureRandom)
+           -> Result<(), error::Unspecified>;
+}
+
+// The length of the modulus of the encoded messages of `Pkcs1Verify` and
+// `PssDecode`.
+const MOD_BITS: usize = 2048;
+const MOD_LEN: usize = MOD_BITS / 8;
+
+// The message of the `Class::Fixed` inputs.
+const FIXED_MSG: &'static [u8] = b"ring side-channel test";
+
+/// The EMSA-PKCS1-v1_5 check of RSA PKCS#1 1.5 signature verification with
+/// SHA-256, for a 2048-bit modulus. The inputs are encoded messages, i.e.
+/// decrypted signatures.
+pub struct Pkcs1Verify {
+    m_hash: digest::Digest,
+}
+
+impl Pkcs1Verify {
+    /// Constructs the target.
+    pub fn new() -> Pkcs1Verify {
+        Pkcs1Verify {
+            m_hash: digest::digest(&digest::SHA256, FIXED_MSG),
+        }
+    }
+}
+
+impl Target for Pkcs1Verify {
+    fn name(&self) -> &'static str { "Pkcs1Verify" }
+
+    fn input_len(&self) -> usize { MOD_LEN }
+
+    fn prepare(&self, class: Class, rng: &rand::SecureRandom,
+               input: &mut [u8]) -> Result<(), error::Unspecified> {
+        match class {
+            Class::Fixed =>
+                padding::RSA_PKCS1_SHA256.encode(&self.m_hash, input),
+            Class::Random => rng.fill(input),
+        }
+    }
+
+    fn run(&self, input: &[u8], _rng: &rand::SecureRandom)
+           -> Result<(), error::Unspecified> {
+        padding::RSA_PKCS1_SHA256.verify(self.m_hash.as_ref(),
+                                         untrusted::Input::from(input),
+                                         MOD_BITS)
+    }
+}
+
+/// The EMSA-PSS decoding of RSA PSS signature verification with SHA-256 and
+/// a 32-byte salt, for a 2048-bit modulus. The inputs are encoded messages,
+/// i.e. decrypted signatures.
+pub struct PssDecode {
+    m_hash: digest::Digest,
+}
+
+impl PssDecode {
+    /// Constructs the target.
+    pub fn new() -> PssDecode {
+        PssDecode {
+            m_hash: digest::digest(&digest::SHA256, FIXED_MSG),
+        }
+    }
+}
+
+impl Target for PssDecode {
+    fn name(&self) -> &'static str { "PssDecode" }
+
+    fn input_len(&self) -> usize { MOD_LEN }
+
+    fn prepare(&self, class: Class, rng: &rand::SecureRandom,
+               input: &mut [u8]) -> Result<(), error::Unspecified> {
+        match class {
+            Class::Fixed =>
+                pss_encode(self.m_hash.as_ref(), &[0x5a; 32], input),
+            Class::Random => rng.fill(input),
+        }
+    }
+
+    fn run(&self, input: &[u8], _rng: &rand::SecureRandom)
+           -> Result<(), error::Unspecified> {
+        padding::RSA_PSS_SHA256.verify(self.m_hash.as_ref(),
+                                       untrusted::Input::from(input),
+                                       MOD_BITS)
+    }
+}
+
+// EMSA-PSS-ENCODE, https://tools.ietf.org/html/rfc8017#section-9.1.1, with
+// SHA-256, for a modulus of exactly `MOD_BITS` bits, starting from the digest
+// `m_hash` of the message.
+fn pss_encode(m_hash: &[u8], salt: &[u8], em: &mut [u8])
+              -> Result<(), error::Unspecified> {
+    let h_len = digest::SHA256.output_len;
+    let em_len = em.len();
+    let db_len = em_len - h_len - 1;
+
+    let h = {
+        let mut ctx = digest::Context::new(&digest::SHA256);
+        ctx.update(&[0u8; 8]);
+        ctx.update(m_hash);
+        ctx.update(salt);
+        ctx.finish()
+    };
+
+    let (db, rest) = em.split_at_mut(db_len);
+    let ps_len = db_len - salt.len() - 1;
+    for b in &mut db[..ps_len] {
+        *b = 0;
+    }
+    db[ps_len] = 0x01;
+    db[(ps_len + 1)..].copy_from_slice(salt);
+    try!(mgf1::xor_mask(&digest::SHA256, h.as_ref(), db));
+    db[0] &= 0x7f;
+
+    rest[..h_len].copy_from_slice(h.as_ref());
+    rest[h_len] = 0xbc;
+    Ok(())
+}
+
+/// RSAES-OAEP decryption with SHA-256 and an empty label, including the
+/// private key operation. The inputs are ciphertexts.
+pub struct RsaDecrypt {
+    key_pair: RSAKeyPair,
+    fixed: Vec<u8>,
+}
+
+impl RsaDecrypt {
+    /// Constructs the target for `key_pair`. The `Class::Fixed` input is the
+    /// encryption, made here, of a fixed message.
+    pub fn new(key_pair: RSAKeyPair, rng: &rand::SecureRandom)
+               -> Result<RsaDecrypt, error::Unspecified> {
+        let public_key = key_pair.public_key_der();
+        let fixed = try!(oaep::encrypt(&oaep::RSA_OAEP_SHA256,
+                                       untrusted::Input::from(&public_key),
+                                       b"", FIXED_MSG, rng));
+        Ok(RsaDecrypt {
+            key_pair: key_pair,
+            fixed: fixed,
+        })
+    }
+}
+
+impl Target for RsaDecrypt {
+    fn name(&self) -> &'static str { "RsaDecrypt" }
+
+    fn input_len(&self) -> usize { self.fixed.len() }
+
+    fn prepare(&self, class: Class, rng: &rand::SecureRandom,
+               input: &mut [u8]) -> Result<(), error::Unspecified> {
+        match class {
+            Class::Fixed => {
+                input.copy_from_slice(&self.fixed);
+                Ok(())
+            },
+            Class::Random => {
+                // A zero first byte keeps the ciphertext less than the
+                // modulus.
+                try!(rng.fill(input));
+                input[0] = 0;
+                Ok(())
+            },
+        }
+    }
+
+    fn run(&self, input: &[u8], rng: &rand::SecureRandom)
+           -> Result<(), error::Unspecified> {
+        oaep::decrypt(&oaep::RSA_OAEP_SHA256, &self.key_pair, b"", input, rng)
+            .map(|_| ())
+    }
+}
+
+/// The decoding and validation of an uncompressed P-256 public key.
+pub struct PointDecode;
+
+impl PointDecode {
+    /// Constructs the target.
+    pub fn new() -> PointDecode { PointDecode }
+}
+
+// The uncompressed encoding of the P-256 generator.
+const P256_GENERATOR: [u8; 1 + 2 * 32] = [
+    0x04,
+    0x6b, 0x17, 0xd1, 0xf2, 0xe1, 0x2c, 0x42, 0x47,
+    0xf8, 0xbc, 0xe6, 0xe5, 0x63, 0xa4, 0x40, 0xf2,
+    0x77, 0x03, 0x7d, 0x81, 0x2d, 0xeb, 0x33, 0xa0,
+    0xf4, 0xa1, 0x39, 0x45, 0xd8, 0x98, 0xc2, 0x96,
+    0x4f, 0xe3, 0x42, 0xe2, 0xfe, 0x1a, 0x7f, 0x9b,
+    0x8e, 0xe7, 0xeb, 0x4a, 0x7c, 0x0f, 0x9e, 0x16,
+    0x2b, 0xce, 0x33, 0x57, 0x6b, 0x31, 0x5e, 0xce,
+    0xcb, 0xb6, 0x40, 0x68, 0x37, 0xbf, 0x51, 0xf5,
+];
+
+impl Target for PointDecode {
+    fn name(&self) -> &'static str { "PointDecode" }
+
+    fn input_len(&self) -> usize { P256_GENERATOR.len() }
+
+    fn prepare(&self, class: Class, rng: &rand::SecureRandom,
+               input: &mut [u8]) -> Result<(), error::Unspecified> {
+        match class {
+            Class::Fixed => {
+                input.copy_from_slice(&P256_GENERATOR);
+                Ok(())
+            },
+            Class::Random => {
+                try!(rng.fill(&mut input[1..]));
+                input[0] = 0x04;
+                Ok(())
+            },
+        }
+    }
+
+    fn run(&self, input: &[u8], _rng: &rand::SecureRandom)
+           -> Result<(), error::Unspecified> {
+        suite_b::parse_p256_uncompressed_point(untrusted::Input::from(input))
+    }
+}
+
+/// The value of `|t|` above which [dudect] considers the timing of a target
+/// to depend on its input.
+///
+/// [dudect]: https://eprint.iacr.org/2016/1123
+pub const T_THRESHOLD: f64 = 4.5;
+
+/// An online Welch's t-test of the measurements of the two classes.
+#[derive(Clone, Debug)]
+pub struct TTest {
+    // Indexed by `Class as usize`. `m2` is the sum of the squared differences
+    // from the mean, as in Welford's algorithm.
+    n: [f64; 2],
+    mean: [f64; 2],
+    m2: [f64; 2],
+}
+
+impl TTest {
+    /// Constructs a test with no measurements.
+    pub fn new() -> TTest {
+        TTest {
+            n: [0.0; 2],
+            mean: [0.0; 2],
+            m2: [0.0; 2],
+        }
+    }
+
+    /// Adds the measurement `x`, e.g. an execution time, of an input of the
+    /// class `class`.
+    pub fn push(&mut self, class: Class, x: f64) {
+        let i = class as usize;
+        self.n[i] += 1.0;
+        let delta = x - self.mean[i];
+        self.mean[i] += delta / self.n[i];
+        self.m2[i] += delta * (x - self.mean[i]);
+    }
+
+    /// The number of measurements.
+    pub fn samples(&self) -> usize { (self.n[0] + self.n[1]) as usize }
+
+    /// Welch's t statistic of the measurements, or 0 if either class has
+    /// fewer than two measurements.
+    pub fn t(&self) -> f64 {
+        if self.n[0] < 2.0 || self.n[1] < 2.0 {
+            return 0.0;
+        }
+        let var0 = self.m2[0] / (self.n[0] - 1.0);
+        let var1 = self.m2[1] / (self.n[1] - 1.0);
+        let se = (var0 / self.n[0] + var1 / self.n[1]).sqrt();
+        if se == 0.0 {
+            return 0.0;
+        }
+        (self.mean[0] - self.mean[1]) / se
+    }
+}
+
+/// Measures `num_samples` executions of `target` with
+/// `std::time::Instant`, with the class of each input chosen at random, and
+/// adds the execution times, in nanoseconds, to `t_test`.
+///
+/// Inputs are prepared before their execution is timed, and the results of
+/// `Target::run` are ignored.
+pub fn measure(target: &Target, rng: &rand::SecureRandom,
+               num_samples: usize, t_test: &mut TTest)
+               -> Result<(), error::Unspecified> {
+    let mut input = vec![0u8; target.input_len()];
+    for _ in 0..num_samples {
+        let mut coin = [0u8; 1];
+        try!(rng.fill(&mut coin));
+        let class = if coin[0] & 1 == 0 { Class::Fixed } else { Class::Random };
+        try!(target.prepare(class, rng, &mut input));
+
+        let start = std::time::Instant::now();
+        let _ = target.run(&input, rng);
+        let elapsed = start.elapsed();
+
+        let nanos = (elapsed.as_secs() as f64) * 1e9 +
+                    (elapsed.subsec_nanos() as f64);
+        t_test.push(class, nanos);
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {error, rand};
+    use signature::RSAKeyPair;
+    use super::*;
+    use untrusted;
+
+    const PRIVATE_KEY: &'static [u8] =
+        include_bytes!("rsa/signature_rsa_example_private_key.der");
+
+    fn check_classes(target: &Target, rng: &rand::SecureRandom) {
+        let mut input = vec![0u8; target.input_len()];
+        for _ in 0..10 {
+            target.prepare(Class::Fixed, rng, &mut input).unwrap();
+            assert_eq!(target.run(&input, rng), Ok(()), "{}", target.name());
+
+            target.prepare(Class::Random, rng, &mut input).unwrap();
+            assert_eq!(target.run(&input, rng), Err(error::Unspecified),
+                       "{}", target.name());
+        }
+    }
+
+    #[test]
+    fn test_side_channel_targets() {
+        let rng = rand::SystemRandom::new();
+        check_classes(&Pkcs1Verify::new(), &rng);
+        check_classes(&PssDecode::new(), &rng);
+        check_classes(&PointDecode::new(), &rng);
+
+        let key_pair =
+            RSAKeyPair::from_der(untrusted::Input::from(PRIVATE_KEY)).unwrap();
+        check_classes(&RsaDecrypt::new(key_pair, &rng).unwrap(), &rng);
+    }
+
+    #[test]
+    fn test_side_channel_t_test() {
+        let mut same = TTest::new();
+        let mut different = TTest::new();
+        assert_eq!(same.t(), 0.0);
+        for i in 0..1000 {
+            let x = (i % 10) as f64;
+            same.push(Class::Fixed, x);
+            same.push(Class::Random, x);
+            different.push(Class::Fixed, x);
+            different.push(Class::Random, x + 1.0);
+        }
+        assert_eq!(same.samples(), 2000);
+        assert!(same.t().abs() < 0.001);
+        assert!(different.t() < -T_THRESHOLD);
+    }
+
+    #[test]
+    fn test_side_channel_measure() {
+        let rng = rand::SystemRandom::new();
+        let mut t_test = TTest::new();
+        measure(&PointDecode::new(), &rng, 100, &mut t_test).unwrap();
+        assert_eq!(t_test.samples(), 100);
+    }
+}
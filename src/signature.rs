@@ -16,7 +16,9 @@
 //!
 //! Use the `verify` function to verify signatures, passing a reference to the
 //! algorithm that identifies the algorithm. See the documentation for `verify`
-//! for examples.
+//! for examples. `UnparsedPublicKey` does the same for callers that have the
+//! public key, message, and signature as byte slices and don't want to depend
+//! on the `untrusted` crate.
 //!
 //! For signature verification, this API treats each combination of parameters
 //! as a separate algorithm. For example, instead of having a single "RSA"
@@ -26,11 +28,17 @@
 //! reduce the risks of algorithm agility and to provide consistency with ECDSA
 //! and EdDSA.
 //!
-//! Currently this module does not support digesting the message to be signed
+//! Most of this module doesn't support digesting the message to be signed
 //! separately from the public key operation, as it is currently being
 //! optimized for Ed25519 and for the implementation of protocols that do not
-//! requiring signing large messages. An interface for efficiently supporting
-//! larger messages may be added later.
+//! requiring signing large messages. The exceptions are Ed25519ph, where a
+//! large message can be digested incrementally and then signed with
+//! `Ed25519KeyPair::sign_prehashed`, and `SigningContext`, which signs a
+//! message supplied incrementally with Ed25519ph or ECDSA.
+//!
+//! Signatures can be verified using a digest of the message that was computed
+//! elsewhere, using the `verify_prehashed` function with ECDSA or with
+//! `ED25519PH`.
 //!
 //! # Examples
 //!
@@ -56,8 +64,8 @@
 //! // key from storage and then create an `Ed25519KeyPair` from the
 //! // deserialized bytes.
 //! let key_pair =
-//!    try!(signature::Ed25519KeyPair::from_bytes(&generated_bytes.private_key,
-//!                                               &generated_bytes.public_key));
+//!    try!(signature::Ed25519KeyPair::from_seed_and_public_key(
+//!             &generated_bytes.private_key, &generated_bytes.public_key));
 //!
 //! // Sign the message "hello, world".
 //! const MESSAGE: &'static [u8] = b"hello, world";
@@ -87,7 +95,9 @@
 //! ## Signing and verifying with RSA (PKCS#1 1.5 padding)
 //!
 //! RSA signing (but not verification) requires the `rsa_signing` feature to
-//! be enabled.
+//! be enabled. Verification of RSA, ECDSA, and Ed25519 signatures doesn't
+//! use the heap, so it works without the `use_heap` feature, e.g. in a
+//! bootloader that has no allocator.
 //!
 //! ```
 //! extern crate ring;
@@ -138,7 +148,7 @@
 //! ```
 
 
-use {error, init, private};
+use {core, digest, ec, error, init, kat, polyfill, private};
 use untrusted;
 
 pub use ec::suite_b::ecdsa::{
@@ -153,44 +163,91 @@ pub use ec::suite_b::ecdsa::{
     ECDSA_P384_SHA256_ASN1,
     ECDSA_P384_SHA384_ASN1,
     ECDSA_P384_SHA512_ASN1,
+
+    ECDSA_P521_SHA512_ASN1,
+
+    ECDSA_P256_SHA256_FIXED,
+    ECDSA_P384_SHA384_FIXED,
+
+    ECDSAKeyPair,
+    ECDSASigningAlgorithm,
+
+    ECDSA_P256_SHA256_ASN1_SIGNING_DETERMINISTIC,
+    ECDSA_P384_SHA384_ASN1_SIGNING_DETERMINISTIC,
+    ECDSA_P521_SHA512_ASN1_SIGNING_DETERMINISTIC,
+
+    ECDSA_P256_SHA256_FIXED_SIGNING_DETERMINISTIC,
+    ECDSA_P384_SHA384_FIXED_SIGNING_DETERMINISTIC,
+};
+
+pub use ec::bls12_381::{
+    BLSParameters,
+
+    BLS12_381_MIN_PK_BASIC,
+    BLS12_381_MIN_PK_POP,
 };
 
 pub use ec::eddsa::{
     EdDSAParameters,
+    EdDSAContextParameters,
+    EdDSAPrehashedParameters,
 
     ED25519,
+    ED25519PH,
 
     Ed25519KeyPair,
-    Ed25519KeyPairBytes
+    Ed25519KeyPairBytes,
+
+    ed25519_verify_batch,
 };
 
+#[cfg(feature = "use_heap")]
+pub use ec::eddsa::ed25519_verify_batch_with;
+
 #[cfg(all(feature = "rsa_signing", feature = "use_heap"))]
 pub use rsa::signing::{RSAKeyPair, RSASigningState};
 
 #[cfg(all(feature = "rsa_signing", feature = "use_heap"))]
 pub use rsa::{
-    // `RSA_PKCS1_SHA1` is intentionally not exposed. At a minimum, we'd need
-    // to create test vectors for signing with it, which we don't currently
-    // have. But, it's a bad idea to use SHA-1 anyway, so perhaps we just won't
-    // ever expose it.
+    // `RSA_PKCS1_SHA1` is intentionally not exposed here. It is only
+    // available from `ring::legacy`.
     RSA_PKCS1_SHA256,
     RSA_PKCS1_SHA384,
     RSA_PKCS1_SHA512,
+    RSA_PKCS1_SHA512_256,
 };
 
-#[cfg(feature = "use_heap")]
 pub use rsa::RSAParameters;
 
-#[cfg(feature = "use_heap")]
 pub use rsa::verification::{
     RSA_PKCS1_2048_8192_SHA1,
     RSA_PKCS1_2048_8192_SHA256,
     RSA_PKCS1_2048_8192_SHA384,
     RSA_PKCS1_2048_8192_SHA512,
+    RSA_PKCS1_2048_8192_SHA512_256,
 
     RSA_PKCS1_3072_8192_SHA384,
+
+    RSA_PKCS1_1024_8192_SHA1_FOR_LEGACY_USE_ONLY,
+    RSA_PKCS1_1024_8192_SHA256_FOR_LEGACY_USE_ONLY,
+    RSA_PKCS1_1024_8192_SHA512_FOR_LEGACY_USE_ONLY,
+
+    RSA_PSS_2048_8192_SHA256,
+    RSA_PSS_2048_8192_SHA384,
+    RSA_PSS_2048_8192_SHA512,
+    RSA_PSS_2048_8192_SHA256_SALT_LEN_0,
+    RSA_PSS_2048_8192_SHA384_SALT_LEN_0,
+    RSA_PSS_2048_8192_SHA512_SALT_LEN_0,
+    RSA_PSS_2048_8192_SHA256_SALT_LEN_20,
+    RSA_PSS_2048_8192_SHA384_SALT_LEN_20,
+    RSA_PSS_2048_8192_SHA512_SALT_LEN_20,
+    RSA_PSS_2048_8192_SHA256_ANY_SALT_LEN,
+    RSA_PSS_2048_8192_SHA384_ANY_SALT_LEN,
+    RSA_PSS_2048_8192_SHA512_ANY_SALT_LEN,
 };
 
+pub use signed_data::verify_signed_data;
+
 /// Lower-level verification primitives. Usage of `ring::signature::verify()`
 /// is preferred when the public key and signature are encoded in standard
 /// formats, as it also handles the parsing.
@@ -198,22 +255,54 @@ pub mod primitive {
     pub use rsa::verification::verify_rsa;
 }
 
+/// Conversions between the encodings of signatures that different protocols
+/// and APIs use.
+pub mod format {
+    pub use ec::suite_b::ecdsa::{ecdsa_asn1_to_fixed, ecdsa_fixed_to_asn1};
+
+    pub use rsa::{rsa_fixed_to_integer, rsa_integer_to_fixed};
+}
+
 /// A public key signature returned from a signing operation.
 pub struct Signature {
-    value: [u8; 64],
+    value: [u8; MAX_LEN],
+    len: usize,
 }
 
+// The longest signature is an ASN.1 DER-encoded P-521 ECDSA signature: a
+// SEQUENCE with a two-byte length of two INTEGERs, each of which may need a
+// leading zero byte.
+const MAX_LEN: usize = 1/*tag:SEQUENCE*/ + 2/*len*/ +
+                       (2 * (1/*tag:INTEGER*/ + 1/*len*/ + 1/*zero*/ +
+                             ec::ELEM_MAX_BYTES));
+
 impl<'a> Signature {
     // Initialize a 64-byte signature from slice of bytes. XXX: This is public
     // so that other *ring* submodules can use it, but it isn't intended for
     // public use.
     #[doc(hidden)]
     pub fn new(signature_bytes: [u8; 64]) -> Signature {
-        Signature { value: signature_bytes }
+        Signature::new_with_len(|value| {
+            value[..signature_bytes.len()].copy_from_slice(&signature_bytes);
+            signature_bytes.len()
+        })
+    }
+
+    // Initialize a signature of variable length by letting `fill` write it
+    // into the (large enough) buffer and return its length. XXX: This is
+    // public so that other *ring* submodules can use it, but it isn't
+    // intended for public use.
+    #[doc(hidden)]
+    pub fn new_with_len<F>(fill: F) -> Signature
+                           where F: FnOnce(&mut [u8; MAX_LEN]) -> usize {
+        let mut r = Signature { value: [0; MAX_LEN], len: 0 };
+        r.len = fill(&mut r.value);
+        assert!(r.len <= MAX_LEN);
+        r
     }
 
     /// Returns a reference to the signature's encoded value.
-    pub fn as_slice(&'a self) -> &'a [u8] { &self.value[..] }
+    pub fn as_slice(&'a self) -> &'a [u8] { &self.value[..self.len] }
 }
 
 /// A signature verification algorithm.
@@ -222,6 +311,21 @@ pub trait VerificationAlgorithm: Sync + private::Private {
     /// `public_key`.
     fn verify(&self, public_key: untrusted::Input, msg: untrusted::Input,
               signature: untrusted::Input) -> Result<(), error::Unspecified>;
+
+    /// The name of the algorithm, which is the name of its `static` in
+    /// *ring*, e.g. `"ECDSA_P256_SHA256_ASN1"`.
+    fn name(&self) -> &'static str;
+
+    /// The approximate security strength of the algorithm, in bits.
+    ///
+    /// This is the lesser of the strengths of the key and of the digest
+    /// algorithm used to hash the message, as estimated in
+    /// [NIST SP 800-57 Part 1] Section 5.6.1. For RSA, which accepts keys of
+    /// a range of sizes, the strength is that of the smallest accepted key.
+    ///
+    /// [NIST SP 800-57 Part 1]:
+    ///     http://nvlpubs.nist.gov/nistpubs/SpecialPublications/NIST.SP.800-57pt1r4.pdf
+    fn security_bits(&self) -> usize;
 }
 
 /// Verify the signature `signature` of message `msg` with the public key
@@ -241,7 +345,6 @@ pub trait VerificationAlgorithm: Sync + private::Private {
 ///     InvalidSignature,
 /// }
 ///
-/// # #[cfg(feature = "use_heap")]
 /// fn verify_rsa_pkcs1_sha256(public_key: untrusted::Input,
 ///                            msg: untrusted::Input, sig: untrusted::Input)
 ///                            -> Result<(), Error> {
@@ -257,6 +360,311 @@ pub fn verify(alg: &VerificationAlgorithm, public_key: untrusted::Input,
     alg.verify(public_key, msg, signature)
 }
 
+/// An unparsed public key for signature verification, which may be invalid.
+///
+/// This is a convenience for callers that don't use `untrusted`: `verify`
+/// parses the key, the message, and the signature with `untrusted`
+/// internally, exactly like the `verify` function.
+///
+/// # Examples
+///
+/// ```
+/// use ring::{rand, signature};
+///
+/// # fn sign_and_verify() -> Result<(), ring::error::Unspecified> {
+/// let rng = rand::SystemRandom::new();
+/// let key_pair = try!(signature::Ed25519KeyPair::generate(&rng));
+/// let sig = key_pair.sign(b"hello");
+///
+/// let public_key = signature::UnparsedPublicKey::new(
+///     &signature::ED25519, key_pair.public_key_bytes());
+/// try!(public_key.verify(b"hello", sig.as_slice()));
+/// assert!(public_key.verify(b"goodbye", sig.as_slice()).is_err());
+/// # Ok(())
+/// # }
+/// # fn main() { sign_and_verify().unwrap() }
+/// ```
+#[derive(Clone, Copy)]
+pub struct UnparsedPublicKey<B: AsRef<[u8]>> {
+    alg: &'static VerificationAlgorithm,
+    bytes: B,
+}
+
+impl<B: AsRef<[u8]>> UnparsedPublicKey<B> {
+    /// Constructs the public key of algorithm `alg` encoded as `bytes`,
+    /// without parsing or validating it.
+    #[inline]
+    pub fn new(alg: &'static VerificationAlgorithm, bytes: B) -> Self {
+        UnparsedPublicKey { alg: alg, bytes: bytes }
+    }
+
+    /// The verification algorithm.
+    #[inline]
+    pub fn algorithm(&self) -> &'static VerificationAlgorithm { self.alg }
+
+    /// The encoded public key.
+    #[inline]
+    pub fn bytes(&self) -> &B { &self.bytes }
+
+    /// Verifies the signature `signature` of the message `msg`; see the
+    /// `verify` function.
+    pub fn verify(&self, msg: &[u8], signature: &[u8])
+                  -> Result<(), error::Unspecified> {
+        verify(self.alg, untrusted::Input::from(self.bytes.as_ref()),
+               untrusted::Input::from(msg), untrusted::Input::from(signature))
+    }
+}
+
+/// A signature verification algorithm that verifies signatures of messages
+/// that have been digested by the caller.
+pub trait PrehashedVerificationAlgorithm: Sync + private::Private {
+    /// The digest algorithm that must be used to compute the digest of the
+    /// message.
+    fn digest_algorithm(&self) -> &'static digest::Algorithm;
+
+    /// Verify the signature `signature` of the message with digest `digest`
+    /// with the public key `public_key`.
+    fn verify_prehashed(&self, public_key: untrusted::Input,
+                        digest: untrusted::Input, signature: untrusted::Input)
+                        -> Result<(), error::Unspecified>;
+}
+
+/// Verify the signature `signature` of the message with digest `digest` with
+/// the public key `public_key` using the algorithm `alg`.
+///
+/// This is useful when the message isn't available to the verifier, e.g.
+/// when only the digest of the message leaves the system that produced the
+/// message. `digest` must have been computed with `alg.digest_algorithm()`;
+/// a `digest` of the wrong length is rejected. When the message is available,
+/// use `verify` instead.
+pub fn verify_prehashed(alg: &PrehashedVerificationAlgorithm,
+                        public_key: untrusted::Input, digest: untrusted::Input,
+                        signature: untrusted::Input)
+                        -> Result<(), error::Unspecified> {
+    init::init_once();
+    alg.verify_prehashed(public_key, digest, signature)
+}
+
+/// A context for verifying a signature of a message that is supplied
+/// incrementally, e.g. as it is received from the network.
+///
+/// The message is digested as it is passed to `update`, so it doesn't need to
+/// be kept in memory or read a second time. `finish` then verifies the
+/// signature of the digested message, exactly like `verify_prehashed`. The
+/// message may be longer than 4 GiB, even on 32-bit platforms; use
+/// `with_max_len` to limit its length instead.
+///
+/// # Examples
+///
+/// ```
+/// extern crate ring;
+/// extern crate untrusted;
+///
+/// use ring::{signature, error};
+///
+/// fn verify_chunks(public_key: untrusted::Input, chunks: &[&[u8]],
+///                  sig: untrusted::Input) -> Result<(), error::Unspecified> {
+///     let mut ctx = signature::VerificationContext::new(
+///         &signature::ECDSA_P256_SHA256_ASN1, public_key);
+///     for chunk in chunks {
+///         ctx.update(chunk);
+///     }
+///     ctx.finish(sig)
+/// }
+/// # fn main() { }
+/// ```
+pub struct VerificationContext<'a> {
+    alg: &'a PrehashedVerificationAlgorithm,
+    public_key: untrusted::Input<'a>,
+    digest_ctx: digest::Context,
+    len: u64,
+    max_len: u64,
+}
+
+impl<'a> VerificationContext<'a> {
+    /// Constructs a new context for verifying a signature with the public key
+    /// `public_key` using the algorithm `alg`.
+    pub fn new(alg: &'a PrehashedVerificationAlgorithm,
+               public_key: untrusted::Input<'a>) -> VerificationContext<'a> {
+        VerificationContext::with_max_len(alg, public_key, core::u64::MAX)
+    }
+
+    /// Like `new`, but `finish` fails if more than `max_len` bytes were
+    /// passed to `update`. Once `max_len` is exceeded, `update` stops
+    /// digesting the data passed to it.
+    pub fn with_max_len(alg: &'a PrehashedVerificationAlgorithm,
+                        public_key: untrusted::Input<'a>, max_len: u64)
+                        -> VerificationContext<'a> {
+        init::init_once();
+        VerificationContext {
+            alg: alg,
+            public_key: public_key,
+            digest_ctx: digest::Context::new(alg.digest_algorithm()),
+            len: 0,
+            max_len: max_len,
+        }
+    }
+
+    /// Updates the digest with all the data in `data`. `update` may be called
+    /// zero or more times before `finish` is called.
+    pub fn update(&mut self, data: &[u8]) {
+        self.len =
+            self.len.saturating_add(polyfill::u64_from_usize(data.len()));
+        if self.len <= self.max_len {
+            self.digest_ctx.update(data);
+        }
+    }
+
+    /// Verifies the signature `signature` of all the data passed to `update`.
+    pub fn finish(self, signature: untrusted::Input)
+                  -> Result<(), error::Unspecified> {
+        if self.len > self.max_len {
+            return Err(error::Unspecified);
+        }
+        let digest = self.digest_ctx.finish();
+        self.alg.verify_prehashed(self.public_key,
+                                  untrusted::Input::from(digest.as_ref()),
+                                  signature)
+    }
+}
+
+/// A key pair for signing, of any type.
+///
+/// This is implemented by `Ed25519KeyPair`, `ECDSAKeyPair`, and
+/// `RSAKeyPair`, so that code that handles public keys, e.g. to put them in
+/// certificates or to register them, can work with all of them in the same
+/// way.
+///
+/// # Examples
+///
+/// ```
+/// use ring::signature;
+///
+/// fn public_key_len<K: signature::KeyPair>(key_pair: &K) -> usize {
+///     key_pair.public_key().as_ref().len()
+/// }
+/// # fn main() { }
+/// ```
+pub trait KeyPair: private::Private {
+    /// The type of the encoded public key.
+    type PublicKey: AsRef<[u8]> + ?Sized;
+
+    /// The public key, encoded in the form that the verification algorithms
+    /// for the key type accept: the 32-byte public key for Ed25519, the
+    /// uncompressed point for ECDSA, and a DER-encoded `RSAPublicKey` for
+    /// RSA.
+    fn public_key(&self) -> &Self::PublicKey;
+}
+
+/// A key pair that can sign messages that have been digested by the caller.
+///
+/// This is implemented by `Ed25519KeyPair`, which signs with [Ed25519ph], and
+/// by `ECDSAKeyPair`.
+///
+/// [Ed25519ph]: https://tools.ietf.org/html/rfc8032#section-5.1
+pub trait PrehashedSigningKey: private::Private {
+    /// The digest algorithm that must be used to compute the digest of the
+    /// message.
+    fn digest_algorithm(&self) -> &'static digest::Algorithm;
+
+    /// Returns the signature of the message with digest `digest`. Fails if
+    /// `digest` wasn't computed with `digest_algorithm()`.
+    fn sign_prehashed(&self, digest: &digest::Digest)
+                      -> Result<Signature, error::Unspecified>;
+}
+
+/// A context for signing a message that is supplied incrementally, e.g. as
+/// it is read from a file.
+///
+/// The message is digested as it is passed to `update`, so it doesn't need to
+/// be kept in memory. `finish` then signs the digest, so an `Ed25519KeyPair`
+/// produces an Ed25519ph signature, which must be verified with `ED25519PH`,
+/// and an `ECDSAKeyPair` produces the same signature that `sign` would.
+///
+/// # Examples
+///
+/// ```
+/// use ring::{signature, error};
+///
+/// fn sign_chunks(key_pair: &signature::Ed25519KeyPair, chunks: &[&[u8]])
+///                -> Result<signature::Signature, error::Unspecified> {
+///     let mut ctx = signature::SigningContext::new(key_pair);
+///     for chunk in chunks {
+///         ctx.update(chunk);
+///     }
+///     ctx.finish()
+/// }
+/// # fn main() { }
+/// ```
+pub struct SigningContext<'a> {
+    key: &'a PrehashedSigningKey,
+    digest_ctx: digest::Context,
+}
+
+impl<'a> SigningContext<'a> {
+    /// Constructs a new context for signing with `key`.
+    pub fn new(key: &'a PrehashedSigningKey) -> SigningContext<'a> {
+        init::init_once();
+        SigningContext {
+            key: key,
+            digest_ctx: digest::Context::new(key.digest_algorithm()),
+        }
+    }
+
+    /// Updates the digest with all the data in `data`. `update` may be called
+    /// zero or more times before `finish` is called.
+    pub fn update(&mut self, data: &[u8]) { self.digest_ctx.update(data); }
+
+    /// Returns the signature of all the data passed to `update`.
+    pub fn finish(self) -> Result<Signature, error::Unspecified> {
+        let digest = self.digest_ctx.finish();
+        self.key.sign_prehashed(&digest)
+    }
+}
+
+/// Runs the known-answer tests of signing and verifying with `ED25519`, with
+/// TEST 1 of [RFC 8032 Section 7.1]; see `kat`.
+///
+/// [RFC 8032 Section 7.1]: https://tools.ietf.org/html/rfc8032#section-7.1
+pub fn known_answer_tests() -> [kat::TestResult; 2] {
+    let signed = Ed25519KeyPair::from_seed_and_public_key(
+            &KAT_ED25519_SEED, &KAT_ED25519_PUBLIC_KEY)
+        .map(|key_pair| {
+            key_pair.sign(b"").as_slice() == &KAT_ED25519_SIGNATURE[..]
+        })
+        .unwrap_or(false);
+    let verified = verify(&ED25519,
+                          untrusted::Input::from(&KAT_ED25519_PUBLIC_KEY),
+                          untrusted::Input::from(b""),
+                          untrusted::Input::from(&KAT_ED25519_SIGNATURE))
+        .is_ok();
+    [
+        kat::TestResult { name: "ED25519 sign", passed: signed },
+        kat::TestResult { name: "ED25519 verify", passed: verified },
+    ]
+}
+
+const KAT_ED25519_SEED: [u8; 32] = [
+    0x9d, 0x61, 0xb1, 0x9d, 0xef, 0xfd, 0x5a, 0x60, 0xba, 0x84, 0x4a, 0xf4,
+    0x92, 0xec, 0x2c, 0xc4, 0x44, 0x49, 0xc5, 0x69, 0x7b, 0x32, 0x69, 0x19,
+    0x70, 0x3b, 0xac, 0x03, 0x1c, 0xae, 0x7f, 0x60,
+];
+
+const KAT_ED25519_PUBLIC_KEY: [u8; 32] = [
+    0xd7, 0x5a, 0x98, 0x01, 0x82, 0xb1, 0x0a, 0xb7, 0xd5, 0x4b, 0xfe, 0xd3,
+    0xc9, 0x64, 0x07, 0x3a, 0x0e, 0xe1, 0x72, 0xf3, 0xda, 0xa6, 0x23, 0x25,
+    0xaf, 0x02, 0x1a, 0x68, 0xf7, 0x07, 0x51, 0x1a,
+];
+
+const KAT_ED25519_SIGNATURE: [u8; 64] = [
+    0xe5, 0x56, 0x43, 0x00, 0xc3, 0x60, 0xac, 0x72, 0x90, 0x86, 0xe2, 0xcc,
+    0x80, 0x6e, 0x82, 0x8a, 0x84, 0x87, 0x7f, 0x1e, 0xb8, 0xe5, 0xd9, 0x74,
+    0xd8, 0x73, 0xe0, 0x65, 0x22, 0x49, 0x01, 0x55, 0x5f, 0xb8, 0x82, 0x15,
+    0x90, 0xa3, 0x3b, 0xac, 0xc6, 0x1e, 0x39, 0x70, 0x1c, 0xf9, 0xb4, 0x6b,
+    0xd2, 0x5b, 0xf5, 0xf0, 0x59, 0x5b, 0xbe, 0x24, 0x65, 0x51, 0x41, 0x43,
+    0x8e, 0x7a, 0x10, 0x0b,
+];
+
 
 #[cfg(test)]
 mod tests {
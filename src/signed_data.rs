@@ -0,0 +1,257 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Verification of signatures over the to-be-signed portion of X.509
+//! certificates, CRLs, and OCSP responses.
+
+use {der, error, signature};
+use untrusted;
+
+/// Verifies the signature `signature` of `tbs` using the public key in
+/// `spki`, with the signature algorithm identified by `algorithm_id`.
+///
+/// `spki` must be a DER-encoded `SubjectPublicKeyInfo`, `algorithm_id` must be
+/// a DER-encoded `AlgorithmIdentifier`, and `signature` must be a DER-encoded
+/// `BIT STRING`, exactly as they appear in X.509 certificates, CRLs, and OCSP
+/// responses. `tbs` is the DER encoding of the signed data (e.g. the
+/// `TBSCertificate`), including its tag and length.
+///
+/// The algorithm identifiers are matched byte-for-byte against the DER
+/// encodings *ring* supports; alternate encodings (e.g. RSA algorithm
+/// identifiers without the `NULL` parameter) are rejected. The combination of
+/// the public key algorithm in `spki` and the signature algorithm must be one
+/// of the following:
+///
+/// | Public Key                | Signature Algorithm                      |
+/// |---------------------------|------------------------------------------|
+/// | `id-ecPublicKey`, P-256   | `ecdsa-with-SHA{256,384,512}`            |
+/// | `id-ecPublicKey`, P-384   | `ecdsa-with-SHA{256,384,512}`            |
+/// | `rsaEncryption`           | `sha{256,384,512}WithRSAEncryption`      |
+/// | `id-Ed25519`              | `id-Ed25519`                             |
+///
+/// SHA-1-based signature algorithms are intentionally not supported. RSA
+/// keys must be 2048-8192 bits, as with `RSA_PKCS1_2048_8192_SHA256` and
+/// friends. RSA is only available when the `use_heap` feature is enabled.
+pub fn verify_signed_data(spki: untrusted::Input,
+                          algorithm_id: untrusted::Input,
+                          tbs: untrusted::Input, signature: untrusted::Input)
+                          -> Result<(), error::Unspecified> {
+    let (public_key_alg_id, public_key) = try!(parse_spki(spki));
+
+    let signature_alg_id = try!(algorithm_id.read_all(error::Unspecified,
+                                                      |input| {
+        der::expect_tag_and_get_value(input, der::Tag::Sequence)
+    }));
+
+    let signature = try!(signature.read_all(error::Unspecified,
+                                            bit_string_with_no_unused_bits));
+
+    let alg = try!(find_algorithm(public_key_alg_id, signature_alg_id));
+    signature::verify(alg, public_key, tbs, signature)
+}
+
+/// Parses the DER-encoded `SubjectPublicKeyInfo` `spki`, returning the
+/// contents of its `AlgorithmIdentifier` and its public key bits.
+pub fn parse_spki<'a>(spki: untrusted::Input<'a>)
+                      -> Result<(untrusted::Input<'a>, untrusted::Input<'a>),
+                                error::Unspecified> {
+    spki.read_all(error::Unspecified, |input| {
+        der::nested(input, der::Tag::Sequence, error::Unspecified, |input| {
+            let alg_id =
+                try!(der::expect_tag_and_get_value(input, der::Tag::Sequence));
+            let public_key = try!(bit_string_with_no_unused_bits(input));
+            Ok((alg_id, public_key))
+        })
+    })
+}
+
+pub fn bit_string_with_no_unused_bits<'a>(input: &mut untrusted::Reader<'a>)
+                                      -> Result<untrusted::Input<'a>,
+                                                error::Unspecified> {
+    der::nested(input, der::Tag::BitString, error::Unspecified, |value| {
+        let unused_bits_at_end = try!(value.read_byte());
+        if unused_bits_at_end != 0 {
+            return Err(error::Unspecified);
+        }
+        Ok(value.skip_to_end())
+    })
+}
+
+pub fn find_algorithm(public_key_alg_id: untrusted::Input,
+                      signature_alg_id: untrusted::Input)
+                      -> Result<&'static signature::VerificationAlgorithm,
+                                error::Unspecified> {
+    let public_key_alg_id = public_key_alg_id.as_slice_less_safe();
+    let signature_alg_id = signature_alg_id.as_slice_less_safe();
+    let found = ECDSA_AND_EDDSA_ALGORITHMS.iter()
+        .chain(rsa_algorithms().iter())
+        .find(|alg| alg.public_key_alg_id == public_key_alg_id &&
+                    alg.signature_alg_id == signature_alg_id);
+    found.map(|alg| alg.verification_alg).ok_or(error::Unspecified)
+}
+
+/// A supported combination of a `SubjectPublicKeyInfo` algorithm and a
+/// signature algorithm. The algorithm identifiers are the contents of the
+/// DER-encoded `AlgorithmIdentifier`, without the outer `SEQUENCE` tag and
+/// length.
+struct SignedDataAlgorithm {
+    public_key_alg_id: &'static [u8],
+    signature_alg_id: &'static [u8],
+    verification_alg: &'static signature::VerificationAlgorithm,
+}
+
+// id-ecPublicKey (1.2.840.10045.2.1) with namedCurve secp256r1
+// (1.2.840.10045.3.1.7).
+pub const ECDSA_P256: &'static [u8] = &[
+    0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01,
+    0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07,
+];
+
+// id-ecPublicKey (1.2.840.10045.2.1) with namedCurve secp384r1
+// (1.3.132.0.34).
+pub const ECDSA_P384: &'static [u8] = &[
+    0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01,
+    0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x22,
+];
+
+// ecdsa-with-SHA256 (1.2.840.10045.4.3.2), with absent parameters.
+const ECDSA_SHA256: &'static [u8] = &[
+    0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02,
+];
+
+// ecdsa-with-SHA384 (1.2.840.10045.4.3.3), with absent parameters.
+const ECDSA_SHA384: &'static [u8] = &[
+    0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x03,
+];
+
+// ecdsa-with-SHA512 (1.2.840.10045.4.3.4), with absent parameters.
+const ECDSA_SHA512: &'static [u8] = &[
+    0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x04,
+];
+
+// id-Ed25519 (1.3.101.112), with absent parameters. The same identifier is
+// used for both the public key and the signature algorithm.
+pub const ED25519: &'static [u8] = &[0x06, 0x03, 0x2b, 0x65, 0x70];
+
+// rsaEncryption (1.2.840.113549.1.1.1), with NULL parameters.
+pub const RSA_ENCRYPTION: &'static [u8] = &[
+    0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01,
+    0x05, 0x00,
+];
+
+static ECDSA_AND_EDDSA_ALGORITHMS: [SignedDataAlgorithm; 7] = [
+    SignedDataAlgorithm {
+        public_key_alg_id: ECDSA_P256,
+        signature_alg_id: ECDSA_SHA256,
+        verification_alg: &signature::ECDSA_P256_SHA256_ASN1,
+    },
+    SignedDataAlgorithm {
+        public_key_alg_id: ECDSA_P256,
+        signature_alg_id: ECDSA_SHA384,
+        verification_alg: &signature::ECDSA_P256_SHA384_ASN1,
+    },
+    SignedDataAlgorithm {
+        public_key_alg_id: ECDSA_P256,
+        signature_alg_id: ECDSA_SHA512,
+        verification_alg: &signature::ECDSA_P256_SHA512_ASN1,
+    },
+    SignedDataAlgorithm {
+        public_key_alg_id: ECDSA_P384,
+        signature_alg_id: ECDSA_SHA256,
+        verification_alg: &signature::ECDSA_P384_SHA256_ASN1,
+    },
+    SignedDataAlgorithm {
+        public_key_alg_id: ECDSA_P384,
+        signature_alg_id: ECDSA_SHA384,
+        verification_alg: &signature::ECDSA_P384_SHA384_ASN1,
+    },
+    SignedDataAlgorithm {
+        public_key_alg_id: ECDSA_P384,
+        signature_alg_id: ECDSA_SHA512,
+        verification_alg: &signature::ECDSA_P384_SHA512_ASN1,
+    },
+    SignedDataAlgorithm {
+        public_key_alg_id: ED25519,
+        signature_alg_id: ED25519,
+        verification_alg: &signature::ED25519,
+    },
+];
+
+fn rsa_algorithms() -> &'static [SignedDataAlgorithm] {
+    // sha256WithRSAEncryption (1.2.840.113549.1.1.11), with NULL parameters.
+    const RSA_PKCS1_SHA256: &'static [u8] = &[
+        0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b,
+        0x05, 0x00,
+    ];
+
+    // sha384WithRSAEncryption (1.2.840.113549.1.1.12), with NULL parameters.
+    const RSA_PKCS1_SHA384: &'static [u8] = &[
+        0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0c,
+        0x05, 0x00,
+    ];
+
+    // sha512WithRSAEncryption (1.2.840.113549.1.1.13), with NULL parameters.
+    const RSA_PKCS1_SHA512: &'static [u8] = &[
+        0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0d,
+        0x05, 0x00,
+    ];
+
+    static RSA_ALGORITHMS: [SignedDataAlgorithm; 3] = [
+        SignedDataAlgorithm {
+            public_key_alg_id: RSA_ENCRYPTION,
+            signature_alg_id: RSA_PKCS1_SHA256,
+            verification_alg: &signature::RSA_PKCS1_2048_8192_SHA256,
+        },
+        SignedDataAlgorithm {
+            public_key_alg_id: RSA_ENCRYPTION,
+            signature_alg_id: RSA_PKCS1_SHA384,
+            verification_alg: &signature::RSA_PKCS1_2048_8192_SHA384,
+        },
+        SignedDataAlgorithm {
+            public_key_alg_id: RSA_ENCRYPTION,
+            signature_alg_id: RSA_PKCS1_SHA512,
+            verification_alg: &signature::RSA_PKCS1_2048_8192_SHA512,
+        },
+    ];
+
+    &RSA_ALGORITHMS
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {signature, test};
+    use untrusted;
+
+    #[test]
+    fn test_verify_signed_data() {
+        test::from_file("src/signed_data_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+
+            let spki = test_case.consume_bytes("SPKI");
+            let algorithm_id = test_case.consume_bytes("AlgorithmId");
+            let tbs = test_case.consume_bytes("TBS");
+            let sig = test_case.consume_bytes("Sig");
+            let expected_result = test_case.consume_string("Result");
+
+            let actual_result = signature::verify_signed_data(
+                untrusted::Input::from(&spki),
+                untrusted::Input::from(&algorithm_id),
+                untrusted::Input::from(&tbs), untrusted::Input::from(&sig));
+            assert_eq!(actual_result.is_ok(), expected_result == "OK");
+
+            Ok(())
+        });
+    }
+}
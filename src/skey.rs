@@ -0,0 +1,239 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! S/KEY one-time passwords, as specified in [RFC 2289].
+//!
+//! The OTP with count `n` is the result of applying the hash function `n`
+//! times to the hash of the seed and the secret pass-phrase, each hash being
+//! folded to 64 bits. The server stores only the last OTP it accepted. The
+//! next OTP, which has a count one less, hashes to the stored one, so the
+//! server can verify it, but the stored one can't be used to compute it.
+//!
+//! `generate` computes an OTP, e.g. to initialize the server with the OTP of
+//! the highest count or to respond to a challenge. `verify` checks a response
+//! against the last accepted OTP. The caller stores the accepted OTP and the
+//! decremented count, and must make the user reinitialize the sequence with
+//! a new seed before the count reaches zero.
+//!
+//! Of the hash functions of RFC 2289, only SHA-1, i.e. "otp-sha1", is
+//! supported; *ring* doesn't implement MD4 or MD5. OTPs are exchanged in the
+//! hexadecimal format, which `parse_hex` parses; the six-word format isn't
+//! supported.
+//!
+//! # Example
+//!
+//! ```
+//! use ring::skey;
+//!
+//! # fn main() { foo().unwrap() }
+//! # fn foo() -> Result<(), ring::error::Unspecified> {
+//! let passphrase = b"This is a test.";
+//!
+//! // Initialization: the server stores the OTP with count 100.
+//! let mut stored = try!(skey::generate(&skey::SHA1, passphrase, b"TeSt",
+//!                                      100));
+//!
+//! // The server sends the challenge "otp-sha1 99 TeSt" and the user's
+//! // generator responds with the OTP with count 99.
+//! let response = try!(skey::generate(&skey::SHA1, passphrase, b"TeSt", 99));
+//! try!(skey::verify(&skey::SHA1, &stored, &response));
+//! stored = response;
+//!
+//! // The same OTP can't be used again.
+//! assert!(skey::verify(&skey::SHA1, &stored, &response).is_err());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [RFC 2289]: https://tools.ietf.org/html/rfc2289
+
+use {constant_time, digest, error};
+
+/// The length of an OTP, in bytes.
+pub const OTP_LEN: usize = 8;
+
+/// A one-time password.
+pub type OTP = [u8; OTP_LEN];
+
+/// The minimum length of the secret pass-phrase, in bytes.
+pub const MIN_PASSPHRASE_LEN: usize = 10;
+
+/// The maximum length of the seed, in bytes.
+pub const MAX_SEED_LEN: usize = 16;
+
+/// An RFC 2289 hash function.
+pub struct Algorithm {
+    name: &'static str,
+    digest_alg: &'static digest::Algorithm,
+    fold: fn(&digest::Digest) -> OTP,
+}
+
+impl Algorithm {
+    /// The name of the algorithm in challenges, e.g. "otp-sha1".
+    #[inline(always)]
+    pub fn name(&self) -> &'static str { self.name }
+}
+
+/// SHA-1, folded as in RFC 2289 Appendix A.
+pub static SHA1: Algorithm = Algorithm {
+    name: "otp-sha1",
+    digest_alg: &digest::SHA1,
+    fold: fold_sha1,
+};
+
+/// Computes the OTP with count `count` for `passphrase` and `seed`.
+///
+/// `passphrase` must be at least `MIN_PASSPHRASE_LEN` bytes long. `seed`
+/// must be 1 to `MAX_SEED_LEN` alphanumeric ASCII characters; it is
+/// case-insensitive.
+pub fn generate(alg: &'static Algorithm, passphrase: &[u8], seed: &[u8],
+                count: u32) -> Result<OTP, error::Unspecified> {
+    if passphrase.len() < MIN_PASSPHRASE_LEN {
+        return Err(error::Unspecified);
+    }
+    if seed.is_empty() || seed.len() > MAX_SEED_LEN {
+        return Err(error::Unspecified);
+    }
+    let mut lowercase_seed = [0u8; MAX_SEED_LEN];
+    for (out, b) in lowercase_seed.iter_mut().zip(seed) {
+        *out = match *b {
+            b'0'...b'9' | b'a'...b'z' => *b,
+            b'A'...b'Z' => *b - b'A' + b'a',
+            _ => { return Err(error::Unspecified); },
+        };
+    }
+
+    let mut ctx = digest::Context::new(alg.digest_alg);
+    ctx.update(&lowercase_seed[..seed.len()]);
+    ctx.update(passphrase);
+    let mut otp = (alg.fold)(&ctx.finish());
+    for _ in 0..count {
+        otp = step(alg, &otp);
+    }
+    Ok(otp)
+}
+
+/// Verifies that `otp` is the OTP that precedes `previous`, the last OTP
+/// that was accepted (or the one stored at initialization).
+///
+/// On success, the caller must replace `previous` with `otp` and decrement
+/// the count before it accepts another OTP.
+pub fn verify(alg: &'static Algorithm, previous: &OTP, otp: &OTP)
+              -> Result<(), error::Unspecified> {
+    constant_time::verify_slices_are_equal(&step(alg, otp), previous)
+}
+
+/// Parses an OTP in the hexadecimal format of RFC 2289 Section 6, e.g.
+/// "BB9E 6AE1 979D 8FF4". Letters may be in either case, and white space is
+/// ignored.
+pub fn parse_hex(input: &[u8]) -> Result<OTP, error::Unspecified> {
+    let mut otp = [0u8; OTP_LEN];
+    let mut num_digits = 0;
+    for b in input {
+        let digit = match *b {
+            b'0'...b'9' => *b - b'0',
+            b'a'...b'f' => *b - b'a' + 10,
+            b'A'...b'F' => *b - b'A' + 10,
+            b' ' | b'\t' | b'\r' | b'\n' => { continue; },
+            _ => { return Err(error::Unspecified); },
+        };
+        if num_digits == 2 * OTP_LEN {
+            return Err(error::Unspecified);
+        }
+        otp[num_digits / 2] |= digit << (4 * (1 - (num_digits % 2)));
+        num_digits += 1;
+    }
+    if num_digits != 2 * OTP_LEN {
+        return Err(error::Unspecified);
+    }
+    Ok(otp)
+}
+
+fn step(alg: &'static Algorithm, otp: &OTP) -> OTP {
+    (alg.fold)(&digest::digest(alg.digest_alg, otp))
+}
+
+// RFC 2289's reference implementation XORs the digest as an array of 32-bit
+// words in the byte order of a little-endian machine, so each word of the
+// result is little-endian.
+fn fold_sha1(digest: &digest::Digest) -> OTP {
+    let digest = digest.as_ref();
+    let mut words = [0u32; 5];
+    for (word, chunk) in words.iter_mut().zip(digest.chunks(4)) {
+        *word = chunk.iter().fold(0, |acc, b| (acc << 8) | (*b as u32));
+    }
+    let folded = [words[0] ^ words[2] ^ words[4], words[1] ^ words[3]];
+    let mut otp = [0u8; OTP_LEN];
+    for (chunk, word) in otp.chunks_mut(4).zip(folded.iter()) {
+        for (i, b) in chunk.iter_mut().enumerate() {
+            *b = (*word >> (8 * i)) as u8;
+        }
+    }
+    otp
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {error, test};
+    use super::*;
+
+    #[test]
+    fn test_skey() {
+        test::from_file("src/skey_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let passphrase = test_case.consume_bytes("Passphrase");
+            let seed = test_case.consume_bytes("Seed");
+            let count = test_case.consume_usize("Count") as u32;
+            let expected = test_case.consume_bytes("OTP");
+
+            let otp = try!(generate(&SHA1, &passphrase, &seed, count));
+            assert_eq!(&otp[..], &expected[..]);
+
+            let previous = try!(generate(&SHA1, &passphrase, &seed,
+                                         count + 1));
+            assert_eq!(verify(&SHA1, &previous, &otp), Ok(()));
+            assert_eq!(verify(&SHA1, &otp, &previous),
+                       Err(error::Unspecified));
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_skey_generate_rejects() {
+        let passphrase = b"This is a test.";
+        assert!(generate(&SHA1, passphrase, b"TeSt", 0).is_ok());
+        assert_eq!(generate(&SHA1, passphrase, b"TeSt", 0),
+                   generate(&SHA1, passphrase, b"test", 0));
+        assert!(generate(&SHA1, b"too short", b"TeSt", 0).is_err());
+        assert!(generate(&SHA1, passphrase, b"", 0).is_err());
+        assert!(generate(&SHA1, passphrase, b"0123456789abcdef", 0).is_ok());
+        assert!(generate(&SHA1, passphrase, b"0123456789abcdefg", 0)
+                    .is_err());
+        assert!(generate(&SHA1, passphrase, b"Te St", 0).is_err());
+        assert_eq!(SHA1.name(), "otp-sha1");
+    }
+
+    #[test]
+    fn test_skey_parse_hex() {
+        let expected = [0xbb, 0x9e, 0x6a, 0xe1, 0x97, 0x9d, 0x8f, 0xf4];
+        assert_eq!(parse_hex(b"BB9E 6AE1 979D 8FF4"), Ok(expected));
+        assert_eq!(parse_hex(b"bb9e6ae1979d8ff4\r\n"), Ok(expected));
+        assert!(parse_hex(b"BB9E 6AE1 979D 8FF").is_err());
+        assert!(parse_hex(b"BB9E 6AE1 979D 8FF40").is_err());
+        assert!(parse_hex(b"BB9E-6AE1-979D-8FF4").is_err());
+        assert!(parse_hex(b"").is_err());
+    }
+}
@@ -0,0 +1,455 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+//! Hierarchical deterministic key derivation, as specified in [SLIP-0010],
+//! which generalizes [BIP-32] to other curves.
+//!
+//! A master key is derived from a seed, and each child key is derived from
+//! its parent's key and chain code with a 32-bit index. Indexes of `HARDENED`
+//! and above select hardened children, which can only be derived from the
+//! parent's private key. Smaller indexes select normal children, which can
+//! also be derived from the parent's public key with
+//! `ExtendedPublicKey::derive_child`, so that a party that holds only the
+//! public key can compute the children's public keys.
+//!
+//! Ed25519 only supports hardened derivation. The derived Ed25519 private key
+//! is the RFC 8032 private key, for use with
+//! `signature::Ed25519KeyPair::from_seed_unchecked`. The derived P-256
+//! private key is a big-endian-encoded scalar, for use with
+//! `agreement::EphemeralPrivateKey::from_seed`.
+//!
+//! The secp256k1 curve of BIP-32 isn't supported, since *ring* doesn't
+//! implement secp256k1. Neither is the serialization of extended keys
+//! (`xprv` and `xpub`), which requires the RIPEMD-160 key fingerprints.
+//!
+//! [SLIP-0010]: https://github.com/satoshilabs/slips/blob/master/slip-0010.md
+//! [BIP-32]:
+//!     https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki
+//!
+//! # Example
+//!
+//! ```
+//! # extern crate ring;
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::{signature, slip10};
+//!
+//! // In practice, the seed is derived from a secret, e.g. a BIP-39 mnemonic.
+//! let seed = [0u8; 32];
+//! let master = try!(slip10::ExtendedPrivateKey::from_seed(&slip10::ED25519,
+//!                                                         &seed));
+//! let account = try!(master.derive_path(&[44 | slip10::HARDENED,
+//!                                         501 | slip10::HARDENED,
+//!                                         0 | slip10::HARDENED]));
+//! let key_pair = try!(signature::Ed25519KeyPair::from_seed_unchecked(
+//!                         account.private_key()));
+//! let _ = key_pair.sign(b"hello, world");
+//! # Ok(())
+//! # }
+//! ```
+
+use {digest, error, hmac, init, signature};
+use ec::suite_b::slip10 as p256;
+use untrusted;
+
+/// A curve for key derivation.
+pub struct Curve {
+    id: CurveID,
+    seed_key: &'static [u8],
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum CurveID {
+    Ed25519,
+    P256,
+}
+
+/// Key derivation for Ed25519 (`ed25519` in SLIP-0010). Only hardened
+/// derivation is supported.
+pub static ED25519: Curve = Curve {
+    id: CurveID::Ed25519,
+    seed_key: b"ed25519 seed",
+};
+
+/// Key derivation for NIST P-256 (`nist256p1` in SLIP-0010).
+pub static NIST_P256: Curve = Curve {
+    id: CurveID::P256,
+    seed_key: b"Nist256p1 seed",
+};
+
+/// Indexes at or above `HARDENED` select hardened children.
+pub const HARDENED: u32 = 0x8000_0000;
+
+/// The length of a chain code.
+pub const CHAIN_CODE_LEN: usize = 32;
+
+/// The length of a private key, for every curve.
+pub const PRIVATE_KEY_LEN: usize = 32;
+
+/// The length of a public key, for every curve. P-256 public keys are
+/// compressed points. Ed25519 public keys are prefixed with a zero byte.
+pub const PUBLIC_KEY_LEN: usize = 33;
+
+// BIP-32 requires seeds of 128 to 512 bits.
+const MIN_SEED_LEN: usize = 128 / 8;
+const MAX_SEED_LEN: usize = 512 / 8;
+
+/// A private key and its chain code.
+pub struct ExtendedPrivateKey {
+    curve: &'static Curve,
+    private_key: [u8; PRIVATE_KEY_LEN],
+    chain_code: [u8; CHAIN_CODE_LEN],
+}
+
+impl ExtendedPrivateKey {
+    /// Derives the master key from `seed`, which must be 16 to 64 bytes
+    /// long.
+    pub fn from_seed(curve: &'static Curve, seed: &[u8])
+                     -> Result<ExtendedPrivateKey, error::Unspecified> {
+        init::init_once();
+
+        if seed.len() < MIN_SEED_LEN || seed.len() > MAX_SEED_LEN {
+            return Err(error::Unspecified);
+        }
+
+        let key = hmac::SigningKey::new(&digest::SHA512, curve.seed_key);
+        let mut i = hmac::sign(&key, seed);
+        loop {
+            {
+                let (il, ir) = i.as_ref().split_at(PRIVATE_KEY_LEN);
+                if curve.id == CurveID::Ed25519 ||
+                   p256::private_key_is_valid(il) {
+                    return Ok(ExtendedPrivateKey::new(curve, il, ir));
+                }
+            }
+            // SLIP-0010: If IL is not a valid private key, then use I as the
+            // seed.
+            i = hmac::sign(&key, i.as_ref());
+        }
+    }
+
+    fn new(curve: &'static Curve, private_key: &[u8], chain_code: &[u8])
+           -> ExtendedPrivateKey {
+        let mut r = ExtendedPrivateKey {
+            curve: curve,
+            private_key: [0; PRIVATE_KEY_LEN],
+            chain_code: [0; CHAIN_CODE_LEN],
+        };
+        r.private_key.copy_from_slice(private_key);
+        r.chain_code.copy_from_slice(chain_code);
+        r
+    }
+
+    /// Derives the child with the given index (CKDpriv). Fails if the curve
+    /// is Ed25519 and `index` isn't hardened.
+    pub fn derive_child(&self, index: u32)
+                        -> Result<ExtendedPrivateKey, error::Unspecified> {
+        let mut data = [0u8; 1 + PRIVATE_KEY_LEN + 4];
+        if index >= HARDENED {
+            data[1..(1 + PRIVATE_KEY_LEN)].copy_from_slice(&self.private_key);
+        } else {
+            if self.curve.id == CurveID::Ed25519 {
+                return Err(error::Unspecified);
+            }
+            try!(p256::public_from_private(&self.private_key,
+                                           &mut data[..PUBLIC_KEY_LEN]));
+        }
+        data[(1 + PRIVATE_KEY_LEN)..].copy_from_slice(&be_u32(index));
+
+        let key = hmac::SigningKey::new(&digest::SHA512, &self.chain_code);
+        loop {
+            let i = hmac::sign(&key, &data);
+            let (il, ir) = i.as_ref().split_at(PRIVATE_KEY_LEN);
+            if self.curve.id == CurveID::Ed25519 {
+                return Ok(ExtendedPrivateKey::new(self.curve, il, ir));
+            }
+            let mut child = [0u8; PRIVATE_KEY_LEN];
+            if p256::private_key_tweak_add(&self.private_key, il,
+                                           &mut child).is_ok() {
+                return Ok(ExtendedPrivateKey::new(self.curve, &child, ir));
+            }
+            retry_data(&mut data, ir);
+        }
+    }
+
+    /// Derives the descendant at the given path, relative to `self`, by
+    /// deriving each child in turn.
+    pub fn derive_path(&self, path: &[u32])
+                       -> Result<ExtendedPrivateKey, error::Unspecified> {
+        let mut r = ExtendedPrivateKey::new(self.curve, &self.private_key,
+                                            &self.chain_code);
+        for index in path {
+            r = try!(r.derive_child(*index));
+        }
+        Ok(r)
+    }
+
+    /// Returns the public key and the chain code.
+    pub fn public_key(&self)
+                      -> Result<ExtendedPublicKey, error::Unspecified> {
+        let mut r = ExtendedPublicKey {
+            curve: self.curve,
+            public_key: [0; PUBLIC_KEY_LEN],
+            chain_code: self.chain_code,
+        };
+        match self.curve.id {
+            CurveID::Ed25519 => {
+                let key_pair =
+                    try!(signature::Ed25519KeyPair::from_seed_unchecked(
+                            &self.private_key));
+                r.public_key[1..].copy_from_slice(key_pair.public_key_bytes());
+            },
+            CurveID::P256 => {
+                try!(p256::public_from_private(&self.private_key,
+                                               &mut r.public_key));
+            },
+        }
+        Ok(r)
+    }
+
+    /// The curve.
+    pub fn curve(&self) -> &'static Curve { self.curve }
+
+    /// The private key.
+    pub fn private_key(&self) -> &[u8] { &self.private_key }
+
+    /// The chain code.
+    pub fn chain_code(&self) -> &[u8] { &self.chain_code }
+}
+
+/// A public key and its chain code, for deriving the public keys of normal
+/// children. Only P-256 is supported.
+pub struct ExtendedPublicKey {
+    curve: &'static Curve,
+    public_key: [u8; PUBLIC_KEY_LEN],
+    chain_code: [u8; CHAIN_CODE_LEN],
+}
+
+impl ExtendedPublicKey {
+    /// Constructs an extended public key from its components. `public_key`
+    /// must be encoded as described for `PUBLIC_KEY_LEN`; P-256 public keys
+    /// are validated.
+    pub fn new(curve: &'static Curve, public_key: untrusted::Input,
+               chain_code: untrusted::Input)
+               -> Result<ExtendedPublicKey, error::Unspecified> {
+        init::init_once();
+
+        if public_key.len() != PUBLIC_KEY_LEN ||
+           chain_code.len() != CHAIN_CODE_LEN {
+            return Err(error::Unspecified);
+        }
+        match curve.id {
+            CurveID::Ed25519 => {
+                if public_key.as_slice_less_safe()[0] != 0 {
+                    return Err(error::Unspecified);
+                }
+            },
+            CurveID::P256 => {
+                try!(p256::verify_compressed_point(public_key));
+            },
+        }
+        let mut r = ExtendedPublicKey {
+            curve: curve,
+            public_key: [0; PUBLIC_KEY_LEN],
+            chain_code: [0; CHAIN_CODE_LEN],
+        };
+        r.public_key.copy_from_slice(public_key.as_slice_less_safe());
+        r.chain_code.copy_from_slice(chain_code.as_slice_less_safe());
+        Ok(r)
+    }
+
+    /// Derives the public key of the normal child with the given index
+    /// (CKDpub). Fails if `index` is hardened or if the curve is Ed25519.
+    pub fn derive_child(&self, index: u32)
+                        -> Result<ExtendedPublicKey, error::Unspecified> {
+        if index >= HARDENED || self.curve.id == CurveID::Ed25519 {
+            return Err(error::Unspecified);
+        }
+        let mut data = [0u8; PUBLIC_KEY_LEN + 4];
+        data[..PUBLIC_KEY_LEN].copy_from_slice(&self.public_key);
+        data[PUBLIC_KEY_LEN..].copy_from_slice(&be_u32(index));
+
+        let key = hmac::SigningKey::new(&digest::SHA512, &self.chain_code);
+        loop {
+            let i = hmac::sign(&key, &data);
+            let (il, ir) = i.as_ref().split_at(PRIVATE_KEY_LEN);
+            let mut r = ExtendedPublicKey {
+                curve: self.curve,
+                public_key: [0; PUBLIC_KEY_LEN],
+                chain_code: [0; CHAIN_CODE_LEN],
+            };
+            if p256::public_key_tweak_add(
+                    untrusted::Input::from(&self.public_key), il,
+                    &mut r.public_key).is_ok() {
+                r.chain_code.copy_from_slice(ir);
+                return Ok(r);
+            }
+            retry_data(&mut data, ir);
+        }
+    }
+
+    /// Derives the descendant at the given path, relative to `self`, by
+    /// deriving each child in turn. None of the indexes may be hardened.
+    pub fn derive_path(&self, path: &[u32])
+                       -> Result<ExtendedPublicKey, error::Unspecified> {
+        let mut r = ExtendedPublicKey {
+            curve: self.curve,
+            public_key: self.public_key,
+            chain_code: self.chain_code,
+        };
+        for index in path {
+            r = try!(r.derive_child(*index));
+        }
+        Ok(r)
+    }
+
+    /// The curve.
+    pub fn curve(&self) -> &'static Curve { self.curve }
+
+    /// The public key, encoded as described for `PUBLIC_KEY_LEN`.
+    pub fn public_key(&self) -> &[u8] { &self.public_key }
+
+    /// The chain code.
+    pub fn chain_code(&self) -> &[u8] { &self.chain_code }
+}
+
+// SLIP-0010: If the derived key is invalid, then derive again with the data
+// 0x01 || IR || ser32(i). `data` always ends with ser32(i).
+fn retry_data(data: &mut [u8], ir: &[u8]) {
+    let index_start = data.len() - 4;
+    data[0] = 1;
+    data[1..index_start].copy_from_slice(ir);
+}
+
+fn be_u32(value: u32) -> [u8; 4] {
+    [(value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8,
+     value as u8]
+}
+
+#[cfg(test)]
+mod tests {
+    use test;
+    use std;
+    use super::*;
+    use untrusted;
+
+    #[test]
+    fn test_slip10() {
+        test::from_file("src/slip10_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+
+            let curve = match test_case.consume_string("Curve").as_str() {
+                "ED25519" => &ED25519,
+                "NIST_P256" => &NIST_P256,
+                s => panic!("Unsupported curve: {}", s),
+            };
+            let seed = test_case.consume_bytes("Seed");
+            let path = parse_path(&test_case.consume_string("Path"));
+            let chain_code = test_case.consume_bytes("ChainCode");
+            let private_key = test_case.consume_bytes("PrivateKey");
+            let public_key = test_case.consume_bytes("PublicKey");
+
+            let master = ExtendedPrivateKey::from_seed(curve, &seed).unwrap();
+            let key = master.derive_path(&path).unwrap();
+            assert_eq!(key.private_key(), &private_key[..]);
+            assert_eq!(key.chain_code(), &chain_code[..]);
+            let public = key.public_key().unwrap();
+            assert_eq!(public.public_key(), &public_key[..]);
+            assert_eq!(public.chain_code(), &chain_code[..]);
+
+            // Derive the trailing normal children from the public key of
+            // their closest hardened (or master) ancestor.
+            let normal_len =
+                path.iter().rev().take_while(|i| **i < HARDENED).count();
+            let (private_path, public_path) =
+                path.split_at(path.len() - normal_len);
+            let ancestor = master.derive_path(private_path).unwrap();
+            let ancestor = ancestor.public_key().unwrap();
+            let ancestor = ExtendedPublicKey::new(
+                    curve, untrusted::Input::from(ancestor.public_key()),
+                    untrusted::Input::from(ancestor.chain_code())).unwrap();
+            let result = ancestor.derive_path(public_path);
+            if curve.id == CurveID::Ed25519 && !public_path.is_empty() {
+                assert!(result.is_err());
+            } else {
+                let public = result.unwrap();
+                assert_eq!(public.public_key(), &public_key[..]);
+                assert_eq!(public.chain_code(), &chain_code[..]);
+            }
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_slip10_invalid() {
+        let seed = [0u8; 32];
+
+        // Seeds must be 128 to 512 bits.
+        assert!(ExtendedPrivateKey::from_seed(&NIST_P256, &seed[..15])
+                    .is_err());
+        assert!(ExtendedPrivateKey::from_seed(&NIST_P256, &seed[..16])
+                    .is_ok());
+        let long_seed = [0u8; 65];
+        assert!(ExtendedPrivateKey::from_seed(&NIST_P256, &long_seed[..64])
+                    .is_ok());
+        assert!(ExtendedPrivateKey::from_seed(&NIST_P256, &long_seed)
+                    .is_err());
+
+        // Ed25519 only supports hardened derivation.
+        let master = ExtendedPrivateKey::from_seed(&ED25519, &seed).unwrap();
+        assert!(master.derive_child(0).is_err());
+        assert!(master.derive_child(HARDENED).is_ok());
+
+        // Hardened children can't be derived from a public key.
+        let master = ExtendedPrivateKey::from_seed(&NIST_P256, &seed).unwrap();
+        let public = master.public_key().unwrap();
+        assert!(public.derive_child(HARDENED - 1).is_ok());
+        assert!(public.derive_child(HARDENED).is_err());
+
+        // Public keys are validated.
+        let chain_code = untrusted::Input::from(public.chain_code());
+        let mut point = [0u8; PUBLIC_KEY_LEN];
+        point.copy_from_slice(public.public_key());
+        assert!(ExtendedPublicKey::new(&NIST_P256,
+                                       untrusted::Input::from(&point),
+                                       chain_code).is_ok());
+        point[0] = 4;
+        assert!(ExtendedPublicKey::new(&NIST_P256,
+                                       untrusted::Input::from(&point),
+                                       chain_code).is_err());
+        // There is no point with x == 1.
+        let mut not_on_curve = [0u8; PUBLIC_KEY_LEN];
+        not_on_curve[0] = 2;
+        not_on_curve[PUBLIC_KEY_LEN - 1] = 1;
+        assert!(ExtendedPublicKey::new(&NIST_P256,
+                                       untrusted::Input::from(&not_on_curve),
+                                       chain_code).is_err());
+        assert!(ExtendedPublicKey::new(&NIST_P256,
+                                       untrusted::Input::from(&point[1..]),
+                                       chain_code).is_err());
+    }
+
+    // Parses paths like "m/0H/1", where "H" marks hardened indexes.
+    fn parse_path(path: &str) -> std::vec::Vec<u32> {
+        let mut components = path.split('/');
+        assert_eq!(components.next(), Some("m"));
+        components.map(|c| {
+            if c.ends_with('H') {
+                c[..(c.len() - 1)].parse::<u32>().unwrap() | HARDENED
+            } else {
+                c.parse().unwrap()
+            }
+        }).collect()
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! SPAKE2+, an augmented password-authenticated key exchange, as specified
+//! in [RFC 9383].
+//!
+//! Only the `SPAKE2+-P256-SHA256-HKDF-SHA256-HMAC-SHA256` ciphersuite is
+//! implemented. This is the ciphersuite that Matter (formerly CHIP) uses for
+//! device commissioning.
+//!
+//! The prover (client) knows the password. The verifier (server) only stores
+//! a registration record derived from it, so that a compromise of the
+//! verifier doesn't directly reveal the password. The PBKDF that derives
+//! `w0s || w1s` from the password is chosen by the application (Matter uses
+//! PBKDF2-HMAC-SHA256; see `ring::pbkdf2`).
+//!
+//! The exchange is:
+//!
+//! 1. The prover calls `Prover::start` and sends `shareP` to the verifier.
+//! 2. The verifier calls `Verifier::start` and sends `shareV` and `confirmV`
+//!    to the prover.
+//! 3. The prover calls `Prover::finish` and sends `confirmP` to the verifier.
+//! 4. The verifier calls `Verifier::finish`.
+//!
+//! Both parties may use the shared key only after their `finish` succeeds.
+//!
+//! [RFC 9383]: https://tools.ietf.org/html/rfc9383
+//!
+//! # Example
+//!
+//! ```
+//! # extern crate ring;
+//! # extern crate untrusted;
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::{pbkdf2, rand, spake2plus};
+//!
+//! let rng = rand::SystemRandom::new();
+//!
+//! // Registration.
+//! let mut w0s_w1s = [0u8; spake2plus::PBKDF_OUTPUT_LEN];
+//! pbkdf2::derive(&pbkdf2::HMAC_SHA256, 1000, b"salt", b"20202021",
+//!                &mut w0s_w1s);
+//! let secrets = try!(spake2plus::ProverSecrets::from_pbkdf_output(&w0s_w1s));
+//! let mut record = [0u8; spake2plus::REGISTRATION_RECORD_LEN];
+//! try!(secrets.registration_record(&mut record));
+//! let record = try!(spake2plus::RegistrationRecord::from_bytes(
+//!     untrusted::Input::from(&record)));
+//!
+//! // The exchange.
+//! let context = b"CHIP PAKE V1 Commissioning";
+//! let prover = try!(spake2plus::Prover::start(&secrets, &rng));
+//! let verifier = try!(spake2plus::Verifier::start(
+//!     &record, context, b"", b"", untrusted::Input::from(prover.share()),
+//!     &rng));
+//! let (confirm_p, prover_key) = try!(prover.finish(
+//!     context, b"", b"", untrusted::Input::from(verifier.share()),
+//!     untrusted::Input::from(verifier.confirmation())));
+//! let verifier_key =
+//!     try!(verifier.finish(untrusted::Input::from(confirm_p.as_ref())));
+//!
+//! assert_eq!(prover_key.as_ref(), verifier_key.as_ref());
+//! # Ok(())
+//! # }
+//! ```
+
+pub use ec::suite_b::spake2plus::{
+    Confirmation,
+    Prover,
+    ProverSecrets,
+    RegistrationRecord,
+    SharedKey,
+    Verifier,
+
+    CONFIRMATION_LEN,
+    PBKDF_OUTPUT_LEN,
+    REGISTRATION_RECORD_LEN,
+    SCALAR_LEN,
+    SHARE_LEN,
+    SHARED_KEY_LEN,
+};
@@ -0,0 +1,493 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! A client for the SSH agent protocol, as specified in
+//! [draft-miller-ssh-agent].
+//!
+//! This lets a program sign with keys held by the user's `ssh-agent` (or by
+//! a hardware token that an agent fronts, like a YubiKey) without having
+//! access to the private keys. Signatures are returned in the formats that
+//! `ring::signature::verify` accepts, not in the SSH wire format, so they are
+//! interchangeable with signatures produced by *ring*'s own key pairs.
+//!
+//! The client doesn't open the connection itself; it works over any stream.
+//! On Unix-like systems the agent listens on the Unix domain socket named by
+//! the `SSH_AUTH_SOCK` environment variable.
+//!
+//! Ed25519, RSA (with SHA-256 or SHA-512), and ECDSA P-256 and P-384 keys
+//! are supported. Security key (`sk-*`) keys and certificates are listed by
+//! `Client::identities` but can't be used for signing.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # extern crate ring;
+//! # fn main() { example().unwrap() }
+//! # #[cfg(unix)]
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::{error, ssh_agent};
+//! use std::os::unix::net::UnixStream;
+//!
+//! let path = try!(std::env::var_os("SSH_AUTH_SOCK")
+//!                     .ok_or(error::Unspecified));
+//! let stream = try!(UnixStream::connect(path)
+//!                       .map_err(|_| error::Unspecified));
+//! let mut agent = ssh_agent::Client::new(stream);
+//!
+//! let identities = try!(agent.identities());
+//! let identity = try!(identities.iter()
+//!                               .find(|id| id.key_type() == b"ssh-ed25519")
+//!                               .ok_or(error::Unspecified));
+//! let signature = try!(agent.sign_ed25519(identity, b"hello, world"));
+//! # let _ = signature;
+//! # Ok(())
+//! # }
+//! # #[cfg(not(unix))]
+//! # fn example() -> Result<(), ring::error::Unspecified> { Ok(()) }
+//! ```
+//!
+//! [draft-miller-ssh-agent]:
+//!     https://tools.ietf.org/html/draft-miller-ssh-agent-04
+
+use {der, error, signature};
+use std::io;
+use std::vec::Vec;
+use untrusted;
+
+/// An algorithm for signing with a key held by an SSH agent.
+pub struct SigningAlgorithm {
+    key_type: &'static [u8],
+    signature_type: &'static [u8],
+    flags: u32,
+    signature_format: SignatureFormat,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SignatureFormat {
+    // The signature is used as-is.
+    Raw,
+
+    // The signature is a pair of mpints, r and s, which are converted to an
+    // ASN.1 `Ecdsa-Sig-Value`.
+    ECDSAASN1,
+}
+
+/// Ed25519 signing. The signature can be verified with
+/// `ring::signature::ED25519`.
+pub static ED25519: SigningAlgorithm = SigningAlgorithm {
+    key_type: b"ssh-ed25519",
+    signature_type: b"ssh-ed25519",
+    flags: 0,
+    signature_format: SignatureFormat::Raw,
+};
+
+/// RSA PKCS#1 1.5 signing with SHA-256. The signature can be verified with
+/// `ring::signature::RSA_PKCS1_2048_8192_SHA256`.
+pub static RSA_PKCS1_SHA256: SigningAlgorithm = SigningAlgorithm {
+    key_type: b"ssh-rsa",
+    signature_type: b"rsa-sha2-256",
+    flags: SSH_AGENT_RSA_SHA2_256,
+    signature_format: SignatureFormat::Raw,
+};
+
+/// RSA PKCS#1 1.5 signing with SHA-512. The signature can be verified with
+/// `ring::signature::RSA_PKCS1_2048_8192_SHA512`.
+pub static RSA_PKCS1_SHA512: SigningAlgorithm = SigningAlgorithm {
+    key_type: b"ssh-rsa",
+    signature_type: b"rsa-sha2-512",
+    flags: SSH_AGENT_RSA_SHA2_512,
+    signature_format: SignatureFormat::Raw,
+};
+
+/// ECDSA signing with P-256 and SHA-256. The signature can be verified with
+/// `ring::signature::ECDSA_P256_SHA256_ASN1`.
+pub static ECDSA_P256_SHA256_ASN1: SigningAlgorithm = SigningAlgorithm {
+    key_type: b"ecdsa-sha2-nistp256",
+    signature_type: b"ecdsa-sha2-nistp256",
+    flags: 0,
+    signature_format: SignatureFormat::ECDSAASN1,
+};
+
+/// ECDSA signing with P-384 and SHA-384. The signature can be verified with
+/// `ring::signature::ECDSA_P384_SHA384_ASN1`.
+pub static ECDSA_P384_SHA384_ASN1: SigningAlgorithm = SigningAlgorithm {
+    key_type: b"ecdsa-sha2-nistp384",
+    signature_type: b"ecdsa-sha2-nistp384",
+    flags: 0,
+    signature_format: SignatureFormat::ECDSAASN1,
+};
+
+/// A key held by an SSH agent.
+pub struct Identity {
+    key_blob: Vec<u8>,
+    key_type_len: usize,
+    comment: Vec<u8>,
+}
+
+impl Identity {
+    /// The SSH key type, e.g. `ssh-ed25519` or `ssh-rsa`.
+    pub fn key_type(&self) -> &[u8] {
+        &self.key_blob[4..(4 + self.key_type_len)]
+    }
+
+    /// The public key in the SSH wire format.
+    pub fn key_blob(&self) -> &[u8] { &self.key_blob }
+
+    /// The comment the agent has for the key, usually the name of the file
+    /// that it was loaded from.
+    pub fn comment(&self) -> &[u8] { &self.comment }
+
+    /// The public key in the format that `ring::signature::verify` accepts:
+    /// the 32-byte public key for Ed25519 keys, a DER-encoded `RSAPublicKey`
+    /// for RSA keys, and an uncompressed point for ECDSA keys.
+    pub fn public_key(&self) -> Result<Vec<u8>, error::Unspecified> {
+        let key_blob = untrusted::Input::from(&self.key_blob);
+        key_blob.read_all(error::Unspecified, |input| {
+            let key_type = try!(read_string(input));
+            let key_type = key_type.as_slice_less_safe();
+            if key_type == ED25519.key_type {
+                let public_key = try!(read_string(input));
+                if public_key.len() != 32 {
+                    return Err(error::Unspecified);
+                }
+                Ok(public_key.as_slice_less_safe().to_vec())
+            } else if key_type == RSA_PKCS1_SHA256.key_type {
+                let e = try!(read_mpint(input));
+                let n = try!(read_mpint(input));
+                let mut integers = Vec::new();
+                der::write_tlv(der::Tag::Integer, n, &mut integers);
+                der::write_tlv(der::Tag::Integer, e, &mut integers);
+                let mut public_key = Vec::new();
+                der::write_tlv(der::Tag::Sequence, &integers, &mut public_key);
+                Ok(public_key)
+            } else if key_type == ECDSA_P256_SHA256_ASN1.key_type ||
+                      key_type == ECDSA_P384_SHA384_ASN1.key_type {
+                // The curve name is the suffix of the key type.
+                let curve = try!(read_string(input));
+                if !key_type.ends_with(curve.as_slice_less_safe()) {
+                    return Err(error::Unspecified);
+                }
+                let q = try!(read_string(input));
+                Ok(q.as_slice_less_safe().to_vec())
+            } else {
+                Err(error::Unspecified)
+            }
+        })
+    }
+}
+
+/// A client connection to an SSH agent.
+pub struct Client<S: io::Read + io::Write> {
+    stream: S,
+}
+
+impl<S: io::Read + io::Write> Client<S> {
+    /// Constructs a client that talks to the agent over `stream`.
+    pub fn new(stream: S) -> Client<S> { Client { stream: stream } }
+
+    /// Returns the stream.
+    pub fn into_inner(self) -> S { self.stream }
+
+    /// Lists the keys held by the agent.
+    pub fn identities(&mut self) -> Result<Vec<Identity>, error::Unspecified> {
+        let response =
+            try!(self.request(SSH_AGENTC_REQUEST_IDENTITIES, &[],
+                              SSH_AGENT_IDENTITIES_ANSWER));
+        untrusted::Input::from(&response).read_all(error::Unspecified,
+                                                   |input| {
+            let count = try!(read_u32(input));
+            let mut identities = Vec::new();
+            for _ in 0..count {
+                let key_blob = try!(read_string(input));
+                let comment = try!(read_string(input));
+                let key_type = try!(key_blob.read_all(error::Unspecified,
+                                                      |input| {
+                    let key_type = try!(read_string(input));
+                    let _ = input.skip_to_end();
+                    Ok(key_type)
+                }));
+                identities.push(Identity {
+                    key_blob: key_blob.as_slice_less_safe().to_vec(),
+                    key_type_len: key_type.len(),
+                    comment: comment.as_slice_less_safe().to_vec(),
+                });
+            }
+            Ok(identities)
+        })
+    }
+
+    /// Asks the agent to sign `msg` with the key `identity` using
+    /// `algorithm`. The key type of `identity` must match `algorithm`.
+    ///
+    /// The signature is returned in the format that the corresponding
+    /// `ring::signature` verification algorithm accepts.
+    pub fn sign(&mut self, identity: &Identity,
+                algorithm: &'static SigningAlgorithm, msg: &[u8])
+                -> Result<Vec<u8>, error::Unspecified> {
+        if identity.key_type() != algorithm.key_type {
+            return Err(error::Unspecified);
+        }
+        let mut request = Vec::new();
+        write_string(&identity.key_blob, &mut request);
+        write_string(msg, &mut request);
+        write_u32(algorithm.flags, &mut request);
+        let response =
+            try!(self.request(SSH_AGENTC_SIGN_REQUEST, &request,
+                              SSH_AGENT_SIGN_RESPONSE));
+
+        untrusted::Input::from(&response).read_all(error::Unspecified,
+                                                   |input| {
+            let signature = try!(read_string(input));
+            signature.read_all(error::Unspecified, |input| {
+                // Agents that don't understand the flags return an
+                // `ssh-rsa` (SHA-1) signature for RSA keys, which is
+                // rejected here.
+                let signature_type = try!(read_string(input));
+                if signature_type.as_slice_less_safe() !=
+                        algorithm.signature_type {
+                    return Err(error::Unspecified);
+                }
+                let signature = try!(read_string(input));
+                match algorithm.signature_format {
+                    SignatureFormat::Raw =>
+                        Ok(signature.as_slice_less_safe().to_vec()),
+                    SignatureFormat::ECDSAASN1 =>
+                        signature.read_all(error::Unspecified, |input| {
+                            let r = try!(read_mpint(input));
+                            let s = try!(read_mpint(input));
+                            let mut integers = Vec::new();
+                            der::write_tlv(der::Tag::Integer, r, &mut integers);
+                            der::write_tlv(der::Tag::Integer, s, &mut integers);
+                            let mut signature = Vec::new();
+                            der::write_tlv(der::Tag::Sequence, &integers,
+                                           &mut signature);
+                            Ok(signature)
+                        }),
+                }
+            })
+        })
+    }
+
+    /// Asks the agent to sign `msg` with the Ed25519 key `identity`.
+    pub fn sign_ed25519(&mut self, identity: &Identity, msg: &[u8])
+                        -> Result<signature::Signature, error::Unspecified> {
+        let signature = try!(self.sign(identity, &ED25519, msg));
+        let signature =
+            try!(slice_as_array_ref!(&signature, ED25519_SIGNATURE_LEN));
+        Ok(signature::Signature::new(*signature))
+    }
+
+    // Sends a request and returns the contents of the response, which must
+    // have the type `expected_response_type`.
+    fn request(&mut self, request_type: u8, contents: &[u8],
+               expected_response_type: u8)
+               -> Result<Vec<u8>, error::Unspecified> {
+        let mut request = Vec::with_capacity(4 + 1 + contents.len());
+        write_u32((1 + contents.len()) as u32, &mut request);
+        request.push(request_type);
+        request.extend_from_slice(contents);
+        try!(self.stream.write_all(&request).map_err(|_| error::Unspecified));
+        try!(self.stream.flush().map_err(|_| error::Unspecified));
+
+        let mut len = [0u8; 4];
+        try!(self.stream.read_exact(&mut len).map_err(|_| error::Unspecified));
+        let len = try!(untrusted::Input::from(&len)
+                           .read_all(error::Unspecified, read_u32)) as usize;
+        if len < 1 || len > MAX_MESSAGE_LEN {
+            return Err(error::Unspecified);
+        }
+        let mut response = vec![0u8; len];
+        try!(self.stream.read_exact(&mut response)
+                 .map_err(|_| error::Unspecified));
+        // `SSH_AGENT_FAILURE`, or any other unexpected response, is an error.
+        if response[0] != expected_response_type {
+            return Err(error::Unspecified);
+        }
+        let _ = response.remove(0);
+        Ok(response)
+    }
+}
+
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+
+const SSH_AGENT_RSA_SHA2_256: u32 = 2;
+const SSH_AGENT_RSA_SHA2_512: u32 = 4;
+
+// The limit that OpenSSH's agent imposes on messages.
+const MAX_MESSAGE_LEN: usize = 256 * 1024;
+
+const ED25519_SIGNATURE_LEN: usize = 64;
+
+fn read_u32(input: &mut untrusted::Reader) -> Result<u32, error::Unspecified> {
+    let mut value = 0u32;
+    for _ in 0..4 {
+        value = (value << 8) | (try!(input.read_byte()) as u32);
+    }
+    Ok(value)
+}
+
+fn read_string<'a>(input: &mut untrusted::Reader<'a>)
+                   -> Result<untrusted::Input<'a>, error::Unspecified> {
+    let len = try!(read_u32(input));
+    input.skip_and_get_input(len as usize).map_err(|_| error::Unspecified)
+}
+
+// Reads a positive mpint, returning its big-endian two's complement
+// encoding, which is also the contents of the equivalent DER INTEGER.
+fn read_mpint<'a>(input: &mut untrusted::Reader<'a>)
+                  -> Result<&'a [u8], error::Unspecified> {
+    let value = try!(read_string(input)).as_slice_less_safe();
+    // Zero, negative values, and non-minimal encodings are rejected.
+    if value.is_empty() || (value[0] & 0x80) != 0 ||
+       (value[0] == 0 && (value.len() == 1 || (value[1] & 0x80) == 0)) {
+        return Err(error::Unspecified);
+    }
+    Ok(value)
+}
+
+fn write_u32(value: u32, out: &mut Vec<u8>) {
+    out.extend_from_slice(&[(value >> 24) as u8, (value >> 16) as u8,
+                            (value >> 8) as u8, value as u8]);
+}
+
+fn write_string(value: &[u8], out: &mut Vec<u8>) {
+    write_u32(value.len() as u32, out);
+    out.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use {signature, test};
+    use std::io;
+    use std::vec::Vec;
+    use super::*;
+    use untrusted;
+
+    // A stream that replays recorded responses and records the requests.
+    struct Transcript {
+        responses: io::Cursor<Vec<u8>>,
+        requests: Vec<u8>,
+    }
+
+    impl io::Read for Transcript {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.responses.read(buf)
+        }
+    }
+
+    impl io::Write for Transcript {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.requests.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> { Ok(()) }
+    }
+
+    #[test]
+    fn test_ssh_agent() {
+        test::from_file("src/ssh_agent_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+
+            let algorithm_name = test_case.consume_string("Algorithm");
+            let comment = test_case.consume_bytes("Comment");
+            let public_key = test_case.consume_bytes("PublicKey");
+            let msg = test_case.consume_bytes("Message");
+            let requests = test_case.consume_bytes("Requests");
+            let responses = test_case.consume_bytes("Responses");
+            let result = test_case.consume_string("Result");
+
+            let (algorithm, verification_algorithm):
+                    (_, &signature::VerificationAlgorithm) =
+                match algorithm_name.as_str() {
+                    "ED25519" => (&ED25519, &signature::ED25519),
+                    "RSA_PKCS1_SHA256" =>
+                        (&RSA_PKCS1_SHA256,
+                         &signature::RSA_PKCS1_2048_8192_SHA256),
+                    "RSA_PKCS1_SHA512" =>
+                        (&RSA_PKCS1_SHA512,
+                         &signature::RSA_PKCS1_2048_8192_SHA512),
+                    "ECDSA_P256_SHA256_ASN1" =>
+                        (&ECDSA_P256_SHA256_ASN1,
+                         &signature::ECDSA_P256_SHA256_ASN1),
+                    "ECDSA_P384_SHA384_ASN1" =>
+                        (&ECDSA_P384_SHA384_ASN1,
+                         &signature::ECDSA_P384_SHA384_ASN1),
+                    _ => unreachable!(),
+                };
+
+            let mut client = Client::new(Transcript {
+                responses: io::Cursor::new(responses),
+                requests: Vec::new(),
+            });
+            let identities = client.identities().unwrap();
+            assert_eq!(identities.len(), 1);
+            let identity = &identities[0];
+            assert_eq!(identity.comment(), &comment[..]);
+            assert_eq!(identity.public_key().unwrap(), public_key);
+
+            let sig = if algorithm_name == "ED25519" {
+                client.sign_ed25519(identity, &msg)
+                      .map(|sig| Vec::from(sig.as_slice()))
+            } else {
+                client.sign(identity, algorithm, &msg)
+            };
+            assert_eq!(client.into_inner().requests, requests);
+
+            match sig {
+                Ok(sig) => {
+                    assert_eq!(result, "OK");
+                    assert!(signature::verify(verification_algorithm,
+                                              untrusted::Input::from(
+                                                  &public_key),
+                                              untrusted::Input::from(&msg),
+                                              untrusted::Input::from(&sig))
+                                .is_ok());
+                },
+                Err(_) => { assert_eq!(result, "Error"); },
+            }
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_ssh_agent_bad_responses() {
+        const IDENTITIES_REQUEST: &'static [u8] = &[0, 0, 0, 1, 11];
+        let bad_responses: [&[u8]; 6] = [
+            // The stream ends early.
+            &[0, 0, 0, 5, 12, 0, 0, 0],
+            // An empty message.
+            &[0, 0, 0, 0],
+            // An overlong message.
+            &[0, 4, 0, 1, 12],
+            // `SSH_AGENT_FAILURE`.
+            &[0, 0, 0, 1, 5],
+            // Trailing data after the identities.
+            &[0, 0, 0, 6, 12, 0, 0, 0, 0, 0],
+            // A key blob without a key type.
+            &[0, 0, 0, 15, 12, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0],
+        ];
+        for response in bad_responses.iter() {
+            let mut client = Client::new(Transcript {
+                responses: io::Cursor::new(Vec::from(*response)),
+                requests: Vec::new(),
+            });
+            assert!(client.identities().is_err());
+            assert_eq!(client.into_inner().requests, IDENTITIES_REQUEST);
+        }
+    }
+}
@@ -130,11 +130,11 @@ pub struct TestCase {
 }
 
 impl TestCase {
-    /// Maps the strings "SHA1", "SHA256", "SHA384", and "SHA512" to digest
-    /// algorithms, maps "SHA224" to `None`, and panics on other (erroneous)
-    /// inputs. "SHA224" is mapped to None because *ring* intentionally does
-    /// not support SHA224, but we need to consume test vectors from NIST that
-    /// have SHA224 vectors in them.
+    /// Maps the name of a digest algorithm's static in `ring::digest`, e.g.
+    /// "SHA256" or "SHA3_256", to the digest algorithm, maps "SHA224" to
+    /// `None`, and panics on other (erroneous) inputs. "SHA224" is mapped to
+    /// None because *ring* intentionally does not support SHA224, but we need
+    /// to consume test vectors from NIST that have SHA224 vectors in them.
     pub fn consume_digest_alg(&mut self, key: &str)
                               -> Option<&'static digest::Algorithm> {
         let name = self.consume_string(key);
@@ -144,6 +144,14 @@ impl TestCase {
             "SHA256" => Some(&digest::SHA256),
             "SHA384" => Some(&digest::SHA384),
             "SHA512" => Some(&digest::SHA512),
+            "SHA512_224" => Some(&digest::SHA512_224),
+            "SHA512_256" => Some(&digest::SHA512_256),
+            "SHA3_256" => Some(&digest::SHA3_256),
+            "SHA3_384" => Some(&digest::SHA3_384),
+            "SHA3_512" => Some(&digest::SHA3_512),
+            "BLAKE2B_256" => Some(&digest::BLAKE2B_256),
+            "BLAKE2B_512" => Some(&digest::BLAKE2B_512),
+            "BLAKE2S_256" => Some(&digest::BLAKE2S_256),
             _ => panic!("Unsupported digest algorithm: {}", name),
         }
     }
@@ -0,0 +1,742 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Threshold Schnorr signatures: FROST(Ed25519, SHA-512).
+//!
+//! This implements the two-round FROST protocol from [RFC 9591] with the
+//! FROST(Ed25519, SHA-512) ciphersuite. Any `min_signers` of the
+//! `max_signers` holders of a key share can cooperate to produce a signature
+//! that is an ordinary Ed25519 signature; it can be verified with
+//! `ring::signature::ED25519` using the group public key, and nobody ever
+//! holds the whole private key.
+//!
+//! A signing operation works like this:
+//!
+//! 1. Each participating signer calls `SigningNonces::generate` and sends the
+//!    resulting `SigningCommitments` to the coordinator.
+//! 2. The coordinator sends the message and the list of all the participants'
+//!    commitments, sorted by identifier, to each participant.
+//! 3. Each participant calls `sign` and sends the resulting `SignatureShare`
+//!    to the coordinator.
+//! 4. The coordinator calls `aggregate` to produce the signature. If that
+//!    fails, `verify_signature_share` identifies the misbehaving participants.
+//!
+//! The nonces generated in the first round must be used at most once; `sign`
+//! consumes them to help enforce that.
+//!
+//! Key shares can be generated with `TrustedDealer`, which must be run by a
+//! trusted party that deletes its state after distributing the shares.
+//!
+//! Threshold ECDSA is not provided. The scalar arithmetic that it needs is
+//! available for P-256 and P-384 in `ring::scalar`.
+//!
+//! [RFC 9591]: https://tools.ietf.org/html/rfc9591
+
+use {digest, error, init, rand, signature};
+use ec::edwards25519::{Point, Scalar, ELEM_LEN, SCALAR_LEN};
+use untrusted;
+
+/// The maximum number of signers supported by `TrustedDealer`.
+pub const MAX_SIGNERS: usize = 64;
+
+/// The length of an encoded public key, verifying share, or commitment.
+pub const ELEMENT_LEN: usize = ELEM_LEN;
+
+/// The length of an encoded signing share or signature share.
+pub const SHARE_LEN: usize = SCALAR_LEN;
+
+const CONTEXT_STRING: &'static [u8] = b"FROST-ED25519-SHA512-v1";
+
+/// A participant's identifier, which must be non-zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Identifier(u16);
+
+impl Identifier {
+    /// Constructs an identifier. Fails if `value` is zero.
+    pub fn new(value: u16) -> Result<Identifier, error::Unspecified> {
+        if value == 0 {
+            return Err(error::Unspecified);
+        }
+        Ok(Identifier(value))
+    }
+
+    /// The numeric value of the identifier.
+    #[inline]
+    pub fn value(&self) -> u16 { self.0 }
+
+    #[inline]
+    fn to_scalar(&self) -> Scalar { Scalar::from_u16(self.0) }
+}
+
+/// A trusted dealer that splits a newly-generated private key into shares.
+///
+/// C analog: `trusted_dealer_keygen` in RFC 9591 Appendix C.
+pub struct TrustedDealer {
+    // The coefficients of the secret sharing polynomial, constant term first.
+    // The constant term is the group's private key.
+    coefficients: [Scalar; MAX_SIGNERS],
+    min_signers: usize,
+    max_signers: usize,
+    group_public_key: Point,
+}
+
+impl TrustedDealer {
+    /// Generates a new group private key to be split into `max_signers`
+    /// shares, any `min_signers` of which can sign.
+    ///
+    /// `min_signers` must be at least 2 and no more than `max_signers`, and
+    /// `max_signers` must be no more than `MAX_SIGNERS`.
+    pub fn generate(min_signers: usize, max_signers: usize,
+                    rng: &rand::SecureRandom)
+                    -> Result<TrustedDealer, error::Unspecified> {
+        init::init_once();
+        if min_signers < 2 || min_signers > max_signers ||
+           max_signers > MAX_SIGNERS {
+            return Err(error::Unspecified);
+        }
+        let mut coefficients = [Scalar::zero(); MAX_SIGNERS];
+        for coefficient in &mut coefficients[..min_signers] {
+            *coefficient = try!(Scalar::generate(rng));
+        }
+        if coefficients[0].is_zero() {
+            return Err(error::Unspecified);
+        }
+        Ok(TrustedDealer {
+            coefficients: coefficients,
+            min_signers: min_signers,
+            max_signers: max_signers,
+            group_public_key: Point::from_base_mul(&coefficients[0]),
+        })
+    }
+
+    /// The group public key, which is an Ed25519 public key.
+    #[inline]
+    pub fn group_public_key(&self) -> &[u8] { self.group_public_key.as_bytes() }
+
+    /// Computes the key package for the participant `identifier`, which must
+    /// be no greater than `max_signers`.
+    pub fn key_package(&self, identifier: Identifier)
+                       -> Result<KeyPackage, error::Unspecified> {
+        if identifier.value() as usize > self.max_signers {
+            return Err(error::Unspecified);
+        }
+        // Horner's method.
+        let x = identifier.to_scalar();
+        let mut signing_share = Scalar::zero();
+        for coefficient in self.coefficients[..self.min_signers].iter().rev() {
+            signing_share = signing_share.mul_add(&x, coefficient);
+        }
+        Ok(KeyPackage::new(identifier, self.min_signers, signing_share,
+                           self.group_public_key))
+    }
+}
+
+/// A participant's share of the group's private key, along with the public
+/// values needed to sign.
+pub struct KeyPackage {
+    identifier: Identifier,
+    min_signers: usize,
+    signing_share: Scalar,
+    verifying_share: Point,
+    group_public_key: Point,
+}
+
+impl KeyPackage {
+    /// Reconstructs a key package from its serialized parts.
+    ///
+    /// `signing_share` must be a `SHARE_LEN`-byte encoding of a non-zero
+    /// scalar and `group_public_key` must be a valid Ed25519 public key. The
+    /// share is not (and cannot be) checked for consistency with the group
+    /// public key; a bad share is detected only when its signature shares
+    /// fail `verify_signature_share`.
+    pub fn from_bytes(identifier: Identifier, min_signers: usize,
+                      signing_share: untrusted::Input,
+                      group_public_key: untrusted::Input)
+                      -> Result<KeyPackage, error::Unspecified> {
+        init::init_once();
+        if min_signers < 2 {
+            return Err(error::Unspecified);
+        }
+        let signing_share = try!(Scalar::from_bytes(signing_share));
+        if signing_share.is_zero() {
+            return Err(error::Unspecified);
+        }
+        let group_public_key = try!(Point::from_bytes(group_public_key));
+        Ok(KeyPackage::new(identifier, min_signers, signing_share,
+                           group_public_key))
+    }
+
+    fn new(identifier: Identifier, min_signers: usize, signing_share: Scalar,
+           group_public_key: Point) -> KeyPackage {
+        KeyPackage {
+            identifier: identifier,
+            min_signers: min_signers,
+            signing_share: signing_share,
+            verifying_share: Point::from_base_mul(&signing_share),
+            group_public_key: group_public_key,
+        }
+    }
+
+    /// The participant's identifier.
+    #[inline]
+    pub fn identifier(&self) -> Identifier { self.identifier }
+
+    /// The minimum number of signers needed to produce a signature.
+    #[inline]
+    pub fn min_signers(&self) -> usize { self.min_signers }
+
+    /// The participant's secret signing share, for serialization. It must be
+    /// kept as secret as a private key.
+    #[inline]
+    pub fn signing_share_bytes(&self) -> &[u8] { self.signing_share.as_bytes() }
+
+    /// The participant's public verifying share, which the coordinator needs
+    /// for `verify_signature_share`.
+    #[inline]
+    pub fn verifying_share(&self) -> &[u8] { self.verifying_share.as_bytes() }
+
+    /// The group public key, which is an Ed25519 public key.
+    #[inline]
+    pub fn group_public_key(&self) -> &[u8] { self.group_public_key.as_bytes() }
+}
+
+/// A participant's secret nonces for one signing operation.
+///
+/// C analog: the `nonce` output of `commit` in RFC 9591 Section 5.1.
+pub struct SigningNonces {
+    hiding: Scalar,
+    binding: Scalar,
+    commitments: SigningCommitments,
+}
+
+impl SigningNonces {
+    /// Generates the nonces and commitments for the first round of signing.
+    pub fn generate(key_package: &KeyPackage, rng: &rand::SecureRandom)
+                    -> Result<SigningNonces, error::Unspecified> {
+        let hiding = try!(nonce_generate(&key_package.signing_share, rng));
+        let binding = try!(nonce_generate(&key_package.signing_share, rng));
+        Ok(SigningNonces {
+            hiding: hiding,
+            binding: binding,
+            commitments: SigningCommitments {
+                identifier: key_package.identifier,
+                hiding: Point::from_base_mul(&hiding),
+                binding: Point::from_base_mul(&binding),
+            },
+        })
+    }
+
+    /// The public commitments to the nonces, to be sent to the coordinator.
+    #[inline]
+    pub fn commitments(&self) -> &SigningCommitments { &self.commitments }
+}
+
+// RFC 9591 Section 4.1.
+fn nonce_generate(secret: &Scalar, rng: &rand::SecureRandom)
+                  -> Result<Scalar, error::Unspecified> {
+    let mut random_bytes = [0u8; 32];
+    try!(rng.fill(&mut random_bytes));
+    let mut ctx = h_context(b"nonce");
+    ctx.update(&random_bytes);
+    ctx.update(secret.as_bytes());
+    Ok(Scalar::from_sha512_digest(&ctx.finish()))
+}
+
+/// A participant's public commitments to its nonces for one signing
+/// operation.
+#[derive(Clone)]
+pub struct SigningCommitments {
+    identifier: Identifier,
+    hiding: Point,
+    binding: Point,
+}
+
+impl SigningCommitments {
+    /// Parses the commitments received from participant `identifier`. Each
+    /// commitment must be a valid `ELEMENT_LEN`-byte encoded point other than
+    /// the identity.
+    pub fn from_bytes(identifier: Identifier, hiding: untrusted::Input,
+                      binding: untrusted::Input)
+                      -> Result<SigningCommitments, error::Unspecified> {
+        init::init_once();
+        Ok(SigningCommitments {
+            identifier: identifier,
+            hiding: try!(Point::from_bytes(hiding)),
+            binding: try!(Point::from_bytes(binding)),
+        })
+    }
+
+    /// The identifier of the participant that made the commitments.
+    #[inline]
+    pub fn identifier(&self) -> Identifier { self.identifier }
+
+    /// The encoded hiding nonce commitment.
+    #[inline]
+    pub fn hiding(&self) -> &[u8] { self.hiding.as_bytes() }
+
+    /// The encoded binding nonce commitment.
+    #[inline]
+    pub fn binding(&self) -> &[u8] { self.binding.as_bytes() }
+}
+
+/// A participant's share of a signature.
+#[derive(Clone)]
+pub struct SignatureShare {
+    identifier: Identifier,
+    value: Scalar,
+}
+
+impl SignatureShare {
+    /// Parses the signature share received from participant `identifier`.
+    pub fn from_bytes(identifier: Identifier, value: untrusted::Input)
+                      -> Result<SignatureShare, error::Unspecified> {
+        init::init_once();
+        Ok(SignatureShare {
+            identifier: identifier,
+            value: try!(Scalar::from_bytes(value)),
+        })
+    }
+
+    /// The identifier of the participant that produced the share.
+    #[inline]
+    pub fn identifier(&self) -> Identifier { self.identifier }
+
+    /// The encoded value of the share.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] { self.value.as_bytes() }
+}
+
+/// Computes the participant's signature share of `msg` (the second round of
+/// signing).
+///
+/// `commitments` must contain the commitments of every participant, including
+/// this one, sorted by identifier with no duplicates, and must contain at
+/// least `key_package.min_signers()` entries.
+///
+/// C analog: `sign` in RFC 9591 Section 5.2.
+pub fn sign(key_package: &KeyPackage, nonces: SigningNonces, msg: &[u8],
+            commitments: &[SigningCommitments])
+            -> Result<SignatureShare, error::Unspecified> {
+    try!(check_commitment_list(commitments));
+    if commitments.len() < key_package.min_signers {
+        return Err(error::Unspecified);
+    }
+    let own = try!(find_commitments(commitments, key_package.identifier));
+    if own.hiding.as_bytes() != nonces.commitments.hiding.as_bytes() ||
+       own.binding.as_bytes() != nonces.commitments.binding.as_bytes() {
+        return Err(error::Unspecified);
+    }
+
+    let binding_prefix =
+        binding_factor_prefix(&key_package.group_public_key, commitments, msg);
+    let binding_factor =
+        binding_factor(&binding_prefix, key_package.identifier);
+    let group_commitment = try!(group_commitment(&binding_prefix, commitments));
+    let challenge = challenge(&group_commitment,
+                              &key_package.group_public_key, msg);
+    let lambda = lagrange_coefficient(commitments, key_package.identifier);
+
+    // hiding + (binding * binding_factor) + (lambda * signing_share * c).
+    let value =
+        nonces.binding.mul_add(&binding_factor, &nonces.hiding)
+            .sum(&lambda.product(&key_package.signing_share)
+                        .product(&challenge));
+    Ok(SignatureShare {
+        identifier: key_package.identifier,
+        value: value,
+    })
+}
+
+/// Verifies the signature share `share` from the participant with the public
+/// verifying share `verifying_share`.
+///
+/// `commitments` must be the same list that was given to the participants.
+///
+/// C analog: `verify_signature_share` in RFC 9591 Section 5.4.
+pub fn verify_signature_share(verifying_share: untrusted::Input,
+                              group_public_key: untrusted::Input, msg: &[u8],
+                              commitments: &[SigningCommitments],
+                              share: &SignatureShare)
+                              -> Result<(), error::Unspecified> {
+    init::init_once();
+    let verifying_share = try!(Point::from_bytes(verifying_share));
+    let group_public_key = try!(Point::from_bytes(group_public_key));
+    try!(check_commitment_list(commitments));
+    let own = try!(find_commitments(commitments, share.identifier));
+
+    let binding_prefix =
+        binding_factor_prefix(&group_public_key, commitments, msg);
+    let binding_factor = binding_factor(&binding_prefix, share.identifier);
+    let group_commitment = try!(group_commitment(&binding_prefix, commitments));
+    let challenge = challenge(&group_commitment, &group_public_key, msg);
+    let lambda = lagrange_coefficient(commitments, share.identifier);
+
+    // share * B == hiding + (binding * binding_factor) +
+    //              (verifying_share * lambda * challenge).
+    let commitment_share =
+        try!(try!(own.binding.mul_add_base_vartime(&binding_factor,
+                                                   &Scalar::zero()))
+                 .sum_vartime(&own.hiding));
+    let expected =
+        try!(try!(verifying_share.mul_add_base_vartime(
+                      &lambda.product(&challenge), &Scalar::zero()))
+                 .sum_vartime(&commitment_share));
+    let actual = Point::from_base_mul(&share.value);
+    if actual.as_bytes() != expected.as_bytes() {
+        return Err(error::Unspecified);
+    }
+    Ok(())
+}
+
+/// Aggregates the signature shares into an Ed25519 signature of `msg`.
+///
+/// `commitments` must be the same list that was given to the participants,
+/// and `shares` must contain one share from each of those participants, in
+/// the same order. The resulting signature is verified before it is returned;
+/// if verification fails, use `verify_signature_share` to find the invalid
+/// shares.
+///
+/// C analog: `aggregate` in RFC 9591 Section 5.3.
+pub fn aggregate(group_public_key: untrusted::Input, msg: &[u8],
+                 commitments: &[SigningCommitments], shares: &[SignatureShare])
+                 -> Result<signature::Signature, error::Unspecified> {
+    init::init_once();
+    let group_public_key = try!(Point::from_bytes(group_public_key));
+    try!(check_commitment_list(commitments));
+    if shares.len() != commitments.len() {
+        return Err(error::Unspecified);
+    }
+
+    let binding_prefix =
+        binding_factor_prefix(&group_public_key, commitments, msg);
+    let group_commitment = try!(group_commitment(&binding_prefix, commitments));
+    let mut z = Scalar::zero();
+    for (share, commitment) in shares.iter().zip(commitments) {
+        if share.identifier != commitment.identifier {
+            return Err(error::Unspecified);
+        }
+        z = z.sum(&share.value);
+    }
+
+    let mut signature_bytes = [0u8; 64];
+    signature_bytes[..ELEM_LEN].copy_from_slice(group_commitment.as_bytes());
+    signature_bytes[ELEM_LEN..].copy_from_slice(z.as_bytes());
+    try!(signature::verify(&signature::ED25519,
+                           untrusted::Input::from(group_public_key.as_bytes()),
+                           untrusted::Input::from(msg),
+                           untrusted::Input::from(&signature_bytes)));
+    Ok(signature::Signature::new(signature_bytes))
+}
+
+fn check_commitment_list(commitments: &[SigningCommitments])
+                         -> Result<(), error::Unspecified> {
+    if commitments.is_empty() {
+        return Err(error::Unspecified);
+    }
+    for pair in commitments.windows(2) {
+        if pair[0].identifier >= pair[1].identifier {
+            return Err(error::Unspecified);
+        }
+    }
+    Ok(())
+}
+
+fn find_commitments(commitments: &[SigningCommitments],
+                    identifier: Identifier)
+                    -> Result<&SigningCommitments, error::Unspecified> {
+    commitments.iter()
+               .find(|c| c.identifier == identifier)
+               .ok_or(error::Unspecified)
+}
+
+// A SHA-512 context for one of H1, H3, H4, or H5.
+fn h_context(tag: &[u8]) -> digest::Context {
+    let mut ctx = digest::Context::new(&digest::SHA512);
+    ctx.update(CONTEXT_STRING);
+    ctx.update(tag);
+    ctx
+}
+
+const BINDING_FACTOR_PREFIX_LEN: usize = ELEM_LEN + (2 * 64);
+
+// group_public_key || H4(msg) || H5(encode_group_commitment_list(...)).
+fn binding_factor_prefix(group_public_key: &Point,
+                         commitments: &[SigningCommitments], msg: &[u8])
+                         -> [u8; BINDING_FACTOR_PREFIX_LEN] {
+    let mut prefix = [0u8; BINDING_FACTOR_PREFIX_LEN];
+    {
+        let (public_key_out, rest) = prefix.split_at_mut(ELEM_LEN);
+        let (msg_hash_out, commitment_hash_out) = rest.split_at_mut(64);
+        public_key_out.copy_from_slice(group_public_key.as_bytes());
+
+        let mut msg_ctx = h_context(b"msg");
+        msg_ctx.update(msg);
+        msg_hash_out.copy_from_slice(msg_ctx.finish().as_ref());
+
+        let mut com_ctx = h_context(b"com");
+        for c in commitments {
+            com_ctx.update(c.identifier.to_scalar().as_bytes());
+            com_ctx.update(c.hiding.as_bytes());
+            com_ctx.update(c.binding.as_bytes());
+        }
+        commitment_hash_out.copy_from_slice(com_ctx.finish().as_ref());
+    }
+    prefix
+}
+
+fn binding_factor(prefix: &[u8; BINDING_FACTOR_PREFIX_LEN],
+                  identifier: Identifier) -> Scalar {
+    let mut ctx = h_context(b"rho");
+    ctx.update(prefix);
+    ctx.update(identifier.to_scalar().as_bytes());
+    Scalar::from_sha512_digest(&ctx.finish())
+}
+
+fn group_commitment(binding_prefix: &[u8; BINDING_FACTOR_PREFIX_LEN],
+                    commitments: &[SigningCommitments])
+                    -> Result<Point, error::Unspecified> {
+    let mut r = Point::identity();
+    for c in commitments {
+        let binding_factor = binding_factor(binding_prefix, c.identifier);
+        let binding = try!(c.binding.mul_add_base_vartime(&binding_factor,
+                                                          &Scalar::zero()));
+        r = try!(try!(r.sum_vartime(&c.hiding)).sum_vartime(&binding));
+    }
+    Ok(r)
+}
+
+// H2, which is the same as the Ed25519 challenge computation.
+fn challenge(group_commitment: &Point, group_public_key: &Point, msg: &[u8])
+             -> Scalar {
+    let mut ctx = digest::Context::new(&digest::SHA512);
+    ctx.update(group_commitment.as_bytes());
+    ctx.update(group_public_key.as_bytes());
+    ctx.update(msg);
+    Scalar::from_sha512_digest(&ctx.finish())
+}
+
+// `derive_interpolating_value` from RFC 9591 Section 4.2, for the
+// participants in `commitments`, which must be distinct.
+fn lagrange_coefficient(commitments: &[SigningCommitments],
+                        identifier: Identifier) -> Scalar {
+    let x_i = identifier.to_scalar();
+    let mut numerator = Scalar::one();
+    let mut denominator = Scalar::one();
+    for c in commitments.iter().filter(|c| c.identifier != identifier) {
+        let x_j = c.identifier.to_scalar();
+        numerator = numerator.product(&x_j);
+        denominator = denominator.product(&x_j.difference(&x_i));
+    }
+    numerator.product(&denominator.inverse())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {rand, signature, test, threshold};
+    use core;
+    use std;
+    use super::nonce_generate;
+    use ec::edwards25519::Scalar;
+    use untrusted;
+
+    // FROST(Ed25519, SHA-512) from RFC 9591 Appendix E.1.
+    #[test]
+    fn frost_ed25519_rfc9591_test() {
+        test::from_file("src/threshold_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let group_public_key = test_case.consume_bytes("GroupPublicKey");
+            let min_signers = test_case.consume_usize("MinSigners");
+            let msg = test_case.consume_bytes("Message");
+            let identifiers = test_case.consume_bytes("Identifiers");
+            let mut key_packages = vec![];
+            let mut nonces = vec![];
+            for (i, id) in identifiers.iter().enumerate() {
+                let identifier = try!(threshold::Identifier::new(*id as u16));
+                let share =
+                    test_case.consume_bytes(&format!("SigningShare{}", i));
+                let key_package = try!(threshold::KeyPackage::from_bytes(
+                    identifier, min_signers, untrusted::Input::from(&share),
+                    untrusted::Input::from(&group_public_key)));
+                let hiding_randomness =
+                    test_case.consume_bytes(&format!("HidingRandomness{}", i));
+                let binding_randomness =
+                    test_case.consume_bytes(&format!("BindingRandomness{}", i));
+                let hiding = test_case.consume_bytes(&format!("Hiding{}", i));
+                let binding = test_case.consume_bytes(&format!("Binding{}", i));
+
+                let randomness = [&hiding_randomness[..],
+                                  &binding_randomness[..]];
+                let rng = rand::test_util::FixedSliceSequenceRandom {
+                    bytes: &randomness,
+                    current: core::cell::UnsafeCell::new(0),
+                };
+                let n = try!(threshold::SigningNonces::generate(&key_package,
+                                                                &rng));
+                assert_eq!(n.commitments().hiding(), &hiding[..]);
+                assert_eq!(n.commitments().binding(), &binding[..]);
+                key_packages.push(key_package);
+                nonces.push(n);
+            }
+            let commitments: std::vec::Vec<threshold::SigningCommitments> =
+                nonces.iter().map(|n| n.commitments().clone()).collect();
+
+            let mut shares = vec![];
+            for (i, (key_package, n)) in
+                    key_packages.iter().zip(nonces).enumerate() {
+                let expected =
+                    test_case.consume_bytes(&format!("SignatureShare{}", i));
+                let share =
+                    try!(threshold::sign(key_package, n, &msg, &commitments));
+                assert_eq!(share.as_bytes(), &expected[..]);
+                assert!(threshold::verify_signature_share(
+                            untrusted::Input::from(
+                                key_package.verifying_share()),
+                            untrusted::Input::from(&group_public_key), &msg,
+                            &commitments, &share).is_ok());
+                shares.push(share);
+            }
+
+            let expected = test_case.consume_bytes("Signature");
+            let sig = try!(threshold::aggregate(
+                untrusted::Input::from(&group_public_key), &msg, &commitments,
+                &shares));
+            assert_eq!(sig.as_slice(), &expected[..]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn frost_ed25519_dealer_test() {
+        let rng = rand::SystemRandom::new();
+        let dealer = threshold::TrustedDealer::generate(2, 3, &rng).unwrap();
+        let msg = b"threshold";
+        let ids = [threshold::Identifier::new(1).unwrap(),
+                   threshold::Identifier::new(2).unwrap(),
+                   threshold::Identifier::new(3).unwrap()];
+        assert!(dealer.key_package(threshold::Identifier::new(4).unwrap())
+                      .is_err());
+
+        // Every pair of signers can sign.
+        for &(a, b) in &[(0, 1), (0, 2), (1, 2)] {
+            let ka = dealer.key_package(ids[a]).unwrap();
+            let kb = dealer.key_package(ids[b]).unwrap();
+            let na = threshold::SigningNonces::generate(&ka, &rng).unwrap();
+            let nb = threshold::SigningNonces::generate(&kb, &rng).unwrap();
+            let commitments = [na.commitments().clone(),
+                               nb.commitments().clone()];
+            let sa = threshold::sign(&ka, na, msg, &commitments).unwrap();
+            let sb = threshold::sign(&kb, nb, msg, &commitments).unwrap();
+            let pk = untrusted::Input::from(dealer.group_public_key());
+            let sig = threshold::aggregate(pk, msg, &commitments,
+                                           &[sa.clone(), sb.clone()])
+                .unwrap();
+            assert!(signature::verify(&signature::ED25519, pk,
+                                      untrusted::Input::from(msg),
+                                      untrusted::Input::from(sig.as_slice()))
+                        .is_ok());
+
+            // Shares must be in the same order as the commitments.
+            assert!(threshold::aggregate(pk, msg, &commitments,
+                                         &[sb.clone(), sa.clone()]).is_err());
+
+            // A corrupted share is detected, and attributed to its signer.
+            let mut bad_value = [0u8; threshold::SHARE_LEN];
+            bad_value.copy_from_slice(sb.as_bytes());
+            bad_value[0] ^= 1;
+            let bad = threshold::SignatureShare::from_bytes(
+                kb.identifier(), untrusted::Input::from(&bad_value)).unwrap();
+            assert!(threshold::aggregate(pk, msg, &commitments,
+                                         &[sa.clone(), bad.clone()]).is_err());
+            assert!(threshold::verify_signature_share(
+                        untrusted::Input::from(ka.verifying_share()), pk, msg,
+                        &commitments, &sa).is_ok());
+            assert!(threshold::verify_signature_share(
+                        untrusted::Input::from(kb.verifying_share()), pk, msg,
+                        &commitments, &bad).is_err());
+        }
+
+        // One signer alone is not enough.
+        let ka = dealer.key_package(ids[0]).unwrap();
+        let na = threshold::SigningNonces::generate(&ka, &rng).unwrap();
+        let commitments = [na.commitments().clone()];
+        assert!(threshold::sign(&ka, na, msg, &commitments).is_err());
+    }
+
+    #[test]
+    fn frost_ed25519_commitment_list_test() {
+        let rng = rand::SystemRandom::new();
+        let dealer = threshold::TrustedDealer::generate(2, 3, &rng).unwrap();
+        let k1 = dealer.key_package(threshold::Identifier::new(1).unwrap())
+                       .unwrap();
+        let k2 = dealer.key_package(threshold::Identifier::new(2).unwrap())
+                       .unwrap();
+        let k3 = dealer.key_package(threshold::Identifier::new(3).unwrap())
+                       .unwrap();
+        let n1 = threshold::SigningNonces::generate(&k1, &rng).unwrap();
+        let n2 = threshold::SigningNonces::generate(&k2, &rng).unwrap();
+        let n3 = threshold::SigningNonces::generate(&k3, &rng).unwrap();
+
+        // Unsorted.
+        let unsorted = [n2.commitments().clone(), n1.commitments().clone()];
+        assert!(threshold::sign(&k1, n1, b"", &unsorted).is_err());
+
+        // Duplicated.
+        let duplicated = [n2.commitments().clone(), n2.commitments().clone()];
+        assert!(threshold::sign(&k2, n2, b"", &duplicated).is_err());
+
+        // Missing the signer's own commitments.
+        let n1 = threshold::SigningNonces::generate(&k1, &rng).unwrap();
+        let n2 = threshold::SigningNonces::generate(&k2, &rng).unwrap();
+        let missing = [n1.commitments().clone(), n2.commitments().clone()];
+        assert!(threshold::sign(&k3, n3, b"", &missing).is_err());
+    }
+
+    #[test]
+    fn frost_ed25519_bad_parameters_test() {
+        let rng = rand::SystemRandom::new();
+        assert!(threshold::Identifier::new(0).is_err());
+        assert!(threshold::TrustedDealer::generate(1, 3, &rng).is_err());
+        assert!(threshold::TrustedDealer::generate(4, 3, &rng).is_err());
+        assert!(threshold::TrustedDealer::generate(
+                    2, threshold::MAX_SIGNERS + 1, &rng).is_err());
+        assert!(threshold::TrustedDealer::generate(
+                    threshold::MAX_SIGNERS, threshold::MAX_SIGNERS, &rng)
+                .is_ok());
+
+        // The identity isn't a valid commitment.
+        let mut identity = [0u8; threshold::ELEMENT_LEN];
+        identity[0] = 1;
+        let dealer = threshold::TrustedDealer::generate(2, 2, &rng).unwrap();
+        let id = threshold::Identifier::new(1).unwrap();
+        assert!(threshold::SigningCommitments::from_bytes(
+                    id, untrusted::Input::from(&identity),
+                    untrusted::Input::from(dealer.group_public_key()))
+                .is_err());
+
+        // A zero signing share is rejected.
+        let zero = [0u8; threshold::SHARE_LEN];
+        assert!(threshold::KeyPackage::from_bytes(
+                    id, 2, untrusted::Input::from(&zero),
+                    untrusted::Input::from(dealer.group_public_key()))
+                .is_err());
+
+        // Nonces are derived from both the randomness and the secret.
+        let rng = rand::test_util::FixedByteRandom { byte: 0 };
+        let a = nonce_generate(&Scalar::from_u16(1), &rng).unwrap();
+        let b = nonce_generate(&Scalar::from_u16(2), &rng).unwrap();
+        assert!(a.as_bytes() != b.as_bytes());
+    }
+}
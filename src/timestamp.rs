@@ -0,0 +1,581 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Verification of [RFC 3161] time-stamp tokens.
+//!
+//! A time-stamp authority (TSA) attests that some data existed at a certain
+//! time by signing a `TSTInfo` that contains the time and a digest of the
+//! data, the *message imprint*. `request` constructs the `TimeStampReq` to
+//! send to the TSA. `TimeStampToken::from_der` parses the `TimeStampToken` in
+//! the TSA's response and `TimeStampToken::verify` verifies its signature,
+//! returning the signed `TSTInfo`. Finally, `TSTInfo::verify_message_imprint`
+//! checks that the token is for the data.
+//!
+//! Only the signature is verified. Finding and validating the TSA's
+//! certificate, including that it has the `id-kp-timeStamping` extended key
+//! usage and that it is the one identified by the token's
+//! `signingCertificate` attribute, is up to the caller; `verify` just takes
+//! the TSA's `SubjectPublicKeyInfo`, e.g. from `x509::Certificate::spki`.
+//!
+//! The token must be DER-encoded and have exactly one `SignerInfo`, which
+//! must have signed attributes. The signature algorithms are the ones that
+//! `ring::signature::verify_signed_data` supports, and `rsaEncryption`, which
+//! many TSAs use to mean RSA PKCS#1 1.5 with the `SignerInfo`'s digest
+//! algorithm. The digest algorithms must be SHA-256, SHA-384, or SHA-512.
+//!
+//! Feature: `use_heap`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # extern crate ring;
+//! # extern crate untrusted;
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::{digest, timestamp};
+//!
+//! # let (artifact, token_der, tsa_spki): (&[u8], &[u8], &[u8]) =
+//! #     (&[], &[], &[]);
+//! let token = try!(timestamp::TimeStampToken::from_der(
+//!     untrusted::Input::from(token_der)));
+//! let tst_info = try!(token.verify(untrusted::Input::from(tsa_spki)));
+//! try!(tst_info.verify_message_imprint(artifact));
+//! let gen_time = tst_info.gen_time(); // e.g. "20261016154401Z".
+//! # let _ = gen_time;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [RFC 3161]: https://tools.ietf.org/html/rfc3161
+
+use {der, digest, error, init, pbe, signature, signed_data};
+use constant_time;
+use std::vec::Vec;
+use untrusted;
+
+/// Returns the DER-encoded `MessageImprint` of `data`, using `digest_alg`,
+/// which must be SHA-256, SHA-384, or SHA-512.
+pub fn message_imprint(digest_alg: &'static digest::Algorithm, data: &[u8])
+                       -> Result<Vec<u8>, error::Unspecified> {
+    let alg = try!(find_digest_algorithm(digest_alg));
+    let mut value = Vec::new();
+    let mut algorithm_identifier = Vec::new();
+    der::write_tlv(der::Tag::OID, alg.oid, &mut algorithm_identifier);
+    der::write_tlv(der::Tag::Null, &[], &mut algorithm_identifier);
+    der::write_tlv(der::Tag::Sequence, &algorithm_identifier, &mut value);
+    der::write_tlv(der::Tag::OctetString,
+                   digest::digest(digest_alg, data).as_ref(), &mut value);
+    let mut result = Vec::new();
+    der::write_tlv(der::Tag::Sequence, &value, &mut result);
+    Ok(result)
+}
+
+/// Returns the DER-encoded `TimeStampReq` for `data`, with the message
+/// imprint computed as for `message_imprint`.
+///
+/// `nonce`, if any, is the big-endian encoding of a positive integer without
+/// leading zeros; it should be random, and `TSTInfo::nonce` should then be
+/// checked to be equal to it. If `cert_req` is true then the TSA is asked to
+/// include its certificate in the token.
+pub fn request(digest_alg: &'static digest::Algorithm, data: &[u8],
+               nonce: Option<&[u8]>, cert_req: bool)
+               -> Result<Vec<u8>, error::Unspecified> {
+    let mut value = Vec::new();
+    der::write_tlv(der::Tag::Integer, &[VERSION_1], &mut value);
+    value.extend_from_slice(&try!(message_imprint(digest_alg, data)));
+    if let Some(nonce) = nonce {
+        if nonce.first().map_or(true, |&b| b == 0) {
+            return Err(error::Unspecified);
+        }
+        der::write_positive_integer(nonce, &mut value);
+    }
+    if cert_req {
+        der::write_tlv(der::Tag::Boolean, &[0xff], &mut value);
+    }
+    let mut result = Vec::new();
+    der::write_tlv(der::Tag::Sequence, &value, &mut result);
+    Ok(result)
+}
+
+/// A parsed, but not yet verified, `TimeStampToken`.
+pub struct TimeStampToken<'a> {
+    tst_info: untrusted::Input<'a>,
+    digest_alg: &'static DigestAlgorithm,
+    signed_attrs: untrusted::Input<'a>,
+    signed_attrs_value: untrusted::Input<'a>,
+    signature_alg_id: untrusted::Input<'a>,
+    signature: untrusted::Input<'a>,
+}
+
+impl<'a> TimeStampToken<'a> {
+    /// Parses a DER-encoded `TimeStampToken`, i.e. a CMS `ContentInfo`
+    /// containing a `SignedData` of a `TSTInfo`.
+    pub fn from_der(input: untrusted::Input<'a>)
+                    -> Result<TimeStampToken<'a>, error::Unspecified> {
+        init::init_once();
+        input.read_all(error::Unspecified, |input| {
+            der::nested(input, der::Tag::Sequence, error::Unspecified,
+                        |input| {
+                let content_type =
+                    try!(der::expect_tag_and_get_value(input, der::Tag::OID));
+                if content_type.as_slice_less_safe() != ID_SIGNED_DATA {
+                    return Err(error::Unspecified);
+                }
+                der::nested(input, der::Tag::ContextSpecificConstructed0,
+                            error::Unspecified, |input| {
+                    der::nested(input, der::Tag::Sequence, error::Unspecified,
+                                signed_data)
+                })
+            })
+        })
+    }
+
+    /// Verifies the token's signature with `tsa_spki`, the TSA's DER-encoded
+    /// `SubjectPublicKeyInfo`, and returns the signed `TSTInfo`.
+    ///
+    /// The signed attributes must include the `contentType` attribute, with
+    /// the value `id-ct-TSTInfo`, and the `messageDigest` attribute, with the
+    /// digest of the `TSTInfo`.
+    pub fn verify(&self, tsa_spki: untrusted::Input)
+                  -> Result<TSTInfo<'a>, error::Unspecified> {
+        try!(self.signed_attrs_value.read_all(error::Unspecified, |input| {
+            signed_attrs(input, self.digest_alg.digest_alg, self.tst_info)
+        }));
+
+        let (public_key_alg_id, public_key) =
+            try!(signed_data::parse_spki(tsa_spki));
+        let signature_alg_id =
+            if self.signature_alg_id.as_slice_less_safe() ==
+                    signed_data::RSA_ENCRYPTION {
+                untrusted::Input::from(self.digest_alg.rsa_pkcs1_alg_id)
+            } else {
+                self.signature_alg_id
+            };
+        let alg = try!(signed_data::find_algorithm(public_key_alg_id,
+                                                   signature_alg_id));
+
+        // The signature is over the DER encoding of the signed attributes
+        // with the `SET OF` tag instead of the implicit `[0]` tag.
+        let mut signed = self.signed_attrs.as_slice_less_safe().to_vec();
+        signed[0] = der::Tag::Set as u8;
+        try!(signature::verify(alg, public_key, untrusted::Input::from(&signed),
+                               self.signature));
+
+        TSTInfo::from_der(self.tst_info)
+    }
+}
+
+fn signed_data<'a>(input: &mut untrusted::Reader<'a>)
+                   -> Result<TimeStampToken<'a>, error::Unspecified> {
+    if try!(der::small_nonnegative_integer(input)) != 3 {
+        return Err(error::Unspecified);
+    }
+    let _digest_algorithms =
+        try!(der::expect_tag_and_get_value(input, der::Tag::Set));
+    let tst_info = try!(der::nested(input, der::Tag::Sequence,
+                                    error::Unspecified, |input| {
+        let content_type =
+            try!(der::expect_tag_and_get_value(input, der::Tag::OID));
+        if content_type.as_slice_less_safe() != ID_CT_TST_INFO {
+            return Err(error::Unspecified);
+        }
+        der::nested(input, der::Tag::ContextSpecificConstructed0,
+                    error::Unspecified, |input| {
+            der::expect_tag_and_get_value(input, der::Tag::OctetString)
+        })
+    }));
+    if input.peek(der::Tag::ContextSpecificConstructed0 as u8) {
+        let _certificates = try!(der::expect_tag_and_get_value(
+            input, der::Tag::ContextSpecificConstructed0));
+    }
+    if input.peek(der::Tag::ContextSpecificConstructed1 as u8) {
+        let _crls = try!(der::expect_tag_and_get_value(
+            input, der::Tag::ContextSpecificConstructed1));
+    }
+    der::nested(input, der::Tag::Set, error::Unspecified, |input| {
+        der::nested(input, der::Tag::Sequence, error::Unspecified, |input| {
+            signer_info(input, tst_info)
+        })
+    })
+}
+
+fn signer_info<'a>(input: &mut untrusted::Reader<'a>,
+                   tst_info: untrusted::Input<'a>)
+                   -> Result<TimeStampToken<'a>, error::Unspecified> {
+    let version = try!(der::small_nonnegative_integer(input));
+    if version != 1 && version != 3 {
+        return Err(error::Unspecified);
+    }
+    // The `IssuerAndSerialNumber` or `[0] SubjectKeyIdentifier` identifying
+    // the TSA's certificate.
+    let (sid_tag, _) = try!(der::read_tag_and_get_value(input));
+    if sid_tag != der::Tag::Sequence as u8 &&
+       sid_tag != der::Tag::ContextSpecificPrimitive0 as u8 {
+        return Err(error::Unspecified);
+    }
+    let digest_alg = try!(digest_algorithm_identifier(input));
+    let (signed_attrs, signed_attrs_value) =
+        try!(der::read_tlv(input, der::Tag::ContextSpecificConstructed0));
+    let signature_alg_id =
+        try!(der::expect_tag_and_get_value(input, der::Tag::Sequence));
+    let signature =
+        try!(der::expect_tag_and_get_value(input, der::Tag::OctetString));
+    if input.peek(der::Tag::ContextSpecificConstructed1 as u8) {
+        let _unsigned_attrs = try!(der::expect_tag_and_get_value(
+            input, der::Tag::ContextSpecificConstructed1));
+    }
+    Ok(TimeStampToken {
+        tst_info: tst_info,
+        digest_alg: digest_alg,
+        signed_attrs: signed_attrs,
+        signed_attrs_value: signed_attrs_value,
+        signature_alg_id: signature_alg_id,
+        signature: signature,
+    })
+}
+
+// Checks the `contentType` and `messageDigest` signed attributes. Each must
+// occur exactly once, with exactly one value.
+fn signed_attrs(input: &mut untrusted::Reader,
+                digest_alg: &'static digest::Algorithm,
+                tst_info: untrusted::Input) -> Result<(), error::Unspecified> {
+    let mut content_type_found = false;
+    let mut message_digest_found = false;
+    while !input.at_end() {
+        try!(der::nested(input, der::Tag::Sequence, error::Unspecified,
+                         |input| {
+            let attr_type =
+                try!(der::expect_tag_and_get_value(input, der::Tag::OID));
+            let attr_type = attr_type.as_slice_less_safe();
+            let (found, tag) = if attr_type == CONTENT_TYPE {
+                (&mut content_type_found, der::Tag::OID)
+            } else if attr_type == MESSAGE_DIGEST {
+                (&mut message_digest_found, der::Tag::OctetString)
+            } else {
+                let _values =
+                    try!(der::expect_tag_and_get_value(input, der::Tag::Set));
+                return Ok(());
+            };
+            if *found {
+                return Err(error::Unspecified);
+            }
+            *found = true;
+            let value = try!(der::nested(input, der::Tag::Set,
+                                         error::Unspecified, |input| {
+                der::expect_tag_and_get_value(input, tag)
+            }));
+            if tag == der::Tag::OID {
+                if value.as_slice_less_safe() != ID_CT_TST_INFO {
+                    return Err(error::Unspecified);
+                }
+            } else {
+                let digest =
+                    digest::digest(digest_alg, tst_info.as_slice_less_safe());
+                try!(constant_time::verify_slices_are_equal(
+                    digest.as_ref(), value.as_slice_less_safe()));
+            }
+            Ok(())
+        }));
+    }
+    if !content_type_found || !message_digest_found {
+        return Err(error::Unspecified);
+    }
+    Ok(())
+}
+
+/// A `TSTInfo`, the signed contents of a `TimeStampToken`. All the accessors
+/// return values that borrow from the token.
+pub struct TSTInfo<'a> {
+    policy: untrusted::Input<'a>,
+    digest_alg: &'static digest::Algorithm,
+    hashed_message: untrusted::Input<'a>,
+    serial_number: untrusted::Input<'a>,
+    gen_time: untrusted::Input<'a>,
+    nonce: Option<untrusted::Input<'a>>,
+}
+
+impl<'a> TSTInfo<'a> {
+    fn from_der(input: untrusted::Input<'a>)
+                -> Result<TSTInfo<'a>, error::Unspecified> {
+        input.read_all(error::Unspecified, |input| {
+            der::nested(input, der::Tag::Sequence, error::Unspecified,
+                        |input| {
+                if try!(der::small_nonnegative_integer(input)) != VERSION_1 {
+                    return Err(error::Unspecified);
+                }
+                let policy =
+                    try!(der::expect_tag_and_get_value(input, der::Tag::OID));
+                let (digest_alg, hashed_message) =
+                    try!(der::nested(input, der::Tag::Sequence,
+                                     error::Unspecified, |input| {
+                        let alg = try!(digest_algorithm_identifier(input));
+                        let hashed_message = try!(der::expect_tag_and_get_value(
+                            input, der::Tag::OctetString));
+                        Ok((alg.digest_alg, hashed_message))
+                    }));
+                if hashed_message.len() != digest_alg.output_len {
+                    return Err(error::Unspecified);
+                }
+                let serial_number = try!(der::positive_integer(input));
+                let gen_time = try!(der::expect_tag_and_get_value(
+                    input, der::Tag::GeneralizedTime));
+                if input.peek(der::Tag::Sequence as u8) {
+                    let _accuracy = try!(der::expect_tag_and_get_value(
+                        input, der::Tag::Sequence));
+                }
+                if input.peek(der::Tag::Boolean as u8) {
+                    // `ordering` is `DEFAULT FALSE` so DER requires it to be
+                    // omitted unless it is true.
+                    let ordering = try!(der::expect_tag_and_get_value(
+                        input, der::Tag::Boolean));
+                    if ordering.as_slice_less_safe() != &[0xff][..] {
+                        return Err(error::Unspecified);
+                    }
+                }
+                let nonce = if input.peek(der::Tag::Integer as u8) {
+                    Some(try!(der::positive_integer(input)))
+                } else {
+                    None
+                };
+                if input.peek(der::Tag::ContextSpecificConstructed0 as u8) {
+                    let _tsa = try!(der::expect_tag_and_get_value(
+                        input, der::Tag::ContextSpecificConstructed0));
+                }
+                if input.peek(der::Tag::ContextSpecificConstructed1 as u8) {
+                    let _extensions = try!(der::expect_tag_and_get_value(
+                        input, der::Tag::ContextSpecificConstructed1));
+                }
+                Ok(TSTInfo {
+                    policy: policy,
+                    digest_alg: digest_alg,
+                    hashed_message: hashed_message,
+                    serial_number: serial_number,
+                    gen_time: gen_time,
+                    nonce: nonce,
+                })
+            })
+        })
+    }
+
+    /// Verifies that the message imprint is the digest of `data`.
+    pub fn verify_message_imprint(&self, data: &[u8])
+                                  -> Result<(), error::Unspecified> {
+        let digest = digest::digest(self.digest_alg, data);
+        constant_time::verify_slices_are_equal(
+            digest.as_ref(), self.hashed_message.as_slice_less_safe())
+    }
+
+    /// The contents of the TSA policy OID, without the tag and length.
+    pub fn policy(&self) -> untrusted::Input<'a> { self.policy }
+
+    /// The digest algorithm of the message imprint.
+    pub fn digest_algorithm(&self) -> &'static digest::Algorithm {
+        self.digest_alg
+    }
+
+    /// The serial number, big-endian encoded without leading zeros.
+    pub fn serial_number(&self) -> untrusted::Input<'a> { self.serial_number }
+
+    /// The time, as the contents of the `GeneralizedTime`, e.g.
+    /// `20261016154401Z`, or with fractional seconds.
+    pub fn gen_time(&self) -> untrusted::Input<'a> { self.gen_time }
+
+    /// The nonce from the request, if any, big-endian encoded without leading
+    /// zeros.
+    pub fn nonce(&self) -> Option<untrusted::Input<'a>> { self.nonce }
+}
+
+// A supported digest algorithm. The OID is the contents of the OID, without
+// the tag and length, and `rsa_pkcs1_alg_id` is the contents of the
+// corresponding PKCS#1 1.5 signature algorithm identifier.
+struct DigestAlgorithm {
+    oid: &'static [u8],
+    digest_alg: &'static digest::Algorithm,
+    rsa_pkcs1_alg_id: &'static [u8],
+}
+
+// Reads an `AlgorithmIdentifier` for a digest algorithm, with absent or NULL
+// parameters.
+fn digest_algorithm_identifier(input: &mut untrusted::Reader)
+                               -> Result<&'static DigestAlgorithm,
+                                         error::Unspecified> {
+    der::nested(input, der::Tag::Sequence, error::Unspecified, |input| {
+        let oid = try!(der::expect_tag_and_get_value(input, der::Tag::OID));
+        try!(pbe::optional_null(input));
+        DIGEST_ALGORITHMS.iter()
+            .find(|alg| alg.oid == oid.as_slice_less_safe())
+            .ok_or(error::Unspecified)
+    })
+}
+
+fn find_digest_algorithm(digest_alg: &'static digest::Algorithm)
+                         -> Result<&'static DigestAlgorithm,
+                                   error::Unspecified> {
+    DIGEST_ALGORITHMS.iter()
+        .find(|alg| alg.digest_alg.name() == digest_alg.name())
+        .ok_or(error::Unspecified)
+}
+
+const VERSION_1: u8 = 1;
+
+// id-sha256, id-sha384, and id-sha512 (2.16.840.1.101.3.4.2.{1,2,3}), with
+// sha{256,384,512}WithRSAEncryption (1.2.840.113549.1.1.{11,12,13}) with
+// NULL parameters.
+static DIGEST_ALGORITHMS: [DigestAlgorithm; 3] = [
+    DigestAlgorithm {
+        oid: &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01],
+        digest_alg: &digest::SHA256,
+        rsa_pkcs1_alg_id: &[
+            0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b,
+            0x05, 0x00,
+        ],
+    },
+    DigestAlgorithm {
+        oid: &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02],
+        digest_alg: &digest::SHA384,
+        rsa_pkcs1_alg_id: &[
+            0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0c,
+            0x05, 0x00,
+        ],
+    },
+    DigestAlgorithm {
+        oid: &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03],
+        digest_alg: &digest::SHA512,
+        rsa_pkcs1_alg_id: &[
+            0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0d,
+            0x05, 0x00,
+        ],
+    },
+];
+
+// id-signedData (1.2.840.113549.1.7.2).
+const ID_SIGNED_DATA: &'static [u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02,
+];
+
+// id-ct-TSTInfo (1.2.840.113549.1.9.16.1.4).
+const ID_CT_TST_INFO: &'static [u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x10, 0x01, 0x04,
+];
+
+// id-contentType (1.2.840.113549.1.9.3).
+const CONTENT_TYPE: &'static [u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x03,
+];
+
+// id-messageDigest (1.2.840.113549.1.9.4).
+const MESSAGE_DIGEST: &'static [u8] = &[
+    0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x04,
+];
+
+
+#[cfg(test)]
+mod tests {
+    use test;
+    use super::*;
+    use untrusted;
+
+    #[test]
+    fn test_timestamp() {
+        test::from_file("src/timestamp_tests.txt", |section, test_case| {
+            match section {
+                "Request" => test_request(test_case),
+                "Token" => test_token(test_case),
+                _ => panic!("Unexpected section: {}", section),
+            }
+            Ok(())
+        });
+    }
+
+    fn test_request(test_case: &mut test::TestCase) {
+        let digest_alg = test_case.consume_digest_alg("Digest").unwrap();
+        let data = test_case.consume_bytes("Data");
+        let nonce = test_case.consume_bytes("Nonce");
+        let cert_req = test_case.consume_string("CertReq") == "true";
+        let expected = test_case.consume_bytes("Request");
+
+        let nonce = if nonce.is_empty() { None } else { Some(&nonce[..]) };
+        let actual = request(digest_alg, &data, nonce, cert_req).unwrap();
+        assert_eq!(actual, expected);
+    }
+
+    fn test_token(test_case: &mut test::TestCase) {
+        let token_der = test_case.consume_bytes("Token");
+        let tsa_spki = test_case.consume_bytes("TSASPKI");
+        let data = test_case.consume_bytes("Data");
+        let digest_alg = test_case.consume_digest_alg("Digest").unwrap();
+        let policy = test_case.consume_bytes("Policy");
+        let serial_number = test_case.consume_bytes("SerialNumber");
+        let gen_time = test_case.consume_bytes("GenTime");
+        let nonce = test_case.consume_bytes("Nonce");
+
+        let token =
+            TimeStampToken::from_der(untrusted::Input::from(&token_der))
+                .unwrap();
+        let tst_info = token.verify(untrusted::Input::from(&tsa_spki))
+                            .unwrap();
+        assert!(tst_info.verify_message_imprint(&data).is_ok());
+        assert!(tst_info.verify_message_imprint(b"hello, world!").is_err());
+        assert_eq!(tst_info.digest_algorithm().name(), digest_alg.name());
+        assert_eq!(tst_info.policy().as_slice_less_safe(), &policy[..]);
+        assert_eq!(tst_info.serial_number().as_slice_less_safe(),
+                   &serial_number[..]);
+        assert_eq!(tst_info.gen_time().as_slice_less_safe(), &gen_time[..]);
+        match tst_info.nonce() {
+            Some(actual) => assert_eq!(actual.as_slice_less_safe(), &nonce[..]),
+            None => assert!(nonce.is_empty()),
+        }
+
+        // The wrong key is rejected.
+        let other_spki = {
+            let mut other_spki = tsa_spki.clone();
+            let last = other_spki.len() - 1;
+            other_spki[last] ^= 1;
+            other_spki
+        };
+        assert!(token.verify(untrusted::Input::from(&other_spki)).is_err());
+
+        // Modifying any byte of the `TSTInfo`, the signed attributes, or the
+        // signature is detected, either when parsing or when verifying.
+        let signed_ranges = [token.tst_info, token.signed_attrs,
+                             token.signature];
+        for range in signed_ranges.iter() {
+            let start = offset_in(&token_der, range.as_slice_less_safe());
+            for i in start..(start + range.len()) {
+                let mut modified = token_der.clone();
+                modified[i] ^= 1;
+                let result =
+                    TimeStampToken::from_der(untrusted::Input::from(&modified))
+                        .and_then(|token| {
+                            token.verify(untrusted::Input::from(&tsa_spki))
+                                 .map(|_| ())
+                        });
+                assert!(result.is_err());
+            }
+        }
+    }
+
+    fn offset_in(outer: &[u8], inner: &[u8]) -> usize {
+        inner.as_ptr() as usize - outer.as_ptr() as usize
+    }
+
+    #[test]
+    fn test_request_invalid() {
+        assert!(message_imprint(&digest::SHA1, b"data").is_err());
+        assert!(request(&digest::SHA1, b"data", None, false).is_err());
+        assert!(request(&digest::SHA256, b"data", Some(&[]), false).is_err());
+        assert!(request(&digest::SHA256, b"data", Some(&[0, 1]), false)
+                    .is_err());
+    }
+}
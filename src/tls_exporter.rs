@@ -0,0 +1,489 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! TLS 1.3 keying material exporters, and the signed structures of
+//! [Token Binding] and [Exported Authenticators] that are built on them.
+//!
+//! Everything here starts from a connection's exporter secret, which is
+//! `exporter_master_secret` in [RFC 8446 Section 7.1] and `EXPORTER_SECRET` in
+//! the key log format of most TLS implementations. Only TLS 1.3 is supported;
+//! TLS 1.2's exporter is built on its PRF instead.
+//!
+//! *ring* can't sign with RSA-PSS or ECDSA, so Token Bindings can only be
+//! signed with `rsa2048_pkcs1.5` keys, which requires the `rsa_signing`
+//! feature, and Exported Authenticators can only be signed with Ed25519 keys.
+//!
+//! # Examples
+//!
+//! A spontaneous Exported Authenticator from a server:
+//!
+//! ```
+//! # extern crate ring;
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::{digest, rand, signature, tls_exporter};
+//!
+//! let rng = rand::SystemRandom::new();
+//! # let exporter_secret = [0u8; 32];
+//! # let certificate = [0u8; 100];
+//! let key_pair = try!(signature::Ed25519KeyPair::generate(&rng));
+//!
+//! // The context must be unpredictable, for spontaneous authenticators.
+//! let mut context = [0u8; 32];
+//! try!(rng.fill(&mut context));
+//!
+//! let authenticator = try!(tls_exporter::exported_authenticator(
+//!     &digest::SHA256, &exporter_secret, tls_exporter::Role::Server, b"",
+//!     &context, &[&certificate], &key_pair));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [Token Binding]: https://tools.ietf.org/html/rfc8471
+//! [Exported Authenticators]: https://tools.ietf.org/html/rfc9261
+//! [RFC 8446 Section 7.1]: https://tools.ietf.org/html/rfc8446#section-7.1
+
+use {digest, error, hkdf, hmac, signature, tls_kdf};
+#[cfg(feature = "rsa_signing")]
+use {der, rand};
+use std::vec::Vec;
+use untrusted;
+
+/// Fills `out` with the TLS 1.3 exporter value for `label` and `context`,
+/// i.e. `TLS-Exporter(label, context, out.len())` from
+/// [RFC 8446 Section 7.5].
+///
+/// `digest_alg` is the hash of the connection's cipher suite. `label` may be
+/// at most 249 bytes and `out` may be at most 255 times the digest
+/// algorithm's output length.
+///
+/// [RFC 8446 Section 7.5]: https://tools.ietf.org/html/rfc8446#section-7.5
+pub fn export(digest_alg: &'static digest::Algorithm, exporter_secret: &[u8],
+              label: &[u8], context: &[u8], out: &mut [u8])
+              -> Result<(), error::Unspecified> {
+    let derived = try!(tls_kdf::derive_secret(
+        &hkdf::Prk::new(digest_alg, exporter_secret), label,
+        &digest::digest(digest_alg, b"")));
+    tls_kdf::hkdf_expand_label(&derived, b"exporter",
+                               digest::digest(digest_alg, context).as_ref(),
+                               out)
+}
+
+/// The length of the exported keying material that a Token Binding signs.
+pub const TOKEN_BINDING_EKM_LEN: usize = 32;
+
+/// Returns the exported keying material that Token Bindings on the connection
+/// sign, as specified in [RFC 8471 Section 3.3].
+///
+/// [RFC 8471 Section 3.3]: https://tools.ietf.org/html/rfc8471#section-3.3
+pub fn token_binding_ekm(digest_alg: &'static digest::Algorithm,
+                         exporter_secret: &[u8])
+                         -> Result<[u8; TOKEN_BINDING_EKM_LEN],
+                                   error::Unspecified> {
+    let mut ekm = [0u8; TOKEN_BINDING_EKM_LEN];
+    try!(export(digest_alg, exporter_secret, b"EXPORTER-Token-Binding", b"",
+                &mut ekm));
+    Ok(ekm)
+}
+
+/// A `TokenBindingType`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TokenBindingType {
+    /// `provided_token_binding`, for the server the client is connected to.
+    Provided,
+
+    /// `referred_token_binding`, for a server that the client is being
+    /// redirected to.
+    Referred,
+}
+
+// The `rsa2048_pkcs1.5` `TokenBindingKeyParameters`.
+#[cfg(feature = "rsa_signing")]
+const RSA2048_PKCS1_5: u8 = 0;
+
+/// Signs `ekm` with the key pair of `signing_state`, returning the encoded
+/// `TokenBinding`, which has no extensions. Feature: `rsa_signing`.
+///
+/// `ekm` is the return value of `token_binding_ekm`. The key pair must have
+/// a 2048-bit modulus. `public_key` is its public key, encoded as an ASN.1
+/// `RSAPublicKey`; *ring* can't compute it from the private key. The
+/// signature is verified with `public_key`, so a mismatched public key
+/// results in an error.
+#[cfg(feature = "rsa_signing")]
+pub fn sign_token_binding(tb_type: TokenBindingType,
+                          ekm: &[u8; TOKEN_BINDING_EKM_LEN],
+                          public_key: untrusted::Input,
+                          signing_state: &mut signature::RSASigningState,
+                          rng: &rand::SecureRandom)
+                          -> Result<Vec<u8>, error::Unspecified> {
+    let (n, e) = try!(public_key.read_all(error::Unspecified, |input| {
+        der::nested(input, der::Tag::Sequence, error::Unspecified, |input| {
+            let n = try!(der::positive_integer(input));
+            let e = try!(der::positive_integer(input));
+            Ok((n, e))
+        })
+    }));
+    if n.len() != 2048 / 8 {
+        return Err(error::Unspecified);
+    }
+
+    let tb_type = match tb_type {
+        TokenBindingType::Provided => 0,
+        TokenBindingType::Referred => 1,
+    };
+    let mut msg = Vec::with_capacity(2 + ekm.len());
+    msg.push(tb_type);
+    msg.push(RSA2048_PKCS1_5);
+    msg.extend_from_slice(ekm);
+
+    let mut signature = vec![0; signing_state.key_pair().public_modulus_len()];
+    try!(signing_state.sign(&signature::RSA_PKCS1_SHA256, rng, &msg,
+                            &mut signature));
+    try!(signature::verify(&signature::RSA_PKCS1_2048_8192_SHA256, public_key,
+                           untrusted::Input::from(&msg),
+                           untrusted::Input::from(&signature)));
+
+    let mut rsa_public_key = Vec::new();
+    try!(write_vector(&mut rsa_public_key, 2, n.as_slice_less_safe()));
+    try!(write_vector(&mut rsa_public_key, 1, e.as_slice_less_safe()));
+
+    let mut token_binding = Vec::new();
+    token_binding.push(tb_type);
+    token_binding.push(RSA2048_PKCS1_5);
+    try!(write_vector(&mut token_binding, 2, &rsa_public_key));
+    try!(write_vector(&mut token_binding, 2, &signature));
+    try!(write_vector(&mut token_binding, 2, &[])); // No extensions.
+    Ok(token_binding)
+}
+
+/// Returns the encoded `TokenBindingMessage` containing the given encoded
+/// `TokenBinding`s, e.g. the value of a `Sec-Token-Binding` header before its
+/// base64url encoding.
+pub fn token_binding_message(token_bindings: &[&[u8]])
+                             -> Result<Vec<u8>, error::Unspecified> {
+    if token_bindings.is_empty() {
+        return Err(error::Unspecified);
+    }
+    let mut concatenated = Vec::new();
+    for token_binding in token_bindings {
+        concatenated.extend_from_slice(token_binding);
+    }
+    let mut message = Vec::new();
+    try!(write_vector(&mut message, 2, &concatenated));
+    Ok(message)
+}
+
+/// The side of the connection that sends an Exported Authenticator.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Role {
+    /// The client.
+    Client,
+
+    /// The server.
+    Server,
+}
+
+// The handshake message types.
+const CERTIFICATE_REQUEST: u8 = 13;
+const CLIENT_CERTIFICATE_REQUEST: u8 = 17;
+const CERTIFICATE: u8 = 11;
+const CERTIFICATE_VERIFY: u8 = 15;
+const FINISHED: u8 = 20;
+
+// The `ed25519` `SignatureScheme`.
+const ED25519: u16 = 0x0807;
+
+/// Returns the Exported Authenticator `Certificate || CertificateVerify ||
+/// Finished` for `certificate_chain`, signed with `key_pair`, as specified
+/// in [RFC 9261 Section 5.2].
+///
+/// `digest_alg` is the hash of the connection's cipher suite and `role` is
+/// the side of the connection that is sending the authenticator.
+///
+/// `authenticator_request` is the encoded `CertificateRequest` or
+/// `ClientCertificateRequest` message that is being responded to, or empty
+/// for a spontaneous server authenticator. `certificate_request_context` must
+/// match the request's; for spontaneous authenticators, it must be
+/// unpredictable. `certificate_chain` is the list of DER-encoded
+/// certificates, starting with the end-entity certificate; the entries have no
+/// extensions. Empty authenticators, which refuse a request, aren't supported.
+///
+/// [RFC 9261 Section 5.2]: https://tools.ietf.org/html/rfc9261#section-5.2
+pub fn exported_authenticator(digest_alg: &'static digest::Algorithm,
+                              exporter_secret: &[u8], role: Role,
+                              authenticator_request: &[u8],
+                              certificate_request_context: &[u8],
+                              certificate_chain: &[&[u8]],
+                              key_pair: &signature::Ed25519KeyPair)
+                              -> Result<Vec<u8>, error::Unspecified> {
+    if !authenticator_request.is_empty() {
+        let request_context =
+            try!(request_context(untrusted::Input::from(
+                authenticator_request)));
+        if request_context.as_slice_less_safe() !=
+           certificate_request_context {
+            return Err(error::Unspecified);
+        }
+    }
+    if certificate_chain.is_empty() {
+        return Err(error::Unspecified);
+    }
+
+    let (handshake_context_label, finished_key_label): (&[u8], &[u8]) =
+        match role {
+            Role::Client =>
+                (b"EXPORTER-client authenticator handshake context",
+                 b"EXPORTER-client authenticator finished key"),
+            Role::Server =>
+                (b"EXPORTER-server authenticator handshake context",
+                 b"EXPORTER-server authenticator finished key"),
+        };
+    let mut handshake_context = [0u8; digest::MAX_OUTPUT_LEN];
+    let handshake_context = &mut handshake_context[..digest_alg.output_len];
+    try!(export(digest_alg, exporter_secret, handshake_context_label, b"",
+                handshake_context));
+    let mut finished_key = [0u8; digest::MAX_OUTPUT_LEN];
+    let finished_key = &mut finished_key[..digest_alg.output_len];
+    try!(export(digest_alg, exporter_secret, finished_key_label, b"",
+                finished_key));
+
+    let mut certificate_list = Vec::new();
+    for certificate in certificate_chain {
+        try!(write_vector(&mut certificate_list, 3, certificate));
+        try!(write_vector(&mut certificate_list, 2, &[])); // No extensions.
+    }
+    let mut certificate = Vec::new();
+    try!(write_vector(&mut certificate, 1, certificate_request_context));
+    try!(write_vector(&mut certificate, 3, &certificate_list));
+
+    let mut authenticator = Vec::new();
+    try!(write_handshake(&mut authenticator, CERTIFICATE, &certificate));
+
+    let mut transcript = digest::Context::new(digest_alg);
+    transcript.update(handshake_context);
+    transcript.update(authenticator_request);
+    transcript.update(&authenticator);
+
+    // The signed content is built as in TLS 1.3's `CertificateVerify`,
+    // https://tools.ietf.org/html/rfc8446#section-4.4.3.
+    let mut signed = vec![0x20; 64];
+    signed.extend_from_slice(b"Exported Authenticator\x00");
+    signed.extend_from_slice(transcript.clone().finish().as_ref());
+    let signature = key_pair.sign(&signed);
+
+    let mut certificate_verify = Vec::new();
+    try!(write_u16(&mut certificate_verify, ED25519 as usize));
+    try!(write_vector(&mut certificate_verify, 2, signature.as_slice()));
+    let certificate_verify_start = authenticator.len();
+    try!(write_handshake(&mut authenticator, CERTIFICATE_VERIFY,
+                         &certificate_verify));
+    transcript.update(&authenticator[certificate_verify_start..]);
+
+    let finished_key = hmac::SigningKey::new(digest_alg, finished_key);
+    let verify_data = hmac::sign(&finished_key, transcript.finish().as_ref());
+    try!(write_handshake(&mut authenticator, FINISHED, verify_data.as_ref()));
+    Ok(authenticator)
+}
+
+// Returns the `certificate_request_context` of an encoded
+// `CertificateRequest` or `ClientCertificateRequest` message.
+fn request_context<'a>(request: untrusted::Input<'a>)
+                       -> Result<untrusted::Input<'a>, error::Unspecified> {
+    request.read_all(error::Unspecified, |input| {
+        let msg_type = try!(input.read_byte());
+        if msg_type != CERTIFICATE_REQUEST &&
+           msg_type != CLIENT_CERTIFICATE_REQUEST {
+            return Err(error::Unspecified);
+        }
+        let mut len = 0;
+        for _ in 0..3 {
+            len = (len << 8) | (try!(input.read_byte()) as usize);
+        }
+        let body = try!(input.skip_and_get_input(len));
+        // The rest of the body is the extensions, which aren't needed.
+        let _ = input.skip_to_end();
+        body.read_all(error::Unspecified, |body| {
+            let context_len = try!(body.read_byte()) as usize;
+            let context = try!(body.skip_and_get_input(context_len));
+            let _ = body.skip_to_end();
+            Ok(context)
+        })
+    })
+}
+
+fn write_handshake(out: &mut Vec<u8>, msg_type: u8, body: &[u8])
+                   -> Result<(), error::Unspecified> {
+    out.push(msg_type);
+    write_vector(out, 3, body)
+}
+
+// Writes `value` prefixed with its length, encoded in `len_len` bytes.
+fn write_vector(out: &mut Vec<u8>, len_len: usize, value: &[u8])
+                -> Result<(), error::Unspecified> {
+    if (value.len() as u64) >= (1u64 << (8 * len_len)) {
+        return Err(error::Unspecified);
+    }
+    for i in (0..len_len).rev() {
+        out.push((value.len() >> (8 * i)) as u8);
+    }
+    out.extend_from_slice(value);
+    Ok(())
+}
+
+fn write_u16(out: &mut Vec<u8>, value: usize)
+             -> Result<(), error::Unspecified> {
+    if value > 0xffff {
+        return Err(error::Unspecified);
+    }
+    out.push((value >> 8) as u8);
+    out.push(value as u8);
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {digest, rand, signature, test};
+    use super::*;
+
+    fn digest_alg(test_case: &mut test::TestCase)
+                  -> &'static digest::Algorithm {
+        match test_case.consume_string("Digest").as_str() {
+            "SHA256" => &digest::SHA256,
+            "SHA384" => &digest::SHA384,
+            s => panic!("Unsupported digest: {}", s),
+        }
+    }
+
+    #[test]
+    fn test_tls_exporter() {
+        test::from_file("src/tls_exporter_tests.txt", |section, test_case| {
+            let digest_alg = digest_alg(test_case);
+            let secret = test_case.consume_bytes("Secret");
+            match section {
+                "Export" => {
+                    let label = test_case.consume_bytes("Label");
+                    let context = test_case.consume_bytes("Context");
+                    let expected = test_case.consume_bytes("Output");
+                    let mut actual = vec![0u8; expected.len()];
+                    export(digest_alg, &secret, &label, &context, &mut actual)
+                        .unwrap();
+                    assert_eq!(actual, expected);
+                },
+                "TokenBinding" => {
+                    let tb_type = match test_case.consume_string("Type")
+                                                 .as_str() {
+                        "Provided" => TokenBindingType::Provided,
+                        "Referred" => TokenBindingType::Referred,
+                        s => panic!("Unsupported type: {}", s),
+                    };
+                    let expected = test_case.consume_bytes("TokenBinding");
+                    let ekm = token_binding_ekm(digest_alg, &secret).unwrap();
+                    check_token_binding(tb_type, &ekm, &expected);
+                },
+                "ExportedAuthenticator" => {
+                    let role = match test_case.consume_string("Role")
+                                              .as_str() {
+                        "Client" => Role::Client,
+                        "Server" => Role::Server,
+                        s => panic!("Unsupported role: {}", s),
+                    };
+                    let request = test_case.consume_bytes("Request");
+                    let context = test_case.consume_bytes("Context");
+                    let private_key = test_case.consume_bytes("PrivateKey");
+                    let public_key = test_case.consume_bytes("PublicKey");
+                    let certificate = test_case.consume_bytes("Certificate");
+                    let expected = test_case.consume_bytes("Authenticator");
+                    let key_pair =
+                        signature::Ed25519KeyPair::from_seed_and_public_key(
+                            &private_key, &public_key).unwrap();
+                    let actual = exported_authenticator(
+                        digest_alg, &secret, role, &request, &context,
+                        &[&certificate], &key_pair);
+                    if expected.is_empty() {
+                        assert!(actual.is_err());
+                    } else {
+                        assert_eq!(actual.unwrap(), expected);
+                    }
+                },
+                _ => panic!("Unexpected section: {}", section),
+            }
+            Ok(())
+        });
+    }
+
+    #[cfg(feature = "rsa_signing")]
+    fn check_token_binding(tb_type: TokenBindingType,
+                           ekm: &[u8; TOKEN_BINDING_EKM_LEN],
+                           expected: &[u8]) {
+        use std;
+        use untrusted;
+
+        let private_key = untrusted::Input::from(
+            include_bytes!("rsa/signature_rsa_example_private_key.der"));
+        let key_pair = signature::RSAKeyPair::from_der(private_key).unwrap();
+        let key_pair = std::sync::Arc::new(key_pair);
+        let mut signing_state =
+            signature::RSASigningState::new(key_pair).unwrap();
+        let rng = rand::SystemRandom::new();
+
+        let public_key = include_bytes!(
+            "rsa/signature_rsa_example_public_key.der");
+        let actual = sign_token_binding(tb_type, ekm,
+                                        untrusted::Input::from(public_key),
+                                        &mut signing_state, &rng).unwrap();
+        assert_eq!(&actual[..], expected);
+
+        // A public key that doesn't match the private key is rejected.
+        let mut wrong_public_key = public_key.to_vec();
+        let last = wrong_public_key.len() - 1;
+        wrong_public_key[last] ^= 2; // Keep the exponent odd.
+        assert!(sign_token_binding(tb_type, ekm,
+                                   untrusted::Input::from(&wrong_public_key),
+                                   &mut signing_state, &rng).is_err());
+    }
+
+    #[cfg(not(feature = "rsa_signing"))]
+    fn check_token_binding(_: TokenBindingType, _: &[u8; TOKEN_BINDING_EKM_LEN],
+                           _: &[u8]) {}
+
+    #[test]
+    fn test_token_binding_message() {
+        assert!(token_binding_message(&[]).is_err());
+        assert_eq!(token_binding_message(&[&[1, 2], &[3]]).unwrap(),
+                   vec![0, 3, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_exported_authenticator_invalid_inputs() {
+        let rng = rand::SystemRandom::new();
+        let key_pair = signature::Ed25519KeyPair::generate(&rng).unwrap();
+        let secret = [0u8; 32];
+        let certificate = [0u8; 10];
+        // No certificates.
+        assert!(exported_authenticator(&digest::SHA256, &secret, Role::Server,
+                                       b"", b"", &[], &key_pair).is_err());
+        // The request isn't a `CertificateRequest`.
+        assert!(exported_authenticator(&digest::SHA256, &secret, Role::Client,
+                                       &[11, 0, 0, 1, 0], b"",
+                                       &[&certificate], &key_pair).is_err());
+        // The request's length is wrong.
+        assert!(exported_authenticator(&digest::SHA256, &secret, Role::Client,
+                                       &[13, 0, 0, 2, 0], b"",
+                                       &[&certificate], &key_pair).is_err());
+        assert!(exported_authenticator(&digest::SHA256, &secret, Role::Client,
+                                       &[13, 0, 0, 1, 0], b"",
+                                       &[&certificate], &key_pair).is_ok());
+    }
+}
@@ -0,0 +1,264 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The key derivation functions of TLS 1.2 and TLS 1.3.
+//!
+//! TLS 1.2 derives keys with its PRF, `prf`, specified in
+//! [RFC 5246 Section 5]. The PRF's hash is the cipher suite's PRF hash,
+//! which is SHA-256 for most cipher suites and SHA-384 for some others; it is
+//! given as the digest algorithm of `secret`.
+//!
+//! TLS 1.3 derives keys with `hkdf_expand_label` and `derive_secret`,
+//! specified in [RFC 8446 Section 7.1], together with `hkdf::extract`. The
+//! hash is the cipher suite's hash, again given as the digest algorithm of
+//! the secret. The labels are given without the `"tls13 "` prefix, which is
+//! added by these functions, e.g. `b"key"` and `b"c hs traffic"`.
+//!
+//! # Examples
+//!
+//! Deriving the record protection key and IV from a TLS 1.3 traffic secret:
+//!
+//! ```
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::{aead, digest, hkdf, tls_kdf};
+//!
+//! # let traffic_secret = [0u8; 32];
+//! let secret = hkdf::Prk::new(&digest::SHA256, &traffic_secret);
+//! let mut key = [0u8; 16];
+//! try!(tls_kdf::hkdf_expand_label(&secret, b"key", b"", &mut key));
+//! let mut iv = [0u8; aead::NONCE_LEN];
+//! try!(tls_kdf::hkdf_expand_label(&secret, b"iv", b"", &mut iv));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [RFC 5246 Section 5]: https://tools.ietf.org/html/rfc5246#section-5
+//! [RFC 8446 Section 7.1]: https://tools.ietf.org/html/rfc8446#section-7.1
+
+use {digest, error, hkdf, hmac, secret};
+
+/// Fills `out` with the output of the TLS 1.2 PRF,
+/// `PRF(secret, label, seed)`, i.e. `P_hash(secret, label + seed)`, where the
+/// hash is `secret.digest_algorithm()`.
+///
+/// `seed` is the concatenation of the slices in `seed`, e.g.
+/// `&[client_random, server_random]` for the master secret, so that the seed
+/// doesn't have to be copied into one buffer.
+pub fn prf(secret: &hmac::SigningKey, label: &[u8], seed: &[&[u8]],
+           out: &mut [u8]) {
+    // A(1) = HMAC_hash(secret, label + seed).
+    let mut a = {
+        let mut ctx = hmac::SigningContext::with_key(secret);
+        ctx.update(label);
+        for part in seed {
+            ctx.update(part);
+        }
+        ctx.sign()
+    };
+    for chunk in out.chunks_mut(secret.digest_algorithm().output_len) {
+        let mut ctx = hmac::SigningContext::with_key(secret);
+        ctx.update(a.as_ref());
+        ctx.update(label);
+        for part in seed {
+            ctx.update(part);
+        }
+        chunk.copy_from_slice(&ctx.sign().as_ref()[..chunk.len()]);
+        a = hmac::sign(secret, a.as_ref());
+    }
+}
+
+/// The maximum length of the `label` of `hkdf_expand_label`.
+pub const MAX_LABEL_LEN: usize = 255 - LABEL_PREFIX.len();
+
+/// The maximum length of the `context` of `hkdf_expand_label`.
+pub const MAX_CONTEXT_LEN: usize = 255;
+
+const LABEL_PREFIX: &'static [u8] = b"tls13 ";
+
+/// Fills `out` with `HKDF-Expand-Label(secret, label, context, out.len())`.
+///
+/// `label` must not include the `"tls13 "` prefix, and must be at most
+/// `MAX_LABEL_LEN` bytes long. `context` must be at most `MAX_CONTEXT_LEN`
+/// bytes long. `out.len()` must be at most 255 times the output length of
+/// `secret.digest_algorithm()`, per the HKDF specification.
+pub fn hkdf_expand_label(secret: &hkdf::Prk, label: &[u8],
+                         context: &[u8], out: &mut [u8])
+                         -> Result<(), error::Unspecified> {
+    if label.len() > MAX_LABEL_LEN || context.len() > MAX_CONTEXT_LEN ||
+       out.len() > 255 * secret.digest_algorithm().output_len {
+        return Err(error::Unspecified);
+    }
+
+    // struct {
+    //     uint16 length = Length;
+    //     opaque label<7..255> = "tls13 " + Label;
+    //     opaque context<0..255> = Context;
+    // } HkdfLabel;
+    let mut info = [0u8; 2 + 1 + 255 + 1 + 255];
+    let full_label_len = LABEL_PREFIX.len() + label.len();
+    info[0] = (out.len() >> 8) as u8;
+    info[1] = out.len() as u8;
+    info[2] = full_label_len as u8;
+    info[3..][..LABEL_PREFIX.len()].copy_from_slice(LABEL_PREFIX);
+    info[(3 + LABEL_PREFIX.len())..][..label.len()].copy_from_slice(label);
+    let context_offset = 3 + full_label_len;
+    info[context_offset] = context.len() as u8;
+    info[(context_offset + 1)..][..context.len()].copy_from_slice(context);
+    let info_len = context_offset + 1 + context.len();
+
+    secret.expand(&info[..info_len], out);
+    Ok(())
+}
+
+/// Like `hkdf_expand_label`, but constructs a `V` from `V::secret_len()`
+/// bytes of output, e.g. a traffic key or IV whose type records which
+/// traffic secret it came from.
+pub fn hkdf_expand_label_value<V>(secret: &hkdf::Prk, label: &[u8],
+                                  context: &[u8])
+                                  -> Result<V, error::Unspecified>
+                                  where V: secret::ValueFromSecret {
+    secret::value_from_secret(|out| {
+        hkdf_expand_label(secret, label, context, out)
+    })
+}
+
+/// Returns `Derive-Secret(secret, label, messages)`, given the transcript
+/// hash of `messages`, `messages_hash`.
+///
+/// This is `HKDF-Expand-Label(secret, label, messages_hash, Hash.length)`,
+/// returned as a key for the next step of the key schedule; use it directly
+/// as a traffic secret, or use `as_salt()` for the next HKDF-Extract. `label`
+/// must not include the `"tls13 "` prefix. `messages_hash` must be a digest
+/// that was computed with `secret.digest_algorithm()`, e.g.
+/// `digest::digest(alg, b"")` for the `"derived"` secrets.
+pub fn derive_secret(secret: &hkdf::Prk, label: &[u8],
+                     messages_hash: &digest::Digest)
+                     -> Result<hkdf::Prk, error::Unspecified> {
+    let digest_alg = secret.digest_algorithm();
+    if messages_hash.algorithm().output_len != digest_alg.output_len {
+        return Err(error::Unspecified);
+    }
+    let mut derived = [0u8; digest::MAX_OUTPUT_LEN];
+    let derived = &mut derived[..digest_alg.output_len];
+    try!(hkdf_expand_label(secret, label, messages_hash.as_ref(), derived));
+    Ok(hkdf::Prk::new(digest_alg, derived))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {digest, error, hkdf, hmac, secret, test};
+    use super::*;
+
+    #[test]
+    fn test_tls_kdf() {
+        test::from_file("src/tls_kdf_tests.txt", |section, test_case| {
+            let digest_alg = match test_case.consume_string("Hash").as_str() {
+                "SHA256" => &digest::SHA256,
+                "SHA384" => &digest::SHA384,
+                s => panic!("Unsupported hash: {}", s),
+            };
+            let secret = test_case.consume_bytes("Secret");
+            let label = test_case.consume_bytes("Label");
+            let expected = test_case.consume_bytes("Output");
+            let mut actual = vec![0u8; expected.len()];
+            match section {
+                "PRF" => {
+                    let seed = test_case.consume_bytes("Seed");
+                    let secret = hmac::SigningKey::new(digest_alg, &secret);
+                    prf(&secret, &label, &[&seed], &mut actual);
+                    assert_eq!(actual, expected);
+
+                    // Splitting the seed doesn't change the output.
+                    let (seed_a, seed_b) = seed.split_at(seed.len() / 2);
+                    let mut actual = vec![0u8; expected.len()];
+                    prf(&secret, &label, &[seed_a, seed_b], &mut actual);
+                    assert_eq!(actual, expected);
+                },
+                "ExpandLabel" => {
+                    let context = test_case.consume_bytes("Context");
+                    let secret = hkdf::Prk::new(digest_alg, &secret);
+                    try!(hkdf_expand_label(&secret, &label, &context,
+                                           &mut actual));
+                    assert_eq!(actual, expected);
+
+                    if expected.len() == 16 {
+                        let key: Key16 =
+                            try!(hkdf_expand_label_value(&secret, &label,
+                                                         &context));
+                        assert_eq!(&key.0[..], &expected[..]);
+                    }
+                },
+                _ => panic!("Unexpected section: {}", section),
+            }
+            Ok(())
+        });
+    }
+
+    struct Key16([u8; 16]);
+
+    impl secret::ValueFromSecret for Key16 {
+        fn secret_len() -> usize { 16 }
+        fn from_secret(bytes: &[u8]) -> Self {
+            let mut key = [0u8; 16];
+            key.copy_from_slice(bytes);
+            Key16(key)
+        }
+    }
+
+    #[test]
+    fn test_tls_kdf_derive_secret() {
+        // The early secret and the "derived" secret computed from it in
+        // RFC 8448 Section 3.
+        let zeros = [0u8; 32];
+        let early_secret =
+            hkdf::extract(&hmac::SigningKey::new(&digest::SHA256, &[]),
+                          &zeros);
+        let derived = derive_secret(&early_secret, b"derived",
+                                    &digest::digest(&digest::SHA256, b""))
+                          .unwrap();
+        let expected = test::from_hex(
+            "6f2615a108c702c5678f54fc9dbab69716c076189c48250cebeac3576c3611ba")
+            .unwrap();
+        // `derived` is the salt of the next HKDF-Extract, so compare it by
+        // its output.
+        assert_eq!(hmac::sign(derived.as_salt(), b"").as_ref(),
+                   hmac::sign(&hmac::SigningKey::new(&digest::SHA256,
+                                                     &expected),
+                              b"").as_ref());
+
+        assert!(derive_secret(&early_secret, b"derived",
+                              &digest::digest(&digest::SHA384, b""))
+                    .is_err());
+    }
+
+    #[test]
+    fn test_tls_kdf_expand_label_lengths() {
+        let secret = hkdf::Prk::new(&digest::SHA256, &[0u8; 32]);
+        let mut out = [0u8; 16];
+        assert!(hkdf_expand_label(&secret, &[b'a'; MAX_LABEL_LEN],
+                                  &[0u8; MAX_CONTEXT_LEN], &mut out).is_ok());
+        assert_eq!(hkdf_expand_label(&secret, &[b'a'; MAX_LABEL_LEN + 1], b"",
+                                     &mut out),
+                   Err(error::Unspecified));
+        assert!(hkdf_expand_label(&secret, b"key",
+                                  &[0u8; MAX_CONTEXT_LEN + 1], &mut out)
+                    .is_err());
+        let mut out = vec![0u8; 255 * 32 + 1];
+        assert!(hkdf_expand_label(&secret, b"key", b"", &mut out).is_err());
+        assert!(hkdf_expand_label(&secret, b"key", b"",
+                                  &mut out[..(255 * 32)]).is_ok());
+    }
+}
@@ -0,0 +1,301 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Passphrase-based encryption of small secrets, e.g. the private keys and
+//! API tokens that command-line tools keep in files.
+//!
+//! `seal` derives a key from the passphrase with a password hashing function
+//! and a random salt, encrypts the plaintext with ChaCha20-Poly1305 using a
+//! random nonce, and returns a self-contained, versioned vault that `open`
+//! turns back into the plaintext given the same passphrase. The caller
+//! doesn't need to store or choose salts, nonces, or parameters.
+//!
+//! The key is derived with either Argon2id or PBKDF2-HMAC-SHA256; see
+//! `Params`. A vault is:
+//!
+//! ```text
+//! version       u8        1
+//! kdf           u8        1 (Argon2id) or 2 (PBKDF2-HMAC-SHA256)
+//! kdf params    Argon2id: m, t, and p, each a big-endian u32
+//!               PBKDF2-HMAC-SHA256: the iteration count, a big-endian u32
+//! salt          [u8; 16]
+//! nonce         [u8; 12]
+//! ```
+//!
+//! followed by the ciphertext and the 16-byte tag. Everything before the
+//! ciphertext is the additional authenticated data, so the parameters can't
+//! be changed without detection.
+//!
+//! `open` derives the key using the parameters in the vault, so opening a
+//! vault costs whatever its creator chose, up to the limits that
+//! `argon2::Params::new` and `Params::pbkdf2_hmac_sha256` enforce. Only open
+//! vaults that come from where the application stores its own.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::{argon2, rand, vault};
+//!
+//! // Real applications should use `vault::Params::recommended()`.
+//! let params = vault::Params::argon2id(try!(argon2::Params::new(256, 1, 1)));
+//! let rng = rand::SystemRandom::new();
+//! let sealed = try!(vault::seal(&params, b"correct horse battery staple",
+//!                               b"api token", &rng));
+//!
+//! let opened = try!(vault::open(b"correct horse battery staple", &sealed));
+//! assert_eq!(&opened[..], b"api token");
+//! assert!(vault::open(b"wrong passphrase", &sealed).is_err());
+//! # Ok(())
+//! # }
+//! ```
+
+use {aead, argon2, error, pbkdf2, polyfill, rand, secret, std};
+use core;
+
+/// The version of the vault format that `seal` produces.
+pub const VERSION: u8 = 1;
+
+/// The smallest PBKDF2 iteration count that `Params::pbkdf2_hmac_sha256`
+/// accepts.
+pub const MIN_PBKDF2_ITERATIONS: u32 = 100000;
+
+const KDF_ARGON2ID: u8 = 1;
+const KDF_PBKDF2_HMAC_SHA256: u8 = 2;
+
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+
+static AEAD: &'static aead::Algorithm = &aead::CHACHA20_POLY1305;
+
+/// The key derivation function of a vault and its parameters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Params {
+    kdf: Kdf,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kdf {
+    Argon2id(argon2::Params),
+    Pbkdf2HmacSha256 { iterations: u32 },
+}
+
+impl Params {
+    /// Argon2id with `argon2::Params::recommended()`.
+    pub fn recommended() -> Params {
+        Params::argon2id(argon2::Params::recommended())
+    }
+
+    /// Argon2id with the parameters `params`.
+    pub fn argon2id(params: argon2::Params) -> Params {
+        Params { kdf: Kdf::Argon2id(params) }
+    }
+
+    /// PBKDF2-HMAC-SHA256 with `iterations` iterations, for applications that
+    /// can't use Argon2id, e.g. because it uses too much memory. `iterations`
+    /// must be at least `MIN_PBKDF2_ITERATIONS` and less than 2**32.
+    pub fn pbkdf2_hmac_sha256(iterations: u32)
+                              -> Result<Params, error::Unspecified> {
+        if iterations < MIN_PBKDF2_ITERATIONS {
+            return Err(error::Unspecified);
+        }
+        Ok(Params { kdf: Kdf::Pbkdf2HmacSha256 { iterations: iterations } })
+    }
+
+    fn derive(&self, passphrase: &[u8], salt: &[u8],
+              key: &mut [u8; KEY_LEN]) -> Result<(), error::Unspecified> {
+        match self.kdf {
+            Kdf::Argon2id(ref params) =>
+                argon2::derive(&argon2::ARGON2ID, params, salt, passphrase,
+                               key),
+            Kdf::Pbkdf2HmacSha256 { iterations } => {
+                pbkdf2::derive(&pbkdf2::HMAC_SHA256, iterations as usize, salt,
+                               passphrase, key);
+                Ok(())
+            },
+        }
+    }
+
+    fn encode(&self, out: &mut std::vec::Vec<u8>) {
+        let be = polyfill::slice::be_u8_from_u32;
+        match self.kdf {
+            Kdf::Argon2id(ref params) => {
+                out.push(KDF_ARGON2ID);
+                out.extend_from_slice(&be(params.memory_kib()));
+                out.extend_from_slice(&be(params.iterations()));
+                out.extend_from_slice(&be(params.parallelism()));
+            },
+            Kdf::Pbkdf2HmacSha256 { iterations } => {
+                out.push(KDF_PBKDF2_HMAC_SHA256);
+                out.extend_from_slice(&be(iterations));
+            },
+        }
+    }
+
+    // Parses the encoding written by `encode` at the start of `input`, and
+    // returns the parameters and the length of their encoding.
+    fn parse(input: &[u8]) -> Result<(Params, usize), error::Unspecified> {
+        let u32_at = |i: usize| -> Result<u32, error::Unspecified> {
+            let bytes = try!(input.get(i..(i + 4)).ok_or(error::Unspecified));
+            let bytes = slice_as_array_ref!(bytes, 4).unwrap();
+            Ok(polyfill::slice::u32_from_be_u8(bytes))
+        };
+        match input.first() {
+            Some(&KDF_ARGON2ID) => {
+                let params = try!(argon2::Params::new(try!(u32_at(1)),
+                                                      try!(u32_at(5)),
+                                                      try!(u32_at(9))));
+                Ok((Params::argon2id(params), 1 + 12))
+            },
+            Some(&KDF_PBKDF2_HMAC_SHA256) => {
+                let params = try!(Params::pbkdf2_hmac_sha256(try!(u32_at(1))));
+                Ok((params, 1 + 4))
+            },
+            _ => Err(error::Unspecified),
+        }
+    }
+}
+
+/// Encrypts `plaintext` with a key derived from `passphrase` using `params`,
+/// with a random salt and nonce from `rng`, and returns the vault.
+pub fn seal(params: &Params, passphrase: &[u8], plaintext: &[u8],
+            rng: &rand::SecureRandom)
+            -> Result<std::vec::Vec<u8>, error::Unspecified> {
+    let mut salt_and_nonce = [0u8; SALT_LEN + aead::NONCE_LEN];
+    try!(rng.fill(&mut salt_and_nonce));
+    let (salt, nonce) = salt_and_nonce.split_at(SALT_LEN);
+
+    let mut key = secret::SecretBox::new([0u8; KEY_LEN]);
+    try!(params.derive(passphrase, salt, key.expose_mut()));
+    let key = try!(aead::SealingKey::new(AEAD, key.expose()));
+
+    let mut out = std::vec::Vec::new();
+    out.push(VERSION);
+    params.encode(&mut out);
+    out.extend_from_slice(&salt_and_nonce);
+    let header_len = out.len();
+    out.extend_from_slice(plaintext);
+    out.extend(core::iter::repeat(0).take(AEAD.tag_len()));
+    let (header, in_out) = out.split_at_mut(header_len);
+    let _ = try!(aead::seal_in_place(&key, nonce, in_out, AEAD.tag_len(),
+                                     header));
+    Ok(out)
+}
+
+/// Decrypts the vault `vault` with a key derived from `passphrase` and returns
+/// the plaintext.
+///
+/// Fails if the passphrase is wrong, if the vault has been modified, or if it
+/// has an unsupported version or invalid parameters.
+pub fn open(passphrase: &[u8], vault: &[u8])
+            -> Result<std::vec::Vec<u8>, error::Unspecified> {
+    if vault.first() != Some(&VERSION) {
+        return Err(error::Unspecified);
+    }
+    let (params, params_len) = try!(Params::parse(&vault[1..]));
+    let header_len = 1 + params_len + SALT_LEN + aead::NONCE_LEN;
+    if vault.len() < header_len + AEAD.tag_len() {
+        return Err(error::Unspecified);
+    }
+    let (header, ciphertext) = vault.split_at(header_len);
+    let (salt, nonce) =
+        header[(1 + params_len)..].split_at(SALT_LEN);
+
+    let mut key = secret::SecretBox::new([0u8; KEY_LEN]);
+    try!(params.derive(passphrase, salt, key.expose_mut()));
+    let key = try!(aead::OpeningKey::new(AEAD, key.expose()));
+
+    let mut in_out = ciphertext.to_vec();
+    let plaintext_len =
+        try!(aead::open_in_place(&key, nonce, 0, &mut in_out, header));
+    in_out.truncate(plaintext_len);
+    Ok(in_out)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {argon2, rand, test};
+
+    #[test]
+    fn test_vault() {
+        test::from_file("src/vault_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let passphrase = test_case.consume_bytes("Passphrase");
+            let plaintext = test_case.consume_bytes("Plaintext");
+            let vault = test_case.consume_bytes("Vault");
+
+            assert_eq!(open(&passphrase, &vault).unwrap(), plaintext);
+
+            // The salt and the nonce are the only random inputs.
+            let (params, params_len) = Params::parse(&vault[1..]).unwrap();
+            let salt_and_nonce = &vault[(1 + params_len)..]
+                                       [..(SALT_LEN + aead::NONCE_LEN)];
+            let rng = rand::test_util::FixedSliceRandom {
+                bytes: salt_and_nonce,
+            };
+            assert_eq!(seal(&params, &passphrase, &plaintext, &rng).unwrap(),
+                       vault);
+
+            // Modifications of the version, the KDF, the iteration count, the
+            // salt, the nonce, the ciphertext, and the tag are detected.
+            let salt_start = 1 + params_len;
+            for &i in &[0, 1, params_len, salt_start, salt_start + SALT_LEN,
+                        vault.len() - 17, vault.len() - 1] {
+                let mut modified = vault.clone();
+                modified[i] ^= 1;
+                assert!(open(&passphrase, &modified).is_err());
+            }
+            assert!(open(&passphrase, &vault[..(vault.len() - 1)]).is_err());
+            assert!(open(b"wrong passphrase", &vault).is_err());
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_vault_argon2id() {
+        let rng = rand::SystemRandom::new();
+        let params = Params::argon2id(argon2::Params::new(64, 2, 2).unwrap());
+        let vault = seal(&params, b"passphrase", b"plaintext", &rng).unwrap();
+        assert_eq!(&vault[..14],
+                   &[VERSION, KDF_ARGON2ID, 0, 0, 0, 64, 0, 0, 0, 2, 0, 0, 0,
+                     2]);
+        assert_eq!(&open(b"passphrase", &vault).unwrap()[..], b"plaintext");
+        assert!(open(b"Passphrase", &vault).is_err());
+    }
+
+    #[test]
+    fn test_vault_params() {
+        assert!(Params::pbkdf2_hmac_sha256(MIN_PBKDF2_ITERATIONS - 1)
+                    .is_err());
+        assert!(Params::pbkdf2_hmac_sha256(MIN_PBKDF2_ITERATIONS).is_ok());
+
+        // Vaults with parameters that `seal` wouldn't use are rejected
+        // before any key derivation is done.
+        let mut header = [0u8; 1 + 1 + 4 + SALT_LEN + aead::NONCE_LEN + 16];
+        header[0] = VERSION;
+        header[1] = KDF_PBKDF2_HMAC_SHA256;
+        assert!(open(b"", &header).is_err());
+        header[1] = KDF_ARGON2ID;
+        assert!(open(b"", &header).is_err());
+        header[1] = 3;
+        assert!(open(b"", &header).is_err());
+        assert!(open(b"", &[]).is_err());
+        assert!(open(b"", &[VERSION]).is_err());
+        assert!(open(b"", &[VERSION, KDF_ARGON2ID, 0]).is_err());
+    }
+}
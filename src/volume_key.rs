@@ -0,0 +1,241 @@
+// Copyright 2016 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Derivation of disk and file encryption keys from a passphrase.
+//!
+//! A volume is protected by a `Header`, which holds a random salt and the
+//! `scrypt` parameters, and which is stored unencrypted alongside the
+//! encrypted data. `Header::derive_keys` stretches the passphrase with scrypt,
+//! using the whole serialized header as the salt, and then splits the result
+//! into an encryption key and a MAC key with HKDF-SHA256. Because the
+//! parameters are part of the scrypt salt, an attacker who modifies them in
+//! the stored header only causes different keys to be derived.
+//!
+//! A wrong passphrase isn't detected by `derive_keys`; it results in keys
+//! that fail to authenticate the volume's data.
+//!
+//! The serialized header is `HEADER_LEN` bytes long: a version byte, which
+//! is currently 1, the base-2 logarithm of scrypt's N, scrypt's r and p as
+//! 32-bit big-endian values, and the `SALT_LEN`-byte salt.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::{rand, scrypt, volume_key};
+//!
+//! // Creating a volume. Real applications should use stronger parameters
+//! // such as `scrypt::Params::sensitive()`.
+//! let rng = rand::SystemRandom::new();
+//! let params = try!(scrypt::Params::new(10, 8, 1));
+//! let header = try!(volume_key::Header::generate(params, &rng));
+//! let stored_header = header.to_bytes();
+//! let keys = try!(header.derive_keys(b"correct horse battery staple"));
+//!
+//! // Opening it again.
+//! let header = try!(volume_key::Header::parse(&stored_header));
+//! let keys_again = try!(header.derive_keys(b"correct horse battery staple"));
+//! assert_eq!(keys.encryption_key(), keys_again.encryption_key());
+//! assert_eq!(keys.mac_key(), keys_again.mac_key());
+//! # Ok(())
+//! # }
+//! ```
+
+use {digest, error, hkdf, polyfill, rand, scrypt, secret};
+
+/// The length of the salt.
+pub const SALT_LEN: usize = 32;
+
+/// The length of each of the derived keys.
+pub const KEY_LEN: usize = 32;
+
+/// The length of a serialized `Header`.
+pub const HEADER_LEN: usize = 1 + 1 + 4 + 4 + SALT_LEN;
+
+const VERSION: u8 = 1;
+
+const ENCRYPTION_KEY_LABEL: &'static [u8] = b"ring volume key v1 encryption";
+const MAC_KEY_LABEL: &'static [u8] = b"ring volume key v1 mac";
+
+/// The public parameters of a volume's key derivation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header {
+    params: scrypt::Params,
+    salt: [u8; SALT_LEN],
+}
+
+impl Header {
+    /// Generates a new header with the parameters `params` and a random salt.
+    pub fn generate(params: scrypt::Params, rng: &rand::SecureRandom)
+                    -> Result<Header, error::Unspecified> {
+        let mut salt = [0u8; SALT_LEN];
+        try!(rng.fill(&mut salt));
+        Ok(Header { params: params, salt: salt })
+    }
+
+    /// Parses a header that was serialized with `to_bytes`.
+    ///
+    /// `input` must be exactly `HEADER_LEN` bytes long. Headers with an
+    /// unknown version or invalid scrypt parameters are rejected.
+    pub fn parse(input: &[u8]) -> Result<Header, error::Unspecified> {
+        if input.len() != HEADER_LEN || input[0] != VERSION {
+            return Err(error::Unspecified);
+        }
+        let log2_n = input[1];
+        let r = polyfill::slice::u32_from_be_u8(
+            slice_as_array_ref!(&input[2..6], 4).unwrap());
+        let p = polyfill::slice::u32_from_be_u8(
+            slice_as_array_ref!(&input[6..10], 4).unwrap());
+        let params = try!(scrypt::Params::new(log2_n, r, p));
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(&input[10..]);
+        Ok(Header { params: params, salt: salt })
+    }
+
+    /// Serializes the header.
+    pub fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let mut out = [0u8; HEADER_LEN];
+        out[0] = VERSION;
+        out[1] = self.params.log2_n();
+        out[2..6].copy_from_slice(
+            &polyfill::slice::be_u8_from_u32(self.params.r()));
+        out[6..10].copy_from_slice(
+            &polyfill::slice::be_u8_from_u32(self.params.p()));
+        out[10..].copy_from_slice(&self.salt);
+        out
+    }
+
+    /// The scrypt parameters.
+    #[inline(always)]
+    pub fn params(&self) -> &scrypt::Params { &self.params }
+
+    /// The salt.
+    #[inline(always)]
+    pub fn salt(&self) -> &[u8; SALT_LEN] { &self.salt }
+
+    /// Derives the volume's keys from `passphrase`.
+    pub fn derive_keys(&self, passphrase: &[u8])
+                       -> Result<VolumeKeys, error::Unspecified> {
+        let mut prk = secret::SecretBox::new([0u8; digest::MAX_OUTPUT_LEN]);
+        let prk = &mut prk.expose_mut()[..digest::SHA256.output_len];
+        try!(scrypt::derive(&self.params, passphrase, &self.to_bytes(), prk));
+        let prk = hkdf::Prk::new(&digest::SHA256, prk);
+
+        let mut keys = VolumeKeys {
+            encryption_key: secret::SecretBox::new([0u8; KEY_LEN]),
+            mac_key: secret::SecretBox::new([0u8; KEY_LEN]),
+        };
+        prk.expand(ENCRYPTION_KEY_LABEL, keys.encryption_key.expose_mut());
+        prk.expand(MAC_KEY_LABEL, keys.mac_key.expose_mut());
+        Ok(keys)
+    }
+}
+
+/// The keys derived for a volume. They are zeroized when dropped.
+pub struct VolumeKeys {
+    encryption_key: secret::SecretBox<[u8; KEY_LEN]>,
+    mac_key: secret::SecretBox<[u8; KEY_LEN]>,
+}
+
+impl VolumeKeys {
+    /// The key for encrypting the volume's data.
+    #[inline]
+    pub fn encryption_key(&self) -> &[u8; KEY_LEN] {
+        self.encryption_key.expose()
+    }
+
+    /// The key for authenticating the volume's data.
+    #[inline]
+    pub fn mac_key(&self) -> &[u8; KEY_LEN] { self.mac_key.expose() }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {error, rand, scrypt, test};
+    use super::*;
+
+    #[test]
+    fn test_volume_key() {
+        test::from_file("src/volume_key_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let header_bytes = test_case.consume_bytes("Header");
+            let passphrase = test_case.consume_bytes("Passphrase");
+            let expected_encryption_key =
+                test_case.consume_bytes("EncryptionKey");
+            let expected_mac_key = test_case.consume_bytes("MACKey");
+
+            let header = try!(Header::parse(&header_bytes));
+            assert_eq!(&header.to_bytes()[..], &header_bytes[..]);
+            let keys = try!(header.derive_keys(&passphrase));
+            assert_eq!(&keys.encryption_key()[..],
+                       &expected_encryption_key[..]);
+            assert_eq!(&keys.mac_key()[..], &expected_mac_key[..]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_volume_key_header() {
+        let rng = rand::SystemRandom::new();
+        let params = scrypt::Params::new(4, 1, 1).unwrap();
+        let header = Header::generate(params, &rng).unwrap();
+        let other = Header::generate(params, &rng).unwrap();
+        assert!(header.salt() != other.salt());
+
+        let bytes = header.to_bytes();
+        assert_eq!(Header::parse(&bytes), Ok(header.clone()));
+        assert_eq!(Header::parse(&bytes).unwrap().params(), &params);
+        assert_eq!(Header::parse(&bytes[..(HEADER_LEN - 1)]),
+                   Err(error::Unspecified));
+        let mut long = [0u8; HEADER_LEN + 1];
+        long[..HEADER_LEN].copy_from_slice(&bytes);
+        assert!(Header::parse(&long).is_err());
+
+        // Unknown version.
+        let mut bad = bytes;
+        bad[0] = 2;
+        assert!(Header::parse(&bad).is_err());
+
+        // Invalid scrypt parameters.
+        let mut bad = bytes;
+        bad[1] = 0;
+        assert!(Header::parse(&bad).is_err());
+        let mut bad = bytes;
+        bad[2..6].copy_from_slice(&[0, 0, 0, 0]);
+        assert!(Header::parse(&bad).is_err());
+    }
+
+    #[test]
+    fn test_volume_key_separation() {
+        let rng = rand::SystemRandom::new();
+        let params = scrypt::Params::new(4, 1, 1).unwrap();
+        let header = Header::generate(params, &rng).unwrap();
+        let keys = header.derive_keys(b"passphrase").unwrap();
+        assert!(keys.encryption_key() != keys.mac_key());
+
+        let other_keys = header.derive_keys(b"passphrasf").unwrap();
+        assert!(keys.encryption_key() != other_keys.encryption_key());
+        assert!(keys.mac_key() != other_keys.mac_key());
+
+        // The parameters are bound to the keys.
+        let mut bytes = header.to_bytes();
+        bytes[1] = 5;
+        let other_header = Header::parse(&bytes).unwrap();
+        let other_keys = other_header.derive_keys(b"passphrase").unwrap();
+        assert!(keys.encryption_key() != other_keys.encryption_key());
+    }
+}
@@ -0,0 +1,523 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! The cryptography of the [WireGuard] protocol.
+//!
+//! WireGuard's handshake is `Noise_IKpsk2_25519_ChaChaPoly_BLAKE2s`. Its
+//! primitives map onto `ring` as follows:
+//!
+//! | WireGuard       | `ring`                                             |
+//! |-----------------|----------------------------------------------------|
+//! | `DH`            | `PrivateKey` and `SymmetricState::mix_dh`          |
+//! | `AEAD`          | `ring::aead::CHACHA20_POLY1305`, `transport_nonce` |
+//! | `XAEAD`         | `ring::aead::xchacha20_poly1305`                   |
+//! | `HASH`          | `hash`, or `ring::digest::blake2s`                 |
+//! | `MAC`           | `mac`                                              |
+//! | `HMAC`          | `hmac`                                             |
+//! | `KDF1`..`KDF3`  | `kdf`                                              |
+//!
+//! `SymmetricState` implements the handshake's chaining key and hash, with
+//! Noise's `MixHash`, `MixKey`, `MixKeyAndHash`, `EncryptAndHash`,
+//! `DecryptAndHash`, and `Split` operations. The message formats, the timers,
+//! and the replay and DoS protections are left to the caller.
+//!
+//! # Examples
+//!
+//! The first handshake message, from the initiator's side:
+//!
+//! ```
+//! # extern crate ring;
+//! # extern crate untrusted;
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::{rand, wireguard};
+//!
+//! let rng = rand::SystemRandom::new();
+//! # let initiator_static = try!(wireguard::PrivateKey::generate(&rng));
+//! # let responder_static_public =
+//! #     try!(wireguard::PrivateKey::generate(&rng)).public_key();
+//! # let initiator_static_public = initiator_static.public_key();
+//! # let timestamp = [0u8; 12];
+//!
+//! let mut state = try!(wireguard::SymmetricState::new(
+//!     &responder_static_public));
+//!
+//! // e
+//! let ephemeral = try!(wireguard::PrivateKey::generate(&rng));
+//! let ephemeral_public = ephemeral.public_key();
+//! state.mix_hash(&ephemeral_public);
+//! state.mix_key(&ephemeral_public);
+//!
+//! // es, s
+//! let peer = untrusted::Input::from(&responder_static_public);
+//! try!(state.mix_dh(&ephemeral, peer));
+//! let mut encrypted_static = [0u8; 32 + wireguard::TAG_LEN];
+//! encrypted_static[..32].copy_from_slice(&initiator_static_public);
+//! try!(state.encrypt_and_hash(&mut encrypted_static, wireguard::TAG_LEN));
+//!
+//! // ss, {timestamp}
+//! try!(state.mix_dh(&initiator_static, peer));
+//! let mut encrypted_timestamp = [0u8; 12 + wireguard::TAG_LEN];
+//! encrypted_timestamp[..12].copy_from_slice(&timestamp);
+//! try!(state.encrypt_and_hash(&mut encrypted_timestamp,
+//!                             wireguard::TAG_LEN));
+//!
+//! let mac1_key = wireguard::mac1_key(&responder_static_public);
+//! # let msg = [0u8; 116];
+//! let mac1 = wireguard::mac(&mac1_key, &[&msg]);
+//! # let _ = mac1;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [WireGuard]: https://www.wireguard.com/papers/wireguard.pdf
+
+use {aead, digest, error, hmac, init, rand};
+use digest::blake2s;
+use ec::x25519;
+use untrusted;
+
+/// The length of a `HASH` output, and of WireGuard's symmetric keys.
+pub const HASH_LEN: usize = blake2s::MAX_OUTPUT_LEN;
+
+/// The length of a `MAC` output.
+pub const MAC_LEN: usize = 16;
+
+/// The length of the AEAD tags.
+pub const TAG_LEN: usize = aead::MAX_OVERHEAD_LEN;
+
+/// The length of X25519 public keys.
+pub const PUBLIC_KEY_LEN: usize = x25519::X25519_ELEM_SCALAR_PUBLIC_KEY_LEN;
+
+const PRIVATE_KEY_LEN: usize = x25519::X25519_ELEM_SCALAR_PUBLIC_KEY_LEN;
+
+const CONSTRUCTION: &'static [u8] = b"Noise_IKpsk2_25519_ChaChaPoly_BLAKE2s";
+const IDENTIFIER: &'static [u8] = b"WireGuard v1 zx2c4 Jason@zx2c4.com";
+const LABEL_MAC1: &'static [u8] = b"mac1----";
+const LABEL_COOKIE: &'static [u8] = b"cookie--";
+
+/// An X25519 private key, which, unlike `ring::agreement`'s
+/// `EphemeralPrivateKey`, can be used for more than one key agreement.
+///
+/// WireGuard's static keys are used in every handshake, and its ephemeral
+/// keys are used twice in each one.
+pub struct PrivateKey {
+    bytes: [u8; PRIVATE_KEY_LEN],
+}
+
+impl PrivateKey {
+    /// Generates a new private key.
+    pub fn generate(rng: &rand::SecureRandom)
+                    -> Result<PrivateKey, error::Unspecified> {
+        init::init_once();
+        let mut bytes = [0u8; PRIVATE_KEY_LEN];
+        try!(rng.fill(&mut bytes));
+        Ok(PrivateKey { bytes: bytes })
+    }
+
+    /// Constructs a private key from its 32-byte encoding, e.g. a static key
+    /// from a configuration file.
+    pub fn from_bytes(bytes: &[u8]) -> Result<PrivateKey, error::Unspecified> {
+        init::init_once();
+        let bytes = try!(slice_as_array_ref!(bytes, PRIVATE_KEY_LEN));
+        Ok(PrivateKey { bytes: *bytes })
+    }
+
+    /// Returns the public key.
+    pub fn public_key(&self) -> [u8; PUBLIC_KEY_LEN] {
+        let mut public_key = [0u8; PUBLIC_KEY_LEN];
+        x25519::public_from_private_bytes(&mut public_key, &self.bytes);
+        public_key
+    }
+}
+
+/// `HASH(parts[0] || parts[1] || ...)`: the 32-byte BLAKE2s digest of the
+/// concatenation of `parts`.
+pub fn hash(parts: &[&[u8]]) -> [u8; HASH_LEN] {
+    let mut ctx = blake2s::Context::new(HASH_LEN).unwrap();
+    for part in parts {
+        ctx.update(part);
+    }
+    let mut out = [0u8; HASH_LEN];
+    out.copy_from_slice(ctx.finish().as_ref());
+    out
+}
+
+/// `MAC(key, parts[0] || parts[1] || ...)`: keyed BLAKE2s with a 16-byte
+/// output. `key` must be at most 32 bytes long; WireGuard uses 32-byte keys
+/// and 16-byte cookies.
+///
+/// # Panics
+///
+/// `mac` panics if `key` is longer than 32 bytes.
+pub fn mac(key: &[u8], parts: &[&[u8]]) -> [u8; MAC_LEN] {
+    let mut ctx = blake2s::Context::with_key(key, MAC_LEN).unwrap();
+    for part in parts {
+        ctx.update(part);
+    }
+    let mut out = [0u8; MAC_LEN];
+    out.copy_from_slice(ctx.finish().as_ref());
+    out
+}
+
+/// `HMAC(key, parts[0] || parts[1] || ...)`: HMAC-BLAKE2s.
+pub fn hmac(key: &[u8], parts: &[&[u8]]) -> [u8; HASH_LEN] {
+    let key = hmac::SigningKey::new(&digest::BLAKE2S_256, key);
+    let mut ctx = hmac::SigningContext::with_key(&key);
+    for part in parts {
+        ctx.update(part);
+    }
+    let mut out = [0u8; HASH_LEN];
+    out.copy_from_slice(ctx.sign().as_ref());
+    out
+}
+
+/// `KDF_n(key, input)`: HKDF with HMAC-BLAKE2s, an empty info, and `n` 32-byte
+/// outputs, where `n` is `out.len()`. The chaining key is `key`; HKDF-Extract
+/// isn't salted separately.
+///
+/// `out.len()` must be in the range [1, 255]. WireGuard uses 1, 2, and 3.
+pub fn kdf(key: &[u8], input: &[u8], out: &mut [[u8; HASH_LEN]])
+           -> Result<(), error::Unspecified> {
+    if out.is_empty() || out.len() > 255 {
+        return Err(error::Unspecified);
+    }
+    let prk = hmac(key, &[input]);
+    let mut previous: &[u8] = &[];
+    for (i, out) in out.iter_mut().enumerate() {
+        *out = hmac(&prk, &[previous, &[(i + 1) as u8]]);
+        previous = out;
+    }
+    Ok(())
+}
+
+/// Returns the key for the `mac1` field of messages sent to the peer with
+/// static public key `peer_public_key`: `HASH(LABEL_MAC1 || S_pub)`.
+pub fn mac1_key(peer_public_key: &[u8]) -> [u8; HASH_LEN] {
+    hash(&[LABEL_MAC1, peer_public_key])
+}
+
+/// Returns the `XAEAD` key for the cookie replies of the peer with static
+/// public key `peer_public_key`: `HASH(LABEL_COOKIE || S_pub)`.
+pub fn cookie_key(peer_public_key: &[u8]) -> [u8; HASH_LEN] {
+    hash(&[LABEL_COOKIE, peer_public_key])
+}
+
+/// Returns the `ring::aead::CHACHA20_POLY1305` nonce for the transport data
+/// message with the given counter: four zero bytes followed by the 64-bit
+/// little-endian encoding of `counter`.
+pub fn transport_nonce(counter: u64) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    for (i, b) in nonce[4..].iter_mut().enumerate() {
+        *b = (counter >> (8 * i)) as u8;
+    }
+    nonce
+}
+
+/// The chaining key `C`, the hash `H`, and the current cipher key of a
+/// handshake.
+pub struct SymmetricState {
+    chaining_key: [u8; HASH_LEN],
+    hash: [u8; HASH_LEN],
+    key: Option<[u8; HASH_LEN]>,
+    nonce: u64,
+}
+
+impl SymmetricState {
+    /// Starts a handshake: `C = HASH(CONSTRUCTION)`,
+    /// `H = HASH(HASH(C || IDENTIFIER) || S_pub_r)`, where `S_pub_r` is the
+    /// responder's static public key. Both the initiator and the responder
+    /// start this way.
+    pub fn new(responder_static_public_key: &[u8])
+               -> Result<SymmetricState, error::Unspecified> {
+        init::init_once();
+        if responder_static_public_key.len() != PUBLIC_KEY_LEN {
+            return Err(error::Unspecified);
+        }
+        let chaining_key = hash(&[CONSTRUCTION]);
+        let hash = hash(&[&hash(&[&chaining_key, IDENTIFIER]),
+                          responder_static_public_key]);
+        Ok(SymmetricState {
+            chaining_key: chaining_key,
+            hash: hash,
+            key: None,
+            nonce: 0,
+        })
+    }
+
+    /// `MixHash(data)`: `H = HASH(H || data)`.
+    pub fn mix_hash(&mut self, data: &[u8]) {
+        self.hash = hash(&[&self.hash, data]);
+    }
+
+    /// `MixKey(input)`: `(C, k) = KDF2(C, input)`.
+    ///
+    /// For an ephemeral public key, WireGuard uses `C = KDF1(C, E_pub)`; since
+    /// the key isn't used before the next `MixKey`, that is the same thing.
+    pub fn mix_key(&mut self, input: &[u8]) {
+        let mut out = [[0u8; HASH_LEN]; 2];
+        kdf(&self.chaining_key, input, &mut out).unwrap();
+        self.chaining_key = out[0];
+        self.set_key(out[1]);
+    }
+
+    /// `MixKey(DH(private_key, peer_public_key))`. This fails if
+    /// `peer_public_key` isn't 32 bytes long or if the result of the X25519
+    /// operation is zero.
+    pub fn mix_dh(&mut self, private_key: &PrivateKey,
+                  peer_public_key: untrusted::Input)
+                  -> Result<(), error::Unspecified> {
+        let mut shared = [0u8; PUBLIC_KEY_LEN];
+        try!(x25519::ecdh_bytes(&mut shared, &private_key.bytes,
+                                peer_public_key));
+        self.mix_key(&shared);
+        Ok(())
+    }
+
+    /// `MixKeyAndHash(psk)`: `(C, tau, k) = KDF3(C, psk)`, then
+    /// `MixHash(tau)`. `psk` must be 32 bytes long; WireGuard uses all zeros
+    /// when no pre-shared key is configured.
+    pub fn mix_key_and_hash(&mut self, psk: &[u8])
+                            -> Result<(), error::Unspecified> {
+        if psk.len() != HASH_LEN {
+            return Err(error::Unspecified);
+        }
+        let mut out = [[0u8; HASH_LEN]; 3];
+        try!(kdf(&self.chaining_key, psk, &mut out));
+        self.chaining_key = out[0];
+        self.mix_hash(&out[1]);
+        self.set_key(out[2]);
+        Ok(())
+    }
+
+    /// `EncryptAndHash(plaintext)`: seals the plaintext with the current key,
+    /// using `H` as the additional data, and then mixes the ciphertext into
+    /// `H`.
+    ///
+    /// `in_out` and `out_suffix_capacity` are as for
+    /// `ring::aead::seal_in_place`. This fails if no key has been mixed in
+    /// yet.
+    pub fn encrypt_and_hash(&mut self, in_out: &mut [u8],
+                            out_suffix_capacity: usize)
+                            -> Result<usize, error::Unspecified> {
+        let key = try!(self.key.ok_or(error::Unspecified));
+        let key = try!(aead::SealingKey::new(&aead::CHACHA20_POLY1305, &key));
+        let nonce = transport_nonce(self.nonce);
+        let len = try!(aead::seal_in_place(&key, &nonce, in_out,
+                                           out_suffix_capacity, &self.hash));
+        self.nonce += 1;
+        self.mix_hash(&in_out[..len]);
+        Ok(len)
+    }
+
+    /// `DecryptAndHash(ciphertext)`: opens `in_out`, which is the ciphertext
+    /// followed by the tag, with the current key and `H` as the additional
+    /// data, and mixes the ciphertext into `H`. On success, returns the length
+    /// of the plaintext, which is at the start of `in_out`.
+    ///
+    /// On failure, `H` is unchanged. This fails if no key has been mixed in
+    /// yet.
+    pub fn decrypt_and_hash(&mut self, in_out: &mut [u8])
+                            -> Result<usize, error::Unspecified> {
+        let key = try!(self.key.ok_or(error::Unspecified));
+        let key = try!(aead::OpeningKey::new(&aead::CHACHA20_POLY1305, &key));
+        let nonce = transport_nonce(self.nonce);
+        let new_hash = hash(&[&self.hash, in_out]);
+        let len = try!(aead::open_in_place(&key, &nonce, 0, in_out,
+                                           &self.hash));
+        self.nonce += 1;
+        self.hash = new_hash;
+        Ok(len)
+    }
+
+    /// `Split()`: `(T_1, T_2) = KDF2(C, "")`. The initiator sends with `T_1`
+    /// and receives with `T_2`; the responder does the opposite.
+    pub fn split(self) -> ([u8; HASH_LEN], [u8; HASH_LEN]) {
+        let mut out = [[0u8; HASH_LEN]; 2];
+        kdf(&self.chaining_key, &[], &mut out).unwrap();
+        (out[0], out[1])
+    }
+
+    fn set_key(&mut self, key: [u8; HASH_LEN]) {
+        self.key = Some(key);
+        self.nonce = 0;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use {aead, test};
+    use super::*;
+    use untrusted;
+
+    #[test]
+    fn test_wireguard_handshake() {
+        test::from_file("src/wireguard_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+            let si = test_case.consume_bytes("InitiatorStatic");
+            let sr = test_case.consume_bytes("ResponderStatic");
+            let ei = test_case.consume_bytes("InitiatorEphemeral");
+            let er = test_case.consume_bytes("ResponderEphemeral");
+            let psk = test_case.consume_bytes("PSK");
+            let timestamp = test_case.consume_bytes("Timestamp");
+            let initiation = test_case.consume_bytes("Initiation");
+            let initiation_mac1 = test_case.consume_bytes("InitiationMac1");
+            let response = test_case.consume_bytes("Response");
+            let response_mac1 = test_case.consume_bytes("ResponseMac1");
+            let expected_hash = test_case.consume_bytes("Hash");
+            let initiator_send = test_case.consume_bytes("InitiatorSend");
+            let initiator_receive =
+                test_case.consume_bytes("InitiatorReceive");
+            let cookie = test_case.consume_bytes("Cookie");
+            let initiation_mac2 = test_case.consume_bytes("InitiationMac2");
+            let cookie_nonce = test_case.consume_bytes("CookieNonce");
+            let encrypted_cookie = test_case.consume_bytes("EncryptedCookie");
+
+            let si = PrivateKey::from_bytes(&si).unwrap();
+            let sr = PrivateKey::from_bytes(&sr).unwrap();
+            let ei = PrivateKey::from_bytes(&ei).unwrap();
+            let er = PrivateKey::from_bytes(&er).unwrap();
+            let (si_pub, sr_pub) = (si.public_key(), sr.public_key());
+            let (ei_pub, er_pub) = (ei.public_key(), er.public_key());
+
+            let encrypted_static = &initiation[40..88];
+            let encrypted_timestamp = &initiation[88..116];
+            let encrypted_nothing = &response[44..60];
+            assert_eq!(&initiation[8..40], &ei_pub[..]);
+            assert_eq!(&response[12..44], &er_pub[..]);
+
+            // The initiator.
+            let mut i = SymmetricState::new(&sr_pub).unwrap();
+            i.mix_hash(&ei_pub);
+            i.mix_key(&ei_pub);
+            i.mix_dh(&ei, untrusted::Input::from(&sr_pub)).unwrap();
+            let mut in_out = si_pub.to_vec();
+            in_out.extend_from_slice(&[0u8; TAG_LEN]);
+            let len = i.encrypt_and_hash(&mut in_out, TAG_LEN).unwrap();
+            assert_eq!(&in_out[..len], encrypted_static);
+            i.mix_dh(&si, untrusted::Input::from(&sr_pub)).unwrap();
+            let mut in_out = timestamp.clone();
+            in_out.extend_from_slice(&[0u8; TAG_LEN]);
+            let len = i.encrypt_and_hash(&mut in_out, TAG_LEN).unwrap();
+            assert_eq!(&in_out[..len], encrypted_timestamp);
+            assert_eq!(&mac(&mac1_key(&sr_pub), &[&initiation])[..],
+                       &initiation_mac1[..]);
+
+            // The responder.
+            let mut r = SymmetricState::new(&sr_pub).unwrap();
+            r.mix_hash(&initiation[8..40]);
+            r.mix_key(&initiation[8..40]);
+            r.mix_dh(&sr, untrusted::Input::from(&initiation[8..40]))
+             .unwrap();
+            let mut in_out = encrypted_static.to_vec();
+            let len = r.decrypt_and_hash(&mut in_out).unwrap();
+            assert_eq!(&in_out[..len], &si_pub[..]);
+            r.mix_dh(&sr, untrusted::Input::from(&in_out[..len])).unwrap();
+            let mut in_out = encrypted_timestamp.to_vec();
+            let len = r.decrypt_and_hash(&mut in_out).unwrap();
+            assert_eq!(&in_out[..len], &timestamp[..]);
+
+            r.mix_hash(&er_pub);
+            r.mix_key(&er_pub);
+            r.mix_dh(&er, untrusted::Input::from(&ei_pub)).unwrap();
+            r.mix_dh(&er, untrusted::Input::from(&si_pub)).unwrap();
+            r.mix_key_and_hash(&psk).unwrap();
+            let mut in_out = [0u8; TAG_LEN];
+            let len = r.encrypt_and_hash(&mut in_out, TAG_LEN).unwrap();
+            assert_eq!(&in_out[..len], encrypted_nothing);
+            assert_eq!(&mac(&mac1_key(&si_pub), &[&response])[..],
+                       &response_mac1[..]);
+            assert_eq!(&r.hash[..], &expected_hash[..]);
+            let (r_receive, r_send) = r.split();
+
+            // The initiator processes the response.
+            i.mix_hash(&response[12..44]);
+            i.mix_key(&response[12..44]);
+            i.mix_dh(&ei, untrusted::Input::from(&response[12..44])).unwrap();
+            i.mix_dh(&si, untrusted::Input::from(&response[12..44])).unwrap();
+
+            // A tampered message, or the wrong PSK, is rejected without
+            // changing the hash.
+            {
+                let mut wrong_psk = psk.clone();
+                wrong_psk[0] ^= 1;
+                let mut i2 = SymmetricState {
+                    chaining_key: i.chaining_key,
+                    hash: i.hash,
+                    key: i.key,
+                    nonce: i.nonce,
+                };
+                i2.mix_key_and_hash(&wrong_psk).unwrap();
+                let hash = i2.hash;
+                let mut in_out = encrypted_nothing.to_vec();
+                assert!(i2.decrypt_and_hash(&mut in_out).is_err());
+                assert_eq!(&i2.hash[..], &hash[..]);
+            }
+
+            i.mix_key_and_hash(&psk).unwrap();
+            let mut in_out = encrypted_nothing.to_vec();
+            assert_eq!(i.decrypt_and_hash(&mut in_out), Ok(0));
+            assert_eq!(&i.hash[..], &expected_hash[..]);
+            let (i_send, i_receive) = i.split();
+            assert_eq!(&i_send[..], &initiator_send[..]);
+            assert_eq!(&i_receive[..], &initiator_receive[..]);
+            assert_eq!(&r_send[..], &i_receive[..]);
+            assert_eq!(&r_receive[..], &i_send[..]);
+
+            // Cookies.
+            assert_eq!(&mac(&cookie, &[&initiation, &initiation_mac1])[..],
+                       &initiation_mac2[..]);
+            let key = aead::xchacha20_poly1305::Key::new(&cookie_key(&sr_pub))
+                .unwrap();
+            let mut in_out = encrypted_cookie.clone();
+            let len = aead::xchacha20_poly1305::open_in_place(
+                &key, &cookie_nonce, 0, &mut in_out, &initiation_mac1)
+                .unwrap();
+            assert_eq!(&in_out[..len], &cookie[..]);
+
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn test_wireguard_invalid_inputs() {
+        assert!(SymmetricState::new(&[0u8; PUBLIC_KEY_LEN - 1]).is_err());
+        assert!(PrivateKey::from_bytes(&[1u8; 31]).is_err());
+
+        let mut state = SymmetricState::new(&[9u8; PUBLIC_KEY_LEN]).unwrap();
+
+        // There is no key before the first `MixKey`.
+        let mut in_out = [0u8; TAG_LEN];
+        assert!(state.encrypt_and_hash(&mut in_out, TAG_LEN).is_err());
+        assert!(state.decrypt_and_hash(&mut in_out).is_err());
+
+        // A public key of the wrong length, and a small-order public key,
+        // are rejected.
+        let private_key = PrivateKey::from_bytes(&[1u8; 32]).unwrap();
+        assert!(state.mix_dh(&private_key,
+                             untrusted::Input::from(&[9u8; 31])).is_err());
+        assert!(state.mix_dh(&private_key,
+                             untrusted::Input::from(&[0u8; 32])).is_err());
+
+        assert!(state.mix_key_and_hash(&[0u8; 31]).is_err());
+        assert!(kdf(&[0u8; 32], b"", &mut []).is_err());
+    }
+
+    #[test]
+    fn test_transport_nonce() {
+        assert_eq!(transport_nonce(0x0102030405060708),
+                   [0, 0, 0, 0, 8, 7, 6, 5, 4, 3, 2, 1]);
+    }
+}
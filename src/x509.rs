@@ -0,0 +1,280 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY
+// SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION
+// OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR IN
+// CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Extraction of the public key and signature algorithm from X.509
+//! certificates.
+//!
+//! This is a minimal, strict parser for the outer structure of a DER-encoded
+//! certificate, as specified in [RFC 5280]. It is intended for applications
+//! that pin keys or that have their own trust model; it doesn't validate
+//! chains, names, validity periods, or extensions, and it doesn't even check
+//! the certificate's signature unless `Certificate::verify_signature` is
+//! called. Use a full PKI library for anything that needs path validation.
+//!
+//! The parser requires DER and accepts only version 1 certificates without
+//! extensions and version 3 certificates. The `issuerUniqueID` and
+//! `subjectUniqueID` fields aren't supported. The signature algorithm in the
+//! `TBSCertificate` must match the outer one exactly.
+//!
+//! # Examples
+//!
+//! Pinning the SHA-256 digest of a certificate's `SubjectPublicKeyInfo`, as
+//! in [RFC 7469]:
+//!
+//! ```no_run
+//! # extern crate ring;
+//! # extern crate untrusted;
+//! # fn main() { example().unwrap() }
+//! # fn example() -> Result<(), ring::error::Unspecified> {
+//! use ring::{constant_time, digest, x509};
+//!
+//! # let cert_der: &[u8] = &[];
+//! # let pinned_spki_digest: &[u8] = &[];
+//! let certificate =
+//!     try!(x509::Certificate::from_der(untrusted::Input::from(cert_der)));
+//! let spki_digest = digest::digest(&digest::SHA256,
+//!                                  certificate.spki().as_slice_less_safe());
+//! try!(constant_time::verify_slices_are_equal(spki_digest.as_ref(),
+//!                                             pinned_spki_digest));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [RFC 5280]: https://tools.ietf.org/html/rfc5280
+//! [RFC 7469]: https://tools.ietf.org/html/rfc7469
+
+use {der, error, init, signed_data};
+use untrusted;
+
+/// A parsed X.509 certificate. All the accessors return DER encodings that
+/// borrow from the input.
+pub struct Certificate<'a> {
+    tbs: untrusted::Input<'a>,
+    signature_algorithm: untrusted::Input<'a>,
+    signature: untrusted::Input<'a>,
+    spki: untrusted::Input<'a>,
+    public_key: untrusted::Input<'a>,
+}
+
+impl<'a> Certificate<'a> {
+    /// Parses a DER-encoded `Certificate`.
+    pub fn from_der(input: untrusted::Input<'a>)
+                    -> Result<Certificate<'a>, error::Unspecified> {
+        init::init_once();
+        input.read_all(error::Unspecified, |input| {
+            der::nested(input, der::Tag::Sequence, error::Unspecified,
+                        |input| {
+                let (tbs, tbs_value) =
+                    try!(der::read_tlv(input, der::Tag::Sequence));
+                let (signature_algorithm, _) =
+                    try!(der::read_tlv(input, der::Tag::Sequence));
+                let (signature, _) =
+                    try!(der::read_tlv(input, der::Tag::BitString));
+                // The signature must be a whole number of bytes.
+                let _ = try!(signature.read_all(error::Unspecified, |input| {
+                    signed_data::bit_string_with_no_unused_bits(input)
+                }));
+
+                let (spki, public_key) =
+                    try!(tbs_value.read_all(error::Unspecified, |input| {
+                        tbs_certificate(input, signature_algorithm)
+                    }));
+
+                Ok(Certificate {
+                    tbs: tbs,
+                    signature_algorithm: signature_algorithm,
+                    signature: signature,
+                    spki: spki,
+                    public_key: public_key,
+                })
+            })
+        })
+    }
+
+    /// The DER-encoded `SubjectPublicKeyInfo`, including its tag and length.
+    /// This is what RFC 7469 pins are computed over, and what
+    /// `ring::signature::verify_signed_data` takes.
+    pub fn spki(&self) -> untrusted::Input<'a> { self.spki }
+
+    /// The contents of the `subjectPublicKey` BIT STRING, e.g. the
+    /// uncompressed point for ECDSA keys, the `RSAPublicKey` for RSA keys, or
+    /// the 32-byte public key for Ed25519 keys. This is the encoding that
+    /// `ring::signature::verify` takes.
+    pub fn public_key(&self) -> untrusted::Input<'a> { self.public_key }
+
+    /// The DER-encoded signature `AlgorithmIdentifier`, including its tag and
+    /// length.
+    pub fn signature_algorithm(&self) -> untrusted::Input<'a> {
+        self.signature_algorithm
+    }
+
+    /// The DER-encoded `TBSCertificate`, including its tag and length; i.e.
+    /// the signed data.
+    pub fn tbs(&self) -> untrusted::Input<'a> { self.tbs }
+
+    /// The DER-encoded signature BIT STRING, including its tag and length.
+    pub fn signature(&self) -> untrusted::Input<'a> { self.signature }
+
+    /// Verifies that the certificate was signed by the key in `issuer_spki`,
+    /// a DER-encoded `SubjectPublicKeyInfo`. The supported algorithms are the
+    /// ones that `ring::signature::verify_signed_data` supports.
+    ///
+    /// This only checks the signature; it doesn't check that the certificate
+    /// was actually issued by the issuer's certificate in any other way.
+    pub fn verify_signature(&self, issuer_spki: untrusted::Input)
+                            -> Result<(), error::Unspecified> {
+        signed_data::verify_signed_data(issuer_spki, self.signature_algorithm,
+                                        self.tbs, self.signature)
+    }
+}
+
+// Reads the contents of a `TBSCertificate`, returning the
+// `SubjectPublicKeyInfo`, including its tag and length, and the contents of
+// the `subjectPublicKey`.
+fn tbs_certificate<'a>(input: &mut untrusted::Reader<'a>,
+                       outer_signature_algorithm: untrusted::Input)
+                       -> Result<(untrusted::Input<'a>, untrusted::Input<'a>),
+                                 error::Unspecified> {
+    // The version is `DEFAULT v1`, so a DER encoding omits it for v1. v2
+    // certificates only differ from v1 certificates in the unique
+    // identifiers, which aren't supported.
+    let version = if input.peek(der::Tag::ContextSpecificConstructed0 as u8) {
+        let version =
+            try!(der::nested(input, der::Tag::ContextSpecificConstructed0,
+                             error::Unspecified,
+                             der::small_nonnegative_integer));
+        if version != VERSION_3 {
+            return Err(error::Unspecified);
+        }
+        version
+    } else {
+        VERSION_1
+    };
+
+    let _serial_number =
+        try!(der::expect_tag_and_get_value(input, der::Tag::Integer));
+
+    let (signature_algorithm, _) =
+        try!(der::read_tlv(input, der::Tag::Sequence));
+    if signature_algorithm.as_slice_less_safe() !=
+            outer_signature_algorithm.as_slice_less_safe() {
+        return Err(error::Unspecified);
+    }
+
+    let _issuer = try!(der::expect_tag_and_get_value(input,
+                                                     der::Tag::Sequence));
+    try!(der::nested(input, der::Tag::Sequence, error::Unspecified,
+                     |input| {
+        let _not_before = try!(time(input));
+        let _not_after = try!(time(input));
+        Ok(())
+    }));
+    let _subject = try!(der::expect_tag_and_get_value(input,
+                                                      der::Tag::Sequence));
+
+    let (spki, spki_value) =
+        try!(der::read_tlv(input, der::Tag::Sequence));
+    let public_key = try!(spki_value.read_all(error::Unspecified, |input| {
+        let _algorithm =
+            try!(der::expect_tag_and_get_value(input, der::Tag::Sequence));
+        signed_data::bit_string_with_no_unused_bits(input)
+    }));
+
+    if version == VERSION_3 &&
+       input.peek(der::Tag::ContextSpecificConstructed3 as u8) {
+        try!(der::nested(input, der::Tag::ContextSpecificConstructed3,
+                         error::Unspecified, |input| {
+            der::nested(input, der::Tag::Sequence, error::Unspecified,
+                        |input| {
+                // There must be at least one extension.
+                if input.at_end() {
+                    return Err(error::Unspecified);
+                }
+                while !input.at_end() {
+                    let _extension =
+                        try!(der::expect_tag_and_get_value(
+                                input, der::Tag::Sequence));
+                }
+                Ok(())
+            })
+        }));
+    }
+
+    Ok((spki, public_key))
+}
+
+const VERSION_1: u8 = 0;
+const VERSION_3: u8 = 2;
+
+fn time<'a>(input: &mut untrusted::Reader<'a>)
+            -> Result<untrusted::Input<'a>, error::Unspecified> {
+    let (tag, value) = try!(der::read_tag_and_get_value(input));
+    if tag != der::Tag::UTCTime as u8 &&
+       tag != der::Tag::GeneralizedTime as u8 {
+        return Err(error::Unspecified);
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use {signature, test};
+    use super::*;
+    use untrusted;
+
+    #[test]
+    fn test_x509_certificate() {
+        test::from_file("src/x509_tests.txt", |section, test_case| {
+            assert_eq!(section, "");
+
+            let certificate = test_case.consume_bytes("Certificate");
+            let issuer_spki = test_case.consume_bytes("IssuerSPKI");
+            let result = test_case.consume_string("Result");
+
+            let parsed =
+                Certificate::from_der(untrusted::Input::from(&certificate));
+            if result == "Error" {
+                assert!(parsed.is_err());
+                return Ok(());
+            }
+            assert_eq!(result, "OK");
+            let parsed = parsed.unwrap();
+
+            let spki = test_case.consume_bytes("SPKI");
+            let signature_algorithm =
+                test_case.consume_bytes("SignatureAlgorithm");
+            let signature_valid = test_case.consume_string("SignatureValid");
+
+            assert_eq!(parsed.spki().as_slice_less_safe(), &spki[..]);
+            assert_eq!(parsed.signature_algorithm().as_slice_less_safe(),
+                       &signature_algorithm[..]);
+
+            // The public key is the contents of the SPKI's BIT STRING, which
+            // is at the end of the SPKI.
+            let public_key = parsed.public_key().as_slice_less_safe();
+            assert!(spki.ends_with(public_key));
+            assert_eq!(spki[spki.len() - public_key.len() - 1], 0);
+
+            let issuer_spki = untrusted::Input::from(&issuer_spki);
+            assert_eq!(parsed.verify_signature(issuer_spki).is_ok(),
+                       signature_valid == "true");
+            assert_eq!(signature::verify_signed_data(
+                           issuer_spki, parsed.signature_algorithm(),
+                           parsed.tbs(), parsed.signature()).is_ok(),
+                       signature_valid == "true");
+
+            Ok(())
+        });
+    }
+}